@@ -191,6 +191,9 @@ pub enum Error {
     /// Invalid shard id
     #[error("Invalid state request: {0}")]
     InvalidStateRequest(String),
+    /// The epoch sync proof doesn't link the genesis to the epoch it syncs to
+    #[error("Invalid epoch sync proof: {0}")]
+    InvalidEpochSyncProof(String),
     /// Invalid VRF proof, or incorrect random_output in the header
     #[error("Invalid Randomness Beacon Output")]
     InvalidRandomnessBeaconOutput,
@@ -309,6 +312,7 @@ impl Error {
             | Error::InvalidCongestionInfo(_)
             | Error::InvalidShardId(_)
             | Error::InvalidStateRequest(_)
+            | Error::InvalidEpochSyncProof(_)
             | Error::InvalidRandomnessBeaconOutput
             | Error::InvalidBlockMerkleRoot
             | Error::InvalidProtocolVersion
@@ -386,6 +390,7 @@ impl Error {
             Error::InvalidCongestionInfo(_) => "invalid_congestion_info",
             Error::InvalidShardId(_) => "invalid_shard_id",
             Error::InvalidStateRequest(_) => "invalid_state_request",
+            Error::InvalidEpochSyncProof(_) => "invalid_epoch_sync_proof",
             Error::InvalidRandomnessBeaconOutput => "invalid_randomness_beacon_output",
             Error::InvalidBlockMerkleRoot => "invalid_block_merkele_root",
             Error::InvalidProtocolVersion => "invalid_protocol_version",
@@ -0,0 +1,79 @@
+//! Blocks which `neard database verify --repair` found missing or corrupt
+//! and deleted.  The client requests them from its peers and stores them
+//! back as they arrive, instead of the node having to sync from scratch.
+
+use near_chain_primitives::Error;
+use near_primitives::block::Block;
+use near_primitives::hash::CryptoHash;
+use near_store::db::BLOCKS_TO_REFETCH_KEY;
+use near_store::{DBCol, Store};
+
+use crate::{Chain, ChainStoreAccess};
+
+/// Returns the blocks to refetch.
+pub fn get_blocks_to_refetch(store: &Store) -> Result<Vec<CryptoHash>, Error> {
+    Ok(store.get_ser(DBCol::BlockMisc, BLOCKS_TO_REFETCH_KEY)?.unwrap_or_default())
+}
+
+/// Adds blocks to refetch, keeping the ones added before.
+pub fn add_blocks_to_refetch(store: &Store, hashes: &[CryptoHash]) -> Result<(), Error> {
+    store.transact(|store_update| -> Result<(), Error> {
+        let mut blocks: Vec<CryptoHash> =
+            store_update.get_ser(DBCol::BlockMisc, BLOCKS_TO_REFETCH_KEY)?.unwrap_or_default();
+        for hash in hashes {
+            if !blocks.contains(hash) {
+                blocks.push(*hash);
+            }
+        }
+        Ok(store_update.set_ser(DBCol::BlockMisc, BLOCKS_TO_REFETCH_KEY, &blocks)?)
+    })
+}
+
+impl Chain {
+    pub fn get_blocks_to_refetch(&self) -> Result<Vec<CryptoHash>, Error> {
+        get_blocks_to_refetch(self.chain_store().store())
+    }
+
+    /// Stores a refetched block received from a peer.  The block was already
+    /// processed before it got lost, so it is only checked to be the one
+    /// expected and not processed again.
+    pub fn save_refetched_block(&mut self, block: &Block) -> Result<(), Error> {
+        let hash = *block.hash();
+        // The hash of the block covers its header only, the body must match
+        // the roots in the header.
+        block.check_validity()?;
+
+        // The same block may arrive from several peers at once.
+        let store = self.chain_store().store().clone();
+        let blocks = store.transact(|store_update| -> Result<_, Error> {
+            let mut blocks: Vec<CryptoHash> =
+                store_update.get_ser(DBCol::BlockMisc, BLOCKS_TO_REFETCH_KEY)?.unwrap_or_default();
+            if !blocks.contains(&hash) {
+                return Err(Error::Other(format!("block {hash} isn't being refetched")));
+            }
+            blocks.retain(|block_hash| block_hash != &hash);
+            store_update.insert_ser(DBCol::Block, hash.as_ref(), block)?;
+            store_update.set_ser(DBCol::BlockMisc, BLOCKS_TO_REFETCH_KEY, &blocks)?;
+            Ok(blocks)
+        })?;
+        tracing::info!(target: "chain", %hash, height = block.header().height(), remaining = blocks.len(), "Stored refetched block");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_blocks_to_refetch, get_blocks_to_refetch};
+    use near_primitives::hash::hash;
+    use near_store::test_utils::create_test_store;
+
+    #[test]
+    fn test_add_blocks_to_refetch() {
+        let store = create_test_store();
+        assert!(get_blocks_to_refetch(&store).unwrap().is_empty());
+        let (a, b) = (hash(b"a"), hash(b"b"));
+        add_blocks_to_refetch(&store, &[a]).unwrap();
+        add_blocks_to_refetch(&store, &[a, b]).unwrap();
+        assert_eq!(get_blocks_to_refetch(&store).unwrap(), vec![a, b]);
+    }
+}
@@ -3,6 +3,7 @@ use crate::block_processing_utils::{
 };
 use crate::blocks_delay_tracker::BlocksDelayTracker;
 use crate::chain_update::ChainUpdate;
+use crate::chunk_apply_journal::recover_chunk_apply_journal;
 use crate::crypto_hash_timer::CryptoHashTimer;
 use crate::lightclient::get_epoch_block_producers_view;
 use crate::migrations::check_if_block_is_first_with_chunk_of_version;
@@ -61,7 +62,7 @@ use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{
     combine_hash, merklize, verify_path, Direction, MerklePath, MerklePathItem, PartialMerkleTree,
 };
-use near_primitives::receipt::Receipt;
+use near_primitives::receipt::{Receipt, ReceiptEnum};
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
 use near_primitives::shard_layout::{account_id_to_shard_id, ShardLayout, ShardUId};
 use near_primitives::sharding::{
@@ -93,6 +94,7 @@ use near_primitives::views::{
 use near_store::config::StateSnapshotType;
 use near_store::flat::{store_helper, FlatStorageReadyStatus, FlatStorageStatus};
 use near_store::get_genesis_state_roots;
+use near_store::io_attribution::IoSubsystem;
 use near_store::DBCol;
 use node_runtime::bootstrap_congestion_info;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
@@ -415,6 +417,10 @@ impl Chain {
             state_roots.clone(),
         )?;
 
+        // Undo the chunk applications which a crash left half written, before
+        // flat storage and the in-memory tries are loaded.
+        recover_chunk_apply_journal(runtime_adapter.store(), &runtime_adapter.get_tries())?;
+
         // Check if we have a head in the store, otherwise pick genesis block.
         let mut chain_store = ChainStore::new(
             runtime_adapter.store().clone(),
@@ -1439,6 +1445,7 @@ impl Chain {
         let block_height = block.header().height();
         let _span =
             debug_span!(target: "chain", "start_process_block_async", ?provenance, height=block_height).entered();
+        let _io_subsystem = IoSubsystem::BlockProcessing.enter();
         let block_received_time = self.clock.now();
         metrics::BLOCK_PROCESSING_ATTEMPTS_TOTAL.inc();
 
@@ -1479,6 +1486,7 @@ impl Chain {
         apply_chunks_done_sender: Option<near_async::messaging::Sender<ApplyChunksDoneMessage>>,
     ) -> (Vec<AcceptedBlock>, HashMap<CryptoHash, Error>) {
         let _span = debug_span!(target: "chain", "postprocess_ready_blocks_chain").entered();
+        let _io_subsystem = IoSubsystem::BlockProcessing.enter();
         let mut accepted_blocks = vec![];
         let mut errors = HashMap::new();
         while let Ok((block_hash, apply_result)) = self.apply_chunks_receiver.try_recv() {
@@ -2085,6 +2093,24 @@ impl Chain {
         Ok(())
     }
 
+    /// Moves the flat head of the shard to the candidate for the current chain
+    /// head, as is done after processing a block. Used to unstick flat storage
+    /// manually. Returns the candidate, if there is one.
+    pub fn move_flat_storage_head(&self, shard_id: ShardId) -> Result<Option<CryptoHash>, Error> {
+        let head = self.head()?;
+        let block = self.get_block(&head.last_block_hash)?;
+        let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &head.epoch_id)?;
+        let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
+        if flat_storage_manager.get_flat_storage_for_shard(shard_uid).is_none() {
+            return Ok(None);
+        }
+        let new_flat_head = self.get_new_flat_storage_head(&block, shard_id)?;
+        if let Some(new_flat_head) = new_flat_head {
+            flat_storage_manager.update_flat_storage_for_shard(shard_uid, new_flat_head)?;
+        }
+        Ok(new_flat_head)
+    }
+
     /// Preprocess a block before applying chunks, verify that we have the necessary information
     /// to process the block an the block is valid.
     /// Note that this function does NOT introduce any changes to chain state.
@@ -3578,13 +3604,14 @@ impl Chain {
         let prev_chunk_headers =
             Chain::get_prev_chunk_headers(self.epoch_manager.as_ref(), prev_block)?;
 
+        // Every shard gets the part of the sandbox state patch touching its
+        // accounts, so the whole patch lands in this block.
+        let shard_layout = self.epoch_manager.get_shard_layout(block.header().epoch_id())?;
         let mut maybe_jobs = vec![];
         for (shard_id, (chunk_header, prev_chunk_header)) in
             block.chunks().iter().zip(prev_chunk_headers.iter()).enumerate()
         {
-            // XXX: This is a bit questionable -- sandbox state patching works
-            // only for a single shard. This so far has been enough.
-            let state_patch = state_patch.take();
+            let state_patch = state_patch.take_for_shard(shard_id as ShardId, &shard_layout);
 
             let storage_context =
                 StorageContext { storage_data_source: StorageDataSource::Db, state_patch };
@@ -4456,6 +4483,7 @@ impl Chain {
     ///           more recent blocks. This function will find the first block in `hashes`
     ///           that is on the main chain and returns the blocks after this block. If none of the
     ///           blocks in `hashes` are on the main chain, the function returns an empty vector.
+    ///           Fails if the headers after this block are pruned, see `GCConfig::prune_headers`.
     pub fn retrieve_headers(
         &self,
         hashes: Vec<CryptoHash>,
@@ -4466,6 +4494,16 @@ impl Chain {
             Some(header) => header,
             None => return Ok(vec![]),
         };
+        // Pruned headers would leave gaps in the returned chain, so requests
+        // for headers below the header tail are declined.
+        let header_tail = self.chain_store.header_tail()?;
+        if header.height() + 1 < header_tail {
+            return Err(Error::DBNotFoundErr(format!(
+                "HEADERS FROM HEIGHT: {}, pruned below height {}",
+                header.height() + 1,
+                header_tail
+            )));
+        }
 
         let mut headers = vec![];
         let header_head_height = self.header_head()?.height;
@@ -4525,7 +4563,7 @@ impl Chain {
     ) -> HashMap<ShardId, Vec<Receipt>> {
         let mut result = HashMap::new();
         for receipt in receipts {
-            let shard_id = account_id_to_shard_id(receipt.receiver_id(), shard_layout);
+            let shard_id = receipt.receiver_shard_id(shard_layout);
             let entry = result.entry(shard_id).or_insert_with(Vec::new);
             entry.push(receipt)
         }
@@ -4545,9 +4583,16 @@ impl Chain {
             shard_layout.shard_ids().map(|shard_id| (shard_id, vec![])).collect();
         let mut cache = HashMap::new();
         for receipt in receipts {
-            let &mut shard_id = cache
-                .entry(receipt.receiver_id())
-                .or_insert_with(|| account_id_to_shard_id(receipt.receiver_id(), shard_layout));
+            // Global contract distribution receipts are routed by their target
+            // shard rather than by their receiver.
+            let shard_id = match receipt.receipt() {
+                ReceiptEnum::GlobalContractDistribution(_) => {
+                    receipt.receiver_shard_id(shard_layout)
+                }
+                _ => *cache
+                    .entry(receipt.receiver_id())
+                    .or_insert_with(|| account_id_to_shard_id(receipt.receiver_id(), shard_layout)),
+            };
             // This unwrap should be safe as we pre-populated the map with all
             // valid shard ids.
             result.get_mut(&shard_id).unwrap().push(receipt);
@@ -4583,8 +4628,11 @@ pub fn do_apply_chunks(
     let parent_span =
         tracing::debug_span!(target: "chain", "do_apply_chunks", block_height, %block_hash)
             .entered();
+    let io_subsystem = IoSubsystem::current();
     work.into_par_iter()
         .map(|(shard_id, task)| {
+            // Chunks are applied on rayon threads, carry over the IO attribution of the caller.
+            let _io_subsystem = io_subsystem.enter();
             // As chunks can be processed in parallel, make sure they are all tracked as children of
             // a single span.
             (shard_id, task(&parent_span))
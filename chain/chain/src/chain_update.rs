@@ -1,5 +1,6 @@
 use crate::block_processing_utils::BlockPreprocessInfo;
 use crate::chain::collect_receipts_from_response;
+use crate::chunk_apply_journal::ChunkApplyJournal;
 use crate::metrics::{SHARD_LAYOUT_NUM_SHARDS, SHARD_LAYOUT_VERSION};
 use crate::store::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
 
@@ -37,6 +38,8 @@ pub struct ChainUpdate<'a> {
     doomslug_threshold_mode: DoomslugThresholdMode,
     #[allow(unused)]
     transaction_validity_period: BlockHeightDelta,
+    /// The chunk applications to journal before committing.
+    chunk_apply_journal: ChunkApplyJournal,
 }
 
 impl<'a> ChainUpdate<'a> {
@@ -70,11 +73,18 @@ impl<'a> ChainUpdate<'a> {
             chain_store_update,
             doomslug_threshold_mode,
             transaction_validity_period,
+            chunk_apply_journal: ChunkApplyJournal::default(),
         }
     }
 
     /// Commit changes to the chain into the database.
-    pub fn commit(self) -> Result<(), Error> {
+    pub fn commit(mut self) -> Result<(), Error> {
+        if !self.chunk_apply_journal.is_empty() {
+            let store = self.chain_store_update.store().clone();
+            let mut store_update = store.store_update();
+            self.chunk_apply_journal.write(&store, &mut store_update)?;
+            self.chain_store_update.merge(store_update);
+        }
         self.chain_store_update.commit()
     }
 
@@ -86,6 +96,15 @@ impl<'a> ChainUpdate<'a> {
     ) -> Result<(), Error> {
         let _span = tracing::debug_span!(target: "chain", "apply_chunk_postprocessing", height=block.header().height()).entered();
         for result in apply_results {
+            let (shard_uid, trie_changes) = match &result {
+                ShardUpdateResult::NewChunk(result) => {
+                    (result.shard_uid, &result.apply_result.trie_changes)
+                }
+                ShardUpdateResult::OldChunk(result) => {
+                    (result.shard_uid, &result.apply_result.trie_changes)
+                }
+            };
+            self.chunk_apply_journal.add(*block.hash(), shard_uid, trie_changes.trie_changes());
             self.process_apply_chunk_result(block, result, should_save_state_transition_data)?;
         }
         Ok(())
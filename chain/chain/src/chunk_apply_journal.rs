@@ -0,0 +1,193 @@
+//! Journal of the chunk applications being written to the database.
+//!
+//! The results of applying the chunks of a block are written together with
+//! the block, in a single write.  With the databases of the shards
+//! (`store.shard_dbs`) that write is split in two: the trie nodes and the
+//! flat storage deltas of the shards are written before the main database,
+//! which has the chunk extras and the heads.  After a hard crash in between,
+//! the databases of the shards have the results of a block which, as far as
+//! the main database goes, was never applied.  Applying it again counts the
+//! trie nodes twice, and the flat storage deltas of a block which may never
+//! be applied again are picked up when flat storage is loaded.
+//!
+//! Before the write, the blocks and the trie nodes they insert are recorded
+//! in the journal, in the main database, and the write itself clears the
+//! journal.  A journal left on startup means the write didn't complete: the
+//! parts already written are reverted, which brings the node back to the
+//! exact state before the block, and the block is applied again.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_chain_primitives::Error;
+use near_primitives::errors::StorageError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_store::db::CHUNK_APPLY_JOURNAL_KEY;
+use near_store::flat::store_helper;
+use near_store::trie::TrieRefcountSubtraction;
+use near_store::{DBCol, ShardTries, Store, StoreUpdate, TrieChanges};
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub(crate) struct ChunkApplyJournal {
+    entries: Vec<JournalEntry>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+struct JournalEntry {
+    block_hash: CryptoHash,
+    shard_uid: ShardUId,
+    /// Reverts the trie nodes inserted by applying the chunk.  Only their
+    /// hashes are kept, the reference count is all that needs reverting.
+    reverted_insertions: Vec<TrieRefcountSubtraction>,
+}
+
+impl ChunkApplyJournal {
+    pub(crate) fn add(
+        &mut self,
+        block_hash: CryptoHash,
+        shard_uid: ShardUId,
+        trie_changes: &TrieChanges,
+    ) {
+        let reverted_insertions =
+            trie_changes.insertions().iter().map(|insertion| insertion.revert()).collect();
+        self.entries.push(JournalEntry { block_hash, shard_uid, reverted_insertions });
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the journal in a write of its own, and adds clearing it to
+    /// `store_update`, which must be the write of the chunk applications.
+    pub(crate) fn write(&self, store: &Store, store_update: &mut StoreUpdate) -> Result<(), Error> {
+        let mut journal_update = store.store_update();
+        journal_update.set_ser(DBCol::BlockMisc, CHUNK_APPLY_JOURNAL_KEY, self)?;
+        journal_update.commit()?;
+        store_update.delete(DBCol::BlockMisc, CHUNK_APPLY_JOURNAL_KEY);
+        Ok(())
+    }
+}
+
+/// Reverts the chunk applications left in the journal by a crash.  Must be
+/// called on startup, before flat storage and the in-memory tries are loaded.
+pub(crate) fn recover_chunk_apply_journal(store: &Store, tries: &ShardTries) -> Result<(), Error> {
+    let Some(journal) =
+        store.get_ser::<ChunkApplyJournal>(DBCol::BlockMisc, CHUNK_APPLY_JOURNAL_KEY)?
+    else {
+        return Ok(());
+    };
+    let mut store_update = store.store_update();
+    for JournalEntry { block_hash, shard_uid, reverted_insertions } in &journal.entries {
+        // The delta is written in the same write as the trie nodes, within
+        // the database of the shard.
+        let delta = store_helper::get_delta_changes(store, *shard_uid, *block_hash)
+            .map_err(StorageError::from)?;
+        if delta.is_none() {
+            continue;
+        }
+        tracing::warn!(target: "chain", %block_hash, %shard_uid, "Reverting the results of a chunk application which wasn't completely written");
+        store_helper::remove_delta(&mut store_update, *shard_uid, *block_hash);
+        tries.subtract_refcounts(reverted_insertions, *shard_uid, &mut store_update);
+    }
+    store_update.delete(DBCol::BlockMisc, CHUNK_APPLY_JOURNAL_KEY);
+    store_update.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recover_chunk_apply_journal, ChunkApplyJournal};
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::shard_layout::ShardUId;
+    use near_store::db::CHUNK_APPLY_JOURNAL_KEY;
+    use near_store::flat::{
+        store_helper, BlockInfo, FlatStateChanges, FlatStateDelta, FlatStateDeltaMetadata,
+    };
+    use near_store::test_utils::TestTriesBuilder;
+    use near_store::{DBCol, ShardTries};
+
+    /// The trie nodes and the delta written for a block before a crash are
+    /// reverted on recovery.
+    #[test]
+    fn test_recover_chunk_apply_journal() {
+        let tries = TestTriesBuilder::new().build();
+        let store = tries.get_store();
+        let shard_uid = ShardUId::single_shard();
+        let block_hash = hash(b"block");
+
+        let trie = tries.get_trie_for_shard(shard_uid, CryptoHash::default());
+        let trie_changes = trie.update(vec![(b"key".to_vec(), Some(b"value".to_vec()))]).unwrap();
+        let mut journal = ChunkApplyJournal::default();
+        journal.add(block_hash, shard_uid, &trie_changes);
+        let mut store_update = store.store_update();
+        journal.write(&store, &mut store_update).unwrap();
+
+        // Only the part written to the database of the shard made it.
+        let mut store_update = store.store_update();
+        tries.apply_insertions(&trie_changes, shard_uid, &mut store_update);
+        let metadata = FlatStateDeltaMetadata {
+            block: BlockInfo { hash: block_hash, height: 1, prev_hash: CryptoHash::default() },
+            prev_block_with_changes: None,
+        };
+        let delta = FlatStateDelta { changes: FlatStateChanges::default(), metadata };
+        store_helper::set_delta(&mut store_update, shard_uid, &delta);
+        store_update.commit().unwrap();
+        assert!(store.iter(DBCol::State).next().is_some());
+
+        recover_chunk_apply_journal(&store, &tries).unwrap();
+        assert!(store.iter(DBCol::State).next().is_none());
+        assert!(store_helper::get_delta_changes(&store, shard_uid, block_hash).unwrap().is_none());
+        assert!(!store.exists(DBCol::BlockMisc, CHUNK_APPLY_JOURNAL_KEY).unwrap());
+    }
+
+    /// Applying a block again after recovering from a crash gives the same
+    /// state as applying it once.
+    #[test]
+    fn test_reapply_after_recovery() {
+        let shard_uid = ShardUId::single_shard();
+        let block_hash = hash(b"block");
+        let changes = vec![
+            (b"key".to_vec(), Some(b"value".to_vec())),
+            (b"other key".to_vec(), Some(b"other value".to_vec())),
+        ];
+        let state = |tries: &ShardTries| {
+            tries.get_store().iter(DBCol::State).map(Result::unwrap).collect::<Vec<_>>()
+        };
+
+        let tries = TestTriesBuilder::new().build();
+        let trie = tries.get_trie_for_shard(shard_uid, CryptoHash::default());
+        let trie_changes = trie.update(changes.clone()).unwrap();
+        let mut store_update = tries.store_update();
+        let want_root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+        store_update.commit().unwrap();
+        let want_state = state(&tries);
+
+        let tries = TestTriesBuilder::new().build();
+        let store = tries.get_store();
+        let trie = tries.get_trie_for_shard(shard_uid, CryptoHash::default());
+        let trie_changes = trie.update(changes).unwrap();
+        let mut journal = ChunkApplyJournal::default();
+        journal.add(block_hash, shard_uid, &trie_changes);
+        let mut store_update = store.store_update();
+        journal.write(&store, &mut store_update).unwrap();
+        // The crash happens after the trie nodes are written.
+        let mut store_update = store.store_update();
+        tries.apply_insertions(&trie_changes, shard_uid, &mut store_update);
+        let metadata = FlatStateDeltaMetadata {
+            block: BlockInfo { hash: block_hash, height: 1, prev_hash: CryptoHash::default() },
+            prev_block_with_changes: None,
+        };
+        let delta = FlatStateDelta { changes: FlatStateChanges::default(), metadata };
+        store_helper::set_delta(&mut store_update, shard_uid, &delta);
+        store_update.commit().unwrap();
+
+        recover_chunk_apply_journal(&store, &tries).unwrap();
+        let mut store_update = tries.store_update();
+        let root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+        store_update.commit().unwrap();
+
+        assert_eq!(root, want_root);
+        assert_eq!(state(&tries), want_state);
+        let trie = tries.get_trie_for_shard(shard_uid, root);
+        assert_eq!(trie.get(b"key").unwrap(), Some(b"value".to_vec()));
+    }
+}
@@ -20,6 +20,7 @@ use near_primitives::shard_layout::ShardUId;
 use near_primitives::state::FlatStateValue;
 use near_primitives::state_part::PartId;
 use near_primitives::types::{BlockHeight, StateRoot};
+use near_store::bulk_load::{BulkLoadGuard, BulkLoadPhase};
 use near_store::flat::{
     store_helper, BlockInfo, FetchingStateStatus, FlatStateChanges, FlatStorageCreationMetrics,
     FlatStorageCreationStatus, FlatStorageManager, FlatStorageReadyStatus, FlatStorageStatus,
@@ -48,6 +49,8 @@ pub struct FlatStorageShardCreator {
     fetched_parts_sender: Sender<u64>,
     /// Used by main thread to update the number of traversed state parts.
     fetched_parts_receiver: Receiver<u64>,
+    /// Keeps flat storage in bulk load mode while state is being fetched.
+    bulk_load: Option<BulkLoadGuard>,
     metrics: FlatStorageCreationMetrics,
 }
 
@@ -70,6 +73,7 @@ impl FlatStorageShardCreator {
             remaining_state_parts: None,
             fetched_parts_sender,
             fetched_parts_receiver,
+            bulk_load: None,
             metrics: FlatStorageCreationMetrics::new(shard_uid),
         }
     }
@@ -236,6 +240,10 @@ impl FlatStorageShardCreator {
                 match self.remaining_state_parts {
                     None => {
                         // We need to spawn threads to fetch state parts and fill flat storage data.
+                        if self.bulk_load.is_none() {
+                            self.bulk_load =
+                                Some(store.bulk_load(BulkLoadPhase::FlatStorageCreation));
+                        }
                         let epoch_id = self.epoch_manager.get_epoch_id(&block_hash)?;
                         let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
                         let state_root =
@@ -302,6 +310,7 @@ impl FlatStorageShardCreator {
                             // If all parts were fetched, we can start catchup.
                             info!(target: "chain", %shard_id, %block_hash, "Finished fetching state");
                             self.metrics.set_remaining_state_parts(0);
+                            self.bulk_load = None;
                             store_helper::remove_delta(
                                 &mut store_update,
                                 self.shard_uid,
@@ -462,9 +471,17 @@ impl FlatStorageCreator {
         if shard_creators.is_empty() {
             return Ok(None);
         }
+        Ok(Some(Self::with_shard_creators(shard_creators, num_threads)))
+    }
+
+    /// Creates flat storages for the given shards only, without looking at
+    /// the statuses of the other shards.
+    pub fn with_shard_creators(
+        shard_creators: HashMap<ShardUId, FlatStorageShardCreator>,
+        num_threads: usize,
+    ) -> Self {
         let pool = rayon::ThreadPoolBuilder::new().num_threads(num_threads).build().unwrap();
-        let flat_storage_creator = Self { shard_creators, pool };
-        Ok(Some(flat_storage_creator))
+        Self { shard_creators, pool }
     }
 
     fn create_flat_storage_for_current_epoch(
@@ -0,0 +1,121 @@
+//! Forks near the head of the chain and why the fork choice rule didn't pick
+//! them, for observing fork resolution in experiments with forks.
+
+use near_chain_primitives::Error;
+use near_primitives::block::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+use near_primitives::views::{ForkInfoView, ForkNotChosenReason, ForkTipView};
+use std::collections::{HashMap, HashSet};
+
+use crate::{Chain, ChainStoreAccess};
+
+/// Number of heights below the head looked at by default.
+const DEFAULT_FORK_INFO_HEIGHTS: BlockHeight = 20;
+/// Upper limit of the heights looked at, which keeps the request cheap.
+const MAX_FORK_INFO_HEIGHTS: BlockHeight = 500;
+
+impl Chain {
+    /// Returns the tips of all blocks and headers known at most `num_heights`
+    /// below the head, and as many above it.  Headers further ahead, e.g.
+    /// during header sync, are not looked at.
+    pub fn get_fork_info(&self, num_heights: Option<BlockHeight>) -> Result<ForkInfoView, Error> {
+        let num_heights =
+            num_heights.unwrap_or(DEFAULT_FORK_INFO_HEIGHTS).min(MAX_FORK_INFO_HEIGHTS);
+        let head = self.head()?;
+        let header_head = self.header_head()?;
+        let final_head = self.final_head()?;
+        let from_height = head.height.saturating_sub(num_heights);
+        let to_height = header_head.height.min(head.height + num_heights);
+
+        let mut headers = HashMap::new();
+        for height in from_height..=to_height {
+            for hash in self.chain_store().get_all_header_hashes_by_height(height)? {
+                headers.insert(hash, self.get_block_header(&hash)?);
+            }
+        }
+        let parents: HashSet<CryptoHash> =
+            headers.values().map(|header| *header.prev_hash()).collect();
+
+        let mut tips = vec![];
+        for (hash, header) in &headers {
+            if parents.contains(hash) {
+                continue;
+            }
+            let has_block = self.block_exists(hash)?;
+            let fork_height = self.get_fork_height(header, from_height);
+            let not_chosen_reason = if *hash == head.last_block_hash {
+                None
+            } else if !has_block {
+                Some(ForkNotChosenReason::BlockNotProcessed)
+            } else if self.chain_store().is_block_challenged(hash)? {
+                Some(ForkNotChosenReason::Challenged)
+            } else if fork_height.map_or(true, |height| height < final_head.height) {
+                Some(ForkNotChosenReason::ConflictsWithFinalBlock)
+            } else if header.height() < head.height {
+                Some(ForkNotChosenReason::LowerHeight)
+            } else {
+                Some(ForkNotChosenReason::SameHeightProcessedLater)
+            };
+            let approvals_stake = if header.is_genesis() {
+                0
+            } else {
+                let approvers =
+                    self.epoch_manager.get_epoch_block_approvers_ordered(header.prev_hash())?;
+                approvers
+                    .iter()
+                    .zip(header.approvals())
+                    .filter(|(_, approval)| approval.is_some())
+                    .map(|((approver, _), _)| approver.stake_this_epoch)
+                    .sum()
+            };
+            tips.push(ForkTipView {
+                hash: *hash,
+                height: header.height(),
+                prev_hash: *header.prev_hash(),
+                epoch_id: header.epoch_id().0,
+                block_producer: self
+                    .epoch_manager
+                    .get_block_producer(header.epoch_id(), header.height())
+                    .ok(),
+                has_block,
+                fork_height,
+                num_approvals: header.approvals().iter().flatten().count() as u64,
+                approvals_stake,
+                not_chosen_reason,
+            });
+        }
+        tips.sort_by_key(|tip| {
+            (std::cmp::Reverse(tip.height), tip.not_chosen_reason.is_some(), tip.hash)
+        });
+
+        Ok(ForkInfoView {
+            head_hash: head.last_block_hash,
+            head_height: head.height,
+            header_head_height: header_head.height,
+            final_head_hash: final_head.last_block_hash,
+            final_head_height: final_head.height,
+            from_height,
+            tips,
+        })
+    }
+
+    /// Returns the height of the last canonical ancestor of the header, or of
+    /// the header itself if it is canonical.
+    fn get_fork_height(
+        &self,
+        header: &BlockHeader,
+        from_height: BlockHeight,
+    ) -> Option<BlockHeight> {
+        let mut header = header.clone();
+        while header.height() >= from_height {
+            if self.chain_store().get_block_hash_by_height(header.height()).ok()
+                == Some(*header.hash())
+            {
+                return Some(header.height());
+            }
+            header = self.get_block_header(header.prev_hash()).ok()?;
+        }
+        None
+    }
+}
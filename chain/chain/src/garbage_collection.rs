@@ -11,11 +11,20 @@ use near_primitives::shard_layout::get_block_shard_uid;
 use near_primitives::state_sync::{StateHeaderKey, StatePartKey};
 use near_primitives::types::{BlockHeight, BlockHeightDelta, EpochId, NumBlocks, ShardId};
 use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, index_to_bytes};
+use near_store::db::ABANDONED_FORKS_TAIL_KEY;
 use near_store::flat::store_helper;
-use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId};
+use near_store::io_attribution::IoSubsystem;
+use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId, HEADER_TAIL_KEY};
 
 use crate::types::RuntimeAdapter;
-use crate::{metrics, Chain, ChainStore, ChainStoreAccess, ChainStoreUpdate};
+use crate::{
+    metrics, Chain, ChainStore, ChainStoreAccess, ChainStoreUpdate, LightClientCheckpoint,
+};
+
+/// Maximum number of heights `prune_headers` goes through at every garbage
+/// collection call.  Deleting a header is cheap, so it's much more than
+/// `gc_blocks_limit`, which lets existing nodes catch up.
+const MAX_HEADERS_TO_PRUNE: BlockHeightDelta = 100;
 
 #[derive(Clone)]
 pub enum GCMode {
@@ -139,13 +148,20 @@ impl ChainStore {
         epoch_manager: Arc<dyn EpochManagerAdapter>,
     ) -> Result<(), Error> {
         let _span = tracing::debug_span!(target: "garbage_collection", "clear_data").entered();
+        let _io_subsystem = IoSubsystem::GarbageCollection.enter();
+        near_store::io_throttle::throttle(IoSubsystem::GarbageCollection);
         let tries = runtime_adapter.get_tries();
         let head = self.head()?;
         let tail = self.tail()?;
-        let gc_stop_height = runtime_adapter.get_gc_stop_height(&head.last_block_hash);
+        let mut gc_stop_height = runtime_adapter.get_gc_stop_height(&head.last_block_hash);
         if gc_stop_height > head.height {
             return Err(Error::GCError("gc_stop_height cannot be larger than head.height".into()));
         }
+        if gc_config.deep_pruning {
+            if let Some(catchup_height) = self.catchup_gc_stop_height(epoch_manager.as_ref())? {
+                gc_stop_height = gc_stop_height.min(catchup_height);
+            }
+        }
         let prev_epoch_id = *self.get_block_header(&head.prev_block_hash)?.epoch_id();
         let epoch_change = prev_epoch_id != head.epoch_id;
         let mut fork_tail = self.fork_tail()?;
@@ -153,6 +169,12 @@ impl ChainStore {
         metrics::FORK_TAIL_HEIGHT.set(fork_tail as i64);
         metrics::CHUNK_TAIL_HEIGHT.set(self.chain_store().chunk_tail()? as i64);
         metrics::GC_STOP_HEIGHT.set(gc_stop_height as i64);
+        if gc_config.prune_headers {
+            self.prune_headers(tail)?;
+        }
+        if gc_config.clear_abandoned_forks {
+            self.clear_abandoned_forks_data(tail, gc_config, epoch_manager.as_ref())?;
+        }
         if epoch_change && fork_tail < gc_stop_height {
             // if head doesn't change on the epoch boundary, we may update fork tail several times
             // but that is fine since it doesn't affect correctness and also we limit the number of
@@ -222,6 +244,121 @@ impl ChainStore {
         Ok(())
     }
 
+    /// Deletes the chunk extras and execution outcomes of the blocks on
+    /// abandoned forks, i.e. the blocks at or below the final head which
+    /// aren't on the canonical chain.  Nothing can be built on those blocks
+    /// anymore, but forks clearing only gets to them once they fall behind
+    /// the GC stop height, epochs later.  The blocks themselves and the state
+    /// their trie changes refer to are still left to forks clearing.
+    fn clear_abandoned_forks_data(
+        &mut self,
+        tail: BlockHeight,
+        gc_config: &GCConfig,
+        epoch_manager: &dyn EpochManagerAdapter,
+    ) -> Result<(), Error> {
+        let final_head = match self.final_head() {
+            Ok(final_head) => final_head,
+            Err(Error::DBNotFoundErr(_)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let cleared_height: Option<BlockHeight> =
+            self.store().get_ser(DBCol::BlockMisc, ABANDONED_FORKS_TAIL_KEY)?;
+        let start_height = cleared_height.map_or(tail, |height| height.max(tail)) + 1;
+        let heights =
+            (start_height..=final_head.height).take(gc_config.gc_fork_clean_step as usize);
+        for height in heights {
+            // The canonical chain below the final head doesn't change, and
+            // the heights it skips have no block in the index.
+            let canonical_hash = match self.get_block_hash_by_height(height) {
+                Ok(hash) => Some(hash),
+                Err(Error::DBNotFoundErr(_)) => None,
+                Err(err) => return Err(err),
+            };
+            let blocks_current_height = self.get_all_block_hashes_by_height(height)?;
+            let mut chain_store_update = self.store_update();
+            for block_hash in blocks_current_height.values().flatten() {
+                if Some(*block_hash) != canonical_hash {
+                    chain_store_update.clear_abandoned_block_data(epoch_manager, block_hash)?;
+                }
+            }
+            let mut store_update = chain_store_update.store().store_update();
+            store_update.set_ser(DBCol::BlockMisc, ABANDONED_FORKS_TAIL_KEY, &height)?;
+            chain_store_update.merge(store_update);
+            chain_store_update.commit()?;
+            metrics::ABANDONED_FORKS_TAIL_HEIGHT.set(height as i64);
+        }
+        Ok(())
+    }
+
+    /// Returns the height below which no ongoing catchup needs the blocks.
+    ///
+    /// Catching up shards needs the blocks of the epoch whose state is synced,
+    /// i.e. the one before the epoch being caught up.  The default number of
+    /// epochs to keep leaves plenty of time for that, with deep pruning the
+    /// blocks could be collected before the catchup finishes.
+    fn catchup_gc_stop_height(
+        &self,
+        epoch_manager: &dyn EpochManagerAdapter,
+    ) -> Result<Option<BlockHeight>, Error> {
+        let mut stop_height: Option<BlockHeight> = None;
+        for (epoch_tail_hash, _) in self.iterate_state_sync_infos()? {
+            let prev_hash = *self.get_block_header(&epoch_tail_hash)?.prev_hash();
+            let epoch_first_block = *epoch_manager.get_block_info(&prev_hash)?.epoch_first_block();
+            let height = self.get_block_header(&epoch_first_block)?.height();
+            stop_height = Some(stop_height.map_or(height, |stop_height| stop_height.min(height)));
+        }
+        Ok(stop_height)
+    }
+
+    /// Deletes the headers of the canonical blocks below the tail, which the
+    /// node has already garbage collected the blocks of.  Headers of forks, of
+    /// the genesis block and of the last blocks of the epochs are kept, epoch
+    /// sync reads the latter by epoch id.  Light clients which know one of the
+    /// pruned blocks are served from `DBCol::LightClientCheckpoints`.
+    ///
+    /// Deletes at most `MAX_HEADERS_TO_PRUNE` heights per call, the progress
+    /// is tracked by the header tail.
+    fn prune_headers(&mut self, tail: BlockHeight) -> Result<(), Error> {
+        let header_tail = self.header_tail()?;
+        let new_header_tail = tail.min(header_tail + MAX_HEADERS_TO_PRUNE);
+        if new_header_tail <= header_tail {
+            return Ok(());
+        }
+        let mut store_update = self.store().store_update();
+        for height in header_tail..new_header_tail {
+            let block_hash = match self.get_block_hash_by_height(height) {
+                Ok(block_hash) => block_hash,
+                // The height was skipped.
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            // The hash of the last block of an epoch is the id of the epoch
+            // after the next one, see `EpochSync::get_all_past_epochs`.
+            if self.store().exists(DBCol::EpochInfo, block_hash.as_ref())? {
+                continue;
+            }
+            // Light clients only know the last blocks of the epochs, see
+            // `DBCol::EpochLightClientBlocks`.
+            let header = self.get_block_header(&block_hash)?;
+            let is_epoch_last_block = self
+                .get_epoch_light_client_block(&header.epoch_id().0)
+                .is_ok_and(|block| block.inner_lite.height == height);
+            if is_epoch_last_block {
+                store_update.set_ser(
+                    DBCol::LightClientCheckpoints,
+                    block_hash.as_ref(),
+                    &LightClientCheckpoint::from(&header),
+                )?;
+            }
+            store_update.delete(DBCol::BlockHeader, block_hash.as_ref());
+            self.headers.pop(block_hash.as_ref());
+        }
+        store_update.set_ser(DBCol::BlockMisc, HEADER_TAIL_KEY, &new_header_tail)?;
+        store_update.commit()?;
+        metrics::HEADER_TAIL_HEIGHT.set(new_header_tail as i64);
+        Ok(())
+    }
+
     /// Garbage collect data which archival node doesn’t need to keep.
     ///
     /// Normally, archival nodes keep all the data from the genesis block and
@@ -237,6 +374,8 @@ impl ChainStore {
         runtime_adapter: Arc<dyn RuntimeAdapter>,
     ) -> Result<(), Error> {
         let _span = tracing::debug_span!(target: "chain", "clear_archive_data").entered();
+        let _io_subsystem = IoSubsystem::GarbageCollection.enter();
+        near_store::io_throttle::throttle(IoSubsystem::GarbageCollection);
 
         let head = self.head()?;
         let gc_stop_height = runtime_adapter.get_gc_stop_height(&head.last_block_hash);
@@ -844,11 +983,64 @@ impl<'a> ChainStoreUpdate<'a> {
                 );
             }
             self.gc_col(DBCol::OutcomeIds, &get_block_shard_id(block_hash, shard_id));
+            self.gc_col(DBCol::OutcomeEventsBloom, &get_block_shard_id(block_hash, shard_id));
         }
         self.merge(store_update);
         Ok(())
     }
 
+    /// Deletes the chunk extras and execution outcomes of a block on an
+    /// abandoned fork, see `ChainStore::clear_abandoned_forks_data()`.
+    fn clear_abandoned_block_data(
+        &mut self,
+        epoch_manager: &dyn EpochManagerAdapter,
+        block_hash: &CryptoHash,
+    ) -> Result<(), Error> {
+        let block = match self.get_block(block_hash) {
+            Ok(block) => block,
+            // Never processed, so nothing was stored for it.
+            Err(Error::DBNotFoundErr(_)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        tracing::debug!(target: "garbage_collection", ?block_hash, height = block.header().height(), "Clearing execution data of an abandoned fork");
+        for shard_uid in self.get_shard_uids_to_gc(epoch_manager, block_hash) {
+            self.gc_abandoned_col(DBCol::ChunkExtra, &get_block_shard_uid(block_hash, &shard_uid))?;
+        }
+        for chunk_header in
+            block.chunks().iter().filter(|h| h.height_included() == block.header().height())
+        {
+            let shard_id = chunk_header.shard_id();
+            let outcome_ids =
+                self.chain_store().get_outcomes_by_block_hash_and_shard_id(block_hash, shard_id)?;
+            for outcome_id in outcome_ids {
+                self.gc_abandoned_col(
+                    DBCol::TransactionResultForBlock,
+                    &get_outcome_id_block_hash(&outcome_id, block_hash),
+                )?;
+            }
+            self.gc_abandoned_col(DBCol::OutcomeIds, &get_block_shard_id(block_hash, shard_id))?;
+            self.gc_abandoned_col(
+                DBCol::OutcomeEventsBloom,
+                &get_block_shard_id(block_hash, shard_id),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Like `gc_col()`, and counts the space reclaimed.
+    fn gc_abandoned_col(&mut self, col: DBCol, key: &[u8]) -> Result<(), Error> {
+        let Some(value_len) = self.store().get(col, key)?.map(|value| value.len()) else {
+            return Ok(());
+        };
+        let column = <&str>::from(col);
+        metrics::GC_ABANDONED_FORKS_ROWS.with_label_values(&[column]).inc();
+        metrics::GC_ABANDONED_FORKS_BYTES
+            .with_label_values(&[column])
+            .inc_by((key.len() + value_len) as u64);
+        self.gc_col(col, key);
+        Ok(())
+    }
+
     fn gc_col(&mut self, col: DBCol, key: &[u8]) {
         let mut store_update = self.store().store_update();
         match col {
@@ -941,6 +1133,9 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::OutcomeIds => {
                 store_update.delete(col, key);
             }
+            DBCol::OutcomeEventsBloom => {
+                store_update.delete(col, key);
+            }
             DBCol::StateDlInfos => {
                 store_update.delete(col, key);
             }
@@ -967,8 +1162,10 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::BlockMisc
             | DBCol::_GCCount
             | DBCol::BlockHeight  // block sync needs it + genesis should be accessible
+            | DBCol::SkippedBlockHeights
             | DBCol::_Peers
             | DBCol::RecentOutboundConnections
+            | DBCol::DhtPeerRecords
             | DBCol::BlockMerkleTree
             | DBCol::AccountAnnouncements
             | DBCol::EpochLightClientBlocks
@@ -993,6 +1190,8 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::FlatStorageStatus
             | DBCol::Misc
             | DBCol::_ReceiptIdToShardId
+            | DBCol::EpochShardStats
+            | DBCol::LightClientCheckpoints
             => unreachable!(),
         }
         self.merge(store_update);
@@ -4,21 +4,28 @@ pub use block_processing_utils::BlockProcessingArtifact;
 pub use chain::{check_known, collect_receipts, Chain};
 pub use chain_update::ChainUpdate;
 pub use doomslug::{Doomslug, DoomslugBlockProductionReadiness, DoomslugThresholdMode};
-pub use lightclient::{create_light_client_block_view, get_epoch_block_producers_view};
+pub use lightclient::{
+    create_light_client_block_view, get_chunk_validators_view, get_epoch_block_producers_view,
+};
 pub use near_chain_primitives::{self, Error};
 pub use near_primitives::receipt::ReceiptResult;
-pub use store::{ChainStore, ChainStoreAccess, ChainStoreUpdate, LatestWitnessesInfo};
+pub use store::{
+    ChainStore, ChainStoreAccess, ChainStoreUpdate, LatestWitnessesInfo, LightClientCheckpoint,
+};
 pub use store_validator::{ErrorMessage, StoreValidator};
 pub use types::{Block, BlockHeader, BlockStatus, ChainGenesis, LatestKnown, Provenance};
 
 mod block_processing_utils;
+pub mod block_refetch;
 pub mod blocks_delay_tracker;
 pub mod chain;
 mod chain_update;
+mod chunk_apply_journal;
 pub mod chunks_store;
 pub mod crypto_hash_timer;
 mod doomslug;
 pub mod flat_storage_creator;
+mod fork_info;
 mod garbage_collection;
 mod lightclient;
 pub mod metrics;
@@ -3,8 +3,13 @@ use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::block::BlockHeader;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::types::EpochId;
+use near_primitives::version::ProtocolFeature;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
-use near_primitives::views::{BlockHeaderInnerLiteView, LightClientBlockView};
+use near_primitives::views::{
+    BlockHeaderInnerLiteView, ChunkValidatorView, ChunkValidatorsView, LightClientBlockView,
+    ShardChunkValidatorsView,
+};
+use std::collections::HashSet;
 
 use crate::ChainStoreAccess;
 
@@ -20,6 +25,48 @@ pub fn get_epoch_block_producers_view(
         .collect::<Vec<_>>())
 }
 
+/// Returns the chunk validators of every shard for the chunks produced at the height of the given
+/// block, together with their endorsement weights.
+pub fn get_chunk_validators_view(
+    block_header: &BlockHeader,
+    epoch_manager: &dyn EpochManagerAdapter,
+) -> Result<ChunkValidatorsView, Error> {
+    let epoch_id = block_header.epoch_id();
+    let height = block_header.height();
+    let mandates = epoch_manager.get_epoch_info(epoch_id)?.validator_mandates();
+    let mut shards = vec![];
+    // Chunk validator assignments can only be computed for epochs with mandates.
+    if ProtocolFeature::StatelessValidation
+        .enabled(epoch_manager.get_epoch_protocol_version(epoch_id)?)
+    {
+        for shard_id in epoch_manager.shard_ids(epoch_id)? {
+            let assignments =
+                epoch_manager.get_chunk_validator_assignments(epoch_id, shard_id, height)?;
+            let stats = assignments.compute_endorsement_stats(&HashSet::new());
+            shards.push(ShardChunkValidatorsView {
+                shard_id,
+                total_endorsement_weight: stats.total_stake,
+                required_endorsement_weight: stats.required_stake(),
+                validators: assignments
+                    .assignments()
+                    .iter()
+                    .map(|(account_id, endorsement_weight)| ChunkValidatorView {
+                        account_id: account_id.clone(),
+                        endorsement_weight: *endorsement_weight,
+                    })
+                    .collect(),
+            });
+        }
+    }
+    Ok(ChunkValidatorsView {
+        block_hash: *block_header.hash(),
+        height,
+        target_mandates_per_shard: mandates.target_mandates_per_shard() as u64,
+        stake_per_mandate: mandates.stake_per_mandate(),
+        shards,
+    })
+}
+
 /// Creates the `LightClientBlock` from the information in the chain store for a given block.
 ///
 /// # Arguments
@@ -1,7 +1,8 @@
 use near_o11y::metrics::{
     exponential_buckets, processing_time_buckets, try_create_histogram, try_create_histogram_vec,
-    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_gauge,
-    try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter, IntGauge, IntGaugeVec,
+    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
+    try_create_int_gauge, try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec,
 };
 use std::sync::LazyLock;
 
@@ -70,10 +71,36 @@ pub static TAIL_HEIGHT: LazyLock<IntGauge> =
 pub static CHUNK_TAIL_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
     try_create_int_gauge("near_chunk_tail_height", "Height of chunk tail").unwrap()
 });
+pub static HEADER_TAIL_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge("near_header_tail_height", "Height of header tail").unwrap()
+});
 pub static FORK_TAIL_HEIGHT: LazyLock<IntGauge> =
     LazyLock::new(|| try_create_int_gauge("near_fork_tail_height", "Height of fork tail").unwrap());
 pub static GC_STOP_HEIGHT: LazyLock<IntGauge> =
     LazyLock::new(|| try_create_int_gauge("near_gc_stop_height", "Target height of gc").unwrap());
+pub static ABANDONED_FORKS_TAIL_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_abandoned_forks_tail_height",
+        "Height up to which the execution data of abandoned forks was cleared",
+    )
+    .unwrap()
+});
+pub static GC_ABANDONED_FORKS_ROWS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_gc_abandoned_forks_rows_total",
+        "Rows of execution data of abandoned forks deleted ahead of forks clearing",
+        &["column"],
+    )
+    .unwrap()
+});
+pub static GC_ABANDONED_FORKS_BYTES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_gc_abandoned_forks_bytes_total",
+        "Size of the values of abandoned forks deleted ahead of forks clearing",
+        &["column"],
+    )
+    .unwrap()
+});
 pub static CHUNK_RECEIVED_DELAY: LazyLock<HistogramVec> = LazyLock::new(|| {
     try_create_histogram_vec(
         "near_chunk_receive_delay_seconds",
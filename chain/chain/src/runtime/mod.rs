@@ -8,7 +8,8 @@ use borsh::BorshDeserialize;
 use errors::FromStateViewerErrors;
 use near_async::time::{Duration, Instant};
 use near_chain_configs::{
-    GenesisConfig, ProtocolConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP, MIN_GC_NUM_EPOCHS_TO_KEEP,
+    GenesisConfig, ProtocolConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+    MIN_DEEP_PRUNING_NUM_EPOCHS_TO_KEEP,
 };
 use near_crypto::PublicKey;
 use near_epoch_manager::{EpochManagerAdapter, EpochManagerHandle};
@@ -16,6 +17,7 @@ use near_parameters::{ActionCosts, ExtCosts, RuntimeConfig, RuntimeConfigStore};
 use near_pool::types::TransactionGroupIterator;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::apply::ApplyChunkReason;
+use near_primitives::challenge::TrieValue;
 use near_primitives::congestion_info::{
     CongestionControl, ExtendedCongestionInfo, RejectTransactionReason, ShardAcceptsTransactions,
 };
@@ -34,23 +36,24 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolFeature, ProtocolVersion};
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, ContractCodeView, QueryRequest, QueryResponse,
-    QueryResponseKind, ViewStateResult,
+    AccessKeyList, AccessKeyListOptions, AccountWithProofView, CallResult, ContractCodeView,
+    QueryRequest, QueryResponse, QueryResponseKind, ViewStateOptions, ViewStateResult,
 };
 use near_store::config::StateSnapshotType;
 use near_store::flat::FlatStorageManager;
 use near_store::metadata::DbKind;
 use near_store::{
-    ApplyStatePartResult, DBCol, ShardTries, StateSnapshotConfig, Store, Trie, TrieConfig,
-    TrieUpdate, WrappedTrieChanges, COLD_HEAD_KEY,
+    get_access_key, ApplyStatePartResult, DBCol, ShardTries, StateSnapshotConfig, Store, Trie,
+    TrieConfig, TrieUpdate, WrappedTrieChanges, COLD_HEAD_KEY,
 };
 use near_vm_runner::ContractCode;
 use near_vm_runner::{precompile_contract, ContractRuntimeCache, FilesystemContractRuntimeCache};
 use node_runtime::adapter::ViewRuntimeAdapter;
+use node_runtime::host_call_trace::HostCallTracer;
 use node_runtime::state_viewer::{TrieViewer, ViewApplyState};
 use node_runtime::{
-    validate_transaction, verify_and_charge_transaction, ApplyState, Runtime,
-    ValidatorAccountsUpdate,
+    validate_access_key_expiry, validate_transaction, verify_and_charge_transaction, ApplyState,
+    Runtime, ValidatorAccountsUpdate,
 };
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -77,6 +80,7 @@ pub struct NightshadeRuntime {
     epoch_manager: Arc<EpochManagerHandle>,
     migration_data: Arc<MigrationData>,
     gc_num_epochs_to_keep: u64,
+    host_call_tracer: Arc<HostCallTracer>,
 }
 
 impl NightshadeRuntime {
@@ -99,7 +103,11 @@ impl NightshadeRuntime {
 
         let runtime = Runtime::new();
         let trie_viewer = TrieViewer::new(trie_viewer_state_size_limit, max_gas_burnt_view);
-        let flat_storage_manager = FlatStorageManager::new(store.clone());
+        let flat_storage_manager = FlatStorageManager::with_config(
+            store.clone(),
+            trie_config.flat_storage_key_filter.clone(),
+            trie_config.flat_state_mmap_dir.clone(),
+        );
         let shard_uids: Vec<_> = genesis_config.shard_layout.shard_uids().collect();
         let tries = ShardTries::new(
             store.clone(),
@@ -128,7 +136,10 @@ impl NightshadeRuntime {
             trie_viewer,
             epoch_manager,
             migration_data,
-            gc_num_epochs_to_keep: gc_num_epochs_to_keep.max(MIN_GC_NUM_EPOCHS_TO_KEEP),
+            // The lower limit without deep pruning is applied by
+            // `GCConfig::gc_num_epochs_to_keep`.
+            gc_num_epochs_to_keep: gc_num_epochs_to_keep.max(MIN_DEEP_PRUNING_NUM_EPOCHS_TO_KEEP),
+            host_call_tracer: Arc::new(HostCallTracer::default()),
         })
     }
 
@@ -156,6 +167,7 @@ impl NightshadeRuntime {
                 home_dir: home_dir.to_path_buf(),
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
+                num_checkpoints: 0,
             },
         )
     }
@@ -185,6 +197,7 @@ impl NightshadeRuntime {
                 home_dir: home_dir.to_path_buf(),
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
+                num_checkpoints: 0,
             },
         )
     }
@@ -208,6 +221,12 @@ impl NightshadeRuntime {
         )
     }
 
+    /// Host function calls traced while applying the chunks, see the debug
+    /// RPC `/debug/api/host_call_trace`.
+    pub fn host_call_tracer(&self) -> Arc<HostCallTracer> {
+        Arc::clone(&self.host_call_tracer)
+    }
+
     fn get_shard_uid_from_prev_hash(
         &self,
         shard_id: ShardId,
@@ -385,6 +404,7 @@ impl NightshadeRuntime {
                 is_first_block_with_chunk_of_version,
             },
             congestion_info,
+            host_call_tracer: Some(Arc::clone(&self.host_call_tracer)),
         };
 
         let instant = Instant::now();
@@ -653,6 +673,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         transaction: &SignedTransaction,
         verify_signature: bool,
         epoch_id: &EpochId,
+        next_block_height: BlockHeight,
         current_protocol_version: ProtocolVersion,
         receiver_congestion_info: Option<ExtendedCongestionInfo>,
     ) -> Result<Option<InvalidTxError>, Error> {
@@ -689,7 +710,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                 self.account_id_to_shard_uid(transaction.transaction.signer_id(), epoch_id)?;
             let mut state_update = self.tries.new_trie_update(shard_uid, state_root);
 
-            match verify_and_charge_transaction(
+            if let Err(e) = verify_and_charge_transaction(
                 runtime_config,
                 &mut state_update,
                 gas_price,
@@ -700,9 +721,22 @@ impl RuntimeAdapter for NightshadeRuntime {
                 None,
                 current_protocol_version,
             ) {
-                Ok(_) => Ok(None),
-                Err(e) => Ok(Some(e)),
+                return Ok(Some(e));
             }
+            // An access key which expired before the next block stays expired.
+            let signer_id = transaction.transaction.signer_id();
+            let public_key = transaction.transaction.public_key();
+            if let Some(access_key) = get_access_key(&state_update, signer_id, public_key)? {
+                if let Err(e) = validate_access_key_expiry(
+                    signer_id,
+                    public_key,
+                    &access_key,
+                    next_block_height,
+                ) {
+                    return Ok(Some(e));
+                }
+            }
+            Ok(None)
         } else {
             // Doing basic validation without a state root
             match validate_transaction(
@@ -863,13 +897,22 @@ impl RuntimeAdapter for NightshadeRuntime {
 
                 // Take the transaction out of the pool. Please take note that
                 // the transaction may still be rejected in which case it will
-                // not be returned to the pool. Most notably this may happen
-                // under congestion.
+                // not be returned to the pool. The exception are transactions
+                // rejected due to congestion of their receiver shard, which
+                // are deferred.
                 let tx = transaction_group_iter
                     .next()
                     .expect("peek_next() returned Some, so next() should return Some as well");
                 num_checked_transactions += 1;
 
+                // Verifying the transaction is on the same chain and hasn't expired yet. This is
+                // checked before congestion, so that expired transactions are not deferred.
+                if !chain_validate(&tx) {
+                    tracing::trace!(target: "runtime", tx=?tx.get_hash(), "discarding transaction that failed chain validation");
+                    rejected_invalid_for_chain += 1;
+                    continue;
+                }
+
                 if !congestion_control_accepts_transaction(
                     self.epoch_manager.as_ref(),
                     protocol_version,
@@ -878,15 +921,11 @@ impl RuntimeAdapter for NightshadeRuntime {
                     &prev_block,
                     &tx,
                 )? {
-                    tracing::trace!(target: "runtime", tx=?tx.get_hash(), "discarding transaction due to congestion");
+                    // The transaction stays in the sub-pool of its receiver shard, so that it can
+                    // be included once the shard is no longer congested.
+                    tracing::trace!(target: "runtime", tx=?tx.get_hash(), "deferring transaction due to congestion");
                     rejected_due_to_congestion += 1;
-                    continue;
-                }
-
-                // Verifying the transaction is on the same chain and hasn't expired yet.
-                if !chain_validate(&tx) {
-                    tracing::trace!(target: "runtime", tx=?tx.get_hash(), "discarding transaction that failed chain validation");
-                    rejected_invalid_for_chain += 1;
+                    transaction_group_iter.defer(tx);
                     continue;
                 }
 
@@ -962,6 +1001,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         let _timer = metrics::APPLYING_CHUNKS_TIME
             .with_label_values(&[&apply_reason.to_string(), &shard_id.to_string()])
             .start_timer();
+        let apply_start = std::time::Instant::now();
 
         let mut trie = match storage_config.source {
             StorageDataSource::Db => self.get_trie_for_shard(
@@ -1012,7 +1052,10 @@ impl RuntimeAdapter for NightshadeRuntime {
             transactions,
             storage_config.state_patch,
         ) {
-            Ok(result) => Ok(result),
+            Ok(result) => {
+                near_store::io_throttle::record_chunk_apply_latency(apply_start.elapsed());
+                Ok(result)
+            }
             Err(e) => match e {
                 Error::StorageError(err) => match &err {
                     StorageError::FlatStorageBlockNotSupported(_)
@@ -1036,20 +1079,28 @@ impl RuntimeAdapter for NightshadeRuntime {
         request: &QueryRequest,
     ) -> Result<QueryResponse, crate::near_chain_primitives::error::QueryError> {
         match request {
-            QueryRequest::ViewAccount { account_id } => {
-                let account =
-                    self.view_account(&shard_uid, *state_root, account_id).map_err(|err| {
-                        crate::near_chain_primitives::error::QueryError::from_view_account_error(
-                            err,
-                            block_height,
-                            *block_hash,
-                        )
-                    })?;
-                Ok(QueryResponse {
-                    kind: QueryResponseKind::ViewAccount(account.into()),
-                    block_height,
-                    block_hash: *block_hash,
-                })
+            QueryRequest::ViewAccount { account_id, include_proof } => {
+                let map_err = |err| {
+                    crate::near_chain_primitives::error::QueryError::from_view_account_error(
+                        err,
+                        block_height,
+                        *block_hash,
+                    )
+                };
+                let kind = if *include_proof {
+                    let (account, proof) = self
+                        .view_account_with_proof(&shard_uid, *state_root, account_id)
+                        .map_err(map_err)?;
+                    QueryResponseKind::ViewAccountWithProof(AccountWithProofView {
+                        account: account.into(),
+                        proof,
+                    })
+                } else {
+                    let account =
+                        self.view_account(&shard_uid, *state_root, account_id).map_err(map_err)?;
+                    QueryResponseKind::ViewAccount(account.into())
+                };
+                Ok(QueryResponse { kind, block_height, block_hash: *block_hash })
             }
             QueryRequest::ViewCode { account_id } => {
                 let contract_code = self
@@ -1110,7 +1161,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
-            QueryRequest::ViewState { account_id, prefix, include_proof } => {
+            QueryRequest::ViewState { account_id, prefix, include_proof, options } => {
                 let view_state_result = self
                     .view_state(
                         &shard_uid,
@@ -1118,6 +1169,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                         account_id,
                         prefix.as_ref(),
                         *include_proof,
+                        options,
                     )
                     .map_err(|err| {
                         crate::near_chain_primitives::error::QueryError::from_view_state_error(
@@ -1132,9 +1184,10 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
-            QueryRequest::ViewAccessKeyList { account_id } => {
-                let access_key_list =
-                    self.view_access_keys(&shard_uid, *state_root, account_id).map_err(|err| {
+            QueryRequest::ViewAccessKeyList { account_id, options } => {
+                let access_key_list = self
+                    .view_access_key_list(&shard_uid, *state_root, account_id, options)
+                    .map_err(|err| {
                         crate::near_chain_primitives::error::QueryError::from_view_access_key_error(
                             err,
                             block_height,
@@ -1142,15 +1195,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                         )
                     })?;
                 Ok(QueryResponse {
-                    kind: QueryResponseKind::AccessKeyList(
-                        access_key_list
-                            .into_iter()
-                            .map(|(public_key, access_key)| AccessKeyInfoView {
-                                public_key,
-                                access_key: access_key.into(),
-                            })
-                            .collect(),
-                    ),
+                    kind: QueryResponseKind::AccessKeyList(access_key_list),
                     block_height,
                     block_hash: *block_hash,
                 })
@@ -1459,6 +1504,17 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         self.trie_viewer.view_account(&state_update, account_id)
     }
 
+    fn view_account_with_proof(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        account_id: &AccountId,
+    ) -> Result<(Account, Vec<TrieValue>), node_runtime::state_viewer::errors::ViewAccountError>
+    {
+        let trie = self.tries.get_view_trie_for_shard(*shard_uid, state_root);
+        self.trie_viewer.view_account_with_proof(&trie, account_id)
+    }
+
     fn view_contract_code(
         &self,
         shard_uid: &ShardUId,
@@ -1486,8 +1542,7 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, node_runtime::state_viewer::errors::CallFunctionError> {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
-        let view_state = ViewApplyState {
+        let view_state = || ViewApplyState {
             shard_id: shard_uid.shard_id(),
             block_height: height,
             prev_block_hash: *prev_block_hash,
@@ -1498,9 +1553,32 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
             current_protocol_version,
             cache: Some(self.compiled_contract_cache.handle()),
         };
+        // Most calls are for the recent blocks, which the memtries still hold. The root may
+        // be garbage collected from the memtries during the call though, so internal errors
+        // are retried on disk.
+        if let Some(state_update) = self.tries.new_trie_update_memtrie_view(*shard_uid, state_root)
+        {
+            match self.trie_viewer.call_function(
+                state_update,
+                view_state(),
+                contract_id,
+                method_name,
+                args,
+                logs,
+                epoch_info_provider,
+            ) {
+                Err(node_runtime::state_viewer::errors::CallFunctionError::InternalError {
+                    error_message,
+                }) => {
+                    debug!(target: "runtime", %error_message, "call_function on memtries failed, retrying on disk");
+                }
+                result => return result,
+            }
+        }
+        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
         self.trie_viewer.call_function(
             state_update,
-            view_state,
+            view_state(),
             contract_id,
             method_name,
             args,
@@ -1520,15 +1598,15 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         self.trie_viewer.view_access_key(&state_update, account_id, public_key)
     }
 
-    fn view_access_keys(
+    fn view_access_key_list(
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
         account_id: &AccountId,
-    ) -> Result<Vec<(PublicKey, AccessKey)>, node_runtime::state_viewer::errors::ViewAccessKeyError>
-    {
+        options: &AccessKeyListOptions,
+    ) -> Result<AccessKeyList, node_runtime::state_viewer::errors::ViewAccessKeyError> {
         let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
-        self.trie_viewer.view_access_keys(&state_update, account_id)
+        self.trie_viewer.view_access_key_list(&state_update, account_id, options)
     }
 
     fn view_state(
@@ -1538,8 +1616,9 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
+        options: &ViewStateOptions,
     ) -> Result<ViewStateResult, node_runtime::state_viewer::errors::ViewStateError> {
         let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
-        self.trie_viewer.view_state(&state_update, account_id, prefix, include_proof)
+        self.trie_viewer.view_state(&state_update, account_id, prefix, include_proof, options)
     }
 }
@@ -136,6 +136,7 @@ impl TestEnv {
                 home_dir: PathBuf::from(dir.path()),
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
+                num_checkpoints: 0,
             },
         );
         let state_roots = get_genesis_state_roots(&store).unwrap().unwrap();
@@ -1593,9 +1594,9 @@ fn generate_transaction_pool(
     }
     transactions.shuffle(&mut rng);
 
-    let mut pool = TransactionPool::new(TEST_SEED, None, "");
+    let mut pool = TransactionPool::new(TEST_SEED, None, None, "");
     for transaction in transactions {
-        assert_eq!(pool.insert_transaction(transaction), InsertTransactionResult::Success);
+        assert_eq!(pool.insert_transaction(transaction, 0), InsertTransactionResult::Success);
     }
     pool
 }
@@ -0,0 +1,45 @@
+//! Persistence of the per-shard statistics of finished epochs, see
+//! `EpochShardStats`. The statistics are only saved when
+//! `save_epoch_shard_stats` is enabled and are kept forever.
+
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{EpochId, EpochShardStats};
+use near_store::DBCol;
+
+use super::ChainStore;
+use crate::ChainStoreAccess;
+
+fn epoch_shard_stats_key(epoch_id: &EpochId, shard_uid: &ShardUId) -> Vec<u8> {
+    [epoch_id.0.as_ref(), &shard_uid.to_bytes()].concat()
+}
+
+impl ChainStore {
+    pub fn save_epoch_shard_stats(&self, stats: &EpochShardStats) -> Result<(), std::io::Error> {
+        let mut store_update = self.store().store_update();
+        store_update.set_ser(
+            DBCol::EpochShardStats,
+            &epoch_shard_stats_key(&stats.epoch_id, &stats.shard_uid),
+            stats,
+        )?;
+        store_update.commit()
+    }
+
+    pub fn get_epoch_shard_stats(
+        &self,
+        epoch_id: &EpochId,
+        shard_uid: &ShardUId,
+    ) -> Result<Option<EpochShardStats>, std::io::Error> {
+        self.store().get_ser(DBCol::EpochShardStats, &epoch_shard_stats_key(epoch_id, shard_uid))
+    }
+
+    /// Returns the saved statistics of all shards of the epoch.
+    pub fn get_all_epoch_shard_stats(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<Vec<EpochShardStats>, std::io::Error> {
+        self.store()
+            .iter_prefix_ser::<EpochShardStats>(DBCol::EpochShardStats, epoch_id.0.as_ref())
+            .map(|item| item.map(|(_, stats)| stats))
+            .collect()
+    }
+}
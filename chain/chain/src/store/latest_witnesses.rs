@@ -102,12 +102,14 @@ impl LatestWitnessesInfo {
 impl ChainStore {
     /// Saves an observed `ChunkStateWitness` to the database for later analysis and debugging.
     /// The witness is stored in `DBCol::LatestChunkStateWitnesses`.
-    /// This function does a read-before-write. Don't call it in parallel on the same database,
-    /// or there will be race conditions.
+    /// Nothing is saved while the disk is running out of space.
     pub fn save_latest_chunk_state_witness(
         &mut self,
         witness: &ChunkStateWitness,
     ) -> Result<(), std::io::Error> {
+        if !near_store::disk_pressure::non_essential_writes_allowed() {
+            return Ok(());
+        }
         let start_time = std::time::Instant::now();
         let _span = tracing::info_span!(
             target: "client",
@@ -130,45 +132,6 @@ impl ChainStore {
             return Ok(());
         }
 
-        // Read the current `LatestWitnessesInfo`, or create a new one if there is none.
-        let mut info = self
-            .store()
-            .get_ser::<LatestWitnessesInfo>(DBCol::Misc, LATEST_WITNESSES_INFO)?
-            .unwrap_or_default();
-
-        let new_witness_index = info.next_witness_index;
-
-        // Adjust the info to include the new witness.
-        info.count += 1;
-        info.total_size += serialized_witness.len() as u64;
-        info.next_witness_index += 1;
-
-        let mut store_update = self.store().store_update();
-
-        // Go over witnesses with increasing indexes and remove them until the limits are satisfied.
-        while !info.is_within_limits() && info.lowest_index < info.next_witness_index {
-            let key_to_delete = self
-                .store()
-                .get(DBCol::LatestWitnessesByIndex, &info.lowest_index.to_be_bytes())?
-                .ok_or_else(|| {
-                    std::io::Error::new(
-                        ErrorKind::NotFound,
-                        format!(
-                            "Cannot find witness key to delete with index {}",
-                            info.lowest_index
-                        ),
-                    )
-                })?;
-            let key_deser = LatestWitnessesKey::deserialize(&key_to_delete)?;
-
-            store_update.delete(DBCol::LatestChunkStateWitnesses, &key_to_delete);
-            store_update.delete(DBCol::LatestWitnessesByIndex, &info.lowest_index.to_be_bytes());
-            info.lowest_index += 1;
-            info.count -= 1;
-            info.total_size -= key_deser.witness_size;
-        }
-
-        // Limits are ok, insert the new witness.
         let mut random_uuid = [0u8; 16];
         OsRng.fill_bytes(&mut random_uuid);
         let key = LatestWitnessesKey {
@@ -178,20 +141,64 @@ impl ChainStore {
             witness_size: serialized_witness_size,
             random_uuid,
         };
-        store_update.set(DBCol::LatestChunkStateWitnesses, &key.serialized(), &serialized_witness);
-        store_update.set(
-            DBCol::LatestWitnessesByIndex,
-            &new_witness_index.to_be_bytes(),
-            &key.serialized(),
-        );
 
-        // Update LatestWitnessesInfo
-        store_update.set(DBCol::Misc, &LATEST_WITNESSES_INFO, &borsh::to_vec(&info)?);
+        // The info is read and written in a transaction, so that witnesses
+        // saved concurrently don't overwrite each other's index.
+        let mut store_update_time = std::time::Duration::ZERO;
+        let info = self.store().transact(|store_update| -> Result<_, std::io::Error> {
+            // Read the current `LatestWitnessesInfo`, or create a new one if there is none.
+            let mut info = store_update
+                .get_ser::<LatestWitnessesInfo>(DBCol::Misc, LATEST_WITNESSES_INFO)?
+                .unwrap_or_default();
+
+            let new_witness_index = info.next_witness_index;
+
+            // Adjust the info to include the new witness.
+            info.count += 1;
+            info.total_size += serialized_witness.len() as u64;
+            info.next_witness_index += 1;
+
+            // Go over witnesses with increasing indexes and remove them until the limits are satisfied.
+            while !info.is_within_limits() && info.lowest_index < info.next_witness_index {
+                let key_to_delete = store_update
+                    .get(DBCol::LatestWitnessesByIndex, &info.lowest_index.to_be_bytes())?
+                    .ok_or_else(|| {
+                        std::io::Error::new(
+                            ErrorKind::NotFound,
+                            format!(
+                                "Cannot find witness key to delete with index {}",
+                                info.lowest_index
+                            ),
+                        )
+                    })?;
+                let key_deser = LatestWitnessesKey::deserialize(&key_to_delete)?;
+
+                store_update.delete(DBCol::LatestChunkStateWitnesses, &key_to_delete);
+                store_update
+                    .delete(DBCol::LatestWitnessesByIndex, &info.lowest_index.to_be_bytes());
+                info.lowest_index += 1;
+                info.count -= 1;
+                info.total_size -= key_deser.witness_size;
+            }
+
+            // Limits are ok, insert the new witness.
+            store_update.set(
+                DBCol::LatestChunkStateWitnesses,
+                &key.serialized(),
+                &serialized_witness,
+            );
+            store_update.set(
+                DBCol::LatestWitnessesByIndex,
+                &new_witness_index.to_be_bytes(),
+                &key.serialized(),
+            );
 
-        let store_update_time = start_time.elapsed();
+            // Update LatestWitnessesInfo
+            store_update.set(DBCol::Misc, &LATEST_WITNESSES_INFO, &borsh::to_vec(&info)?);
 
-        // Commit the transaction
-        store_update.commit()?;
+            store_update_time = start_time.elapsed();
+            Ok(info)
+        })?;
 
         let store_commit_time = start_time.elapsed().saturating_sub(store_update_time);
 
@@ -0,0 +1,52 @@
+//! Light client checkpoints, see `DBCol::LightClientCheckpoints`. A checkpoint
+//! keeps the few fields of the header of the last block of an epoch which are
+//! needed to serve `next_light_client_block` to light clients which know that
+//! block. Checkpoints are saved when the header itself gets pruned.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_chain_primitives::Error;
+use near_primitives::block::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{BlockHeight, EpochId};
+use near_store::{DBCol, HEADER_TAIL_KEY};
+
+use super::{option_to_not_found, ChainStore};
+use crate::ChainStoreAccess;
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LightClientCheckpoint {
+    pub epoch_id: EpochId,
+    pub next_epoch_id: EpochId,
+    pub height: BlockHeight,
+}
+
+impl From<&BlockHeader> for LightClientCheckpoint {
+    fn from(header: &BlockHeader) -> Self {
+        Self {
+            epoch_id: *header.epoch_id(),
+            next_epoch_id: *header.next_epoch_id(),
+            height: header.height(),
+        }
+    }
+}
+
+impl ChainStore {
+    pub fn get_light_client_checkpoint(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<LightClientCheckpoint, Error> {
+        option_to_not_found(
+            self.store().get_ser(DBCol::LightClientCheckpoints, block_hash.as_ref()),
+            format_args!("LIGHT CLIENT CHECKPOINT: {}", block_hash),
+        )
+    }
+
+    /// Returns the lowest height from which the node keeps the headers of all
+    /// the canonical blocks, see `prune_headers` of the GC config.
+    pub fn header_tail(&self) -> Result<BlockHeight, Error> {
+        Ok(self
+            .store()
+            .get_ser(DBCol::BlockMisc, HEADER_TAIL_KEY)?
+            .unwrap_or(self.get_genesis_height() + 1))
+    }
+}
@@ -11,17 +11,18 @@ use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::block::Tip;
 use near_primitives::checked_feature;
 use near_primitives::errors::InvalidTxError;
+use near_primitives::events::EventsBloom;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::receipt::Receipt;
-use near_primitives::shard_layout::account_id_to_shard_id;
 use near_primitives::shard_layout::{get_block_shard_uid, ShardLayout, ShardUId};
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, PartialEncodedChunk, ReceiptProof, ShardChunk, ShardChunkHeader,
     StateSyncInfo,
 };
 use near_primitives::state_sync::{
-    ReceiptProofResponse, ShardStateSyncResponseHeader, StateHeaderKey, StateSyncDumpProgress,
+    ReceiptProofResponse, ShardStateSyncResponseHeader, StateHeaderKey, StatePartUpload,
+    StateSyncDumpProgress,
 };
 use near_primitives::stateless_validation::stored_chunk_state_transition_data::StoredChunkStateTransitionData;
 use near_primitives::transaction::{
@@ -39,7 +40,7 @@ use near_primitives::utils::{
     to_timestamp,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::LightClientBlockView;
+use near_primitives::views::{BlockHeightStatus, BlockHeightStatusView, LightClientBlockView};
 use near_store::{
     DBCol, KeyForStateChanges, PartialStorage, Store, StoreUpdate, WrappedTrieChanges,
     CHUNK_TAIL_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY,
@@ -49,11 +50,14 @@ use near_store::{
 use crate::byzantine_assert;
 use crate::chunks_store::ReadOnlyChunksStore;
 use crate::types::{Block, BlockHeader, LatestKnown};
-use near_store::db::{StoreStatistics, STATE_SYNC_DUMP_KEY};
+use near_store::db::{StoreStatistics, STATE_PART_UPLOAD_KEY, STATE_SYNC_DUMP_KEY};
 use std::sync::Arc;
 
+mod epoch_shard_stats;
 mod latest_witnesses;
+mod light_client_checkpoints;
 pub use latest_witnesses::LatestWitnessesInfo;
+pub use light_client_checkpoints::LightClientCheckpoint;
 
 /// lru cache size
 #[cfg(not(feature = "no_cache"))]
@@ -378,8 +382,7 @@ fn filter_incoming_receipts_for_shard(
         let mut filtered_receipts = vec![];
         let ReceiptProof(receipts, shard_proof) = receipt_proof.clone();
         for receipt in receipts {
-            let receiver_shard_id =
-                account_id_to_shard_id(receipt.receiver_id(), target_shard_layout);
+            let receiver_shard_id = receipt.receiver_shard_id(target_shard_layout);
             if receiver_shard_id == target_shard_id {
                 tracing::trace!(target: "chain", receipt_id=?receipt.receipt_id(), "including receipt");
                 filtered_receipts.push(receipt);
@@ -713,6 +716,42 @@ impl ChainStore {
 }
 
 impl ChainStore {
+    /// Returns the status of the given height on the canonical chain, telling
+    /// apart skipped heights from garbage collected blocks and heights which
+    /// were not reached yet.
+    ///
+    /// Heights are read from `DBCol::BlockHeight` and `DBCol::SkippedBlockHeights`,
+    /// which are updated together whenever the canonical chain changes. Heights
+    /// in neither of them are unknown to the node, e.g. because it bootstrapped
+    /// with epoch sync or the heights were indexed before the node saved skipped
+    /// heights.
+    pub fn get_block_height_status(
+        &self,
+        height: BlockHeight,
+    ) -> Result<BlockHeightStatusView, Error> {
+        let head = self.head()?;
+        let is_final = height <= self.final_head()?.height;
+        let status = if height > head.height {
+            BlockHeightStatus::NotYetProduced
+        } else {
+            match self.get_block_hash_by_height(height) {
+                Ok(block_hash) if self.block_exists(&block_hash)? => {
+                    BlockHeightStatus::Available { block_hash }
+                }
+                Ok(block_hash) => BlockHeightStatus::GarbageCollected { block_hash },
+                Err(Error::DBNotFoundErr(_)) => {
+                    if self.store.exists(DBCol::SkippedBlockHeights, &index_to_bytes(height))? {
+                        BlockHeightStatus::Skipped
+                    } else {
+                        BlockHeightStatus::Unknown
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        Ok(BlockHeightStatusView { height, is_final, status })
+    }
+
     /// Returns outcomes on all forks generated by applying transaction or
     /// receipt with the given id.
     pub fn get_outcomes_by_id(
@@ -762,6 +801,18 @@ impl ChainStore {
             .unwrap_or_default())
     }
 
+    /// Returns the bloom filter of the events emitted in the chunk of the given block and shard
+    /// id, or `None` if no event was.
+    pub fn get_outcome_events_bloom(
+        &self,
+        block_hash: &CryptoHash,
+        shard_id: ShardId,
+    ) -> Result<Option<EventsBloom>, Error> {
+        Ok(self
+            .store
+            .get_ser(DBCol::OutcomeEventsBloom, &get_block_shard_id(block_hash, shard_id))?)
+    }
+
     /// Get all execution outcomes generated when the chunk are applied
     pub fn get_block_execution_outcomes(
         &self,
@@ -1049,6 +1100,38 @@ impl ChainStore {
         }
         store_update.commit().map_err(|err| err.into())
     }
+
+    /// Constructs key 'STATE_PART_UPLOAD:<ShardId>'.  Only one part of a
+    /// shard is uploaded at a time.
+    fn state_part_upload_key(shard_id: ShardId) -> Vec<u8> {
+        let mut key = STATE_PART_UPLOAD_KEY.to_vec();
+        key.extend(b":".to_vec());
+        key.extend(shard_id.to_le_bytes());
+        key
+    }
+
+    /// Retrieves the unfinished upload of a state part of the given shard.
+    pub fn get_state_part_upload(
+        &self,
+        shard_id: ShardId,
+    ) -> Result<Option<StatePartUpload>, Error> {
+        Ok(self.store.get_ser(DBCol::BlockMisc, &ChainStore::state_part_upload_key(shard_id))?)
+    }
+
+    /// Updates the unfinished upload of a state part of the given shard.
+    pub fn set_state_part_upload(
+        &self,
+        shard_id: ShardId,
+        value: Option<&StatePartUpload>,
+    ) -> Result<(), Error> {
+        let mut store_update = self.store.store_update();
+        let key = ChainStore::state_part_upload_key(shard_id);
+        match value {
+            None => store_update.delete(DBCol::BlockMisc, &key),
+            Some(value) => store_update.set_ser(DBCol::BlockMisc, &key, value)?,
+        }
+        store_update.commit().map_err(|err| err.into())
+    }
 }
 
 impl ChainStoreAccess for ChainStore {
@@ -1351,12 +1434,16 @@ pub(crate) struct ChainStoreCacheUpdate {
     partial_chunks: HashMap<ChunkHash, Arc<PartialEncodedChunk>>,
     block_hash_per_height: HashMap<BlockHeight, HashMap<EpochId, HashSet<CryptoHash>>>,
     pub(crate) height_to_hashes: HashMap<BlockHeight, Option<CryptoHash>>,
+    /// Heights of `height_to_hashes` without a block which the canonical chain
+    /// skips, as opposed to heights above the head.
+    skipped_heights: HashSet<BlockHeight>,
     next_block_hashes: HashMap<CryptoHash, CryptoHash>,
     epoch_light_client_blocks: HashMap<CryptoHash, Arc<LightClientBlockView>>,
     outgoing_receipts: HashMap<(CryptoHash, ShardId), Arc<Vec<Receipt>>>,
     incoming_receipts: HashMap<(CryptoHash, ShardId), Arc<Vec<ReceiptProof>>>,
     outcomes: HashMap<(CryptoHash, CryptoHash), ExecutionOutcomeWithProof>,
     outcome_ids: HashMap<(CryptoHash, ShardId), Vec<CryptoHash>>,
+    outcome_events_blooms: HashMap<(CryptoHash, ShardId), EventsBloom>,
     invalid_chunks: HashMap<ChunkHash, Arc<EncodedShardChunk>>,
     transactions: HashMap<CryptoHash, Arc<SignedTransaction>>,
     receipts: HashMap<CryptoHash, Arc<Receipt>>,
@@ -1763,6 +1850,7 @@ impl<'a> ChainStoreUpdate<'a> {
             // Clean up block indices between blocks.
             for height in (header_height + 1)..prev_height {
                 self.chain_store_cache_update.height_to_hashes.insert(height, None);
+                self.chain_store_cache_update.skipped_heights.insert(height);
             }
             // Override block ordinal to hash mapping for blocks in between.
             // At this point block_merkle_tree for header is already saved.
@@ -1783,6 +1871,7 @@ impl<'a> ChainStoreUpdate<'a> {
                     self.chain_store_cache_update
                         .height_to_hashes
                         .insert(header_height, Some(header_hash));
+                    self.chain_store_cache_update.skipped_heights.remove(&header_height);
                     self.chain_store_cache_update
                         .next_block_hashes
                         .insert(header_prev_hash, header_hash);
@@ -1821,6 +1910,7 @@ impl<'a> ChainStoreUpdate<'a> {
                 if prev_tip.height > t.height {
                     for height in (t.height + 1)..=prev_tip.height {
                         self.chain_store_cache_update.height_to_hashes.insert(height, None);
+                        self.chain_store_cache_update.skipped_heights.remove(&height);
                     }
                 }
             }
@@ -1836,6 +1926,7 @@ impl<'a> ChainStoreUpdate<'a> {
             .block_ordinal_to_hash
             .insert(block_ordinal, t.last_block_hash);
         self.chain_store_cache_update.height_to_hashes.insert(t.height, Some(t.last_block_hash));
+        self.chain_store_cache_update.skipped_heights.remove(&t.height);
         self.chain_store_cache_update
             .next_block_hashes
             .insert(t.prev_block_hash, t.last_block_hash);
@@ -1987,6 +2078,12 @@ impl<'a> ChainStoreUpdate<'a> {
         outcomes: Vec<ExecutionOutcomeWithId>,
         proofs: Vec<MerklePath>,
     ) {
+        let events_bloom = EventsBloom::from_outcomes(&outcomes);
+        if !events_bloom.is_empty() {
+            self.chain_store_cache_update
+                .outcome_events_blooms
+                .insert((*block_hash, shard_id), events_bloom);
+        }
         let mut outcome_ids = Vec::with_capacity(outcomes.len());
         for (outcome_with_id, proof) in outcomes.into_iter().zip(proofs.into_iter()) {
             outcome_ids.push(outcome_with_id.id);
@@ -2208,6 +2305,14 @@ impl<'a> ChainStoreUpdate<'a> {
                 .chain_store_cache_update
                 .outcome_ids
                 .insert((*block_hash, shard_id), outcome_ids);
+            if let Some(events_bloom) =
+                source_store.get_outcome_events_bloom(block_hash, shard_id)?
+            {
+                chain_store_update
+                    .chain_store_cache_update
+                    .outcome_events_blooms
+                    .insert((*block_hash, shard_id), events_bloom);
+            }
         }
         chain_store_update
             .chain_store_cache_update
@@ -2380,10 +2485,16 @@ impl<'a> ChainStoreUpdate<'a> {
         }
 
         for (height, hash) in self.chain_store_cache_update.height_to_hashes.iter() {
+            let key = index_to_bytes(*height);
             if let Some(hash) = hash {
-                store_update.set_ser(DBCol::BlockHeight, &index_to_bytes(*height), hash)?;
+                store_update.set_ser(DBCol::BlockHeight, &key, hash)?;
+            } else {
+                store_update.delete(DBCol::BlockHeight, &key);
+            }
+            if self.chain_store_cache_update.skipped_heights.contains(height) {
+                store_update.set_ser(DBCol::SkippedBlockHeights, &key, &())?;
             } else {
-                store_update.delete(DBCol::BlockHeight, &index_to_bytes(*height));
+                store_update.delete(DBCol::SkippedBlockHeights, &key);
             }
         }
         for (block_hash, next_hash) in self.chain_store_cache_update.next_block_hashes.iter() {
@@ -2442,6 +2553,15 @@ impl<'a> ChainStoreUpdate<'a> {
                     &ids,
                 )?;
             }
+            for ((block_hash, shard_id), events_bloom) in
+                self.chain_store_cache_update.outcome_events_blooms.iter()
+            {
+                store_update.set_ser(
+                    DBCol::OutcomeEventsBloom,
+                    &get_block_shard_id(block_hash, *shard_id),
+                    events_bloom,
+                )?;
+            }
         }
 
         for (block_hash, refcount) in self.chain_store_cache_update.block_refcounts.iter() {
@@ -2608,8 +2728,10 @@ impl<'a> ChainStoreUpdate<'a> {
             block_ordinal_to_hash,
             processed_block_heights,
 
+            skipped_heights: _,
             outcomes: _,
             outcome_ids: _,
+            outcome_events_blooms: _,
         } = self.chain_store_cache_update;
         for (hash, block) in blocks {
             self.chain_store.blocks.put(hash.into(), block);
@@ -11,6 +11,7 @@ use near_primitives::transaction::{ExecutionOutcomeWithProof, SignedTransaction}
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{BlockHeight, EpochId};
 use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, index_to_bytes};
+use near_store::db::ABANDONED_FORKS_TAIL_KEY;
 use near_store::{
     DBCol, TrieChanges, CHUNK_TAIL_KEY, FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY, TAIL_KEY,
 };
@@ -353,11 +354,30 @@ pub(crate) fn chunk_tx_exists(
     Ok(())
 }
 
+/// Whether garbage collection already deleted the chunk extras and outcomes
+/// of the block because it is on a fork abandoned behind the final head.
+fn is_cleared_abandoned_block(
+    sv: &StoreValidator,
+    block_hash: &CryptoHash,
+    height: BlockHeight,
+) -> Result<bool, StoreValidatorError> {
+    let cleared_height: Option<BlockHeight> =
+        sv.store.get_ser(DBCol::BlockMisc, ABANDONED_FORKS_TAIL_KEY)?;
+    if cleared_height.map_or(true, |cleared_height| height > cleared_height) {
+        return Ok(false);
+    }
+    let canonical_hash: Option<CryptoHash> =
+        sv.store.get_ser(DBCol::BlockHeight, &index_to_bytes(height))?;
+    Ok(canonical_hash.as_ref() != Some(block_hash))
+}
+
 pub(crate) fn block_chunks_exist(
     sv: &mut StoreValidator,
-    _block_hash: &CryptoHash,
+    block_hash: &CryptoHash,
     block: &Block,
 ) -> Result<(), StoreValidatorError> {
+    let execution_data_cleared =
+        is_cleared_abandoned_block(sv, block_hash, block.header().height())?;
     for chunk_header in block.chunks().iter() {
         if chunk_header.height_included() == block.header().height() {
             if let Some(me) = &sv.me {
@@ -382,7 +402,7 @@ pub(crate) fn block_chunks_exist(
                         "Can't get Chunk {:?} from storage",
                         chunk_header
                     );
-                    if cares_about_shard {
+                    if cares_about_shard && !execution_data_cleared {
                         let shard_uid = sv
                             .epoch_manager
                             .shard_id_to_uid(chunk_header.shard_id(), block.header().epoch_id())
@@ -533,26 +553,31 @@ pub(crate) fn trie_changes_chunk_extra_exists(
         sv.store.get_ser::<Block>(DBCol::Block, block_hash.as_ref()),
         "Can't get Block from DB - trie_changes_chunk_extra_exists"
     );
-    // 2) Chunk Extra with `block_hash` and `shard_uid` should be available and match with the new root
-    let chunk_extra = unwrap_or_err_db!(
-        sv.store
-            .get_ser::<ChunkExtra>(DBCol::ChunkExtra, &get_block_shard_uid(block_hash, shard_uid)),
-        "Can't get Chunk Extra from storage with key {:?} {:?}",
-        block_hash,
-        shard_uid
-    );
-    check_discrepancy!(chunk_extra.state_root(), &new_root, "State Root discrepancy");
-    // 3) Chunk Extra with `prev_block_hash` and `shard_uid` should match with the old root if available
-    if let Ok(Some(prev_chunk_extra)) = sv.store.get_ser::<ChunkExtra>(
-        DBCol::ChunkExtra,
-        &get_block_shard_uid(block.header().prev_hash(), shard_uid),
-    ) {
-        check_discrepancy!(
-            prev_chunk_extra.state_root(),
-            &trie_changes.old_root,
-            "Prev State Root discrepancy, previous ChunkExtra {:?}",
-            prev_chunk_extra
+    // Chunk extras of abandoned forks are deleted before their trie changes.
+    if !is_cleared_abandoned_block(sv, block_hash, block.header().height())? {
+        // 2) Chunk Extra with `block_hash` and `shard_uid` should be available and match with the new root
+        let chunk_extra = unwrap_or_err_db!(
+            sv.store.get_ser::<ChunkExtra>(
+                DBCol::ChunkExtra,
+                &get_block_shard_uid(block_hash, shard_uid)
+            ),
+            "Can't get Chunk Extra from storage with key {:?} {:?}",
+            block_hash,
+            shard_uid
         );
+        check_discrepancy!(chunk_extra.state_root(), &new_root, "State Root discrepancy");
+        // 3) Chunk Extra with `prev_block_hash` and `shard_uid` should match with the old root if available
+        if let Ok(Some(prev_chunk_extra)) = sv.store.get_ser::<ChunkExtra>(
+            DBCol::ChunkExtra,
+            &get_block_shard_uid(block.header().prev_hash(), shard_uid),
+        ) {
+            check_discrepancy!(
+                prev_chunk_extra.state_root(),
+                &trie_changes.old_root,
+                "Prev State Root discrepancy, previous ChunkExtra {:?}",
+                prev_chunk_extra
+            );
+        }
     }
     // 4) Trie should exist for `shard_uid` and the root
     let trie = sv.runtime.get_tries().get_trie_for_shard(*shard_uid, new_root);
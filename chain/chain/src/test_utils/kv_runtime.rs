@@ -1105,6 +1105,7 @@ impl RuntimeAdapter for KeyValueRuntime {
         _transaction: &SignedTransaction,
         _verify_signature: bool,
         _epoch_id: &EpochId,
+        _next_block_height: BlockHeight,
         _current_protocol_version: ProtocolVersion,
         _receiver_congestion_info: Option<ExtendedCongestionInfo>,
     ) -> Result<Option<InvalidTxError>, Error> {
@@ -1342,6 +1343,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                         public_key: PublicKey::empty(KeyType::ED25519),
                         access_key: AccessKey::full_access().into(),
                     }],
+                    next_cursor: None,
                 }),
                 block_height,
                 block_hash: *block_hash,
@@ -1355,6 +1357,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                 kind: QueryResponseKind::ViewState(ViewStateResult {
                     values: Default::default(),
                     proof: vec![],
+                    next_cursor: None,
                 }),
                 block_height,
                 block_hash: *block_hash,
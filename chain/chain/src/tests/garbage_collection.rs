@@ -1,3 +1,4 @@
+use assert_matches::assert_matches;
 use near_async::time::Clock;
 use rand::Rng;
 use std::sync::Arc;
@@ -9,7 +10,7 @@ use crate::test_utils::{
     get_chain_with_num_shards,
 };
 use crate::types::Tip;
-use crate::{ChainStoreAccess, StoreValidator};
+use crate::{ChainStoreAccess, Error, StoreValidator};
 
 use near_chain_configs::{GCConfig, GenesisConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP};
 use near_epoch_manager::EpochManagerAdapter;
@@ -18,6 +19,7 @@ use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::merkle::PartialMerkleTree;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::test_utils::{create_test_signer, TestBlockBuilder};
+use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{BlockHeight, NumBlocks, StateRoot};
 use near_primitives::validator_signer::ValidatorSigner;
 use near_store::test_utils::gen_changes;
@@ -726,6 +728,59 @@ fn test_clear_old_data() {
     }
 }
 
+#[test]
+fn test_prune_headers() {
+    let max_height = 30usize;
+    let mut chain = get_chain_with_epoch_length(Clock::real(), 3);
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    for i in 1..=max_height {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i as BlockHeight,
+        );
+    }
+
+    let gc_config = GCConfig { gc_blocks_limit: 100, prune_headers: true, ..GCConfig::default() };
+    chain.clear_data(&gc_config).unwrap();
+    // Headers are pruned below the tail left by the previous call.
+    chain.clear_data(&gc_config).unwrap();
+
+    let tail = chain.chain_store().tail().unwrap();
+    assert!(tail > 1);
+    let store = chain.chain_store().store().clone();
+    let mut num_pruned = 0;
+    for i in 0..=max_height {
+        // Epoch sync needs the headers of the last blocks of the epochs.
+        let is_epoch_last_block = blocks
+            .get(i + 1)
+            .is_some_and(|next| next.header().epoch_id() != blocks[i].header().epoch_id());
+        let expected_pruned = i > 0 && (i as BlockHeight) < tail && !is_epoch_last_block;
+        let header = store.get(DBCol::BlockHeader, blocks[i].hash().as_ref()).unwrap();
+        assert_eq!(header.is_none(), expected_pruned, "height {}", i);
+        num_pruned += expected_pruned as usize;
+    }
+    assert!(num_pruned > 0);
+
+    // Requests for headers below the header tail are declined instead of
+    // being served with gaps, while the headers above it are still served.
+    assert_eq!(chain.chain_store().header_tail().unwrap(), tail);
+    assert_matches!(
+        chain.retrieve_headers(vec![*blocks[0].hash()], 100, None),
+        Err(Error::DBNotFoundErr(_))
+    );
+    let headers = chain.retrieve_headers(vec![*blocks[tail as usize].hash()], 100, None).unwrap();
+    let heights: Vec<_> = headers.iter().map(|header| header.height()).collect();
+    assert_eq!(heights, (tail + 1..=max_height as BlockHeight).collect::<Vec<_>>());
+}
+
 // Adds block to the chain at given height after prev_block.
 fn add_block(
     chain: &mut Chain,
@@ -775,6 +830,64 @@ fn add_block(
     *prev_block = block.clone();
 }
 
+/// The chunk extras of a fork abandoned behind the final head are deleted
+/// while the fork itself is still far from the GC stop height.
+#[test]
+fn test_clear_abandoned_forks_data() {
+    let mut chain = get_chain(Clock::real());
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    for i in 1..=8 {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i,
+        );
+    }
+    // A block at height 4 on top of the one at height 2, competing with the
+    // canonical block at height 4.
+    let fork_block =
+        TestBlockBuilder::new(Clock::real(), &blocks[2], signer.clone()).height(4).build();
+    let shard_uid = ShardUId { version: 0, shard_id: 0 };
+    let chunk_extra = ChunkExtra::new_with_only_state_root(&Trie::EMPTY_ROOT);
+    let mut store_update = chain.mut_chain_store().store_update();
+    store_update.save_block(fork_block.clone());
+    store_update.inc_block_refcount(fork_block.header().prev_hash()).unwrap();
+    store_update.save_block_header(fork_block.header().clone()).unwrap();
+    store_update.save_chunk_extra(fork_block.hash(), &shard_uid, chunk_extra.clone());
+    store_update.save_chunk_extra(blocks[4].hash(), &shard_uid, chunk_extra);
+    store_update.save_final_head(&Tip::from_header(blocks[6].header())).unwrap();
+    let epoch_manager_update = epoch_manager
+        .add_validator_proposals(
+            BlockInfo::from_header(fork_block.header(), 2),
+            *fork_block.header().random_value(),
+        )
+        .unwrap();
+    store_update.merge(epoch_manager_update);
+    store_update.commit().unwrap();
+
+    chain.clear_data(&GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }).unwrap();
+
+    let store = chain.chain_store().store().clone();
+    let chunk_extra_exists = |block: &Block| {
+        let key = near_primitives::shard_layout::get_block_shard_uid(block.hash(), &shard_uid);
+        store.get(DBCol::ChunkExtra, &key).unwrap().is_some()
+    };
+    assert!(!chunk_extra_exists(&fork_block));
+    assert!(chunk_extra_exists(&blocks[4]));
+    // The block is left to forks clearing.
+    assert!(chain.get_block(fork_block.hash()).is_ok());
+    let cleared_height: Option<BlockHeight> =
+        store.get_ser(DBCol::BlockMisc, near_store::db::ABANDONED_FORKS_TAIL_KEY).unwrap();
+    assert_eq!(cleared_height, Some(6));
+}
+
 #[test]
 fn test_clear_old_data_fixed_height() {
     let mut chain = get_chain(Clock::real());
@@ -6,7 +6,10 @@ use near_async::time::{Clock, Duration, FakeClock, Utc};
 use near_o11y::testonly::init_test_logger;
 use near_primitives::hash::CryptoHash;
 use near_primitives::test_utils::TestBlockBuilder;
+use near_primitives::utils::index_to_bytes;
 use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::views::{BlockHeightStatus, ForkNotChosenReason};
+use near_store::DBCol;
 use num_rational::Ratio;
 
 #[test]
@@ -247,3 +250,96 @@ fn next_blocks() {
     assert_eq!(chain.mut_chain_store().get_next_block_hash(&b1_hash).unwrap(), b3_hash);
     assert_eq!(chain.mut_chain_store().get_next_block_hash(&b3_hash).unwrap(), b4_hash);
 }
+
+/// Checks that the status of a height tells apart blocks on the canonical chain,
+/// skipped heights and heights above the head, also after a reorg.
+///
+/// 0 -> b1 -> b3
+///        \
+///         -> c2 -> c4
+#[test]
+fn block_height_status() {
+    init_test_logger();
+    let (mut chain, _, _, signer) = setup(Clock::real());
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let b1 = TestBlockBuilder::new(Clock::real(), &genesis, signer.clone()).height(1).build();
+    let b3 = TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).height(3).build();
+    let c2 = TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).height(2).build();
+    let c4 = TestBlockBuilder::new(Clock::real(), &c2, signer).height(4).build();
+    let b1_hash = *b1.hash();
+    let b3_hash = *b3.hash();
+    let c2_hash = *c2.hash();
+    let c4_hash = *c4.hash();
+    chain.process_block_test(&None, b1).unwrap();
+    chain.process_block_test(&None, b3).unwrap();
+
+    let status = |chain: &crate::Chain, height| {
+        chain.chain_store().get_block_height_status(height).unwrap().status
+    };
+    assert_eq!(status(&chain, 1), BlockHeightStatus::Available { block_hash: b1_hash });
+    assert_eq!(status(&chain, 2), BlockHeightStatus::Skipped);
+    assert_eq!(status(&chain, 3), BlockHeightStatus::Available { block_hash: b3_hash });
+    assert_eq!(status(&chain, 4), BlockHeightStatus::NotYetProduced);
+
+    // The fork fills height 2 and skips height 3 instead.
+    chain.process_block_test(&None, c2).unwrap();
+    chain.process_block_test(&None, c4).unwrap();
+    assert_eq!(status(&chain, 2), BlockHeightStatus::Available { block_hash: c2_hash });
+    assert_eq!(status(&chain, 3), BlockHeightStatus::Skipped);
+    assert_eq!(status(&chain, 4), BlockHeightStatus::Available { block_hash: c4_hash });
+
+    // Heights skipped before the node saved skipped heights are unknown.
+    let mut store_update = chain.chain_store().store().store_update();
+    store_update.delete(DBCol::SkippedBlockHeights, &index_to_bytes(3));
+    store_update.commit().unwrap();
+    assert_eq!(status(&chain, 3), BlockHeightStatus::Unknown);
+}
+
+/// Checks that fork info lists the tips of all forks and tells why they
+/// aren't the head.
+///
+/// 0 -> b1 -> b3 -> b4 -> b5
+///        \
+///         -> c2
+#[test]
+fn fork_info() {
+    init_test_logger();
+    let (mut chain, _, _, signer) = setup(Clock::real());
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let b1 = TestBlockBuilder::new(Clock::real(), &genesis, signer.clone()).height(1).build();
+    let c2 = TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).height(2).build();
+    let b3 = TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).height(3).build();
+    let b4 = TestBlockBuilder::new(Clock::real(), &b3, signer.clone()).build();
+    let b5 = TestBlockBuilder::new(Clock::real(), &b4, signer).build();
+    let c2_hash = *c2.hash();
+    let b3_hash = *b3.hash();
+    let b5_hash = *b5.hash();
+    chain.process_block_test(&None, b1).unwrap();
+    chain.process_block_test(&None, c2).unwrap();
+    chain.process_block_test(&None, b3).unwrap();
+
+    let tips = |chain: &crate::Chain| {
+        let fork_info = chain.get_fork_info(None).unwrap();
+        fork_info
+            .tips
+            .iter()
+            .map(|tip| (tip.hash, tip.fork_height, tip.not_chosen_reason))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        tips(&chain),
+        vec![(b3_hash, Some(3), None), (c2_hash, Some(1), Some(ForkNotChosenReason::LowerHeight)),]
+    );
+
+    // Once b3 is final, c2 can't become canonical anymore.
+    chain.process_block_test(&None, b4).unwrap();
+    chain.process_block_test(&None, b5).unwrap();
+    assert_eq!(chain.final_head().unwrap().height, 3);
+    assert_eq!(
+        tips(&chain),
+        vec![
+            (b5_hash, Some(5), None),
+            (c2_hash, Some(1), Some(ForkNotChosenReason::ConflictsWithFinalBlock)),
+        ]
+    );
+}
@@ -412,6 +412,8 @@ pub trait RuntimeAdapter: Send + Sync {
     /// a validation error, or `None` in case the transaction succeeded.
     /// Throws an `Error` with `ErrorKind::StorageError` in case the runtime throws
     /// `RuntimeError::StorageError`.
+    /// `next_block_height` is the height of the earliest block which can include the
+    /// transaction, transactions signed with access keys expired by then are rejected.
     fn validate_tx(
         &self,
         gas_price: Balance,
@@ -419,6 +421,7 @@ pub trait RuntimeAdapter: Send + Sync {
         transaction: &SignedTransaction,
         verify_signature: bool,
         epoch_id: &EpochId,
+        next_block_height: BlockHeight,
         current_protocol_version: ProtocolVersion,
         receiver_congestion_info: Option<ExtendedCongestionInfo>,
     ) -> Result<Option<InvalidTxError>, Error>;
@@ -5,7 +5,9 @@ use itertools::Itertools;
 
 use near_pool::types::TransactionGroupIterator;
 use near_pool::{InsertTransactionResult, PoolIteratorWrapper, TransactionPool};
-use near_primitives::shard_layout::{account_id_to_shard_uid, ShardLayout, ShardUId};
+use near_primitives::shard_layout::{
+    account_id_to_shard_id, account_id_to_shard_uid, ShardLayout, ShardUId,
+};
 use near_primitives::{
     epoch_info::RngSeed,
     sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
@@ -43,24 +45,36 @@ pub struct ShardedTransactionPool {
     /// If set, new transactions that bring the size of the pool over this limit will be rejected.
     /// The size is tracked and enforced separately for each shard.
     pool_size_limit: Option<u64>,
+
+    /// If set, new transactions that bring the size of transactions targeting their receiver
+    /// shard over this limit will be rejected. The size is tracked and enforced separately for
+    /// each pair of signer and receiver shards.
+    receiver_shard_pool_size_limit: Option<u64>,
 }
 
 impl ShardedTransactionPool {
-    pub fn new(rng_seed: RngSeed, pool_size_limit: Option<u64>) -> Self {
-        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit }
+    pub fn new(
+        rng_seed: RngSeed,
+        pool_size_limit: Option<u64>,
+        receiver_shard_pool_size_limit: Option<u64>,
+    ) -> Self {
+        Self { tx_pools: HashMap::new(), rng_seed, pool_size_limit, receiver_shard_pool_size_limit }
     }
 
     pub fn get_pool_iterator(&mut self, shard_uid: ShardUId) -> Option<PoolIteratorWrapper<'_>> {
         self.tx_pools.get_mut(&shard_uid).map(|pool| pool.pool_iterator())
     }
 
-    /// Tries to insert the transaction into the pool for a given shard.
+    /// Tries to insert the transaction into the pool for a given shard. The shard layout is used
+    /// to find the shard of the transaction receiver.
     pub fn insert_transaction(
         &mut self,
         shard_uid: ShardUId,
+        shard_layout: &ShardLayout,
         tx: SignedTransaction,
     ) -> InsertTransactionResult {
-        self.pool_for_shard(shard_uid).insert_transaction(tx)
+        let receiver_shard_id = account_id_to_shard_id(tx.transaction.receiver_id(), shard_layout);
+        self.pool_for_shard(shard_uid).insert_transaction(tx, receiver_shard_id)
     }
 
     pub fn remove_transactions(&mut self, shard_uid: ShardUId, transactions: &[SignedTransaction]) {
@@ -85,6 +99,7 @@ impl ShardedTransactionPool {
             TransactionPool::new(
                 Self::random_seed(&self.rng_seed, shard_uid.shard_id()),
                 self.pool_size_limit,
+                self.receiver_shard_pool_size_limit,
                 &shard_uid.to_string(),
             )
         })
@@ -98,17 +113,34 @@ impl ShardedTransactionPool {
             .join("; ")
     }
 
+    /// Returns the transactions of all shards, ordered by signer and nonce, e.g.
+    /// to hand them off to another node.
+    pub fn all_transactions(&self) -> Vec<SignedTransaction> {
+        self.tx_pools
+            .values()
+            .flat_map(|pool| pool.transactions())
+            .cloned()
+            .sorted_by(|a, b| {
+                (a.transaction.signer_id(), a.transaction.nonce())
+                    .cmp(&(b.transaction.signer_id(), b.transaction.nonce()))
+            })
+            .collect()
+    }
+
     /// Reintroduces transactions back during the chain reorg. Returns the number of transactions
     /// that were added or are already present in the pool.
     pub fn reintroduce_transactions(
         &mut self,
         shard_uid: ShardUId,
+        shard_layout: &ShardLayout,
         transactions: &[SignedTransaction],
     ) -> usize {
         let mut reintroduced_count = 0;
         let pool = self.pool_for_shard(shard_uid);
         for tx in transactions {
-            reintroduced_count += match pool.insert_transaction(tx.clone()) {
+            let receiver_shard_id =
+                account_id_to_shard_id(tx.transaction.receiver_id(), shard_layout);
+            reintroduced_count += match pool.insert_transaction(tx.clone(), receiver_shard_id) {
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate => 1,
                 InsertTransactionResult::NoSpaceLeft => 0,
             }
@@ -146,7 +178,7 @@ impl ShardedTransactionPool {
         for tx in transactions {
             let signer_id = tx.transaction.signer_id();
             let new_shard_uid = account_id_to_shard_uid(&signer_id, new_shard_layout);
-            self.insert_transaction(new_shard_uid, tx);
+            self.insert_transaction(new_shard_uid, new_shard_layout, tx);
         }
     }
 }
@@ -195,7 +227,7 @@ mod tests {
         let old_shard_layout = ShardLayout::get_simple_nightshade_layout();
         let new_shard_layout = ShardLayout::get_simple_nightshade_layout_v2();
 
-        let mut pool = ShardedTransactionPool::new(TEST_SEED, None);
+        let mut pool = ShardedTransactionPool::new(TEST_SEED, None, None);
 
         let mut shard_id_to_accounts = HashMap::new();
         shard_id_to_accounts.insert(0, vec!["aaa", "abcd", "a-a-a-a-a"]);
@@ -237,7 +269,7 @@ mod tests {
 
             let shard_uid =
                 ShardUId { shard_id: signer_shard_id as u32, version: old_shard_layout.version() };
-            pool.insert_transaction(shard_uid, tx);
+            pool.insert_transaction(shard_uid, &old_shard_layout, tx);
         }
 
         // reshard
@@ -2,7 +2,8 @@
 //! without backwards compatibility of JSON encoding.
 use crate::types::StatusError;
 use near_primitives::congestion_info::CongestionInfo;
-use near_primitives::types::EpochId;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{BlockHeightDelta, EpochId, ShardId};
 use near_primitives::views::{
     CatchupStatusView, ChainProcessingInfo, EpochValidatorInfo, RequestedStatePartsView,
     SyncStatusView,
@@ -193,6 +194,8 @@ pub enum DebugStatus {
     ChainProcessingStatus,
     // The state parts already requested.
     RequestedStateParts,
+    // Flat storage of the shards in the current epoch.
+    FlatStorageStatus,
 }
 
 impl actix::Message for DebugStatus {
@@ -214,4 +217,44 @@ pub enum DebugStatusResponse {
     ChainProcessingStatus(ChainProcessingInfo),
     // The state parts already requested.
     RequestedStateParts(Vec<RequestedStatePartsView>),
+    // Flat storage of the shards in the current epoch.
+    FlatStorageStatus(Vec<FlatStorageStatusView>),
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct FlatStorageStatusView {
+    pub shard_uid: ShardUId,
+    // Status stored on disk, e.g. `Ready` or `FetchingState`.
+    pub status: String,
+    // Set when flat storage is ready or catching up.
+    pub flat_head_hash: Option<CryptoHash>,
+    pub flat_head_height: Option<BlockHeight>,
+    // How far the flat head is behind the final head of the chain.
+    pub blocks_behind_final_head: Option<BlockHeightDelta>,
+    // Number of state parts already fetched and the total number, while
+    // flat storage is being created.
+    pub fetched_state_parts: Option<(u64, u64)>,
+    // Set when flat storage is loaded.
+    pub num_deltas: Option<usize>,
+    pub move_head_enabled: Option<bool>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlatStorageAction {
+    // Move the flat head the same way as after processing a block.
+    MoveHead,
+    // Delete flat storage and create it again from the trie.
+    Rebuild,
+}
+
+// Admin request to act on the flat storage of a shard, sent via POST.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct FlatStorageControl {
+    pub shard_id: ShardId,
+    pub action: FlatStorageAction,
+}
+
+impl actix::Message for FlatStorageControl {
+    type Result = Result<FlatStorageStatusView, StatusError>;
 }
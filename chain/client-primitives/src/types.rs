@@ -4,16 +4,19 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::network::PeerId;
 use near_primitives::sharding::{ChunkHash, ShardChunk};
+use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, MaybeBlockId, ShardId,
-    TransactionOrReceiptId,
+    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, EpochShardStats, MaybeBlockId,
+    ShardId, TransactionOrReceiptId,
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, DownloadStatusView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    GasPriceView, LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView,
-    QueryRequest, QueryResponse, ReceiptView, ShardSyncDownloadView, SplitStorageInfoView,
-    StateChangesKindsView, StateChangesRequestView, StateChangesView, SyncStatusView, TxStatusView,
+    BlockHeightStatusView, BlockReceiptsView, BlockView, ChunkValidatorsView, ChunkView,
+    DownloadStatusView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ForkInfoView, GasPriceView,
+    LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView, QueryRequest,
+    QueryResponse, ReceiptView, ShardSyncDownloadView, SplitStorageInfoView, StateChangesKindsView,
+    StateChangesRequestView, StateChangesView, StateWitnessView, SyncStatusView, TxStatusView,
+    WatchedAccountView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 use std::collections::HashMap;
@@ -510,6 +513,83 @@ impl From<near_chain_primitives::Error> for GetChunkError {
     }
 }
 
+/// Receipts included or executed in the chunks of a block, optionally
+/// filtered by their receiver and predecessor.
+#[derive(Debug)]
+pub struct GetBlockReceipts {
+    pub block_reference: BlockReference,
+    pub receiver_id: Option<AccountId>,
+    pub predecessor_id: Option<AccountId>,
+}
+
+impl Message for GetBlockReceipts {
+    type Result = Result<BlockReceiptsView, GetBlockReceiptsError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetBlockReceiptsError {
+    #[error("IO Error: {error_message}")]
+    IOError { error_message: String },
+    #[error("Block either has never been observed on the node or has been garbage collected: {error_message}")]
+    UnknownBlock { error_message: String },
+    #[error("There are no fully synchronized blocks yet")]
+    NotSyncedYet,
+    #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
+    UnknownChunk { chunk_hash: ChunkHash },
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {error_message}")]
+    Unreachable { error_message: String },
+}
+
+impl From<near_chain_primitives::Error> for GetBlockReceiptsError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => {
+                Self::IOError { error_message: error.to_string() }
+            }
+            near_chain_primitives::Error::DBNotFoundErr(error_message) => {
+                Self::UnknownBlock { error_message }
+            }
+            near_chain_primitives::Error::ChunkMissing(chunk_hash) => {
+                Self::UnknownChunk { chunk_hash }
+            }
+            _ => Self::Unreachable { error_message: error.to_string() },
+        }
+    }
+}
+
+/// State witnesses of the chunk at the given height and shard, which the node
+/// recorded because `save_latest_witnesses` is enabled.
+#[derive(Debug)]
+pub struct GetStateWitnesses {
+    pub height: BlockHeight,
+    pub shard_id: ShardId,
+    pub epoch_id: Option<EpochId>,
+    /// Return the decoded witnesses instead of their borsh encoding.
+    pub decode: bool,
+}
+
+impl Message for GetStateWitnesses {
+    type Result = Result<Vec<StateWitnessView>, GetStateWitnessesError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetStateWitnessesError {
+    #[error("IO Error: {error_message}")]
+    IOError { error_message: String },
+    #[error("No state witness is recorded for height {height} and shard {shard_id}")]
+    UnknownStateWitness { height: BlockHeight, shard_id: ShardId },
+}
+
+impl From<std::io::Error> for GetStateWitnessesError {
+    fn from(error: std::io::Error) -> Self {
+        Self::IOError { error_message: error.to_string() }
+    }
+}
+
 /// Queries client for given path / data.
 #[derive(Clone, Debug)]
 pub struct Query {
@@ -688,6 +768,36 @@ impl Message for GetNetworkInfo {
     type Result = Result<NetworkInfoResponse, String>;
 }
 
+/// Returns the public RPC URLs advertised by the connected peers which track
+/// the given shard.
+#[derive(Debug)]
+pub struct GetShardRpcEndpoints {
+    pub shard_id: ShardId,
+}
+
+impl Message for GetShardRpcEndpoints {
+    type Result = Vec<String>;
+}
+
+/// Data of the watched accounts, see `watched_accounts` in the client config.
+/// Returns all of them if `account_ids` is empty.
+#[derive(Debug)]
+pub struct GetWatchedAccounts {
+    pub account_ids: Vec<AccountId>,
+}
+
+impl Message for GetWatchedAccounts {
+    type Result = Vec<WatchedAccountView>;
+}
+
+/// All transactions of the pool, ordered by signer and nonce.
+#[derive(Debug)]
+pub struct ExportTransactionPool {}
+
+impl Message for ExportTransactionPool {
+    type Result = Vec<SignedTransaction>;
+}
+
 #[derive(Debug)]
 pub struct GetGasPrice {
     pub block_id: MaybeBlockId,
@@ -822,6 +932,16 @@ impl Message for GetValidatorOrdered {
     type Result = Result<Vec<ValidatorStakeView>, GetValidatorInfoError>;
 }
 
+/// Chunk validators of all shards at the height of the given block.
+#[derive(Debug)]
+pub struct GetChunkValidators {
+    pub block_id: MaybeBlockId,
+}
+
+impl Message for GetChunkValidators {
+    type Result = Result<ChunkValidatorsView, GetValidatorInfoError>;
+}
+
 #[derive(Debug)]
 pub struct GetStateChanges {
     pub block_hash: CryptoHash,
@@ -1098,6 +1218,63 @@ impl From<near_chain_primitives::Error> for GetMaintenanceWindowsError {
     }
 }
 
+/// Statistics of the shards saved at the end of the epoch, see
+/// `save_epoch_shard_stats` in the client config.
+#[derive(Debug)]
+pub struct GetEpochShardStats {
+    pub epoch_id: EpochId,
+}
+
+impl Message for GetEpochShardStats {
+    type Result = Result<Vec<EpochShardStats>, GetEpochShardStatsError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetEpochShardStatsError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetEpochShardStatsError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GetBlockHeightStatus {
+    pub height: BlockHeight,
+}
+
+impl Message for GetBlockHeightStatus {
+    type Result = Result<BlockHeightStatusView, GetBlockHeightStatusError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetBlockHeightStatusError {
+    #[error("There are no fully synchronized blocks yet")]
+    NotSyncedYet,
+    #[error("IO Error: {0}")]
+    IOError(String),
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetBlockHeightStatusError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            near_chain_primitives::Error::DBNotFoundErr(_) => Self::NotSyncedYet,
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GetClientConfig {}
 
@@ -1126,6 +1303,37 @@ impl From<near_chain_primitives::Error> for GetClientConfigError {
     }
 }
 
+/// Forks near the head of the chain, see `Chain::get_fork_info`.
+#[derive(Debug)]
+pub struct GetForkInfo {
+    pub num_heights: Option<BlockHeight>,
+}
+
+impl Message for GetForkInfo {
+    type Result = Result<ForkInfoView, GetForkInfoError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetForkInfoError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetForkInfoError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GetSplitStorageInfo {}
 
@@ -1163,7 +1371,10 @@ impl From<std::io::Error> for GetSplitStorageInfoError {
 #[cfg(feature = "sandbox")]
 #[derive(Debug)]
 pub enum SandboxMessage {
-    SandboxPatchState(Vec<near_primitives::state_record::StateRecord>),
+    SandboxPatchState {
+        records: Vec<near_primitives::state_record::StateRecord>,
+        deletions: Vec<near_primitives::sandbox::SandboxStateDeletion>,
+    },
     SandboxPatchStateStatus,
     SandboxFastForward(near_primitives::types::BlockHeightDelta),
     SandboxFastForwardStatus,
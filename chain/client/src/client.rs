@@ -12,6 +12,7 @@ use crate::sync::adapter::SyncShardInfo;
 use crate::sync::block::BlockSync;
 use crate::sync::header::HeaderSync;
 use crate::sync::state::{StateSync, StateSyncResult};
+use crate::watched_accounts::WatchedAccounts;
 use crate::SyncAdapter;
 use crate::SyncMessage;
 use crate::{metrics, SyncStatus};
@@ -24,7 +25,7 @@ use near_chain::chain::{
     ApplyChunksDoneMessage, ApplyStatePartsRequest, BlockCatchUpRequest, BlockMissingChunks,
     BlocksCatchUpState, LoadMemtrieRequest, VerifyBlockHashAndSignatureResult,
 };
-use near_chain::flat_storage_creator::FlatStorageCreator;
+use near_chain::flat_storage_creator::{FlatStorageCreator, FlatStorageShardCreator};
 use near_chain::orphan::OrphanMissingChunks;
 use near_chain::state_snapshot_actor::SnapshotCallbacks;
 use near_chain::test_utils::format_hash;
@@ -80,6 +81,7 @@ use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
 use near_primitives::views::{CatchupStatusView, DroppedReason};
+use near_store::flat::FlatStorageStatus;
 use near_store::ShardUId;
 use reed_solomon_erasure::galois_8::ReedSolomon;
 use std::cmp::max;
@@ -180,6 +182,9 @@ pub struct Client {
     flat_storage_creator: Option<FlatStorageCreator>,
     /// A map storing the last time a block was requested for state sync.
     pub last_time_sync_block_requested: HashMap<CryptoHash, near_async::time::Utc>,
+    /// Blocks deleted as missing or corrupt by `neard database verify
+    /// --repair`, requested from peers until they are stored back.
+    blocks_to_refetch: HashSet<CryptoHash>,
     /// Helper module for stateless validation functionality like chunk witness production, validation
     /// chunk endorsements tracking etc.
     pub chunk_validator: ChunkValidator,
@@ -192,6 +197,9 @@ pub struct Client {
     pub partial_witness_adapter: PartialWitnessSenderForClient,
     // Optional value used for the Chunk Distribution Network Feature.
     chunk_distribution_network: Option<ChunkDistributionNetwork>,
+    /// Data of the accounts listed in `watched_accounts` of the config,
+    /// collected from the validated state witnesses.
+    pub watched_accounts: WatchedAccounts,
 }
 
 impl AsRef<Client> for Client {
@@ -276,8 +284,11 @@ impl Client {
             chain.chain_store(),
             chain_config.background_migration_threads,
         )?;
-        let sharded_tx_pool =
-            ShardedTransactionPool::new(rng_seed, config.transaction_pool_size_limit);
+        let sharded_tx_pool = ShardedTransactionPool::new(
+            rng_seed,
+            config.transaction_pool_size_limit,
+            config.transaction_pool_receiver_shard_size_limit,
+        );
         let sync_status = SyncStatus::AwaitingPeers;
         let epoch_sync = EpochSync::new(
             clock.clone(),
@@ -354,6 +365,12 @@ impl Client {
             panic_on_validation_error,
         );
         let chunk_distribution_network = ChunkDistributionNetwork::from_config(&config);
+        let watched_accounts = WatchedAccounts::new(&config.watched_accounts);
+        let blocks_to_refetch: HashSet<CryptoHash> =
+            chain.get_blocks_to_refetch()?.into_iter().collect();
+        if !blocks_to_refetch.is_empty() {
+            info!(target: "client", num_blocks = blocks_to_refetch.len(), "Refetching blocks from peers");
+        }
         Ok(Self {
             #[cfg(feature = "test_features")]
             adv_produce_blocks: None,
@@ -399,11 +416,13 @@ impl Client {
             tier1_accounts_cache: None,
             flat_storage_creator,
             last_time_sync_block_requested: HashMap::new(),
+            blocks_to_refetch,
             chunk_validator,
             chunk_inclusion_tracker: ChunkInclusionTracker::new(),
             chunk_endorsement_tracker,
             partial_witness_adapter,
             chunk_distribution_network,
+            watched_accounts,
         })
     }
 
@@ -472,9 +491,12 @@ impl Client {
                 ) {
                     // By now the chunk must be in store, otherwise the block would have been orphaned
                     let chunk = self.chain.get_chunk(&chunk_header.chunk_hash()).unwrap();
-                    let reintroduced_count = self
-                        .sharded_tx_pool
-                        .reintroduce_transactions(shard_uid, &chunk.transactions());
+                    let shard_layout = self.epoch_manager.get_shard_layout(&epoch_id)?;
+                    let reintroduced_count = self.sharded_tx_pool.reintroduce_transactions(
+                        shard_uid,
+                        &shard_layout,
+                        &chunk.transactions(),
+                    );
                     if reintroduced_count < chunk.transactions().len() {
                         debug!(target: "client",
                             reintroduced_count,
@@ -1023,6 +1045,7 @@ impl Client {
     ) -> Result<PreparedTransactions, Error> {
         let Self { chain, sharded_tx_pool, runtime_adapter: runtime, .. } = self;
         let shard_id = shard_uid.shard_id as ShardId;
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_block.hash())?;
         let prepared_transactions = if let Some(mut iter) =
             sharded_tx_pool.get_pool_iterator(shard_uid)
         {
@@ -1032,7 +1055,6 @@ impl Client {
                 source: StorageDataSource::Db,
                 state_patch: Default::default(),
             };
-            let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_block.hash())?;
             let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
             let last_chunk_transactions_size =
                 if ProtocolFeature::StatelessValidation.enabled(protocol_version) {
@@ -1061,8 +1083,12 @@ impl Client {
         };
         // Reintroduce valid transactions back to the pool. They will be removed when the chunk is
         // included into the block.
-        let reintroduced_count = sharded_tx_pool
-            .reintroduce_transactions(shard_uid, &prepared_transactions.transactions);
+        let shard_layout = self.epoch_manager.get_shard_layout(&epoch_id)?;
+        let reintroduced_count = sharded_tx_pool.reintroduce_transactions(
+            shard_uid,
+            &shard_layout,
+            &prepared_transactions.transactions,
+        );
         if reintroduced_count < prepared_transactions.transactions.len() {
             debug!(target: "client", reintroduced_count, num_tx = prepared_transactions.transactions.len(), "Reintroduced transactions");
         }
@@ -1153,6 +1179,11 @@ impl Client {
     ) -> Result<(), near_chain::Error> {
         let _span =
             debug_span!(target: "chain", "receive_block_impl", was_requested, ?peer_id).entered();
+        if self.blocks_to_refetch.contains(block.hash()) {
+            self.chain.save_refetched_block(&block)?;
+            self.blocks_to_refetch.remove(block.hash());
+            return Ok(());
+        }
         self.chain.blocks_delay_tracker.mark_block_received(&block);
         // To protect ourselves from spamming, we do some pre-check on block height before we do any
         // real processing.
@@ -2316,6 +2347,7 @@ impl Client {
                 tx,
                 true,
                 &epoch_id,
+                head.height + 1,
                 protocol_version,
                 receiver_congestion_info,
             )
@@ -2356,6 +2388,7 @@ impl Client {
                     tx,
                     false,
                     &epoch_id,
+                    head.height + 1,
                     protocol_version,
                     receiver_congestion_info,
                 )
@@ -2368,7 +2401,12 @@ impl Client {
             } else {
                 // Transactions only need to be recorded if the node is a validator.
                 if me.is_some() {
-                    match self.sharded_tx_pool.insert_transaction(shard_uid, tx.clone()) {
+                    let shard_layout = self.epoch_manager.get_shard_layout(&epoch_id)?;
+                    match self.sharded_tx_pool.insert_transaction(
+                        shard_uid,
+                        &shard_layout,
+                        tx.clone(),
+                    ) {
                         InsertTransactionResult::Success => {
                             trace!(target: "client", ?shard_uid, tx_hash = ?tx.get_hash(), "Recorded a transaction.");
                         }
@@ -2656,6 +2694,64 @@ impl Client {
         };
         Ok(result)
     }
+
+    /// Deletes flat storage of the shard and starts creating it again from
+    /// scratch. The creation is then driven by
+    /// `run_flat_storage_creation_step`, as on startup.
+    pub fn rebuild_flat_storage(&mut self, shard_uid: ShardUId) -> Result<(), near_chain::Error> {
+        if !self.config.flat_storage_creation_enabled {
+            return Err(near_chain::Error::Other("flat storage creation is disabled".to_string()));
+        }
+        let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
+        match flat_storage_manager.get_flat_storage_status(shard_uid) {
+            FlatStorageStatus::Ready(_) => {
+                // Chunks of blocks in processing are applied on other threads
+                // which read the flat state directly.
+                if self.chain.blocks_in_processing_len() > 0 {
+                    return Err(near_chain::Error::Other(
+                        "blocks are being processed, try again later".to_string(),
+                    ));
+                }
+                let mut store_update = self.chain.chain_store().store().store_update();
+                if !flat_storage_manager
+                    .remove_flat_storage_for_shard(shard_uid, &mut store_update)?
+                {
+                    return Err(near_chain::Error::Other(format!(
+                        "flat storage for shard {shard_uid} is not loaded"
+                    )));
+                }
+                store_update.commit()?;
+            }
+            // Nothing to delete, creation starts from the beginning anyway.
+            FlatStorageStatus::Empty => {}
+            // State parts may still be written in the background.
+            status @ (FlatStorageStatus::Creation(_) | FlatStorageStatus::Disabled) => {
+                return Err(near_chain::Error::Other(format!(
+                    "cannot rebuild flat storage for shard {shard_uid} with status {status:?}"
+                )));
+            }
+        }
+        info!(target: "client", %shard_uid, "Rebuilding flat storage");
+
+        let shard_creator = FlatStorageShardCreator::new(
+            shard_uid,
+            self.chain.head()?.height,
+            self.epoch_manager.clone(),
+            self.runtime_adapter.clone(),
+        );
+        match &mut self.flat_storage_creator {
+            Some(flat_storage_creator) => {
+                flat_storage_creator.shard_creators.insert(shard_uid, shard_creator);
+            }
+            None => {
+                self.flat_storage_creator = Some(FlatStorageCreator::with_shard_creators(
+                    HashMap::from([(shard_uid, shard_creator)]),
+                    self.config.client_background_migration_threads,
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 /* implements functions used to communicate with network */
@@ -2677,6 +2773,17 @@ impl Client {
         }
     }
 
+    /// Requests each of the blocks to refetch from a random peer at the
+    /// highest height.
+    pub fn request_blocks_to_refetch(&self, highest_height_peers: &[HighestHeightPeerInfo]) {
+        for hash in &self.blocks_to_refetch {
+            let Some(peer_info) = highest_height_peers.choose(&mut thread_rng()) else {
+                return;
+            };
+            self.request_block(*hash, peer_info.peer_info.id.clone());
+        }
+    }
+
     pub fn ban_peer(&self, peer_id: PeerId, ban_reason: ReasonForBan) {
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
             NetworkRequests::BanPeer { peer_id, ban_reason },
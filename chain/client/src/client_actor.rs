@@ -46,8 +46,9 @@ use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::logic::get_shards_cares_about_this_or_next_epoch;
 use near_client_primitives::types::{
-    Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, NetworkInfoResponse,
-    StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus,
+    Error, ExportTransactionPool, GetClientConfig, GetClientConfigError, GetNetworkInfo,
+    GetShardRpcEndpoints, GetWatchedAccounts, NetworkInfoResponse, StateSyncStatus, Status,
+    StatusError, StatusSyncInfo, SyncStatus,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::{EpochManagerAdapter, RngSeed};
@@ -65,12 +66,13 @@ use near_primitives::block::Tip;
 use near_primitives::block_header::ApprovalType;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
+use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, BlockHeight, EpochId};
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
-use near_primitives::views::{DetailedDebugStatus, ValidatorInfo};
+use near_primitives::views::{DetailedDebugStatus, ValidatorInfo, WatchedAccountView};
 #[cfg(feature = "test_features")]
 use near_store::DBCol;
 use near_store::ShardUId;
@@ -88,6 +90,9 @@ const STATUS_WAIT_TIME_MULTIPLIER: i32 = 10;
 /// `max_block_production_time` times this multiplier is how long we wait before rebroadcasting
 /// the current `head`
 const HEAD_STALL_MULTIPLIER: u32 = 4;
+/// How often blocks lost from the database are requested again until they
+/// arrive, see `Client::request_blocks_to_refetch`.
+const BLOCK_REFETCH_PERIOD: Duration = Duration::seconds(10);
 
 pub type ClientActor = ActixWrapper<ClientActorInner>;
 
@@ -250,6 +255,8 @@ pub struct ClientActorInner {
     // Last time when log_summary method was called.
     log_summary_timer_next_attempt: near_async::time::Utc,
 
+    block_refetch_next_attempt: near_async::time::Utc,
+
     doomslug_timer_next_attempt: near_async::time::Utc,
     sync_timer_next_attempt: near_async::time::Utc,
     sync_started: bool,
@@ -374,11 +381,13 @@ impl ClientActorInner {
                 known_producers: vec![],
                 tier1_accounts_keys: vec![],
                 tier1_accounts_data: vec![],
+                reachability: Default::default(),
             },
             last_validator_announce_time: None,
             info_helper,
             block_production_next_attempt: now,
             log_summary_timer_next_attempt: now,
+            block_refetch_next_attempt: now,
             doomslug_timer_next_attempt: now,
             sync_timer_next_attempt: now,
             sync_started: false,
@@ -671,9 +680,13 @@ impl Handler<near_client_primitives::types::SandboxMessage> for ClientActorInner
         msg: near_client_primitives::types::SandboxMessage,
     ) -> near_client_primitives::types::SandboxResponse {
         match msg {
-            near_client_primitives::types::SandboxMessage::SandboxPatchState(state) => {
+            near_client_primitives::types::SandboxMessage::SandboxPatchState {
+                records,
+                deletions,
+            } => {
                 self.client.chain.patch_state(
-                    near_primitives::sandbox::state_patch::SandboxStatePatch::new(state),
+                    near_primitives::sandbox::state_patch::SandboxStatePatch::new(records)
+                        .with_deletions(deletions),
                 );
                 near_client_primitives::types::SandboxResponse::SandboxNoResponse
             }
@@ -738,6 +751,15 @@ impl Handler<Status> for ClientActorInner {
         let epoch_start_height =
             self.client.epoch_manager.get_epoch_start_height(&head.last_block_hash).ok();
 
+        // Until the chain makes progress the final blocks are the genesis block,
+        // which is referenced by the default hash.
+        let doomslug_final_block = self
+            .client
+            .chain
+            .get_block_header(self.client.chain.head_header()?.last_ds_final_block())
+            .ok();
+        let final_head = self.client.chain.final_head().ok();
+
         let protocol_version = self
             .client
             .epoch_manager
@@ -810,6 +832,14 @@ impl Handler<Status> for ClientActorInner {
                 earliest_block_time,
                 epoch_id: Some(head.epoch_id),
                 epoch_start_height,
+                doomslug_final_block_hash: doomslug_final_block
+                    .as_ref()
+                    .map(|header| *header.hash()),
+                doomslug_final_block_height: doomslug_final_block
+                    .as_ref()
+                    .map(|header| header.height()),
+                final_block_hash: final_head.as_ref().map(|tip| tip.last_block_hash),
+                final_block_height: final_head.as_ref().map(|tip| tip.height),
             },
             validator_account_id,
             validator_public_key,
@@ -818,6 +848,7 @@ impl Handler<Status> for ClientActorInner {
             uptime_sec,
             genesis_hash: *self.client.chain.genesis().hash(),
             detailed_debug_status,
+            reachability: Some(self.network_info.reachability.clone()),
         })
     }
 }
@@ -863,6 +894,31 @@ impl Handler<GetNetworkInfo> for ClientActorInner {
     }
 }
 
+impl Handler<GetShardRpcEndpoints> for ClientActorInner {
+    fn handle(&mut self, msg: GetShardRpcEndpoints) -> Vec<String> {
+        let mut endpoints: Vec<String> = (self.network_info.connected_peers.iter())
+            .map(|peer| &peer.full_peer_info.chain_info)
+            .filter(|chain_info| chain_info.tracked_shards.contains(&msg.shard_id))
+            .filter_map(|chain_info| chain_info.rpc_url.clone())
+            .collect();
+        endpoints.sort();
+        endpoints.dedup();
+        endpoints
+    }
+}
+
+impl Handler<GetWatchedAccounts> for ClientActorInner {
+    fn handle(&mut self, msg: GetWatchedAccounts) -> Vec<WatchedAccountView> {
+        self.client.watched_accounts.get(&msg.account_ids)
+    }
+}
+
+impl Handler<ExportTransactionPool> for ClientActorInner {
+    fn handle(&mut self, _msg: ExportTransactionPool) -> Vec<SignedTransaction> {
+        self.client.sharded_tx_pool.all_transactions()
+    }
+}
+
 impl Handler<ApplyChunksDoneMessage> for ClientActorInner {
     fn handle(&mut self, _msg: ApplyChunksDoneMessage) {
         let validator_signer = self.client.validator_signer.get();
@@ -1280,6 +1336,17 @@ impl ClientActorInner {
             "log_summary",
         );
         delay = core::cmp::min(delay, self.log_summary_timer_next_attempt - now);
+
+        self.block_refetch_next_attempt = self.run_timer(
+            BLOCK_REFETCH_PERIOD,
+            self.block_refetch_next_attempt,
+            ctx,
+            |act, _ctx| {
+                act.client.request_blocks_to_refetch(&act.network_info.highest_height_peers)
+            },
+            "block_refetch",
+        );
+        delay = core::cmp::min(delay, self.block_refetch_next_attempt - now);
         timer.observe_duration();
         delay
     }
@@ -1540,14 +1607,10 @@ impl ClientActorInner {
         if !self.client.config.flat_storage_creation_enabled {
             return;
         }
-        match self.client.run_flat_storage_creation_step() {
-            Ok(false) => {}
-            Ok(true) => {
-                return;
-            }
-            Err(err) => {
-                error!(target: "client", "Error occurred during flat storage creation step: {:?}", err);
-            }
+        // Keep polling after all flat storages are created, so that a rebuild
+        // requested later gets picked up.
+        if let Err(err) = self.client.run_flat_storage_creation_step() {
+            error!(target: "client", "Error occurred during flat storage creation step: {:?}", err);
         }
 
         ctx.run_later(
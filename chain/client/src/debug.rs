@@ -8,7 +8,8 @@ use near_chain::crypto_hash_timer::CryptoHashTimer;
 use near_chain::{near_chain_primitives, Block, Chain, ChainStoreAccess};
 use near_client_primitives::debug::{
     ApprovalAtHeightStatus, BlockProduction, ChunkCollection, DebugBlockStatusData, DebugStatus,
-    DebugStatusResponse, MissedHeightInfo, ProductionAtHeight, ValidatorStatus,
+    DebugStatusResponse, FlatStorageAction, FlatStorageControl, FlatStorageStatusView,
+    MissedHeightInfo, ProductionAtHeight, ValidatorStatus,
 };
 use near_client_primitives::types::Error;
 use near_client_primitives::{
@@ -19,6 +20,7 @@ use near_epoch_manager::EpochManagerAdapter;
 use near_o11y::log_assert;
 use near_performance_metrics_macros::perf;
 use near_primitives::congestion_info::CongestionControl;
+use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_sync::get_num_state_parts;
 use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
 use near_primitives::types::{AccountId, BlockHeight, NumShards, ShardId, ValidatorInfoIdentifier};
@@ -28,6 +30,7 @@ use near_primitives::{
     types::EpochId,
     views::ValidatorInfo,
 };
+use near_store::flat::{FlatStorageCreationStatus, FlatStorageStatus};
 use near_store::DBCol;
 use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
@@ -178,10 +181,33 @@ impl Handler<DebugStatus> for ClientActorInner {
             DebugStatus::ChainProcessingStatus => Ok(DebugStatusResponse::ChainProcessingStatus(
                 self.client.chain.get_chain_processing_info(),
             )),
+            DebugStatus::FlatStorageStatus => {
+                Ok(DebugStatusResponse::FlatStorageStatus(self.get_flat_storage_status()?))
+            }
         }
     }
 }
 
+impl Handler<FlatStorageControl> for ClientActorInner {
+    #[perf]
+    fn handle(&mut self, msg: FlatStorageControl) -> Result<FlatStorageStatusView, StatusError> {
+        let head = self.client.chain.head()?;
+        let shard_uid = self
+            .client
+            .epoch_manager
+            .shard_id_to_uid(msg.shard_id, &head.epoch_id)
+            .map_err(near_chain_primitives::Error::from)?;
+        tracing::info!(target: "client", %shard_uid, action = ?msg.action, "Flat storage control request");
+        match msg.action {
+            FlatStorageAction::MoveHead => {
+                self.client.chain.move_flat_storage_head(msg.shard_id)?;
+            }
+            FlatStorageAction::Rebuild => self.client.rebuild_flat_storage(shard_uid)?,
+        }
+        Ok(self.get_flat_storage_status_view(shard_uid)?)
+    }
+}
+
 impl ClientActorInner {
     // Gets a list of block producers and chunk-only producers for a given epoch.
     fn get_producers_for_epoch(
@@ -364,6 +390,57 @@ impl ClientActorInner {
         Ok(TrackedShardsView { shards_tracked_this_epoch, shards_tracked_next_epoch })
     }
 
+    fn get_flat_storage_status(
+        &self,
+    ) -> Result<Vec<FlatStorageStatusView>, near_chain_primitives::Error> {
+        let epoch_id = self.client.chain.head()?.epoch_id;
+        let shard_layout = self.client.epoch_manager.get_shard_layout(&epoch_id)?;
+        shard_layout
+            .shard_uids()
+            .map(|shard_uid| self.get_flat_storage_status_view(shard_uid))
+            .collect()
+    }
+
+    fn get_flat_storage_status_view(
+        &self,
+        shard_uid: ShardUId,
+    ) -> Result<FlatStorageStatusView, near_chain_primitives::Error> {
+        let flat_storage_manager = self.client.runtime_adapter.get_flat_storage_manager();
+        let chain_store = self.client.chain.chain_store();
+        let status = flat_storage_manager.get_flat_storage_status(shard_uid);
+        let (status_name, flat_head_hash, fetched_state_parts) = match &status {
+            FlatStorageStatus::Disabled => ("Disabled", None, None),
+            FlatStorageStatus::Empty => ("Empty", None, None),
+            FlatStorageStatus::Creation(FlatStorageCreationStatus::SavingDeltas) => {
+                ("SavingDeltas", None, None)
+            }
+            FlatStorageStatus::Creation(FlatStorageCreationStatus::FetchingState(fetching)) => {
+                ("FetchingState", None, Some((fetching.part_id, fetching.num_parts)))
+            }
+            FlatStorageStatus::Creation(FlatStorageCreationStatus::CatchingUp(flat_head)) => {
+                ("CatchingUp", Some(*flat_head), None)
+            }
+            FlatStorageStatus::Ready(ready) => ("Ready", Some(ready.flat_head.hash), None),
+        };
+        let flat_head_height =
+            flat_head_hash.map(|hash| chain_store.get_block_height(&hash)).transpose()?;
+        let final_head_height = chain_store.final_head()?.height;
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid);
+        Ok(FlatStorageStatusView {
+            shard_uid,
+            status: status_name.to_string(),
+            flat_head_hash,
+            flat_head_height,
+            blocks_behind_final_head: flat_head_height
+                .map(|height| final_head_height.saturating_sub(height)),
+            fetched_state_parts,
+            num_deltas: flat_storage.as_ref().map(|flat_storage| flat_storage.num_deltas()),
+            move_head_enabled: flat_storage
+                .as_ref()
+                .map(|flat_storage| flat_storage.is_move_head_enabled()),
+        })
+    }
+
     fn get_recent_epoch_info(
         &mut self,
     ) -> Result<Vec<EpochInfoView>, near_chain_primitives::Error> {
@@ -6,10 +6,11 @@ use near_async::messaging::Handler;
 use near_chain::{types::RuntimeAdapter, ChainStore, ChainStoreAccess};
 use near_chain_configs::GCConfig;
 use near_epoch_manager::EpochManagerAdapter;
-use near_primitives::types::BlockHeight;
-use near_store::{metadata::DbKind, Store};
+use near_primitives::types::{BlockHeight, NumBlocks};
+use near_store::{metadata::DbKind, DBCol, Store};
 use std::sync::Arc;
-use tracing::warn;
+use strum::IntoEnumIterator;
+use tracing::{debug, warn};
 
 /// An actor for garbage collection that runs in its own thread
 /// The actor runs periodically, as determined by `gc_step_period`,
@@ -43,11 +44,42 @@ impl GCActor {
         }
     }
 
+    /// Returns the number of blocks to collect at this step, fewer than
+    /// `gc_blocks_limit` while the database is behind with compactions.
+    fn gc_blocks_limit(&self) -> Result<NumBlocks, near_chain::Error> {
+        let gc_blocks_limit = self.gc_config.gc_blocks_limit;
+        if !self.gc_config.throttle.enabled {
+            return Ok(gc_blocks_limit);
+        }
+        let store = self.store.store();
+        let write_stalled = store.is_write_stalled()?;
+        let mut compaction_debt = 0;
+        for col in DBCol::iter() {
+            compaction_debt += store.compaction_debt(col)?.unwrap_or(0);
+        }
+        let limit = self.gc_config.throttle.gc_blocks_limit(
+            gc_blocks_limit,
+            write_stalled,
+            compaction_debt,
+        );
+        metrics::GC_BLOCKS_LIMIT.set(limit as i64);
+        if limit < gc_blocks_limit {
+            debug!(target: "garbage collection", write_stalled, compaction_debt, limit, "Throttling garbage collection");
+        }
+        Ok(limit)
+    }
+
     fn clear_data(&mut self) -> Result<(), near_chain::Error> {
+        let gc_blocks_limit = self.gc_blocks_limit()?;
+        if gc_blocks_limit == 0 {
+            return Ok(());
+        }
+        let gc_config = GCConfig { gc_blocks_limit, ..self.gc_config.clone() };
+
         // A RPC node should do regular garbage collection.
         if !self.is_archive {
             return self.store.clear_data(
-                &self.gc_config,
+                &gc_config,
                 self.runtime_adapter.clone(),
                 self.epoch_manager.clone(),
             );
@@ -57,11 +89,12 @@ impl GCActor {
         // on the hot storage. In order to determine if split storage is enabled
         // *and* that the migration to split storage is finished we can check
         // the store kind. It's only set to hot after the migration is finished.
+        // Headers aren't copied to the cold storage, so they are never pruned.
         let store = self.store.store();
         let kind = store.get_db_kind()?;
         if kind == Some(DbKind::Hot) {
             return self.store.clear_data(
-                &self.gc_config,
+                &GCConfig { prune_headers: false, ..gc_config },
                 self.runtime_adapter.clone(),
                 self.epoch_manager.clone(),
             );
@@ -69,7 +102,7 @@ impl GCActor {
 
         // An archival node with legacy storage or in the midst of migration to split
         // storage should do the legacy clear_archive_data.
-        self.store.clear_archive_data(self.gc_config.gc_blocks_limit, self.runtime_adapter.clone())
+        self.store.clear_archive_data(gc_blocks_limit, self.runtime_adapter.clone())
     }
 
     fn gc(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
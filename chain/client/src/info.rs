@@ -491,6 +491,7 @@ impl InfoHelper {
                 is_validator,
                 signer,
             ),
+            signer: signer.clone(),
         };
         self.telemetry_sender.send(telemetry_event);
     }
@@ -610,12 +611,8 @@ impl InfoHelper {
             },
             extra_info: serde_json::to_string(&extra_telemetry_info(client_config)).unwrap(),
         };
-        // Sign telemetry if there is a signer present.
-        if let Some(signer) = signer {
-            signer.sign_telemetry(&info)
-        } else {
-            serde_json::to_value(&info).expect("Telemetry must serialize to json")
-        }
+        // The telemetry actor signs the payload after filtering it.
+        serde_json::to_value(&info).expect("Telemetry must serialize to json")
     }
 
     fn log_chain_processing_info(&mut self, client: &crate::Client, epoch_id: &EpochId) {
@@ -1021,6 +1018,7 @@ mod tests {
                 tier1_connections: vec![],
                 tier1_accounts_keys: vec![],
                 tier1_accounts_data: vec![],
+                reachability: Default::default(),
             },
             &config,
             0.0,
@@ -1,11 +1,13 @@
 pub use near_client_primitives::types::{
-    Error, GetBlock, GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunk,
-    GetClientConfig, GetExecutionOutcome, GetExecutionOutcomeResponse,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
-    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetShardChunk, GetSplitStorageInfo,
-    GetStateChanges, GetStateChangesInBlock, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfo, GetValidatorOrdered, Query,
-    QueryError, Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
+    Error, ExportTransactionPool, GetBlock, GetBlockHeightStatus, GetBlockProof,
+    GetBlockProofResponse, GetBlockReceipts, GetBlockWithMerkleTree, GetChunk, GetChunkValidators,
+    GetClientConfig, GetEpochShardStats, GetExecutionOutcome, GetExecutionOutcomeResponse,
+    GetExecutionOutcomesForBlock, GetForkInfo, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
+    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetShardChunk, GetShardRpcEndpoints,
+    GetSplitStorageInfo, GetStateChanges, GetStateChangesInBlock, GetStateChangesWithCauseInBlock,
+    GetStateChangesWithCauseInBlockForTrackedShards, GetStateWitnesses, GetValidatorInfo,
+    GetValidatorOrdered, GetWatchedAccounts, Query, QueryError, Status, StatusResponse, SyncStatus,
+    TxStatus, TxStatusError,
 };
 
 pub use crate::client::{Client, ProduceChunkResult};
@@ -45,3 +47,4 @@ pub mod test_utils;
 #[cfg(test)]
 mod tests;
 mod view_client_actor;
+mod watched_accounts;
@@ -85,6 +85,14 @@ pub(crate) static GC_TIME: LazyLock<Histogram> = LazyLock::new(|| {
     try_create_histogram("near_gc_time", "Time taken to do garbage collection").unwrap()
 });
 
+pub(crate) static GC_BLOCKS_LIMIT: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_gc_blocks_limit",
+        "Number of blocks garbage collected at a step, lowered while the database is behind with compactions",
+    )
+    .unwrap()
+});
+
 pub(crate) static TGAS_USAGE_HIST: LazyLock<HistogramVec> = LazyLock::new(|| {
     try_create_histogram_vec(
         "near_chunk_tgas_used_hist",
@@ -463,6 +471,15 @@ pub(crate) static STATE_SYNC_RETRY_PART: LazyLock<IntCounterVec> = LazyLock::new
     .unwrap()
 });
 
+pub(crate) static STATE_SYNC_HEDGED_PART_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_hedged_part_requests_total",
+        "Number of part requests sent to another peer while the earlier requests were still pending",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_HEADER_ERROR: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_state_sync_header_error_total",
@@ -597,6 +614,16 @@ pub(crate) static ORPHAN_CHUNK_STATE_WITNESSES_TOTAL_COUNT: LazyLock<IntCounterV
         .unwrap()
     });
 
+pub(crate) static PRODUCED_CHUNK_STATE_WITNESS_VALIDATION_FAILED_TOTAL: LazyLock<IntCounterVec> =
+    LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_produced_chunk_state_witness_validation_failed_total",
+            "Total number of state witnesses produced by this node which failed self-validation and were not distributed",
+            &["shard_id", "reason"],
+        )
+        .unwrap()
+    });
+
 pub(crate) static CHUNK_STATE_WITNESS_NETWORK_ROUNDTRIP_TIME: LazyLock<HistogramVec> =
     LazyLock::new(|| {
         try_create_histogram_vec(
@@ -681,3 +708,14 @@ pub(crate) static PARTIAL_WITNESS_CACHE_SIZE: LazyLock<Gauge> = LazyLock::new(||
     )
     .unwrap()
 });
+
+pub(crate) static SHADOW_CHUNK_APPLICATION_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_shadow_chunk_application_total",
+        "Chunks of untracked shards shadow applied by a non-validator node, by result: \
+         'ok' if the result matches the chunk, 'mismatch' if the witness turned out to be \
+         invalid and 'error' if the chunk couldn't be applied",
+        &["shard_id", "result"],
+    )
+    .unwrap()
+});
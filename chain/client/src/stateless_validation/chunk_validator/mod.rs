@@ -1,6 +1,7 @@
 pub mod orphan_witness_handling;
 pub mod orphan_witness_pool;
 
+use crate::metrics;
 use crate::Client;
 use itertools::Itertools;
 use near_async::futures::{AsyncComputationSpawner, AsyncComputationSpawnerExt};
@@ -19,6 +20,7 @@ use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
 use near_primitives::stateless_validation::state_witness::{
     ChunkStateWitness, ChunkStateWitnessAck, ChunkStateWitnessSize,
 };
+use near_primitives::types::ShardId;
 use near_primitives::validator_signer::ValidatorSigner;
 use orphan_witness_pool::OrphanStateWitnessPool;
 use std::sync::Arc;
@@ -181,6 +183,57 @@ impl ChunkValidator {
         Ok(())
     }
 
+    /// Applies the chunk of the state witness on a node which isn't a chunk
+    /// validator, to check the result of the chunk producer independently.
+    /// Nothing is endorsed, the outcome is only recorded in metrics.
+    fn start_shadow_applying_chunk(
+        &self,
+        state_witness: ChunkStateWitness,
+        chain: &Chain,
+    ) -> Result<(), Error> {
+        let shard_id = state_witness.chunk_header.shard_id();
+        let pre_validation_result = chunk_validation::pre_validate_chunk_state_witness(
+            &state_witness,
+            chain,
+            self.epoch_manager.as_ref(),
+            self.runtime_adapter.as_ref(),
+        );
+        let pre_validation_result = match pre_validation_result {
+            Ok(pre_validation_result) => pre_validation_result,
+            Err(err) => {
+                record_shadow_chunk_application(shard_id, Err(&err));
+                return Err(err);
+            }
+        };
+
+        let epoch_manager = self.epoch_manager.clone();
+        let runtime_adapter = self.runtime_adapter.clone();
+        let cache = self.main_state_transition_result_cache.clone();
+        self.validation_spawner.spawn("shadow_chunk_application", move || {
+            let chunk_hash = state_witness.chunk_header.chunk_hash();
+            let height_created = state_witness.chunk_header.height_created();
+            let result = chunk_validation::validate_chunk_state_witness(
+                state_witness,
+                pre_validation_result,
+                epoch_manager.as_ref(),
+                runtime_adapter.as_ref(),
+                &cache,
+            );
+            if let Err(err) = &result {
+                tracing::warn!(
+                    target: "client",
+                    ?chunk_hash,
+                    shard_id,
+                    height_created,
+                    ?err,
+                    "Shadow chunk application failed",
+                );
+            }
+            record_shadow_chunk_application(shard_id, result.as_ref().map(|_| ()));
+        });
+        Ok(())
+    }
+
     /// TESTING ONLY: Used to override the value of panic_on_validation_error, for example,
     /// when the chunks validation errors are expected when testing adversarial behavior and
     /// the test should not panic for the invalid chunks witnesses.
@@ -190,6 +243,19 @@ impl ChunkValidator {
     }
 }
 
+/// Invalid witnesses count as mismatches, other errors mean that the node
+/// couldn't apply the chunk, e.g. because it's missing the previous block.
+fn record_shadow_chunk_application(shard_id: ShardId, result: Result<(), &Error>) {
+    let result = match result {
+        Ok(()) => "ok",
+        Err(err) if err.is_bad_data() => "mismatch",
+        Err(_) => "error",
+    };
+    metrics::SHADOW_CHUNK_APPLICATION_TOTAL
+        .with_label_values(&[&shard_id.to_string(), result])
+        .inc();
+}
+
 pub(crate) fn send_chunk_endorsement_to_block_producers(
     chunk_header: &ShardChunkHeader,
     epoch_manager: &dyn EpochManagerAdapter,
@@ -249,21 +315,25 @@ impl Client {
 
         // Chunk producers should not receive state witness from themselves.
         log_assert!(
-            signer.is_some(),
+            signer.is_some() || self.config.shadow_chunk_application,
             "Received a chunk state witness but this is not a validator node. Witness={:?}",
             witness
         );
-        let signer = signer.unwrap();
+
+        if self.config.save_latest_witnesses {
+            self.chain.chain_store.save_latest_chunk_state_witness(&witness)?;
+        }
+        self.watched_accounts.observe_state_witness(&witness);
+
+        let Some(signer) = signer else {
+            return self.shadow_apply_chunk_state_witness(witness);
+        };
 
         // Send the acknowledgement for the state witness back to the chunk producer.
         // This is currently used for network roundtrip time measurement, so we do not need to
         // wait for validation to finish.
         self.send_state_witness_ack(&witness, &signer);
 
-        if self.config.save_latest_witnesses {
-            self.chain.chain_store.save_latest_chunk_state_witness(&witness)?;
-        }
-
         match self.chain.get_block(witness.chunk_header.prev_block_hash()) {
             Ok(block) => self.process_chunk_state_witness_with_prev_block(
                 witness,
@@ -280,6 +350,30 @@ impl Client {
         }
     }
 
+    /// Shadow applies the chunk of the witness, see `shadow_chunk_application`
+    /// in the client config. Chunks of the tracked shards are skipped, as the
+    /// node applies them anyway.
+    fn shadow_apply_chunk_state_witness(
+        &mut self,
+        witness: ChunkStateWitness,
+    ) -> Result<(), Error> {
+        if !self.config.shadow_chunk_application {
+            return Err(Error::NotAValidator(format!("process chunk state witness")));
+        }
+        let prev_block_hash = witness.chunk_header.prev_block_hash();
+        let shard_id = witness.chunk_header.shard_id();
+        if self.shard_tracker.care_about_shard(None, prev_block_hash, shard_id, true) {
+            tracing::debug!(
+                target: "client",
+                chunk_hash=?witness.chunk_header.chunk_hash(),
+                shard_id,
+                "Skipping shadow application of a chunk of a tracked shard",
+            );
+            return Ok(());
+        }
+        self.chunk_validator.start_shadow_applying_chunk(witness, &self.chain)
+    }
+
     fn send_state_witness_ack(&self, witness: &ChunkStateWitness, signer: &Arc<ValidatorSigner>) {
         // In production PartialWitnessActor does not forward a state witness to the chunk producer that
         // produced the witness. However some tests bypass PartialWitnessActor, thus when a chunk producer
@@ -51,6 +51,9 @@ pub struct PartialWitnessActor {
     /// Currently used to find the chain HEAD when validating partial witnesses,
     /// but should be removed if we implement retrieving this info from the client
     store: Store,
+    /// Whether to collect the witness parts reaching the node while it isn't a
+    /// validator, see `shadow_chunk_application` in the client config.
+    shadow_chunk_application: bool,
 }
 
 impl Actor for PartialWitnessActor {}
@@ -107,6 +110,7 @@ impl PartialWitnessActor {
         my_signer: MutableValidatorSigner,
         epoch_manager: Arc<dyn EpochManagerAdapter>,
         store: Store,
+        shadow_chunk_application: bool,
     ) -> Self {
         let partial_witness_tracker =
             PartialEncodedStateWitnessTracker::new(client_sender, epoch_manager.clone());
@@ -118,6 +122,7 @@ impl PartialWitnessActor {
             state_witness_tracker: ChunkStateWitnessTracker::new(clock),
             encoders: WitnessEncoderCache::new(),
             store,
+            shadow_chunk_application,
         }
     }
 
@@ -268,16 +273,17 @@ impl PartialWitnessActor {
     ) -> Result<(), Error> {
         tracing::debug!(target: "client", ?partial_witness, "Receive PartialEncodedStateWitnessMessage");
 
-        let signer = self
-            .my_signer
-            .get()
-            .ok_or(Error::NotAValidator(format!("handle partial encoded state witness")))?;
+        let Some(signer) = self.my_signer.get() else {
+            // Only chunk validators forward the parts, other nodes just
+            // collect them.
+            return self.handle_shadow_partial_encoded_state_witness(partial_witness);
+        };
 
         // Validate the partial encoded state witness and forward the part to all the chunk validators.
         if validate_partial_encoded_state_witness(
             self.epoch_manager.as_ref(),
             &partial_witness,
-            &signer,
+            Some(signer.as_ref()),
             &self.store,
         )? {
             self.forward_state_witness_part(partial_witness)?;
@@ -293,16 +299,15 @@ impl PartialWitnessActor {
     ) -> Result<(), Error> {
         tracing::debug!(target: "client", ?partial_witness, "Receive PartialEncodedStateWitnessForwardMessage");
 
-        let signer = self
-            .my_signer
-            .get()
-            .ok_or(Error::NotAValidator(format!("handle partial encoded state witness forward")))?;
+        let Some(signer) = self.my_signer.get() else {
+            return self.handle_shadow_partial_encoded_state_witness(partial_witness);
+        };
 
         // Validate the partial encoded state witness and store the partial encoded state witness.
         if validate_partial_encoded_state_witness(
             self.epoch_manager.as_ref(),
             &partial_witness,
-            &signer,
+            Some(signer.as_ref()),
             &self.store,
         )? {
             self.partial_witness_tracker.store_partial_encoded_state_witness(partial_witness)?;
@@ -311,6 +316,26 @@ impl PartialWitnessActor {
         Ok(())
     }
 
+    /// Collects the witness part on a node which isn't a validator, so that
+    /// the reconstructed witness can be shadow applied by the client.
+    fn handle_shadow_partial_encoded_state_witness(
+        &mut self,
+        partial_witness: PartialEncodedStateWitness,
+    ) -> Result<(), Error> {
+        if !self.shadow_chunk_application {
+            return Err(Error::NotAValidator(format!("handle partial encoded state witness")));
+        }
+        if validate_partial_encoded_state_witness(
+            self.epoch_manager.as_ref(),
+            &partial_witness,
+            None,
+            &self.store,
+        )? {
+            self.partial_witness_tracker.store_partial_encoded_state_witness(partial_witness)?;
+        }
+        Ok(())
+    }
+
     /// Handles the state witness ack message from the chunk validator.
     /// It computes the round-trip time between sending the state witness and receiving
     /// the ack message and updates the corresponding metric with it.
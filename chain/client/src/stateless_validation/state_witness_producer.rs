@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use near_async::messaging::{CanSend, IntoSender};
+use near_chain::stateless_validation::chunk_validation::pre_validate_chunk_state_witness;
 use near_chain::{BlockHeader, Chain, ChainStoreAccess};
 use near_chain_primitives::Error;
 use near_o11y::log_assert_fail;
@@ -10,13 +11,17 @@ use near_primitives::checked_feature;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::receipt::Receipt;
 use near_primitives::sharding::{ChunkHash, ReceiptProof, ShardChunk, ShardChunkHeader};
+use near_primitives::stateless_validation::partial_witness::MAX_COMPRESSED_STATE_WITNESS_SIZE;
 use near_primitives::stateless_validation::state_witness::{
-    ChunkStateTransition, ChunkStateWitness,
+    ChunkStateTransition, ChunkStateWitness, EncodedChunkStateWitness,
+    MAX_UNCOMPRESSED_STATE_WITNESS_SIZE,
 };
 use near_primitives::stateless_validation::stored_chunk_state_transition_data::StoredChunkStateTransitionData;
 use near_primitives::types::{AccountId, EpochId};
+use near_primitives::utils::compression::CompressedData;
 use near_primitives::validator_signer::ValidatorSigner;
 
+use crate::metrics;
 use crate::stateless_validation::chunk_validator::send_chunk_endorsement_to_block_producers;
 use crate::Client;
 
@@ -56,6 +61,10 @@ impl Client {
             self.chain.chain_store.save_latest_chunk_state_witness(&state_witness)?;
         }
 
+        if self.config.validate_produced_state_witnesses {
+            self.validate_produced_state_witness(&state_witness)?;
+        }
+
         let height = chunk_header.height_created();
         if self
             .epoch_manager
@@ -80,6 +89,65 @@ impl Client {
         Ok(())
     }
 
+    /// Runs the checks chunk validators perform on a state witness before
+    /// they start applying the chunk: the size limits enforced when the witness
+    /// is received and the pre-validation of receipts and transactions.
+    /// A witness which fails them would be rejected by every validator, so it's
+    /// better to not distribute it at all and surface the failure here.
+    fn validate_produced_state_witness(
+        &self,
+        state_witness: &ChunkStateWitness,
+    ) -> Result<(), Error> {
+        let chunk_header = &state_witness.chunk_header;
+        let shard_id = chunk_header.shard_id();
+        let record_failure = |reason: &str, err: &Error| {
+            metrics::PRODUCED_CHUNK_STATE_WITNESS_VALIDATION_FAILED_TOTAL
+                .with_label_values(&[&shard_id.to_string(), reason])
+                .inc();
+            tracing::error!(
+                target: "client",
+                ?err,
+                reason,
+                shard_id,
+                chunk_hash = ?chunk_header.chunk_hash(),
+                height_created = chunk_header.height_created(),
+                "produced state witness failed self-validation, not distributing it"
+            );
+        };
+
+        let (encoded_witness, raw_witness_size) = EncodedChunkStateWitness::encode(state_witness)?;
+        let size_error = if raw_witness_size as u64 > MAX_UNCOMPRESSED_STATE_WITNESS_SIZE {
+            Some(format!(
+                "Raw state witness size {} exceeds limit of {}",
+                raw_witness_size, MAX_UNCOMPRESSED_STATE_WITNESS_SIZE
+            ))
+        } else if encoded_witness.size_bytes() as u64 > MAX_COMPRESSED_STATE_WITNESS_SIZE.as_u64() {
+            Some(format!(
+                "Compressed state witness size {} exceeds limit of {}",
+                encoded_witness.size_bytes(),
+                MAX_COMPRESSED_STATE_WITNESS_SIZE.as_u64()
+            ))
+        } else {
+            None
+        };
+        if let Some(message) = size_error {
+            let err = Error::InvalidChunkStateWitness(message);
+            record_failure("size", &err);
+            return Err(err);
+        }
+
+        if let Err(err) = pre_validate_chunk_state_witness(
+            state_witness,
+            &self.chain,
+            self.epoch_manager.as_ref(),
+            self.runtime_adapter.as_ref(),
+        ) {
+            record_failure("pre_validation", &err);
+            return Err(err);
+        }
+        Ok(())
+    }
+
     pub(crate) fn create_state_witness(
         &mut self,
         chunk_producer: AccountId,
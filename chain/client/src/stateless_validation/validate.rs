@@ -21,10 +21,13 @@ const MAX_HEIGHTS_AHEAD: BlockHeightDelta = 5;
 /// - partial_witness signature is valid and from the expected chunk_producer
 /// TODO(stateless_validation): Include checks from handle_orphan_state_witness in orphan_witness_handling.rs
 /// These include checks based on epoch_id validity, witness size, height_created, distance from chain head, etc.
+///
+/// `signer` is `None` on nodes which shadow apply the chunks without being chunk validators,
+/// in which case the part isn't required to be meant for us.
 pub fn validate_partial_encoded_state_witness(
     epoch_manager: &dyn EpochManagerAdapter,
     partial_witness: &PartialEncodedStateWitness,
-    signer: &ValidatorSigner,
+    signer: Option<&ValidatorSigner>,
     store: &Store,
 ) -> Result<bool, Error> {
     let ChunkProductionKey { shard_id, epoch_id, height_created } =
@@ -52,7 +55,7 @@ pub fn validate_partial_encoded_state_witness(
     if !validate_chunk_production_key(
         epoch_manager,
         partial_witness.chunk_production_key(),
-        signer.validator_id(),
+        signer.map(|signer| signer.validator_id()),
         store,
     )? {
         return Ok(false);
@@ -75,7 +78,7 @@ pub fn validate_chunk_endorsement(
     if !validate_chunk_production_key(
         epoch_manager,
         endorsement.chunk_production_key(),
-        endorsement.account_id(),
+        Some(endorsement.account_id()),
         store,
     )? {
         return Ok(false);
@@ -90,7 +93,7 @@ pub fn validate_chunk_endorsement(
 
 /// Function to validate ChunkProductionKey. We check the following:
 /// - shard_id is valid
-/// - account_id is one of the validators for the chunk
+/// - account_id, if given, is one of the validators for the chunk
 /// - height_created is in (last_final_height..chain_head_height + MAX_HEIGHTS_AHEAD] range
 /// - epoch_id is within epoch_manager's possible_epochs_of_height_around_tip
 /// Returns:
@@ -101,7 +104,7 @@ pub fn validate_chunk_endorsement(
 fn validate_chunk_production_key(
     epoch_manager: &dyn EpochManagerAdapter,
     chunk_production_key: ChunkProductionKey,
-    account_id: &AccountId,
+    account_id: Option<&AccountId>,
     store: &Store,
 ) -> Result<bool, Error> {
     let shard_id = chunk_production_key.shard_id;
@@ -119,10 +122,12 @@ fn validate_chunk_production_key(
 
     // Reject witnesses/endorsements for chunks for which the account_id isn't a validator.
     // It's an error, as chunk producer shouldn't send the witness/endorsement to/from a non-validator node.
-    let chunk_validator_assignments =
-        epoch_manager.get_chunk_validator_assignments(&epoch_id, shard_id, height_created)?;
-    if !chunk_validator_assignments.contains(account_id) {
-        return Err(Error::NotAChunkValidator);
+    if let Some(account_id) = account_id {
+        let chunk_validator_assignments =
+            epoch_manager.get_chunk_validator_assignments(&epoch_id, shard_id, height_created)?;
+        if !chunk_validator_assignments.contains(account_id) {
+            return Err(Error::NotAChunkValidator);
+        }
     }
 
     // TODO(https://github.com/near/nearcore/issues/11301): replace these direct DB accesses with messages
@@ -7,12 +7,14 @@ use near_chain::types::Tip;
 use near_chain::{BlockHeader, Chain, ChainStoreAccess, Error};
 use near_chain_configs::EpochSyncConfig;
 use near_client_primitives::types::{EpochSyncStatus, SyncStatus};
+use near_crypto::Signature;
 use near_epoch_manager::EpochManagerAdapter;
 use near_network::client::{EpochSyncRequestMessage, EpochSyncResponseMessage};
 use near_network::types::{
     HighestHeightPeerInfo, NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest,
 };
 use near_performance_metrics_macros::perf;
+use near_primitives::block_header::{Approval, ApprovalInner};
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::epoch_info::EpochInfo;
 use near_primitives::epoch_manager::AGGREGATOR_KEY;
@@ -20,15 +22,18 @@ use near_primitives::epoch_sync::{
     CompressedEpochSyncProof, EpochSyncProof, EpochSyncProofCurrentEpochData,
     EpochSyncProofLastEpochData, EpochSyncProofPastEpochData,
 };
+use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::PartialMerkleTree;
 use near_primitives::network::PeerId;
-use near_primitives::types::{BlockHeight, EpochId};
+use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::types::{Balance, BlockHeight, EpochId};
 use near_primitives::utils::compression::CompressedData;
 use near_store::{DBCol, Store, FINAL_HEAD_KEY};
 use rand::seq::SliceRandom;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tracing::instrument;
 
 pub struct EpochSync {
@@ -37,6 +42,16 @@ pub struct EpochSync {
     genesis: BlockHeader,
     async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
     config: EpochSyncConfig,
+    /// Peers which sent an invalid proof or didn't respond in time; they are
+    /// asked again only once no other peer is left.
+    failed_peers: HashSet<PeerId>,
+    /// The proof served to the peers, along with the epoch of the final head
+    /// it was derived at. The proof only changes once the final head moves to
+    /// another epoch.
+    last_epoch_sync_response_cache: Arc<Mutex<Option<(EpochId, CompressedEpochSyncProof)>>>,
+    /// Whether a request is being served. Requests arriving meanwhile are
+    /// dropped, so that a flood of requests can't tie up the node.
+    response_in_progress: Arc<AtomicBool>,
 }
 
 impl EpochSync {
@@ -47,7 +62,36 @@ impl EpochSync {
         async_computation_spawner: Arc<dyn AsyncComputationSpawner>,
         config: EpochSyncConfig,
     ) -> Self {
-        Self { clock, network_adapter, genesis, async_computation_spawner, config }
+        Self {
+            clock,
+            network_adapter,
+            genesis,
+            async_computation_spawner,
+            config,
+            failed_peers: HashSet::new(),
+            last_epoch_sync_response_cache: Arc::new(Mutex::new(None)),
+            response_in_progress: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns the proof for the epoch of the final head, deriving it unless it is cached.
+    fn get_or_derive_compressed_proof(
+        store: Store,
+        cache: &Mutex<Option<(EpochId, CompressedEpochSyncProof)>>,
+    ) -> Result<CompressedEpochSyncProof, Error> {
+        let tip = store
+            .get_ser::<Tip>(DBCol::BlockMisc, FINAL_HEAD_KEY)?
+            .ok_or_else(|| Error::Other("Could not find tip".to_string()))?;
+        if let Some((epoch_id, proof)) = &*cache.lock().unwrap() {
+            if *epoch_id == tip.epoch_id {
+                return Ok(proof.clone());
+            }
+        }
+        let proof = Self::derive_epoch_sync_proof(store)?;
+        let (compressed_proof, _) = CompressedEpochSyncProof::encode(&proof)
+            .map_err(|err| Error::Other(format!("Failed to compress epoch sync proof: {err}")))?;
+        *cache.lock().unwrap() = Some((tip.epoch_id, compressed_proof.clone()));
+        Ok(compressed_proof)
     }
 
     /// Derives an epoch sync proof for a recent epoch, that can be directly used to bootstrap
@@ -85,7 +129,35 @@ impl EpochSync {
                 Error::Other("Could not find second last block of target epoch".to_string())
             })?;
 
-        Self::derive_epoch_sync_proof_from_final_block(store, target_epoch_second_last_block_header)
+        let next_block_header_after_final_block =
+            Self::find_header_endorsing_parent(&store, target_epoch_second_last_block_header)?;
+        Self::derive_epoch_sync_proof_from_final_block(store, next_block_header_after_final_block)
+    }
+
+    /// Walks back from `header` to the latest block of the same epoch whose parent is right
+    /// below it. The approvals in such a block endorse the parent, rather than skip it, so
+    /// they prove that the parent is final.
+    fn find_header_endorsing_parent(
+        store: &Store,
+        mut header: BlockHeader,
+    ) -> Result<BlockHeader, Error> {
+        loop {
+            let prev = store
+                .get_ser::<BlockHeader>(DBCol::BlockHeader, header.prev_hash().as_bytes())?
+                .ok_or_else(|| {
+                    Error::Other(format!("Could not find block header {}", header.prev_hash()))
+                })?;
+            if prev.epoch_id() != header.epoch_id() {
+                return Err(Error::Other(format!(
+                    "No block in epoch {:?} endorses its parent",
+                    header.epoch_id()
+                )));
+            }
+            if prev.height() + 1 == header.height() {
+                return Ok(header);
+            }
+            header = prev;
+        }
     }
 
     /// Derives an epoch sync proof using a target epoch which the given block header is in.
@@ -299,14 +371,16 @@ impl EpochSync {
                             epoch_id
                         ))
                     })?;
-                let third_last_block_header = store
+                let endorsing_block_header =
+                    Self::find_header_endorsing_parent(store, second_last_block_header)?;
+                let final_block_header = store
                     .get_ser::<BlockHeader>(
                         DBCol::BlockHeader,
-                        second_last_block_header.prev_hash().as_bytes(),
+                        endorsing_block_header.prev_hash().as_bytes(),
                     )?
                     .ok_or_else(|| {
                         Error::Other(format!(
-                            "Could not find third last block header for epoch {:?}",
+                            "Could not find final block header for epoch {:?}",
                             epoch_id
                         ))
                     })?;
@@ -314,13 +388,9 @@ impl EpochSync {
                     Error::Other(format!("Could not find epoch info for epoch {:?}", epoch_id))
                 })?;
                 Ok(EpochSyncProofPastEpochData {
-                    block_producers: epoch_info
-                        .block_producers_settlement()
-                        .iter()
-                        .map(|validator_id| epoch_info.get_validator(*validator_id))
-                        .collect(),
-                    last_final_block_header: third_last_block_header,
-                    approvals_for_last_final_block: second_last_block_header.approvals().to_vec(),
+                    block_producers: block_producers_ordered(epoch_info),
+                    last_final_block_header: final_block_header,
+                    approvals_for_last_final_block: endorsing_block_header.approvals().to_vec(),
                 })
             })
             .collect::<Result<Vec<_>, _>>()?;
@@ -330,7 +400,7 @@ impl EpochSync {
     /// Performs the epoch sync logic if applicable in the current state of the blockchain.
     /// This is periodically called by the client actor.
     pub fn run(
-        &self,
+        &mut self,
         status: &mut SyncStatus,
         chain: &Chain,
         highest_height: BlockHeight,
@@ -348,8 +418,16 @@ impl EpochSync {
                 return Ok(());
             }
             SyncStatus::EpochSync(status) => {
-                if status.attempt_time + self.config.timeout_for_epoch_sync < self.clock.now_utc() {
+                if self.failed_peers.contains(&status.source_peer_id) {
+                    tracing::warn!(
+                        "Epoch sync from {} failed; retrying with another peer",
+                        status.source_peer_id
+                    );
+                } else if status.attempt_time + self.config.timeout_for_epoch_sync
+                    < self.clock.now_utc()
+                {
                     tracing::warn!("Epoch sync from {} timed out; retrying", status.source_peer_id);
+                    self.failed_peers.insert(status.source_peer_id.clone());
                 } else {
                     return Ok(());
                 }
@@ -357,8 +435,17 @@ impl EpochSync {
             _ => {}
         }
 
-        // TODO(#11976): Implement a more robust logic for picking a peer to request epoch sync from.
-        let peer = highest_height_peers
+        // Prefer the peers which haven't failed us yet, but once all of them did, give
+        // them another chance rather than getting stuck.
+        let mut candidates: Vec<_> = highest_height_peers
+            .iter()
+            .filter(|peer| !self.failed_peers.contains(&peer.peer_info.id))
+            .collect();
+        if candidates.is_empty() {
+            self.failed_peers.clear();
+            candidates = highest_height_peers.iter().collect();
+        }
+        let peer = candidates
             .choose(&mut rand::thread_rng())
             .ok_or_else(|| Error::Other("No peers to request epoch sync from".to_string()))?;
 
@@ -375,8 +462,149 @@ impl EpochSync {
         Ok(())
     }
 
+    /// Verifies that the proof links the genesis to the epoch being synced to: the block
+    /// producers of every epoch are committed to by the blocks of the epoch before, and a
+    /// final block of every epoch is endorsed by more than 2/3 of the stake of its block
+    /// producers.
+    fn verify_proof(&self, proof: &EpochSyncProof) -> Result<(), Error> {
+        let EpochSyncProof { past_epochs, last_epoch, current_epoch } = proof;
+        let Some(prev_epoch) = past_epochs.last() else {
+            return Err(Error::InvalidEpochSyncProof("no past epochs".to_string()));
+        };
+
+        let mut expected_epoch_id = *self.genesis.next_epoch_id();
+        let mut expected_bp_hash = *self.genesis.next_bp_hash();
+        for epoch in past_epochs {
+            let header = &epoch.last_final_block_header;
+            Self::verify_epoch_handoff(
+                header,
+                &epoch.block_producers,
+                &expected_epoch_id,
+                &expected_bp_hash,
+            )?;
+            Self::verify_endorsement(
+                header,
+                &epoch.approvals_for_last_final_block,
+                &epoch.block_producers,
+            )?;
+            expected_epoch_id = *header.next_epoch_id();
+            expected_bp_hash = *header.next_bp_hash();
+        }
+
+        if block_producers_ordered(&last_epoch.epoch_info) != prev_epoch.block_producers {
+            return Err(Error::InvalidEpochSyncProof(
+                "epoch info of the last epoch doesn't match its block producers".to_string(),
+            ));
+        }
+        let final_header = &last_epoch.final_block_header_in_next_epoch;
+        let current_block_producers = block_producers_ordered(&last_epoch.next_epoch_info);
+        Self::verify_epoch_handoff(
+            final_header,
+            &current_block_producers,
+            &expected_epoch_id,
+            &expected_bp_hash,
+        )?;
+        Self::verify_endorsement(
+            final_header,
+            &last_epoch.approvals_for_final_block_in_next_epoch,
+            &current_block_producers,
+        )?;
+        if !bp_hash_matches(
+            final_header.next_bp_hash(),
+            &block_producers_ordered(&last_epoch.next_next_epoch_info),
+        ) {
+            return Err(Error::InvalidEpochSyncProof(
+                "epoch info of the next epoch doesn't match the next_bp_hash".to_string(),
+            ));
+        }
+
+        // The id of the next epoch is the hash of the last block of the previous one,
+        // which ties the blocks around the start of the current epoch to the final block.
+        let first_header = &current_epoch.first_block_header_in_epoch;
+        let last_header_in_prev_epoch = &current_epoch.last_block_header_in_prev_epoch;
+        let second_last_header_in_prev_epoch =
+            &current_epoch.second_last_block_header_in_prev_epoch;
+        if final_header.next_epoch_id().0 != *last_header_in_prev_epoch.hash()
+            || first_header.prev_hash() != last_header_in_prev_epoch.hash()
+            || first_header.epoch_id() != final_header.epoch_id()
+            || last_header_in_prev_epoch.prev_hash() != second_last_header_in_prev_epoch.hash()
+        {
+            return Err(Error::InvalidEpochSyncProof(
+                "blocks around the start of the epoch don't link to its final block".to_string(),
+            ));
+        }
+        if last_epoch.last_block_in_epoch.hash() != last_header_in_prev_epoch.hash()
+            || last_epoch.second_last_block_in_epoch.hash()
+                != second_last_header_in_prev_epoch.hash()
+            || last_epoch.last_block_in_epoch.epoch_first_block()
+                != last_epoch.first_block_in_epoch.hash()
+            || current_epoch.first_block_info_in_epoch.hash() != first_header.hash()
+        {
+            return Err(Error::InvalidEpochSyncProof(
+                "block infos don't match the block headers".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that `header` is in the epoch following the one verified so far, and that the
+    /// block producers of its epoch are the ones committed to by that epoch.
+    fn verify_epoch_handoff(
+        header: &BlockHeader,
+        block_producers: &[ValidatorStake],
+        expected_epoch_id: &EpochId,
+        expected_bp_hash: &CryptoHash,
+    ) -> Result<(), Error> {
+        if header.epoch_id() != expected_epoch_id {
+            return Err(Error::InvalidEpochSyncProof(format!(
+                "block {} is in epoch {:?}, expected {:?}",
+                header.hash(),
+                header.epoch_id(),
+                expected_epoch_id
+            )));
+        }
+        if !bp_hash_matches(expected_bp_hash, block_producers) {
+            return Err(Error::InvalidEpochSyncProof(format!(
+                "block producers of epoch {:?} don't match the next_bp_hash of the epoch before",
+                header.epoch_id()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that the approvals endorse `header` with more than 2/3 of the stake of the
+    /// block producers. The approvals are in the order of the block producers.
+    fn verify_endorsement(
+        header: &BlockHeader,
+        approvals: &[Option<Box<Signature>>],
+        block_producers: &[ValidatorStake],
+    ) -> Result<(), Error> {
+        let data = Approval::get_data_for_sig(
+            &ApprovalInner::Endorsement(*header.hash()),
+            header.height() + 1,
+        );
+        let total_stake: Balance = block_producers.iter().map(|bp| bp.stake()).sum();
+        let approved_stake: Balance = block_producers
+            .iter()
+            .zip(approvals)
+            .filter(|(bp, approval)| {
+                approval.as_ref().is_some_and(|signature| signature.verify(&data, bp.public_key()))
+            })
+            .map(|(bp, _)| bp.stake())
+            .sum();
+        if approved_stake * 3 <= total_stake * 2 {
+            return Err(Error::InvalidEpochSyncProof(format!(
+                "block {} is endorsed by {} of {} stake",
+                header.hash(),
+                approved_stake,
+                total_stake
+            )));
+        }
+        Ok(())
+    }
+
     pub fn apply_proof(
-        &self,
+        &mut self,
         status: &mut SyncStatus,
         chain: &mut Chain,
         proof: EpochSyncProof,
@@ -403,7 +631,10 @@ impl EpochSync {
             return Ok(());
         }
 
-        // TODO(#11932): Verify the proof.
+        if let Err(err) = self.verify_proof(&proof) {
+            self.failed_peers.insert(source_peer);
+            return Err(err);
+        }
 
         let last_header = proof.current_epoch.first_block_header_in_epoch;
         let mut store_update = chain.chain_store.store().store_update();
@@ -475,36 +706,29 @@ impl EpochSync {
 impl Handler<EpochSyncRequestMessage> for ClientActorInner {
     #[perf]
     fn handle(&mut self, msg: EpochSyncRequestMessage) {
-        if !self.client.epoch_sync.config.enabled {
-            // TODO(#11937): before we have rate limiting, don't respond to epoch sync requests
-            // unless config is enabled.
+        let epoch_sync = &self.client.epoch_sync;
+        if epoch_sync.config.ignore_epoch_sync_network_requests {
+            return;
+        }
+        if epoch_sync.response_in_progress.swap(true, Ordering::AcqRel) {
+            tracing::debug!("Dropping epoch sync request, another one is being served");
             return;
         }
         let store = self.client.chain.chain_store.store().clone();
         let network_adapter = self.client.network_adapter.clone();
+        let cache = epoch_sync.last_epoch_sync_response_cache.clone();
+        let response_in_progress = epoch_sync.response_in_progress.clone();
         let route_back = msg.route_back;
-        self.client.epoch_sync.async_computation_spawner.spawn(
-            "respond to epoch sync request",
-            move || {
-                let proof = match EpochSync::derive_epoch_sync_proof(store) {
-                    Ok(epoch_sync_proof) => epoch_sync_proof,
-                    Err(err) => {
-                        tracing::error!(?err, "Failed to derive epoch sync proof");
-                        return;
-                    }
-                };
-                let (compressed_proof, _) = match CompressedEpochSyncProof::encode(&proof) {
-                    Ok(compressed_proof) => compressed_proof,
-                    Err(err) => {
-                        tracing::error!(?err, "Failed to compress epoch sync proof");
-                        return;
-                    }
-                };
-                network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                    NetworkRequests::EpochSyncResponse { route_back, proof: compressed_proof },
-                ));
-            },
-        )
+        epoch_sync.async_computation_spawner.spawn("respond to epoch sync request", move || {
+            let result = EpochSync::get_or_derive_compressed_proof(store, &cache);
+            response_in_progress.store(false, Ordering::Release);
+            match result {
+                Ok(proof) => network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                    NetworkRequests::EpochSyncResponse { route_back, proof },
+                )),
+                Err(err) => tracing::error!(?err, "Failed to derive epoch sync proof"),
+            }
+        })
     }
 }
 
@@ -529,3 +753,24 @@ impl Handler<EpochSyncResponseMessage> for ClientActorInner {
         }
     }
 }
+
+/// Block producers of the epoch without repetitions, in the order of the block producers
+/// settlement. That's the order of the approvals in the blocks of the epoch, and the order
+/// in which `next_bp_hash` commits to them.
+fn block_producers_ordered(epoch_info: &EpochInfo) -> Vec<ValidatorStake> {
+    let mut seen = HashSet::new();
+    epoch_info
+        .block_producers_settlement()
+        .iter()
+        .filter(|validator_id| seen.insert(**validator_id))
+        .map(|validator_id| epoch_info.get_validator(*validator_id))
+        .collect()
+}
+
+/// Whether `bp_hash` commits to `block_producers`. Headers of old protocol versions
+/// hash the block producers in the V1 format.
+fn bp_hash_matches(bp_hash: &CryptoHash, block_producers: &[ValidatorStake]) -> bool {
+    *bp_hash == CryptoHash::hash_borsh_iter(block_producers)
+        || *bp_hash
+            == CryptoHash::hash_borsh_iter(block_producers.iter().map(|bp| bp.clone().into_v1()))
+}
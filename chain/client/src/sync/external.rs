@@ -9,8 +9,13 @@ use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub enum StateFileType {
-    StatePart { part_id: u64, num_parts: u64 },
+    StatePart {
+        part_id: u64,
+        num_parts: u64,
+    },
     StateHeader,
+    /// Signed hashes of the header and the parts, see `SignedStateDumpManifest`.
+    StateManifest,
 }
 
 impl ToString for StateFileType {
@@ -18,6 +23,7 @@ impl ToString for StateFileType {
         match self {
             StateFileType::StatePart { .. } => StateFileType::part_str(),
             StateFileType::StateHeader => StateFileType::header_str(),
+            StateFileType::StateManifest => StateFileType::manifest_str(),
         }
     }
 }
@@ -31,12 +37,17 @@ impl StateFileType {
         String::from("header")
     }
 
+    pub fn manifest_str() -> String {
+        String::from("manifest")
+    }
+
     pub fn filename(&self) -> String {
         match self {
             StateFileType::StatePart { part_id, num_parts } => {
                 format!("state_part_{:06}_of_{:06}", part_id, num_parts)
             }
             StateFileType::StateHeader => "header".to_string(),
+            StateFileType::StateManifest => "manifest".to_string(),
         }
     }
 }
@@ -60,6 +71,10 @@ pub enum ExternalConnection {
     },
 }
 
+/// State parts larger than this are uploaded to S3 in chunks of this size.
+/// S3 requires every chunk but the last to be at least 5 MiB.
+pub const MULTIPART_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
 const GCS_ENCODE_SET: &percent_encoding::AsciiSet =
     &percent_encoding::NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_');
 
@@ -171,6 +186,89 @@ impl ExternalConnection {
         }
     }
 
+    /// Whether large files can be uploaded in chunks, see
+    /// `start_multipart_upload()`.
+    pub fn supports_multipart_upload(&self) -> bool {
+        matches!(self, ExternalConnection::S3 { .. })
+    }
+
+    /// Starts a multipart upload of a file, returns the id of the upload.
+    /// The file only appears once the upload is completed.
+    pub async fn start_multipart_upload(&self, location: &str) -> Result<String, anyhow::Error> {
+        let ExternalConnection::S3 { bucket } = self else {
+            anyhow::bail!("Multipart uploads are only supported by S3");
+        };
+        let response =
+            bucket.initiate_multipart_upload(location, "application/octet-stream").await?;
+        tracing::debug!(target: "state_sync_dump", location, upload_id = response.upload_id, "Started a multipart upload");
+        Ok(response.upload_id)
+    }
+
+    /// Uploads chunk `index`, counting from 0, of a multipart upload and
+    /// returns its etag.
+    pub async fn put_multipart_chunk(
+        &self,
+        location: &str,
+        upload_id: &str,
+        index: usize,
+        chunk: &[u8],
+    ) -> Result<String, anyhow::Error> {
+        let ExternalConnection::S3 { bucket } = self else {
+            anyhow::bail!("Multipart uploads are only supported by S3");
+        };
+        let part = bucket
+            .put_multipart_chunk(
+                chunk.to_vec(),
+                location,
+                index as u32 + 1,
+                upload_id,
+                "application/octet-stream",
+            )
+            .await?;
+        tracing::debug!(target: "state_sync_dump", location, index, chunk_length = chunk.len(), "Uploaded a chunk");
+        Ok(part.etag)
+    }
+
+    /// Puts the uploaded chunks, given by their etags in order, together
+    /// into the file.
+    pub async fn complete_multipart_upload(
+        &self,
+        location: &str,
+        upload_id: &str,
+        chunk_etags: &[String],
+    ) -> Result<(), anyhow::Error> {
+        let ExternalConnection::S3 { bucket } = self else {
+            anyhow::bail!("Multipart uploads are only supported by S3");
+        };
+        let parts = chunk_etags
+            .iter()
+            .enumerate()
+            .map(|(index, etag)| s3::serde_types::Part {
+                etag: etag.clone(),
+                part_number: index as u32 + 1,
+            })
+            .collect();
+        let response = bucket.complete_multipart_upload(location, upload_id, parts).await?;
+        if response.status_code() != 200 {
+            anyhow::bail!("Bad response status code: {}", response.status_code());
+        }
+        tracing::debug!(target: "state_sync_dump", location, num_chunks = chunk_etags.len(), "Completed a multipart upload");
+        Ok(())
+    }
+
+    /// Drops an unfinished multipart upload and the chunks uploaded so far.
+    pub async fn abort_multipart_upload(
+        &self,
+        location: &str,
+        upload_id: &str,
+    ) -> Result<(), anyhow::Error> {
+        let ExternalConnection::S3 { bucket } = self else {
+            anyhow::bail!("Multipart uploads are only supported by S3");
+        };
+        bucket.abort_upload(location, upload_id).await?;
+        Ok(())
+    }
+
     fn extract_file_name_from_full_path(full_path: String) -> String {
         return Self::extract_file_name_from_path_buf(PathBuf::from(full_path));
     }
@@ -249,7 +347,42 @@ impl ExternalConnection {
         epoch_id: &EpochId,
         epoch_height: u64,
     ) -> Result<bool, anyhow::Error> {
-        let file_type = StateFileType::StateHeader;
+        self.is_file_stored_for_epoch(
+            shard_id,
+            chain_id,
+            epoch_id,
+            epoch_height,
+            StateFileType::StateHeader,
+        )
+        .await
+    }
+
+    /// Check if the signed state dump manifest exists in the external storage.
+    pub async fn is_state_sync_manifest_stored_for_epoch(
+        &self,
+        shard_id: ShardId,
+        chain_id: &String,
+        epoch_id: &EpochId,
+        epoch_height: u64,
+    ) -> Result<bool, anyhow::Error> {
+        self.is_file_stored_for_epoch(
+            shard_id,
+            chain_id,
+            epoch_id,
+            epoch_height,
+            StateFileType::StateManifest,
+        )
+        .await
+    }
+
+    async fn is_file_stored_for_epoch(
+        &self,
+        shard_id: ShardId,
+        chain_id: &String,
+        epoch_id: &EpochId,
+        epoch_height: u64,
+        file_type: StateFileType,
+    ) -> Result<bool, anyhow::Error> {
         let directory_path = external_storage_location_directory(
             chain_id,
             epoch_id,
@@ -258,17 +391,18 @@ impl ExternalConnection {
             &file_type,
         );
         let file_names = self.list_objects(shard_id, &directory_path).await?;
-        let header_exits = file_names.contains(&file_type.filename());
+        let file_exists = file_names.contains(&file_type.filename());
         tracing::debug!(
             target: "state_sync_dump",
             ?directory_path,
+            ?file_type,
             "{}",
-            match header_exits {
-                true => "Header has already been dumped.",
-                false => "Header has not been dumped.",
+            match file_exists {
+                true => "File has already been dumped.",
+                false => "File has not been dumped.",
             }
         );
-        Ok(header_exits)
+        Ok(file_exists)
     }
 }
 
@@ -309,7 +443,7 @@ pub fn location_prefix(
             "chain_id={}/epoch_height={}/epoch_id={}/shard_id={}",
             chain_id, epoch_height, epoch_id.0, shard_id
         ),
-        StateFileType::StateHeader => format!(
+        StateFileType::StateHeader | StateFileType::StateManifest => format!(
             "chain_id={}/epoch_height={}/epoch_id={}/headers/shard_id={}",
             chain_id, epoch_height, epoch_id.0, shard_id
         ),
@@ -509,6 +509,7 @@ mod test {
                 },
                 tracked_shards: vec![],
                 archival: false,
+                rpc_url: None,
                 last_block: Some(BlockInfo {
                     height: chain2.head().unwrap().height,
                     hash: chain2.head().unwrap().last_block_hash,
@@ -611,6 +612,7 @@ mod test {
                 },
                 tracked_shards: vec![],
                 archival: false,
+                rpc_url: None,
                 last_block: Some(BlockInfo {
                     height: chain2.head().unwrap().height,
                     hash: chain2.head().unwrap().last_block_hash,
@@ -836,6 +838,7 @@ mod test {
                 },
                 tracked_shards: vec![],
                 archival: false,
+                rpc_url: None,
                 last_block: Some(BlockInfo {
                     height: chain2.head().unwrap().height,
                     hash: chain2.head().unwrap().last_block_hash,
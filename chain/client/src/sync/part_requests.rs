@@ -0,0 +1,209 @@
+//! Bookkeeping of the state parts requested from the peers.
+//!
+//! A part which doesn't arrive within `STATE_PART_HEDGE_DELAY` is requested
+//! from one more peer, up to `MAX_STATE_PART_REQUESTS_IN_FLIGHT` peers at a
+//! time, and whichever answers first wins. A request which isn't answered
+//! within the state sync timeout counts as a failure of the peer, both for the
+//! part, which is then requested from other peers, and for the reputation of
+//! the peer, which makes it less likely to be picked for other parts.
+//!
+//! Responses don't tell which peer sent them, so a part is credited to a peer
+//! only if no other request for the part was in flight.
+
+use near_async::time::{Duration, Utc};
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::types::ShardId;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet};
+
+/// Maximum number of parts requested from a peer at a time.
+pub const MAX_STATE_PART_REQUEST: usize = 16;
+/// A part not received this long after it was last requested is requested
+/// from one more peer.
+pub const STATE_PART_HEDGE_DELAY: Duration = Duration::seconds(5);
+/// Maximum number of peers a part is requested from at a time.
+pub const MAX_STATE_PART_REQUESTS_IN_FLIGHT: usize = 3;
+
+type PartKey = (ShardId, CryptoHash, u64);
+
+#[derive(Default)]
+struct PartState {
+    /// Peers the part is requested from, with the time of the requests.
+    requests: Vec<(PeerId, Utc)>,
+    /// Peers which didn't serve the part in time.
+    failed: HashSet<PeerId>,
+}
+
+#[derive(Default)]
+struct PeerState {
+    in_flight: usize,
+    served: u64,
+    failed: u64,
+}
+
+impl PeerState {
+    /// Chance of the peer serving a part, estimated from its history.
+    fn reputation(&self) -> f64 {
+        (self.served + 1) as f64 / (self.served + self.failed + 2) as f64
+    }
+}
+
+pub(crate) struct PartRequests {
+    timeout: Duration,
+    parts: HashMap<PartKey, PartState>,
+    peers: HashMap<PeerId, PeerState>,
+}
+
+impl PartRequests {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout, parts: HashMap::new(), peers: HashMap::new() }
+    }
+
+    /// Number of parts in flight to `peer_id`.
+    pub fn in_flight(&self, peer_id: &PeerId) -> usize {
+        self.peers.get(peer_id).map_or(0, |peer| peer.in_flight)
+    }
+
+    /// Drops the requests unanswered for longer than the timeout, counting
+    /// them as failures of the peers.
+    pub fn expire(&mut self, now: Utc) {
+        let timeout = self.timeout;
+        let peers = &mut self.peers;
+        self.parts.retain(|_, part| {
+            part.requests.retain(|(peer_id, sent)| {
+                if now - *sent <= timeout {
+                    return true;
+                }
+                let peer = peers.entry(peer_id.clone()).or_default();
+                peer.in_flight = peer.in_flight.saturating_sub(1);
+                peer.failed += 1;
+                part.failed.insert(peer_id.clone());
+                false
+            });
+            !part.requests.is_empty() || !part.failed.is_empty()
+        });
+    }
+
+    pub fn sent(
+        &mut self,
+        peer_id: PeerId,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        part_id: u64,
+        now: Utc,
+    ) {
+        self.peers.entry(peer_id.clone()).or_default().in_flight += 1;
+        self.parts.entry((shard_id, sync_hash, part_id)).or_default().requests.push((peer_id, now));
+    }
+
+    /// Records that the part arrived, from a peer or otherwise.
+    pub fn received(&mut self, shard_id: ShardId, sync_hash: CryptoHash, part_id: u64) {
+        let Some(part) = self.parts.remove(&(shard_id, sync_hash, part_id)) else { return };
+        let unambiguous = part.requests.len() == 1;
+        for (peer_id, _) in part.requests {
+            let peer = self.peers.entry(peer_id).or_default();
+            peer.in_flight = peer.in_flight.saturating_sub(1);
+            if unambiguous {
+                peer.served += 1;
+            }
+        }
+    }
+
+    /// Number of peers which failed to serve the part.
+    pub fn failures(&self, shard_id: ShardId, sync_hash: CryptoHash, part_id: u64) -> usize {
+        self.parts.get(&(shard_id, sync_hash, part_id)).map_or(0, |part| part.failed.len())
+    }
+
+    /// Whether the part has been waited for long enough to be requested from
+    /// one more peer.
+    pub fn hedge_due(
+        &self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        part_id: u64,
+        now: Utc,
+    ) -> bool {
+        let Some(part) = self.parts.get(&(shard_id, sync_hash, part_id)) else { return false };
+        let Some(last) = part.requests.iter().map(|(_, sent)| *sent).max() else { return false };
+        part.requests.len() < MAX_STATE_PART_REQUESTS_IN_FLIGHT
+            && now - last >= STATE_PART_HEDGE_DELAY
+    }
+
+    /// Picks a peer to request the part from, out of `candidates`, at random
+    /// weighted by reputation. Skips the peers busy with other parts, those
+    /// already asked for the part, and those which failed to serve it, unless
+    /// no one else is left.
+    pub fn choose_peer(
+        &self,
+        candidates: &[PeerId],
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        part_id: u64,
+    ) -> Option<PeerId> {
+        let part = self.parts.get(&(shard_id, sync_hash, part_id));
+        let available: Vec<&PeerId> = candidates
+            .iter()
+            .filter(|peer_id| self.in_flight(peer_id) < MAX_STATE_PART_REQUEST)
+            .filter(|peer_id| {
+                part.map_or(true, |part| part.requests.iter().all(|(p, _)| p != *peer_id))
+            })
+            .collect();
+        let fresh: Vec<&PeerId> = available
+            .iter()
+            .copied()
+            .filter(|peer_id| part.map_or(true, |part| !part.failed.contains(*peer_id)))
+            .collect();
+        let pool = if fresh.is_empty() { available } else { fresh };
+        pool.choose_weighted(&mut rand::thread_rng(), |peer_id| {
+            self.peers.get(*peer_id).map_or(0.5, PeerState::reputation)
+        })
+        .ok()
+        .map(|peer_id| (*peer_id).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartRequests, MAX_STATE_PART_REQUESTS_IN_FLIGHT, STATE_PART_HEDGE_DELAY};
+    use near_async::time::{Duration, Utc};
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::network::PeerId;
+
+    fn make_peer_id() -> PeerId {
+        PeerId::new(SecretKey::from_random(KeyType::ED25519).public_key())
+    }
+
+    #[test]
+    fn test_hedge_and_expire() {
+        let now = Utc::UNIX_EPOCH;
+        let hash = CryptoHash::default();
+        let peers: Vec<PeerId> = (0..4).map(|_| make_peer_id()).collect();
+        let mut requests = PartRequests::new(Duration::seconds(60));
+
+        let first = requests.choose_peer(&peers, 0, hash, 7).unwrap();
+        requests.sent(first.clone(), 0, hash, 7, now);
+        assert!(!requests.hedge_due(0, hash, 7, now + Duration::seconds(1)));
+        // A hedged request goes to another peer.
+        let now = now + STATE_PART_HEDGE_DELAY;
+        assert!(requests.hedge_due(0, hash, 7, now));
+        for _ in 1..MAX_STATE_PART_REQUESTS_IN_FLIGHT {
+            let peer = requests.choose_peer(&peers, 0, hash, 7).unwrap();
+            assert_ne!(peer, first);
+            requests.sent(peer, 0, hash, 7, now);
+        }
+        assert!(!requests.hedge_due(0, hash, 7, now + STATE_PART_HEDGE_DELAY));
+
+        // Once the requests time out, the part goes to the one peer left.
+        requests.expire(now + Duration::seconds(61));
+        assert_eq!(requests.failures(0, hash, 7), MAX_STATE_PART_REQUESTS_IN_FLIGHT);
+        let left = requests.choose_peer(&peers, 0, hash, 7).unwrap();
+        requests.sent(left.clone(), 0, hash, 7, now + Duration::seconds(61));
+        requests.received(0, hash, 7);
+        assert_eq!(requests.failures(0, hash, 7), 0);
+        assert_eq!(requests.in_flight(&left), 0);
+        assert_eq!(requests.peers[&left].served, 1);
+        assert_eq!(requests.peers[&first].failed, 1);
+    }
+}
@@ -11,8 +11,10 @@
 //!
 //! For downloading - the code is picking the potential target nodes (all direct peers that are tracking the shard
 //! (and are high enough) + validators from that epoch that were tracking the shard)
-//! Then for each part that we're missing, we're 'randomly' picking a target from whom we'll request it - but we make
-//! sure to not request more than MAX_STATE_PART_REQUESTS from each.
+//! Then for each part that we're missing, we're 'randomly' picking a target from whom we'll request it, favouring the
+//! targets which served parts before - but we make sure to not request more than MAX_STATE_PART_REQUEST from each.
+//! Parts which are slow to arrive are requested from more targets at once, see `part_requests`.
+//! If external storage is configured, the parts which the peers fail to serve are downloaded from it instead.
 //!
 //! WARNING: with the current design, we're putting quite a load on the validators - as we request a lot of data from
 //!         them (if you assume that we have 100 validators and 30 peers - we send 100/130 of requests to validators).
@@ -24,6 +26,7 @@ use crate::metrics;
 use crate::sync::external::{
     create_bucket_readonly, external_storage_location, ExternalConnection,
 };
+use crate::sync::part_requests::{PartRequests, MAX_STATE_PART_REQUEST};
 use borsh::BorshDeserialize;
 use futures::{future, FutureExt};
 use near_async::futures::{FutureSpawner, FutureSpawnerExt};
@@ -37,25 +40,25 @@ use near_chain_configs::{ExternalStorageConfig, ExternalStorageLocation, SyncCon
 use near_client_primitives::types::{
     format_shard_sync_phase, DownloadStatus, ShardSyncDownload, ShardSyncStatus,
 };
+use near_crypto::PublicKey;
 use near_epoch_manager::EpochManagerAdapter;
 use near_network::types::PeerManagerMessageRequest;
 use near_network::types::{
     HighestHeightPeerInfo, NetworkRequests, NetworkResponses, PeerManagerAdapter,
 };
-use near_primitives::hash::CryptoHash;
+use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::network::PeerId;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_part::PartId;
 use near_primitives::state_sync::{
-    ShardStateSyncResponse, ShardStateSyncResponseHeader, StatePartKey,
+    ShardStateSyncResponse, ShardStateSyncResponseHeader, SignedStateDumpManifest,
+    StateDumpManifest, StatePartKey,
 };
 use near_primitives::types::{AccountId, EpochHeight, EpochId, ShardId, StateRoot};
 use near_store::DBCol;
 use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
+use rand::thread_rng;
 use std::collections::HashMap;
-use std::num::NonZeroUsize;
-use std::ops::Add;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
@@ -64,11 +67,6 @@ use tracing::info;
 
 use super::external::StateFileType;
 
-/// Maximum number of state parts to request per peer on each round when node is trying to download the state.
-pub const MAX_STATE_PART_REQUEST: u64 = 16;
-/// Number of state parts already requested stored as pending.
-/// This number should not exceed MAX_STATE_PART_REQUEST times (number of peers in the network).
-pub const MAX_PENDING_PART: u64 = MAX_STATE_PART_REQUEST * 10000;
 /// Time limit per state dump iteration.
 /// A node must check external storage for parts to dump again once time is up.
 pub const STATE_DUMP_ITERATION_TIME_LIMIT_SECS: u64 = 300;
@@ -80,25 +78,16 @@ pub enum StateSyncResult {
     Completed,
 }
 
-struct PendingRequestStatus {
-    clock: Clock,
-    /// Number of parts that are in progress (we requested them from a given peer but didn't get the answer yet).
-    missing_parts: usize,
-    wait_until: Utc,
-}
-
-impl PendingRequestStatus {
-    fn new(clock: Clock, timeout: Duration) -> Self {
-        Self { clock: clock.clone(), missing_parts: 1, wait_until: clock.now_utc().add(timeout) }
-    }
-    fn expired(&self) -> bool {
-        self.clock.now_utc() > self.wait_until
-    }
-}
-
 pub enum StateSyncFileDownloadResult {
-    StateHeader { header_length: u64, header: ShardStateSyncResponseHeader },
-    StatePart { part_length: u64 },
+    StateHeader {
+        header_length: u64,
+        header: ShardStateSyncResponseHeader,
+        /// Verified manifest of the dump, if trusted dump producers are configured.
+        manifest: Option<StateDumpManifest>,
+    },
+    StatePart {
+        part_length: u64,
+    },
 }
 
 /// Signals that a state part was downloaded and saved to RocksDB.
@@ -110,32 +99,32 @@ pub struct StateSyncGetFileResult {
     result: Result<StateSyncFileDownloadResult, String>,
 }
 
-/// How to retrieve the state data.
-enum StateSyncInner {
-    /// Request both the state header and state parts from the peers.
-    Peers {
-        /// Which parts were requested from which peer and when.
-        last_part_id_requested: HashMap<(PeerId, ShardId), PendingRequestStatus>,
-        /// Map from which part we requested to whom.
-        requested_target: lru::LruCache<(u64, CryptoHash), PeerId>,
-    },
-    /// Requests the state header from peers but gets the state parts from an
-    /// external storage.
-    External {
-        /// Chain ID.
-        chain_id: String,
-        /// This semaphore imposes a restriction on the maximum number of simultaneous downloads
-        semaphore: Arc<tokio::sync::Semaphore>,
-        /// Connection to the external storage.
-        external: ExternalConnection,
-    },
+/// External storage to get the state header from, and the state parts which
+/// the peers fail to serve.
+struct ExternalStorage {
+    /// Chain ID.
+    chain_id: String,
+    /// This semaphore imposes a restriction on the maximum number of simultaneous downloads
+    semaphore: Arc<tokio::sync::Semaphore>,
+    /// Connection to the external storage.
+    external: ExternalConnection,
+    /// If not empty, only state matching a manifest signed by one of
+    /// these keys is accepted.
+    trusted_dump_producers: Arc<Vec<PublicKey>>,
+    /// Verified manifests of the shards whose header was downloaded.
+    manifests: HashMap<(ShardId, CryptoHash), Arc<StateDumpManifest>>,
+    /// Number of peers which have to fail to serve a part before it is
+    /// downloaded from the external storage.
+    fallback_threshold: usize,
 }
 
 /// Helper to track state sync.
 pub struct StateSync {
     clock: Clock,
-    /// How to retrieve the state data.
-    inner: StateSyncInner,
+    /// State parts requested from the peers.
+    part_requests: PartRequests,
+    /// Where to get the state from, besides the peers.
+    external: Option<ExternalStorage>,
 
     /// Is used for communication with the peers.
     network_adapter: PeerManagerAdapter,
@@ -168,17 +157,14 @@ impl StateSync {
         sync_config: &SyncConfig,
         catchup: bool,
     ) -> Self {
-        let inner = match sync_config {
-            SyncConfig::Peers => StateSyncInner::Peers {
-                last_part_id_requested: Default::default(),
-                requested_target: lru::LruCache::new(
-                    NonZeroUsize::new(MAX_PENDING_PART as usize).unwrap(),
-                ),
-            },
+        let external = match sync_config {
+            SyncConfig::Peers => None,
             SyncConfig::ExternalStorage(ExternalStorageConfig {
                 location,
                 num_concurrent_requests,
                 num_concurrent_requests_during_catchup,
+                trusted_dump_producers,
+                external_storage_fallback_threshold,
             }) => {
                 let external = match location {
                     ExternalStorageLocation::S3 { bucket, region, .. } => {
@@ -206,17 +192,21 @@ impl StateSync {
                 } else {
                     *num_concurrent_requests
                 } as usize;
-                StateSyncInner::External {
+                Some(ExternalStorage {
                     chain_id: chain_id.to_string(),
                     semaphore: Arc::new(tokio::sync::Semaphore::new(num_permits)),
                     external,
-                }
+                    trusted_dump_producers: Arc::new(trusted_dump_producers.clone()),
+                    manifests: HashMap::new(),
+                    fallback_threshold: *external_storage_fallback_threshold as usize,
+                })
             }
         };
         let (tx, rx) = channel::<StateSyncGetFileResult>();
         StateSync {
             clock,
-            inner,
+            part_requests: PartRequests::new(timeout),
+            external,
             network_adapter,
             timeout,
             state_parts_apply_results: HashMap::new(),
@@ -282,8 +272,12 @@ impl StateSync {
                         )?;
                 }
                 ShardSyncStatus::StateDownloadParts => {
-                    let res =
-                        self.sync_shards_download_parts_status(shard_id, shard_sync_download, now);
+                    let res = self.sync_shards_download_parts_status(
+                        shard_id,
+                        shard_sync_download,
+                        sync_hash,
+                        now,
+                    );
                     download_timeout = res.0;
                     run_shard_state_download = res.1;
                 }
@@ -392,7 +386,11 @@ impl StateSync {
                 let (download_result, download) = match result {
                     Err(err) => (Err(err), None),
                     // Store the header
-                    Ok(StateSyncFileDownloadResult::StateHeader { header_length, header }) => {
+                    Ok(StateSyncFileDownloadResult::StateHeader {
+                        header_length,
+                        header,
+                        manifest,
+                    }) => {
                         info!(target: "sync", ?header_length, ?part_id, "processing state header");
                         if shard_sync_download.status != ShardSyncStatus::StateDownloadHeader {
                             continue;
@@ -405,6 +403,11 @@ impl StateSync {
                             .set_state_header(shard_id, sync_hash, header)
                             .map_err(|err| format!("State sync set_state_header error: {err:?}"))
                             .map(|_| header_length);
+                        if let (Ok(_), Some(manifest), Some(external)) =
+                            (&result, manifest, &mut self.external)
+                        {
+                            external.manifests.insert((shard_id, sync_hash), Arc::new(manifest));
+                        }
                         (result, download)
                     }
                     // Part was stored on the tx side.
@@ -413,6 +416,9 @@ impl StateSync {
                         if shard_sync_download.status != ShardSyncStatus::StateDownloadParts {
                             continue;
                         }
+                        if let Some(part_id) = part_id {
+                            self.part_requests.received(shard_id, sync_hash, part_id.idx);
+                        }
                         (
                             Ok(part_length),
                             part_id.and_then(|part_id| {
@@ -490,50 +496,16 @@ impl StateSync {
         shard_id: ShardId,
         sync_hash: CryptoHash,
     ) {
-        match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, requested_target } => {
-                let key = (part_id, sync_hash);
-                // Check that it came from the target that we requested it from.
-                if let Some(target) = requested_target.get(&key) {
-                    if last_part_id_requested.get_mut(&(target.clone(), shard_id)).map_or(
-                        false,
-                        |request| {
-                            request.missing_parts = request.missing_parts.saturating_sub(1);
-                            request.missing_parts == 0
-                        },
-                    ) {
-                        last_part_id_requested.remove(&(target.clone(), shard_id));
-                    }
-                }
-            }
-            StateSyncInner::External { .. } => {
-                // Do nothing.
-            }
-        }
+        self.part_requests.received(shard_id, sync_hash, part_id);
     }
 
-    /// Avoids peers that already have outstanding requests for parts.
-    fn select_peers(
-        &mut self,
-        highest_height_peers: &[HighestHeightPeerInfo],
-        shard_id: ShardId,
-    ) -> Result<Vec<PeerId>, near_chain::Error> {
-        let peers: Vec<PeerId> =
-            highest_height_peers.iter().map(|peer| peer.peer_info.id.clone()).collect();
-        let res = match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, .. } => {
-                last_part_id_requested.retain(|_, request| !request.expired());
-                peers
-                    .into_iter()
-                    .filter(|peer| {
-                        // If we still have a pending request from this node - don't add another one.
-                        !last_part_id_requested.contains_key(&(peer.clone(), shard_id))
-                    })
-                    .collect::<Vec<_>>()
-            }
-            StateSyncInner::External { .. } => peers,
-        };
-        Ok(res)
+    /// Avoids peers that are busy serving other parts.
+    fn select_peers(&self, highest_height_peers: &[HighestHeightPeerInfo]) -> Vec<PeerId> {
+        highest_height_peers
+            .iter()
+            .map(|peer| peer.peer_info.id.clone())
+            .filter(|peer_id| self.part_requests.in_flight(peer_id) < MAX_STATE_PART_REQUEST)
+            .collect()
     }
 
     /// Returns new ShardSyncDownload if successful, otherwise returns given shard_sync_download
@@ -547,18 +519,12 @@ impl StateSync {
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         state_parts_future_spawner: &dyn FutureSpawner,
     ) -> Result<(), near_chain::Error> {
-        let mut possible_targets = vec![];
-        match self.inner {
-            StateSyncInner::Peers { .. } => {
-                possible_targets = self.select_peers(highest_height_peers, shard_id)?;
-                if possible_targets.is_empty() {
-                    tracing::debug!(target: "sync", "Can't request a state header: No possible targets");
-                    // In most cases it means that all the targets are currently busy (that we have a pending request with them).
-                    return Ok(());
-                }
-            }
-            // We do not need to select a target for external storage.
-            StateSyncInner::External { .. } => {}
+        let possible_targets = self.select_peers(highest_height_peers);
+        // Without external storage there is no one else to get the state from.
+        if possible_targets.is_empty() && self.external.is_none() {
+            tracing::debug!(target: "sync", "Can't request a state header: No possible targets");
+            // In most cases it means that all the targets are currently busy (that we have a pending request with them).
+            return Ok(());
         }
 
         // Downloading strategy starts here
@@ -577,7 +543,7 @@ impl StateSync {
                 self.request_shard_parts(
                     shard_id,
                     sync_hash,
-                    possible_targets,
+                    &possible_targets,
                     shard_sync_download,
                     chain,
                     runtime_adapter,
@@ -601,8 +567,8 @@ impl StateSync {
         state_parts_future_spawner: &dyn FutureSpawner,
     ) {
         let header_download = new_shard_sync_download.get_header_download_mut().unwrap();
-        match &mut self.inner {
-            StateSyncInner::Peers { .. } => {
+        match &self.external {
+            None => {
                 let peer_id = possible_targets.choose(&mut thread_rng()).cloned().unwrap();
                 tracing::debug!(target: "sync", ?peer_id, shard_id, ?sync_hash, ?possible_targets, "request_shard_header");
                 assert!(header_download.run_me.load(Ordering::SeqCst));
@@ -627,7 +593,7 @@ impl StateSync {
                         }),
                 );
             }
-            StateSyncInner::External { chain_id, external, .. } => {
+            Some(ExternalStorage { chain_id, external, trusted_dump_producers, .. }) => {
                 let sync_block_header = chain.get_block_header(&sync_hash).unwrap();
                 let epoch_id = sync_block_header.epoch_id();
                 let epoch_info = chain.epoch_manager.get_epoch_info(epoch_id).unwrap();
@@ -638,8 +604,9 @@ impl StateSync {
                     sync_hash,
                     epoch_id,
                     epoch_height,
-                    &chain_id.clone(),
+                    chain_id,
                     external.clone(),
+                    trusted_dump_producers.clone(),
                     state_parts_future_spawner,
                     self.state_parts_mpsc_tx.clone(),
                 );
@@ -648,64 +615,71 @@ impl StateSync {
     }
 
     /// Makes requests to download state parts for the given epoch of the given shard.
+    ///
+    /// Each part is requested from one of the peers, picked by their
+    /// reputation. Parts which take too long get requested from more peers at
+    /// once. Parts which too many peers failed to serve, or which no peer is
+    /// available for, are downloaded from the external storage, if any.
     fn request_shard_parts(
         &mut self,
         shard_id: ShardId,
         sync_hash: CryptoHash,
-        possible_targets: Vec<PeerId>,
+        possible_targets: &[PeerId],
         new_shard_sync_download: &mut ShardSyncDownload,
         chain: &Chain,
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         state_parts_future_spawner: &dyn FutureSpawner,
     ) {
-        // Iterate over all parts that needs to be requested (i.e. download.run_me is true).
+        let now = self.clock.now_utc();
+        self.part_requests.expire(now);
+        let mut external_exhausted = false;
         // Parts are ordered such that its index match its part_id.
-        match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, requested_target } => {
-                // We'll select all the 'highest' peers + validators as candidates (excluding those that gave us timeout in the past).
-                // And for each one of them, we'll ask for up to 16 (MAX_STATE_PART_REQUEST) parts.
-                let possible_targets_sampler =
-                    SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
-
-                // For every part that needs to be requested it is selected one
-                // peer (target) randomly to request the part from.
-                // IMPORTANT: here we use 'zip' with possible_target_sampler -
-                // which is limited. So at any moment we'll not request more
-                // than possible_targets.len() * MAX_STATE_PART_REQUEST parts.
-                for ((part_id, download), target) in
-                    parts_to_fetch(new_shard_sync_download).zip(possible_targets_sampler)
-                {
-                    sent_request_part(
-                        self.clock.clone(),
-                        target.clone(),
-                        part_id,
-                        shard_id,
-                        sync_hash,
-                        last_part_id_requested,
-                        requested_target,
-                        self.timeout,
-                    );
+        for (part_id, download) in new_shard_sync_download.downloads.iter_mut().enumerate() {
+            let part_id = part_id as u64;
+            if download.done {
+                continue;
+            }
+            let hedge = self.part_requests.hedge_due(shard_id, sync_hash, part_id, now);
+            if !download.run_me.load(Ordering::SeqCst) && !hedge {
+                continue;
+            }
+            let use_external = self.external.as_ref().is_some_and(|external| {
+                self.part_requests.failures(shard_id, sync_hash, part_id)
+                    >= external.fallback_threshold
+            });
+            let peer_id = if use_external {
+                None
+            } else {
+                self.part_requests.choose_peer(possible_targets, shard_id, sync_hash, part_id)
+            };
+            match (peer_id, &self.external) {
+                (Some(peer_id), _) => {
+                    if hedge {
+                        metrics::STATE_SYNC_HEDGED_PART_REQUESTS
+                            .with_label_values(&[&shard_id.to_string()])
+                            .inc();
+                    }
+                    self.part_requests.sent(peer_id.clone(), shard_id, sync_hash, part_id, now);
                     request_part_from_peers(
                         part_id,
-                        target,
+                        peer_id,
                         download,
                         shard_id,
                         sync_hash,
                         &self.network_adapter,
                     );
                 }
-            }
-            StateSyncInner::External { chain_id, semaphore, external } => {
-                let sync_block_header = chain.get_block_header(&sync_hash).unwrap();
-                let epoch_id = sync_block_header.epoch_id();
-                let epoch_info = chain.epoch_manager.get_epoch_info(epoch_id).unwrap();
-                let epoch_height = epoch_info.epoch_height();
+                (None, Some(external)) if !external_exhausted => {
+                    let sync_block_header = chain.get_block_header(&sync_hash).unwrap();
+                    let epoch_id = sync_block_header.epoch_id();
+                    let epoch_info = chain.epoch_manager.get_epoch_info(epoch_id).unwrap();
+                    let epoch_height = epoch_info.epoch_height();
 
-                let shard_state_header = chain.get_state_header(shard_id, sync_hash).unwrap();
-                let state_root = shard_state_header.chunk_prev_state_root();
-                let state_num_parts = shard_state_header.num_state_parts();
+                    let shard_state_header = chain.get_state_header(shard_id, sync_hash).unwrap();
+                    let state_root = shard_state_header.chunk_prev_state_root();
+                    let state_num_parts = shard_state_header.num_state_parts();
+                    let manifest = external.manifests.get(&(shard_id, sync_hash)).cloned();
 
-                for (part_id, download) in parts_to_fetch(new_shard_sync_download) {
                     request_part_from_external_storage(
                         part_id,
                         download,
@@ -714,18 +688,19 @@ impl StateSync {
                         epoch_id,
                         epoch_height,
                         state_num_parts,
-                        &chain_id.clone(),
+                        &external.chain_id,
                         state_root,
-                        semaphore.clone(),
-                        external.clone(),
+                        external.semaphore.clone(),
+                        external.external.clone(),
+                        external.trusted_dump_producers.clone(),
+                        manifest,
                         runtime_adapter.clone(),
                         state_parts_future_spawner,
                         self.state_parts_mpsc_tx.clone(),
                     );
-                    if semaphore.available_permits() == 0 {
-                        break;
-                    }
+                    external_exhausted = external.semaphore.available_permits() == 0;
                 }
+                (None, _) => {}
             }
         }
     }
@@ -910,6 +885,7 @@ impl StateSync {
         &mut self,
         shard_id: ShardId,
         shard_sync_download: &mut ShardSyncDownload,
+        sync_hash: CryptoHash,
         now: Utc,
     ) -> (bool, bool) {
         // Step 2 - download all the parts (each part is usually around 1MB).
@@ -919,7 +895,7 @@ impl StateSync {
         let mut parts_done = true;
         let num_parts = shard_sync_download.downloads.len();
         let mut num_parts_done = 0;
-        for part_download in shard_sync_download.downloads.iter_mut() {
+        for (part_id, part_download) in shard_sync_download.downloads.iter_mut().enumerate() {
             if !part_download.done {
                 parts_done = false;
                 let prev = part_download.prev_update_time;
@@ -939,7 +915,9 @@ impl StateSync {
                         part_download.prev_update_time = now;
                     }
                 }
-                if part_download.run_me.load(Ordering::SeqCst) {
+                if part_download.run_me.load(Ordering::SeqCst)
+                    || self.part_requests.hedge_due(shard_id, sync_hash, part_id as u64, now)
+                {
                     run_shard_state_download = true;
                 }
             }
@@ -1112,24 +1090,62 @@ impl StateSync {
     }
 }
 
-/// Returns parts that still need to be fetched.
-fn parts_to_fetch(
-    new_shard_sync_download: &mut ShardSyncDownload,
-) -> impl Iterator<Item = (u64, &mut DownloadStatus)> {
-    new_shard_sync_download
-        .downloads
-        .iter_mut()
-        .enumerate()
-        .filter(|(_, download)| download.run_me.load(Ordering::SeqCst))
-        .map(|(part_id, download)| (part_id as u64, download))
+/// Downloads the manifest of the dumped state and checks that it is signed by
+/// one of the trusted dump producers and describes the state being synced.
+async fn download_trusted_manifest(
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    location: &str,
+    trusted_dump_producers: &[PublicKey],
+    external: &ExternalConnection,
+) -> Result<StateDumpManifest, String> {
+    let data = external
+        .get_file(shard_id, location, &StateFileType::StateManifest)
+        .await
+        .map_err(|err| err.to_string())?;
+    let signed = SignedStateDumpManifest::try_from_slice(&data)
+        .map_err(|_| format!("Could not parse state dump manifest for shard {shard_id}"))?;
+    if !trusted_dump_producers.contains(&signed.public_key) {
+        return Err(format!(
+            "State dump manifest for shard {shard_id} is signed by untrusted key {}",
+            signed.public_key
+        ));
+    }
+    if !signed.verify() {
+        return Err(format!("Invalid signature of state dump manifest for shard {shard_id}"));
+    }
+    let manifest = signed.manifest;
+    if manifest.sync_hash != sync_hash || manifest.shard_id != shard_id {
+        return Err(format!(
+            "State dump manifest for shard {shard_id} describes shard {} at {}",
+            manifest.shard_id, manifest.sync_hash
+        ));
+    }
+    Ok(manifest)
 }
 
 async fn download_header_from_external_storage(
     shard_id: ShardId,
     sync_hash: CryptoHash,
     location: String,
+    manifest_location: String,
     external: ExternalConnection,
+    trusted_dump_producers: Arc<Vec<PublicKey>>,
 ) -> Result<StateSyncFileDownloadResult, std::string::String> {
+    let manifest = if trusted_dump_producers.is_empty() {
+        None
+    } else {
+        Some(
+            download_trusted_manifest(
+                shard_id,
+                sync_hash,
+                &manifest_location,
+                &trusted_dump_producers,
+                &external,
+            )
+            .await?,
+        )
+    };
     external
     .get_file(shard_id, &location, &StateFileType::StateHeader)
     .await
@@ -1137,12 +1153,22 @@ async fn download_header_from_external_storage(
     .and_then(|data| {
         info!(target: "sync", ?shard_id, "downloaded state header");
         let header_length = data.len() as u64;
-        ShardStateSyncResponseHeader::try_from_slice(&data)
-        .map(|header| StateSyncFileDownloadResult::StateHeader { header_length , header })
+        if let Some(manifest) = &manifest {
+            if hash(&data) != manifest.header_hash {
+                return Err(format!("State sync header for shard {shard_id} doesn't match the manifest"));
+            }
+        }
+        let header = ShardStateSyncResponseHeader::try_from_slice(&data)
         .map_err(|_| {
             tracing::info!(target: "sync", %shard_id, %sync_hash, "Could not parse downloaded header.");
             format!("Could not parse state sync header for shard {shard_id:?}")
-        })
+        })?;
+        if let Some(manifest) = &manifest {
+            if manifest.part_hashes.len() as u64 != header.num_state_parts() {
+                return Err(format!("State dump manifest for shard {shard_id} lists {} parts, expected {}", manifest.part_hashes.len(), header.num_state_parts()));
+            }
+        }
+        Ok(StateSyncFileDownloadResult::StateHeader { header_length, header, manifest })
     })
 }
 
@@ -1155,6 +1181,7 @@ fn request_header_from_external_storage(
     epoch_height: EpochHeight,
     chain_id: &str,
     external: ExternalConnection,
+    trusted_dump_producers: Arc<Vec<PublicKey>>,
     state_parts_future_spawner: &dyn FutureSpawner,
     state_parts_mpsc_tx: Sender<StateSyncGetFileResult>,
 ) {
@@ -1172,10 +1199,17 @@ fn request_header_from_external_storage(
         shard_id,
         &StateFileType::StateHeader,
     );
+    let manifest_location = external_storage_location(
+        chain_id,
+        epoch_id,
+        epoch_height,
+        shard_id,
+        &StateFileType::StateManifest,
+    );
     state_parts_future_spawner.spawn(
         "download_header_from_external_storage",
         async move {
-            let result = download_header_from_external_storage(shard_id, sync_hash, location, external).await;
+            let result = download_header_from_external_storage(shard_id, sync_hash, location, manifest_location, external, trusted_dump_producers).await;
             match state_parts_mpsc_tx.send(StateSyncGetFileResult {
                 sync_hash,
                 shard_id,
@@ -1199,6 +1233,7 @@ async fn download_and_store_part_from_external_storage(
     sync_hash: CryptoHash,
     state_root: StateRoot,
     external: ExternalConnection,
+    expected_part_hash: Option<CryptoHash>,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
 ) -> Result<StateSyncFileDownloadResult, String> {
     external
@@ -1207,7 +1242,9 @@ async fn download_and_store_part_from_external_storage(
     .map_err(|err| err.to_string())
     .and_then(|data|  {
         info!(target: "sync", ?shard_id, ?part_id, "downloaded state part");
-        if runtime_adapter.validate_state_part(&state_root, part_id, &data) {
+        if expected_part_hash.map_or(false, |expected_hash| hash(&data) != expected_hash) {
+            Err(format!("State part doesn't match the manifest. part_id={part_id:?}, shard_id={shard_id}"))
+        } else if runtime_adapter.validate_state_part(&state_root, part_id, &data) {
             let mut store_update = runtime_adapter.store().store_update();
             borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id.idx))
             .and_then(|key| {
@@ -1235,6 +1272,8 @@ fn request_part_from_external_storage(
     state_root: StateRoot,
     semaphore: Arc<Semaphore>,
     external: ExternalConnection,
+    trusted_dump_producers: Arc<Vec<PublicKey>>,
+    manifest: Option<Arc<StateDumpManifest>>,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
     state_parts_future_spawner: &dyn FutureSpawner,
     state_parts_mpsc_tx: Sender<StateSyncGetFileResult>,
@@ -1253,6 +1292,13 @@ fn request_part_from_external_storage(
         shard_id,
         &StateFileType::StatePart { part_id, num_parts },
     );
+    let manifest_location = external_storage_location(
+        chain_id,
+        epoch_id,
+        epoch_height,
+        shard_id,
+        &StateFileType::StateManifest,
+    );
 
     match semaphore.try_acquire_owned() {
         Ok(permit) => {
@@ -1261,16 +1307,39 @@ fn request_part_from_external_storage(
                 async move {
                     let file_type = StateFileType::StatePart { part_id, num_parts };
                     let part_id = PartId{ idx: part_id, total: num_parts };
-                    let result = download_and_store_part_from_external_storage(
-                        part_id,
-                        &file_type,
-                        location,
-                        shard_id,
-                        sync_hash,
-                        state_root,
-                        external,
-                        runtime_adapter)
-                        .await;
+                    // The manifest is only missing if the header was stored
+                    // before a restart, in which case it is downloaded again.
+                    let expected_part_hash = match manifest {
+                        _ if trusted_dump_producers.is_empty() => Ok(None),
+                        Some(manifest) => Ok(Some(manifest.part_hashes[part_id.idx as usize])),
+                        None => download_trusted_manifest(
+                            shard_id,
+                            sync_hash,
+                            &manifest_location,
+                            &trusted_dump_producers,
+                            &external,
+                        )
+                        .await
+                        .and_then(|manifest| {
+                            manifest.part_hashes.get(part_id.idx as usize).copied().map(Some).ok_or_else(|| {
+                                format!("State dump manifest for shard {shard_id} has no part {}", part_id.idx)
+                            })
+                        }),
+                    };
+                    let result = match expected_part_hash {
+                        Ok(expected_part_hash) => download_and_store_part_from_external_storage(
+                            part_id,
+                            &file_type,
+                            location,
+                            shard_id,
+                            sync_hash,
+                            state_root,
+                            external,
+                            expected_part_hash,
+                            runtime_adapter)
+                            .await,
+                        Err(err) => Err(err),
+                    };
 
                     match state_parts_mpsc_tx.send(StateSyncGetFileResult {
                         sync_hash,
@@ -1331,26 +1400,6 @@ fn request_part_from_peers(
     );
 }
 
-fn sent_request_part(
-    clock: Clock,
-    peer_id: PeerId,
-    part_id: u64,
-    shard_id: ShardId,
-    sync_hash: CryptoHash,
-    last_part_id_requested: &mut HashMap<(PeerId, ShardId), PendingRequestStatus>,
-    requested_target: &mut lru::LruCache<(u64, CryptoHash), PeerId>,
-    timeout: Duration,
-) {
-    // FIXME: something is wrong - the index should have a shard_id too.
-    requested_target.put((part_id, sync_hash), peer_id.clone());
-    last_part_id_requested
-        .entry((peer_id, shard_id))
-        .and_modify(|pending_request| {
-            pending_request.missing_parts += 1;
-        })
-        .or_insert_with(|| PendingRequestStatus::new(clock, timeout));
-}
-
 /// Works around how data requests to external storage are done.
 /// This function investigates if the response is valid and updates `done` and `error` appropriately.
 /// If the response is successful, then the downloaded state file was written to the DB.
@@ -1383,68 +1432,6 @@ fn process_download_response(
     }
 }
 
-/// Create an abstract collection of elements to be shuffled.
-/// Each element will appear in the shuffled output exactly `limit` times.
-/// Use it as an iterator to access the shuffled collection.
-///
-/// ```rust,ignore
-/// let sampler = SamplerLimited::new(vec![1, 2, 3], 2);
-///
-/// let res = sampler.collect::<Vec<_>>();
-///
-/// assert!(res.len() == 6);
-/// assert!(res.iter().filter(|v| v == 1).count() == 2);
-/// assert!(res.iter().filter(|v| v == 2).count() == 2);
-/// assert!(res.iter().filter(|v| v == 3).count() == 2);
-/// ```
-///
-/// Out of the 90 possible values of `res` in the code above on of them is:
-///
-/// ```
-/// vec![1, 2, 1, 3, 3, 2];
-/// ```
-struct SamplerLimited<T> {
-    data: Vec<T>,
-    limit: Vec<u64>,
-}
-
-impl<T> SamplerLimited<T> {
-    fn new(data: Vec<T>, limit: u64) -> Self {
-        if limit == 0 {
-            Self { data: vec![], limit: vec![] }
-        } else {
-            let len = data.len();
-            Self { data, limit: vec![limit; len] }
-        }
-    }
-}
-
-impl<T: Clone> Iterator for SamplerLimited<T> {
-    type Item = T;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.limit.is_empty() {
-            None
-        } else {
-            let len = self.limit.len();
-            let ix = thread_rng().gen_range(0..len);
-            self.limit[ix] -= 1;
-
-            if self.limit[ix] == 0 {
-                if ix + 1 != len {
-                    self.limit[ix] = self.limit[len - 1];
-                    self.data.swap(ix, len - 1);
-                }
-
-                self.limit.pop();
-                self.data.pop()
-            } else {
-                Some(self.data[ix].clone())
-            }
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
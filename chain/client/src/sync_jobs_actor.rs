@@ -10,6 +10,7 @@ use near_performance_metrics_macros::perf;
 use near_primitives::state_part::PartId;
 use near_primitives::state_sync::StatePartKey;
 use near_primitives::types::ShardId;
+use near_store::bulk_load::BulkLoadPhase;
 use near_store::DBCol;
 
 // Set the mailbox capacity for the SyncJobsActor from default 16 to 100.
@@ -71,6 +72,7 @@ impl SyncJobsActor {
         let _span: tracing::span::EnteredSpan =
             tracing::debug_span!(target: "sync", "apply_parts").entered();
         let store = msg.runtime_adapter.store();
+        let _bulk_load = store.bulk_load(BulkLoadPhase::StateSync);
 
         let shard_id = msg.shard_uid.shard_id as ShardId;
         for part_id in 0..msg.num_parts {
@@ -163,6 +163,7 @@ pub fn setup(
         signer.clone(),
         epoch_manager.clone(),
         store.clone(),
+        false,
     ));
     let partial_witness_adapter = partial_witness_addr.with_auto_span_context();
 
@@ -449,6 +450,7 @@ fn process_peer_manager_message_default(
                         }),
                         tracked_shards: vec![0, 1, 2, 3],
                         archival: true,
+                        rpc_url: None,
                     },
                 },
                 received_bytes_per_sec: 0,
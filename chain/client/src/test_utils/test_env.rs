@@ -547,7 +547,7 @@ impl TestEnv {
                         last_block.header().prev_hash(),
                         last_block.header().hash(),
                         last_block.header().epoch_id(),
-                        &QueryRequest::ViewAccount { account_id },
+                        &QueryRequest::ViewAccount { account_id, include_proof: false },
                     )
                     .unwrap();
                 match response.kind {
@@ -597,6 +597,7 @@ impl TestEnv {
                     account_id,
                     prefix: vec![].into(),
                     include_proof: false,
+                    options: Default::default(),
                 },
             )
             .unwrap();
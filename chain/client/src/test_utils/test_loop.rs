@@ -78,7 +78,7 @@ where
     fn query_balance(&self, account_id: &AccountId) -> Balance {
         let response = self.runtime_query(
             account_id,
-            QueryRequest::ViewAccount { account_id: account_id.clone() },
+            QueryRequest::ViewAccount { account_id: account_id.clone(), include_proof: false },
         );
         if let QueryResponseKind::ViewAccount(account_view) = response.kind {
             account_view.amount
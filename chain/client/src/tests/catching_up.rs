@@ -335,6 +335,7 @@ fn test_catchup_receipts_sync_common(wait_till: u64, send: u64, sync_hold: bool)
                                                 BlockReference::latest(),
                                                 QueryRequest::ViewAccount {
                                                     account_id: account_to.clone(),
+                                                    include_proof: false,
                                                 },
                                             )
                                             .with_span_context(),
@@ -532,6 +533,7 @@ fn test_catchup_random_single_part_sync_common(skip_15: bool, non_zero: bool, he
                                                     BlockReference::latest(),
                                                     QueryRequest::ViewAccount {
                                                         account_id: validators[j].clone(),
+                                                        include_proof: false,
                                                     },
                                                 )
                                                 .with_span_context(),
@@ -71,7 +71,10 @@ fn test_keyvalue_runtime_balances() {
             let actor = connectors_[i].view_client_actor.send(
                 Query::new(
                     BlockReference::latest(),
-                    QueryRequest::ViewAccount { account_id: validators[i].clone() },
+                    QueryRequest::ViewAccount {
+                        account_id: validators[i].clone(),
+                        include_proof: false,
+                    },
                 )
                 .with_span_context(),
             );
@@ -195,7 +198,10 @@ fn test_cross_shard_tx_callback(
             let actor = actor.send(
                 Query::new(
                     BlockReference::latest(),
-                    QueryRequest::ViewAccount { account_id: account_id.clone() },
+                    QueryRequest::ViewAccount {
+                        account_id: account_id.clone(),
+                        include_proof: false,
+                    },
                 )
                 .with_span_context(),
             );
@@ -293,7 +299,10 @@ fn test_cross_shard_tx_callback(
                     let actor = actor.send(
                         Query::new(
                             BlockReference::latest(),
-                            QueryRequest::ViewAccount { account_id: validators[i].clone() },
+                            QueryRequest::ViewAccount {
+                                account_id: validators[i].clone(),
+                                include_proof: false,
+                            },
                         )
                         .with_span_context(),
                     );
@@ -347,7 +356,10 @@ fn test_cross_shard_tx_callback(
             let actor = actor.send(
                 Query::new(
                     BlockReference::latest(),
-                    QueryRequest::ViewAccount { account_id: account_id.clone() },
+                    QueryRequest::ViewAccount {
+                        account_id: account_id.clone(),
+                        include_proof: false,
+                    },
                 )
                 .with_span_context(),
             );
@@ -504,7 +516,10 @@ fn test_cross_shard_tx_common(
             let actor = actor.send(
                 Query::new(
                     BlockReference::latest(),
-                    QueryRequest::ViewAccount { account_id: validators[i].clone() },
+                    QueryRequest::ViewAccount {
+                        account_id: validators[i].clone(),
+                        include_proof: false,
+                    },
                 )
                 .with_span_context(),
             );
@@ -40,7 +40,10 @@ fn query_client() {
         let actor = actor_handles.view_client_actor.send(
             Query::new(
                 BlockReference::latest(),
-                QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+                QueryRequest::ViewAccount {
+                    account_id: "test".parse().unwrap(),
+                    include_proof: false,
+                },
             )
             .with_span_context(),
         );
@@ -2,8 +2,9 @@
 //! Useful for querying from RPC.
 
 use crate::{
-    metrics, sync, GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock, GetShardChunk,
-    GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
+    metrics, sync, GetChunk, GetChunkValidators, GetExecutionOutcomeResponse,
+    GetNextLightClientBlock, GetShardChunk, GetStateChanges, GetStateChangesInBlock,
+    GetValidatorInfo, GetValidatorOrdered,
 };
 use actix::{Addr, SyncArbiter};
 use near_async::actix_wrapper::SyncActixWrapper;
@@ -11,19 +12,21 @@ use near_async::messaging::{Actor, CanSend, Handler};
 use near_async::time::{Clock, Duration, Instant};
 use near_chain::types::{RuntimeAdapter, Tip};
 use near_chain::{
-    get_epoch_block_producers_view, Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode,
+    get_chunk_validators_view, get_epoch_block_producers_view, Chain, ChainGenesis,
+    ChainStoreAccess, DoomslugThresholdMode, LightClientCheckpoint,
 };
 use near_chain_configs::{ClientConfig, MutableValidatorSigner, ProtocolConfigView};
 use near_chain_primitives::error::EpochErrorResultToChainError;
 use near_client_primitives::types::{
-    Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
-    GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome, GetExecutionOutcomeError,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetMaintenanceWindows,
-    GetMaintenanceWindowsError, GetNextLightClientBlockError, GetProtocolConfig,
-    GetProtocolConfigError, GetReceipt, GetReceiptError, GetSplitStorageInfo,
+    Error, GetBlock, GetBlockError, GetBlockHeightStatus, GetBlockHeightStatusError, GetBlockProof,
+    GetBlockProofError, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunkError,
+    GetEpochShardStats, GetEpochShardStatsError, GetExecutionOutcome, GetExecutionOutcomeError,
+    GetExecutionOutcomesForBlock, GetForkInfo, GetForkInfoError, GetGasPrice, GetGasPriceError,
+    GetMaintenanceWindows, GetMaintenanceWindowsError, GetNextLightClientBlockError,
+    GetProtocolConfig, GetProtocolConfigError, GetReceipt, GetReceiptError, GetSplitStorageInfo,
     GetSplitStorageInfoError, GetStateChangesError, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
-    TxStatus, TxStatusError,
+    GetStateChangesWithCauseInBlockForTrackedShards, GetStateWitnesses, GetStateWitnessesError,
+    GetValidatorInfoError, Query, QueryError, TxStatus, TxStatusError,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
@@ -48,19 +51,21 @@ use near_primitives::state_sync::{
 };
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockId, BlockReference, EpochReference, Finality, MaybeBlockId,
-    ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
+    AccountId, BlockHeight, BlockId, BlockReference, EpochReference, EpochShardStats, Finality,
+    MaybeBlockId, ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
 };
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ExecutionStatusView,
-    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView,
-    LightClientBlockView, MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView,
-    SignedTransactionView, SplitStorageInfoView, StateChangesKindsView, StateChangesView,
-    TxExecutionStatus, TxStatusView,
+    BlockHeightStatusView, BlockReceiptKind, BlockReceiptView, BlockReceiptsView, BlockView,
+    ChunkValidatorsView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
+    ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum,
+    FinalExecutionStatus, ForkInfoView, GasPriceView, LightClientBlockView, MaintenanceWindowsView,
+    QueryRequest, QueryResponse, ReceiptView, SignedTransactionView, SplitStorageInfoView,
+    StateChangesKindsView, StateChangesView, StateWitnessView, TxExecutionStatus, TxStatusView,
 };
 use near_store::flat::{FlatStorageReadyStatus, FlatStorageStatus};
+use near_store::io_attribution::IoSubsystem;
 use near_store::{DBCol, COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY};
 use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
@@ -132,6 +137,7 @@ impl ViewClientActorInner {
         adv: crate::adversarial::Controls,
     ) -> Addr<ViewClientActor> {
         SyncArbiter::start(config.view_client_threads, move || {
+            IoSubsystem::ViewClient.set_for_current_thread();
             let view_client_actor = ViewClientActorInner::new(
                 clock.clone(),
                 validator.clone(),
@@ -904,6 +910,22 @@ impl Handler<GetValidatorOrdered> for ViewClientActorInner {
         })?)
     }
 }
+
+impl Handler<GetChunkValidators> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetChunkValidators,
+    ) -> Result<ChunkValidatorsView, GetValidatorInfoError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetChunkValidators"])
+            .start_timer();
+        Ok(self
+            .maybe_block_id_to_block_header(msg.block_id)
+            .and_then(|header| get_chunk_validators_view(&header, self.epoch_manager.as_ref()))?)
+    }
+}
 /// Returns a list of change kinds per account in a store for a given block.
 impl Handler<GetStateChangesInBlock> for ViewClientActorInner {
     #[perf]
@@ -1019,10 +1041,20 @@ impl Handler<GetNextLightClientBlock> for ViewClientActorInner {
         let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
             .with_label_values(&["GetNextLightClientBlock"])
             .start_timer();
-        let last_block_header = self.chain.get_block_header(&msg.last_block_hash)?;
-        let last_epoch_id = *last_block_header.epoch_id();
-        let last_next_epoch_id = *last_block_header.next_epoch_id();
-        let last_height = last_block_header.height();
+        let last_block = match self.chain.get_block_header(&msg.last_block_hash) {
+            Ok(header) => LightClientCheckpoint::from(&header),
+            // The header may have been pruned, in which case the light client
+            // is expected to know the last block of an epoch.
+            Err(near_chain::Error::DBNotFoundErr(_)) => {
+                self.chain.chain_store().get_light_client_checkpoint(&msg.last_block_hash)?
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let LightClientCheckpoint {
+            epoch_id: last_epoch_id,
+            next_epoch_id: last_next_epoch_id,
+            height: last_height,
+        } = last_block;
         let head = self.chain.head()?;
 
         if last_epoch_id == head.epoch_id || last_next_epoch_id == head.epoch_id {
@@ -1174,6 +1206,93 @@ impl Handler<GetReceipt> for ViewClientActorInner {
     }
 }
 
+impl Handler<GetBlockReceipts> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetBlockReceipts,
+    ) -> Result<BlockReceiptsView, GetBlockReceiptsError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetBlockReceipts"])
+            .start_timer();
+        let block = self
+            .get_block_by_reference(&msg.block_reference)?
+            .ok_or(GetBlockReceiptsError::NotSyncedYet)?;
+        let block_hash = *block.hash();
+        let block_height = block.header().height();
+        let matches_filter = |receipt: &Receipt| {
+            msg.receiver_id.as_ref().map_or(true, |id| receipt.receiver_id() == id)
+                && msg.predecessor_id.as_ref().map_or(true, |id| receipt.predecessor_id() == id)
+        };
+
+        let mut receipts = vec![];
+        for chunk_header in block.chunks().iter() {
+            if !chunk_header.is_new_chunk(block_height) {
+                continue;
+            }
+            let shard_id = chunk_header.shard_id();
+            let chunk = self.chain.get_chunk(&chunk_header.chunk_hash())?;
+            for receipt in chunk.prev_outgoing_receipts() {
+                if matches_filter(receipt) {
+                    receipts.push(BlockReceiptView {
+                        shard_id,
+                        kind: BlockReceiptKind::Included,
+                        receipt: receipt.clone().into(),
+                    });
+                }
+            }
+            // Outcomes are stored for transactions and receipts alike. Only the
+            // ids of receipts resolve to an entry in the receipts column.
+            let outcome_ids = self
+                .chain
+                .chain_store()
+                .get_outcomes_by_block_hash_and_shard_id(&block_hash, shard_id)?;
+            for outcome_id in outcome_ids {
+                let Some(receipt) = self.chain.chain_store().get_receipt(&outcome_id)? else {
+                    continue;
+                };
+                if matches_filter(&receipt) {
+                    receipts.push(BlockReceiptView {
+                        shard_id,
+                        kind: BlockReceiptKind::Executed,
+                        receipt: Receipt::clone(&receipt).into(),
+                    });
+                }
+            }
+        }
+        Ok(BlockReceiptsView { block_hash, block_height, receipts })
+    }
+}
+
+impl Handler<GetStateWitnesses> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetStateWitnesses,
+    ) -> Result<Vec<StateWitnessView>, GetStateWitnessesError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetStateWitnesses"])
+            .start_timer();
+        let witnesses = self.chain.chain_store().get_latest_witnesses(
+            Some(msg.height),
+            Some(msg.shard_id),
+            msg.epoch_id,
+        )?;
+        if witnesses.is_empty() {
+            return Err(GetStateWitnessesError::UnknownStateWitness {
+                height: msg.height,
+                shard_id: msg.shard_id,
+            });
+        }
+        Ok(witnesses
+            .into_iter()
+            .map(|witness| StateWitnessView::new(witness, msg.decode))
+            .collect())
+    }
+}
+
 impl Handler<GetBlockProof> for ViewClientActorInner {
     #[perf]
     fn handle(&mut self, msg: GetBlockProof) -> Result<GetBlockProofResponse, GetBlockProofError> {
@@ -1311,11 +1430,14 @@ impl Handler<BlockHeadersRequest> for ViewClientActorInner {
         let BlockHeadersRequest(hashes) = msg;
 
         if self.adv.disable_header_sync() {
-            None
-        } else if let Ok(headers) = self.retrieve_headers(hashes) {
-            Some(headers)
-        } else {
-            None
+            return None;
+        }
+        match self.retrieve_headers(hashes) {
+            Ok(headers) => Some(headers),
+            Err(err) => {
+                tracing::debug!(target: "client", ?err, "Declined block headers request");
+                None
+            }
         }
     }
 }
@@ -1327,6 +1449,7 @@ impl Handler<StateRequestHeader> for ViewClientActorInner {
         let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
             .with_label_values(&["StateRequestHeader"])
             .start_timer();
+        let _io_subsystem = IoSubsystem::StateSyncServing.enter();
         let StateRequestHeader { shard_id, sync_hash } = msg;
         if self.throttle_state_sync_request() {
             tracing::debug!(target: "sync", ?sync_hash, "Throttle state sync requests");
@@ -1405,6 +1528,7 @@ impl Handler<StateRequestPart> for ViewClientActorInner {
         let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
             .with_label_values(&["StateRequestPart"])
             .start_timer();
+        let _io_subsystem = IoSubsystem::StateSyncServing.enter();
         let StateRequestPart { shard_id, sync_hash, part_id } = msg;
         if self.throttle_state_sync_request() {
             tracing::debug!(target: "sync", ?sync_hash, "Throttle state sync requests");
@@ -1549,6 +1673,41 @@ impl Handler<GetMaintenanceWindows> for ViewClientActorInner {
     }
 }
 
+impl Handler<GetEpochShardStats> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetEpochShardStats,
+    ) -> Result<Vec<EpochShardStats>, GetEpochShardStatsError> {
+        tracing::debug!(target: "client", ?msg);
+        self.chain
+            .chain_store()
+            .get_all_epoch_shard_stats(&msg.epoch_id)
+            .map_err(|err| GetEpochShardStatsError::IOError(err.to_string()))
+    }
+}
+
+impl Handler<GetBlockHeightStatus> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetBlockHeightStatus,
+    ) -> Result<BlockHeightStatusView, GetBlockHeightStatusError> {
+        tracing::debug!(target: "client", ?msg);
+        Ok(self.chain.chain_store().get_block_height_status(msg.height)?)
+    }
+}
+
+impl Handler<GetForkInfo> for ViewClientActorInner {
+    #[perf]
+    fn handle(&mut self, msg: GetForkInfo) -> Result<ForkInfoView, GetForkInfoError> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer =
+            metrics::VIEW_CLIENT_MESSAGE_TIME.with_label_values(&["GetForkInfo"]).start_timer();
+        Ok(self.chain.get_fork_info(msg.num_heights)?)
+    }
+}
+
 impl Handler<GetSplitStorageInfo> for ViewClientActorInner {
     fn handle(
         &mut self,
@@ -0,0 +1,166 @@
+//! Lightweight live data of the accounts listed in `watched_accounts` of the
+//! client config, e.g. for exchanges which want to detect deposits without
+//! tracking whole shards.
+//!
+//! The data is collected from the chunk state witnesses which pass through the
+//! node, so only chunk validators collect it, and only for the chunks they
+//! validate. Witnesses carry the receipts applied by the chunk, the transactions
+//! it includes and the state needed to validate them, i.e. the accounts of the
+//! senders.
+
+use near_primitives::stateless_validation::state_witness::ChunkStateWitness;
+use near_primitives::types::AccountId;
+use near_primitives::views::{
+    WatchedAccountStateView, WatchedAccountView, WatchedReceiptView, WatchedTransactionView,
+};
+use near_store::{get_account, PartialStorage, Trie};
+use std::collections::{HashMap, VecDeque};
+
+/// Number of the most recent transactions and receipts kept for each account.
+const MAX_RECENT_ITEMS: usize = 100;
+
+pub struct WatchedAccounts {
+    accounts: HashMap<AccountId, WatchedAccount>,
+}
+
+#[derive(Default)]
+struct WatchedAccount {
+    state: Option<WatchedAccountStateView>,
+    transactions: VecDeque<WatchedTransactionView>,
+    receipts: VecDeque<WatchedReceiptView>,
+}
+
+impl WatchedAccounts {
+    pub fn new(account_ids: &[AccountId]) -> Self {
+        let accounts = account_ids
+            .iter()
+            .map(|account_id| (account_id.clone(), WatchedAccount::default()))
+            .collect();
+        Self { accounts }
+    }
+
+    /// Returns the data of the given watched accounts, or of all of them if
+    /// none are given. Accounts which aren't watched are skipped.
+    pub fn get(&self, account_ids: &[AccountId]) -> Vec<WatchedAccountView> {
+        let mut views: Vec<_> = self
+            .accounts
+            .iter()
+            .filter(|(account_id, _)| account_ids.is_empty() || account_ids.contains(account_id))
+            .map(|(account_id, account)| WatchedAccountView {
+                account_id: account_id.clone(),
+                state: account.state.clone(),
+                transactions: account.transactions.iter().cloned().collect(),
+                receipts: account.receipts.iter().cloned().collect(),
+            })
+            .collect();
+        views.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        views
+    }
+
+    pub fn observe_state_witness(&mut self, witness: &ChunkStateWitness) {
+        if self.accounts.is_empty() {
+            return;
+        }
+        let height_created = witness.chunk_header.height_created();
+
+        for proof in witness.source_receipt_proofs.values() {
+            for receipt in &proof.0 {
+                let Some(account) = self.accounts.get_mut(receipt.receiver_id()) else {
+                    continue;
+                };
+                // The same receipt may be carried by the witnesses of several
+                // chunks.
+                if account.receipts.iter().any(|r| &r.receipt.receipt_id == receipt.receipt_id()) {
+                    continue;
+                }
+                let receipt =
+                    WatchedReceiptView { height_created, receipt: receipt.clone().into() };
+                push_recent(&mut account.receipts, receipt);
+            }
+        }
+
+        let mut senders = vec![];
+        for transaction in &witness.new_transactions {
+            let signer_id = transaction.transaction.signer_id();
+            let Some(account) = self.accounts.get_mut(signer_id) else {
+                continue;
+            };
+            let transaction =
+                WatchedTransactionView { height_created, transaction: transaction.clone().into() };
+            push_recent(&mut account.transactions, transaction);
+            senders.push(signer_id.clone());
+        }
+        if senders.is_empty() {
+            return;
+        }
+        senders.sort();
+        senders.dedup();
+
+        // The state used to validate the new transactions is the state before
+        // applying the chunk.
+        let trie = Trie::from_recorded_storage(
+            PartialStorage { nodes: witness.new_transactions_validation_state.clone() },
+            witness.chunk_header.prev_state_root(),
+            true,
+        );
+        for account_id in senders {
+            match get_account(&trie, &account_id) {
+                Ok(Some(account)) => {
+                    let state = WatchedAccountStateView { height_created, account: account.into() };
+                    self.accounts.get_mut(&account_id).unwrap().state = Some(state);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::debug!(target: "client", %account_id, ?err, "Watched account is missing from the state witness");
+                }
+            }
+        }
+    }
+}
+
+fn push_recent<T>(items: &mut VecDeque<T>, item: T) {
+    if items.len() >= MAX_RECENT_ITEMS {
+        items.pop_front();
+    }
+    items.push_back(item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchedAccounts;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::receipt::{Receipt, ReceiptPriority};
+    use near_primitives::sharding::{ReceiptProof, ShardProof};
+    use near_primitives::stateless_validation::state_witness::ChunkStateWitness;
+    use near_primitives::types::AccountId;
+
+    fn witness_with_receipts(height: u64, receipts: Vec<Receipt>) -> ChunkStateWitness {
+        let mut witness = ChunkStateWitness::new_dummy(height, 0, CryptoHash::default());
+        let proof = ShardProof { from_shard_id: 0, to_shard_id: 0, proof: vec![] };
+        witness.source_receipt_proofs.insert(Default::default(), ReceiptProof(receipts, proof));
+        witness
+    }
+
+    #[test]
+    fn test_observe_receipts() {
+        let alice: AccountId = "alice.near".parse().unwrap();
+        let bob: AccountId = "bob.near".parse().unwrap();
+        let mut watched_accounts = WatchedAccounts::new(&[alice.clone()]);
+
+        let to_alice = Receipt::new_balance_refund(&alice, 10, ReceiptPriority::NoPriority);
+        let to_bob = Receipt::new_balance_refund(&bob, 20, ReceiptPriority::NoPriority);
+        watched_accounts
+            .observe_state_witness(&witness_with_receipts(1, vec![to_alice.clone(), to_bob]));
+        // The receipt is seen again in the witness of the next chunk.
+        watched_accounts.observe_state_witness(&witness_with_receipts(2, vec![to_alice.clone()]));
+
+        let views = watched_accounts.get(&[]);
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].account_id, alice);
+        assert_eq!(views[0].state, None);
+        assert_eq!(views[0].receipts.len(), 1);
+        assert_eq!(views[0].receipts[0].height_created, 1);
+        assert_eq!(&views[0].receipts[0].receipt.receipt_id, to_alice.receipt_id());
+        assert!(watched_accounts.get(&[bob]).is_empty());
+    }
+}
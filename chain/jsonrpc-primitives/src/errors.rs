@@ -21,6 +21,220 @@ pub struct RpcError {
     /// Deprecated please use the `error_struct` instead
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<Value>,
+    /// Stable machine-readable classification of the error. Clients should
+    /// use it instead of matching on `error_struct` cause names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_info: Option<RpcErrorInfo>,
+}
+
+/// Machine-readable description of an RPC error.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RpcErrorInfo {
+    /// Stable numeric code, see [`RpcErrorCode`].
+    pub code: u32,
+    pub category: RpcErrorCategory,
+    /// Whether the same request may succeed if it is retried later, possibly
+    /// against another node.
+    pub retryable: bool,
+}
+
+impl From<RpcErrorCode> for RpcErrorInfo {
+    fn from(code: RpcErrorCode) -> Self {
+        Self { code: code as u32, category: code.category(), retryable: code.is_retryable() }
+    }
+}
+
+/// Broad class of an RPC error. Lets clients handle errors they don't know
+/// specifically.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcErrorCategory {
+    /// The request is malformed and was not handled.
+    RequestValidation,
+    /// The requested entity does not exist, or is not known to the node.
+    NotFound,
+    /// The node is currently not able to serve the request, e.g. because it
+    /// is syncing or doesn't track the required shard.
+    Unavailable,
+    /// The request was handled, but its content is invalid.
+    InvalidRequest,
+    /// The request was handled, but the execution it triggered failed.
+    ExecutionFailure,
+    /// The node didn't manage to handle the request in time.
+    Timeout,
+    /// The node failed to handle the request for internal reasons.
+    Internal,
+}
+
+/// Stable numeric codes of all RPC errors.
+///
+/// The values are part of the public API: they must never be changed or
+/// reused. New errors get new values in the range of their category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RpcErrorCode {
+    ParseError = 1000,
+    MethodNotFound = 1001,
+    InvalidParams = 1002,
+
+    UnknownBlock = 2000,
+    UnknownChunk = 2001,
+    UnknownEpoch = 2002,
+    UnknownTransaction = 2003,
+    UnknownReceipt = 2004,
+    UnknownTransactionOrReceipt = 2005,
+    UnknownAccount = 2006,
+    UnknownAccessKey = 2007,
+    NoContractCode = 2008,
+    GarbageCollectedBlock = 2009,
+    UnknownStateWitness = 2010,
+
+    NotSyncedYet = 3000,
+    NodeIsSyncing = 3001,
+    NoNewBlocks = 3002,
+    UnavailableShard = 3003,
+    DoesNotTrackShard = 3004,
+    RequestRouted = 3005,
+    ValidatorInfoUnavailable = 3006,
+    NotConfirmed = 3007,
+    EpochOutOfBounds = 3008,
+
+    InvalidTransaction = 4000,
+    InvalidAccount = 4001,
+    InvalidShardId = 4002,
+    TooLargeContractState = 4003,
+    InconsistentState = 4004,
+
+    ContractExecutionError = 5000,
+    TxExecutionError = 5001,
+
+    TimeoutError = 6000,
+
+    InternalError = 9000,
+    Closed = 9001,
+    /// The error has no dedicated code. Seeing it in a response is a bug.
+    Unclassified = 9999,
+}
+
+impl RpcErrorCode {
+    /// Maps a cause name, i.e. the `name` field of an error serialized into
+    /// `error_struct`, to its code. The same cause name means the same thing
+    /// regardless of the method that returned it.
+    pub fn from_cause_name(name: &str) -> Self {
+        match name {
+            "PARSE_ERROR" => Self::ParseError,
+            "METHOD_NOT_FOUND" => Self::MethodNotFound,
+            "UNKNOWN_BLOCK" => Self::UnknownBlock,
+            "UNKNOWN_CHUNK" => Self::UnknownChunk,
+            "UNKNOWN_EPOCH" => Self::UnknownEpoch,
+            "UNKNOWN_TRANSACTION" => Self::UnknownTransaction,
+            "UNKNOWN_RECEIPT" => Self::UnknownReceipt,
+            "UNKNOWN_TRANSACTION_OR_RECEIPT" => Self::UnknownTransactionOrReceipt,
+            "UNKNOWN_ACCOUNT" => Self::UnknownAccount,
+            "UNKNOWN_ACCESS_KEY" => Self::UnknownAccessKey,
+            "NO_CONTRACT_CODE" => Self::NoContractCode,
+            "GARBAGE_COLLECTED_BLOCK" => Self::GarbageCollectedBlock,
+            "UNKNOWN_STATE_WITNESS" => Self::UnknownStateWitness,
+            "NOT_SYNCED_YET" | "NO_SYNCED_BLOCKS" => Self::NotSyncedYet,
+            "NODE_IS_SYNCING" => Self::NodeIsSyncing,
+            "NO_NEW_BLOCKS" => Self::NoNewBlocks,
+            "UNAVAILABLE_SHARD" => Self::UnavailableShard,
+            "DOES_NOT_TRACK_SHARD" => Self::DoesNotTrackShard,
+            "REQUEST_ROUTED" => Self::RequestRouted,
+            "VALIDATOR_INFO_UNAVAILABLE" => Self::ValidatorInfoUnavailable,
+            "NOT_CONFIRMED" => Self::NotConfirmed,
+            "EPOCH_OUT_OF_BOUNDS" => Self::EpochOutOfBounds,
+            "INVALID_TRANSACTION" => Self::InvalidTransaction,
+            "INVALID_ACCOUNT" => Self::InvalidAccount,
+            "INVALID_SHARD_ID" => Self::InvalidShardId,
+            "TOO_LARGE_CONTRACT_STATE" => Self::TooLargeContractState,
+            "INCONSISTENT_STATE" => Self::InconsistentState,
+            "CONTRACT_EXECUTION_ERROR" => Self::ContractExecutionError,
+            "TIMEOUT_ERROR" => Self::TimeoutError,
+            "INTERNAL_ERROR" => Self::InternalError,
+            _ => Self::Unclassified,
+        }
+    }
+
+    pub fn category(self) -> RpcErrorCategory {
+        match self {
+            Self::ParseError | Self::MethodNotFound | Self::InvalidParams => {
+                RpcErrorCategory::RequestValidation
+            }
+            Self::UnknownBlock
+            | Self::UnknownChunk
+            | Self::UnknownEpoch
+            | Self::UnknownTransaction
+            | Self::UnknownReceipt
+            | Self::UnknownTransactionOrReceipt
+            | Self::UnknownAccount
+            | Self::UnknownAccessKey
+            | Self::NoContractCode
+            | Self::GarbageCollectedBlock
+            | Self::UnknownStateWitness => RpcErrorCategory::NotFound,
+            Self::NotSyncedYet
+            | Self::NodeIsSyncing
+            | Self::NoNewBlocks
+            | Self::UnavailableShard
+            | Self::DoesNotTrackShard
+            | Self::RequestRouted
+            | Self::ValidatorInfoUnavailable
+            | Self::NotConfirmed
+            | Self::EpochOutOfBounds => RpcErrorCategory::Unavailable,
+            Self::InvalidTransaction
+            | Self::InvalidAccount
+            | Self::InvalidShardId
+            | Self::TooLargeContractState
+            | Self::InconsistentState => RpcErrorCategory::InvalidRequest,
+            Self::ContractExecutionError | Self::TxExecutionError => {
+                RpcErrorCategory::ExecutionFailure
+            }
+            Self::TimeoutError => RpcErrorCategory::Timeout,
+            Self::InternalError | Self::Closed | Self::Unclassified => RpcErrorCategory::Internal,
+        }
+    }
+
+    /// Whether retrying the same request later may succeed. Errors which
+    /// depend on the node not having caught up yet (e.g. a block which is not
+    /// final yet) are retryable, errors describing the request are not.
+    pub fn is_retryable(self) -> bool {
+        match self {
+            Self::UnknownTransaction
+            | Self::UnknownTransactionOrReceipt
+            | Self::NotSyncedYet
+            | Self::NodeIsSyncing
+            | Self::NoNewBlocks
+            | Self::UnavailableShard
+            | Self::DoesNotTrackShard
+            | Self::RequestRouted
+            | Self::ValidatorInfoUnavailable
+            | Self::NotConfirmed
+            | Self::TimeoutError
+            | Self::InternalError
+            | Self::Closed => true,
+            Self::ParseError
+            | Self::MethodNotFound
+            | Self::InvalidParams
+            | Self::UnknownBlock
+            | Self::UnknownChunk
+            | Self::UnknownEpoch
+            | Self::UnknownReceipt
+            | Self::UnknownAccount
+            | Self::UnknownAccessKey
+            | Self::NoContractCode
+            | Self::GarbageCollectedBlock
+            | Self::UnknownStateWitness
+            | Self::EpochOutOfBounds
+            | Self::InvalidTransaction
+            | Self::InvalidAccount
+            | Self::InvalidShardId
+            | Self::TooLargeContractState
+            | Self::InconsistentState
+            | Self::ContractExecutionError
+            | Self::TxExecutionError
+            | Self::Unclassified => false,
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
@@ -51,7 +265,13 @@ impl RpcError {
     ///
     /// Mostly for completeness, doesn't do anything but filling in the corresponding fields.
     pub fn new(code: i64, message: String, data: Option<Value>) -> Self {
-        RpcError { code, message, data, error_struct: None }
+        RpcError { code, message, data, error_struct: None, error_info: None }
+    }
+
+    /// Sets the machine-readable classification of the error.
+    pub fn with_error_code(mut self, code: RpcErrorCode) -> Self {
+        self.error_info = Some(code.into());
+        self
     }
 
     /// Create an Invalid Param error.
@@ -67,6 +287,7 @@ impl RpcError {
             }
         };
         RpcError::new(-32_602, "Invalid params".to_owned(), Some(value))
+            .with_error_code(RpcErrorCode::InvalidParams)
     }
 
     /// Create a server error.
@@ -77,6 +298,7 @@ impl RpcError {
             "Server error".to_owned(),
             e.map(|v| to_value(v).expect("Must be representable in JSON")),
         )
+        .with_error_code(RpcErrorCode::InternalError)
     }
 
     /// Create a parse error.
@@ -88,6 +310,7 @@ impl RpcError {
             error_struct: Some(RpcErrorKind::RequestValidationError(
                 RpcRequestValidationErrorKind::ParseError { error_message: e },
             )),
+            error_info: Some(RpcErrorCode::ParseError.into()),
         }
     }
 
@@ -118,15 +341,29 @@ impl RpcError {
                 "name": "INTERNAL_ERROR",
                 "info": serde_json::json!({"error_message": info})
             }))),
+            error_info: Some(RpcErrorCode::InternalError.into()),
         }
     }
 
     fn new_handler_error(error_data: Option<Value>, error_struct: Value) -> Self {
+        let error_code = match error_struct["name"].as_str() {
+            Some(name) => RpcErrorCode::from_cause_name(name),
+            None => RpcErrorCode::Unclassified,
+        };
+        Self::new_handler_error_with_code(error_data, error_struct, error_code)
+    }
+
+    fn new_handler_error_with_code(
+        error_data: Option<Value>,
+        error_struct: Value,
+        error_code: RpcErrorCode,
+    ) -> Self {
         RpcError {
             code: -32_000,
             message: "Server error".to_owned(),
             data: error_data,
             error_struct: Some(RpcErrorKind::HandlerError(error_struct)),
+            error_info: Some(error_code.into()),
         }
     }
 
@@ -139,6 +376,7 @@ impl RpcError {
             error_struct: Some(RpcErrorKind::RequestValidationError(
                 RpcRequestValidationErrorKind::MethodNotFound { method_name: method },
             )),
+            error_info: Some(RpcErrorCode::MethodNotFound.into()),
         }
     }
 }
@@ -183,10 +421,68 @@ impl From<ServerError> for RpcError {
             }
         };
         match e {
-            ServerError::TxExecutionError(_) => {
-                RpcError::new_handler_error(Some(error_data.clone()), error_data)
-            }
-            _ => RpcError::new_internal_error(Some(error_data), e.to_string()),
+            ServerError::TxExecutionError(_) => RpcError::new_handler_error_with_code(
+                Some(error_data.clone()),
+                error_data,
+                RpcErrorCode::TxExecutionError,
+            ),
+            ServerError::Timeout => RpcError::new_internal_error(Some(error_data), e.to_string())
+                .with_error_code(RpcErrorCode::TimeoutError),
+            ServerError::Closed => RpcError::new_internal_error(Some(error_data), e.to_string())
+                .with_error_code(RpcErrorCode::Closed),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{RpcError, RpcErrorCategory, RpcErrorCode, RpcErrorInfo, ServerError};
+    use crate::types::blocks::RpcBlockError;
+    use crate::types::transactions::RpcTransactionError;
+
+    #[test]
+    fn test_handler_error_info() {
+        let error = RpcError::from(RpcBlockError::UnknownBlock { error_message: String::new() });
+        assert_eq!(
+            error.error_info,
+            Some(RpcErrorInfo {
+                code: 2000,
+                category: RpcErrorCategory::NotFound,
+                retryable: false
+            })
+        );
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(
+            json["error_info"],
+            serde_json::json!({"code": 2000, "category": "NOT_FOUND", "retryable": false})
+        );
+        assert_eq!(serde_json::from_value::<RpcError>(json).unwrap(), error);
+
+        let error = RpcError::from(RpcTransactionError::TimeoutError);
+        assert_eq!(error.error_info, Some(RpcErrorCode::TimeoutError.into()));
+        assert_eq!(error.error_info.unwrap().category, RpcErrorCategory::Timeout);
+        assert!(error.error_info.unwrap().retryable);
+    }
+
+    #[test]
+    fn test_internal_and_validation_error_info() {
+        let error = RpcError::from(RpcBlockError::InternalError { error_message: "oops".into() });
+        assert_eq!(error.error_info, Some(RpcErrorCode::InternalError.into()));
+
+        let error = RpcError::parse_error("bad json".to_string());
+        assert_eq!(error.error_info.unwrap().category, RpcErrorCategory::RequestValidation);
+
+        let error = RpcError::method_not_found("foo".to_string());
+        assert_eq!(error.error_info, Some(RpcErrorCode::MethodNotFound.into()));
+
+        let error = RpcError::from(ServerError::Closed);
+        assert_eq!(error.error_info, Some(RpcErrorCode::Closed.into()));
+    }
+
+    #[test]
+    fn test_error_info_is_optional_when_deserializing() {
+        let error: RpcError =
+            serde_json::from_str(r#"{"code": -32000, "message": "Server error"}"#).unwrap();
+        assert_eq!(error.error_info, None);
+    }
+}
@@ -54,3 +54,43 @@ impl From<RpcBlockError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(error_data, error_data_value)
     }
 }
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcBlockExistsError {
+    #[error("There are no fully synchronized blocks yet")]
+    NotSyncedYet,
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcBlockExistsRequest {
+    pub height: near_primitives::types::BlockHeight,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RpcBlockExistsResponse {
+    /// Whether the node can serve the block at the requested height.
+    pub exists: bool,
+    #[serde(flatten)]
+    pub block_height_status: near_primitives::views::BlockHeightStatusView,
+}
+
+impl From<RpcBlockExistsError> for crate::errors::RpcError {
+    fn from(error: RpcBlockExistsError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcBlockExistsError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -0,0 +1,73 @@
+//! Admin control of the database compactions, see `compaction_schedule` in
+//! the store config.
+use crate::errors::RpcError;
+use serde::{Deserialize, Serialize};
+
+/// Compaction requested by the operator.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompactionRequest {
+    /// Names of the columns to compact, e.g. `State`.
+    pub columns: Vec<String>,
+    /// Hex encoded first key of the compacted range.
+    #[serde(default)]
+    pub start: Option<String>,
+    /// Hex encoded key ending the compacted range, exclusive.
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Compacts the cold database instead of the hot one.
+    #[serde(default)]
+    pub cold: bool,
+    /// Starts the compaction as soon as possible instead of waiting for the
+    /// next maintenance window.
+    #[serde(default)]
+    pub now: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct CompactionJobView {
+    pub column: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub cold: bool,
+    /// Whether the compaction was requested by the operator rather than
+    /// scheduled in the config.
+    pub manual: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ColumnCompactionDebtView {
+    pub column: String,
+    pub cold: bool,
+    /// Estimated number of bytes which compactions still have to rewrite.
+    pub debt_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompactionStatusView {
+    /// Whether the hot database is in one of its maintenance windows.
+    pub in_maintenance_window: bool,
+    pub running: Option<CompactionJobView>,
+    pub queued: Vec<CompactionJobView>,
+    /// Columns with nonzero compaction debt.
+    pub debt: Vec<ColumnCompactionDebtView>,
+}
+
+pub trait CompactionHandler: Sync + Send {
+    fn status(&self) -> Result<CompactionStatusView, RpcError>;
+    /// Queues the compactions of the requested columns.
+    fn schedule(&self, request: CompactionRequest) -> Result<CompactionStatusView, RpcError>;
+}
+
+/// For tests and for nodes which can't compact their database, e.g. ones
+/// serving RPC from a secondary instance.
+pub struct DummyCompactionHandler {}
+
+impl CompactionHandler for DummyCompactionHandler {
+    fn status(&self) -> Result<CompactionStatusView, RpcError> {
+        Err(RpcError::new_internal_error(None, "Not implemented".to_string()))
+    }
+
+    fn schedule(&self, _request: CompactionRequest) -> Result<CompactionStatusView, RpcError> {
+        Err(RpcError::new_internal_error(None, "Not implemented".to_string()))
+    }
+}
@@ -0,0 +1,35 @@
+use serde_json::Value;
+
+pub type RpcEpochShardStatsResponse = Vec<near_primitives::types::EpochShardStats>;
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcEpochShardStatsError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcEpochShardStatsRequest {
+    pub epoch_id: near_primitives::types::EpochId,
+}
+
+impl From<RpcEpochShardStatsError> for crate::errors::RpcError {
+    fn from(error: RpcEpochShardStatsError) -> Self {
+        let error_data = match &error {
+            RpcEpochShardStatsError::InternalError { .. } => Some(Value::String(error.to_string())),
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcEpochShardStatsError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -0,0 +1,40 @@
+use near_primitives::types::BlockHeight;
+use near_primitives::views::ForkInfoView;
+use serde_json::Value;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct RpcForkInfoRequest {
+    /// Number of heights below the head to look for forks at, 20 by default
+    /// and at most 500.
+    #[serde(default)]
+    pub num_heights: Option<BlockHeight>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcForkInfoResponse {
+    #[serde(flatten)]
+    pub fork_info: ForkInfoView,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcForkInfoError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcForkInfoError> for crate::errors::RpcError {
+    fn from(error: RpcForkInfoError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcForkInfoError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -0,0 +1,73 @@
+//! Tracing of the host function calls made by the contracts, for profiling
+//! their gas usage on a local node.
+use crate::errors::RpcError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, Gas};
+use serde::{Deserialize, Serialize};
+
+/// Receivers whose function calls are traced from now on.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HostCallTraceRequest {
+    /// An empty list disables tracing.
+    pub receivers: Vec<AccountId>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct HostCallTraceStatusView {
+    pub receivers: Vec<AccountId>,
+    /// Number of the recent receipts with a trace available.
+    pub num_traces: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct HostCallView {
+    pub name: String,
+    pub args: Vec<u64>,
+    pub burnt_gas_before: Gas,
+    pub burnt_gas_after: Gas,
+    pub used_gas_before: Gas,
+    pub used_gas_after: Gas,
+    pub failed: bool,
+}
+
+/// Host function calls made by a function call action of the receipt.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FunctionCallTraceView {
+    pub receiver_id: AccountId,
+    pub method_name: String,
+    pub host_calls: Vec<HostCallView>,
+    /// Whether the trace misses the calls made after a limit was reached.
+    pub truncated: bool,
+}
+
+pub trait HostCallTraceHandler: Sync + Send {
+    fn status(&self) -> Result<HostCallTraceStatusView, RpcError>;
+    fn set_receivers(
+        &self,
+        request: HostCallTraceRequest,
+    ) -> Result<HostCallTraceStatusView, RpcError>;
+    /// Traces of the function calls of the receipt, if it has been traced
+    /// recently.
+    fn trace(&self, receipt_id: CryptoHash) -> Result<Vec<FunctionCallTraceView>, RpcError>;
+}
+
+/// For tests and for nodes which don't apply the chunks themselves, e.g. ones
+/// serving RPC from a secondary instance.
+pub struct DummyHostCallTraceHandler {}
+
+impl HostCallTraceHandler for DummyHostCallTraceHandler {
+    fn status(&self) -> Result<HostCallTraceStatusView, RpcError> {
+        Err(RpcError::new_internal_error(None, "Not implemented".to_string()))
+    }
+
+    fn set_receivers(
+        &self,
+        _request: HostCallTraceRequest,
+    ) -> Result<HostCallTraceStatusView, RpcError> {
+        Err(RpcError::new_internal_error(None, "Not implemented".to_string()))
+    }
+
+    fn trace(&self, _receipt_id: CryptoHash) -> Result<Vec<FunctionCallTraceView>, RpcError> {
+        Err(RpcError::new_internal_error(None, "Not implemented".to_string()))
+    }
+}
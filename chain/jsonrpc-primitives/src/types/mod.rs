@@ -2,10 +2,14 @@ pub mod blocks;
 pub mod changes;
 pub mod chunks;
 pub mod client_config;
+pub mod compaction;
 pub mod config;
 pub mod congestion;
 pub mod entity_debug;
+pub mod epoch_shard_stats;
+pub mod fork_info;
 pub mod gas_price;
+pub mod host_call_trace;
 pub mod light_client;
 pub mod maintenance;
 pub mod network_info;
@@ -13,6 +17,9 @@ pub mod query;
 pub mod receipts;
 pub mod sandbox;
 pub mod split_storage;
+pub mod state_witness;
 pub mod status;
 pub mod transactions;
+pub mod tx_pool;
 pub mod validator;
+pub mod watched_accounts;
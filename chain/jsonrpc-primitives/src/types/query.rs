@@ -12,7 +12,13 @@ pub enum RpcQueryError {
     #[error("There are no fully synchronized blocks on the node yet")]
     NoSyncedBlocks,
     #[error("The node does not track the shard ID {requested_shard_id}")]
-    UnavailableShard { requested_shard_id: near_primitives::types::ShardId },
+    UnavailableShard {
+        requested_shard_id: near_primitives::types::ShardId,
+        /// Public RPC URLs of the connected peers which track the shard, as
+        /// advertised by them. The query may be retried on any of them.
+        #[serde(default)]
+        rpc_endpoints: Vec<String>,
+    },
     #[error(
         "The data for block #{block_height} is garbage collected on this node, use an archival node to fetch historical data"
     )]
@@ -75,6 +81,9 @@ pub struct RpcQueryResponse {
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 #[serde(untagged)]
 pub enum QueryResponseKind {
+    // Must come before `ViewAccount`, which would match the account fields and
+    // silently drop the proof.
+    ViewAccountWithProof(near_primitives::views::AccountWithProofView),
     ViewAccount(near_primitives::views::AccountView),
     ViewCode(near_primitives::views::ContractCodeView),
     ViewState(near_primitives::views::ViewStateResult),
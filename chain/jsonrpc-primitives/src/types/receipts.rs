@@ -38,3 +38,53 @@ impl From<RpcReceiptError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
     }
 }
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcBlockReceiptsRequest {
+    #[serde(flatten)]
+    pub block_reference: near_primitives::types::BlockReference,
+    /// Only return receipts sent to this account.
+    #[serde(default)]
+    pub receiver_id: Option<near_primitives::types::AccountId>,
+    /// Only return receipts sent by this account.
+    #[serde(default)]
+    pub predecessor_id: Option<near_primitives::types::AccountId>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcBlockReceiptsResponse {
+    #[serde(flatten)]
+    pub block_receipts_view: near_primitives::views::BlockReceiptsView,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcBlockReceiptsError {
+    #[error("Block not found: {error_message}")]
+    UnknownBlock {
+        #[serde(skip_serializing)]
+        error_message: String,
+    },
+    #[error("There are no fully synchronized blocks yet")]
+    NotSyncedYet,
+    #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
+    UnknownChunk { chunk_hash: near_primitives::sharding::ChunkHash },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcBlockReceiptsError> for crate::errors::RpcError {
+    fn from(error: RpcBlockReceiptsError) -> Self {
+        let error_data = Some(serde_json::Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcBlockReceiptsError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
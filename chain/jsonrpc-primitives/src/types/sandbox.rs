@@ -1,9 +1,13 @@
+use near_primitives::sandbox::SandboxStateDeletion;
 use near_primitives::state_record::StateRecord;
 use near_primitives::types::BlockHeightDelta;
 
+/// Records and deletions of a request are applied together in one block.
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct RpcSandboxPatchStateRequest {
     pub records: Vec<StateRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deletions: Vec<SandboxStateDeletion>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
@@ -0,0 +1,48 @@
+use near_primitives::types::{BlockHeight, EpochId, ShardId};
+use near_primitives::views::StateWitnessView;
+use serde_json::Value;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct RpcStateWitnessRequest {
+    /// Height at which the chunk of the witness was produced.
+    pub height: BlockHeight,
+    pub shard_id: ShardId,
+    /// Only return witnesses of this epoch, useful around epoch boundaries.
+    #[serde(default)]
+    pub epoch_id: Option<EpochId>,
+    /// Return the decoded witnesses instead of their base64 encoded borsh.
+    #[serde(default)]
+    pub decode: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcStateWitnessResponse {
+    /// More than one witness is returned when the node recorded several
+    /// witnesses for the same chunk, e.g. the produced and a received one.
+    pub witnesses: Vec<StateWitnessView>,
+}
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcStateWitnessError {
+    #[error("No state witness is recorded for height {height} and shard {shard_id}. Witnesses are only recorded when `save_latest_witnesses` is enabled")]
+    UnknownStateWitness { height: BlockHeight, shard_id: ShardId },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcStateWitnessError> for crate::errors::RpcError {
+    fn from(error: RpcStateWitnessError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcStateWitnessError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -1,12 +1,12 @@
 #[cfg(feature = "debug_types")]
 use near_client_primitives::debug::{
-    DebugBlockStatusData, EpochInfoView, TrackedShardsView, ValidatorStatus,
+    DebugBlockStatusData, EpochInfoView, FlatStorageStatusView, TrackedShardsView, ValidatorStatus,
 };
 #[cfg(feature = "debug_types")]
 use near_primitives::views::{
-    CatchupStatusView, ChainProcessingInfo, NetworkGraphView, NetworkRoutesView, PeerStoreView,
-    RecentOutboundConnectionsView, RequestedStatePartsView, SnapshotHostsView,
-    SplitStorageInfoView, SyncStatusView,
+    CatchupStatusView, ChainProcessingInfo, NetworkGraphView, NetworkRoutesView,
+    PeerAccessListView, PeerStoreView, RecentOutboundConnectionsView, RequestedStatePartsView,
+    SnapshotHostsView, SplitStorageInfoView, SyncStatusView, Tier1ConnectionsView,
 };
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -35,7 +35,10 @@ pub enum DebugStatusResponse {
     RecentOutboundConnections(RecentOutboundConnectionsView),
     Routes(NetworkRoutesView),
     SnapshotHosts(SnapshotHostsView),
+    Tier1Connections(Tier1ConnectionsView),
+    PeerAccessList(PeerAccessListView),
     SplitStoreStatus(SplitStorageInfoView),
+    FlatStorageStatus(Vec<FlatStorageStatusView>),
 }
 
 #[cfg(feature = "debug_types")]
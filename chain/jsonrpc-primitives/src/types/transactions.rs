@@ -8,6 +8,10 @@ pub struct RpcSendTransactionRequest {
     pub signed_transaction: near_primitives::transaction::SignedTransaction,
     #[serde(default)]
     pub wait_until: near_primitives::views::TxExecutionStatus,
+    /// Attach the proofs of the transaction outcome and of the outcomes of the
+    /// receipts the transaction was converted to.
+    #[serde(default)]
+    pub include_proof: bool,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -16,6 +20,10 @@ pub struct RpcTransactionStatusRequest {
     pub transaction_info: TransactionInfo,
     #[serde(default)]
     pub wait_until: near_primitives::views::TxExecutionStatus,
+    /// Attach the proofs of the transaction outcome and of the outcomes of the
+    /// receipts the transaction was converted to.
+    #[serde(default)]
+    pub include_proof: bool,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -56,6 +64,22 @@ pub struct RpcTransactionResponse {
     #[serde(flatten)]
     pub final_execution_outcome: Option<near_primitives::views::FinalExecutionOutcomeViewEnum>,
     pub final_execution_status: near_primitives::views::TxExecutionStatus,
+    /// Proofs of the outcomes of the transaction and of its first-level
+    /// receipts, if requested with `include_proof`. Outcomes which the node
+    /// can't prove, e.g. because they are not included in the outcome root of
+    /// any block yet, have no proof.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outcome_proofs: Vec<RpcExecutionOutcomeProof>,
+}
+
+/// Proof of an execution outcome against the outcome root of the block
+/// `outcome_proof.block_hash`, i.e. the first block with a new chunk of the
+/// shard after the outcome was produced. Same as the one returned by
+/// `light_client_proof`, but without proving the block itself.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RpcExecutionOutcomeProof {
+    pub outcome_proof: near_primitives::views::ExecutionOutcomeWithIdView,
+    pub outcome_root_proof: near_primitives::merkle::MerklePath,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
@@ -102,6 +126,7 @@ impl From<near_primitives::views::TxStatusView> for RpcTransactionResponse {
         Self {
             final_execution_outcome: view.execution_outcome,
             final_execution_status: view.status,
+            outcome_proofs: vec![],
         }
     }
 }
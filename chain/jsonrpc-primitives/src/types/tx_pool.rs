@@ -0,0 +1,28 @@
+//! Hand-off of the transaction pool between nodes, e.g. during rolling
+//! restarts of RPC nodes: the pool exported from the node going down is
+//! imported into one which stays up.
+
+use near_primitives::hash::CryptoHash;
+
+/// Transactions of the pool, as base64 encoded borsh, like in `send_tx`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct TransactionPoolDump {
+    pub transactions: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct TransactionPoolImportResult {
+    /// Number of transactions accepted into the pool or forwarded to the
+    /// validators.
+    pub accepted: usize,
+    pub rejected: Vec<RejectedTransaction>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RejectedTransaction {
+    /// Position of the transaction in the imported dump.
+    pub index: usize,
+    /// Missing if the transaction couldn't be decoded.
+    pub hash: Option<CryptoHash>,
+    pub error: String,
+}
@@ -1,7 +1,15 @@
 use serde_json::Value;
 
-pub type RpcValidatorsOrderedResponse =
-    Vec<near_primitives::views::validator_stake_view::ValidatorStakeView>;
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(untagged)]
+pub enum RpcValidatorsOrderedResponse {
+    BlockProducers(Vec<near_primitives::views::validator_stake_view::ValidatorStakeView>),
+    /// Returned when `include_chunk_validators` is set in the request.
+    WithChunkValidators {
+        block_producers: Vec<near_primitives::views::validator_stake_view::ValidatorStakeView>,
+        chunk_validators: near_primitives::views::ChunkValidatorsView,
+    },
+}
 
 #[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
@@ -20,9 +28,12 @@ pub struct RpcValidatorRequest {
     pub epoch_reference: near_primitives::types::EpochReference,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
 pub struct RpcValidatorsOrderedRequest {
     pub block_id: near_primitives::types::MaybeBlockId,
+    /// Also return the chunk validators of every shard at the height of the block.
+    #[serde(default)]
+    pub include_chunk_validators: bool,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
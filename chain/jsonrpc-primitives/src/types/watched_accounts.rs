@@ -0,0 +1,37 @@
+use serde_json::Value;
+
+pub type RpcWatchedAccountsResponse = Vec<near_primitives::views::WatchedAccountView>;
+
+#[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcWatchedAccountsError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct RpcWatchedAccountsRequest {
+    /// Accounts to return the data of. All watched accounts if empty.
+    #[serde(default)]
+    pub account_ids: Vec<near_primitives::types::AccountId>,
+}
+
+impl From<RpcWatchedAccountsError> for crate::errors::RpcError {
+    fn from(error: RpcWatchedAccountsError) -> Self {
+        let error_data = match &error {
+            RpcWatchedAccountsError::InternalError { .. } => Some(Value::String(error.to_string())),
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcWatchedAccountsError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -8,10 +8,11 @@ use near_jsonrpc_primitives::types::changes::{
 use near_jsonrpc_primitives::types::transactions::{
     RpcTransactionResponse, RpcTransactionStatusRequest,
 };
-use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
+use near_jsonrpc_primitives::types::validator::{
+    RpcValidatorsOrderedRequest, RpcValidatorsOrderedResponse,
+};
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{BlockId, BlockReference, EpochReference, MaybeBlockId, ShardId};
-use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
     BlockView, ChunkView, EpochValidatorInfo, GasPriceView, StatusResponse,
 };
@@ -238,7 +239,7 @@ impl JsonRpcClient {
     pub fn EXPERIMENTAL_validators_ordered(
         &self,
         request: RpcValidatorsOrderedRequest,
-    ) -> RpcRequest<Vec<ValidatorStakeView>> {
+    ) -> RpcRequest<RpcValidatorsOrderedResponse> {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_validators_ordered", request)
     }
 
@@ -267,6 +268,14 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_split_storage_info", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_fork_info(
+        &self,
+        request: near_jsonrpc_primitives::types::fork_info::RpcForkInfoRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::fork_info::RpcForkInfoResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_fork_info", request)
+    }
+
     pub fn validators(
         &self,
         epoch_id_or_block_id: Option<EpochReference>,
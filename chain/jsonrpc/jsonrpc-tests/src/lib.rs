@@ -12,7 +12,9 @@ use near_client::ViewClientActor;
 use near_jsonrpc::{start_http, RpcConfig};
 use near_jsonrpc_primitives::{
     message::{from_slice, Message},
+    types::compaction::DummyCompactionHandler,
     types::entity_debug::DummyEntityDebugHandler,
+    types::host_call_trace::DummyHostCallTraceHandler,
 };
 use near_network::tcp;
 use near_primitives::types::NumBlocks;
@@ -62,6 +64,8 @@ pub fn start_all_with_validity_period(
         #[cfg(feature = "test_features")]
         noop().into_multi_sender(),
         Arc::new(DummyEntityDebugHandler {}),
+        Arc::new(DummyCompactionHandler {}),
+        Arc::new(DummyHostCallTraceHandler {}),
     );
     (actor_handles.view_client_actor, addr)
 }
@@ -10,7 +10,9 @@ use near_actix_test_utils::run_actix;
 use near_crypto::{KeyType, PublicKey, Signature};
 use near_jsonrpc::client::{new_client, ChunkId};
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
-use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
+use near_jsonrpc_primitives::types::validator::{
+    RpcValidatorsOrderedRequest, RpcValidatorsOrderedResponse,
+};
 use near_network::test_utils::wait_or_timeout;
 use near_o11y::testonly::init_test_logger;
 use near_primitives::account::{AccessKey, AccessKeyPermission};
@@ -161,21 +163,30 @@ fn test_query_account() {
         let query_response_1 = client
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::latest(),
-                request: QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+                request: QueryRequest::ViewAccount {
+                    account_id: "test".parse().unwrap(),
+                    include_proof: false,
+                },
             })
             .await
             .unwrap();
         let query_response_2 = client
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::BlockId(BlockId::Height(0)),
-                request: QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+                request: QueryRequest::ViewAccount {
+                    account_id: "test".parse().unwrap(),
+                    include_proof: false,
+                },
             })
             .await
             .unwrap();
         let query_response_3 = client
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::BlockId(BlockId::Hash(block_hash)),
-                request: QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+                request: QueryRequest::ViewAccount {
+                    account_id: "test".parse().unwrap(),
+                    include_proof: false,
+                },
             })
             .await
             .unwrap();
@@ -224,7 +235,10 @@ fn test_query_access_keys() {
         let query_response = client
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::latest(),
-                request: QueryRequest::ViewAccessKeyList { account_id: "test".parse().unwrap() },
+                request: QueryRequest::ViewAccessKeyList {
+                    account_id: "test".parse().unwrap(),
+                    options: Default::default(),
+                },
             })
             .await
             .unwrap();
@@ -301,6 +315,7 @@ fn test_query_state() {
                     account_id: "test".parse().unwrap(),
                     prefix: vec![].into(),
                     include_proof: false,
+                    options: Default::default(),
                 },
             })
             .await
@@ -450,10 +465,16 @@ fn test_health_ok() {
 #[test]
 fn test_validators_ordered() {
     test_with_client!(test_utils::NodeType::Validator, client, async move {
-        let validators = client
-            .EXPERIMENTAL_validators_ordered(RpcValidatorsOrderedRequest { block_id: None })
+        let response = client
+            .EXPERIMENTAL_validators_ordered(RpcValidatorsOrderedRequest {
+                block_id: None,
+                include_chunk_validators: false,
+            })
             .await
             .unwrap();
+        let RpcValidatorsOrderedResponse::BlockProducers(validators) = response else {
+            panic!("unexpected response {response:?}");
+        };
         assert_eq!(
             validators.into_iter().map(|v| v.take_account_id()).collect::<Vec<_>>(),
             vec!["test1"]
@@ -461,6 +482,35 @@ fn test_validators_ordered() {
     });
 }
 
+#[test]
+fn test_validators_ordered_with_chunk_validators() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let response = client
+            .EXPERIMENTAL_validators_ordered(RpcValidatorsOrderedRequest {
+                block_id: None,
+                include_chunk_validators: true,
+            })
+            .await
+            .unwrap();
+        let RpcValidatorsOrderedResponse::WithChunkValidators { block_producers, chunk_validators } =
+            response
+        else {
+            panic!("unexpected response {response:?}");
+        };
+        assert_eq!(
+            block_producers.into_iter().map(|v| v.take_account_id()).collect::<Vec<_>>(),
+            vec!["test1"]
+        );
+        assert!(!chunk_validators.shards.is_empty());
+        for shard in chunk_validators.shards {
+            let validators =
+                shard.validators.iter().map(|v| v.account_id.as_str()).collect::<Vec<_>>();
+            assert_eq!(validators, vec!["test1"]);
+            assert!(shard.required_endorsement_weight <= shard.total_endorsement_weight);
+        }
+    });
+}
+
 /// Retrieve genesis config via JSON RPC.
 /// WARNING: Be mindful about changing genesis structure as it is part of the public protocol!
 #[test]
@@ -11,9 +11,10 @@ use near_jsonrpc_primitives::types::transactions::{RpcTransactionStatusRequest,
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_o11y::testonly::{init_integration_logger, init_test_logger};
 use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::merkle::{compute_root_from_path_and_item, verify_path};
 use near_primitives::serialize::to_base64;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::BlockReference;
+use near_primitives::types::{BlockId, BlockReference};
 use near_primitives::views::{FinalExecutionStatus, TxExecutionStatus};
 use near_time::Clock;
 
@@ -67,6 +68,7 @@ fn test_send_tx_async() {
                                     sender_account_id: signer_account_id,
                                 },
                                 wait_until: TxExecutionStatus::Executed,
+                                include_proof: false,
                             })
                             .map_err(|err| println!("Error: {:?}", err))
                             .map_ok(|result| {
@@ -115,6 +117,57 @@ fn test_send_tx_commit() {
     });
 }
 
+/// Test that the outcome proofs of the transaction and its receipt verify
+/// against the outcome roots of the blocks they point to.
+#[test]
+fn test_tx_status_with_outcome_proofs() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::send_money(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer.into(),
+            100,
+            block_hash,
+        );
+        let bytes = borsh::to_vec(&tx).unwrap();
+        let result = client.broadcast_tx_commit(to_base64(&bytes)).await.unwrap();
+        assert!(result.outcome_proofs.is_empty());
+
+        let result = client
+            .tx(RpcTransactionStatusRequest {
+                transaction_info: TransactionInfo::from_signed_tx(tx.clone()),
+                wait_until: TxExecutionStatus::Final,
+                include_proof: true,
+            })
+            .await
+            .unwrap();
+        let outcome = result.final_execution_outcome.unwrap().into_outcome();
+        let mut want_ids = vec![tx.get_hash()];
+        want_ids.extend(outcome.transaction_outcome.outcome.receipt_ids.iter().copied());
+        let got_ids =
+            result.outcome_proofs.iter().map(|proof| proof.outcome_proof.id).collect::<Vec<_>>();
+        assert_eq!(got_ids, want_ids);
+        for proof in result.outcome_proofs {
+            let block = client
+                .block(BlockReference::BlockId(BlockId::Hash(proof.outcome_proof.block_hash)))
+                .await
+                .unwrap();
+            let chunk_outcome_root = compute_root_from_path_and_item(
+                &proof.outcome_proof.proof,
+                &proof.outcome_proof.to_hashes(),
+            );
+            assert!(verify_path(
+                block.header.outcome_root,
+                &proof.outcome_root_proof,
+                &chunk_outcome_root
+            ));
+        }
+    });
+}
+
 /// Test that expired transaction should be rejected
 #[test]
 fn test_expired_tx() {
@@ -217,6 +270,7 @@ fn test_tx_status_missing_tx() {
                 sender_account_id: "test1".parse().unwrap(),
             },
             wait_until: TxExecutionStatus::None,
+            include_proof: false,
         };
         match client.tx(request).await {
             Err(e) => {
@@ -243,6 +297,7 @@ fn test_check_invalid_tx() {
                 hash(&[1]),
             )),
             wait_until: TxExecutionStatus::None,
+            include_proof: false,
         };
         match client.tx(request).await {
             Err(e) => {
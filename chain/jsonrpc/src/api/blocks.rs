@@ -1,9 +1,11 @@
 use near_async::messaging::AsyncSendError;
 use serde_json::Value;
 
-use near_client_primitives::types::GetBlockError;
+use near_client_primitives::types::{GetBlockError, GetBlockHeightStatusError};
 use near_jsonrpc_primitives::errors::RpcParseError;
-use near_jsonrpc_primitives::types::blocks::{RpcBlockError, RpcBlockRequest};
+use near_jsonrpc_primitives::types::blocks::{
+    RpcBlockError, RpcBlockExistsError, RpcBlockExistsRequest, RpcBlockRequest,
+};
 use near_primitives::types::BlockReference;
 
 use super::{Params, RpcFrom, RpcRequest};
@@ -39,3 +41,33 @@ impl RpcFrom<GetBlockError> for RpcBlockError {
         }
     }
 }
+
+impl RpcRequest for RpcBlockExistsRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::new(value).try_singleton(|height| Ok(Self { height })).unwrap_or_parse()
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcBlockExistsError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetBlockHeightStatusError> for RpcBlockExistsError {
+    fn rpc_from(error: GetBlockHeightStatusError) -> Self {
+        match error {
+            GetBlockHeightStatusError::NotSyncedYet => Self::NotSyncedYet,
+            GetBlockHeightStatusError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            GetBlockHeightStatusError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcBlockExistsError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
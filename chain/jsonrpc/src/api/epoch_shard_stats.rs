@@ -0,0 +1,36 @@
+use near_async::messaging::AsyncSendError;
+use serde_json::Value;
+
+use near_client_primitives::types::GetEpochShardStatsError;
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::epoch_shard_stats::{
+    RpcEpochShardStatsError, RpcEpochShardStatsRequest,
+};
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcEpochShardStatsRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcEpochShardStatsError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetEpochShardStatsError> for RpcEpochShardStatsError {
+    fn rpc_from(error: GetEpochShardStatsError) -> Self {
+        match error {
+            GetEpochShardStatsError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            GetEpochShardStatsError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
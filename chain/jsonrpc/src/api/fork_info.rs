@@ -0,0 +1,40 @@
+use near_async::messaging::AsyncSendError;
+use near_client_primitives::types::{GetForkInfo, GetForkInfoError};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::fork_info::{RpcForkInfoError, RpcForkInfoRequest};
+use serde_json::Value;
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcForkInfoRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcForkInfoError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<RpcForkInfoRequest> for GetForkInfo {
+    fn rpc_from(request: RpcForkInfoRequest) -> Self {
+        Self { num_heights: request.num_heights }
+    }
+}
+
+impl RpcFrom<GetForkInfoError> for RpcForkInfoError {
+    fn rpc_from(error: GetForkInfoError) -> Self {
+        match error {
+            GetForkInfoError::IOError(error_message) => Self::InternalError { error_message },
+            GetForkInfoError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcForkInfoError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
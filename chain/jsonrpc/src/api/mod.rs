@@ -2,7 +2,7 @@ use near_async::messaging::AsyncSendError;
 use serde_json::Value;
 
 use near_jsonrpc_primitives::errors::RpcParseError;
-use near_jsonrpc_primitives::errors::{RpcError, ServerError};
+use near_jsonrpc_primitives::errors::{RpcError, RpcErrorCode, ServerError};
 
 mod blocks;
 mod changes;
@@ -10,6 +10,8 @@ mod chunks;
 mod client_config;
 mod config;
 mod congestion;
+mod epoch_shard_stats;
+mod fork_info;
 mod gas_price;
 mod light_client;
 mod maintenance;
@@ -18,9 +20,11 @@ mod query;
 mod receipts;
 mod sandbox;
 mod split_storage;
+mod state_witness;
 mod status;
 mod transactions;
 mod validator;
+mod watched_accounts;
 
 pub(crate) trait RpcRequest: Sized {
     fn parse(value: Value) -> Result<Self, RpcParseError>;
@@ -57,11 +61,16 @@ where
 
 impl RpcFrom<AsyncSendError> for RpcError {
     fn rpc_from(error: AsyncSendError) -> Self {
+        let error_code = match error {
+            AsyncSendError::Timeout => RpcErrorCode::TimeoutError,
+            AsyncSendError::Closed | AsyncSendError::Dropped => RpcErrorCode::Closed,
+        };
         RpcError::new(
             -32_000,
             "Server error".to_string(),
             Some(serde_json::Value::String(error.to_string())),
         )
+        .with_error_code(error_code)
     }
 }
 
@@ -57,9 +57,9 @@ fn parse_path_data(path: String, data: String) -> Result<RpcQueryRequest, RpcPar
     let maybe_extra_arg = path_parts.next();
 
     let request = match query_command {
-        "account" => QueryRequest::ViewAccount { account_id },
+        "account" => QueryRequest::ViewAccount { account_id, include_proof: false },
         "access_key" => match maybe_extra_arg {
-            None => QueryRequest::ViewAccessKeyList { account_id },
+            None => QueryRequest::ViewAccessKeyList { account_id, options: Default::default() },
             Some(pk) => QueryRequest::ViewAccessKey {
                 account_id,
                 public_key: pk
@@ -72,6 +72,7 @@ fn parse_path_data(path: String, data: String) -> Result<RpcQueryRequest, RpcPar
             account_id,
             prefix: parse_data()?.into(),
             include_proof: false,
+            options: Default::default(),
         },
         "call" => match maybe_extra_arg {
             Some(method_name) => QueryRequest::CallFunction {
@@ -99,7 +100,7 @@ impl RpcFrom<QueryError> for RpcQueryError {
             QueryError::InternalError { error_message } => Self::InternalError { error_message },
             QueryError::NoSyncedBlocks => Self::NoSyncedBlocks,
             QueryError::UnavailableShard { requested_shard_id } => {
-                Self::UnavailableShard { requested_shard_id }
+                Self::UnavailableShard { requested_shard_id, rpc_endpoints: vec![] }
             }
             QueryError::UnknownBlock { block_reference } => Self::UnknownBlock { block_reference },
             QueryError::GarbageCollectedBlock { block_height, block_hash } => {
@@ -152,6 +153,9 @@ impl RpcFrom<near_primitives::views::QueryResponseKind>
             near_primitives::views::QueryResponseKind::ViewAccount(account_view) => {
                 Self::ViewAccount(account_view)
             }
+            near_primitives::views::QueryResponseKind::ViewAccountWithProof(account_view) => {
+                Self::ViewAccountWithProof(account_view)
+            }
             near_primitives::views::QueryResponseKind::ViewCode(contract_code_view) => {
                 Self::ViewCode(contract_code_view)
             }
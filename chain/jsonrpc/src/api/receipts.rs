@@ -1,9 +1,12 @@
 use super::{Params, RpcFrom, RpcRequest};
 use near_async::messaging::AsyncSendError;
-use near_client_primitives::types::{GetReceipt, GetReceiptError};
+use near_client_primitives::types::{
+    GetBlockReceipts, GetBlockReceiptsError, GetReceipt, GetReceiptError,
+};
 use near_jsonrpc_primitives::errors::RpcParseError;
 use near_jsonrpc_primitives::types::receipts::{
-    ReceiptReference, RpcReceiptError, RpcReceiptRequest,
+    ReceiptReference, RpcBlockReceiptsError, RpcBlockReceiptsRequest, RpcReceiptError,
+    RpcReceiptRequest,
 };
 use serde_json::Value;
 
@@ -40,3 +43,47 @@ impl RpcFrom<GetReceiptError> for RpcReceiptError {
         }
     }
 }
+
+impl RpcRequest for RpcBlockReceiptsRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcBlockReceiptsError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<RpcBlockReceiptsRequest> for GetBlockReceipts {
+    fn rpc_from(request: RpcBlockReceiptsRequest) -> Self {
+        Self {
+            block_reference: request.block_reference,
+            receiver_id: request.receiver_id,
+            predecessor_id: request.predecessor_id,
+        }
+    }
+}
+
+impl RpcFrom<GetBlockReceiptsError> for RpcBlockReceiptsError {
+    fn rpc_from(error: GetBlockReceiptsError) -> Self {
+        match error {
+            GetBlockReceiptsError::UnknownBlock { error_message } => {
+                Self::UnknownBlock { error_message }
+            }
+            GetBlockReceiptsError::NotSyncedYet => Self::NotSyncedYet,
+            GetBlockReceiptsError::UnknownChunk { chunk_hash } => Self::UnknownChunk { chunk_hash },
+            GetBlockReceiptsError::IOError { error_message } => {
+                Self::InternalError { error_message }
+            }
+            GetBlockReceiptsError::Unreachable { ref error_message } => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcBlockReceiptsError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use near_async::messaging::AsyncSendError;
+use near_client_primitives::types::{GetStateWitnesses, GetStateWitnessesError};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::state_witness::{RpcStateWitnessError, RpcStateWitnessRequest};
+use serde_json::Value;
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcStateWitnessRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcStateWitnessError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<RpcStateWitnessRequest> for GetStateWitnesses {
+    fn rpc_from(request: RpcStateWitnessRequest) -> Self {
+        Self {
+            height: request.height,
+            shard_id: request.shard_id,
+            epoch_id: request.epoch_id,
+            decode: request.decode,
+        }
+    }
+}
+
+impl RpcFrom<GetStateWitnessesError> for RpcStateWitnessError {
+    fn rpc_from(error: GetStateWitnessesError) -> Self {
+        match error {
+            GetStateWitnessesError::UnknownStateWitness { height, shard_id } => {
+                Self::UnknownStateWitness { height, shard_id }
+            }
+            GetStateWitnessesError::IOError { error_message } => {
+                Self::InternalError { error_message }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::RpcRequest;
+    use near_jsonrpc_primitives::types::state_witness::RpcStateWitnessRequest;
+
+    #[test]
+    fn test_parse_state_witness_params() {
+        let params = serde_json::json!({"height": 10, "shard_id": 1});
+        assert_eq!(
+            RpcStateWitnessRequest::parse(params).unwrap(),
+            RpcStateWitnessRequest { height: 10, shard_id: 1, epoch_id: None, decode: false }
+        );
+        let params = serde_json::json!({"height": 10, "shard_id": 1, "decode": true});
+        assert!(RpcStateWitnessRequest::parse(params).unwrap().decode);
+    }
+}
@@ -50,6 +50,9 @@ impl RpcFrom<near_client_primitives::debug::DebugStatusResponse>
                     x,
                 )
             }
+            near_client_primitives::debug::DebugStatusResponse::FlatStorageStatus(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::FlatStorageStatus(x)
+            }
         }
     }
 }
@@ -74,6 +77,12 @@ impl RpcFrom<near_network::debug::DebugStatus>
             near_network::debug::DebugStatus::SnapshotHosts(x) => {
                 near_jsonrpc_primitives::types::status::DebugStatusResponse::SnapshotHosts(x)
             }
+            near_network::debug::DebugStatus::Tier1(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::Tier1Connections(x)
+            }
+            near_network::debug::DebugStatus::AccessList(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::PeerAccessList(x)
+            }
         }
     }
 }
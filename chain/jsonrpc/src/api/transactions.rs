@@ -19,6 +19,7 @@ impl RpcRequest for RpcSendTransactionRequest {
                     signed_transaction: decode_signed_transaction(value)?,
                     // will be ignored in `broadcast_tx_async`, `broadcast_tx_commit`
                     wait_until: Default::default(),
+                    include_proof: false,
                 })
             })
             .try_pair(|_: String, _: String| {
@@ -40,6 +41,7 @@ impl RpcRequest for RpcTransactionStatusRequest {
                 Ok(RpcTransactionStatusRequest {
                     transaction_info: decode_signed_transaction(signed_tx)?.into(),
                     wait_until: Default::default(),
+                    include_proof: false,
                 })
             })
             .try_pair(|tx_hash, sender_account_id| {
@@ -47,6 +49,7 @@ impl RpcRequest for RpcTransactionStatusRequest {
                     transaction_info: TransactionInfo::TransactionId { tx_hash, sender_account_id }
                         .into(),
                     wait_until: Default::default(),
+                    include_proof: false,
                 })
             })
             .unwrap_or_parse()?)
@@ -187,6 +190,14 @@ mod tests {
         assert!(RpcSendTransactionRequest::parse(params).is_ok());
     }
 
+    #[test]
+    fn test_serialize_tx_status_params_with_include_proof() {
+        let tx_hash = CryptoHash::new().to_string();
+        let account_id = "sender.testnet";
+        let params = serde_json::json!({"tx_hash": tx_hash, "sender_account_id": account_id, "include_proof": true});
+        assert!(RpcTransactionStatusRequest::parse(params).unwrap().include_proof);
+    }
+
     // The params are invalid because wait_until is supported only in send tx params passed by object
     #[test]
     fn test_serialize_send_tx_too_many_params() {
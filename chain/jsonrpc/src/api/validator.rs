@@ -54,7 +54,9 @@ impl RpcFrom<GetValidatorInfoError> for RpcValidatorError {
 #[cfg(test)]
 mod tests {
     use crate::api::RpcRequest;
-    use near_jsonrpc_primitives::types::validator::RpcValidatorRequest;
+    use near_jsonrpc_primitives::types::validator::{
+        RpcValidatorRequest, RpcValidatorsOrderedRequest,
+    };
     use near_primitives::hash::CryptoHash;
     use near_primitives::types::{BlockId, EpochId, EpochReference};
 
@@ -107,4 +109,21 @@ mod tests {
             RpcValidatorRequest { epoch_reference: EpochReference::EpochId(EpochId(epoch_id)) }
         );
     }
+
+    #[test]
+    fn test_parse_validators_ordered_params() {
+        let params = serde_json::json!({"block_id": null});
+        assert_eq!(
+            RpcValidatorsOrderedRequest::parse(params).unwrap(),
+            RpcValidatorsOrderedRequest { block_id: None, include_chunk_validators: false }
+        );
+        let params = serde_json::json!({"block_id": 12345, "include_chunk_validators": true});
+        assert_eq!(
+            RpcValidatorsOrderedRequest::parse(params).unwrap(),
+            RpcValidatorsOrderedRequest {
+                block_id: Some(BlockId::Height(12345)),
+                include_chunk_validators: true,
+            }
+        );
+    }
 }
@@ -0,0 +1,363 @@
+//! Read-only GraphQL API served at `/graphql` when `enable_graphql` is set.
+//!
+//! The API lets clients fetch nested data, e.g. block → chunks → receipts →
+//! outcomes, in a single request. Every resolver is backed by the same view
+//! client messages as the JSON-RPC methods, and the views are returned as JSON
+//! scalars in the same format as over JSON-RPC.
+
+use async_graphql::{Context, EmptyMutation, EmptySubscription, InputObject, Json, Object, Schema};
+use near_async::messaging::{CanSend, MessageWithCallback, SendAsync};
+use near_client::{GetBlock, GetChunk, GetExecutionOutcome, Query};
+use near_client_primitives::types::GetExecutionOutcomeError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{
+    AccountId, BlockId, BlockReference, Finality, TransactionOrReceiptId,
+};
+use near_primitives::views::{
+    AccessKeyInfoView, AccessKeyListOptions, AccountView, BlockHeaderView, BlockView,
+    ChunkHeaderView, ChunkView, ExecutionOutcomeWithIdView, QueryRequest, QueryResponseKind,
+    ReceiptView, SignedTransactionView,
+};
+
+use crate::ViewClientSenderForRpc;
+
+/// Maximum nesting of the queries. The deepest path of the schema, block →
+/// chunks → receipts → outcome, is well within the limit.
+const MAX_QUERY_DEPTH: usize = 16;
+
+pub(crate) type GraphQLSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub(crate) fn schema(view_client_sender: ViewClientSenderForRpc) -> GraphQLSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(view_client_sender)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .finish()
+}
+
+async fn view_client_send<M, T, E>(ctx: &Context<'_>, msg: M) -> async_graphql::Result<T>
+where
+    ViewClientSenderForRpc: CanSend<MessageWithCallback<M, Result<T, E>>>,
+    T: Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    let sender = ctx.data::<ViewClientSenderForRpc>()?;
+    sender
+        .send_async(msg)
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?
+        .map_err(|err| async_graphql::Error::new(err.to_string()))
+}
+
+fn parse<T: std::str::FromStr>(value: &str, what: &str) -> async_graphql::Result<T> {
+    value.parse().map_err(|_| async_graphql::Error::new(format!("invalid {}: {}", what, value)))
+}
+
+/// Selects a block by height, hash or finality. At most one of the fields may
+/// be set, the last final block is selected if none is.
+#[derive(InputObject, Default)]
+pub(crate) struct BlockSelector {
+    height: Option<u64>,
+    hash: Option<String>,
+    /// `optimistic`, `near-final` (or `doomslug`) or `final`.
+    finality: Option<String>,
+}
+
+impl BlockSelector {
+    fn block_reference(&self) -> async_graphql::Result<BlockReference> {
+        match (self.height, &self.hash, &self.finality) {
+            (None, None, None) => Ok(BlockReference::Finality(Finality::Final)),
+            (Some(height), None, None) => Ok(BlockReference::BlockId(BlockId::Height(height))),
+            (None, Some(hash), None) => {
+                Ok(BlockReference::BlockId(BlockId::Hash(parse(hash, "block hash")?)))
+            }
+            (None, None, Some(finality)) => {
+                let finality = serde_json::from_value(finality.as_str().into()).map_err(|_| {
+                    async_graphql::Error::new(format!("invalid finality: {}", finality))
+                })?;
+                Ok(BlockReference::Finality(finality))
+            }
+            _ => Err(async_graphql::Error::new("only one of height, hash and finality may be set")),
+        }
+    }
+}
+
+pub(crate) struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn block(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] block: BlockSelector,
+    ) -> async_graphql::Result<Block> {
+        Ok(Block(view_client_send(ctx, GetBlock(block.block_reference()?)).await?))
+    }
+
+    async fn chunk(&self, ctx: &Context<'_>, hash: String) -> async_graphql::Result<Chunk> {
+        let chunk_hash = ChunkHash(parse(&hash, "chunk hash")?);
+        Ok(Chunk(view_client_send(ctx, GetChunk::ChunkHash(chunk_hash)).await?))
+    }
+
+    async fn account(
+        &self,
+        ctx: &Context<'_>,
+        account_id: String,
+        #[graphql(default)] block: BlockSelector,
+    ) -> async_graphql::Result<Account> {
+        let account_id: AccountId = parse(&account_id, "account id")?;
+        let request =
+            QueryRequest::ViewAccount { account_id: account_id.clone(), include_proof: false };
+        let response = view_client_send(ctx, Query::new(block.block_reference()?, request)).await?;
+        let QueryResponseKind::ViewAccount(account) = response.kind else {
+            return Err(async_graphql::Error::new("unexpected query response"));
+        };
+        Ok(Account { account_id, block_hash: response.block_hash, account })
+    }
+
+    /// Outcome of the transaction, if it was executed.
+    async fn transaction_outcome(
+        &self,
+        ctx: &Context<'_>,
+        hash: String,
+        sender_id: String,
+    ) -> async_graphql::Result<Option<Json<ExecutionOutcomeWithIdView>>> {
+        let id = TransactionOrReceiptId::Transaction {
+            transaction_hash: parse(&hash, "transaction hash")?,
+            sender_id: parse(&sender_id, "account id")?,
+        };
+        outcome(ctx, id).await
+    }
+
+    /// Outcome of the receipt, if it was executed.
+    async fn receipt_outcome(
+        &self,
+        ctx: &Context<'_>,
+        receipt_id: String,
+        receiver_id: String,
+    ) -> async_graphql::Result<Option<Json<ExecutionOutcomeWithIdView>>> {
+        let id = TransactionOrReceiptId::Receipt {
+            receipt_id: parse(&receipt_id, "receipt id")?,
+            receiver_id: parse(&receiver_id, "account id")?,
+        };
+        outcome(ctx, id).await
+    }
+}
+
+/// Returns the outcome, or `None` if the transaction or receipt wasn't
+/// executed yet.
+async fn outcome(
+    ctx: &Context<'_>,
+    id: TransactionOrReceiptId,
+) -> async_graphql::Result<Option<Json<ExecutionOutcomeWithIdView>>> {
+    let sender = ctx.data::<ViewClientSenderForRpc>()?;
+    match sender
+        .send_async(GetExecutionOutcome { id })
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?
+    {
+        Ok(response) => Ok(Some(Json(response.outcome_proof))),
+        Err(
+            GetExecutionOutcomeError::NotConfirmed { .. }
+            | GetExecutionOutcomeError::UnknownTransactionOrReceipt { .. },
+        ) => Ok(None),
+        Err(err) => Err(async_graphql::Error::new(err.to_string())),
+    }
+}
+
+pub(crate) struct Block(BlockView);
+
+#[Object]
+impl Block {
+    async fn hash(&self) -> String {
+        self.0.header.hash.to_string()
+    }
+
+    async fn height(&self) -> u64 {
+        self.0.header.height
+    }
+
+    async fn author(&self) -> String {
+        self.0.author.to_string()
+    }
+
+    async fn header(&self) -> Json<BlockHeaderView> {
+        Json(self.0.header.clone())
+    }
+
+    /// Chunks included in the block. Shards without a new chunk at the height
+    /// of the block are skipped.
+    async fn chunks(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Chunk>> {
+        let mut chunks = vec![];
+        for chunk_header in &self.0.chunks {
+            if chunk_header.height_included != self.0.header.height {
+                continue;
+            }
+            let chunk_hash = ChunkHash(chunk_header.chunk_hash);
+            chunks.push(Chunk(view_client_send(ctx, GetChunk::ChunkHash(chunk_hash)).await?));
+        }
+        Ok(chunks)
+    }
+}
+
+pub(crate) struct Chunk(ChunkView);
+
+#[Object]
+impl Chunk {
+    async fn hash(&self) -> String {
+        self.0.header.chunk_hash.to_string()
+    }
+
+    async fn shard_id(&self) -> u64 {
+        self.0.header.shard_id
+    }
+
+    async fn author(&self) -> String {
+        self.0.author.to_string()
+    }
+
+    async fn header(&self) -> Json<ChunkHeaderView> {
+        Json(self.0.header.clone())
+    }
+
+    async fn transactions(&self) -> Vec<Transaction> {
+        self.0.transactions.iter().cloned().map(Transaction).collect()
+    }
+
+    /// Receipts applied in the chunk.
+    async fn receipts(&self) -> Vec<Receipt> {
+        self.0.receipts.iter().cloned().map(Receipt).collect()
+    }
+}
+
+pub(crate) struct Transaction(SignedTransactionView);
+
+#[Object]
+impl Transaction {
+    async fn hash(&self) -> String {
+        self.0.hash.to_string()
+    }
+
+    async fn signer_id(&self) -> String {
+        self.0.signer_id.to_string()
+    }
+
+    async fn receiver_id(&self) -> String {
+        self.0.receiver_id.to_string()
+    }
+
+    async fn transaction(&self) -> Json<SignedTransactionView> {
+        Json(self.0.clone())
+    }
+
+    async fn outcome(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<Json<ExecutionOutcomeWithIdView>>> {
+        let id = TransactionOrReceiptId::Transaction {
+            transaction_hash: self.0.hash,
+            sender_id: self.0.signer_id.clone(),
+        };
+        outcome(ctx, id).await
+    }
+}
+
+pub(crate) struct Receipt(ReceiptView);
+
+#[Object]
+impl Receipt {
+    async fn id(&self) -> String {
+        self.0.receipt_id.to_string()
+    }
+
+    async fn predecessor_id(&self) -> String {
+        self.0.predecessor_id.to_string()
+    }
+
+    async fn receiver_id(&self) -> String {
+        self.0.receiver_id.to_string()
+    }
+
+    async fn receipt(&self) -> Json<ReceiptView> {
+        Json(self.0.clone())
+    }
+
+    async fn outcome(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<Json<ExecutionOutcomeWithIdView>>> {
+        let id = TransactionOrReceiptId::Receipt {
+            receipt_id: self.0.receipt_id,
+            receiver_id: self.0.receiver_id.clone(),
+        };
+        outcome(ctx, id).await
+    }
+}
+
+pub(crate) struct Account {
+    account_id: AccountId,
+    block_hash: CryptoHash,
+    account: AccountView,
+}
+
+#[Object]
+impl Account {
+    async fn account_id(&self) -> String {
+        self.account_id.to_string()
+    }
+
+    /// Hash of the block at which the account was read.
+    async fn block_hash(&self) -> String {
+        self.block_hash.to_string()
+    }
+
+    async fn account(&self) -> Json<AccountView> {
+        Json(self.account.clone())
+    }
+
+    /// Access keys of the account at the same block as the account.
+    async fn access_keys(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<Json<AccessKeyInfoView>>> {
+        let request = QueryRequest::ViewAccessKeyList {
+            account_id: self.account_id.clone(),
+            options: AccessKeyListOptions::default(),
+        };
+        let block_reference = BlockReference::BlockId(BlockId::Hash(self.block_hash));
+        let response = view_client_send(ctx, Query::new(block_reference, request)).await?;
+        let QueryResponseKind::AccessKeyList(list) = response.kind else {
+            return Err(async_graphql::Error::new("unexpected query response"));
+        };
+        Ok(list.keys.into_iter().map(Json).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockSelector;
+    use near_primitives::types::{BlockId, BlockReference, Finality};
+
+    #[test]
+    fn test_block_selector() {
+        let selector = |height, hash: Option<&str>, finality: Option<&str>| BlockSelector {
+            height,
+            hash: hash.map(Into::into),
+            finality: finality.map(Into::into),
+        };
+        assert_eq!(
+            selector(None, None, None).block_reference().unwrap(),
+            BlockReference::Finality(Finality::Final)
+        );
+        assert_eq!(
+            selector(Some(10), None, None).block_reference().unwrap(),
+            BlockReference::BlockId(BlockId::Height(10))
+        );
+        assert_eq!(
+            selector(None, None, Some("doomslug")).block_reference().unwrap(),
+            BlockReference::Finality(Finality::DoomSlug)
+        );
+        assert!(selector(None, Some("not a hash"), None).block_reference().is_err());
+        assert!(selector(Some(10), None, Some("final")).block_reference().is_err());
+    }
+}
@@ -12,17 +12,22 @@ use near_async::messaging::{
 };
 use near_chain_configs::GenesisConfig;
 use near_client::{
-    DebugStatus, GetBlock, GetBlockProof, GetChunk, GetClientConfig, GetExecutionOutcome,
-    GetGasPrice, GetMaintenanceWindows, GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig,
-    GetReceipt, GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
-    ProcessTxRequest, ProcessTxResponse, Query, Status, TxStatus,
+    DebugStatus, ExportTransactionPool, GetBlock, GetBlockHeightStatus, GetBlockProof,
+    GetBlockReceipts, GetChunk, GetChunkValidators, GetClientConfig, GetEpochShardStats,
+    GetExecutionOutcome, GetForkInfo, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
+    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetShardRpcEndpoints, GetStateChanges,
+    GetStateChangesInBlock, GetStateWitnesses, GetValidatorInfo, GetValidatorOrdered,
+    GetWatchedAccounts, ProcessTxRequest, ProcessTxResponse, Query, Status, TxStatus,
 };
+use near_client_primitives::debug::{FlatStorageControl, FlatStorageStatusView};
 use near_client_primitives::types::GetSplitStorageInfo;
 pub use near_jsonrpc_client as client;
-use near_jsonrpc_primitives::errors::{RpcError, RpcErrorKind};
+use near_jsonrpc_primitives::errors::{RpcError, RpcErrorCategory, RpcErrorKind};
 use near_jsonrpc_primitives::message::{Message, Request};
+use near_jsonrpc_primitives::types::compaction::{CompactionHandler, CompactionRequest};
 use near_jsonrpc_primitives::types::config::{RpcProtocolConfigError, RpcProtocolConfigResponse};
 use near_jsonrpc_primitives::types::entity_debug::{EntityDebugHandler, EntityQueryWithParams};
+use near_jsonrpc_primitives::types::host_call_trace::{HostCallTraceHandler, HostCallTraceRequest};
 use near_jsonrpc_primitives::types::query::RpcQueryRequest;
 use near_jsonrpc_primitives::types::split_storage::{
     RpcSplitStorageInfoRequest, RpcSplitStorageInfoResponse,
@@ -30,13 +35,18 @@ use near_jsonrpc_primitives::types::split_storage::{
 use near_jsonrpc_primitives::types::transactions::{
     RpcSendTransactionRequest, RpcTransactionResponse,
 };
-use near_network::debug::GetDebugStatus;
+use near_network::debug::{GetDebugStatus, PeerAccessControl};
 use near_network::tcp;
 use near_o11y::metrics::{prometheus, Encoder, TextEncoder};
+use near_primitives::borsh::{self, BorshDeserialize};
 use near_primitives::hash::CryptoHash;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::{AccountId, BlockHeight, BlockId, BlockReference};
-use near_primitives::views::{QueryRequest, TxExecutionStatus};
+use near_primitives::types::{
+    AccountId, BlockHeight, BlockId, BlockReference, TransactionOrReceiptId,
+};
+use near_primitives::views::{
+    BlockHeightStatus, FinalExecutionOutcomeViewEnum, QueryRequest, TxExecutionStatus,
+};
 use serde_json::{json, Value};
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -45,6 +55,7 @@ use tokio::time::{sleep, timeout};
 use tracing::{error, info};
 
 mod api;
+mod graphql;
 mod metrics;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
@@ -95,6 +106,9 @@ pub struct RpcConfig {
     // be read from this directory, instead of the contents compiled into the binary. This allows
     // for quick iterative development.
     pub experimental_debug_pages_src_path: Option<String>,
+    // If true, serve the read-only GraphQL API at `/graphql`.
+    #[serde(default)]
+    pub enable_graphql: bool,
 }
 
 impl Default for RpcConfig {
@@ -107,6 +121,7 @@ impl Default for RpcConfig {
             limits_config: Default::default(),
             enable_debug_rpc: false,
             experimental_debug_pages_src_path: None,
+            enable_graphql: false,
         }
     }
 }
@@ -221,8 +236,12 @@ fn process_query_response(
 #[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
 pub struct ClientSenderForRpc(
     AsyncSender<DebugStatus, ActixResult<DebugStatus>>,
+    AsyncSender<ExportTransactionPool, ActixResult<ExportTransactionPool>>,
+    AsyncSender<FlatStorageControl, ActixResult<FlatStorageControl>>,
     AsyncSender<GetClientConfig, ActixResult<GetClientConfig>>,
     AsyncSender<GetNetworkInfo, ActixResult<GetNetworkInfo>>,
+    AsyncSender<GetShardRpcEndpoints, ActixResult<GetShardRpcEndpoints>>,
+    AsyncSender<GetWatchedAccounts, ActixResult<GetWatchedAccounts>>,
     AsyncSender<ProcessTxRequest, ActixResult<ProcessTxRequest>>,
     AsyncSender<Status, ActixResult<Status>>,
     Sender<ProcessTxRequest>,
@@ -242,9 +261,14 @@ pub struct ClientSenderForRpc(
 #[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
 pub struct ViewClientSenderForRpc(
     AsyncSender<GetBlock, ActixResult<GetBlock>>,
+    AsyncSender<GetBlockHeightStatus, ActixResult<GetBlockHeightStatus>>,
     AsyncSender<GetBlockProof, ActixResult<GetBlockProof>>,
+    AsyncSender<GetBlockReceipts, ActixResult<GetBlockReceipts>>,
     AsyncSender<GetChunk, ActixResult<GetChunk>>,
+    AsyncSender<GetChunkValidators, ActixResult<GetChunkValidators>>,
+    AsyncSender<GetEpochShardStats, ActixResult<GetEpochShardStats>>,
     AsyncSender<GetExecutionOutcome, ActixResult<GetExecutionOutcome>>,
+    AsyncSender<GetForkInfo, ActixResult<GetForkInfo>>,
     AsyncSender<GetGasPrice, ActixResult<GetGasPrice>>,
     AsyncSender<GetMaintenanceWindows, ActixResult<GetMaintenanceWindows>>,
     AsyncSender<GetNextLightClientBlock, ActixResult<GetNextLightClientBlock>>,
@@ -253,6 +277,7 @@ pub struct ViewClientSenderForRpc(
     AsyncSender<GetSplitStorageInfo, ActixResult<GetSplitStorageInfo>>,
     AsyncSender<GetStateChanges, ActixResult<GetStateChanges>>,
     AsyncSender<GetStateChangesInBlock, ActixResult<GetStateChangesInBlock>>,
+    AsyncSender<GetStateWitnesses, ActixResult<GetStateWitnesses>>,
     AsyncSender<GetValidatorInfo, ActixResult<GetValidatorInfo>>,
     AsyncSender<GetValidatorOrdered, ActixResult<GetValidatorOrdered>>,
     AsyncSender<Query, ActixResult<Query>>,
@@ -270,7 +295,10 @@ pub struct GCSenderForRpc(
 );
 
 #[derive(Clone, near_async::MultiSend, near_async::MultiSenderFrom)]
-pub struct PeerManagerSenderForRpc(AsyncSender<GetDebugStatus, ActixResult<GetDebugStatus>>);
+pub struct PeerManagerSenderForRpc(
+    AsyncSender<GetDebugStatus, ActixResult<GetDebugStatus>>,
+    AsyncSender<PeerAccessControl, ActixResult<PeerAccessControl>>,
+);
 
 struct JsonRpcHandler {
     client_sender: ClientSenderForRpc,
@@ -283,29 +311,41 @@ struct JsonRpcHandler {
     enable_debug_rpc: bool,
     debug_pages_src_path: Option<PathBuf>,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
+    compaction_handler: Arc<dyn CompactionHandler>,
+    host_call_trace_handler: Arc<dyn HostCallTraceHandler>,
 }
 
 impl JsonRpcHandler {
-    async fn process(&self, message: Message) -> Message {
+    /// Processes the message. Returns the response and, if the message was a
+    /// request, its metrics name.
+    async fn process(&self, message: Message) -> (Option<String>, Message) {
         let id = message.id();
         match message {
-            Message::Request(request) => Message::response(id, self.process_request(request).await),
-            _ => Message::error(RpcError::parse_error(
-                "JSON RPC Request format was expected".to_owned(),
-            )),
+            Message::Request(request) => {
+                let (metrics_name, response) = self.process_request(request).await;
+                (Some(metrics_name), Message::response(id, response))
+            }
+            _ => (
+                None,
+                Message::error(RpcError::parse_error(
+                    "JSON RPC Request format was expected".to_owned(),
+                )),
+            ),
         }
     }
 
     // `process_request` increments affected metrics but the request processing is done by
     // `process_request_internal`.
-    async fn process_request(&self, request: Request) -> Result<Value, RpcError> {
+    async fn process_request(&self, request: Request) -> (String, Result<Value, RpcError>) {
         let timer = Instant::now();
+        let request_size = json_size(&request.params);
         let (metrics_name, response) = self.process_request_internal(request).await;
 
         metrics::HTTP_RPC_REQUEST_COUNT.with_label_values(&[&metrics_name]).inc();
         metrics::RPC_PROCESSING_TIME
             .with_label_values(&[&metrics_name])
             .observe(timer.elapsed().as_secs_f64());
+        metrics::RPC_REQUEST_SIZE.with_label_values(&[&metrics_name]).observe(request_size as f64);
 
         if let Err(err) = &response {
             metrics::RPC_ERROR_COUNT
@@ -313,7 +353,7 @@ impl JsonRpcHandler {
                 .inc();
         }
 
-        response
+        (metrics_name, response)
     }
 
     /// Processes the request without updating any metrics.
@@ -341,7 +381,13 @@ impl JsonRpcHandler {
                     Err(err) => return (method_name, Err(RpcError::from(err))),
                 };
                 let metrics_name = match params.request {
-                    QueryRequest::ViewAccount { .. } => "query_view_account",
+                    QueryRequest::ViewAccount { include_proof, .. } => {
+                        if include_proof {
+                            "query_view_account_with_proof"
+                        } else {
+                            "query_view_account"
+                        }
+                    }
                     QueryRequest::ViewCode { .. } => "query_view_code",
                     QueryRequest::ViewState { include_proof, .. } => {
                         if include_proof {
@@ -369,6 +415,9 @@ impl JsonRpcHandler {
         Ok(match request.method.as_ref() {
             // Handlers ordered alphabetically
             "block" => process_method_call(request, |params| self.block(params)).await,
+            "block_exists" => {
+                process_method_call(request, |params| self.block_exists(params)).await
+            }
             "broadcast_tx_async" => {
                 process_method_call(request, |params| async {
                     let tx = self.send_tx_async(params).await.to_string();
@@ -401,6 +450,9 @@ impl JsonRpcHandler {
             "client_config" => {
                 process_method_call(request, |_params: ()| self.client_config()).await
             }
+            "EXPERIMENTAL_block_receipts" => {
+                process_method_call(request, |params| self.block_receipts(params)).await
+            }
             "EXPERIMENTAL_changes" => {
                 process_method_call(request, |params| self.changes_in_block_by_type(params)).await
             }
@@ -437,12 +489,24 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_validators_ordered" => {
                 process_method_call(request, |params| self.validators_ordered(params)).await
             }
+            "EXPERIMENTAL_epoch_shard_stats" => {
+                process_method_call(request, |params| self.epoch_shard_stats(params)).await
+            }
             "EXPERIMENTAL_maintenance_windows" => {
                 process_method_call(request, |params| self.maintenance_windows(params)).await
             }
+            "EXPERIMENTAL_watched_accounts" => {
+                process_method_call(request, |params| self.watched_accounts(params)).await
+            }
             "EXPERIMENTAL_split_storage_info" => {
                 process_method_call(request, |params| self.split_storage_info(params)).await
             }
+            "EXPERIMENTAL_state_witness" => {
+                process_method_call(request, |params| self.state_witness(params)).await
+            }
+            "EXPERIMENTAL_fork_info" => {
+                process_method_call(request, |params| self.fork_info(params)).await
+            }
             #[cfg(feature = "sandbox")]
             "sandbox_patch_state" => {
                 process_method_call(request, |params| self.sandbox_patch_state(params)).await
@@ -693,16 +757,21 @@ impl JsonRpcHandler {
             return Ok(RpcTransactionResponse {
                 final_execution_outcome: None,
                 final_execution_status: TxExecutionStatus::None,
+                outcome_proofs: vec![],
             });
         }
         let tx = request_data.signed_transaction;
         match self.send_tx_internal(tx.clone(), false).await? {
             ProcessTxResponse::ValidTx | ProcessTxResponse::RequestRouted => {
-                self.tx_status_fetch(
+                let mut response = self.tx_status_fetch(
                     near_jsonrpc_primitives::types::transactions::TransactionInfo::from_signed_tx(tx.clone()),
                     request_data.wait_until,
                     false,
-                ).await
+                ).await?;
+                if request_data.include_proof {
+                    self.attach_outcome_proofs(&mut response).await?;
+                }
+                Ok(response)
             }
             network_client_response=> {
                 Err(
@@ -724,6 +793,7 @@ impl JsonRpcHandler {
         self.send_tx(RpcSendTransactionRequest {
             signed_transaction: request_data.signed_transaction,
             wait_until: TxExecutionStatus::ExecutedOptimistic,
+            include_proof: request_data.include_proof,
         })
         .await
     }
@@ -797,6 +867,9 @@ impl JsonRpcHandler {
                     "/debug/api/requested_state_parts" => {
                         self.client_send(DebugStatus::RequestedStateParts).await?.rpc_into()
                     }
+                    "/debug/api/flat_storage_status" => {
+                        self.client_send(DebugStatus::FlatStorageStatus).await?.rpc_into()
+                    }
                     "/debug/api/peer_store" => self
                         .peer_manager_send(near_network::debug::GetDebugStatus::PeerStore)
                         .await?
@@ -819,6 +892,14 @@ impl JsonRpcHandler {
                         .peer_manager_send(near_network::debug::GetDebugStatus::SnapshotHosts)
                         .await?
                         .rpc_into(),
+                    "/debug/api/tier1" => self
+                        .peer_manager_send(near_network::debug::GetDebugStatus::Tier1)
+                        .await?
+                        .rpc_into(),
+                    "/debug/api/peer_access_list" => self
+                        .peer_manager_send(near_network::debug::GetDebugStatus::AccessList)
+                        .await?
+                        .rpc_into(),
                     "/debug/api/split_store_info" => {
                         let split_storage_info: RpcSplitStorageInfoResponse = self
                             .split_storage_info(RpcSplitStorageInfoRequest {})
@@ -854,6 +935,100 @@ impl JsonRpcHandler {
         }
     }
 
+    pub async fn flat_storage_control(
+        &self,
+        request: FlatStorageControl,
+    ) -> Result<Option<FlatStorageStatusView>, near_jsonrpc_primitives::types::status::RpcStatusError>
+    {
+        if self.enable_debug_rpc {
+            let status = self.client_send(request).await?;
+            Ok(Some(status))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn peer_access_control(
+        &self,
+        request: PeerAccessControl,
+    ) -> Result<
+        Option<near_jsonrpc_primitives::types::status::DebugStatusResponse>,
+        near_jsonrpc_primitives::types::status::RpcStatusError,
+    > {
+        if self.enable_debug_rpc {
+            Ok(Some(self.peer_manager_send(request).await?.rpc_into()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn export_tx_pool(
+        &self,
+    ) -> Result<
+        Option<near_jsonrpc_primitives::types::tx_pool::TransactionPoolDump>,
+        near_jsonrpc_primitives::types::status::RpcStatusError,
+    > {
+        if !self.enable_debug_rpc {
+            return Ok(None);
+        }
+        let transactions = self
+            .client_sender
+            .send_async(ExportTransactionPool {})
+            .await
+            .map_err(RpcFrom::rpc_from)?
+            .into_iter()
+            .map(|tx| near_primitives::serialize::to_base64(&borsh::to_vec(&tx).unwrap()))
+            .collect();
+        Ok(Some(near_jsonrpc_primitives::types::tx_pool::TransactionPoolDump { transactions }))
+    }
+
+    /// Submits the transactions of a pool exported from another node, as if
+    /// they were sent with `send_tx`. Each transaction is validated against
+    /// the current state, so the ones which were included meanwhile or became
+    /// invalid are rejected.
+    pub async fn import_tx_pool(
+        &self,
+        dump: near_jsonrpc_primitives::types::tx_pool::TransactionPoolDump,
+    ) -> Option<near_jsonrpc_primitives::types::tx_pool::TransactionPoolImportResult> {
+        use near_jsonrpc_primitives::types::tx_pool::{
+            RejectedTransaction, TransactionPoolImportResult,
+        };
+
+        if !self.enable_debug_rpc {
+            return None;
+        }
+        let mut result = TransactionPoolImportResult::default();
+        for (index, encoded) in dump.transactions.into_iter().enumerate() {
+            let tx = match near_primitives::serialize::from_base64(&encoded)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| {
+                    SignedTransaction::try_from_slice(&bytes).map_err(|err| err.to_string())
+                }) {
+                Ok(tx) => tx,
+                Err(err) => {
+                    let error = format!("Failed to decode transaction: {}", err);
+                    result.rejected.push(RejectedTransaction { index, hash: None, error });
+                    continue;
+                }
+            };
+            let hash = Some(tx.get_hash());
+            let error = match self.send_tx_internal(tx, false).await {
+                Ok(ProcessTxResponse::ValidTx | ProcessTxResponse::RequestRouted) => {
+                    result.accepted += 1;
+                    continue;
+                }
+                Ok(ProcessTxResponse::InvalidTx(err)) => err.to_string(),
+                Ok(ProcessTxResponse::DoesNotTrackShard) => {
+                    "The node does not track the shard of the transaction".to_string()
+                }
+                Ok(ProcessTxResponse::NoResponse) => "Timed out".to_string(),
+                Err(err) => err.to_string(),
+            };
+            result.rejected.push(RejectedTransaction { index, hash, error });
+        }
+        Some(result)
+    }
+
     pub async fn protocol_config(
         &self,
         request_data: near_jsonrpc_primitives::types::config::RpcProtocolConfigRequest,
@@ -873,9 +1048,31 @@ impl JsonRpcHandler {
         near_jsonrpc_primitives::types::query::RpcQueryResponse,
         near_jsonrpc_primitives::types::query::RpcQueryError,
     > {
-        let query_response = self
+        let query_response = match self
             .view_client_send(Query::new(request_data.block_reference, request_data.request))
-            .await?;
+            .await
+        {
+            Ok(query_response) => query_response,
+            Err(near_jsonrpc_primitives::types::query::RpcQueryError::UnavailableShard {
+                requested_shard_id,
+                ..
+            }) => {
+                // Point the caller to the peers which can answer the query.
+                // Failing to get them is not worth masking the actual error.
+                let rpc_endpoints = self
+                    .client_sender
+                    .send_async(GetShardRpcEndpoints { shard_id: requested_shard_id })
+                    .await
+                    .unwrap_or_default();
+                return Err(
+                    near_jsonrpc_primitives::types::query::RpcQueryError::UnavailableShard {
+                        requested_shard_id,
+                        rpc_endpoints,
+                    },
+                );
+            }
+            Err(err) => return Err(err),
+        };
         Ok(query_response.rpc_into())
     }
 
@@ -887,12 +1084,65 @@ impl JsonRpcHandler {
         near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
         near_jsonrpc_primitives::types::transactions::RpcTransactionError,
     > {
-        let tx_status = self
+        let mut tx_status = self
             .tx_status_fetch(request_data.transaction_info, request_data.wait_until, fetch_receipt)
             .await?;
+        if request_data.include_proof {
+            self.attach_outcome_proofs(&mut tx_status).await?;
+        }
         Ok(tx_status.rpc_into())
     }
 
+    /// Attaches the proofs of the outcomes of the transaction and of the
+    /// receipts it was converted to. Outcomes which the node can't prove, e.g.
+    /// because they are not included in the outcome root of any block yet, are
+    /// in an untracked shard or were garbage collected, are skipped.
+    async fn attach_outcome_proofs(
+        &self,
+        response: &mut near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
+    ) -> Result<(), near_jsonrpc_primitives::types::transactions::RpcTransactionError> {
+        let outcome = match &response.final_execution_outcome {
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome)) => outcome,
+            Some(FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome)) => {
+                &outcome.final_outcome
+            }
+            None => return Ok(()),
+        };
+        let transaction = &outcome.transaction;
+        let ids = std::iter::once(TransactionOrReceiptId::Transaction {
+            transaction_hash: transaction.hash,
+            sender_id: transaction.signer_id.clone(),
+        })
+        .chain(outcome.transaction_outcome.outcome.receipt_ids.iter().map(|receipt_id| {
+            TransactionOrReceiptId::Receipt {
+                receipt_id: *receipt_id,
+                receiver_id: transaction.receiver_id.clone(),
+            }
+        }))
+        .collect::<Vec<_>>();
+        let mut outcome_proofs = vec![];
+        for id in ids {
+            match self
+                .view_client_sender
+                .send_async(GetExecutionOutcome { id })
+                .await
+                .map_err(RpcFrom::rpc_from)?
+            {
+                Ok(proof) => outcome_proofs.push(
+                    near_jsonrpc_primitives::types::transactions::RpcExecutionOutcomeProof {
+                        outcome_proof: proof.outcome_proof,
+                        outcome_root_proof: proof.outcome_root_proof,
+                    },
+                ),
+                Err(err) => {
+                    tracing::debug!(target: "jsonrpc", ?err, "skipping outcome proof");
+                }
+            }
+        }
+        response.outcome_proofs = outcome_proofs;
+        Ok(())
+    }
+
     async fn block(
         &self,
         request_data: near_jsonrpc_primitives::types::blocks::RpcBlockRequest,
@@ -904,6 +1154,24 @@ impl JsonRpcHandler {
         Ok(near_jsonrpc_primitives::types::blocks::RpcBlockResponse { block_view })
     }
 
+    /// Tells whether the node has a block at the given height and, if it
+    /// doesn't, whether the height was skipped, garbage collected or not
+    /// reached yet.
+    async fn block_exists(
+        &self,
+        request_data: near_jsonrpc_primitives::types::blocks::RpcBlockExistsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::blocks::RpcBlockExistsResponse,
+        near_jsonrpc_primitives::types::blocks::RpcBlockExistsError,
+    > {
+        let block_height_status =
+            self.view_client_send(GetBlockHeightStatus { height: request_data.height }).await?;
+        Ok(near_jsonrpc_primitives::types::blocks::RpcBlockExistsResponse {
+            exists: matches!(block_height_status.status, BlockHeightStatus::Available { .. }),
+            block_height_status,
+        })
+    }
+
     async fn chunk(
         &self,
         request_data: near_jsonrpc_primitives::types::chunks::RpcChunkRequest,
@@ -951,6 +1219,42 @@ impl JsonRpcHandler {
         })
     }
 
+    async fn block_receipts(
+        &self,
+        request_data: near_jsonrpc_primitives::types::receipts::RpcBlockReceiptsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::receipts::RpcBlockReceiptsResponse,
+        near_jsonrpc_primitives::types::receipts::RpcBlockReceiptsError,
+    > {
+        let block_receipts_view =
+            self.view_client_send(GetBlockReceipts::rpc_from(request_data)).await?;
+        Ok(near_jsonrpc_primitives::types::receipts::RpcBlockReceiptsResponse {
+            block_receipts_view,
+        })
+    }
+
+    async fn state_witness(
+        &self,
+        request_data: near_jsonrpc_primitives::types::state_witness::RpcStateWitnessRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::state_witness::RpcStateWitnessResponse,
+        near_jsonrpc_primitives::types::state_witness::RpcStateWitnessError,
+    > {
+        let witnesses = self.view_client_send(GetStateWitnesses::rpc_from(request_data)).await?;
+        Ok(near_jsonrpc_primitives::types::state_witness::RpcStateWitnessResponse { witnesses })
+    }
+
+    async fn fork_info(
+        &self,
+        request_data: near_jsonrpc_primitives::types::fork_info::RpcForkInfoRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::fork_info::RpcForkInfoResponse,
+        near_jsonrpc_primitives::types::fork_info::RpcForkInfoError,
+    > {
+        let fork_info = self.view_client_send(GetForkInfo::rpc_from(request_data)).await?;
+        Ok(near_jsonrpc_primitives::types::fork_info::RpcForkInfoResponse { fork_info })
+    }
+
     async fn receipt(
         &self,
         request_data: near_jsonrpc_primitives::types::receipts::RpcReceiptRequest,
@@ -1126,10 +1430,24 @@ impl JsonRpcHandler {
         near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedResponse,
         near_jsonrpc_primitives::types::validator::RpcValidatorError,
     > {
-        let near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest { block_id } =
-            request;
-        let validators = self.view_client_send(GetValidatorOrdered { block_id }).await?;
-        Ok(validators)
+        use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedResponse;
+
+        let near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest {
+            block_id,
+            include_chunk_validators,
+        } = request;
+        if !include_chunk_validators {
+            let validators = self.view_client_send(GetValidatorOrdered { block_id }).await?;
+            return Ok(RpcValidatorsOrderedResponse::BlockProducers(validators));
+        }
+        // Resolve the block once so that both parts of the response refer to the same block.
+        let chunk_validators = self.view_client_send(GetChunkValidators { block_id }).await?;
+        let block_producers = self
+            .view_client_send(GetValidatorOrdered {
+                block_id: Some(BlockId::Hash(chunk_validators.block_hash)),
+            })
+            .await?;
+        Ok(RpcValidatorsOrderedResponse::WithChunkValidators { block_producers, chunk_validators })
     }
 
     /// If experimental_debug_pages_src_path config is set, reads the html file from that
@@ -1160,6 +1478,39 @@ impl JsonRpcHandler {
         Ok(windows.iter().map(|r| (r.start, r.end)).collect())
     }
 
+    /// Returns the statistics of the shards saved at the end of the epoch.
+    /// Empty unless the node has `save_epoch_shard_stats` enabled.
+    async fn epoch_shard_stats(
+        &self,
+        request: near_jsonrpc_primitives::types::epoch_shard_stats::RpcEpochShardStatsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::epoch_shard_stats::RpcEpochShardStatsResponse,
+        near_jsonrpc_primitives::types::epoch_shard_stats::RpcEpochShardStatsError,
+    > {
+        let near_jsonrpc_primitives::types::epoch_shard_stats::RpcEpochShardStatsRequest {
+            epoch_id,
+        } = request;
+        Ok(self.view_client_send(GetEpochShardStats { epoch_id }).await?)
+    }
+
+    /// Returns the data collected for the accounts listed in `watched_accounts`
+    /// of the node config.
+    async fn watched_accounts(
+        &self,
+        request: near_jsonrpc_primitives::types::watched_accounts::RpcWatchedAccountsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::watched_accounts::RpcWatchedAccountsResponse,
+        near_jsonrpc_primitives::types::watched_accounts::RpcWatchedAccountsError,
+    > {
+        let near_jsonrpc_primitives::types::watched_accounts::RpcWatchedAccountsRequest {
+            account_ids,
+        } = request;
+        self.client_sender
+            .send_async(GetWatchedAccounts { account_ids })
+            .await
+            .map_err(RpcFrom::rpc_from)
+    }
+
     async fn client_config(
         &self,
     ) -> Result<
@@ -1192,9 +1543,10 @@ impl JsonRpcHandler {
         near_jsonrpc_primitives::types::sandbox::RpcSandboxPatchStateError,
     > {
         self.client_sender
-            .send_async(near_client_primitives::types::SandboxMessage::SandboxPatchState(
-                patch_state_request.records,
-            ))
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxPatchState {
+                records: patch_state_request.records,
+                deletions: patch_state_request.deletions,
+            })
             .await
             .map_err(RpcFrom::rpc_from)?;
 
@@ -1355,17 +1707,16 @@ async fn rpc_handler(
     message: web::Json<Message>,
     handler: web::Data<JsonRpcHandler>,
 ) -> HttpResponse {
-    let message = handler.process(message.0).await;
+    let (metrics_name, message) = handler.process(message.0).await;
     let mut response = if let Message::Response(response) = &message {
         match &response.result {
             Ok(_) => HttpResponse::Ok(),
             Err(err) => match &err.error_struct {
                 Some(RpcErrorKind::RequestValidationError(_)) => HttpResponse::BadRequest(),
-                Some(RpcErrorKind::HandlerError(error_struct)) => {
-                    if error_struct["name"] == "TIMEOUT_ERROR" {
-                        HttpResponse::RequestTimeout()
-                    } else {
-                        HttpResponse::Ok()
+                Some(RpcErrorKind::HandlerError(_)) => {
+                    match err.error_info.map(|error_info| error_info.category) {
+                        Some(RpcErrorCategory::Timeout) => HttpResponse::RequestTimeout(),
+                        _ => HttpResponse::Ok(),
                     }
                 }
                 Some(RpcErrorKind::InternalError(_)) => HttpResponse::InternalServerError(),
@@ -1375,7 +1726,38 @@ async fn rpc_handler(
     } else {
         HttpResponse::InternalServerError()
     };
-    response.json(message)
+    // Serialize the response here rather than with `HttpResponseBuilder::json`
+    // to know its size.
+    let body = match serde_json::to_vec(&message) {
+        Ok(body) => body,
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+    if let Some(metrics_name) = metrics_name {
+        metrics::RPC_RESPONSE_SIZE.with_label_values(&[&metrics_name]).observe(body.len() as f64);
+    }
+    response.insert_header(header::ContentType::json()).body(body)
+}
+
+/// Returns the length of the JSON serialization of the value, without
+/// allocating it.
+fn json_size(value: &Value) -> usize {
+    struct ByteCounter(usize);
+
+    impl std::io::Write for ByteCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = ByteCounter(0);
+    // Serializing a `Value` can't fail.
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
 }
 
 async fn status_handler(handler: web::Data<JsonRpcHandler>) -> Result<HttpResponse, HttpError> {
@@ -1416,6 +1798,118 @@ async fn handle_entity_debug(
     }
 }
 
+async fn compaction_status_handler(
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.enable_debug_rpc {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+    match handler.compaction_handler.status() {
+        Ok(value) => Ok(HttpResponse::Ok().json(&value)),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
+async fn compaction_schedule_handler(
+    req: web::Json<CompactionRequest>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.enable_debug_rpc {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+    match handler.compaction_handler.schedule(req.0) {
+        Ok(value) => Ok(HttpResponse::Ok().json(&value)),
+        Err(err) => Ok(HttpResponse::BadRequest().body(format!("{:?}", err))),
+    }
+}
+
+async fn host_call_trace_status_handler(
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.enable_debug_rpc {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+    match handler.host_call_trace_handler.status() {
+        Ok(value) => Ok(HttpResponse::Ok().json(&value)),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
+async fn host_call_trace_receivers_handler(
+    req: web::Json<HostCallTraceRequest>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.enable_debug_rpc {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+    match handler.host_call_trace_handler.set_receivers(req.0) {
+        Ok(value) => Ok(HttpResponse::Ok().json(&value)),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
+async fn host_call_trace_handler(
+    path: web::Path<CryptoHash>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    if !handler.enable_debug_rpc {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+    match handler.host_call_trace_handler.trace(*path) {
+        Ok(value) => Ok(HttpResponse::Ok().json(&value)),
+        Err(err) => Ok(HttpResponse::NotFound().body(format!("{:?}", err))),
+    }
+}
+
+async fn flat_storage_control_handler(
+    req: web::Json<FlatStorageControl>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    match handler.flat_storage_control(req.0).await {
+        Ok(Some(value)) => Ok(HttpResponse::Ok().json(&value)),
+        Ok(None) => Ok(HttpResponse::MethodNotAllowed().finish()),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
+async fn peer_access_control_handler(
+    req: web::Json<PeerAccessControl>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    match handler.peer_access_control(req.0).await {
+        Ok(Some(value)) => Ok(HttpResponse::Ok().json(&value)),
+        Ok(None) => Ok(HttpResponse::MethodNotAllowed().finish()),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
+async fn tx_pool_export_handler(
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    match handler.export_tx_pool().await {
+        Ok(Some(value)) => Ok(HttpResponse::Ok().json(&value)),
+        Ok(None) => Ok(HttpResponse::MethodNotAllowed().finish()),
+        Err(err) => Ok(HttpResponse::ServiceUnavailable().body(format!("{:?}", err))),
+    }
+}
+
+async fn tx_pool_import_handler(
+    req: web::Json<near_jsonrpc_primitives::types::tx_pool::TransactionPoolDump>,
+    handler: web::Data<JsonRpcHandler>,
+) -> Result<HttpResponse, HttpError> {
+    match handler.import_tx_pool(req.0).await {
+        Some(value) => Ok(HttpResponse::Ok().json(&value)),
+        None => Ok(HttpResponse::MethodNotAllowed().finish()),
+    }
+}
+
+async fn graphql_handler(
+    request: web::Json<async_graphql::Request>,
+    schema: web::Data<graphql::GraphQLSchema>,
+) -> Result<HttpResponse, HttpError> {
+    Ok(HttpResponse::Ok().json(schema.execute(request.into_inner()).await))
+}
+
 async fn debug_block_status_handler(
     path: web::Path<u64>,
     handler: web::Data<JsonRpcHandler>,
@@ -1443,12 +1937,28 @@ async fn network_info_handler(
     }
 }
 
-pub async fn prometheus_handler() -> Result<HttpResponse, HttpError> {
+#[derive(serde::Deserialize)]
+pub struct MetricsQuery {
+    /// Comma separated prefixes of the names of the metrics to export, e.g.
+    /// `near_rpc_,near_block_`. All metrics are exported if not set.
+    prefix: Option<String>,
+}
+
+pub async fn prometheus_handler(
+    query: web::Query<MetricsQuery>,
+) -> Result<HttpResponse, HttpError> {
     metrics::PROMETHEUS_REQUEST_COUNT.inc();
 
+    let mut metric_families = prometheus::gather();
+    if let Some(prefix) = &query.prefix {
+        let prefixes: Vec<&str> = prefix.split(',').filter(|prefix| !prefix.is_empty()).collect();
+        metric_families
+            .retain(|family| prefixes.iter().any(|prefix| family.get_name().starts_with(prefix)));
+    }
+
     let mut buffer = vec![];
     let encoder = TextEncoder::new();
-    encoder.encode(&prometheus::gather(), &mut buffer).unwrap();
+    encoder.encode(&metric_families, &mut buffer).unwrap();
 
     match String::from_utf8(buffer) {
         Ok(text) => Ok(HttpResponse::Ok().body(text)),
@@ -1550,6 +2060,8 @@ pub fn start_http(
     peer_manager_sender: PeerManagerSenderForRpc,
     #[cfg(feature = "test_features")] gc_sender: GCSenderForRpc,
     entity_debug_handler: Arc<dyn EntityDebugHandler>,
+    compaction_handler: Arc<dyn CompactionHandler>,
+    host_call_trace_handler: Arc<dyn HostCallTraceHandler>,
 ) -> Vec<(&'static str, actix_web::dev::ServerHandle)> {
     let RpcConfig {
         addr,
@@ -1559,7 +2071,9 @@ pub fn start_http(
         limits_config,
         enable_debug_rpc,
         experimental_debug_pages_src_path: debug_pages_src_path,
+        enable_graphql,
     } = config;
+    let graphql_schema = enable_graphql.then(|| graphql::schema(view_client_sender.clone()));
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr.to_string());
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
     info!(target:"network", "Starting http server at {}", addr);
@@ -1576,6 +2090,8 @@ pub fn start_http(
                 enable_debug_rpc,
                 debug_pages_src_path: debug_pages_src_path.clone().map(Into::into),
                 entity_debug_handler: entity_debug_handler.clone(),
+                compaction_handler: compaction_handler.clone(),
+                host_call_trace_handler: host_call_trace_handler.clone(),
                 #[cfg(feature = "test_features")]
                 gc_sender: gc_sender.clone(),
             }))
@@ -1595,6 +2111,33 @@ pub fn start_http(
             .service(web::resource("/network_info").route(web::get().to(network_info_handler)))
             .service(web::resource("/metrics").route(web::get().to(prometheus_handler)))
             .service(web::resource("/debug/api/entity").route(web::post().to(handle_entity_debug)))
+            .service(
+                web::resource("/debug/api/flat_storage_control")
+                    .route(web::post().to(flat_storage_control_handler)),
+            )
+            .service(
+                web::resource("/debug/api/compaction")
+                    .route(web::get().to(compaction_status_handler))
+                    .route(web::post().to(compaction_schedule_handler)),
+            )
+            .service(
+                web::resource("/debug/api/host_call_trace")
+                    .route(web::get().to(host_call_trace_status_handler))
+                    .route(web::post().to(host_call_trace_receivers_handler)),
+            )
+            .service(
+                web::resource("/debug/api/host_call_trace/{receipt_id}")
+                    .route(web::get().to(host_call_trace_handler)),
+            )
+            .service(
+                web::resource("/debug/api/peer_access_control")
+                    .route(web::post().to(peer_access_control_handler)),
+            )
+            .service(
+                web::resource("/debug/api/tx_pool")
+                    .route(web::get().to(tx_pool_export_handler))
+                    .route(web::post().to(tx_pool_import_handler)),
+            )
             .service(web::resource("/debug/api/{api}").route(web::get().to(debug_handler)))
             .service(
                 web::resource("/debug/api/block_status/{starting_height}")
@@ -1605,6 +2148,12 @@ pub fn start_http(
             )
             .service(debug_html)
             .service(display_debug_html)
+            .configure(|cfg| {
+                if let Some(graphql_schema) = &graphql_schema {
+                    cfg.app_data(web::Data::new(graphql_schema.clone()))
+                        .service(web::resource("/graphql").route(web::post().to(graphql_handler)));
+                }
+            })
     });
 
     match listener.listen(addr.std_listener().unwrap()) {
@@ -10,6 +10,24 @@ pub static RPC_PROCESSING_TIME: LazyLock<HistogramVec> = LazyLock::new(|| {
     )
     .unwrap()
 });
+pub static RPC_REQUEST_SIZE: LazyLock<HistogramVec> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_histogram_vec(
+        "near_rpc_request_size_bytes",
+        "Size of the params of rpc queries",
+        &["method"],
+        Some(exponential_buckets(64.0, 4.0, 9).unwrap()),
+    )
+    .unwrap()
+});
+pub static RPC_RESPONSE_SIZE: LazyLock<HistogramVec> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_histogram_vec(
+        "near_rpc_response_size_bytes",
+        "Size of the responses to rpc queries",
+        &["method"],
+        Some(exponential_buckets(64.0, 4.0, 10).unwrap()),
+    )
+    .unwrap()
+});
 pub static RPC_TIMEOUT_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
     near_o11y::metrics::try_create_int_counter(
         "near_rpc_timeout_total",
@@ -0,0 +1,274 @@
+//! Peers which the node refuses to talk to, or exclusively talks to.
+//!
+//! Rules come from two sources: a JSON file (see `NetworkConfig::access_list_file`),
+//! which the PeerManager re-reads periodically and re-applies whenever it
+//! changes, and bans issued by the operator through the admin API, which last
+//! until they are lifted or the node restarts.
+//!
+//! A rule matches either a PeerId or a range of IP addresses. A peer matching
+//! any deny rule or ban is refused. If the allow list is not empty, the peers
+//! matching none of its rules are refused as well.
+
+use anyhow::Context as _;
+use near_crypto::PublicKey;
+use near_primitives::network::PeerId;
+use near_primitives::views::PeerAccessListView;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::Path;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseRuleError {
+    #[error("{0:?} is neither a peer id, an IP address nor an IP range")]
+    Invalid(String),
+    #[error("invalid prefix length in {0:?}")]
+    PrefixLength(String),
+}
+
+/// Range of IP addresses, like 192.0.2.0/24.
+/// IPv4 addresses are mapped to IPv6, as in the `blacklist` module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpNet {
+    addr: Ipv6Addr,
+    prefix_len: u8,
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn mask(addr: Ipv6Addr, prefix_len: u8) -> Ipv6Addr {
+    let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+    Ipv6Addr::from(u128::from(addr) & mask)
+}
+
+impl IpNet {
+    /// Returns None if `prefix_len` is larger than the size of `ip`.
+    pub fn new(ip: IpAddr, prefix_len: u8) -> Option<Self> {
+        let prefix_len = match ip {
+            IpAddr::V4(_) if prefix_len <= 32 => prefix_len + 96,
+            IpAddr::V6(_) if prefix_len <= 128 => prefix_len,
+            _ => return None,
+        };
+        Some(Self { addr: mask(to_ipv6(ip), prefix_len), prefix_len })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        mask(to_ipv6(ip), self.prefix_len) == self.addr
+    }
+}
+
+impl std::fmt::Display for IpNet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.addr.to_ipv4_mapped() {
+            Some(ip) if self.prefix_len >= 96 => write!(f, "{}/{}", ip, self.prefix_len - 96),
+            _ => write!(f, "{}/{}", self.addr, self.prefix_len),
+        }
+    }
+}
+
+/// Peer id like `ed25519:...`, IP address, or IP range like `192.0.2.0/24`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Rule {
+    Peer(PeerId),
+    Ip(IpNet),
+}
+
+impl Rule {
+    pub fn matches(&self, peer_id: &PeerId, ip: Option<IpAddr>) -> bool {
+        match self {
+            Rule::Peer(id) => id == peer_id,
+            Rule::Ip(net) => ip.is_some_and(|ip| net.contains(ip)),
+        }
+    }
+}
+
+impl std::str::FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((ip, prefix_len)) = s.split_once('/') {
+            let ip: IpAddr = ip.parse().map_err(|_| ParseRuleError::Invalid(s.to_string()))?;
+            return prefix_len
+                .parse()
+                .ok()
+                .and_then(|prefix_len| IpNet::new(ip, prefix_len))
+                .map(Rule::Ip)
+                .ok_or_else(|| ParseRuleError::PrefixLength(s.to_string()));
+        }
+        if let Ok(ip) = s.parse::<IpAddr>() {
+            let prefix_len = if ip.is_ipv4() { 32 } else { 128 };
+            return Ok(Rule::Ip(IpNet::new(ip, prefix_len).unwrap()));
+        }
+        match s.parse::<PublicKey>() {
+            Ok(key) => Ok(Rule::Peer(PeerId::new(key))),
+            Err(_) => Err(ParseRuleError::Invalid(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::Peer(id) => write!(f, "{id}"),
+            Rule::Ip(net) => write!(f, "{net}"),
+        }
+    }
+}
+
+impl serde::Serialize for Rule {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Rule {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = <String as serde::Deserialize>::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Contents of the access list file, for example:
+/// `{"deny": ["ed25519:...", "192.0.2.0/24"], "allow": []}`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(default)]
+    pub allow: Vec<Rule>,
+    #[serde(default)]
+    pub deny: Vec<Rule>,
+}
+
+impl Rules {
+    /// A missing file is the same as an empty one.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s).with_context(|| format!("parsing {path:?}")),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("reading {path:?}")),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct AccessList {
+    rules: Rules,
+    /// Bans issued through the admin API.
+    banned: HashSet<Rule>,
+}
+
+impl AccessList {
+    /// Returns whether the rules have changed.
+    pub fn set_rules(&mut self, rules: Rules) -> bool {
+        if self.rules == rules {
+            return false;
+        }
+        self.rules = rules;
+        true
+    }
+
+    pub fn ban(&mut self, rule: Rule) {
+        self.banned.insert(rule);
+    }
+
+    pub fn unban(&mut self, rule: &Rule) {
+        self.banned.remove(rule);
+    }
+
+    pub fn is_allowed(&self, peer_id: &PeerId, ip: Option<IpAddr>) -> bool {
+        let matches = |rule: &Rule| rule.matches(peer_id, ip);
+        if self.rules.deny.iter().any(matches) || self.banned.iter().any(matches) {
+            return false;
+        }
+        self.rules.allow.is_empty() || self.rules.allow.iter().any(matches)
+    }
+
+    pub fn view(&self) -> PeerAccessListView {
+        fn to_strings<'a>(rules: impl Iterator<Item = &'a Rule>) -> Vec<String> {
+            let mut v: Vec<_> = rules.map(|r| r.to_string()).collect();
+            v.sort();
+            v
+        }
+        PeerAccessListView {
+            allow: to_strings(self.rules.allow.iter()),
+            deny: to_strings(self.rules.deny.iter()),
+            banned: to_strings(self.banned.iter()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessList, Rule, Rules};
+    use crate::network_protocol::testonly as data;
+    use crate::testonly::make_rng;
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let parse = |s: &str| s.parse::<Rule>().map(|r| r.to_string()).ok();
+        assert_eq!(parse("192.0.2.4"), Some("192.0.2.4/32".to_string()));
+        assert_eq!(parse("192.0.2.4/24"), Some("192.0.2.0/24".to_string()));
+        assert_eq!(parse("::ffff:192.0.2.4/120"), Some("192.0.2.0/24".to_string()));
+        assert_eq!(parse("2001:db8::1/32"), Some("2001:db8::/32".to_string()));
+        assert_eq!(parse("0.0.0.0/0"), Some("0.0.0.0/0".to_string()));
+        assert_eq!(parse("192.0.2.4/33"), None);
+        assert_eq!(parse("192.0.2.4:80"), None);
+        assert_eq!(parse("foo"), None);
+
+        let peer_id = data::make_peer_id(&mut make_rng(19283));
+        assert_eq!(parse(&peer_id.to_string()), Some(peer_id.to_string()));
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let mut rng = make_rng(9812734);
+        let a = data::make_peer_id(&mut rng);
+        let b = data::make_peer_id(&mut rng);
+        let rule = |s: &str| s.parse::<Rule>().unwrap();
+
+        let mut list = AccessList::default();
+        assert!(list.is_allowed(&a, ip("192.0.2.4")));
+
+        list.set_rules(Rules { allow: vec![], deny: vec![rule("192.0.2.0/24")] });
+        assert!(!list.is_allowed(&a, ip("192.0.2.4")));
+        assert!(!list.is_allowed(&a, ip("::ffff:192.0.2.5")));
+        assert!(list.is_allowed(&a, ip("192.0.3.4")));
+
+        // With an allow list, only the peers on it are allowed, unless denied.
+        list.set_rules(Rules {
+            allow: vec![Rule::Peer(a.clone()), rule("10.0.0.0/8")],
+            deny: vec![rule("10.0.0.1")],
+        });
+        assert!(list.is_allowed(&a, ip("192.0.2.4")));
+        assert!(!list.is_allowed(&b, ip("192.0.2.4")));
+        assert!(list.is_allowed(&b, ip("10.1.2.3")));
+        assert!(!list.is_allowed(&b, ip("10.0.0.1")));
+
+        list.ban(Rule::Peer(a.clone()));
+        assert!(!list.is_allowed(&a, ip("192.0.2.4")));
+        list.unban(&Rule::Peer(a.clone()));
+        assert!(list.is_allowed(&a, ip("192.0.2.4")));
+        assert!(!list.set_rules(list.rules.clone()));
+        assert_eq!(list.view().banned, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access_list.json");
+        assert_eq!(Rules::load(&path).unwrap(), Rules::default());
+        std::fs::write(&path, r#"{"deny": ["192.0.2.0/24"]}"#).unwrap();
+        assert_eq!(Rules::load(&path).unwrap().deny, vec!["192.0.2.0/24".parse().unwrap()]);
+        std::fs::write(&path, r#"{"deny": ["192.0.2.0/33"]}"#).unwrap();
+        assert!(Rules::load(&path).is_err());
+    }
+}
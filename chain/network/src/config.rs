@@ -1,9 +1,17 @@
 use crate::blacklist;
 use crate::concurrency::rate;
+use crate::dht;
 use crate::network_protocol::PeerAddr;
 use crate::network_protocol::PeerInfo;
+use crate::peer::compression;
+use crate::peer::encryption::EncryptionMode;
 use crate::peer_manager::peer_store;
 use crate::rate_limits::messages_limits;
+use crate::rate_limits::{admission, bandwidth, message_size};
+use crate::reachability;
+use crate::recorder;
+use crate::seen_messages;
+use crate::sim;
 use crate::snapshot_hosts;
 use crate::stun;
 use crate::tcp;
@@ -17,7 +25,7 @@ use near_primitives::network::PeerId;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::types::AccountId;
 use near_primitives::validator_signer::ValidatorSigner;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 /// How much height horizon to give to consider peer up to date.
@@ -101,6 +109,11 @@ pub struct Tier1 {
     /// - a node will try to start outbound TIER1 connections iff `enable_outbound` is true.
     pub enable_inbound: bool,
     pub enable_outbound: bool,
+    /// Routes to use for the TIER1 messages to the given validators, instead
+    /// of the proxies they advertise: the validator itself for a direct
+    /// connection, or proxies trusted by this node.  The node keeps
+    /// connections to them and prefers them over any other.
+    pub pinned_routes: HashMap<AccountId, Vec<PeerAddr>>,
 }
 
 #[derive(Clone)]
@@ -115,10 +128,33 @@ impl SocketOptions {
     }
 }
 
+/// See [`crate::reachability`].
+#[derive(Clone)]
+pub struct ReachabilityConfig {
+    /// Whether to ask the gateway to forward the port of the node.
+    pub port_mapping: bool,
+    /// Whether to check at startup that the node is reachable from the internet.
+    pub check: bool,
+    /// Servers telling the node its external IP, if the gateway doesn't.
+    pub stun_servers: Vec<stun::ServerAddr>,
+    /// How often to check whether the local IP of the node has changed.
+    pub local_ip_check_interval: Option<time::Duration>,
+}
+
+/// Address the node listens on, besides `NetworkConfig::node_addr`.
+#[derive(Clone)]
+pub struct ListenAddr {
+    pub addr: tcp::ListenerAddr,
+    /// Whether the address is sent to the peers in the handshake.
+    pub advertise: bool,
+}
+
 /// Validated configuration for the peer-to-peer manager.
 #[derive(Clone)]
 pub struct NetworkConfig {
     pub node_addr: Option<tcp::ListenerAddr>,
+    /// More addresses to listen on, for nodes on several networks at once.
+    pub extra_listen_addrs: Vec<ListenAddr>,
     pub node_key: SecretKey,
     pub validator: ValidatorConfig,
 
@@ -154,6 +190,9 @@ pub struct NetworkConfig {
     pub max_send_peers: u32,
     /// Duration for checking on stats from the peers.
     pub peer_stats_period: time::Duration,
+    /// How often to ping the TIER2 peers, to route through the ones with lower
+    /// round trip times. `None` ignores the round trip times.
+    pub tier2_ping_interval: Option<time::Duration>,
     /// Time to persist Accounts Id in the router without removing them.
     pub ttl_account_id_router: time::Duration,
     /// Number of hops a message is allowed to travel before being dropped.
@@ -177,6 +216,8 @@ pub struct NetworkConfig {
     pub inbound_disabled: bool,
     /// Whether this is an archival node.
     pub archive: bool,
+    /// Public URL of the JSON-RPC server of this node, advertised to the peers.
+    pub public_rpc_url: Option<String>,
     /// Maximal rate at which SyncAccountsData can be broadcasted.
     pub accounts_data_broadcast_rate_limit: rate::Limit,
     /// Maximal rate at which SyncSnapshotHosts can be broadcasted.
@@ -193,9 +234,50 @@ pub struct NetworkConfig {
     //   * ignoring received deleted edges as well
     pub skip_tombstones: Option<time::Duration>,
 
+    /// Whether to open a direct TIER2 connection to the chunk producer which a
+    /// transaction is forwarded to, if there is none yet. Meant for RPC nodes,
+    /// so that forwarded transactions don't have to travel over multiple hops
+    /// before reaching the chunk producer.
+    pub direct_tx_forwarding: bool,
+
     /// Configuration of rate limits for incoming messages.
     pub received_messages_rate_limits: messages_limits::Config,
 
+    /// Caps on the bandwidth used to talk to the peers.
+    pub bandwidth_limits: bandwidth::Config,
+
+    /// Protection against floods of inbound connections and handshakes.
+    pub admission: admission::Config,
+
+    /// Port mapping and reachability check at startup.
+    pub reachability: ReachabilityConfig,
+
+    /// Whether to encrypt the connections with the peers.
+    pub encryption: EncryptionMode,
+
+    /// Compression of the large messages sent to the peers.
+    pub message_compression: compression::Config,
+
+    /// Size limits of the messages, per kind of message.
+    pub message_size_limits: message_size::Config,
+
+    /// File with the rules of the peer access list, see `crate::access_list`.
+    /// Relative paths are resolved against the home directory when loading the config.
+    pub access_list_file: Option<std::path::PathBuf>,
+
+    /// Discovery of peers through the DHT, see `crate::dht`.
+    pub dht: dht::Config,
+
+    /// Cache of recently seen messages, see `crate::seen_messages`.
+    pub seen_messages: seen_messages::Config,
+
+    /// Recording of the messages exchanged with the peers, see `crate::recorder`.
+    /// Relative paths are resolved against the home directory when loading the config.
+    pub record_traffic: Option<recorder::Config>,
+
+    /// TEST-ONLY: virtual network to use instead of TCP, see `crate::sim`.
+    pub simulation: Option<Arc<sim::Network>>,
+
     #[cfg(test)]
     pub(crate) event_sink:
         near_async::messaging::Sender<crate::peer_manager::peer_manager_actor::Event>,
@@ -270,15 +352,7 @@ impl NetworkConfig {
                     anyhow::bail!("public_addrs: {ip} is not a valid IP. If you wanted to specify a loopback IP, use 127.0.0.1 instead.");
                 }
             } else {
-                // TODO(gprusak): use !ip.is_global() instead, once it is stable.
-                if ip.is_loopback()
-                    || ip.is_unspecified()
-                    || match ip {
-                        std::net::IpAddr::V4(ip) => ip.is_private(),
-                        // TODO(gprusak): use ip.is_unique_local() once stable.
-                        std::net::IpAddr::V6(_) => false,
-                    }
-                {
+                if !reachability::is_public_ip(ip) {
                     anyhow::bail!("public_addrs: {ip} is not a public IP.");
                 }
             }
@@ -290,7 +364,7 @@ impl NetworkConfig {
                 proxies: if !cfg.public_addrs.is_empty() {
                     ValidatorProxies::Static(cfg.public_addrs)
                 } else {
-                    ValidatorProxies::Dynamic(cfg.trusted_stun_servers)
+                    ValidatorProxies::Dynamic(cfg.trusted_stun_servers.clone())
                 },
             },
             node_addr: match cfg.addr.as_str() {
@@ -299,6 +373,19 @@ impl NetworkConfig {
                     addr.parse().context("Failed to parse SocketAddr")?,
                 )),
             },
+            extra_listen_addrs: cfg
+                .extra_addrs
+                .iter()
+                .map(|extra| {
+                    if extra.addr.port() == 0 {
+                        anyhow::bail!("extra_addrs: {} has no port", extra.addr);
+                    }
+                    Ok(ListenAddr {
+                        addr: tcp::ListenerAddr::new(extra.addr),
+                        advertise: extra.advertise,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?,
             peer_store: peer_store::Config {
                 boot_nodes: if cfg.boot_nodes.is_empty() {
                     vec![]
@@ -319,6 +406,7 @@ impl NetworkConfig {
                 connect_only_to_boot_nodes: cfg.experimental.connect_only_to_boot_nodes,
                 ban_window: cfg.ban_window.try_into()?,
                 peer_expiration_duration: cfg.peer_expiration_duration.try_into()?,
+                score_half_life: cfg.peer_score_half_life.try_into()?,
             },
             snapshot_hosts: snapshot_hosts::Config {
                 snapshot_hosts_cache_size: cfg.snapshot_hosts_cache_size,
@@ -355,6 +443,7 @@ impl NetworkConfig {
             archival_peer_connections_lower_bound: cfg.archival_peer_connections_lower_bound,
             max_send_peers: PEERS_RESPONSE_MAX_PEERS,
             peer_stats_period: cfg.peer_stats_period.try_into()?,
+            tier2_ping_interval: cfg.tier2_ping_interval,
             ttl_account_id_router: cfg.ttl_account_id_router.try_into()?,
             routed_message_ttl: ROUTED_MESSAGE_TTL,
             max_routes_to_store: MAX_ROUTES_TO_STORE,
@@ -362,6 +451,7 @@ impl NetworkConfig {
             push_info_period: time::Duration::milliseconds(100),
             outbound_disabled: false,
             archive,
+            public_rpc_url: cfg.public_rpc_url,
             accounts_data_broadcast_rate_limit: rate::Limit { qps: 0.1, burst: 1 },
             snapshot_hosts_broadcast_rate_limit: rate::Limit { qps: 0.1, burst: 1 },
             routing_table_update_rate_limit: rate::Limit { qps: 1., burst: 1 },
@@ -371,6 +461,7 @@ impl NetworkConfig {
                 advertise_proxies_interval: time::Duration::minutes(15),
                 enable_inbound: cfg.experimental.tier1_enable_inbound,
                 enable_outbound: cfg.experimental.tier1_enable_outbound,
+                pinned_routes: cfg.experimental.tier1_pinned_routes,
             }),
             inbound_disabled: cfg.experimental.inbound_disabled,
             skip_tombstones: if cfg.experimental.skip_sending_tombstones_seconds > 0 {
@@ -378,8 +469,25 @@ impl NetworkConfig {
             } else {
                 None
             },
+            direct_tx_forwarding: cfg.experimental.direct_tx_forwarding,
             // Use a preset to configure rate limits and override entries with user defined values later.
             received_messages_rate_limits: messages_limits::Config::standard_preset(),
+            bandwidth_limits: cfg.bandwidth_limits,
+            admission: cfg.admission,
+            reachability: ReachabilityConfig {
+                port_mapping: cfg.port_mapping,
+                check: cfg.reachability_check,
+                stun_servers: cfg.trusted_stun_servers,
+                local_ip_check_interval: cfg.local_ip_check_interval,
+            },
+            encryption: cfg.encryption,
+            message_compression: cfg.message_compression,
+            message_size_limits: cfg.message_size_limits,
+            access_list_file: cfg.access_list_file,
+            dht: cfg.dht,
+            seen_messages: cfg.seen_messages,
+            record_traffic: cfg.record_traffic,
+            simulation: None,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -393,6 +501,16 @@ impl NetworkConfig {
         PeerId::new(self.node_key.public_key())
     }
 
+    /// Addresses of the node sent to the peers in the handshake.  Wildcard
+    /// addresses like 0.0.0.0 tell the peers nothing, so they are left out.
+    pub fn advertised_listen_addrs(&self) -> Vec<std::net::SocketAddr> {
+        let Some(node_addr) = &self.node_addr else { return vec![] };
+        std::iter::once(**node_addr)
+            .chain(self.extra_listen_addrs.iter().filter(|a| a.advertise).map(|a| *a.addr))
+            .filter(|addr| !addr.ip().is_unspecified())
+            .collect()
+    }
+
     /// TEST-ONLY: Returns network config with given seed used for peer id.
     pub fn from_seed(seed: &str, node_addr: tcp::ListenerAddr) -> Self {
         let node_key = SecretKey::from_seed(KeyType::ED25519, seed);
@@ -408,6 +526,7 @@ impl NetworkConfig {
         };
         NetworkConfig {
             node_addr: Some(node_addr),
+            extra_listen_addrs: vec![],
             node_key,
             validator,
             peer_store: peer_store::Config {
@@ -417,6 +536,7 @@ impl NetworkConfig {
                 ban_window: time::Duration::seconds(1),
                 peer_expiration_duration: time::Duration::seconds(60 * 60),
                 connect_only_to_boot_nodes: false,
+                score_half_life: time::Duration::hours(1),
             },
             snapshot_hosts: snapshot_hosts::Config {
                 snapshot_hosts_cache_size: 1000,
@@ -436,6 +556,8 @@ impl NetworkConfig {
             archival_peer_connections_lower_bound: 10,
             max_send_peers: PEERS_RESPONSE_MAX_PEERS,
             peer_stats_period: time::Duration::seconds(5),
+            // Pings would interleave with the messages the tests expect.
+            tier2_ping_interval: None,
             ttl_account_id_router: time::Duration::seconds(60 * 60),
             routed_message_ttl: ROUTED_MESSAGE_TTL,
             max_routes_to_store: 1,
@@ -444,6 +566,7 @@ impl NetworkConfig {
             outbound_disabled: false,
             inbound_disabled: false,
             archive: false,
+            public_rpc_url: None,
             accounts_data_broadcast_rate_limit: rate::Limit { qps: 100., burst: 1000000 },
             snapshot_hosts_broadcast_rate_limit: rate::Limit { qps: 100., burst: 1000000 },
             routing_table_update_rate_limit: rate::Limit { qps: 10., burst: 1 },
@@ -455,9 +578,27 @@ impl NetworkConfig {
                 advertise_proxies_interval: time::Duration::hours(1000),
                 enable_inbound: true,
                 enable_outbound: true,
+                pinned_routes: HashMap::new(),
             }),
             skip_tombstones: None,
+            direct_tx_forwarding: false,
             received_messages_rate_limits: messages_limits::Config::default(),
+            bandwidth_limits: bandwidth::Config::default(),
+            admission: admission::Config::default(),
+            reachability: ReachabilityConfig {
+                port_mapping: false,
+                check: false,
+                stun_servers: vec![],
+                local_ip_check_interval: None,
+            },
+            encryption: EncryptionMode::default(),
+            message_compression: compression::Config::default(),
+            message_size_limits: message_size::Config::default(),
+            access_list_file: None,
+            dht: dht::Config::default(),
+            seen_messages: seen_messages::Config::default(),
+            record_traffic: None,
+            simulation: None,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -489,6 +630,37 @@ impl NetworkConfig {
             );
         }
 
+        if !self.peer_store.score_half_life.is_positive() {
+            anyhow::bail!(
+                "peer_score_half_life({}) must be positive.",
+                self.peer_store.score_half_life
+            );
+        }
+
+        if !self.extra_listen_addrs.is_empty() {
+            let Some(node_addr) = &self.node_addr else {
+                anyhow::bail!("extra_addrs require addr to be set.");
+            };
+            let mut addrs = HashSet::from([**node_addr]);
+            for extra in &self.extra_listen_addrs {
+                if !addrs.insert(*extra.addr) {
+                    anyhow::bail!("Address {} to listen on is given more than once.", *extra.addr);
+                }
+            }
+        }
+
+        if let Some(interval) = self.tier2_ping_interval {
+            if !interval.is_positive() {
+                anyhow::bail!("tier2_ping_interval({interval}) must be positive.");
+            }
+        }
+
+        if let Some(interval) = self.reachability.local_ip_check_interval {
+            if !interval.is_positive() {
+                anyhow::bail!("local_ip_check_interval({interval}) must be positive.");
+            }
+        }
+
         if UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE * 2 > self.peer_recent_time_window {
             anyhow::bail!(
                 "Very short peer_recent_time_window({}). it should be at least twice update_interval_last_time_received_message({}).",
@@ -514,6 +686,12 @@ impl NetworkConfig {
         if let Err(err) = self.received_messages_rate_limits.validate() {
             anyhow::bail!("One or more invalid rate limits: {err:?}");
         }
+        self.bandwidth_limits.validate()?;
+        self.admission.validate()?;
+        self.message_compression.validate()?;
+        self.message_size_limits.validate()?;
+        self.dht.validate()?;
+        self.seen_messages.validate()?;
 
         Ok(VerifiedConfig { node_id: self.node_id(), inner: self })
     }
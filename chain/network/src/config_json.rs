@@ -1,7 +1,15 @@
+use crate::dht;
 use crate::network_protocol::PeerAddr;
+use crate::peer::compression;
+use crate::peer::encryption::EncryptionMode;
 use crate::rate_limits::messages_limits;
+use crate::rate_limits::{admission, bandwidth, message_size};
+use crate::recorder;
+use crate::seen_messages;
 use crate::stun;
 use near_async::time::Duration;
+use near_primitives::types::AccountId;
+use std::collections::HashMap;
 
 /// Time to persist Accounts Id in the router without removing them in seconds.
 pub const TTL_ACCOUNT_ID_ROUTER: i64 = 60 * 60;
@@ -52,6 +60,10 @@ fn default_peer_stats_period() -> Duration {
     Duration::seconds(5)
 }
 /// Period to update the list of peers we connect to.
+fn default_tier2_ping_interval() -> Option<Duration> {
+    Some(Duration::seconds(30))
+}
+
 fn default_monitor_peers_max_period() -> Duration {
     Duration::seconds(60)
 }
@@ -67,12 +79,40 @@ fn default_snapshot_hosts_cache_size() -> u32 {
 fn default_peer_expiration_duration() -> Duration {
     Duration::seconds(7 * 24 * 60 * 60)
 }
+/// Half-life of the points which make up the scores of the peers.
+fn default_peer_score_half_life() -> Duration {
+    Duration::hours(1)
+}
 
 /// This is a list of public STUN servers provided by Google,
 /// which are known to have good availability. To avoid trusting
 /// a centralized entity (and DNS used for domain resolution),
 /// prefer to set up your own STUN server, or (even better)
 /// use public_addrs instead.
+fn default_reachability_check() -> bool {
+    true
+}
+
+/// How often to look up the local IP used for outbound traffic.
+fn default_local_ip_check_interval() -> Option<Duration> {
+    Some(Duration::seconds(10))
+}
+
+fn default_advertise() -> bool {
+    true
+}
+
+/// Address to listen on for incoming connections, besides `addr`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ExtraListenAddr {
+    pub addr: std::net::SocketAddr,
+    /// Whether to tell the peers about the address in the handshake.  Turn it
+    /// off for addresses the peers shouldn't dial back, e.g. a management
+    /// interface.
+    #[serde(default = "default_advertise")]
+    pub advertise: bool,
+}
+
 pub(crate) fn default_trusted_stun_servers() -> Vec<stun::ServerAddr> {
     vec![
         "stun.l.google.com:19302".to_string(),
@@ -87,6 +127,11 @@ pub(crate) fn default_trusted_stun_servers() -> Vec<stun::ServerAddr> {
 pub struct Config {
     /// Local address to listen for incoming connections.
     pub addr: String,
+    /// More local addresses to listen for incoming connections, e.g. for a
+    /// node reachable both on a private LAN and on a public IP or IPv6.
+    /// Requires `addr` to be set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_addrs: Vec<ExtraListenAddr>,
     /// Comma separated list of nodes to connect to.
     /// Examples:
     ///   ed25519:86EtEy7epneKyrcJwSWP7zsisTkfDRH5CFVszt4qiQYw@31.192.22.209:24567
@@ -148,6 +193,12 @@ pub struct Config {
     #[serde(default = "default_peer_stats_period")]
     #[serde(with = "near_async::time::serde_duration_as_std")]
     pub peer_stats_period: Duration,
+    /// How often to ping the peers to measure the round trip times, so that
+    /// routed messages go through the faster of the peers on the shortest
+    /// paths. With `null`, the round trip times are not taken into account.
+    #[serde(default = "default_tier2_ping_interval")]
+    #[serde(with = "near_async::time::serde_opt_duration_as_std")]
+    pub tier2_ping_interval: Option<Duration>,
     // Period to monitor peers (connect to new ones etc).
     #[serde(default = "default_monitor_peers_max_period")]
     #[serde(with = "near_async::time::serde_duration_as_std")]
@@ -163,6 +214,13 @@ pub struct Config {
     #[serde(default = "default_peer_expiration_duration")]
     #[serde(with = "near_async::time::serde_duration_as_std")]
     pub peer_expiration_duration: Duration,
+    /// The score of a peer, which decides which connections to drop and
+    /// which peers to connect to, is made of points the peer earns by
+    /// serving our requests and loses by misbehaving.  The points decay by
+    /// half every this amount of time.
+    #[serde(default = "default_peer_score_half_life")]
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub peer_score_half_life: Duration,
 
     /// List of the public addresses (in the format "<node public key>@<IP>:<port>") of trusted nodes,
     /// which are willing to route messages to this node. Useful only if this node is a validator.
@@ -209,6 +267,80 @@ pub struct Config {
     /// such a case.
     #[serde(default = "default_trusted_stun_servers")]
     pub trusted_stun_servers: Vec<stun::ServerAddr>,
+    /// Public URL of the JSON-RPC server of this node, e.g.
+    /// `https://rpc.example.com`. It is advertised to the peers in the
+    /// handshake, so that RPC nodes which don't track a shard can point their
+    /// clients to nodes which do.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_rpc_url: Option<String>,
+    /// Caps on the upload and download bandwidth, per peer and for all peers
+    /// together, in bytes per second.  Messages needed for consensus, like
+    /// chunk endorsements, are never delayed by the upload caps, while state
+    /// parts served to syncing nodes wait behind the rest of the traffic.
+    #[serde(default)]
+    pub bandwidth_limits: bandwidth::Config,
+    /// Protection of the node against floods of inbound handshakes: over
+    /// `handshake_rate`, peers have to solve a proof-of-work puzzle of
+    /// `challenge_difficulty` bits before their handshakes are processed.
+    /// `max_inbound_connections_per_ip` caps the connections from a single IP.
+    #[serde(default)]
+    pub admission: admission::Config,
+    /// Ask the gateway of the local network to forward the port of the node
+    /// with NAT-PMP, for nodes behind a home router.  The mapping is renewed
+    /// for as long as the node runs.
+    #[serde(default)]
+    pub port_mapping: bool,
+    /// Check at startup whether other nodes can connect to this node, by
+    /// connecting to its external address: the one mapped on the gateway, or
+    /// else the IP reported by trusted_stun_servers.  The result is reported
+    /// in `/status`.
+    #[serde(default = "default_reachability_check")]
+    pub reachability_check: bool,
+    /// How often to check whether the local IP used for outbound traffic has
+    /// changed.  On a change, the connections opened from the old IP are
+    /// closed and the peers are reconnected to right away, instead of waiting
+    /// for the connections to time out.  With `null`, the IP isn't watched.
+    #[serde(default = "default_local_ip_check_interval")]
+    #[serde(with = "near_async::time::serde_opt_duration_as_std")]
+    pub local_ip_check_interval: Option<Duration>,
+    /// Whether to encrypt the connections with the peers: `disabled`,
+    /// `enabled` to encrypt the connections with the peers which support it,
+    /// or `required` to refuse the peers which don't. Only `required`
+    /// protects against an on-path attacker, who can make the connections
+    /// fall back to plaintext with `enabled`.
+    #[serde(default)]
+    pub encryption: EncryptionMode,
+    /// Compression of blocks, chunk parts and state witness parts sent to
+    /// the peers which support it.
+    #[serde(default)]
+    pub message_compression: compression::Config,
+    /// Size limits of the messages, in bytes, for each kind of message:
+    /// `handshake`, `block`, `witness`, `routed` and `other`.  A received
+    /// message over its limit is dropped, or with `"oversize_policy":
+    /// "disconnect"`, the connection is closed.
+    #[serde(default)]
+    pub message_size_limits: message_size::Config,
+    /// JSON file with peer ids and IP ranges of the peers to refuse, or to
+    /// exclusively allow, like `{"deny": ["ed25519:...", "192.0.2.0/24"]}`.
+    /// Relative to the home directory. The file is checked for changes
+    /// periodically, and the new rules are applied without a restart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_list_file: Option<std::path::PathBuf>,
+    /// Discovery of peers through a Kademlia-style DHT, which lets the node
+    /// find peers even if its boot nodes are down. The nodes found are saved
+    /// in the DB, so that they can be reached after a restart.
+    #[serde(default)]
+    pub dht: dht::Config,
+    /// Cache of the hashes of recently received blocks and routed messages,
+    /// used to recognize duplicates.  It takes up to `max_bytes`, and keeps a
+    /// hash for up to `max_ttl`, less when the traffic fills the cache sooner.
+    #[serde(default)]
+    pub seen_messages: seen_messages::Config,
+    /// Record the messages exchanged with the peers to `file`, relative to
+    /// the home directory, for replaying them when debugging.  Costs an extra
+    /// serialization of every message, so leave it off unless needed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_traffic: Option<recorder::Config>,
     // Experimental part of the JSON config. Regular users/validators should not have to set any values there.
     // Field names in here can change/disappear at any moment without warning.
     #[serde(default)]
@@ -265,6 +397,15 @@ pub struct ExperimentalConfig {
     #[serde(default = "default_tier1_new_connections_per_attempt")]
     pub tier1_new_connections_per_attempt: u64,
 
+    /// See `near_network::config::Tier1::pinned_routes`. Maps validator
+    /// account ids to addresses in the format "<node public key>@<IP>:<port>".
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tier1_pinned_routes: HashMap<AccountId, Vec<PeerAddr>>,
+
+    /// See `near_network::config::NetworkConfig::direct_tx_forwarding`.
+    #[serde(default)]
+    pub direct_tx_forwarding: bool,
+
     /// See `NetworkConfig`.
     /// Fields set here will override the NetworkConfig fields.
     #[serde(default)]
@@ -299,6 +440,8 @@ impl Default for ExperimentalConfig {
             tier1_enable_outbound: default_tier1_enable_outbound(),
             tier1_connect_interval: default_tier1_connect_interval(),
             tier1_new_connections_per_attempt: default_tier1_new_connections_per_attempt(),
+            tier1_pinned_routes: HashMap::new(),
+            direct_tx_forwarding: false,
             network_config_overrides: Default::default(),
         }
     }
@@ -308,6 +451,7 @@ impl Default for Config {
     fn default() -> Self {
         Config {
             addr: "0.0.0.0:24567".to_string(),
+            extra_addrs: vec![],
             boot_nodes: "".to_string(),
             whitelist_nodes: "".to_string(),
             max_num_peers: default_max_num_peers(),
@@ -327,11 +471,26 @@ impl Default for Config {
             blacklist: vec![],
             ttl_account_id_router: default_ttl_account_id_router(),
             peer_stats_period: default_peer_stats_period(),
+            tier2_ping_interval: default_tier2_ping_interval(),
             monitor_peers_max_period: default_monitor_peers_max_period(),
             peer_expiration_duration: default_peer_expiration_duration(),
+            peer_score_half_life: default_peer_score_half_life(),
             public_addrs: vec![],
             allow_private_ip_in_public_addrs: false,
             trusted_stun_servers: default_trusted_stun_servers(),
+            public_rpc_url: None,
+            bandwidth_limits: Default::default(),
+            admission: Default::default(),
+            port_mapping: false,
+            reachability_check: default_reachability_check(),
+            local_ip_check_interval: default_local_ip_check_interval(),
+            encryption: EncryptionMode::default(),
+            message_compression: compression::Config::default(),
+            message_size_limits: message_size::Config::default(),
+            access_list_file: None,
+            dht: dht::Config::default(),
+            seen_messages: seen_messages::Config::default(),
+            record_traffic: None,
             experimental: Default::default(),
         }
     }
@@ -1,7 +1,8 @@
+use crate::access_list::Rule;
 use ::actix::Message;
 use near_primitives::views::{
-    NetworkGraphView, NetworkRoutesView, PeerStoreView, RecentOutboundConnectionsView,
-    SnapshotHostsView,
+    NetworkGraphView, NetworkRoutesView, PeerAccessListView, PeerStoreView,
+    RecentOutboundConnectionsView, SnapshotHostsView, Tier1ConnectionsView,
 };
 
 // Different debug requests that can be sent by HTML pages, via GET.
@@ -11,6 +12,8 @@ pub enum GetDebugStatus {
     RecentOutboundConnections,
     Routes,
     SnapshotHosts,
+    Tier1,
+    AccessList,
 }
 
 #[derive(actix::MessageResponse, Debug)]
@@ -20,8 +23,32 @@ pub enum DebugStatus {
     RecentOutboundConnections(RecentOutboundConnectionsView),
     Routes(NetworkRoutesView),
     SnapshotHosts(SnapshotHostsView),
+    Tier1(Tier1ConnectionsView),
+    AccessList(PeerAccessListView),
 }
 
 impl Message for GetDebugStatus {
     type Result = DebugStatus;
 }
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerAccessAction {
+    /// Disconnects the matching peers and refuses them until unbanned.
+    Ban,
+    /// Lifts a ban issued with `Ban`. Unbanning a peer id also lifts
+    /// the ban of the peer for misbehaving, if any.
+    Unban,
+}
+
+// Admin request to ban or unban peers, sent via POST.
+// The response is the resulting `DebugStatus::AccessList`.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PeerAccessControl {
+    pub action: PeerAccessAction,
+    pub rule: Rule,
+}
+
+impl Message for PeerAccessControl {
+    type Result = DebugStatus;
+}
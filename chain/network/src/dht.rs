@@ -0,0 +1,345 @@
+//! Kademlia-style discovery of peers.
+//!
+//! A node learns about its first peers from its boot nodes, so a new node
+//! whose boot nodes are all down cannot join the network. With the DHT
+//! enabled, nodes keep the records of the nodes they learn about (see
+//! `SignedPeerRecord`) in a Kademlia routing table, and periodically look up
+//! a key, asking the connected peers closest to it for the records closest to
+//! it. The nodes found are added to the peer store, from which the
+//! PeerManager picks the peers to connect to, so that every lookup starts from
+//! peers closer to the key than before. The routing table is saved in the DB,
+//! so that a restarted node can reach the nodes it knew even if its boot nodes
+//! are down.
+//!
+//! Only the records signed by the nodes they describe are accepted, so nobody
+//! can make other nodes connect to wrong addresses on behalf of honest nodes.
+//! Like in Kademlia, full buckets keep their records rather than taking new
+//! ones, so flooding a node with records of made up nodes doesn't push the
+//! nodes it knows out of its routing table.
+
+use crate::network_protocol::{PeerInfo, SignedPeerRecord};
+use crate::store;
+use near_async::time;
+use near_crypto::SecretKey;
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+/// Maximal number of records in a bucket of the routing table, which is also
+/// the number of records sent in response to DhtFindNodes.
+pub(crate) const BUCKET_SIZE: usize = 16;
+/// The closest records and the record of the responding node.
+pub(crate) const MAX_RECORDS_PER_RESPONSE: usize = BUCKET_SIZE + 1;
+/// Number of peers asked for nodes in a lookup.
+pub(crate) const LOOKUP_PARALLELISM: usize = 3;
+/// Records older than that are dropped, so that nodes which are gone are
+/// eventually forgotten. Nodes re-sign their records well before that.
+const RECORD_TTL: time::Duration = time::Duration::hours(24);
+/// How long a record of this node is used before it is re-signed.
+const OWN_RECORD_REFRESH_INTERVAL: time::Duration = time::Duration::hours(1);
+/// Records signed further in the future than that are rejected.
+const MAX_CLOCK_SKEW: time::Duration = time::Duration::minutes(5);
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Whether to discover peers through the DHT.
+    pub enabled: bool,
+    /// How often to look up a key.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub lookup_interval: time::Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { enabled: false, lookup_interval: time::Duration::seconds(30) }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.lookup_interval <= time::Duration::ZERO {
+            anyhow::bail!("dht.lookup_interval must be positive, got {}", self.lookup_interval);
+        }
+        Ok(())
+    }
+}
+
+/// Position of the node in the key space of the DHT.
+pub(crate) fn key(peer_id: &PeerId) -> CryptoHash {
+    CryptoHash::hash_borsh(peer_id)
+}
+
+/// Kademlia distance between keys. Compares like the numbers it encodes in big endian.
+pub(crate) fn distance(a: &CryptoHash, b: &CryptoHash) -> [u8; 32] {
+    std::array::from_fn(|i| a.0[i] ^ b.0[i])
+}
+
+/// Index of the bucket for the keys at `distance`, i.e. its floor(log2),
+/// or None if the keys are equal.
+fn bucket_index(distance: &[u8; 32]) -> Option<usize> {
+    let i = distance.iter().position(|b| *b != 0)?;
+    Some((31 - i) * 8 + (7 - distance[i].leading_zeros() as usize))
+}
+
+/// Records of the known nodes, bucketed by their distance from this node.
+pub(crate) struct RoutingTable {
+    local_key: CryptoHash,
+    /// buckets[i] holds the records of the nodes at distance [2^i, 2^(i+1)),
+    /// from the least to the most recently updated.
+    buckets: Vec<Vec<SignedPeerRecord>>,
+}
+
+impl RoutingTable {
+    pub fn new(node_id: &PeerId) -> Self {
+        Self { local_key: key(node_id), buckets: vec![vec![]; 256] }
+    }
+
+    fn bucket(&mut self, peer_id: &PeerId) -> Option<&mut Vec<SignedPeerRecord>> {
+        let i = bucket_index(&distance(&self.local_key, &key(peer_id)))?;
+        Some(&mut self.buckets[i])
+    }
+
+    /// Inserts the record, unless a newer record of the same node is known
+    /// or the bucket is full. Returns whether the record has been inserted.
+    pub fn insert(&mut self, record: SignedPeerRecord) -> bool {
+        let Some(bucket) = self.bucket(&record.peer_addr.peer_id) else { return false };
+        if let Some(i) = bucket.iter().position(|r| r.peer_addr.peer_id == record.peer_addr.peer_id)
+        {
+            if bucket[i].timestamp >= record.timestamp {
+                return false;
+            }
+            bucket.remove(i);
+        } else if bucket.len() >= BUCKET_SIZE {
+            return false;
+        }
+        bucket.push(record);
+        true
+    }
+
+    /// Removes the records signed before `min_timestamp`. Returns whether any has been removed.
+    pub fn expire(&mut self, min_timestamp: time::Utc) -> bool {
+        let len = self.len();
+        for bucket in &mut self.buckets {
+            bucket.retain(|r| r.timestamp >= min_timestamp);
+        }
+        self.len() != len
+    }
+
+    /// Up to `count` records closest to `target`, the closest first.
+    pub fn closest(&self, target: &CryptoHash, count: usize) -> Vec<SignedPeerRecord> {
+        let mut records: Vec<_> = self.records().collect();
+        records.sort_by_cached_key(|r| distance(target, &key(&r.peer_addr.peer_id)));
+        records.into_iter().take(count).cloned().collect()
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &SignedPeerRecord> {
+        self.buckets.iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(Vec::len).sum()
+    }
+}
+
+/// State of the DHT of this node.
+pub(crate) struct Dht {
+    store: store::Store,
+    table: RoutingTable,
+    own_record: Option<SignedPeerRecord>,
+    /// Peers asked for nodes in the last lookup, which haven't responded yet.
+    /// Responses from other peers are ignored.
+    awaiting_response: HashSet<PeerId>,
+    /// Number of lookups so far.
+    lookups: u64,
+}
+
+impl Dht {
+    /// Loads the routing table saved in the DB.
+    pub fn new(store: store::Store, node_id: &PeerId) -> Self {
+        let mut table = RoutingTable::new(node_id);
+        for record in store.get_dht_records() {
+            table.insert(record);
+        }
+        Self { store, table, own_record: None, awaiting_response: HashSet::new(), lookups: 0 }
+    }
+
+    fn save(&mut self) {
+        let records: Vec<_> = self.table.records().cloned().collect();
+        if let Err(err) = self.store.set_dht_records(&records) {
+            tracing::error!(target: "network", ?err, "Failed to save the DHT records");
+        }
+    }
+
+    /// Known nodes, as candidates to connect to.
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        self.table
+            .records()
+            .map(|r| PeerInfo::new(r.peer_addr.peer_id.clone(), r.peer_addr.addr))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Record of this node at `addr`, re-signed when it gets old or the address changes.
+    pub fn own_record(
+        &mut self,
+        now: time::Utc,
+        addr: SocketAddr,
+        node_key: &SecretKey,
+    ) -> SignedPeerRecord {
+        match &self.own_record {
+            Some(record)
+                if record.peer_addr.addr == addr
+                    && now - record.timestamp < OWN_RECORD_REFRESH_INTERVAL => {}
+            _ => self.own_record = Some(SignedPeerRecord::new(addr, now, node_key)),
+        }
+        self.own_record.clone().unwrap()
+    }
+
+    /// Key to look up next: every other lookup is for the key of this node,
+    /// which finds the nodes closest to it, and the others are for random
+    /// keys, which keep the farther buckets filled.
+    pub fn start_lookup(&mut self, node_id: &PeerId) -> CryptoHash {
+        self.awaiting_response.clear();
+        self.lookups += 1;
+        if self.lookups % 2 == 1 {
+            key(node_id)
+        } else {
+            CryptoHash(rand::random())
+        }
+    }
+
+    pub fn expect_response(&mut self, peer_id: PeerId) {
+        self.awaiting_response.insert(peer_id);
+    }
+
+    /// Returns whether a response from the peer is expected, and if so, stops expecting it.
+    pub fn take_response(&mut self, peer_id: &PeerId) -> bool {
+        self.awaiting_response.remove(peer_id)
+    }
+
+    pub fn closest(&self, target: &CryptoHash, count: usize) -> Vec<SignedPeerRecord> {
+        self.table.closest(target, count)
+    }
+
+    /// Removes the records which have expired.
+    pub fn expire(&mut self, now: time::Utc) {
+        if self.table.expire(now - RECORD_TTL) {
+            self.save();
+        }
+    }
+
+    /// Inserts records with verified signatures, skipping the expired ones.
+    /// Returns the records which have been inserted.
+    pub fn insert(
+        &mut self,
+        now: time::Utc,
+        records: Vec<SignedPeerRecord>,
+    ) -> Vec<SignedPeerRecord> {
+        let inserted: Vec<_> = records
+            .into_iter()
+            .filter(|r| now - RECORD_TTL <= r.timestamp && r.timestamp <= now + MAX_CLOCK_SKEW)
+            .filter(|r| self.table.insert(r.clone()))
+            .collect();
+        if !inserted.is_empty() {
+            self.save();
+        }
+        inserted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bucket_index, distance, key, RoutingTable, BUCKET_SIZE};
+    use crate::network_protocol::testonly as data;
+    use crate::network_protocol::SignedPeerRecord;
+    use crate::testonly::make_rng;
+    use near_async::time;
+    use near_primitives::hash::CryptoHash;
+
+    fn make_record(rng: &mut impl rand::Rng, timestamp: time::Utc) -> SignedPeerRecord {
+        let node_key = data::make_secret_key(rng);
+        SignedPeerRecord::new(data::make_addr(rng), timestamp, &node_key)
+    }
+
+    #[test]
+    fn test_bucket_index() {
+        let mut d = [0; 32];
+        assert_eq!(bucket_index(&d), None);
+        d[31] = 1;
+        assert_eq!(bucket_index(&d), Some(0));
+        d[30] = 0b101;
+        assert_eq!(bucket_index(&d), Some(10));
+        d[0] = 0x80;
+        assert_eq!(bucket_index(&d), Some(255));
+    }
+
+    #[test]
+    fn test_closest() {
+        let mut rng = make_rng(87123491);
+        let now = time::FakeClock::default().now_utc();
+        let mut table = RoutingTable::new(&data::make_peer_id(&mut rng));
+        let records: Vec<_> = (0..100).map(|_| make_record(&mut rng, now)).collect();
+        for r in &records {
+            table.insert(r.clone());
+        }
+        // Random nodes fall mostly into the farthest buckets, some of which overflow.
+        assert!(table.len() < records.len());
+
+        let target = CryptoHash::hash_bytes(b"target");
+        let closest = table.closest(&target, 5);
+        assert_eq!(closest.len(), 5);
+        let dist = |r: &SignedPeerRecord| distance(&target, &key(&r.peer_addr.peer_id));
+        assert!(closest.windows(2).all(|w| dist(&w[0]) <= dist(&w[1])));
+        assert!(table.records().all(|r| closest.contains(r) || dist(r) >= dist(&closest[4])));
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut rng = make_rng(9123874);
+        let clock = time::FakeClock::default();
+        let node_id = data::make_peer_id(&mut rng);
+        let mut table = RoutingTable::new(&node_id);
+
+        // Nodes fill their buckets up to BUCKET_SIZE.
+        let mut farthest = vec![];
+        while farthest.len() <= BUCKET_SIZE {
+            let r = make_record(&mut rng, clock.now_utc());
+            if bucket_index(&distance(&key(&node_id), &key(&r.peer_addr.peer_id))) == Some(255) {
+                farthest.push(r);
+            }
+        }
+        for r in &farthest[..BUCKET_SIZE] {
+            assert!(table.insert(r.clone()));
+        }
+        assert!(!table.insert(farthest[BUCKET_SIZE].clone()));
+
+        // A newer record of a known node replaces the older one, but not vice versa.
+        let mut table = RoutingTable::new(&node_id);
+        let node_key = data::make_secret_key(&mut rng);
+        let old = SignedPeerRecord::new(data::make_addr(&mut rng), clock.now_utc(), &node_key);
+        assert!(table.insert(old.clone()));
+        clock.advance(time::Duration::minutes(1));
+        let new = SignedPeerRecord::new(data::make_addr(&mut rng), clock.now_utc(), &node_key);
+        assert!(table.insert(new.clone()));
+        assert!(!table.insert(old));
+        assert_eq!(table.records().collect::<Vec<_>>(), vec![&new]);
+
+        // The record of this node doesn't fit anywhere.
+        let own = SignedPeerRecord::new(
+            data::make_addr(&mut rng),
+            clock.now_utc(),
+            &data::make_secret_key(&mut make_rng(9123874)),
+        );
+        assert_eq!(own.peer_addr.peer_id, node_id);
+        assert!(!table.insert(own));
+
+        assert!(!table.expire(new.timestamp));
+        assert!(table.expire(clock.now_utc() + time::Duration::seconds(1)));
+        assert_eq!(table.len(), 0);
+    }
+}
@@ -10,11 +10,13 @@ mod peer;
 mod peer_manager;
 mod private_actix;
 mod rate_limits;
+mod reachability;
 mod snapshot_hosts;
 mod stats;
 mod store;
 mod stun;
 
+pub mod access_list;
 pub mod actix;
 pub mod blacklist;
 pub mod client;
@@ -22,9 +24,13 @@ pub mod concurrency;
 pub mod config;
 pub mod config_json;
 pub mod debug;
+pub mod dht;
 pub mod raw;
+pub mod recorder;
 pub mod routing;
+pub mod seen_messages;
 pub mod shards_manager;
+pub mod sim;
 pub mod state_sync;
 pub mod state_witness;
 pub mod tcp;
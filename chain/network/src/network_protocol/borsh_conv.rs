@@ -11,9 +11,14 @@ impl From<&net::Handshake> for mem::Handshake {
             sender_peer_id: x.sender_peer_id.clone(),
             target_peer_id: x.target_peer_id.clone(),
             sender_listen_port: x.sender_listen_port,
+            sender_listen_addrs: vec![],
             sender_chain_info: x.sender_chain_info.clone(),
             partial_edge_info: x.partial_edge_info.clone(),
             owned_account: None,
+            sender_rpc_url: None,
+            encryption_offer: None,
+            supported_compressions: vec![],
+            admission_proof: None,
         }
     }
 }
@@ -70,6 +75,9 @@ impl From<&mem::HandshakeFailureReason> for net::HandshakeFailureReason {
             mem::HandshakeFailureReason::InvalidTarget => {
                 net::HandshakeFailureReason::InvalidTarget
             }
+            mem::HandshakeFailureReason::AdmissionChallenge(_) => {
+                panic!("AdmissionChallenge is not supported in Borsh encoding")
+            }
         }
     }
 }
@@ -231,6 +239,11 @@ impl From<&mem::PeerMessage> for net::PeerMessage {
                 net::PeerMessage::SyncRoutingTable(net::RoutingTableUpdate::default())
             }
 
+            // DHT messages are not supported either.
+            mem::PeerMessage::DhtFindNodes(_) | mem::PeerMessage::DhtNodes(_) => {
+                net::PeerMessage::SyncRoutingTable(net::RoutingTableUpdate::default())
+            }
+
             mem::PeerMessage::PeersRequest(_) => net::PeerMessage::PeersRequest,
             mem::PeerMessage::PeersResponse(pr) => net::PeerMessage::PeersResponse(pr.peers),
             mem::PeerMessage::BlockHeadersRequest(bhs) => {
@@ -356,19 +356,140 @@ pub struct Handshake {
     pub(crate) target_peer_id: PeerId,
     /// Sender's listening addr.
     pub(crate) sender_listen_port: Option<u16>,
+    /// All the addresses the sender listens on and advertises.
+    pub(crate) sender_listen_addrs: Vec<std::net::SocketAddr>,
     /// Peer's chain information.
     pub(crate) sender_chain_info: PeerChainInfoV2,
     /// Represents new `edge`. Contains only `none` and `Signature` from the sender.
     pub(crate) partial_edge_info: PartialEdgeInfo,
     /// Account owned by the sender.
     pub(crate) owned_account: Option<SignedOwnedAccount>,
+    /// Public URL of the sender's JSON-RPC server.
+    pub(crate) sender_rpc_url: Option<String>,
+    /// Present iff the sender is willing to encrypt the connection.
+    pub(crate) encryption_offer: Option<EncryptionOffer>,
+    /// Compression algorithms which the sender can decompress.
+    pub(crate) supported_compressions: Vec<Compression>,
+    /// Solution of the AdmissionChallenge sent by the receiver in response
+    /// to an earlier Handshake on the same connection.
+    pub(crate) admission_proof: Option<AdmissionProof>,
+}
+
+/// Compression algorithm of large messages. A connection uses one only if
+/// both peers support it, see `peer::compression`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Compression {
+    Zstd,
+}
+
+/// Ephemeral X25519 key of the sender of a Handshake, signed with its node key
+/// together with the nonce of the edge proposed in the same Handshake, so that
+/// it can be neither replaced on the way nor moved to another Handshake. If
+/// both sides of the connection offer a key, everything sent after the
+/// Handshakes is encrypted with keys derived from them, see `peer::encryption`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct EncryptionOffer {
+    pub(crate) ephemeral_key: [u8; 32],
+    pub(crate) signature: Signature,
+}
+
+impl EncryptionOffer {
+    fn build_hash(
+        sender: &PeerId,
+        target: &PeerId,
+        edge_nonce: u64,
+        ephemeral_key: &[u8; 32],
+    ) -> CryptoHash {
+        CryptoHash::hash_borsh(("EncryptionOffer", sender, target, edge_nonce, ephemeral_key))
+    }
+
+    pub fn new(
+        sender: &PeerId,
+        target: &PeerId,
+        edge_nonce: u64,
+        ephemeral_key: [u8; 32],
+        node_key: &near_crypto::SecretKey,
+    ) -> Self {
+        let signature =
+            node_key.sign(Self::build_hash(sender, target, edge_nonce, &ephemeral_key).as_ref());
+        Self { ephemeral_key, signature }
+    }
+
+    /// Checks that the offer has been made by `sender` in the Handshake to
+    /// `target` proposing the edge with `edge_nonce`.
+    pub fn verify(&self, sender: &PeerId, target: &PeerId, edge_nonce: u64) -> bool {
+        self.signature.verify(
+            Self::build_hash(sender, target, edge_nonce, &self.ephemeral_key).as_ref(),
+            sender.public_key(),
+        )
+    }
+}
+
+/// Puzzle which a node receiving more handshakes than it is willing to
+/// process asks the peer to solve before it accepts the connection, see
+/// `rate_limits::admission`. Solving it takes 2^difficulty hashes on average,
+/// checking the solution takes one.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AdmissionChallenge {
+    pub(crate) seed: CryptoHash,
+    /// Number of leading zero bits required of the solution hash.
+    pub(crate) difficulty: u8,
+}
+
+/// Solution of an AdmissionChallenge.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AdmissionProof {
+    pub(crate) seed: CryptoHash,
+    pub(crate) nonce: u64,
+}
+
+impl AdmissionChallenge {
+    fn build_hash(seed: &CryptoHash, sender: &PeerId, nonce: u64) -> CryptoHash {
+        CryptoHash::hash_borsh(("AdmissionChallenge", seed, sender, nonce))
+    }
+
+    fn leading_zeros(hash: &CryptoHash) -> u32 {
+        let mut zeros = 0;
+        for b in hash.as_ref() {
+            zeros += b.leading_zeros();
+            if *b != 0 {
+                break;
+            }
+        }
+        zeros
+    }
+
+    /// Finds a solution for `sender`. CPU-heavy, run it on rayon.
+    pub fn solve(&self, sender: &PeerId) -> AdmissionProof {
+        let nonce = (0..)
+            .find(|nonce| {
+                Self::leading_zeros(&Self::build_hash(&self.seed, sender, *nonce))
+                    >= self.difficulty as u32
+            })
+            .unwrap();
+        AdmissionProof { seed: self.seed, nonce }
+    }
+
+    /// Checks that `proof` is a solution of this challenge found by `sender`.
+    pub fn verify(&self, sender: &PeerId, proof: &AdmissionProof) -> bool {
+        proof.seed == self.seed
+            && Self::leading_zeros(&Self::build_hash(&self.seed, sender, proof.nonce))
+                >= self.difficulty as u32
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, strum::IntoStaticStr)]
 pub enum HandshakeFailureReason {
-    ProtocolVersionMismatch { version: u32, oldest_supported_version: u32 },
+    ProtocolVersionMismatch {
+        version: u32,
+        oldest_supported_version: u32,
+    },
     GenesisMismatch(GenesisId),
     InvalidTarget,
+    /// The receiver is flooded with handshakes and will accept this one only
+    /// once the sender resends it with a solution of the challenge.
+    /// Proto encoding only.
+    AdmissionChallenge(AdmissionChallenge),
 }
 
 /// See SyncAccountsData in network_protocol/network.proto.
@@ -398,6 +519,60 @@ pub struct PeersResponse {
     pub direct_peers: Vec<PeerInfo>,
 }
 
+/// Address at which a node accepts connections, signed with its node key, so
+/// that nobody else can advertise a different address for it. Exchanged in the
+/// DHT, see `crate::dht`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SignedPeerRecord {
+    pub(crate) peer_addr: PeerAddr,
+    /// Time of signing. A newer record of a node replaces the older ones.
+    pub(crate) timestamp: time::Utc,
+    pub(crate) signature: Signature,
+}
+
+impl SignedPeerRecord {
+    fn build_hash(peer_addr: &PeerAddr, timestamp: &time::Utc) -> CryptoHash {
+        CryptoHash::hash_borsh((
+            "PeerRecord",
+            &peer_addr.peer_id,
+            &peer_addr.addr,
+            timestamp.unix_timestamp_nanos(),
+        ))
+    }
+
+    pub fn new(
+        addr: std::net::SocketAddr,
+        timestamp: time::Utc,
+        node_key: &near_crypto::SecretKey,
+    ) -> Self {
+        let peer_addr = PeerAddr { addr, peer_id: PeerId::new(node_key.public_key()) };
+        let signature = node_key.sign(Self::build_hash(&peer_addr, &timestamp).as_ref());
+        Self { peer_addr, timestamp, signature }
+    }
+
+    /// Checks that the record has been signed by the node it describes.
+    pub fn verify(&self) -> bool {
+        self.signature.verify(
+            Self::build_hash(&self.peer_addr, &self.timestamp).as_ref(),
+            self.peer_addr.peer_id.public_key(),
+        )
+    }
+}
+
+/// Asks the peer for the records of the nodes closest to `target` which it knows.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DhtFindNodes {
+    pub target: CryptoHash,
+    /// Record of the sender, so that the peer can add it to its routing table.
+    pub sender_record: Option<SignedPeerRecord>,
+}
+
+/// Response to DhtFindNodes.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DhtNodes {
+    pub records: Vec<SignedPeerRecord>,
+}
+
 /// Message sent when gracefully disconnecting from the other peer.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Disconnect {
@@ -424,6 +599,9 @@ pub enum PeerMessage {
     PeersRequest(PeersRequest),
     PeersResponse(PeersResponse),
 
+    DhtFindNodes(DhtFindNodes),
+    DhtNodes(DhtNodes),
+
     BlockHeadersRequest(Vec<CryptoHash>),
     BlockHeaders(Vec<BlockHeader>),
 
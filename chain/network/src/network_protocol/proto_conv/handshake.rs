@@ -2,7 +2,10 @@
 use super::*;
 
 use crate::network_protocol::proto;
-use crate::network_protocol::{Handshake, HandshakeFailureReason};
+use crate::network_protocol::{
+    AdmissionChallenge, AdmissionProof, Compression, EncryptionOffer, Handshake,
+    HandshakeFailureReason,
+};
 use crate::network_protocol::{PeerChainInfoV2, PeerInfo};
 use near_primitives::block::GenesisId;
 use protobuf::MessageField as MF;
@@ -63,6 +66,89 @@ impl TryFrom<&proto::PeerChainInfo> for PeerChainInfoV2 {
 
 //////////////////////////////////////////
 
+#[derive(thiserror::Error, Debug)]
+pub enum ParseEncryptionOfferError {
+    #[error("ephemeral_key: want 32 bytes, got {0}")]
+    EphemeralKey(usize),
+    #[error("signature {0}")]
+    Signature(ParseRequiredError<ParseSignatureError>),
+}
+
+impl From<&EncryptionOffer> for proto::EncryptionOffer {
+    fn from(x: &EncryptionOffer) -> Self {
+        Self {
+            ephemeral_key: x.ephemeral_key.to_vec(),
+            signature: MF::some((&x.signature).into()),
+            ..Self::default()
+        }
+    }
+}
+
+impl TryFrom<&proto::EncryptionOffer> for EncryptionOffer {
+    type Error = ParseEncryptionOfferError;
+    fn try_from(p: &proto::EncryptionOffer) -> Result<Self, Self::Error> {
+        Ok(Self {
+            ephemeral_key: p
+                .ephemeral_key
+                .as_slice()
+                .try_into()
+                .map_err(|_| Self::Error::EphemeralKey(p.ephemeral_key.len()))?,
+            signature: try_from_required(&p.signature).map_err(Self::Error::Signature)?,
+        })
+    }
+}
+
+//////////////////////////////////////////
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseAdmissionChallengeError {
+    #[error("seed {0}")]
+    Seed(ParseRequiredError<ParseCryptoHashError>),
+    #[error("difficulty {0}")]
+    Difficulty(std::num::TryFromIntError),
+}
+
+impl From<&AdmissionChallenge> for proto::AdmissionChallenge {
+    fn from(x: &AdmissionChallenge) -> Self {
+        Self {
+            seed: MF::some((&x.seed).into()),
+            difficulty: x.difficulty.into(),
+            ..Self::default()
+        }
+    }
+}
+
+impl TryFrom<&proto::AdmissionChallenge> for AdmissionChallenge {
+    type Error = ParseAdmissionChallengeError;
+    fn try_from(p: &proto::AdmissionChallenge) -> Result<Self, Self::Error> {
+        Ok(Self {
+            seed: try_from_required(&p.seed).map_err(Self::Error::Seed)?,
+            difficulty: p.difficulty.try_into().map_err(Self::Error::Difficulty)?,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseAdmissionProofError {
+    #[error("seed {0}")]
+    Seed(ParseRequiredError<ParseCryptoHashError>),
+}
+
+impl From<&AdmissionProof> for proto::AdmissionProof {
+    fn from(x: &AdmissionProof) -> Self {
+        Self { seed: MF::some((&x.seed).into()), nonce: x.nonce, ..Self::default() }
+    }
+}
+
+impl TryFrom<&proto::AdmissionProof> for AdmissionProof {
+    type Error = ParseAdmissionProofError;
+    fn try_from(p: &proto::AdmissionProof) -> Result<Self, Self::Error> {
+        Ok(Self { seed: try_from_required(&p.seed).map_err(Self::Error::Seed)?, nonce: p.nonce })
+    }
+}
+
+//////////////////////////////////////////
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseHandshakeError {
     #[error("sender_peer_id {0}")]
@@ -71,12 +157,18 @@ pub enum ParseHandshakeError {
     TargetPeerId(ParseRequiredError<ParsePublicKeyError>),
     #[error("sender_listen_port {0}")]
     SenderListenPort(std::num::TryFromIntError),
+    #[error("sender_listen_addrs {0}")]
+    SenderListenAddrs(ParseVecError<ParseSocketAddrError>),
     #[error("sender_chain_info {0}")]
     SenderChainInfo(ParseRequiredError<ParsePeerChainInfoV2Error>),
     #[error("partial_edge_info {0}")]
     PartialEdgeInfo(ParseRequiredError<ParsePartialEdgeInfoError>),
     #[error("owned_account {0}")]
     OwnedAccount(ParseSignedOwnedAccountError),
+    #[error("encryption_offer {0}")]
+    EncryptionOffer(ParseEncryptionOfferError),
+    #[error("admission_proof {0}")]
+    AdmissionProof(ParseAdmissionProofError),
 }
 
 impl From<&Handshake> for proto::Handshake {
@@ -87,9 +179,20 @@ impl From<&Handshake> for proto::Handshake {
             sender_peer_id: MF::some((&x.sender_peer_id).into()),
             target_peer_id: MF::some((&x.target_peer_id).into()),
             sender_listen_port: x.sender_listen_port.unwrap_or(0).into(),
+            sender_listen_addrs: x.sender_listen_addrs.iter().map(Into::into).collect(),
             sender_chain_info: MF::some((&x.sender_chain_info).into()),
             partial_edge_info: MF::some((&x.partial_edge_info).into()),
             owned_account: x.owned_account.as_ref().map(Into::into).into(),
+            sender_rpc_url: x.sender_rpc_url.clone().unwrap_or_default(),
+            encryption_offer: x.encryption_offer.as_ref().map(Into::into).into(),
+            supported_compressions: x
+                .supported_compressions
+                .iter()
+                .map(|c| match c {
+                    Compression::Zstd => proto::handshake::Compression::Zstd.into(),
+                })
+                .collect(),
+            admission_proof: x.admission_proof.as_ref().map(Into::into).into(),
             ..Self::default()
         }
     }
@@ -114,12 +217,32 @@ impl TryFrom<&proto::Handshake> for Handshake {
                     Some(port)
                 }
             },
+            sender_listen_addrs: try_from_slice(&p.sender_listen_addrs)
+                .map_err(Self::Error::SenderListenAddrs)?,
             sender_chain_info: try_from_required(&p.sender_chain_info)
                 .map_err(Self::Error::SenderChainInfo)?,
             partial_edge_info: try_from_required(&p.partial_edge_info)
                 .map_err(Self::Error::PartialEdgeInfo)?,
             owned_account: try_from_optional(&p.owned_account)
                 .map_err(Self::Error::OwnedAccount)?,
+            sender_rpc_url: if p.sender_rpc_url.is_empty() {
+                None
+            } else {
+                Some(p.sender_rpc_url.clone())
+            },
+            encryption_offer: try_from_optional(&p.encryption_offer)
+                .map_err(Self::Error::EncryptionOffer)?,
+            // Algorithms unknown to this node are skipped.
+            supported_compressions: p
+                .supported_compressions
+                .iter()
+                .filter_map(|c| match c.enum_value() {
+                    Ok(proto::handshake::Compression::Zstd) => Some(Compression::Zstd),
+                    _ => None,
+                })
+                .collect(),
+            admission_proof: try_from_optional(&p.admission_proof)
+                .map_err(Self::Error::AdmissionProof)?,
         })
     }
 }
@@ -150,6 +273,12 @@ impl From<(&PeerInfo, &HandshakeFailureReason)> for proto::HandshakeFailure {
                 reason: proto::handshake_failure::Reason::InvalidTarget.into(),
                 ..Self::default()
             },
+            HandshakeFailureReason::AdmissionChallenge(challenge) => Self {
+                peer_info: MF::some(pi.into()),
+                reason: proto::handshake_failure::Reason::AdmissionChallenge.into(),
+                admission_challenge: MF::some(challenge.into()),
+                ..Self::default()
+            },
         }
     }
 }
@@ -160,6 +289,8 @@ pub enum ParseHandshakeFailureError {
     PeerInfo(ParseRequiredError<ParsePeerInfoError>),
     #[error("genesis_id: {0}")]
     GenesisId(ParseRequiredError<ParseGenesisIdError>),
+    #[error("admission_challenge: {0}")]
+    AdmissionChallenge(ParseRequiredError<ParseAdmissionChallengeError>),
     #[error("reason: unknown")]
     UnknownReason,
 }
@@ -183,6 +314,12 @@ impl TryFrom<&proto::HandshakeFailure> for (PeerInfo, HandshakeFailureReason) {
             proto::handshake_failure::Reason::InvalidTarget => {
                 HandshakeFailureReason::InvalidTarget
             }
+            proto::handshake_failure::Reason::AdmissionChallenge => {
+                HandshakeFailureReason::AdmissionChallenge(
+                    try_from_required(&x.admission_challenge)
+                        .map_err(Self::Error::AdmissionChallenge)?,
+                )
+            }
             proto::handshake_failure::Reason::UNKNOWN => return Err(Self::Error::UnknownReason),
         };
         Ok((pi, hfr))
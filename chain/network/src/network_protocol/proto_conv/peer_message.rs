@@ -5,8 +5,8 @@ use crate::network_protocol::proto::peer_message::Message_type as ProtoMT;
 use crate::network_protocol::proto::{self};
 use crate::network_protocol::state_sync::{SnapshotHostInfo, SyncSnapshotHosts};
 use crate::network_protocol::{
-    AdvertisedPeerDistance, Disconnect, DistanceVector, PeerMessage, PeersRequest, PeersResponse,
-    RoutingTableUpdate, SyncAccountsData,
+    AdvertisedPeerDistance, DhtFindNodes, DhtNodes, Disconnect, DistanceVector, PeerMessage,
+    PeersRequest, PeersResponse, RoutingTableUpdate, SignedPeerRecord, SyncAccountsData,
 };
 use crate::network_protocol::{RoutedMessage, RoutedMessageV2};
 use crate::types::StateResponseInfo;
@@ -228,6 +228,41 @@ impl TryFrom<&proto::SyncSnapshotHosts> for SyncSnapshotHosts {
 
 //////////////////////////////////////////
 
+#[derive(thiserror::Error, Debug)]
+pub enum ParseSignedPeerRecordError {
+    #[error("peer_addr {0}")]
+    PeerAddr(ParseRequiredError<ParsePeerAddrError>),
+    #[error("timestamp {0}")]
+    Timestamp(ParseRequiredError<ParseTimestampError>),
+    #[error("signature {0}")]
+    Signature(ParseRequiredError<ParseSignatureError>),
+}
+
+impl From<&SignedPeerRecord> for proto::SignedPeerRecord {
+    fn from(x: &SignedPeerRecord) -> Self {
+        Self {
+            peer_addr: MF::some((&x.peer_addr).into()),
+            timestamp: MF::some(utc_to_proto(&x.timestamp)),
+            signature: MF::some((&x.signature).into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<&proto::SignedPeerRecord> for SignedPeerRecord {
+    type Error = ParseSignedPeerRecordError;
+    fn try_from(x: &proto::SignedPeerRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            peer_addr: try_from_required(&x.peer_addr).map_err(Self::Error::PeerAddr)?,
+            timestamp: map_from_required(&x.timestamp, utc_from_proto)
+                .map_err(Self::Error::Timestamp)?,
+            signature: try_from_required(&x.signature).map_err(Self::Error::Signature)?,
+        })
+    }
+}
+
+//////////////////////////////////////////
+
 impl From<&PeerMessage> for proto::PeerMessage {
     fn from(x: &PeerMessage) -> Self {
         Self {
@@ -271,6 +306,15 @@ impl From<&PeerMessage> for proto::PeerMessage {
                     direct_peers: pr.direct_peers.iter().map(Into::into).collect(),
                     ..Default::default()
                 }),
+                PeerMessage::DhtFindNodes(r) => ProtoMT::DhtFindNodes(proto::DhtFindNodes {
+                    target: MF::some((&r.target).into()),
+                    sender_record: r.sender_record.as_ref().map(Into::into).into(),
+                    ..Default::default()
+                }),
+                PeerMessage::DhtNodes(r) => ProtoMT::DhtNodes(proto::DhtNodes {
+                    records: r.records.iter().map(Into::into).collect(),
+                    ..Default::default()
+                }),
                 PeerMessage::BlockHeadersRequest(bhs) => {
                     ProtoMT::BlockHeadersRequest(proto::BlockHeadersRequest {
                         block_hashes: bhs.iter().map(Into::into).collect(),
@@ -364,6 +408,12 @@ pub enum ParsePeerMessageError {
     PeersRequest(ParsePeersRequestError),
     #[error("peers_response: {0}")]
     PeersResponse(ParseVecError<ParsePeerInfoError>),
+    #[error("dht_find_nodes target: {0}")]
+    DhtFindNodesTarget(ParseRequiredError<ParseCryptoHashError>),
+    #[error("dht_find_nodes sender_record: {0}")]
+    DhtFindNodesSenderRecord(ParseSignedPeerRecordError),
+    #[error("dht_nodes: {0}")]
+    DhtNodes(ParseVecError<ParseSignedPeerRecordError>),
     #[error("block_headers_request: {0}")]
     BlockHeadersRequest(ParseVecError<ParseCryptoHashError>),
     #[error("block_headers_response: {0}")]
@@ -441,6 +491,14 @@ impl TryFrom<&proto::PeerMessage> for PeerMessage {
                 direct_peers: try_from_slice(&pr.direct_peers)
                     .map_err(Self::Error::PeersResponse)?,
             }),
+            ProtoMT::DhtFindNodes(r) => PeerMessage::DhtFindNodes(DhtFindNodes {
+                target: try_from_required(&r.target).map_err(Self::Error::DhtFindNodesTarget)?,
+                sender_record: try_from_optional(&r.sender_record)
+                    .map_err(Self::Error::DhtFindNodesSenderRecord)?,
+            }),
+            ProtoMT::DhtNodes(r) => PeerMessage::DhtNodes(DhtNodes {
+                records: try_from_slice(&r.records).map_err(Self::Error::DhtNodes)?,
+            }),
             ProtoMT::BlockHeadersRequest(bhr) => PeerMessage::BlockHeadersRequest(
                 try_from_slice(&bhr.block_hashes).map_err(Self::Error::BlockHeadersRequest)?,
             ),
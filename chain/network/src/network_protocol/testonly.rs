@@ -345,9 +345,14 @@ pub fn make_handshake<R: Rng>(rng: &mut R, chain: &Chain) -> Handshake {
         sender_peer_id: a_id,
         target_peer_id: b_id,
         sender_listen_port: Some(rng.gen()),
+        sender_listen_addrs: vec![],
         sender_chain_info: chain.get_peer_chain_info(),
         partial_edge_info: make_partial_edge(rng),
         owned_account: None,
+        sender_rpc_url: None,
+        encryption_offer: None,
+        supported_compressions: vec![],
+        admission_proof: None,
     }
 }
 
@@ -56,13 +56,64 @@ fn bad_account_data_size() {
     assert!(ad.sign(&signer.into()).is_err());
 }
 
+#[test]
+fn encryption_offer() {
+    let mut rng = make_rng(72391841);
+    let node_key = data::make_secret_key(&mut rng);
+    let sender = PeerId::new(node_key.public_key());
+    let target = data::make_peer_id(&mut rng);
+    let offer = EncryptionOffer::new(&sender, &target, 7, rng.gen(), &node_key);
+    assert!(offer.verify(&sender, &target, 7));
+    // The offer is bound to the connection it has been made for.
+    assert!(!offer.verify(&sender, &data::make_peer_id(&mut rng), 7));
+    // The offer is bound to the edge proposed in the same Handshake.
+    assert!(!offer.verify(&sender, &target, 8));
+    // A replaced key is detected.
+    let forged = EncryptionOffer { ephemeral_key: rng.gen(), ..offer };
+    assert!(!forged.verify(&sender, &target, 7));
+}
+
+#[test]
+fn signed_peer_record() {
+    let mut rng = make_rng(1827364);
+    let clock = time::FakeClock::default();
+    let node_key = data::make_secret_key(&mut rng);
+    let record = SignedPeerRecord::new(data::make_addr(&mut rng), clock.now_utc(), &node_key);
+    assert!(record.verify());
+    // Neither the address nor the timestamp can be replaced.
+    let mut forged = record.clone();
+    forged.peer_addr.addr = data::make_addr(&mut rng);
+    assert!(!forged.verify());
+    let mut forged = record.clone();
+    forged.timestamp += time::Duration::seconds(1);
+    assert!(!forged.verify());
+}
+
 #[test]
 fn serialize_deserialize_protobuf_only() {
     let mut rng = make_rng(39521947542);
     let mut clock = time::FakeClock::default();
     let chain = data::Chain::make(&mut clock, &mut rng, 12);
+    let node_key = data::make_secret_key(&mut rng);
+    let sender = PeerId::new(node_key.public_key());
+    let target = data::make_peer_id(&mut rng);
     let msgs = [
         PeerMessage::Tier1Handshake(data::make_handshake(&mut rng, &chain)),
+        PeerMessage::Tier1Handshake(Handshake {
+            encryption_offer: Some(EncryptionOffer::new(
+                &sender,
+                &target,
+                rng.gen(),
+                rng.gen(),
+                &node_key,
+            )),
+            ..data::make_handshake(&mut rng, &chain)
+        }),
+        PeerMessage::Tier2Handshake(Handshake {
+            sender_rpc_url: Some("https://rpc.example.com".to_string()),
+            supported_compressions: vec![Compression::Zstd],
+            ..data::make_handshake(&mut rng, &chain)
+        }),
         PeerMessage::SyncAccountsData(SyncAccountsData {
             accounts_data: (0..4)
                 .map(|_| Arc::new(data::make_signed_account_data(&mut rng, &clock.clock())))
@@ -70,6 +121,37 @@ fn serialize_deserialize_protobuf_only() {
             incremental: true,
             requesting_full_sync: true,
         }),
+        PeerMessage::DhtFindNodes(DhtFindNodes {
+            target: CryptoHash::hash_bytes(b"target"),
+            sender_record: Some(SignedPeerRecord::new(
+                data::make_addr(&mut rng),
+                clock.now_utc(),
+                &node_key,
+            )),
+        }),
+        PeerMessage::DhtNodes(DhtNodes {
+            records: (0..3)
+                .map(|_| {
+                    let key = data::make_secret_key(&mut rng);
+                    SignedPeerRecord::new(data::make_addr(&mut rng), clock.now_utc(), &key)
+                })
+                .collect(),
+        }),
+        PeerMessage::HandshakeFailure(
+            data::make_peer_info(&mut rng),
+            HandshakeFailureReason::AdmissionChallenge(AdmissionChallenge {
+                seed: CryptoHash::hash_bytes(b"seed"),
+                difficulty: 16,
+            }),
+        ),
+        PeerMessage::Tier2Handshake(Handshake {
+            admission_proof: Some(AdmissionProof {
+                seed: CryptoHash::hash_bytes(b"seed"),
+                nonce: rng.gen(),
+            }),
+            sender_listen_addrs: vec![data::make_addr(&mut rng), data::make_addr(&mut rng)],
+            ..data::make_handshake(&mut rng, &chain)
+        }),
     ];
     for m in msgs {
         let m2 = PeerMessage::deserialize(Encoding::Proto, &m.serialize(Encoding::Proto))
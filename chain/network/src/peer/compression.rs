@@ -0,0 +1,255 @@
+//! Compression of large messages.
+//!
+//! Peers list the compression algorithms they support in their Handshakes.
+//! If both support zstd, every message sent after the Handshakes is prefixed
+//! with a byte telling whether the rest is compressed. Only blocks, chunk
+//! parts and state witness parts are compressed, and only above a size
+//! threshold, since compressing small messages isn't worth the CPU.
+//!
+//! Compression adapts to how well it works for each message type: state
+//! witness parts, for example, are pieces of an already compressed witness,
+//! so compressing them again hardly saves anything. Types whose messages
+//! don't shrink enough are sent uncompressed for a while, after which
+//! compression is tried again.
+
+use crate::network_protocol::{Compression, PeerMessage, RoutedMessageBody};
+use crate::stats::metrics;
+use std::collections::HashMap;
+use std::io::Read as _;
+
+const TAG_UNCOMPRESSED: u8 = 0;
+const TAG_ZSTD: u8 = 1;
+
+/// Messages of a type compressed on average to more than this fraction of
+/// their size are not worth compressing.
+const MAX_USEFUL_RATIO: f64 = 0.9;
+/// Weight of the last message in the average compression ratio of its type.
+const RATIO_SMOOTHING: f64 = 0.2;
+/// How many messages of a type which doesn't compress well are sent
+/// uncompressed before trying again.
+const SKIP_AFTER_POOR_RATIO: u32 = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum Error {
+    #[error("empty message")]
+    Empty,
+    #[error("unknown compression tag {0}")]
+    UnknownTag(u8),
+    #[error("decompressed message too large: want <={max_bytes}B")]
+    TooLarge { max_bytes: usize },
+    #[error("zstd: {0}")]
+    Zstd(#[source] std::io::Error),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Whether to offer compression to the peers.
+    pub enabled: bool,
+    /// Messages smaller than this are never compressed.
+    pub threshold_bytes: usize,
+    /// zstd compression level.
+    pub level: i32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { enabled: true, threshold_bytes: 16 * 1024, level: 1 }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let levels = zstd::compression_level_range();
+        if !levels.contains(&self.level) {
+            anyhow::bail!("message_compression.level must be in {levels:?}, got {}", self.level);
+        }
+        Ok(())
+    }
+
+    /// Compression algorithms to list in the Handshake.
+    pub fn supported(&self) -> Vec<Compression> {
+        if self.enabled {
+            vec![Compression::Zstd]
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Whether compressing the message might be worth it.
+fn is_compressible(msg: &PeerMessage) -> bool {
+    match msg {
+        PeerMessage::Block(_) => true,
+        PeerMessage::Routed(msg) => matches!(
+            msg.body,
+            RoutedMessageBody::VersionedPartialEncodedChunk(_)
+                | RoutedMessageBody::PartialEncodedChunkForward(_)
+                | RoutedMessageBody::PartialEncodedChunkResponse(_)
+                | RoutedMessageBody::PartialEncodedStateWitness(_)
+                | RoutedMessageBody::PartialEncodedStateWitnessForward(_)
+        ),
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct TypeStats {
+    /// Average ratio of the compressed to the uncompressed size.
+    ratio: Option<f64>,
+    /// Number of messages still to be sent uncompressed.
+    skip: u32,
+}
+
+/// Compresses the messages sent on a connection.
+pub(crate) struct Compressor {
+    threshold_bytes: usize,
+    level: i32,
+    stats: HashMap<&'static str, TypeStats>,
+}
+
+impl Compressor {
+    pub fn new(config: &Config) -> Self {
+        Self { threshold_bytes: config.threshold_bytes, level: config.level, stats: HashMap::new() }
+    }
+
+    /// Turns the serialized `msg` into a frame, compressing it if worth it.
+    pub fn compress(&mut self, msg: &PeerMessage, bytes: Vec<u8>) -> Vec<u8> {
+        if !is_compressible(msg) || bytes.len() < self.threshold_bytes {
+            return uncompressed(bytes);
+        }
+        let msg_type = msg.msg_variant();
+        let stats = self.stats.entry(msg_type).or_default();
+        if stats.skip > 0 {
+            stats.skip -= 1;
+            return uncompressed(bytes);
+        }
+        let mut frame = vec![TAG_ZSTD];
+        // Fails only with an invalid level, the message goes uncompressed then.
+        let compressed = zstd::stream::copy_encode(&bytes[..], &mut frame, self.level).is_ok();
+        let ratio = frame.len() as f64 / bytes.len() as f64;
+        let ratio = match stats.ratio {
+            Some(avg) => avg * (1. - RATIO_SMOOTHING) + ratio * RATIO_SMOOTHING,
+            None => ratio,
+        };
+        stats.ratio = Some(ratio);
+        if ratio > MAX_USEFUL_RATIO {
+            tracing::debug!(target: "network", msg_type, ratio, "messages don't compress well, skipping compression for a while");
+            stats.skip = SKIP_AFTER_POOR_RATIO;
+        }
+        let labels = [msg_type];
+        metrics::PEER_MESSAGE_COMPRESSION_INPUT_BYTES
+            .with_label_values(&labels)
+            .inc_by(bytes.len() as u64);
+        if !compressed || frame.len() >= bytes.len() {
+            metrics::PEER_MESSAGE_COMPRESSION_OUTPUT_BYTES
+                .with_label_values(&labels)
+                .inc_by(bytes.len() as u64);
+            return uncompressed(bytes);
+        }
+        metrics::PEER_MESSAGE_COMPRESSION_OUTPUT_BYTES
+            .with_label_values(&labels)
+            .inc_by(frame.len() as u64);
+        frame
+    }
+}
+
+fn uncompressed(mut bytes: Vec<u8>) -> Vec<u8> {
+    bytes.insert(0, TAG_UNCOMPRESSED);
+    bytes
+}
+
+/// Extracts the message from a frame, decompressing it if needed.
+/// Messages larger than `max_bytes` after decompression are rejected.
+pub(crate) fn decompress(mut frame: Vec<u8>, max_bytes: usize) -> Result<Vec<u8>, Error> {
+    match frame.first() {
+        None => Err(Error::Empty),
+        Some(&TAG_UNCOMPRESSED) => {
+            frame.remove(0);
+            Ok(frame)
+        }
+        Some(&TAG_ZSTD) => {
+            let mut bytes = vec![];
+            zstd::stream::Decoder::new(&frame[1..])
+                .map_err(Error::Zstd)?
+                .take(max_bytes as u64 + 1)
+                .read_to_end(&mut bytes)
+                .map_err(Error::Zstd)?;
+            if bytes.len() > max_bytes {
+                return Err(Error::TooLarge { max_bytes });
+            }
+            Ok(bytes)
+        }
+        Some(&tag) => Err(Error::UnknownTag(tag)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, Compressor, Config, Error, SKIP_AFTER_POOR_RATIO};
+    use crate::network_protocol::testonly as data;
+    use crate::network_protocol::PeerMessage;
+    use crate::testonly::make_rng;
+    use near_async::time;
+    use rand::RngCore as _;
+
+    /// Only the type of the message matters for compression.
+    fn make_block_message(seed: u64) -> PeerMessage {
+        let mut rng = make_rng(seed);
+        let mut clock = time::FakeClock::default();
+        let chain = data::Chain::make(&mut clock, &mut rng, 2);
+        PeerMessage::Block(chain.blocks[0].clone())
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let block = make_block_message(78124);
+        let mut compressor =
+            Compressor::new(&Config { threshold_bytes: 1000, ..Config::default() });
+
+        // Large messages are compressed.
+        let bytes = vec![7; 10000];
+        let frame = compressor.compress(&block, bytes.clone());
+        assert!(frame.len() < 1000);
+        assert_eq!(decompress(frame, 10000).unwrap(), bytes);
+        // Small ones aren't.
+        let bytes = vec![7; 100];
+        let frame = compressor.compress(&block, bytes.clone());
+        assert_eq!(frame.len(), 101);
+        assert_eq!(decompress(frame, 10000).unwrap(), bytes);
+        // Other types of messages aren't either.
+        let msg = PeerMessage::Disconnect(crate::network_protocol::Disconnect {
+            remove_from_connection_store: false,
+        });
+        let bytes = vec![7; 10000];
+        let frame = compressor.compress(&msg, bytes.clone());
+        assert_eq!(frame.len(), 10001);
+        assert_eq!(decompress(frame, 10000).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_incompressible() {
+        let block = make_block_message(98712);
+        let mut compressor = Compressor::new(&Config { threshold_bytes: 0, ..Config::default() });
+
+        let mut random = vec![0; 10000];
+        make_rng(98712).fill_bytes(&mut random);
+        // Incompressible messages are sent as they are, and compression
+        // isn't even attempted for a while.
+        assert_eq!(compressor.compress(&block, random.clone()).len(), 10001);
+        for _ in 0..SKIP_AFTER_POOR_RATIO {
+            assert_eq!(compressor.compress(&block, vec![7; 10000]).len(), 10001);
+        }
+        assert!(compressor.compress(&block, vec![7; 10000]).len() < 1000);
+    }
+
+    #[test]
+    fn test_decompression_bomb() {
+        let mut compressor = Compressor::new(&Config { threshold_bytes: 0, ..Config::default() });
+        let block = make_block_message(8127);
+        let frame = compressor.compress(&block, vec![0; 1 << 20]);
+        assert!(matches!(decompress(frame, 1000), Err(Error::TooLarge { .. })));
+        assert!(matches!(decompress(vec![], 1000), Err(Error::Empty)));
+        assert!(matches!(decompress(vec![5, 1, 2], 1000), Err(Error::UnknownTag(5))));
+    }
+}
@@ -0,0 +1,247 @@
+//! Encryption of the traffic between peers.
+//!
+//! Each side of a connection willing to encrypt it generates an ephemeral
+//! X25519 key and sends it in its Handshake, signed with its node key together
+//! with the nonce of the edge proposed in the Handshake (see `EncryptionOffer`).
+//! A Handshake with an offer whose signature doesn't cover its edge is
+//! rejected. If both Handshakes carry a key, the peers compute the
+//! Diffie-Hellman of their ephemeral keys and derive with HKDF-SHA256 a
+//! ChaCha20-Poly1305 key for each direction of the connection, as in the NN
+//! pattern of the Noise protocol framework. The signatures authenticate the
+//! ephemeral keys, so an on-path attacker can neither read nor tamper with the
+//! traffic of an encrypted connection, and the ephemeral keys give forward
+//! secrecy.
+//!
+//! The rest of the Handshake is not signed though, so an on-path attacker can
+//! remove the offer from it, and the connection then falls back to plaintext
+//! with `EncryptionMode::Enabled`. Such fallbacks are logged and counted in
+//! `near_peer_encryption_fallback_total`, but only `EncryptionMode::Required`
+//! protects against them.
+//!
+//! Every frame sent after the Handshakes is encrypted separately, with the
+//! number of frames sent before it in the same direction as the nonce, so
+//! frames which are dropped, reordered or replayed fail to decrypt.
+
+use crate::network_protocol::EncryptionOffer;
+use crate::types::PeerType;
+use near_crypto::SecretKey;
+use near_primitives::network::PeerId;
+use ring::{aead, agreement, hkdf};
+
+const HKDF_SALT: &[u8] = b"near-network-encryption";
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum Error {
+    #[error("invalid ephemeral key")]
+    InvalidKey,
+    #[error("frame failed to encrypt")]
+    Encrypt,
+    #[error("frame failed to decrypt")]
+    Decrypt,
+    #[error("nonce exhausted")]
+    NonceExhausted,
+}
+
+/// Whether to encrypt the connections with other peers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptionMode {
+    /// Never encrypt.
+    #[default]
+    Disabled,
+    /// Encrypt the connections with the peers which support encryption, and
+    /// fall back to plaintext with the others. This doesn't protect against
+    /// an on-path attacker, who can remove the offer from the Handshake.
+    Enabled,
+    /// Encrypt all the connections, refusing the peers which don't support encryption.
+    Required,
+}
+
+/// Ephemeral key offered in a Handshake, kept until the Handshake of the
+/// other side arrives.
+pub(crate) struct EphemeralKey {
+    private_key: agreement::EphemeralPrivateKey,
+    public_key: [u8; 32],
+}
+
+impl EphemeralKey {
+    pub fn new() -> Self {
+        let private_key = agreement::EphemeralPrivateKey::generate(
+            &agreement::X25519,
+            &ring::rand::SystemRandom::new(),
+        )
+        .expect("generating an X25519 key failed");
+        let public_key = private_key
+            .compute_public_key()
+            .expect("computing an X25519 public key failed")
+            .as_ref()
+            .try_into()
+            .unwrap();
+        Self { private_key, public_key }
+    }
+
+    /// Offers the key in the Handshake proposing the edge with `edge_nonce`.
+    pub fn offer(
+        &self,
+        sender: &PeerId,
+        target: &PeerId,
+        edge_nonce: u64,
+        node_key: &SecretKey,
+    ) -> EncryptionOffer {
+        EncryptionOffer::new(sender, target, edge_nonce, self.public_key, node_key)
+    }
+
+    /// Derives the ciphers of the connection from this key and the key
+    /// offered by the peer, which should already be verified.
+    /// `peer_type` is the type of the connection from this node's side.
+    pub fn agree(
+        self,
+        offer: &EncryptionOffer,
+        peer_type: PeerType,
+    ) -> Result<(SendCipher, RecvCipher), Error> {
+        // Both sides need to agree on the order of the keys.
+        let (outbound_key, inbound_key) = match peer_type {
+            PeerType::Outbound => (&self.public_key, &offer.ephemeral_key),
+            PeerType::Inbound => (&offer.ephemeral_key, &self.public_key),
+        };
+        let derive = |secret: &[u8], direction: &[u8]| -> Result<aead::LessSafeKey, Error> {
+            let info = [direction, &outbound_key[..], &inbound_key[..]];
+            let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, HKDF_SALT).extract(secret);
+            let okm = prk.expand(&info, &aead::CHACHA20_POLY1305).map_err(|_| Error::InvalidKey)?;
+            Ok(aead::LessSafeKey::new(aead::UnboundKey::from(okm)))
+        };
+        let (outbound_to_inbound, inbound_to_outbound) = agreement::agree_ephemeral(
+            self.private_key,
+            &agreement::UnparsedPublicKey::new(&agreement::X25519, &offer.ephemeral_key),
+            Error::InvalidKey,
+            |secret| Ok((derive(secret, b"outbound")?, derive(secret, b"inbound")?)),
+        )?;
+        let (send, recv) = match peer_type {
+            PeerType::Outbound => (outbound_to_inbound, inbound_to_outbound),
+            PeerType::Inbound => (inbound_to_outbound, outbound_to_inbound),
+        };
+        Ok((SendCipher(Cipher::new(send)), RecvCipher(Cipher::new(recv))))
+    }
+}
+
+struct Cipher {
+    key: aead::LessSafeKey,
+    /// Number of frames processed so far.
+    counter: u64,
+}
+
+impl Cipher {
+    fn new(key: aead::LessSafeKey) -> Self {
+        Self { key, counter: 0 }
+    }
+
+    fn next_nonce(&mut self) -> Result<aead::Nonce, Error> {
+        let mut nonce = [0; aead::NONCE_LEN];
+        nonce[4..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter = self.counter.checked_add(1).ok_or(Error::NonceExhausted)?;
+        Ok(aead::Nonce::assume_unique_for_key(nonce))
+    }
+}
+
+/// Encrypts the frames sent on a connection.
+pub(crate) struct SendCipher(Cipher);
+
+impl SendCipher {
+    /// Encrypts `frame` in place, appending the authentication tag.
+    pub fn seal(&mut self, frame: &mut Vec<u8>) -> Result<(), Error> {
+        let nonce = self.0.next_nonce()?;
+        self.0
+            .key
+            .seal_in_place_append_tag(nonce, aead::Aad::empty(), frame)
+            .map_err(|_| Error::Encrypt)
+    }
+}
+
+/// Decrypts the frames received on a connection.
+pub(crate) struct RecvCipher(Cipher);
+
+impl RecvCipher {
+    /// Decrypts `frame` in place, removing the authentication tag.
+    pub fn open(&mut self, frame: &mut Vec<u8>) -> Result<(), Error> {
+        let nonce = self.0.next_nonce()?;
+        let len = self
+            .0
+            .key
+            .open_in_place(nonce, aead::Aad::empty(), frame)
+            .map_err(|_| Error::Decrypt)?
+            .len();
+        frame.truncate(len);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncryptionMode, EphemeralKey, RecvCipher, SendCipher};
+    use crate::network_protocol::testonly as data;
+    use crate::testonly::make_rng;
+    use crate::types::PeerType;
+    use near_primitives::network::PeerId;
+
+    /// Ciphers of the outbound and the inbound side of a connection.
+    fn connect(seed: u64) -> ((SendCipher, RecvCipher), (SendCipher, RecvCipher)) {
+        let mut rng = make_rng(seed);
+        let a_key = data::make_secret_key(&mut rng);
+        let b_key = data::make_secret_key(&mut rng);
+        let a = PeerId::new(a_key.public_key());
+        let b = PeerId::new(b_key.public_key());
+        let a_ephemeral = EphemeralKey::new();
+        let b_ephemeral = EphemeralKey::new();
+        let a_offer = a_ephemeral.offer(&a, &b, 1, &a_key);
+        let b_offer = b_ephemeral.offer(&b, &a, 1, &b_key);
+        (
+            a_ephemeral.agree(&b_offer, PeerType::Outbound).unwrap(),
+            b_ephemeral.agree(&a_offer, PeerType::Inbound).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_agree() {
+        let ((mut a_send, mut a_recv), (mut b_send, mut b_recv)) = connect(93847120);
+
+        for msg in [&b"hello"[..], &[], &[7; 1000]] {
+            let mut frame = msg.to_vec();
+            a_send.seal(&mut frame).unwrap();
+            assert_ne!(frame, msg);
+            b_recv.open(&mut frame).unwrap();
+            assert_eq!(frame, msg);
+
+            let mut frame = msg.to_vec();
+            b_send.seal(&mut frame).unwrap();
+            a_recv.open(&mut frame).unwrap();
+            assert_eq!(frame, msg);
+        }
+
+        // Tampered frames are rejected.
+        let mut frame = b"hello".to_vec();
+        a_send.seal(&mut frame).unwrap();
+        frame[0] ^= 1;
+        assert!(b_recv.open(&mut frame).is_err());
+    }
+
+    /// A frame sent twice fails to decrypt the second time.
+    #[test]
+    fn test_replay() {
+        let ((mut a_send, _), (_, mut b_recv)) = connect(1238971);
+
+        let mut frame = b"block".to_vec();
+        a_send.seal(&mut frame).unwrap();
+        let mut replayed = frame.clone();
+        b_recv.open(&mut frame).unwrap();
+        assert!(b_recv.open(&mut replayed).is_err());
+    }
+
+    #[test]
+    fn test_mode_serde() {
+        assert_eq!(
+            serde_json::from_str::<EncryptionMode>("\"required\"").unwrap(),
+            EncryptionMode::Required
+        );
+        assert_eq!(EncryptionMode::default(), EncryptionMode::Disabled);
+    }
+}
@@ -1,5 +1,7 @@
+pub(crate) mod compression;
+pub(crate) mod encryption;
 pub(crate) mod peer_actor;
-mod stream;
+pub(crate) mod stream;
 mod tracker;
 mod transfer_stats;
 
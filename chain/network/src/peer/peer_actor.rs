@@ -3,15 +3,19 @@ use crate::client::{
     AnnounceAccountRequest, BlockHeadersRequest, BlockHeadersResponse, BlockRequest, BlockResponse,
     ProcessTxRequest, RecvChallenge, StateRequestHeader, StateRequestPart, StateResponse,
 };
+use crate::concurrency;
 use crate::concurrency::atomic_cell::AtomicCell;
 use crate::concurrency::demux;
 use crate::config::PEERS_RESPONSE_MAX_PEERS;
 use crate::network_protocol::SnapshotHostInfoVerificationError;
 use crate::network_protocol::{
-    DistanceVector, Edge, EdgeState, Encoding, OwnedAccount, ParsePeerMessageError,
-    PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, PeersRequest, PeersResponse,
-    RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate, SyncAccountsData, SyncSnapshotHosts,
+    AdmissionChallenge, AdmissionProof, DistanceVector, Edge, EdgeState, Encoding, EncryptionOffer,
+    OwnedAccount, ParsePeerMessageError, PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo,
+    PeersRequest, PeersResponse, RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate,
+    SyncAccountsData, SyncSnapshotHosts,
 };
+use crate::peer::compression;
+use crate::peer::encryption::{self, EncryptionMode};
 use crate::peer::stream;
 use crate::peer::tracker::Tracker;
 use crate::peer_manager::connection;
@@ -19,8 +23,14 @@ use crate::peer_manager::network_state::{NetworkState, PRUNE_EDGES_AFTER};
 #[cfg(test)]
 use crate::peer_manager::peer_manager_actor::Event;
 use crate::peer_manager::peer_manager_actor::MAX_TIER2_PEERS;
+use crate::peer_manager::peer_store::PeerEvent;
 use crate::private_actix::{RegisterPeerError, SendMessage};
+use crate::rate_limits::admission;
+use crate::rate_limits::bandwidth::ConnectionLimiters;
+use crate::rate_limits::message_size::{MessageKind, OversizePolicy};
 use crate::rate_limits::messages_limits;
+use crate::rate_limits::qos::QosClass;
+use crate::recorder::Direction;
 use crate::routing::edge::verify_nonce;
 use crate::routing::NetworkTopologyChange;
 use crate::snapshot_hosts::SnapshotHostInfoError;
@@ -97,6 +107,10 @@ pub struct HandshakeCompletedEvent {
 pub(crate) enum ClosingReason {
     #[error("too many inbound connections in connecting state")]
     TooManyInbound,
+    #[error("too many inbound connections from the same IP")]
+    TooManyInboundFromIp,
+    #[error("too many inbound handshakes")]
+    TooManyHandshakes,
     #[error("outbound not allowed: {0}")]
     OutboundNotAllowed(connection::PoolError),
 
@@ -120,6 +134,14 @@ pub(crate) enum ClosingReason {
     TooLargeClockSkew,
     #[error("owned_account.peer_id doesn't match handshake.sender_peer_id")]
     OwnedAccountMismatch,
+    #[error("peer doesn't support encryption, which is required")]
+    EncryptionRequired,
+    #[error("Received a message over the size limit of its kind")]
+    MessageTooLarge,
+    #[error("Local IP of the connection is no longer in use")]
+    LocalIpChanged,
+    #[error("Peer reconnected from another IP")]
+    PeerMigrated,
     #[error("PeerActor stopped NOT via PeerActor::stop()")]
     Unknown,
 }
@@ -131,6 +153,8 @@ impl ClosingReason {
     pub(crate) fn remove_from_connection_store(&self) -> bool {
         match self {
             ClosingReason::TooManyInbound => false, // outbound may be still be OK
+            ClosingReason::TooManyInboundFromIp => false, // outbound may be still be OK
+            ClosingReason::TooManyHandshakes => false, // the flood will pass
             ClosingReason::OutboundNotAllowed(_) => true, // outbound not allowed
             ClosingReason::Ban(_) => true,          // banned
             ClosingReason::HandshakeFailed => false, // handshake may simply time out
@@ -141,6 +165,10 @@ impl ClosingReason {
             ClosingReason::DisconnectMessage => false, // graceful disconnect
             ClosingReason::TooLargeClockSkew => true, // reconnect will fail for the same reason
             ClosingReason::OwnedAccountMismatch => true, // misbehaving peer
+            ClosingReason::EncryptionRequired => true, // reconnect will fail for the same reason
+            ClosingReason::MessageTooLarge => true, // misbehaving peer
+            ClosingReason::LocalIpChanged => false, // reconnecting from the new IP
+            ClosingReason::PeerMigrated => false,   // the peer is reconnecting
             ClosingReason::Unknown => false,        // only happens in tests
         }
     }
@@ -154,10 +182,12 @@ pub(crate) struct PeerActor {
     /// This node's id and address (either listening or socket address).
     my_node_info: PeerInfo,
 
-    /// TEST-ONLY
+    /// Addresses of both ends of the stream.
     stream_id: crate::tcp::StreamId,
     /// Peer address from connection.
     peer_addr: SocketAddr,
+    /// Local address of the connection.
+    local_addr: SocketAddr,
     /// Peer type.
     peer_type: PeerType,
 
@@ -187,6 +217,17 @@ pub(crate) struct PeerActor {
 
     /// Per-message rate limits for incoming messages.
     received_messages_rate_limits: messages_limits::RateLimits,
+    /// Ephemeral key offered in the Handshake of an outbound connection.
+    encryption_key: Option<encryption::EphemeralKey>,
+    /// Slot taken by an inbound connection from the IP of the peer.
+    _inbound_slot: Option<admission::InboundSlot>,
+    /// Challenge sent to the peer, whose solution the next Handshake of the
+    /// peer has to carry.
+    admission_challenge: Option<AdmissionChallenge>,
+    /// Compresses the messages sent, once both sides agree on compression.
+    compressor: Option<Mutex<compression::Compressor>>,
+    /// Whether the messages received are compressed.
+    decompress: bool,
 }
 
 impl Debug for PeerActor {
@@ -202,6 +243,8 @@ struct HandshakeSpec {
     tier: tcp::Tier,
     protocol_version: ProtocolVersion,
     partial_edge_info: PartialEdgeInfo,
+    encryption_offer: Option<EncryptionOffer>,
+    admission_proof: Option<AdmissionProof>,
 }
 
 type HandshakeSignalSender = tokio::sync::oneshot::Sender<std::convert::Infallible>;
@@ -256,6 +299,26 @@ impl PeerActor {
         force_encoding: Option<Encoding>,
         network_state: Arc<NetworkState>,
     ) -> Result<(actix::Addr<Self>, HandshakeSignal), ClosingReason> {
+        let encryption_key = match (&stream.type_, network_state.config.encryption) {
+            (
+                tcp::StreamType::Outbound { .. },
+                EncryptionMode::Enabled | EncryptionMode::Required,
+            ) => Some(encryption::EphemeralKey::new()),
+            _ => None,
+        };
+        let inbound_slot = match &stream.type_ {
+            tcp::StreamType::Inbound => {
+                Some(network_state.admission.inbound_connection(stream.peer_addr.ip()).ok_or_else(
+                    || {
+                        metrics::INBOUND_HANDSHAKES_NOT_ADMITTED
+                            .with_label_values(&["ip_cap"])
+                            .inc();
+                        ClosingReason::TooManyInboundFromIp
+                    },
+                )?)
+            }
+            tcp::StreamType::Outbound { .. } => None,
+        };
         let connecting_status = match &stream.type_ {
             tcp::StreamType::Inbound => ConnectingStatus::Inbound(
                 network_state
@@ -285,11 +348,23 @@ impl PeerActor {
                             .map_err(ClosingReason::OutboundNotAllowed)?
                     }
                 },
-                handshake_spec: HandshakeSpec {
-                    partial_edge_info: network_state.propose_edge(&clock, peer_id, None),
-                    protocol_version: PROTOCOL_VERSION,
-                    tier: *tier,
-                    peer_id: peer_id.clone(),
+                handshake_spec: {
+                    let partial_edge_info = network_state.propose_edge(&clock, peer_id, None);
+                    HandshakeSpec {
+                        encryption_offer: encryption_key.as_ref().map(|key| {
+                            key.offer(
+                                &network_state.config.node_id(),
+                                peer_id,
+                                partial_edge_info.nonce,
+                                &network_state.config.node_key,
+                            )
+                        }),
+                        partial_edge_info,
+                        protocol_version: PROTOCOL_VERSION,
+                        tier: *tier,
+                        peer_id: peer_id.clone(),
+                        admission_proof: None,
+                    }
                 },
             },
         };
@@ -299,6 +374,10 @@ impl PeerActor {
             tcp::StreamType::Outbound { tier, .. } if tier == &tcp::Tier::T1 => {
                 Some(Encoding::Proto)
             }
+            // Encryption is offered only in proto Handshakes. Besides, a borsh
+            // copy of the Handshake would reach the peer after it has started
+            // decrypting.
+            tcp::StreamType::Outbound { .. } if encryption_key.is_some() => Some(Encoding::Proto),
             _ => force_encoding,
         };
         let my_node_info = PeerInfo {
@@ -312,6 +391,11 @@ impl PeerActor {
             &network_state.config.received_messages_rate_limits,
             clock.now(),
         );
+        let bandwidth_limiters = ConnectionLimiters::new(
+            &clock,
+            &network_state.config.bandwidth_limits,
+            &network_state.bandwidth_limiters,
+        );
         // recv is the HandshakeSignal returned by this spawn_inner() call.
         let (send, recv): (HandshakeSignalSender, HandshakeSignal) =
             tokio::sync::oneshot::channel();
@@ -320,15 +404,25 @@ impl PeerActor {
             Self::start_in_arbiter(&actix::Arbiter::new().handle(), move |ctx| {
                 let stream_id = stream.id();
                 let peer_addr = stream.peer_addr;
+                let local_addr = stream.local_addr;
                 let stream_type = stream.type_.clone();
                 let stats = Arc::new(connection::Stats::default());
-                let framed = stream::FramedStream::spawn(ctx, stream, stats.clone());
+                // Until the Handshakes are done, only they may be sent.
+                let max_recv_bytes = network_state.config.message_size_limits.handshake as usize;
+                let framed = stream::FramedStream::spawn(
+                    ctx,
+                    stream,
+                    stats.clone(),
+                    bandwidth_limiters,
+                    max_recv_bytes,
+                );
                 Self {
                     closing_reason: None,
                     clock,
                     my_node_info,
                     stream_id,
                     peer_addr,
+                    local_addr,
                     peer_type: match &stream_type {
                         tcp::StreamType::Inbound => PeerType::Inbound,
                         tcp::StreamType::Outbound { .. } => PeerType::Outbound,
@@ -353,6 +447,11 @@ impl PeerActor {
                     .into(),
                     network_state,
                     received_messages_rate_limits,
+                    encryption_key,
+                    _inbound_slot: inbound_slot,
+                    admission_challenge: None,
+                    compressor: None,
+                    decompress: false,
                 }
             }),
             recv,
@@ -395,6 +494,9 @@ impl PeerActor {
         if let (PeerStatus::Ready(conn), PeerMessage::PeersRequest(_)) = (&self.peer_status, msg) {
             conn.last_time_peer_requested.store(Some(self.clock.now()));
         }
+        if let Some(recorder) = self.network_state.recorder.get() {
+            recorder.record(Direction::Outbound, self.other_peer_id(), msg);
+        }
         if let Some(enc) = self.encoding() {
             return self.send_message_with_encoding(msg, enc);
         }
@@ -417,7 +519,7 @@ impl PeerActor {
             // peers to update its height at the peer. In the future we will introduce a new
             // peer message type for that and then we can enable this check again.
             //PeerMessage::Block(b) if self.tracker.lock().has_received(b.hash()) => return,
-            PeerMessage::BlockRequest(h) => self.tracker.lock().push_request(*h),
+            PeerMessage::BlockRequest(h) => self.tracker.lock().push_request(*h, self.clock.now()),
             PeerMessage::SyncAccountsData(d) => metrics::SYNC_ACCOUNTS_DATA
                 .with_label_values(&[
                     "sent",
@@ -434,17 +536,27 @@ impl PeerActor {
             _ => (),
         };
 
-        let bytes = msg.serialize(enc);
+        let mut bytes = msg.serialize(enc);
+        let msg_len = bytes.len();
+        // The peer would reject the message anyway, if it has the same limits.
+        if let Some(kind) = self.network_state.config.message_size_limits.oversize(msg, msg_len) {
+            metrics::PEER_MESSAGE_OVERSIZE_TOTAL.with_label_values(&["sent", kind.as_ref()]).inc();
+            tracing::warn!(target: "network", msg_type = msg.msg_variant(), msg_len, "Not sending a message over the size limit");
+            return;
+        }
+        if let Some(compressor) = &self.compressor {
+            bytes = compressor.lock().compress(msg, bytes);
+        }
         self.tracker.lock().increment_sent(&self.clock, bytes.len() as u64);
         let bytes_len = bytes.len();
         tracing::trace!(target: "network", msg_len = bytes_len);
-        self.framed.send(stream::Frame(bytes));
+        self.framed.send(QosClass::of(msg), stream::Frame(bytes));
         metrics::PEER_DATA_SENT_BYTES.inc_by(bytes_len as u64);
         let msg_type = msg.msg_variant();
         metrics::PEER_MESSAGE_SENT_BY_TYPE_TOTAL.with_label_values(&[msg_type]).inc();
         metrics::PEER_MESSAGE_SENT_BY_TYPE_BYTES
             .with_label_values(&[msg_type])
-            .inc_by(bytes_len as u64);
+            .inc_by(msg_len as u64);
     }
 
     fn send_handshake(&self, spec: HandshakeSpec) {
@@ -460,6 +572,7 @@ impl PeerActor {
             sender_peer_id: self.network_state.config.node_id(),
             target_peer_id: spec.peer_id,
             sender_listen_port: self.network_state.config.node_addr.as_ref().map(|a| a.port()),
+            sender_listen_addrs: self.network_state.config.advertised_listen_addrs(),
             sender_chain_info: PeerChainInfoV2 {
                 genesis_id: self.network_state.genesis_id.clone(),
                 // TODO: remove `height` from PeerChainInfo
@@ -476,6 +589,10 @@ impl PeerActor {
                 }
                 .sign(&signer)
             }),
+            sender_rpc_url: self.network_state.config.public_rpc_url.clone(),
+            encryption_offer: spec.encryption_offer,
+            supported_compressions: self.network_state.config.message_compression.supported(),
+            admission_proof: spec.admission_proof,
         };
         let msg = match spec.tier {
             tcp::Tier::T1 => PeerMessage::Tier1Handshake(handshake),
@@ -492,6 +609,29 @@ impl PeerActor {
         ctx.stop();
     }
 
+    /// Drops a received message of `len` bytes over the limit of its `kind`,
+    /// closing the connection if the policy says so. An oversize Handshake, or
+    /// any oversize message before the Handshakes are done, always closes it:
+    /// the peer would wait for a response otherwise.
+    fn received_oversize(
+        &mut self,
+        ctx: &mut actix::Context<PeerActor>,
+        kind: Option<MessageKind>,
+        len: usize,
+    ) {
+        let kind_label = kind.as_ref().map_or("unknown", |kind| kind.as_ref());
+        metrics::PEER_MESSAGE_OVERSIZE_TOTAL.with_label_values(&["received", kind_label]).inc();
+        tracing::debug!(target: "network", peer = %self.peer_info, kind = kind_label, len, "Received a message over the size limit");
+        let connecting = matches!(self.peer_status, PeerStatus::Connecting(..));
+        if connecting
+            || kind == Some(MessageKind::Handshake)
+            || self.network_state.config.message_size_limits.oversize_policy
+                == OversizePolicy::Disconnect
+        {
+            self.stop(ctx, ClosingReason::MessageTooLarge);
+        }
+    }
+
     /// `PeerId` of the current node.
     fn my_node_id(&self) -> &PeerId {
         &self.my_node_info.id
@@ -578,6 +718,47 @@ impl PeerActor {
                     ));
                     return;
                 }
+                // Over the rate of inbound handshakes, the peer has to resend
+                // the handshake with a solution of a challenge.
+                if let Some(challenge) = self.admission_challenge.take() {
+                    if !handshake
+                        .admission_proof
+                        .as_ref()
+                        .is_some_and(|proof| challenge.verify(&handshake.sender_peer_id, proof))
+                    {
+                        tracing::debug!(target: "network", peer_id=?handshake.sender_peer_id, "invalid solution of the admission challenge, disconnecting");
+                        metrics::INBOUND_HANDSHAKES_NOT_ADMITTED
+                            .with_label_values(&["bad_proof"])
+                            .inc();
+                        self.stop(ctx, ClosingReason::HandshakeFailed);
+                        return;
+                    }
+                } else {
+                    match self.network_state.admission.handshake() {
+                        admission::Verdict::Process => {}
+                        // Challenges are supported only in proto encoding.
+                        admission::Verdict::Challenge(challenge)
+                            if self.encoding() == Some(Encoding::Proto) =>
+                        {
+                            metrics::INBOUND_HANDSHAKES_NOT_ADMITTED
+                                .with_label_values(&["challenged"])
+                                .inc();
+                            self.send_message_or_log(&PeerMessage::HandshakeFailure(
+                                self.my_node_info.clone(),
+                                HandshakeFailureReason::AdmissionChallenge(challenge.clone()),
+                            ));
+                            self.admission_challenge = Some(challenge);
+                            return;
+                        }
+                        admission::Verdict::Challenge(_) | admission::Verdict::Refuse => {
+                            metrics::INBOUND_HANDSHAKES_NOT_ADMITTED
+                                .with_label_values(&["refused"])
+                                .inc();
+                            self.stop(ctx, ClosingReason::TooManyHandshakes);
+                            return;
+                        }
+                    }
+                }
 
                 // Verify if nonce is sane.
                 if let Err(err) = verify_nonce(&self.clock, handshake.partial_edge_info.nonce) {
@@ -615,8 +796,97 @@ impl PeerActor {
             }
         }
 
-        // Merge partial edges.
+        // Agree on the keys of the connection, if both sides offer encryption.
+        // The outbound side has made its offer already, the inbound side makes
+        // one only in response to an offer.
+        let mode = self.network_state.config.encryption;
+        let my_key = match cs {
+            ConnectingStatus::Outbound { .. } => self.encryption_key.take(),
+            ConnectingStatus::Inbound { .. } => match (mode, &handshake.encryption_offer) {
+                (EncryptionMode::Disabled, _) | (_, None) => None,
+                _ => Some(encryption::EphemeralKey::new()),
+            },
+        };
+        // The offer has to be signed together with the edge of this Handshake,
+        // so that it can't be taken from another one. Both sides sign the
+        // edge with the same nonce, see below.
         let nonce = handshake.partial_edge_info.nonce;
+        let mut encryption = None;
+        if let (Some(key), Some(offer)) = (my_key, &handshake.encryption_offer) {
+            if !offer.verify(&handshake.sender_peer_id, self.my_node_id(), nonce) {
+                self.stop(ctx, ClosingReason::Ban(ReasonForBan::InvalidSignature));
+                return;
+            }
+            let my_offer = key.offer(
+                self.my_node_id(),
+                &handshake.sender_peer_id,
+                nonce,
+                &self.network_state.config.node_key,
+            );
+            match key.agree(offer, self.peer_type) {
+                Ok(ciphers) => encryption = Some((my_offer, ciphers)),
+                Err(err) => {
+                    tracing::debug!(target: "network", peer_id=?handshake.sender_peer_id, %err, "failed to agree on the encryption keys");
+                    self.stop(ctx, ClosingReason::HandshakeFailed);
+                    return;
+                }
+            }
+        }
+        if encryption.is_none() {
+            match mode {
+                EncryptionMode::Disabled => {}
+                // The offer might have been removed on the way, which only
+                // `Required` protects against.
+                EncryptionMode::Enabled => {
+                    tracing::warn!(target: "network", peer_id=?handshake.sender_peer_id, "peer doesn't support encryption, the connection is not encrypted");
+                    let peer_type: &str = self.peer_type.into();
+                    metrics::PEER_ENCRYPTION_FALLBACK_TOTAL.with_label_values(&[peer_type]).inc();
+                }
+                EncryptionMode::Required => {
+                    tracing::debug!(target: "network", peer_id=?handshake.sender_peer_id, "peer doesn't support encryption, disconnecting");
+                    self.stop(ctx, ClosingReason::EncryptionRequired);
+                    return;
+                }
+            }
+        }
+        // The peer may send messages of any kind after its Handshake, prefixed
+        // with the compression tag.
+        let max_recv_bytes = self.network_state.config.message_size_limits.max_limit() as usize;
+        self.framed.set_max_recv_bytes(max_recv_bytes + 1);
+
+        // Everything after the two Handshakes is encrypted: the outbound side
+        // starts encrypting right away, since it has sent its Handshake already,
+        // the inbound side once it has sent the response to this Handshake,
+        // after the connection is registered.
+        let (encryption_offer, mut send_cipher) = match encryption {
+            Some((my_offer, (send_cipher, recv_cipher))) => {
+                self.framed.start_decryption(recv_cipher);
+                (Some(my_offer), Some(send_cipher))
+            }
+            None => (None, None),
+        };
+        if self.peer_type == PeerType::Outbound {
+            if let Some(send_cipher) = send_cipher.take() {
+                self.framed.start_encryption(send_cipher);
+            }
+        }
+
+        // Compress the messages after the Handshakes if both sides support it.
+        // Like encryption, the inbound side starts compressing only once it
+        // has sent the response to this Handshake.
+        let mut compressor = None;
+        let my_compressions = self.network_state.config.message_compression.supported();
+        if my_compressions.iter().any(|c| handshake.supported_compressions.contains(c)) {
+            self.decompress = true;
+            compressor = Some(Mutex::new(compression::Compressor::new(
+                &self.network_state.config.message_compression,
+            )));
+        }
+        if self.peer_type == PeerType::Outbound {
+            self.compressor = compressor.take();
+        }
+
+        // Merge partial edges.
         let partial_edge_info = match cs {
             ConnectingStatus::Outbound { handshake_spec, .. } => {
                 handshake_spec.partial_edge_info.clone()
@@ -640,9 +910,18 @@ impl PeerActor {
         // preemptively.
         let peer_info = PeerInfo {
             id: handshake.sender_peer_id.clone(),
+            // A peer listening on several networks is dialed back on the one
+            // the connection came from.
             addr: handshake
-                .sender_listen_port
-                .map(|port| SocketAddr::new(self.peer_addr.ip(), port)),
+                .sender_listen_addrs
+                .iter()
+                .find(|addr| addr.ip() == self.peer_addr.ip())
+                .copied()
+                .or_else(|| {
+                    handshake
+                        .sender_listen_port
+                        .map(|port| SocketAddr::new(self.peer_addr.ip(), port))
+                }),
             account_id: None,
         };
 
@@ -655,8 +934,10 @@ impl PeerActor {
             genesis_id: handshake.sender_chain_info.genesis_id.clone(),
             tracked_shards: handshake.sender_chain_info.tracked_shards.clone(),
             archival: handshake.sender_chain_info.archival,
+            rpc_url: handshake.sender_rpc_url.clone(),
             last_block: Default::default(),
             peer_type: self.peer_type,
+            local_addr: self.local_addr,
             stats: self.stats.clone(),
             _peer_connections_metric: metrics::PEER_CONNECTIONS.new_point(&metrics::Connection {
                 type_: self.peer_type,
@@ -717,12 +998,19 @@ impl PeerActor {
                         act.peer_status = PeerStatus::Ready(conn.clone());
                         // Respond to handshake if it's inbound and connection was consolidated.
                         if act.peer_type == PeerType::Inbound {
+                            act.network_state.reachability.inbound_connection(act.peer_addr);
                             act.send_handshake(HandshakeSpec{
                                 peer_id: handshake.sender_peer_id.clone(),
                                 tier,
                                 protocol_version: handshake.protocol_version,
                                 partial_edge_info: partial_edge_info,
+                                encryption_offer,
+                                admission_proof: None,
                             });
+                            if let Some(send_cipher) = send_cipher {
+                                act.framed.start_encryption(send_cipher);
+                            }
+                            act.compressor = compressor;
                         }
                         // TIER1 is strictly reserved for BFT consensensus messages,
                         // so all kinds of periodical syncs happen only on TIER2 connections.
@@ -892,6 +1180,32 @@ impl PeerActor {
                         self.network_state.peer_store.add_direct_peer(&self.clock, peer_info);
                         self.stop(ctx, ClosingReason::HandshakeFailed);
                     }
+                    HandshakeFailureReason::AdmissionChallenge(challenge) => {
+                        if challenge.difficulty > admission::MAX_SOLVED_DIFFICULTY {
+                            tracing::warn!(target: "network", "Node ({}) asked for a solution of an admission challenge of difficulty {}, which is too hard", peer_info, challenge.difficulty);
+                            self.stop(ctx, ClosingReason::HandshakeFailed);
+                            return;
+                        }
+                        // Retry the handshake with the solution.
+                        let my_node_id = self.my_node_id().clone();
+                        ctx.wait(
+                            wrap_future(concurrency::rayon::run(move || {
+                                challenge.solve(&my_node_id)
+                            }))
+                            .then(move |proof, act: &mut Self, _| {
+                                if let PeerStatus::Connecting(
+                                    _,
+                                    ConnectingStatus::Outbound { handshake_spec, .. },
+                                ) = &mut act.peer_status
+                                {
+                                    handshake_spec.admission_proof = Some(proof);
+                                    let spec = handshake_spec.clone();
+                                    act.send_handshake(spec);
+                                }
+                                actix::fut::ready(())
+                            }),
+                        );
+                    }
                 }
             }
             // TODO(gprusak): LastEdge should rather be a variant of HandshakeFailure.
@@ -921,12 +1235,21 @@ impl PeerActor {
                     self.stop(ctx, ClosingReason::HandshakeFailed);
                     return;
                 }
-                // Recreate the edge with a newer nonce.
+                // Recreate the edge with a newer nonce, and the encryption
+                // offer signed together with it.
                 handshake_spec.partial_edge_info = self.network_state.propose_edge(
                     &self.clock,
                     &handshake_spec.peer_id,
                     Some(std::cmp::max(edge.next(), Edge::create_fresh_nonce(&self.clock))),
                 );
+                handshake_spec.encryption_offer = self.encryption_key.as_ref().map(|key| {
+                    key.offer(
+                        &self.my_node_info.id,
+                        &handshake_spec.peer_id,
+                        handshake_spec.partial_edge_info.nonce,
+                        &self.network_state.config.node_key,
+                    )
+                });
                 let spec = handshake_spec.clone();
                 ctx.wait(actix::fut::ready(()).then(move |_, act: &mut Self, _| {
                     act.send_handshake(spec);
@@ -956,10 +1279,11 @@ impl PeerActor {
         clock: &time::Clock,
         network_state: &NetworkState,
         peer_id: PeerId,
+        author: PeerId,
         msg_hash: CryptoHash,
         body: RoutedMessageBody,
     ) -> Result<Option<RoutedMessageBody>, ReasonForBan> {
-        Ok(network_state.receive_routed_message(clock, peer_id, msg_hash, body).await)
+        Ok(network_state.receive_routed_message(clock, peer_id, author, msg_hash, body).await)
     }
 
     fn receive_message(
@@ -978,7 +1302,7 @@ impl PeerActor {
                 sink.send(Event::MessageProcessed(tier, msg));
             }
         };
-        let was_requested = match &msg {
+        let (was_requested, latency) = match &msg {
             PeerMessage::Block(block) => {
                 self.network_state.txns_since_last_block.store(0, Ordering::Release);
                 let hash = *block.hash();
@@ -992,21 +1316,39 @@ impl PeerActor {
                 });
                 let mut tracker = self.tracker.lock();
                 tracker.push_received(hash);
-                tracker.has_request(&hash)
+                let was_requested = tracker.has_request(&hash);
+                // A block broadcast by many peers is passed to the client only once.
+                // The copies which were requested from this peer are passed anyway.
+                if !self.network_state.seen_messages.lock().insert(self.clock.now(), hash, "Block")
+                    && !was_requested
+                {
+                    #[cfg(test)]
+                    message_processed_event();
+                    return;
+                }
+                (was_requested, tracker.request_latency(&hash, self.clock.now()))
             }
-            _ => false,
+            _ => (false, None),
         };
         let clock = self.clock.clone();
         let network_state = self.network_state.clone();
         let peer_id = conn.peer_info.id.clone();
         let handling_future = async move {
+            let record_peer_event =
+                |event| network_state.peer_store.record_peer_event(&clock, &peer_id, event);
             Ok(match msg {
                 PeerMessage::Routed(msg) => {
                     let msg_hash = msg.hash();
+                    if msg.author == peer_id
+                        && matches!(msg.msg.body, RoutedMessageBody::PartialEncodedChunkResponse(_))
+                    {
+                        record_peer_event(PeerEvent::UsefulResponse(None));
+                    }
                     Self::receive_routed_message(
                         &clock,
                         &network_state,
                         peer_id,
+                        msg.author.clone(),
                         msg_hash,
                         msg.msg.body,
                     )
@@ -1033,6 +1375,9 @@ impl PeerActor {
                     .flatten()
                     .map(PeerMessage::BlockHeaders),
                 PeerMessage::Block(block) => {
+                    if was_requested {
+                        record_peer_event(PeerEvent::UsefulResponse(latency));
+                    }
                     network_state
                         .client
                         .send_async(BlockResponse { block, peer_id, was_requested })
@@ -1053,12 +1398,14 @@ impl PeerActor {
                     None
                 }
                 PeerMessage::BlockHeaders(headers) => {
-                    if let Ok(Err(ban_reason)) = network_state
+                    match network_state
                         .client
-                        .send_async(BlockHeadersResponse(headers, peer_id))
+                        .send_async(BlockHeadersResponse(headers, peer_id.clone()))
                         .await
                     {
-                        return Err(ban_reason);
+                        Ok(Err(ban_reason)) => return Err(ban_reason),
+                        Ok(Ok(())) => record_peer_event(PeerEvent::UsefulResponse(None)),
+                        Err(_) => {}
                     }
                     None
                 }
@@ -1081,12 +1428,14 @@ impl PeerActor {
                     .flatten()
                     .map(|response| PeerMessage::VersionedStateResponse(*response.0)),
                 PeerMessage::VersionedStateResponse(info) => {
+                    record_peer_event(PeerEvent::UsefulResponse(None));
                     //TODO: Route to state sync actor.
                     network_state.client.send_async(StateResponse(info.into())).await.ok();
                     None
                 }
                 msg => {
                     tracing::error!(target: "network", "Peer received unexpected type: {:?}", msg);
+                    record_peer_event(PeerEvent::DroppedMessage);
                     None
                 }
             })
@@ -1197,6 +1546,34 @@ impl PeerActor {
                 #[cfg(test)]
                 message_processed_event();
             }
+            PeerMessage::DhtFindNodes(request) => {
+                let clock = self.clock.clone();
+                let network_state = self.network_state.clone();
+                ctx.spawn(wrap_future(async move {
+                    match network_state.dht_find_nodes(&clock, &conn.peer_info.id, request).await {
+                        Ok(Some(response)) => {
+                            conn.send_message(Arc::new(PeerMessage::DhtNodes(response)))
+                        }
+                        Ok(None) => {}
+                        Err(ban_reason) => conn.stop(Some(ban_reason)),
+                    }
+                    #[cfg(test)]
+                    message_processed_event();
+                }));
+            }
+            PeerMessage::DhtNodes(response) => {
+                let clock = self.clock.clone();
+                let network_state = self.network_state.clone();
+                ctx.spawn(wrap_future(async move {
+                    if let Err(ban_reason) =
+                        network_state.dht_nodes(&clock, &conn.peer_info.id, response).await
+                    {
+                        conn.stop(Some(ban_reason));
+                    }
+                    #[cfg(test)]
+                    message_processed_event();
+                }));
+            }
             PeerMessage::RequestUpdateNonce(edge_info) => {
                 let clock = self.clock.clone();
                 let network_state = self.network_state.clone();
@@ -1327,12 +1704,11 @@ impl PeerActor {
                 let for_me = self.network_state.message_for_me(&msg.target);
                 if for_me {
                     // Check if we have already received this message.
-                    let fastest = self
-                        .network_state
-                        .recent_routed_messages
-                        .lock()
-                        .put(CryptoHash::hash_borsh(&msg.body), ())
-                        .is_none();
+                    let fastest = self.network_state.seen_messages.lock().insert(
+                        self.clock.now(),
+                        CryptoHash::hash_borsh(&msg.body),
+                        msg.body_variant(),
+                    );
                     // Register that the message has been received.
                     metrics::record_routed_msg_metrics(&self.clock, &msg, conn.tier, fastest);
                 }
@@ -1389,9 +1765,17 @@ impl PeerActor {
                             #[cfg(test)]
                             message_processed_event();
                         }
-                        RoutedMessageBody::Pong(_pong) => {
+                        RoutedMessageBody::Pong(pong) => {
+                            match conn.tier {
+                                tcp::Tier::T1 => {
+                                    self.network_state.tier1_pong_received(&self.clock, pong)
+                                }
+                                tcp::Tier::T2 => {
+                                    self.network_state.tier2_pong_received(&self.clock, pong)
+                                }
+                            }
                             #[cfg(test)]
-                            self.network_state.config.event_sink.send(Event::Pong(_pong.clone()));
+                            self.network_state.config.event_sink.send(Event::Pong(pong.clone()));
                             #[cfg(test)]
                             message_processed_event();
                         }
@@ -1586,6 +1970,9 @@ impl actix::Handler<stream::Error> for PeerActor {
             }
             // It is expected in a sense that the peer might be just slow.
             stream::Error::Send(stream::SendError::QueueOverflow { .. }) => true,
+            // The frames might have been tampered with on the way, so the peer
+            // isn't necessarily at fault.
+            stream::Error::Recv(stream::RecvError::Decrypt(_)) => true,
             stream::Error::Recv(stream::RecvError::IO(err))
             | stream::Error::Send(stream::SendError::IO(err)) => match err.kind() {
                 // Connection has been closed.
@@ -1634,6 +2021,31 @@ impl actix::Handler<stream::Frame> for PeerActor {
             self.tracker.lock().increment_received(&self.clock, msg.len() as u64);
         }
 
+        let network_state = self.network_state.clone();
+        let size_limits = &network_state.config.message_size_limits;
+        let msg = if self.decompress {
+            match compression::decompress(msg, size_limits.max_limit() as usize) {
+                Ok(msg) => msg,
+                Err(compression::Error::TooLarge { max_bytes }) => {
+                    self.received_oversize(ctx, None, max_bytes + 1);
+                    return;
+                }
+                Err(err) => {
+                    tracing::debug!(target: "network", peer = %self.peer_info, %err, "Received a frame which failed to decompress");
+                    return;
+                }
+            }
+        } else {
+            msg
+        };
+
+        // Reject oversize messages before parsing them, as far as their kind
+        // can be told from the encoding, and once more after.
+        if msg.len() as u64 > size_limits.encoded_limit(&msg) {
+            self.received_oversize(ctx, MessageKind::peek(&msg), msg.len());
+            return;
+        }
+
         let mut peer_msg = match self.parse_message(&msg) {
             Ok(msg) => msg,
             Err(err) => {
@@ -1642,7 +2054,15 @@ impl actix::Handler<stream::Frame> for PeerActor {
             }
         };
 
+        if let Some(kind) = size_limits.oversize(&peer_msg, msg.len()) {
+            self.received_oversize(ctx, Some(kind), msg.len());
+            return;
+        }
+
         tracing::trace!(target: "network", "Received message: {}", peer_msg);
+        if let Some(recorder) = self.network_state.recorder.get() {
+            recorder.record(Direction::Inbound, self.other_peer_id(), &peer_msg);
+        }
 
         let now = self.clock.now();
         {
@@ -1654,6 +2074,13 @@ impl actix::Handler<stream::Frame> for PeerActor {
             if !self.received_messages_rate_limits.is_allowed(&peer_msg, now) {
                 metrics::PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
                 tracing::debug!(target: "network", "Peer {} is being rate limited for message {}", self.peer_info, peer_msg.msg_variant());
+                if let Some(peer_id) = self.other_peer_id() {
+                    self.network_state.peer_store.record_peer_event(
+                        &self.clock,
+                        peer_id,
+                        PeerEvent::DroppedMessage,
+                    );
+                }
                 return;
             }
         }
@@ -1710,7 +2137,7 @@ impl actix::Handler<WithSpanContext<SendMessage>> for PeerActor {
 #[derive(actix::Message, Debug)]
 #[rtype(result = "()")]
 pub(crate) struct Stop {
-    pub ban_reason: Option<ReasonForBan>,
+    pub reason: ClosingReason,
 }
 
 impl actix::Handler<WithSpanContext<Stop>> for PeerActor {
@@ -1719,13 +2146,7 @@ impl actix::Handler<WithSpanContext<Stop>> for PeerActor {
     #[perf]
     fn handle(&mut self, msg: WithSpanContext<Stop>, ctx: &mut Self::Context) -> Self::Result {
         let (_span, msg) = handler_debug_span!(target: "network", msg);
-        self.stop(
-            ctx,
-            match msg.ban_reason {
-                Some(reason) => ClosingReason::Ban(reason),
-                None => ClosingReason::PeerManagerRequest,
-            },
-        );
+        self.stop(ctx, msg.reason);
     }
 }
 
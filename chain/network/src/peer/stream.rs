@@ -1,24 +1,32 @@
+use crate::peer::encryption::{self, RecvCipher, SendCipher};
 use crate::peer_manager::connection;
+use crate::rate_limits::bandwidth::ConnectionLimiters;
+use crate::rate_limits::qos::{QosClass, SendQueue};
 use crate::stats::metrics;
 use crate::tcp;
 use actix::fut::future::wrap_future;
 use actix::AsyncContext as _;
 use bytesize::{GIB, MIB};
+use near_async::time;
+use parking_lot::Mutex;
 use std::io;
 use std::net::SocketAddr;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::io::AsyncReadExt as _;
 use tokio::io::AsyncWriteExt as _;
 
 /// Maximum size of network message in encoded format.
 /// We encode length as `u32`, and therefore maximum size can't be larger than `u32::MAX`.
-const NETWORK_MESSAGE_MAX_SIZE_BYTES: usize = 512 * MIB as usize;
+pub(crate) const NETWORK_MESSAGE_MAX_SIZE_BYTES: usize = 512 * MIB as usize;
 /// Maximum capacity of write buffer in bytes.
 const MAX_WRITE_BUFFER_CAPACITY_BYTES: usize = GIB as usize;
 
-type ReadHalf = tokio::io::ReadHalf<tokio::net::TcpStream>;
-type WriteHalf = tokio::io::WriteHalf<tokio::net::TcpStream>;
+type ReadHalf = tokio::io::ReadHalf<tcp::Io>;
+type WriteHalf = tokio::io::WriteHalf<tcp::Io>;
+
+/// Length of the authentication tag appended to the encrypted frames.
+const TAG_LEN: usize = 16;
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum SendError {
@@ -34,12 +42,21 @@ pub(crate) enum RecvError {
     IO(#[source] io::Error),
     #[error("message too large: got {got_bytes}B, want <={want_max_bytes}B")]
     MessageTooLarge { got_bytes: usize, want_max_bytes: usize },
+    #[error("decryption: {0}")]
+    Decrypt(#[source] encryption::Error),
 }
 
 #[derive(actix::Message, PartialEq, Eq, Clone, Debug)]
 #[rtype(result = "()")]
 pub(crate) struct Frame(pub Vec<u8>);
 
+/// Entry of the send queue.
+enum Queued {
+    Frame(QosClass, Frame),
+    /// The frames queued after this one are encrypted.
+    StartEncryption(SendCipher),
+}
+
 /// Stream critical error.
 /// Actor is responsible for calling ctx.stop() after receiving stream::Error.
 /// Actor might receive more than 1 stream::Error, but should call ctx.stop() just after the
@@ -57,7 +74,11 @@ pub(crate) enum Error {
 }
 
 pub(crate) struct FramedStream<Actor: actix::Actor> {
-    queue_send: tokio::sync::mpsc::UnboundedSender<Frame>,
+    queue_send: tokio::sync::mpsc::UnboundedSender<Queued>,
+    /// Shared with the recv loop, which decrypts the frames with it once set.
+    recv_cipher: Arc<Mutex<Option<RecvCipher>>>,
+    /// Shared with the recv loop, which fails on larger frames.
+    max_recv_bytes: Arc<AtomicUsize>,
     stats: Arc<connection::Stats>,
     send_buf_size_metric: Arc<metrics::IntGaugeGuard>,
     addr: actix::Addr<Actor>,
@@ -73,9 +94,14 @@ where
         ctx: &mut actix::Context<Actor>,
         stream: tcp::Stream,
         stats: Arc<connection::Stats>,
+        limiters: ConnectionLimiters,
+        max_recv_bytes: usize,
     ) -> Self {
         let (tcp_recv, tcp_send) = tokio::io::split(stream.stream);
+        let limiters = Arc::new(limiters);
         let (queue_send, queue_recv) = tokio::sync::mpsc::unbounded_channel();
+        let recv_cipher = Arc::new(Mutex::new(None));
+        let max_recv_bytes = Arc::new(AtomicUsize::new(max_recv_bytes));
         let send_buf_size_metric = Arc::new(metrics::MetricGuard::new(
             &*metrics::PEER_DATA_WRITE_BUFFER_SIZE,
             vec![stream.peer_addr.to_string()],
@@ -84,8 +110,11 @@ where
             let addr = ctx.address();
             let stats = stats.clone();
             let m = send_buf_size_metric.clone();
+            let limiters = limiters.clone();
             async move {
-                if let Err(err) = Self::run_send_loop(tcp_send, queue_recv, stats, m).await {
+                if let Err(err) =
+                    Self::run_send_loop(tcp_send, queue_recv, stats, m, limiters).await
+                {
                     addr.do_send(Error::Send(SendError::IO(err)));
                 }
             }
@@ -93,22 +122,59 @@ where
         ctx.spawn(wrap_future({
             let addr = ctx.address();
             let stats = stats.clone();
+            let recv_cipher = recv_cipher.clone();
+            let max_recv_bytes = max_recv_bytes.clone();
             async move {
-                if let Err(err) =
-                    Self::run_recv_loop(stream.peer_addr, tcp_recv, addr.clone(), stats).await
+                if let Err(err) = Self::run_recv_loop(
+                    stream.peer_addr,
+                    tcp_recv,
+                    addr.clone(),
+                    stats,
+                    limiters,
+                    recv_cipher,
+                    max_recv_bytes,
+                )
+                .await
                 {
                     addr.do_send(Error::Recv(err));
                 }
             }
         }));
-        Self { queue_send, stats, send_buf_size_metric, addr: ctx.address() }
+        Self {
+            queue_send,
+            recv_cipher,
+            max_recv_bytes,
+            stats,
+            send_buf_size_metric,
+            addr: ctx.address(),
+        }
     }
 
-    /// Pushes `msg` to the send queue.
+    /// Encrypts the frames passed to `send` from now on.
+    pub fn start_encryption(&self, cipher: SendCipher) {
+        let _ = self.queue_send.send(Queued::StartEncryption(cipher));
+    }
+
+    /// Decrypts the frames received after the one currently handled by the
+    /// Actor. The recv loop doesn't read the next frame until the Actor has
+    /// handled the current one, so this has to be called from within the
+    /// handler of the last frame which the peer sends unencrypted.
+    pub fn start_decryption(&self, cipher: RecvCipher) {
+        *self.recv_cipher.lock() = Some(cipher);
+    }
+
+    /// Limits the size of the frames received after the one currently handled
+    /// by the Actor, like `start_decryption`. Larger frames close the stream.
+    pub fn set_max_recv_bytes(&self, max_recv_bytes: usize) {
+        self.max_recv_bytes.store(max_recv_bytes, Ordering::Relaxed);
+    }
+
+    /// Pushes `msg` to the send queue, to be sent in the order of its `class`,
+    /// see [`SendQueue`].
     /// Silently drops message if the connection has been closed.
     /// If the message is too large, it will be silently dropped inside run_send_loop.
     /// Emits a critical error to Actor if send queue is full.
-    pub fn send(&self, frame: Frame) {
+    pub fn send(&self, class: QosClass, frame: Frame) {
         let msg = &frame.0;
         let mut buf_size =
             self.stats.bytes_to_send.fetch_add(msg.len() as u64, Ordering::Acquire) as usize;
@@ -125,7 +191,7 @@ where
                 want_max_bytes: MAX_WRITE_BUFFER_CAPACITY_BYTES,
             }));
         }
-        let _ = self.queue_send.send(frame);
+        let _ = self.queue_send.send(Queued::Frame(class, frame));
     }
 
     /// Event loop receiving and processing messages.
@@ -134,6 +200,9 @@ where
     /// then the loop will start reading the next message before the subhandler returns.
     /// Loop uses a fixed small buffer allocated by BufReader.
     /// For each message it allocates a Vec with exact size of the message.
+    /// Loop waits before reading the next message while over the download caps.
+    /// Once `recv_cipher` is set, the messages are decrypted with it.
+    /// Messages over `max_recv_bytes` fail the loop before they are read.
     // TODO(gprusak): once borsh support is dropped, we can parse a proto
     // directly from the stream.
     async fn run_recv_loop(
@@ -141,6 +210,9 @@ where
         read: ReadHalf,
         addr: actix::Addr<Actor>,
         stats: Arc<connection::Stats>,
+        limiters: Arc<ConnectionLimiters>,
+        recv_cipher: Arc<Mutex<Option<RecvCipher>>>,
+        max_recv_bytes: Arc<AtomicUsize>,
     ) -> Result<(), RecvError> {
         const READ_BUFFER_CAPACITY: usize = 8 * 1024;
         let mut read = tokio::io::BufReader::with_capacity(READ_BUFFER_CAPACITY, read);
@@ -153,11 +225,11 @@ where
         );
         loop {
             let n = read.read_u32_le().await.map_err(RecvError::IO)? as usize;
-            if n > NETWORK_MESSAGE_MAX_SIZE_BYTES {
-                return Err(RecvError::MessageTooLarge {
-                    got_bytes: n,
-                    want_max_bytes: NETWORK_MESSAGE_MAX_SIZE_BYTES,
-                });
+            let max_size =
+                max_recv_bytes.load(Ordering::Relaxed).min(NETWORK_MESSAGE_MAX_SIZE_BYTES);
+            let max_size = if recv_cipher.lock().is_some() { max_size + TAG_LEN } else { max_size };
+            if n > max_size {
+                return Err(RecvError::MessageTooLarge { got_bytes: n, want_max_bytes: max_size });
             }
             msg_size_metric.observe(n as f64);
             buf_size_metric.set(n as i64);
@@ -168,48 +240,130 @@ where
             buf_size_metric.set(0);
             stats.received_messages.fetch_add(1, Ordering::Relaxed);
             stats.received_bytes.fetch_add(n as u64, Ordering::Relaxed);
+            if let Some(cipher) = recv_cipher.lock().as_mut() {
+                cipher.open(&mut buf).map_err(RecvError::Decrypt)?;
+            }
             if let Err(_) = addr.send(Frame(buf)).await {
                 // We got mailbox error, which means that Actor has stopped,
                 // so we should just close the stream.
                 return Ok(());
             }
+            limiters.download(n).await;
         }
     }
+
+    /// Event loop sending the queued messages in the order of [`SendQueue`].
+    /// Sync and background messages wait for the upload caps.
+    /// Writes go through a BufWriter, which is flushed whenever the loop
+    /// runs out of messages to send or waits for the caps.
     async fn run_send_loop(
         tcp_send: WriteHalf,
-        mut queue_recv: tokio::sync::mpsc::UnboundedReceiver<Frame>,
+        mut queue_recv: tokio::sync::mpsc::UnboundedReceiver<Queued>,
         stats: Arc<connection::Stats>,
         buf_size_metric: Arc<metrics::IntGaugeGuard>,
+        limiters: Arc<ConnectionLimiters>,
     ) -> io::Result<()> {
         const WRITE_BUFFER_CAPACITY: usize = 8 * 1024;
-        let mut writer = tokio::io::BufWriter::with_capacity(WRITE_BUFFER_CAPACITY, tcp_send);
-        while let Some(Frame(mut msg)) = queue_recv.recv().await {
-            // Try writing a batch of messages and flush once at the end.
-            loop {
-                // TODO(gprusak): sending a too large message should probably be treated as a bug,
-                // since dropping messages may lead to hard-to-debug high-level issues.
-                if msg.len() > NETWORK_MESSAGE_MAX_SIZE_BYTES {
-                    metrics::MessageDropped::InputTooLong.inc_unknown_msg();
-                } else {
-                    writer.write_u32_le(msg.len() as u32).await?;
-                    writer.write_all(&msg[..]).await?;
+        let mut writer = Writer {
+            writer: tokio::io::BufWriter::with_capacity(WRITE_BUFFER_CAPACITY, tcp_send),
+            cipher: None,
+            stats,
+            buf_size_metric,
+            limiters: limiters.clone(),
+        };
+        let mut queue = SendQueue::default();
+        // Whether the actor dropped its end of the queue. The messages queued
+        // before that are still sent.
+        let mut closed = false;
+        loop {
+            while let Ok(item) = queue_recv.try_recv() {
+                writer.push(&mut queue, item).await?;
+            }
+            if queue.is_empty() {
+                writer.writer.flush().await?;
+                if closed {
+                    return Ok(());
                 }
-                stats.messages_to_send.fetch_sub(1, Ordering::Release);
-                stats.bytes_to_send.fetch_sub(msg.len() as u64, Ordering::Release);
-                buf_size_metric.sub(msg.len() as i64);
-                msg = match queue_recv.try_recv() {
-                    Ok(Frame(it)) => it,
-                    Err(_) => break,
-                };
+                match queue_recv.recv().await {
+                    Some(item) => writer.push(&mut queue, item).await?,
+                    None => closed = true,
+                }
+                continue;
+            }
+            let delay = limiters.upload_delay();
+            let Some(Frame(msg)) = queue.pop(delay > time::Duration::ZERO) else {
+                writer.writer.flush().await?;
+                // Both futures are cancellation safe. A new message ends the
+                // wait, since it might not have to wait for the caps.
+                tokio::select! {
+                    _ = limiters.sleep(delay) => {}
+                    item = queue_recv.recv(), if !closed => match item {
+                        Some(item) => writer.push(&mut queue, item).await?,
+                        None => closed = true,
+                    },
+                }
+                continue;
+            };
+            writer.write(msg).await?;
+        }
+    }
+}
+
+/// Write side of the connection.
+struct Writer {
+    writer: tokio::io::BufWriter<WriteHalf>,
+    cipher: Option<SendCipher>,
+    stats: Arc<connection::Stats>,
+    buf_size_metric: Arc<metrics::IntGaugeGuard>,
+    limiters: Arc<ConnectionLimiters>,
+}
+
+impl Writer {
+    async fn push(&mut self, queue: &mut SendQueue<Frame>, item: Queued) -> io::Result<()> {
+        match item {
+            Queued::Frame(class, frame) => {
+                let len = frame.0.len();
+                for (_, len) in queue.push(class, frame, len) {
+                    metrics::PEER_MESSAGES_DROPPED_BY_QOS
+                        .with_label_values(&[class.as_ref()])
+                        .inc();
+                    self.dequeued(len);
+                }
+            }
+            // The frames queued before have to go out unencrypted, and the
+            // peer is expecting them right away anyway.
+            Queued::StartEncryption(cipher) => {
+                while let Some(Frame(msg)) = queue.pop(false) {
+                    self.write(msg).await?;
+                }
+                self.cipher = Some(cipher);
             }
-            // This is an unconditional flush, which means that even if new messages
-            // will be added to the queue in the meantime, we will wait for the buffer
-            // to be flushed before sending them. This is suboptimal in case messages are small
-            // and added to the queue at a rate similar to flush latency. To fix that
-            // we would need to put writer.flush() and queue_recv.recv() into a tokio::select
-            // and make sure that both are cancellation-safe.
-            writer.flush().await?;
         }
         Ok(())
     }
+
+    async fn write(&mut self, mut msg: Vec<u8>) -> io::Result<()> {
+        let len = msg.len();
+        self.limiters.consume_upload(len);
+        // TODO(gprusak): sending a too large message should probably be treated as a bug,
+        // since dropping messages may lead to hard-to-debug high-level issues.
+        if len > NETWORK_MESSAGE_MAX_SIZE_BYTES {
+            metrics::MessageDropped::InputTooLong.inc_unknown_msg();
+        } else {
+            if let Some(cipher) = &mut self.cipher {
+                cipher.seal(&mut msg).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            }
+            self.writer.write_u32_le(msg.len() as u32).await?;
+            self.writer.write_all(&msg[..]).await?;
+        }
+        self.dequeued(len);
+        Ok(())
+    }
+
+    /// Updates the stats once a message of `len` bytes leaves the queue.
+    fn dequeued(&self, len: usize) {
+        self.stats.messages_to_send.fetch_sub(1, Ordering::Release);
+        self.stats.bytes_to_send.fetch_sub(len as u64, Ordering::Release);
+        self.buf_size_metric.sub(len as i64);
+    }
 }
@@ -179,6 +179,71 @@ async fn peer_communication() -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn test_compressed_communication(
+    outbound_compression: bool,
+    inbound_compression: bool,
+) -> anyhow::Result<()> {
+    tracing::info!("test_compressed_communication({outbound_compression},{inbound_compression})");
+
+    let mut rng = make_rng(1938470182);
+    let mut clock = time::FakeClock::default();
+
+    let chain = Arc::new(data::Chain::make(&mut clock, &mut rng, 12));
+    let mut make_cfg = |enabled| {
+        let mut network = chain.make_config(&mut rng);
+        // Compress every message which can be compressed.
+        network.message_compression.enabled = enabled;
+        network.message_compression.threshold_bytes = 0;
+        PeerConfig { chain: chain.clone(), network, force_encoding: None }
+    };
+    let inbound_cfg = make_cfg(inbound_compression);
+    let outbound_cfg = make_cfg(outbound_compression);
+    let (outbound_stream, inbound_stream) =
+        tcp::Stream::loopback(inbound_cfg.id(), tcp::Tier::T2).await;
+    let mut inbound = PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await;
+    let mut outbound =
+        PeerHandle::start_endpoint(clock.clock(), outbound_cfg, outbound_stream).await;
+
+    outbound.complete_handshake().await;
+    inbound.complete_handshake().await;
+
+    let message_processed = |want| {
+        move |ev| match ev {
+            Event::Network(PME::MessageProcessed(_, got)) if got == want => Some(()),
+            _ => None,
+        }
+    };
+    for (from, to) in [(&outbound, &inbound), (&inbound, &outbound)] {
+        tracing::info!(target:"test","Block");
+        let mut events = to.events.from_now();
+        let want = PeerMessage::Block(chain.blocks[5].clone());
+        from.send(want.clone()).await;
+        events.recv_until(message_processed(want)).await;
+
+        tracing::info!(target:"test","PeersRequest");
+        let mut events = to.events.from_now();
+        let want =
+            PeerMessage::PeersRequest(PeersRequest { max_peers: None, max_direct_peers: None });
+        from.send(want.clone()).await;
+        events.recv_until(message_processed(want)).await;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+// Verifies that peers understand each other whether or not they compress messages.
+async fn compressed_communication() -> anyhow::Result<()> {
+    init_test_logger();
+    for outbound in [false, true] {
+        for inbound in [false, true] {
+            test_compressed_communication(outbound, inbound)
+                .await
+                .with_context(|| format!("(outbound={outbound},inbound={inbound})"))?;
+        }
+    }
+    Ok(())
+}
+
 async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: Option<Encoding>) {
     let mut rng = make_rng(89028037453);
     let mut clock = time::FakeClock::default();
@@ -207,10 +272,15 @@ async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: O
         sender_peer_id: outbound_cfg.id(),
         target_peer_id: inbound.cfg.id(),
         sender_listen_port: Some(outbound_port),
+        sender_listen_addrs: vec![],
         sender_chain_info: outbound_cfg.chain.get_peer_chain_info(),
         partial_edge_info: outbound_cfg
             .partial_edge_info(&inbound.cfg.id(), Edge::create_fresh_nonce(&clock.clock())),
         owned_account: None,
+        sender_rpc_url: None,
+        encryption_offer: None,
+        supported_compressions: vec![],
+        admission_proof: None,
     };
     // We will also introduce chain_id mismatch, but ProtocolVersionMismatch is expected to take priority.
     handshake.sender_chain_info.genesis_id.chain_id = "unknown_chain".to_string();
@@ -1,10 +1,13 @@
 use crate::actix::ActixSystem;
 use crate::network_protocol::testonly as data;
 use crate::peer::stream;
+use crate::rate_limits::bandwidth::ConnectionLimiters;
+use crate::rate_limits::qos::QosClass;
 use crate::tcp;
 use crate::testonly::make_rng;
 use actix::Actor as _;
 use actix::ActorContext as _;
+use near_async::time;
 use rand::Rng as _;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -25,7 +28,7 @@ struct SendFrame(stream::Frame);
 impl actix::Handler<SendFrame> for Actor {
     type Result = ();
     fn handle(&mut self, SendFrame(frame): SendFrame, _ctx: &mut Self::Context) {
-        self.stream.send(frame);
+        self.stream.send(QosClass::Background, frame);
     }
 }
 
@@ -55,7 +58,14 @@ impl Actor {
             queue_recv,
             system: ActixSystem::spawn(|| {
                 Actor::create(|ctx| {
-                    let stream = stream::FramedStream::spawn(ctx, s, Arc::default());
+                    let limiters = ConnectionLimiters::unlimited(&time::Clock::real());
+                    let stream = stream::FramedStream::spawn(
+                        ctx,
+                        s,
+                        Arc::default(),
+                        limiters,
+                        stream::NETWORK_MESSAGE_MAX_SIZE_BYTES,
+                    );
                     Self { stream, queue_send }
                 })
             })
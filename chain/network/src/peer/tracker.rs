@@ -1,6 +1,8 @@
 use crate::peer::transfer_stats::TransferStats;
+use ::time::ext::InstantExt as _;
 use near_async::time;
 use near_primitives::hash::CryptoHash;
+use std::num::NonZeroUsize;
 
 /// Maximum number of requests and responses to track.
 const MAX_TRACK_SIZE: usize = 30;
@@ -47,6 +49,8 @@ pub(crate) struct Tracker {
     pub(crate) received_bytes: TransferStats,
     /// Sent requests.
     requested: CircularUniqueQueue,
+    /// When the tracked requests were sent.
+    request_times: lru::LruCache<CryptoHash, time::Instant>,
     /// Received elements.
     received: CircularUniqueQueue,
 }
@@ -57,6 +61,7 @@ impl Default for Tracker {
             sent_bytes: TransferStats::default(),
             received_bytes: TransferStats::default(),
             requested: CircularUniqueQueue::new(MAX_TRACK_SIZE),
+            request_times: lru::LruCache::new(NonZeroUsize::new(MAX_TRACK_SIZE).unwrap()),
             received: CircularUniqueQueue::new(MAX_TRACK_SIZE),
         }
     }
@@ -86,8 +91,18 @@ impl Tracker {
         self.requested.contains(hash)
     }
 
-    pub(crate) fn push_request(&mut self, hash: CryptoHash) {
+    pub(crate) fn push_request(&mut self, hash: CryptoHash, now: time::Instant) {
         self.requested.push(hash);
+        self.request_times.push(hash, now);
+    }
+
+    /// Time since the request for `hash` was sent, if it is tracked.
+    pub(crate) fn request_latency(
+        &self,
+        hash: &CryptoHash,
+        now: time::Instant,
+    ) -> Option<time::Duration> {
+        self.request_times.peek(hash).map(|sent| now.signed_duration_since(*sent))
     }
 }
 
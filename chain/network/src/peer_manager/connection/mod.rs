@@ -6,7 +6,7 @@ use crate::network_protocol::{
     SnapshotHostInfo, SyncAccountsData, SyncSnapshotHosts,
 };
 use crate::peer::peer_actor;
-use crate::peer::peer_actor::PeerActor;
+use crate::peer::peer_actor::{ClosingReason, PeerActor};
 use crate::private_actix::SendMessage;
 use crate::stats::metrics;
 use crate::tcp;
@@ -21,6 +21,7 @@ use near_primitives::types::ShardId;
 use std::collections::{hash_map::Entry, HashMap};
 use std::fmt;
 use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, Weak};
 
@@ -89,10 +90,14 @@ pub(crate) struct Connection {
     pub tracked_shards: Vec<ShardId>,
     /// Denote if a node is running in archival mode or not.
     pub archival: bool,
+    /// Public URL of the peer's JSON-RPC server, if it advertises one.
+    pub rpc_url: Option<String>,
     pub last_block: ArcSwap<Option<BlockInfo>>,
 
     /// Who started connection. Inbound (other) or Outbound (us).
     pub peer_type: PeerType,
+    /// Local address of the TCP connection.
+    pub local_addr: SocketAddr,
     /// Time where the connection was established.
     pub established_time: time::Instant,
 
@@ -128,12 +133,20 @@ impl Connection {
             last_block: *self.last_block.load().as_ref(),
             tracked_shards: self.tracked_shards.clone(),
             archival: self.archival,
+            rpc_url: self.rpc_url.clone(),
         };
         FullPeerInfo { peer_info: self.peer_info.clone(), chain_info }
     }
 
     pub fn stop(&self, ban_reason: Option<ReasonForBan>) {
-        self.addr.do_send(peer_actor::Stop { ban_reason }.with_span_context());
+        self.close(match ban_reason {
+            Some(reason) => ClosingReason::Ban(reason),
+            None => ClosingReason::PeerManagerRequest,
+        });
+    }
+
+    pub fn close(&self, reason: ClosingReason) {
+        self.addr.do_send(peer_actor::Stop { reason }.with_span_context());
     }
 
     // TODO(gprusak): embed Stream directly in Connection,
@@ -0,0 +1,52 @@
+use crate::access_list::{AccessList, Rule, Rules};
+use crate::debug::{PeerAccessAction, PeerAccessControl};
+use near_primitives::views::PeerAccessListView;
+
+impl super::NetworkState {
+    /// Re-reads the access list file, and if its rules have changed, applies
+    /// them and disconnects the peers which are no longer allowed.
+    pub fn reload_access_list(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.config.access_list_file else { return Ok(()) };
+        let rules = Rules::load(path)?;
+        if !self.access_list.lock().set_rules(rules) {
+            return Ok(());
+        }
+        tracing::info!(target: "network", ?path, "Applied the new peer access list");
+        self.disconnect_disallowed_peers(&self.access_list.lock());
+        Ok(())
+    }
+
+    pub fn access_list_view(&self) -> PeerAccessListView {
+        self.access_list.lock().view()
+    }
+
+    pub fn peer_access_control(&self, request: PeerAccessControl) -> PeerAccessListView {
+        tracing::info!(target: "network", action = ?request.action, rule = %request.rule, "Peer access control");
+        let mut access_list = self.access_list.lock();
+        match request.action {
+            PeerAccessAction::Ban => {
+                access_list.ban(request.rule);
+                self.disconnect_disallowed_peers(&access_list);
+            }
+            PeerAccessAction::Unban => {
+                access_list.unban(&request.rule);
+                if let Rule::Peer(peer_id) = &request.rule {
+                    self.peer_store.peer_unban(peer_id);
+                }
+            }
+        }
+        access_list.view()
+    }
+
+    fn disconnect_disallowed_peers(&self, access_list: &AccessList) {
+        for pool in [&self.tier1, &self.tier2] {
+            for conn in pool.load().ready.values() {
+                let peer_info = &conn.peer_info;
+                if !access_list.is_allowed(&peer_info.id, peer_info.addr.map(|addr| addr.ip())) {
+                    tracing::info!(target: "network", peer_id = %peer_info.id, "Disconnecting peer not allowed by the access list");
+                    conn.stop(None);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::concurrency;
+use crate::dht;
+use crate::network_protocol::{DhtFindNodes, DhtNodes, PeerInfo, PeerMessage, SignedPeerRecord};
+use crate::stats::metrics;
+use crate::types::ReasonForBan;
+use near_async::time;
+use near_primitives::network::PeerId;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+impl super::NetworkState {
+    /// Address at which other nodes can connect to this node, if known.
+    fn dht_own_addr(&self) -> Option<SocketAddr> {
+        if let Some(addr) = self.reachability.external_addr() {
+            return Some(addr);
+        }
+        let addr = *self.config.node_addr.as_deref()?;
+        (!addr.ip().is_unspecified()).then_some(addr)
+    }
+
+    fn dht_own_record(&self, clock: &time::Clock, dht: &mut dht::Dht) -> Option<SignedPeerRecord> {
+        let addr = self.dht_own_addr()?;
+        Some(dht.own_record(clock.now_utc(), addr, &self.config.node_key))
+    }
+
+    /// Adds the nodes saved in the DB to the peer store, so that they can be
+    /// connected to even if the boot nodes are down.
+    pub fn dht_add_saved_peers(&self, clock: &time::Clock) {
+        let peers = self.dht.lock().peers();
+        tracing::debug!(target: "network", count = peers.len(), "Adding peers saved by the DHT");
+        self.peer_store.add_indirect_peers(clock, peers.into_iter());
+    }
+
+    /// Starts a lookup: asks the connected peers closest to the next key for
+    /// the nodes closest to it.
+    pub fn dht_lookup(&self, clock: &time::Clock) {
+        let tier2 = self.tier2.load();
+        let mut dht = self.dht.lock();
+        dht.expire(clock.now_utc());
+        metrics::DHT_ROUTING_TABLE_SIZE.set(dht.len() as i64);
+        let target = dht.start_lookup(&self.config.node_id());
+        let sender_record = self.dht_own_record(clock, &mut dht);
+        let mut peers: Vec<_> = tier2.ready.values().collect();
+        peers.sort_by_cached_key(|conn| dht::distance(&target, &dht::key(&conn.peer_info.id)));
+        let msg = Arc::new(PeerMessage::DhtFindNodes(DhtFindNodes { target, sender_record }));
+        for conn in peers.into_iter().take(dht::LOOKUP_PARALLELISM) {
+            dht.expect_response(conn.peer_info.id.clone());
+            conn.send_message(msg.clone());
+        }
+    }
+
+    /// Handles DhtFindNodes from `peer_id`. Returns None if the DHT is disabled.
+    pub async fn dht_find_nodes(
+        &self,
+        clock: &time::Clock,
+        peer_id: &PeerId,
+        request: DhtFindNodes,
+    ) -> Result<Option<DhtNodes>, ReasonForBan> {
+        if !self.config.dht.enabled {
+            return Ok(None);
+        }
+        if let Some(record) = request.sender_record {
+            if &record.peer_addr.peer_id != peer_id {
+                return Err(ReasonForBan::Abusive);
+            }
+            self.dht_add_records(clock, vec![record]).await?;
+        }
+        let mut dht = self.dht.lock();
+        let mut records = dht.closest(&request.target, dht::BUCKET_SIZE);
+        records.extend(self.dht_own_record(clock, &mut dht));
+        Ok(Some(DhtNodes { records }))
+    }
+
+    /// Handles DhtNodes from `peer_id`. Only responses to the requests of the
+    /// last lookup are taken into account.
+    pub async fn dht_nodes(
+        &self,
+        clock: &time::Clock,
+        peer_id: &PeerId,
+        response: DhtNodes,
+    ) -> Result<(), ReasonForBan> {
+        if !self.dht.lock().take_response(peer_id) {
+            tracing::debug!(target: "network", %peer_id, "Ignoring unexpected DhtNodes");
+            return Ok(());
+        }
+        if response.records.len() > dht::MAX_RECORDS_PER_RESPONSE {
+            return Err(ReasonForBan::Abusive);
+        }
+        self.dht_add_records(clock, response.records).await
+    }
+
+    /// Verifies the records and adds the new ones to the routing table and
+    /// to the peer store.
+    async fn dht_add_records(
+        &self,
+        clock: &time::Clock,
+        records: Vec<SignedPeerRecord>,
+    ) -> Result<(), ReasonForBan> {
+        let records =
+            concurrency::rayon::run(move || records.iter().all(|r| r.verify()).then_some(records))
+                .await
+                .ok_or(ReasonForBan::InvalidSignature)?;
+        let received = records.len();
+        let node_id = self.config.node_id();
+        let records: Vec<_> = {
+            let access_list = self.access_list.lock();
+            records
+                .into_iter()
+                .filter(|r| {
+                    let peer_addr = &r.peer_addr;
+                    peer_addr.peer_id != node_id
+                        && access_list.is_allowed(&peer_addr.peer_id, Some(peer_addr.addr.ip()))
+                })
+                .collect()
+        };
+        let added = self.dht.lock().insert(clock.now_utc(), records);
+        metrics::DHT_RECORDS_RECEIVED.with_label_values(&["true"]).inc_by(added.len() as u64);
+        metrics::DHT_RECORDS_RECEIVED
+            .with_label_values(&["false"])
+            .inc_by((received - added.len()) as u64);
+        self.peer_store.add_indirect_peers(
+            clock,
+            added.into_iter().map(|r| PeerInfo::new(r.peer_addr.peer_id, r.peer_addr.addr)),
+        );
+        Ok(())
+    }
+}
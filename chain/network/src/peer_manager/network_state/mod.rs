@@ -1,3 +1,4 @@
+use crate::access_list::AccessList;
 use crate::accounts_data::{AccountDataCache, AccountDataError};
 use crate::announce_accounts::AnnounceAccountCache;
 use crate::client::{
@@ -7,6 +8,7 @@ use crate::client::{
 use crate::concurrency::demux;
 use crate::concurrency::runtime::Runtime;
 use crate::config;
+use crate::dht::Dht;
 use crate::network_protocol::{
     Edge, EdgeState, PartialEdgeInfo, PeerIdOrHash, PeerInfo, PeerMessage, RawRoutedMessage,
     RoutedMessageBody, RoutedMessageV2, SignedAccountData, SnapshotHostInfo,
@@ -17,8 +19,12 @@ use crate::peer_manager::connection;
 use crate::peer_manager::connection_store;
 use crate::peer_manager::peer_store;
 use crate::private_actix::RegisterPeerError;
+use crate::rate_limits::{admission, bandwidth};
+use crate::reachability::Reachability;
+use crate::recorder::Recorder;
 use crate::routing::route_back_cache::RouteBackCache;
 use crate::routing::NetworkTopologyChange;
+use crate::seen_messages::SeenMessages;
 use crate::shards_manager::ShardsManagerRequestFromNetwork;
 use crate::snapshot_hosts::{SnapshotHostInfoError, SnapshotHostsCache};
 use crate::state_witness::{
@@ -39,23 +45,22 @@ use near_primitives::network::PeerId;
 use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
 use near_primitives::types::AccountId;
 use parking_lot::Mutex;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroUsize;
 use std::sync::atomic::AtomicUsize;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tracing::Instrument as _;
 
+mod access_list;
+mod dht;
 mod routing;
 mod tier1;
+mod tier1_stats;
+mod tier2_rtts;
 
 /// Limit number of pending Peer actors to avoid OOM.
 pub(crate) const LIMIT_PENDING_PEERS: usize = 60;
 
-/// Size of LRU cache size of recent routed messages.
-/// It should be large enough to detect duplicates (i.e. all messages received during
-/// production of 1 block should fit).
-const RECENT_ROUTED_MESSAGES_CACHE_SIZE: usize = 10000;
-
 /// How long a peer has to be unreachable, until we prune it from the in-memory graph.
 const PRUNE_UNREACHABLE_PEERS_AFTER: time::Duration = time::Duration::hours(1);
 
@@ -65,6 +70,13 @@ pub const PRUNE_EDGES_AFTER: time::Duration = time::Duration::minutes(30);
 /// How long to wait between reconnection attempts to the same peer
 pub(crate) const RECONNECT_ATTEMPT_INTERVAL: time::Duration = time::Duration::seconds(10);
 
+/// Minimal interval between attempts to open a direct connection to the same
+/// chunk producer for the purpose of forwarding transactions.
+const DIRECT_TX_FORWARDING_CONNECT_INTERVAL: time::Duration = time::Duration::seconds(30);
+
+/// Number of chunk producers for which the last direct connection attempt is remembered.
+const DIRECT_TX_FORWARDING_ATTEMPTS_CACHE_SIZE: usize = 1000;
+
 impl WhitelistNode {
     pub fn from_peer_info(pi: &PeerInfo) -> anyhow::Result<Self> {
         Ok(Self {
@@ -125,15 +137,19 @@ pub(crate) struct NetworkState {
     pub connection_store: connection_store::ConnectionStore,
     /// List of peers to which we should re-establish a connection
     pub pending_reconnect: Mutex<Vec<PeerInfo>>,
+    /// Notified when `pending_reconnect` should be polled without waiting
+    /// for the next poll.
+    pub reconnect_now: tokio::sync::Notify,
     /// A graph of the whole NEAR network.
     pub graph: Arc<crate::routing::Graph>,
     /// A sparsified graph of the whole NEAR network.
     /// TODO(saketh): deprecate graph above, rename this to RoutingTable
     pub graph_v2: Arc<crate::routing::GraphV2>,
 
-    /// Hashes of the body of recently received routed messages.
-    /// It allows us to determine whether messages arrived faster over TIER1 or TIER2 network.
-    pub recent_routed_messages: Mutex<lru::LruCache<CryptoHash, ()>>,
+    /// Hashes of recently received blocks and bodies of routed messages.
+    /// Duplicate blocks aren't passed to the client, and for routed messages
+    /// it tells whether they arrived faster over TIER1 or TIER2 network.
+    pub seen_messages: Mutex<SeenMessages>,
 
     /// Hash of messages that requires routing back to respective previous hop.
     pub tier2_route_back: Mutex<RouteBackCache>,
@@ -142,14 +158,36 @@ pub(crate) struct NetworkState {
     /// so routing shouldn't really be needed.
     /// TODO(gprusak): consider removing it altogether.
     pub tier1_route_back: Mutex<RouteBackCache>,
+    /// Round trip times and connection failures of TIER1, for debugging.
+    tier1_stats: Mutex<tier1_stats::Tier1Stats>,
+    /// Round trip times of TIER2, which the routing takes into account.
+    tier2_rtts: Mutex<tier2_rtts::Tier2Rtts>,
 
     /// Shared counter across all PeerActors, which counts number of `RoutedMessageBody::ForwardTx`
     /// messages sincce last block.
     pub txns_since_last_block: AtomicUsize,
 
+    /// Caps on the bandwidth of all connections together.
+    pub bandwidth_limiters: bandwidth::GlobalLimiters,
+    /// Rate of inbound handshakes and inbound connections per IP.
+    pub admission: admission::Admission,
+
+    /// Whether the node can be reached from the internet.
+    pub reachability: Reachability,
+
+    /// Time of the last attempt to open a direct connection to a chunk producer,
+    /// in order to forward transactions to it. See `NetworkConfig::direct_tx_forwarding`.
+    direct_tx_forwarding_attempts: Mutex<lru::LruCache<PeerId, time::Instant>>,
+
     /// Whitelisted nodes, which are allowed to connect even if the connection limit has been
     /// reached.
     whitelist_nodes: Vec<WhitelistNode>,
+    /// Peers refused or exclusively allowed, see `crate::access_list`.
+    access_list: Mutex<AccessList>,
+    /// Recorder of the traffic, if enabled, see `crate::recorder`.
+    pub recorder: OnceLock<Recorder>,
+    /// Routing table of the DHT, see `crate::dht`.
+    dht: Mutex<Dht>,
 
     /// Mutex which prevents overlapping calls to tier1_advertise_proxies.
     tier1_advertise_proxies_mutex: tokio::sync::Mutex<()>,
@@ -198,16 +236,26 @@ impl NetworkState {
             peer_store,
             snapshot_hosts: Arc::new(SnapshotHostsCache::new(config.snapshot_hosts.clone())),
             connection_store: connection_store::ConnectionStore::new(store.clone()).unwrap(),
+            dht: Mutex::new(Dht::new(store.clone(), &config.node_id())),
             pending_reconnect: Mutex::new(Vec::<PeerInfo>::new()),
+            reconnect_now: tokio::sync::Notify::new(),
             accounts_data: Arc::new(AccountDataCache::new()),
             account_announcements: Arc::new(AnnounceAccountCache::new(store)),
             tier2_route_back: Mutex::new(RouteBackCache::default()),
             tier1_route_back: Mutex::new(RouteBackCache::default()),
-            recent_routed_messages: Mutex::new(lru::LruCache::new(
-                NonZeroUsize::new(RECENT_ROUTED_MESSAGES_CACHE_SIZE).unwrap(),
-            )),
+            tier1_stats: Mutex::new(tier1_stats::Tier1Stats::default()),
+            tier2_rtts: Mutex::new(tier2_rtts::Tier2Rtts::default()),
+            seen_messages: Mutex::new(SeenMessages::new(clock, config.seen_messages.clone())),
             txns_since_last_block: AtomicUsize::new(0),
+            bandwidth_limiters: bandwidth::GlobalLimiters::new(clock, &config.bandwidth_limits),
+            admission: admission::Admission::new(clock, &config.admission),
+            reachability: Reachability::default(),
+            direct_tx_forwarding_attempts: Mutex::new(lru::LruCache::new(
+                NonZeroUsize::new(DIRECT_TX_FORWARDING_ATTEMPTS_CACHE_SIZE).unwrap(),
+            )),
             whitelist_nodes,
+            access_list: Mutex::new(AccessList::default()),
+            recorder: OnceLock::new(),
             add_edges_demux: demux::Demux::new(config.routing_table_update_rate_limit),
             update_routes_demux: demux::Demux::new(config.routing_table_update_rate_limit),
             set_chain_info_mutex: Mutex::new(()),
@@ -300,6 +348,12 @@ impl NetworkState {
                 return Err(RegisterPeerError::Blacklisted);
             }
 
+            let ip = peer_info.addr.map(|addr| addr.ip());
+            if !this.access_list.lock().is_allowed(&peer_info.id, ip) {
+                tracing::debug!(target: "network", id = ?peer_info.id, "Dropping connection from peer not allowed by the access list");
+                return Err(RegisterPeerError::Blacklisted);
+            }
+
             if this.peer_store.is_banned(&peer_info.id) {
                 tracing::debug!(target: "network", id = ?peer_info.id, "Dropping connection from banned peer");
                 return Err(RegisterPeerError::Banned);
@@ -320,6 +374,7 @@ impl NetworkState {
                     if !edge.verify() {
                         return Err(RegisterPeerError::InvalidEdge);
                     }
+                    this.close_migrated(&this.tier1, &conn);
                     this.tier1.insert_ready(conn).map_err(RegisterPeerError::PoolError)?;
                 }
                 tcp::Tier::T2 => {
@@ -341,6 +396,7 @@ impl NetworkState {
                     this.add_edges(&clock, vec![edge.clone()])
                         .await
                         .map_err(|_: ReasonForBan| RegisterPeerError::InvalidEdge)?;
+                    this.close_migrated(&this.tier2, &conn);
                     // Insert to the local connection pool
                     this.tier2.insert_ready(conn.clone()).map_err(RegisterPeerError::PoolError)?;
                     // Update the V2 routing table
@@ -408,6 +464,15 @@ impl NetworkState {
             // and push a reconnect attempt, if applicable
             if this.connection_store.connection_closed(&conn.peer_info, &conn.peer_type, &reason) {
                 this.pending_reconnect.lock().push(conn.peer_info.clone());
+            } else if matches!(reason, ClosingReason::LocalIpChanged)
+                && conn.peer_type == PeerType::Outbound
+            {
+                // The connection was fine until the IP changed, so reconnect
+                // even if it is too recent to be in the ConnectionStore.
+                this.pending_reconnect.lock().push(conn.peer_info.clone());
+            }
+            if matches!(reason, ClosingReason::LocalIpChanged) {
+                this.reconnect_now.notify_one();
             }
 
             #[cfg(test)]
@@ -423,6 +488,18 @@ impl NetworkState {
     }
 
     /// Attempt to connect to the given peer until successful, up to max_attempts times
+    /// Opens a connection to the peer, over the simulated network if the config sets one.
+    pub(crate) async fn dial(
+        &self,
+        peer_info: &PeerInfo,
+        tier: tcp::Tier,
+    ) -> anyhow::Result<tcp::Stream> {
+        match &self.config.simulation {
+            Some(network) => network.connect(self.config.node_addr.as_ref(), peer_info, tier).await,
+            None => tcp::Stream::connect(peer_info, tier, &self.config.socket_options).await,
+        }
+    }
+
     pub async fn reconnect(
         self: &Arc<Self>,
         clock: time::Clock,
@@ -434,13 +511,15 @@ impl NetworkState {
             interval.tick(&clock).await;
 
             let result = async {
-                let stream =
-                    tcp::Stream::connect(&peer_info, tcp::Tier::T2, &self.config.socket_options)
-                        .await
-                        .context("tcp::Stream::connect()")?;
+                let stream = self.dial(&peer_info, tcp::Tier::T2).await.context("dial()")?;
                 PeerActor::spawn_and_handshake(clock.clone(), stream, None, self.clone())
                     .await
                     .context("PeerActor::spawn()")?;
+                // The peer may reject the connection, e.g. while it still has
+                // the previous one open.
+                if !self.tier2.load().ready.contains_key(&peer_info.id) {
+                    anyhow::bail!("handshake failed");
+                }
                 anyhow::Ok(())
             }
             .await;
@@ -461,6 +540,44 @@ impl NetworkState {
         }
     }
 
+    /// Closes the connections opened from `old_ip`, which the node no longer
+    /// uses, and reconnects to the peers.  The TIER1 proxies are advertised
+    /// again, as their addresses may have changed along with the IP.
+    pub async fn local_ip_changed(self: &Arc<Self>, clock: &time::Clock, old_ip: IpAddr) {
+        metrics::LOCAL_IP_CHANGES.inc();
+        let mut closed = 0;
+        for pool in [&self.tier1, &self.tier2] {
+            for conn in pool.load().ready.values() {
+                if conn.local_addr.ip() == old_ip {
+                    conn.close(ClosingReason::LocalIpChanged);
+                    closed += 1;
+                }
+            }
+        }
+        metrics::MIGRATED_CONNECTIONS.with_label_values(&["local"]).inc_by(closed);
+        tracing::info!(target: "network", %old_ip, closed, "local IP has changed, reconnecting to the peers");
+        if self.config.tier1.is_some() {
+            self.tier1_advertise_proxies(clock).await;
+        }
+    }
+
+    /// A peer which has changed its IP connects again while its connection
+    /// from the old IP is still open, as nothing is sent over it to notice it
+    /// is dead.  The old connection is closed, so that the next attempt of the
+    /// peer succeeds.
+    fn close_migrated(&self, pool: &connection::Pool, conn: &connection::Connection) {
+        if conn.peer_type != PeerType::Inbound {
+            return;
+        }
+        let Some(old) = pool.load().ready.get(&conn.peer_info.id).cloned() else { return };
+        let ip = |c: &connection::Connection| c.peer_info.addr.map(|addr| addr.ip());
+        if old.peer_type == PeerType::Inbound && ip(&old) != ip(conn) {
+            tracing::info!(target: "network", peer_id = %conn.peer_info.id, old_addr = ?old.peer_info.addr, new_addr = ?conn.peer_info.addr, "peer reconnected from another IP");
+            metrics::MIGRATED_CONNECTIONS.with_label_values(&["peer"]).inc();
+            old.close(ClosingReason::PeerMigrated);
+        }
+    }
+
     /// Determine if the given target is referring to us.
     pub fn message_for_me(&self, target: &PeerIdOrHash) -> bool {
         let my_peer_id = self.config.node_id();
@@ -582,7 +699,14 @@ impl NetworkState {
                 RawRoutedMessage { target: PeerIdOrHash::PeerId(peer_id.clone()), body: msg },
             );
             actix::spawn(async move {
-                this.receive_routed_message(&clock, peer_id, msg.hash(), msg.msg.body).await;
+                this.receive_routed_message(
+                    &clock,
+                    peer_id.clone(),
+                    peer_id,
+                    msg.hash(),
+                    msg.msg.body,
+                )
+                .await;
             });
             return true;
         }
@@ -642,6 +766,10 @@ impl NetworkState {
             return false;
         };
 
+        if self.config.direct_tx_forwarding && matches!(msg, RoutedMessageBody::ForwardTx(_)) {
+            self.connect_for_tx_forwarding(clock, &target);
+        }
+
         let mut success = false;
         let msg = RawRoutedMessage { target: PeerIdOrHash::PeerId(target), body: msg };
         let msg = self.sign_message(clock, msg);
@@ -651,10 +779,49 @@ impl NetworkState {
         success
     }
 
+    /// Makes sure that there is (or soon will be) a direct TIER2 connection to the
+    /// chunk producer `peer_id`, which transactions are being forwarded to.
+    /// Until the connection is established, transactions are routed as usual.
+    fn connect_for_tx_forwarding(self: &Arc<Self>, clock: &time::Clock, peer_id: &PeerId) {
+        let tier2 = self.tier2.load();
+        let status = if tier2.ready.contains_key(peer_id) {
+            "connected"
+        } else if tier2.outbound_handshakes.contains(peer_id) {
+            "connecting"
+        } else if tier2.ready.len() + tier2.outbound_handshakes.len()
+            >= self.config.max_num_peers as usize
+        {
+            "no_capacity"
+        } else {
+            let now = clock.now();
+            let mut attempts = self.direct_tx_forwarding_attempts.lock();
+            match attempts.get(peer_id) {
+                Some(last) if now - *last < DIRECT_TX_FORWARDING_CONNECT_INTERVAL => "rate_limited",
+                _ => match self.peer_store.get_peer_state(peer_id) {
+                    Some(state) if state.peer_info.addr.is_some() => {
+                        attempts.put(peer_id.clone(), now);
+                        tracing::debug!(target: "network", ?peer_id, "Connecting to chunk producer to forward transactions");
+                        let this = self.clone();
+                        let clock = clock.clone();
+                        // PeerActor is spawned by `reconnect`, which requires an actix system,
+                        // hence actix::spawn rather than self.spawn.
+                        actix::spawn(async move {
+                            this.reconnect(clock, state.peer_info, 1).await;
+                        });
+                        "connect_attempt"
+                    }
+                    _ => "unknown_address",
+                },
+            }
+        };
+        metrics::DIRECT_TX_FORWARDING.with_label_values(&[status]).inc();
+    }
+
     pub async fn receive_routed_message(
         &self,
         clock: &time::Clock,
         peer_id: PeerId,
+        author: PeerId,
         msg_hash: CryptoHash,
         body: RoutedMessageBody,
     ) -> Option<RoutedMessageBody> {
@@ -740,7 +907,9 @@ impl NetworkState {
                 None
             }
             RoutedMessageBody::EpochSyncResponse(proof) => {
-                self.client.send(EpochSyncResponseMessage { from_peer: peer_id, proof });
+                // The response may be routed through other peers, while
+                // epoch sync needs to know which peer derived the proof.
+                self.client.send(EpochSyncResponseMessage { from_peer: author, proof });
                 None
             }
             body => {
@@ -1,7 +1,7 @@
 use super::NetworkState;
 use crate::network_protocol::{
-    DistanceVector, Edge, EdgeState, PartialEdgeInfo, PeerMessage, RoutedMessageV2,
-    RoutingTableUpdate,
+    DistanceVector, Edge, EdgeState, PartialEdgeInfo, PeerMessage, Ping, Pong, RawRoutedMessage,
+    RoutedMessageBody, RoutedMessageV2, RoutingTableUpdate,
 };
 use crate::peer_manager::connection;
 use crate::peer_manager::network_state::PeerIdOrHash;
@@ -195,6 +195,29 @@ impl NetworkState {
         }
     }
 
+    /// Pings the peers of all TIER2 connections, to measure the round trip
+    /// times which the routing takes into account.
+    pub fn tier2_ping(&self, clock: &time::Clock) {
+        let connected: HashSet<PeerId> = self.tier2.load().ready.keys().cloned().collect();
+        let my_peer_id = self.config.node_id();
+        for peer_id in &connected {
+            let nonce = rand::random();
+            self.tier2_rtts.lock().ping_sent(clock.now(), nonce, peer_id.clone(), &connected);
+            let body = RoutedMessageBody::Ping(Ping { nonce, source: my_peer_id.clone() });
+            let msg = RawRoutedMessage { target: PeerIdOrHash::PeerId(peer_id.clone()), body };
+            self.send_message_to_peer(clock, tcp::Tier::T2, self.sign_message(clock, msg));
+        }
+    }
+
+    pub fn tier2_pong_received(&self, clock: &time::Clock, pong: &Pong) {
+        let mut tier2_rtts = self.tier2_rtts.lock();
+        if tier2_rtts.pong_received(clock.now(), pong.nonce, &pong.source) {
+            let rtts = Arc::new(tier2_rtts.rtts().clone());
+            self.graph.routing_table.set_rtts(rtts.clone());
+            self.graph_v2.routing_table.set_rtts(rtts);
+        }
+    }
+
     /// Accepts a routed message. If we expect a response for the message, writes an entry in
     /// the appropriate RouteBackCache recording the peer node from which the message came.
     /// The cache entry will later be used to route back the response to the message.
@@ -1,23 +1,47 @@
+use super::tier1_stats::Tier1ConnectFailure;
 use crate::accounts_data::{AccountDataCacheSnapshot, LocalAccountData};
 use crate::config::{self, FrozenValidatorConfig};
 use crate::network_protocol::{
-    AccountData, PeerAddr, PeerInfo, PeerMessage, SignedAccountData, SyncAccountsData,
+    AccountData, PeerAddr, PeerIdOrHash, PeerInfo, PeerMessage, Ping, Pong, RawRoutedMessage,
+    RoutedMessageBody, SignedAccountData, SyncAccountsData,
 };
 use crate::peer::peer_actor::PeerActor;
 use crate::peer_manager::connection;
 use crate::stun;
 use crate::tcp;
 use crate::types::PeerType;
+use ::time::ext::InstantExt as _;
 use near_async::time;
 use near_crypto::PublicKey;
 use near_o11y::log_assert;
 use near_primitives::network::PeerId;
+use near_primitives::views::{
+    Tier1ConnectionFailureView, Tier1ConnectionView, Tier1ConnectionsView,
+};
 use rand::seq::IteratorRandom as _;
 use rand::seq::SliceRandom as _;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 impl super::NetworkState {
+    /// Routes to the validator with `account_key` pinned in the config.
+    fn tier1_pinned_routes(
+        &self,
+        accounts_data: &AccountDataCacheSnapshot,
+        account_key: &PublicKey,
+    ) -> Option<&[PeerAddr]> {
+        let pinned_routes = &self.config.tier1.as_ref()?.pinned_routes;
+        pinned_routes
+            .iter()
+            .find(|(account_id, _)| {
+                accounts_data
+                    .keys_by_id
+                    .get(*account_id)
+                    .is_some_and(|keys| keys.contains(account_key))
+            })
+            .map(|(_, routes)| &routes[..])
+    }
+
     // Returns a snapshot of ValidatorConfig of this node iff it belongs to TIER1 according to `accounts_data`.
     fn tier1_validator_config(
         &self,
@@ -52,16 +76,16 @@ impl super::NetworkState {
             }
             handles.push(async move {
                 let res = async {
-                    let stream = tcp::Stream::connect(
-                        &PeerInfo {
-                            id: proxy.peer_id.clone(),
-                            addr: Some(proxy.addr),
-                            account_id: None,
-                        },
-                        tcp::Tier::T1,
-                        &self.config.socket_options,
-                    )
-                    .await?;
+                    let stream = self
+                        .dial(
+                            &PeerInfo {
+                                id: proxy.peer_id.clone(),
+                                addr: Some(proxy.addr),
+                                account_id: None,
+                            },
+                            tcp::Tier::T1,
+                        )
+                        .await?;
                     anyhow::Ok(PeerActor::spawn_and_handshake(clock.clone(), stream, None, self.clone()).await?)
                 }.await;
                 if let Err(err) = res {
@@ -263,6 +287,13 @@ impl super::NetworkState {
                         safe.insert(key, &conn.peer_info.id);
                     }
                 }
+                // Routes pinned in the config have priority over the others.
+                for key in &accounts_data.keys {
+                    let routes = self.tier1_pinned_routes(&accounts_data, key).unwrap_or_default();
+                    if let Some(conn) = routes.iter().find_map(|r| tier1.ready.get(&r.peer_id)) {
+                        safe.insert(key, &conn.peer_info.id);
+                    }
+                }
             }
             // All the other nodes should accept inbound connections from TIER1 nodes
             // (to act as a TIER1 proxy).
@@ -305,6 +336,8 @@ impl super::NetworkState {
             let mut handles = vec![];
             let mut account_keys: Vec<_> = proxies_by_account.keys().copied().collect();
             account_keys.shuffle(&mut rand::thread_rng());
+            // Accounts with pinned routes go first.
+            account_keys.sort_by_key(|key| self.tier1_pinned_routes(&accounts_data, key).is_none());
             for account_key in account_keys {
                 // tier1_connect() is responsible for connecting to proxies
                 // of this node. tier1_connect() connects only to proxies
@@ -317,38 +350,67 @@ impl super::NetworkState {
                 if handles.len() as u64 >= tier1_cfg.new_connections_per_attempt {
                     break;
                 }
+                let pinned_routes = self.tier1_pinned_routes(&accounts_data, account_key);
                 // If we are already connected to some proxy of account_key, then
-                // don't establish another connection.
-                if safe.contains_key(account_key) {
-                    continue;
+                // don't establish another connection, unless it isn't a pinned route.
+                if let Some(peer_id) = safe.get(account_key) {
+                    if pinned_routes
+                        .map_or(true, |routes| routes.iter().any(|r| &r.peer_id == *peer_id))
+                    {
+                        continue;
+                    }
                 }
                 // Find addresses of proxies of account_key.
-                let proxies: Vec<&PeerAddr> =
-                    proxies_by_account.get(account_key).into_iter().flatten().map(|x| *x).collect();
+                let proxies: Vec<&PeerAddr> = match pinned_routes {
+                    Some(routes) => routes.iter().collect(),
+                    None => proxies_by_account
+                        .get(account_key)
+                        .into_iter()
+                        .flatten()
+                        .map(|x| *x)
+                        .collect(),
+                };
                 // Select a random proxy of the account_key and try to connect to it.
                 let proxy = proxies.iter().choose(&mut rand::thread_rng());
                 if let Some(proxy) = proxy {
                     let proxy = (*proxy).clone();
+                    let account_key = account_key.clone();
                     handles.push(async move {
-                        let stream = tcp::Stream::connect(
-                            &PeerInfo {
-                                id: proxy.peer_id,
-                                addr: Some(proxy.addr),
-                                account_id: None,
-                            },
-                            tcp::Tier::T1,
-                            &self.config.socket_options,
-                        )
-                        .await?;
-                        PeerActor::spawn_and_handshake(clock.clone(), stream, None, self.clone())
+                        let res: anyhow::Result<_> = async {
+                            let stream = self
+                                .dial(
+                                    &PeerInfo {
+                                        id: proxy.peer_id.clone(),
+                                        addr: Some(proxy.addr),
+                                        account_id: None,
+                                    },
+                                    tcp::Tier::T1,
+                                )
+                                .await?;
+                            PeerActor::spawn_and_handshake(
+                                clock.clone(),
+                                stream,
+                                None,
+                                self.clone(),
+                            )
                             .await
+                        }
+                        .await;
+                        (proxy, account_key, res)
                     });
                 }
             }
             tracing::debug!(target:"network","{}: establishing {} new connections",self.config.node_id(),handles.len());
-            for res in futures_util::future::join_all(handles).await {
+            for (proxy, account_key, res) in futures_util::future::join_all(handles).await {
                 if let Err(err) = res {
                     tracing::info!(target:"network", ?err, "{}: failed to establish a TIER1 connection",self.config.node_id());
+                    self.tier1_stats.lock().connect_failed(Tier1ConnectFailure {
+                        peer_id: proxy.peer_id,
+                        addr: proxy.addr,
+                        account_key,
+                        time: clock.now_utc(),
+                        error: format!("{err:#}"),
+                    });
                 }
             }
             tracing::debug!(target:"network","{}: establishing new connections DONE",self.config.node_id());
@@ -361,6 +423,14 @@ impl super::NetworkState {
     // TODO(gprusak): If not, consider precomputing the AccountKey -> Connection mapping.
     pub fn get_tier1_proxy(&self, data: &SignedAccountData) -> Option<Arc<connection::Connection>> {
         let tier1 = self.tier1.load();
+        // Prefer the routes pinned in the config.
+        let accounts_data = self.accounts_data.load();
+        for route in self.tier1_pinned_routes(&accounts_data, &data.account_key).unwrap_or_default()
+        {
+            if let Some(conn) = tier1.ready.get(&route.peer_id) {
+                return Some(conn.clone());
+            }
+        }
         // Prefer direct connections.
         if let Some(conn) = tier1.ready_by_account_key.get(&data.account_key) {
             return Some(conn.clone());
@@ -374,4 +444,79 @@ impl super::NetworkState {
         }
         None
     }
+
+    /// Pings the peers of all TIER1 connections, to measure the round trip times.
+    pub fn tier1_ping(&self, clock: &time::Clock) {
+        let tier1 = self.tier1.load();
+        let connected: HashSet<PeerId> = tier1.ready.keys().cloned().collect();
+        let my_peer_id = self.config.node_id();
+        for peer_id in &connected {
+            // The loop connection of a validator which is its own proxy.
+            if peer_id == &my_peer_id {
+                continue;
+            }
+            let nonce = rand::random();
+            self.tier1_stats.lock().ping_sent(clock.now(), nonce, peer_id.clone(), &connected);
+            let body = RoutedMessageBody::Ping(Ping { nonce, source: my_peer_id.clone() });
+            let msg = RawRoutedMessage { target: PeerIdOrHash::PeerId(peer_id.clone()), body };
+            self.send_message_to_peer(clock, tcp::Tier::T1, self.sign_message(clock, msg));
+        }
+    }
+
+    pub fn tier1_pong_received(&self, clock: &time::Clock, pong: &Pong) {
+        self.tier1_stats.lock().pong_received(clock.now(), pong.nonce, &pong.source);
+    }
+
+    pub fn tier1_debug_view(&self, clock: &time::Clock) -> Tier1ConnectionsView {
+        let now = clock.now();
+        let accounts_data = self.accounts_data.load();
+        let mut accounts_by_proxy = HashMap::<_, Vec<_>>::new();
+        for d in accounts_data.data.values() {
+            for p in &d.proxies {
+                accounts_by_proxy.entry(&p.peer_id).or_default().push(d.account_key.clone());
+            }
+        }
+        let tier1 = self.tier1.load();
+        let stats = self.tier1_stats.lock();
+        let connections = tier1
+            .ready
+            .values()
+            .map(|conn| {
+                let peer_id = &conn.peer_info.id;
+                let peer_account_key = conn.owned_account.as_ref().map(|a| a.account_key.clone());
+                let mut account_keys = accounts_by_proxy.remove(peer_id).unwrap_or_default();
+                account_keys.extend(peer_account_key.clone());
+                account_keys.sort();
+                account_keys.dedup();
+                let pinned = account_keys.iter().any(|key| {
+                    self.tier1_pinned_routes(&accounts_data, key)
+                        .is_some_and(|routes| routes.iter().any(|r| &r.peer_id == peer_id))
+                });
+                Tier1ConnectionView {
+                    peer_id: peer_id.clone(),
+                    addr: format!("{:?}", conn.peer_info.addr),
+                    is_outbound_peer: conn.peer_type == PeerType::Outbound,
+                    peer_account_key,
+                    account_keys,
+                    pinned,
+                    connection_established_time_millis: now
+                        .signed_duration_since(conn.established_time)
+                        .whole_milliseconds()
+                        as u64,
+                    rtt_millis: stats.rtt(peer_id).map(|rtt| rtt.whole_milliseconds() as u64),
+                }
+            })
+            .collect();
+        let recent_failures = stats
+            .recent_failures()
+            .map(|f| Tier1ConnectionFailureView {
+                peer_id: f.peer_id.clone(),
+                addr: f.addr.to_string(),
+                account_key: f.account_key.clone(),
+                time: f.time.unix_timestamp(),
+                error: f.error.clone(),
+            })
+            .collect();
+        Tier1ConnectionsView { connections, recent_failures }
+    }
 }
@@ -0,0 +1,70 @@
+//! Round trip times of the TIER1 connections and the recent failures to
+//! establish them, for the debug page.
+
+use ::time::ext::InstantExt as _;
+use near_async::time;
+use near_crypto::PublicKey;
+use near_primitives::network::PeerId;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+
+/// Number of connection failures to keep.
+const MAX_RECENT_FAILURES: usize = 32;
+/// Pings not answered within this time are forgotten.
+const PING_TIMEOUT: time::Duration = time::Duration::minutes(1);
+
+pub(crate) struct Tier1ConnectFailure {
+    pub peer_id: PeerId,
+    pub addr: SocketAddr,
+    pub account_key: PublicKey,
+    pub time: time::Utc,
+    pub error: String,
+}
+
+#[derive(Default)]
+pub(crate) struct Tier1Stats {
+    /// Pings sent over TIER1 connections, by nonce.
+    pending_pings: HashMap<u64, (PeerId, time::Instant)>,
+    /// Round trip time of the last answered ping, by peer.
+    rtts: HashMap<PeerId, time::Duration>,
+    recent_failures: VecDeque<Tier1ConnectFailure>,
+}
+
+impl Tier1Stats {
+    /// Records a ping sent to `peer_id`.  `connected` are the peers of all
+    /// the TIER1 connections, the stats of the others are dropped.
+    pub fn ping_sent(
+        &mut self,
+        now: time::Instant,
+        nonce: u64,
+        peer_id: PeerId,
+        connected: &HashSet<PeerId>,
+    ) {
+        self.pending_pings.retain(|_, (_, sent)| now.signed_duration_since(*sent) < PING_TIMEOUT);
+        self.rtts.retain(|peer_id, _| connected.contains(peer_id));
+        self.pending_pings.insert(nonce, (peer_id, now));
+    }
+
+    pub fn pong_received(&mut self, now: time::Instant, nonce: u64, source: &PeerId) {
+        if let Some((peer_id, sent)) = self.pending_pings.remove(&nonce) {
+            if &peer_id == source {
+                self.rtts.insert(peer_id, now.signed_duration_since(sent));
+            }
+        }
+    }
+
+    pub fn rtt(&self, peer_id: &PeerId) -> Option<time::Duration> {
+        self.rtts.get(peer_id).copied()
+    }
+
+    pub fn connect_failed(&mut self, failure: Tier1ConnectFailure) {
+        if self.recent_failures.len() == MAX_RECENT_FAILURES {
+            self.recent_failures.pop_front();
+        }
+        self.recent_failures.push_back(failure);
+    }
+
+    pub fn recent_failures(&self) -> impl Iterator<Item = &Tier1ConnectFailure> {
+        self.recent_failures.iter()
+    }
+}
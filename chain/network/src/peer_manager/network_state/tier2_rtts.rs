@@ -0,0 +1,56 @@
+//! Round trip times of the TIER2 connections, measured with pings. Among the
+//! next hops on the shortest paths to a peer, the routing prefers the ones
+//! with lower round trip times, see `RoutingTableView`.
+
+use crate::routing::routing_table_view::RttTable;
+use ::time::ext::InstantExt as _;
+use near_async::time;
+use near_primitives::network::PeerId;
+use std::collections::{HashMap, HashSet};
+
+/// Pings not answered within this time are forgotten.
+const PING_TIMEOUT: time::Duration = time::Duration::minutes(1);
+/// Weight of the latest measurement in the smoothed round trip time, as in
+/// TCP (RFC 6298), so that a single slow pong doesn't reroute the traffic.
+const SMOOTHING: f64 = 0.125;
+
+#[derive(Default)]
+pub(crate) struct Tier2Rtts {
+    /// Pings sent over TIER2 connections, by nonce.
+    pending_pings: HashMap<u64, (PeerId, time::Instant)>,
+    /// Smoothed round trip time, by peer.
+    rtts: RttTable,
+}
+
+impl Tier2Rtts {
+    /// Records a ping sent to `peer_id`.  `connected` are the peers of all
+    /// the TIER2 connections, the round trip times of the others are dropped.
+    pub fn ping_sent(
+        &mut self,
+        now: time::Instant,
+        nonce: u64,
+        peer_id: PeerId,
+        connected: &HashSet<PeerId>,
+    ) {
+        self.pending_pings.retain(|_, (_, sent)| now.signed_duration_since(*sent) < PING_TIMEOUT);
+        self.rtts.retain(|peer_id, _| connected.contains(peer_id));
+        self.pending_pings.insert(nonce, (peer_id, now));
+    }
+
+    /// Returns whether the pong answers one of the pings, i.e. whether the
+    /// round trip times have changed.
+    pub fn pong_received(&mut self, now: time::Instant, nonce: u64, source: &PeerId) -> bool {
+        let Some((peer_id, sent)) = self.pending_pings.remove(&nonce) else { return false };
+        if &peer_id != source {
+            return false;
+        }
+        let rtt = now.signed_duration_since(sent);
+        let srtt = self.rtts.entry(peer_id).or_insert(rtt);
+        *srtt = *srtt * (1. - SMOOTHING) + rtt * SMOOTHING;
+        true
+    }
+
+    pub fn rtts(&self) -> &RttTable {
+        &self.rtts
+    }
+}
@@ -1,6 +1,6 @@
 use crate::client::{ClientSenderForNetwork, SetNetworkInfo};
 use crate::config;
-use crate::debug::{DebugStatus, GetDebugStatus};
+use crate::debug::{DebugStatus, GetDebugStatus, PeerAccessControl};
 use crate::network_protocol;
 use crate::network_protocol::SyncSnapshotHosts;
 use crate::network_protocol::{
@@ -10,6 +10,7 @@ use crate::peer::peer_actor::PeerActor;
 use crate::peer_manager::connection;
 use crate::peer_manager::network_state::{NetworkState, WhitelistNode};
 use crate::peer_manager::peer_store;
+use crate::recorder::Recorder;
 use crate::shards_manager::ShardsManagerRequestFromNetwork;
 use crate::state_witness::PartialWitnessSenderForNetwork;
 use crate::stats::metrics;
@@ -86,6 +87,8 @@ const PREFER_PREVIOUSLY_CONNECTED_PEER: f64 = 0.6;
 pub(crate) const UPDATE_CONNECTION_STORE_INTERVAL: time::Duration = time::Duration::minutes(1);
 /// How often to poll the NetworkState for closed connections we'd like to re-establish.
 pub(crate) const POLL_CONNECTION_STORE_INTERVAL: time::Duration = time::Duration::minutes(1);
+/// How often to check the access list file for changes.
+const RELOAD_ACCESS_LIST_INTERVAL: time::Duration = time::Duration::seconds(10);
 
 /// Actor that manages peers connections.
 pub struct PeerManagerActor {
@@ -244,6 +247,14 @@ impl PeerManagerActor {
             partial_witness_adapter,
             whitelist_nodes,
         ));
+        state.reload_access_list().context("access_list_file")?;
+        if let Some(record_traffic) = &state.config.record_traffic {
+            let recorder = Recorder::start(&clock, record_traffic).context("record_traffic")?;
+            state.recorder.get_or_init(|| recorder);
+        }
+        if state.config.dht.enabled {
+            state.dht_add_saved_peers(&clock);
+        }
         arbiter.spawn({
             let arbiter = arbiter.clone();
             let state = state.clone();
@@ -251,36 +262,53 @@ impl PeerManagerActor {
             async move {
                 // Start server if address provided.
                 if let Some(server_addr) = &state.config.node_addr {
-                    tracing::debug!(target: "network", at = ?server_addr, "starting public server");
-                    let mut listener = match server_addr.listener() {
-                        Ok(it) => it,
-                        Err(e) => {
-                            panic!("failed to start listening on server_addr={server_addr:?} e={e:?}")
+                    let server_addrs = std::iter::once(server_addr)
+                        .chain(state.config.extra_listen_addrs.iter().map(|a| &a.addr));
+                    let mut listeners = vec![];
+                    for server_addr in server_addrs {
+                        tracing::debug!(target: "network", at = ?server_addr, "starting public server");
+                        let listener = match &state.config.simulation {
+                            Some(network) => Ok(network.listen(server_addr)),
+                            None => server_addr.listener(),
+                        };
+                        match listener {
+                            Ok(it) => listeners.push(it),
+                            Err(e) => {
+                                panic!("failed to start listening on server_addr={server_addr:?} e={e:?}")
+                            }
                         }
-                    };
+                    }
                     #[cfg(test)]
                     state.config.event_sink.send(Event::ServerStarted);
+                    // Map the port and check the reachability of the node once it listens.
                     arbiter.spawn({
                         let clock = clock.clone();
                         let state = state.clone();
-                        async move {
-                            loop {
-                                if let Ok(stream) = listener.accept().await {
-                                    // Always let the new peer to send a handshake message.
-                                    // Only then we can decide whether we should accept a connection.
-                                    // It is expected to be reasonably cheap: eventually, for TIER2 network
-                                    // we would like to exchange set of connected peers even without establishing
-                                    // a proper connection.
-                                    tracing::debug!(target: "network", from = ?stream.peer_addr, "got new connection");
-                                    if let Err(err) =
-                                        PeerActor::spawn(clock.clone(), stream, None, state.clone())
-                                    {
-                                        tracing::info!(target:"network", ?err, "PeerActor::spawn()");
+                        async move { state.reachability.run(&clock, &state.config).await }
+                    });
+                    for mut listener in listeners {
+                        arbiter.spawn({
+                            let clock = clock.clone();
+                            let state = state.clone();
+                            async move {
+                                loop {
+                                    if let Ok(stream) = listener.accept().await {
+                                        // Always let the new peer to send a handshake message.
+                                        // Only then we can decide whether we should accept a connection.
+                                        // It is expected to be reasonably cheap: eventually, for TIER2 network
+                                        // we would like to exchange set of connected peers even without establishing
+                                        // a proper connection.
+                                        tracing::debug!(target: "network", from = ?stream.peer_addr, "got new connection");
+                                        if let Err(err) =
+                                            PeerActor::spawn(clock.clone(), stream, None, state.clone())
+                                        {
+                                            tracing::info!(target:"network", ?err, "PeerActor::spawn()");
+                                        }
                                     }
                                 }
                             }
-                        }
-                    });
+                        });
+                    }
                 }
                 if let Some(cfg) = state.config.tier1.clone() {
                     // Connect to TIER1 proxies and broadcast the list those connections periodically.
@@ -306,6 +334,67 @@ impl PeerManagerActor {
                             loop {
                                 interval.tick().await;
                                 state.tier1_connect(&clock).await;
+                                state.tier1_ping(&clock);
+                            }
+                        }
+                    });
+                }
+                if let Some(ping_interval) = state.config.tier2_ping_interval {
+                    // Measure the round trip times of TIER2 periodically.
+                    arbiter.spawn({
+                        let clock = clock.clone();
+                        let state = state.clone();
+                        let mut interval = time::Interval::new(clock.now(), ping_interval);
+                        async move {
+                            loop {
+                                interval.tick(&clock).await;
+                                state.tier2_ping(&clock);
+                            }
+                        }
+                    });
+                }
+                if let Some(check_interval) = state.config.reachability.local_ip_check_interval {
+                    // Watch the local IP, to move the connections over to the new one as
+                    // soon as it changes.
+                    arbiter.spawn({
+                        let clock = clock.clone();
+                        let state = state.clone();
+                        let want_ipv4 = state.config.node_addr.as_ref().map_or(true, |addr| addr.is_ipv4());
+                        let mut interval = time::Interval::new(clock.now(), check_interval);
+                        async move {
+                            loop {
+                                interval.tick(&clock).await;
+                                if let Some(old_ip) = state.reachability.local_ip_changed(want_ipv4).await {
+                                    state.local_ip_changed(&clock, old_ip).await;
+                                }
+                            }
+                        }
+                    });
+                }
+                if state.config.access_list_file.is_some() {
+                    arbiter.spawn({
+                        let clock = clock.clone();
+                        let state = state.clone();
+                        let mut interval = time::Interval::new(clock.now(), RELOAD_ACCESS_LIST_INTERVAL);
+                        async move {
+                            loop {
+                                interval.tick(&clock).await;
+                                if let Err(err) = state.reload_access_list() {
+                                    tracing::warn!(target: "network", ?err, "Failed to reload the peer access list, keeping the previous one");
+                                }
+                            }
+                        }
+                    });
+                }
+                if state.config.dht.enabled {
+                    arbiter.spawn({
+                        let clock = clock.clone();
+                        let state = state.clone();
+                        let mut interval = time::Interval::new(clock.now(), state.config.dht.lookup_interval);
+                        async move {
+                            loop {
+                                interval.tick(&clock).await;
+                                state.dht_lookup(&clock);
                             }
                         }
                     });
@@ -318,7 +407,11 @@ impl PeerManagerActor {
                     let mut interval = time::Interval::new(clock.now(), POLL_CONNECTION_STORE_INTERVAL);
                     async move {
                         loop {
-                            interval.tick(&clock).await;
+                            // Interval::tick() and Notify::notified() are cancel-safe.
+                            tokio::select! {
+                                _ = interval.tick(&clock) => {}
+                                _ = state.reconnect_now.notified() => {}
+                            }
                             // Poll the NetworkState for all pending reconnect attempts
                             let pending_reconnect = state.poll_pending_reconnect();
                             // Spawn a separate reconnect loop for each pending reconnect attempt
@@ -467,8 +560,8 @@ impl PeerManagerActor {
     }
 
     /// Check if the number of connections (excluding whitelisted ones) exceeds ideal_connections_hi.
-    /// If so, constructs a safe set of peers and selects one random peer outside of that set
-    /// and sends signal to stop connection to it gracefully.
+    /// If so, constructs a safe set of peers and selects the peer with the lowest score outside
+    /// of that set and sends signal to stop connection to it gracefully.
     ///
     /// Safe set construction process:
     /// 1. Add all whitelisted peers to the safe set.
@@ -542,9 +635,19 @@ impl PeerManagerActor {
         }
 
         // Build valid candidate list to choose the peer to be removed. All peers outside the safe set.
-        let candidates = tier2.ready.values().filter(|p| !safe_set.contains(&p.peer_info.id));
-        if let Some(p) = candidates.choose(&mut rand::thread_rng()) {
+        let mut candidates: Vec<_> = tier2
+            .ready
+            .values()
+            .filter(|p| !safe_set.contains(&p.peer_info.id))
+            .map(|p| (self.state.peer_store.peer_score(&self.clock, &p.peer_info.id), p))
+            .collect();
+        // Break the ties between the peers with the same score randomly.
+        candidates.shuffle(&mut rand::thread_rng());
+        if let Some((score, p)) =
+            candidates.into_iter().reduce(|worst, p| if p.0 < worst.0 { p } else { worst })
+        {
             tracing::debug!(target: "network", id = ?p.peer_info.id,
+                score,
                 tier2_len = tier2.ready.len(),
                 ideal_connections_hi = self.state.config.ideal_connections_hi,
                 "Stop active connection"
@@ -583,6 +686,7 @@ impl PeerManagerActor {
             let prefer_previously_connected_peer =
                 thread_rng().gen_bool(PREFER_PREVIOUSLY_CONNECTED_PEER);
             if let Some(peer_info) = self.state.peer_store.unconnected_peer(
+                &self.clock,
                 |peer_state| {
                     // Ignore connecting to ourself
                     self.my_peer_id == peer_state.peer_info.id
@@ -602,7 +706,7 @@ impl PeerManagerActor {
                     let clock = self.clock.clone();
                     async move {
                         let result = async {
-                            let stream = tcp::Stream::connect(&peer_info, tcp::Tier::T2, &state.config.socket_options).await.context("tcp::Stream::connect()")?;
+                            let stream = state.dial(&peer_info, tcp::Tier::T2).await.context("dial()")?;
                             PeerActor::spawn_and_handshake(clock.clone(),stream,None,state.clone()).await.context("PeerActor::spawn()")?;
                             anyhow::Ok(())
                         }.await;
@@ -706,6 +810,7 @@ impl PeerManagerActor {
                 .collect(),
             tier1_accounts_keys: self.state.accounts_data.load().keys.iter().cloned().collect(),
             tier1_accounts_data: self.state.accounts_data.load().data.values().cloned().collect(),
+            reachability: self.state.reachability.view(),
         }
     }
 
@@ -1147,6 +1252,7 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
     fn handle(&mut self, msg: GetDebugStatus, _ctx: &mut actix::Context<Self>) -> Self::Result {
         match msg {
             GetDebugStatus::PeerStore => {
+                let now = self.clock.now_utc();
                 let mut peer_states_view = self
                     .state
                     .peer_store
@@ -1167,6 +1273,10 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
                                 (attempt_time.unix_timestamp(), foo)
                             },
                         ),
+                        score: known_peer_state
+                            .score
+                            .value(self.state.config.peer_store.score_half_life, now)
+                            .round() as i64,
                     })
                     .collect::<Vec<_>>();
 
@@ -1223,6 +1333,16 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
                     })
                     .collect::<Vec<_>>(),
             }),
+            GetDebugStatus::Tier1 => DebugStatus::Tier1(self.state.tier1_debug_view(&self.clock)),
+            GetDebugStatus::AccessList => DebugStatus::AccessList(self.state.access_list_view()),
         }
     }
 }
+
+impl actix::Handler<PeerAccessControl> for PeerManagerActor {
+    type Result = DebugStatus;
+    #[perf]
+    fn handle(&mut self, msg: PeerAccessControl, _ctx: &mut actix::Context<Self>) -> Self::Result {
+        DebugStatus::AccessList(self.state.peer_access_control(msg))
+    }
+}
@@ -8,17 +8,25 @@ use lru::LruCache;
 use near_async::time;
 use near_primitives::network::PeerId;
 use parking_lot::Mutex;
-use rand::seq::IteratorRandom;
+use rand::seq::{IteratorRandom, SliceRandom};
 use rand::thread_rng;
 use std::net::SocketAddr;
 use std::num::NonZeroUsize;
 use std::ops::Not;
 
+mod score;
 #[cfg(test)]
 mod testonly;
 #[cfg(test)]
 mod tests;
 
+pub use score::{PeerEvent, PeerScore};
+
+/// Number of random candidates the best scored peer to connect to is picked
+/// from.  Picking among a sample rather than all the peers keeps trying new
+/// peers, whose score we don't know yet.
+const CONNECT_CANDIDATES_SAMPLE_SIZE: usize = 5;
+
 /// The PeerStore is an in-memory cache of known peer states. It is used to:
 ///     - Store information about known peers in the network. Peers may be discovered
 ///       by connecting to them directly or by learning about them from other peers.
@@ -72,6 +80,8 @@ pub struct Config {
     pub peer_expiration_duration: time::Duration,
     /// Duration of the ban for misbehaving peers.
     pub ban_window: time::Duration,
+    /// Half-life of the points of the peers, see [`PeerScore`].
+    pub score_half_life: time::Duration,
 }
 
 /// Known peers store, maintaining cache of known peers
@@ -181,6 +191,28 @@ impl Inner {
             .collect()
     }
 
+    /// Find the best scored peer among a random sample of the peers based
+    /// on filter.
+    fn find_best_peer<F>(&self, now: time::Utc, filter: F) -> Option<PeerInfo>
+    where
+        F: FnMut(&&KnownPeerState) -> bool,
+    {
+        let mut candidates = (self.peer_states.iter().map(|(_, v)| v))
+            .filter(filter)
+            .choose_multiple(&mut thread_rng(), CONNECT_CANDIDATES_SAMPLE_SIZE);
+        // The sample isn't in random order, and ties are common.
+        candidates.shuffle(&mut thread_rng());
+        let half_life = self.config.score_half_life;
+        let best = candidates.into_iter().reduce(|best, candidate| {
+            if candidate.score.value(half_life, now) > best.score.value(half_life, now) {
+                candidate
+            } else {
+                best
+            }
+        })?;
+        Some(best.peer_info.clone())
+    }
+
     /// Create new pair between peer_info.id and peer_addr removing
     /// old pairs if necessary.
     fn update_peer_info(
@@ -410,6 +442,15 @@ impl PeerStore {
         Ok(())
     }
 
+    /// Lifts the ban of the peer, if any.
+    pub fn peer_unban(&self, peer_id: &PeerId) {
+        let mut inner = self.0.lock();
+        if inner.peer_states.get(peer_id).is_some_and(|s| s.status.is_banned()) {
+            tracing::info!(target: "network", "Unbanning peer {}", peer_id);
+            inner.peer_unban(peer_id).unwrap();
+        }
+    }
+
     pub fn peer_ban(
         &self,
         clock: &time::Clock,
@@ -418,26 +459,49 @@ impl PeerStore {
     ) -> anyhow::Result<()> {
         tracing::warn!(target: "network", "Banning peer {} for {:?}", peer_id, ban_reason);
         let mut inner = self.0.lock();
+        let half_life = inner.config.score_half_life;
         if let Some(peer_state) = inner.peer_states.get_mut(peer_id) {
             let now = clock.now_utc();
             peer_state.last_seen = now;
             peer_state.status = KnownPeerStatus::Banned(ban_reason, now);
+            peer_state.score.record(half_life, now, PeerEvent::Banned);
         } else {
             bail!("Peer {} is missing in the peer store", peer_id);
         }
         Ok(())
     }
 
+    /// Updates the score of the peer, see [`PeerScore`].
+    pub fn record_peer_event(&self, clock: &time::Clock, peer_id: &PeerId, event: PeerEvent) {
+        let mut inner = self.0.lock();
+        let half_life = inner.config.score_half_life;
+        if let Some(peer_state) = inner.peer_states.peek_mut(peer_id) {
+            peer_state.score.record(half_life, clock.now_utc(), event);
+        }
+    }
+
+    /// Returns the score of the peer, zero if it isn't known.
+    pub fn peer_score(&self, clock: &time::Clock, peer_id: &PeerId) -> f64 {
+        let inner = self.0.lock();
+        inner.peer_states.peek(peer_id).map_or(0.0, |peer_state| {
+            peer_state.score.value(inner.config.score_half_life, clock.now_utc())
+        })
+    }
+
     /// Return unconnected or peers with unknown status that we can try to connect to.
-    /// Peers with unknown addresses are filtered out.
+    /// Peers with unknown addresses are filtered out.  Among the candidates,
+    /// peers with better scores are preferred.
     pub fn unconnected_peer(
         &self,
+        clock: &time::Clock,
         ignore_fn: impl Fn(&KnownPeerState) -> bool,
         prefer_previously_connected_peer: bool,
     ) -> Option<PeerInfo> {
         let inner = self.0.lock();
+        let now = clock.now_utc();
         if prefer_previously_connected_peer {
-            let preferred_peer = inner.find_peers(
+            let preferred_peer = inner.find_best_peer(
+                now,
                 |p| {
                     (p.status == KnownPeerStatus::NotConnected)
                         && !ignore_fn(p)
@@ -445,28 +509,20 @@ impl PeerStore {
                         // if we're connecting only to the boot nodes - filter out the nodes that are not bootnodes.
                         && (!inner.config.connect_only_to_boot_nodes || inner.boot_nodes.contains(&p.peer_info.id))
                 },
-                1,
-            )
-            .get(0)
-            .cloned();
+            );
             // If we found a preferred peer - return it.
             if preferred_peer.is_some() {
                 return preferred_peer;
             };
             // otherwise, pick a peer from the wider pool below.
         }
-        inner.find_peers(
-            |p| {
-                (p.status == KnownPeerStatus::NotConnected || p.status == KnownPeerStatus::Unknown)
-                    && !ignore_fn(p)
-                    && p.peer_info.addr.is_some()
-                    // If we're connecting only to the boot nodes - filter out the nodes that are not boot nodes.
-                    && (!inner.config.connect_only_to_boot_nodes || inner.boot_nodes.contains(&p.peer_info.id))
-            },
-            1,
-        )
-        .get(0)
-        .cloned()
+        inner.find_best_peer(now, |p| {
+            (p.status == KnownPeerStatus::NotConnected || p.status == KnownPeerStatus::Unknown)
+                && !ignore_fn(p)
+                && p.peer_info.addr.is_some()
+                // If we're connecting only to the boot nodes - filter out the nodes that are not boot nodes.
+                && (!inner.config.connect_only_to_boot_nodes || inner.boot_nodes.contains(&p.peer_info.id))
+        })
     }
 
     /// Return healthy known peers up to given amount.
@@ -0,0 +1,119 @@
+//! Scores of how useful the peers are to us.
+//!
+//! A peer earns points for serving what we request from it: blocks, headers,
+//! chunks and state parts.  It loses points for the messages we have to drop,
+//! e.g. rate limited or unexpected ones, and many of them when it gets banned.
+//! The points decay by half every `score_half_life`, so that the score
+//! follows the recent behavior of the peer and a ban is eventually forgotten.
+//! The time the peer takes to serve our requests counts against it too.
+//!
+//! The score picks the connection to drop when there are too many, and
+//! favors the known peers which served us well when connecting to new ones.
+
+use near_async::time;
+
+/// Points for a response to one of our requests.
+const USEFUL_RESPONSE_POINTS: f64 = 1.0;
+/// Points for a message we had to drop.
+const DROPPED_MESSAGE_POINTS: f64 = -5.0;
+/// Points for a message the peer was banned for.
+const BANNED_POINTS: f64 = -100.0;
+/// Points lost per second of the average response latency.
+const LATENCY_POINTS_PER_SEC: f64 = -10.0;
+/// Weight of a new latency in the moving average.
+const NEW_LATENCY_WEIGHT: f64 = 0.2;
+
+/// Something the peer did which changes its score.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerEvent {
+    /// The peer served one of our requests, in the given time if known.
+    UsefulResponse(Option<time::Duration>),
+    /// We dropped a message of the peer.
+    DroppedMessage,
+    /// The peer got banned.
+    Banned,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PeerScore {
+    /// Points as of `updated`.
+    points: f64,
+    updated: Option<time::Utc>,
+    /// Moving average of the time the peer takes to serve our requests.
+    latency: Option<time::Duration>,
+}
+
+impl PeerScore {
+    fn points_at(&self, half_life: time::Duration, now: time::Utc) -> f64 {
+        let Some(updated) = self.updated else {
+            return 0.0;
+        };
+        let elapsed = (now - updated).as_seconds_f64().max(0.0);
+        self.points * 0.5f64.powf(elapsed / half_life.as_seconds_f64())
+    }
+
+    pub(crate) fn record(&mut self, half_life: time::Duration, now: time::Utc, event: PeerEvent) {
+        let mut points = self.points_at(half_life, now);
+        match event {
+            PeerEvent::UsefulResponse(latency) => {
+                points += USEFUL_RESPONSE_POINTS;
+                if let Some(latency) = latency {
+                    self.latency = Some(match self.latency {
+                        Some(average) => {
+                            average * (1.0 - NEW_LATENCY_WEIGHT) + latency * NEW_LATENCY_WEIGHT
+                        }
+                        None => latency,
+                    });
+                }
+            }
+            PeerEvent::DroppedMessage => points += DROPPED_MESSAGE_POINTS,
+            PeerEvent::Banned => points += BANNED_POINTS,
+        }
+        self.points = points;
+        self.updated = Some(now);
+    }
+
+    /// The score of the peer at `now`, the higher the better.  Peers we know
+    /// nothing about score zero.
+    pub fn value(&self, half_life: time::Duration, now: time::Utc) -> f64 {
+        let latency = self.latency.map_or(0.0, |latency| latency.as_seconds_f64());
+        self.points_at(half_life, now) + latency * LATENCY_POINTS_PER_SEC
+    }
+
+    /// Average time the peer takes to serve our requests, if measured.
+    pub fn latency(&self) -> Option<time::Duration> {
+        self.latency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PeerEvent, PeerScore};
+    use near_async::time;
+
+    #[test]
+    fn test_peer_score() {
+        let half_life = time::Duration::hours(1);
+        let now = time::Utc::UNIX_EPOCH;
+        let mut good = PeerScore::default();
+        let mut bad = PeerScore::default();
+        assert_eq!(good.value(half_life, now), 0.0);
+
+        for _ in 0..10 {
+            good.record(half_life, now, PeerEvent::UsefulResponse(None));
+        }
+        bad.record(half_life, now, PeerEvent::UsefulResponse(None));
+        bad.record(half_life, now, PeerEvent::DroppedMessage);
+        assert_eq!(good.value(half_life, now), 10.0);
+        assert!(bad.value(half_life, now) < 0.0);
+
+        // The points halve every half-life.
+        assert_eq!(good.value(half_life, now + half_life), 5.0);
+
+        // Slow responses count against the peer.
+        let mut slow = good.clone();
+        slow.record(half_life, now, PeerEvent::UsefulResponse(Some(time::Duration::seconds(2))));
+        assert_eq!(slow.latency(), Some(time::Duration::seconds(2)));
+        assert!(slow.value(half_life, now) < good.value(half_life, now));
+    }
+}
@@ -37,6 +37,7 @@ fn make_config(
         connect_only_to_boot_nodes,
         ban_window: time::Duration::seconds(1),
         peer_expiration_duration: time::Duration::days(1000),
+        score_half_life: time::Duration::hours(1),
     }
 }
 
@@ -66,8 +67,8 @@ fn test_unconnected_peer() {
         PeerStore::new(&clock.clock(), make_config(&boot_nodes, Blacklist::default(), false))
             .unwrap();
 
-    assert!(peer_store.unconnected_peer(|_| false, false).is_some());
-    assert!(peer_store.unconnected_peer(|_| true, false).is_none());
+    assert!(peer_store.unconnected_peer(&clock.clock(), |_| false, false).is_some());
+    assert!(peer_store.unconnected_peer(&clock.clock(), |_| true, false).is_none());
 }
 
 #[test]
@@ -120,7 +121,7 @@ fn test_unknown_vs_not_connected() {
     // if we prefer 'previously connected' peers - we should keep picking 'b'.
     assert_eq!(
         (0..10)
-            .map(|_| peer_store.unconnected_peer(|_| false, true).unwrap().id)
+            .map(|_| peer_store.unconnected_peer(&clock.clock(), |_| false, true).unwrap().id)
             .collect::<HashSet<PeerId>>(),
         [peer_info_b.id.clone()].into_iter().collect::<HashSet<_>>()
     );
@@ -128,7 +129,7 @@ fn test_unknown_vs_not_connected() {
     // if we don't care, we should pick either 'b' or 'boot'.
     assert_eq!(
         (0..100)
-            .map(|_| peer_store.unconnected_peer(|_| false, false).unwrap().id)
+            .map(|_| peer_store.unconnected_peer(&clock.clock(), |_| false, false).unwrap().id)
             .collect::<HashSet<PeerId>>(),
         [peer_info_b.id.clone(), peer_info_boot_node.id.clone()]
             .into_iter()
@@ -164,7 +165,10 @@ fn test_unconnected_peer_only_boot_nodes() {
                 .unwrap();
         peer_store.add_direct_peer(&clock.clock(), peer_in_store.clone());
         peer_store.peer_connected(&clock.clock(), &peer_info_a);
-        assert_eq!(peer_store.unconnected_peer(|_| false, false), Some(peer_in_store.clone()));
+        assert_eq!(
+            peer_store.unconnected_peer(&clock.clock(), |_| false, false),
+            Some(peer_in_store.clone())
+        );
     }
 
     // 1 boot node (peer_info_a) that we're already connected to.
@@ -176,7 +180,7 @@ fn test_unconnected_peer_only_boot_nodes() {
                 .unwrap();
         peer_store.add_direct_peer(&clock.clock(), peer_in_store);
         peer_store.peer_connected(&clock.clock(), &peer_info_a);
-        assert_eq!(peer_store.unconnected_peer(|_| false, false), None);
+        assert_eq!(peer_store.unconnected_peer(&clock.clock(), |_| false, false), None);
     }
 
     // 1 boot node (peer_info_a) is in the store.
@@ -188,7 +192,10 @@ fn test_unconnected_peer_only_boot_nodes() {
         )
         .unwrap();
         peer_store.add_direct_peer(&clock.clock(), peer_info_a.clone());
-        assert_eq!(peer_store.unconnected_peer(|_| false, false), Some(peer_info_a.clone()));
+        assert_eq!(
+            peer_store.unconnected_peer(&clock.clock(), |_| false, false),
+            Some(peer_info_a.clone())
+        );
     }
 }
 
@@ -482,3 +489,39 @@ fn test_lru_ignore_duplicate_peers() {
     peer_store.add_indirect_peers(&clock.clock(), peer_infos[10..].iter().cloned());
     assert_peers_in_cache(&peer_store, &peer_ids[5..], &peer_addresses[5..]);
 }
+
+#[test]
+fn test_unconnected_peer_prefers_higher_score() {
+    let clock = time::FakeClock::default();
+    let good_peer = gen_peer_info(0);
+    let bad_peer = gen_peer_info(1);
+    let boot_nodes = vec![good_peer.clone(), bad_peer.clone()];
+    let peer_store =
+        PeerStore::new(&clock.clock(), make_config(&boot_nodes, Blacklist::default(), false))
+            .unwrap();
+    peer_store.record_peer_event(
+        &clock.clock(),
+        &good_peer.id,
+        PeerEvent::UsefulResponse(Some(time::Duration::milliseconds(100))),
+    );
+    peer_store.record_peer_event(&clock.clock(), &bad_peer.id, PeerEvent::DroppedMessage);
+    assert!(
+        peer_store.peer_score(&clock.clock(), &good_peer.id)
+            > peer_store.peer_score(&clock.clock(), &bad_peer.id)
+    );
+    for _ in 0..10 {
+        assert_eq!(
+            peer_store.unconnected_peer(&clock.clock(), |_| false, false),
+            Some(good_peer.clone())
+        );
+    }
+
+    // Once the bad peer is banned and unbanned, it is still avoided.
+    peer_store.peer_ban(&clock.clock(), &bad_peer.id, ReasonForBan::Abusive).unwrap();
+    clock.advance(time::Duration::seconds(2));
+    peer_store.update(&clock.clock());
+    assert_eq!(
+        peer_store.unconnected_peer(&clock.clock(), |_| false, false),
+        Some(good_peer.clone())
+    );
+}
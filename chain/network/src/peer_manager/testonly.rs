@@ -17,6 +17,7 @@ use crate::peer;
 use crate::peer::peer_actor::ClosingReason;
 use crate::peer_manager::network_state::NetworkState;
 use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::recorder;
 use crate::shards_manager::ShardsManagerRequestFromNetwork;
 use crate::snapshot_hosts::SnapshotHostsCache;
 use crate::state_witness::PartialWitnessSenderForNetworkInput;
@@ -37,7 +38,9 @@ use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::state_sync::ShardStateSyncResponse;
 use near_primitives::state_sync::ShardStateSyncResponseV2;
 use near_primitives::types::AccountId;
-use std::collections::HashSet;
+use rand::Rng;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -272,6 +275,52 @@ impl ActorHandler {
         conn
     }
 
+    /// Replays the inbound messages of a traffic recording, see `crate::recorder`.
+    /// The keys of the recorded peers are unknown, so each of them is
+    /// impersonated by a new inbound connection, which makes its own
+    /// handshake, and the recorded handshakes are skipped. The clock is
+    /// advanced between the messages as in the recording.
+    ///
+    /// Returns the connections, which are closed once dropped.
+    pub async fn replay(
+        &self,
+        clock: &time::FakeClock,
+        rng: &mut impl Rng,
+        chain: &Arc<data::Chain>,
+        records: impl IntoIterator<Item = recorder::Record>,
+    ) -> Vec<peer::testonly::PeerHandle> {
+        let mut peers = HashMap::new();
+        let mut last_time = None;
+        for record in records {
+            if record.direction != recorder::Direction::Inbound {
+                continue;
+            }
+            let msg = record.peer_message().unwrap();
+            if matches!(
+                msg,
+                PeerMessage::Tier1Handshake(_)
+                    | PeerMessage::Tier2Handshake(_)
+                    | PeerMessage::HandshakeFailure(..)
+                    | PeerMessage::LastEdge(_)
+            ) {
+                continue;
+            }
+            if let Some(last_time) = last_time {
+                clock.advance(time::Duration::nanoseconds((record.time - last_time).max(0)));
+            }
+            last_time = Some(record.time);
+            let peer = match peers.entry(record.peer_id) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let conn = self.start_inbound(chain.clone(), chain.make_config(rng)).await;
+                    e.insert(conn.handshake(&clock.clock()).await)
+                }
+            };
+            peer.send(msg).await;
+        }
+        peers.into_values().collect()
+    }
+
     pub async fn start_outbound(
         &self,
         chain: Arc<data::Chain>,
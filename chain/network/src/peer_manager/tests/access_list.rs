@@ -0,0 +1,73 @@
+use crate::access_list::Rule;
+use crate::debug::{PeerAccessAction, PeerAccessControl};
+use crate::network_protocol::testonly as data;
+use crate::peer::peer_actor::ClosingReason;
+use crate::peer_manager::testonly::start as start_pm;
+use crate::private_actix::RegisterPeerError;
+use crate::tcp;
+use crate::testonly::{abort_on_panic, make_rng};
+use near_async::time;
+use near_store::db::TestDB;
+use std::sync::Arc;
+
+// Peers denied in the access list file are refused, until the file changes.
+#[tokio::test]
+async fn access_list_file() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("access_list.json");
+
+    let peer_cfg = chain.make_config(rng);
+    std::fs::write(&path, format!(r#"{{"deny": ["{}"]}}"#, peer_cfg.node_id())).unwrap();
+    let mut cfg = chain.make_config(rng);
+    cfg.access_list_file = Some(path.clone());
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+
+    tracing::info!(target:"test", "a denied peer is refused");
+    let conn = pm.start_inbound(chain.clone(), peer_cfg.clone()).await;
+    assert_eq!(
+        ClosingReason::RejectedByPeerManager(RegisterPeerError::Blacklisted),
+        conn.manager_fail_handshake(&clock.clock()).await
+    );
+
+    tracing::info!(target:"test", "the peer is accepted once the file is emptied");
+    std::fs::write(&path, "{}").unwrap();
+    pm.with_state(|s| async move { s.reload_access_list().unwrap() }).await;
+    let conn = pm.start_inbound(chain.clone(), peer_cfg).await;
+    conn.handshake(&clock.clock()).await;
+}
+
+// Banning a connected peer through the admin API disconnects it.
+#[tokio::test]
+async fn admin_ban() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let pm0 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let pm1 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let id1 = pm1.cfg.node_id();
+    pm0.connect_to(&pm1.peer_info(), tcp::Tier::T2).await;
+    pm0.wait_for_routing_table(&[(id1.clone(), vec![id1.clone()])]).await;
+
+    let control = |action| PeerAccessControl { action, rule: Rule::Peer(id1.clone()) };
+
+    tracing::info!(target:"test", "ban {id1}");
+    let request = control(PeerAccessAction::Ban);
+    let view = pm0.with_state(|s| async move { s.peer_access_control(request) }).await;
+    assert_eq!(view.banned, vec![id1.to_string()]);
+    pm0.wait_for_routing_table(&[]).await;
+
+    tracing::info!(target:"test", "unban {id1} and reconnect");
+    let request = control(PeerAccessAction::Unban);
+    let view = pm0.with_state(|s| async move { s.peer_access_control(request) }).await;
+    assert!(view.banned.is_empty());
+    pm0.connect_to(&pm1.peer_info(), tcp::Tier::T2).await;
+    pm0.wait_for_routing_table(&[(id1.clone(), vec![id1.clone()])]).await;
+}
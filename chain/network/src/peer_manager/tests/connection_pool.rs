@@ -1,7 +1,8 @@
+use crate::config;
 use crate::config::SocketOptions;
 use crate::network_protocol::testonly as data;
-use crate::network_protocol::PeerMessage;
 use crate::network_protocol::{Encoding, Handshake, OwnedAccount, PartialEdgeInfo};
+use crate::network_protocol::{PeerInfo, PeerMessage};
 use crate::peer::peer_actor::ClosingReason;
 use crate::peer_manager;
 use crate::peer_manager::connection;
@@ -9,6 +10,7 @@ use crate::peer_manager::network_state::LIMIT_PENDING_PEERS;
 use crate::peer_manager::peer_manager_actor::Event as PME;
 use crate::peer_manager::testonly::Event;
 use crate::private_actix::RegisterPeerError;
+use crate::rate_limits::admission;
 use crate::tcp;
 use crate::testonly::make_rng;
 use crate::testonly::stream::Stream;
@@ -99,6 +101,7 @@ async fn loop_connection() {
             sender_peer_id: pm.cfg.node_id(),
             target_peer_id: pm.cfg.node_id(),
             sender_listen_port: Some(port),
+            sender_listen_addrs: vec![],
             sender_chain_info: chain.get_peer_chain_info(),
             partial_edge_info: PartialEdgeInfo::new(
                 &pm.cfg.node_id(),
@@ -107,6 +110,10 @@ async fn loop_connection() {
                 &pm.cfg.node_key,
             ),
             owned_account: None,
+            sender_rpc_url: None,
+            encryption_offer: None,
+            supported_compressions: vec![],
+            admission_proof: None,
         }))
         .await;
     let reason = events
@@ -161,6 +168,7 @@ async fn owned_account_mismatch() {
             sender_peer_id: cfg.node_id(),
             target_peer_id: pm.cfg.node_id(),
             sender_listen_port: Some(port),
+            sender_listen_addrs: vec![],
             sender_chain_info: chain.get_peer_chain_info(),
             partial_edge_info: PartialEdgeInfo::new(
                 &cfg.node_id(),
@@ -177,6 +185,10 @@ async fn owned_account_mismatch() {
                 }
                 .sign(&signer),
             ),
+            sender_rpc_url: None,
+            encryption_offer: None,
+            supported_compressions: vec![],
+            admission_proof: None,
         }))
         .await;
     let reason = events
@@ -289,6 +301,7 @@ async fn invalid_edge() {
                 sender_peer_id: cfg.node_id(),
                 target_peer_id: pm.cfg.node_id(),
                 sender_listen_port: Some(port),
+                sender_listen_addrs: vec![],
                 sender_chain_info: chain.get_peer_chain_info(),
                 partial_edge_info: edge.clone(),
                 owned_account: Some(
@@ -299,6 +312,10 @@ async fn invalid_edge() {
                     }
                     .sign(&signer),
                 ),
+                sender_rpc_url: None,
+                encryption_offer: None,
+                supported_compressions: vec![],
+                admission_proof: None,
             };
             let handshake = match tier {
                 tcp::Tier::T1 => PeerMessage::Tier1Handshake(handshake),
@@ -325,3 +342,111 @@ async fn invalid_edge() {
         }
     }
 }
+
+#[tokio::test]
+async fn handshake_admission() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = chain.make_config(rng);
+    cfg.admission.handshake_rate = Some(admission::HandshakeRate { per_sec: 0.001, burst: 1 });
+    cfg.admission.challenge_difficulty = 8;
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        cfg,
+        chain.clone(),
+    )
+    .await;
+    // Over the rate, the peer has to solve a challenge, but gets in.
+    for _ in 0..3 {
+        let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+        conn.handshake(&clock.clock()).await;
+    }
+
+    // Without challenges, the handshakes over the rate are refused.
+    let mut cfg = chain.make_config(rng);
+    cfg.admission.handshake_rate = Some(admission::HandshakeRate { per_sec: 0.001, burst: 1 });
+    cfg.admission.challenge_difficulty = 0;
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        cfg,
+        chain.clone(),
+    )
+    .await;
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    conn.handshake(&clock.clock()).await;
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    assert_eq!(ClosingReason::TooManyHandshakes, conn.manager_fail_handshake(&clock.clock()).await);
+}
+
+#[tokio::test]
+async fn inbound_connections_per_ip() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = chain.make_config(rng);
+    cfg.admission.max_inbound_connections_per_ip = Some(1);
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        cfg,
+        chain.clone(),
+    )
+    .await;
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    let _peer = conn.handshake(&clock.clock()).await;
+    // All the test connections come from 127.0.0.1.
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    assert_eq!(
+        ClosingReason::TooManyInboundFromIp,
+        conn.manager_fail_handshake(&clock.clock()).await
+    );
+}
+
+#[tokio::test]
+async fn extra_listen_addrs() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    // pm0 listens on IPv6 and, besides, on IPv4.
+    let extra_addr = tcp::ListenerAddr::reserve_for_test_on(std::net::Ipv4Addr::LOCALHOST.into());
+    let mut cfg = chain.make_config(rng);
+    cfg.extra_listen_addrs = vec![config::ListenAddr { addr: extra_addr, advertise: true }];
+    let pm0 = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        cfg,
+        chain.clone(),
+    )
+    .await;
+    let pm1 = peer_manager::testonly::start(
+        clock.clock(),
+        near_store::db::TestDB::new(),
+        chain.make_config(rng),
+        chain.clone(),
+    )
+    .await;
+
+    tracing::info!(target:"test", "connect pm1 to pm0 over IPv4");
+    let pm0_info = pm0.peer_info();
+    pm1.connect_to(&PeerInfo { addr: Some(*extra_addr), ..pm0_info.clone() }, tcp::Tier::T2).await;
+    // pm1 would dial pm0 back over IPv4, i.e. on the extra address, rather
+    // than on the IPv4 counterpart of the main address.
+    let addr = pm1
+        .with_state(move |s| async move {
+            s.tier2.load().ready.get(&pm0_info.id).map(|conn| conn.peer_info.addr)
+        })
+        .await;
+    assert_eq!(Some(Some(*extra_addr)), addr);
+}
@@ -0,0 +1,65 @@
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::PeerMessage;
+use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::peer_manager::testonly::start as start_pm;
+use crate::peer_manager::testonly::{ActorHandler, Event};
+use crate::tcp;
+use crate::testonly::{abort_on_panic, make_rng, Rng};
+use near_async::time;
+use near_primitives::network::PeerId;
+use near_store::db::TestDB;
+use std::sync::Arc;
+
+/// Runs a lookup on `pm` and waits until it processes a response.
+async fn lookup(clock: &time::Clock, pm: &ActorHandler) {
+    let mut events = pm.events.from_now();
+    let clock = clock.clone();
+    pm.with_state(|s| async move { s.dht_lookup(&clock) }).await;
+    events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::MessageProcessed(tcp::Tier::T2, PeerMessage::DhtNodes(_))) => {
+                Some(())
+            }
+            _ => None,
+        })
+        .await;
+}
+
+async fn knows_peer(pm: &ActorHandler, peer_id: &PeerId) -> bool {
+    let peer_id = peer_id.clone();
+    pm.with_state(|s| async move { s.peer_store.load().contains_key(&peer_id) }).await
+}
+
+// Nodes connected only to a common node learn about each other through the DHT,
+// and remember each other across restarts.
+#[tokio::test]
+async fn discover_through_dht() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let make_config = |rng: &mut Rng| {
+        let mut cfg = chain.make_config(rng);
+        cfg.dht.enabled = true;
+        cfg
+    };
+
+    let pm0 = start_pm(clock.clock(), TestDB::new(), make_config(rng), chain.clone()).await;
+    let db1 = TestDB::new();
+    let pm1 = start_pm(clock.clock(), db1.clone(), make_config(rng), chain.clone()).await;
+    let pm2 = start_pm(clock.clock(), TestDB::new(), make_config(rng), chain.clone()).await;
+    let id2 = pm2.cfg.node_id();
+    pm1.connect_to(&pm0.peer_info(), tcp::Tier::T2).await;
+    pm2.connect_to(&pm0.peer_info(), tcp::Tier::T2).await;
+
+    tracing::info!(target:"test", "pm2 publishes its record to pm0");
+    lookup(&clock.clock(), &pm2).await;
+    tracing::info!(target:"test", "pm1 learns about pm2 from pm0");
+    lookup(&clock.clock(), &pm1).await;
+    assert!(knows_peer(&pm1, &id2).await);
+
+    tracing::info!(target:"test", "a node started with pm1's DB knows about pm2 too");
+    let pm3 = start_pm(clock.clock(), db1, make_config(rng), chain.clone()).await;
+    assert!(knows_peer(&pm3, &id2).await);
+}
@@ -0,0 +1,161 @@
+use crate::config::SocketOptions;
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::{
+    Edge, Encoding, EncryptionOffer, Handshake, PartialEdgeInfo, PeerMessage,
+};
+use crate::peer::encryption::EncryptionMode;
+use crate::peer::peer_actor::ClosingReason;
+use crate::peer_manager::testonly::start as start_pm;
+use crate::tcp;
+use crate::testonly::{abort_on_panic, make_rng, stream};
+use near_async::time;
+use near_primitives::network::PeerId;
+use near_primitives::version;
+use near_store::db::TestDB;
+use rand::Rng as _;
+use std::sync::Arc;
+
+// Test routing over encrypted connections, which needs the routing table
+// updates to get through.
+#[tokio::test]
+async fn encrypted_routing() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    tracing::info!(target:"test", "connect three nodes in a line");
+    let mut pms = vec![];
+    for mode in [EncryptionMode::Enabled, EncryptionMode::Required, EncryptionMode::Enabled] {
+        let mut cfg = chain.make_config(rng);
+        cfg.encryption = mode;
+        pms.push(start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await);
+    }
+    pms[0].connect_to(&pms[1].peer_info(), tcp::Tier::T2).await;
+    pms[2].connect_to(&pms[1].peer_info(), tcp::Tier::T2).await;
+
+    let id0 = pms[0].cfg.node_id();
+    let id1 = pms[1].cfg.node_id();
+    let id2 = pms[2].cfg.node_id();
+
+    tracing::info!(target:"test", "wait for {id0} routing table");
+    pms[0]
+        .wait_for_routing_table(&[
+            (id1.clone(), vec![id1.clone()]),
+            (id2.clone(), vec![id1.clone()]),
+        ])
+        .await;
+    tracing::info!(target:"test", "wait for {id2} routing table");
+    pms[2]
+        .wait_for_routing_table(&[
+            (id0.clone(), vec![id1.clone()]),
+            (id1.clone(), vec![id1.clone()]),
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn encryption_required() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = chain.make_config(rng);
+    cfg.encryption = EncryptionMode::Required;
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+
+    tracing::info!(target:"test", "a peer not supporting encryption is refused");
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    assert_eq!(
+        ClosingReason::EncryptionRequired,
+        conn.manager_fail_handshake(&clock.clock()).await
+    );
+
+    tracing::info!(target:"test", "a peer supporting encryption is accepted");
+    let mut cfg = chain.make_config(rng);
+    cfg.encryption = EncryptionMode::Enabled;
+    let conn = pm.start_inbound(chain.clone(), cfg).await;
+    conn.handshake(&clock.clock()).await;
+}
+
+#[tokio::test]
+async fn encryption_optional() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = chain.make_config(rng);
+    cfg.encryption = EncryptionMode::Enabled;
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+
+    tracing::info!(target:"test", "a peer not supporting encryption is accepted");
+    let conn = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    conn.handshake(&clock.clock()).await;
+}
+
+// An encryption offer has to be signed together with the edge proposed in the
+// same Handshake, otherwise the Handshake is rejected.
+#[tokio::test]
+async fn encryption_offer_bound_to_edge() {
+    abort_on_panic();
+    let mut rng = make_rng(921853235);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = chain.make_config(rng);
+    cfg.encryption = EncryptionMode::Enabled;
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+
+    for (offer_nonce_delta, accepted) in [(2, false), (0, true)] {
+        let stream =
+            tcp::Stream::connect(&pm.peer_info(), tcp::Tier::T2, &SocketOptions::default())
+                .await
+                .unwrap();
+        let mut stream = stream::Stream::new(Some(Encoding::Proto), stream);
+        let peer_key = data::make_secret_key(rng);
+        let peer_id = PeerId::new(peer_key.public_key());
+        let nonce = Edge::create_fresh_nonce(&clock.clock());
+        let offer = EncryptionOffer::new(
+            &peer_id,
+            &pm.cfg.node_id(),
+            nonce + offer_nonce_delta,
+            rng.gen(),
+            &peer_key,
+        );
+        stream
+            .write(&PeerMessage::Tier2Handshake(Handshake {
+                protocol_version: version::PROTOCOL_VERSION,
+                oldest_supported_version: version::PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+                sender_peer_id: peer_id.clone(),
+                target_peer_id: pm.cfg.node_id(),
+                sender_listen_port: Some(24567),
+                sender_listen_addrs: vec![],
+                sender_chain_info: chain.get_peer_chain_info(),
+                partial_edge_info: PartialEdgeInfo::new(
+                    &peer_id,
+                    &pm.cfg.node_id(),
+                    nonce,
+                    &peer_key,
+                ),
+                owned_account: None,
+                sender_rpc_url: None,
+                encryption_offer: Some(offer),
+                supported_compressions: vec![],
+                admission_proof: None,
+            }))
+            .await;
+        match stream.read().await {
+            Ok(PeerMessage::Tier2Handshake(got)) if accepted => {
+                assert!(got.encryption_offer.unwrap().verify(&pm.cfg.node_id(), &peer_id, nonce));
+            }
+            Err(err) if !accepted && err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+            got => panic!("accepted = {accepted}, got = {got:?}"),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::config::SocketOptions;
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::{Encoding, Handshake, PartialEdgeInfo, PeerMessage};
+use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::peer_manager::testonly::start as start_pm;
+use crate::peer_manager::testonly::Event;
+use crate::rate_limits::message_size;
+use crate::tcp;
+use crate::testonly::{abort_on_panic, make_rng, stream};
+use near_async::time;
+use near_primitives::network::PeerId;
+use near_primitives::version;
+use near_store::db::TestDB;
+use std::sync::Arc;
+
+// A message over the limit of its kind is dropped, but the connection is kept.
+#[tokio::test]
+async fn oversize_message_rejected() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = chain.make_config(rng);
+    cfg.message_size_limits = message_size::Config { block: 1, ..Default::default() };
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+    let peer = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    let peer = peer.handshake(&clock.clock()).await;
+
+    let mut events = pm.events.from_now();
+    peer.send(PeerMessage::Block(chain.blocks[5].clone())).await;
+    let headers = PeerMessage::BlockHeaders(chain.get_block_headers());
+    peer.send(headers.clone()).await;
+    events
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::MessageProcessed(_, PeerMessage::Block(_))) => {
+                panic!("oversize block got processed")
+            }
+            Event::PeerManager(PME::MessageProcessed(_, got)) if got == headers => Some(()),
+            _ => None,
+        })
+        .await;
+}
+
+// A Handshake over the limit closes the connection instead of leaving the peer
+// waiting for a response.
+#[tokio::test]
+async fn oversize_handshake_closes_connection() {
+    abort_on_panic();
+    let mut rng = make_rng(921853234);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = chain.make_config(rng);
+    cfg.message_size_limits = message_size::Config { handshake: 100, ..Default::default() };
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+
+    let stream = tcp::Stream::connect(&pm.peer_info(), tcp::Tier::T2, &SocketOptions::default())
+        .await
+        .unwrap();
+    let mut stream = stream::Stream::new(Some(Encoding::Proto), stream);
+    let peer_key = data::make_secret_key(rng);
+    let peer_id = PeerId::new(peer_key.public_key());
+    let handshake = PeerMessage::Tier2Handshake(Handshake {
+        protocol_version: version::PROTOCOL_VERSION,
+        oldest_supported_version: version::PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+        sender_peer_id: peer_id.clone(),
+        target_peer_id: pm.cfg.node_id(),
+        sender_listen_port: Some(24567),
+        sender_listen_addrs: vec![],
+        sender_chain_info: chain.get_peer_chain_info(),
+        partial_edge_info: PartialEdgeInfo::new(&peer_id, &pm.cfg.node_id(), 1, &peer_key),
+        owned_account: None,
+        sender_rpc_url: None,
+        encryption_offer: None,
+        supported_compressions: vec![],
+        admission_proof: None,
+    });
+    assert!(handshake.serialize(Encoding::Proto).len() > 100);
+    stream.write(&handshake).await;
+    match stream.read().await {
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+        got => panic!("got = {got:?}, want UnexpectedEof"),
+    }
+}
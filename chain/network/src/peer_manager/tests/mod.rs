@@ -1,8 +1,14 @@
+mod access_list;
 mod accounts_data;
 mod connection_pool;
+mod dht;
+mod encryption;
 mod fuzzers;
+mod message_size;
 mod nonce;
+mod recorder;
 mod routing;
+mod sim;
 mod snapshot_hosts;
 mod tier1;
 mod tier2;
@@ -0,0 +1,70 @@
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::PeerMessage;
+use crate::peer_manager::peer_manager_actor::Event as PME;
+use crate::peer_manager::testonly::start as start_pm;
+use crate::peer_manager::testonly::{ActorHandler, Event};
+use crate::recorder;
+use crate::testonly::{abort_on_panic, make_rng};
+use near_async::time;
+use near_store::db::TestDB;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Waits for `pm` to process `want`, received after the call.
+fn wait_for_message(pm: &ActorHandler, want: &PeerMessage) -> impl Future<Output = ()> {
+    let mut events = pm.events.from_now();
+    let want = want.clone();
+    async move {
+        events
+            .recv_until(|ev| match ev {
+                Event::PeerManager(PME::MessageProcessed(_, got)) if got == want => Some(()),
+                _ => None,
+            })
+            .await;
+    }
+}
+
+// The messages recorded by one node, replayed to another, get processed by it.
+#[tokio::test]
+async fn record_and_replay() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("traffic");
+
+    let mut cfg = chain.make_config(rng);
+    cfg.record_traffic = Some(recorder::Config { file: path.clone(), max_file_bytes: u64::MAX });
+    let pm = start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await;
+
+    tracing::info!(target:"test", "a peer sends a block to the recording node");
+    let peer = pm.start_inbound(chain.clone(), chain.make_config(rng)).await;
+    let peer = peer.handshake(&clock.clock()).await;
+    let want = PeerMessage::Block(chain.blocks[5].clone());
+    let processed = wait_for_message(&pm, &want);
+    peer.send(want.clone()).await;
+    processed.await;
+    pm.with_state(|s| async move { s.recorder.get().unwrap().flush() }).await;
+
+    let records = recorder::Reader::new(std::fs::File::open(&path).unwrap())
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    let received = |want: &PeerMessage| {
+        records.iter().any(|r| {
+            r.direction == recorder::Direction::Inbound
+                && r.peer_id.as_ref() == Some(&peer.cfg.id())
+                && &r.peer_message().unwrap() == want
+        })
+    };
+    assert!(received(&want));
+    assert!(records.iter().any(|r| r.direction == recorder::Direction::Outbound
+        && matches!(r.peer_message().unwrap(), PeerMessage::Tier2Handshake(_))));
+
+    tracing::info!(target:"test", "the block gets processed by another node");
+    let pm = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let processed = wait_for_message(&pm, &want);
+    let _peers = pm.replay(&clock, rng, &chain, records).await;
+    processed.await;
+}
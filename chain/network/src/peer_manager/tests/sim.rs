@@ -0,0 +1,71 @@
+use crate::network_protocol::testonly as data;
+use crate::peer::peer_actor::PeerActor;
+use crate::peer_manager::testonly::start as start_pm;
+use crate::peer_manager::testonly::ActorHandler;
+use crate::sim;
+use crate::tcp;
+use crate::testonly::{abort_on_panic, make_rng};
+use near_async::time;
+use near_store::db::TestDB;
+use std::sync::Arc;
+
+/// Makes `from` connect to `to` over the simulated network.
+async fn connect(
+    clock: &time::Clock,
+    from: &ActorHandler,
+    to: &ActorHandler,
+) -> anyhow::Result<()> {
+    let clock = clock.clone();
+    let peer_info = to.peer_info();
+    from.with_state(move |s| async move {
+        let stream = s.dial(&peer_info, tcp::Tier::T2).await?;
+        PeerActor::spawn_and_handshake(clock, stream, None, s).await?;
+        anyhow::Ok(())
+    })
+    .await
+}
+
+// PeerManagers connected over a simulated network route through each other,
+// and can't connect across a partition.
+#[tokio::test]
+async fn simulated_network() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let net = sim::Network::new(clock.clock(), 8123);
+
+    let mut pms = vec![];
+    for _ in 0..3 {
+        let mut cfg = chain.make_config(rng);
+        cfg.simulation = Some(net.clone());
+        pms.push(start_pm(clock.clock(), TestDB::new(), cfg, chain.clone()).await);
+    }
+    let ids: Vec<_> = pms.iter().map(|pm| pm.cfg.node_id()).collect();
+    let addrs: Vec<_> = pms.iter().map(|pm| **pm.cfg.node_addr.as_ref().unwrap()).collect();
+
+    tracing::info!(target:"test", "connect the nodes in a line");
+    connect(&clock.clock(), &pms[0], &pms[1]).await.unwrap();
+    connect(&clock.clock(), &pms[1], &pms[2]).await.unwrap();
+    pms[0]
+        .wait_for_routing_table(&[
+            (ids[1].clone(), vec![ids[1].clone()]),
+            (ids[2].clone(), vec![ids[1].clone()]),
+        ])
+        .await;
+
+    tracing::info!(target:"test", "the nodes across a partition can't connect");
+    net.partition(&[addrs[2]], &addrs);
+    assert!(connect(&clock.clock(), &pms[0], &pms[2]).await.is_err());
+
+    tracing::info!(target:"test", "they can once it is healed");
+    net.heal();
+    connect(&clock.clock(), &pms[0], &pms[2]).await.unwrap();
+    pms[0]
+        .wait_for_routing_table(&[
+            (ids[1].clone(), vec![ids[1].clone()]),
+            (ids[2].clone(), vec![ids[2].clone()]),
+        ])
+        .await;
+}
@@ -1,5 +1,6 @@
 use crate::broadcast;
 use crate::network_protocol::testonly as data;
+use crate::peer::peer_actor::ClosingReason;
 use crate::peer_manager::connection_store::STORED_CONNECTIONS_MIN_DURATION;
 use crate::peer_manager::network_state::RECONNECT_ATTEMPT_INTERVAL;
 use crate::peer_manager::peer_manager_actor::Event as PME;
@@ -295,3 +296,44 @@ async fn test_reconnect_after_restart_outbound_side_multi() {
     pm0.wait_for_direct_connection(id3.clone()).await;
     pm0.wait_for_direct_connection(id4.clone()).await;
 }
+
+#[tokio::test]
+async fn test_reconnect_after_local_ip_change() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let pm0 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let pm1 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+
+    let id1 = pm1.cfg.node_id().clone();
+
+    tracing::info!(target:"test", "connect pm0 to pm1");
+    pm0.connect_to(&pm1.peer_info(), tcp::Tier::T2).await;
+
+    tracing::info!(target:"test", "the local IP of pm0 changes");
+    let mut pm0_ev = pm0.events.from_now();
+    let mut pm1_ev = pm1.events.from_now();
+    pm0.with_state({
+        let clock = clock.clock();
+        move |s| async move { s.local_ip_changed(&clock, "127.0.0.1".parse().unwrap()).await }
+    })
+    .await;
+    pm0_ev
+        .recv_until(|ev| match ev {
+            Event::PeerManager(PME::ConnectionClosed(ev))
+                if ev.reason == ClosingReason::LocalIpChanged =>
+            {
+                Some(())
+            }
+            _ => None,
+        })
+        .await;
+    wait_for_connection_closed(&mut pm1_ev).await;
+
+    tracing::info!(target:"test", "check that pm0 reconnects without waiting for the connection store poll");
+    clock.advance(RECONNECT_ATTEMPT_INTERVAL);
+    pm0.wait_for_direct_connection(id1.clone()).await;
+}
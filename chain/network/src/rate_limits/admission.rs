@@ -0,0 +1,209 @@
+//! Admission of inbound connections, protecting the node against floods of
+//! connections and handshakes.
+//!
+//! Every inbound handshake costs the node a signature verification and
+//! possibly a key agreement, so the node processes only so many per second.
+//! Over that rate a peer gets an `AdmissionChallenge` in response to its
+//! handshake, and the handshake is processed once the peer resends it with a
+//! solution. Peers following the protocol still get in, just a bit later,
+//! while a flood costs the attacker far more CPU than it costs the node.
+//!
+//! Besides, the node may cap the number of inbound connections coming from a
+//! single IP, so that a single host can't take all the inbound slots.
+
+use crate::network_protocol::AdmissionChallenge;
+use crate::rate_limits::token_bucket::TokenBucket;
+use near_async::time;
+use near_primitives::hash::CryptoHash;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+/// Highest difficulty of an `AdmissionChallenge` which the node is willing
+/// to solve, i.e. about 16M hashes.
+pub(crate) const MAX_SOLVED_DIFFICULTY: u8 = 24;
+
+/// Rate of inbound handshakes.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HandshakeRate {
+    pub per_sec: f32,
+    /// How many handshakes may be processed at once after a period of quiet.
+    pub burst: u32,
+}
+
+/// Admission of inbound connections.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Rate of inbound handshakes, over all peers, processed without a
+    /// challenge. `None` processes all of them right away.
+    pub handshake_rate: Option<HandshakeRate>,
+    /// Number of leading zero bits required of the solutions of the
+    /// challenges. With 0 the handshakes over the rate are refused instead.
+    pub challenge_difficulty: u8,
+    /// Cap on the inbound connections from a single IP, both established and
+    /// in the middle of the handshake. Off by default, since many nodes may
+    /// share an IP behind a NAT.
+    pub max_inbound_connections_per_ip: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            handshake_rate: Some(HandshakeRate { per_sec: 20., burst: 100 }),
+            challenge_difficulty: 16,
+            max_inbound_connections_per_ip: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(rate) = &self.handshake_rate {
+            TokenBucket::validate_refill_rate(rate.per_sec)?;
+            if rate.per_sec == 0. || rate.burst == 0 {
+                anyhow::bail!("admission.handshake_rate must be positive, got {rate:?}");
+            }
+        }
+        if self.challenge_difficulty > MAX_SOLVED_DIFFICULTY {
+            anyhow::bail!(
+                "admission.challenge_difficulty can be at most {MAX_SOLVED_DIFFICULTY}, got {}",
+                self.challenge_difficulty
+            );
+        }
+        if self.max_inbound_connections_per_ip == Some(0) {
+            anyhow::bail!("admission.max_inbound_connections_per_ip must be positive");
+        }
+        Ok(())
+    }
+}
+
+/// What to do with an inbound handshake.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Verdict {
+    Process,
+    Challenge(AdmissionChallenge),
+    Refuse,
+}
+
+/// Admission state of the whole node, shared by all connections.
+pub(crate) struct Admission {
+    clock: time::Clock,
+    challenge_difficulty: u8,
+    max_inbound_connections_per_ip: Option<usize>,
+    handshakes: Option<Mutex<TokenBucket>>,
+    inbound_per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Admission {
+    pub fn new(clock: &time::Clock, config: &Config) -> Self {
+        Self {
+            clock: clock.clone(),
+            challenge_difficulty: config.challenge_difficulty,
+            max_inbound_connections_per_ip: config.max_inbound_connections_per_ip,
+            handshakes: config.handshake_rate.map(|rate| {
+                Mutex::new(
+                    TokenBucket::new(rate.burst, rate.burst, rate.per_sec, clock.now()).unwrap(),
+                )
+            }),
+            inbound_per_ip: Default::default(),
+        }
+    }
+
+    /// Decides what to do with an inbound handshake which doesn't carry a
+    /// solution of a challenge.
+    pub fn handshake(&self) -> Verdict {
+        let Some(handshakes) = &self.handshakes else { return Verdict::Process };
+        if handshakes.lock().unwrap().acquire(1, self.clock.now()) {
+            return Verdict::Process;
+        }
+        if self.challenge_difficulty == 0 {
+            return Verdict::Refuse;
+        }
+        Verdict::Challenge(AdmissionChallenge {
+            seed: CryptoHash::hash_bytes(&rand::random::<[u8; 32]>()),
+            difficulty: self.challenge_difficulty,
+        })
+    }
+
+    /// Takes a slot for an inbound connection from `ip`, which is released
+    /// once the returned guard is dropped. Returns `None` if `ip` has used up
+    /// its slots.
+    pub fn inbound_connection(&self, ip: IpAddr) -> Option<InboundSlot> {
+        let mut inbound = self.inbound_per_ip.lock().unwrap();
+        let n = inbound.entry(ip).or_default();
+        if self.max_inbound_connections_per_ip.is_some_and(|max| *n >= max) {
+            return None;
+        }
+        *n += 1;
+        Some(InboundSlot { ip, inbound_per_ip: self.inbound_per_ip.clone() })
+    }
+}
+
+/// Slot of an inbound connection from an IP, see `Admission::inbound_connection`.
+pub(crate) struct InboundSlot {
+    ip: IpAddr,
+    inbound_per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Drop for InboundSlot {
+    fn drop(&mut self) {
+        let mut inbound = self.inbound_per_ip.lock().unwrap();
+        if let Some(n) = inbound.get_mut(&self.ip) {
+            *n -= 1;
+            if *n == 0 {
+                inbound.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Admission, Config, HandshakeRate, Verdict};
+    use near_async::time;
+    use near_primitives::network::PeerId;
+
+    #[test]
+    fn test_handshake_rate() {
+        let clock = time::FakeClock::default();
+        let config = Config {
+            handshake_rate: Some(HandshakeRate { per_sec: 1., burst: 2 }),
+            challenge_difficulty: 8,
+            max_inbound_connections_per_ip: None,
+        };
+        let admission = Admission::new(&clock.clock(), &config);
+        assert_eq!(admission.handshake(), Verdict::Process);
+        assert_eq!(admission.handshake(), Verdict::Process);
+        let Verdict::Challenge(challenge) = admission.handshake() else {
+            panic!("expected a challenge")
+        };
+        let peer_id = PeerId::random();
+        let proof = challenge.solve(&peer_id);
+        assert!(challenge.verify(&peer_id, &proof));
+        // The solution is bound to the peer which found it.
+        assert!(!challenge.verify(&PeerId::random(), &proof));
+        clock.advance(time::Duration::seconds(1));
+        assert_eq!(admission.handshake(), Verdict::Process);
+
+        let admission =
+            Admission::new(&clock.clock(), &Config { challenge_difficulty: 0, ..config });
+        admission.handshake();
+        admission.handshake();
+        assert_eq!(admission.handshake(), Verdict::Refuse);
+    }
+
+    #[test]
+    fn test_inbound_connections_per_ip() {
+        let clock = time::FakeClock::default();
+        let config = Config { max_inbound_connections_per_ip: Some(2), ..Config::default() };
+        let admission = Admission::new(&clock.clock(), &config);
+        let ip = "1.2.3.4".parse().unwrap();
+        let first = admission.inbound_connection(ip).unwrap();
+        let _second = admission.inbound_connection(ip).unwrap();
+        assert!(admission.inbound_connection(ip).is_none());
+        assert!(admission.inbound_connection("1.2.3.5".parse().unwrap()).is_some());
+        drop(first);
+        assert!(admission.inbound_connection(ip).is_some());
+    }
+}
@@ -0,0 +1,205 @@
+//! Caps on the bandwidth used by the node, per peer and in total.
+//!
+//! Each cap is a token bucket counting bytes, which may go into debt: a
+//! message is let through whenever the bucket isn't empty, however large it
+//! is, and the bucket then has to refill past zero before the next one goes.
+//! This way a state part larger than the burst is still sent, just not more
+//! often than the rate allows.
+//!
+//! Critical and data messages are uploaded right away and only count against
+//! the caps, sync and background ones wait for them, see `qos` for the order
+//! in which the messages go.  Downloads are capped by delaying reading from
+//! the connection, which the sender notices as TCP backpressure.
+
+use ::time::ext::InstantExt as _;
+use near_async::time;
+use std::sync::{Arc, Mutex};
+
+/// A cap on bandwidth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Limit {
+    pub bytes_per_sec: u64,
+    /// How many bytes may go through at once after a period of quiet.
+    pub burst_bytes: u64,
+}
+
+/// Bandwidth caps of the node.  No caps by default.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Cap on the bytes sent to a single peer.
+    pub peer_upload: Option<Limit>,
+    /// Cap on the bytes received from a single peer.
+    pub peer_download: Option<Limit>,
+    /// Cap on the bytes sent to all peers together.
+    pub upload: Option<Limit>,
+    /// Cap on the bytes received from all peers together.
+    pub download: Option<Limit>,
+}
+
+impl Config {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, limit) in [
+            ("peer_upload", &self.peer_upload),
+            ("peer_download", &self.peer_download),
+            ("upload", &self.upload),
+            ("download", &self.download),
+        ] {
+            if let Some(limit) = limit {
+                if limit.bytes_per_sec == 0 || limit.burst_bytes == 0 {
+                    anyhow::bail!("bandwidth_limits.{name} must be positive, got {limit:?}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Token bucket of bytes which may go into debt.
+struct Bucket {
+    limit: Limit,
+    tokens: f64,
+    updated: time::Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: time::Instant) {
+        let elapsed = now.signed_duration_since(self.updated).as_seconds_f64().max(0.0);
+        self.tokens = (self.tokens + elapsed * self.limit.bytes_per_sec as f64)
+            .min(self.limit.burst_bytes as f64);
+        self.updated = now;
+    }
+
+    fn delay(&mut self, now: time::Instant) -> time::Duration {
+        self.refill(now);
+        if self.tokens >= 0.0 {
+            return time::Duration::ZERO;
+        }
+        time::Duration::seconds_f64(-self.tokens / self.limit.bytes_per_sec as f64)
+    }
+}
+
+/// A bandwidth cap shared by whoever consumes it.  Without a limit it lets
+/// everything through.
+pub(crate) struct Limiter {
+    bucket: Option<Mutex<Bucket>>,
+}
+
+impl Limiter {
+    pub fn new(clock: &time::Clock, limit: Option<Limit>) -> Self {
+        Self {
+            bucket: limit.map(|limit| {
+                Mutex::new(Bucket { limit, tokens: limit.burst_bytes as f64, updated: clock.now() })
+            }),
+        }
+    }
+
+    /// How long to wait before the next message may go through.
+    pub fn delay(&self, now: time::Instant) -> time::Duration {
+        self.bucket
+            .as_ref()
+            .map_or(time::Duration::ZERO, |bucket| bucket.lock().unwrap().delay(now))
+    }
+
+    /// Counts `bytes` against the cap, without waiting.
+    pub fn consume(&self, now: time::Instant, bytes: usize) {
+        if let Some(bucket) = &self.bucket {
+            let mut bucket = bucket.lock().unwrap();
+            bucket.refill(now);
+            bucket.tokens -= bytes as f64;
+        }
+    }
+}
+
+/// Limiters of the whole node, shared by all connections.
+pub(crate) struct GlobalLimiters {
+    upload: Arc<Limiter>,
+    download: Arc<Limiter>,
+}
+
+impl GlobalLimiters {
+    pub fn new(clock: &time::Clock, config: &Config) -> Self {
+        Self {
+            upload: Arc::new(Limiter::new(clock, config.upload)),
+            download: Arc::new(Limiter::new(clock, config.download)),
+        }
+    }
+}
+
+/// Limiters applying to a single connection: its own and the global ones.
+pub(crate) struct ConnectionLimiters {
+    clock: time::Clock,
+    peer_upload: Limiter,
+    peer_download: Limiter,
+    upload: Arc<Limiter>,
+    download: Arc<Limiter>,
+}
+
+impl ConnectionLimiters {
+    pub fn new(clock: &time::Clock, config: &Config, global: &GlobalLimiters) -> Self {
+        Self {
+            clock: clock.clone(),
+            peer_upload: Limiter::new(clock, config.peer_upload),
+            peer_download: Limiter::new(clock, config.peer_download),
+            upload: global.upload.clone(),
+            download: global.download.clone(),
+        }
+    }
+
+    /// Limiters which let everything through.
+    #[cfg(test)]
+    pub fn unlimited(clock: &time::Clock) -> Self {
+        Self::new(clock, &Config::default(), &GlobalLimiters::new(clock, &Config::default()))
+    }
+
+    pub fn upload_delay(&self) -> time::Duration {
+        let now = self.clock.now();
+        self.peer_upload.delay(now).max(self.upload.delay(now))
+    }
+
+    pub fn consume_upload(&self, bytes: usize) {
+        let now = self.clock.now();
+        self.peer_upload.consume(now, bytes);
+        self.upload.consume(now, bytes);
+    }
+
+    /// Counts `bytes` just received against the caps and waits until more
+    /// may be received.
+    pub async fn download(&self, bytes: usize) {
+        let now = self.clock.now();
+        self.peer_download.consume(now, bytes);
+        self.download.consume(now, bytes);
+        let delay = self.peer_download.delay(now).max(self.download.delay(now));
+        if delay > time::Duration::ZERO {
+            self.clock.sleep(delay).await;
+        }
+    }
+
+    pub fn sleep(&self, delay: time::Duration) -> impl std::future::Future<Output = ()> + 'static {
+        let clock = self.clock.clone();
+        async move { clock.sleep(delay).await }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ConnectionLimiters, GlobalLimiters, Limit};
+    use near_async::time;
+
+    /// A message larger than the burst goes through, and the next one waits
+    /// for the debt to be paid off.
+    #[test]
+    fn test_limiter_debt() {
+        let clock = time::FakeClock::default();
+        let limit = Limit { bytes_per_sec: 1000, burst_bytes: 1000 };
+        let config = Config { peer_upload: Some(limit), ..Config::default() };
+        let global = GlobalLimiters::new(&clock.clock(), &config);
+        let limiters = ConnectionLimiters::new(&clock.clock(), &config, &global);
+
+        assert_eq!(limiters.upload_delay(), time::Duration::ZERO);
+        limiters.consume_upload(3000);
+        assert_eq!(limiters.upload_delay(), time::Duration::seconds(2));
+        clock.advance(time::Duration::seconds(2));
+        assert_eq!(limiters.upload_delay(), time::Duration::ZERO);
+    }
+}
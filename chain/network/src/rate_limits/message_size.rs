@@ -0,0 +1,230 @@
+//! Size limits of the messages exchanged with the peers, per kind of message.
+//!
+//! A frame over the largest of the limits can't be skipped without breaking
+//! the framing (and the encryption), so it closes the connection, and so does
+//! a frame over the handshake limit before the Handshakes are done. Below
+//! that, each kind of message has a limit of its own, and a message over it is
+//! handled according to the `OversizePolicy`. By default it is rejected and
+//! the connection is kept, so that a kind of message which grows larger can be
+//! rolled out while some nodes still run with the old limits: they just miss
+//! those messages, instead of dropping their peers.
+//!
+//! Limits apply to messages in their encoded form, after decompression. The
+//! kind of a message is read from its first bytes, so that an oversize message
+//! is rejected before it is parsed. Routed messages are the exception: their
+//! kind depends on the body, so they are checked against the larger of the
+//! routed and witness limits first, and against their own once parsed.
+
+use crate::network_protocol::{PeerMessage, RoutedMessageBody};
+use crate::peer::stream::NETWORK_MESSAGE_MAX_SIZE_BYTES;
+use bytesize::MIB;
+
+/// Kinds of messages with a size limit of their own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum MessageKind {
+    Handshake,
+    Block,
+    /// Parts of the chunk state witness, routed to the chunk validators.
+    Witness,
+    /// Routed messages other than the state witness.
+    Routed,
+    Other,
+}
+
+impl MessageKind {
+    pub fn of(msg: &PeerMessage) -> Self {
+        match msg {
+            PeerMessage::Tier1Handshake(_)
+            | PeerMessage::Tier2Handshake(_)
+            | PeerMessage::HandshakeFailure(..) => Self::Handshake,
+            PeerMessage::Block(_) => Self::Block,
+            PeerMessage::Routed(routed) => match &routed.msg.body {
+                RoutedMessageBody::PartialEncodedStateWitness(_)
+                | RoutedMessageBody::PartialEncodedStateWitnessForward(_) => Self::Witness,
+                _ => Self::Routed,
+            },
+            _ => Self::Other,
+        }
+    }
+
+    /// Reads the kind of the encoded `msg` without parsing it. Routed messages
+    /// are `Routed` whatever their body. Returns `None` if `msg` is malformed.
+    pub fn peek(msg: &[u8]) -> Option<Self> {
+        match *msg.first()? {
+            // Proto field numbers start at 4 and borsh variants are below 32,
+            // see `PeerMessage` in network.proto.
+            tag if tag < 32 => Some(Self::of_borsh_variant(tag)),
+            _ => Self::peek_proto(msg),
+        }
+    }
+
+    fn of_borsh_variant(variant: u8) -> Self {
+        // Variants of `borsh::PeerMessage`.
+        match variant {
+            0 | 1 => Self::Handshake,
+            11 => Self::Block,
+            13 => Self::Routed,
+            _ => Self::Other,
+        }
+    }
+
+    fn peek_proto(mut msg: &[u8]) -> Option<Self> {
+        const TRACE_CONTEXT: u64 = 26;
+        const WIRE_TYPE_LEN: u64 = 2;
+        loop {
+            let tag = read_varint(&mut msg)?;
+            let field = tag >> 3;
+            if field != TRACE_CONTEXT {
+                return Some(match field {
+                    4 | 5 | 27 => Self::Handshake,
+                    15 => Self::Block,
+                    17 => Self::Routed,
+                    _ => Self::Other,
+                });
+            }
+            if tag & 7 != WIRE_TYPE_LEN {
+                return None;
+            }
+            let len = usize::try_from(read_varint(&mut msg)?).ok()?;
+            msg = msg.get(len..)?;
+        }
+    }
+}
+
+fn read_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for i in 0..10 {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte < 0x80 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// What to do with a received message over its limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizePolicy {
+    /// Drop the message and keep the connection.
+    #[default]
+    Reject,
+    /// Close the connection.
+    Disconnect,
+}
+
+/// Size limits of the messages, in bytes.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub handshake: u64,
+    pub block: u64,
+    pub witness: u64,
+    pub routed: u64,
+    /// Limit of all the other messages.
+    pub other: u64,
+    pub oversize_policy: OversizePolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let max = NETWORK_MESSAGE_MAX_SIZE_BYTES as u64;
+        Self {
+            handshake: MIB,
+            block: max,
+            witness: max,
+            // Chunk parts, with the receipts of the chunk.
+            routed: 64 * MIB,
+            // State parts are the largest of these, up to `STATE_PART_MEMORY_LIMIT`.
+            other: 64 * MIB,
+            oversize_policy: OversizePolicy::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for kind in [
+            MessageKind::Handshake,
+            MessageKind::Block,
+            MessageKind::Witness,
+            MessageKind::Routed,
+            MessageKind::Other,
+        ] {
+            let limit = self.limit(kind);
+            if limit == 0 || limit > NETWORK_MESSAGE_MAX_SIZE_BYTES as u64 {
+                anyhow::bail!(
+                    "message_size_limits.{} must be in [1, {NETWORK_MESSAGE_MAX_SIZE_BYTES}], got {limit}",
+                    kind.as_ref()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn limit(&self, kind: MessageKind) -> u64 {
+        match kind {
+            MessageKind::Handshake => self.handshake,
+            MessageKind::Block => self.block,
+            MessageKind::Witness => self.witness,
+            MessageKind::Routed => self.routed,
+            MessageKind::Other => self.other,
+        }
+    }
+
+    /// The largest of the limits, above which a frame closes the connection.
+    pub fn max_limit(&self) -> u64 {
+        self.handshake.max(self.block).max(self.witness).max(self.routed).max(self.other)
+    }
+
+    /// Limit of the encoded `msg` before it is parsed, see the module docs.
+    pub fn encoded_limit(&self, msg: &[u8]) -> u64 {
+        match MessageKind::peek(msg) {
+            Some(MessageKind::Routed) => self.routed.max(self.witness),
+            Some(kind) => self.limit(kind),
+            None => self.max_limit(),
+        }
+    }
+
+    /// Returns the kind of `msg` if its encoding of `len` bytes is over the limit.
+    pub fn oversize(&self, msg: &PeerMessage, len: usize) -> Option<MessageKind> {
+        let kind = MessageKind::of(msg);
+        (len as u64 > self.limit(kind)).then_some(kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, MessageKind};
+    use crate::network_protocol::testonly as data;
+    use crate::network_protocol::{Encoding, PeerMessage};
+    use crate::testonly::make_rng;
+    use near_async::time;
+
+    #[test]
+    fn test_oversize() {
+        let mut rng = make_rng(19385389);
+        let rng = &mut rng;
+        let clock = time::FakeClock::default();
+        let chain = data::Chain::make(&clock, rng, 2);
+        let block = PeerMessage::Block(chain.blocks[1].clone());
+        let config = Config { block: 1000, ..Config::default() };
+        assert_eq!(config.oversize(&block, 1000), None);
+        assert_eq!(config.oversize(&block, 1001), Some(MessageKind::Block));
+        let headers = PeerMessage::BlockHeaders(chain.get_block_headers());
+        assert_eq!(config.oversize(&headers, 1001), None);
+
+        assert_eq!(config.encoded_limit(&block.serialize(Encoding::Proto)), 1000);
+        assert_eq!(config.encoded_limit(&block.serialize(Encoding::Borsh)), 1000);
+        assert_eq!(config.encoded_limit(&headers.serialize(Encoding::Proto)), config.other);
+        assert_eq!(config.encoded_limit(&headers.serialize(Encoding::Borsh)), config.other);
+        assert_eq!(config.encoded_limit(&[]), config.max_limit());
+
+        assert!(config.validate().is_ok());
+        assert!(Config { witness: 0, ..Config::default() }.validate().is_err());
+        assert!(Config { other: u64::MAX, ..Config::default() }.validate().is_err());
+    }
+}
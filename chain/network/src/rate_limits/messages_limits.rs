@@ -148,6 +148,8 @@ pub enum RateLimitedPeerMessageKey {
     SyncAccountsData,
     PeersRequest,
     PeersResponse,
+    DhtFindNodes,
+    DhtNodes,
     BlockHeadersRequest,
     BlockHeaders,
     BlockRequest,
@@ -170,6 +172,8 @@ pub enum RateLimitedPeerMessageKey {
     ChunkStateWitnessAck,
     PartialEncodedStateWitness,
     PartialEncodedStateWitnessForward,
+    EpochSyncRequest,
+    EpochSyncResponse,
 }
 
 /// Given a `PeerMessage` returns a tuple containing the `RateLimitedPeerMessageKey`
@@ -187,6 +191,8 @@ fn get_key_and_token_cost(message: &PeerMessage) -> Option<(RateLimitedPeerMessa
         PeerMessage::SyncAccountsData(_) => Some((SyncAccountsData, 1)),
         PeerMessage::PeersRequest(_) => Some((PeersRequest, 1)),
         PeerMessage::PeersResponse(_) => Some((PeersResponse, 1)),
+        PeerMessage::DhtFindNodes(_) => Some((DhtFindNodes, 1)),
+        PeerMessage::DhtNodes(_) => Some((DhtNodes, 1)),
         PeerMessage::BlockHeadersRequest(_) => Some((BlockHeadersRequest, 1)),
         PeerMessage::BlockHeaders(_) => Some((BlockHeaders, 1)),
         PeerMessage::BlockRequest(_) => Some((BlockRequest, 1)),
@@ -218,8 +224,8 @@ fn get_key_and_token_cost(message: &PeerMessage) -> Option<(RateLimitedPeerMessa
                 Some((PartialEncodedStateWitnessForward, 1))
             }
             RoutedMessageBody::VersionedChunkEndorsement(_) => Some((ChunkEndorsement, 1)),
-            RoutedMessageBody::EpochSyncRequest => None,
-            RoutedMessageBody::EpochSyncResponse(_) => None,
+            RoutedMessageBody::EpochSyncRequest => Some((EpochSyncRequest, 1)),
+            RoutedMessageBody::EpochSyncResponse(_) => Some((EpochSyncResponse, 1)),
             RoutedMessageBody::Ping(_)
             | RoutedMessageBody::Pong(_)
             | RoutedMessageBody::_UnusedChunkStateWitness
@@ -1,2 +1,6 @@
+pub mod admission;
+pub mod bandwidth;
+pub mod message_size;
 pub mod messages_limits;
+pub mod qos;
 pub mod token_bucket;
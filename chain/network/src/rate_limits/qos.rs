@@ -0,0 +1,271 @@
+//! Order in which the messages queued on a connection are sent.
+//!
+//! Every message belongs to a [`QosClass`]. Consensus-critical messages go
+//! before anything else. The other classes share the connection by weighted
+//! fair queuing: each class is served in proportion to its weight while it
+//! has messages waiting, so a backlog of sync responses can't hold back the
+//! blocks and chunks, and vice versa.
+//!
+//! Critical and data messages are never delayed by the upload caps (see
+//! `bandwidth`), while sync and background ones wait for them.
+//!
+//! When too many sync responses pile up on a connection, the oldest ones are
+//! dropped: the syncing node asks again anyway, whereas a connection whose
+//! send queue overflows gets closed.
+
+use crate::network_protocol::{PeerMessage, RoutedMessageBody};
+use bytesize::MIB;
+use std::collections::VecDeque;
+
+/// Virtual time is counted in bytes scaled by `WEIGHT_SCALE / weight`, so that
+/// the heaviest class advances by 1 per byte.
+const WEIGHT_SCALE: u64 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum::AsRefStr)]
+pub(crate) enum QosClass {
+    /// Approvals, chunk endorsements and state witnesses, which have to
+    /// arrive within a block time.
+    Critical,
+    /// Blocks and chunks.
+    Data,
+    /// Responses to syncing nodes, e.g. state parts and block headers.
+    Sync,
+    /// Everything else: gossip, transactions, routing.
+    Background,
+}
+
+/// What to do when the messages of a class pile up.
+enum DropPolicy {
+    Never,
+    /// Drop the oldest messages, while more than `max_bytes` are queued.
+    Oldest {
+        max_bytes: usize,
+    },
+}
+
+impl QosClass {
+    pub fn of(msg: &PeerMessage) -> Self {
+        match msg {
+            PeerMessage::Block(_) => Self::Data,
+            PeerMessage::VersionedStateResponse(_) | PeerMessage::BlockHeaders(_) => Self::Sync,
+            PeerMessage::Routed(msg) => match msg.body {
+                RoutedMessageBody::BlockApproval(_)
+                | RoutedMessageBody::ChunkEndorsement(_)
+                | RoutedMessageBody::VersionedChunkEndorsement(_)
+                | RoutedMessageBody::ChunkStateWitnessAck(_)
+                | RoutedMessageBody::PartialEncodedStateWitness(_)
+                | RoutedMessageBody::PartialEncodedStateWitnessForward(_) => Self::Critical,
+                RoutedMessageBody::VersionedPartialEncodedChunk(_)
+                | RoutedMessageBody::PartialEncodedChunkForward(_)
+                | RoutedMessageBody::PartialEncodedChunkResponse(_) => Self::Data,
+                RoutedMessageBody::EpochSyncResponse(_) => Self::Sync,
+                _ => Self::Background,
+            },
+            _ => Self::Background,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Share of the connection, relative to the other non-critical classes.
+    fn weight(self) -> u64 {
+        match self {
+            Self::Critical => WEIGHT_SCALE,
+            Self::Data => 16,
+            Self::Background => 4,
+            Self::Sync => 1,
+        }
+    }
+
+    /// Whether the messages wait for the upload caps.
+    fn is_capped(self) -> bool {
+        matches!(self, Self::Sync | Self::Background)
+    }
+
+    fn drop_policy(self) -> DropPolicy {
+        match self {
+            Self::Sync => DropPolicy::Oldest { max_bytes: 256 * MIB as usize },
+            Self::Critical | Self::Data | Self::Background => DropPolicy::Never,
+        }
+    }
+}
+
+struct ClassQueue<T> {
+    msgs: VecDeque<(T, usize)>,
+    bytes: usize,
+    /// Virtual time at which the first message of the class starts.
+    start: u64,
+}
+
+/// Messages waiting to be sent on a connection, by QoS class.
+pub(crate) struct SendQueue<T> {
+    classes: [ClassQueue<T>; 4],
+    /// Start of the last message served.
+    now: u64,
+}
+
+impl<T> Default for SendQueue<T> {
+    fn default() -> Self {
+        Self {
+            classes: std::array::from_fn(|_| ClassQueue {
+                msgs: VecDeque::new(),
+                bytes: 0,
+                start: 0,
+            }),
+            now: 0,
+        }
+    }
+}
+
+impl<T> SendQueue<T> {
+    /// Queues `msg`. Returns the messages dropped to make room for it,
+    /// together with their sizes.
+    pub fn push(&mut self, class: QosClass, msg: T, bytes: usize) -> Vec<(T, usize)> {
+        let queue = &mut self.classes[class.index()];
+        // A class which was idle doesn't get to catch up with the others.
+        if queue.msgs.is_empty() {
+            queue.start = queue.start.max(self.now);
+        }
+        queue.msgs.push_back((msg, bytes));
+        queue.bytes += bytes;
+        let mut dropped = vec![];
+        if let DropPolicy::Oldest { max_bytes } = class.drop_policy() {
+            while queue.bytes > max_bytes && queue.msgs.len() > 1 {
+                let (msg, bytes) = queue.msgs.pop_front().unwrap();
+                queue.bytes -= bytes;
+                dropped.push((msg, bytes));
+            }
+        }
+        dropped
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.iter().all(|queue| queue.msgs.is_empty())
+    }
+
+    /// Pops the next message to send. With `capped`, i.e. while the upload is
+    /// over the caps, only critical and data messages are considered.
+    pub fn pop(&mut self, capped: bool) -> Option<T> {
+        let critical = &mut self.classes[QosClass::Critical.index()];
+        if let Some((msg, bytes)) = critical.msgs.pop_front() {
+            critical.bytes -= bytes;
+            return Some(msg);
+        }
+        // The classes held back by the caps don't get to catch up afterwards
+        // either, hence the max with `now`.
+        let now = self.now;
+        let class = [QosClass::Data, QosClass::Background, QosClass::Sync]
+            .into_iter()
+            .filter(|class| !capped || !class.is_capped())
+            .filter(|class| !self.classes[class.index()].msgs.is_empty())
+            .min_by_key(|class| self.classes[class.index()].start.max(now))?;
+        let queue = &mut self.classes[class.index()];
+        let (msg, bytes) = queue.msgs.pop_front().unwrap();
+        queue.bytes -= bytes;
+        self.now = queue.start.max(now);
+        queue.start = self.now + bytes as u64 * WEIGHT_SCALE / class.weight();
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QosClass, SendQueue};
+    use crate::network_protocol::testonly as data;
+    use crate::network_protocol::{PeerMessage, RoutedMessageBody};
+    use crate::testonly::make_rng;
+    use bytesize::MIB;
+    use near_async::time;
+    use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitness;
+    use near_primitives::types::EpochId;
+
+    /// Critical messages go first, and the other classes share the rest in
+    /// proportion to their weights.
+    #[test]
+    fn test_weighted_fair_queuing() {
+        let mut queue = SendQueue::default();
+        for class in [QosClass::Sync, QosClass::Background, QosClass::Data] {
+            for _ in 0..40 {
+                queue.push(class, class, 100);
+            }
+        }
+        queue.push(QosClass::Critical, QosClass::Critical, 100_000);
+        assert_eq!(queue.pop(false), Some(QosClass::Critical));
+
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..21 {
+            *counts.entry(queue.pop(false).unwrap()).or_insert(0) += 1;
+        }
+        assert_eq!(counts[&QosClass::Data], 16);
+        assert_eq!(counts[&QosClass::Background], 4);
+        assert_eq!(counts[&QosClass::Sync], 1);
+
+        // Over the caps, only data goes.
+        while let Some(class) = queue.pop(true) {
+            assert_eq!(class, QosClass::Data);
+        }
+        assert!(!queue.is_empty());
+        // Once under the caps again, the classes held back share the
+        // connection with data, without making up for the time they waited.
+        for _ in 0..40 {
+            queue.push(QosClass::Data, QosClass::Data, 100);
+        }
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..21 {
+            *counts.entry(queue.pop(false).unwrap()).or_insert(0) += 1;
+        }
+        assert_eq!(counts[&QosClass::Data], 16);
+        assert_eq!(counts[&QosClass::Background], 4);
+        assert_eq!(counts[&QosClass::Sync], 1);
+    }
+
+    /// State witnesses jump ahead of the blocks and chunks queued before them.
+    #[test]
+    fn test_witness_before_blocks() {
+        let mut rng = make_rng(921853233);
+        let rng = &mut rng;
+        let clock = time::FakeClock::default();
+        let chain = data::Chain::make(&clock, rng, 2);
+        let block = PeerMessage::Block(chain.blocks[1].clone());
+        let chunk = chain.chunks.values().next().unwrap();
+        let witness = PartialEncodedStateWitness::new(
+            EpochId::default(),
+            chunk.cloned_header(),
+            0,
+            vec![],
+            0,
+            &data::make_validator_signer(rng).into(),
+        );
+        let witness = PeerMessage::Routed(Box::new(data::make_routed_message(
+            rng,
+            RoutedMessageBody::PartialEncodedStateWitness(witness),
+        )));
+        assert_eq!(QosClass::of(&block), QosClass::Data);
+        assert_eq!(QosClass::of(&witness), QosClass::Critical);
+
+        let mut queue = SendQueue::default();
+        queue.push(QosClass::of(&block), "block", 100_000);
+        queue.push(QosClass::of(&witness), "witness", 100);
+        assert_eq!(queue.pop(true), Some("witness"));
+        assert_eq!(queue.pop(true), Some("block"));
+        assert_eq!(queue.pop(true), None);
+    }
+
+    #[test]
+    fn test_drop_oldest_sync() {
+        let mut queue = SendQueue::default();
+        let mut push = |i| -> Vec<i32> {
+            queue.push(QosClass::Sync, i, 100 * MIB as usize).into_iter().map(|(i, _)| i).collect()
+        };
+        assert_eq!(push(0), vec![]);
+        assert_eq!(push(1), vec![]);
+        assert_eq!(push(2), vec![0]);
+        assert_eq!(push(3), vec![1]);
+        // Other classes are never dropped.
+        for i in 0..4 {
+            assert!(queue.push(QosClass::Data, 10 + i, 100 * MIB as usize).is_empty());
+        }
+    }
+}
@@ -1,8 +1,10 @@
+use crate::concurrency;
 use crate::config::SocketOptions;
 use crate::network_protocol::{
     Encoding, Handshake, HandshakeFailureReason, PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash,
     PeerMessage, Ping, Pong, RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate,
 };
+use crate::rate_limits::admission::MAX_SOLVED_DIFFICULTY;
 use crate::tcp;
 use crate::types::{
     Edge, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerInfo,
@@ -210,6 +212,7 @@ fn new_handshake(
         // we have to set this even if we have no intention of listening since otherwise
         // the peer will drop our connection
         sender_listen_port: Some(listen_port),
+        sender_listen_addrs: vec![],
         sender_chain_info: PeerChainInfoV2 {
             genesis_id: GenesisId { chain_id: chain_id.to_string(), hash: genesis_hash },
             height: head_height,
@@ -218,6 +221,10 @@ fn new_handshake(
         },
         partial_edge_info: PartialEdgeInfo::new(my_peer_id, target_peer_id, nonce, secret_key),
         owned_account: None,
+        sender_rpc_url: None,
+        encryption_offer: None,
+        supported_compressions: vec![],
+        admission_proof: None,
     })
 }
 
@@ -338,7 +345,7 @@ impl Connection {
         head_height: BlockHeight,
         tracked_shards: Vec<ShardId>,
     ) -> Result<(), ConnectError> {
-        let handshake = new_handshake(
+        let mut handshake = new_handshake(
             &self.secret_key,
             &self.my_peer_id,
             &self.peer_id,
@@ -356,7 +363,23 @@ impl Connection {
 
         let start = Instant::now();
 
-        let (message, timestamp) = self.stream.recv_message().await?;
+        let (mut message, mut timestamp) = self.stream.recv_message().await?;
+
+        // A node flooded with handshakes accepts ours once we solve its challenge.
+        if let (
+            PeerMessage::Tier2Handshake(h),
+            PeerMessage::HandshakeFailure(_, HandshakeFailureReason::AdmissionChallenge(challenge)),
+        ) = (&mut handshake, &message)
+        {
+            if challenge.difficulty <= MAX_SOLVED_DIFFICULTY {
+                let challenge = challenge.clone();
+                let my_peer_id = self.my_peer_id.clone();
+                h.admission_proof =
+                    Some(concurrency::rayon::run(move || challenge.solve(&my_peer_id)).await);
+                self.stream.write_message(&handshake).await.map_err(ConnectError::IO)?;
+                (message, timestamp) = self.stream.recv_message().await?;
+            }
+        }
 
         match message {
             // TODO: maybe check the handshake for sanity
@@ -0,0 +1,229 @@
+//! Whether other nodes can connect to this node.
+//!
+//! A node behind a home router isn't reachable from the internet unless the
+//! router forwards its port.  The node works anyway, since it connects to
+//! other nodes itself, but nobody can connect to it, which is easy to miss.
+//! At startup the node optionally asks the gateway to forward its port
+//! (see [`nat_pmp`]) and then checks whether it can connect to itself
+//! through its external address.  The result is reported in `/status`.
+//!
+//! The node also watches the local IP it uses for outbound traffic.  When it
+//! changes, e.g. on a DHCP renewal or a failover to another uplink, the
+//! connections opened from the old IP are dead, even though it may take the
+//! OS minutes to notice.  The peer manager closes them right away and
+//! reconnects (see `NetworkState::local_ip_changed`).
+
+use crate::config;
+use crate::stun;
+use near_async::time;
+use near_primitives::views::{PortMappingView, ReachabilityStatusView, ReachabilityView};
+use parking_lot::Mutex;
+use std::net::{IpAddr, SocketAddr};
+
+pub(crate) mod nat_pmp;
+
+/// Lifetime of the port mapping to request, as recommended by RFC 6886.
+/// The mapping is renewed halfway through.
+const MAPPING_LIFETIME: time::Duration = time::Duration::hours(2);
+/// How long to wait for the mapping to be renewed after a failure.
+const MAPPING_RETRY_INTERVAL: time::Duration = time::Duration::minutes(1);
+/// Timeout of connecting to the external address of the node.
+const CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// Addresses (from the ranges reserved for documentation) used to find the
+/// local IP of the default route.  Connecting a UDP socket sends nothing.
+const LOCAL_IP_PROBE_V4: &str = "192.0.2.1:9";
+const LOCAL_IP_PROBE_V6: &str = "[2001:db8::1]:9";
+
+// TODO(gprusak): use !ip.is_global() instead, once it is stable.
+pub(crate) fn is_public_ip(ip: IpAddr) -> bool {
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || match ip {
+            IpAddr::V4(ip) => ip.is_private(),
+            // TODO(gprusak): use ip.is_unique_local() once stable.
+            IpAddr::V6(_) => false,
+        })
+}
+
+#[derive(Default)]
+pub(crate) struct Reachability {
+    view: Mutex<ReachabilityView>,
+    /// Last known local IP used for outbound traffic.
+    local_ip: Mutex<Option<IpAddr>>,
+}
+
+impl Reachability {
+    pub fn view(&self) -> ReachabilityView {
+        self.view.lock().clone()
+    }
+
+    /// External address of the node, if known.
+    pub fn external_addr(&self) -> Option<SocketAddr> {
+        self.view.lock().external_addr.as_ref()?.parse().ok()
+    }
+
+    /// Looks up the local IP used for outbound traffic.  Returns the previous
+    /// one if it has changed since the last call.  While the node has no
+    /// route to the internet, the last known IP is kept.
+    pub async fn local_ip_changed(&self, want_ipv4: bool) -> Option<IpAddr> {
+        let ip = Self::query_local_ip(want_ipv4).await?;
+        self.record_local_ip(ip)
+    }
+
+    fn record_local_ip(&self, ip: IpAddr) -> Option<IpAddr> {
+        let old = self.local_ip.lock().replace(ip)?;
+        (old != ip).then_some(old)
+    }
+
+    async fn query_local_ip(want_ipv4: bool) -> Option<IpAddr> {
+        let (bind, probe) = if want_ipv4 {
+            ("0.0.0.0:0", LOCAL_IP_PROBE_V4)
+        } else {
+            ("[::]:0", LOCAL_IP_PROBE_V6)
+        };
+        let socket = tokio::net::UdpSocket::bind(bind).await.ok()?;
+        socket.connect(probe).await.ok()?;
+        Some(socket.local_addr().ok()?.ip())
+    }
+
+    /// Records an inbound connection.  One from the internet proves that the
+    /// node is reachable, whatever the self check said.
+    pub fn inbound_connection(&self, peer_addr: SocketAddr) {
+        if !is_public_ip(peer_addr.ip()) {
+            return;
+        }
+        let mut view = self.view.lock();
+        if view.status != ReachabilityStatusView::Reachable {
+            tracing::info!(target: "network", %peer_addr, "node is reachable: got an inbound connection from the internet");
+            view.status = ReachabilityStatusView::Reachable;
+        }
+    }
+
+    fn set_port_mapping(&self, mapping: &Result<nat_pmp::Mapping, nat_pmp::Error>) {
+        let mut view = self.view.lock();
+        view.port_mapping = Some(match mapping {
+            Ok(mapping) => {
+                view.external_addr = Some(mapping.external_addr.to_string());
+                PortMappingView::Mapped {
+                    external_addr: mapping.external_addr.to_string(),
+                    lifetime_sec: mapping.lifetime.whole_seconds() as u32,
+                }
+            }
+            Err(err) => PortMappingView::Failed { error: err.to_string() },
+        });
+    }
+
+    async fn map_port(
+        &self,
+        clock: &time::Clock,
+        node_addr: SocketAddr,
+    ) -> Result<nat_pmp::Mapping, nat_pmp::Error> {
+        if !node_addr.is_ipv4() {
+            return Err(nat_pmp::Error::NotIpv4);
+        }
+        let gateway = nat_pmp::default_gateway()?;
+        let mapping =
+            nat_pmp::map_tcp_port(clock, gateway, node_addr.port(), MAPPING_LIFETIME).await;
+        match &mapping {
+            Ok(mapping) => {
+                tracing::info!(target: "network", %gateway, external_addr = %mapping.external_addr, "mapped the port of the node with NAT-PMP")
+            }
+            Err(err) => {
+                tracing::warn!(target: "network", %gateway, %err, "failed to map the port of the node with NAT-PMP")
+            }
+        }
+        self.set_port_mapping(&mapping);
+        mapping
+    }
+
+    /// Asks the STUN servers for the external IP of the node.  Only trusted
+    /// if all the servers which respond agree.
+    async fn query_external_ip(
+        clock: &time::Clock,
+        stun_servers: &[stun::ServerAddr],
+        want_ipv4: bool,
+    ) -> Option<IpAddr> {
+        let mut ips = vec![];
+        for addr in stun_servers {
+            let Some(addr) = stun::lookup_host(addr, want_ipv4).await else {
+                continue;
+            };
+            match stun::query(clock, &addr).await {
+                Ok(ip) => ips.push(ip),
+                Err(err) => {
+                    tracing::debug!(target: "network", "STUN lookup failed for {addr}: {err}")
+                }
+            }
+        }
+        let ip = *ips.first()?;
+        ips.iter().all(|other| other == &ip).then_some(ip)
+    }
+
+    async fn check(&self, external_addr: SocketAddr) {
+        let status = match tokio::time::timeout(
+            CHECK_TIMEOUT,
+            tokio::net::TcpStream::connect(external_addr),
+        )
+        .await
+        {
+            Ok(Ok(_)) => ReachabilityStatusView::Reachable,
+            Ok(Err(err)) => ReachabilityStatusView::Unreachable { error: err.to_string() },
+            Err(_) => ReachabilityStatusView::Unreachable { error: "timeout".to_string() },
+        };
+        let mut view = self.view.lock();
+        // An inbound connection may have come in meanwhile.
+        if view.status == ReachabilityStatusView::Reachable {
+            return;
+        }
+        match &status {
+            ReachabilityStatusView::Unreachable { error } => {
+                tracing::warn!(target: "network", %external_addr, %error, "node seems unreachable from the internet: forward its port on the router or enable network.port_mapping")
+            }
+            _ => tracing::info!(target: "network", %external_addr, "node is reachable"),
+        }
+        view.status = status;
+    }
+
+    /// Maps the port of the node and checks whether it is reachable, as
+    /// configured.  With the port mapped, keeps renewing the mapping and
+    /// never returns.
+    pub async fn run(&self, clock: &time::Clock, config: &config::NetworkConfig) {
+        let Some(node_addr) = config.node_addr.as_deref().copied() else {
+            return;
+        };
+        let cfg = &config.reachability;
+        let mapping =
+            if cfg.port_mapping { self.map_port(clock, node_addr).await.ok() } else { None };
+        if cfg.check {
+            let external_addr = match &mapping {
+                Some(mapping) => Some(SocketAddr::V4(mapping.external_addr)),
+                None => Self::query_external_ip(clock, &cfg.stun_servers, node_addr.is_ipv4())
+                    .await
+                    .map(|ip| SocketAddr::new(ip, node_addr.port())),
+            };
+            match external_addr {
+                Some(external_addr) => {
+                    self.view.lock().external_addr = Some(external_addr.to_string());
+                    self.check(external_addr).await;
+                }
+                None => {
+                    tracing::warn!(target: "network", "cannot check whether the node is reachable: its external address is unknown")
+                }
+            }
+        }
+        if !cfg.port_mapping {
+            return;
+        }
+        let mut renew_after = match &mapping {
+            Some(mapping) => (mapping.lifetime / 2u32).max(MAPPING_RETRY_INTERVAL),
+            None => MAPPING_RETRY_INTERVAL,
+        };
+        loop {
+            clock.sleep(renew_after).await;
+            renew_after = match self.map_port(clock, node_addr).await {
+                Ok(mapping) => (mapping.lifetime / 2u32).max(MAPPING_RETRY_INTERVAL),
+                Err(_) => MAPPING_RETRY_INTERVAL,
+            };
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Client of [NAT-PMP](https://datatracker.ietf.org/doc/html/rfc6886), which
+//! asks the gateway of the local network to forward a port to this machine.
+//! Supported by most home routers, often along with UPnP.
+
+use near_async::time;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+/// Port on which the gateway listens for requests.
+const SERVER_PORT: u16 = 5351;
+const VERSION: u8 = 0;
+const OP_EXTERNAL_ADDRESS: u8 = 0;
+const OP_MAP_TCP: u8 = 2;
+/// Set in the opcode of the responses.
+const RESPONSE_BIT: u8 = 128;
+
+/// The request is sent again after each timeout, which doubles every time.
+const ATTEMPTS: u32 = 4;
+const INITIAL_TIMEOUT: time::Duration = time::Duration::milliseconds(250);
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum Error {
+    #[error("only IPv4 is supported")]
+    NotIpv4,
+    #[error("cannot determine the default gateway")]
+    NoGateway,
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("no response from the gateway {0}")]
+    Timeout(SocketAddrV4),
+    #[error("malformed response")]
+    MalformedResponse,
+    #[error("request refused with result code {0}")]
+    Refused(u16),
+}
+
+/// A port forwarded by the gateway.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Mapping {
+    pub external_addr: SocketAddrV4,
+    /// How long the gateway keeps the mapping, unless renewed.
+    pub lifetime: time::Duration,
+}
+
+/// Reads the default gateway from the routing table, which is only known
+/// how to do on Linux.
+pub(crate) fn default_gateway() -> Result<Ipv4Addr, Error> {
+    let table = std::fs::read_to_string("/proc/net/route").map_err(|_| Error::NoGateway)?;
+    parse_route_table(&table).ok_or(Error::NoGateway)
+}
+
+/// Finds the gateway of the default route in the contents of `/proc/net/route`,
+/// where addresses are in hex, in the byte order of the machine.
+fn parse_route_table(table: &str) -> Option<Ipv4Addr> {
+    for line in table.lines().skip(1) {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let (Some(destination), Some(gateway)) = (fields.get(1), fields.get(2)) else {
+            continue;
+        };
+        if *destination != "00000000" {
+            continue;
+        }
+        let gateway = u32::from_str_radix(gateway, 16).ok()?;
+        if gateway != 0 {
+            return Some(Ipv4Addr::from(gateway.to_ne_bytes()));
+        }
+    }
+    None
+}
+
+/// Sends `req` to the gateway and waits for the response to it.
+async fn request(clock: &time::Clock, gateway: SocketAddrV4, req: &[u8]) -> Result<Vec<u8>, Error> {
+    let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(gateway).await?;
+    let mut timeout = INITIAL_TIMEOUT;
+    let mut buf = [0; 16];
+    for _ in 0..ATTEMPTS {
+        socket.send(req).await?;
+        let deadline = clock.now() + timeout;
+        loop {
+            // Both futures are cancellation safe.
+            let n = tokio::select! {
+                _ = clock.sleep_until(deadline) => break,
+                n = socket.recv(&mut buf) => n?,
+            };
+            // Responses to earlier attempts may still arrive.
+            if n >= 4 && buf[0] == VERSION && buf[1] == req[1] | RESPONSE_BIT {
+                let result = u16::from_be_bytes([buf[2], buf[3]]);
+                if result != 0 {
+                    return Err(Error::Refused(result));
+                }
+                return Ok(buf[..n].to_vec());
+            }
+        }
+        timeout = timeout * 2;
+    }
+    Err(Error::Timeout(gateway))
+}
+
+fn parse_external_address_response(resp: &[u8]) -> Result<Ipv4Addr, Error> {
+    let ip: [u8; 4] = resp.get(8..12).ok_or(Error::MalformedResponse)?.try_into().unwrap();
+    Ok(Ipv4Addr::from(ip))
+}
+
+fn encode_map_request(internal_port: u16, external_port: u16, lifetime_sec: u32) -> [u8; 12] {
+    let mut req = [0; 12];
+    req[0] = VERSION;
+    req[1] = OP_MAP_TCP;
+    req[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    req[6..8].copy_from_slice(&external_port.to_be_bytes());
+    req[8..12].copy_from_slice(&lifetime_sec.to_be_bytes());
+    req
+}
+
+/// Returns the mapped external port and the lifetime of the mapping.
+fn parse_map_response(resp: &[u8]) -> Result<(u16, u32), Error> {
+    if resp.len() < 16 {
+        return Err(Error::MalformedResponse);
+    }
+    let external_port = u16::from_be_bytes([resp[10], resp[11]]);
+    let lifetime_sec = u32::from_be_bytes([resp[12], resp[13], resp[14], resp[15]]);
+    Ok((external_port, lifetime_sec))
+}
+
+/// Asks the gateway to forward TCP connections to `port` of this machine,
+/// preferably from the same external port, for `lifetime`.  Requesting the
+/// same mapping again renews it.
+pub(crate) async fn map_tcp_port(
+    clock: &time::Clock,
+    gateway: Ipv4Addr,
+    port: u16,
+    lifetime: time::Duration,
+) -> Result<Mapping, Error> {
+    let gateway = SocketAddrV4::new(gateway, SERVER_PORT);
+    let resp = request(clock, gateway, &[VERSION, OP_EXTERNAL_ADDRESS]).await?;
+    let external_ip = parse_external_address_response(&resp)?;
+    let req = encode_map_request(port, port, lifetime.whole_seconds() as u32);
+    let resp = request(clock, gateway, &req).await?;
+    let (external_port, lifetime_sec) = parse_map_response(&resp)?;
+    Ok(Mapping {
+        external_addr: SocketAddrV4::new(external_ip, external_port),
+        lifetime: time::Duration::seconds(lifetime_sec.into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_map_request, parse_map_response, parse_route_table};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_parse_route_table() {
+        let gateway = u32::from_ne_bytes([192, 168, 1, 1]);
+        let table = format!(
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+             eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+             eth0\t00000000\t{gateway:08X}\t0003\t0\t0\t0\t00000000\n"
+        );
+        assert_eq!(parse_route_table(&table), Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(parse_route_table("Iface\tDestination\tGateway\n"), None);
+    }
+
+    #[test]
+    fn test_map_request() {
+        let req = encode_map_request(24567, 24567, 7200);
+        assert_eq!(req, [0, 2, 0, 0, 0x5f, 0xf7, 0x5f, 0xf7, 0, 0, 0x1c, 0x20]);
+
+        let mut resp = [0; 16];
+        resp[1] = 130;
+        resp[10..12].copy_from_slice(&24568u16.to_be_bytes());
+        resp[12..16].copy_from_slice(&3600u32.to_be_bytes());
+        assert_eq!(parse_map_response(&resp).unwrap(), (24568, 3600));
+        assert!(parse_map_response(&resp[..12]).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Recording of the messages exchanged with the peers, for replaying them
+//! later, see `peer_manager::testonly::ActorHandler::replay`.
+//!
+//! Recording is meant for chasing sync and routing bugs which are hard to
+//! reproduce. Every `PeerMessage` received or sent by the node is appended to
+//! a file, as a little endian u32 length followed by the Borsh of a
+//! [`Record`]. The messages are stored in the Proto encoding, whichever
+//! encoding the connection uses.
+//!
+//! The file is written by a thread of its own. If it falls behind, messages
+//! are dropped rather than slowing the node down, see the
+//! `near_traffic_recorder_dropped_messages` metric.
+
+use crate::network_protocol::{Encoding, ParsePeerMessageError, PeerMessage};
+use crate::stats::metrics;
+use anyhow::Context as _;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_async::time;
+use near_primitives::network::PeerId;
+use std::io::{Read, Write};
+use std::sync::mpsc;
+
+/// Number of records waiting to be written, over which new ones are dropped.
+const QUEUE_SIZE: usize = 10_000;
+
+fn default_max_file_bytes() -> u64 {
+    bytesize::GIB
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Config {
+    /// File to append the records to.
+    pub file: std::path::PathBuf,
+    /// Size of the file at which the recording stops.
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// When the message was received or sent, in nanoseconds since the Unix epoch.
+    pub time: i64,
+    pub direction: Direction,
+    /// Peer on the other side of the connection, unknown before the handshake.
+    pub peer_id: Option<PeerId>,
+    /// `PeerMessage` in the Proto encoding.
+    pub message: Vec<u8>,
+}
+
+impl Record {
+    pub fn peer_message(&self) -> Result<PeerMessage, ParsePeerMessageError> {
+        PeerMessage::deserialize(Encoding::Proto, &self.message)
+    }
+}
+
+enum Command {
+    Record(Record),
+    #[cfg(test)]
+    Flush(mpsc::Sender<()>),
+}
+
+pub(crate) struct Recorder {
+    clock: time::Clock,
+    sender: mpsc::SyncSender<Command>,
+}
+
+impl Recorder {
+    pub fn start(clock: &time::Clock, config: &Config) -> anyhow::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.file)
+            .with_context(|| format!("failed to open {}", config.file.display()))?;
+        let mut written = file.metadata()?.len();
+        let max_file_bytes = config.max_file_bytes;
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+        std::thread::Builder::new().name("traffic_recorder".to_string()).spawn(move || {
+            let mut out = std::io::BufWriter::new(file);
+            loop {
+                // Flush whenever the queue is drained, so that the file is
+                // complete up to the last message while the node is quiet.
+                let cmd = match receiver.try_recv() {
+                    Ok(cmd) => cmd,
+                    Err(mpsc::TryRecvError::Empty) => {
+                        if let Err(err) = out.flush() {
+                            tracing::warn!(target: "network", %err, "traffic recording failed");
+                            return;
+                        }
+                        match receiver.recv() {
+                            Ok(cmd) => cmd,
+                            Err(_) => return,
+                        }
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => return,
+                };
+                let record = match cmd {
+                    Command::Record(record) => record,
+                    #[cfg(test)]
+                    Command::Flush(done) => {
+                        out.flush().unwrap();
+                        done.send(()).unwrap();
+                        continue;
+                    }
+                };
+                let bytes = borsh::to_vec(&record).unwrap();
+                written += 4 + bytes.len() as u64;
+                if written > max_file_bytes {
+                    tracing::warn!(target: "network", max_file_bytes, "traffic recording reached its size limit, stopping");
+                    return;
+                }
+                let res = out
+                    .write_all(&(bytes.len() as u32).to_le_bytes())
+                    .and_then(|()| out.write_all(&bytes));
+                if let Err(err) = res {
+                    tracing::warn!(target: "network", %err, "traffic recording failed");
+                    return;
+                }
+            }
+        })?;
+        Ok(Self { clock: clock.clone(), sender })
+    }
+
+    pub fn record(&self, direction: Direction, peer_id: Option<&PeerId>, msg: &PeerMessage) {
+        let record = Record {
+            time: self.clock.now_utc().unix_timestamp_nanos() as i64,
+            direction,
+            peer_id: peer_id.cloned(),
+            message: msg.serialize(Encoding::Proto),
+        };
+        if let Err(mpsc::TrySendError::Full(_)) = self.sender.try_send(Command::Record(record)) {
+            metrics::TRAFFIC_RECORDER_DROPPED_MESSAGES.inc();
+        }
+    }
+
+    /// Waits until the records so far are written to the file.
+    #[cfg(test)]
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        self.sender.send(Command::Flush(done)).unwrap();
+        wait.recv().unwrap();
+    }
+}
+
+/// Reads the records of a file written by the recorder. A record cut short,
+/// e.g. by a crash of the node, ends the iteration.
+pub struct Reader<R> {
+    inner: R,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = std::io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut len = [0u8; 4];
+        let mut bytes = vec![];
+        let res = self.inner.read_exact(&mut len).and_then(|()| {
+            bytes.resize(u32::from_le_bytes(len) as usize, 0);
+            self.inner.read_exact(&mut bytes)
+        });
+        match res {
+            Ok(()) => Some(borsh::from_slice(&bytes)),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use crate::routing;
 use lru::LruCache;
+use near_async::time;
 use near_primitives::network::PeerId;
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 
@@ -9,6 +11,15 @@ use std::sync::Arc;
 mod tests;
 
 const LAST_ROUTED_CACHE_SIZE: usize = 10_000;
+/// Next hops whose round trip time is at most this many times the one of the
+/// fastest next hop (plus `RTT_SLACK`) are considered as good as the fastest,
+/// so that the traffic is still spread among them.
+const RTT_TOLERANCE: f64 = 1.5;
+/// Makes differences of round trip times below measurement noise irrelevant.
+const RTT_SLACK: time::Duration = time::Duration::milliseconds(5);
+
+/// Round trip time of each direct peer.
+pub(crate) type RttTable = HashMap<PeerId, time::Duration>;
 
 pub(crate) struct RoutingTableView(Mutex<Inner>);
 
@@ -27,15 +38,29 @@ struct Inner {
     find_route_calls: u64,
     /// Last time the given peer was selected by find_route_by_peer_id.
     last_routed: LruCache<PeerId, u64>,
+
+    /// Round trip times of the direct peers.
+    rtts: Arc<RttTable>,
 }
 
 impl Inner {
     /// Select a connected peer on some shortest path to `peer_id`.
+    /// Of those, only the ones with a round trip time close to the fastest
+    /// one's are considered, together with the ones not measured yet.
     /// If there are several such peers, pick the least recently used one.
     fn find_next_hop(&mut self, peer_id: &PeerId) -> Result<PeerId, FindRouteError> {
         let peers = self.next_hops.get(peer_id).ok_or(FindRouteError::PeerUnreachable)?;
+        let max_rtt = peers
+            .iter()
+            .filter_map(|p| self.rtts.get(p))
+            .min()
+            .map(|fastest| *fastest * RTT_TOLERANCE + RTT_SLACK);
         let next_hop = peers
             .iter()
+            .filter(|p| match (max_rtt, self.rtts.get(*p)) {
+                (Some(max_rtt), Some(rtt)) => *rtt <= max_rtt,
+                _ => true,
+            })
             .min_by_key(|p| self.last_routed.get(*p).copied().unwrap_or(0))
             .ok_or(FindRouteError::PeerUnreachable)?;
         self.last_routed.put(next_hop.clone(), self.find_route_calls);
@@ -66,9 +91,14 @@ impl RoutingTableView {
             distance: Default::default(),
             find_route_calls: 0,
             last_routed: LruCache::new(NonZeroUsize::new(LAST_ROUTED_CACHE_SIZE).unwrap()),
+            rtts: Default::default(),
         }))
     }
 
+    pub(crate) fn set_rtts(&self, rtts: Arc<RttTable>) {
+        self.0.lock().rtts = rtts;
+    }
+
     pub(crate) fn update(
         &self,
         next_hops: Arc<routing::NextHopTable>,
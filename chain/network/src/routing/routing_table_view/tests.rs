@@ -2,7 +2,9 @@ use crate::network_protocol::testonly as data;
 use crate::routing;
 use crate::routing::routing_table_view::*;
 use crate::testonly::make_rng;
+use near_async::time;
 use rand::seq::SliceRandom;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 #[test]
@@ -27,3 +29,25 @@ fn find_route() {
         assert!(next_hops.get(p).unwrap().contains(&got));
     }
 }
+
+#[test]
+fn find_route_prefers_low_rtt() {
+    let mut rng = make_rng(385305732);
+    let rng = &mut rng;
+
+    let target = data::make_peer_id(rng);
+    let peers: Vec<_> = (0..4).map(|_| data::make_peer_id(rng)).collect();
+    let next_hops = Arc::new(routing::NextHopTable::from([(target.clone(), peers.clone())]));
+    let rtts = [100, 10, 12].map(time::Duration::milliseconds);
+
+    let rtv = RoutingTableView::new();
+    rtv.update(next_hops, Default::default());
+    rtv.set_rtts(Arc::new(peers.iter().cloned().zip(rtts).collect()));
+    // The slow peer is avoided, the fast ones and the one not measured yet
+    // share the traffic.
+    let mut got = HashSet::new();
+    for _ in 0..100 {
+        got.insert(rtv.find_next_hop_for_target(&target).unwrap());
+    }
+    assert_eq!(got, peers[1..].iter().cloned().collect());
+}
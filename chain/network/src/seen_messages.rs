@@ -0,0 +1,170 @@
+//! Hashes of the recently seen messages, to recognize the duplicates.
+//!
+//! The same message arrives from many peers: a block is broadcast by each of
+//! them, a routed message may come over TIER1 and TIER2. Only the first copy
+//! is worth processing, and processing the others is what turns a burst of
+//! blocks during a reorg into a storm of re-broadcasts.
+//!
+//! A hash is remembered for a TTL which adapts to the traffic, within the
+//! memory budget of the cache. When the budget runs out, the oldest hashes are
+//! evicted and the TTL shrinks to the age of the evicted ones. Without the
+//! pressure, the TTL grows back by as much time as passes, up to `max_ttl`.
+//! This way a busy RPC node keeps its memory bounded, while a quiet node
+//! recognizes duplicates arriving long after the originals.
+
+use crate::stats::metrics;
+use ::time::ext::InstantExt as _;
+use near_async::time;
+use near_primitives::hash::CryptoHash;
+use std::collections::{HashMap, VecDeque};
+
+/// Estimated memory taken by an entry: the hash, stored in both the map and
+/// the queue, with the bookkeeping.
+const ENTRY_BYTES: u64 = 128;
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Memory budget of the cache, in bytes.
+    pub max_bytes: u64,
+    /// The TTL never shrinks below that, even if the cache goes over budget.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub min_ttl: time::Duration,
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub max_ttl: time::Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_bytes: 16 * bytesize::MIB,
+            min_ttl: time::Duration::seconds(5),
+            max_ttl: time::Duration::minutes(10),
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.max_bytes < ENTRY_BYTES {
+            anyhow::bail!("seen_messages.max_bytes must be at least {ENTRY_BYTES}");
+        }
+        if self.min_ttl <= time::Duration::ZERO || self.min_ttl > self.max_ttl {
+            anyhow::bail!(
+                "seen_messages.min_ttl ({}) must be positive and at most max_ttl ({})",
+                self.min_ttl,
+                self.max_ttl
+            );
+        }
+        Ok(())
+    }
+}
+
+struct Entry {
+    kind: &'static str,
+    seen_at: time::Instant,
+}
+
+pub(crate) struct SeenMessages {
+    config: Config,
+    ttl: time::Duration,
+    /// When the TTL was last adjusted.
+    ttl_updated_at: time::Instant,
+    entries: HashMap<CryptoHash, Entry>,
+    /// Hashes in the order they were seen.
+    queue: VecDeque<CryptoHash>,
+}
+
+impl SeenMessages {
+    pub fn new(clock: &time::Clock, config: Config) -> Self {
+        Self {
+            ttl: config.max_ttl,
+            ttl_updated_at: clock.now(),
+            config,
+            entries: HashMap::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Records a message of the given kind. Returns true if it hasn't been
+    /// seen within the TTL.
+    pub fn insert(&mut self, now: time::Instant, hash: CryptoHash, kind: &'static str) -> bool {
+        self.evict(now);
+        if self.entries.contains_key(&hash) {
+            metrics::SEEN_MESSAGES_DUPLICATES.with_label_values(&[kind]).inc();
+            return false;
+        }
+        self.entries.insert(hash, Entry { kind, seen_at: now });
+        self.queue.push_back(hash);
+        metrics::SEEN_MESSAGES.with_label_values(&[kind]).inc();
+        self.evict(now);
+        true
+    }
+
+    fn bytes(&self) -> u64 {
+        self.entries.len() as u64 * ENTRY_BYTES
+    }
+
+    fn remove_oldest(&mut self) -> Option<Entry> {
+        let hash = self.queue.pop_front()?;
+        let entry = self.entries.remove(&hash)?;
+        metrics::SEEN_MESSAGES.with_label_values(&[entry.kind]).dec();
+        Some(entry)
+    }
+
+    fn evict(&mut self, now: time::Instant) {
+        self.ttl =
+            (self.ttl + now.signed_duration_since(self.ttl_updated_at)).min(self.config.max_ttl);
+        self.ttl_updated_at = now;
+        while let Some(hash) = self.queue.front() {
+            if now.signed_duration_since(self.entries[hash].seen_at) <= self.ttl {
+                break;
+            }
+            self.remove_oldest();
+        }
+        while self.bytes() > self.config.max_bytes {
+            let Some(entry) = self.remove_oldest() else { break };
+            self.ttl = now.signed_duration_since(entry.seen_at).max(self.config.min_ttl);
+        }
+        metrics::SEEN_MESSAGES_TTL.set(self.ttl.whole_milliseconds() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, SeenMessages, ENTRY_BYTES};
+    use near_async::time;
+    use near_primitives::hash::CryptoHash;
+
+    #[test]
+    fn test_adaptive_ttl() {
+        let clock = time::FakeClock::default();
+        let config = Config {
+            max_bytes: 10 * ENTRY_BYTES,
+            min_ttl: time::Duration::seconds(1),
+            max_ttl: time::Duration::seconds(60),
+        };
+        let mut seen = SeenMessages::new(&clock.clock(), config);
+        let hash = |i: u64| CryptoHash::hash_borsh(i);
+
+        assert!(seen.insert(clock.now(), hash(0), "Block"));
+        assert!(!seen.insert(clock.now(), hash(0), "Block"));
+        clock.advance(time::Duration::seconds(61));
+        assert!(seen.insert(clock.now(), hash(0), "Block"));
+
+        // Over the budget, the TTL shrinks to the age of the evicted entries.
+        for i in 1..=10 {
+            clock.advance(time::Duration::seconds(2));
+            assert!(seen.insert(clock.now(), hash(i), "Routed"));
+        }
+        assert_eq!(seen.entries.len(), 10);
+        assert_eq!(seen.ttl, time::Duration::seconds(20));
+
+        // It grows back along with the time.
+        clock.advance(time::Duration::seconds(15));
+        assert!(!seen.insert(clock.now(), hash(10), "Routed"));
+        assert_eq!(seen.ttl, time::Duration::seconds(35));
+        // The evicted entry is forgotten.
+        assert!(seen.insert(clock.now(), hash(0), "Block"));
+    }
+}
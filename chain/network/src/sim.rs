@@ -0,0 +1,373 @@
+//! In-process virtual network, over which PeerManagers can talk to each other
+//! without opening real sockets.
+//!
+//! Set `NetworkConfig::simulation` to the same `Network` in the configs of all
+//! the nodes: they will listen on their `node_addr` and connect to each other
+//! through the `Network`, while running the same code as in production.
+//!
+//! Data written to a connection is delivered after the latency of the link
+//! between the two nodes, according to the `Clock` of the `Network`, so a
+//! `FakeClock` (for example the one of a TestLoop) controls when it arrives.
+//! Lost packets are modeled as retransmissions, which delay the data (and all
+//! the data sent after it on the connection) by `RETRANSMISSION_TIMEOUT`.
+//! The links between partitioned nodes don't deliver anything until the
+//! partition is healed, and new connections across it fail.
+//!
+//! Losses are drawn from an RNG seeded at creation, so as long as the nodes
+//! write the same data in the same order, the network behaves the same.
+
+use crate::network_protocol::PeerInfo;
+use crate::tcp;
+use anyhow::anyhow;
+use near_async::time;
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::{Rng as _, SeedableRng as _};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _, DuplexStream, ReadHalf, WriteHalf};
+
+/// Delay of the data carried by a lost packet, like the minimal RTO of TCP.
+pub const RETRANSMISSION_TIMEOUT: time::Duration = time::Duration::milliseconds(200);
+/// Size of the buffers of a connection, in each direction.
+const BUFFER_SIZE: usize = 64 * 1024;
+/// Ports assigned to the outbound connections start from here.
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// Properties of the link between two nodes, in each direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkConfig {
+    pub latency: time::Duration,
+    /// Probability that a packet is lost, in [0, 1).
+    pub loss: f64,
+}
+
+/// Link of an outbound connection made by a node without a `node_addr`.
+type Endpoint = Option<SocketAddr>;
+
+struct Inner {
+    rng: StdRng,
+    listeners: HashMap<SocketAddr, tokio::sync::mpsc::UnboundedSender<tcp::Stream>>,
+    default_link: LinkConfig,
+    /// Keyed by the pair of node addresses, the smaller one first.
+    links: HashMap<(SocketAddr, SocketAddr), LinkConfig>,
+    /// Pairs of nodes which can't reach each other, the smaller address first.
+    blocked: HashSet<(SocketAddr, SocketAddr)>,
+    next_port: u16,
+}
+
+fn ordered(a: SocketAddr, b: SocketAddr) -> (SocketAddr, SocketAddr) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+impl Inner {
+    fn link(&self, from: Endpoint, to: Endpoint) -> LinkConfig {
+        match (from, to) {
+            (Some(a), Some(b)) => {
+                self.links.get(&ordered(a, b)).copied().unwrap_or(self.default_link)
+            }
+            _ => self.default_link,
+        }
+    }
+
+    fn is_blocked(&self, from: Endpoint, to: Endpoint) -> bool {
+        match (from, to) {
+            (Some(a), Some(b)) => self.blocked.contains(&ordered(a, b)),
+            _ => false,
+        }
+    }
+
+    /// Delay of the next packet sent from `from` to `to`.
+    fn delay(&mut self, from: Endpoint, to: Endpoint) -> time::Duration {
+        let link = self.link(from, to);
+        let mut delay = link.latency;
+        while link.loss > 0. && self.rng.gen_bool(link.loss) {
+            delay += RETRANSMISSION_TIMEOUT;
+        }
+        delay
+    }
+
+    fn ephemeral_addr(&mut self, from: Endpoint) -> SocketAddr {
+        let ip = from.map_or(IpAddr::V6(Ipv6Addr::LOCALHOST), |addr| addr.ip());
+        let port = self.next_port;
+        self.next_port = self.next_port.checked_add(1).unwrap_or(FIRST_EPHEMERAL_PORT);
+        SocketAddr::new(ip, port)
+    }
+}
+
+/// Virtual network, see the module docs.
+pub struct Network {
+    clock: time::Clock,
+    inner: Mutex<Inner>,
+    /// Notified whenever the partitions change.
+    changed: tokio::sync::watch::Sender<()>,
+}
+
+impl Network {
+    pub fn new(clock: time::Clock, seed: u64) -> Arc<Self> {
+        Arc::new(Self {
+            clock,
+            inner: Mutex::new(Inner {
+                rng: StdRng::seed_from_u64(seed),
+                listeners: HashMap::new(),
+                default_link: LinkConfig::default(),
+                links: HashMap::new(),
+                blocked: HashSet::new(),
+                next_port: FIRST_EPHEMERAL_PORT,
+            }),
+            changed: tokio::sync::watch::channel(()).0,
+        })
+    }
+
+    /// Sets the properties of the links which weren't set with `set_link`.
+    pub fn set_default_link(&self, link: LinkConfig) {
+        assert!((0. ..1.).contains(&link.loss), "loss must be in [0, 1), got {}", link.loss);
+        self.inner.lock().default_link = link;
+    }
+
+    /// Sets the properties of the link between the nodes listening on `a` and `b`.
+    /// Applies to the data sent from now on, including on existing connections.
+    pub fn set_link(&self, a: SocketAddr, b: SocketAddr, link: LinkConfig) {
+        assert!((0. ..1.).contains(&link.loss), "loss must be in [0, 1), got {}", link.loss);
+        self.inner.lock().links.insert(ordered(a, b), link);
+    }
+
+    /// Cuts all the links between the nodes of `group` and the other nodes of `nodes`.
+    pub fn partition(&self, group: &[SocketAddr], nodes: &[SocketAddr]) {
+        let mut inner = self.inner.lock();
+        for a in group {
+            for b in nodes.iter().filter(|b| !group.contains(b)) {
+                inner.blocked.insert(ordered(*a, *b));
+            }
+        }
+        drop(inner);
+        self.changed.send_replace(());
+    }
+
+    /// Restores all the links cut by `partition`.
+    pub fn heal(&self) {
+        self.inner.lock().blocked.clear();
+        self.changed.send_replace(());
+    }
+
+    /// Starts accepting the connections to `addr`.
+    pub(crate) fn listen(&self, addr: &tcp::ListenerAddr) -> tcp::Listener {
+        let (send, recv) = tokio::sync::mpsc::unbounded_channel();
+        self.inner.lock().listeners.insert(**addr, send);
+        tcp::Listener::Sim(recv)
+    }
+
+    /// Connects to `peer_info` on behalf of the node listening on `from`.
+    /// Like a TCP connect, it takes a round trip.
+    pub(crate) async fn connect(
+        self: &Arc<Self>,
+        from: Option<&tcp::ListenerAddr>,
+        peer_info: &PeerInfo,
+        tier: tcp::Tier,
+    ) -> anyhow::Result<tcp::Stream> {
+        let to = peer_info
+            .addr
+            .ok_or_else(|| anyhow!("Trying to connect to peer with no public address"))?;
+        let from = from.map(|addr| **addr);
+        let rtt = {
+            let mut inner = self.inner.lock();
+            inner.delay(from, Some(to)) + inner.delay(Some(to), from)
+        };
+        self.clock.sleep(rtt).await;
+        let mut inner = self.inner.lock();
+        if inner.is_blocked(from, Some(to)) {
+            anyhow::bail!("{to} is unreachable");
+        }
+        let listener = inner
+            .listeners
+            .get(&to)
+            .filter(|listener| !listener.is_closed())
+            .ok_or_else(|| anyhow!("connection refused by {to}"))?
+            .clone();
+        let local_addr = inner.ephemeral_addr(from);
+        drop(inner);
+
+        let (outbound, outbound_far) = tokio::io::duplex(BUFFER_SIZE);
+        let (inbound, inbound_far) = tokio::io::duplex(BUFFER_SIZE);
+        let (outbound_recv, outbound_send) = tokio::io::split(outbound_far);
+        let (inbound_recv, inbound_send) = tokio::io::split(inbound_far);
+        self.spawn_link(from, Some(to), outbound_recv, inbound_send);
+        self.spawn_link(Some(to), from, inbound_recv, outbound_send);
+        listener
+            .send(tcp::Stream::sim(inbound, tcp::StreamType::Inbound, to, local_addr))
+            .map_err(|_| anyhow!("connection refused by {to}"))?;
+        let type_ = tcp::StreamType::Outbound { peer_id: peer_info.id.clone(), tier };
+        Ok(tcp::Stream::sim(outbound, type_, local_addr, to))
+    }
+
+    /// Carries the data written on one end of a connection to the other end.
+    fn spawn_link(
+        self: &Arc<Self>,
+        from: Endpoint,
+        to: Endpoint,
+        mut recv: ReadHalf<DuplexStream>,
+        mut send: WriteHalf<DuplexStream>,
+    ) {
+        let (queue_send, mut queue_recv) = tokio::sync::mpsc::unbounded_channel();
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0; BUFFER_SIZE];
+            // Data is delivered in order, even if the delay decreases.
+            let mut deliver_at = this.clock.now();
+            loop {
+                let n = match recv.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => n,
+                };
+                let delay = this.inner.lock().delay(from, to);
+                deliver_at = deliver_at.max(this.clock.now() + delay);
+                if queue_send.send((deliver_at, buf[..n].to_vec())).is_err() {
+                    return;
+                }
+            }
+        });
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut changed = this.changed.subscribe();
+            while let Some((deliver_at, data)) = queue_recv.recv().await {
+                this.clock.sleep_until(deliver_at).await;
+                while this.inner.lock().is_blocked(from, to) {
+                    if changed.changed().await.is_err() {
+                        return;
+                    }
+                }
+                if send.write_all(&data).await.is_err() {
+                    return;
+                }
+            }
+            // The sender closed its end of the connection.
+            let _ = send.shutdown().await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LinkConfig, Network};
+    use crate::network_protocol::testonly as data;
+    use crate::network_protocol::PeerInfo;
+    use crate::tcp;
+    use crate::testonly::make_rng;
+    use near_async::time;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    const STEP: time::Duration = time::Duration::milliseconds(10);
+
+    /// Advances the clock in steps until `fut` completes.
+    /// Returns its output and the time it took.
+    async fn time_until<T: Send + 'static>(
+        clock: &time::FakeClock,
+        fut: impl std::future::Future<Output = T> + Send + 'static,
+    ) -> (T, time::Duration) {
+        let start = clock.now();
+        let handle = tokio::spawn(fut);
+        loop {
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            if handle.is_finished() {
+                break;
+            }
+            clock.advance(STEP);
+        }
+        (handle.await.unwrap(), clock.now() - start)
+    }
+
+    async fn connect(
+        net: &Arc<Network>,
+        from: &tcp::ListenerAddr,
+        to: &tcp::ListenerAddr,
+    ) -> anyhow::Result<(tcp::Stream, tcp::Stream)> {
+        let mut listener = net.listen(to);
+        let peer_info = PeerInfo {
+            id: data::make_peer_id(&mut make_rng(1)),
+            addr: Some(**to),
+            account_id: None,
+        };
+        let outbound = net.connect(Some(from), &peer_info, tcp::Tier::T2).await?;
+        Ok((outbound, listener.accept().await?))
+    }
+
+    #[tokio::test]
+    async fn test_latency() {
+        let clock = time::FakeClock::default();
+        let net = Network::new(clock.clock(), 12345);
+        let a = tcp::ListenerAddr::reserve_for_test();
+        let b = tcp::ListenerAddr::reserve_for_test();
+        let latency = time::Duration::milliseconds(100);
+        net.set_link(*a, *b, LinkConfig { latency, loss: 0. });
+
+        let ((mut outbound, mut inbound), took) = time_until(&clock, {
+            let net = net.clone();
+            async move { connect(&net, &a, &b).await.unwrap() }
+        })
+        .await;
+        assert!(took >= latency * 2, "took {took}");
+
+        outbound.stream.write_all(b"hello").await.unwrap();
+        let (buf, took) = time_until(&clock, async move {
+            let mut buf = [0; 5];
+            inbound.stream.read_exact(&mut buf).await.unwrap();
+            buf
+        })
+        .await;
+        assert_eq!(&buf, b"hello");
+        assert!(took >= latency && took < latency + STEP * 2, "took {took}");
+    }
+
+    #[tokio::test]
+    async fn test_partition() {
+        let clock = time::FakeClock::default();
+        let net = Network::new(clock.clock(), 12345);
+        let a = tcp::ListenerAddr::reserve_for_test();
+        let b = tcp::ListenerAddr::reserve_for_test();
+        let (mut outbound, mut inbound) = connect(&net, &a, &b).await.unwrap();
+
+        net.partition(&[*a], &[*a, *b]);
+        assert!(connect(&net, &a, &b).await.is_err());
+        outbound.stream.write_all(b"hello").await.unwrap();
+        let read = tokio::spawn(async move {
+            let mut buf = [0; 5];
+            inbound.stream.read_exact(&mut buf).await.unwrap();
+            buf
+        });
+        clock.advance(time::Duration::seconds(10));
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!read.is_finished());
+
+        // The data sent during the partition is delivered once it is healed.
+        net.heal();
+        assert_eq!(&read.await.unwrap(), b"hello");
+        assert!(connect(&net, &a, &b).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_loss_is_deterministic() {
+        let delays = |seed| {
+            let net = Network::new(time::FakeClock::default().clock(), seed);
+            let a = Some(*tcp::ListenerAddr::reserve_for_test());
+            let b = Some(*tcp::ListenerAddr::reserve_for_test());
+            net.set_default_link(LinkConfig { latency: time::Duration::ZERO, loss: 0.5 });
+            let mut inner = net.inner.lock();
+            let delays: Vec<_> = (0..100).map(|_| inner.delay(a, b)).collect();
+            delays
+        };
+        let got = delays(42);
+        assert_eq!(got, delays(42));
+        assert!(got.iter().any(|d| *d == time::Duration::ZERO));
+        assert!(got.iter().any(|d| *d > time::Duration::ZERO));
+    }
+}
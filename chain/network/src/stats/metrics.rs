@@ -186,6 +186,41 @@ pub(crate) static PEER_MESSAGE_SENT_BY_TYPE_BYTES: LazyLock<IntCounterVec> = Laz
     )
     .unwrap()
 });
+pub(crate) static PEER_MESSAGES_DROPPED_BY_QOS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_messages_dropped_by_qos",
+        "Messages dropped from the send queues because too many of their QoS class were waiting",
+        &["class"],
+    )
+    .unwrap()
+});
+pub(crate) static INBOUND_HANDSHAKES_NOT_ADMITTED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_inbound_handshakes_not_admitted",
+        "Inbound handshakes over the admission rate or cap, by what happened to them",
+        &["verdict"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_MESSAGE_COMPRESSION_INPUT_BYTES: LazyLock<IntCounterVec> =
+    LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_peer_message_compression_input_bytes",
+            "Size of the messages considered for compression, by message types",
+            &["type"],
+        )
+        .unwrap()
+    });
+pub(crate) static PEER_MESSAGE_COMPRESSION_OUTPUT_BYTES: LazyLock<IntCounterVec> = LazyLock::new(
+    || {
+        try_create_int_counter_vec(
+            "near_peer_message_compression_output_bytes",
+            "Size of the messages considered for compression as sent, compressed or not, by message types",
+            &["type"],
+        )
+        .unwrap()
+    },
+);
 pub(crate) static PEER_MESSAGE_SENT_BY_TYPE_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_peer_message_sent_by_type_total",
@@ -203,6 +238,22 @@ pub(crate) static PEER_MESSAGE_RATE_LIMITED_BY_TYPE_TOTAL: LazyLock<IntCounterVe
         )
         .unwrap()
     });
+pub(crate) static PEER_ENCRYPTION_FALLBACK_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_encryption_fallback_total",
+        "Number of connections left unencrypted although encryption was enabled",
+        &["peer_type"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_MESSAGE_OVERSIZE_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_message_oversize_total",
+        "Number of messages sent/received over the size limit of their kind, which were dropped",
+        &["direction", "kind"],
+    )
+    .unwrap()
+});
 pub(crate) static SYNC_ACCOUNTS_DATA: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_sync_accounts_data",
@@ -220,6 +271,20 @@ pub(crate) static SYNC_SNAPSHOT_HOSTS: LazyLock<IntCounterVec> = LazyLock::new(|
     .unwrap()
 });
 
+pub(crate) static DHT_ROUTING_TABLE_SIZE: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge("near_dht_routing_table_size", "Number of nodes in the DHT routing table")
+        .unwrap()
+});
+
+pub(crate) static DHT_RECORDS_RECEIVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_dht_records_received",
+        "Number of DHT records received from the peers, by whether they have been added to the routing table",
+        &["added"],
+    )
+    .unwrap()
+});
+
 pub(crate) static REQUEST_COUNT_BY_TYPE_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_requests_count_by_type_total",
@@ -228,6 +293,13 @@ pub(crate) static REQUEST_COUNT_BY_TYPE_TOTAL: LazyLock<IntCounterVec> = LazyLoc
     )
     .unwrap()
 });
+pub(crate) static TRAFFIC_RECORDER_DROPPED_MESSAGES: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_traffic_recorder_dropped_messages",
+        "Messages left out of the traffic recording, because the file was written too slowly",
+    )
+    .unwrap()
+});
 
 // Routing table metrics
 pub(crate) static ROUTING_TABLE_RECALCULATIONS: LazyLock<IntCounter> = LazyLock::new(|| {
@@ -367,6 +439,49 @@ pub(crate) static ALREADY_CONNECTED_ACCOUNT: LazyLock<IntCounter> = LazyLock::ne
     .unwrap()
 });
 
+pub(crate) static SEEN_MESSAGES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_seen_messages",
+        "Number of hashes in the cache of recently seen messages, by kind of message",
+        &["kind"],
+    )
+    .unwrap()
+});
+
+pub(crate) static SEEN_MESSAGES_DUPLICATES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_seen_messages_duplicates",
+        "Number of messages recognized as duplicates of recently seen ones, by kind of message",
+        &["kind"],
+    )
+    .unwrap()
+});
+
+pub(crate) static SEEN_MESSAGES_TTL: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_seen_messages_ttl_ms",
+        "Current TTL of the hashes in the cache of recently seen messages",
+    )
+    .unwrap()
+});
+
+pub(crate) static LOCAL_IP_CHANGES: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_local_ip_changes",
+        "Number of times the local IP used for outbound traffic has changed",
+    )
+    .unwrap()
+});
+
+pub(crate) static MIGRATED_CONNECTIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_migrated_connections",
+        "Number of connections closed because the IP of one of their ends has changed",
+        &["side"],
+    )
+    .unwrap()
+});
+
 pub(crate) static ACCOUNT_TO_PEER_LOOKUPS: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_account_to_peer_lookups",
@@ -380,6 +495,17 @@ pub(crate) static ACCOUNT_TO_PEER_LOOKUPS: LazyLock<IntCounterVec> = LazyLock::n
     .unwrap()
 });
 
+pub(crate) static DIRECT_TX_FORWARDING: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_direct_tx_forwarding",
+        "number of forwarded transactions by the state of the direct connection to the chunk producer",
+        // Status is one of "connected", "connecting", "connect_attempt",
+        // "rate_limited", "no_capacity" or "unknown_address".
+        &["status"],
+    )
+    .unwrap()
+});
+
 pub(crate) static NETWORK_ROUTED_MSG_DISTANCES: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_network_routed_msg_distances",
@@ -1,6 +1,7 @@
 /// Store module defines atomic DB operations on top of schema module.
 /// All transactions should be implemented within this module,
 /// in particular schema::StoreUpdate is not exported.
+use crate::network_protocol::SignedPeerRecord;
 use crate::types::ConnectionInfo;
 use near_primitives::network::AnnounceAccount;
 use near_primitives::types::AccountId;
@@ -78,6 +79,25 @@ impl Store {
     }
 }
 
+// DHT storage.
+impl Store {
+    #[tracing::instrument(
+        target = "network::store",
+        level = "trace",
+        "Store::set_dht_records",
+        skip_all
+    )]
+    pub fn set_dht_records(&mut self, records: &Vec<SignedPeerRecord>) -> Result<(), Error> {
+        let mut update = self.0.new_update();
+        update.set::<schema::DhtPeerRecords>(&(), records);
+        self.0.commit(update).map_err(Error)
+    }
+
+    pub fn get_dht_records(&self) -> Vec<SignedPeerRecord> {
+        self.0.get::<schema::DhtPeerRecords>(&()).unwrap_or(Some(vec![])).unwrap_or(vec![])
+    }
+}
+
 impl From<Arc<dyn near_store::db::Database>> for Store {
     fn from(store: Arc<dyn near_store::db::Database>) -> Self {
         Self(schema::Store::from(store))
@@ -1,3 +1,4 @@
+use crate::network_protocol::{PeerAddr, SignedPeerRecord};
 use crate::types as primitives;
 /// Schema module defines a type-safe access to the DB.
 /// It is a concise definition of key and value types
@@ -85,6 +86,38 @@ impl BorshRepr for EdgeRepr {
     }
 }
 
+/// A Borsh representation of the SignedPeerRecord.
+#[derive(BorshSerialize, BorshDeserialize, ProtocolSchema)]
+pub(super) struct SignedPeerRecordRepr {
+    peer_id: PeerId,
+    addr: std::net::SocketAddr,
+    /// UNIX timestamp in nanos.
+    timestamp: u64,
+    signature: Signature,
+}
+
+impl BorshRepr for SignedPeerRecordRepr {
+    type T = SignedPeerRecord;
+
+    fn to_repr(r: &SignedPeerRecord) -> Self {
+        Self {
+            peer_id: r.peer_addr.peer_id.clone(),
+            addr: r.peer_addr.addr,
+            timestamp: r.timestamp.unix_timestamp_nanos() as u64,
+            signature: r.signature.clone(),
+        }
+    }
+
+    fn from_repr(r: Self) -> Result<SignedPeerRecord, Error> {
+        Ok(SignedPeerRecord {
+            peer_addr: PeerAddr { peer_id: r.peer_id, addr: r.addr },
+            timestamp: time::Utc::from_unix_timestamp_nanos(r.timestamp as i128)
+                .map_err(invalid_data)?,
+            signature: r.signature,
+        })
+    }
+}
+
 /////////////////////////////////////////////
 // Columns
 
@@ -102,6 +135,13 @@ impl Column for RecentOutboundConnections {
     type Value = Vec<ConnectionInfoRepr>;
 }
 
+pub(super) struct DhtPeerRecords;
+impl Column for DhtPeerRecords {
+    const COL: DBCol = DBCol::DhtPeerRecords;
+    type Key = Borsh<()>;
+    type Value = Vec<SignedPeerRecordRepr>;
+}
+
 ////////////////////////////////////////////////////
 // Storage
 
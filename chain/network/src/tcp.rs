@@ -4,7 +4,10 @@ use anyhow::{anyhow, Context as _};
 use near_primitives::network::PeerId;
 use std::collections::HashMap;
 use std::fmt;
+use std::pin::Pin;
 use std::sync::Mutex;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
 const LISTENER_BACKLOG: u32 = 128;
 
@@ -34,9 +37,57 @@ pub(crate) enum StreamType {
     Outbound { peer_id: PeerId, tier: Tier },
 }
 
+/// Bytes of a connection: either a TCP socket, or a link of a simulated network
+/// (see `crate::sim`).
+#[derive(Debug)]
+pub(crate) enum Io {
+    Tcp(tokio::net::TcpStream),
+    Sim(tokio::io::DuplexStream),
+}
+
+impl AsyncRead for Io {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            Io::Sim(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Io {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            Io::Sim(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_flush(cx),
+            Io::Sim(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            Io::Sim(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Stream {
-    pub(crate) stream: tokio::net::TcpStream,
+    pub(crate) stream: Io,
     pub(crate) type_: StreamType,
     /// cached stream.local_addr()
     pub(crate) local_addr: std::net::SocketAddr,
@@ -80,7 +131,22 @@ impl Socket {
 
 impl Stream {
     fn new(stream: tokio::net::TcpStream, type_: StreamType) -> std::io::Result<Self> {
-        Ok(Self { peer_addr: stream.peer_addr()?, local_addr: stream.local_addr()?, stream, type_ })
+        Ok(Self {
+            peer_addr: stream.peer_addr()?,
+            local_addr: stream.local_addr()?,
+            stream: Io::Tcp(stream),
+            type_,
+        })
+    }
+
+    /// Stream over a link of a simulated network.
+    pub(crate) fn sim(
+        stream: tokio::io::DuplexStream,
+        type_: StreamType,
+        local_addr: std::net::SocketAddr,
+        peer_addr: std::net::SocketAddr,
+    ) -> Self {
+        Self { stream: Io::Sim(stream), type_, local_addr, peer_addr }
     }
 
     pub async fn connect(
@@ -206,10 +272,18 @@ impl ListenerAddr {
 
     /// TEST-ONLY: reserves a random port on localhost for a TCP listener.
     pub fn reserve_for_test() -> Self {
-        let guard = tokio::net::TcpSocket::new_v6().unwrap();
+        Self::reserve_for_test_on(std::net::Ipv6Addr::LOCALHOST.into())
+    }
+
+    /// TEST-ONLY: reserves a random port on the given local IP for a TCP listener.
+    pub fn reserve_for_test_on(ip: std::net::IpAddr) -> Self {
+        let guard = match ip {
+            std::net::IpAddr::V4(_) => tokio::net::TcpSocket::new_v4().unwrap(),
+            std::net::IpAddr::V6(_) => tokio::net::TcpSocket::new_v6().unwrap(),
+        };
         guard.set_reuseaddr(true).unwrap();
         guard.set_reuseport(true).unwrap();
-        guard.bind("[::1]:0".parse().unwrap()).unwrap();
+        guard.bind(std::net::SocketAddr::new(ip, 0)).unwrap();
         let addr = guard.local_addr().unwrap();
         RESERVED_LISTENER_ADDRS.lock().unwrap().insert(addr, guard);
         Self(addr)
@@ -217,11 +291,15 @@ impl ListenerAddr {
 
     /// Constructs a std::net::TcpListener, for usage outside of near_network.
     pub fn std_listener(&self) -> std::io::Result<std::net::TcpListener> {
-        self.listener()?.0.into_std()
+        self.tcp_listener()?.into_std()
     }
 
     /// Constructs a Listener out of ListenerAddr.
     pub(crate) fn listener(&self) -> std::io::Result<Listener> {
+        Ok(Listener::Tcp(self.tcp_listener()?))
+    }
+
+    fn tcp_listener(&self) -> std::io::Result<tokio::net::TcpListener> {
         let socket = match &self.0 {
             std::net::SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4()?,
             std::net::SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6()?,
@@ -231,7 +309,7 @@ impl ListenerAddr {
         }
         socket.set_reuseaddr(true)?;
         socket.bind(self.0)?;
-        Ok(Listener(socket.listen(LISTENER_BACKLOG)?))
+        socket.listen(LISTENER_BACKLOG)
     }
 
     pub(crate) fn is_ipv4(&self) -> bool {
@@ -239,11 +317,22 @@ impl ListenerAddr {
     }
 }
 
-pub(crate) struct Listener(tokio::net::TcpListener);
+pub(crate) enum Listener {
+    Tcp(tokio::net::TcpListener),
+    /// Receives the connections made over a simulated network.
+    Sim(tokio::sync::mpsc::UnboundedReceiver<Stream>),
+}
 
 impl Listener {
     pub async fn accept(&mut self) -> std::io::Result<Stream> {
-        let (stream, _) = self.0.accept().await?;
-        Stream::new(stream, StreamType::Inbound)
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Stream::new(stream, StreamType::Inbound)
+            }
+            Listener::Sim(recv) => recv.recv().await.ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotConnected, "simulated network is gone")
+            }),
+        }
     }
 }
@@ -9,6 +9,7 @@ pub use crate::network_protocol::{
     PartialEncodedChunkResponseMsg, PeerChainInfoV2, PeerInfo, SnapshotHostInfo, StateResponseInfo,
     StateResponseInfoV1, StateResponseInfoV2,
 };
+use crate::peer_manager::peer_store::PeerScore;
 use crate::routing::routing_table_view::RoutingTableInfo;
 pub use crate::state_sync::StateSyncResponse;
 use near_async::messaging::{AsyncSender, Sender};
@@ -25,6 +26,7 @@ use near_primitives::stateless_validation::partial_witness::PartialEncodedStateW
 use near_primitives::stateless_validation::state_witness::ChunkStateWitnessAck;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, BlockHeight, EpochHeight, ShardId};
+use near_primitives::views::ReachabilityView;
 use near_schema_checker_lib::ProtocolSchema;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
@@ -117,6 +119,9 @@ pub struct KnownPeerState {
     // Last time we tried to connect to this peer.
     // This data is not persisted in storage.
     pub last_outbound_attempt: Option<(time::Utc, Result<(), String>)>,
+    /// How useful the peer has been to us.
+    /// This data is not persisted in storage.
+    pub score: PeerScore,
 }
 
 impl KnownPeerState {
@@ -127,6 +132,7 @@ impl KnownPeerState {
             first_seen: now,
             last_seen: now,
             last_outbound_attempt: None,
+            score: PeerScore::default(),
         }
     }
 }
@@ -348,6 +354,8 @@ pub struct PeerChainInfo {
     pub tracked_shards: Vec<ShardId>,
     /// Denote if a node is running in archival mode or not.
     pub archival: bool,
+    /// Public URL of the peer's JSON-RPC server, if it advertises one.
+    pub rpc_url: Option<String>,
 }
 
 // Information about the connected peer that is shared with the rest of the system.
@@ -386,6 +394,8 @@ pub struct NetworkInfo {
     pub tier1_accounts_data: Vec<Arc<SignedAccountData>>,
     /// TIER1 connections.
     pub tier1_connections: Vec<ConnectedPeerInfo>,
+    /// Whether the node can be reached from the internet.
+    pub reachability: ReachabilityView,
 }
 
 #[derive(Debug, actix::MessageResponse, PartialEq, Eq)]
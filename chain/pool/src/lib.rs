@@ -1,5 +1,6 @@
+use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 
 use crate::types::{PoolKey, TransactionGroup, TransactionGroupIterator};
 
@@ -8,7 +9,7 @@ use near_o11y::metrics::prometheus::core::{AtomicI64, GenericGauge};
 use near_primitives::epoch_info::RngSeed;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, ShardId};
 use std::ops::Bound;
 
 mod metrics;
@@ -24,23 +25,47 @@ pub enum InsertTransactionResult {
     NoSpaceLeft,
 }
 
+/// Accounting of the transactions in the pool which target a single receiver shard.
+#[derive(Default)]
+struct ReceiverShardPool {
+    /// Number of transactions targeting the shard.
+    len: usize,
+    /// Total size of transactions targeting the shard measured in bytes.
+    transaction_size: u64,
+}
+
 /// Transaction pool: keeps track of transactions that were not yet accepted into the block chain.
+///
+/// The pool is split into sub-pools by the shard of the transaction receiver, each with its own
+/// size limit. Transactions targeting a congested shard can't be included into chunks until the
+/// congestion goes away, so they are kept in the pool, but they can only fill up the sub-pool of
+/// their receiver shard and never take the space of transactions targeting healthy shards.
+/// Transactions of all sub-pools are still grouped by the signer key, because the pool iterator
+/// has to return the transactions of every access key in the nonce order.
 pub struct TransactionPool {
     /// Transactions are grouped by a pair of (account ID, signer public key).
     /// NOTE: It's more efficient on average to keep transactions unsorted and with potentially
     /// conflicting nonce than to create a BTreeMap for every transaction.
     transactions: BTreeMap<PoolKey, Vec<SignedTransaction>>,
-    /// Set of all hashes to quickly check if the given transaction is in the pool.
-    unique_transactions: HashSet<CryptoHash>,
+    /// Receiver shard and size of every transaction in the pool by its hash. Used to quickly check
+    /// if the given transaction is in the pool and to update the sub-pool of the transaction when
+    /// it's removed.
+    unique_transactions: HashMap<CryptoHash, (ShardId, u64)>,
+    /// Sub-pools by the shard of the transaction receiver.
+    receiver_shard_pools: BTreeMap<ShardId, ReceiverShardPool>,
     /// A uniquely generated key seed to randomize PoolKey order.
     key_seed: RngSeed,
     /// The key after which the pool iterator starts. Doesn't have to be present in the pool.
     last_used_key: PoolKey,
     /// If set, new transactions that bring the size of the pool over this limit will be rejected.
     total_transaction_size_limit: Option<u64>,
+    /// If set, new transactions that bring the size of the sub-pool of their receiver shard over
+    /// this limit will be rejected.
+    receiver_shard_transaction_size_limit: Option<u64>,
     /// Total size of transactions in the pool measured in bytes.
     total_transaction_size: u64,
     /// Metrics tracked for transaction pool.
+    metrics_label: String,
     transaction_pool_count_metric: GenericGauge<AtomicI64>,
     transaction_pool_size_metric: GenericGauge<AtomicI64>,
 }
@@ -49,6 +74,7 @@ impl TransactionPool {
     pub fn new(
         key_seed: RngSeed,
         total_transaction_size_limit: Option<u64>,
+        receiver_shard_transaction_size_limit: Option<u64>,
         metrics_label: &str,
     ) -> Self {
         let transaction_pool_count_metric =
@@ -62,10 +88,13 @@ impl TransactionPool {
         Self {
             key_seed,
             transactions: BTreeMap::new(),
-            unique_transactions: HashSet::new(),
+            unique_transactions: HashMap::new(),
+            receiver_shard_pools: BTreeMap::new(),
             last_used_key: CryptoHash::default(),
             total_transaction_size_limit,
+            receiver_shard_transaction_size_limit,
             total_transaction_size: 0,
+            metrics_label: metrics_label.to_string(),
             transaction_pool_count_metric,
             transaction_pool_size_metric,
         }
@@ -78,31 +107,48 @@ impl TransactionPool {
         hash(&v)
     }
 
-    /// Inserts a signed transaction that passed validation into the pool.
+    /// Inserts a signed transaction that passed validation into the pool. `receiver_shard_id` is
+    /// the shard of the transaction receiver, which determines the sub-pool of the transaction.
     #[must_use]
     pub fn insert_transaction(
         &mut self,
         signed_transaction: SignedTransaction,
+        receiver_shard_id: ShardId,
     ) -> InsertTransactionResult {
-        if !self.unique_transactions.insert(signed_transaction.get_hash()) {
+        let tx_hash = signed_transaction.get_hash();
+        if self.unique_transactions.contains_key(&tx_hash) {
             // The hash of this transaction was already seen, skip it.
             return InsertTransactionResult::Duplicate;
         }
+        let tx_size = signed_transaction.get_size();
         // We never expect the total size to go over `u64` during real operation as that would
         // be more than 10^9 GiB of RAM consumed for transaction pool, so panicing here is intended
         // to catch a logic error in estimation of transaction size.
         let new_total_transaction_size = self
             .total_transaction_size
-            .checked_add(signed_transaction.get_size())
+            .checked_add(tx_size)
             .expect("Total transaction size is too large");
         if let Some(limit) = self.total_transaction_size_limit {
             if new_total_transaction_size > limit {
                 return InsertTransactionResult::NoSpaceLeft;
             }
         }
+        let receiver_shard_pool = self.receiver_shard_pools.entry(receiver_shard_id).or_default();
+        let new_receiver_shard_transaction_size = receiver_shard_pool
+            .transaction_size
+            .checked_add(tx_size)
+            .expect("Receiver shard transaction size is too large");
+        if let Some(limit) = self.receiver_shard_transaction_size_limit {
+            if new_receiver_shard_transaction_size > limit {
+                return InsertTransactionResult::NoSpaceLeft;
+            }
+        }
 
         // At this point transaction is accepted to the pool.
+        receiver_shard_pool.len += 1;
+        receiver_shard_pool.transaction_size = new_receiver_shard_transaction_size;
         self.total_transaction_size = new_total_transaction_size;
+        self.unique_transactions.insert(tx_hash, (receiver_shard_id, tx_size));
         let signer_id = signed_transaction.transaction.signer_id();
         let signer_public_key = signed_transaction.transaction.public_key();
         self.transactions
@@ -112,6 +158,7 @@ impl TransactionPool {
 
         self.transaction_pool_count_metric.inc();
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
+        self.update_receiver_shard_metrics(receiver_shard_id);
         InsertTransactionResult::Success
     }
 
@@ -130,7 +177,7 @@ impl TransactionPool {
         let mut grouped_transactions = HashMap::new();
         for tx in transactions {
             // If transaction is not present in the pool, skip it.
-            if !self.unique_transactions.remove(&tx.get_hash()) {
+            if !self.forget_transaction(&tx.get_hash()) {
                 continue;
             }
 
@@ -143,18 +190,7 @@ impl TransactionPool {
         }
         for (key, hashes) in grouped_transactions {
             if let Entry::Occupied(mut entry) = self.transactions.entry(key) {
-                entry.get_mut().retain(|tx| {
-                    if !hashes.contains(&tx.get_hash()) {
-                        return true;
-                    }
-                    // See the comment above where we increase the size for reasoning why panicing
-                    // here catches a logic error.
-                    self.total_transaction_size = self
-                        .total_transaction_size
-                        .checked_sub(tx.get_size())
-                        .expect("Total transaction size dropped below zero");
-                    false
-                });
+                entry.get_mut().retain(|tx| !hashes.contains(&tx.get_hash()));
                 if entry.get().is_empty() {
                     entry.remove_entry();
                 }
@@ -162,8 +198,50 @@ impl TransactionPool {
         }
 
         // We can update metrics only once for the whole batch of transactions.
+        self.update_metrics();
+    }
+
+    /// Removes the transaction with the given hash from the size accounting of the pool and of its
+    /// sub-pool. Returns false if the transaction is not in the pool.
+    fn forget_transaction(&mut self, tx_hash: &CryptoHash) -> bool {
+        let Some((receiver_shard_id, tx_size)) = self.unique_transactions.remove(tx_hash) else {
+            return false;
+        };
+        // See the comment in `insert_transaction` where we increase the size for reasoning why
+        // panicing here catches a logic error.
+        self.total_transaction_size = self
+            .total_transaction_size
+            .checked_sub(tx_size)
+            .expect("Total transaction size dropped below zero");
+        let receiver_shard_pool = self
+            .receiver_shard_pools
+            .get_mut(&receiver_shard_id)
+            .expect("Sub-pool of a transaction in the pool must exist");
+        receiver_shard_pool.len -= 1;
+        receiver_shard_pool.transaction_size = receiver_shard_pool
+            .transaction_size
+            .checked_sub(tx_size)
+            .expect("Receiver shard transaction size dropped below zero");
+        true
+    }
+
+    fn update_metrics(&self) {
         self.transaction_pool_count_metric.set(self.unique_transactions.len() as i64);
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
+        for receiver_shard_id in self.receiver_shard_pools.keys() {
+            self.update_receiver_shard_metrics(*receiver_shard_id);
+        }
+    }
+
+    fn update_receiver_shard_metrics(&self, receiver_shard_id: ShardId) {
+        let receiver_shard_pool = &self.receiver_shard_pools[&receiver_shard_id];
+        let labels = [self.metrics_label.as_str(), &receiver_shard_id.to_string()];
+        metrics::TRANSACTION_POOL_RECEIVER_SHARD_COUNT
+            .with_label_values(&labels)
+            .set(receiver_shard_pool.len as i64);
+        metrics::TRANSACTION_POOL_RECEIVER_SHARD_SIZE
+            .with_label_values(&labels)
+            .set(receiver_shard_pool.transaction_size as i64);
     }
 
     /// Returns the number of unique transactions in the pool.
@@ -171,10 +249,25 @@ impl TransactionPool {
         self.unique_transactions.len()
     }
 
+    /// Returns all transactions in the pool, in no particular order.
+    pub fn transactions(&self) -> impl Iterator<Item = &SignedTransaction> {
+        self.transactions.values().flatten()
+    }
+
     /// Returns the total size of transactions in the pool in bytes.
     pub fn transaction_size(&self) -> u64 {
         self.total_transaction_size
     }
+
+    /// Returns the number of transactions in the sub-pool of the given receiver shard.
+    pub fn receiver_shard_len(&self, receiver_shard_id: ShardId) -> usize {
+        self.receiver_shard_pools.get(&receiver_shard_id).map_or(0, |pool| pool.len)
+    }
+
+    /// Returns the total size of transactions in the sub-pool of the given receiver shard in bytes.
+    pub fn receiver_shard_transaction_size(&self, receiver_shard_id: ShardId) -> u64 {
+        self.receiver_shard_pools.get(&receiver_shard_id).map_or(0, |pool| pool.transaction_size)
+    }
 }
 
 /// PoolIterator is a structure to pull transactions from the pool.
@@ -184,112 +277,172 @@ pub struct PoolIteratorWrapper<'a> {
     /// Mutable reference to the pool, to avoid exposing it while the iterator exists.
     pool: &'a mut TransactionPool,
 
-    /// Queue of transaction groups. Each group there is sorted by nonce.
-    sorted_groups: VecDeque<TransactionGroup>,
+    /// Transaction groups pulled from the pool. Each group there is sorted by nonce.
+    sorted_groups: HashMap<PoolKey, TransactionGroup>,
+
+    /// The groups in the order the iterator returns them. Filled with every group of the pool on
+    /// the first call to `next()`.
+    queue: Option<BinaryHeap<QueuedGroup>>,
+
+    /// Number of groups queued so far.
+    num_queued: u64,
+
+    /// The group returned by the last call to `next()`. It is queued again on the next call, once
+    /// the caller has pulled its transactions.
+    last_group: Option<PoolKey>,
+
+    /// Deferred transactions of the groups which were exhausted by the iterator. They are returned
+    /// to the pool when the iterator is dropped, so that they are not iterated over again.
+    deferred_groups: Vec<(PoolKey, Vec<SignedTransaction>)>,
+}
+
+/// A transaction group waiting in the queue of the pool iterator.
+///
+/// The groups are ordered by the priority fee of their next transaction, highest first. A high
+/// priority fee of a transaction only counts once the transactions with smaller nonces of the same
+/// access key are pulled. Groups with the same fee are returned in the order they were queued.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct QueuedGroup {
+    priority_fee: u64,
+    order: Reverse<u64>,
+    key: PoolKey,
+}
+
+fn priority_fee(transaction: &SignedTransaction) -> u64 {
+    transaction.transaction.priority_fee().unwrap_or_default()
 }
 
 impl<'a> PoolIteratorWrapper<'a> {
     pub fn new(pool: &'a mut TransactionPool) -> Self {
-        Self { pool, sorted_groups: Default::default() }
+        Self {
+            pool,
+            sorted_groups: Default::default(),
+            queue: None,
+            num_queued: 0,
+            last_group: None,
+            deferred_groups: Default::default(),
+        }
+    }
+
+    fn queue(&mut self, key: PoolKey, priority_fee: u64) {
+        let order = Reverse(self.num_queued);
+        self.num_queued += 1;
+        let queue = self.queue.as_mut().expect("queue is initialized on the first call");
+        queue.push(QueuedGroup { priority_fee, order, key });
+    }
+
+    /// Queues every group of the pool, starting after the last used key so that the groups are
+    /// taken round robin across chunks.
+    fn queue_pool_groups(&mut self) {
+        self.queue = Some(BinaryHeap::with_capacity(self.pool.transactions.len()));
+        let last_used_key = self.pool.last_used_key;
+        let groups: Vec<_> = self
+            .pool
+            .transactions
+            .range((Bound::Excluded(last_used_key), Bound::Unbounded))
+            .chain(self.pool.transactions.range(..=last_used_key))
+            .map(|(key, transactions)| {
+                let next = transactions.iter().min_by_key(|tx| tx.transaction.nonce());
+                (*key, next.map_or(0, priority_fee))
+            })
+            .collect();
+        for (key, priority_fee) in groups {
+            self.queue(key, priority_fee);
+        }
+    }
+
+    /// Queues the group again after the caller pulled its transactions, or discards it if it has
+    /// none left.
+    fn requeue(&mut self, key: PoolKey) {
+        let group = self.sorted_groups.get(&key).expect("returned groups are kept");
+        if let Some(next) = group.peek_next() {
+            let priority_fee = priority_fee(next);
+            self.queue(key, priority_fee);
+            return;
+        }
+        let group = self.sorted_groups.remove(&key).expect("just checked existence");
+        for hash in group.removed_transaction_hashes {
+            self.pool.forget_transaction(&hash);
+        }
+        if !group.deferred_transactions.is_empty() {
+            self.deferred_groups.push((group.key, group.deferred_transactions));
+        }
+
+        self.pool.transaction_pool_count_metric.set(self.pool.unique_transactions.len() as i64);
+        self.pool.transaction_pool_size_metric.set(self.pool.transaction_size() as i64);
     }
 }
 
 /// The iterator works with the following algorithm:
-/// On next(), the iterator tries to get a transaction group from the pool, sorts transactions in
-/// it, and add it to the back of the sorted groups queue.
-/// Remembers the last used key, so it can continue from the next key.
+/// On the first next(), the iterator queues every transaction group of the pool, starting after
+/// the last used key.
 ///
-/// If the pool is empty, the iterator gets the group from the front of the sorted groups queue.
+/// On every next(), the iterator first queues again the group it returned last time, behind the
+/// groups with the same priority fee. If this group is empty (no transactions left inside), then
+/// the iterator discards it instead and updates `unique_transactions` in the pool. Deferred
+/// transactions of the group are kept aside until the iterator is dropped.
 ///
-/// If this group is empty (no transactions left inside), then the iterator discards it and
-/// updates `unique_transactions` in the pool. Then gets the next one.
+/// Then it takes the group with the highest priority fee from the queue. If the group is still in
+/// the pool, the iterator removes it from there, sorts transactions in it and remembers its key as
+/// the last used key. The iterator returns a mutable reference to this group.
 ///
-/// Once a non-empty group is found, this group is pushed to the back of the sorted groups queue
-/// and the iterator returns a mutable reference to this group.
+/// Without priority fees this takes the groups of the pool round robin, one transaction from each,
+/// and then goes round robin over the groups which still have transactions left.
 ///
-/// If the sorted groups queue is empty, the iterator returns None.
+/// If the queue is empty, the iterator returns None.
 ///
 /// When the iterator is dropped, `unique_transactions` in the pool is updated for every group.
-/// And all non-empty group from the sorted groups queue are inserted back into the pool.
+/// And all non-empty groups taken from the pool are inserted back into the pool together with the
+/// deferred transactions.
 impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
     fn next(&mut self) -> Option<&mut TransactionGroup> {
-        if !self.pool.transactions.is_empty() {
-            let key = *self
-                .pool
-                .transactions
-                .range((Bound::Excluded(self.pool.last_used_key), Bound::Unbounded))
-                .next()
-                .map(|(k, _v)| k)
-                .unwrap_or_else(|| {
-                    self.pool
-                        .transactions
-                        .keys()
-                        .next()
-                        .expect("we've just checked that the map is not empty")
-                });
-            self.pool.last_used_key = key;
+        if self.queue.is_none() {
+            self.queue_pool_groups();
+        }
+        if let Some(key) = self.last_group.take() {
+            self.requeue(key);
+        }
+        let QueuedGroup { key, .. } = self.queue.as_mut().expect("just initialized").pop()?;
+        if !self.sorted_groups.contains_key(&key) {
             let mut transactions =
-                self.pool.transactions.remove(&key).expect("just checked existence");
-            transactions.sort_by_key(|st| std::cmp::Reverse(st.transaction.nonce()));
-            self.sorted_groups.push_back(TransactionGroup {
+                self.pool.transactions.remove(&key).expect("queued groups are in the pool");
+            transactions.sort_by_key(|st| Reverse(st.transaction.nonce()));
+            self.pool.last_used_key = key;
+            self.sorted_groups.insert(
                 key,
-                transactions,
-                removed_transaction_hashes: vec![],
-                removed_transaction_size: 0,
-            });
-            Some(self.sorted_groups.back_mut().expect("just pushed"))
-        } else {
-            while let Some(sorted_group) = self.sorted_groups.pop_front() {
-                if sorted_group.transactions.is_empty() {
-                    for hash in sorted_group.removed_transaction_hashes {
-                        self.pool.unique_transactions.remove(&hash);
-                    }
-                    // See the comment in `insert_transaction` where we increase the size for reasoning
-                    // why panicing here catches a logic error.
-                    self.pool.total_transaction_size = self
-                        .pool
-                        .total_transaction_size
-                        .checked_sub(sorted_group.removed_transaction_size)
-                        .expect("Total transaction size dropped below zero");
-
-                    self.pool
-                        .transaction_pool_count_metric
-                        .set(self.pool.unique_transactions.len() as i64);
-                    self.pool.transaction_pool_size_metric.set(self.pool.transaction_size() as i64);
-                } else {
-                    self.sorted_groups.push_back(sorted_group);
-                    return Some(self.sorted_groups.back_mut().expect("just pushed"));
-                }
-            }
-            None
+                TransactionGroup {
+                    key,
+                    transactions,
+                    removed_transaction_hashes: vec![],
+                    deferred_transactions: vec![],
+                },
+            );
         }
+        self.last_group = Some(key);
+        self.sorted_groups.get_mut(&key)
     }
 }
 
-/// When a pool iterator is dropped, all remaining non empty transaction groups from the sorted
-/// groups queue are inserted back into the pool. And removed transactions hashes from groups are
-/// removed from the pool's unique_transactions.
+/// When a pool iterator is dropped, all remaining non empty transaction groups taken from the pool
+/// are inserted back into the pool, as well as the deferred transactions. And removed
+/// transactions hashes from groups are removed from the pool's unique_transactions.
 impl<'a> Drop for PoolIteratorWrapper<'a> {
     fn drop(&mut self) {
-        for group in self.sorted_groups.drain(..) {
+        for (_, group) in self.sorted_groups.drain() {
             for hash in group.removed_transaction_hashes {
-                self.pool.unique_transactions.remove(&hash);
+                self.pool.forget_transaction(&hash);
             }
-            // See the comment in `insert_transaction` where we increase the size for reasoning
-            // why panicing here catches a logic error.
-            self.pool.total_transaction_size = self
-                .pool
-                .total_transaction_size
-                .checked_sub(group.removed_transaction_size)
-                .expect("Total transaction size dropped below zero");
-
-            if !group.transactions.is_empty() {
-                self.pool.transactions.insert(group.key, group.transactions);
+            let mut transactions = group.transactions;
+            transactions.extend(group.deferred_transactions);
+            if !transactions.is_empty() {
+                self.pool.transactions.entry(group.key).or_default().extend(transactions);
             }
         }
+        for (key, transactions) in self.deferred_groups.drain(..) {
+            self.pool.transactions.entry(key).or_default().extend(transactions);
+        }
         // We can update metrics only once for the whole batch of transactions.
-        self.pool.transaction_pool_count_metric.set(self.pool.unique_transactions.len() as i64);
-        self.pool.transaction_pool_size_metric.set(self.pool.transaction_size() as i64);
+        self.pool.update_metrics();
     }
 }
 
@@ -309,7 +462,7 @@ impl TransactionGroupIteratorWrapper {
                 key: PoolKey::default(),
                 transactions: vec![transaction.clone()],
                 removed_transaction_hashes: vec![],
-                removed_transaction_size: 0,
+                deferred_transactions: vec![],
             })
             .collect();
 
@@ -372,11 +525,11 @@ mod tests {
         mut transactions: Vec<SignedTransaction>,
         expected_weight: u32,
     ) -> (Vec<u64>, TransactionPool) {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, None, None, "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions {
-            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+            assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
         }
         (
             prepare_transactions(&mut pool, expected_weight)
@@ -482,12 +635,12 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, None, None, "");
         let mut rng = thread_rng();
         transactions.shuffle(&mut rng);
         for tx in transactions.clone() {
             println!("{:?}", tx);
-            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+            assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
         }
         assert_eq!(pool.len(), n as usize);
 
@@ -543,7 +696,7 @@ mod tests {
 
         for tx in transactions {
             assert!(matches!(
-                pool.insert_transaction(tx),
+                pool.insert_transaction(tx, 0),
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate
             ));
         }
@@ -581,7 +734,7 @@ mod tests {
 
         for tx in transactions {
             assert!(matches!(
-                pool.insert_transaction(tx),
+                pool.insert_transaction(tx, 0),
                 InsertTransactionResult::Success | InsertTransactionResult::Duplicate
             ));
         }
@@ -596,13 +749,13 @@ mod tests {
 
     #[test]
     fn test_transaction_pool_size() {
-        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        let mut pool = TransactionPool::new(TEST_SEED, None, None, "");
         let transactions = generate_transactions("alice.near", "alice.near", 1, 100);
         let mut total_transaction_size = 0;
         // Adding transactions increases the size.
         for tx in transactions.clone() {
             total_transaction_size += tx.get_size();
-            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+            assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
             assert_eq!(pool.transaction_size(), total_transaction_size);
         }
         // Removing transactions decreases the size.
@@ -614,19 +767,139 @@ mod tests {
         assert_eq!(pool.transaction_size(), 0);
     }
 
+    #[test]
+    fn test_transactions() {
+        let mut pool = TransactionPool::new(TEST_SEED, None, None, "");
+        let mut transactions = generate_transactions("alice.near", "alice.near", 1, 10);
+        transactions.extend(generate_transactions("bob.near", "bob.near", 1, 10));
+        for tx in transactions.clone() {
+            assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
+        }
+        let mut hashes: Vec<_> = pool.transactions().map(|tx| tx.get_hash()).collect();
+        let mut expected: Vec<_> = transactions.iter().map(|tx| tx.get_hash()).collect();
+        hashes.sort();
+        expected.sort();
+        assert_eq!(hashes, expected);
+    }
+
     #[test]
     fn test_transaction_pool_size_limit() {
         let transactions = generate_transactions("alice.near", "alice.near", 1, 100);
         // Each transaction is at least 1 byte in size, so the last transaction will not fit.
         let pool_size_limit =
             transactions.iter().map(|tx| tx.get_size()).sum::<u64>().checked_sub(1).unwrap();
-        let mut pool = TransactionPool::new(TEST_SEED, Some(pool_size_limit), "");
+        let mut pool = TransactionPool::new(TEST_SEED, Some(pool_size_limit), None, "");
         for (i, tx) in transactions.iter().cloned().enumerate() {
             if i + 1 < transactions.len() {
-                assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+                assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
             } else {
-                assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::NoSpaceLeft);
+                assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::NoSpaceLeft);
             }
         }
     }
+
+    #[test]
+    fn test_receiver_shard_size_limit() {
+        let congested = generate_transactions("alice.near", "alice.near", 1, 10);
+        let healthy = generate_transactions("bob.near", "bob.near", 1, 10);
+        let receiver_shard_size_limit = congested.iter().map(|tx| tx.get_size()).sum::<u64>();
+        let mut pool = TransactionPool::new(TEST_SEED, None, Some(receiver_shard_size_limit), "");
+        for tx in congested.iter().cloned() {
+            assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
+        }
+        // The sub-pool of shard 0 is full, but the other sub-pools are not affected.
+        let extra = generate_transactions("carol.near", "carol.near", 1, 1).pop().unwrap();
+        assert_eq!(pool.insert_transaction(extra.clone(), 0), InsertTransactionResult::NoSpaceLeft);
+        for tx in healthy {
+            assert_eq!(pool.insert_transaction(tx, 1), InsertTransactionResult::Success);
+        }
+        assert_eq!(pool.receiver_shard_len(0), 10);
+        assert_eq!(pool.receiver_shard_len(1), 10);
+        assert_eq!(pool.receiver_shard_transaction_size(0), receiver_shard_size_limit);
+
+        // Removing transactions makes space only in their sub-pool.
+        pool.remove_transactions(&congested[..1]);
+        assert_eq!(pool.receiver_shard_len(0), 9);
+        assert_eq!(pool.insert_transaction(extra, 0), InsertTransactionResult::Success);
+    }
+
+    /// Deferred transactions are not returned again by the same iterator, but stay in the pool.
+    #[test]
+    fn test_deferred_transactions_stay_in_pool() {
+        let mut transactions = generate_transactions("alice.near", "alice.near", 1, 5);
+        let healthy = generate_transactions("bob.near", "bob.near", 1, 5);
+        let mut pool = TransactionPool::new(TEST_SEED, None, None, "");
+        for tx in transactions.iter().cloned() {
+            assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
+        }
+        for tx in healthy.iter().cloned() {
+            assert_eq!(pool.insert_transaction(tx, 1), InsertTransactionResult::Success);
+        }
+
+        let mut included = vec![];
+        let mut pool_iter = pool.pool_iterator();
+        while let Some(group) = pool_iter.next() {
+            while let Some(tx) = group.next() {
+                if tx.transaction.signer_id().as_str() == "alice.near" {
+                    group.defer(tx);
+                } else {
+                    included.push(tx);
+                    break;
+                }
+            }
+        }
+        drop(pool_iter);
+
+        assert_eq!(included, healthy);
+        assert_eq!(pool.len(), 5);
+        assert_eq!(pool.receiver_shard_len(0), 5);
+        assert_eq!(pool.receiver_shard_len(1), 0);
+        assert_eq!(pool.transaction_size(), pool.receiver_shard_transaction_size(0));
+        let mut remaining = prepare_transactions(&mut pool, 10);
+        remaining.sort_by_key(|tx| tx.transaction.nonce());
+        transactions.sort_by_key(|tx| tx.transaction.nonce());
+        assert_eq!(remaining, transactions);
+    }
+
+    /// Transactions with a higher priority fee are returned first, but never before the
+    /// transactions with smaller nonces of the same access key.
+    #[test]
+    fn test_pool_iterator_priority_fee() {
+        let transaction = |signer_id: &str, nonce, priority_fee| {
+            let signer_id: AccountId = signer_id.parse().unwrap();
+            let signer = Arc::new(
+                InMemorySigner::from_seed(signer_id.clone(), KeyType::ED25519, signer_id.as_str())
+                    .into(),
+            );
+            SignedTransaction::from_actions_v1(
+                nonce,
+                signer_id,
+                "bob.near".parse().unwrap(),
+                &*signer,
+                vec![],
+                CryptoHash::default(),
+                priority_fee,
+            )
+        };
+        let mut pool = TransactionPool::new(TEST_SEED, None, None, "");
+        for tx in [
+            transaction("alice.near", 1, 0),
+            transaction("alice.near", 2, 100),
+            transaction("carol.near", 1, 10),
+            transaction("dave.near", 1, 0),
+            transaction("eve.near", 1, 50),
+        ] {
+            assert_eq!(pool.insert_transaction(tx, 0), InsertTransactionResult::Success);
+        }
+
+        let order: Vec<_> = prepare_transactions(&mut pool, 5)
+            .iter()
+            .map(|tx| (tx.transaction.signer_id().to_string(), tx.transaction.nonce()))
+            .collect();
+        let expected = |signer_id: &str, nonce| (signer_id.to_string(), nonce);
+        assert_eq!(order[..2], [expected("eve.near", 1), expected("carol.near", 1)]);
+        let alice = order.iter().position(|tx| *tx == expected("alice.near", 1)).unwrap();
+        assert_eq!(order[alice + 1], expected("alice.near", 2));
+        assert!(order.contains(&expected("dave.near", 1)));
+    }
 }
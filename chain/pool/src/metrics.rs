@@ -18,3 +18,21 @@ pub static TRANSACTION_POOL_SIZE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
     )
     .unwrap()
 });
+
+pub static TRANSACTION_POOL_RECEIVER_SHARD_COUNT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_int_gauge_vec(
+        "near_transaction_pool_receiver_shard_entries",
+        "Number of transactions in a given shard pool which target a given receiver shard",
+        &["shard_id", "receiver_shard_id"],
+    )
+    .unwrap()
+});
+
+pub static TRANSACTION_POOL_RECEIVER_SHARD_SIZE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    near_o11y::metrics::try_create_int_gauge_vec(
+        "near_transaction_pool_receiver_shard_size",
+        "Total size in bytes of transactions in a given shard pool which target a given receiver shard",
+        &["shard_id", "receiver_shard_id"],
+    )
+    .unwrap()
+});
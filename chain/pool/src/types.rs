@@ -3,7 +3,8 @@ use near_primitives::transaction::SignedTransaction;
 
 /// Trait acts like an iterator. It iterates over transactions groups by returning mutable
 /// references to them. Each transaction group implements a draining iterator to pull transactions.
-/// The order of the transaction groups is round robin scheduling.
+/// The groups whose next transaction has a higher priority fee come first, the order of the
+/// groups with the same fee is round robin scheduling.
 pub trait TransactionGroupIterator {
     fn next(&mut self) -> Option<&mut TransactionGroup>;
 }
@@ -20,8 +21,9 @@ pub struct TransactionGroup {
     pub(crate) transactions: Vec<SignedTransaction>,
     /// Hashes of the transactions that were pulled from the group using `.next()`.
     pub(crate) removed_transaction_hashes: Vec<CryptoHash>,
-    /// Total size of transactions that were pulled from the group using `.next()`.
-    pub(crate) removed_transaction_size: u64,
+    /// Transactions that were pulled from the group using `.next()` and then returned with
+    /// `.defer()`. They stay in the pool, but are not returned by `.next()` again.
+    pub(crate) deferred_transactions: Vec<SignedTransaction>,
 }
 
 impl TransactionGroup {
@@ -30,13 +32,25 @@ impl TransactionGroup {
     pub fn next(&mut self) -> Option<SignedTransaction> {
         if let Some(tx) = self.transactions.pop() {
             self.removed_transaction_hashes.push(tx.get_hash());
-            self.removed_transaction_size += tx.get_size();
             Some(tx)
         } else {
             None
         }
     }
 
+    /// Returns a transaction pulled with `.next()` back to the pool without including it, for
+    /// example because its receiver shard is congested. The transaction is kept in the pool after
+    /// the iteration, but it won't be returned by this group again.
+    pub fn defer(&mut self, tx: SignedTransaction) {
+        let tx_hash = tx.get_hash();
+        if let Some(index) =
+            self.removed_transaction_hashes.iter().rposition(|hash| *hash == tx_hash)
+        {
+            self.removed_transaction_hashes.swap_remove(index);
+        }
+        self.deferred_transactions.push(tx);
+    }
+
     pub fn peek_next(&self) -> Option<&SignedTransaction> {
         self.transactions.last()
     }
@@ -369,6 +369,35 @@ impl From<NearActions> for Vec<crate::models::Operation> {
                     );
                 }
 
+                // Idempotency keys don't move any balance.
+                near_primitives::transaction::Action::IdempotencyKey(_) => {}
+
+                // The deposits of an atomic batch are moved by the receipts it
+                // sends, which are converted on their own.
+                near_primitives::transaction::Action::AtomicBatch(_) => {}
+
+                // Global contracts have no Rosetta operations. The storage
+                // cost burnt by a deploy shows up in the account balance changes.
+                near_primitives::transaction::Action::DeployGlobalContract(_) => {}
+                near_primitives::transaction::Action::UseGlobalContract(_) => {}
+
+                // Namespaces have no Rosetta operations, only the deposit of a
+                // namespaced call moves balance.
+                near_primitives::transaction::Action::DeployNamespacedContract(_) => {}
+                near_primitives::transaction::Action::NamespacedFunctionCall(action) => {
+                    let deposit = action.function_call.deposit;
+                    if deposit > 0 {
+                        operations.push(
+                            validated_operations::TransferOperation {
+                                account: sender_account_identifier.clone(),
+                                amount: -crate::models::Amount::from_yoctonear(deposit),
+                                predecessor_id: Some(sender_account_identifier.clone()),
+                            }
+                            .into_operation(crate::models::OperationIdentifier::new(&operations)),
+                        );
+                    }
+                }
+
                 near_primitives::transaction::Action::Stake(action) => {
                     operations.push(
                         validated_operations::StakeOperation {
@@ -883,6 +912,8 @@ mod tests {
                             permanent_storage_bytes: 0,
                             storage_paid_at: 0,
                             storage_usage: 200000,
+                            namespaces: Default::default(),
+                            global_contract: false,
                         },
                     },
                 },
@@ -900,6 +931,8 @@ mod tests {
                             permanent_storage_bytes: 0,
                             storage_paid_at: 0,
                             storage_usage: 200000,
+                            namespaces: Default::default(),
+                            global_contract: false,
                         },
                     },
                 },
@@ -915,6 +948,8 @@ mod tests {
                             permanent_storage_bytes: 0,
                             storage_paid_at: 0,
                             storage_usage: 200000,
+                            namespaces: Default::default(),
+                            global_contract: false,
                         },
                     },
                 },
@@ -932,6 +967,8 @@ mod tests {
                             permanent_storage_bytes: 0,
                             storage_paid_at: 0,
                             storage_usage: 200000,
+                            namespaces: Default::default(),
+                            global_contract: false,
                         },
                     },
                 },
@@ -947,6 +984,8 @@ mod tests {
                     permanent_storage_bytes: 0,
                     storage_paid_at: 0,
                     storage_usage: 200000,
+                    namespaces: Default::default(),
+                    global_contract: false,
                 },
             );
             accounts_previous_state.insert(
@@ -959,6 +998,8 @@ mod tests {
                     permanent_storage_bytes: 0,
                     storage_paid_at: 0,
                     storage_usage: 200000,
+                    namespaces: Default::default(),
+                    global_contract: false,
                 },
             );
             let transactions = super::transactions::convert_block_changes_to_transactions(
@@ -332,7 +332,7 @@ pub(crate) async fn query_account(
 > {
     let query = near_client::Query::new(
         block_id,
-        near_primitives::views::QueryRequest::ViewAccount { account_id },
+        near_primitives::views::QueryRequest::ViewAccount { account_id, include_proof: false },
     );
     let account_info_response = match view_client_addr.send(query.with_span_context()).await? {
         Ok(query_response) => query_response,
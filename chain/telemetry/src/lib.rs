@@ -1,11 +1,17 @@
 mod metrics;
 
 use awc::{Client, Connector};
-use futures::FutureExt;
 use near_async::messaging::{Actor, Handler};
 use near_async::time::{Duration, Instant};
+use near_crypto::SecretKey;
 use near_performance_metrics_macros::perf;
+use near_primitives::validator_signer::ValidatorSigner;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::ops::Sub;
+use std::rc::Rc;
+use std::sync::Arc;
 
 /// Timeout for establishing connection.
 const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
@@ -17,15 +23,37 @@ pub struct TelemetryConfig {
     #[serde(default = "default_reporting_interval")]
     #[serde(with = "near_async::time::serde_duration_as_std")]
     pub reporting_interval: Duration,
+    /// Fields of the payload to report, as dot separated paths, e.g. `chain`
+    /// or `system.cpu_usage`. Everything is reported if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Fields of the payload not to report, e.g. `chain.account_id`. Applied
+    /// after `include`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Maximum number of payloads kept for each endpoint while uploads to it
+    /// fail. Once the queue is full, the oldest payload is dropped.
+    #[serde(default = "default_retry_queue_size")]
+    pub retry_queue_size: usize,
 }
 
 fn default_reporting_interval() -> Duration {
     Duration::seconds(10)
 }
 
+fn default_retry_queue_size() -> usize {
+    10
+}
+
 impl Default for TelemetryConfig {
     fn default() -> Self {
-        Self { endpoints: vec![], reporting_interval: default_reporting_interval() }
+        Self {
+            endpoints: vec![],
+            reporting_interval: default_reporting_interval(),
+            include: vec![],
+            exclude: vec![],
+            retry_queue_size: default_retry_queue_size(),
+        }
     }
 }
 
@@ -34,11 +62,28 @@ impl Default for TelemetryConfig {
 #[rtype(result = "()")]
 pub struct TelemetryEvent {
     pub content: serde_json::Value,
+    /// Validators sign the reported payload, i.e. the content after applying
+    /// the filters of the config.
+    pub signer: Option<Arc<ValidatorSigner>>,
+}
+
+/// Payloads waiting to be uploaded to a single endpoint. Every endpoint has
+/// its own queue, so that a slow or unreachable endpoint doesn't affect the
+/// others.
+struct EndpointQueue {
+    endpoint: String,
+    /// Payloads with their sequence numbers, oldest first.
+    payloads: VecDeque<(u64, Value)>,
+    /// Whether a task is uploading the queued payloads.
+    uploading: bool,
 }
 
 pub struct TelemetryActor {
     config: TelemetryConfig,
     client: Client,
+    node_key: Option<SecretKey>,
+    queues: Vec<Rc<RefCell<EndpointQueue>>>,
+    next_payload_id: u64,
     last_telemetry_update: Instant,
 }
 
@@ -60,19 +105,59 @@ impl TelemetryActor {
                 );
             }
         }
+        for path in config.include.iter().chain(config.exclude.iter()) {
+            if path.split('.').any(str::is_empty) {
+                panic!("Invalid telemetry field path: {:?}", path);
+            }
+        }
 
         let client = Client::builder()
             .timeout(CONNECT_TIMEOUT)
             .connector(Connector::new().max_http_version(awc::http::Version::HTTP_11))
             .finish();
+        let queues = config
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                Rc::new(RefCell::new(EndpointQueue {
+                    endpoint: endpoint.clone(),
+                    payloads: VecDeque::new(),
+                    uploading: false,
+                }))
+            })
+            .collect();
         let reporting_interval = config.reporting_interval;
         Self {
             config,
             client,
+            node_key: None,
+            queues,
+            next_payload_id: 0,
             // Let the node report telemetry info at the startup.
             last_telemetry_update: Instant::now().sub(reporting_interval),
         }
     }
+
+    /// Signs every payload with the node key, adding a `node_signature`
+    /// field. This lets the receiver check that the payload comes from the
+    /// node with the reported `node_id`.
+    pub fn with_node_key(mut self, node_key: SecretKey) -> Self {
+        self.node_key = Some(node_key);
+        self
+    }
+
+    fn payload(&self, event: TelemetryEvent) -> Value {
+        let mut payload = filter_payload(event.content, &self.config.include, &self.config.exclude);
+        if let Some(signer) = &event.signer {
+            payload = signer.sign_telemetry(&payload);
+        }
+        if let Some(node_key) = &self.node_key {
+            let content =
+                serde_json::to_string(&payload).expect("Telemetry must serialize to JSON");
+            payload["node_signature"] = node_key.sign(content.as_bytes()).to_string().into();
+        }
+        payload
+    }
 }
 
 impl Handler<TelemetryEvent> for TelemetryActor {
@@ -85,30 +170,152 @@ impl Handler<TelemetryEvent> for TelemetryActor {
             // request per `self.config.reporting_interval`.
             return;
         }
-        for endpoint in self.config.endpoints.iter() {
-            let endpoint = endpoint.clone();
-            near_performance_metrics::actix::spawn(
-                "telemetry",
-                self.client
-                    .post(endpoint.clone())
-                    .insert_header(("Content-Type", "application/json"))
-                    .force_close() // See https://github.com/near/nearcore/pull/11914
-                    .send_json(&msg.content)
-                    .map(move |response| {
-                        let result = if let Err(error) = response {
-                            tracing::warn!(
-                                target: "telemetry",
-                                err = ?error,
-                                endpoint = ?endpoint,
-                                "Failed to send telemetry data");
-                            "failed"
-                        } else {
-                            "ok"
-                        };
-                        metrics::TELEMETRY_RESULT.with_label_values(&[result]).inc();
-                    }),
-            );
+        let payload = self.payload(msg);
+        let payload_id = self.next_payload_id;
+        self.next_payload_id += 1;
+        for queue in self.queues.iter() {
+            let mut queue_ref = queue.borrow_mut();
+            while queue_ref.payloads.len() >= self.config.retry_queue_size.max(1) {
+                queue_ref.payloads.pop_front();
+                metrics::TELEMETRY_DROPPED.with_label_values(&[&queue_ref.endpoint]).inc();
+            }
+            queue_ref.payloads.push_back((payload_id, payload.clone()));
+            if !queue_ref.uploading {
+                queue_ref.uploading = true;
+                near_performance_metrics::actix::spawn(
+                    "telemetry",
+                    upload_queued_payloads(self.client.clone(), queue.clone()),
+                );
+            }
         }
         self.last_telemetry_update = now;
     }
 }
+
+/// Uploads the queued payloads of the endpoint, oldest first. Stops at the
+/// first failure, the remaining payloads are retried together with the next
+/// reported one.
+async fn upload_queued_payloads(client: Client, queue: Rc<RefCell<EndpointQueue>>) {
+    loop {
+        let (endpoint, payload_id, payload) = {
+            let queue = queue.borrow();
+            let Some((payload_id, payload)) = queue.payloads.front() else { break };
+            (queue.endpoint.clone(), *payload_id, payload.clone())
+        };
+        let response = client
+            .post(endpoint.clone())
+            .insert_header(("Content-Type", "application/json"))
+            .force_close() // See https://github.com/near/nearcore/pull/11914
+            .send_json(&payload)
+            .await;
+        let uploaded = match response {
+            Ok(response) if response.status().is_success() => true,
+            Ok(response) => {
+                tracing::warn!(
+                    target: "telemetry",
+                    status = ?response.status(),
+                    ?endpoint,
+                    "Telemetry endpoint rejected the data");
+                false
+            }
+            Err(error) => {
+                tracing::warn!(
+                    target: "telemetry",
+                    err = ?error,
+                    ?endpoint,
+                    "Failed to send telemetry data");
+                false
+            }
+        };
+        metrics::TELEMETRY_RESULT
+            .with_label_values(&[if uploaded { "ok" } else { "failed" }])
+            .inc();
+        if !uploaded {
+            break;
+        }
+        // The payload may have been dropped from the full queue meanwhile.
+        let mut queue = queue.borrow_mut();
+        if queue.payloads.front().is_some_and(|(id, _)| *id == payload_id) {
+            queue.payloads.pop_front();
+        }
+    }
+    queue.borrow_mut().uploading = false;
+}
+
+/// Keeps only the `include` fields of the payload, if any, and removes the
+/// `exclude` fields from it.
+fn filter_payload(payload: Value, include: &[String], exclude: &[String]) -> Value {
+    let mut payload = if include.is_empty() {
+        payload
+    } else {
+        let mut filtered = Value::Object(Default::default());
+        for path in include {
+            if let Some(value) = payload.pointer(&json_pointer(path)) {
+                insert_field(&mut filtered, path, value.clone());
+            }
+        }
+        filtered
+    };
+    for path in exclude {
+        let (parent, field) = match path.rsplit_once('.') {
+            Some((parent, field)) => (json_pointer(parent), field),
+            None => (String::new(), path.as_str()),
+        };
+        if let Some(Value::Object(parent)) = payload.pointer_mut(&parent) {
+            parent.remove(field);
+        }
+    }
+    payload
+}
+
+fn json_pointer(path: &str) -> String {
+    path.split('.').map(|field| format!("/{}", field)).collect()
+}
+
+fn insert_field(payload: &mut Value, path: &str, value: Value) {
+    let mut current = payload;
+    let mut fields = path.split('.').peekable();
+    while let Some(field) = fields.next() {
+        let Value::Object(object) = current else { return };
+        if fields.peek().is_none() {
+            object.insert(field.to_string(), value);
+            return;
+        }
+        current = object.entry(field).or_insert_with(|| Value::Object(Default::default()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filter_payload;
+    use serde_json::json;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn test_filter_payload() {
+        let payload = json!({
+            "agent": {"name": "near-rs", "version": "1.0"},
+            "system": {"cpu_usage": 1.0, "memory_usage": 2},
+            "chain": {"node_id": "ed25519:abc", "account_id": "test.near", "num_peers": 3},
+        });
+        assert_eq!(filter_payload(payload.clone(), &[], &[]), payload);
+        assert_eq!(
+            filter_payload(payload.clone(), &paths(&["agent", "chain.num_peers", "missing"]), &[]),
+            json!({"agent": {"name": "near-rs", "version": "1.0"}, "chain": {"num_peers": 3}}),
+        );
+        assert_eq!(
+            filter_payload(payload.clone(), &[], &paths(&["system", "chain.account_id"])),
+            json!({
+                "agent": {"name": "near-rs", "version": "1.0"},
+                "chain": {"node_id": "ed25519:abc", "num_peers": 3},
+            }),
+        );
+        assert_eq!(
+            filter_payload(payload, &paths(&["chain"]), &paths(&["chain.node_id", "agent"])),
+            json!({"chain": {"account_id": "test.near", "num_peers": 3}}),
+        );
+    }
+}
@@ -9,3 +9,13 @@ pub(crate) static TELEMETRY_RESULT: LazyLock<near_o11y::metrics::IntCounterVec>
         )
         .unwrap()
     });
+
+pub(crate) static TELEMETRY_DROPPED: LazyLock<near_o11y::metrics::IntCounterVec> =
+    LazyLock::new(|| {
+        near_o11y::metrics::try_create_int_counter_vec(
+            "near_telemetry_dropped",
+            "Number of telemetry payloads dropped from the full retry queue of an endpoint",
+            &["endpoint"],
+        )
+        .unwrap()
+    });
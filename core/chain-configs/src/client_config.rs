@@ -2,6 +2,7 @@
 use crate::ExternalStorageLocation::GCS;
 use crate::MutableConfigValue;
 use bytesize::ByteSize;
+use near_crypto::PublicKey;
 use near_primitives::types::{
     AccountId, BlockHeight, BlockHeightDelta, Gas, NumBlocks, NumSeats, ShardId,
 };
@@ -25,12 +26,20 @@ pub enum LogSummaryStyle {
 /// Minimum number of epochs for which we keep store data
 pub const MIN_GC_NUM_EPOCHS_TO_KEEP: u64 = 3;
 
+/// Minimum number of epochs for which we keep store data with
+/// `GCConfig::deep_pruning`.  The previous epoch is needed to serve state sync
+/// and to validate the current one.
+pub const MIN_DEEP_PRUNING_NUM_EPOCHS_TO_KEEP: u64 = 2;
+
 /// Default number of epochs for which we keep store data
 pub const DEFAULT_GC_NUM_EPOCHS_TO_KEEP: u64 = 5;
 
 /// Default number of concurrent requests to external storage to fetch state parts.
 pub const DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL: u32 = 25;
 pub const DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL: u32 = 5;
+/// Default number of peers which have to fail to serve a state part before it
+/// is downloaded from external storage.
+pub const DEFAULT_STATE_SYNC_EXTERNAL_STORAGE_FALLBACK_THRESHOLD: u64 = 3;
 
 /// Configuration for garbage collection.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
@@ -50,6 +59,29 @@ pub struct GCConfig {
     /// How often gc should be run
     #[serde(with = "near_time::serde_duration_as_std")]
     pub gc_step_period: Duration,
+
+    /// Whether to also garbage collect the headers of the blocks below the
+    /// tail.  Only applies to non-archival nodes.  Headers grow forever
+    /// otherwise; the headers of the last blocks of the epochs are kept for
+    /// epoch sync and light clients are still served from the per-epoch light
+    /// client checkpoints.
+    pub prune_headers: bool,
+
+    /// Whether `gc_num_epochs_to_keep` may go below
+    /// `MIN_GC_NUM_EPOCHS_TO_KEEP`, down to
+    /// `MIN_DEEP_PRUNING_NUM_EPOCHS_TO_KEEP`.  For validators with small disks.
+    /// Blocks, chunks and outcomes of the pruned epochs can't be queried over
+    /// RPC anymore, and garbage collection waits for ongoing catchups.
+    pub deep_pruning: bool,
+
+    /// Whether to delete the chunk extras and execution outcomes of the
+    /// blocks on forks abandoned behind the final head right away, instead of
+    /// keeping them until forks clearing gets to the blocks.
+    pub clear_abandoned_forks: bool,
+
+    /// Adapts the number of blocks garbage collected at every step to the
+    /// load of the database.
+    pub throttle: GCThrottleConfig,
 }
 
 impl Default for GCConfig {
@@ -59,13 +91,82 @@ impl Default for GCConfig {
             gc_fork_clean_step: 100,
             gc_num_epochs_to_keep: DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
             gc_step_period: Duration::seconds(1),
+            prune_headers: false,
+            deep_pruning: false,
+            clear_abandoned_forks: true,
+            throttle: GCThrottleConfig::default(),
         }
     }
 }
 
 impl GCConfig {
     pub fn gc_num_epochs_to_keep(&self) -> u64 {
-        max(MIN_GC_NUM_EPOCHS_TO_KEEP, self.gc_num_epochs_to_keep)
+        let min_num_epochs_to_keep = if self.deep_pruning {
+            MIN_DEEP_PRUNING_NUM_EPOCHS_TO_KEEP
+        } else {
+            MIN_GC_NUM_EPOCHS_TO_KEEP
+        };
+        max(min_num_epochs_to_keep, self.gc_num_epochs_to_keep)
+    }
+}
+
+/// Deleting blocks leaves RocksDB with data to compact away, and once the
+/// compactions fall behind RocksDB delays every write, including the ones of
+/// block processing.  This happens when a lot is collected in a row, e.g. when
+/// an archival node has just moved to split storage and its hot database is
+/// collected down to the last epochs.  With the throttle, garbage collection
+/// collects fewer blocks while the compactions of the hot database are
+/// behind, and pauses while RocksDB stalls writes.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(default)]
+pub struct GCThrottleConfig {
+    pub enabled: bool,
+    /// Bytes the compactions of the hot database still have to rewrite above
+    /// which fewer blocks are collected at every step.
+    pub compaction_debt_soft_limit: ByteSize,
+    /// Bytes the compactions still have to rewrite at which garbage
+    /// collection pauses.  Should be below the
+    /// `soft_pending_compaction_bytes_limit` of RocksDB, 64 GiB by default,
+    /// at which RocksDB starts delaying writes.
+    pub compaction_debt_hard_limit: ByteSize,
+}
+
+impl Default for GCThrottleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            compaction_debt_soft_limit: ByteSize::gib(8),
+            compaction_debt_hard_limit: ByteSize::gib(32),
+        }
+    }
+}
+
+impl GCThrottleConfig {
+    /// Returns the number of blocks to collect at the next step, out of
+    /// `gc_blocks_limit`, given whether the database stalls writes and how
+    /// many bytes its compactions still have to rewrite.
+    pub fn gc_blocks_limit(
+        &self,
+        gc_blocks_limit: NumBlocks,
+        write_stalled: bool,
+        compaction_debt: u64,
+    ) -> NumBlocks {
+        if !self.enabled {
+            return gc_blocks_limit;
+        }
+        let soft_limit = self.compaction_debt_soft_limit.as_u64();
+        let hard_limit = self.compaction_debt_hard_limit.as_u64();
+        if write_stalled || compaction_debt >= hard_limit {
+            return 0;
+        }
+        if compaction_debt <= soft_limit {
+            return gc_blocks_limit;
+        }
+        // Scales down linearly between the limits, and collects at least a
+        // block so that garbage collection keeps going.
+        let headroom = (hard_limit - compaction_debt) as u128;
+        let range = (hard_limit - soft_limit) as u128;
+        ((gc_blocks_limit as u128 * headroom / range) as NumBlocks).max(1)
     }
 }
 
@@ -77,6 +178,10 @@ fn default_num_concurrent_requests_during_catchup() -> u32 {
     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL
 }
 
+fn default_external_storage_fallback_threshold() -> u64 {
+    DEFAULT_STATE_SYNC_EXTERNAL_STORAGE_FALLBACK_THRESHOLD
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct ExternalStorageConfig {
     /// Location of state parts.
@@ -89,6 +194,17 @@ pub struct ExternalStorageConfig {
     /// to reduce the performance impact of state sync.
     #[serde(default = "default_num_concurrent_requests_during_catchup")]
     pub num_concurrent_requests_during_catchup: u32,
+    /// Public keys of the validators trusted to dump state. If not empty, the
+    /// state header and the state parts are only accepted if they match a
+    /// manifest signed by one of these keys.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_dump_producers: Vec<PublicKey>,
+    /// State parts are requested from the peers first, and downloaded from
+    /// the external storage once this many peers failed to serve them, or if
+    /// no peer can serve them. With 0 the state parts are downloaded from the
+    /// external storage only.
+    #[serde(default = "default_external_storage_fallback_threshold")]
+    pub external_storage_fallback_threshold: u64,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -161,6 +277,9 @@ impl StateSyncConfig {
                 num_concurrent_requests: DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
                 num_concurrent_requests_during_catchup:
                     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL,
+                trusted_dump_producers: vec![],
+                external_storage_fallback_threshold:
+                    DEFAULT_STATE_SYNC_EXTERNAL_STORAGE_FALLBACK_THRESHOLD,
             }),
         }
     }
@@ -180,6 +299,11 @@ pub struct EpochSyncConfig {
     pub epoch_sync_accept_proof_max_horizon: BlockHeightDelta,
     #[serde(with = "near_time::serde_duration_as_std")]
     pub timeout_for_epoch_sync: Duration,
+    /// Don't serve epoch sync proofs to the peers. Deriving a proof reads the
+    /// headers of the last block of every epoch since genesis, so nodes short
+    /// on IO may opt out. Proofs are cached for the duration of an epoch.
+    #[serde(default)]
+    pub ignore_epoch_sync_network_requests: bool,
 }
 
 impl Default for EpochSyncConfig {
@@ -192,6 +316,7 @@ impl Default for EpochSyncConfig {
             epoch_sync_horizon: 216000,
             epoch_sync_accept_proof_max_horizon: 86400,
             timeout_for_epoch_sync: Duration::seconds(60),
+            ignore_epoch_sync_network_requests: false,
         }
     }
 }
@@ -330,6 +455,10 @@ pub fn default_transaction_pool_size_limit() -> Option<u64> {
     Some(100_000_000) // 100 MB.
 }
 
+pub fn default_transaction_pool_receiver_shard_size_limit() -> Option<u64> {
+    Some(50_000_000) // 50 MB.
+}
+
 pub fn default_tx_routing_height_horizon() -> BlockHeightDelta {
     4
 }
@@ -484,6 +613,9 @@ pub struct ClientConfig {
     /// Limit of the size of per-shard transaction pool measured in bytes. If not set, the size
     /// will be unbounded.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Limit of the size of transactions targeting a single receiver shard in the per-shard
+    /// transaction pool measured in bytes. If not set, the size will be unbounded.
+    pub transaction_pool_receiver_shard_size_limit: Option<u64>,
     // Allows more detailed logging, for example a list of orphaned blocks.
     pub enable_multiline_logging: bool,
     // Configuration for resharding.
@@ -516,6 +648,20 @@ pub struct ClientConfig {
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// If true, chunk producers run the checks performed by chunk validators against
+    /// every state witness they produce before distributing it. A witness which fails
+    /// the checks is not distributed and the failure is logged.
+    pub validate_produced_state_witnesses: bool,
+    /// Save statistics of the tracked shards at the end of each epoch to
+    /// DBCol::EpochShardStats.
+    pub save_epoch_shard_stats: bool,
+    /// Accounts for which the data passing through the node in state
+    /// witnesses is collected, see `GetWatchedAccounts`.
+    pub watched_accounts: Vec<AccountId>,
+    /// If true, nodes which aren't validators apply the chunks of the state
+    /// witnesses reaching them for shards they don't track, and report in
+    /// metrics whether the results match those of the chunk producers.
+    pub shadow_chunk_application: bool,
 }
 
 impl ClientConfig {
@@ -590,6 +736,7 @@ impl ClientConfig {
             state_sync: StateSyncConfig::default(),
             epoch_sync: EpochSyncConfig::default(),
             transaction_pool_size_limit: None,
+            transaction_pool_receiver_shard_size_limit: None,
             enable_multiline_logging: false,
             resharding_config: MutableConfigValue::new(
                 ReshardingConfig::default(),
@@ -604,6 +751,33 @@ impl ClientConfig {
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
             save_latest_witnesses: false,
+            validate_produced_state_witnesses: false,
+            save_epoch_shard_stats: false,
+            watched_accounts: vec![],
+            shadow_chunk_application: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::GCThrottleConfig;
+    use bytesize::ByteSize;
+
+    #[test]
+    fn test_gc_throttle() {
+        let config = GCThrottleConfig {
+            enabled: true,
+            compaction_debt_soft_limit: ByteSize::b(100),
+            compaction_debt_hard_limit: ByteSize::b(200),
+        };
+        assert_eq!(config.gc_blocks_limit(10, false, 50), 10);
+        assert_eq!(config.gc_blocks_limit(10, false, 150), 5);
+        assert_eq!(config.gc_blocks_limit(10, false, 199), 1);
+        assert_eq!(config.gc_blocks_limit(10, false, 200), 0);
+        assert_eq!(config.gc_blocks_limit(10, true, 0), 0);
+
+        let config = GCThrottleConfig { enabled: false, ..config };
+        assert_eq!(config.gc_blocks_limit(10, true, 1000), 10);
+    }
+}
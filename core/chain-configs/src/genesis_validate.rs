@@ -1,6 +1,8 @@
 use crate::genesis_config::{Genesis, GenesisConfig, GenesisContents};
 use near_config_utils::{ValidationError, ValidationErrors};
 use near_crypto::key_conversion::is_valid_staking_key;
+use near_primitives::account::AccountNamespace;
+use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::state_record::StateRecord;
 use near_primitives::types::AccountId;
 use num_rational::Rational32;
@@ -31,6 +33,11 @@ struct GenesisValidator<'a> {
     account_ids: HashSet<AccountId>,
     access_key_account_ids: HashSet<AccountId>,
     contract_account_ids: HashSet<AccountId>,
+    namespaced_contracts: HashSet<(AccountId, AccountNamespace)>,
+    /// Hashes of the global contracts with a record.
+    global_contracts: HashSet<CryptoHash>,
+    /// Hashes of the global contracts used by accounts.
+    used_global_contracts: HashMap<CryptoHash, AccountId>,
     validation_errors: &'a mut ValidationErrors,
 }
 
@@ -46,6 +53,9 @@ impl<'a> GenesisValidator<'a> {
             account_ids: HashSet::new(),
             access_key_account_ids: HashSet::new(),
             contract_account_ids: HashSet::new(),
+            namespaced_contracts: HashSet::new(),
+            global_contracts: HashSet::new(),
+            used_global_contracts: HashMap::new(),
             validation_errors: validation_errors,
         }
     }
@@ -58,6 +68,9 @@ impl<'a> GenesisValidator<'a> {
                         format!("Duplicate account id {} in genesis records", account_id);
                     self.validation_errors.push_genesis_semantics_error(error_message)
                 }
+                if account.global_contract() {
+                    self.used_global_contracts.insert(account.code_hash(), account_id.clone());
+                }
                 self.total_supply += account.locked() + account.amount();
                 self.account_ids.insert(account_id.clone());
                 if account.locked() > 0 {
@@ -75,6 +88,22 @@ impl<'a> GenesisValidator<'a> {
                 }
                 self.contract_account_ids.insert(account_id.clone());
             }
+            StateRecord::NamespacedContract { account_id, namespace, .. } => {
+                if !self.namespaced_contracts.insert((account_id.clone(), namespace.clone())) {
+                    let error_message = format!(
+                        "account {} has more than one contract deployed to namespace {}",
+                        account_id, namespace
+                    );
+                    self.validation_errors.push_genesis_semantics_error(error_message)
+                }
+            }
+            StateRecord::GlobalContract { code } => {
+                if !self.global_contracts.insert(hash(code)) {
+                    let error_message =
+                        format!("global contract {} has more than one record", hash(code));
+                    self.validation_errors.push_genesis_semantics_error(error_message)
+                }
+            }
             _ => {}
         }
     }
@@ -128,6 +157,23 @@ impl<'a> GenesisValidator<'a> {
             }
         }
 
+        for (account_id, _) in &self.namespaced_contracts {
+            if !self.account_ids.contains(account_id) {
+                let error_message = format!("contract account {} does not exist,", account_id);
+                self.validation_errors.push_genesis_semantics_error(error_message)
+            }
+        }
+
+        for (code_hash, account_id) in &self.used_global_contracts {
+            if !self.global_contracts.contains(code_hash) {
+                let error_message = format!(
+                    "global contract {} used by account {} does not exist",
+                    code_hash, account_id
+                );
+                self.validation_errors.push_genesis_semantics_error(error_message)
+            }
+        }
+
         if self.genesis_config.online_max_threshold <= self.genesis_config.online_min_threshold {
             let error_message = format!(
                 "Online max threshold {} smaller than min threshold {}",
@@ -334,4 +380,62 @@ mod test {
         let genesis = &Genesis::new(config, records).unwrap();
         validate_genesis(genesis).unwrap();
     }
+
+    #[test]
+    #[should_panic(expected = "account test has more than one contract deployed to namespace ns")]
+    fn test_more_than_one_namespaced_contract() {
+        let mut config = GenesisConfig::default();
+        config.validators = vec![AccountInfo {
+            account_id: "test".parse().unwrap(),
+            public_key: VALID_ED25519_RISTRETTO_KEY.parse().unwrap(),
+            amount: 10,
+        }];
+        config.total_supply = 110;
+        let namespaced_contract = |code: Vec<u8>| StateRecord::NamespacedContract {
+            account_id: "test".parse().unwrap(),
+            namespace: AccountNamespace::new("ns"),
+            code,
+        };
+        let records = GenesisRecords(vec![
+            StateRecord::Account { account_id: "test".parse().unwrap(), account: create_account() },
+            namespaced_contract([1, 2, 3].to_vec()),
+            namespaced_contract([1, 2, 3, 4].to_vec()),
+        ]);
+        let genesis = &Genesis::new(config, records).unwrap();
+        validate_genesis(genesis).unwrap();
+    }
+
+    fn global_contract_genesis(global_contracts: Vec<Vec<u8>>) -> Genesis {
+        let mut config = GenesisConfig::default();
+        config.validators = vec![AccountInfo {
+            account_id: "test".parse().unwrap(),
+            public_key: VALID_ED25519_RISTRETTO_KEY.parse().unwrap(),
+            amount: 10,
+        }];
+        config.total_supply = 110;
+        let mut account = create_account();
+        account.set_global_contract_hash(near_primitives::hash::hash(&[1, 2, 3]));
+        let mut records =
+            vec![StateRecord::Account { account_id: "test".parse().unwrap(), account }];
+        records
+            .extend(global_contracts.into_iter().map(|code| StateRecord::GlobalContract { code }));
+        Genesis::new(config, GenesisRecords(records)).unwrap()
+    }
+
+    #[test]
+    fn test_global_contract_account() {
+        validate_genesis(&global_contract_genesis(vec![vec![1, 2, 3]])).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "used by account test does not exist")]
+    fn test_missing_global_contract() {
+        validate_genesis(&global_contract_genesis(vec![vec![1, 2, 3, 4]])).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "has more than one record")]
+    fn test_duplicate_global_contract() {
+        validate_genesis(&global_contract_genesis(vec![vec![1, 2, 3], vec![1, 2, 3]])).unwrap();
+    }
 }
@@ -17,14 +17,15 @@ pub use client_config::{
     default_orphan_state_witness_pool_size, default_produce_chunk_add_transactions_time_limit,
     default_state_sync_enabled, default_state_sync_timeout, default_sync_check_period,
     default_sync_height_threshold, default_sync_max_block_requests, default_sync_step_period,
-    default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
-    default_tx_routing_height_horizon, default_view_client_threads,
-    default_view_client_throttle_period, ChunkDistributionNetworkConfig, ChunkDistributionUris,
-    ClientConfig, DumpConfig, EpochSyncConfig, ExternalStorageConfig, ExternalStorageLocation,
-    GCConfig, LogSummaryStyle, ReshardingConfig, ReshardingHandle, StateSyncConfig, SyncConfig,
+    default_transaction_pool_receiver_shard_size_limit, default_transaction_pool_size_limit,
+    default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
+    default_view_client_threads, default_view_client_throttle_period,
+    ChunkDistributionNetworkConfig, ChunkDistributionUris, ClientConfig, DumpConfig,
+    EpochSyncConfig, ExternalStorageConfig, ExternalStorageLocation, GCConfig, GCThrottleConfig,
+    LogSummaryStyle, ReshardingConfig, ReshardingHandle, StateSyncConfig, SyncConfig,
     DEFAULT_GC_NUM_EPOCHS_TO_KEEP, DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
-    DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL, MIN_GC_NUM_EPOCHS_TO_KEEP,
-    TEST_STATE_SYNC_TIMEOUT,
+    DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL,
+    MIN_DEEP_PRUNING_NUM_EPOCHS_TO_KEEP, MIN_GC_NUM_EPOCHS_TO_KEEP, TEST_STATE_SYNC_TIMEOUT,
 };
 pub use genesis_config::{
     get_initial_supply, stream_records_from_file, Genesis, GenesisChangeConfig, GenesisConfig,
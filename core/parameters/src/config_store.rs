@@ -1,5 +1,5 @@
 use crate::config::{CongestionControlConfig, RuntimeConfig};
-use crate::parameter_table::{ParameterTable, ParameterTableDiff};
+use crate::parameter_table::{InvalidConfigError, ParameterTable, ParameterTableDiff};
 use crate::vm;
 use near_primitives_core::types::ProtocolVersion;
 use near_primitives_core::version::PROTOCOL_VERSION;
@@ -50,11 +50,32 @@ static CONFIG_DIFFS: &[(ProtocolVersion, &str)] = &[
     // Increase main_storage_proof_size_soft_limit
     (72, include_config!("72.yaml")),
     (129, include_config!("129.yaml")),
+    // WebAssembly SIMD in contracts.
+    (148, include_config!("148.yaml")),
+    // SHA3 host functions.
+    (149, include_config!("149.yaml")),
+    // Groth16 proof verification host function.
+    (150, include_config!("150.yaml")),
+    // `emit_event` host function for structured events.
+    (154, include_config!("154.yaml")),
+    // `random_beacon` host function.
+    (155, include_config!("155.yaml")),
 ];
 
 /// Testnet parameters for versions <= 29, which (incorrectly) differed from mainnet parameters
 pub static INITIAL_TESTNET_CONFIG: &str = include_config!("parameters_testnet.yaml");
 
+/// Error returned when the runtime parameter overrides of a chain can't be applied.
+#[derive(thiserror::Error, Debug)]
+pub enum ParameterOverridesError {
+    #[error("the runtime parameters of chain `{0}` can't be overridden")]
+    ProtectedChain(String),
+    #[error("failed parsing the runtime parameter overrides: {0}")]
+    Parse(String),
+    #[error("the overridden parameters of version {protocol_version} are invalid: {message}")]
+    InvalidConfig { protocol_version: ProtocolVersion, message: String },
+}
+
 /// Stores runtime config for each protocol version where it was updated.
 #[derive(Clone, Debug)]
 pub struct RuntimeConfigStore {
@@ -74,18 +95,35 @@ impl RuntimeConfigStore {
     /// protocol upgrades this is done for all protocol versions
     /// TODO #4775: introduce new protocol version to have the same runtime config for all chains
     pub fn new(genesis_runtime_config: Option<&RuntimeConfig>) -> Self {
+        Self::with_overrides(genesis_runtime_config, None).unwrap_or_else(|(protocol_version, err)| panic!("Failed generating `RuntimeConfig` from parameters for version {protocol_version}. Error: {err}"))
+    }
+
+    /// Like [`Self::new`], with the `overrides` parameter values replacing the ones of every
+    /// protocol version.
+    fn with_overrides(
+        genesis_runtime_config: Option<&RuntimeConfig>,
+        overrides: Option<&ParameterTable>,
+    ) -> Result<Self, (ProtocolVersion, InvalidConfigError)> {
         let mut params: ParameterTable =
             BASE_CONFIG.parse().expect("Failed parsing base parameter file.");
+        let runtime_config = |params: &ParameterTable| match overrides {
+            Some(overrides) => {
+                let mut params = params.clone();
+                params.apply_overrides(overrides);
+                RuntimeConfig::new(&params)
+            }
+            None => RuntimeConfig::new(params),
+        };
 
         let mut store = BTreeMap::new();
         #[cfg(not(feature = "calimero_zero_storage"))]
         {
-            let initial_config = RuntimeConfig::new(&params).unwrap_or_else(|err| panic!("Failed generating `RuntimeConfig` from parameters for base parameter file. Error: {err}"));
+            let initial_config = runtime_config(&params).map_err(|err| (0, err))?;
             store.insert(0, Arc::new(initial_config));
         }
         #[cfg(feature = "calimero_zero_storage")]
         {
-            let mut initial_config = RuntimeConfig::new(&params).unwrap_or_else(|err| panic!("Failed generating `RuntimeConfig` from parameters for base parameter file. Error: {err}"));
+            let mut initial_config = runtime_config(&params).map_err(|err| (0, err))?;
             let fees = Arc::make_mut(&mut initial_config.fees);
             fees.storage_usage_config.storage_amount_per_byte = 0;
             store.insert(0, Arc::new(initial_config));
@@ -97,11 +135,12 @@ impl RuntimeConfigStore {
             #[cfg(not(feature = "calimero_zero_storage"))]
             store.insert(
                 *protocol_version,
-                Arc::new(RuntimeConfig::new(&params).unwrap_or_else(|err| panic!("Failed generating `RuntimeConfig` from parameters for version {protocol_version}. Error: {err}"))),
+                Arc::new(runtime_config(&params).map_err(|err| (*protocol_version, err))?),
             );
             #[cfg(feature = "calimero_zero_storage")]
             {
-                let mut runtime_config = RuntimeConfig::new(&params).unwrap_or_else(|err| panic!("Failed generating `RuntimeConfig` from parameters for version {protocol_version}. Error: {err}"));
+                let mut runtime_config =
+                    runtime_config(&params).map_err(|err| (*protocol_version, err))?;
                 let fees = Arc::make_mut(&mut runtime_config.fees);
                 fees.storage_usage_config.storage_amount_per_byte = 0;
                 store.insert(*protocol_version, Arc::new(runtime_config));
@@ -124,7 +163,7 @@ impl RuntimeConfigStore {
             store.insert(0, Arc::new(runtime_config.clone()));
         }
 
-        Self { store }
+        Ok(Self { store })
     }
 
     /// Create store of runtime configs for the given chain id.
@@ -143,22 +182,56 @@ impl RuntimeConfigStore {
             }
             near_primitives_core::chains::BENCHMARKNET => {
                 let mut config_store = Self::new(None);
-                let mut config = RuntimeConfig::clone(config_store.get_config(PROTOCOL_VERSION));
-                config.congestion_control_config.max_tx_gas = 10u64.pow(16);
-                config.congestion_control_config.min_tx_gas = 10u64.pow(16);
-                config.witness_config.main_storage_proof_size_soft_limit = 999_999_999_999_999;
-                config.witness_config.new_transactions_validation_state_size_soft_limit =
-                    999_999_999_999_999;
-                let mut wasm_config = vm::Config::clone(&config.wasm_config);
-                wasm_config.limit_config.per_receipt_storage_proof_size_limit = 999_999_999_999_999;
-                config.wasm_config = Arc::new(wasm_config);
-                config_store.store.insert(PROTOCOL_VERSION, Arc::new(config));
+                config_store.raise_benchmarknet_limits();
                 config_store
             }
             _ => Self::new(None),
         }
     }
 
+    /// Like [`Self::for_chain_id`], with the parameter values of the `overrides` YAML replacing
+    /// the ones of every protocol version.
+    ///
+    /// The overrides use the format of `parameters.yaml`. They let localnets try out proposed
+    /// parameter changes without patching the binary, so they are refused for mainnet and testnet.
+    pub fn for_chain_id_with_overrides(
+        chain_id: &str,
+        overrides: &str,
+    ) -> Result<Self, ParameterOverridesError> {
+        if [near_primitives_core::chains::MAINNET, near_primitives_core::chains::TESTNET]
+            .contains(&chain_id)
+        {
+            return Err(ParameterOverridesError::ProtectedChain(chain_id.to_string()));
+        }
+        let overrides: ParameterTable = overrides
+            .parse()
+            .map_err(|err: InvalidConfigError| ParameterOverridesError::Parse(err.to_string()))?;
+        let mut config_store =
+            Self::with_overrides(None, Some(&overrides)).map_err(|(protocol_version, err)| {
+                ParameterOverridesError::InvalidConfig {
+                    protocol_version,
+                    message: err.to_string(),
+                }
+            })?;
+        if chain_id == near_primitives_core::chains::BENCHMARKNET {
+            config_store.raise_benchmarknet_limits();
+        }
+        Ok(config_store)
+    }
+
+    fn raise_benchmarknet_limits(&mut self) {
+        let mut config = RuntimeConfig::clone(self.get_config(PROTOCOL_VERSION));
+        config.congestion_control_config.max_tx_gas = 10u64.pow(16);
+        config.congestion_control_config.min_tx_gas = 10u64.pow(16);
+        config.witness_config.main_storage_proof_size_soft_limit = 999_999_999_999_999;
+        config.witness_config.new_transactions_validation_state_size_soft_limit =
+            999_999_999_999_999;
+        let mut wasm_config = vm::Config::clone(&config.wasm_config);
+        wasm_config.limit_config.per_receipt_storage_proof_size_limit = 999_999_999_999_999;
+        config.wasm_config = Arc::new(wasm_config);
+        self.store.insert(PROTOCOL_VERSION, Arc::new(config));
+    }
+
     /// Constructs test store.
     pub fn with_one_config(runtime_config: RuntimeConfig) -> Self {
         Self { store: BTreeMap::from_iter([(0, Arc::new(runtime_config))].iter().cloned()) }
@@ -198,6 +271,7 @@ impl RuntimeConfigStore {
 mod tests {
     use super::*;
     use crate::cost::{ActionCosts, ExtCosts};
+    use assert_matches::assert_matches;
     use near_primitives_core::version::ProtocolFeature::{
         DecreaseFunctionCallBaseCost, LowerDataReceiptAndEcrecoverBaseCost, LowerStorageCost,
         LowerStorageKeyLimit,
@@ -264,6 +338,33 @@ mod tests {
         assert!(base_cfg.storage_amount_per_byte() > new_cfg.storage_amount_per_byte());
     }
 
+    #[test]
+    fn test_parameter_overrides() {
+        let overrides = "max_gas_burnt: 500_000_000_000_000\n";
+        let store = RuntimeConfigStore::for_chain_id_with_overrides("localnet", overrides).unwrap();
+        let base_store = RuntimeConfigStore::new(None);
+        for (protocol_version, config) in store.store.iter() {
+            assert_eq!(config.wasm_config.limit_config.max_gas_burnt, 500_000_000_000_000);
+            let base_config = base_store.get_config(*protocol_version);
+            assert_eq!(config.fees, base_config.fees);
+        }
+
+        for chain_id in ["mainnet", "testnet"] {
+            assert_matches!(
+                RuntimeConfigStore::for_chain_id_with_overrides(chain_id, overrides),
+                Err(ParameterOverridesError::ProtectedChain(_))
+            );
+        }
+        assert_matches!(
+            RuntimeConfigStore::for_chain_id_with_overrides("localnet", "max_gas_burned: 1\n"),
+            Err(ParameterOverridesError::Parse(_))
+        );
+        assert_matches!(
+            RuntimeConfigStore::for_chain_id_with_overrides("localnet", "max_gas_burnt: true\n"),
+            Err(ParameterOverridesError::InvalidConfig { protocol_version: 0, .. })
+        );
+    }
+
     #[test]
     fn test_override_account_length() {
         // Check that default value is 32.
@@ -89,6 +89,8 @@ impl ExtCostsConfig {
             ExtCosts::keccak256_byte => SAFETY_MULTIPLIER * 7157035,
             ExtCosts::keccak512_base => SAFETY_MULTIPLIER * 1937129412,
             ExtCosts::keccak512_byte => SAFETY_MULTIPLIER * 12216567,
+            ExtCosts::sha3_256_base => SAFETY_MULTIPLIER * 1959830425,
+            ExtCosts::sha3_256_byte => SAFETY_MULTIPLIER * 7157035,
             ExtCosts::ripemd160_base => SAFETY_MULTIPLIER * 284558362,
             ExtCosts::ed25519_verify_base => SAFETY_MULTIPLIER * 1513656750,
             ExtCosts::ed25519_verify_byte => SAFETY_MULTIPLIER * 7157035,
@@ -133,6 +135,8 @@ impl ExtCostsConfig {
             ExtCosts::alt_bn128_pairing_check_element => 5_102_000_000_000,
             ExtCosts::alt_bn128_g1_sum_base => 3_000_000_000,
             ExtCosts::alt_bn128_g1_sum_element => 5_000_000_000,
+            ExtCosts::alt_bn128_groth16_verify_base => 30_807_000_000_000,
+            ExtCosts::alt_bn128_groth16_verify_element => 320_000_000_000,
             ExtCosts::bls12381_p1_sum_base => SAFETY_MULTIPLIER * 5_500_000_000,
             ExtCosts::bls12381_p1_sum_element => SAFETY_MULTIPLIER * 2_000_000_000,
             ExtCosts::bls12381_p2_sum_base => SAFETY_MULTIPLIER * 6_200_000_000,
@@ -272,6 +276,10 @@ pub enum ExtCosts {
     bls12381_p2_decompress_element = 82,
     storage_large_read_overhead_base = 83,
     storage_large_read_overhead_byte = 84,
+    sha3_256_base = 85,
+    sha3_256_byte = 86,
+    alt_bn128_groth16_verify_base = 87,
+    alt_bn128_groth16_verify_element = 88,
 }
 
 // Type of an action, used in fees logic.
@@ -341,6 +349,8 @@ impl ExtCosts {
             ExtCosts::keccak256_byte => Parameter::WasmKeccak256Byte,
             ExtCosts::keccak512_base => Parameter::WasmKeccak512Base,
             ExtCosts::keccak512_byte => Parameter::WasmKeccak512Byte,
+            ExtCosts::sha3_256_base => Parameter::WasmSha3256Base,
+            ExtCosts::sha3_256_byte => Parameter::WasmSha3256Byte,
             ExtCosts::ripemd160_base => Parameter::WasmRipemd160Base,
             ExtCosts::ripemd160_block => Parameter::WasmRipemd160Block,
             ExtCosts::ecrecover_base => Parameter::WasmEcrecoverBase,
@@ -387,6 +397,10 @@ impl ExtCosts {
             ExtCosts::alt_bn128_pairing_check_element => Parameter::WasmAltBn128PairingCheckElement,
             ExtCosts::alt_bn128_g1_sum_base => Parameter::WasmAltBn128G1SumBase,
             ExtCosts::alt_bn128_g1_sum_element => Parameter::WasmAltBn128G1SumElement,
+            ExtCosts::alt_bn128_groth16_verify_base => Parameter::WasmAltBn128Groth16VerifyBase,
+            ExtCosts::alt_bn128_groth16_verify_element => {
+                Parameter::WasmAltBn128Groth16VerifyElement
+            }
             ExtCosts::yield_create_base => Parameter::WasmYieldCreateBase,
             ExtCosts::yield_create_byte => Parameter::WasmYieldCreateByte,
             ExtCosts::yield_resume_base => Parameter::WasmYieldResumeBase,
@@ -9,7 +9,7 @@ pub mod view;
 pub mod vm;
 
 pub use config::{AccountCreationConfig, RuntimeConfig};
-pub use config_store::RuntimeConfigStore;
+pub use config_store::{ParameterOverridesError, RuntimeConfigStore};
 pub use cost::{
     transfer_exec_fee, transfer_send_fee, ActionCosts, ExtCosts, ExtCostsConfig, Fee,
     ParameterCost, RuntimeFeesConfig, StorageUsageConfig,
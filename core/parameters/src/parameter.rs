@@ -73,6 +73,7 @@ pub enum Parameter {
 
     // Smart contract dynamic gas costs
     WasmRegularOpCost,
+    WasmSimdOpCost,
     WasmGrowMemCost,
     /// Base cost for a host function
     WasmBase,
@@ -96,6 +97,10 @@ pub enum Parameter {
     WasmKeccak256Byte,
     WasmKeccak512Base,
     WasmKeccak512Byte,
+    #[strum(serialize = "wasm_sha3_256_base")]
+    WasmSha3256Base,
+    #[strum(serialize = "wasm_sha3_256_byte")]
+    WasmSha3256Byte,
     WasmRipemd160Base,
     WasmRipemd160Block,
     WasmEcrecoverBase,
@@ -138,6 +143,8 @@ pub enum Parameter {
     WasmAltBn128PairingCheckElement,
     WasmAltBn128G1SumBase,
     WasmAltBn128G1SumElement,
+    WasmAltBn128Groth16VerifyBase,
+    WasmAltBn128Groth16VerifyElement,
     WasmYieldCreateBase,
     WasmYieldCreateByte,
     WasmYieldResumeBase,
@@ -207,6 +214,12 @@ pub enum Parameter {
     EthImplicitAccounts,
     YieldResume,
     DiscardCustomSections,
+    WasmSimd,
+    Sha3HostFunctions,
+    Groth16Verify,
+    NativeContracts,
+    StructuredEvents,
+    RandomBeacon,
 
     // Congestion Control
     MaxCongestionIncomingGas,
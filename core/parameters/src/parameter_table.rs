@@ -223,6 +223,7 @@ impl core::fmt::Display for ParameterValue {
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct ParameterTable {
     parameters: BTreeMap<Parameter, ParameterValue>,
 }
@@ -313,6 +314,7 @@ impl TryFrom<&ParameterTable> for RuntimeConfig {
                 vm_kind: params.get(Parameter::VmKind)?,
                 grow_mem_cost: params.get(Parameter::WasmGrowMemCost)?,
                 regular_op_cost: params.get(Parameter::WasmRegularOpCost)?,
+                simd_op_cost: params.get(Parameter::WasmSimdOpCost)?,
                 disable_9393_fix: params.get(Parameter::Disable9393Fix)?,
                 discard_custom_sections: params.get(Parameter::DiscardCustomSections)?,
                 limit_config: serde_yaml::from_value(params.yaml_map(Parameter::vm_limits()))
@@ -329,6 +331,12 @@ impl TryFrom<&ParameterTable> for RuntimeConfig {
                 function_call_weight: params.get(Parameter::FunctionCallWeight)?,
                 eth_implicit_accounts: params.get(Parameter::EthImplicitAccounts)?,
                 yield_resume_host_functions: params.get(Parameter::YieldResume)?,
+                wasm_simd: params.get(Parameter::WasmSimd)?,
+                sha3_host_functions: params.get(Parameter::Sha3HostFunctions)?,
+                groth16_verify: params.get(Parameter::Groth16Verify)?,
+                native_contracts: params.get(Parameter::NativeContracts)?,
+                structured_events: params.get(Parameter::StructuredEvents)?,
+                random_beacon: params.get(Parameter::RandomBeacon)?,
             }),
             account_creation_config: AccountCreationConfig {
                 min_allowed_top_level_account_length: params
@@ -405,6 +413,12 @@ impl ParameterTable {
         Ok(())
     }
 
+    /// Replaces the values of the parameters set in `overrides`.
+    pub(crate) fn apply_overrides(&mut self, overrides: &ParameterTable) {
+        self.parameters
+            .extend(overrides.parameters.iter().map(|(key, value)| (*key, value.clone())));
+    }
+
     fn yaml_map(&self, params: impl Iterator<Item = &'static Parameter>) -> serde_yaml::Value {
         // All parameter values can be serialized as YAML, so we don't ever expect this to fail.
         serde_yaml::to_value(
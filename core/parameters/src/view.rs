@@ -209,6 +209,8 @@ pub struct VMConfigView {
     pub grow_mem_cost: u32,
     /// Gas cost of a regular operation.
     pub regular_op_cost: u32,
+    /// Gas cost of an operation from the SIMD proposal.
+    pub simd_op_cost: u32,
 
     /// See [VMConfig::vm_kind](crate::vm::Config::vm_kind).
     pub vm_kind: crate::vm::VMKind,
@@ -235,6 +237,18 @@ pub struct VMConfigView {
     pub eth_implicit_accounts: bool,
     /// See [VMConfig::yield_resume_host_functions](`crate::vm::Config::yield_resume_host_functions).
     pub yield_resume_host_functions: bool,
+    /// See [VMConfig::wasm_simd](crate::vm::Config::wasm_simd).
+    pub wasm_simd: bool,
+    /// See [VMConfig::sha3_host_functions](crate::vm::Config::sha3_host_functions).
+    pub sha3_host_functions: bool,
+    /// See [VMConfig::groth16_verify](crate::vm::Config::groth16_verify).
+    pub groth16_verify: bool,
+    /// See [VMConfig::native_contracts](crate::vm::Config::native_contracts).
+    pub native_contracts: bool,
+    /// See [VMConfig::structured_events](crate::vm::Config::structured_events).
+    pub structured_events: bool,
+    /// See [VMConfig::random_beacon](crate::vm::Config::random_beacon).
+    pub random_beacon: bool,
 
     /// Describes limits for VM and Runtime.
     ///
@@ -249,6 +263,7 @@ impl From<crate::vm::Config> for VMConfigView {
             ext_costs: ExtCostsConfigView::from(config.ext_costs),
             grow_mem_cost: config.grow_mem_cost,
             regular_op_cost: config.regular_op_cost,
+            simd_op_cost: config.simd_op_cost,
             disable_9393_fix: config.disable_9393_fix,
             discard_custom_sections: config.discard_custom_sections,
             limit_config: config.limit_config,
@@ -262,6 +277,12 @@ impl From<crate::vm::Config> for VMConfigView {
             vm_kind: config.vm_kind,
             eth_implicit_accounts: config.eth_implicit_accounts,
             yield_resume_host_functions: config.yield_resume_host_functions,
+            wasm_simd: config.wasm_simd,
+            sha3_host_functions: config.sha3_host_functions,
+            groth16_verify: config.groth16_verify,
+            native_contracts: config.native_contracts,
+            structured_events: config.structured_events,
+            random_beacon: config.random_beacon,
         }
     }
 }
@@ -272,6 +293,7 @@ impl From<VMConfigView> for crate::vm::Config {
             ext_costs: crate::ExtCostsConfig::from(view.ext_costs),
             grow_mem_cost: view.grow_mem_cost,
             regular_op_cost: view.regular_op_cost,
+            simd_op_cost: view.simd_op_cost,
             disable_9393_fix: view.disable_9393_fix,
             discard_custom_sections: view.discard_custom_sections,
             limit_config: view.limit_config,
@@ -285,6 +307,12 @@ impl From<VMConfigView> for crate::vm::Config {
             vm_kind: view.vm_kind,
             eth_implicit_accounts: view.eth_implicit_accounts,
             yield_resume_host_functions: view.yield_resume_host_functions,
+            wasm_simd: view.wasm_simd,
+            sha3_host_functions: view.sha3_host_functions,
+            groth16_verify: view.groth16_verify,
+            native_contracts: view.native_contracts,
+            structured_events: view.structured_events,
+            random_beacon: view.random_beacon,
         }
     }
 }
@@ -346,6 +374,11 @@ pub struct ExtCostsConfigView {
     /// Cost of getting sha256 per byte
     pub keccak512_byte: Gas,
 
+    /// Cost of getting sha3-256 base
+    pub sha3_256_base: Gas,
+    /// Cost of getting sha3-256 per byte
+    pub sha3_256_byte: Gas,
+
     /// Cost of getting ripemd160 base
     pub ripemd160_base: Gas,
     /// Cost of getting ripemd160 per message block
@@ -461,6 +494,10 @@ pub struct ExtCostsConfigView {
     pub alt_bn128_pairing_check_base: Gas,
     /// Per element cost for pairing check
     pub alt_bn128_pairing_check_element: Gas,
+    /// Base cost for Groth16 proof verification
+    pub alt_bn128_groth16_verify_base: Gas,
+    /// Per public input cost for Groth16 proof verification
+    pub alt_bn128_groth16_verify_element: Gas,
     /// Base cost for creating a yield promise.
     pub yield_create_base: Gas,
     /// Per byte cost of arguments and method name.
@@ -513,6 +550,8 @@ impl From<crate::ExtCostsConfig> for ExtCostsConfigView {
             keccak256_byte: config.gas_cost(ExtCosts::keccak256_byte),
             keccak512_base: config.gas_cost(ExtCosts::keccak512_base),
             keccak512_byte: config.gas_cost(ExtCosts::keccak512_byte),
+            sha3_256_base: config.gas_cost(ExtCosts::sha3_256_base),
+            sha3_256_byte: config.gas_cost(ExtCosts::sha3_256_byte),
             ripemd160_base: config.gas_cost(ExtCosts::ripemd160_base),
             ripemd160_block: config.gas_cost(ExtCosts::ripemd160_block),
             ed25519_verify_base: config.gas_cost(ExtCosts::ed25519_verify_base),
@@ -561,6 +600,9 @@ impl From<crate::ExtCostsConfig> for ExtCostsConfigView {
             alt_bn128_pairing_check_base: config.gas_cost(ExtCosts::alt_bn128_pairing_check_base),
             alt_bn128_pairing_check_element: config
                 .gas_cost(ExtCosts::alt_bn128_pairing_check_element),
+            alt_bn128_groth16_verify_base: config.gas_cost(ExtCosts::alt_bn128_groth16_verify_base),
+            alt_bn128_groth16_verify_element: config
+                .gas_cost(ExtCosts::alt_bn128_groth16_verify_element),
             yield_create_base: config.gas_cost(ExtCosts::yield_create_base),
             yield_create_byte: config.gas_cost(ExtCosts::yield_create_byte),
             yield_resume_base: config.gas_cost(ExtCosts::yield_resume_base),
@@ -617,6 +659,8 @@ impl From<ExtCostsConfigView> for crate::ExtCostsConfig {
                 ExtCosts::keccak256_byte => view.keccak256_byte,
                 ExtCosts::keccak512_base => view.keccak512_base,
                 ExtCosts::keccak512_byte => view.keccak512_byte,
+                ExtCosts::sha3_256_base => view.sha3_256_base,
+                ExtCosts::sha3_256_byte => view.sha3_256_byte,
                 ExtCosts::ripemd160_base => view.ripemd160_base,
                 ExtCosts::ripemd160_block => view.ripemd160_block,
                 ExtCosts::ed25519_verify_base => view.ed25519_verify_base,
@@ -659,6 +703,8 @@ impl From<ExtCostsConfigView> for crate::ExtCostsConfig {
                 ExtCosts::alt_bn128_g1_sum_element => view.alt_bn128_g1_sum_element,
                 ExtCosts::alt_bn128_pairing_check_base => view.alt_bn128_pairing_check_base,
                 ExtCosts::alt_bn128_pairing_check_element => view.alt_bn128_pairing_check_element,
+                ExtCosts::alt_bn128_groth16_verify_base => view.alt_bn128_groth16_verify_base,
+                ExtCosts::alt_bn128_groth16_verify_element => view.alt_bn128_groth16_verify_element,
                 ExtCosts::yield_create_base => view.yield_create_base,
                 ExtCosts::yield_create_byte => view.yield_create_byte,
                 ExtCosts::yield_resume_base => view.yield_resume_base,
@@ -162,6 +162,9 @@ pub struct Config {
     /// Gas cost of a regular operation.
     pub regular_op_cost: u32,
 
+    /// Gas cost of an operation from the SIMD proposal.
+    pub simd_op_cost: u32,
+
     /// The kind of the VM implementation to use
     pub vm_kind: VMKind,
 
@@ -199,6 +202,24 @@ pub struct Config {
     /// Whether to discard custom sections.
     pub discard_custom_sections: bool,
 
+    /// Enable the `WasmSimd` protocol feature.
+    pub wasm_simd: bool,
+
+    /// Enable the host functions added by the `Sha3HostFunctions` protocol feature.
+    pub sha3_host_functions: bool,
+    /// Enable the host functions added by the `Groth16Verify` protocol feature.
+    pub groth16_verify: bool,
+    /// Run the native implementations of the contracts registered in
+    /// `near-vm-runner` instead of their WASM code.  No protocol version
+    /// enables it before the first implementation is registered.
+    pub native_contracts: bool,
+    /// Enable the `emit_event` host function added by the `StructuredEvents`
+    /// protocol feature.
+    pub structured_events: bool,
+    /// Enable the `random_beacon` host function added by the `RandomBeacon`
+    /// protocol feature.
+    pub random_beacon: bool,
+
     /// Describes limits for VM and Runtime.
     pub limit_config: LimitConfig,
 }
@@ -220,10 +241,16 @@ impl Config {
         };
         self.grow_mem_cost = 0;
         self.regular_op_cost = 0;
+        self.simd_op_cost = 0;
         self.limit_config.max_gas_burnt = u64::MAX;
     }
 
     pub fn enable_all_features(&mut self) {
+        self.sha3_host_functions = true;
+        self.groth16_verify = true;
+        self.native_contracts = true;
+        self.structured_events = true;
+        self.random_beacon = true;
         self.yield_resume_host_functions = true;
         self.eth_implicit_accounts = true;
         self.function_call_weight = true;
@@ -2,10 +2,11 @@
 use crate::checked_feature;
 use crate::hash::CryptoHash;
 use crate::serialize::dec_format;
-use crate::types::{Balance, Nonce, ProtocolVersion, StorageUsage};
+use crate::types::{Balance, BlockHeight, Nonce, ProtocolVersion, StorageUsage};
 use borsh::{BorshDeserialize, BorshSerialize};
 pub use near_account_id as id;
 use near_schema_checker_lib::ProtocolSchema;
+use std::collections::BTreeMap;
 use std::io;
 
 #[derive(
@@ -22,12 +23,22 @@ use std::io;
     serde::Deserialize,
     ProtocolSchema,
 )]
+// The discriminants are the borsh tags of the variants. `V1` and `V2` keep the
+// tags they had when they were implicit, the newer variants are explicit so
+// that their tags don't depend on whether `V2` is compiled in. They are not the
+// version bytes of the serialized `Account`, see `TryFrom<u8>`.
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
 pub enum AccountVersion {
     #[cfg_attr(not(feature = "protocol_feature_nonrefundable_transfer_nep491"), default)]
-    V1,
+    V1 = 0,
     #[default]
     #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-    V2,
+    V2 = 1,
+    /// Account with contracts deployed to namespaces, see `Account::namespaces`.
+    V3 = 2,
+    /// Account which can use a global contract, see `Account::global_contract`.
+    V4 = 3,
 }
 
 impl TryFrom<u8> for AccountVersion {
@@ -38,11 +49,66 @@ impl TryFrom<u8> for AccountVersion {
             1 => Ok(AccountVersion::V1),
             #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
             2 => Ok(AccountVersion::V2),
+            3 => Ok(AccountVersion::V3),
+            4 => Ok(AccountVersion::V4),
             _ => Err(()),
         }
     }
 }
 
+/// Name of a contract deployed to an account next to its main contract.
+///
+/// Each namespace has its own code and its own storage, so that an account can
+/// host several contracts which don't see each other's state.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    ProtocolSchema,
+)]
+#[serde(transparent)]
+pub struct AccountNamespace(String);
+
+impl AccountNamespace {
+    /// Longest allowed namespace name, in bytes.
+    pub const MAX_LEN: usize = 64;
+
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Namespaces are non-empty and made of lowercase alphanumeric characters,
+    /// `-` and `_`. In particular they can't contain the separators used in
+    /// the trie keys.
+    pub fn is_valid(&self) -> bool {
+        !self.0.is_empty()
+            && self.0.len() <= Self::MAX_LEN
+            && self.0.bytes().all(|c| matches!(c, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_'))
+    }
+}
+
+impl std::fmt::Display for AccountNamespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 /// Per account information stored in the state.
 #[cfg_attr(
     not(feature = "protocol_feature_nonrefundable_transfer_nep491"),
@@ -67,6 +133,15 @@ pub struct Account {
     /// Version of Account in re migrations and similar.
     #[serde(default)]
     version: AccountVersion,
+    /// Hashes of the code deployed to the namespaces of the account. Only V3
+    /// accounts have namespaces.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    namespaces: BTreeMap<AccountNamespace, CryptoHash>,
+    /// Whether `code_hash` is the hash of a global contract, which is stored
+    /// once for all the accounts using it rather than in the storage of this
+    /// account. Only V4 accounts use global contracts.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    global_contract: bool,
 }
 
 impl Account {
@@ -110,6 +185,8 @@ impl Account {
             code_hash,
             storage_usage,
             version: account_version,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         }
     }
 
@@ -150,6 +227,25 @@ impl Account {
         self.version
     }
 
+    #[inline]
+    pub fn namespaces(&self) -> &BTreeMap<AccountNamespace, CryptoHash> {
+        &self.namespaces
+    }
+
+    /// Hash of the code deployed to the namespace, `None` if nothing was
+    /// deployed to it.
+    #[inline]
+    pub fn namespace_code_hash(&self, namespace: &AccountNamespace) -> Option<CryptoHash> {
+        self.namespaces.get(namespace).copied()
+    }
+
+    /// Whether the account uses the global contract with `code_hash` rather
+    /// than a contract stored in its own storage.
+    #[inline]
+    pub fn global_contract(&self) -> bool {
+        self.global_contract
+    }
+
     #[inline]
     pub fn set_amount(&mut self, amount: Balance) {
         self.amount = amount;
@@ -166,9 +262,19 @@ impl Account {
         self.locked = locked;
     }
 
+    /// Sets the hash of the code stored in the storage of the account.
     #[inline]
     pub fn set_code_hash(&mut self, code_hash: CryptoHash) {
         self.code_hash = code_hash;
+        self.global_contract = false;
+    }
+
+    /// Makes the account use the global contract with `code_hash`, upgrading
+    /// the account to V4 which can refer to global contracts.
+    pub fn set_global_contract_hash(&mut self, code_hash: CryptoHash) {
+        self.version = AccountVersion::V4;
+        self.code_hash = code_hash;
+        self.global_contract = true;
     }
 
     #[inline]
@@ -179,6 +285,15 @@ impl Account {
     pub fn set_version(&mut self, version: AccountVersion) {
         self.version = version;
     }
+
+    /// Sets the hash of the code deployed to the namespace, upgrading the
+    /// account to V3 which stores the namespaces.
+    pub fn set_namespace_code_hash(&mut self, namespace: AccountNamespace, code_hash: CryptoHash) {
+        if self.version < AccountVersion::V3 {
+            self.version = AccountVersion::V3;
+        }
+        self.namespaces.insert(namespace, code_hash);
+    }
 }
 
 /// These accounts are serialized in merklized state.
@@ -211,6 +326,30 @@ struct AccountV2 {
     permanent_storage_bytes: StorageUsage,
 }
 
+/// Accounts with namespaces. Unlike `AccountV2`, the layout doesn't depend on
+/// the enabled features, so `permanent_storage_bytes` is always there.
+#[derive(BorshSerialize, BorshDeserialize, ProtocolSchema)]
+struct AccountV3 {
+    amount: Balance,
+    locked: Balance,
+    code_hash: CryptoHash,
+    storage_usage: StorageUsage,
+    permanent_storage_bytes: StorageUsage,
+    namespaces: BTreeMap<AccountNamespace, CryptoHash>,
+}
+
+/// Accounts which can use global contracts, on top of what `AccountV3` has.
+#[derive(BorshSerialize, BorshDeserialize, ProtocolSchema)]
+struct AccountV4 {
+    amount: Balance,
+    locked: Balance,
+    code_hash: CryptoHash,
+    storage_usage: StorageUsage,
+    permanent_storage_bytes: StorageUsage,
+    namespaces: BTreeMap<AccountNamespace, CryptoHash>,
+    global_contract: bool,
+}
+
 /// We need custom serde deserialization in order to parse mainnet genesis accounts (LegacyAccounts)
 /// as accounts V1. This preserves the mainnet genesis hash.
 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
@@ -232,6 +371,10 @@ impl<'de> serde::Deserialize<'de> for Account {
             storage_usage: StorageUsage,
             #[serde(default)]
             version: Option<AccountVersion>,
+            #[serde(default)]
+            namespaces: BTreeMap<AccountNamespace, CryptoHash>,
+            #[serde(default)]
+            global_contract: bool,
         }
 
         let account_data = AccountData::deserialize(deserializer)?;
@@ -260,6 +403,8 @@ impl<'de> serde::Deserialize<'de> for Account {
                     storage_usage: account_data.storage_usage,
                     permanent_storage_bytes,
                     version,
+                    namespaces: account_data.namespaces,
+                    global_contract: account_data.global_contract,
                 })
             }
             None => Ok(Account {
@@ -268,7 +413,15 @@ impl<'de> serde::Deserialize<'de> for Account {
                 code_hash: account_data.code_hash,
                 storage_usage: account_data.storage_usage,
                 permanent_storage_bytes: 0,
-                version: AccountVersion::V1,
+                version: if account_data.global_contract {
+                    AccountVersion::V4
+                } else if !account_data.namespaces.is_empty() {
+                    AccountVersion::V3
+                } else {
+                    AccountVersion::V1
+                },
+                namespaces: account_data.namespaces,
+                global_contract: account_data.global_contract,
             }),
         }
     }
@@ -280,15 +433,16 @@ impl BorshDeserialize for Account {
         // either a sentinel or a balance.
         let sentinel_or_amount = u128::deserialize_reader(rd)?;
         if sentinel_or_amount == Account::SERIALIZATION_SENTINEL {
-            if cfg!(not(feature = "protocol_feature_nonrefundable_transfer_nep491")) {
+            // Account v2 or newer.
+            let version_byte = u8::deserialize_reader(rd)?;
+            if cfg!(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))
+                && !matches!(version_byte, 3 | 4)
+            {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidData,
                     format!("account serialization sentinel not allowed for AccountV1"),
                 ));
             }
-
-            // Account v2 or newer.
-            let version_byte = u8::deserialize_reader(rd)?;
             let version = AccountVersion::try_from(version_byte).map_err(|_| {
                 io::Error::new(
                     io::ErrorKind::InvalidData,
@@ -298,24 +452,70 @@ impl BorshDeserialize for Account {
                     ),
                 )
             })?;
-            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-            if version < AccountVersion::V2 {
-                return Err(io::Error::new(
+            match version {
+                AccountVersion::V1 => Err(io::Error::new(
                     io::ErrorKind::InvalidData,
                     format!("expected account version 2 or higher, got {:?}", version),
-                ));
-            }
-            let account = AccountV2::deserialize_reader(rd)?;
-
-            Ok(Account {
-                amount: account.amount,
-                locked: account.locked,
+                )),
                 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-                permanent_storage_bytes: account.permanent_storage_bytes,
-                code_hash: account.code_hash,
-                storage_usage: account.storage_usage,
-                version,
-            })
+                AccountVersion::V2 => {
+                    let account = AccountV2::deserialize_reader(rd)?;
+                    Ok(Account {
+                        amount: account.amount,
+                        locked: account.locked,
+                        permanent_storage_bytes: account.permanent_storage_bytes,
+                        code_hash: account.code_hash,
+                        storage_usage: account.storage_usage,
+                        version,
+                        namespaces: BTreeMap::new(),
+                        global_contract: false,
+                    })
+                }
+                AccountVersion::V3 => {
+                    let account = AccountV3::deserialize_reader(rd)?;
+                    if cfg!(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))
+                        && account.permanent_storage_bytes > 0
+                    {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "permanent storage bytes not allowed without nonrefundable storage",
+                        ));
+                    }
+                    Ok(Account {
+                        amount: account.amount,
+                        locked: account.locked,
+                        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                        permanent_storage_bytes: account.permanent_storage_bytes,
+                        code_hash: account.code_hash,
+                        storage_usage: account.storage_usage,
+                        version,
+                        namespaces: account.namespaces,
+                        global_contract: false,
+                    })
+                }
+                AccountVersion::V4 => {
+                    let account = AccountV4::deserialize_reader(rd)?;
+                    if cfg!(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))
+                        && account.permanent_storage_bytes > 0
+                    {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "permanent storage bytes not allowed without nonrefundable storage",
+                        ));
+                    }
+                    Ok(Account {
+                        amount: account.amount,
+                        locked: account.locked,
+                        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                        permanent_storage_bytes: account.permanent_storage_bytes,
+                        code_hash: account.code_hash,
+                        storage_usage: account.storage_usage,
+                        version,
+                        namespaces: account.namespaces,
+                        global_contract: account.global_contract,
+                    })
+                }
+            }
         } else {
             // Account v1
             let locked = u128::deserialize_reader(rd)?;
@@ -330,6 +530,8 @@ impl BorshDeserialize for Account {
                 version: AccountVersion::V1,
                 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
                 permanent_storage_bytes: 0,
+                namespaces: BTreeMap::new(),
+                global_contract: false,
             })
         }
     }
@@ -344,39 +546,59 @@ impl BorshSerialize for Account {
             storage_usage: self.storage_usage(),
         };
 
-        #[cfg(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))]
-        {
-            legacy_account.serialize(writer)
-        }
-
-        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-        {
-            match self.version {
-                // It might be tempting to lazily convert old V1 to V2
-                // while serializing. But that would break the borsh assumptions
-                // of unique binary representation.
-                AccountVersion::V1 => {
-                    if self.permanent_storage_bytes > 0 {
-                        panic!("Trying to serialize V1 account with permanent_storage_bytes");
-                    }
-                    legacy_account.serialize(writer)
-                }
-                AccountVersion::V2 => {
-                    let account = AccountV2 {
-                        amount: self.amount(),
-                        locked: self.locked(),
-                        code_hash: self.code_hash(),
-                        storage_usage: self.storage_usage(),
-                        permanent_storage_bytes: self.permanent_storage_bytes(),
-                    };
-                    let sentinel = Account::SERIALIZATION_SENTINEL;
-                    // For now a constant, but if we need V3 later we can use this
-                    // field instead of sentinel magic.
-                    let version = 2u8;
-                    BorshSerialize::serialize(&sentinel, writer)?;
-                    BorshSerialize::serialize(&version, writer)?;
-                    account.serialize(writer)
+        match self.version {
+            // It might be tempting to lazily convert old V1 to V2
+            // while serializing. But that would break the borsh assumptions
+            // of unique binary representation.
+            AccountVersion::V1 => {
+                if self.permanent_storage_bytes() > 0 {
+                    panic!("Trying to serialize V1 account with permanent_storage_bytes");
                 }
+                legacy_account.serialize(writer)
+            }
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            AccountVersion::V2 => {
+                let account = AccountV2 {
+                    amount: self.amount(),
+                    locked: self.locked(),
+                    code_hash: self.code_hash(),
+                    storage_usage: self.storage_usage(),
+                    permanent_storage_bytes: self.permanent_storage_bytes(),
+                };
+                let sentinel = Account::SERIALIZATION_SENTINEL;
+                // For now a constant, but if we need V3 later we can use this
+                // field instead of sentinel magic.
+                let version = 2u8;
+                BorshSerialize::serialize(&sentinel, writer)?;
+                BorshSerialize::serialize(&version, writer)?;
+                account.serialize(writer)
+            }
+            AccountVersion::V3 => {
+                let account = AccountV3 {
+                    amount: self.amount(),
+                    locked: self.locked(),
+                    code_hash: self.code_hash(),
+                    storage_usage: self.storage_usage(),
+                    permanent_storage_bytes: self.permanent_storage_bytes(),
+                    namespaces: self.namespaces.clone(),
+                };
+                BorshSerialize::serialize(&Account::SERIALIZATION_SENTINEL, writer)?;
+                BorshSerialize::serialize(&3u8, writer)?;
+                account.serialize(writer)
+            }
+            AccountVersion::V4 => {
+                let account = AccountV4 {
+                    amount: self.amount(),
+                    locked: self.locked(),
+                    code_hash: self.code_hash(),
+                    storage_usage: self.storage_usage(),
+                    permanent_storage_bytes: self.permanent_storage_bytes(),
+                    namespaces: self.namespaces.clone(),
+                    global_contract: self.global_contract,
+                };
+                BorshSerialize::serialize(&Account::SERIALIZATION_SENTINEL, writer)?;
+                BorshSerialize::serialize(&4u8, writer)?;
+                account.serialize(writer)
             }
         }
     }
@@ -436,6 +658,9 @@ pub enum AccessKeyPermission {
     /// Grants full access to the account.
     /// NOTE: It's used to replace account-level public keys.
     FullAccess,
+
+    /// Grants access to all actions that can't spend more than an allowance.
+    LimitedFullAccess(LimitedFullAccessPermission),
 }
 
 /// Grants limited permission to make transactions with FunctionCallActions
@@ -476,6 +701,36 @@ pub struct FunctionCallPermission {
     pub method_names: Vec<String>,
 }
 
+/// Grants permission to make transactions with any actions, as long as they don't cost more than
+/// the allowance of the key. Wallets use it for session keys.
+///
+/// To keep the allowance meaningful, the key can't add or delete keys of its account, deploy a
+/// contract to it, call its contract or delete it: any of these would let it get more than the
+/// allowance out of the account.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Debug,
+    ProtocolSchema,
+)]
+pub struct LimitedFullAccessPermission {
+    /// Balance this access key can still spend on the fees, gas and deposits of its transactions.
+    /// When this access key is used, both account balance and the allowance are decreased by
+    /// the cost of the transaction.
+    #[serde(with = "dec_format")]
+    pub allowance: Balance,
+
+    /// The last block height at which transactions signed with this access key are accepted.
+    /// `None` means the key doesn't expire.
+    pub expiry_height: Option<BlockHeight>,
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -497,6 +752,18 @@ mod tests {
         Account::new(0, 0, 1, CryptoHash::default(), 0, protocol_version);
     }
 
+    /// The borsh tags of the versions which existed before `V3` don't change.
+    #[test]
+    fn test_account_version_borsh_tags() {
+        assert_eq!(borsh::to_vec(&AccountVersion::V1).unwrap(), [0]);
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        assert_eq!(borsh::to_vec(&AccountVersion::V2).unwrap(), [1]);
+        assert_eq!(borsh::to_vec(&AccountVersion::V3).unwrap(), [2]);
+        assert_eq!(borsh::to_vec(&AccountVersion::V4).unwrap(), [3]);
+        assert_eq!(AccountVersion::try_from_slice(&[0]).unwrap(), AccountVersion::V1);
+        assert_eq!(AccountVersion::try_from_slice(&[3]).unwrap(), AccountVersion::V4);
+    }
+
     #[test]
     fn test_legacy_account_serde_serialization() {
         let old_account = LegacyAccount {
@@ -553,6 +820,8 @@ mod tests {
             code_hash: CryptoHash::default(),
             storage_usage: 1000,
             version: AccountVersion::V1,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         };
         let serialized_account = serde_json::to_string(&account).unwrap();
         let deserialized_account: Account = serde_json::from_str(&serialized_account).unwrap();
@@ -574,6 +843,8 @@ mod tests {
             code_hash: CryptoHash::default(),
             storage_usage: 1000,
             version: AccountVersion::V1,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         };
         let serialized_account = serde_json::to_string(&account).unwrap();
         let deserialization_result: Result<Account, serde_json::Error> =
@@ -591,6 +862,8 @@ mod tests {
             code_hash: CryptoHash::default(),
             storage_usage: 100,
             version: AccountVersion::V1,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         };
         let serialized_account = borsh::to_vec(&account).unwrap();
         assert_eq!(
@@ -612,6 +885,8 @@ mod tests {
             code_hash: CryptoHash::default(),
             storage_usage: 100,
             version: AccountVersion::V1,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         };
         let serialized_account = borsh::to_vec(&account).unwrap();
         <Account as BorshDeserialize>::deserialize(&mut &serialized_account[..]).unwrap();
@@ -632,6 +907,8 @@ mod tests {
             code_hash: CryptoHash::default(),
             storage_usage: 100,
             version: AccountVersion::V1,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         };
         let _ = borsh::to_vec(&account);
     }
@@ -646,6 +923,8 @@ mod tests {
             code_hash: CryptoHash::default(),
             storage_usage: 1000,
             version: AccountVersion::V2,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         };
         let serialized_account = serde_json::to_string(&account).unwrap();
         let deserialized_account: Account = serde_json::from_str(&serialized_account).unwrap();
@@ -662,6 +941,8 @@ mod tests {
             code_hash: CryptoHash::default(),
             storage_usage: 100,
             version: AccountVersion::V2,
+            namespaces: BTreeMap::new(),
+            global_contract: false,
         };
         let serialized_account = borsh::to_vec(&account).unwrap();
         if cfg!(feature = "protocol_feature_nonrefundable_transfer_nep491") {
@@ -674,4 +955,69 @@ mod tests {
             <Account as BorshDeserialize>::deserialize(&mut &serialized_account[..]).unwrap();
         assert_eq!(deserialized_account, account);
     }
+
+    #[test]
+    fn test_account_v3_serde_serialization() {
+        let mut account = Account::new(10_000_000, 100_000, 0, CryptoHash::default(), 1000, 0);
+        account.set_namespace_code_hash(AccountNamespace::new("token"), hash(b"token"));
+        assert_eq!(account.version(), AccountVersion::V3);
+        let serialized_account = serde_json::to_string(&account).unwrap();
+        let deserialized_account: Account = serde_json::from_str(&serialized_account).unwrap();
+        assert_eq!(deserialized_account, account);
+    }
+
+    #[test]
+    fn test_account_v3_borsh_serialization() {
+        let mut account = Account::new(1_000_000, 1_000_000, 0, CryptoHash::default(), 100, 0);
+        account.set_namespace_code_hash(AccountNamespace::new("token"), hash(b"token"));
+        account.set_namespace_code_hash(AccountNamespace::new("dao"), hash(b"dao"));
+        let serialized_account = borsh::to_vec(&account).unwrap();
+        let deserialized_account =
+            <Account as BorshDeserialize>::deserialize(&mut &serialized_account[..]).unwrap();
+        assert_eq!(deserialized_account, account);
+        assert_eq!(
+            deserialized_account.namespace_code_hash(&AccountNamespace::new("dao")),
+            Some(hash(b"dao"))
+        );
+    }
+
+    #[test]
+    fn test_account_v4_serialization() {
+        let mut account = Account::new(1_000_000, 1_000_000, 0, CryptoHash::default(), 100, 0);
+        account.set_global_contract_hash(hash(b"token"));
+        account.set_namespace_code_hash(AccountNamespace::new("dao"), hash(b"dao"));
+        assert_eq!(account.version(), AccountVersion::V4);
+        let serialized_account = borsh::to_vec(&account).unwrap();
+        let deserialized_account =
+            <Account as BorshDeserialize>::deserialize(&mut &serialized_account[..]).unwrap();
+        assert_eq!(deserialized_account, account);
+        assert!(deserialized_account.global_contract());
+        let serialized_account = serde_json::to_string(&account).unwrap();
+        let deserialized_account: Account = serde_json::from_str(&serialized_account).unwrap();
+        assert_eq!(deserialized_account, account);
+
+        // Deploying a contract of its own stops the account from using the global one.
+        account.set_code_hash(hash(b"own"));
+        assert!(!account.global_contract());
+        assert_eq!(account.version(), AccountVersion::V4);
+    }
+
+    #[test]
+    fn test_account_version_tags() {
+        for (version, tag) in
+            [(AccountVersion::V1, 1), (AccountVersion::V3, 3), (AccountVersion::V4, 4)]
+        {
+            assert_eq!(borsh::to_vec(&version).unwrap(), [tag]);
+            assert_eq!(AccountVersion::try_from(tag), Ok(version));
+        }
+    }
+
+    #[test]
+    fn test_account_namespace_validity() {
+        assert!(AccountNamespace::new("token_v2-beta").is_valid());
+        assert!(!AccountNamespace::new("").is_valid());
+        assert!(!AccountNamespace::new("Token").is_valid());
+        assert!(!AccountNamespace::new("a#b").is_valid());
+        assert!(!AccountNamespace::new("a".repeat(AccountNamespace::MAX_LEN + 1)).is_valid());
+    }
 }
@@ -170,6 +170,41 @@ pub enum ProtocolFeature {
     // in order to calculate the rewards and kickouts for the chunk validators.
     // This feature introduces BlockHeaderV5.
     ChunkEndorsementsInBlockHeader,
+    /// Idempotency keys deduplicating function call receipts on their receiver,
+    /// see `IdempotencyKeyAction`.
+    IdempotencyKeys,
+    /// Receipts sent all-or-nothing once congestion control lets all of them
+    /// through, see `AtomicBatchAction`.
+    AtomicBatches,
+    /// WebAssembly SIMD proposal (128-bit vector instructions) in contracts,
+    /// metered with the separate `wasm_simd_op_cost`. The near-vm singlepass
+    /// backend doesn't generate code for the vector instructions yet, so until
+    /// it does, contracts using them are rejected when prepared for near-vm.
+    WasmSimd,
+    /// `sha3_256` host function, for the contracts compatible with Ethereum.
+    Sha3HostFunctions,
+    /// `alt_bn128_groth16_verify` host function, verifying a Groth16 proof on
+    /// the alt_bn128 curve in a single call.
+    Groth16Verify,
+    /// Lets an account deploy several contracts under named namespaces, each
+    /// with its own storage, next to its main contract.
+    AccountNamespaces,
+    /// Accepts `TransactionV1`, whose priority fee is burnt on top of the gas
+    /// and makes chunk producers include the transaction earlier.
+    TransactionPriorityFee,
+    /// `emit_event` host function, recording events with indexed topics in
+    /// the execution outcome and a bloom filter of them for every chunk.
+    StructuredEvents,
+    /// `random_beacon` host function, giving every receipt its own randomness
+    /// derived from the VRF output of the block producer.
+    RandomBeacon,
+    /// `LimitedFullAccess` access key permission, allowing all actions that
+    /// can't spend more than the allowance of the key until an optional
+    /// expiry height.
+    LimitedFullAccessKeys,
+    /// Contract code published once for the whole network and used by
+    /// accounts by its hash, without storing a copy of their own.
+    GlobalContracts,
 }
 
 impl ProtocolFeature {
@@ -245,6 +280,17 @@ impl ProtocolFeature {
             // that always enables this for mocknet (see config_mocknet function).
             ProtocolFeature::ShuffleShardAssignments => 143,
             ProtocolFeature::ChunkEndorsementsInBlockHeader => 145,
+            ProtocolFeature::IdempotencyKeys => 146,
+            ProtocolFeature::AtomicBatches => 147,
+            ProtocolFeature::WasmSimd => 148,
+            ProtocolFeature::Sha3HostFunctions => 149,
+            ProtocolFeature::Groth16Verify => 150,
+            ProtocolFeature::AccountNamespaces => 151,
+            ProtocolFeature::TransactionPriorityFee => 153,
+            ProtocolFeature::StructuredEvents => 154,
+            ProtocolFeature::RandomBeacon => 155,
+            ProtocolFeature::LimitedFullAccessKeys => 156,
+            ProtocolFeature::GlobalContracts => 157,
         }
     }
 
@@ -257,7 +303,7 @@ impl ProtocolFeature {
 const STABLE_PROTOCOL_VERSION: ProtocolVersion = 72;
 
 // On nightly, pick big enough version to support all features.
-const NIGHTLY_PROTOCOL_VERSION: ProtocolVersion = 145;
+const NIGHTLY_PROTOCOL_VERSION: ProtocolVersion = 157;
 
 /// Largest protocol version supported by the current binary.
 pub const PROTOCOL_VERSION: ProtocolVersion = if cfg!(feature = "nightly_protocol") {
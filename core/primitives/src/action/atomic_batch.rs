@@ -0,0 +1,150 @@
+//! AtomicBatchAction sends receipts to several receivers all-or-nothing.
+//!
+//! This is the module containing the types introduced for atomic batches.
+
+use super::{Action, ACTION_ATOMIC_BATCH_NUMBER, ACTION_DELEGATE_NUMBER};
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives_core::types::{AccountId, BlockHeight};
+use near_schema_checker_lib::ProtocolSchema;
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind, Read};
+
+/// Receipts which are either all accepted into the queues of their receiving
+/// shards or all refunded.
+///
+/// The batch waits while congestion control would buffer any of its receipts
+/// instead of forwarding it.  Once all of them can be forwarded in the same
+/// chunk, they are sent together.  If that doesn't happen until
+/// `max_block_height`, the deposits and gas of the receipts are refunded.
+///
+/// The batch sends the receipts on behalf of the signer of the transaction,
+/// so the transaction must be addressed to its signer.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    ProtocolSchema,
+)]
+pub struct AtomicBatchAction {
+    /// The maximal height of the block at which the receipts may be sent.
+    /// Transactions whose batch could wait longer than the runtime allows are
+    /// rejected.
+    pub max_block_height: BlockHeight,
+    pub receipts: Vec<AtomicBatchReceipt>,
+}
+
+/// A receipt of an atomic batch.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    ProtocolSchema,
+)]
+pub struct AtomicBatchReceipt {
+    pub receiver_id: AccountId,
+    /// Neither delegate actions nor atomic batches can be nested in a batch.
+    pub actions: Vec<BatchedAction>,
+}
+
+impl From<AtomicBatchAction> for Action {
+    fn from(atomic_batch_action: AtomicBatchAction) -> Self {
+        Self::AtomicBatch(Box::new(atomic_batch_action))
+    }
+}
+
+impl AtomicBatchReceipt {
+    pub fn get_actions(&self) -> Vec<Action> {
+        self.actions.iter().map(|a| a.clone().into()).collect()
+    }
+}
+
+/// This is Action which mustn't contain DelegateAction or AtomicBatchAction.
+///
+/// Like `NonDelegateAction`, this avoids the recursion when the batch is
+/// deserialized, so the inner Action mustn't be made public.
+#[derive(Serialize, BorshSerialize, Deserialize, PartialEq, Eq, Clone, Debug, ProtocolSchema)]
+pub struct BatchedAction(Action);
+
+/// A small private module to protect the private fields inside `BatchedAction`.
+mod private_batched_action {
+    use super::*;
+
+    impl From<BatchedAction> for Action {
+        fn from(action: BatchedAction) -> Self {
+            action.0
+        }
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("attempted to construct BatchedAction from Action::Delegate or Action::AtomicBatch")]
+    pub struct IsNestedAction;
+
+    impl TryFrom<Action> for BatchedAction {
+        type Error = IsNestedAction;
+
+        fn try_from(action: Action) -> Result<Self, IsNestedAction> {
+            if matches!(action, Action::Delegate(_) | Action::AtomicBatch(_)) {
+                Err(IsNestedAction)
+            } else {
+                Ok(Self(action))
+            }
+        }
+    }
+
+    impl borsh::de::BorshDeserialize for BatchedAction {
+        fn deserialize_reader<R: Read>(rd: &mut R) -> ::core::result::Result<Self, Error> {
+            match u8::deserialize_reader(rd)? {
+                ACTION_DELEGATE_NUMBER | ACTION_ATOMIC_BATCH_NUMBER => Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "AtomicBatchAction mustn't contain a delegate action or a nested batch",
+                )),
+                n => borsh::de::EnumExt::deserialize_variant(rd, n).map(Self),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::TransferAction;
+
+    fn create_atomic_batch(actions: Vec<Action>) -> Action {
+        Action::AtomicBatch(Box::new(AtomicBatchAction {
+            max_block_height: 2,
+            receipts: vec![AtomicBatchReceipt {
+                receiver_id: "bbb".parse().unwrap(),
+                actions: actions.into_iter().map(|a| BatchedAction::try_from(a).unwrap()).collect(),
+            }],
+        }))
+    }
+
+    #[test]
+    fn test_atomic_batch_deserialization() {
+        let atomic_batch = create_atomic_batch(vec![]);
+        let serialized = borsh::to_vec(&atomic_batch).unwrap();
+        // Expected Action::AtomicBatch has not been moved in enum Action
+        assert_eq!(serialized[0], ACTION_ATOMIC_BATCH_NUMBER);
+
+        // Expected a nested batch error
+        assert_eq!(
+            BatchedAction::try_from_slice(&serialized).map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        assert!(BatchedAction::try_from(atomic_batch).is_err());
+
+        let atomic_batch = create_atomic_batch(vec![TransferAction { deposit: 1 }.into()]);
+        let serialized = borsh::to_vec(&atomic_batch).unwrap();
+        assert_eq!(Action::try_from_slice(&serialized).unwrap(), atomic_batch);
+    }
+}
@@ -3,7 +3,7 @@
 //! NEP: <https://github.com/near/NEPs/pull/366>
 //! This is the module containing the types introduced for delegate actions.
 
-use super::Action;
+use super::{Action, ACTION_DELEGATE_NUMBER};
 use crate::signable_message::{SignableMessage, SignableMessageType};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::{PublicKey, Signature};
@@ -14,8 +14,6 @@ use near_schema_checker_lib::ProtocolSchema;
 use serde::{Deserialize, Serialize};
 use std::io::{Error, ErrorKind, Read};
 
-/// This is an index number of Action::Delegate in Action enumeration
-const ACTION_DELEGATE_NUMBER: u8 = 8;
 /// This action allows to execute the inner actions behalf of the defined sender.
 #[derive(
     BorshSerialize,
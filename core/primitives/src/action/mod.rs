@@ -1,9 +1,11 @@
+pub mod atomic_batch;
 pub mod delegate;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::PublicKey;
 use near_primitives_core::{
-    account::AccessKey,
+    account::{AccessKey, AccountNamespace},
+    hash::CryptoHash,
     serialize::dec_format,
     types::{AccountId, Balance, Gas},
 };
@@ -137,6 +139,128 @@ impl fmt::Debug for FunctionCallAction {
     }
 }
 
+/// Deploys a contract to a namespace of the receiver, next to its main
+/// contract.  The contract of a namespace only sees the storage of that
+/// namespace.
+#[serde_as]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    ProtocolSchema,
+)]
+pub struct DeployNamespacedContractAction {
+    pub namespace: AccountNamespace,
+    /// WebAssembly binary
+    #[serde_as(as = "Base64")]
+    pub code: Vec<u8>,
+}
+
+impl fmt::Debug for DeployNamespacedContractAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeployNamespacedContractAction")
+            .field("namespace", &format_args!("{}", &self.namespace))
+            .field("code", &format_args!("{}", base64(&self.code)))
+            .finish()
+    }
+}
+
+/// Calls the contract deployed to a namespace of the receiver.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    ProtocolSchema,
+)]
+pub struct NamespacedFunctionCallAction {
+    pub namespace: AccountNamespace,
+    pub function_call: FunctionCallAction,
+}
+
+/// Publishes a contract to all shards, so that any account can use it by its
+/// hash with `UseGlobalContractAction` instead of deploying a copy of it.
+/// The receiver pays for the storage of the code once, and can't remove it.
+#[serde_as]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    ProtocolSchema,
+)]
+pub struct DeployGlobalContractAction {
+    /// WebAssembly binary
+    #[serde_as(as = "Base64")]
+    pub code: Vec<u8>,
+}
+
+impl fmt::Debug for DeployGlobalContractAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeployGlobalContractAction")
+            .field("code", &format_args!("{}", base64(&self.code)))
+            .finish()
+    }
+}
+
+/// Makes the global contract with the given code hash the contract of the
+/// receiver, replacing the contract it had.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    ProtocolSchema,
+)]
+pub struct UseGlobalContractAction {
+    pub code_hash: CryptoHash,
+}
+
+/// Key deduplicating the receipt on its receiver, so that a retried
+/// transaction doesn't execute its function calls twice.
+///
+/// The receiver remembers the key for a bounded number of blocks and fails
+/// receipts carrying a key it remembers.  The key is only remembered if the
+/// receipt succeeds, so a failed receipt may be retried with the same key.
+#[serde_as]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    ProtocolSchema,
+)]
+pub struct IdempotencyKeyAction {
+    #[serde_as(as = "Base64")]
+    pub key: Vec<u8>,
+}
+
+impl fmt::Debug for IdempotencyKeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdempotencyKeyAction")
+            .field("key", &format_args!("{}", base64(&self.key)))
+            .finish()
+    }
+}
+
 /// An action which stakes signer_id tokens and setup's validator public key
 #[derive(
     BorshSerialize,
@@ -202,27 +326,48 @@ pub struct NonrefundableStorageTransferAction {
     strum::AsRefStr,
     ProtocolSchema,
 )]
+// The discriminants are the borsh tags of the variants. They are explicit so
+// that the tags don't depend on which feature-gated variants are compiled in.
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
 pub enum Action {
     /// Create an (sub)account using a transaction `receiver_id` as an ID for
     /// a new account ID must pass validation rules described here
     /// <http://nomicon.io/Primitives/Account.html>.
-    CreateAccount(CreateAccountAction),
+    CreateAccount(CreateAccountAction) = 0,
     /// Sets a Wasm code to a receiver_id
-    DeployContract(DeployContractAction),
-    FunctionCall(Box<FunctionCallAction>),
-    Transfer(TransferAction),
-    Stake(Box<StakeAction>),
-    AddKey(Box<AddKeyAction>),
-    DeleteKey(Box<DeleteKeyAction>),
-    DeleteAccount(DeleteAccountAction),
-    Delegate(Box<delegate::SignedDelegateAction>),
+    DeployContract(DeployContractAction) = 1,
+    FunctionCall(Box<FunctionCallAction>) = 2,
+    Transfer(TransferAction) = 3,
+    Stake(Box<StakeAction>) = 4,
+    AddKey(Box<AddKeyAction>) = 5,
+    DeleteKey(Box<DeleteKeyAction>) = 6,
+    DeleteAccount(DeleteAccountAction) = 7,
+    Delegate(Box<delegate::SignedDelegateAction>) = ACTION_DELEGATE_NUMBER,
     #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
     /// Makes a non-refundable transfer for storage allowance.
     /// Only possible during new account creation.
     /// For implicit account creation, it has to be the only action in the receipt.
-    NonrefundableStorageTransfer(NonrefundableStorageTransferAction),
+    NonrefundableStorageTransfer(NonrefundableStorageTransferAction) = 9,
+    /// Must be the first action of a receipt with function calls.
+    IdempotencyKey(IdempotencyKeyAction) = 10,
+    /// Sends receipts to several receivers all-or-nothing, see
+    /// `AtomicBatchAction`.  Must be the only action of the transaction.
+    AtomicBatch(Box<atomic_batch::AtomicBatchAction>) = ACTION_ATOMIC_BATCH_NUMBER,
+    /// Sets a Wasm code to a namespace of the receiver_id.
+    DeployNamespacedContract(Box<DeployNamespacedContractAction>) = 12,
+    NamespacedFunctionCall(Box<NamespacedFunctionCallAction>) = 13,
+    DeployGlobalContract(DeployGlobalContractAction) = 14,
+    /// Sets a global contract, by its hash, as the Wasm code of the receiver_id.
+    UseGlobalContract(Box<UseGlobalContractAction>) = 15,
 }
 
+/// Borsh tag of `Action::Delegate`, which delegate actions and atomic batches
+/// can't contain.
+pub(crate) const ACTION_DELEGATE_NUMBER: u8 = 8;
+/// Borsh tag of `Action::AtomicBatch`, which atomic batches can't contain.
+pub(crate) const ACTION_ATOMIC_BATCH_NUMBER: u8 = 11;
+
 const _: () = assert!(
     // 1 word for tag plus the largest variant `DeployContractAction` which is a 3-word `Vec`.
     // The `<=` check covers platforms that have pointers smaller than 8 bytes as well as random
@@ -235,12 +380,14 @@ impl Action {
     pub fn get_prepaid_gas(&self) -> Gas {
         match self {
             Action::FunctionCall(a) => a.gas,
+            Action::NamespacedFunctionCall(a) => a.function_call.gas,
             _ => 0,
         }
     }
     pub fn get_deposit_balance(&self) -> Balance {
         match self {
             Action::FunctionCall(a) => a.deposit,
+            Action::NamespacedFunctionCall(a) => a.function_call.deposit,
             Action::Transfer(a) => a.deposit,
             #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
             Action::NonrefundableStorageTransfer(a) => a.deposit,
@@ -280,6 +427,36 @@ impl From<NonrefundableStorageTransferAction> for Action {
     }
 }
 
+impl From<DeployNamespacedContractAction> for Action {
+    fn from(deploy_namespaced_contract_action: DeployNamespacedContractAction) -> Self {
+        Self::DeployNamespacedContract(Box::new(deploy_namespaced_contract_action))
+    }
+}
+
+impl From<NamespacedFunctionCallAction> for Action {
+    fn from(namespaced_function_call_action: NamespacedFunctionCallAction) -> Self {
+        Self::NamespacedFunctionCall(Box::new(namespaced_function_call_action))
+    }
+}
+
+impl From<DeployGlobalContractAction> for Action {
+    fn from(deploy_global_contract_action: DeployGlobalContractAction) -> Self {
+        Self::DeployGlobalContract(deploy_global_contract_action)
+    }
+}
+
+impl From<UseGlobalContractAction> for Action {
+    fn from(use_global_contract_action: UseGlobalContractAction) -> Self {
+        Self::UseGlobalContract(Box::new(use_global_contract_action))
+    }
+}
+
+impl From<IdempotencyKeyAction> for Action {
+    fn from(idempotency_key_action: IdempotencyKeyAction) -> Self {
+        Self::IdempotencyKey(idempotency_key_action)
+    }
+}
+
 impl From<StakeAction> for Action {
     fn from(stake_action: StakeAction) -> Self {
         Self::Stake(Box::new(stake_action))
@@ -303,3 +480,22 @@ impl From<DeleteAccountAction> for Action {
         Self::DeleteAccount(delete_account_action)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_tags_do_not_depend_on_features() {
+        let idempotency_key = Action::IdempotencyKey(IdempotencyKeyAction { key: vec![1] });
+        assert_eq!(borsh::to_vec(&idempotency_key).unwrap()[0], 10);
+        let use_global_contract = Action::UseGlobalContract(Box::new(UseGlobalContractAction {
+            code_hash: CryptoHash::default(),
+        }));
+        assert_eq!(borsh::to_vec(&use_global_contract).unwrap()[0], 15);
+        assert_eq!(
+            Action::try_from_slice(&borsh::to_vec(&use_global_contract).unwrap()).unwrap(),
+            use_global_contract
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use crate::hash::CryptoHash;
 use crate::serialize::dec_format;
-use crate::types::{AccountId, Balance, EpochId, Gas, Nonce};
+use crate::types::{AccountId, Balance, BlockHeight, EpochId, Gas, Nonce};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::PublicKey;
+use near_primitives_core::account::AccountNamespace;
 use near_primitives_core::types::ProtocolVersion;
 use near_schema_checker_lib::ProtocolSchema;
 use std::fmt::{Debug, Display};
@@ -272,6 +273,12 @@ pub enum InvalidAccessKeyError {
     },
     /// Having a deposit with a function call action is not allowed with a function call access key.
     DepositWithFunctionCall,
+    /// The access key can't be used after its expiry height.
+    AccessKeyExpired {
+        account_id: AccountId,
+        public_key: Box<PublicKey>,
+        expiry_height: BlockHeight,
+    },
 }
 
 /// Describes the error for validating a list of actions.
@@ -320,6 +327,20 @@ pub enum ActionsValidationError {
     /// `ProtocolFeature` here because we don't want to leak the internals of
     /// that type into observable borsh serialization.
     UnsupportedProtocolFeature { protocol_feature: String, version: ProtocolVersion },
+    /// The IdempotencyKey action must be the first action and there can be only one.
+    IdempotencyKeyMustBeFirst,
+    /// The length of the key exceeded the limit in an IdempotencyKey action.
+    IdempotencyKeyLengthExceeded { length: u64, limit: u64 },
+    /// The IdempotencyKey action is only allowed along with FunctionCall actions.
+    IdempotencyKeyWithoutFunctionCall,
+    /// The AtomicBatch action must be the only action of the transaction.
+    AtomicBatchMustBeOnlyOne,
+    /// The number of receipts of an AtomicBatch action is zero or exceeds the limit.
+    InvalidAtomicBatchReceiptsNumber { total_number: u64, limit: u64 },
+    /// The namespace of a DeployNamespacedContract or NamespacedFunctionCall action is not valid.
+    InvalidNamespace { namespace: String },
+    /// The `max_block_height` of an AtomicBatch action is too far ahead of the current height.
+    AtomicBatchMaxBlockHeightTooLarge { max_block_height: u64, limit: u64 },
 }
 
 /// Describes the error for validating a receipt.
@@ -461,6 +482,43 @@ impl Display for ActionsValidationError {
                     protocol_feature,
                     version,
             ),
+            ActionsValidationError::IdempotencyKeyMustBeFirst => write!(
+                f,
+                "The IdempotencyKey action must be the first action and there can be only one"
+            ),
+            ActionsValidationError::IdempotencyKeyLengthExceeded { length, limit } => write!(
+                f,
+                "The length of the idempotency key {} exceeds the maximum allowed length {}",
+                length, limit
+            ),
+            ActionsValidationError::IdempotencyKeyWithoutFunctionCall => write!(
+                f,
+                "The IdempotencyKey action requires at least one FunctionCall action"
+            ),
+            ActionsValidationError::AtomicBatchMustBeOnlyOne => {
+                write!(f, "The AtomicBatch action must be the only action of the transaction")
+            }
+            ActionsValidationError::InvalidAtomicBatchReceiptsNumber { total_number, limit } => {
+                write!(
+                    f,
+                    "The AtomicBatch action has {} receipts, it must have at least one and at most {}",
+                    total_number, limit
+                )
+            }
+            ActionsValidationError::InvalidNamespace { namespace } => write!(
+                f,
+                "Namespace {:?} must be 1 to {} lowercase alphanumeric characters, '-' or '_'",
+                namespace,
+                AccountNamespace::MAX_LEN
+            ),
+            ActionsValidationError::AtomicBatchMaxBlockHeightTooLarge {
+                max_block_height,
+                limit,
+            } => write!(
+                f,
+                "The max_block_height {} of the AtomicBatch action exceeds the limit {}",
+                max_block_height, limit
+            ),
         }
     }
 }
@@ -578,6 +636,23 @@ pub enum ActionErrorKind {
     DelegateActionNonceTooLarge { delegate_nonce: Nonce, upper_bound: Nonce },
     /// Non-refundable storage transfer to an existing account is not allowed according to NEP-491.
     NonRefundableTransferToExistingAccount { account_id: AccountId },
+    /// The receiver already executed a receipt with the same idempotency key
+    /// within the deduplication window.
+    DuplicateIdempotencyKey { account_id: AccountId },
+    /// Receiver of the transaction doesn't match its signer, on whose behalf
+    /// the receipts of an atomic batch are sent.
+    AtomicBatchReceiverIsNotSigner { receiver_id: AccountId, signer_id: AccountId },
+    /// The receipts of the atomic batch couldn't all be sent until its
+    /// `max_block_height`.
+    AtomicBatchExpired,
+    /// The receiver already remembers `IdempotencyKeys::MAX_ENTRIES` keys of the
+    /// predecessor whose window hasn't ended, so a receipt with a new key can't
+    /// be deduplicated.
+    IdempotencyKeysFull { account_id: AccountId },
+    /// There is no global contract with the hash given to UseGlobalContract
+    /// on the shard of the receiver, either because it was never deployed or
+    /// because it hasn't reached that shard yet.
+    GlobalContractDoesNotExist { code_hash: CryptoHash },
 }
 
 impl From<ActionErrorKind> for ActionError {
@@ -698,6 +773,13 @@ impl Display for InvalidAccessKeyError {
             InvalidAccessKeyError::DepositWithFunctionCall => {
                 write!(f, "Having a deposit with a function call action is not allowed with a function call access key.")
             }
+            InvalidAccessKeyError::AccessKeyExpired { account_id, public_key, expiry_height } => {
+                write!(
+                    f,
+                    "Access Key {:?}:{} expired at block height {}",
+                    account_id, public_key, expiry_height
+                )
+            }
         }
     }
 }
@@ -921,6 +1003,11 @@ impl Display for ActionErrorKind {
             ActionErrorKind::NonRefundableTransferToExistingAccount { account_id} => {
                 write!(f, "Can't make non-refundable storage transfer to {} because it already exists", account_id)
             }
+            ActionErrorKind::DuplicateIdempotencyKey { account_id } => write!(f, "Account {} already executed a receipt with the same idempotency key", account_id),
+            ActionErrorKind::AtomicBatchReceiverIsNotSigner { receiver_id, signer_id } => write!(f, "Transaction receiver {} of AtomicBatch doesn't match its signer {}", receiver_id, signer_id),
+            ActionErrorKind::AtomicBatchExpired => write!(f, "AtomicBatch has expired before all its receipts could be sent"),
+            ActionErrorKind::IdempotencyKeysFull { account_id } => write!(f, "Account {} remembers too many idempotency keys of the predecessor to accept a new one", account_id),
+            ActionErrorKind::GlobalContractDoesNotExist { code_hash } => write!(f, "Global contract with code hash {} does not exist", code_hash),
         }
     }
 }
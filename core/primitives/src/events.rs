@@ -0,0 +1,174 @@
+//! Structured events emitted by contracts with the `emit_event` host function.
+//!
+//! Unlike logs, events have indexed topics: every chunk gets an [`EventsBloom`] of the accounts
+//! that emitted events and of their topics, so that an indexer looking for some events can skip
+//! the chunks that don't have them without reading their execution outcomes.
+
+use crate::hash::{hash, CryptoHash};
+use crate::transaction::ExecutionOutcomeWithId;
+use crate::types::AccountId;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_schema_checker_lib::ProtocolSchema;
+use serde_with::base64::Base64;
+use serde_with::serde_as;
+use std::fmt;
+
+/// An event emitted by a contract, recorded in the execution outcome of its receipt.
+#[serde_as]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    ProtocolSchema,
+)]
+pub struct ContractEvent {
+    /// Hashes the event is indexed by.
+    pub topics: Vec<CryptoHash>,
+    /// Payload of the event, opaque to the protocol.
+    #[serde_as(as = "Base64")]
+    pub data: Vec<u8>,
+}
+
+/// Number of bits of an [`EventsBloom`].
+const BLOOM_BITS: usize = 2048;
+
+/// Number of bits of an [`EventsBloom`] set by every item added to it.
+const BLOOM_BITS_PER_ITEM: usize = 3;
+
+/// Bloom filter of the events in the execution outcomes of a chunk.
+///
+/// It holds the accounts that emitted the events and their topics. The bits of an item are
+/// taken from its hash, two bytes at a time, as Ethereum does for its logs.
+#[serde_as]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    PartialEq,
+    Eq,
+    ProtocolSchema,
+)]
+pub struct EventsBloom(#[serde_as(as = "Base64")] pub [u8; BLOOM_BITS / 8]);
+
+impl Default for EventsBloom {
+    fn default() -> Self {
+        Self([0; BLOOM_BITS / 8])
+    }
+}
+
+impl fmt::Debug for EventsBloom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits_set: u32 = self.0.iter().map(|byte| byte.count_ones()).sum();
+        write!(f, "EventsBloom({bits_set} bits set)")
+    }
+}
+
+impl EventsBloom {
+    /// Builds the filter of the events in `outcomes`.
+    pub fn from_outcomes<'a>(
+        outcomes: impl IntoIterator<Item = &'a ExecutionOutcomeWithId>,
+    ) -> Self {
+        let mut bloom = Self::default();
+        for outcome_with_id in outcomes {
+            let outcome = &outcome_with_id.outcome;
+            for event in outcome.metadata.events() {
+                bloom.add_account(&outcome.executor_id);
+                for topic in &event.topics {
+                    bloom.add_topic(topic);
+                }
+            }
+        }
+        bloom
+    }
+
+    /// Whether nothing was added to the filter.
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|byte| *byte == 0)
+    }
+
+    pub fn add_account(&mut self, account_id: &AccountId) {
+        self.add(account_id.as_bytes());
+    }
+
+    pub fn add_topic(&mut self, topic: &CryptoHash) {
+        self.add(topic.as_ref());
+    }
+
+    /// Whether `account_id` might have emitted events. A `false` is always right.
+    pub fn might_contain_account(&self, account_id: &AccountId) -> bool {
+        self.might_contain(account_id.as_bytes())
+    }
+
+    /// Whether there might be events with `topic`. A `false` is always right.
+    pub fn might_contain_topic(&self, topic: &CryptoHash) -> bool {
+        self.might_contain(topic.as_ref())
+    }
+
+    fn add(&mut self, item: &[u8]) {
+        for bit in Self::bits(item) {
+            self.0[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    fn might_contain(&self, item: &[u8]) -> bool {
+        Self::bits(item).all(|bit| self.0[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    fn bits(item: &[u8]) -> impl Iterator<Item = usize> {
+        let item_hash = hash(item);
+        (0..BLOOM_BITS_PER_ITEM).map(move |i| {
+            let bytes = [item_hash.0[2 * i], item_hash.0[2 * i + 1]];
+            usize::from(u16::from_be_bytes(bytes)) % BLOOM_BITS
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{ExecutionMetadata, ExecutionMetadataV4, ExecutionOutcome};
+
+    fn outcome(executor_id: &str, events: Vec<ContractEvent>) -> ExecutionOutcomeWithId {
+        let metadata = if events.is_empty() {
+            ExecutionMetadata::V3(Default::default())
+        } else {
+            ExecutionMetadata::V4(Box::new(ExecutionMetadataV4 {
+                gas_profile: Default::default(),
+                events,
+                refunds: vec![],
+            }))
+        };
+        ExecutionOutcomeWithId {
+            id: hash(executor_id.as_bytes()),
+            outcome: ExecutionOutcome {
+                executor_id: executor_id.parse().unwrap(),
+                metadata,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_events_bloom() {
+        let transfer = hash(b"Transfer");
+        let outcomes = [
+            outcome("alice.near", vec![]),
+            outcome("token.near", vec![ContractEvent { topics: vec![transfer], data: vec![] }]),
+        ];
+        let bloom = EventsBloom::from_outcomes(&outcomes);
+        assert!(bloom.might_contain_account(&"token.near".parse().unwrap()));
+        assert!(bloom.might_contain_topic(&transfer));
+        // With only six bits of 2048 set, these are not false positives.
+        assert!(!bloom.might_contain_account(&"alice.near".parse().unwrap()));
+        assert!(!bloom.might_contain_topic(&hash(b"Approval")));
+
+        assert!(EventsBloom::from_outcomes(&outcomes[..1]).is_empty());
+    }
+}
@@ -282,10 +282,10 @@ mod test {
             storage_remove_base -> 33 [0% host]
             storage_remove_key_byte -> 34 [0% host]
             storage_remove_ret_value_byte -> 35 [0% host]
-            storage_has_key_base -> 36 [1% host]
-            storage_has_key_byte -> 37 [1% host]
-            storage_iter_create_prefix_base -> 38 [1% host]
-            storage_iter_create_prefix_byte -> 39 [1% host]
+            storage_has_key_base -> 36 [0% host]
+            storage_has_key_byte -> 37 [0% host]
+            storage_iter_create_prefix_base -> 38 [0% host]
+            storage_iter_create_prefix_byte -> 39 [0% host]
             storage_iter_create_range_base -> 40 [1% host]
             storage_iter_create_from_byte -> 41 [1% host]
             storage_iter_create_to_byte -> 42 [1% host]
@@ -318,19 +318,23 @@ mod test {
             bls12381_g1_multiexp_base -> 69 [1% host]
             bls12381_g1_multiexp_element -> 70 [1% host]
             bls12381_g2_multiexp_base -> 71 [1% host]
-            bls12381_g2_multiexp_element -> 72 [2% host]
-            bls12381_map_fp_to_g1_base -> 73 [2% host]
-            bls12381_map_fp_to_g1_element -> 74 [2% host]
-            bls12381_map_fp2_to_g2_base -> 75 [2% host]
-            bls12381_map_fp2_to_g2_element -> 76 [2% host]
-            bls12381_pairing_base -> 77 [2% host]
-            bls12381_pairing_element -> 78 [2% host]
+            bls12381_g2_multiexp_element -> 72 [1% host]
+            bls12381_map_fp_to_g1_base -> 73 [1% host]
+            bls12381_map_fp_to_g1_element -> 74 [1% host]
+            bls12381_map_fp2_to_g2_base -> 75 [1% host]
+            bls12381_map_fp2_to_g2_element -> 76 [1% host]
+            bls12381_pairing_base -> 77 [1% host]
+            bls12381_pairing_element -> 78 [1% host]
             bls12381_p1_decompress_base -> 79 [2% host]
             bls12381_p1_decompress_element -> 80 [2% host]
             bls12381_p2_decompress_base -> 81 [2% host]
             bls12381_p2_decompress_element -> 82 [2% host]
             storage_large_read_overhead_base -> 83 [2% host]
             storage_large_read_overhead_byte -> 84 [2% host]
+            sha3_256_base -> 85 [2% host]
+            sha3_256_byte -> 86 [2% host]
+            alt_bn128_groth16_verify_base -> 87 [2% host]
+            alt_bn128_groth16_verify_element -> 88 [2% host]
             ------ Actions --------
             create_account -> 1000
             delete_account -> 1001
@@ -1,5 +1,6 @@
-use crate::hash::CryptoHash;
+use crate::hash::{hash, CryptoHash};
 use crate::serialize::dec_format;
+use crate::shard_layout::{account_id_to_shard_id, ShardLayout, ShardVersion};
 use crate::transaction::{Action, TransferAction};
 use crate::types::{AccountId, Balance, BlockHeight, ShardId};
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -259,6 +260,45 @@ impl Receipt {
         *self.receipt_id()
     }
 
+    /// Shard the receipt is applied on. That's the shard of the receiver,
+    /// except for the global contract distribution receipts which go to all
+    /// shards in turn.  A distribution receipt sent before a resharding goes
+    /// to the shard of its receiver, which restarts the distribution in the
+    /// new shard layout.
+    pub fn receiver_shard_id(&self, shard_layout: &ShardLayout) -> ShardId {
+        match self.receipt() {
+            ReceiptEnum::GlobalContractDistribution(distribution)
+                if distribution.shard_layout_version == shard_layout.version() =>
+            {
+                distribution.target_shard
+            }
+            _ => account_id_to_shard_id(self.receiver_id(), shard_layout),
+        }
+    }
+
+    /// Generates a receipt distributing the code of a global contract to
+    /// `target_shard`, without a receipt_id. The account which deployed the
+    /// contract is both its predecessor and its receiver.
+    pub fn new_global_contract_distribution(
+        account_id: AccountId,
+        code: Vec<u8>,
+        shard_layout_version: ShardVersion,
+        target_shard: ShardId,
+        already_delivered_shards: Vec<ShardId>,
+    ) -> Self {
+        Receipt::V0(ReceiptV0 {
+            predecessor_id: account_id.clone(),
+            receiver_id: account_id,
+            receipt_id: CryptoHash::default(),
+            receipt: ReceiptEnum::GlobalContractDistribution(GlobalContractDistributionReceipt {
+                shard_layout_version,
+                target_shard,
+                already_delivered_shards,
+                code,
+            }),
+        })
+    }
+
     /// Generates a receipt with a transfer from system for a given balance without a receipt_id.
     /// This should be used for token refunds instead of gas refunds. It inherits priority from the parent receipt.
     /// It doesn't refund the allowance of the access key. For gas refunds use `new_gas_refund`.
@@ -364,6 +404,7 @@ pub enum ReceiptEnum {
     Data(DataReceipt),
     PromiseYield(ActionReceipt),
     PromiseResume(DataReceipt),
+    GlobalContractDistribution(GlobalContractDistributionReceipt),
 }
 
 /// ActionReceipt is derived from an Action from `Transaction or from Receipt`
@@ -427,6 +468,43 @@ impl fmt::Debug for DataReceipt {
     }
 }
 
+/// Carries the code of a global contract from shard to shard, each shard
+/// storing it and forwarding the receipt to the next one, until all shards
+/// have it.
+#[serde_as]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Hash,
+    PartialEq,
+    Eq,
+    Clone,
+    serde::Serialize,
+    serde::Deserialize,
+    ProtocolSchema,
+)]
+pub struct GlobalContractDistributionReceipt {
+    /// Version of the shard layout the shard ids of the receipt refer to.
+    pub shard_layout_version: ShardVersion,
+    /// Shard on which the receipt is applied, regardless of its receiver.
+    pub target_shard: ShardId,
+    /// Shards which already store the code.
+    pub already_delivered_shards: Vec<ShardId>,
+    #[serde_as(as = "Base64")]
+    pub code: Vec<u8>,
+}
+
+impl fmt::Debug for GlobalContractDistributionReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GlobalContractDistributionReceipt")
+            .field("shard_layout_version", &self.shard_layout_version)
+            .field("target_shard", &self.target_shard)
+            .field("already_delivered_shards", &self.already_delivered_shards)
+            .field("code", &format_args!("{}", AbbrBytes(&self.code)))
+            .finish()
+    }
+}
+
 /// A temporary data which is created by processing of DataReceipt
 /// stored in a state trie with a key = `account_id` + `data_id` until
 /// `input_data_ids` of all incoming Receipts are satisfied
@@ -526,6 +604,59 @@ pub struct BufferedReceiptIndices {
     pub shard_buffers: BTreeMap<ShardId, TrieQueueIndices>,
 }
 
+/// Idempotency keys of the receipts from one predecessor recently executed by
+/// an account, oldest first, see `IdempotencyKeyAction`.
+///
+/// This is the value stored in the `IDEMPOTENCY_KEYS` trie column.
+#[derive(Default, BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug, ProtocolSchema)]
+pub struct IdempotencyKeys {
+    pub entries: Vec<IdempotencyKeyEntry>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug, ProtocolSchema)]
+pub struct IdempotencyKeyEntry {
+    /// Hash of the key, see [`IdempotencyKeys::key_hash`].
+    pub key_hash: CryptoHash,
+    /// Height at which the receipt with the key was executed.
+    pub block_height: BlockHeight,
+}
+
+impl IdempotencyKeys {
+    /// Number of blocks for which a key is remembered.
+    pub const WINDOW: BlockHeight = 10_000;
+    /// Number of keys remembered per account for each predecessor.  While
+    /// that many keys of a predecessor are in their window, its receipts with
+    /// new keys fail, which bounds the size of the value read and written by
+    /// every receipt with a key.  The receipts of other predecessors are not
+    /// affected, as their keys are stored separately.  Keys are never forgotten
+    /// before their window ends, as a retry would run twice then.
+    pub const MAX_ENTRIES: usize = 64;
+
+    pub fn key_hash(key: &[u8]) -> CryptoHash {
+        hash(key)
+    }
+
+    /// Forgets the keys whose window ended before `block_height`.
+    pub fn prune(&mut self, block_height: BlockHeight) {
+        self.entries.retain(|entry| entry.block_height.saturating_add(Self::WINDOW) > block_height);
+    }
+
+    pub fn contains(&self, key_hash: &CryptoHash) -> bool {
+        self.entries.iter().any(|entry| &entry.key_hash == key_hash)
+    }
+
+    /// Whether no more keys can be remembered until some windows end.
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= Self::MAX_ENTRIES
+    }
+
+    /// Remembers the key.  The caller checks that the keys are not full.
+    pub fn insert(&mut self, key_hash: CryptoHash, block_height: BlockHeight) {
+        debug_assert!(!self.is_full());
+        self.entries.push(IdempotencyKeyEntry { key_hash, block_height });
+    }
+}
+
 /// Map of shard to list of receipts to send to it.
 pub type ReceiptResult = HashMap<ShardId, Vec<Receipt>>;
 
@@ -573,4 +704,27 @@ mod tests {
         let receipt2 = Receipt::try_from_slice(&serialized_receipt).unwrap();
         assert_eq!(receipt_v1, receipt2);
     }
+
+    #[test]
+    fn test_global_contract_distribution_receiver_shard_id() {
+        let shard_layout = ShardLayout::get_simple_nightshade_layout();
+        let account_id: AccountId = "aaa".parse().unwrap();
+        let receipt = |shard_layout_version| {
+            Receipt::new_global_contract_distribution(
+                account_id.clone(),
+                vec![],
+                shard_layout_version,
+                3,
+                vec![0, 1, 2],
+            )
+        };
+
+        assert_eq!(receipt(shard_layout.version()).receiver_shard_id(&shard_layout), 3);
+        // After a resharding the receipt goes to the shard of its receiver.
+        assert_eq!(
+            receipt(shard_layout.version() - 1).receiver_shard_id(&shard_layout),
+            account_id_to_shard_id(&account_id, &shard_layout)
+        );
+        assert_ne!(account_id_to_shard_id(&account_id, &shard_layout), 3);
+    }
 }
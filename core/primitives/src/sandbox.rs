@@ -1,6 +1,36 @@
+use crate::types::{AccountId, StoreKey};
+use near_crypto::PublicKey;
+
+/// State entry to be removed via sandbox state patching.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum SandboxStateDeletion {
+    /// Removes the account together with its code, access keys and data.
+    Account { account_id: AccountId },
+    /// Removes a single access key of the account.
+    AccessKey { account_id: AccountId, public_key: PublicKey },
+    /// Removes the contract code and resets the code hash of the account.
+    Contract { account_id: AccountId },
+    /// Removes a single data record of the contract.
+    Data { account_id: AccountId, data_key: StoreKey },
+}
+
+impl SandboxStateDeletion {
+    pub fn account_id(&self) -> &AccountId {
+        match self {
+            Self::Account { account_id }
+            | Self::AccessKey { account_id, .. }
+            | Self::Contract { account_id }
+            | Self::Data { account_id, .. } => account_id,
+        }
+    }
+}
+
 #[cfg(feature = "sandbox")]
 pub mod state_patch {
-    use crate::state_record::StateRecord;
+    use super::SandboxStateDeletion;
+    use crate::shard_layout::{account_id_to_shard_id, ShardLayout};
+    use crate::state_record::{state_record_to_account_id, StateRecord};
+    use crate::types::ShardId;
 
     /// Changes to the state to be applied via sandbox-only state patching
     /// feature.
@@ -9,46 +39,81 @@ pub mod state_patch {
     /// object can be non-empty only if `sandbox` feature is enabled.  On
     /// non-sandbox build, this struct is ZST and its methods are essentially
     /// short-circuited by treating the type as always empty.
+    ///
+    /// All records and deletions of a patch are applied in the same block, so
+    /// a patch touching several accounts is never observed half-applied.
     #[derive(Default)]
     pub struct SandboxStatePatch {
         records: Vec<StateRecord>,
+        deletions: Vec<SandboxStateDeletion>,
     }
 
     impl SandboxStatePatch {
         pub fn new(records: Vec<StateRecord>) -> SandboxStatePatch {
-            SandboxStatePatch { records }
+            SandboxStatePatch { records, deletions: vec![] }
+        }
+
+        pub fn with_deletions(mut self, deletions: Vec<SandboxStateDeletion>) -> SandboxStatePatch {
+            self.deletions.extend(deletions);
+            self
         }
 
         pub fn is_empty(&self) -> bool {
-            self.records.is_empty()
+            self.records.is_empty() && self.deletions.is_empty()
         }
 
         pub fn clear(&mut self) {
             self.records.clear();
+            self.deletions.clear();
         }
 
         pub fn take(&mut self) -> SandboxStatePatch {
-            Self { records: core::mem::take(&mut self.records) }
+            Self {
+                records: core::mem::take(&mut self.records),
+                deletions: core::mem::take(&mut self.deletions),
+            }
+        }
+
+        /// Takes the part of the patch touching accounts of the given shard.
+        pub fn take_for_shard(
+            &mut self,
+            shard_id: ShardId,
+            shard_layout: &ShardLayout,
+        ) -> SandboxStatePatch {
+            let in_shard =
+                |account_id| account_id_to_shard_id(account_id, shard_layout) == shard_id;
+            // The records without an account are taken with the first shard.
+            let (records, rest) =
+                core::mem::take(&mut self.records).into_iter().partition(|record| {
+                    state_record_to_account_id(record)
+                        .map_or(true, |account_id| in_shard(account_id))
+                });
+            self.records = rest;
+            let (deletions, rest) = core::mem::take(&mut self.deletions)
+                .into_iter()
+                .partition(|deletion: &SandboxStateDeletion| in_shard(deletion.account_id()));
+            self.deletions = rest;
+            Self { records, deletions }
         }
 
         pub fn merge(&mut self, other: SandboxStatePatch) {
             self.records.extend(other.records);
+            self.deletions.extend(other.deletions);
         }
-    }
 
-    impl IntoIterator for SandboxStatePatch {
-        type Item = StateRecord;
-        type IntoIter = std::vec::IntoIter<StateRecord>;
-
-        fn into_iter(self) -> Self::IntoIter {
-            self.records.into_iter()
+        /// Splits the patch into the records to write and the entries to delete.
+        pub fn into_parts(self) -> (Vec<StateRecord>, Vec<SandboxStateDeletion>) {
+            (self.records, self.deletions)
         }
     }
 }
 
 #[cfg(not(feature = "sandbox"))]
 pub mod state_patch {
+    use super::SandboxStateDeletion;
+    use crate::shard_layout::ShardLayout;
     use crate::state_record::StateRecord;
+    use crate::types::ShardId;
 
     #[derive(Default)]
     pub struct SandboxStatePatch;
@@ -65,16 +130,14 @@ pub mod state_patch {
             Self
         }
         #[inline(always)]
+        pub fn take_for_shard(&mut self, _shard_id: ShardId, _shard_layout: &ShardLayout) -> Self {
+            Self
+        }
+        #[inline(always)]
         pub fn merge(&self, _other: SandboxStatePatch) {}
-    }
-
-    impl IntoIterator for SandboxStatePatch {
-        type Item = StateRecord;
-        type IntoIter = std::iter::Empty<StateRecord>;
-
         #[inline(always)]
-        fn into_iter(self) -> Self::IntoIter {
-            std::iter::empty()
+        pub fn into_parts(self) -> (Vec<StateRecord>, Vec<SandboxStateDeletion>) {
+            (vec![], vec![])
         }
     }
 }
@@ -1,11 +1,12 @@
-use crate::account::{AccessKey, Account};
+use crate::account::{AccessKey, Account, AccountNamespace};
 use crate::hash::{hash, CryptoHash};
 use crate::receipt::{Receipt, ReceivedData};
 use crate::trie_key::trie_key_parsers::{
-    parse_account_id_from_access_key_key, parse_account_id_from_account_key,
-    parse_account_id_from_contract_code_key, parse_account_id_from_contract_data_key,
-    parse_account_id_from_received_data_key, parse_data_id_from_received_data_key,
-    parse_data_key_from_contract_data_key, parse_public_key_from_access_key_key,
+    is_namespaced_contract_key, parse_account_id_from_access_key_key,
+    parse_account_id_from_account_key, parse_account_id_from_contract_code_key,
+    parse_account_id_from_contract_data_key, parse_account_id_from_received_data_key,
+    parse_data_id_from_received_data_key, parse_data_key_from_contract_data_key,
+    parse_public_key_from_access_key_key, parse_trie_key_namespaced_contract_from_raw_key,
 };
 use crate::trie_key::{col, TrieKey};
 use crate::types::{AccountId, StoreKey, StoreValue};
@@ -43,6 +44,29 @@ pub enum StateRecord {
     /// Delayed Receipt.
     /// The receipt was delayed because the shard was overwhelmed.
     DelayedReceipt(Box<Receipt>),
+    /// Code of a global contract encoded in base64. It isn't stored under an
+    /// account but on every shard, under its hash. The accounts using it are
+    /// the accounts with `Account::global_contract` set and its hash as the
+    /// code hash.
+    GlobalContract {
+        #[serde_as(as = "Base64")]
+        code: Vec<u8>,
+    },
+    /// Code of the contract deployed to a namespace of the account, encoded in base64.
+    NamespacedContract {
+        account_id: AccountId,
+        namespace: AccountNamespace,
+        #[serde_as(as = "Base64")]
+        code: Vec<u8>,
+    },
+    /// Data records inside the contract deployed to a namespace of the account,
+    /// encoded in base64.
+    NamespacedData {
+        account_id: AccountId,
+        namespace: AccountNamespace,
+        data_key: StoreKey,
+        value: StoreValue,
+    },
 }
 
 impl StateRecord {
@@ -59,6 +83,23 @@ impl StateRecord {
         value: Vec<u8>,
     ) -> Result<Option<StateRecord>, std::io::Error> {
         Ok(match key[0] {
+            col::CONTRACT_CODE | col::CONTRACT_DATA if is_namespaced_contract_key(&key) => {
+                match parse_trie_key_namespaced_contract_from_raw_key(&key)? {
+                    TrieKey::NamespacedContractCode { account_id, namespace } => {
+                        Some(StateRecord::NamespacedContract { account_id, namespace, code: value })
+                    }
+                    TrieKey::NamespacedContractData { account_id, namespace, key } => {
+                        Some(StateRecord::NamespacedData {
+                            account_id,
+                            namespace,
+                            data_key: key.into(),
+                            value: value.into(),
+                        })
+                    }
+                    _ => unreachable!("namespaced contract keys are code or data"),
+                }
+            }
+            col::GLOBAL_CONTRACT_CODE => Some(StateRecord::GlobalContract { code: value }),
             col::ACCOUNT => Some(StateRecord::Account {
                 account_id: parse_account_id_from_account_key(&key)?,
                 account: Account::try_from_slice(&value)?,
@@ -119,6 +160,9 @@ impl StateRecord {
             StateRecord::PostponedReceipt { .. } => "PostponedReceipt",
             StateRecord::ReceivedData { .. } => "ReceivedData",
             StateRecord::DelayedReceipt { .. } => "DelayedReceipt",
+            StateRecord::GlobalContract { .. } => "GlobalContract",
+            StateRecord::NamespacedContract { .. } => "NamespacedContract",
+            StateRecord::NamespacedData { .. } => "NamespacedData",
         }
         .to_string()
     }
@@ -152,6 +196,20 @@ impl Display for StateRecord {
             ),
             StateRecord::PostponedReceipt(receipt) => write!(f, "Postponed receipt {:?}", receipt),
             StateRecord::DelayedReceipt(receipt) => write!(f, "Delayed receipt {:?}", receipt),
+            StateRecord::GlobalContract { code } => {
+                write!(f, "Global contract {}: ...", hash(code))
+            }
+            StateRecord::NamespacedContract { account_id, namespace, code: _ } => {
+                write!(f, "Code for {:?} in namespace {}: ...", account_id, namespace)
+            }
+            StateRecord::NamespacedData { account_id, namespace, data_key, value } => write!(
+                f,
+                "Storage {:?},{},{:?}: {:?}",
+                account_id,
+                namespace,
+                to_printable(data_key.as_ref()),
+                to_printable(value.as_ref())
+            ),
         }
     }
 }
@@ -171,16 +229,21 @@ fn to_printable(blob: &[u8]) -> String {
     }
 }
 
-pub fn state_record_to_account_id(state_record: &StateRecord) -> &AccountId {
+/// Account of the record, `None` for the records which aren't stored under an
+/// account and belong to every shard.
+pub fn state_record_to_account_id(state_record: &StateRecord) -> Option<&AccountId> {
     match state_record {
         StateRecord::Account { account_id, .. }
         | StateRecord::AccessKey { account_id, .. }
         | StateRecord::Contract { account_id, .. }
         | StateRecord::ReceivedData { account_id, .. }
-        | StateRecord::Data { account_id, .. } => account_id,
+        | StateRecord::Data { account_id, .. }
+        | StateRecord::NamespacedContract { account_id, .. }
+        | StateRecord::NamespacedData { account_id, .. } => Some(account_id),
         StateRecord::PostponedReceipt(receipt) | StateRecord::DelayedReceipt(receipt) => {
-            receipt.receiver_id()
+            Some(receipt.receiver_id())
         }
+        StateRecord::GlobalContract { .. } => None,
     }
 }
 
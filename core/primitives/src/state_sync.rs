@@ -4,7 +4,9 @@ use crate::sharding::{
     ReceiptProof, ShardChunk, ShardChunkHeader, ShardChunkHeaderV1, ShardChunkV1,
 };
 use crate::types::{BlockHeight, EpochId, ShardId, StateRoot, StateRootNode};
+use crate::validator_signer::ValidatorSigner;
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::{PublicKey, Signature};
 use near_primitives_core::types::EpochHeight;
 use near_schema_checker_lib::ProtocolSchema;
 use std::sync::Arc;
@@ -281,9 +283,63 @@ pub enum StateSyncDumpProgress {
     },
 }
 
+/// Hashes of the state sync header and of the state parts of a shard, dumped
+/// to external storage next to the header.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StateDumpManifest {
+    /// Block hash of the first block of the epoch whose state was dumped.
+    pub sync_hash: CryptoHash,
+    pub shard_id: ShardId,
+    /// Hash of the borsh serialized `ShardStateSyncResponseHeader`.
+    pub header_hash: CryptoHash,
+    /// Hash of every state part, indexed by part id.
+    pub part_hashes: Vec<CryptoHash>,
+}
+
+/// State dump manifest signed by the validator which dumped the state.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignedStateDumpManifest {
+    pub manifest: StateDumpManifest,
+    pub public_key: PublicKey,
+    pub signature: Signature,
+}
+
+impl SignedStateDumpManifest {
+    pub fn new(manifest: StateDumpManifest, signer: &ValidatorSigner) -> Self {
+        let signature = signer.sign_state_dump_manifest(&manifest);
+        Self { manifest, public_key: signer.public_key(), signature }
+    }
+
+    /// Checks that the manifest was signed with the included public key.
+    pub fn verify(&self) -> bool {
+        self.signature.verify(CryptoHash::hash_borsh(&self.manifest).as_ref(), &self.public_key)
+    }
+}
+
+/// Multipart upload of a state part to external storage which is underway.
+/// The dumper saves it after every chunk, so that it continues the upload
+/// after a failure or a restart instead of uploading the part again.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatePartUpload {
+    /// Location of the part in external storage.
+    pub location: String,
+    pub part_id: u64,
+    pub upload_id: String,
+    /// Hash of the part.  The upload is only continued with the same part,
+    /// so that the chunks of different parts are never put together.
+    pub part_hash: CryptoHash,
+    /// Etags of the chunks uploaded so far, in order.
+    pub chunk_etags: Vec<String>,
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::state_sync::{get_num_state_parts, STATE_PART_MEMORY_LIMIT};
+    use crate::hash::{hash, CryptoHash};
+    use crate::state_sync::{
+        get_num_state_parts, SignedStateDumpManifest, StateDumpManifest, STATE_PART_MEMORY_LIMIT,
+    };
+    use crate::validator_signer::InMemoryValidatorSigner;
+    use near_crypto::KeyType;
 
     #[test]
     fn test_get_num_state_parts() {
@@ -294,4 +350,22 @@ mod tests {
         assert_eq!(get_num_state_parts(STATE_PART_MEMORY_LIMIT.as_u64() * 100), 100);
         assert_eq!(get_num_state_parts(STATE_PART_MEMORY_LIMIT.as_u64() * 100 + 1), 101);
     }
+
+    #[test]
+    fn test_state_dump_manifest_signature() {
+        let signer =
+            InMemoryValidatorSigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test")
+                .into();
+        let manifest = StateDumpManifest {
+            sync_hash: CryptoHash::default(),
+            shard_id: 0,
+            header_hash: hash(b"header"),
+            part_hashes: vec![hash(b"part 0"), hash(b"part 1")],
+        };
+        let mut signed = SignedStateDumpManifest::new(manifest, &signer);
+        assert!(signed.verify());
+
+        signed.manifest.part_hashes[1] = hash(b"tampered part");
+        assert!(!signed.verify());
+    }
 }
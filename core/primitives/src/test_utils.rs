@@ -6,6 +6,7 @@ use crate::challenge::Challenges;
 use crate::errors::EpochError;
 use crate::hash::CryptoHash;
 
+use crate::shard_layout::ShardLayout;
 use crate::sharding::{ShardChunkHeader, ShardChunkHeaderV3};
 use crate::stateless_validation::chunk_endorsements_bitmap::ChunkEndorsementsBitmap;
 use crate::transaction::{
@@ -969,14 +970,21 @@ impl Block {
     }
 }
 
-#[derive(Default)]
 pub struct MockEpochInfoProvider {
     pub validators: HashMap<AccountId, Balance>,
+    /// All accounts are on shard 0 regardless of the layout.
+    pub shard_layout: ShardLayout,
+}
+
+impl Default for MockEpochInfoProvider {
+    fn default() -> Self {
+        Self { validators: HashMap::new(), shard_layout: ShardLayout::v0_single_shard() }
+    }
 }
 
 impl MockEpochInfoProvider {
     pub fn new(validators: impl Iterator<Item = (AccountId, Balance)>) -> Self {
-        MockEpochInfoProvider { validators: validators.collect() }
+        MockEpochInfoProvider { validators: validators.collect(), ..Default::default() }
     }
 }
 
@@ -1013,6 +1021,10 @@ impl EpochInfoProvider for MockEpochInfoProvider {
     ) -> Result<ShardId, EpochError> {
         Ok(0)
     }
+
+    fn shard_layout(&self, _epoch_id: &EpochId) -> Result<ShardLayout, EpochError> {
+        Ok(self.shard_layout.clone())
+    }
 }
 
 /// Encode array of `u64` to be passed as a smart contract argument.
@@ -2,9 +2,12 @@
 pub use crate::action::NonrefundableStorageTransferAction;
 pub use crate::action::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
-    DeployContractAction, FunctionCallAction, StakeAction, TransferAction,
+    DeployContractAction, DeployGlobalContractAction, DeployNamespacedContractAction,
+    FunctionCallAction, IdempotencyKeyAction, NamespacedFunctionCallAction, StakeAction,
+    TransferAction, UseGlobalContractAction,
 };
 use crate::errors::TxExecutionError;
+use crate::events::ContractEvent;
 use crate::hash::{hash, CryptoHash};
 use crate::merkle::MerklePath;
 use crate::profile_data_v3::ProfileDataV3;
@@ -24,6 +27,9 @@ use std::io::{Error, ErrorKind, Read, Write};
 
 pub type LogEntry = String;
 
+/// The amount of yoctoNEAR in one unit of the transaction priority fee.
+pub const PRIORITY_FEE_UNIT: Balance = 1_000_000_000_000;
+
 #[derive(
     BorshSerialize, BorshDeserialize, serde::Serialize, PartialEq, Eq, Debug, Clone, ProtocolSchema,
 )]
@@ -134,6 +140,11 @@ impl Transaction {
             Transaction::V1(tx) => Some(tx.priority_fee),
         }
     }
+
+    /// The priority fee in yoctoNEAR, zero for transactions without one.
+    pub fn priority_fee_amount(&self) -> Balance {
+        Balance::from(self.priority_fee().unwrap_or_default()) * PRIORITY_FEE_UNIT
+    }
 }
 
 impl BorshSerialize for Transaction {
@@ -416,6 +427,74 @@ pub enum ExecutionMetadata {
     V2(crate::profile_data_v2::ProfileDataV2),
     /// V3: With ProfileData by gas parameters
     V3(Box<ProfileDataV3>),
+    /// V4: With ProfileData by gas parameters, the events emitted by the contract and
+    /// the refunds issued by the runtime
+    V4(Box<ExecutionMetadataV4>),
+}
+
+impl ExecutionMetadata {
+    /// Events emitted with `emit_event`, which only `V4` records.
+    pub fn events(&self) -> &[ContractEvent] {
+        match self {
+            ExecutionMetadata::V4(metadata) => &metadata.events,
+            ExecutionMetadata::V1 | ExecutionMetadata::V2(_) | ExecutionMetadata::V3(_) => &[],
+        }
+    }
+
+    /// Refund receipts issued by the execution, which only `V4` records.
+    pub fn refunds(&self) -> &[RefundReceipt] {
+        match self {
+            ExecutionMetadata::V4(metadata) => &metadata.refunds,
+            ExecutionMetadata::V1 | ExecutionMetadata::V2(_) | ExecutionMetadata::V3(_) => &[],
+        }
+    }
+}
+
+/// Metadata of an execution that emitted events or issued refunds.
+///
+/// Other outcomes keep using `V3`, so that the stored outcomes don't change for them.
+#[derive(
+    BorshSerialize, BorshDeserialize, PartialEq, Clone, Eq, Debug, Default, ProtocolSchema,
+)]
+pub struct ExecutionMetadataV4 {
+    pub gas_profile: ProfileDataV3,
+    pub events: Vec<ContractEvent>,
+    pub refunds: Vec<RefundReceipt>,
+}
+
+/// Reason for which the runtime issued a refund receipt.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Debug,
+    ProtocolSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundReason {
+    /// Prepaid gas which was not used by the receipt, including the difference
+    /// in gas price, refunded to the signer of the transaction.
+    UnusedGas,
+    /// Deposit attached to a receipt which failed, refunded to its predecessor.
+    FailedReceiptDeposit,
+    /// Balance of an account deleted by the receipt, paid out to the
+    /// beneficiary.
+    DeletedAccountBalance,
+}
+
+/// Refund receipt issued by the execution of a receipt, recorded when the
+/// runtime creates it.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Clone, Eq, Debug, ProtocolSchema)]
+pub struct RefundReceipt {
+    pub receipt_id: CryptoHash,
+    pub receiver_id: AccountId,
+    pub amount: Balance,
+    pub reason: RefundReason,
 }
 
 impl fmt::Debug for ExecutionOutcome {
@@ -451,6 +530,7 @@ impl ExecutionOutcomeWithId {
         result.push(self.id);
         result.push(CryptoHash::hash_borsh(PartialExecutionOutcome::from(&self.outcome)));
         result.extend(self.outcome.logs.iter().map(|log| hash(log.as_bytes())));
+        result.extend(self.outcome.metadata.events().iter().map(CryptoHash::hash_borsh));
         result
     }
 }
@@ -1,3 +1,4 @@
+use crate::account::AccountNamespace;
 use crate::hash::CryptoHash;
 use crate::types::AccountId;
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -10,6 +11,10 @@ pub(crate) const ACCOUNT_DATA_SEPARATOR: u8 = b',';
 // The use of `ACCESS_KEY` as a separator is a historical artefact.
 // Changing it would require a very long DB migration for basically no benefits.
 pub(crate) const ACCESS_KEY_SEPARATOR: u8 = col::ACCESS_KEY;
+/// Separates the account id from the namespace in the contract code and data
+/// keys of the namespaced contracts. Neither account ids nor namespaces may
+/// contain it.
+pub(crate) const NAMESPACE_SEPARATOR: u8 = b'#';
 
 /// Type identifiers used for DB key generation to store values in the key-value storage.
 pub mod col {
@@ -56,12 +61,18 @@ pub mod col {
     /// backpressure on the receiving shard.
     /// (`primitives::receipt::Receipt`).
     pub const BUFFERED_RECEIPT: u8 = 14;
+    /// Idempotency keys recently executed by an `account_id` for the receipts
+    /// of a predecessor (`primitives::receipt::IdempotencyKeys`).
+    pub const IDEMPOTENCY_KEYS: u8 = 15;
+    /// Code of the global contracts, by its hash. Every shard has a copy of
+    /// all of them.
+    // NOTE: IDEMPOTENCY_KEYS = 15 was the last unique nibble in the trie, this
+    // column starts a new branch under the nibble 1.
+    pub const GLOBAL_CONTRACT_CODE: u8 = 16;
     /// All columns except those used for the delayed receipts queue, the yielded promises
-    /// queue, and the outgoing receipts buffer, which are global state for the shard.
-
-    // NOTE: NEW_COLUMN = 15 will be the last unique nibble in the trie!
-    // Consider demultiplexing on 15 and using 2-nibble prefixes.
-    pub const COLUMNS_WITH_ACCOUNT_ID_IN_KEY: [(u8, &str); 9] = [
+    /// queue, the outgoing receipts buffer, and the global contracts, which are global
+    /// state for the shard.
+    pub const COLUMNS_WITH_ACCOUNT_ID_IN_KEY: [(u8, &str); 10] = [
         (ACCOUNT, "Account"),
         (CONTRACT_CODE, "ContractCode"),
         (ACCESS_KEY, "AccessKey"),
@@ -71,9 +82,10 @@ pub mod col {
         (POSTPONED_RECEIPT, "PostponedReceipt"),
         (CONTRACT_DATA, "ContractData"),
         (PROMISE_YIELD_RECEIPT, "PromiseYieldReceipt"),
+        (IDEMPOTENCY_KEYS, "IdempotencyKeys"),
     ];
 
-    pub const ALL_COLUMNS_WITH_NAMES: [(u8, &'static str); 14] = [
+    pub const ALL_COLUMNS_WITH_NAMES: [(u8, &'static str); 16] = [
         (ACCOUNT, "Account"),
         (CONTRACT_CODE, "ContractCode"),
         (ACCESS_KEY, "AccessKey"),
@@ -88,11 +100,13 @@ pub mod col {
         (PROMISE_YIELD_RECEIPT, "PromiseYieldReceipt"),
         (BUFFERED_RECEIPT_INDICES, "BufferedReceiptIndices"),
         (BUFFERED_RECEIPT, "BufferedReceipt"),
+        (IDEMPOTENCY_KEYS, "IdempotencyKeys"),
+        (GLOBAL_CONTRACT_CODE, "GlobalContractCode"),
     ];
 }
 
 /// Describes the key of a specific key-value record in a state trie.
-#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize, ProtocolSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, BorshDeserialize, BorshSerialize, ProtocolSchema)]
 pub enum TrieKey {
     /// Used to store `primitives::account::Account` struct for a given `AccountId`.
     Account { account_id: AccountId },
@@ -143,6 +157,19 @@ pub enum TrieKey {
     /// per ordered shard pair. The trie for shard X stores all queues for pairs
     /// (X,*) without (X,X).
     BufferedReceipt { receiving_shard: ShardId, index: u64 },
+    /// Used to store the idempotency keys recently executed by a given receiver `AccountId`
+    /// for the receipts of a given predecessor `AccountId`
+    /// (`primitives::receipt::IdempotencyKeys`).
+    IdempotencyKeys { account_id: AccountId, predecessor_id: AccountId },
+    /// Used to store `Vec<u8>` contract code deployed to a namespace of a given `AccountId`.
+    /// Shares the column with `ContractCode`.
+    NamespacedContractCode { account_id: AccountId, namespace: AccountNamespace },
+    /// Used to store a key-value record `Vec<u8>` within a contract deployed to a namespace of a
+    /// given `AccountId` and a given key. Shares the column with `ContractData`.
+    NamespacedContractData { account_id: AccountId, namespace: AccountNamespace, key: Vec<u8> },
+    /// Used to store `Vec<u8>` code of a global contract with the given hash.
+    /// NOTE: Every shard stores all global contracts.
+    GlobalContractCode { code_hash: CryptoHash },
 }
 
 /// Provides `len` function.
@@ -218,6 +245,29 @@ impl TrieKey {
                     + std::mem::size_of::<u16>()
                     + std::mem::size_of_val(index)
             }
+            TrieKey::IdempotencyKeys { account_id, predecessor_id } => {
+                col::IDEMPOTENCY_KEYS.len()
+                    + account_id.len()
+                    + ACCOUNT_DATA_SEPARATOR.len()
+                    + predecessor_id.len()
+            }
+            TrieKey::NamespacedContractCode { account_id, namespace } => {
+                col::CONTRACT_CODE.len()
+                    + account_id.len()
+                    + NAMESPACE_SEPARATOR.len()
+                    + namespace.len()
+            }
+            TrieKey::NamespacedContractData { account_id, namespace, key } => {
+                col::CONTRACT_DATA.len()
+                    + account_id.len()
+                    + NAMESPACE_SEPARATOR.len()
+                    + namespace.len()
+                    + ACCOUNT_DATA_SEPARATOR.len()
+                    + key.len()
+            }
+            TrieKey::GlobalContractCode { code_hash } => {
+                col::GLOBAL_CONTRACT_CODE.len() + code_hash.as_ref().len()
+            }
         }
     }
 
@@ -298,6 +348,30 @@ impl TrieKey {
                 buf.extend(&(*receiving_shard as u16).to_le_bytes());
                 buf.extend(&index.to_le_bytes());
             }
+            TrieKey::IdempotencyKeys { account_id, predecessor_id } => {
+                buf.push(col::IDEMPOTENCY_KEYS);
+                buf.extend(account_id.as_bytes());
+                buf.push(ACCOUNT_DATA_SEPARATOR);
+                buf.extend(predecessor_id.as_bytes());
+            }
+            TrieKey::NamespacedContractCode { account_id, namespace } => {
+                buf.push(col::CONTRACT_CODE);
+                buf.extend(account_id.as_bytes());
+                buf.push(NAMESPACE_SEPARATOR);
+                buf.extend(namespace.as_str().as_bytes());
+            }
+            TrieKey::NamespacedContractData { account_id, namespace, key } => {
+                buf.push(col::CONTRACT_DATA);
+                buf.extend(account_id.as_bytes());
+                buf.push(NAMESPACE_SEPARATOR);
+                buf.extend(namespace.as_str().as_bytes());
+                buf.push(ACCOUNT_DATA_SEPARATOR);
+                buf.extend(key);
+            }
+            TrieKey::GlobalContractCode { code_hash } => {
+                buf.push(col::GLOBAL_CONTRACT_CODE);
+                buf.extend(code_hash.as_ref());
+            }
         };
         debug_assert_eq!(expected_len, buf.len() - start_len);
     }
@@ -326,6 +400,10 @@ impl TrieKey {
             TrieKey::PromiseYieldReceipt { receiver_id, .. } => Some(receiver_id.clone()),
             TrieKey::BufferedReceiptIndices => None,
             TrieKey::BufferedReceipt { .. } => None,
+            TrieKey::IdempotencyKeys { account_id, .. } => Some(account_id.clone()),
+            TrieKey::NamespacedContractCode { account_id, .. } => Some(account_id.clone()),
+            TrieKey::NamespacedContractData { account_id, .. } => Some(account_id.clone()),
+            TrieKey::GlobalContractCode { .. } => None,
         }
     }
 }
@@ -409,12 +487,62 @@ pub mod trie_key_parsers {
         data.iter().position(|&byte| byte == separator).map(|idx| &data[..idx])
     }
 
+    /// Returns the account id part of the contract code or data key owner,
+    /// dropping the namespace of the namespaced contracts.
+    fn strip_namespace(owner: &[u8]) -> &[u8] {
+        next_token(owner, NAMESPACE_SEPARATOR).unwrap_or(owner)
+    }
+
+    /// Whether the contract code or data key belongs to a namespaced contract,
+    /// i.e. is `TrieKey::NamespacedContractCode` or
+    /// `TrieKey::NamespacedContractData`.
+    pub fn is_namespaced_contract_key(raw_key: &[u8]) -> bool {
+        let owner = match raw_key.split_first() {
+            Some((&col::CONTRACT_CODE, tail)) => tail,
+            Some((&col::CONTRACT_DATA, tail)) => {
+                next_token(tail, ACCOUNT_DATA_SEPARATOR).unwrap_or(tail)
+            }
+            _ => return false,
+        };
+        owner.contains(&NAMESPACE_SEPARATOR)
+    }
+
+    /// Parses `TrieKey::NamespacedContractCode` or
+    /// `TrieKey::NamespacedContractData` from the raw key.
+    pub fn parse_trie_key_namespaced_contract_from_raw_key(
+        raw_key: &[u8],
+    ) -> Result<TrieKey, std::io::Error> {
+        let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+        let (owner, data_key) = match raw_key.split_first() {
+            Some((&col::CONTRACT_CODE, tail)) => (tail, None),
+            Some((&col::CONTRACT_DATA, tail)) => {
+                let owner = next_token(tail, ACCOUNT_DATA_SEPARATOR).ok_or_else(|| {
+                    invalid("raw key does not have ACCOUNT_DATA_SEPARATOR to be a namespaced key")
+                })?;
+                (owner, Some(&tail[owner.len() + ACCOUNT_DATA_SEPARATOR.len()..]))
+            }
+            _ => return Err(invalid("raw key does not belong to a contract column")),
+        };
+        let account_id = next_token(owner, NAMESPACE_SEPARATOR)
+            .ok_or_else(|| invalid("raw key does not have NAMESPACE_SEPARATOR"))?;
+        let namespace = std::str::from_utf8(&owner[account_id.len() + NAMESPACE_SEPARATOR.len()..])
+            .map(AccountNamespace::new)
+            .map_err(|_| invalid("raw key namespace has invalid UTF-8 format"))?;
+        let account_id = parse_account_id_from_slice(account_id, "NamespacedContract")?;
+        Ok(match data_key {
+            None => TrieKey::NamespacedContractCode { account_id, namespace },
+            Some(key) => {
+                TrieKey::NamespacedContractData { account_id, namespace, key: key.to_vec() }
+            }
+        })
+    }
+
     pub fn parse_account_id_from_contract_data_key(
         raw_key: &[u8],
     ) -> Result<AccountId, std::io::Error> {
         let account_id_prefix = parse_account_id_prefix(col::CONTRACT_DATA, raw_key)?;
-        if let Some(account_id) = next_token(account_id_prefix, ACCOUNT_DATA_SEPARATOR) {
-            parse_account_id_from_slice(account_id, "ContractData")
+        if let Some(owner) = next_token(account_id_prefix, ACCOUNT_DATA_SEPARATOR) {
+            parse_account_id_from_slice(strip_namespace(owner), "ContractData")
         } else {
             Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
@@ -428,6 +556,35 @@ pub mod trie_key_parsers {
         parse_account_id_from_slice(account_id, "Account")
     }
 
+    pub fn parse_account_id_from_idempotency_keys_key(
+        raw_key: &[u8],
+    ) -> Result<AccountId, std::io::Error> {
+        let account_id_prefix = parse_account_id_prefix(col::IDEMPOTENCY_KEYS, raw_key)?;
+        if let Some(account_id) = next_token(account_id_prefix, ACCOUNT_DATA_SEPARATOR) {
+            parse_account_id_from_slice(account_id, "IdempotencyKeys")
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "raw key does not have ACCOUNT_DATA_SEPARATOR to be TrieKey::IdempotencyKeys",
+            ))
+        }
+    }
+
+    pub fn parse_predecessor_id_from_idempotency_keys_key(
+        raw_key: &[u8],
+        account_id: &AccountId,
+    ) -> Result<AccountId, std::io::Error> {
+        let prefix_len =
+            col::IDEMPOTENCY_KEYS.len() + account_id.len() + ACCOUNT_DATA_SEPARATOR.len();
+        if raw_key.len() < prefix_len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "raw key is too short for TrieKey::IdempotencyKeys",
+            ));
+        }
+        parse_account_id_from_slice(&raw_key[prefix_len..], "IdempotencyKeys")
+    }
+
     pub fn parse_account_id_from_access_key_key(
         raw_key: &[u8],
     ) -> Result<AccountId, std::io::Error> {
@@ -445,8 +602,8 @@ pub mod trie_key_parsers {
     pub fn parse_account_id_from_contract_code_key(
         raw_key: &[u8],
     ) -> Result<AccountId, std::io::Error> {
-        let account_id = parse_account_id_prefix(col::CONTRACT_CODE, raw_key)?;
-        parse_account_id_from_slice(account_id, "ContractCode")
+        let owner = parse_account_id_prefix(col::CONTRACT_CODE, raw_key)?;
+        parse_account_id_from_slice(strip_namespace(owner), "ContractCode")
     }
 
     pub fn parse_trie_key_access_key_from_raw_key(
@@ -467,7 +624,9 @@ pub mod trie_key_parsers {
             let account_id = match col {
                 col::ACCOUNT => parse_account_id_from_account_key(raw_key)?,
                 col::CONTRACT_CODE => parse_account_id_from_contract_code_key(raw_key)?,
+                col::CONTRACT_DATA => parse_account_id_from_contract_data_key(raw_key)?,
                 col::ACCESS_KEY => parse_account_id_from_access_key_key(raw_key)?,
+                col::IDEMPOTENCY_KEYS => parse_account_id_from_idempotency_keys_key(raw_key)?,
                 _ => parse_account_id_from_trie_key_with_separator(col, raw_key, col_name)?,
             };
             return Ok(Some(account_id));
@@ -537,6 +696,28 @@ pub mod trie_key_parsers {
         res.extend(prefix);
         res
     }
+
+    pub fn get_raw_prefix_for_idempotency_keys(account_id: &AccountId) -> Vec<u8> {
+        let mut res = Vec::with_capacity(
+            col::IDEMPOTENCY_KEYS.len() + account_id.len() + ACCOUNT_DATA_SEPARATOR.len(),
+        );
+        res.push(col::IDEMPOTENCY_KEYS);
+        res.extend(account_id.as_bytes());
+        res.push(ACCOUNT_DATA_SEPARATOR);
+        res
+    }
+
+    /// Prefix of the keys of the given column (`col::CONTRACT_CODE` or
+    /// `col::CONTRACT_DATA`) which belong to the namespaced contracts of the
+    /// account.
+    pub fn get_raw_prefix_for_namespaces(column: u8, account_id: &AccountId) -> Vec<u8> {
+        let mut res =
+            Vec::with_capacity(column.len() + account_id.len() + NAMESPACE_SEPARATOR.len());
+        res.push(column);
+        res.extend(account_id.as_bytes());
+        res.push(NAMESPACE_SEPARATOR);
+        res
+    }
 }
 
 #[cfg(test)]
@@ -592,6 +773,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_key_for_idempotency_keys_consistency() {
+        for account_id in OK_ACCOUNT_IDS.iter().map(|x| x.parse::<AccountId>().unwrap()) {
+            let predecessor_id: AccountId = "relayer.near".parse().unwrap();
+            let key = TrieKey::IdempotencyKeys {
+                account_id: account_id.clone(),
+                predecessor_id: predecessor_id.clone(),
+            };
+            let raw_key = key.to_vec();
+            assert_eq!(raw_key.len(), key.len());
+            assert!(raw_key
+                .starts_with(&trie_key_parsers::get_raw_prefix_for_idempotency_keys(&account_id)));
+            assert_eq!(
+                trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().unwrap(),
+                account_id
+            );
+            assert_eq!(
+                trie_key_parsers::parse_predecessor_id_from_idempotency_keys_key(
+                    &raw_key,
+                    &account_id
+                )
+                .unwrap(),
+                predecessor_id
+            );
+            assert_eq!(key.get_account_id(), Some(account_id));
+        }
+    }
+
     #[test]
     fn test_key_for_access_key_consistency() {
         let public_key = PublicKey::empty(KeyType::ED25519);
@@ -663,6 +872,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_key_for_namespaced_contract_consistency() {
+        let namespace = AccountNamespace::new("token");
+        let raw_data_key = b"0123,#456789" as &[u8];
+        for account_id in OK_ACCOUNT_IDS.iter().map(|x| x.parse::<AccountId>().unwrap()) {
+            let code_key = TrieKey::NamespacedContractCode {
+                account_id: account_id.clone(),
+                namespace: namespace.clone(),
+            };
+            let data_key = TrieKey::NamespacedContractData {
+                account_id: account_id.clone(),
+                namespace: namespace.clone(),
+                key: raw_data_key.to_vec(),
+            };
+            for key in [code_key, data_key] {
+                let raw_key = key.to_vec();
+                assert_eq!(raw_key.len(), key.len());
+                assert!(trie_key_parsers::is_namespaced_contract_key(&raw_key));
+                assert_eq!(
+                    trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().unwrap(),
+                    account_id
+                );
+                assert_eq!(key.get_account_id(), Some(account_id.clone()));
+                assert_eq!(
+                    trie_key_parsers::parse_trie_key_namespaced_contract_from_raw_key(&raw_key)
+                        .unwrap(),
+                    key
+                );
+            }
+            let raw_key = TrieKey::ContractData { account_id, key: raw_data_key.to_vec() }.to_vec();
+            assert!(!trie_key_parsers::is_namespaced_contract_key(&raw_key));
+        }
+    }
+
     #[test]
     fn test_key_for_received_data_consistency() {
         for account_id in OK_ACCOUNT_IDS.iter().map(|x| x.parse::<AccountId>().unwrap()) {
@@ -746,6 +989,19 @@ mod tests {
         assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
     }
 
+    #[test]
+    fn test_key_for_global_contract_code_consistency() {
+        let key = TrieKey::GlobalContractCode { code_hash: CryptoHash::hash_bytes(b"code") };
+        let raw_key = key.to_vec();
+        assert_eq!(raw_key.len(), key.len());
+        assert_eq!(key.get_account_id(), None);
+        assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
+        // No other column starts with the same nibble.
+        assert!(col::ALL_COLUMNS_WITH_NAMES
+            .iter()
+            .all(|(col, _)| *col == col::GLOBAL_CONTRACT_CODE || col >> 4 != raw_key[0] >> 4));
+    }
+
     #[test]
     fn test_key_for_promise_yield_consistency() {
         let key = TrieKey::PromiseYieldIndices;
@@ -3,6 +3,7 @@ use crate::challenge::ChallengesResult;
 use crate::errors::EpochError;
 use crate::hash::CryptoHash;
 use crate::serialize::dec_format;
+use crate::shard_layout::ShardLayout;
 use crate::trie_key::TrieKey;
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::PublicKey;
@@ -15,8 +16,10 @@ use std::sync::Arc;
 use std::sync::LazyLock;
 
 mod chunk_validator_stats;
+mod epoch_shard_stats;
 
 pub use chunk_validator_stats::ChunkStats;
+pub use epoch_shard_stats::EpochShardStats;
 
 /// Hash used by to store state root.
 pub type StateRoot = CryptoHash;
@@ -28,7 +31,9 @@ pub type StateRoot = CryptoHash;
 pub enum Finality {
     #[serde(rename = "optimistic")]
     None,
-    #[serde(rename = "near-final")]
+    /// The last block finalized by doomslug, i.e. the last block with
+    /// approvals from more than 2/3 of the stake on the next height.
+    #[serde(rename = "near-final", alias = "doomslug")]
     DoomSlug,
     #[serde(rename = "final")]
     #[default]
@@ -140,13 +145,15 @@ impl StateChangesKinds {
                     TrieKey::Account { account_id } => {
                         Some(Ok(StateChangeKind::AccountTouched { account_id }))
                     }
-                    TrieKey::ContractCode { account_id } => {
+                    TrieKey::ContractCode { account_id }
+                    | TrieKey::NamespacedContractCode { account_id, .. } => {
                         Some(Ok(StateChangeKind::ContractCodeTouched { account_id }))
                     }
                     TrieKey::AccessKey { account_id, .. } => {
                         Some(Ok(StateChangeKind::AccessKeyTouched { account_id }))
                     }
-                    TrieKey::ContractData { account_id, .. } => {
+                    TrieKey::ContractData { account_id, .. }
+                    | TrieKey::NamespacedContractData { account_id, .. } => {
                         Some(Ok(StateChangeKind::DataTouched { account_id }))
                     }
                     _ => None,
@@ -354,6 +361,11 @@ impl StateChanges {
                 TrieKey::PromiseYieldReceipt { .. } => {}
                 TrieKey::BufferedReceiptIndices => {}
                 TrieKey::BufferedReceipt { .. } => {}
+                TrieKey::IdempotencyKeys { .. } => {}
+                // Namespaced contracts have no state change views yet.
+                TrieKey::NamespacedContractCode { .. } => {}
+                TrieKey::NamespacedContractData { .. } => {}
+                TrieKey::GlobalContractCode { .. } => {}
             }
         }
 
@@ -1109,6 +1121,9 @@ pub trait EpochInfoProvider: Send + Sync {
         account_id: &AccountId,
         epoch_id: &EpochId,
     ) -> Result<ShardId, EpochError>;
+
+    /// Shard layout of the given epoch.
+    fn shard_layout(&self, epoch_id: &EpochId) -> Result<ShardLayout, EpochError>;
 }
 
 /// Mode of the trie cache.
@@ -1152,6 +1167,7 @@ mod tests {
     use near_primitives_core::types::Balance;
 
     use super::validator_stake::ValidatorStake;
+    use super::Finality;
 
     fn new_validator_stake(stake: Balance) -> ValidatorStake {
         ValidatorStake::new(
@@ -1168,6 +1184,19 @@ mod tests {
         assert_eq!(new_validator_stake(12).num_mandates(5), 2);
     }
 
+    #[test]
+    fn test_finality_deserialization() {
+        for (value, finality) in [
+            ("optimistic", Finality::None),
+            ("near-final", Finality::DoomSlug),
+            ("doomslug", Finality::DoomSlug),
+            ("final", Finality::Final),
+        ] {
+            assert_eq!(serde_json::from_value::<Finality>(value.into()).unwrap(), finality);
+        }
+        assert_eq!(serde_json::to_value(Finality::DoomSlug).unwrap(), "near-final");
+    }
+
     #[test]
     fn test_validator_partial_mandate_weight() {
         assert_eq!(new_validator_stake(0).partial_mandate_weight(5), 0);
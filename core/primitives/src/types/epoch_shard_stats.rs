@@ -0,0 +1,38 @@
+use super::{EpochHeight, EpochId, Gas};
+use crate::hash::CryptoHash;
+use crate::shard_layout::ShardUId;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Summary of a shard over a finished epoch. Saved by nodes which enable
+/// `save_epoch_shard_stats` to collect data for capacity planning.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct EpochShardStats {
+    pub epoch_id: EpochId,
+    pub epoch_height: EpochHeight,
+    pub shard_uid: ShardUId,
+    /// Last block of the epoch. The state of the shard is measured after
+    /// this block.
+    pub last_block_hash: CryptoHash,
+    /// Memory usage of the state trie of the shard.
+    pub state_size: u64,
+    pub num_accounts: u64,
+    /// Change of `num_accounts` since the previous epoch, if the stats of the
+    /// previous epoch were saved.
+    pub num_accounts_delta: Option<i64>,
+    /// Number of blocks in the epoch which contain a new chunk of the shard.
+    pub num_chunks: u64,
+    pub num_transactions: u64,
+    /// Number of receipts executed, including the ones converted from
+    /// transactions within the shard.
+    pub num_receipts: u64,
+    pub gas_burnt: Gas,
+}
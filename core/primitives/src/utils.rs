@@ -314,6 +314,19 @@ pub fn create_random_seed(
     res.as_ref().to_vec()
 }
 
+/// Creates the randomness of a receipt returned by the `random_beacon` host function from the
+/// `receipt_id` and the `random_value` of the block the receipt is executed in.
+/// The prefix keeps it different from the random seed of any action of the receipt.
+pub fn create_random_beacon(receipt_id: &CryptoHash, random_value: &CryptoHash) -> CryptoHash {
+    const PREFIX: &[u8] = b"random_beacon";
+    let mut bytes: Vec<u8> =
+        Vec::with_capacity(PREFIX.len() + size_of::<CryptoHash>() + size_of::<CryptoHash>());
+    bytes.extend_from_slice(PREFIX);
+    bytes.extend_from_slice(random_value.as_ref());
+    bytes.extend_from_slice(receipt_id.as_ref());
+    hash(&bytes)
+}
+
 /// Creates a new CryptoHash ID based on the protocol version.
 /// Before `CREATE_HASH_PROTOCOL_VERSION` it uses `create_nonce_with_nonce` with
 /// just `base` and `salt`. But after `CREATE_HASH_PROTOCOL_VERSION` it uses
@@ -116,6 +116,16 @@ impl ValidatorMandates {
 
         Self { config, stake_per_mandate, mandates, partials }
     }
+
+    /// The desired number of mandates per shard.
+    pub fn target_mandates_per_shard(&self) -> usize {
+        self.config.target_mandates_per_shard
+    }
+
+    /// The amount of stake a whole mandate is worth.
+    pub fn stake_per_mandate(&self) -> Balance {
+        self.stake_per_mandate
+    }
 }
 
 #[cfg(feature = "rand")]
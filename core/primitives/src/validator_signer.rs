@@ -9,12 +9,12 @@ use crate::challenge::ChallengeBody;
 use crate::hash::CryptoHash;
 use crate::network::{AnnounceAccount, PeerId};
 use crate::sharding::ChunkHash;
+use crate::state_sync::StateDumpManifest;
 use crate::stateless_validation::chunk_endorsement::{
     ChunkEndorsementInner, ChunkEndorsementMetadata,
 };
 use crate::stateless_validation::partial_witness::PartialEncodedStateWitnessInner;
 use crate::stateless_validation::state_witness::EncodedChunkStateWitness;
-use crate::telemetry::TelemetryInfo;
 use crate::types::{AccountId, BlockHeight, EpochId};
 use crate::utils::compression::CompressedData;
 
@@ -45,11 +45,11 @@ impl ValidatorSigner {
         }
     }
 
-    /// Serializes telemetry info to JSON and signs it, returning JSON with "signature" field.
-    pub fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value {
+    /// Signs the telemetry payload, returning it with "signature" field.
+    pub fn sign_telemetry(&self, content: &serde_json::Value) -> serde_json::Value {
         match self {
-            ValidatorSigner::Empty(signer) => signer.sign_telemetry(info),
-            ValidatorSigner::InMemory(signer) => signer.sign_telemetry(info),
+            ValidatorSigner::Empty(signer) => signer.sign_telemetry(content),
+            ValidatorSigner::InMemory(signer) => signer.sign_telemetry(content),
         }
     }
 
@@ -129,6 +129,14 @@ impl ValidatorSigner {
         }
     }
 
+    /// Signs the manifest of state parts dumped to external storage.
+    pub fn sign_state_dump_manifest(&self, manifest: &StateDumpManifest) -> Signature {
+        match self {
+            ValidatorSigner::Empty(signer) => signer.sign_state_dump_manifest(manifest),
+            ValidatorSigner::InMemory(signer) => signer.sign_state_dump_manifest(manifest),
+        }
+    }
+
     /// Signs account announce.
     pub fn sign_account_announce(
         &self,
@@ -216,8 +224,8 @@ impl EmptyValidatorSigner {
         PublicKey::empty(KeyType::ED25519)
     }
 
-    fn sign_telemetry(&self, _info: &TelemetryInfo) -> serde_json::Value {
-        serde_json::Value::default()
+    fn sign_telemetry(&self, content: &serde_json::Value) -> serde_json::Value {
+        content.clone()
     }
 
     fn sign_block_header_parts(
@@ -261,6 +269,10 @@ impl EmptyValidatorSigner {
         (CryptoHash::hash_borsh(challenge_body), Signature::default())
     }
 
+    fn sign_state_dump_manifest(&self, _manifest: &StateDumpManifest) -> Signature {
+        Signature::default()
+    }
+
     fn sign_account_announce(
         &self,
         _account_id: &AccountId,
@@ -314,9 +326,9 @@ impl InMemoryValidatorSigner {
         &self.account_id
     }
 
-    fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value {
-        let mut value = serde_json::to_value(info).expect("Telemetry must serialize to JSON");
-        let content = serde_json::to_string(&value).expect("Telemetry must serialize to JSON");
+    fn sign_telemetry(&self, content: &serde_json::Value) -> serde_json::Value {
+        let mut value = content.clone();
+        let content = serde_json::to_string(content).expect("Telemetry must serialize to JSON");
         value["signature"] = self.signer.sign(content.as_bytes()).to_string().into();
         value
     }
@@ -364,6 +376,10 @@ impl InMemoryValidatorSigner {
         (hash, signature)
     }
 
+    fn sign_state_dump_manifest(&self, manifest: &StateDumpManifest) -> Signature {
+        self.signer.sign(CryptoHash::hash_borsh(manifest).as_ref())
+    }
+
     pub fn sign_account_announce(
         &self,
         account_id: &AccountId,
@@ -3,7 +3,11 @@
 //! These types should only change when we cannot avoid this. Thus, when the counterpart internal
 //! type gets changed, the view should preserve the old shape and only re-map the necessary bits
 //! from the source structure in the relevant `From<SourceStruct>` impl.
-use crate::account::{AccessKey, AccessKeyPermission, Account, FunctionCallPermission};
+use crate::account::{
+    AccessKey, AccessKeyPermission, Account, AccountNamespace, FunctionCallPermission,
+    LimitedFullAccessPermission,
+};
+use crate::action::atomic_batch::{AtomicBatchAction, AtomicBatchReceipt};
 use crate::action::delegate::{DelegateAction, SignedDelegateAction};
 use crate::block::{Block, BlockHeader, Tip};
 use crate::block_header::{
@@ -11,27 +15,35 @@ use crate::block_header::{
     BlockHeaderInnerRestV5, BlockHeaderV1, BlockHeaderV2, BlockHeaderV3, BlockHeaderV5,
 };
 use crate::block_header::{BlockHeaderInnerRestV4, BlockHeaderV4};
-use crate::challenge::{Challenge, ChallengesResult};
+use crate::challenge::{Challenge, ChallengesResult, PartialState};
 use crate::checked_feature;
 use crate::congestion_info::{CongestionInfo, CongestionInfoV1};
 use crate::errors::TxExecutionError;
+use crate::events::ContractEvent;
 use crate::hash::{hash, CryptoHash};
 use crate::merkle::{combine_hash, MerklePath};
 use crate::network::PeerId;
-use crate::receipt::{ActionReceipt, DataReceipt, DataReceiver, Receipt, ReceiptEnum, ReceiptV1};
+use crate::receipt::{
+    ActionReceipt, DataReceipt, DataReceiver, GlobalContractDistributionReceipt, Receipt,
+    ReceiptEnum, ReceiptV1,
+};
 use crate::serialize::dec_format;
+use crate::shard_layout::ShardVersion;
 use crate::sharding::{
     ChunkHash, ShardChunk, ShardChunkHeader, ShardChunkHeaderInner, ShardChunkHeaderInnerV2,
     ShardChunkHeaderInnerV3, ShardChunkHeaderV3,
 };
 use crate::stateless_validation::chunk_endorsements_bitmap::ChunkEndorsementsBitmap;
+use crate::stateless_validation::state_witness::{ChunkStateTransition, ChunkStateWitness};
 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
 use crate::transaction::NonrefundableStorageTransferAction;
 use crate::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
-    DeployContractAction, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithIdAndProof,
-    ExecutionStatus, FunctionCallAction, PartialExecutionOutcome, PartialExecutionStatus,
-    SignedTransaction, StakeAction, TransferAction,
+    DeployContractAction, DeployGlobalContractAction, DeployNamespacedContractAction,
+    ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithIdAndProof, ExecutionStatus,
+    FunctionCallAction, IdempotencyKeyAction, NamespacedFunctionCallAction,
+    PartialExecutionOutcome, PartialExecutionStatus, RefundReason, RefundReceipt,
+    SignedTransaction, StakeAction, TransferAction, UseGlobalContractAction,
 };
 use crate::types::{
     AccountId, AccountWithPublicKey, Balance, BlockHeight, EpochHeight, EpochId, FunctionArgs, Gas,
@@ -51,8 +63,9 @@ use near_schema_checker_lib::ProtocolSchema;
 use near_time::Utc;
 use serde_with::base64::Base64;
 use serde_with::serde_as;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::num::NonZeroU32;
 use std::ops::Range;
 use std::sync::Arc;
 use strum::IntoEnumIterator;
@@ -73,6 +86,12 @@ pub struct AccountView {
     /// TODO(2271): deprecated.
     #[serde(default)]
     pub storage_paid_at: BlockHeight,
+    /// Hashes of the contracts deployed to the namespaces of the account.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub namespaces: BTreeMap<AccountNamespace, CryptoHash>,
+    /// Whether `code_hash` is the hash of a global contract the account uses.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub global_contract: bool,
 }
 
 /// A view of the contract code.
@@ -95,6 +114,8 @@ impl From<&Account> for AccountView {
             code_hash: account.code_hash(),
             storage_usage: account.storage_usage(),
             storage_paid_at: 0,
+            namespaces: account.namespaces().clone(),
+            global_contract: account.global_contract(),
         }
     }
 }
@@ -111,14 +132,21 @@ impl From<&AccountView> for Account {
         let permanent_storage_bytes = view.permanent_storage_bytes;
         #[cfg(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))]
         let permanent_storage_bytes = 0;
-        Account::new(
+        let mut account = Account::new(
             view.amount,
             view.locked,
             permanent_storage_bytes,
             view.code_hash,
             view.storage_usage,
             PROTOCOL_VERSION,
-        )
+        );
+        if view.global_contract {
+            account.set_global_contract_hash(view.code_hash);
+        }
+        for (namespace, code_hash) in &view.namespaces {
+            account.set_namespace_code_hash(namespace.clone(), *code_hash);
+        }
+        account
     }
 }
 
@@ -146,6 +174,11 @@ pub enum AccessKeyPermissionView {
         method_names: Vec<String>,
     },
     FullAccess,
+    LimitedFullAccess {
+        #[serde(with = "dec_format")]
+        allowance: Balance,
+        expiry_height: Option<BlockHeight>,
+    },
 }
 
 impl From<AccessKeyPermission> for AccessKeyPermissionView {
@@ -157,6 +190,12 @@ impl From<AccessKeyPermission> for AccessKeyPermissionView {
                 method_names: func_call.method_names,
             },
             AccessKeyPermission::FullAccess => AccessKeyPermissionView::FullAccess,
+            AccessKeyPermission::LimitedFullAccess(permission) => {
+                AccessKeyPermissionView::LimitedFullAccess {
+                    allowance: permission.allowance,
+                    expiry_height: permission.expiry_height,
+                }
+            }
         }
     }
 }
@@ -172,6 +211,12 @@ impl From<AccessKeyPermissionView> for AccessKeyPermission {
                 })
             }
             AccessKeyPermissionView::FullAccess => AccessKeyPermission::FullAccess,
+            AccessKeyPermissionView::LimitedFullAccess { allowance, expiry_height } => {
+                AccessKeyPermission::LimitedFullAccess(LimitedFullAccessPermission {
+                    allowance,
+                    expiry_height,
+                })
+            }
         }
     }
 }
@@ -217,6 +262,20 @@ pub struct ViewStateResult {
     #[serde_as(as = "Vec<Base64>")]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub proof: Vec<Arc<[u8]>>,
+    /// Set if the values were limited and there are more. Pass it as `cursor_base64` of the next
+    /// request to get the next page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<StoreKey>,
+}
+
+/// Account together with the trie nodes proving it against the state root.
+#[serde_as]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AccountWithProofView {
+    #[serde(flatten)]
+    pub account: AccountView,
+    #[serde_as(as = "Vec<Base64>")]
+    pub proof: Vec<Arc<[u8]>>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Default)]
@@ -240,11 +299,77 @@ pub struct AccessKeyInfoView {
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct AccessKeyList {
     pub keys: Vec<AccessKeyInfoView>,
+    /// Set if the list was limited and there are more matching keys. Pass it as `cursor` of the
+    /// next request to get the next page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<PublicKey>,
 }
 
 impl FromIterator<AccessKeyInfoView> for AccessKeyList {
     fn from_iter<I: IntoIterator<Item = AccessKeyInfoView>>(iter: I) -> Self {
-        Self { keys: iter.into_iter().collect() }
+        Self { keys: iter.into_iter().collect(), next_cursor: None }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessKeyPermissionKind {
+    FullAccess,
+    FunctionCall,
+    LimitedFullAccess,
+}
+
+/// Pagination and filtering of the `view_access_key_list` query. Keys are listed in the order
+/// in which they are stored in the trie.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct AccessKeyListOptions {
+    /// Only keys stored after this key are listed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<PublicKey>,
+    /// Maximum number of keys to list, at least one. If not set, all matching keys are listed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<NonZeroU32>,
+    /// Only keys with this kind of permission are listed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission: Option<AccessKeyPermissionKind>,
+    /// Only function call keys allowing calls to this receiver are listed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiver_id: Option<AccountId>,
+}
+
+/// Pagination of the `view_state` query. Values are listed in the order of their keys.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct ViewStateOptions {
+    /// The `next_cursor` of the previous page, i.e. the key from which values are listed.
+    #[serde(default, rename = "cursor_base64", skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<StoreKey>,
+    /// Maximum number of values to list. If set, the size limit of the state of the account
+    /// isn't enforced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+impl AccessKeyListOptions {
+    /// Returns true if the access key passes the filters of the options.
+    pub fn matches(&self, access_key: &AccessKey) -> bool {
+        match &access_key.permission {
+            AccessKeyPermission::FullAccess => {
+                self.permission.map_or(true, |kind| kind == AccessKeyPermissionKind::FullAccess)
+                    && self.receiver_id.is_none()
+            }
+            AccessKeyPermission::LimitedFullAccess(_) => {
+                self.permission
+                    .map_or(true, |kind| kind == AccessKeyPermissionKind::LimitedFullAccess)
+                    && self.receiver_id.is_none()
+            }
+            AccessKeyPermission::FunctionCall(permission) => {
+                self.permission.map_or(true, |kind| kind == AccessKeyPermissionKind::FunctionCall)
+                    && self
+                        .receiver_id
+                        .as_ref()
+                        .map_or(true, |receiver_id| permission.receiver_id == receiver_id.as_str())
+            }
+        }
     }
 }
 
@@ -257,6 +382,9 @@ pub struct KnownPeerStateView {
     pub first_seen: i64,
     pub last_seen: i64,
     pub last_attempt: Option<(i64, String)>,
+    /// Score of how useful the peer has been, rounded.
+    #[serde(default)]
+    pub score: i64,
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -281,6 +409,7 @@ pub struct SnapshotHostInfoView {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum QueryResponseKind {
     ViewAccount(AccountView),
+    ViewAccountWithProof(AccountWithProofView),
     ViewCode(ContractCodeView),
     ViewState(ViewStateResult),
     CallResult(CallResult),
@@ -293,6 +422,8 @@ pub enum QueryResponseKind {
 pub enum QueryRequest {
     ViewAccount {
         account_id: AccountId,
+        #[serde(default, skip_serializing_if = "is_false")]
+        include_proof: bool,
     },
     ViewCode {
         account_id: AccountId,
@@ -303,6 +434,8 @@ pub enum QueryRequest {
         prefix: StoreKey,
         #[serde(default, skip_serializing_if = "is_false")]
         include_proof: bool,
+        #[serde(flatten)]
+        options: ViewStateOptions,
     },
     ViewAccessKey {
         account_id: AccountId,
@@ -310,6 +443,8 @@ pub enum QueryRequest {
     },
     ViewAccessKeyList {
         account_id: AccountId,
+        #[serde(flatten)]
+        options: AccessKeyListOptions,
     },
     CallFunction {
         account_id: AccountId,
@@ -344,6 +479,17 @@ pub struct StatusSyncInfo {
     pub earliest_block_time: Option<Utc>,
     pub epoch_id: Option<EpochId>,
     pub epoch_start_height: Option<BlockHeight>,
+    /// The last block finalized by doomslug, which is what the `near-final`
+    /// (or `doomslug`) finality refers to.
+    #[serde(default)]
+    pub doomslug_final_block_hash: Option<CryptoHash>,
+    #[serde(default)]
+    pub doomslug_final_block_height: Option<BlockHeight>,
+    /// The last final block, which is what the `final` finality refers to.
+    #[serde(default)]
+    pub final_block_hash: Option<CryptoHash>,
+    #[serde(default)]
+    pub final_block_height: Option<BlockHeight>,
 }
 
 // TODO: add more information to ValidatorInfo
@@ -454,6 +600,49 @@ pub struct SnapshotHostsView {
     pub hosts: Vec<SnapshotHostInfoView>,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct Tier1ConnectionsView {
+    pub connections: Vec<Tier1ConnectionView>,
+    /// Failed attempts to establish TIER1 connections, the most recent last.
+    pub recent_failures: Vec<Tier1ConnectionFailureView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct Tier1ConnectionView {
+    pub peer_id: PeerId,
+    pub addr: String,
+    pub is_outbound_peer: bool,
+    /// Account key of the peer, if it is a TIER1 validator itself.
+    pub peer_account_key: Option<PublicKey>,
+    /// Validators whose messages go through this connection, either
+    /// directly or with the peer as their proxy.
+    pub account_keys: Vec<PublicKey>,
+    /// Whether the connection is a route pinned in the config.
+    pub pinned: bool,
+    pub connection_established_time_millis: u64,
+    /// Round trip time of the last ping, if answered.
+    pub rtt_millis: Option<u64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct Tier1ConnectionFailureView {
+    pub peer_id: PeerId,
+    pub addr: String,
+    /// Validator the connection was meant for.
+    pub account_key: PublicKey,
+    pub time: i64,
+    pub error: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct PeerAccessListView {
+    /// Rules from the access list file.
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    /// Bans issued through the admin API.
+    pub banned: Vec<String>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
 pub struct EdgeView {
     pub peer0: PeerId,
@@ -718,6 +907,38 @@ pub struct StatusResponse {
     /// Information about last blocks, network, epoch and chain & chunk info.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detailed_debug_status: Option<DetailedDebugStatus>,
+    /// Whether the node can be reached by other nodes from the internet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reachability: Option<ReachabilityView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReachabilityView {
+    /// Address of the node as seen from the internet, if known.
+    pub external_addr: Option<String>,
+    /// Result of mapping the port of the node on the gateway, if enabled.
+    pub port_mapping: Option<PortMappingView>,
+    pub status: ReachabilityStatusView,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum PortMappingView {
+    Mapped { external_addr: String, lifetime_sec: u32 },
+    Failed { error: String },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub enum ReachabilityStatusView {
+    /// Not checked yet, or the node doesn't listen for connections.
+    #[default]
+    Unknown,
+    /// The node could connect to itself through its external address, or
+    /// another node connected to it from the internet.
+    Reachable,
+    /// The node couldn't connect to itself through its external address.
+    /// Some gateways don't let connections loop back, so this is only
+    /// a warning sign until another node connects.
+    Unreachable { error: String },
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -1226,6 +1447,127 @@ impl ChunkView {
     }
 }
 
+/// A chunk state witness recorded by the node.
+#[serde_as]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct StateWitnessView {
+    pub chunk_hash: CryptoHash,
+    /// Size of the borsh encoded witness in bytes.
+    pub size: u64,
+    /// The borsh encoded witness, set unless the decoded witness was requested.
+    #[serde_as(as = "Option<Base64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<ChunkStateWitnessView>,
+}
+
+impl StateWitnessView {
+    pub fn new(witness: ChunkStateWitness, decode: bool) -> Self {
+        let chunk_hash = witness.chunk_header.chunk_hash().0;
+        let raw = borsh::to_vec(&witness).unwrap();
+        let size = raw.len() as u64;
+        if decode {
+            Self { chunk_hash, size, raw: None, decoded: Some(witness.into()) }
+        } else {
+            Self { chunk_hash, size, raw: Some(raw), decoded: None }
+        }
+    }
+}
+
+/// Decoded chunk state witness. Partial states and receipt proofs are summarized by their
+/// sizes, which is what usually matters when looking into the size of a witness.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ChunkStateWitnessView {
+    pub chunk_producer: AccountId,
+    pub epoch_id: CryptoHash,
+    pub chunk_header: ChunkHeaderView,
+    pub main_state_transition: ChunkStateTransitionView,
+    pub source_receipt_proofs: Vec<ReceiptProofSummaryView>,
+    pub applied_receipts_hash: CryptoHash,
+    pub transactions: Vec<SignedTransactionView>,
+    pub implicit_transitions: Vec<ChunkStateTransitionView>,
+    pub new_transactions: Vec<SignedTransactionView>,
+    pub new_transactions_validation_state: PartialStateSummaryView,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkStateTransitionView {
+    pub block_hash: CryptoHash,
+    pub base_state: PartialStateSummaryView,
+    pub post_state_root: CryptoHash,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PartialStateSummaryView {
+    /// Number of trie nodes and values in the partial state.
+    pub num_values: u64,
+    /// Total size of the trie nodes and values in bytes.
+    pub size: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptProofSummaryView {
+    pub chunk_hash: CryptoHash,
+    pub from_shard_id: ShardId,
+    pub num_receipts: u64,
+    /// Size of the borsh encoded receipts and their merkle proof in bytes.
+    pub size: u64,
+}
+
+impl From<ChunkStateWitness> for ChunkStateWitnessView {
+    fn from(witness: ChunkStateWitness) -> Self {
+        let mut source_receipt_proofs: Vec<ReceiptProofSummaryView> = witness
+            .source_receipt_proofs
+            .iter()
+            .map(|(chunk_hash, proof)| ReceiptProofSummaryView {
+                chunk_hash: chunk_hash.0,
+                from_shard_id: proof.1.from_shard_id,
+                num_receipts: proof.0.len() as u64,
+                size: borsh::object_length(proof).unwrap() as u64,
+            })
+            .collect();
+        // Receipt proofs are kept in a hash map, sort them to get a stable view.
+        source_receipt_proofs.sort_by_key(|proof| (proof.from_shard_id, proof.chunk_hash));
+        Self {
+            chunk_producer: witness.chunk_producer,
+            epoch_id: witness.epoch_id.0,
+            chunk_header: witness.chunk_header.into(),
+            main_state_transition: witness.main_state_transition.into(),
+            source_receipt_proofs,
+            applied_receipts_hash: witness.applied_receipts_hash,
+            transactions: witness.transactions.into_iter().map(Into::into).collect(),
+            implicit_transitions: witness
+                .implicit_transitions
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            new_transactions: witness.new_transactions.into_iter().map(Into::into).collect(),
+            new_transactions_validation_state: (&witness.new_transactions_validation_state).into(),
+        }
+    }
+}
+
+impl From<ChunkStateTransition> for ChunkStateTransitionView {
+    fn from(transition: ChunkStateTransition) -> Self {
+        Self {
+            block_hash: transition.block_hash,
+            base_state: (&transition.base_state).into(),
+            post_state_root: transition.post_state_root,
+        }
+    }
+}
+
+impl From<&PartialState> for PartialStateSummaryView {
+    fn from(state: &PartialState) -> Self {
+        let PartialState::TrieValues(values) = state;
+        Self {
+            num_values: values.len() as u64,
+            size: values.iter().map(|value| value.len() as u64).sum(),
+        }
+    }
+}
+
 #[serde_as]
 #[derive(
     BorshSerialize,
@@ -1278,6 +1620,34 @@ pub enum ActionView {
         delegate_action: DelegateAction,
         signature: Signature,
     },
+    IdempotencyKey {
+        #[serde_as(as = "Base64")]
+        key: Vec<u8>,
+    },
+    AtomicBatch {
+        max_block_height: BlockHeight,
+        receipts: Vec<AtomicBatchReceipt>,
+    },
+    DeployNamespacedContract {
+        namespace: AccountNamespace,
+        #[serde_as(as = "Base64")]
+        code: Vec<u8>,
+    },
+    NamespacedFunctionCall {
+        namespace: AccountNamespace,
+        method_name: String,
+        args: FunctionArgs,
+        gas: Gas,
+        #[serde(with = "dec_format")]
+        deposit: Balance,
+    },
+    DeployGlobalContract {
+        #[serde_as(as = "Base64")]
+        code: Vec<u8>,
+    },
+    UseGlobalContract {
+        code_hash: CryptoHash,
+    },
 }
 
 impl From<Action> for ActionView {
@@ -1314,6 +1684,29 @@ impl From<Action> for ActionView {
                 delegate_action: action.delegate_action,
                 signature: action.signature,
             },
+            Action::IdempotencyKey(action) => ActionView::IdempotencyKey { key: action.key },
+            Action::AtomicBatch(action) => ActionView::AtomicBatch {
+                max_block_height: action.max_block_height,
+                receipts: action.receipts,
+            },
+            Action::DeployNamespacedContract(action) => {
+                let code = hash(&action.code).as_ref().to_vec();
+                ActionView::DeployNamespacedContract { namespace: action.namespace, code }
+            }
+            Action::NamespacedFunctionCall(action) => ActionView::NamespacedFunctionCall {
+                namespace: action.namespace,
+                method_name: action.function_call.method_name,
+                args: action.function_call.args.into(),
+                gas: action.function_call.gas,
+                deposit: action.function_call.deposit,
+            },
+            Action::DeployGlobalContract(action) => {
+                let code = hash(&action.code).as_ref().to_vec();
+                ActionView::DeployGlobalContract { code }
+            }
+            Action::UseGlobalContract(action) => {
+                ActionView::UseGlobalContract { code_hash: action.code_hash }
+            }
         }
     }
 }
@@ -1355,6 +1748,35 @@ impl TryFrom<ActionView> for Action {
             ActionView::Delegate { delegate_action, signature } => {
                 Action::Delegate(Box::new(SignedDelegateAction { delegate_action, signature }))
             }
+            ActionView::IdempotencyKey { key } => {
+                Action::IdempotencyKey(IdempotencyKeyAction { key })
+            }
+            ActionView::AtomicBatch { max_block_height, receipts } => {
+                Action::AtomicBatch(Box::new(AtomicBatchAction { max_block_height, receipts }))
+            }
+            ActionView::DeployNamespacedContract { namespace, code } => {
+                Action::DeployNamespacedContract(Box::new(DeployNamespacedContractAction {
+                    namespace,
+                    code,
+                }))
+            }
+            ActionView::NamespacedFunctionCall { namespace, method_name, args, gas, deposit } => {
+                Action::NamespacedFunctionCall(Box::new(NamespacedFunctionCallAction {
+                    namespace,
+                    function_call: FunctionCallAction {
+                        method_name,
+                        args: args.into(),
+                        gas,
+                        deposit,
+                    },
+                }))
+            }
+            ActionView::DeployGlobalContract { code } => {
+                Action::DeployGlobalContract(DeployGlobalContractAction { code })
+            }
+            ActionView::UseGlobalContract { code_hash } => {
+                Action::UseGlobalContract(Box::new(UseGlobalContractAction { code_hash }))
+            }
         })
     }
 }
@@ -1542,6 +1964,15 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
             ExecutionMetadata::V1 => 1,
             ExecutionMetadata::V2(_) => 2,
             ExecutionMetadata::V3(_) => 3,
+            ExecutionMetadata::V4(_) => 4,
+        };
+        // V4 only adds the events and the refunds, which `ExecutionOutcomeView` shows in their
+        // own fields.
+        let metadata = match metadata {
+            ExecutionMetadata::V4(metadata) => {
+                ExecutionMetadata::V3(Box::new(metadata.gas_profile))
+            }
+            metadata => metadata,
         };
         let mut gas_profile = match metadata {
             ExecutionMetadata::V1 => None,
@@ -1611,6 +2042,7 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
 
                 Some(costs)
             }
+            ExecutionMetadata::V4(_) => unreachable!("V4 metadata was turned into V3 above"),
         };
         if let Some(ref mut costs) = gas_profile {
             // The order doesn't really matter, but the default one is just
@@ -1667,11 +2099,51 @@ pub struct ExecutionOutcomeView {
     /// Execution metadata, versioned
     #[serde(default)]
     pub metadata: ExecutionMetadataView,
+    /// Events emitted by the contract with `emit_event`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<ContractEvent>,
+    /// Refund receipts issued by the execution of the receipt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refunds: Vec<RefundView>,
+}
+
+/// Balance returned by the runtime through a refund receipt, i.e. a transfer
+/// from the `system` account.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct RefundView {
+    /// Id of the refund receipt.
+    pub receipt_id: CryptoHash,
+    pub receiver_id: AccountId,
+    #[serde(with = "dec_format")]
+    pub amount: Balance,
+    pub reason: RefundReason,
+}
+
+impl From<RefundReceipt> for RefundView {
+    fn from(refund: RefundReceipt) -> Self {
+        Self {
+            receipt_id: refund.receipt_id,
+            receiver_id: refund.receiver_id,
+            amount: refund.amount,
+            reason: refund.reason,
+        }
+    }
 }
 
 impl From<ExecutionOutcome> for ExecutionOutcomeView {
     fn from(outcome: ExecutionOutcome) -> Self {
         Self {
+            events: outcome.metadata.events().to_vec(),
+            refunds: outcome.metadata.refunds().iter().cloned().map(Into::into).collect(),
             logs: outcome.logs,
             receipt_ids: outcome.receipt_ids,
             gas_burnt: outcome.gas_burnt,
@@ -1714,6 +2186,7 @@ impl ExecutionOutcomeView {
         result.push(id);
         result.push(CryptoHash::hash_borsh(&PartialExecutionOutcome::from(self)));
         result.extend(self.logs.iter().map(|log| hash(log.as_bytes())));
+        result.extend(self.events.iter().map(CryptoHash::hash_borsh));
         result
     }
 }
@@ -1944,6 +2417,42 @@ pub struct ValidatorStakeViewV1 {
     pub stake: Balance,
 }
 
+/// Assignment of chunk validators to shards at a given height.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkValidatorsView {
+    /// Block for which the assignment was computed.
+    pub block_hash: CryptoHash,
+    /// Height at which the chunk validators validate the chunks of the shards.
+    pub height: BlockHeight,
+    /// The desired number of mandates per shard in the epoch.
+    pub target_mandates_per_shard: u64,
+    /// The amount of stake a whole mandate is worth in the epoch.
+    #[serde(with = "dec_format")]
+    pub stake_per_mandate: Balance,
+    /// Empty in epochs before stateless validation.
+    pub shards: Vec<ShardChunkValidatorsView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShardChunkValidatorsView {
+    pub shard_id: ShardId,
+    /// Sum of the endorsement weights of all chunk validators of the shard.
+    #[serde(with = "dec_format")]
+    pub total_endorsement_weight: Balance,
+    /// Endorsement weight needed for a chunk of the shard to be included in a block.
+    #[serde(with = "dec_format")]
+    pub required_endorsement_weight: Balance,
+    pub validators: Vec<ChunkValidatorView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkValidatorView {
+    pub account_id: AccountId,
+    /// Stake the validator endorses the chunk with, derived from its mandates.
+    #[serde(with = "dec_format")]
+    pub endorsement_weight: Balance,
+}
+
 #[derive(
     BorshSerialize,
     BorshDeserialize,
@@ -2012,6 +2521,13 @@ pub enum ReceiptEnumView {
         #[serde(default = "default_is_promise")]
         is_promise_resume: bool,
     },
+    GlobalContractDistribution {
+        shard_layout_version: ShardVersion,
+        target_shard: ShardId,
+        already_delivered_shards: Vec<ShardId>,
+        #[serde_as(as = "Base64")]
+        code: Vec<u8>,
+    },
 }
 
 // Default value used when deserializing ReceiptEnumViews which are missing either the
@@ -2061,6 +2577,14 @@ impl From<Receipt> for ReceiptView {
                         is_promise_resume,
                     }
                 }
+                ReceiptEnum::GlobalContractDistribution(distribution) => {
+                    ReceiptEnumView::GlobalContractDistribution {
+                        shard_layout_version: distribution.shard_layout_version,
+                        target_shard: distribution.target_shard,
+                        already_delivered_shards: distribution.already_delivered_shards,
+                        code: distribution.code,
+                    }
+                }
             },
             priority,
         }
@@ -2118,6 +2642,17 @@ impl TryFrom<ReceiptView> for Receipt {
                         ReceiptEnum::Data(data_receipt)
                     }
                 }
+                ReceiptEnumView::GlobalContractDistribution {
+                    shard_layout_version,
+                    target_shard,
+                    already_delivered_shards,
+                    code,
+                } => ReceiptEnum::GlobalContractDistribution(GlobalContractDistributionReceipt {
+                    shard_layout_version,
+                    target_shard,
+                    already_delivered_shards,
+                    code,
+                }),
             },
             priority: receipt_view.priority,
         }))
@@ -2494,6 +3029,157 @@ pub struct SplitStorageInfoView {
     pub hot_db_kind: Option<String>,
 }
 
+/// Forks known to the node in the last heights before its head.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForkInfoView {
+    pub head_hash: CryptoHash,
+    pub head_height: BlockHeight,
+    pub header_head_height: BlockHeight,
+    pub final_head_hash: CryptoHash,
+    pub final_head_height: BlockHeight,
+    /// Lowest height at which blocks were looked for.
+    pub from_height: BlockHeight,
+    /// Tips of the forks ordered by height, highest first.  The canonical
+    /// tip, i.e. the head, is always first.
+    pub tips: Vec<ForkTipView>,
+}
+
+/// A block or header without known children.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForkTipView {
+    pub hash: CryptoHash,
+    pub height: BlockHeight,
+    pub prev_hash: CryptoHash,
+    pub epoch_id: CryptoHash,
+    pub block_producer: Option<AccountId>,
+    /// Whether the block was processed, rather than only its header.
+    pub has_block: bool,
+    /// Height of the last block which the fork shares with the canonical
+    /// chain, unknown if it is below `from_height`.
+    pub fork_height: Option<BlockHeight>,
+    /// Number of approvals of the previous block included in the tip.
+    pub num_approvals: u64,
+    /// Stake of the block producers whose approvals are included in the tip.
+    #[serde(with = "dec_format")]
+    pub approvals_stake: Balance,
+    /// Why the fork choice rule didn't make the tip the head, unset for the
+    /// head itself.
+    pub not_chosen_reason: Option<ForkNotChosenReason>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkNotChosenReason {
+    /// Only the header is known, the block wasn't processed (yet).
+    BlockNotProcessed,
+    /// The block was challenged and is invalid.
+    Challenged,
+    /// The fork branches off below the final head, so it can never become
+    /// canonical.
+    ConflictsWithFinalBlock,
+    /// The head is higher.  The fork choice rule picks the highest block.
+    LowerHeight,
+    /// The head has the same height and was processed earlier.  Ties keep
+    /// the current head.
+    SameHeightProcessedLater,
+}
+
+/// Receipts of a block, collected from all of its chunks.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct BlockReceiptsView {
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+    pub receipts: Vec<BlockReceiptView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct BlockReceiptView {
+    /// Shard of the chunk which includes or executes the receipt.
+    pub shard_id: ShardId,
+    pub kind: BlockReceiptKind,
+    #[serde(flatten)]
+    pub receipt: ReceiptView,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockReceiptKind {
+    /// The receipt was produced by the previous chunk of the shard and is
+    /// included in the chunk of this block as an outgoing receipt.
+    Included,
+    /// The receipt was executed when applying the chunk of this block.
+    Executed,
+}
+
+/// Status of a height of the canonical chain, as seen by the node.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BlockHeightStatus {
+    /// The canonical chain has a block at this height and the node stores it.
+    Available { block_hash: CryptoHash },
+    /// The canonical chain has a block at this height, but the node has
+    /// already garbage collected it.
+    GarbageCollected { block_hash: CryptoHash },
+    /// The canonical chain skips this height, i.e. there is no block at it.
+    /// Unless the height is final, a reorg can still put a block at it.
+    Skipped,
+    /// The height is above the head of the node.
+    NotYetProduced,
+    /// The node has no record of the height, e.g. because it bootstrapped with
+    /// epoch sync after it, or the height is older than the node's record of
+    /// skipped heights.
+    Unknown,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeightStatusView {
+    pub height: BlockHeight,
+    /// Whether the height is at or below the final head, in which case its
+    /// status can only change by the block getting garbage collected.
+    pub is_final: bool,
+    #[serde(flatten)]
+    pub status: BlockHeightStatus,
+}
+
+/// Data of an account listed in `watched_accounts` of the client config. It's
+/// collected from the chunk state witnesses validated by the node, so it only
+/// covers the chunks which the node validated.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WatchedAccountView {
+    pub account_id: AccountId,
+    /// The last observed state of the account. Witnesses only carry the state
+    /// of the accounts which send transactions, so it's unknown until the
+    /// account sends one.
+    pub state: Option<WatchedAccountStateView>,
+    /// The most recent transactions sent by the account, oldest first.
+    pub transactions: Vec<WatchedTransactionView>,
+    /// The most recent receipts received by the account, oldest first.
+    pub receipts: Vec<WatchedReceiptView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WatchedAccountStateView {
+    /// The state is the one before applying the chunk created at this height.
+    pub height_created: BlockHeight,
+    #[serde(flatten)]
+    pub account: AccountView,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WatchedTransactionView {
+    /// Height at which the chunk including the transaction was created.
+    pub height_created: BlockHeight,
+    #[serde(flatten)]
+    pub transaction: SignedTransactionView,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WatchedReceiptView {
+    /// Height at which the chunk applying the receipt was created.
+    pub height_created: BlockHeight,
+    #[serde(flatten)]
+    pub receipt: ReceiptView,
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct CongestionInfoView {
     #[serde(with = "dec_format")]
@@ -2607,4 +3293,44 @@ mod tests {
         let view: FinalExecutionOutcomeViewEnum = serde_json::from_str(json).unwrap();
         assert!(matches!(view, FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(_)));
     }
+
+    #[test]
+    fn test_deserialize_view_access_key_list_request() {
+        use super::{AccessKeyListOptions, AccessKeyPermissionKind, QueryRequest};
+        use std::num::NonZeroU32;
+
+        // Requests without pagination options list all keys.
+        let json = r#"{"request_type":"view_access_key_list","account_id":"relayer.near"}"#;
+        let request: QueryRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            request,
+            QueryRequest::ViewAccessKeyList {
+                account_id: "relayer.near".parse().unwrap(),
+                options: AccessKeyListOptions::default(),
+            }
+        );
+
+        let json = r#"{"request_type":"view_access_key_list","account_id":"relayer.near","cursor":"ed25519:HM7ax8jJf41JozvanXepzhtD45AeRFcwJQCuLXFuDkjA","limit":100,"permission":"function_call","receiver_id":"app.near"}"#;
+        let request: QueryRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            request,
+            QueryRequest::ViewAccessKeyList {
+                account_id: "relayer.near".parse().unwrap(),
+                options: AccessKeyListOptions {
+                    cursor: Some(
+                        "ed25519:HM7ax8jJf41JozvanXepzhtD45AeRFcwJQCuLXFuDkjA".parse().unwrap()
+                    ),
+                    limit: NonZeroU32::new(100),
+                    permission: Some(AccessKeyPermissionKind::FunctionCall),
+                    receiver_id: Some("app.near".parse().unwrap()),
+                },
+            }
+        );
+        assert_eq!(serde_json::to_string(&request).unwrap(), json);
+
+        // An empty page can't be told apart from the last one.
+        let json =
+            r#"{"request_type":"view_access_key_list","account_id":"relayer.near","limit":0}"#;
+        assert!(serde_json::from_str::<QueryRequest>(json).is_err());
+    }
 }
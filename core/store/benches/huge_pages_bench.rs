@@ -0,0 +1,49 @@
+//! Benchmarks for random reads from a buffer as big as a memtrie arena, with
+//! and without transparent huge pages.
+//!
+//! The difference comes from TLB misses, so it only shows up on hosts which
+//! have transparent huge pages enabled, see `near_store::io_features`.
+
+#[macro_use]
+extern crate bencher;
+
+use bencher::{black_box, Bencher};
+use near_store::io_features::{advise_huge_pages, huge_pages_supported};
+use rand::{Rng, SeedableRng};
+
+const BUFFER_SIZE: usize = 1 << 30;
+const READS: usize = 100_000;
+
+fn random_reads(bench: &mut Bencher, huge_pages: bool) {
+    if huge_pages && !huge_pages_supported() {
+        eprintln!("transparent huge pages are not enabled, reads use regular pages");
+    }
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    if huge_pages {
+        advise_huge_pages(&mut buffer);
+    }
+    // Touch every page so that page faults aren't measured.
+    for i in (0..BUFFER_SIZE).step_by(4096) {
+        buffer[i] = i as u8;
+    }
+    let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+    let offsets: Vec<usize> = (0..READS).map(|_| rng.gen_range(0..BUFFER_SIZE)).collect();
+    bench.iter(|| {
+        let mut sum = 0u64;
+        for &offset in &offsets {
+            sum += buffer[offset] as u64;
+        }
+        black_box(sum)
+    });
+}
+
+fn random_reads_regular_pages(bench: &mut Bencher) {
+    random_reads(bench, false);
+}
+
+fn random_reads_huge_pages(bench: &mut Bencher) {
+    random_reads(bench, true);
+}
+
+benchmark_group!(benches, random_reads_regular_pages, random_reads_huge_pages);
+benchmark_main!(benches);
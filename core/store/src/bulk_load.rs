@@ -0,0 +1,71 @@
+//! Tuning of the database for phases which write large amounts of data.
+//!
+//! State sync, flat storage creation and database migrations write gigabytes
+//! of data within minutes.  With the regular settings RocksDB flushes small
+//! memtables and keeps compacting the freshly written files, which multiplies
+//! the amount of IO.  For the duration of such a phase the columns it writes
+//! are switched to bigger write buffers with automatic compactions disabled.
+//! The regular settings are restored when the phase is over, after which
+//! RocksDB compacts the accumulated files in the background.
+//!
+//! Phases may overlap, e.g. state sync of several shards, so the database
+//! counts the active phases per column and only restores the regular
+//! settings of a column once the last phase writing it has finished.
+
+use crate::db::Database;
+use crate::{metrics, DBCol};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum BulkLoadPhase {
+    /// Applying state parts downloaded during state sync.
+    StateSync,
+    /// Filling flat storage from the trie.
+    FlatStorageCreation,
+    /// Migrating the database to a new version.
+    Migration,
+}
+
+impl BulkLoadPhase {
+    /// Columns written in bulk during the phase.
+    pub fn columns(self) -> Vec<DBCol> {
+        match self {
+            Self::StateSync => vec![DBCol::State, DBCol::FlatState],
+            Self::FlatStorageCreation => vec![DBCol::FlatState],
+            // Migrations may rewrite any column, but only the state columns
+            // hold enough data to be worth the memory of the bigger
+            // memtables, which would add up to gigabytes over all columns.
+            Self::Migration => vec![DBCol::State, DBCol::FlatState],
+        }
+    }
+}
+
+/// Keeps the columns of a phase in bulk load mode until dropped.
+#[must_use = "the columns are in bulk load mode only while the guard is alive"]
+pub struct BulkLoadGuard {
+    storage: Arc<dyn Database>,
+    phase: BulkLoadPhase,
+}
+
+impl BulkLoadGuard {
+    pub(crate) fn new(storage: Arc<dyn Database>, phase: BulkLoadPhase) -> Self {
+        // The settings only affect performance, so failing to change them is
+        // not a reason to fail the phase itself.
+        if let Err(err) = storage.set_bulk_load(&phase.columns(), true) {
+            tracing::warn!(target: "store", ?phase, ?err, "Failed to enter bulk load mode");
+        }
+        metrics::BULK_LOAD_ACTIVE.with_label_values(&[phase.into()]).inc();
+        Self { storage, phase }
+    }
+}
+
+impl Drop for BulkLoadGuard {
+    fn drop(&mut self) {
+        let phase = self.phase;
+        if let Err(err) = self.storage.set_bulk_load(&phase.columns(), false) {
+            tracing::warn!(target: "store", ?phase, ?err, "Failed to leave bulk load mode");
+        }
+        metrics::BULK_LOAD_ACTIVE.with_label_values(&[phase.into()]).dec();
+    }
+}
@@ -1,5 +1,6 @@
 use crate::columns::DBKeyType;
 use crate::db::{ColdDB, COLD_HEAD_KEY, HEAD_KEY};
+use crate::io_attribution::IoSubsystem;
 use crate::{metrics, DBCol, DBTransaction, Database, Store, TrieChanges};
 
 use borsh::BorshDeserialize;
@@ -82,7 +83,9 @@ pub fn update_cold_db(
     num_threads: usize,
 ) -> io::Result<bool> {
     let _span = tracing::debug_span!(target: "cold_store", "update cold db", height = height);
+    crate::io_throttle::throttle(IoSubsystem::ColdCopy);
     let _timer = metrics::COLD_COPY_DURATION.start_timer();
+    let _io_subsystem = IoSubsystem::ColdCopy.enter();
 
     if hot_store.get_for_cold(DBCol::BlockHeight, &height.to_le_bytes())?.is_none() {
         return Ok(false);
@@ -106,6 +109,7 @@ pub fn update_cold_db(
                 .into_par_iter() // Process every cold column as a separate task in thread pool in parallel.
                 // Copy column to cold db.
                 .map(|col: DBCol| -> io::Result<()> {
+                    let _io_subsystem = IoSubsystem::ColdCopy.enter();
                     if col == DBCol::State {
                         copy_state_from_store(shard_layout, block_hash_key, cold_db, &hot_store)
                     } else {
@@ -303,6 +307,9 @@ pub fn update_cold_head(
     return Ok(());
 }
 
+/// Shortest sleep of `copy_all_data_to_cold_throttled`.
+const THROTTLE_MIN_SLEEP: std::time::Duration = std::time::Duration::from_millis(10);
+
 pub enum CopyAllDataToColdStatus {
     EverythingCopied,
     Interrupted,
@@ -316,6 +323,21 @@ pub fn copy_all_data_to_cold(
     batch_size: usize,
     keep_going: &std::sync::Arc<std::sync::atomic::AtomicBool>,
 ) -> io::Result<CopyAllDataToColdStatus> {
+    copy_all_data_to_cold_throttled(cold_db, hot_store, batch_size, None, keep_going)
+}
+
+/// Like `copy_all_data_to_cold`, but copies at most `max_bytes_per_second`,
+/// if given, so that the copy doesn't starve other users of the disks.
+pub fn copy_all_data_to_cold_throttled(
+    cold_db: std::sync::Arc<ColdDB>,
+    hot_store: &Store,
+    batch_size: usize,
+    max_bytes_per_second: Option<u64>,
+    keep_going: &std::sync::Arc<std::sync::atomic::AtomicBool>,
+) -> io::Result<CopyAllDataToColdStatus> {
+    let _io_subsystem = IoSubsystem::ColdCopy.enter();
+    let start = std::time::Instant::now();
+    let mut copied_bytes = 0u64;
     for col in DBCol::iter() {
         if col.is_cold() {
             tracing::info!(target: "cold_store", ?col, "Started column migration");
@@ -326,7 +348,19 @@ pub fn copy_all_data_to_cold(
                     return Ok(CopyAllDataToColdStatus::Interrupted);
                 }
                 let (key, value) = result?;
+                copied_bytes += (key.len() + value.len()) as u64;
                 transaction.set_and_write_if_full(col, key.to_vec(), value.to_vec())?;
+                if let Some(max_bytes_per_second) = max_bytes_per_second {
+                    let due = std::time::Duration::from_secs_f64(
+                        copied_bytes as f64 / max_bytes_per_second.max(1) as f64,
+                    );
+                    // Sleeping for every key would cost more than the copy.
+                    if let Some(ahead) = due.checked_sub(start.elapsed()) {
+                        if ahead >= THROTTLE_MIN_SLEEP {
+                            std::thread::sleep(ahead);
+                        }
+                    }
+                }
             }
             transaction.write()?;
             tracing::info!(target: "cold_store", ?col, "Finished column migration");
@@ -611,6 +645,7 @@ impl BatchTransaction {
             return Ok(());
         }
 
+        crate::io_throttle::throttle(IoSubsystem::ColdCopy);
         let column_label = [<&str>::from(self.transaction.ops[0].col())];
 
         crate::metrics::COLD_STORE_MIGRATION_BATCH_WRITE_COUNT
@@ -293,6 +293,36 @@ pub enum DBCol {
     /// Witnesses with the lowest index are garbage collected first.
     /// u64 -> LatestWitnessesKey
     LatestWitnessesByIndex,
+    /// Statistics of the shards over finished epochs, saved only when
+    /// `save_epoch_shard_stats` is enabled. Used for analytics and never
+    /// garbage collected.
+    /// - *Rows*: EpochId || ShardUId
+    /// - *Column type*: `EpochShardStats`
+    EpochShardStats,
+    /// Light client checkpoints, one per epoch, saved for the last block of
+    /// the epoch when its header is pruned, see `prune_headers` of the GC
+    /// config. They let the node keep serving `next_light_client_block` to
+    /// light clients which know the block. Never garbage collected.
+    /// - *Rows*: BlockHash (CryptoHash)
+    /// - *Column type*: `LightClientCheckpoint`
+    LightClientCheckpoints,
+    /// Signed records of the nodes in the DHT routing table of the node, so
+    /// that it can find peers after a restart even if the boot nodes are down.
+    /// - *Rows*: single row (empty row name)
+    /// - *Content type*: Vec of [network_primitives::network_protocol::SignedPeerRecord]
+    DhtPeerRecords,
+    /// Bloom filter of the events emitted in a chunk, by block hash and shard id, see
+    /// `near_primitives::events::EventsBloom`. Only chunks with events have one.
+    /// - *Rows*: BlockShardId (BlockHash || ShardId) - 40 bytes
+    /// - *Column type*: `EventsBloom`
+    OutcomeEventsBloom,
+    /// Heights skipped by the canonical chain, i.e. the heights between a block
+    /// of the chain and its previous block. Updated together with
+    /// `DBCol::BlockHeight` and never garbage collected, so that a height can
+    /// be told apart from the height of a garbage collected block.
+    /// - *Rows*: height (u64)
+    /// - *Column type*: empty
+    SkippedBlockHeights,
 }
 
 /// Defines different logical parts of a db key.
@@ -302,7 +332,8 @@ pub enum DBCol {
 /// Currently only used in cold storage continuous migration.
 #[derive(PartialEq, Copy, Clone, Debug, Hash, Eq, strum::EnumIter)]
 pub enum DBKeyType {
-    /// Empty row name. Used in DBCol::LastComponentNonce and DBCol::RecentOutboundConnections
+    /// Empty row name. Used in DBCol::LastComponentNonce, DBCol::RecentOutboundConnections
+    /// and DBCol::DhtPeerRecords
     Empty,
     /// Set of predetermined strings. Used, for example, in DBCol::BlockMisc
     StringLiteral,
@@ -429,6 +460,7 @@ impl DBCol {
             | DBCol::IncomingReceipts
             | DBCol::NextBlockHashes
             | DBCol::OutcomeIds
+            | DBCol::OutcomeEventsBloom
             | DBCol::OutgoingReceipts
             | DBCol::Receipts
             | DBCol::State
@@ -463,6 +495,11 @@ impl DBCol {
             // LatestChunkStateWitnesses stores the last N observed witnesses, used only for debugging.
             DBCol::LatestChunkStateWitnesses => false,
             DBCol::LatestWitnessesByIndex => false,
+            // EpochShardStats is only needed for analytics on the node which saved it.
+            DBCol::EpochShardStats => false,
+            // LightClientCheckpoints stand in for the headers pruned from the hot storage, while
+            // the cold storage keeps all the headers.
+            DBCol::LightClientCheckpoints => false,
             // Deprecated.
             DBCol::_ReceiptIdToShardId => false,
             // This can be re-constructed from the Chunks column, so no need to store in Cold DB.
@@ -472,8 +509,10 @@ impl DBCol {
             DBCol::BlockHeader
             | DBCol::_GCCount
             | DBCol::BlockHeight
+            | DBCol::SkippedBlockHeights
             | DBCol::_Peers
             | DBCol::RecentOutboundConnections
+            | DBCol::DhtPeerRecords
             | DBCol::BlockMerkleTree
             | DBCol::AccountAnnouncements
             | DBCol::EpochLightClientBlocks
@@ -503,6 +542,13 @@ impl DBCol {
         matches!(*self, DBCol::DbVersion | DBCol::BlockMisc) || self.is_cold()
     }
 
+    /// Whether the keys of the column start with the `ShardUId` of the shard
+    /// the row belongs to, which lets [`crate::db::ShardedDB`] keep the rows
+    /// of a shard in a database of its own.
+    pub fn is_keyed_by_shard_uid(&self) -> bool {
+        self.key_type().first() == Some(&DBKeyType::ShardUId)
+    }
+
     /// Vector of DBKeyType s concatenation of which results in key for the column.
     pub fn key_type(&self) -> &'static [DBKeyType] {
         match self {
@@ -566,6 +612,11 @@ impl DBCol {
             DBCol::StateTransitionData => &[DBKeyType::BlockHash, DBKeyType::ShardId],
             DBCol::LatestChunkStateWitnesses => &[DBKeyType::LatestWitnessesKey],
             DBCol::LatestWitnessesByIndex => &[DBKeyType::LatestWitnessIndex],
+            DBCol::EpochShardStats => &[DBKeyType::EpochId, DBKeyType::ShardUId],
+            DBCol::LightClientCheckpoints => &[DBKeyType::BlockHash],
+            DBCol::DhtPeerRecords => &[DBKeyType::Empty],
+            DBCol::OutcomeEventsBloom => &[DBKeyType::BlockHash, DBKeyType::ShardId],
+            DBCol::SkippedBlockHeights => &[DBKeyType::BlockHeight],
         }
     }
 }
@@ -1,3 +1,4 @@
+use crate::db::backend::StoreBackend;
 use crate::trie::{
     DEFAULT_SHARD_CACHE_DELETIONS_QUEUE_CAPACITY, DEFAULT_SHARD_CACHE_TOTAL_SIZE_LIMIT,
 };
@@ -8,6 +9,11 @@ use std::{collections::HashMap, iter::FromIterator};
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct StoreConfig {
+    /// Storage engine of the database, `rocksdb` (the default) or `memory`.
+    /// The latter doesn't persist anything and is only useful for tests.
+    #[serde(skip_serializing_if = "StoreBackend::is_default")]
+    pub backend: StoreBackend,
+
     /// Path to the database.  If relative, resolved relative to neard home
     /// directory.  This is useful if node runs with a separate disk holding the
     /// database.
@@ -63,12 +69,24 @@ pub struct StoreConfig {
     pub claim_sweat_prefetch_config: Vec<PrefetchConfig>,
     pub kaiching_prefetch_config: Vec<PrefetchConfig>,
 
+    /// Rules deriving the contract storage keys which function calls will
+    /// read from their arguments, so that the keys are prefetched before the
+    /// calls are executed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefetch_rules: Vec<PrefetchRuleConfig>,
+
     /// List of shard UIDs for which we should load the tries in memory.
     /// TODO(#9511): This does not automatically survive resharding. We may need to figure out a
     /// strategy for that.
     pub load_mem_tries_for_shards: Vec<ShardUId>,
     /// If true, load mem trie for each shard being tracked; this has priority over `load_mem_tries_for_shards`.
     pub load_mem_tries_for_tracked_shards: bool,
+    /// If true, in-memory tries are saved to the `memtries` directory of the
+    /// store on shutdown and loaded from there on the next start, unless the
+    /// flat storage head has moved in between.  This replaces the minutes of
+    /// loading big shards from flat storage with reading a file of about the
+    /// size of the in-memory trie, so there must be enough disk space for it.
+    pub persist_mem_tries: bool,
 
     /// Path where to create RocksDB checkpoints during database migrations or
     /// `false` to disable that feature.
@@ -99,6 +117,436 @@ pub struct StoreConfig {
 
     // TODO (#9989): To be phased out in favor of state_snapshot_config
     pub state_snapshot_enabled: bool,
+
+    /// RocksDB settings used while the node bulk loads data, see
+    /// [`crate::bulk_load`].
+    pub bulk_load: BulkLoadConfig,
+
+    /// Compactions which the node runs on its own during maintenance windows.
+    pub compaction_schedule: CompactionScheduleConfig,
+
+    /// Retention of flat storage deltas, i.e. the state changes of the blocks
+    /// after the flat storage head.
+    pub flat_storage_deltas: FlatStorageDeltasConfig,
+
+    /// In-memory filters of the keys in flat storage, which answer lookups
+    /// of missing keys without reading the database.
+    pub flat_storage_key_filter: FlatStorageKeyFilterConfig,
+
+    /// Keep a copy of the flat state of every shard in an append-only file
+    /// under the hot store, `flat_state_mmap/`, and serve flat storage reads
+    /// from it through a memory map instead of from RocksDB, see
+    /// [`crate::flat::FlatStateMmap`].  Meant for RPC nodes, whose view calls
+    /// are mostly flat storage reads; it takes about as much disk as the
+    /// flat state and memory for an index of its keys.
+    pub flat_state_mmap: bool,
+
+    /// Compression of large values of the State column with a trained zstd
+    /// dictionary, on top of the block compression of RocksDB.
+    pub value_compression: ValueCompressionConfig,
+
+    /// What the node gives up as the disk of the database fills up, see
+    /// [`crate::disk_pressure`].
+    pub disk_pressure: DiskPressureConfig,
+
+    /// Let RocksDB iterators read ahead and multi-gets overlap their reads
+    /// with io_uring if the host supports it, see [`crate::io_features`].
+    pub io_uring: bool,
+
+    /// Back the memtrie arenas and RocksDB memtables with transparent huge
+    /// pages if the host supports them, see [`crate::io_features`].
+    pub huge_pages: bool,
+
+    /// Settings of the RocksDB secondary instance opened by `neard run
+    /// --secondary`, see [`crate::secondary`].
+    pub secondary: SecondaryConfig,
+
+    /// Memory shared by the trie and compiled contract caches, which is
+    /// redistributed among them as the node runs, see
+    /// [`crate::memory_budget`].
+    pub memory_budget: MemoryBudgetConfig,
+
+    /// Slowing down garbage collection, the state dumper and copying to the
+    /// cold database while chunk application is slow, see
+    /// [`crate::io_throttle`].
+    pub io_throttle: IoThrottleConfig,
+
+    /// Periodic recomputation of state roots from flat storage, to detect
+    /// corruption of the database early.
+    pub state_root_audit: StateRootAuditConfig,
+
+    /// Bucket holding the database when `backend` is `object_storage`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_storage: Option<ObjectStorageConfig>,
+
+    /// Shards whose state and flat storage are kept in RocksDB instances of
+    /// their own, e.g. to spread the IO over several disks, see
+    /// [`crate::db::ShardedDB`].  Only supported for the hot database.  A
+    /// shard can't be moved out of the main database once it has state in
+    /// it; it has to be synced from scratch instead.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shard_dbs: Vec<ShardDbConfig>,
+
+    /// RocksDB settings of columns, by column name, e.g. `State`, replacing
+    /// the defaults of the node.  Meant for experimenting with the tuning
+    /// without recompiling neard.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub rocksdb_overrides: HashMap<String, RocksDbColumnConfig>,
+}
+
+/// RocksDB settings of a column, see `rocksdb_overrides` of [`StoreConfig`].
+/// Settings which aren't set keep their defaults.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RocksDbColumnConfig {
+    /// Size of the block cache of the column.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_cache_size: Option<bytesize::ByteSize>,
+    /// Compression of all levels of the column.  By default, the first two
+    /// levels aren't compressed, the bottommost one uses zstd and the others
+    /// lz4.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<RocksDbCompression>,
+    /// Bits per key of the bloom filter, 0 disables the filter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bloom_filter_bits_per_key: Option<u32>,
+    /// Size of a single memtable of the column.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_buffer_size: Option<bytesize::ByteSize>,
+    /// Maximum number of memtables of the column kept in memory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_write_buffer_number: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RocksDbCompression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+/// Database of a shard, see `shard_dbs` of [`StoreConfig`].
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct ShardDbConfig {
+    pub shard_uid: ShardUId,
+    /// Path to the database.  If relative, resolved relative to neard home
+    /// directory.  By default, the database is in `<path>-shards/<shard_uid>`
+    /// where `<path>` is the path of the main database.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<std::path::PathBuf>,
+}
+
+/// Location of the cold database in an object storage, see
+/// [`crate::db::ObjectStorageDB`].
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct ObjectStorageConfig {
+    pub location: ObjectStorageLocation,
+    /// Prefix of the names of the objects, e.g. `mainnet/cold/`.
+    #[serde(default)]
+    pub prefix: String,
+    /// JSON file with `access_key` and `secret_key` of the bucket.  For GCS
+    /// these are the HMAC keys of a service account.  If not set, the
+    /// credentials are taken from the environment.
+    #[serde(default)]
+    pub credentials_file: Option<std::path::PathBuf>,
+    /// Size of the in-memory cache of the values read from the bucket.
+    #[serde(default = "default_object_storage_cache_size")]
+    pub cache_size: bytesize::ByteSize,
+}
+
+fn default_object_storage_cache_size() -> bytesize::ByteSize {
+    bytesize::ByteSize::gib(1)
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum ObjectStorageLocation {
+    S3 {
+        /// Name of the bucket.
+        bucket: String,
+        /// Region of the bucket, e.g. `us-west-1`.
+        region: String,
+    },
+    GCS {
+        /// Name of the bucket.
+        bucket: String,
+    },
+}
+
+/// Settings applied to the columns written by a bulk load phase for the
+/// duration of the phase.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct BulkLoadConfig {
+    /// Whether to switch columns to the settings below during bulk loads.
+    pub enabled: bool,
+    /// Size of a single memtable of the column.
+    pub write_buffer_size: bytesize::ByteSize,
+    /// Maximum number of memtables of the column kept in memory.  Together
+    /// with `write_buffer_size` this bounds the memory used by each column
+    /// during the bulk load.
+    pub max_write_buffer_number: u32,
+}
+
+impl Default for BulkLoadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            write_buffer_size: bytesize::ByteSize::mib(256),
+            max_write_buffer_number: 4,
+        }
+    }
+}
+
+/// Compactions run by the node during maintenance windows, so that RocksDB
+/// doesn't have to catch up on them at peak traffic, stalling writes.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CompactionScheduleConfig {
+    /// Times of the day, in UTC, during which the scheduled compactions may
+    /// start, e.g. `{"start": "02:00", "end": "04:00"}`.  A window ends on
+    /// the next day if it ends before it starts.
+    pub maintenance_windows: Vec<MaintenanceWindowConfig>,
+    /// Compactions run once in every maintenance window, in order.
+    pub compactions: Vec<ScheduledCompactionConfig>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct MaintenanceWindowConfig {
+    /// Start of the window as `HH:MM`.
+    pub start: String,
+    /// End of the window as `HH:MM`.
+    pub end: String,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ScheduledCompactionConfig {
+    /// Names of the compacted columns, e.g. `State`.
+    pub columns: Vec<String>,
+    /// Hex encoded first key of the compacted range.  The range starts at the
+    /// beginning of the column if not set.
+    pub start: Option<String>,
+    /// Hex encoded key ending the compacted range, exclusive.  The range ends
+    /// at the end of the column if not set.
+    pub end: Option<String>,
+    /// Skips the compaction of a column unless its estimated compaction debt
+    /// is at least this big.
+    pub min_debt: Option<bytesize::ByteSize>,
+}
+
+/// Flat storage keeps the deltas of all blocks after its head, which is the
+/// last final block.  If blocks stop being finalised, the deltas accumulate
+/// and every lookup has to go through more of them.  With the limits below
+/// set, a background job applies the oldest deltas to the flat state, moving
+/// the head past the last final block.  Only deltas of blocks without forks
+/// are compacted, but if the chain later switches to a fork starting before
+/// the new head, the node can't apply its chunks and has to state sync.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct FlatStorageDeltasConfig {
+    /// Maximum number of deltas of a shard to keep.  Unlimited if not set.
+    pub max_deltas: Option<usize>,
+    /// Maximum total size of the changes of a shard cached in memory.
+    /// Unlimited if not set.
+    pub max_cached_changes_size: Option<bytesize::ByteSize>,
+    /// The deltas of blocks at most this many heights below the highest
+    /// delta are never compacted, regardless of the limits.
+    pub min_retained_heights: u64,
+    /// How often the limits are checked.
+    pub compaction_period: std::time::Duration,
+}
+
+impl FlatStorageDeltasConfig {
+    /// Whether any limit is set, i.e. deltas may be compacted.
+    pub fn compaction_enabled(&self) -> bool {
+        self.max_deltas.is_some() || self.max_cached_changes_size.is_some()
+    }
+}
+
+impl Default for FlatStorageDeltasConfig {
+    fn default() -> Self {
+        Self {
+            max_deltas: None,
+            max_cached_changes_size: None,
+            min_retained_heights: 100,
+            compaction_period: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+/// Bloom filters over the keys in the flat state of every shard, see
+/// [`crate::flat::FlatStateKeyFilter`].  A filter is built in the background
+/// when the flat storage of a shard is created and takes `bits_per_key` bits
+/// per key, so it is worth enabling on nodes with enough spare RAM.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct FlatStorageKeyFilterConfig {
+    pub enabled: bool,
+    /// Bits of the filter per key.  10 bits make about 1% of the lookups of
+    /// missing keys go to the database.
+    pub bits_per_key: u32,
+    /// Number of keys the filter is first sized for.  It grows as more keys
+    /// are added, but every growth raises the rate of false positives a bit.
+    pub initial_capacity: u64,
+}
+
+impl Default for FlatStorageKeyFilterConfig {
+    fn default() -> Self {
+        Self { enabled: false, bits_per_key: 10, initial_capacity: 10_000_000 }
+    }
+}
+
+/// Compression of the values of the State column, see
+/// [`crate::db::CompressedDB`].  The dictionary is trained when the database
+/// is first opened with the compression enabled and has enough values to
+/// sample.  Values compressed before stay readable when it is disabled.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ValueCompressionConfig {
+    pub enabled: bool,
+    /// Values smaller than this are stored as they are.
+    pub min_value_size: bytesize::ByteSize,
+    /// zstd compression level.
+    pub level: i32,
+    /// Size of the dictionary to train.
+    pub dictionary_size: bytesize::ByteSize,
+    /// How much of the values to sample to train the dictionary.
+    pub train_sample_size: bytesize::ByteSize,
+}
+
+impl Default for ValueCompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_value_size: bytesize::ByteSize::b(256),
+            level: 3,
+            dictionary_size: bytesize::ByteSize::kib(112),
+            train_sample_size: bytesize::ByteSize::mib(64),
+        }
+    }
+}
+
+/// Free disk space thresholds at which the node degrades.  Each threshold
+/// should be below the previous one.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct DiskPressureConfig {
+    pub enabled: bool,
+    /// How often the free space is checked.
+    pub check_period: std::time::Duration,
+    /// Below this the node stops writing data it can do without, like state
+    /// sync dumps, the latest witnesses and epoch shard stats.
+    pub reduce_writes_below: bytesize::ByteSize,
+    /// Below this the node keeps logging errors so that operators notice.
+    pub alert_below: bytesize::ByteSize,
+    /// Below this the node shuts down, while RocksDB still has room to flush
+    /// its memtables and the database stays consistent.
+    pub halt_below: bytesize::ByteSize,
+}
+
+impl Default for DiskPressureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_period: std::time::Duration::from_secs(10),
+            reduce_writes_below: bytesize::ByteSize::gib(20),
+            alert_below: bytesize::ByteSize::gib(10),
+            halt_below: bytesize::ByteSize::gib(2),
+        }
+    }
+}
+
+/// Settings of a RocksDB secondary instance, which reads the database of
+/// another `neard` process and follows its writes.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SecondaryConfig {
+    /// How often the secondary instance replays the writes of the primary
+    /// since the previous catch-up.
+    pub catch_up_period: std::time::Duration,
+}
+
+impl Default for SecondaryConfig {
+    fn default() -> Self {
+        Self { catch_up_period: std::time::Duration::from_secs(1) }
+    }
+}
+
+/// Limits of the memory budget of the caches.  When enabled, the sizes in
+/// `trie_cache`, `view_trie_cache` and `max_loaded_contracts` are only where
+/// the caches start from.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct MemoryBudgetConfig {
+    pub enabled: bool,
+    /// Upper bound of the total size of the caches.
+    pub max_bytes: bytesize::ByteSize,
+    /// The caches take at most this percentage of the RAM which is available
+    /// to them, i.e. the RAM available on the host plus their own size.
+    pub max_available_memory_percent: u8,
+    /// Every cache keeps at least this much, however rarely it misses.
+    pub min_cache_bytes: bytesize::ByteSize,
+    /// How often the budget is redistributed.
+    pub rebalance_period: std::time::Duration,
+}
+
+impl Default for MemoryBudgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_bytes: bytesize::ByteSize::gib(4),
+            max_available_memory_percent: 50,
+            min_cache_bytes: bytesize::ByteSize::mib(10),
+            rebalance_period: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+/// When background work yields the disk to chunk application.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct IoThrottleConfig {
+    pub enabled: bool,
+    /// Background work waits while the moving average of the time it takes
+    /// to apply a chunk is above this.
+    pub chunk_apply_latency_threshold: std::time::Duration,
+    /// Longest a background step waits before it goes ahead anyway, so that
+    /// e.g. garbage collection keeps up with a node which is always slow.
+    pub max_delay: std::time::Duration,
+    /// Latencies of chunks applied longer ago than this are ignored, e.g.
+    /// once the node stops tracking shards.
+    pub latency_ttl: std::time::Duration,
+}
+
+impl Default for IoThrottleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            chunk_apply_latency_threshold: std::time::Duration::from_millis(500),
+            max_delay: std::time::Duration::from_secs(5),
+            latency_ttl: std::time::Duration::from_secs(10),
+        }
+    }
+}
+
+/// Settings of the state root audit, see
+/// [`crate::flat::StateRootAuditHandle`].
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct StateRootAuditConfig {
+    /// An audit builds the whole trie of a shard in memory, which takes as
+    /// much RAM as its memtrie.
+    pub enabled: bool,
+    /// Time between audits, each of a random shard.
+    pub period: std::time::Duration,
+}
+
+impl Default for StateRootAuditConfig {
+    fn default() -> Self {
+        Self { enabled: false, period: std::time::Duration::from_secs(6 * 60 * 60) }
+    }
 }
 
 /// Config used to control state snapshot creation. This is used for state sync and resharding.
@@ -106,6 +554,12 @@ pub struct StoreConfig {
 #[serde(default)]
 pub struct StateSnapshotConfig {
     pub state_snapshot_type: StateSnapshotType,
+    /// Number of state checkpoints to keep, i.e. copies of the hot database
+    /// made together with the state snapshots at the epoch boundaries.  They
+    /// can be opened while the node is running, e.g. to fork the network or
+    /// export the state of a past epoch.  Snapshots are made every epoch only
+    /// with `EveryEpoch`.
+    pub num_checkpoints: usize,
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -127,7 +581,7 @@ pub enum MigrationSnapshot {
 }
 
 /// Mode in which to open the storage.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     /// Open an existing database in read-only mode.  Fail if it doesn’t exist.
     ReadOnly,
@@ -137,11 +591,15 @@ pub enum Mode {
     ReadWrite,
     /// Creates a new database in read-write mode.  Fails if it exists.
     Create,
+    /// Open an existing database as a read-only secondary instance, which
+    /// follows the writes of the process owning the database.  Fail if it
+    /// doesn’t exist.
+    Secondary,
 }
 
 impl Mode {
     pub const fn read_only(self) -> bool {
-        matches!(self, Mode::ReadOnly)
+        matches!(self, Mode::ReadOnly | Mode::Secondary)
     }
     pub const fn read_write(self) -> bool {
         !self.read_only()
@@ -157,7 +615,7 @@ impl Mode {
     /// `None` if the mode requires creation of a new database.
     pub const fn but_cannot_create(self) -> Option<Self> {
         match self {
-            Self::ReadOnly | Self::ReadWriteExisting => Some(self),
+            Self::ReadOnly | Self::ReadWriteExisting | Self::Secondary => Some(self),
             Self::ReadWrite => Some(Self::ReadWriteExisting),
             Self::Create => None,
         }
@@ -176,18 +634,27 @@ impl StoreConfig {
     }
 
     /// Returns cache size for given column.
-    pub const fn col_cache_size(&self, col: DBCol) -> bytesize::ByteSize {
+    pub fn col_cache_size(&self, col: DBCol) -> bytesize::ByteSize {
+        if let Some(size) = self.col_overrides(col).and_then(|config| config.block_cache_size) {
+            return size;
+        }
         match col {
             DBCol::State => self.col_state_cache_size,
             DBCol::FlatState => self.col_flat_state_cache_size,
             _ => bytesize::ByteSize::mib(32),
         }
     }
+
+    /// Returns the RocksDB settings overridden for the column.
+    pub fn col_overrides(&self, col: DBCol) -> Option<&RocksDbColumnConfig> {
+        self.rocksdb_overrides.get(<&str>::from(col))
+    }
 }
 
 impl Default for StoreConfig {
     fn default() -> Self {
         Self {
+            backend: StoreBackend::default(),
             path: None,
             enable_statistics: false,
             enable_statistics_export: true,
@@ -265,6 +732,7 @@ impl Default for StoreConfig {
                 sender: "wallet.kaiching".to_owned(),
                 method_name: "ft_on_transfer".to_owned(),
             }],
+            prefetch_rules: vec![],
 
             // TODO(#9511): Consider adding here shard id 3 or all shards after
             // this feature will be tested. Until that, use at your own risk.
@@ -273,6 +741,7 @@ impl Default for StoreConfig {
             // requires more RAM and takes several minutes on startup.
             load_mem_tries_for_shards: Default::default(),
             load_mem_tries_for_tracked_shards: false,
+            persist_mem_tries: false,
 
             migration_snapshot: Default::default(),
 
@@ -280,6 +749,23 @@ impl Default for StoreConfig {
 
             // TODO: To be phased out in favor of state_snapshot_config
             state_snapshot_enabled: false,
+
+            bulk_load: Default::default(),
+            compaction_schedule: Default::default(),
+            flat_storage_deltas: Default::default(),
+            flat_storage_key_filter: Default::default(),
+            flat_state_mmap: false,
+            value_compression: Default::default(),
+            disk_pressure: Default::default(),
+            io_uring: false,
+            huge_pages: false,
+            secondary: Default::default(),
+            memory_budget: Default::default(),
+            io_throttle: Default::default(),
+            state_root_audit: Default::default(),
+            object_storage: None,
+            shard_dbs: vec![],
+            rocksdb_overrides: HashMap::new(),
         }
     }
 }
@@ -356,3 +842,63 @@ pub struct PrefetchConfig {
     /// Contract method name.
     pub method_name: String,
 }
+
+/// Prefetching of the contract storage keys read by calls of a method.  For
+/// example, the rule
+///
+/// ```json
+/// {
+///   "receiver": "token.sweat",
+///   "method_name": "record_batch",
+///   "keys": [{"list": "/steps_batch", "value": "/0", "prefix": [116, 0], "encoding": "sha256"}]
+/// }
+/// ```
+///
+/// prefetches the record of every account in the `steps_batch` argument of
+/// `record_batch` calls, as the built-in SWEAT prefetcher does.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PrefetchRuleConfig {
+    /// Contract account id.
+    pub receiver: String,
+    /// Predecessors whose calls the rule applies to, all if empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub senders: Vec<String>,
+    /// Contract method name.
+    pub method_name: String,
+    /// Keys built from the JSON arguments of the call.
+    pub keys: Vec<PrefetchKeyPattern>,
+}
+
+/// How a contract storage key is built from the JSON arguments of a call.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PrefetchKeyPattern {
+    /// JSON pointer to an array in the arguments.  If set, a key is built
+    /// from every element of the array, otherwise one from the arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list: Option<String>,
+    /// JSON pointer to the string, within the array element or the
+    /// arguments, the key is built from.
+    pub value: String,
+    /// Bytes the key starts with, usually the prefix of a collection of the
+    /// contract.
+    pub prefix: Vec<u8>,
+    pub encoding: PrefetchKeyEncoding,
+    /// Whether the key is the SHA-256 hash of the prefix and the encoded
+    /// value, as used by hashed maps of the contract SDKs.
+    pub hash_key: bool,
+}
+
+/// Encoding of the value in a key built by a [`PrefetchKeyPattern`].
+#[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchKeyEncoding {
+    /// The bytes of the string.
+    #[default]
+    Raw,
+    /// The string serialized with borsh, i.e. prefixed with its length.
+    Borsh,
+    /// The SHA-256 hash of the bytes of the string.
+    Sha256,
+}
@@ -5,9 +5,14 @@ use std::io;
 
 pub(crate) mod rocksdb;
 
+pub mod backend;
+
 mod colddb;
+mod compresseddb;
 mod mixeddb;
+mod object_storage;
 mod recoverydb;
+mod shardeddb;
 mod splitdb;
 
 pub mod refcount;
@@ -17,20 +22,25 @@ mod testdb;
 mod database_tests;
 
 pub use self::colddb::ColdDB;
+pub use self::compresseddb::CompressedDB;
 pub use self::mixeddb::{MixedDB, ReadOrder};
+pub use self::object_storage::{open_bucket, ObjectStorageDB};
 pub use self::recoverydb::RecoveryDB;
 pub use self::rocksdb::RocksDB;
+pub use self::shardeddb::ShardedDB;
 pub use self::splitdb::SplitDB;
 
 pub use self::slice::DBSlice;
 pub use self::testdb::TestDB;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 // `DBCol::BlockMisc` keys
 pub const HEAD_KEY: &[u8; 4] = b"HEAD";
 pub const TAIL_KEY: &[u8; 4] = b"TAIL";
 pub const CHUNK_TAIL_KEY: &[u8; 10] = b"CHUNK_TAIL";
 pub const FORK_TAIL_KEY: &[u8; 9] = b"FORK_TAIL";
+pub const ABANDONED_FORKS_TAIL_KEY: &[u8; 20] = b"ABANDONED_FORKS_TAIL";
+pub const HEADER_TAIL_KEY: &[u8; 11] = b"HEADER_TAIL";
 pub const HEADER_HEAD_KEY: &[u8; 11] = b"HEADER_HEAD";
 pub const FINAL_HEAD_KEY: &[u8; 10] = b"FINAL_HEAD";
 pub const LATEST_KNOWN_KEY: &[u8; 12] = b"LATEST_KNOWN";
@@ -41,6 +51,9 @@ pub const GENESIS_CONGESTION_INFO_KEY: &[u8] = b"GENESIS_CONGESTION_INFO_KEY";
 pub const COLD_HEAD_KEY: &[u8; 9] = b"COLD_HEAD";
 pub const STATE_SYNC_DUMP_KEY: &[u8; 15] = b"STATE_SYNC_DUMP";
 pub const STATE_SNAPSHOT_KEY: &[u8; 18] = b"STATE_SNAPSHOT_KEY";
+pub const BLOCKS_TO_REFETCH_KEY: &[u8; 17] = b"BLOCKS_TO_REFETCH";
+pub const CHUNK_APPLY_JOURNAL_KEY: &[u8; 19] = b"CHUNK_APPLY_JOURNAL";
+pub const STATE_PART_UPLOAD_KEY: &[u8; 17] = b"STATE_PART_UPLOAD";
 
 // `DBCol::Misc` keys
 pub const FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS_KEY: &[u8] =
@@ -53,6 +66,7 @@ pub struct DBTransaction {
     pub(crate) ops: Vec<DBOp>,
 }
 
+#[derive(Clone)]
 pub(crate) enum DBOp {
     /// Sets `key` to `value`, without doing any checks.
     Set { col: DBCol, key: Vec<u8>, value: Vec<u8> },
@@ -194,6 +208,23 @@ pub trait Database: Sync + Send {
         Ok(self.get_raw_bytes(col, key)?.and_then(DBSlice::strip_refcount))
     }
 
+    /// Returns the values for the given keys, in the same order, with the
+    /// reference counts stripped for reference-counted columns.
+    ///
+    /// Databases which can read several keys at once, like RocksDB with
+    /// io_uring, overlap the reads instead of doing them one after another.
+    fn multi_get(&self, col: DBCol, keys: &[&[u8]]) -> io::Result<Vec<Option<DBSlice<'_>>>> {
+        keys.iter()
+            .map(|key| {
+                if col.is_rc() {
+                    self.get_with_rc_stripped(col, key)
+                } else {
+                    self.get_raw_bytes(col, key)
+                }
+            })
+            .collect()
+    }
+
     /// Iterate over all items in given column in lexicographical order sorted
     /// by the key.
     ///
@@ -239,6 +270,15 @@ pub trait Database: Sync + Send {
     /// Atomically apply all operations in given batch at once.
     fn write(&self, batch: DBTransaction) -> io::Result<()>;
 
+    /// Like [`Self::write`], if `check`, which may read the database, passes.
+    /// No other write to the database lands between the two, see
+    /// [`crate::Store::transaction`].
+    fn write_checked(
+        &self,
+        batch: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()>;
+
     /// Flush all in-memory data to disk.
     ///
     /// This is a no-op for in-memory databases.
@@ -250,6 +290,32 @@ pub trait Database: Sync + Send {
     /// is blocking until compaction finishes. Otherwise, this is a no-op.
     fn compact(&self) -> io::Result<()>;
 
+    /// Compacts the keys of the column within `[start, end)`, or the whole
+    /// column if both bounds are `None`.  Blocks until the compaction
+    /// finishes.
+    ///
+    /// This is a no-op for databases which don't compact.
+    fn compact_range(
+        &self,
+        _col: DBCol,
+        _start: Option<&[u8]>,
+        _end: Option<&[u8]>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns the estimated number of bytes which compactions of the column
+    /// still have to rewrite, or `None` if the database doesn't compact.
+    fn compaction_debt(&self, _col: DBCol) -> io::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Returns whether the database delays or stops writes because
+    /// compactions fall behind.
+    fn is_write_stalled(&self) -> io::Result<bool> {
+        Ok(false)
+    }
+
     /// Returns statistics about the database if available.
     fn get_store_statistics(&self) -> Option<StoreStatistics>;
 
@@ -265,6 +331,47 @@ pub trait Database: Sync + Send {
     fn copy_if_test(&self) -> Option<Arc<dyn Database>> {
         None
     }
+
+    /// Enters (`enabled == true`) or leaves bulk load mode for the given
+    /// columns, see [`crate::bulk_load`].  Calls are counted per column, so a
+    /// column leaves the mode once it left it as many times as it entered it.
+    ///
+    /// This is a no-op for databases which have no tunable write settings.
+    fn set_bulk_load(&self, _columns: &[DBCol], _enabled: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Makes the writes of the primary instance since the previous call
+    /// visible, if the database was opened in [`crate::Mode::Secondary`].
+    fn catch_up_with_primary(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Orders the writes to a database with its checked writes: plain writes take
+/// it shared, checked writes exclusively.  Databases which write to others
+/// just pass the checked writes on.
+#[derive(Default)]
+pub(crate) struct CommitLock(RwLock<()>);
+
+impl CommitLock {
+    pub fn write(
+        &self,
+        check: Option<&dyn Fn() -> io::Result<()>>,
+        write: impl FnOnce() -> io::Result<()>,
+    ) -> io::Result<()> {
+        match check {
+            None => {
+                let _guard = self.0.read().unwrap_or_else(|err| err.into_inner());
+                write()
+            }
+            Some(check) => {
+                let _guard = self.0.write().unwrap_or_else(|err| err.into_inner());
+                check()?;
+                write()
+            }
+        }
+    }
 }
 
 fn assert_no_overwrite(col: DBCol, key: &[u8], value: &[u8], old_value: &[u8]) {
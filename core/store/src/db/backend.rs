@@ -0,0 +1,182 @@
+//! Storage engines which [`crate::StoreOpener`] can open the databases with.
+//!
+//! The rest of the node only ever talks to a [`Database`], so the engine is
+//! an implementation detail of the opener.  The built-in engines are selected
+//! with the `backend` field of [`StoreConfig`]; other ones can be plugged in
+//! with [`crate::StoreOpener::with_backend`].
+
+use crate::db::rocksdb::snapshot::{Snapshot, SnapshotError};
+use crate::db::{Database, ObjectStorageDB, RocksDB, TestDB};
+use crate::metadata::DbMetadata;
+use crate::{Mode, StoreConfig, Temperature};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Built-in storage engines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreBackend {
+    #[default]
+    #[serde(rename = "rocksdb")]
+    RocksDB,
+    /// Keeps the databases in memory, for as long as the process runs.
+    /// Meant for tests and benchmarks which don't care about persistence.
+    Memory,
+    /// Keeps the database in an S3 compatible object storage configured with
+    /// `object_storage` of [`StoreConfig`].  Only supported for the cold
+    /// database.
+    ObjectStorage,
+}
+
+impl StoreBackend {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub(crate) fn get(self) -> &'static dyn DatabaseBackend {
+        static MEMORY: LazyLock<MemoryBackend> = LazyLock::new(MemoryBackend::default);
+        match self {
+            Self::RocksDB => &RocksDBBackend,
+            Self::Memory => &*MEMORY,
+            Self::ObjectStorage => &ObjectStorageBackend,
+        }
+    }
+}
+
+/// Storage engine opening the databases at given paths.
+///
+/// Implementations don't need to check the version nor the kind of the
+/// databases, which is done by the opener.
+pub trait DatabaseBackend: Send + Sync {
+    /// Returns version and kind of the database at `path` or `None` if it
+    /// doesn't exist.  Must not modify the database.
+    fn get_metadata(&self, path: &Path, config: &StoreConfig) -> io::Result<Option<DbMetadata>>;
+
+    /// Opens the database at `path`, creating it if the mode allows it.
+    ///
+    /// `temp` specifies whether the database is cold or hot which may affect
+    /// how reference counted columns are handled.
+    fn open(
+        &self,
+        path: &Path,
+        config: &StoreConfig,
+        mode: Mode,
+        temp: Temperature,
+    ) -> io::Result<Arc<dyn Database>>;
+
+    /// Creates a snapshot of the database before it gets migrated, see
+    /// `migration_snapshot` in [`StoreConfig`].  Engines which can't do that
+    /// migrate without a snapshot.
+    fn snapshot(
+        &self,
+        _path: &Path,
+        _config: &StoreConfig,
+        _temp: Temperature,
+    ) -> Result<Snapshot, SnapshotError> {
+        Ok(Snapshot::none())
+    }
+}
+
+pub struct RocksDBBackend;
+
+impl DatabaseBackend for RocksDBBackend {
+    fn get_metadata(&self, path: &Path, config: &StoreConfig) -> io::Result<Option<DbMetadata>> {
+        RocksDB::get_metadata(path, config)
+    }
+
+    fn open(
+        &self,
+        path: &Path,
+        config: &StoreConfig,
+        mode: Mode,
+        temp: Temperature,
+    ) -> io::Result<Arc<dyn Database>> {
+        Ok(Arc::new(RocksDB::open(path, config, mode, temp)?))
+    }
+
+    fn snapshot(
+        &self,
+        path: &Path,
+        config: &StoreConfig,
+        temp: Temperature,
+    ) -> Result<Snapshot, SnapshotError> {
+        Snapshot::new(path, config, temp)
+    }
+}
+
+/// In-memory databases, identified by their paths so that opening the same
+/// path again gives the same database.
+#[derive(Default)]
+pub struct MemoryBackend {
+    databases: Mutex<HashMap<PathBuf, Arc<TestDB>>>,
+}
+
+impl DatabaseBackend for MemoryBackend {
+    fn get_metadata(&self, path: &Path, _config: &StoreConfig) -> io::Result<Option<DbMetadata>> {
+        let db = self.databases.lock().unwrap().get(path).cloned();
+        db.map(|db| DbMetadata::read(db.as_ref())).transpose()
+    }
+
+    fn open(
+        &self,
+        path: &Path,
+        _config: &StoreConfig,
+        mode: Mode,
+        _temp: Temperature,
+    ) -> io::Result<Arc<dyn Database>> {
+        let mut databases = self.databases.lock().unwrap();
+        match databases.get(path) {
+            Some(db) => Ok(db.clone()),
+            None if mode.can_create() => {
+                let db = TestDB::new();
+                databases.insert(path.to_path_buf(), db.clone());
+                Ok(db)
+            }
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "database does not exist")),
+        }
+    }
+}
+
+/// Cold databases in an object storage.  The path of the database is ignored,
+/// the objects are kept under the prefix of the configured bucket instead.
+pub struct ObjectStorageBackend;
+
+impl ObjectStorageBackend {
+    fn open_db(config: &StoreConfig, mode: Mode) -> io::Result<ObjectStorageDB> {
+        let Some(object_storage) = &config.object_storage else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "object_storage must be configured to use the object_storage backend",
+            ));
+        };
+        ObjectStorageDB::open(object_storage, mode)
+    }
+}
+
+impl DatabaseBackend for ObjectStorageBackend {
+    fn get_metadata(&self, _path: &Path, config: &StoreConfig) -> io::Result<Option<DbMetadata>> {
+        let db = Self::open_db(config, Mode::ReadOnly)?;
+        if DbMetadata::maybe_read_version(&db)?.is_none() {
+            return Ok(None);
+        }
+        DbMetadata::read(&db).map(Some)
+    }
+
+    fn open(
+        &self,
+        _path: &Path,
+        config: &StoreConfig,
+        mode: Mode,
+        temp: Temperature,
+    ) -> io::Result<Arc<dyn Database>> {
+        if temp != Temperature::Cold {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "object_storage backend is only supported for the cold database",
+            ));
+        }
+        Ok(Arc::new(Self::open_db(config, mode)?))
+    }
+}
@@ -53,6 +53,11 @@ impl Database for ColdDB {
         self.cold.get_with_rc_stripped(col, key)
     }
 
+    fn multi_get(&self, col: DBCol, keys: &[&[u8]]) -> std::io::Result<Vec<Option<DBSlice<'_>>>> {
+        Self::check_is_in_colddb(col)?;
+        self.cold.multi_get(col, keys)
+    }
+
     /// Iterates over all values in a column.
     fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
         Self::log_assert_is_in_colddb(col);
@@ -88,25 +93,54 @@ impl Database for ColdDB {
     /// operations or operations decreasing reference count of a value.  If
     /// debug assertions are not enabled, such operations are filtered out.
     fn write(&self, mut transaction: DBTransaction) -> std::io::Result<()> {
-        let mut idx = 0;
-        while idx < transaction.ops.len() {
-            if adjust_op(&mut transaction.ops[idx]) {
-                idx += 1;
-            } else {
-                transaction.ops.swap_remove(idx);
-            }
-        }
+        adjust_ops(&mut transaction);
         self.cold.write(transaction)
     }
 
+    fn write_checked(
+        &self,
+        mut transaction: DBTransaction,
+        check: &dyn Fn() -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        adjust_ops(&mut transaction);
+        self.cold.write_checked(transaction, check)
+    }
+
     fn compact(&self) -> std::io::Result<()> {
         self.cold.compact()
     }
 
+    fn compact_range(
+        &self,
+        col: DBCol,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> std::io::Result<()> {
+        if !col.is_in_colddb() {
+            return Ok(());
+        }
+        self.cold.compact_range(col, start, end)
+    }
+
+    fn compaction_debt(&self, col: DBCol) -> std::io::Result<Option<u64>> {
+        if !col.is_in_colddb() {
+            return Ok(None);
+        }
+        self.cold.compaction_debt(col)
+    }
+
+    fn is_write_stalled(&self) -> std::io::Result<bool> {
+        self.cold.is_write_stalled()
+    }
+
     fn flush(&self) -> std::io::Result<()> {
         self.cold.flush()
     }
 
+    fn catch_up_with_primary(&self) -> std::io::Result<()> {
+        self.cold.catch_up_with_primary()
+    }
+
     fn get_store_statistics(&self) -> Option<crate::StoreStatistics> {
         self.cold.get_store_statistics()
     }
@@ -120,6 +154,19 @@ impl Database for ColdDB {
     }
 }
 
+/// Adjusts the operations of the transaction for the cold storage, dropping
+/// the ones it doesn't need, see [`adjust_op`].
+fn adjust_ops(transaction: &mut DBTransaction) {
+    let mut idx = 0;
+    while idx < transaction.ops.len() {
+        if adjust_op(&mut transaction.ops[idx]) {
+            idx += 1;
+        } else {
+            transaction.ops.swap_remove(idx);
+        }
+    }
+}
+
 /// Adjust database operation to be performed on cold storage.
 ///
 /// Returns whether the operation should be kept or dropped.  Generally, dropped
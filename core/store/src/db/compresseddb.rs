@@ -0,0 +1,477 @@
+//! Compression of large values of the State column with zstd dictionaries.
+//!
+//! Contract code and large contract data are values of the State column.
+//! RocksDB compresses blocks of neighbouring values, which are unrelated
+//! trie nodes and values since the keys end with their hashes, so it finds
+//! little to share between them.  A dictionary trained on the values of the
+//! database holds what they do share, e.g. the sections every Wasm module
+//! has, and compresses each value on its own.
+//!
+//! A compressed value starts with a header of [`MAGIC`], the version of the
+//! format, the id of the dictionary and the length of the value.  Values
+//! stored before the compression was enabled aren't rewritten, and the
+//! values written by contracts may start with [`MAGIC`] too.  The keys of the
+//! State column end with the hash of the value though, so a stored value is
+//! a compressed one only if it starts with [`MAGIC`] and its hash isn't the
+//! one in the key.
+//!
+//! Adding a reference to a value writes the value again, and RocksDB keeps
+//! the bytes written first when merging the reference counts.  Those may be
+//! compressed with another dictionary or not compressed at all, either way
+//! they decompress to the same value.
+//!
+//! There is no `ContractCode` column to compress, the code of the contracts
+//! is stored in the State column like any other value of the trie.
+
+use super::refcount;
+use super::{DBIterator, DBOp, DBSlice, DBTransaction, Database, StoreStatistics};
+use crate::config::ValueCompressionConfig;
+use crate::metadata::COMPRESSION_DICTIONARY_KEY_PREFIX;
+use crate::{DBCol, Mode};
+use near_primitives::hash::hash;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, RwLock};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+/// First bytes of a compressed value.
+const MAGIC: [u8; 3] = [0xfd, b'N', b'Z'];
+
+/// Version of the format of compressed values.  Values with a version the
+/// node doesn't know can't be read, they were written by a newer release.
+const FORMAT_VERSION: u8 = 1;
+
+/// Magic, format version, dictionary id and length of the value.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + 4;
+
+/// Id of values compressed without a dictionary, before one was trained.
+const NO_DICTIONARY: u32 = 0;
+
+/// Fewer samples than this make a dictionary which doesn't help.
+const MIN_TRAIN_SAMPLES: usize = 1000;
+
+/// Compresses the values of the State column written to the database and
+/// decompresses the ones read from it.
+pub struct CompressedDB {
+    db: Arc<dyn Database>,
+    /// `None` if the compression is disabled, values are only decompressed.
+    encoder: Option<Encoder>,
+    /// Dictionaries by their ids, loaded as they are needed.  A secondary
+    /// instance may read values compressed with a dictionary the primary
+    /// trained after the secondary was opened.
+    decoders: RwLock<HashMap<u32, Arc<DecoderDictionary<'static>>>>,
+}
+
+#[derive(Clone)]
+struct Encoder {
+    level: i32,
+    min_value_size: usize,
+    dictionary: Option<(u32, Arc<EncoderDictionary<'static>>)>,
+}
+
+impl Encoder {
+    /// Returns the compressed value, or `None` if the value should be stored
+    /// as it is.
+    fn encode(&self, value: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if value.len() < self.min_value_size || value.len() > u32::MAX as usize {
+            return Ok(None);
+        }
+        let (id, frame) = match &self.dictionary {
+            Some((id, dictionary)) => (
+                *id,
+                zstd::bulk::Compressor::with_prepared_dictionary(dictionary)?.compress(value)?,
+            ),
+            None => (NO_DICTIONARY, zstd::bulk::Compressor::new(self.level)?.compress(value)?),
+        };
+        if HEADER_LEN + frame.len() >= value.len() {
+            return Ok(None);
+        }
+        let mut compressed = Vec::with_capacity(HEADER_LEN + frame.len());
+        compressed.extend_from_slice(&MAGIC);
+        compressed.push(FORMAT_VERSION);
+        compressed.extend_from_slice(&id.to_le_bytes());
+        compressed.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        compressed.extend_from_slice(&frame);
+        Ok(Some(compressed))
+    }
+}
+
+fn dictionary_key(id: u32) -> Vec<u8> {
+    [&COMPRESSION_DICTIONARY_KEY_PREFIX[..], &id.to_be_bytes()].concat()
+}
+
+impl CompressedDB {
+    fn encode_transaction(&self, transaction: &mut DBTransaction) -> io::Result<()> {
+        if let Some(encoder) = &self.encoder {
+            for op in &mut transaction.ops {
+                Self::encode_op(encoder, op)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wraps the database.  If the compression is enabled and the database
+    /// opened for writing has no dictionary yet, trains one on the values
+    /// already in it.
+    pub fn new(
+        db: Arc<dyn Database>,
+        config: &ValueCompressionConfig,
+        mode: Mode,
+    ) -> io::Result<Arc<Self>> {
+        let mut this = Self { db, encoder: None, decoders: RwLock::new(HashMap::new()) };
+        if !config.enabled {
+            return Ok(Arc::new(this));
+        }
+        let mut dictionary = this.latest_dictionary()?;
+        if dictionary.is_none() && mode.read_write() {
+            dictionary = this.train_dictionary(config)?;
+        }
+        this.encoder = Some(Encoder {
+            level: config.level,
+            min_value_size: config.min_value_size.as_u64() as usize,
+            dictionary: dictionary.map(|(id, dictionary)| {
+                (id, Arc::new(EncoderDictionary::copy(&dictionary, config.level)))
+            }),
+        });
+        Ok(Arc::new(this))
+    }
+
+    /// Returns the dictionary with the highest id, which new values are
+    /// compressed with.
+    fn latest_dictionary(&self) -> io::Result<Option<(u32, Vec<u8>)>> {
+        let mut latest = None;
+        for item in self.db.iter_prefix(DBCol::DbVersion, COMPRESSION_DICTIONARY_KEY_PREFIX) {
+            let (key, value) = item?;
+            let id = key[COMPRESSION_DICTIONARY_KEY_PREFIX.len()..].try_into().map_err(|_| {
+                io::Error::other(format!("invalid compression dictionary key {key:?}"))
+            })?;
+            latest = Some((u32::from_be_bytes(id), value.into_vec()));
+        }
+        Ok(latest)
+    }
+
+    /// Trains a dictionary on a sample of the values of the State column and
+    /// stores it in the database.
+    fn train_dictionary(
+        &self,
+        config: &ValueCompressionConfig,
+    ) -> io::Result<Option<(u32, Vec<u8>)>> {
+        let min_value_size = config.min_value_size.as_u64() as usize;
+        let mut samples = vec![];
+        let mut sample_size = 0;
+        for item in self.iter(DBCol::State) {
+            let (_, value) = item?;
+            if value.len() < min_value_size {
+                continue;
+            }
+            sample_size += value.len() as u64;
+            samples.push(value);
+            if sample_size >= config.train_sample_size.as_u64() {
+                break;
+            }
+        }
+        if samples.len() < MIN_TRAIN_SAMPLES {
+            tracing::info!(target: "store", samples = samples.len(), "Too few values to train a compression dictionary, compressing without one");
+            return Ok(None);
+        }
+        let dictionary = match zstd::dict::from_samples(
+            &samples,
+            config.dictionary_size.as_u64() as usize,
+        ) {
+            Ok(dictionary) => dictionary,
+            Err(err) => {
+                tracing::warn!(target: "store", ?err, "Failed to train a compression dictionary, compressing without one");
+                return Ok(None);
+            }
+        };
+        let id = NO_DICTIONARY + 1;
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::DbVersion, dictionary_key(id), dictionary.clone());
+        self.db.write(transaction)?;
+        tracing::info!(target: "store", samples = samples.len(), size = dictionary.len(), "Trained compression dictionary");
+        Ok(Some((id, dictionary)))
+    }
+
+    fn decoder(&self, id: u32) -> io::Result<Arc<DecoderDictionary<'static>>> {
+        if let Some(decoder) = self.decoders.read().unwrap().get(&id) {
+            return Ok(decoder.clone());
+        }
+        let dictionary = self.db.get_raw_bytes(DBCol::DbVersion, &dictionary_key(id))?;
+        let dictionary = dictionary
+            .ok_or_else(|| io::Error::other(format!("compression dictionary {id} is missing")))?;
+        let decoder = Arc::new(DecoderDictionary::copy(&dictionary));
+        self.decoders.write().unwrap().insert(id, decoder.clone());
+        Ok(decoder)
+    }
+
+    /// Returns the decompressed value, or `None` if the value is stored as
+    /// it is.
+    fn decode(&self, key: &[u8], value: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if value.len() < HEADER_LEN || value[..MAGIC.len()] != MAGIC {
+            return Ok(None);
+        }
+        if key.get(8..) == Some(&hash(value).0[..]) {
+            return Ok(None);
+        }
+        let (header, frame) = value.split_at(HEADER_LEN);
+        let version = header[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            let msg = format!(
+                "value of {key:?} is compressed with format version {version}, \
+                 which was added in a newer release of neard"
+            );
+            return Err(io::Error::other(msg));
+        }
+        let id = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let len = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        let decoded = if id == NO_DICTIONARY {
+            zstd::bulk::Decompressor::new()?.decompress(frame, len)?
+        } else {
+            let decoder = self.decoder(id)?;
+            let mut decompressor = zstd::bulk::Decompressor::with_prepared_dictionary(&decoder)?;
+            decompressor.decompress(frame, len)?
+        };
+        Ok(Some(decoded))
+    }
+
+    /// Like [`Self::decode`] but for a value with its reference count.
+    fn decode_with_rc(&self, key: &[u8], value: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let (Some(payload), rc) = refcount::decode_value_with_rc(value) else {
+            return Ok(None);
+        };
+        Ok(self.decode(key, payload)?.map(|decoded| [&decoded[..], &rc.to_le_bytes()].concat()))
+    }
+
+    fn decode_slice<'a>(
+        &self,
+        col: DBCol,
+        key: &[u8],
+        value: DBSlice<'a>,
+        with_rc: bool,
+    ) -> io::Result<DBSlice<'a>> {
+        if col != DBCol::State {
+            return Ok(value);
+        }
+        let decoded =
+            if with_rc { self.decode_with_rc(key, &value)? } else { self.decode(key, &value)? };
+        Ok(decoded.map_or(value, DBSlice::from_vec))
+    }
+
+    fn decode_iter<'a>(
+        &'a self,
+        col: DBCol,
+        iter: DBIterator<'a>,
+        with_rc: bool,
+    ) -> DBIterator<'a> {
+        if col != DBCol::State {
+            return iter;
+        }
+        Box::new(iter.map(move |item| {
+            let (key, value) = item?;
+            let decoded = if with_rc {
+                self.decode_with_rc(&key, &value)?
+            } else {
+                self.decode(&key, &value)?
+            };
+            Ok((key, decoded.map_or(value, Vec::into_boxed_slice)))
+        }))
+    }
+
+    /// Compresses the value of an operation on the State column, which has
+    /// the reference count attached.
+    fn encode_op(encoder: &Encoder, op: &mut DBOp) -> io::Result<()> {
+        let value = match op {
+            DBOp::Set { col: DBCol::State, value, .. }
+            | DBOp::Insert { col: DBCol::State, value, .. }
+            | DBOp::UpdateRefcount { col: DBCol::State, value, .. } => value,
+            _ => return Ok(()),
+        };
+        let (Some(payload), rc) = refcount::decode_value_with_rc(value) else {
+            return Ok(());
+        };
+        if let Some(compressed) = encoder.encode(payload)? {
+            *value = [&compressed[..], &rc.to_le_bytes()].concat();
+        }
+        Ok(())
+    }
+}
+
+impl Database for CompressedDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        let value = self.db.get_raw_bytes(col, key)?;
+        value.map(|value| self.decode_slice(col, key, value, col.is_rc())).transpose()
+    }
+
+    fn get_with_rc_stripped(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        let value = self.db.get_with_rc_stripped(col, key)?;
+        value.map(|value| self.decode_slice(col, key, value, false)).transpose()
+    }
+
+    fn multi_get(&self, col: DBCol, keys: &[&[u8]]) -> io::Result<Vec<Option<DBSlice<'_>>>> {
+        let values = self.db.multi_get(col, keys)?;
+        keys.iter()
+            .zip(values)
+            .map(|(key, value)| {
+                value.map(|value| self.decode_slice(col, key, value, false)).transpose()
+            })
+            .collect()
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        self.decode_iter(col, self.db.iter(col), false)
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        self.decode_iter(col, self.db.iter_prefix(col, key_prefix), false)
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        self.decode_iter(col, self.db.iter_range(col, lower_bound, upper_bound), false)
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        self.decode_iter(col, self.db.iter_raw_bytes(col), col.is_rc())
+    }
+
+    fn write(&self, mut transaction: DBTransaction) -> io::Result<()> {
+        self.encode_transaction(&mut transaction)?;
+        self.db.write(transaction)
+    }
+
+    fn write_checked(
+        &self,
+        mut transaction: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.encode_transaction(&mut transaction)?;
+        self.db.write_checked(transaction, check)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.db.flush()
+    }
+
+    fn compact(&self) -> io::Result<()> {
+        self.db.compact()
+    }
+
+    fn compact_range(
+        &self,
+        col: DBCol,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> io::Result<()> {
+        self.db.compact_range(col, start, end)
+    }
+
+    fn compaction_debt(&self, col: DBCol) -> io::Result<Option<u64>> {
+        self.db.compaction_debt(col)
+    }
+
+    fn is_write_stalled(&self) -> io::Result<bool> {
+        self.db.is_write_stalled()
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        self.db.get_store_statistics()
+    }
+
+    fn create_checkpoint(
+        &self,
+        path: &std::path::Path,
+        columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        self.db.create_checkpoint(path, columns_to_keep)
+    }
+
+    fn copy_if_test(&self) -> Option<Arc<dyn Database>> {
+        let db = self.db.copy_if_test()?;
+        let decoders = RwLock::new(self.decoders.read().unwrap().clone());
+        Some(Arc::new(Self { db, encoder: self.encoder.clone(), decoders }))
+    }
+
+    fn set_bulk_load(&self, columns: &[DBCol], enabled: bool) -> io::Result<()> {
+        self.db.set_bulk_load(columns, enabled)
+    }
+
+    fn catch_up_with_primary(&self) -> io::Result<()> {
+        self.db.catch_up_with_primary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompressedDB, MAGIC};
+    use crate::config::ValueCompressionConfig;
+    use crate::db::refcount::add_positive_refcount;
+    use crate::db::{DBTransaction, Database, TestDB};
+    use crate::{DBCol, Mode};
+    use near_primitives::hash::hash;
+    use std::num::NonZeroU32;
+
+    fn state_key(value: &[u8]) -> Vec<u8> {
+        [&[0u8; 8][..], &hash(value).0[..]].concat()
+    }
+
+    fn write_value(db: &dyn Database, value: &[u8]) {
+        let mut transaction = DBTransaction::new();
+        let value_with_rc = add_positive_refcount(value, NonZeroU32::new(1).unwrap());
+        transaction.update_refcount(DBCol::State, state_key(value), value_with_rc);
+        db.write(transaction).unwrap();
+    }
+
+    fn read_value(db: &dyn Database, value: &[u8]) -> Vec<u8> {
+        db.get_with_rc_stripped(DBCol::State, &state_key(value)).unwrap().unwrap().to_vec()
+    }
+
+    /// Values sharing their field names, like the records of a contract.
+    fn value(i: u32) -> Vec<u8> {
+        let fields: Vec<String> = (0..100).map(|j| format!("field_{j}:{}", i * j % 997)).collect();
+        fields.join(",").into_bytes()
+    }
+
+    #[test]
+    fn test_compressed_values() {
+        let inner = TestDB::new();
+        let config = ValueCompressionConfig { enabled: true, ..Default::default() };
+        let db = CompressedDB::new(inner.clone(), &config, Mode::ReadWrite).unwrap();
+
+        // Compressed without a dictionary, none was trained yet.
+        let large = value(0);
+        write_value(db.as_ref(), &large);
+        let stored = inner.get_with_rc_stripped(DBCol::State, &state_key(&large)).unwrap().unwrap();
+        assert!(stored.starts_with(&MAGIC) && stored.len() < large.len());
+        assert_eq!(read_value(db.as_ref(), &large), large);
+
+        // Stored as it is, even though it looks like a compressed value.
+        let small = [&MAGIC[..], &[1; 20]].concat();
+        write_value(db.as_ref(), &small);
+        assert_eq!(read_value(inner.as_ref(), &small), small);
+        assert_eq!(read_value(db.as_ref(), &small), small);
+        let large_lookalike = [&MAGIC[..], &[1; 500]].concat();
+        write_value(inner.as_ref(), &large_lookalike);
+        assert_eq!(read_value(db.as_ref(), &large_lookalike), large_lookalike);
+
+        // A dictionary is trained once there are enough values, the values
+        // compressed before stay readable.
+        for i in 1..2000 {
+            write_value(db.as_ref(), &value(i));
+        }
+        let db = CompressedDB::new(inner.clone(), &config, Mode::ReadWrite).unwrap();
+        assert!(db.encoder.as_ref().unwrap().dictionary.is_some());
+        let new = value(5000);
+        write_value(db.as_ref(), &new);
+        assert_eq!(read_value(db.as_ref(), &new), new);
+        assert_eq!(read_value(db.as_ref(), &large), large);
+
+        // Reading doesn't need the compression to be enabled.
+        let disabled = ValueCompressionConfig::default();
+        let db = CompressedDB::new(inner, &disabled, Mode::ReadOnly).unwrap();
+        assert_eq!(read_value(db.as_ref(), &new), new);
+    }
+}
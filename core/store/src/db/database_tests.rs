@@ -42,4 +42,38 @@ mod tests {
             assert_eq!(keys, vec!["aa", "aa1"]);
         }
     }
+
+    /// Plain writes, including the ones not going through a `Store`, wait for
+    /// the checked write in progress, and the failed checks write nothing.
+    #[test]
+    fn test_db_write_checked() {
+        for db in test_and_rocksdb() {
+            let db = db.as_ref();
+            std::thread::scope(|scope| {
+                let mut transaction = DBTransaction::new();
+                transaction.set(DBCol::BlockMisc, vec![1], vec![1]);
+                db.write_checked(transaction, &|| {
+                    let writer = scope.spawn(|| {
+                        let mut transaction = DBTransaction::new();
+                        transaction.set(DBCol::BlockMisc, vec![1], vec![2]);
+                        db.write(transaction).unwrap();
+                    });
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    assert!(!writer.is_finished());
+                    assert!(db.get_raw_bytes(DBCol::BlockMisc, &[1])?.is_none());
+                    Ok(())
+                })
+                .unwrap();
+            });
+            let value = db.get_raw_bytes(DBCol::BlockMisc, &[1]).unwrap();
+            assert_eq!(value.as_deref(), Some(&[2][..]));
+
+            let mut transaction = DBTransaction::new();
+            transaction.set(DBCol::BlockMisc, vec![1], vec![3]);
+            let check = || -> std::io::Result<()> { Err(std::io::Error::other("conflict")) };
+            assert!(db.write_checked(transaction, &check).is_err());
+            let value = db.get_raw_bytes(DBCol::BlockMisc, &[1]).unwrap();
+            assert_eq!(value.as_deref(), Some(&[2][..]));
+        }
+    }
 }
@@ -114,6 +114,14 @@ impl Database for MixedDB {
         self.write_db.write(batch)
     }
 
+    fn write_checked(
+        &self,
+        batch: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.write_db.write_checked(batch, check)
+    }
+
     /// There is no need to flush a read-only DB.
     fn flush(&self) -> io::Result<()> {
         self.write_db.flush()
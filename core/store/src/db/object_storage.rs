@@ -0,0 +1,327 @@
+//! Cold database kept in an S3 compatible object storage.
+//!
+//! Every key is stored as a separate object named
+//! `{prefix}{column}/{hex encoded key}`.  Hex encoding keeps the lexicographic
+//! order of the keys, so listing the objects of a column gives the keys in the
+//! same order as RocksDB would.  Values fetched from the bucket are kept in an
+//! in-memory cache bounded by `cache_size` of [`ObjectStorageConfig`].
+//!
+//! Object storage doesn't support transactions, so the operations of a
+//! [`DBTransaction`] are applied one by one.  This is fine for the cold
+//! database: the cold store loop writes the data of a block before updating
+//! the cold head, so a write interrupted half way is simply redone.
+
+use crate::config::{ObjectStorageConfig, ObjectStorageLocation};
+use crate::db::{refcount, CommitLock, DBIterator, DBOp, DBSlice, DBTransaction, Database};
+use crate::{DBCol, Mode, StoreStatistics};
+use lru::LruCache;
+use std::io;
+use std::sync::Mutex;
+
+/// Timeout of a single request to the object storage.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub struct ObjectStorageDB {
+    bucket: s3::Bucket,
+    prefix: String,
+    read_only: bool,
+    cache: Mutex<ValueCache>,
+    /// Orders the writes with the checked writes.
+    commit_lock: CommitLock,
+}
+
+/// LRU cache of the values bounded by their total size.
+struct ValueCache {
+    values: LruCache<(DBCol, Vec<u8>), Vec<u8>>,
+    size: u64,
+    max_size: u64,
+}
+
+impl ValueCache {
+    fn get(&mut self, col: DBCol, key: &[u8]) -> Option<Vec<u8>> {
+        self.values.get(&(col, key.to_vec())).cloned()
+    }
+
+    fn put(&mut self, col: DBCol, key: Vec<u8>, value: Vec<u8>) {
+        let size = (key.len() + value.len()) as u64;
+        if size > self.max_size {
+            self.remove(col, &key);
+            return;
+        }
+        self.size += size;
+        if let Some(((_, old_key), old_value)) = self.values.push((col, key), value) {
+            self.size -= (old_key.len() + old_value.len()) as u64;
+        }
+        while self.size > self.max_size {
+            let Some(((_, key), value)) = self.values.pop_lru() else { break };
+            self.size -= (key.len() + value.len()) as u64;
+        }
+    }
+
+    fn remove(&mut self, col: DBCol, key: &[u8]) {
+        if let Some(value) = self.values.pop(&(col, key.to_vec())) {
+            self.size -= (key.len() + value.len()) as u64;
+        }
+    }
+
+    fn clear(&mut self, col: DBCol) {
+        let keys: Vec<_> =
+            self.values.iter().map(|(k, _)| k).filter(|k| k.0 == col).cloned().collect();
+        for (col, key) in keys {
+            self.remove(col, &key);
+        }
+    }
+}
+
+/// Returns the bucket of the object storage with the credentials of the
+/// config.
+pub fn open_bucket(config: &ObjectStorageConfig) -> io::Result<s3::Bucket> {
+    let credentials = match &config.credentials_file {
+        Some(path) => {
+            #[derive(serde::Deserialize)]
+            struct Keys {
+                access_key: String,
+                secret_key: String,
+            }
+            let keys: Keys = serde_json::from_slice(&std::fs::read(path)?)?;
+            s3::creds::Credentials::new(
+                Some(&keys.access_key),
+                Some(&keys.secret_key),
+                None,
+                None,
+                None,
+            )
+        }
+        None => s3::creds::Credentials::default(),
+    }
+    .map_err(io::Error::other)?;
+    let (bucket, region) = match &config.location {
+        ObjectStorageLocation::S3 { bucket, region } => {
+            (bucket, region.parse::<s3::Region>().map_err(io::Error::other)?)
+        }
+        // GCS exposes an S3 compatible API which works with HMAC keys.
+        ObjectStorageLocation::GCS { bucket } => (
+            bucket,
+            s3::Region::Custom {
+                region: "auto".to_string(),
+                endpoint: "https://storage.googleapis.com".to_string(),
+            },
+        ),
+    };
+    let mut bucket = s3::Bucket::new(bucket, region, credentials).map_err(io::Error::other)?;
+    bucket.set_request_timeout(Some(REQUEST_TIMEOUT));
+    Ok(bucket)
+}
+
+impl ObjectStorageDB {
+    pub fn open(config: &ObjectStorageConfig, mode: Mode) -> io::Result<Self> {
+        let bucket = open_bucket(config)?;
+        let cache = ValueCache {
+            values: LruCache::unbounded(),
+            size: 0,
+            max_size: config.cache_size.as_u64(),
+        };
+        Ok(Self {
+            bucket,
+            prefix: config.prefix.clone(),
+            read_only: mode.read_only(),
+            cache: Mutex::new(cache),
+            commit_lock: CommitLock::default(),
+        })
+    }
+
+    fn apply(&self, transaction: DBTransaction) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "object storage database is opened in read-only mode",
+            ));
+        }
+        for op in transaction.ops {
+            match op {
+                DBOp::Set { col, key, value } => self.put_object(col, key, value)?,
+                DBOp::Insert { col, key, value } => {
+                    if cfg!(debug_assertions) {
+                        if let Some(old_value) = self.get_object(col, &key)? {
+                            super::assert_no_overwrite(col, &key, &value, &*old_value)
+                        }
+                    }
+                    self.put_object(col, key, value)?
+                }
+                DBOp::UpdateRefcount { col, key, value } => {
+                    // The cold database turns these into sets, see `ColdDB`,
+                    // so this is only reached by tools writing to it directly.
+                    let existing = self.get_object(col, &key)?;
+                    let merged = refcount::refcount_merge(existing.as_deref(), [value.as_slice()]);
+                    if merged.is_empty() {
+                        self.delete_object(col, &key)?;
+                    } else {
+                        self.put_object(col, key, merged)?;
+                    }
+                }
+                DBOp::Delete { col, key } => self.delete_object(col, &key)?,
+                DBOp::DeleteAll { col } => {
+                    for key in self.list_keys(col)? {
+                        self.delete_object(col, &key)?;
+                    }
+                    self.cache.lock().unwrap().clear(col);
+                }
+                DBOp::DeleteRange { col, from, to } => {
+                    for key in self.list_keys(col)? {
+                        if (&from..&to).contains(&&key) {
+                            self.delete_object(col, &key)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn column_prefix(&self, col: DBCol) -> String {
+        format!("{}{}/", self.prefix, <&str>::from(col))
+    }
+
+    fn object_name(&self, col: DBCol, key: &[u8]) -> String {
+        format!("{}{}", self.column_prefix(col), hex::encode(key))
+    }
+
+    fn get_object(&self, col: DBCol, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if let Some(value) = self.cache.lock().unwrap().get(col, key) {
+            return Ok(Some(value));
+        }
+        let value = match self.bucket.get_object_blocking(self.object_name(col, key)) {
+            Ok(response) if response.status_code() == 404 => return Ok(None),
+            Ok(response) if response.status_code() == 200 => response.bytes().to_vec(),
+            Ok(response) => {
+                return Err(io::Error::other(format!(
+                    "unexpected response status {} for {col:?} object",
+                    response.status_code()
+                )))
+            }
+            Err(s3::error::S3Error::Http(404, _)) => return Ok(None),
+            Err(err) => return Err(io::Error::other(err)),
+        };
+        self.cache.lock().unwrap().put(col, key.to_vec(), value.clone());
+        Ok(Some(value))
+    }
+
+    fn put_object(&self, col: DBCol, key: Vec<u8>, value: Vec<u8>) -> io::Result<()> {
+        self.bucket
+            .put_object_blocking(self.object_name(col, &key), &value)
+            .map_err(io::Error::other)?;
+        self.cache.lock().unwrap().put(col, key, value);
+        Ok(())
+    }
+
+    fn delete_object(&self, col: DBCol, key: &[u8]) -> io::Result<()> {
+        self.cache.lock().unwrap().remove(col, key);
+        match self.bucket.delete_object_blocking(self.object_name(col, key)) {
+            Ok(_) | Err(s3::error::S3Error::Http(404, _)) => Ok(()),
+            Err(err) => Err(io::Error::other(err)),
+        }
+    }
+
+    /// Lists the keys of the column, in order.
+    fn list_keys(&self, col: DBCol) -> io::Result<Vec<Vec<u8>>> {
+        let prefix = self.column_prefix(col);
+        let results = self.bucket.list_blocking(prefix.clone(), None).map_err(io::Error::other)?;
+        let mut keys = results
+            .into_iter()
+            .flat_map(|result| result.contents)
+            .map(|object| {
+                let name = object.key.strip_prefix(&prefix).unwrap_or(&object.key);
+                hex::decode(name).map_err(io::Error::other)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Iterates over the keys of the column accepted by `filter`, fetching the
+    /// values lazily.
+    fn iter_objects<'a>(
+        &'a self,
+        col: DBCol,
+        filter: impl Fn(&[u8]) -> bool + 'a,
+    ) -> impl Iterator<Item = io::Result<(Box<[u8]>, Box<[u8]>)>> + 'a {
+        let keys = match self.list_keys(col) {
+            Ok(keys) => keys,
+            Err(err) => return itertools::Either::Left(std::iter::once(Err(err))),
+        };
+        itertools::Either::Right(keys.into_iter().filter(move |key| filter(key)).filter_map(
+            move |key| match self.get_object(col, &key) {
+                // The object may have been deleted since listing.
+                Ok(None) => None,
+                Ok(Some(value)) => Some(Ok((key.into_boxed_slice(), value.into_boxed_slice()))),
+                Err(err) => Some(Err(err)),
+            },
+        ))
+    }
+}
+
+impl Database for ObjectStorageDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        Ok(self.get_object(col, key)?.map(DBSlice::from_vec))
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        refcount::iter_with_rc_logic(col, self.iter_objects(col, |_| true))
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        let iterator = self.iter_objects(col, move |key| key.starts_with(key_prefix));
+        refcount::iter_with_rc_logic(col, iterator)
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        let lower = lower_bound.map(<[u8]>::to_vec);
+        let upper = upper_bound.map(<[u8]>::to_vec);
+        let iterator = self.iter_objects(col, move |key| {
+            lower.as_deref().map_or(true, |lower| key >= lower)
+                && upper.as_deref().map_or(true, |upper| key < upper)
+        });
+        refcount::iter_with_rc_logic(col, iterator)
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        Box::new(self.iter_objects(col, |_| true))
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        self.commit_lock.write(None, || self.apply(transaction))
+    }
+
+    fn write_checked(
+        &self,
+        transaction: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.commit_lock.write(Some(check), || self.apply(transaction))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn compact(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        None
+    }
+
+    fn create_checkpoint(
+        &self,
+        _path: &std::path::Path,
+        _columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("checkpoints of an object storage database are not supported")
+    }
+}
@@ -66,6 +66,16 @@ impl Database for RecoveryDB {
         }
     }
 
+    fn write_checked(
+        &self,
+        mut transaction: DBTransaction,
+        check: &dyn Fn() -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        self.filter_db_ops(&mut transaction);
+        self.ops_written.fetch_add(transaction.ops.len() as i64, Ordering::Relaxed);
+        self.cold.write_checked(transaction, check)
+    }
+
     fn compact(&self) -> std::io::Result<()> {
         self.cold.compact()
     }
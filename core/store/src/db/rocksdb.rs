@@ -1,15 +1,20 @@
-use crate::config::Mode;
-use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, StatsValue};
+use crate::config::{BulkLoadConfig, Mode, RocksDbColumnConfig, RocksDbCompression};
+use crate::db::{
+    refcount, CommitLock, DBIterator, DBOp, DBSlice, DBTransaction, Database, StatsValue,
+};
+use crate::io_attribution::IoSubsystem;
+use crate::io_features::{IoFeatures, HUGE_PAGE_SIZE};
 use crate::{metadata, metrics, DBCol, StoreConfig, StoreStatistics, Temperature};
 use ::rocksdb::{
     BlockBasedOptions, Cache, ColumnFamily, Env, IteratorMode, Options, ReadOptions, WriteBatch, DB,
 };
 use anyhow::Context;
 use itertools::Itertools;
+use near_o11y::metrics::IntCounter;
 use std::io;
 use std::ops::Deref;
 use std::path::Path;
-use std::sync::LazyLock;
+use std::sync::{LazyLock, Mutex};
 use strum::IntoEnumIterator;
 use tracing::warn;
 
@@ -26,6 +31,7 @@ static CF_PROPERTY_NAMES: LazyLock<Vec<std::ffi::CString>> = LazyLock::new(|| {
         &[
             properties::LIVE_SST_FILES_SIZE,
             properties::ESTIMATE_LIVE_DATA_SIZE,
+            properties::ESTIMATE_NUM_KEYS,
             properties::COMPACTION_PENDING,
             properties::NUM_RUNNING_COMPACTIONS,
             properties::ESTIMATE_PENDING_COMPACTION_BYTES,
@@ -54,6 +60,20 @@ pub struct RocksDB {
     /// want.
     cf_handles: enum_map::EnumMap<DBCol, Option<std::ptr::NonNull<ColumnFamily>>>,
 
+    bulk_load_config: BulkLoadConfig,
+    /// Write settings of the columns restored after bulk loads, see
+    /// [`regular_column_write_options`].
+    column_write_options: enum_map::EnumMap<DBCol, [(&'static str, String); 3]>,
+    /// Whether iterators read ahead asynchronously, see
+    /// [`crate::io_features`].
+    async_io: bool,
+    /// Number of active bulk load phases per column.
+    bulk_load_counts: Mutex<enum_map::EnumMap<DBCol, usize>>,
+    /// Whether the database was opened in [`Mode::Secondary`].
+    secondary: bool,
+    /// Orders the writes with the checked writes.
+    commit_lock: CommitLock,
+
     // RAII-style of keeping track of the number of instances of RocksDB and
     // counting total sum of max_open_files.
     _instance_tracker: instance_tracker::InstanceTracker,
@@ -89,7 +109,8 @@ impl RocksDB {
         temp: Temperature,
     ) -> io::Result<Self> {
         let columns = DBCol::iter().collect_vec();
-        Self::open_with_columns(path, store_config, mode, temp, &columns)
+        let features = IoFeatures::detect(store_config);
+        Self::open_with_columns(path, store_config, mode, temp, &columns, features)
     }
 
     /// Opens the database with given set of column families configured.
@@ -108,18 +129,43 @@ impl RocksDB {
     ///
     /// With all that, it’s actually quite messy if at some point we’ll end up
     /// opening cold storage as hot since it’ll create all the missing columns.
+    ///
+    /// `features` are the I/O features of the host to use, see
+    /// [`IoFeatures::detect`].
     fn open_with_columns(
         path: &Path,
         store_config: &StoreConfig,
         mode: Mode,
         temp: Temperature,
         columns: &[DBCol],
+        features: IoFeatures,
     ) -> io::Result<Self> {
         let counter = instance_tracker::InstanceTracker::try_new(store_config.max_open_files)
             .map_err(io::Error::other)?;
-        let (db, db_opt) = Self::open_db(path, store_config, mode, temp, columns)?;
+        let (db, db_opt) = Self::open_db(path, store_config, mode, temp, columns, features)?;
         let cf_handles = Self::get_cf_handles(&db, columns);
-        Ok(Self { db, db_opt, cf_handles, _instance_tracker: counter })
+        Ok(Self {
+            db,
+            db_opt,
+            cf_handles,
+            bulk_load_config: store_config.bulk_load.clone(),
+            column_write_options: enum_map::enum_map! {
+                col => regular_column_write_options(store_config.col_overrides(col)),
+            },
+            async_io: features.io_uring,
+            bulk_load_counts: Mutex::new(Default::default()),
+            secondary: mode == Mode::Secondary,
+            commit_lock: CommitLock::default(),
+            _instance_tracker: counter,
+        })
+    }
+
+    /// Returns the directory of the files of the secondary instance of the
+    /// database at `path`, i.e. its info log, which is next to the database.
+    fn secondary_path(path: &Path) -> std::path::PathBuf {
+        let mut dir = path.as_os_str().to_owned();
+        dir.push("-secondary");
+        dir.into()
     }
 
     /// Opens the database with given column families configured.
@@ -129,10 +175,13 @@ impl RocksDB {
         mode: Mode,
         temp: Temperature,
         columns: &[DBCol],
+        features: IoFeatures,
     ) -> io::Result<(DB, Options)> {
         let options = rocksdb_options(store_config, mode);
-        let cfs = cf_descriptors(columns, store_config, temp);
-        let db = if mode.read_only() {
+        let cfs = cf_descriptors(columns, store_config, temp, features);
+        let db = if mode == Mode::Secondary {
+            DB::open_cf_descriptors_as_secondary(&options, path, &Self::secondary_path(path), cfs)
+        } else if mode.read_only() {
             DB::open_cf_descriptors_read_only(&options, path, cfs, false)
         } else {
             DB::open_cf_descriptors(&options, path, cfs)
@@ -234,6 +283,9 @@ impl RocksDB {
     ) -> RocksDBIterator<'a> {
         let cf_handle = self.cf_handle(col).unwrap();
         let mut read_options = rocksdb_read_options();
+        if self.async_io {
+            read_options.set_async_io(true);
+        }
         if prefix.is_some() && (lower_bound.is_some() || upper_bound.is_some()) {
             panic!("Cannot iterate both with prefix and lower/upper bounds at the same time.");
         }
@@ -252,17 +304,45 @@ impl RocksDB {
             read_options.set_iterate_upper_bound(upper_bound);
         }
         let iter = self.db.iterator_cf_opt(cf_handle, read_options, IteratorMode::Start);
-        RocksDBIterator(iter)
+        // Iterators can yield millions of entries, so resolve the metrics once
+        // rather than on every step.
+        let subsystem: &'static str = IoSubsystem::current().into();
+        let column: &'static str = col.into();
+        RocksDBIterator {
+            iter,
+            entries_read: metrics::DATABASE_OPS_BY_SUBSYSTEM
+                .with_label_values(&[subsystem, "iter"]),
+            bytes_read: metrics::DATABASE_BYTES_BY_SUBSYSTEM
+                .with_label_values(&[subsystem, "iter"]),
+            column_entries_read: metrics::DATABASE_OPS_BY_COLUMN
+                .with_label_values(&[column, "iter"]),
+            column_bytes_read: metrics::DATABASE_BYTES_BY_COLUMN
+                .with_label_values(&[column, "iter"]),
+        }
     }
 }
 
-struct RocksDBIterator<'a>(rocksdb::DBIteratorWithThreadMode<'a, DB>);
+struct RocksDBIterator<'a> {
+    iter: rocksdb::DBIteratorWithThreadMode<'a, DB>,
+    entries_read: IntCounter,
+    bytes_read: IntCounter,
+    column_entries_read: IntCounter,
+    column_bytes_read: IntCounter,
+}
 
 impl<'a> Iterator for RocksDBIterator<'a> {
     type Item = io::Result<(Box<[u8]>, Box<[u8]>)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(self.0.next()?.map_err(io::Error::other))
+        let item = self.iter.next()?.map_err(io::Error::other);
+        if let Ok((key, value)) = &item {
+            let bytes = (key.len() + value.len()) as u64;
+            self.entries_read.inc();
+            self.bytes_read.inc_by(bytes);
+            self.column_entries_read.inc();
+            self.column_bytes_read.inc_by(bytes);
+        }
+        Some(item)
     }
 }
 
@@ -298,6 +378,59 @@ impl RocksDB {
         Ok(())
     }
 
+    #[tracing::instrument(
+        target = "store::db::rocksdb",
+        level = "trace",
+        "RocksDB::write",
+        skip_all
+    )]
+    fn write_batch(
+        &self,
+        transaction: DBTransaction,
+        check: Option<&dyn Fn() -> io::Result<()>>,
+    ) -> io::Result<()> {
+        let subsystem: &'static str = IoSubsystem::current().into();
+        let num_ops = transaction.ops.len() as u64;
+        let num_bytes = transaction.ops.iter().map(DBOp::bytes).sum::<usize>() as u64;
+        // Transactions can hold millions of ops, so look up the metrics of
+        // each column once.
+        let mut by_column = enum_map::EnumMap::<DBCol, (u64, u64)>::default();
+        for op in &transaction.ops {
+            let (ops, bytes) = &mut by_column[op.col()];
+            *ops += 1;
+            *bytes += op.bytes() as u64;
+        }
+        let write_batch_start = std::time::Instant::now();
+        let batch = self.build_write_batch(transaction)?;
+        let elapsed = write_batch_start.elapsed();
+        if elapsed.as_secs_f32() > 0.15 {
+            tracing::warn!(
+                target = "store::db::rocksdb",
+                message = "making a write batch took a very long time, make smaller transactions!",
+                ?elapsed,
+                backtrace = %std::backtrace::Backtrace::force_capture()
+            );
+        }
+        let _timer = metrics::DATABASE_OP_LATENCY_BY_SUBSYSTEM
+            .with_label_values(&[subsystem, "write"])
+            .start_timer();
+        metrics::DATABASE_OPS_BY_SUBSYSTEM.with_label_values(&[subsystem, "write"]).inc_by(num_ops);
+        metrics::DATABASE_BYTES_BY_SUBSYSTEM
+            .with_label_values(&[subsystem, "write"])
+            .inc_by(num_bytes);
+        for (col, (ops, bytes)) in by_column {
+            if ops > 0 {
+                metrics::DATABASE_OPS_BY_COLUMN
+                    .with_label_values(&[col.into(), "write"])
+                    .inc_by(ops);
+                metrics::DATABASE_BYTES_BY_COLUMN
+                    .with_label_values(&[col.into(), "write"])
+                    .inc_by(bytes);
+            }
+        }
+        self.commit_lock.write(check, || self.db.write(batch).map_err(io::Error::other))
+    }
+
     #[tracing::instrument(
         target = "store::db::rocksdb",
         level = "trace",
@@ -346,8 +479,12 @@ impl RocksDB {
 
 impl Database for RocksDB {
     fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        let subsystem: &'static str = IoSubsystem::current().into();
         let timer =
             metrics::DATABASE_OP_LATENCY_HIST.with_label_values(&["get", col.into()]).start_timer();
+        let subsystem_timer = metrics::DATABASE_OP_LATENCY_BY_SUBSYSTEM
+            .with_label_values(&[subsystem, "get"])
+            .start_timer();
         let read_options = rocksdb_read_options();
         let result = self
             .db
@@ -355,9 +492,53 @@ impl Database for RocksDB {
             .map_err(io::Error::other)?
             .map(DBSlice::from_rocksdb_slice);
         timer.observe_duration();
+        subsystem_timer.observe_duration();
+        let bytes = (key.len() + result.as_deref().map_or(0, <[u8]>::len)) as u64;
+        metrics::DATABASE_OPS_BY_SUBSYSTEM.with_label_values(&[subsystem, "get"]).inc();
+        metrics::DATABASE_BYTES_BY_SUBSYSTEM.with_label_values(&[subsystem, "get"]).inc_by(bytes);
+        metrics::DATABASE_OPS_BY_COLUMN.with_label_values(&[col.into(), "get"]).inc();
+        metrics::DATABASE_BYTES_BY_COLUMN.with_label_values(&[col.into(), "get"]).inc_by(bytes);
         Ok(result)
     }
 
+    fn multi_get(&self, col: DBCol, keys: &[&[u8]]) -> io::Result<Vec<Option<DBSlice<'_>>>> {
+        let subsystem: &'static str = IoSubsystem::current().into();
+        let timer = metrics::DATABASE_OP_LATENCY_HIST
+            .with_label_values(&["multi_get", col.into()])
+            .start_timer();
+        let mut read_options = rocksdb_read_options();
+        // With io_uring, RocksDB reads the blocks of the keys from different
+        // files in parallel.
+        if self.async_io {
+            read_options.set_async_io(true);
+        }
+        let results = self
+            .db
+            .batched_multi_get_cf_opt(self.cf_handle(col)?, keys, false, &read_options)
+            .into_iter()
+            .map(|result| {
+                let value = result.map_err(io::Error::other)?.map(DBSlice::from_rocksdb_slice);
+                Ok(if col.is_rc() { value.and_then(DBSlice::strip_refcount) } else { value })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        timer.observe_duration();
+        let bytes = keys.iter().map(|key| key.len()).sum::<usize>()
+            + results.iter().map(|value| value.as_deref().map_or(0, <[u8]>::len)).sum::<usize>();
+        metrics::DATABASE_OPS_BY_SUBSYSTEM
+            .with_label_values(&[subsystem, "multi_get"])
+            .inc_by(keys.len() as u64);
+        metrics::DATABASE_BYTES_BY_SUBSYSTEM
+            .with_label_values(&[subsystem, "multi_get"])
+            .inc_by(bytes as u64);
+        metrics::DATABASE_OPS_BY_COLUMN
+            .with_label_values(&[col.into(), "multi_get"])
+            .inc_by(keys.len() as u64);
+        metrics::DATABASE_BYTES_BY_COLUMN
+            .with_label_values(&[col.into(), "multi_get"])
+            .inc_by(bytes as u64);
+        Ok(results)
+    }
+
     fn iter_raw_bytes(&self, col: DBCol) -> DBIterator {
         Box::new(self.iter_raw_bytes_internal(col, None, None, None))
     }
@@ -381,25 +562,16 @@ impl Database for RocksDB {
         refcount::iter_with_rc_logic(col, iter)
     }
 
-    #[tracing::instrument(
-        target = "store::db::rocksdb",
-        level = "trace",
-        "RocksDB::write",
-        skip_all
-    )]
     fn write(&self, transaction: DBTransaction) -> io::Result<()> {
-        let write_batch_start = std::time::Instant::now();
-        let batch = self.build_write_batch(transaction)?;
-        let elapsed = write_batch_start.elapsed();
-        if elapsed.as_secs_f32() > 0.15 {
-            tracing::warn!(
-                target = "store::db::rocksdb",
-                message = "making a write batch took a very long time, make smaller transactions!",
-                ?elapsed,
-                backtrace = %std::backtrace::Backtrace::force_capture()
-            );
-        }
-        self.db.write(batch).map_err(io::Error::other)
+        self.write_batch(transaction, None)
+    }
+
+    fn write_checked(
+        &self,
+        transaction: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.write_batch(transaction, Some(check))
     }
 
     #[tracing::instrument(
@@ -415,6 +587,35 @@ impl Database for RocksDB {
         Ok(())
     }
 
+    fn compact_range(
+        &self,
+        col: DBCol,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> io::Result<()> {
+        tracing::info!(target: "store::db::rocksdb", %col, ?start, ?end, "RocksDB::compact_range");
+        self.db.compact_range_cf(self.cf_handle(col)?, start, end);
+        Ok(())
+    }
+
+    fn compaction_debt(&self, col: DBCol) -> io::Result<Option<u64>> {
+        self.db
+            .property_int_value_cf(
+                self.cf_handle(col)?,
+                ::rocksdb::properties::ESTIMATE_PENDING_COMPACTION_BYTES,
+            )
+            .map_err(io::Error::other)
+    }
+
+    fn is_write_stalled(&self) -> io::Result<bool> {
+        use ::rocksdb::properties::{ACTUAL_DELAYED_WRITE_RATE, IS_WRITE_STOPPED};
+        let stopped = self.db.property_int_value(IS_WRITE_STOPPED).map_err(io::Error::other)?;
+        // The rate is only set while writes are being delayed.
+        let delayed_write_rate =
+            self.db.property_int_value(ACTUAL_DELAYED_WRITE_RATE).map_err(io::Error::other)?;
+        Ok(stopped.unwrap_or(0) != 0 || delayed_write_rate.unwrap_or(0) != 0)
+    }
+
     #[tracing::instrument(
         target = "store::db::rocksdb",
         level = "debug",
@@ -430,6 +631,48 @@ impl Database for RocksDB {
         Ok(())
     }
 
+    fn catch_up_with_primary(&self) -> io::Result<()> {
+        if self.secondary {
+            self.db.try_catch_up_with_primary().map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn set_bulk_load(&self, columns: &[DBCol], enabled: bool) -> io::Result<()> {
+        if !self.bulk_load_config.enabled {
+            return Ok(());
+        }
+        let mut counts = self.bulk_load_counts.lock().unwrap();
+        for &col in columns {
+            // Columns which the instance isn't setup for, e.g. in cold storage.
+            if self.cf_handles[col].is_none() {
+                continue;
+            }
+            let count = &mut counts[col];
+            if enabled {
+                *count += 1;
+                if *count > 1 {
+                    continue;
+                }
+            } else {
+                debug_assert!(*count > 0, "{col} left bulk load mode more often than entered");
+                *count = count.saturating_sub(1);
+                if *count > 0 {
+                    continue;
+                }
+            }
+            let options = if enabled {
+                bulk_load_column_options(&self.bulk_load_config)
+            } else {
+                self.column_write_options[col].clone()
+            };
+            let options = options.iter().map(|(key, value)| (*key, value.as_str())).collect_vec();
+            tracing::info!(target: "store::db::rocksdb", %col, enabled, "RocksDB::set_bulk_load");
+            self.db.set_options_cf(self.cf_handle(col)?, &options).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
     /// Trying to get
     /// 1. RocksDB statistics
     /// 2. Selected RockdDB properties for column families
@@ -468,8 +711,12 @@ impl Database for RocksDB {
             return Ok(());
         };
         let opts = common_rocksdb_options();
-        let cfs =
-            cf_descriptors(&DBCol::iter().collect_vec(), &StoreConfig::default(), Temperature::Hot);
+        let cfs = cf_descriptors(
+            &DBCol::iter().collect_vec(),
+            &StoreConfig::default(),
+            Temperature::Hot,
+            IoFeatures::default(),
+        );
         let mut db = DB::open_cf_descriptors(&opts, path, cfs)
             .with_context(|| format!("failed to open checkpoint at {}", path.display()))?;
         for col in DBCol::iter() {
@@ -500,6 +747,7 @@ fn cf_descriptors(
     columns: &[DBCol],
     store_config: &StoreConfig,
     temp: Temperature,
+    features: IoFeatures,
 ) -> Vec<rocksdb::ColumnFamilyDescriptor> {
     columns
         .iter()
@@ -507,7 +755,7 @@ fn cf_descriptors(
         .map(|col| {
             rocksdb::ColumnFamilyDescriptor::new(
                 col_name(col),
-                rocksdb_column_options(col, store_config, temp),
+                rocksdb_column_options(col, store_config, temp, features),
             )
         })
         .collect::<Vec<_>>()
@@ -544,6 +792,11 @@ fn rocksdb_options(store_config: &StoreConfig, mode: Mode) -> Options {
     opts.create_missing_column_families(mode.read_write());
     opts.create_if_missing(mode.can_create());
     opts.set_max_open_files(store_config.max_open_files.try_into().unwrap_or(i32::MAX));
+    if mode == Mode::Secondary {
+        // Secondary instances must keep all the files open to catch up with
+        // the primary, which may delete them in the meantime.
+        opts.set_max_open_files(-1);
+    }
     // TODO(mina86): Perhaps enable statistics even in read-only mode?
     if mode.read_write() && store_config.enable_statistics {
         // Rust API doesn't permit choosing stats level. The default stats level
@@ -575,6 +828,7 @@ fn use_block_cache_for_index_and_filter_blocks(db_col: DBCol) -> bool {
 
 fn rocksdb_block_based_options(store_config: &StoreConfig, db_col: DBCol) -> BlockBasedOptions {
     let cache_size = store_config.col_cache_size(db_col);
+    let overrides = store_config.col_overrides(db_col);
 
     let mut block_opts = BlockBasedOptions::default();
     block_opts.set_block_size(store_config.block_size.as_u64().try_into().unwrap());
@@ -587,12 +841,57 @@ fn rocksdb_block_based_options(store_config: &StoreConfig, db_col: DBCol) -> Blo
     } else {
         block_opts.set_cache_index_and_filter_blocks(false);
     }
-    block_opts.set_bloom_filter(10.0, true);
+    match overrides.and_then(|config| config.bloom_filter_bits_per_key) {
+        Some(0) => {}
+        Some(bits_per_key) => block_opts.set_bloom_filter(bits_per_key.into(), true),
+        None => block_opts.set_bloom_filter(10.0, true),
+    }
 
     block_opts
 }
 
-fn rocksdb_column_options(col: DBCol, store_config: &StoreConfig, temp: Temperature) -> Options {
+const COLUMN_MEMTABLE_MEMORY_BUDGET: usize = 128 * bytesize::MIB as usize;
+const COLUMN_WRITE_BUFFER_SIZE: usize = COLUMN_MEMTABLE_MEMORY_BUDGET / 4;
+const COLUMN_MAX_WRITE_BUFFER_NUMBER: i32 = 6;
+
+/// Mutable column options used during bulk loads.
+fn bulk_load_column_options(config: &BulkLoadConfig) -> [(&'static str, String); 3] {
+    [
+        ("write_buffer_size", config.write_buffer_size.as_u64().to_string()),
+        ("max_write_buffer_number", config.max_write_buffer_number.to_string()),
+        ("disable_auto_compactions", "true".to_string()),
+    ]
+}
+
+/// Regular values of the options changed by [`bulk_load_column_options`].
+fn regular_column_write_options(
+    overrides: Option<&RocksDbColumnConfig>,
+) -> [(&'static str, String); 3] {
+    let (write_buffer_size, max_write_buffer_number) = column_write_buffers(overrides);
+    [
+        ("write_buffer_size", write_buffer_size.to_string()),
+        ("max_write_buffer_number", max_write_buffer_number.to_string()),
+        ("disable_auto_compactions", "false".to_string()),
+    ]
+}
+
+/// Returns the size and the maximum number of memtables of the column.
+fn column_write_buffers(overrides: Option<&RocksDbColumnConfig>) -> (usize, i32) {
+    let write_buffer_size = overrides
+        .and_then(|config| config.write_buffer_size)
+        .map_or(COLUMN_WRITE_BUFFER_SIZE, |size| size.as_u64().try_into().unwrap());
+    let max_write_buffer_number = overrides
+        .and_then(|config| config.max_write_buffer_number)
+        .map_or(COLUMN_MAX_WRITE_BUFFER_NUMBER, |number| number.try_into().unwrap_or(i32::MAX));
+    (write_buffer_size, max_write_buffer_number)
+}
+
+fn rocksdb_column_options(
+    col: DBCol,
+    store_config: &StoreConfig,
+    temp: Temperature,
+    features: IoFeatures,
+) -> Options {
     let mut opts = Options::default();
     set_compression_options(&mut opts);
     opts.set_level_compaction_dynamic_level_bytes(true);
@@ -610,10 +909,25 @@ fn rocksdb_column_options(col: DBCol, store_config: &StoreConfig, temp: Temperat
     // the rest use LZ4 compression.
     // See the implementation here:
     //      https://github.com/facebook/rocksdb/blob/c18c4a081c74251798ad2a1abf83bad417518481/options/options.cc#L588.
-    let memtable_memory_budget = 128 * bytesize::MIB as usize;
-    opts.optimize_level_style_compaction(memtable_memory_budget);
+    opts.optimize_level_style_compaction(COLUMN_MEMTABLE_MEMORY_BUDGET);
+    // Same values as set above unless overridden, spelled out so that they
+    // can be restored after a bulk load.
+    let overrides = store_config.col_overrides(col);
+    let (write_buffer_size, max_write_buffer_number) = column_write_buffers(overrides);
+    opts.set_write_buffer_size(write_buffer_size);
+    opts.set_max_write_buffer_number(max_write_buffer_number);
+    if let Some(compression) = overrides.and_then(|config| config.compression) {
+        // Replaces the compression per level set above.
+        let compression = rocksdb_compression_type(compression);
+        opts.set_compression_per_level(&[]);
+        opts.set_compression_type(compression);
+        opts.set_bottommost_compression_type(compression);
+    }
 
     opts.set_target_file_size_base(64 * bytesize::MIB);
+    if features.huge_pages {
+        opts.set_memtable_huge_page_size(HUGE_PAGE_SIZE);
+    }
     if temp == Temperature::Hot && col.is_rc() {
         opts.set_merge_operator("refcount merge", RocksDB::refcount_merge, RocksDB::refcount_merge);
         opts.set_compaction_filter("empty value filter", RocksDB::empty_value_compaction_filter);
@@ -621,6 +935,15 @@ fn rocksdb_column_options(col: DBCol, store_config: &StoreConfig, temp: Temperat
     opts
 }
 
+fn rocksdb_compression_type(compression: RocksDbCompression) -> rocksdb::DBCompressionType {
+    match compression {
+        RocksDbCompression::None => rocksdb::DBCompressionType::None,
+        RocksDbCompression::Snappy => rocksdb::DBCompressionType::Snappy,
+        RocksDbCompression::Lz4 => rocksdb::DBCompressionType::Lz4,
+        RocksDbCompression::Zstd => rocksdb::DBCompressionType::Zstd,
+    }
+}
+
 fn set_compression_options(opts: &mut Options) {
     opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
     opts.set_bottommost_compression_type(rocksdb::DBCompressionType::Zstd);
@@ -662,7 +985,16 @@ impl RocksDB {
         // to read the version without modifying the database before we figure
         // out if there are any necessary migrations to perform.
         let cols = [DBCol::DbVersion];
-        let db = Self::open_with_columns(path, config, Mode::ReadOnly, Temperature::Hot, &cols)?;
+        // Reading the version doesn't need the I/O features of the host.
+        let features = IoFeatures::default();
+        let db = Self::open_with_columns(
+            path,
+            config,
+            Mode::ReadOnly,
+            Temperature::Hot,
+            &cols,
+            features,
+        )?;
         Some(metadata::DbMetadata::read(&db)).transpose()
     }
 
@@ -802,9 +1134,124 @@ mod tests {
     use crate::db::{Database, StatsValue};
     use crate::{DBCol, NodeStorage, StoreStatistics};
     use assert_matches::assert_matches;
+    use std::sync::Arc;
 
     use super::*;
 
+    #[test]
+    fn test_nested_bulk_loads() {
+        use crate::bulk_load::BulkLoadPhase;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = StoreConfig::test_config();
+        let rocksdb = Arc::new(
+            RocksDB::open(tmp_dir.path(), &config, Mode::ReadWrite, Temperature::Hot).unwrap(),
+        );
+        let store = crate::Store::new(rocksdb.clone());
+        let counts = || {
+            let counts = rocksdb.bulk_load_counts.lock().unwrap();
+            (counts[DBCol::State], counts[DBCol::FlatState], counts[DBCol::Block])
+        };
+
+        let state_sync = store.bulk_load(BulkLoadPhase::StateSync);
+        let flat_storage = store.bulk_load(BulkLoadPhase::FlatStorageCreation);
+        assert_eq!(counts(), (1, 2, 0));
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::FlatState, &[1], &[2]);
+        store_update.commit().unwrap();
+        drop(state_sync);
+        assert_eq!(counts(), (0, 1, 0));
+        drop(flat_storage);
+        assert_eq!(counts(), (0, 0, 0));
+        assert_eq!(store.get(DBCol::FlatState, &[1]).unwrap().as_deref(), Some(&[2][..]));
+
+        // Migrations leave the small columns alone.
+        let migration = store.bulk_load(BulkLoadPhase::Migration);
+        assert_eq!(counts(), (1, 1, 0));
+        drop(migration);
+        assert_eq!(counts(), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_rocksdb_overrides() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let mut config = StoreConfig::test_config();
+        config.rocksdb_overrides.insert(
+            "State".to_string(),
+            RocksDbColumnConfig {
+                block_cache_size: Some(bytesize::ByteSize::mib(1)),
+                compression: Some(RocksDbCompression::Zstd),
+                bloom_filter_bits_per_key: Some(0),
+                write_buffer_size: Some(bytesize::ByteSize::mib(8)),
+                max_write_buffer_number: None,
+            },
+        );
+        assert_eq!(config.col_cache_size(DBCol::State), bytesize::ByteSize::mib(1));
+        assert_eq!(config.col_cache_size(DBCol::FlatState), config.col_flat_state_cache_size);
+
+        let rocksdb =
+            RocksDB::open(tmp_dir.path(), &config, Mode::ReadWrite, Temperature::Hot).unwrap();
+        assert_eq!(
+            rocksdb.column_write_options[DBCol::State][0].1,
+            (8 * bytesize::MIB).to_string()
+        );
+        assert_eq!(
+            rocksdb.column_write_options[DBCol::State][1].1,
+            COLUMN_MAX_WRITE_BUFFER_NUMBER.to_string()
+        );
+        assert_eq!(
+            rocksdb.column_write_options[DBCol::Block][0].1,
+            COLUMN_WRITE_BUFFER_SIZE.to_string()
+        );
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::BlockMisc, b"key".to_vec(), b"value".to_vec());
+        rocksdb.write(transaction).unwrap();
+        assert_eq!(
+            rocksdb.get_raw_bytes(DBCol::BlockMisc, b"key").unwrap().as_deref(),
+            Some(&b"value"[..])
+        );
+    }
+
+    /// The I/O features end up in the RocksDB options, and the ones the host
+    /// doesn't support are left out without failing to open the database.
+    #[test]
+    fn test_io_features_options() {
+        let config = StoreConfig { io_uring: true, huge_pages: true, ..StoreConfig::test_config() };
+        let columns = DBCol::iter().collect_vec();
+        for supported in [false, true] {
+            let tmp_dir = tempfile::tempdir().unwrap();
+            let features = IoFeatures::new(&config, supported, supported);
+            let rocksdb = RocksDB::open_with_columns(
+                tmp_dir.path(),
+                &config,
+                Mode::ReadWrite,
+                Temperature::Hot,
+                &columns,
+                features,
+            )
+            .unwrap();
+            assert_eq!(rocksdb.async_io, supported);
+
+            // RocksDB saves the options of the column families in the latest
+            // OPTIONS file of the database.
+            let options_file = std::fs::read_dir(tmp_dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .filter(|name| name.starts_with("OPTIONS-"))
+                .max()
+                .unwrap();
+            let options = std::fs::read_to_string(tmp_dir.path().join(options_file)).unwrap();
+            let huge_page_size = if supported { HUGE_PAGE_SIZE } else { 0 };
+            assert!(options.contains(&format!("memtable_huge_page_size={huge_page_size}\n")));
+
+            let mut transaction = DBTransaction::new();
+            transaction.set(DBCol::BlockMisc, b"key".to_vec(), b"value".to_vec());
+            rocksdb.write(transaction).unwrap();
+            let values = rocksdb.iter(DBCol::BlockMisc).collect::<io::Result<Vec<_>>>().unwrap();
+            assert_eq!(values.len(), 1);
+        }
+    }
+
     #[test]
     fn rocksdb_merge_sanity() {
         let (_tmp_dir, opener) = NodeStorage::test_opener();
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use near_primitives::shard_layout::ShardUId;
+
+use crate::db::{
+    CommitLock, DBIterator, DBOp, DBSlice, DBTransaction, Database, SplitDB, StoreStatistics,
+};
+use crate::DBCol;
+
+/// Database keeping the rows of some shards in databases of their own, e.g.
+/// on other disks.
+///
+/// Only the rows of columns whose keys start with the `ShardUId`, i.e. the
+/// state and the flat storage of the shard, are kept in the database of the
+/// shard.  Everything else, including the rows of the other shards, is kept in
+/// the main database.
+///
+/// Writes are only atomic within each database.  The databases of the shards
+/// are written before the main one, so that after a crash the heads kept in
+/// the main database never point to state which wasn't written.
+pub struct ShardedDB {
+    main: Arc<dyn Database>,
+    /// Databases of the shards, by the bytes of their `ShardUId`.
+    shards: HashMap<[u8; 8], Arc<dyn Database>>,
+    /// Orders the writes with the checked writes, which span the databases.
+    commit_lock: CommitLock,
+}
+
+impl ShardedDB {
+    /// Fails if the main database holds state of any of the given shards,
+    /// which would be hidden by the database of the shard.
+    pub fn new(
+        main: Arc<dyn Database>,
+        shards: Vec<(ShardUId, Arc<dyn Database>)>,
+    ) -> io::Result<Arc<Self>> {
+        for (shard_uid, _) in &shards {
+            if main.iter_prefix(DBCol::State, &shard_uid.to_bytes()).next().is_some() {
+                let msg = format!(
+                    "state of shard {shard_uid} is in the main database; \
+                     to move it into a database of its own, sync the shard from scratch"
+                );
+                return Err(io::Error::other(msg));
+            }
+        }
+        let shards = shards.into_iter().map(|(shard_uid, db)| (shard_uid.to_bytes(), db)).collect();
+        Ok(Arc::new(Self { main, shards, commit_lock: CommitLock::default() }))
+    }
+
+    /// Returns the default path of the database of the shard, which is next
+    /// to the main database at `db_path`.
+    pub fn default_shard_db_path(db_path: &Path, shard_uid: ShardUId) -> PathBuf {
+        let mut dir = db_path.as_os_str().to_owned();
+        dir.push("-shards");
+        PathBuf::from(dir).join(shard_uid.to_string())
+    }
+
+    /// Returns the shards which have a database at the default path next to
+    /// the main database at `db_path`.
+    pub fn list_default_shard_dbs(db_path: &Path) -> io::Result<Vec<ShardUId>> {
+        let dir = Self::default_shard_db_path(db_path, ShardUId::single_shard());
+        let dir = dir.parent().unwrap();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+        let mut shard_uids = vec![];
+        for entry in entries {
+            if let Some(shard_uid) = entry?.file_name().to_str().and_then(|name| name.parse().ok())
+            {
+                shard_uids.push(shard_uid);
+            }
+        }
+        Ok(shard_uids)
+    }
+
+    /// Writes the operations on the state of the shards to their databases,
+    /// and the others to the main one.
+    fn write_split(&self, transaction: DBTransaction) -> io::Result<()> {
+        let mut main = DBTransaction::new();
+        let mut shards: HashMap<[u8; 8], DBTransaction> = HashMap::new();
+        for op in transaction.ops {
+            let shard = match &op {
+                DBOp::Set { col, key, .. }
+                | DBOp::Insert { col, key, .. }
+                | DBOp::UpdateRefcount { col, key, .. }
+                | DBOp::Delete { col, key } => self.shard_of(*col, key),
+                // The range may span several shards, deleting it where there
+                // is nothing to delete is cheap.
+                DBOp::DeleteAll { col } | DBOp::DeleteRange { col, .. } => {
+                    if col.is_keyed_by_shard_uid() {
+                        for shard in self.shards.keys() {
+                            shards.entry(*shard).or_default().ops.push(op.clone());
+                        }
+                    }
+                    None
+                }
+            };
+            match shard {
+                Some(shard) => shards.entry(shard).or_default().ops.push(op),
+                None => main.ops.push(op),
+            }
+        }
+        for (shard, transaction) in shards {
+            self.shards[&shard].write(transaction)?;
+        }
+        self.main.write(main)
+    }
+
+    /// Returns the shard whose database holds the row, if it isn't the main
+    /// database.
+    fn shard_of(&self, col: DBCol, key: &[u8]) -> Option<[u8; 8]> {
+        if !col.is_keyed_by_shard_uid() {
+            return None;
+        }
+        let shard: [u8; 8] = key.get(..8)?.try_into().unwrap();
+        self.shards.contains_key(&shard).then_some(shard)
+    }
+
+    fn db_of(&self, col: DBCol, key: &[u8]) -> &dyn Database {
+        match self.shard_of(col, key) {
+            Some(shard) => self.shards[&shard].as_ref(),
+            None => self.main.as_ref(),
+        }
+    }
+
+    /// Returns the databases which may hold rows of the column.
+    fn dbs_of(&self, col: DBCol) -> Vec<&dyn Database> {
+        let mut dbs = vec![self.main.as_ref()];
+        if col.is_keyed_by_shard_uid() {
+            dbs.extend(self.shards.values().map(|db| db.as_ref()));
+        }
+        dbs
+    }
+
+    fn all_dbs(&self) -> impl Iterator<Item = &dyn Database> {
+        std::iter::once(self.main.as_ref()).chain(self.shards.values().map(|db| db.as_ref()))
+    }
+
+    /// Merges the iterators over the databases which may hold rows of the
+    /// column.  The databases hold disjoint sets of keys.
+    fn merge_iters<'a>(
+        &'a self,
+        col: DBCol,
+        iter: impl Fn(&'a dyn Database) -> DBIterator<'a>,
+    ) -> DBIterator<'a> {
+        let mut dbs = self.dbs_of(col).into_iter();
+        let first = iter(dbs.next().unwrap());
+        dbs.fold(first, |merged, db| SplitDB::merge_iter(merged, iter(db)))
+    }
+}
+
+impl Database for ShardedDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        self.db_of(col, key).get_raw_bytes(col, key)
+    }
+
+    fn get_with_rc_stripped(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        self.db_of(col, key).get_with_rc_stripped(col, key)
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        self.merge_iters(col, |db| db.iter(col))
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        if key_prefix.len() >= 8 {
+            return self.db_of(col, key_prefix).iter_prefix(col, key_prefix);
+        }
+        self.merge_iters(col, |db| db.iter_prefix(col, key_prefix))
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        self.merge_iters(col, |db| db.iter_range(col, lower_bound, upper_bound))
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        self.merge_iters(col, |db| db.iter_raw_bytes(col))
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        self.commit_lock.write(None, || self.write_split(transaction))
+    }
+
+    fn write_checked(
+        &self,
+        transaction: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.commit_lock.write(Some(check), || self.write_split(transaction))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.all_dbs().try_for_each(|db| db.flush())
+    }
+
+    fn compact(&self) -> io::Result<()> {
+        self.all_dbs().try_for_each(|db| db.compact())
+    }
+
+    fn compact_range(
+        &self,
+        col: DBCol,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> io::Result<()> {
+        self.dbs_of(col).into_iter().try_for_each(|db| db.compact_range(col, start, end))
+    }
+
+    fn compaction_debt(&self, col: DBCol) -> io::Result<Option<u64>> {
+        let mut total = None;
+        for db in self.dbs_of(col) {
+            if let Some(debt) = db.compaction_debt(col)? {
+                total = Some(total.unwrap_or(0) + debt);
+            }
+        }
+        Ok(total)
+    }
+
+    fn is_write_stalled(&self) -> io::Result<bool> {
+        for db in self.all_dbs() {
+            if db.is_write_stalled()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Statistics of the main database only, the ones of the shards are
+    /// exported by their RocksDB instances but not merged in.
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        self.main.get_store_statistics()
+    }
+
+    /// The databases of the shards are checkpointed at their default paths
+    /// next to the checkpoint of the main database, where the opener finds
+    /// them.
+    fn create_checkpoint(
+        &self,
+        path: &Path,
+        columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        self.main.create_checkpoint(path, columns_to_keep)?;
+        for (shard, db) in &self.shards {
+            let shard_uid = ShardUId::try_from(&shard[..]).unwrap();
+            let shard_path = Self::default_shard_db_path(path, shard_uid);
+            std::fs::create_dir_all(shard_path.parent().unwrap())?;
+            db.create_checkpoint(&shard_path, columns_to_keep)?;
+        }
+        Ok(())
+    }
+
+    fn set_bulk_load(&self, columns: &[DBCol], enabled: bool) -> io::Result<()> {
+        self.all_dbs().try_for_each(|db| db.set_bulk_load(columns, enabled))
+    }
+
+    /// The databases of the shards catch up first, for the same reason they
+    /// are written first.
+    fn catch_up_with_primary(&self) -> io::Result<()> {
+        for db in self.shards.values() {
+            db.catch_up_with_primary()?;
+        }
+        self.main.catch_up_with_primary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShardedDB;
+    use crate::db::{DBTransaction, Database, TestDB};
+    use crate::DBCol;
+    use near_primitives::shard_layout::ShardUId;
+    use std::sync::Arc;
+
+    fn key(shard_uid: ShardUId, suffix: &[u8]) -> Vec<u8> {
+        [&shard_uid.to_bytes()[..], suffix].concat()
+    }
+
+    #[test]
+    fn test_sharded_db_routes_shard_rows() {
+        let shard0 = ShardUId { version: 3, shard_id: 0 };
+        let shard1 = ShardUId { version: 3, shard_id: 1 };
+        let main = TestDB::new();
+        let shard1_db = TestDB::new();
+        let db =
+            ShardedDB::new(main.clone(), vec![(shard1, shard1_db.clone() as Arc<dyn Database>)])
+                .unwrap();
+
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::FlatState, key(shard0, b"a"), b"0".to_vec());
+        transaction.set(DBCol::FlatState, key(shard1, b"a"), b"1".to_vec());
+        transaction.set(DBCol::BlockMisc, key(shard1, b"a"), b"misc".to_vec());
+        db.write(transaction).unwrap();
+
+        // Rows of the shard keyed by its ShardUId are in its database.
+        let get = |db: &dyn Database, col, key: &[u8]| {
+            db.get_raw_bytes(col, key).unwrap().map(|value| value.to_vec())
+        };
+        assert_eq!(get(&*shard1_db, DBCol::FlatState, &key(shard1, b"a")), Some(b"1".to_vec()));
+        assert_eq!(get(&*main, DBCol::FlatState, &key(shard1, b"a")), None);
+        assert_eq!(get(&*main, DBCol::FlatState, &key(shard0, b"a")), Some(b"0".to_vec()));
+        assert_eq!(get(&*main, DBCol::BlockMisc, &key(shard1, b"a")), Some(b"misc".to_vec()));
+        assert_eq!(get(&*db, DBCol::FlatState, &key(shard1, b"a")), Some(b"1".to_vec()));
+
+        // Iterators see the rows of all databases, in order.
+        let keys: Vec<_> = db.iter(DBCol::FlatState).map(|item| item.unwrap().0.to_vec()).collect();
+        assert_eq!(keys, vec![key(shard0, b"a"), key(shard1, b"a")]);
+        assert_eq!(db.iter_prefix(DBCol::FlatState, &shard1.to_bytes()).count(), 1);
+
+        let mut transaction = DBTransaction::new();
+        transaction.delete_all(DBCol::FlatState);
+        db.write(transaction).unwrap();
+        assert_eq!(db.iter(DBCol::FlatState).count(), 0);
+    }
+
+    #[test]
+    fn test_sharded_db_refuses_state_in_main_db() {
+        let shard_uid = ShardUId::single_shard();
+        let main = TestDB::new();
+        let mut transaction = DBTransaction::new();
+        transaction.update_refcount(
+            DBCol::State,
+            key(shard_uid, &[0; 32]),
+            vec![1, 1, 0, 0, 0, 0, 0, 0, 0],
+        );
+        main.write(transaction).unwrap();
+        let shard_db: Arc<dyn Database> = TestDB::new();
+        assert!(ShardedDB::new(main, vec![(shard_uid, shard_db)]).is_err());
+    }
+}
@@ -177,6 +177,14 @@ impl Database for SplitDB {
         self.hot.write(batch)
     }
 
+    fn write_checked(
+        &self,
+        batch: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.hot.write_checked(batch, check)
+    }
+
     fn flush(&self) -> io::Result<()> {
         let msg = "flush is not allowed - the split storage is read only.";
         log_assert_fail!("{}", msg);
@@ -3,7 +3,7 @@ use std::io;
 use std::ops::Bound;
 use std::sync::{Arc, RwLock};
 
-use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database};
+use crate::db::{refcount, CommitLock, DBIterator, DBOp, DBSlice, DBTransaction, Database};
 use crate::{DBCol, StoreStatistics};
 
 /// An in-memory database intended for tests and IO-agnostic estimations.
@@ -18,6 +18,9 @@ pub struct TestDB {
     // The TestDB doesn't produce any stats on its own, it's up to the user of
     // this class to set the stats as they need it.
     stats: RwLock<Option<StoreStatistics>>,
+
+    /// Orders the writes with the checked writes.
+    commit_lock: CommitLock,
 }
 
 impl TestDB {
@@ -30,6 +33,47 @@ impl TestDB {
     pub fn set_store_statistics(&self, stats: StoreStatistics) {
         *self.stats.write().unwrap() = Some(stats);
     }
+
+    fn apply(&self, transaction: DBTransaction) -> io::Result<()> {
+        let mut db = self.db.write().unwrap();
+        for op in transaction.ops {
+            match op {
+                DBOp::Set { col, key, value } => {
+                    db[col].insert(key, value);
+                }
+                DBOp::Insert { col, key, value } => {
+                    if cfg!(debug_assertions) {
+                        if let Some(old_value) = db[col].get(&key) {
+                            super::assert_no_overwrite(col, &key, &value, &*old_value)
+                        }
+                    }
+                    db[col].insert(key, value);
+                }
+                DBOp::UpdateRefcount { col, key, value } => {
+                    let existing = db[col].get(&key).map(Vec::as_slice);
+                    let operands = [value.as_slice()];
+                    let merged = refcount::refcount_merge(existing, operands);
+                    if merged.is_empty() {
+                        db[col].remove(&key);
+                    } else {
+                        debug_assert!(
+                            refcount::decode_value_with_rc(&merged).1 > 0,
+                            "Inserting value with non-positive refcount"
+                        );
+                        db[col].insert(key, merged);
+                    }
+                }
+                DBOp::Delete { col, key } => {
+                    db[col].remove(&key);
+                }
+                DBOp::DeleteAll { col } => db[col].clear(),
+                DBOp::DeleteRange { col, from, to } => {
+                    db[col].retain(|key, _| !(&from..&to).contains(&key));
+                }
+            };
+        }
+        Ok(())
+    }
 }
 
 impl Database for TestDB {
@@ -76,44 +120,15 @@ impl Database for TestDB {
     }
 
     fn write(&self, transaction: DBTransaction) -> io::Result<()> {
-        let mut db = self.db.write().unwrap();
-        for op in transaction.ops {
-            match op {
-                DBOp::Set { col, key, value } => {
-                    db[col].insert(key, value);
-                }
-                DBOp::Insert { col, key, value } => {
-                    if cfg!(debug_assertions) {
-                        if let Some(old_value) = db[col].get(&key) {
-                            super::assert_no_overwrite(col, &key, &value, &*old_value)
-                        }
-                    }
-                    db[col].insert(key, value);
-                }
-                DBOp::UpdateRefcount { col, key, value } => {
-                    let existing = db[col].get(&key).map(Vec::as_slice);
-                    let operands = [value.as_slice()];
-                    let merged = refcount::refcount_merge(existing, operands);
-                    if merged.is_empty() {
-                        db[col].remove(&key);
-                    } else {
-                        debug_assert!(
-                            refcount::decode_value_with_rc(&merged).1 > 0,
-                            "Inserting value with non-positive refcount"
-                        );
-                        db[col].insert(key, merged);
-                    }
-                }
-                DBOp::Delete { col, key } => {
-                    db[col].remove(&key);
-                }
-                DBOp::DeleteAll { col } => db[col].clear(),
-                DBOp::DeleteRange { col, from, to } => {
-                    db[col].retain(|key, _| !(&from..&to).contains(&key));
-                }
-            };
-        }
-        Ok(())
+        self.commit_lock.write(None, || self.apply(transaction))
+    }
+
+    fn write_checked(
+        &self,
+        transaction: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.commit_lock.write(Some(check), || self.apply(transaction))
     }
 
     fn flush(&self) -> io::Result<()> {
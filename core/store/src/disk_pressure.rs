@@ -0,0 +1,206 @@
+//! Graceful degradation of the node as the disks of its databases fill up.
+//!
+//! RocksDB doesn't cope with a full disk: a failed flush or compaction can
+//! leave the database in a state which needs a manual repair.  Instead of
+//! running until that happens, the node checks the free space periodically
+//! and, as it drops below the thresholds in [`DiskPressureConfig`]:
+//!
+//! 1. stops writing data it can do without, see
+//!    [`non_essential_writes_allowed`],
+//! 2. keeps logging errors so that operators notice,
+//! 3. shuts down while RocksDB still has room to flush.
+
+use crate::config::DiskPressureConfig;
+use crate::metrics;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the monitor checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, strum::IntoStaticStr)]
+#[repr(u8)]
+pub enum DiskPressure {
+    Normal = 0,
+    /// Non-essential writes are disabled.
+    ReducedWrites = 1,
+    /// Non-essential writes are disabled and errors are logged.
+    Critical = 2,
+    /// The node is shutting down.
+    Halt = 3,
+}
+
+impl DiskPressure {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Normal,
+            1 => Self::ReducedWrites,
+            2 => Self::Critical,
+            _ => Self::Halt,
+        }
+    }
+}
+
+impl DiskPressureConfig {
+    /// Returns the pressure for the given free space.
+    pub fn pressure(&self, available: u64) -> DiskPressure {
+        if available < self.halt_below.as_u64() {
+            DiskPressure::Halt
+        } else if available < self.alert_below.as_u64() {
+            DiskPressure::Critical
+        } else if available < self.reduce_writes_below.as_u64() {
+            DiskPressure::ReducedWrites
+        } else {
+            DiskPressure::Normal
+        }
+    }
+}
+
+/// Pressure of the process, updated by the monitor.
+static DISK_PRESSURE: AtomicU8 = AtomicU8::new(DiskPressure::Normal as u8);
+
+pub fn disk_pressure() -> DiskPressure {
+    DiskPressure::from_u8(DISK_PRESSURE.load(Ordering::Relaxed))
+}
+
+/// Whether the node should write data which it doesn't need to operate,
+/// e.g. data kept for debugging or served to other nodes as a courtesy.
+pub fn non_essential_writes_allowed() -> bool {
+    disk_pressure() == DiskPressure::Normal
+}
+
+fn set_disk_pressure(pressure: DiskPressure) {
+    DISK_PRESSURE.store(pressure as u8, Ordering::Relaxed);
+    metrics::DISK_PRESSURE_LEVEL.set(pressure as i64);
+}
+
+/// Returns the space available to unprivileged users on the filesystem of
+/// the path.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: statvfs only writes into `stat`.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "free disk space can't be checked"))
+}
+
+pub struct DiskPressureMonitorHandle {
+    handle: JoinHandle<()>,
+    keep_running: Arc<AtomicBool>,
+}
+
+impl DiskPressureMonitorHandle {
+    /// Starts monitoring the disks of the databases, given as pairs of the
+    /// database name and its path.  `halt` is called once the free space of
+    /// any of them drops below `halt_below` and should shut the node down.
+    pub fn start(
+        config: DiskPressureConfig,
+        databases: Vec<(String, PathBuf)>,
+        halt: impl FnOnce() + Send + 'static,
+    ) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let keep_running = Arc::new(AtomicBool::new(true));
+        let keep_running_clone = keep_running.clone();
+        let handle = std::thread::spawn(move || {
+            let mut halt = Some(halt);
+            while keep_running.load(Ordering::Relaxed) {
+                let pressure = check_disks(&config, &databases);
+                if pressure == DiskPressure::Halt {
+                    if let Some(halt) = halt.take() {
+                        tracing::error!(target: "store", "Shutting down the node before the disk runs out of space");
+                        halt();
+                    }
+                }
+                let next_run = Instant::now() + config.check_period;
+                while keep_running.load(Ordering::Relaxed) && Instant::now() < next_run {
+                    std::thread::sleep(STOP_POLL_INTERVAL.min(config.check_period));
+                }
+            }
+        });
+        Some(Self { handle, keep_running: keep_running_clone })
+    }
+
+    pub fn stop(self) {
+        self.keep_running.store(false, Ordering::Relaxed);
+        self.handle.join().expect("join should not fail here");
+    }
+}
+
+/// Updates the pressure of the process from the free space of the disks and
+/// returns it.  The pressure never goes back from `Halt`.
+fn check_disks(config: &DiskPressureConfig, databases: &[(String, PathBuf)]) -> DiskPressure {
+    let mut pressure = DiskPressure::Normal;
+    for (database, path) in databases {
+        let available = match available_space(path) {
+            Ok(available) => available,
+            Err(err) => {
+                tracing::warn!(target: "store", %database, ?path, ?err, "Failed to check free disk space");
+                continue;
+            }
+        };
+        metrics::DISK_AVAILABLE_BYTES.with_label_values(&[database.as_str()]).set(available as i64);
+        let database_pressure = config.pressure(available);
+        if database_pressure >= DiskPressure::Critical {
+            tracing::error!(
+                target: "store",
+                %database,
+                ?path,
+                available = %bytesize::ByteSize(available),
+                "Disk is running out of space, free up space or the node will shut down below {}",
+                config.halt_below,
+            );
+        }
+        pressure = pressure.max(database_pressure);
+    }
+
+    let previous = disk_pressure();
+    let pressure = if previous == DiskPressure::Halt { previous } else { pressure };
+    if pressure != previous {
+        let pressure_str: &'static str = pressure.into();
+        if pressure > previous {
+            tracing::warn!(target: "store", pressure = pressure_str, "Disk pressure increased");
+        } else {
+            tracing::info!(target: "store", pressure = pressure_str, "Disk pressure decreased");
+        }
+        set_disk_pressure(pressure);
+    }
+    pressure
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiskPressure;
+    use crate::config::DiskPressureConfig;
+    use bytesize::ByteSize;
+
+    #[test]
+    fn test_disk_pressure_thresholds() {
+        let config = DiskPressureConfig {
+            reduce_writes_below: ByteSize::gib(3),
+            alert_below: ByteSize::gib(2),
+            halt_below: ByteSize::gib(1),
+            ..Default::default()
+        };
+        assert_eq!(config.pressure(ByteSize::gib(4).as_u64()), DiskPressure::Normal);
+        assert_eq!(config.pressure(ByteSize::gib(3).as_u64()), DiskPressure::Normal);
+        assert_eq!(config.pressure(ByteSize::gib(3).as_u64() - 1), DiskPressure::ReducedWrites);
+        assert_eq!(config.pressure(ByteSize::mib(1500).as_u64()), DiskPressure::Critical);
+        assert_eq!(config.pressure(0), DiskPressure::Halt);
+    }
+}
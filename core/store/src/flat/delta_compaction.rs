@@ -0,0 +1,46 @@
+//! Background job keeping the flat storage deltas within the limits set in
+//! `flat_storage_deltas` of the store config, see [`FlatStorageDeltasConfig`].
+
+use super::FlatStorageManager;
+use crate::config::FlatStorageDeltasConfig;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the job checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct FlatStorageDeltasCompactionHandle {
+    handle: JoinHandle<()>,
+    keep_running: Arc<AtomicBool>,
+}
+
+impl FlatStorageDeltasCompactionHandle {
+    /// Starts the job, unless the config doesn't limit the deltas.
+    pub fn start(
+        flat_storage_manager: FlatStorageManager,
+        config: FlatStorageDeltasConfig,
+    ) -> Option<Self> {
+        if !config.compaction_enabled() {
+            return None;
+        }
+        let keep_running = Arc::new(AtomicBool::new(true));
+        let keep_running_clone = keep_running.clone();
+        let handle = std::thread::spawn(move || {
+            while keep_running.load(Ordering::Relaxed) {
+                flat_storage_manager.compact_deltas(&config);
+                let next_run = Instant::now() + config.compaction_period;
+                while keep_running.load(Ordering::Relaxed) && Instant::now() < next_run {
+                    std::thread::sleep(STOP_POLL_INTERVAL.min(config.compaction_period));
+                }
+            }
+        });
+        Some(Self { handle, keep_running: keep_running_clone })
+    }
+
+    pub fn stop(self) {
+        self.keep_running.store(false, Ordering::Relaxed);
+        self.handle.join().expect("join should not fail here");
+    }
+}
@@ -0,0 +1,192 @@
+//! Bloom filter over the keys in the flat state of a shard.
+//!
+//! Many lookups are for keys which don't exist, e.g. checking whether the
+//! receiver of a transfer to an implicit account exists.  Those are answered
+//! by the deltas only if the key was deleted recently, otherwise they go to
+//! RocksDB, which has to check its bloom filters of every level and often
+//! read a block anyway.  The filter answers most of them from memory.
+//!
+//! The filter is built by scanning the flat state in the background and
+//! isn't consulted until the scan is done.  Keys which the flat head moves
+//! into the flat state are added right away, so the filter doesn't miss the
+//! keys written during the scan.  Deleted keys can't be removed from a bloom
+//! filter, they stay false positives until the filter is built again on the
+//! next start of the node.
+
+use crate::config::FlatStorageKeyFilterConfig;
+use crate::flat::store_helper;
+use crate::Store;
+use near_primitives::shard_layout::ShardUId;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use super::metrics::FlatStorageMetrics;
+use super::POISONED_LOCK_ERR;
+
+/// Fixed size bloom filter whose bits can be set concurrently.
+struct BloomFilter {
+    bits: Box<[AtomicU64]>,
+    num_hashes: u32,
+    capacity: u64,
+    len: AtomicU64,
+}
+
+impl BloomFilter {
+    fn new(capacity: u64, bits_per_key: u32) -> Self {
+        let num_bits = capacity.max(1) * u64::from(bits_per_key.max(1));
+        let bits = (0..num_bits.div_ceil(64)).map(|_| AtomicU64::new(0)).collect();
+        // k = ln(2) * m / n minimises the rate of false positives.
+        let num_hashes =
+            ((f64::from(bits_per_key) * std::f64::consts::LN_2).round() as u32).clamp(1, 30);
+        Self { bits, num_hashes, capacity, len: AtomicU64::new(0) }
+    }
+
+    /// Bit positions of a key, with double hashing.
+    fn positions(&self, key_hash: u64) -> impl Iterator<Item = u64> + '_ {
+        let num_bits = self.bits.len() as u64 * 64;
+        let (h1, h2) = (key_hash & 0xffff_ffff, (key_hash >> 32) | 1);
+        (0..u64::from(self.num_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    /// Adds a key, returns whether the filter reached its capacity.
+    fn insert(&self, key_hash: u64) -> bool {
+        for position in self.positions(key_hash) {
+            self.bits[(position / 64) as usize].fetch_or(1 << (position % 64), Ordering::Relaxed);
+        }
+        self.len.fetch_add(1, Ordering::Relaxed) + 1 >= self.capacity
+    }
+
+    fn may_contain(&self, key_hash: u64) -> bool {
+        self.positions(key_hash).all(|position| {
+            self.bits[(position / 64) as usize].load(Ordering::Relaxed) & (1 << (position % 64))
+                != 0
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.bits.len() * std::mem::size_of::<u64>()
+    }
+}
+
+/// Answers whether a key may be in the flat state of a shard.  Once the
+/// filter is full, another one twice as big is added, so it doesn't need to
+/// know the number of keys upfront.
+pub struct FlatStateKeyFilter {
+    bits_per_key: u32,
+    filters: RwLock<Vec<BloomFilter>>,
+    /// Whether the scan of the flat state is done.
+    ready: AtomicBool,
+}
+
+impl FlatStateKeyFilter {
+    pub(crate) fn new(config: &FlatStorageKeyFilterConfig) -> Self {
+        let filter = BloomFilter::new(config.initial_capacity, config.bits_per_key);
+        Self {
+            bits_per_key: config.bits_per_key,
+            filters: RwLock::new(vec![filter]),
+            ready: AtomicBool::new(false),
+        }
+    }
+
+    fn hash_key(key: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub(crate) fn insert(&self, key: &[u8]) {
+        let key_hash = Self::hash_key(key);
+        let full = {
+            let filters = self.filters.read().expect(POISONED_LOCK_ERR);
+            filters.last().expect("there is always a filter").insert(key_hash)
+        };
+        if full {
+            let mut filters = self.filters.write().expect(POISONED_LOCK_ERR);
+            let last = filters.last().expect("there is always a filter");
+            // Another insert may have grown the filter already.
+            if last.len.load(Ordering::Relaxed) >= last.capacity {
+                let capacity = last.capacity * 2;
+                filters.push(BloomFilter::new(capacity, self.bits_per_key));
+            }
+        }
+    }
+
+    /// Returns false if the key is certainly not in the flat state.  Always
+    /// true until the filter is built.
+    pub(crate) fn may_contain(&self, key: &[u8]) -> bool {
+        if !self.ready.load(Ordering::Acquire) {
+            return true;
+        }
+        let key_hash = Self::hash_key(key);
+        let filters = self.filters.read().expect(POISONED_LOCK_ERR);
+        filters.iter().any(|filter| filter.may_contain(key_hash))
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.filters.read().expect(POISONED_LOCK_ERR).iter().map(BloomFilter::size).sum()
+    }
+
+    /// Adds the keys in the flat state of the shard in a background thread
+    /// and makes the filter ready once they are all added.
+    pub(crate) fn build(
+        self: &Arc<Self>,
+        store: Store,
+        shard_uid: ShardUId,
+        metrics: FlatStorageMetrics,
+    ) {
+        let filter = self.clone();
+        let spawn_result = std::thread::Builder::new()
+            .name(format!("flat-key-filter-{shard_uid}"))
+            .spawn(move || {
+                let mut num_keys = 0u64;
+                for entry in store_helper::iter_flat_state_entries(shard_uid, &store, None, None) {
+                    match entry {
+                        Ok((key, _)) => filter.insert(&key),
+                        Err(err) => {
+                            tracing::warn!(target: "store", %shard_uid, ?err, "Failed to build flat state key filter");
+                            return;
+                        }
+                    }
+                    num_keys += 1;
+                }
+                filter.ready.store(true, Ordering::Release);
+                metrics.set_key_filter_size(filter.size());
+                tracing::info!(target: "store", %shard_uid, num_keys, size = filter.size(), "Built flat state key filter");
+            });
+        if let Err(err) = spawn_result {
+            tracing::warn!(target: "store", %shard_uid, ?err, "Failed to start building flat state key filter");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlatStateKeyFilter;
+    use crate::config::FlatStorageKeyFilterConfig;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn test_key_filter() {
+        let filter = FlatStateKeyFilter::new(&FlatStorageKeyFilterConfig {
+            enabled: true,
+            bits_per_key: 10,
+            initial_capacity: 100,
+        });
+        for i in 0u32..1000 {
+            filter.insert(&i.to_le_bytes());
+        }
+        // Not ready yet, everything may be there.
+        assert!(filter.may_contain(b"missing"));
+        filter.ready.store(true, Ordering::Release);
+
+        // The filter grew, and kept all the keys.
+        assert!(filter.filters.read().unwrap().len() > 1);
+        for i in 0u32..1000 {
+            assert!(filter.may_contain(&i.to_le_bytes()));
+        }
+        let false_positives =
+            (1000u32..11000).filter(|i| filter.may_contain(&i.to_le_bytes())).count();
+        assert!(false_positives < 500, "{false_positives}");
+    }
+}
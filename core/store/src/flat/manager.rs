@@ -1,3 +1,4 @@
+use crate::config::{FlatStorageDeltasConfig, FlatStorageKeyFilterConfig};
 use crate::flat::{
     store_helper, BlockInfo, FlatStorageReadyStatus, FlatStorageStatus, POISONED_LOCK_ERR,
 };
@@ -6,6 +7,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::{BlockHeight, RawStateChangesWithTrieKey};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tracing::debug;
 
@@ -33,11 +35,32 @@ pub struct FlatStorageManagerInner {
     /// this epoch can share the same `head` and `tail`, similar for shards for the next epoch,
     /// but such overhead is negligible comparing the delta sizes, so we think it's ok.
     flat_storages: Mutex<HashMap<ShardUId, FlatStorage>>,
+    /// Key filters are built for the flat storages created, if enabled.
+    key_filter_config: FlatStorageKeyFilterConfig,
+    /// Directory of the memory mapped copies of the flat state, if enabled.
+    mmap_dir: Option<PathBuf>,
 }
 
 impl FlatStorageManager {
     pub fn new(store: Store) -> Self {
-        Self(Arc::new(FlatStorageManagerInner { store, flat_storages: Default::default() }))
+        Self::with_config(store, Default::default(), None)
+    }
+
+    pub fn with_config(
+        store: Store,
+        key_filter_config: FlatStorageKeyFilterConfig,
+        mmap_dir: Option<PathBuf>,
+    ) -> Self {
+        Self(Arc::new(FlatStorageManagerInner {
+            store,
+            flat_storages: Default::default(),
+            key_filter_config,
+            mmap_dir,
+        }))
+    }
+
+    pub(crate) fn store(&self) -> &Store {
+        &self.0.store
     }
 
     /// When a node starts from an empty database, this function must be called to ensure
@@ -72,6 +95,12 @@ impl FlatStorageManager {
         tracing::debug!(target: "store", ?shard_uid, "Creating flat storage for shard");
         let mut flat_storages = self.0.flat_storages.lock().expect(POISONED_LOCK_ERR);
         let flat_storage = FlatStorage::new(self.0.store.clone(), shard_uid)?;
+        if self.0.key_filter_config.enabled {
+            flat_storage.enable_key_filter(&self.0.key_filter_config);
+        }
+        if let Some(mmap_dir) = &self.0.mmap_dir {
+            flat_storage.enable_mmap(mmap_dir);
+        }
         let original_value = flat_storages.insert(shard_uid, flat_storage);
         if original_value.is_some() {
             // Generally speaking this shouldn't happen. It may only happen when
@@ -215,6 +244,20 @@ impl FlatStorageManager {
         flat_storages.get(&shard_uid).cloned()
     }
 
+    /// Compacts the deltas of every shard which exceed the limits of `config`,
+    /// see [`FlatStorage::compact_deltas`].
+    pub fn compact_deltas(&self, config: &FlatStorageDeltasConfig) {
+        let flat_storages: Vec<_> = {
+            let flat_storages = self.0.flat_storages.lock().expect(POISONED_LOCK_ERR);
+            flat_storages.iter().map(|(shard_uid, storage)| (*shard_uid, storage.clone())).collect()
+        };
+        for (shard_uid, flat_storage) in flat_storages {
+            if let Err(err) = flat_storage.compact_deltas(config) {
+                debug!(target: "store", ?shard_uid, ?err, "Cannot compact flat storage deltas");
+            }
+        }
+    }
+
     /// Removes FlatStorage object from FlatStorageManager.
     /// If FlatStorageManager did have that object, then removes all information about Flat State and returns Ok(true).
     /// Otherwise does nothing and returns Ok(false).
@@ -4,6 +4,7 @@ use near_primitives::{shard_layout::ShardUId, types::BlockHeight};
 
 use super::FlatStorageStatus;
 
+#[derive(Clone)]
 pub(crate) struct FlatStorageMetrics {
     flat_head_height: IntGauge,
     distance_to_head: IntGauge,
@@ -11,6 +12,11 @@ pub(crate) struct FlatStorageMetrics {
     cached_deltas: IntGauge,
     cached_changes_num_items: IntGauge,
     cached_changes_size: IntGauge,
+    compacted_deltas: IntCounter,
+    key_filter_skipped_reads: IntCounter,
+    key_filter_size: IntGauge,
+    mmap_reads: IntCounter,
+    mmap_size: IntGauge,
 }
 
 impl FlatStorageMetrics {
@@ -29,9 +35,39 @@ impl FlatStorageMetrics {
                 .with_label_values(&[&shard_uid_label]),
             cached_changes_size: flat_state_metrics::FLAT_STORAGE_CACHED_CHANGES_SIZE
                 .with_label_values(&[&shard_uid_label]),
+            compacted_deltas: flat_state_metrics::FLAT_STORAGE_COMPACTED_DELTAS
+                .with_label_values(&[&shard_uid_label]),
+            key_filter_skipped_reads: flat_state_metrics::FLAT_STORAGE_KEY_FILTER_SKIPPED_READS
+                .with_label_values(&[&shard_uid_label]),
+            key_filter_size: flat_state_metrics::FLAT_STORAGE_KEY_FILTER_SIZE
+                .with_label_values(&[&shard_uid_label]),
+            mmap_reads: flat_state_metrics::FLAT_STORAGE_MMAP_READS
+                .with_label_values(&[&shard_uid_label]),
+            mmap_size: flat_state_metrics::FLAT_STORAGE_MMAP_SIZE
+                .with_label_values(&[&shard_uid_label]),
         }
     }
 
+    pub(crate) fn inc_key_filter_skipped_reads(&self) {
+        self.key_filter_skipped_reads.inc();
+    }
+
+    pub(crate) fn set_key_filter_size(&self, size: usize) {
+        self.key_filter_size.set(size as i64);
+    }
+
+    pub(crate) fn inc_mmap_reads(&self) {
+        self.mmap_reads.inc();
+    }
+
+    pub(crate) fn set_mmap_size(&self, size: u64) {
+        self.mmap_size.set(size as i64);
+    }
+
+    pub(crate) fn inc_compacted_deltas(&self, num_deltas: usize) {
+        self.compacted_deltas.inc_by(num_deltas as u64);
+    }
+
     pub(crate) fn set_distance_to_head(&self, distance: usize, height: Option<BlockHeight>) {
         self.distance_to_head.set(height.unwrap_or(0) as i64);
         self.hops_to_head.set(distance as i64);
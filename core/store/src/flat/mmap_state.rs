@@ -0,0 +1,505 @@
+//! Flat state of a shard in an append-only file, read through a memory map.
+//!
+//! Reading the flat state from RocksDB goes through the block cache, block
+//! decompression and the bloom filters of every level, which is most of the
+//! cost of a view call on a node serving RPC traffic.  With
+//! `store.flat_state_mmap`, the flat state at the flat head is also kept in a
+//! file per shard and the position of every key in it is indexed in memory,
+//! so that a lookup is a hash map lookup and a read from the page cache.
+//! RocksDB stays the source of truth, the file is only a copy for reading.
+//!
+//! The file is a header followed by records: an entry sets or deletes a key,
+//! a commit marks the flat head which the entries before it add up to.
+//! Moving the flat head appends the changes of the blocks and a commit, so
+//! the file only grows.  Entries after the last commit, left by a crash, are
+//! dropped when the file is opened.  A file committed at another flat head
+//! than the one of the flat storage, or mostly made of overwritten entries,
+//! is built again.
+//!
+//! Building scans the flat state in the background.  Until it is done, reads
+//! go to RocksDB and the changes of flat head moves are queued, to be
+//! appended after the scan: they overwrite whatever the scan read of the keys
+//! they change, so the file ends up at the flat head regardless.
+
+use crate::flat::{store_helper, BlockInfo, FlatStateChanges};
+use crate::Store;
+use borsh::{BorshDeserialize, BorshSerialize};
+use memmap2::Mmap;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::state::FlatStateValue;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use super::metrics::FlatStorageMetrics;
+use super::POISONED_LOCK_ERR;
+
+const MAGIC: &[u8; 8] = b"FLATMMAP";
+const FORMAT_VERSION: u32 = 1;
+const BUFFER_SIZE: usize = 16 * 1024 * 1024;
+
+/// Key length of a commit record.
+const COMMIT_MARKER: u32 = u32::MAX;
+/// Value length of an entry deleting its key.
+const DELETED_MARKER: u32 = u32::MAX;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct Header {
+    format_version: u32,
+    shard_uid: ShardUId,
+}
+
+fn file_path(dir: &Path, shard_uid: ShardUId) -> PathBuf {
+    dir.join(format!("{shard_uid}.flat"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Position of a value within the file.
+#[derive(Clone, Copy)]
+struct ValueLocation {
+    offset: u64,
+    len: u32,
+}
+
+/// Index of the keys of the file, built as its records are written or read.
+#[derive(Default)]
+struct Index {
+    values: HashMap<Box<[u8]>, ValueLocation>,
+    /// Bytes of the entries which later entries overwrote.
+    dead_bytes: u64,
+}
+
+impl Index {
+    fn set(&mut self, key: &[u8], value: Option<ValueLocation>) {
+        let previous = match value {
+            Some(value) => self.values.insert(key.into(), value),
+            None => self.values.remove(key),
+        };
+        if let Some(previous) = previous {
+            self.dead_bytes += entry_size(key.len(), previous.len);
+        }
+    }
+}
+
+fn entry_size(key_len: usize, value_len: u32) -> u64 {
+    8 + key_len as u64 + u64::from(value_len)
+}
+
+/// Serializes records, keeping track of the offsets they end up at.
+struct RecordWriter<W: Write> {
+    writer: W,
+    offset: u64,
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn write_entry(
+        &mut self,
+        index: &mut Index,
+        key: &[u8],
+        value: Option<&FlatStateValue>,
+    ) -> io::Result<()> {
+        let key_len = u32::try_from(key.len()).map_err(|_| invalid_data("key too long"))?;
+        self.writer.write_all(&key_len.to_le_bytes())?;
+        self.writer.write_all(key)?;
+        let location = match value {
+            Some(value) => {
+                let bytes = borsh::to_vec(value)?;
+                let len = u32::try_from(bytes.len()).map_err(|_| invalid_data("value too long"))?;
+                self.writer.write_all(&len.to_le_bytes())?;
+                self.writer.write_all(&bytes)?;
+                Some(ValueLocation { offset: self.offset + 8 + u64::from(key_len), len })
+            }
+            None => {
+                self.writer.write_all(&DELETED_MARKER.to_le_bytes())?;
+                None
+            }
+        };
+        self.offset += entry_size(key.len(), location.map_or(0, |location| location.len));
+        index.set(key, location);
+        Ok(())
+    }
+
+    fn write_changes(&mut self, index: &mut Index, changes: &FlatStateChanges) -> io::Result<()> {
+        for (key, value) in &changes.0 {
+            self.write_entry(index, key, value.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn write_commit(&mut self, flat_head: &BlockInfo) -> io::Result<()> {
+        let bytes = borsh::to_vec(flat_head)?;
+        self.writer.write_all(&COMMIT_MARKER.to_le_bytes())?;
+        self.writer.write_all(&bytes)?;
+        self.offset += 4 + bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// The file, mapped, with the index of its keys.
+struct MappedFile {
+    file: File,
+    mmap: Mmap,
+    index: Index,
+    flat_head: BlockInfo,
+}
+
+impl MappedFile {
+    fn map(file: File, index: Index, flat_head: BlockInfo) -> io::Result<Self> {
+        // SAFETY: the file is only ever appended to, by this process, so the
+        // mapped bytes don't change under the readers.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self { file, mmap, index, flat_head })
+    }
+
+    fn len(&self) -> u64 {
+        self.mmap.len() as u64
+    }
+
+    /// Whether most of the file is overwritten entries.
+    fn needs_rebuild(&self) -> bool {
+        self.index.dead_bytes > self.len() / 2
+    }
+
+    fn get(&self, key: &[u8]) -> io::Result<Option<FlatStateValue>> {
+        let Some(location) = self.index.values.get(key) else {
+            return Ok(None);
+        };
+        let start = location.offset as usize;
+        let bytes = self
+            .mmap
+            .get(start..start + location.len as usize)
+            .ok_or_else(|| invalid_data("value out of bounds"))?;
+        Ok(Some(FlatStateValue::try_from_slice(bytes)?))
+    }
+
+    /// Appends the changes of moving the flat head to `flat_head`.
+    fn append(&mut self, changes: &FlatStateChanges, flat_head: BlockInfo) -> io::Result<()> {
+        let mut writer = RecordWriter { writer: Vec::new(), offset: self.len() };
+        writer.write_changes(&mut self.index, changes)?;
+        writer.write_commit(&flat_head)?;
+        self.file.write_all(&writer.writer)?;
+        self.file.sync_data()?;
+        // SAFETY: see `map`.
+        self.mmap = unsafe { Mmap::map(&self.file)? };
+        self.flat_head = flat_head;
+        Ok(())
+    }
+}
+
+/// Reads the file of the shard, dropping the entries after its last commit.
+/// Returns `None` if the file is of another format or shard.
+fn read_file(path: &Path, shard_uid: ShardUId) -> io::Result<Option<MappedFile>> {
+    let file = OpenOptions::new().read(true).append(true).open(path)?;
+    // SAFETY: see `MappedFile::map`, the file isn't appended to while it is read.
+    let mmap = unsafe { Mmap::map(&file)? };
+    let mut bytes: &[u8] = &mmap;
+    if bytes.get(..MAGIC.len()) != Some(MAGIC.as_slice()) {
+        return Err(invalid_data("not a flat state file"));
+    }
+    bytes = &bytes[MAGIC.len()..];
+    let header = Header::deserialize(&mut bytes)?;
+    if header.format_version != FORMAT_VERSION || header.shard_uid != shard_uid {
+        return Ok(None);
+    }
+
+    let mut index = Index::default();
+    let mut uncommitted = Vec::new();
+    let mut committed = None;
+    // An error means a record cut short by a crash, after the last commit.
+    let _ = (|| -> io::Result<()> {
+        loop {
+            let key_len = u32::deserialize(&mut bytes)?;
+            if key_len == COMMIT_MARKER {
+                let flat_head = BlockInfo::deserialize(&mut bytes)?;
+                for (key, value) in uncommitted.drain(..) {
+                    index.set(key, value);
+                }
+                committed = Some(((mmap.len() - bytes.len()) as u64, flat_head));
+                continue;
+            }
+            let key = bytes.get(..key_len as usize).ok_or_else(|| invalid_data("truncated key"))?;
+            bytes = &bytes[key.len()..];
+            let value_len = u32::deserialize(&mut bytes)?;
+            let value = if value_len == DELETED_MARKER {
+                None
+            } else {
+                let value_offset = (mmap.len() - bytes.len()) as u64;
+                bytes = bytes
+                    .get(value_len as usize..)
+                    .ok_or_else(|| invalid_data("truncated value"))?;
+                Some(ValueLocation { offset: value_offset, len: value_len })
+            };
+            uncommitted.push((key, value));
+        }
+    })();
+
+    let Some((committed_len, flat_head)) = committed else {
+        return Ok(None);
+    };
+    drop(mmap);
+    if committed_len < file.metadata()?.len() {
+        file.set_len(committed_len)?;
+    }
+    Ok(Some(MappedFile::map(file, index, flat_head)?))
+}
+
+enum State {
+    /// The file is being built.  Holds the changes of the flat head moves
+    /// since the scan of the flat state started.
+    Building(Vec<(FlatStateChanges, BlockInfo)>),
+    Ready(MappedFile),
+    /// Building or appending to the file failed, reads go to the database.
+    Failed,
+}
+
+/// Copy of the flat state of a shard at the flat head, in a memory mapped
+/// file.  See the module docs.
+pub struct FlatStateMmap {
+    path: PathBuf,
+    shard_uid: ShardUId,
+    state: RwLock<State>,
+    metrics: FlatStorageMetrics,
+}
+
+impl FlatStateMmap {
+    /// Opens the file of the shard in `dir`, or starts building it if it
+    /// isn't at `flat_head`.  Must be called while the flat head can't move.
+    pub(crate) fn open(
+        dir: &Path,
+        store: Store,
+        shard_uid: ShardUId,
+        flat_head: BlockInfo,
+        metrics: FlatStorageMetrics,
+    ) -> Arc<Self> {
+        let path = file_path(dir, shard_uid);
+        let existing = match read_file(&path, shard_uid) {
+            Ok(Some(file)) if file.flat_head.hash == flat_head.hash && !file.needs_rebuild() => {
+                Some(file)
+            }
+            Ok(Some(file)) => {
+                tracing::info!(target: "store", %shard_uid, file_flat_head = %file.flat_head.hash, flat_head = %flat_head.hash, dead_bytes = file.index.dead_bytes, "Rebuilding flat state file");
+                None
+            }
+            Ok(None) => None,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                tracing::warn!(target: "store", %shard_uid, ?err, "Failed to read flat state file, rebuilding it");
+                None
+            }
+        };
+        let state = match existing {
+            Some(file) => {
+                metrics.set_mmap_size(file.len());
+                State::Ready(file)
+            }
+            None => State::Building(Vec::new()),
+        };
+        let building = matches!(state, State::Building(_));
+        let this = Arc::new(Self { path, shard_uid, state: RwLock::new(state), metrics });
+        if building {
+            this.build(store, flat_head);
+        }
+        this
+    }
+
+    /// Returns the value of the key at the flat head, or `None` if the file
+    /// can't answer and the database has to be read instead.
+    pub(crate) fn get(&self, key: &[u8]) -> Option<Option<FlatStateValue>> {
+        let state = self.state.read().expect(POISONED_LOCK_ERR);
+        let State::Ready(file) = &*state else {
+            return None;
+        };
+        match file.get(key) {
+            Ok(value) => {
+                self.metrics.inc_mmap_reads();
+                Some(value)
+            }
+            Err(err) => {
+                tracing::warn!(target: "store", shard_uid = %self.shard_uid, ?err, "Failed to read flat state file");
+                None
+            }
+        }
+    }
+
+    /// Records moving the flat head to `flat_head` with `changes`.
+    pub(crate) fn apply_changes(&self, changes: &FlatStateChanges, flat_head: BlockInfo) {
+        let mut state = self.state.write().expect(POISONED_LOCK_ERR);
+        match &mut *state {
+            State::Building(pending) => pending.push((changes.clone(), flat_head)),
+            State::Ready(file) => match file.append(changes, flat_head) {
+                Ok(()) => self.metrics.set_mmap_size(file.len()),
+                Err(err) => {
+                    tracing::warn!(target: "store", shard_uid = %self.shard_uid, ?err, "Failed to append to flat state file, reading from the database");
+                    *state = State::Failed;
+                }
+            },
+            State::Failed => {}
+        }
+    }
+
+    /// Stops using the file and removes it, e.g. when the flat state of the
+    /// shard is cleared.
+    pub(crate) fn remove(&self) {
+        *self.state.write().expect(POISONED_LOCK_ERR) = State::Failed;
+        self.metrics.set_mmap_size(0);
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            if err.kind() != io::ErrorKind::NotFound {
+                tracing::warn!(target: "store", shard_uid = %self.shard_uid, ?err, "Failed to remove flat state file");
+            }
+        }
+    }
+
+    /// Writes the flat state of the shard to the file in a background
+    /// thread, and starts reading from the file once it is done.
+    fn build(self: &Arc<Self>, store: Store, flat_head: BlockInfo) {
+        let this = self.clone();
+        let spawn_result = std::thread::Builder::new()
+            .name(format!("flat-mmap-{}", self.shard_uid))
+            .spawn(move || match this.build_file(&store, flat_head) {
+                Ok(size) => {
+                    tracing::info!(target: "store", shard_uid = %this.shard_uid, size, "Built flat state file");
+                }
+                Err(err) => {
+                    tracing::warn!(target: "store", shard_uid = %this.shard_uid, ?err, "Failed to build flat state file");
+                    *this.state.write().expect(POISONED_LOCK_ERR) = State::Failed;
+                }
+            });
+        if let Err(err) = spawn_result {
+            tracing::warn!(target: "store", shard_uid = %self.shard_uid, ?err, "Failed to start building flat state file");
+            *self.state.write().expect(POISONED_LOCK_ERR) = State::Failed;
+        }
+    }
+
+    /// Writes the file next to its final location and moves it there once
+    /// complete.  Returns the size of the file.
+    fn build_file(&self, store: &Store, flat_head: BlockInfo) -> io::Result<u64> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        let mut writer = BufWriter::with_capacity(BUFFER_SIZE, File::create(&tmp_path)?);
+        let header =
+            borsh::to_vec(&Header { format_version: FORMAT_VERSION, shard_uid: self.shard_uid })?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&header)?;
+        let offset = (MAGIC.len() + header.len()) as u64;
+        let mut writer = RecordWriter { writer, offset };
+        let mut index = Index::default();
+        for entry in store_helper::iter_flat_state_entries(self.shard_uid, store, None, None) {
+            let (key, value) = entry.map_err(io::Error::other)?;
+            writer.write_entry(&mut index, &key, Some(&value))?;
+        }
+
+        // The flat head doesn't move while the queued changes are appended
+        // and the file swapped in.
+        let mut state = self.state.write().expect(POISONED_LOCK_ERR);
+        let State::Building(pending) = &mut *state else {
+            return Err(io::Error::other("flat state file removed while building"));
+        };
+        let mut flat_head = flat_head;
+        for (changes, new_flat_head) in pending.drain(..) {
+            writer.write_changes(&mut index, &changes)?;
+            flat_head = new_flat_head;
+        }
+        writer.write_commit(&flat_head)?;
+        let file = writer.writer.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&tmp_path, &self.path)?;
+        let file = OpenOptions::new().read(true).append(true).open(&self.path)?;
+        let file = MappedFile::map(file, index, flat_head)?;
+        let size = file.len();
+        self.metrics.set_mmap_size(size);
+        *state = State::Ready(file);
+        Ok(size)
+    }
+
+    #[cfg(test)]
+    fn is_ready(&self) -> bool {
+        matches!(*self.state.read().unwrap(), State::Ready(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file_path, FlatStateMmap};
+    use crate::flat::metrics::FlatStorageMetrics;
+    use crate::flat::{store_helper, BlockInfo, FlatStateChanges};
+    use crate::test_utils::create_test_store;
+    use near_primitives::hash::hash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::state::FlatStateValue;
+    use std::io::Write;
+    use std::time::Duration;
+
+    fn wait_until_ready(mmap: &FlatStateMmap) {
+        for _ in 0..100 {
+            if mmap.is_ready() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        panic!("flat state file wasn't built");
+    }
+
+    fn value(bytes: &[u8]) -> FlatStateValue {
+        FlatStateValue::Inlined(bytes.to_vec())
+    }
+
+    #[test]
+    fn test_flat_state_mmap() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = create_test_store();
+        let shard_uid = ShardUId::single_shard();
+        let metrics = FlatStorageMetrics::new(shard_uid);
+        let mut store_update = store.store_update();
+        for key in [b"a", b"b"] {
+            store_helper::set_flat_state_value(
+                &mut store_update,
+                shard_uid,
+                key.to_vec(),
+                Some(value(key)),
+            );
+        }
+        store_update.commit().unwrap();
+        let head1 = BlockInfo { hash: hash(b"1"), height: 1, prev_hash: hash(b"0") };
+        let head2 = BlockInfo { hash: hash(b"2"), height: 2, prev_hash: head1.hash };
+
+        let mmap =
+            FlatStateMmap::open(dir.path(), store.clone(), shard_uid, head1, metrics.clone());
+        wait_until_ready(&mmap);
+        assert_eq!(mmap.get(b"a"), Some(Some(value(b"a"))));
+        assert_eq!(mmap.get(b"c"), Some(None));
+
+        let changes =
+            FlatStateChanges([(b"a".to_vec(), None), (b"c".to_vec(), Some(value(b"c")))].into());
+        mmap.apply_changes(&changes, head2);
+        assert_eq!(mmap.get(b"a"), Some(None));
+        assert_eq!(mmap.get(b"c"), Some(Some(value(b"c"))));
+        drop(mmap);
+
+        // Entries after the last commit are dropped, and the file is used as
+        // it is if it is at the flat head.
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(file_path(dir.path(), shard_uid))
+            .unwrap();
+        file.write_all(&[1, 0, 0, 0, b'b']).unwrap();
+        let mmap =
+            FlatStateMmap::open(dir.path(), store.clone(), shard_uid, head2, metrics.clone());
+        assert!(mmap.is_ready());
+        assert_eq!(mmap.get(b"b"), Some(Some(value(b"b"))));
+        assert_eq!(mmap.get(b"c"), Some(Some(value(b"c"))));
+
+        // At another flat head the file is built again from the flat state.
+        let mmap = FlatStateMmap::open(dir.path(), store, shard_uid, head1, metrics);
+        wait_until_ready(&mmap);
+        assert_eq!(mmap.get(b"a"), Some(Some(value(b"a"))));
+        assert_eq!(mmap.get(b"c"), Some(None));
+    }
+}
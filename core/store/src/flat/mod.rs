@@ -27,9 +27,13 @@
 
 mod chunk_view;
 pub mod delta;
+mod delta_compaction;
 mod inlining_migration;
+mod key_filter;
 mod manager;
 mod metrics;
+mod mmap_state;
+mod state_root_audit;
 mod storage;
 pub mod store_helper;
 #[cfg(test)]
@@ -38,9 +42,13 @@ mod types;
 
 pub use chunk_view::FlatStorageChunkView;
 pub use delta::{FlatStateChanges, FlatStateDelta, FlatStateDeltaMetadata};
+pub use delta_compaction::FlatStorageDeltasCompactionHandle;
 pub use inlining_migration::{inline_flat_state_values, FlatStateValuesInliningMigrationHandle};
+pub use key_filter::FlatStateKeyFilter;
 pub use manager::FlatStorageManager;
 pub use metrics::FlatStorageCreationMetrics;
+pub use mmap_state::FlatStateMmap;
+pub use state_root_audit::StateRootAuditHandle;
 pub use storage::FlatStorage;
 pub use types::{
     BlockInfo, FetchingStateStatus, FlatStateIterator, FlatStorageCreationStatus, FlatStorageError,
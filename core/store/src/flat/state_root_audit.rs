@@ -0,0 +1,143 @@
+//! Periodic audit of flat storage against the committed state roots.
+//!
+//! A validator reads the state from flat storage, so a value silently
+//! corrupted on disk makes it compute wrong state roots and produce or
+//! endorse invalid chunks before anything else notices.  The audit
+//! recomputes the state root of the flat head of a random shard, and of a
+//! random recent chunk from there, from the flat state and the deltas, and
+//! compares them with the roots in `ChunkExtra`.
+//!
+//! The flat head is pinned while the flat state is read, and the whole trie
+//! of the shard is built in memory, so an audit takes about as long and as
+//! much memory as loading the memtrie of the shard.
+
+use crate::config::StateRootAuditConfig;
+use crate::flat::{store_helper, BlockInfo, FlatStorageManager};
+use crate::metrics;
+use crate::trie::mem::loading::{compute_state_roots_from_flat_state, get_state_root};
+use near_primitives::errors::StorageError;
+use near_primitives::shard_layout::ShardUId;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the loop checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, PartialEq, Eq)]
+enum AuditResult {
+    /// The roots computed from flat storage are the committed ones.
+    Match,
+    /// The root computed for the block at the height isn't the committed one.
+    Divergence { height: u64 },
+    /// The shard has no flat storage anymore, or the node stopped during
+    /// the audit.
+    Skipped,
+}
+
+pub struct StateRootAuditHandle {
+    handle: JoinHandle<()>,
+    keep_running: Arc<AtomicBool>,
+}
+
+impl StateRootAuditHandle {
+    /// Starts auditing a random shard with flat storage every `period`.
+    pub fn start(
+        flat_storage_manager: FlatStorageManager,
+        config: StateRootAuditConfig,
+    ) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let keep_running = Arc::new(AtomicBool::new(true));
+        let keep_running_clone = keep_running.clone();
+        let handle = std::thread::spawn(move || {
+            while keep_running.load(Ordering::Relaxed) {
+                let next_run = Instant::now() + config.period;
+                while keep_running.load(Ordering::Relaxed) && Instant::now() < next_run {
+                    std::thread::sleep(STOP_POLL_INTERVAL.min(config.period));
+                }
+                let shard_uids = flat_storage_manager.get_shard_uids();
+                let Some(&shard_uid) = shard_uids.choose(&mut rand::thread_rng()) else {
+                    continue;
+                };
+                let label = shard_uid.to_string();
+                let result = audit_shard(&flat_storage_manager, shard_uid, &keep_running);
+                let result_label = match &result {
+                    Ok(AuditResult::Match) => "match",
+                    Ok(AuditResult::Divergence { .. }) => "divergence",
+                    Ok(AuditResult::Skipped) => continue,
+                    Err(err) => {
+                        tracing::warn!(target: "store", %shard_uid, ?err, "Failed to audit state root");
+                        "error"
+                    }
+                };
+                metrics::STATE_ROOT_AUDITS.with_label_values(&[&label, result_label]).inc();
+                match result {
+                    Ok(AuditResult::Divergence { height }) => {
+                        tracing::error!(target: "store", %shard_uid, height, "State root computed from flat storage differs from the committed one, the database may be corrupted");
+                        metrics::STATE_ROOT_AUDIT_DIVERGENCE.with_label_values(&[&label]).set(1);
+                    }
+                    Ok(AuditResult::Match) => {
+                        tracing::info!(target: "store", %shard_uid, "State root audit passed");
+                        metrics::STATE_ROOT_AUDIT_DIVERGENCE.with_label_values(&[&label]).set(0);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Some(Self { handle, keep_running: keep_running_clone })
+    }
+
+    pub fn stop(self) {
+        self.keep_running.store(false, Ordering::Relaxed);
+        self.handle.join().expect("join should not fail here");
+    }
+}
+
+/// Recomputes the roots of the flat head and of the chain of blocks up to a
+/// random block with a delta, and compares them with the committed ones.
+fn audit_shard(
+    flat_storage_manager: &FlatStorageManager,
+    shard_uid: ShardUId,
+    keep_running: &AtomicBool,
+) -> Result<AuditResult, StorageError> {
+    let Some(flat_storage) = flat_storage_manager.get_flat_storage_for_shard(shard_uid) else {
+        return Ok(AuditResult::Skipped);
+    };
+    let _pin = flat_storage.pin_head();
+    let store = flat_storage_manager.store();
+    let flat_head = flat_storage.get_head();
+
+    let deltas: HashMap<_, BlockInfo> = store_helper::get_all_deltas_metadata(store, shard_uid)?
+        .into_iter()
+        .map(|delta| (delta.block.hash, delta.block))
+        .collect();
+    let mut blocks = vec![];
+    let target = deltas.values().collect::<Vec<_>>().choose(&mut rand::thread_rng()).copied();
+    let mut block = target;
+    while let Some(info) = block {
+        blocks.push(*info);
+        block = deltas.get(&info.prev_hash);
+    }
+    blocks.reverse();
+    // Deltas of blocks which don't build on the flat head can't be applied.
+    if blocks.first().is_some_and(|first| first.prev_hash != flat_head.hash) {
+        blocks.clear();
+    }
+
+    let Some(state_roots) =
+        compute_state_roots_from_flat_state(store, shard_uid, &flat_head, &blocks, keep_running)?
+    else {
+        return Ok(AuditResult::Skipped);
+    };
+    for (block, state_root) in std::iter::once(&flat_head).chain(&blocks).zip(state_roots) {
+        if get_state_root(store, block.hash, shard_uid)? != state_root {
+            return Ok(AuditResult::Divergence { height: block.height });
+        }
+    }
+    Ok(AuditResult::Match)
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 
 use near_primitives::errors::StorageError;
@@ -8,13 +9,16 @@ use near_primitives::state::FlatStateValue;
 use near_primitives::types::BlockHeight;
 use tracing::{debug, warn};
 
+use crate::config::{FlatStorageDeltasConfig, FlatStorageKeyFilterConfig};
 use crate::flat::delta::{BlockWithChangesInfo, CachedFlatStateChanges};
 use crate::flat::BlockInfo;
 use crate::flat::{FlatStorageReadyStatus, FlatStorageStatus};
 use crate::{Store, StoreUpdate};
 
 use super::delta::{CachedFlatStateDelta, FlatStateDelta};
+use super::key_filter::FlatStateKeyFilter;
 use super::metrics::FlatStorageMetrics;
+use super::mmap_state::FlatStateMmap;
 use super::store_helper;
 use super::types::FlatStorageError;
 
@@ -48,6 +52,13 @@ pub(crate) struct FlatStorageInner {
     /// The flag has a numerical value and not a bool, to let us detect attempts
     /// to disable move head multiple times.
     move_head_enabled: bool,
+    /// Number of live [`FlatHeadPin`]s.  The flat head doesn't move while
+    /// there are any.
+    head_pins: usize,
+    /// Filter of the keys in the flat state at `flat_head`, if enabled.
+    key_filter: Option<Arc<FlatStateKeyFilter>>,
+    /// Copy of the flat state at `flat_head` to read from, if enabled.
+    mmap: Option<Arc<FlatStateMmap>>,
     metrics: FlatStorageMetrics,
 }
 
@@ -221,6 +232,56 @@ impl FlatStorageInner {
         Ok(new_head)
     }
 
+    /// Returns the block to move the flat head to so that the deltas fit into
+    /// the limits of `config`, if they don't already.
+    ///
+    /// The head only moves along the blocks which are the only child of their
+    /// parent, so that no delta on a fork is lost, and never to the blocks
+    /// within `min_retained_heights` of the highest delta.
+    fn get_compacted_flat_head(&self, config: &FlatStorageDeltasConfig) -> Option<CryptoHash> {
+        let max_deltas = config.max_deltas.unwrap_or(usize::MAX);
+        let max_size = config.max_cached_changes_size.map_or(u64::MAX, |size| size.as_u64());
+        let mut num_deltas = self.deltas.len();
+        let mut size: u64 = self.deltas.values().map(|delta| delta.changes.total_size()).sum();
+        if num_deltas <= max_deltas && size <= max_size {
+            return None;
+        }
+
+        let max_height = self.deltas.values().map(|delta| delta.metadata.block.height).max()?;
+        let mut children: HashMap<CryptoHash, Vec<&CachedFlatStateDelta>> = HashMap::new();
+        for delta in self.deltas.values() {
+            children.entry(delta.metadata.block.prev_hash).or_default().push(delta);
+        }
+        let mut new_head = None;
+        let mut head = self.flat_head.hash;
+        while num_deltas > max_deltas || size > max_size {
+            let [child] = children.get(&head).map(Vec::as_slice).unwrap_or_default() else {
+                break;
+            };
+            let block = child.metadata.block;
+            if block.height + config.min_retained_heights > max_height {
+                break;
+            }
+            num_deltas -= 1;
+            size -= child.changes.total_size();
+            head = block.hash;
+            new_head = Some(head);
+        }
+        new_head
+    }
+
+    /// Whether the key filter shows that the key isn't in the flat state at
+    /// the flat head, so that there is no need to read it.
+    fn key_filter_excludes(&self, key: &[u8]) -> bool {
+        match &self.key_filter {
+            Some(filter) if !filter.may_contain(key) => {
+                self.metrics.inc_key_filter_skipped_reads();
+                true
+            }
+            _ => false,
+        }
+    }
+
     #[cfg(test)]
     pub fn test_get_new_flat_head(
         &self,
@@ -278,12 +339,46 @@ impl FlatStorage {
             flat_head,
             deltas,
             move_head_enabled: true,
+            head_pins: 0,
+            key_filter: None,
+            mmap: None,
             metrics,
         };
         inner.update_delta_metrics();
         Ok(Self(Arc::new(RwLock::new(inner))))
     }
 
+    /// Starts building a filter of the keys in the flat state, which lets
+    /// lookups of missing keys skip the database once it is built.
+    pub fn enable_key_filter(&self, config: &FlatStorageKeyFilterConfig) {
+        let mut guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        if guard.key_filter.is_some() {
+            return;
+        }
+        // The filter is in place before the scan starts, so that the keys
+        // written by flat head moves during the scan are added too.
+        let filter = Arc::new(FlatStateKeyFilter::new(config));
+        guard.key_filter = Some(filter.clone());
+        filter.build(guard.store.clone(), guard.shard_uid, guard.metrics.clone());
+    }
+
+    /// Starts reading the flat state from a memory mapped file in `dir`,
+    /// once it is built.
+    pub fn enable_mmap(&self, dir: &Path) {
+        let mut guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        if guard.mmap.is_some() {
+            return;
+        }
+        let mmap = FlatStateMmap::open(
+            dir,
+            guard.store.clone(),
+            guard.shard_uid,
+            guard.flat_head,
+            guard.metrics.clone(),
+        );
+        guard.mmap = Some(mmap);
+    }
+
     /// Get sequence of blocks `target_block_hash` (inclusive) to flat head (exclusive)
     /// in backwards chain order. Returns an error if there is no path between them.
     #[cfg(test)]
@@ -314,6 +409,12 @@ impl FlatStorage {
             };
         }
 
+        if guard.key_filter_excludes(key) {
+            return Ok(None);
+        }
+        if let Some(value) = guard.mmap.as_ref().and_then(|mmap| mmap.get(key)) {
+            return Ok(value);
+        }
         let value = store_helper::get_flat_state_value(&guard.store, guard.shard_uid, key)?;
         Ok(value)
     }
@@ -336,6 +437,12 @@ impl FlatStorage {
             };
         }
 
+        if guard.key_filter_excludes(key) {
+            return Ok(false);
+        }
+        if let Some(value) = guard.mmap.as_ref().and_then(|mmap| mmap.get(key)) {
+            return Ok(value.is_some());
+        }
         let db_key = store_helper::encode_flat_state_db_key(guard.shard_uid, key);
         Ok(guard.store.exists(crate::DBCol::FlatState, &db_key).map_err(|err| {
             FlatStorageError::StorageInternalError(format!("failed to read FlatState value: {err}"))
@@ -372,7 +479,7 @@ impl FlatStorage {
         strict: bool,
     ) -> Result<(), FlatStorageError> {
         let mut guard = self.0.write().expect(crate::flat::POISONED_LOCK_ERR);
-        if !guard.move_head_enabled {
+        if !guard.move_head_enabled || guard.head_pins > 0 {
             return Ok(());
         }
 
@@ -394,13 +501,23 @@ impl FlatStorage {
             // path from old to new head. Otherwise we return internal error.
             let changes = store_helper::get_delta_changes(&guard.store, shard_uid, block_hash)?
                 .ok_or_else(|| missing_delta_error(&block_hash))?;
-            changes.apply_to_flat_state(&mut store_update, guard.shard_uid);
             let metadata = guard
                 .deltas
                 .get(&block_hash)
                 .ok_or_else(|| missing_delta_error(&block_hash))?
                 .metadata;
             let block = metadata.block;
+            if let Some(filter) = &guard.key_filter {
+                for (key, value) in &changes.0 {
+                    if value.is_some() {
+                        filter.insert(key);
+                    }
+                }
+            }
+            if let Some(mmap) = &guard.mmap {
+                mmap.apply_changes(&changes, block);
+            }
+            changes.apply_to_flat_state(&mut store_update, guard.shard_uid);
             let block_height = block.height;
             store_helper::set_flat_storage_status(
                 &mut store_update,
@@ -445,6 +562,33 @@ impl FlatStorage {
         self.update_flat_head_impl(block_hash, true)
     }
 
+    /// Applies the oldest deltas to the flat state if there are more of them
+    /// than `config` allows, see [`FlatStorageDeltasConfig`].  Returns the
+    /// number of removed deltas.
+    pub fn compact_deltas(
+        &self,
+        config: &FlatStorageDeltasConfig,
+    ) -> Result<usize, FlatStorageError> {
+        let (new_head, num_deltas) = {
+            let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+            if !guard.move_head_enabled || guard.head_pins > 0 {
+                return Ok(0);
+            }
+            match guard.get_compacted_flat_head(config) {
+                Some(new_head) => (new_head, guard.deltas.len()),
+                None => return Ok(0),
+            }
+        };
+        // Fails with `BlockNotSupported` if the head moved past `new_head` in
+        // the meantime.
+        self.update_flat_head_impl(&new_head, true)?;
+        let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+        let num_compacted = num_deltas.saturating_sub(guard.deltas.len());
+        guard.metrics.inc_compacted_deltas(num_compacted);
+        tracing::info!(target: "store", shard_uid = %guard.shard_uid, flat_head_height = guard.flat_head.height, num_compacted, "Compacted flat storage deltas");
+        Ok(num_compacted)
+    }
+
     /// Adds a delta (including the changes and block info) to flat storage,
     /// returns a StoreUpdate to store the delta on disk. Node that this StoreUpdate should be
     /// committed to disk in one db transaction together with the rest of changes caused by block,
@@ -474,7 +618,10 @@ impl FlatStorage {
 
     /// Clears all State key-value pairs from flat storage.
     pub fn clear_state(&self, store_update: &mut StoreUpdate) -> Result<(), StorageError> {
-        let guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        let mut guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        if let Some(mmap) = guard.mmap.take() {
+            mmap.remove();
+        }
         let shard_uid = guard.shard_uid;
         store_helper::remove_all_flat_state_values(store_update, shard_uid);
         store_helper::remove_all_deltas(store_update, shard_uid);
@@ -483,6 +630,34 @@ impl FlatStorage {
         Ok(())
     }
 
+    /// Returns the current flat head.
+    pub fn get_head(&self) -> BlockInfo {
+        let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+        guard.flat_head
+    }
+
+    /// Returns the number of deltas above the flat head kept in memory.
+    pub fn num_deltas(&self) -> usize {
+        let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+        guard.deltas.len()
+    }
+
+    /// Returns whether the flat head is allowed to move. Moving is disabled
+    /// while a state snapshot is being made.
+    pub fn is_move_head_enabled(&self) -> bool {
+        let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+        guard.move_head_enabled
+    }
+
+    /// Keeps the flat head, and thus the flat state on disk, where it is
+    /// until the returned pin is dropped.  Unlike disabling head moves for a
+    /// state snapshot, pins of different users don't interfere.
+    pub(crate) fn pin_head(&self) -> FlatHeadPin {
+        let mut guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        guard.head_pins += 1;
+        FlatHeadPin(self.clone())
+    }
+
     pub(crate) fn get_head_hash(&self) -> CryptoHash {
         let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
         guard.flat_head.hash
@@ -505,12 +680,23 @@ impl FlatStorage {
     }
 }
 
+/// See [`FlatStorage::pin_head`].
+pub(crate) struct FlatHeadPin(FlatStorage);
+
+impl Drop for FlatHeadPin {
+    fn drop(&mut self) {
+        let mut guard = self.0 .0.write().expect(super::POISONED_LOCK_ERR);
+        guard.head_pins -= 1;
+    }
+}
+
 fn missing_delta_error(block_hash: &CryptoHash) -> FlatStorageError {
     FlatStorageError::StorageInternalError(format!("delta does not exist for block {block_hash}"))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::FlatStorageDeltasConfig;
     use crate::flat::delta::{
         BlockWithChangesInfo, FlatStateChanges, FlatStateDelta, FlatStateDeltaMetadata,
     };
@@ -653,6 +839,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn compact_deltas() {
+        let config = FlatStorageDeltasConfig {
+            max_deltas: Some(3),
+            min_retained_heights: 5,
+            ..Default::default()
+        };
+        for (chain, expected_head) in
+            [(MockChain::linear_chain(10), Some(4)), (MockChain::chain_with_two_forks(10), None)]
+        {
+            let shard_uid = ShardUId::single_shard();
+            let store = create_test_store();
+            let mut store_update = store.store_update();
+            store_helper::set_flat_storage_status(
+                &mut store_update,
+                shard_uid,
+                FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+            );
+            for i in 1..10 {
+                let delta = FlatStateDelta {
+                    changes: FlatStateChanges::default(),
+                    metadata: FlatStateDeltaMetadata {
+                        block: chain.get_block(i),
+                        prev_block_with_changes: None,
+                    },
+                };
+                store_helper::set_delta(&mut store_update, shard_uid, &delta);
+            }
+            store_update.commit().unwrap();
+
+            let flat_storage_manager = FlatStorageManager::new(store.clone());
+            flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+            let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+            let num_compacted = flat_storage.compact_deltas(&config).unwrap();
+            match expected_head {
+                // Blocks within `min_retained_heights` of block 9 are kept,
+                // even though there are more deltas than `max_deltas`.
+                Some(height) => {
+                    assert_eq!(num_compacted, height as usize);
+                    assert_eq!(flat_storage.get_head().hash, chain.get_block_hash(height));
+                    assert_eq!(
+                        store_helper::get_all_deltas_metadata(&store, shard_uid).unwrap().len(),
+                        9 - height as usize
+                    );
+                }
+                // Blocks 1 and 2 are forks, so nothing can be compacted.
+                None => {
+                    assert_eq!(num_compacted, 0);
+                    assert_eq!(flat_storage.get_head().hash, chain.get_block_hash(0));
+                }
+            }
+        }
+    }
+
     #[test]
     fn skipped_heights() {
         // Create a linear chain where some heights are skipped.
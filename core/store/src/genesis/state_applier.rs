@@ -1,14 +1,15 @@
 use crate::flat::FlatStateChanges;
 use crate::{
     get_account, has_received_data, set, set_access_key, set_account, set_code,
-    set_delayed_receipt, set_postponed_receipt, set_promise_yield_receipt, set_received_data,
-    ShardTries, TrieUpdate,
+    set_delayed_receipt, set_global_code, set_namespaced_code, set_postponed_receipt,
+    set_promise_yield_receipt, set_received_data, ShardTries, TrieUpdate,
 };
 
 use near_chain_configs::Genesis;
 use near_crypto::PublicKey;
 use near_parameters::StorageUsageConfig;
 use near_primitives::account::{AccessKey, Account};
+use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{DelayedReceiptIndices, Receipt, ReceiptEnum, ReceivedData};
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_record::{state_record_to_account_id, StateRecord};
@@ -60,6 +61,20 @@ impl<'a> StorageComputer<'a> {
             StateRecord::PostponedReceipt(_) => None,
             StateRecord::ReceivedData { .. } => None,
             StateRecord::DelayedReceipt(_) => None,
+            // Accounts don't pay for the global contracts they use.
+            StateRecord::GlobalContract { .. } => None,
+            // As when the contract is deployed, the namespace also pays for its
+            // name and code hash stored in the account.
+            StateRecord::NamespacedContract { account_id, namespace, code } => {
+                let storage_usage =
+                    namespace.len() as u64 + std::mem::size_of::<CryptoHash>() as u64;
+                Some((account_id.clone(), storage_usage + code.len() as u64))
+            }
+            StateRecord::NamespacedData { account_id, data_key, value, .. } => {
+                let storage_usage =
+                    self.config.num_extra_bytes_record + data_key.len() as u64 + value.len() as u64;
+                Some((account_id.clone(), storage_usage))
+            }
         };
         if let Some((account_id, storage_usage)) = account_and_storage {
             *self.result.entry(account_id).or_default() += storage_usage;
@@ -178,7 +193,10 @@ impl GenesisStateApplier {
             "processing records…"
         );
         genesis.for_each_record(|record: &StateRecord| {
-            if !account_ids.contains(state_record_to_account_id(record)) {
+            // The records without an account belong to every shard.
+            if state_record_to_account_id(record)
+                .is_some_and(|account_id| !account_ids.contains(account_id))
+            {
                 return;
             }
             storage_computer.process_record(record);
@@ -244,6 +262,41 @@ impl GenesisStateApplier {
                 StateRecord::DelayedReceipt(receipt) => storage.modify(|state_update| {
                     set_delayed_receipt(state_update, delayed_receipts_indices, &*receipt);
                 }),
+                StateRecord::GlobalContract { code } => storage.modify(|state_update| {
+                    set_global_code(state_update, &ContractCode::new(code.clone(), None));
+                }),
+                StateRecord::NamespacedContract { account_id, namespace, code } => {
+                    storage.modify(|state_update| {
+                        let code = ContractCode::new(code.clone(), None);
+                        if let Some(acc) =
+                            get_account(state_update, account_id).expect("Failed to read state")
+                        {
+                            set_namespaced_code(
+                                state_update,
+                                account_id.clone(),
+                                namespace.clone(),
+                                &code,
+                            );
+                            assert_eq!(Some(*code.hash()), acc.namespace_code_hash(namespace));
+                        } else {
+                            tracing::error!(
+                                target: "runtime",
+                                %account_id,
+                                %namespace,
+                                code_hash = %code.hash(),
+                                message = "namespaced code for non-existent account",
+                            );
+                        }
+                    })
+                }
+                StateRecord::NamespacedData { account_id, namespace, data_key, value } => {
+                    let key = TrieKey::NamespacedContractData {
+                        account_id: account_id.clone(),
+                        namespace: namespace.clone(),
+                        key: data_key.clone().into(),
+                    };
+                    storage.modify(|state_update| state_update.set(key, value.clone().into()))
+                }
             }
         });
 
@@ -313,7 +366,9 @@ impl GenesisStateApplier {
                         set_promise_yield_receipt(state_update, &receipt);
                     });
                 }
-                ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => {
+                ReceiptEnum::Data(_)
+                | ReceiptEnum::PromiseResume(_)
+                | ReceiptEnum::GlobalContractDistribution(_) => {
                     panic!("Expected action receipt")
                 }
             }
@@ -0,0 +1,102 @@
+//! Attribution of database IO to the node subsystems issuing it.
+//!
+//! Database accesses are synchronous, so the subsystem responsible for an
+//! access is the one which is currently running on the accessing thread.
+//! Subsystems mark the scope of their work with [`IoSubsystem::enter`] and
+//! the RocksDB layer labels its metrics with [`IoSubsystem::current`].
+//!
+//! The attribution is per thread. Work handed over to another thread (e.g. a
+//! rayon pool) needs to re-enter the subsystem on the worker thread, otherwise
+//! its IO is reported as [`IoSubsystem::Other`].
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum IoSubsystem {
+    /// IO which was not attributed to any specific subsystem.
+    Other,
+    /// Block processing, including applying chunks.
+    BlockProcessing,
+    /// View client requests, e.g. RPC queries and view calls.
+    ViewClient,
+    /// Serving state sync headers and parts to other nodes.
+    StateSyncServing,
+    /// Garbage collection of old chain data.
+    GarbageCollection,
+    /// Copying data from the hot to the cold database.
+    ColdCopy,
+    /// Obtaining state parts to dump them to external storage.
+    StateDump,
+}
+
+thread_local! {
+    static CURRENT_IO_SUBSYSTEM: Cell<IoSubsystem> = const { Cell::new(IoSubsystem::Other) };
+}
+
+impl IoSubsystem {
+    /// Returns the subsystem to which IO issued by the current thread is attributed.
+    pub fn current() -> Self {
+        CURRENT_IO_SUBSYSTEM.with(|current| current.get())
+    }
+
+    /// Attributes IO issued by the current thread to this subsystem until the
+    /// returned guard is dropped, after which the previous attribution is
+    /// restored.
+    #[must_use = "IO is attributed to the subsystem only while the guard is alive"]
+    pub fn enter(self) -> IoSubsystemGuard {
+        let prev = CURRENT_IO_SUBSYSTEM.with(|current| current.replace(self));
+        IoSubsystemGuard { prev, _not_send: PhantomData }
+    }
+
+    /// Attributes all IO issued by the current thread to this subsystem,
+    /// unless overridden by [`IoSubsystem::enter`]. Meant for threads which
+    /// are dedicated to a single subsystem, e.g. the view client threads.
+    pub fn set_for_current_thread(self) {
+        CURRENT_IO_SUBSYSTEM.with(|current| current.set(self));
+    }
+}
+
+/// Restores the previous IO attribution of the thread when dropped.
+pub struct IoSubsystemGuard {
+    prev: IoSubsystem,
+    // The guard manipulates thread local state, so it must be dropped on the
+    // thread where it was created.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for IoSubsystemGuard {
+    fn drop(&mut self) {
+        CURRENT_IO_SUBSYSTEM.with(|current| current.set(self.prev));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IoSubsystem;
+
+    #[test]
+    fn test_nested_io_subsystems() {
+        assert_eq!(IoSubsystem::current(), IoSubsystem::Other);
+        {
+            let _gc = IoSubsystem::GarbageCollection.enter();
+            assert_eq!(IoSubsystem::current(), IoSubsystem::GarbageCollection);
+            {
+                let _cold = IoSubsystem::ColdCopy.enter();
+                assert_eq!(IoSubsystem::current(), IoSubsystem::ColdCopy);
+            }
+            assert_eq!(IoSubsystem::current(), IoSubsystem::GarbageCollection);
+        }
+        assert_eq!(IoSubsystem::current(), IoSubsystem::Other);
+    }
+
+    #[test]
+    fn test_io_subsystem_is_per_thread() {
+        let _gc = IoSubsystem::GarbageCollection.enter();
+        std::thread::spawn(|| assert_eq!(IoSubsystem::current(), IoSubsystem::Other))
+            .join()
+            .unwrap();
+        assert_eq!(IoSubsystem::current(), IoSubsystem::GarbageCollection);
+    }
+}
@@ -0,0 +1,154 @@
+//! Optional features of the host which the storage can take advantage of,
+//! enabled with `io_uring` and `huge_pages` of [`StoreConfig`].
+//!
+//! The features are only used if the host supports them, which is detected at
+//! runtime, so the same config works on every host.  Both are Linux only.
+//!
+//! * io_uring lets RocksDB read the blocks of iterators asynchronously, which
+//!   reduces the number of syscalls of large scans, and issue the reads of a
+//!   multi-get at once, which is how the values of state parts are looked up.
+//!   It takes effect only if RocksDB was built with liburing, otherwise the
+//!   reads stay synchronous.
+//! * Transparent huge pages back the chunks of the in-memory trie arenas and
+//!   the RocksDB memtables, which reduces TLB misses on hosts with a lot of
+//!   memory.
+
+use crate::StoreConfig;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+
+/// Size of the transparent huge pages on x86_64 and aarch64 with 4 KiB pages.
+pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Whether the chunks of memtrie arenas are backed by huge pages.
+static ARENA_HUGE_PAGES: AtomicBool = AtomicBool::new(false);
+
+/// The features of the host enabled by the config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IoFeatures {
+    pub io_uring: bool,
+    pub huge_pages: bool,
+}
+
+impl IoFeatures {
+    /// Returns the features enabled by the config which the host supports.
+    pub fn detect(config: &StoreConfig) -> Self {
+        Self::new(config, *IO_URING_SUPPORTED, huge_pages_supported())
+    }
+
+    /// Returns the features enabled by the config out of the supported ones.
+    pub(crate) fn new(
+        config: &StoreConfig,
+        io_uring_supported: bool,
+        huge_pages_supported: bool,
+    ) -> Self {
+        let io_uring = config.io_uring && io_uring_supported;
+        if config.io_uring && !io_uring {
+            tracing::warn!(target: "store", "io_uring is enabled in the config but the host doesn't support it");
+        }
+        let huge_pages = config.huge_pages && huge_pages_supported;
+        if config.huge_pages && !huge_pages {
+            tracing::warn!(target: "store", "huge_pages is enabled in the config but transparent huge pages are disabled on the host");
+        }
+        Self { io_uring, huge_pages }
+    }
+}
+
+/// Enables the features of the host for the whole process, i.e. the parts of
+/// the storage which don't get the config, like the memtrie arenas.
+pub fn configure(config: &StoreConfig) -> IoFeatures {
+    let features = IoFeatures::detect(config);
+    ARENA_HUGE_PAGES.store(features.huge_pages, Ordering::Relaxed);
+    tracing::info!(target: "store", ?features, "Configured storage I/O features");
+    features
+}
+
+pub(crate) fn arena_huge_pages() -> bool {
+    ARENA_HUGE_PAGES.load(Ordering::Relaxed)
+}
+
+static IO_URING_SUPPORTED: LazyLock<bool> = LazyLock::new(probe_io_uring);
+
+/// Checks that io_uring isn't disabled in the kernel by setting up a ring.
+#[cfg(target_os = "linux")]
+fn probe_io_uring() -> bool {
+    // `struct io_uring_params` is 120 bytes and must be zeroed.
+    let mut params = [0u8; 120];
+    // SAFETY: the kernel only writes into `params`, which is big enough.
+    let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, 1u32, params.as_mut_ptr()) };
+    if fd < 0 {
+        return false;
+    }
+    // SAFETY: `fd` is a file descriptor we own.
+    unsafe { libc::close(fd as libc::c_int) };
+    true
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe_io_uring() -> bool {
+    false
+}
+
+/// Returns whether transparent huge pages can be requested with `madvise`,
+/// i.e. their mode is `always` or `madvise`.
+pub fn huge_pages_supported() -> bool {
+    let Ok(enabled) = std::fs::read_to_string("/sys/kernel/mm/transparent_hugepage/enabled") else {
+        return false;
+    };
+    // The active mode is in brackets, e.g. `always [madvise] never`.
+    enabled.contains("[always]") || enabled.contains("[madvise]")
+}
+
+/// Asks the kernel to back the buffer with huge pages.  Only the part of the
+/// buffer aligned to [`HUGE_PAGE_SIZE`] can be backed, so buffers should be
+/// a few huge pages big.  Should be called before the buffer is written to.
+pub fn advise_huge_pages(buffer: &mut [u8]) {
+    let start = buffer.as_mut_ptr() as usize;
+    let aligned_start = start.next_multiple_of(HUGE_PAGE_SIZE);
+    let end = start + buffer.len();
+    let aligned_end = end - end % HUGE_PAGE_SIZE;
+    if aligned_start >= aligned_end {
+        return;
+    }
+    #[cfg(target_os = "linux")]
+    // SAFETY: the range is within `buffer` and madvise doesn't change its
+    // contents.
+    unsafe {
+        libc::madvise(
+            aligned_start as *mut libc::c_void,
+            aligned_end - aligned_start,
+            libc::MADV_HUGEPAGE,
+        );
+    }
+}
+
+/// Allocates a zeroed chunk for a memtrie arena.
+pub(crate) fn new_arena_chunk(size: usize) -> Vec<u8> {
+    let mut chunk = vec![0; size];
+    if arena_huge_pages() {
+        advise_huge_pages(&mut chunk);
+    }
+    chunk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IoFeatures;
+    use crate::StoreConfig;
+
+    #[test]
+    fn test_unsupported_features_fall_back() {
+        let config = StoreConfig { io_uring: true, huge_pages: true, ..StoreConfig::test_config() };
+        assert_eq!(IoFeatures::new(&config, false, false), IoFeatures::default());
+        assert_eq!(
+            IoFeatures::new(&config, true, false),
+            IoFeatures { io_uring: true, huge_pages: false }
+        );
+        assert_eq!(
+            IoFeatures::new(&config, false, true),
+            IoFeatures { io_uring: false, huge_pages: true }
+        );
+        // Supported features stay off unless the config enables them.
+        assert_eq!(IoFeatures::new(&StoreConfig::test_config(), true, true), IoFeatures::default());
+    }
+}
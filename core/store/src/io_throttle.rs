@@ -0,0 +1,121 @@
+//! Yielding the disk to chunk application.
+//!
+//! Garbage collection, the state dumper and copying to the cold database
+//! read and write a lot, and compete for the disk with applying chunks,
+//! which has to finish in time for the node to keep up with the chain.  The
+//! time it takes to apply a chunk is tracked as a moving average and, while
+//! it is above the threshold of [`IoThrottleConfig`], the background work
+//! waits before each of its steps, up to `max_delay`.
+//!
+//! The throttle is process wide, like the IO it arbitrates: the node applies
+//! chunks and runs the background work against the same disks.
+
+use crate::config::IoThrottleConfig;
+use crate::io_attribution::IoSubsystem;
+use crate::metrics;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// How often a throttled step checks whether it can go ahead.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Weight of a new latency in the moving average, in 1/8ths.
+const NEW_LATENCY_WEIGHT: u32 = 1;
+
+static CONFIG: RwLock<Option<IoThrottleConfig>> = RwLock::new(None);
+
+/// Moving average of the chunk application latency and when it was last
+/// updated.
+static CHUNK_APPLY_LATENCY: Mutex<Option<(Duration, Instant)>> = Mutex::new(None);
+
+/// Sets the config of the throttle, which is disabled until then.
+pub fn configure(config: IoThrottleConfig) {
+    *CONFIG.write().unwrap() = config.enabled.then_some(config);
+}
+
+fn config() -> Option<IoThrottleConfig> {
+    CONFIG.read().unwrap().clone()
+}
+
+/// Records how long applying a chunk took.
+pub fn record_chunk_apply_latency(latency: Duration) {
+    let mut average = CHUNK_APPLY_LATENCY.lock().unwrap();
+    let latency = match *average {
+        Some((average, _)) => {
+            (average * (8 - NEW_LATENCY_WEIGHT) + latency * NEW_LATENCY_WEIGHT) / 8
+        }
+        None => latency,
+    };
+    *average = Some((latency, Instant::now()));
+    metrics::IO_THROTTLE_CHUNK_APPLY_LATENCY_MS.set(latency.as_millis() as i64);
+}
+
+/// Whether background work should wait for chunk application to speed up.
+pub fn should_yield() -> bool {
+    config().is_some_and(|config| is_slow(&config))
+}
+
+fn is_slow(config: &IoThrottleConfig) -> bool {
+    match *CHUNK_APPLY_LATENCY.lock().unwrap() {
+        Some((latency, updated)) => {
+            latency > config.chunk_apply_latency_threshold && updated.elapsed() < config.latency_ttl
+        }
+        None => false,
+    }
+}
+
+/// Blocks the thread of the background `subsystem` while chunk application
+/// is slow, up to `max_delay`.  Meant to be called before each step of the
+/// background work, e.g. a batch of writes.
+pub fn throttle(subsystem: IoSubsystem) {
+    let Some(config) = config() else {
+        return;
+    };
+    let start = Instant::now();
+    while is_slow(&config) && start.elapsed() < config.max_delay {
+        std::thread::sleep(POLL_INTERVAL.min(config.max_delay));
+    }
+    record_delay(subsystem, start.elapsed());
+}
+
+fn record_delay(subsystem: IoSubsystem, delay: Duration) {
+    if delay >= POLL_INTERVAL {
+        let subsystem: &'static str = subsystem.into();
+        metrics::IO_THROTTLE_DELAY_MS
+            .with_label_values(&[subsystem])
+            .inc_by(delay.as_millis() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{configure, record_chunk_apply_latency, should_yield, throttle};
+    use crate::config::IoThrottleConfig;
+    use crate::io_attribution::IoSubsystem;
+    use std::time::{Duration, Instant};
+
+    /// Background work waits while chunk application is slow, but not longer
+    /// than the max delay.
+    #[test]
+    fn test_throttle() {
+        configure(IoThrottleConfig {
+            enabled: true,
+            chunk_apply_latency_threshold: Duration::from_millis(100),
+            max_delay: Duration::from_millis(200),
+            latency_ttl: Duration::from_secs(60),
+        });
+        record_chunk_apply_latency(Duration::from_millis(10));
+        assert!(!should_yield());
+
+        for _ in 0..20 {
+            record_chunk_apply_latency(Duration::from_secs(1));
+        }
+        assert!(should_yield());
+        let start = Instant::now();
+        throttle(IoSubsystem::GarbageCollection);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        configure(IoThrottleConfig::default());
+        assert!(!should_yield());
+    }
+}
@@ -2,34 +2,38 @@
 
 extern crate core;
 
+use crate::bulk_load::{BulkLoadGuard, BulkLoadPhase};
 use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, StoreStatistics};
 pub use crate::trie::iterator::{TrieIterator, TrieTraversalItem};
-pub use crate::trie::update::{TrieUpdate, TrieUpdateIterator, TrieUpdateValuePtr};
+pub use crate::trie::range_iterator::{KeyRange, TrieRangeCursor, TrieRangeIterator};
+pub use crate::trie::update::{
+    ContractStorage, TrieUpdate, TrieUpdateIterator, TrieUpdateValuePtr,
+};
 pub use crate::trie::{
-    estimator, resharding_v2, ApplyStatePartResult, KeyForStateChanges, KeyLookupMode, NibbleSlice,
-    PartialStorage, PrefetchApi, PrefetchError, RawTrieNode, RawTrieNodeWithSize, ShardTries,
-    StateSnapshot, StateSnapshotConfig, Trie, TrieAccess, TrieCache, TrieCachingStorage,
-    TrieChanges, TrieConfig, TrieDBStorage, TrieStorage, WrappedTrieChanges,
-    STATE_SNAPSHOT_COLUMNS,
+    estimator, list_state_checkpoints, open_state_checkpoint, resharding_v2, ApplyStatePartResult,
+    KeyForStateChanges, KeyLookupMode, NibbleSlice, PartialStorage, PrefetchApi, PrefetchError,
+    RawTrieNode, RawTrieNodeWithSize, ShardTries, StateSnapshot, StateSnapshotConfig, Trie,
+    TrieAccess, TrieCache, TrieCachingStorage, TrieChanges, TrieConfig, TrieDBStorage, TrieStorage,
+    WrappedTrieChanges, STATE_SNAPSHOT_COLUMNS,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 pub use columns::DBCol;
 use db::{SplitDB, GENESIS_CONGESTION_INFO_KEY};
 pub use db::{
     CHUNK_TAIL_KEY, COLD_HEAD_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, GENESIS_JSON_HASH_KEY,
-    GENESIS_STATE_ROOTS_KEY, HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY,
+    GENESIS_STATE_ROOTS_KEY, HEADER_HEAD_KEY, HEADER_TAIL_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY,
     LATEST_KNOWN_KEY, STATE_SNAPSHOT_KEY, STATE_SYNC_DUMP_KEY, TAIL_KEY,
 };
 use metadata::{DbKind, DbVersion, KIND_KEY, VERSION_KEY};
 use near_crypto::PublicKey;
 use near_fmt::{AbbrBytes, StorageKey};
-use near_primitives::account::{AccessKey, Account};
+use near_primitives::account::{AccessKey, Account, AccountNamespace};
 use near_primitives::congestion_info::CongestionInfo;
 pub use near_primitives::errors::{MissingTrieValueContext, StorageError};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
-    BufferedReceiptIndices, DelayedReceiptIndices, PromiseYieldIndices, PromiseYieldTimeout,
-    Receipt, ReceiptEnum, ReceivedData,
+    BufferedReceiptIndices, DelayedReceiptIndices, IdempotencyKeys, PromiseYieldIndices,
+    PromiseYieldTimeout, Receipt, ReceiptEnum, ReceivedData,
 };
 pub use near_primitives::shard_layout::ShardUId;
 use near_primitives::trie_key::{trie_key_parsers, TrieKey};
@@ -43,22 +47,33 @@ use std::sync::LazyLock;
 use std::{fmt, io};
 use strum;
 
+pub mod bulk_load;
 pub mod cold_storage;
 mod columns;
 pub mod config;
 pub mod db;
+pub mod disk_pressure;
 pub mod flat;
 pub mod genesis;
+pub mod io_attribution;
+pub mod io_features;
+pub mod io_throttle;
+pub mod memory_budget;
 pub mod metadata;
 pub mod metrics;
 pub mod migrations;
 mod opener;
 mod rocksdb_metrics;
+pub mod secondary;
 mod sync_utils;
 pub mod test_utils;
 pub mod trie;
 
-pub use crate::config::{Mode, StoreConfig};
+pub use crate::config::{
+    FlatStorageKeyFilterConfig, MemoryBudgetConfig, Mode, ObjectStorageConfig,
+    ObjectStorageLocation, RocksDbColumnConfig, RocksDbCompression, SecondaryConfig, ShardDbConfig,
+    StoreConfig, ValueCompressionConfig,
+};
 pub use crate::opener::{
     checkpoint_hot_storage_and_cleanup_columns, StoreMigrator, StoreOpener, StoreOpenerError,
 };
@@ -95,6 +110,7 @@ const STATE_FILE_END_MARK: u8 = 255;
 ///
 /// Provides access to hot storage, cold storage and split storage. Typically
 /// users will want to use one of the above via the Store abstraction.
+#[derive(Clone)]
 pub struct NodeStorage {
     hot_storage: Arc<dyn Database>,
     cold_storage: Option<Arc<crate::db::ColdDB>>,
@@ -124,13 +140,10 @@ impl NodeStorage {
     }
 
     /// Constructs new object backed by given database.
-    fn from_rocksdb(
-        hot_storage: crate::db::RocksDB,
-        cold_storage: Option<crate::db::RocksDB>,
+    fn from_databases(
+        hot_storage: Arc<dyn Database>,
+        cold_storage: Option<Arc<dyn Database>>,
     ) -> Self {
-        let hot_storage = Arc::new(hot_storage);
-        let cold_storage = cold_storage.map(|storage| Arc::new(storage));
-
         let cold_db = if let Some(cold_storage) = cold_storage {
             Some(Arc::new(crate::db::ColdDB::new(cold_storage)))
         } else {
@@ -256,6 +269,17 @@ impl NodeStorage {
 }
 
 impl NodeStorage {
+    /// Makes the writes of the primary instances visible to the databases
+    /// opened in [`Mode::Secondary`], see [`crate::secondary`].  The cold
+    /// database catches up first, so that the hot one never points at blocks
+    /// it doesn't have yet.
+    pub fn catch_up_with_primary(&self) -> io::Result<()> {
+        if let Some(cold_storage) = &self.cold_storage {
+            cold_storage.catch_up_with_primary()?;
+        }
+        self.hot_storage.catch_up_with_primary()
+    }
+
     /// Returns whether the storage has a cold database.
     pub fn has_cold(&self) -> bool {
         self.cold_storage.is_some()
@@ -287,6 +311,12 @@ impl Store {
         Self { storage }
     }
 
+    /// Switches the columns written during the given phase to bulk load
+    /// settings until the returned guard is dropped.
+    pub fn bulk_load(&self, phase: BulkLoadPhase) -> BulkLoadGuard {
+        BulkLoadGuard::new(self.storage.clone(), phase)
+    }
+
     /// Fetches value from given column.
     ///
     /// If the key does not exist in the column returns `None`.  Otherwise
@@ -313,6 +343,13 @@ impl Store {
         self.get(column, key)?.as_deref().map(T::try_from_slice).transpose()
     }
 
+    /// Fetches the values of several keys of a column at once, in the order
+    /// of the keys.  With `io_uring` enabled in the config, RocksDB overlaps
+    /// the reads.
+    pub fn multi_get(&self, column: DBCol, keys: &[&[u8]]) -> io::Result<Vec<Option<DBSlice<'_>>>> {
+        self.storage.multi_get(column, keys)
+    }
+
     pub fn exists(&self, column: DBCol, key: &[u8]) -> io::Result<bool> {
         self.get(column, key).map(|value| value.is_some())
     }
@@ -321,6 +358,38 @@ impl Store {
         StoreUpdate::new(Arc::clone(&self.storage))
     }
 
+    /// Starts a transaction, a [`StoreUpdate`] whose reads with
+    /// [`StoreUpdate::get`] are checked on commit.  If any of the values it
+    /// read was changed by another commit in the meantime, the commit fails
+    /// with a [`TransactionConflict`] and writes nothing.
+    pub fn transaction(&self) -> StoreUpdate {
+        let mut store_update = self.store_update();
+        store_update.reads = Some(vec![]);
+        store_update
+    }
+
+    /// Builds a transaction with `f` and commits it.  On a conflict, `f` is
+    /// run again on a new transaction, up to `MAX_TRANSACTION_ATTEMPTS`
+    /// times.  Use it instead of ordering the writers of the same keys.
+    pub fn transact<R, E: From<io::Error>>(
+        &self,
+        mut f: impl FnMut(&mut StoreUpdate) -> Result<R, E>,
+    ) -> Result<R, E> {
+        let mut attempt = 1;
+        loop {
+            let mut transaction = self.transaction();
+            let result = f(&mut transaction)?;
+            match transaction.commit() {
+                Ok(()) => return Ok(result),
+                Err(err) if attempt < MAX_TRANSACTION_ATTEMPTS && is_conflict(&err) => {
+                    tracing::debug!(target: "store", attempt, ?err, "Retrying transaction");
+                    attempt += 1;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
     pub fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
         self.storage.iter(col)
     }
@@ -416,6 +485,29 @@ impl Store {
         self.storage.compact()
     }
 
+    /// Blocking compaction of the keys of the column within `[start, end)` if
+    /// supported by storage.
+    pub fn compact_range(
+        &self,
+        col: DBCol,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> io::Result<()> {
+        self.storage.compact_range(col, start, end)
+    }
+
+    /// Estimated number of bytes pending compaction in the column, `None` if
+    /// the storage doesn't compact.
+    pub fn compaction_debt(&self, col: DBCol) -> io::Result<Option<u64>> {
+        self.storage.compaction_debt(col)
+    }
+
+    /// Whether the storage delays or stops writes because compactions fall
+    /// behind.
+    pub fn is_write_stalled(&self) -> io::Result<bool> {
+        self.storage.is_write_stalled()
+    }
+
     pub fn get_store_statistics(&self) -> Option<StoreStatistics> {
         self.storage.get_store_statistics()
     }
@@ -443,10 +535,38 @@ impl Store {
     }
 }
 
+/// How many times [`Store::transact`] builds a transaction before giving up
+/// on the conflicts.
+const MAX_TRANSACTION_ATTEMPTS: usize = 10;
+
+/// A value read by a transaction, as stored in the database.
+struct TransactionRead {
+    column: DBCol,
+    key: Vec<u8>,
+    value: Option<Vec<u8>>,
+}
+
+/// A value read by a transaction was changed by another commit before the
+/// transaction was committed.
+#[derive(Debug, thiserror::Error)]
+#[error("transaction conflict on {column} {key}")]
+pub struct TransactionConflict {
+    pub column: DBCol,
+    pub key: String,
+}
+
+/// Whether the commit of a transaction failed with a [`TransactionConflict`].
+pub fn is_conflict(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<TransactionConflict>())
+}
+
 /// Keeps track of current changes to the database and can commit all of them to the database.
 pub struct StoreUpdate {
     transaction: DBTransaction,
     storage: Arc<dyn Database>,
+    /// Values read through the update, only recorded for transactions, see
+    /// [`Store::transaction`].
+    reads: Option<Vec<TransactionRead>>,
 }
 
 impl StoreUpdate {
@@ -456,7 +576,69 @@ impl StoreUpdate {
     };
 
     pub(crate) fn new(db: Arc<dyn Database>) -> Self {
-        StoreUpdate { transaction: DBTransaction::new(), storage: db }
+        StoreUpdate { transaction: DBTransaction::new(), storage: db, reads: None }
+    }
+
+    /// Fetches value from given column, as it will be once the update is
+    /// committed, i.e. including the changes made by the update so far.
+    ///
+    /// For transactions, values read from the database are checked for
+    /// conflicts on commit.
+    pub fn get(&mut self, column: DBCol, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let mut refcount_ops = vec![];
+        for op in self.transaction.ops.iter().rev() {
+            match op {
+                DBOp::Set { col, key: op_key, value }
+                | DBOp::Insert { col, key: op_key, value }
+                    if *col == column && op_key == key =>
+                {
+                    if refcount_ops.is_empty() {
+                        return Ok(Some(value.clone()));
+                    }
+                    let merged = refcount::refcount_merge(Some(value.as_slice()), refcount_ops);
+                    return Ok(refcount::strip_refcount(merged));
+                }
+                DBOp::Delete { col, key: op_key } if *col == column && op_key == key => {
+                    return Ok(None)
+                }
+                DBOp::DeleteAll { col } if *col == column => return Ok(None),
+                DBOp::DeleteRange { col, from, to }
+                    if *col == column && from.as_slice() <= key && key < to.as_slice() =>
+                {
+                    return Ok(None)
+                }
+                DBOp::UpdateRefcount { col, key: op_key, value }
+                    if *col == column && op_key == key =>
+                {
+                    refcount_ops.push(value.as_slice());
+                }
+                _ => {}
+            }
+        }
+
+        let value = self.storage.get_raw_bytes(column, key)?.map(|value| value.to_vec());
+        let result = if !column.is_rc() {
+            value.clone()
+        } else if refcount_ops.is_empty() {
+            value.clone().and_then(refcount::strip_refcount)
+        } else {
+            // The operands are collected newest first, but their order
+            // doesn't change the sum.
+            refcount::strip_refcount(refcount::refcount_merge(value.as_deref(), refcount_ops))
+        };
+        if let Some(reads) = &mut self.reads {
+            reads.push(TransactionRead { column, key: key.to_vec(), value });
+        }
+        Ok(result)
+    }
+
+    /// Like [`Self::get`], deserializing the value.
+    pub fn get_ser<T: BorshDeserialize>(
+        &mut self,
+        column: DBCol,
+        key: &[u8],
+    ) -> io::Result<Option<T>> {
+        self.get(column, key)?.as_deref().map(T::try_from_slice).transpose()
     }
 
     /// Inserts a new value into the database.
@@ -600,11 +782,34 @@ impl StoreUpdate {
     /// Merge another store update into this one.
     ///
     /// Panics if `self`’s and `other`’s storage are incompatible.
+    ///
+    /// If `other` is a transaction, so is the merged update.
     pub fn merge(&mut self, other: StoreUpdate) {
         assert!(core::ptr::addr_eq(Arc::as_ptr(&self.storage), Arc::as_ptr(&other.storage)));
+        if let Some(other_reads) = other.reads {
+            self.reads.get_or_insert_with(Vec::new).extend(other_reads);
+        }
         self.transaction.merge(other.transaction)
     }
 
+    /// Checks that the values read by the transaction are still the ones in
+    /// the database.
+    fn check_reads(storage: &dyn Database, reads: &[TransactionRead]) -> io::Result<()> {
+        for read in reads {
+            let value = storage.get_raw_bytes(read.column, &read.key)?;
+            if value.as_deref() != read.value.as_deref() {
+                metrics::TRANSACTION_CONFLICTS
+                    .with_label_values(&[<&str>::from(read.column)])
+                    .inc();
+                return Err(io::Error::other(TransactionConflict {
+                    column: read.column,
+                    key: StorageKey(&read.key).to_string(),
+                }));
+            }
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(
         level = "trace",
         target = "store::update",
@@ -718,7 +923,15 @@ impl StoreUpdate {
                 }
             }
         }
-        self.storage.write(self.transaction)
+        let Self { transaction, storage, reads } = self;
+        match reads {
+            // The database lets no other write land between checking the
+            // reads and writing the transaction.
+            Some(reads) => {
+                storage.write_checked(transaction, &|| Self::check_reads(storage.as_ref(), &reads))
+            }
+            None => storage.write(transaction),
+        }
     }
 }
 
@@ -927,6 +1140,55 @@ pub fn get_buffered_receipt_indices(
     Ok(get(trie, &TrieKey::BufferedReceiptIndices)?.unwrap_or_default())
 }
 
+pub fn get_idempotency_keys(
+    trie: &dyn TrieAccess,
+    account_id: &AccountId,
+    predecessor_id: &AccountId,
+) -> Result<Option<IdempotencyKeys>, StorageError> {
+    get(
+        trie,
+        &TrieKey::IdempotencyKeys {
+            account_id: account_id.clone(),
+            predecessor_id: predecessor_id.clone(),
+        },
+    )
+}
+
+pub fn set_idempotency_keys(
+    state_update: &mut TrieUpdate,
+    account_id: AccountId,
+    predecessor_id: AccountId,
+    idempotency_keys: &IdempotencyKeys,
+) {
+    set(state_update, TrieKey::IdempotencyKeys { account_id, predecessor_id }, idempotency_keys);
+}
+
+/// Removes the idempotency keys of all the predecessors of the account.
+pub fn remove_idempotency_keys(
+    state_update: &mut TrieUpdate,
+    account_id: &AccountId,
+) -> Result<(), StorageError> {
+    let lock = state_update.trie().lock_for_iter();
+    let predecessor_ids = state_update
+        .locked_iter(&trie_key_parsers::get_raw_prefix_for_idempotency_keys(account_id), &lock)?
+        .map(|raw_key| {
+            trie_key_parsers::parse_predecessor_id_from_idempotency_keys_key(&raw_key?, account_id)
+                .map_err(|_e| {
+                    StorageError::StorageInconsistentState(
+                        "Can't parse predecessor id from raw key for IdempotencyKeys".to_string(),
+                    )
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    drop(lock);
+
+    for predecessor_id in predecessor_ids {
+        state_update
+            .remove(TrieKey::IdempotencyKeys { account_id: account_id.clone(), predecessor_id });
+    }
+    Ok(())
+}
+
 pub fn set_access_key(
     state_update: &mut TrieUpdate,
     account_id: AccountId,
@@ -979,6 +1241,38 @@ pub fn get_code(
     trie.get(&key).map(|opt| opt.map(|code| ContractCode::new(code, code_hash)))
 }
 
+pub fn set_namespaced_code(
+    state_update: &mut TrieUpdate,
+    account_id: AccountId,
+    namespace: AccountNamespace,
+    code: &ContractCode,
+) {
+    state_update
+        .set(TrieKey::NamespacedContractCode { account_id, namespace }, code.code().to_vec());
+}
+
+pub fn get_namespaced_code(
+    trie: &dyn TrieAccess,
+    account_id: &AccountId,
+    namespace: &AccountNamespace,
+    code_hash: Option<CryptoHash>,
+) -> Result<Option<ContractCode>, StorageError> {
+    let key = TrieKey::NamespacedContractCode {
+        account_id: account_id.clone(),
+        namespace: namespace.clone(),
+    };
+    trie.get(&key).map(|opt| opt.map(|code| ContractCode::new(code, code_hash)))
+}
+
+pub fn set_global_code(state_update: &mut TrieUpdate, code: &ContractCode) {
+    state_update.set(TrieKey::GlobalContractCode { code_hash: *code.hash() }, code.code().to_vec());
+}
+
+/// Whether the shard stores the global contract with the given hash.
+pub fn has_global_code(trie: &dyn TrieAccess, code_hash: CryptoHash) -> Result<bool, StorageError> {
+    trie.contains_key(&TrieKey::GlobalContractCode { code_hash })
+}
+
 /// Removes account, code and all access keys associated to it.
 pub fn remove_account(
     state_update: &mut TrieUpdate,
@@ -1026,6 +1320,31 @@ pub fn remove_account(
     for key in data_keys {
         state_update.remove(TrieKey::ContractData { account_id: account_id.clone(), key });
     }
+
+    // Removing the code and data of the namespaced contracts
+    use near_primitives::trie_key::col::{CONTRACT_CODE, CONTRACT_DATA};
+    for column in [CONTRACT_CODE, CONTRACT_DATA] {
+        let lock = state_update.trie().lock_for_iter();
+        let namespaced_keys = state_update
+            .locked_iter(
+                &trie_key_parsers::get_raw_prefix_for_namespaces(column, account_id),
+                &lock,
+            )?
+            .map(|raw_key| {
+                trie_key_parsers::parse_trie_key_namespaced_contract_from_raw_key(&raw_key?)
+                    .map_err(|_e| {
+                        StorageError::StorageInconsistentState(
+                            "Can't parse raw key of a namespaced contract".to_string(),
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(lock);
+
+        for key in namespaced_keys {
+            state_update.remove(key);
+        }
+    }
     Ok(())
 }
 
@@ -1176,6 +1495,94 @@ mod tests {
         test_clear_column(crate::test_utils::create_test_store());
     }
 
+    fn test_multi_get(store: Store) {
+        let mut store_update = store.store_update();
+        store_update.increment_refcount(DBCol::State, &[1], &[1]);
+        store_update.increment_refcount(DBCol::State, &[3], &[3]);
+        store_update.commit().unwrap();
+        let values = store.multi_get(DBCol::State, &[&[1], &[2], &[3]]).unwrap();
+        let values: Vec<_> = values.iter().map(|value| value.as_deref()).collect();
+        assert_eq!(values, vec![Some(&[1][..]), None, Some(&[3][..])]);
+    }
+
+    #[test]
+    fn multi_get_rocksdb() {
+        let (_tmp_dir, opener) = NodeStorage::test_opener();
+        test_multi_get(opener.open().unwrap().get_hot_store());
+    }
+
+    #[test]
+    fn multi_get_testdb() {
+        test_multi_get(crate::test_utils::create_test_store());
+    }
+
+    #[test]
+    fn test_store_update_read_your_writes() {
+        let store = crate::test_utils::create_test_store();
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::BlockMisc, &[1], &[1]);
+        store_update.increment_refcount(DBCol::State, &[1], &[1]);
+        store_update.commit().unwrap();
+
+        let mut store_update = store.store_update();
+        assert_eq!(store_update.get(DBCol::BlockMisc, &[1]).unwrap(), Some(vec![1]));
+        store_update.set(DBCol::BlockMisc, &[1], &[2]);
+        assert_eq!(store_update.get(DBCol::BlockMisc, &[1]).unwrap(), Some(vec![2]));
+        store_update.delete_range(DBCol::BlockMisc, &[0], &[2]);
+        assert_eq!(store_update.get(DBCol::BlockMisc, &[1]).unwrap(), None);
+
+        assert_eq!(store_update.get(DBCol::State, &[1]).unwrap(), Some(vec![1]));
+        store_update.decrement_refcount(DBCol::State, &[1]);
+        assert_eq!(store_update.get(DBCol::State, &[1]).unwrap(), None);
+        store_update.increment_refcount(DBCol::State, &[2], &[2]);
+        assert_eq!(store_update.get(DBCol::State, &[2]).unwrap(), Some(vec![2]));
+    }
+
+    #[test]
+    fn test_transaction_conflict() {
+        let store = crate::test_utils::create_test_store();
+        let mut first = store.transaction();
+        let mut second = store.transaction();
+        assert_eq!(first.get(DBCol::BlockMisc, &[1]).unwrap(), None);
+        assert_eq!(second.get(DBCol::BlockMisc, &[1]).unwrap(), None);
+        first.set(DBCol::BlockMisc, &[1], &[1]);
+        second.set(DBCol::BlockMisc, &[1], &[2]);
+        first.commit().unwrap();
+        assert!(super::is_conflict(&second.commit().unwrap_err()));
+        assert_eq!(store.get(DBCol::BlockMisc, &[1]).unwrap().as_deref(), Some(&[1][..]));
+
+        // A plain store update isn't checked.
+        let mut store_update = store.store_update();
+        assert_eq!(store_update.get(DBCol::BlockMisc, &[1]).unwrap(), Some(vec![1]));
+        let mut transaction = store.transaction();
+        transaction.get(DBCol::BlockMisc, &[1]).unwrap();
+        transaction.commit().unwrap();
+        store_update.set(DBCol::BlockMisc, &[1], &[3]);
+        store_update.commit().unwrap();
+    }
+
+    #[test]
+    fn test_transact_retries() {
+        let store = crate::test_utils::create_test_store();
+        let mut attempts = 0;
+        store
+            .transact(|transaction| -> std::io::Result<()> {
+                attempts += 1;
+                let counter = transaction.get(DBCol::BlockMisc, &[1])?.map_or(0, |v| v[0]);
+                if attempts == 1 {
+                    // Another writer increments the counter concurrently.
+                    let mut store_update = store.store_update();
+                    store_update.set(DBCol::BlockMisc, &[1], &[counter + 1]);
+                    store_update.commit()?;
+                }
+                transaction.set(DBCol::BlockMisc, &[1], &[counter + 1]);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(attempts, 2);
+        assert_eq!(store.get(DBCol::BlockMisc, &[1]).unwrap().as_deref(), Some(&[2][..]));
+    }
+
     /// Asserts that elements in the vector are sorted.
     #[track_caller]
     fn assert_sorted(want_count: usize, keys: Vec<Box<[u8]>>) {
@@ -0,0 +1,301 @@
+//! Sizing the in-memory caches of the node from a common memory budget.
+//!
+//! The trie caches of the shards and the cache of loaded contracts are each
+//! configured with a fixed size, which is either too small for the shards
+//! and contracts that are busy or wasted on the ones that aren't.  With the
+//! budget enabled, the caches register themselves and the budget is
+//! periodically redistributed among them: each cache gets a share
+//! proportional to the memory it would have needed to serve its misses
+//! since the previous redistribution.  The budget shrinks as the RAM
+//! available on the host does.
+//!
+//! The deltas of flat storage aren't a cache, flat storage can't work
+//! without them, so they are bounded by `flat_storage_deltas` of the store
+//! config instead.
+
+use crate::config::MemoryBudgetConfig;
+use crate::metrics;
+use near_vm_runner::AnyCache;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the loop checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Loaded contracts are counted in entries rather than bytes, their size
+/// isn't known.  This is roughly the size of a large compiled contract.
+const CONTRACT_CACHE_ENTRY_SIZE: u64 = 4 * bytesize::MIB;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    /// Lookups which found their key, since the cache was created.
+    pub hits: u64,
+    /// Lookups which didn't find their key, since the cache was created.
+    pub misses: u64,
+    /// Bytes taken by the cached entries.
+    pub size: u64,
+    pub size_limit: u64,
+    /// Average size of a cached entry, i.e. the memory a miss would have
+    /// needed to be a hit.
+    pub entry_size: u64,
+}
+
+/// A cache whose size the budget manages.
+pub trait BudgetedCache: Send + Sync {
+    /// Name of the cache in the metrics.
+    fn name(&self) -> String;
+    fn usage(&self) -> CacheUsage;
+    /// Sets the size limit, evicting entries if the cache is larger.
+    fn set_size_limit(&self, size_limit: u64);
+}
+
+impl BudgetedCache for AnyCache {
+    fn name(&self) -> String {
+        "contracts".to_string()
+    }
+
+    fn usage(&self) -> CacheUsage {
+        let size_limit = self.capacity() as u64 * CONTRACT_CACHE_ENTRY_SIZE;
+        CacheUsage {
+            hits: self.hits(),
+            misses: self.misses(),
+            // The size of the loaded contracts isn't known, assume the cache
+            // is full.
+            size: size_limit,
+            size_limit,
+            entry_size: CONTRACT_CACHE_ENTRY_SIZE,
+        }
+    }
+
+    fn set_size_limit(&self, size_limit: u64) {
+        let entries = (size_limit / CONTRACT_CACHE_ENTRY_SIZE).max(1);
+        self.resize(NonZeroUsize::new(entries as usize).unwrap());
+    }
+}
+
+struct RegisteredCache {
+    cache: Weak<dyn BudgetedCache>,
+    /// Usage at the previous redistribution.
+    last_usage: CacheUsage,
+}
+
+pub struct MemoryBudget {
+    config: MemoryBudgetConfig,
+    caches: Mutex<Vec<RegisteredCache>>,
+}
+
+impl MemoryBudget {
+    pub fn new(config: MemoryBudgetConfig) -> Self {
+        Self { config, caches: Mutex::new(vec![]) }
+    }
+
+    /// Adds a cache to the budget.  The cache leaves the budget once it is
+    /// dropped.
+    pub fn register(&self, cache: Weak<dyn BudgetedCache>) {
+        let last_usage = cache.upgrade().map(|cache| cache.usage()).unwrap_or_default();
+        self.caches.lock().unwrap().push(RegisteredCache { cache, last_usage });
+    }
+
+    /// Redistributes the budget among the caches, given the RAM available
+    /// on the host, if known.
+    pub fn rebalance(&self, available_memory: Option<u64>) {
+        let mut registered = self.caches.lock().unwrap();
+        registered.retain(|registered| registered.cache.strong_count() > 0);
+        let caches: Vec<(Arc<dyn BudgetedCache>, CacheUsage, u64)> = registered
+            .iter_mut()
+            .filter_map(|registered| {
+                let cache = registered.cache.upgrade()?;
+                let usage = cache.usage();
+                let misses = usage.misses.saturating_sub(registered.last_usage.misses);
+                registered.last_usage = usage;
+                Some((cache, usage, misses.saturating_mul(usage.entry_size)))
+            })
+            .collect();
+        if caches.is_empty() {
+            return;
+        }
+
+        let used: u64 = caches.iter().map(|(_, usage, _)| usage.size).sum();
+        let mut budget = self.config.max_bytes.as_u64();
+        if let Some(available) = available_memory {
+            let percent = u64::from(self.config.max_available_memory_percent.min(100));
+            budget = budget.min(available.saturating_add(used) / 100 * percent);
+        }
+        metrics::MEMORY_BUDGET_BYTES.set(budget as i64);
+
+        let min_cache_bytes = self.config.min_cache_bytes.as_u64();
+        let spare = budget.saturating_sub(min_cache_bytes * caches.len() as u64);
+        let total_limit: u64 = caches.iter().map(|(_, usage, _)| usage.size_limit).sum();
+        let total_missed: u64 = caches.iter().map(|(_, _, missed)| missed).sum();
+        // Without misses there is nothing to learn from, the caches keep
+        // their shares unless they don't fit anymore.
+        if total_missed == 0 && total_limit <= budget {
+            return;
+        }
+        for (cache, usage, missed) in &caches {
+            let size_limit = if total_missed == 0 {
+                (budget as u128 * usage.size_limit as u128 / total_limit as u128) as u64
+            } else {
+                let share = (spare as u128 * *missed as u128 / total_missed as u128) as u64;
+                let target = min_cache_bytes + share;
+                // Move halfway towards the share so that a single burst of
+                // misses doesn't flush the other caches, unless the caches
+                // are over the budget.
+                if total_limit > budget {
+                    target.min(usage.size_limit)
+                } else {
+                    usage.size_limit / 2 + target / 2
+                }
+            };
+            if size_limit != usage.size_limit {
+                cache.set_size_limit(size_limit);
+            }
+            metrics::MEMORY_BUDGET_CACHE_LIMIT_BYTES
+                .with_label_values(&[&cache.name()])
+                .set(size_limit as i64);
+        }
+    }
+}
+
+/// Returns the RAM available on the host.
+fn available_memory() -> u64 {
+    use sysinfo::{System, SystemExt};
+    let mut system = System::new();
+    system.refresh_memory();
+    // sysinfo reports KiB.
+    system.available_memory() * 1024
+}
+
+pub struct MemoryBudgetHandle {
+    handle: JoinHandle<()>,
+    keep_running: Arc<AtomicBool>,
+}
+
+impl MemoryBudgetHandle {
+    /// Starts redistributing the budget every `rebalance_period`.
+    pub fn start(budget: Arc<MemoryBudget>) -> Self {
+        let keep_running = Arc::new(AtomicBool::new(true));
+        let keep_running_clone = keep_running.clone();
+        let period = budget.config.rebalance_period;
+        let handle = std::thread::spawn(move || {
+            while keep_running.load(Ordering::Relaxed) {
+                let next_run = Instant::now() + period;
+                while keep_running.load(Ordering::Relaxed) && Instant::now() < next_run {
+                    std::thread::sleep(STOP_POLL_INTERVAL.min(period));
+                }
+                budget.rebalance(Some(available_memory()));
+            }
+        });
+        Self { handle, keep_running: keep_running_clone }
+    }
+
+    pub fn stop(self) {
+        self.keep_running.store(false, Ordering::Relaxed);
+        self.handle.join().expect("join should not fail here");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BudgetedCache, CacheUsage, MemoryBudget};
+    use crate::config::MemoryBudgetConfig;
+    use bytesize::ByteSize;
+    use std::sync::{Arc, Mutex};
+
+    struct TestCache(Mutex<CacheUsage>);
+
+    impl TestCache {
+        fn new(size_limit: u64) -> Arc<Self> {
+            Arc::new(Self(Mutex::new(CacheUsage {
+                size: size_limit,
+                size_limit,
+                entry_size: 1,
+                ..Default::default()
+            })))
+        }
+
+        fn miss(&self, misses: u64) {
+            self.0.lock().unwrap().misses += misses;
+        }
+
+        fn size_limit(&self) -> u64 {
+            self.0.lock().unwrap().size_limit
+        }
+    }
+
+    impl BudgetedCache for TestCache {
+        fn name(&self) -> String {
+            "test".to_string()
+        }
+
+        fn usage(&self) -> CacheUsage {
+            *self.0.lock().unwrap()
+        }
+
+        fn set_size_limit(&self, size_limit: u64) {
+            let mut usage = self.0.lock().unwrap();
+            usage.size_limit = size_limit;
+            usage.size = usage.size.min(size_limit);
+        }
+    }
+
+    fn register(budget: &MemoryBudget, cache: &Arc<TestCache>) {
+        let cache: Arc<dyn BudgetedCache> = cache.clone();
+        budget.register(Arc::downgrade(&cache));
+    }
+
+    /// The cache which misses gets more memory, taken from the one which
+    /// doesn't.
+    #[test]
+    fn test_rebalance_by_misses() {
+        let budget = MemoryBudget::new(MemoryBudgetConfig {
+            enabled: true,
+            max_bytes: ByteSize::b(1000),
+            min_cache_bytes: ByteSize::b(100),
+            ..Default::default()
+        });
+        let busy = TestCache::new(500);
+        let idle = TestCache::new(500);
+        register(&budget, &busy);
+        register(&budget, &idle);
+
+        for _ in 0..10 {
+            busy.miss(10);
+            budget.rebalance(None);
+        }
+        assert!(busy.size_limit() > 850, "{}", busy.size_limit());
+        assert!(idle.size_limit() >= 100 && idle.size_limit() < 150, "{}", idle.size_limit());
+
+        // Without misses the shares stay.
+        let limits = (busy.size_limit(), idle.size_limit());
+        budget.rebalance(None);
+        assert_eq!((busy.size_limit(), idle.size_limit()), limits);
+    }
+
+    /// The caches shrink at once when the host runs low on RAM.
+    #[test]
+    fn test_rebalance_low_memory() {
+        let budget = MemoryBudget::new(MemoryBudgetConfig {
+            enabled: true,
+            max_bytes: ByteSize::b(1000),
+            max_available_memory_percent: 50,
+            min_cache_bytes: ByteSize::b(10),
+            ..Default::default()
+        });
+        let first = TestCache::new(500);
+        let second = TestCache::new(500);
+        register(&budget, &first);
+        register(&budget, &second);
+
+        // (0 available + 1000 used) * 50% leaves 500 for both.
+        budget.rebalance(Some(0));
+        assert_eq!(first.size_limit() + second.size_limit(), 500);
+
+        drop(second);
+        budget.rebalance(Some(0));
+        assert_eq!(budget.caches.lock().unwrap().len(), 1);
+    }
+}
@@ -22,6 +22,20 @@ pub(super) const VERSION_KEY: &[u8; 7] = b"VERSION";
 /// The key holds a [`DbKind`] value serialised to a string.
 pub(super) const KIND_KEY: &[u8; 4] = b"KIND";
 
+/// Key for the [`crate::migrations::MigrationRecord`] of the migration in
+/// progress in DBCol::DbVersion.
+pub(super) const MIGRATION_KEY: &[u8; 9] = b"MIGRATION";
+
+/// Key for the saved [`crate::migrations::MigrationProgress`] of the
+/// migration in progress in DBCol::DbVersion.
+pub(super) const MIGRATION_PROGRESS_KEY: &[u8; 18] = b"MIGRATION_PROGRESS";
+
+/// Prefix of the keys of the zstd dictionaries of [`crate::db::CompressedDB`]
+/// in DBCol::DbVersion, followed by the big-endian id of the dictionary.
+/// DBCol::DbVersion is kept by every checkpoint, so the values of checkpoints
+/// can be decompressed as well.
+pub(crate) const COMPRESSION_DICTIONARY_KEY_PREFIX: &[u8; 23] = b"COMPRESSION_DICTIONARY:";
+
 /// Describes what kind the storage is.
 #[derive(
     Clone, Copy, Debug, Eq, PartialEq, strum::Display, strum::EnumString, strum::IntoStaticStr,
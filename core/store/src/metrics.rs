@@ -19,6 +19,163 @@ pub(crate) static DATABASE_OP_LATENCY_HIST: LazyLock<HistogramVec> = LazyLock::n
     .unwrap()
 });
 
+pub(crate) static BULK_LOAD_ACTIVE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_database_bulk_load_active",
+        "Number of bulk load phases during which the database uses bulk load settings",
+        &["phase"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DISK_AVAILABLE_BYTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_disk_available_bytes",
+        "Free space available to the node on the disks of the hot and cold databases",
+        &["database"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DISK_PRESSURE_LEVEL: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_disk_pressure_level",
+        "Degradation of the node due to low free disk space: 0 normal, 1 reduced writes, 2 critical, 3 halting",
+    )
+    .unwrap()
+});
+
+pub(crate) static SECONDARY_CATCH_UP_TIME: LazyLock<Histogram> = LazyLock::new(|| {
+    try_create_histogram(
+        "near_secondary_catch_up_time",
+        "Time taken by a RocksDB secondary instance to catch up with the primary",
+    )
+    .unwrap()
+});
+
+pub(crate) static SECONDARY_HEAD_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_secondary_head_height",
+        "Height of the head seen by a RocksDB secondary instance after catching up",
+    )
+    .unwrap()
+});
+
+pub(crate) static SECONDARY_LAG_MS: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_secondary_lag_ms",
+        "Milliseconds since the head block seen by a RocksDB secondary instance was produced",
+    )
+    .unwrap()
+});
+
+pub(crate) static TRANSACTION_CONFLICTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_store_transaction_conflicts",
+        "Commits of transactions which failed because another commit changed a value they read",
+        &["column"],
+    )
+    .unwrap()
+});
+
+pub(crate) static MEMORY_BUDGET_BYTES: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_memory_budget_bytes",
+        "Memory available to the caches managed by the memory budget",
+    )
+    .unwrap()
+});
+
+pub(crate) static MEMORY_BUDGET_CACHE_LIMIT_BYTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_memory_budget_cache_limit_bytes",
+        "Size limit the memory budget assigned to a cache",
+        &["cache"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DATABASE_OPS_BY_SUBSYSTEM: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_database_ops_by_subsystem",
+        "Number of database reads, iterated entries and writes by the subsystem issuing them",
+        &["subsystem", "op"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DATABASE_BYTES_BY_SUBSYSTEM: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_database_bytes_by_subsystem",
+        "Number of bytes read from or written to the database by the subsystem issuing the operation",
+        &["subsystem", "op"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DATABASE_OPS_BY_COLUMN: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_database_ops_by_column",
+        "Number of database reads, iterated entries and writes by column",
+        &["column", "op"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DATABASE_BYTES_BY_COLUMN: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_database_bytes_by_column",
+        "Number of bytes read from or written to the database by column",
+        &["column", "op"],
+    )
+    .unwrap()
+});
+
+pub(crate) static IO_THROTTLE_DELAY_MS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_io_throttle_delay_ms",
+        "Time background work spent waiting for chunk application to speed up, by subsystem",
+        &["subsystem"],
+    )
+    .unwrap()
+});
+
+pub(crate) static IO_THROTTLE_CHUNK_APPLY_LATENCY_MS: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_io_throttle_chunk_apply_latency_ms",
+        "Moving average of the chunk application latency which background IO is throttled on",
+    )
+    .unwrap()
+});
+
+pub(crate) static STATE_ROOT_AUDITS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_state_root_audits",
+        "Audits of the state roots computed from flat storage, by shard and result",
+        &["shard_uid", "result"],
+    )
+    .unwrap()
+});
+
+pub(crate) static STATE_ROOT_AUDIT_DIVERGENCE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_state_root_audit_divergence",
+        "Whether the last audit of the shard found a state root computed from flat storage which differs from the committed one",
+        &["shard_uid"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DATABASE_OP_LATENCY_BY_SUBSYSTEM: LazyLock<HistogramVec> = LazyLock::new(|| {
+    try_create_histogram_vec(
+        "near_database_op_latency_by_subsystem",
+        "Database reads and writes latency by the subsystem issuing them",
+        &["subsystem", "op"],
+        Some(vec![0.00002, 0.0001, 0.0002, 0.0005, 0.0008, 0.001, 0.002, 0.004, 0.008, 0.1, 0.5]),
+    )
+    .unwrap()
+});
+
 // TODO(#9054): Rename the metric to be consistent with "accounting cache".
 pub static CHUNK_CACHE_HITS: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
@@ -245,6 +402,20 @@ pub(crate) static HAS_STATE_SNAPSHOT: LazyLock<IntGauge> = LazyLock::new(|| {
         .unwrap()
 });
 
+pub(crate) static STATE_CHECKPOINTS: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge("near_state_checkpoints", "Number of state checkpoints kept by the node")
+        .unwrap()
+});
+
+pub(crate) static CREATE_STATE_CHECKPOINT_ELAPSED: LazyLock<Histogram> = LazyLock::new(|| {
+    try_create_histogram_with_buckets(
+        "near_create_state_checkpoint_elapsed_sec",
+        "Latency of making a state checkpoint, in seconds",
+        exponential_buckets(0.01, 1.3, 30).unwrap(),
+    )
+    .unwrap()
+});
+
 pub(crate) static CREATE_STATE_SNAPSHOT_ELAPSED: LazyLock<Histogram> = LazyLock::new(|| {
     try_create_histogram_with_buckets(
         "near_make_state_snapshot_elapsed_sec",
@@ -440,6 +611,14 @@ pub mod flat_state_metrics {
         )
         .unwrap()
     });
+    pub static FLAT_STORAGE_COMPACTED_DELTAS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_flat_storage_compacted_deltas",
+            "Number of deltas applied to flat state before their block became final, to keep the number of deltas within the configured limits",
+            &["shard_uid"],
+        )
+        .unwrap()
+    });
     pub static FLAT_STORAGE_CACHED_DELTAS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
         try_create_int_gauge_vec(
             "near_flat_storage_cached_deltas",
@@ -480,6 +659,40 @@ pub mod flat_state_metrics {
         )
         .unwrap()
     });
+    pub static FLAT_STORAGE_KEY_FILTER_SKIPPED_READS: LazyLock<IntCounterVec> = LazyLock::new(
+        || {
+            try_create_int_counter_vec(
+                "near_flat_storage_key_filter_skipped_reads",
+                "Number of reads of missing keys which the flat state key filter answered without reading the database",
+                &["shard_uid"],
+            )
+            .unwrap()
+        },
+    );
+    pub static FLAT_STORAGE_KEY_FILTER_SIZE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        try_create_int_gauge_vec(
+            "near_flat_storage_key_filter_size",
+            "Memory taken by the flat state key filter in bytes, zero until it is built",
+            &["shard_uid"],
+        )
+        .unwrap()
+    });
+    pub static FLAT_STORAGE_MMAP_READS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        try_create_int_counter_vec(
+            "near_flat_storage_mmap_reads",
+            "Number of flat state reads answered by the memory mapped flat state file",
+            &["shard_uid"],
+        )
+        .unwrap()
+    });
+    pub static FLAT_STORAGE_MMAP_SIZE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        try_create_int_gauge_vec(
+            "near_flat_storage_mmap_size",
+            "Size of the memory mapped flat state file in bytes, zero until it is built",
+            &["shard_uid"],
+        )
+        .unwrap()
+    });
 
     pub mod inlining_migration {
         use near_o11y::metrics::{
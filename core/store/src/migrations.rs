@@ -1,4 +1,5 @@
-use crate::metadata::DbKind;
+use crate::db::StatsValue;
+use crate::metadata::{DbKind, DbVersion, MIGRATION_KEY, MIGRATION_PROGRESS_KEY};
 use crate::{DBCol, Store, StoreUpdate};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_primitives::epoch_manager::EpochSummary;
@@ -14,8 +15,238 @@ use near_primitives::types::{BlockChunkValidatorStats, ChunkStats};
 use near_primitives::utils::get_outcome_id_block_hash;
 use near_primitives::version::ProtocolVersion;
 use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use tracing::info;
 
+/// How often the progress of a migration is logged.
+const REPORT_PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How much of the columns a migration reads is read to measure the read
+/// throughput of the database for the estimates.
+const ESTIMATE_SAMPLE_BYTES: u64 = 64 * bytesize::MIB;
+
+/// Migration of the database which is underway.  Recorded in the database
+/// when the migration starts and deleted once it succeeds, so that a node
+/// restarted after an interruption continues the migration with the
+/// snapshot taken before it rather than failing on the existing snapshot.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MigrationRecord {
+    /// Version of the database when the migration started.
+    pub from_version: DbVersion,
+    /// Snapshot of the database at `from_version`, if snapshots are enabled.
+    pub snapshot: Option<PathBuf>,
+}
+
+impl MigrationRecord {
+    pub fn read(store: &Store) -> io::Result<Option<Self>> {
+        store
+            .get(DBCol::DbVersion, MIGRATION_KEY)?
+            .map(|value| serde_json::from_slice(&value).map_err(io::Error::other))
+            .transpose()
+    }
+
+    pub fn write(&self, store: &Store) -> io::Result<()> {
+        let mut update = store.store_update();
+        update.set(DBCol::DbVersion, MIGRATION_KEY, &serde_json::to_vec(self)?);
+        update.commit()
+    }
+
+    pub fn delete(store: &Store) -> io::Result<()> {
+        let mut update = store.store_update();
+        update.delete(DBCol::DbVersion, MIGRATION_KEY);
+        update.commit()
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct SavedProgress {
+    version: DbVersion,
+    last_key: Vec<u8>,
+    done: u64,
+}
+
+/// Progress of a migration which iterates over the rows of a column.
+///
+/// The progress is saved with every batch of [`BatchedStoreUpdate`], so if
+/// the migration is interrupted, it continues after the last row of the
+/// last batch written instead of from the beginning.  It is also logged
+/// every [`REPORT_PROGRESS_INTERVAL`] with the time left if the total number
+/// of rows is known.
+pub struct MigrationProgress {
+    version: DbVersion,
+    /// Key of the last row processed.
+    last_key: Option<Vec<u8>>,
+    done: u64,
+    total: Option<u64>,
+    /// Rows done when this run of the migration started.
+    done_at_start: u64,
+    started: Instant,
+    last_report: Instant,
+}
+
+impl MigrationProgress {
+    /// Loads the progress saved by an interrupted run of the migration from
+    /// `version`, if any.
+    pub fn load(store: &Store, version: DbVersion) -> io::Result<Self> {
+        let saved = store
+            .get_ser::<SavedProgress>(DBCol::DbVersion, MIGRATION_PROGRESS_KEY)?
+            .filter(|saved| saved.version == version);
+        let (last_key, done) = match saved {
+            Some(saved) => {
+                info!(target: "migrations", version, done = saved.done, "Resuming interrupted migration");
+                (Some(saved.last_key), saved.done)
+            }
+            None => (None, 0),
+        };
+        let now = Instant::now();
+        Ok(Self {
+            version,
+            last_key,
+            done,
+            total: None,
+            done_at_start: done,
+            started: now,
+            last_report: now,
+        })
+    }
+
+    /// Key to start iterating from, skipping the rows which are done.
+    pub fn resume_from(&self) -> Option<Vec<u8>> {
+        self.last_key.as_ref().map(|key| {
+            let mut next = key.clone();
+            next.push(0);
+            next
+        })
+    }
+
+    /// Sets the total number of rows, e.g. estimated with
+    /// [`estimate_num_keys`].
+    pub fn set_total(&mut self, total: Option<u64>) {
+        self.total = total;
+    }
+
+    /// Records that the row with the given key was migrated.
+    pub fn advance(&mut self, key: &[u8]) {
+        self.last_key = Some(key.to_vec());
+        self.done += 1;
+        if self.last_report.elapsed() >= REPORT_PROGRESS_INTERVAL {
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        self.last_report = Instant::now();
+        let elapsed = self.started.elapsed();
+        let rate = (self.done - self.done_at_start) as f64 / elapsed.as_secs_f64().max(1.0);
+        match self.total {
+            Some(total) if total > self.done && rate > 0.0 => {
+                let eta = Duration::from_secs_f64((total - self.done) as f64 / rate);
+                let percent = self.done as f64 * 100.0 / total as f64;
+                info!(target: "migrations", version = self.version, done = self.done, total, "Migration {percent:.1}% done, about {eta:?} left");
+            }
+            _ => {
+                info!(target: "migrations", version = self.version, done = self.done, "Migration in progress")
+            }
+        }
+    }
+
+    fn save(&self, update: &mut StoreUpdate) -> io::Result<()> {
+        let Some(last_key) = &self.last_key else { return Ok(()) };
+        let saved =
+            SavedProgress { version: self.version, last_key: last_key.clone(), done: self.done };
+        update.set_ser(DBCol::DbVersion, MIGRATION_PROGRESS_KEY, &saved)
+    }
+
+    fn finish(&self, store: &Store) -> io::Result<()> {
+        info!(target: "migrations", version = self.version, done = self.done, elapsed = ?self.started.elapsed(), "Migration done");
+        let mut update = store.store_update();
+        update.delete(DBCol::DbVersion, MIGRATION_PROGRESS_KEY);
+        update.commit()
+    }
+}
+
+/// Returns RocksDB's estimate of the number of keys in the column.
+pub fn estimate_num_keys(store: &Store, col: DBCol) -> Option<u64> {
+    column_statistic(store, "rocksdb.estimate-num-keys", col)
+}
+
+fn column_statistic(store: &Store, name: &str, col: DBCol) -> Option<u64> {
+    let statistics = store.get_store_statistics()?;
+    let (_, values) = statistics.data.iter().find(|(stat_name, _)| stat_name == name)?;
+    values.iter().find_map(|value| match value {
+        StatsValue::ColumnValue(value_col, value) if *value_col == col => Some(*value as u64),
+        _ => None,
+    })
+}
+
+/// What a migration does, for estimating it without running it.
+#[derive(Clone, Debug, Default)]
+pub struct MigrationDescription {
+    pub summary: &'static str,
+    /// Columns the migration iterates over.
+    pub reads: Vec<DBCol>,
+    /// Columns the migration writes.  Columns which are only deleted with
+    /// [`StoreUpdate::delete_all`] aren't listed, deleting them is cheap.
+    pub writes: Vec<DBCol>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MigrationEstimate {
+    pub version: DbVersion,
+    pub summary: &'static str,
+    pub read_bytes: u64,
+    /// Assumes that the migration writes about as much as it reads.  The
+    /// space is taken until compactions drop the old data.
+    pub write_bytes: u64,
+    /// Extrapolated from the time it took to read a sample of the columns.
+    /// `None` if the columns are too small to be sampled.
+    pub duration: Option<Duration>,
+}
+
+/// Estimates the migration from `version` by the sizes of the columns it
+/// reads and the speed at which they can be read.
+pub fn estimate_migration(
+    store: &Store,
+    version: DbVersion,
+    description: &MigrationDescription,
+) -> io::Result<MigrationEstimate> {
+    let read_bytes: u64 = description
+        .reads
+        .iter()
+        .filter_map(|col| column_statistic(store, "rocksdb.estimate-live-data-size", *col))
+        .sum();
+    let write_bytes = if description.writes.is_empty() { 0 } else { read_bytes };
+
+    let started = Instant::now();
+    let mut sampled = 0;
+    for col in &description.reads {
+        for item in store.iter_raw_bytes(*col) {
+            let (key, value) = item?;
+            sampled += (key.len() + value.len()) as u64;
+            if sampled >= ESTIMATE_SAMPLE_BYTES {
+                break;
+            }
+        }
+    }
+    let duration = if read_bytes == 0 {
+        Some(Duration::ZERO)
+    } else if sampled > 0 {
+        let rate = sampled as f64 / started.elapsed().as_secs_f64().max(f64::EPSILON);
+        Some(Duration::from_secs_f64((read_bytes + write_bytes) as f64 / rate))
+    } else {
+        None
+    };
+    Ok(MigrationEstimate {
+        version,
+        summary: description.summary,
+        read_bytes,
+        write_bytes,
+        duration,
+    })
+}
+
 pub struct BatchedStoreUpdate<'a> {
     batch_size_limit: usize,
     batch_size: usize,
@@ -23,6 +254,7 @@ pub struct BatchedStoreUpdate<'a> {
     store_update: Option<StoreUpdate>,
     total_size_written: u64,
     printed_total_size_written: u64,
+    progress: Option<MigrationProgress>,
 }
 
 const PRINT_PROGRESS_EVERY_BYTES: u64 = bytesize::GIB;
@@ -36,11 +268,35 @@ impl<'a> BatchedStoreUpdate<'a> {
             store_update: Some(store.store_update()),
             total_size_written: 0,
             printed_total_size_written: 0,
+            progress: None,
+        }
+    }
+
+    /// Like [`Self::new`], but saves the progress with every batch.  The
+    /// rows must be reported with [`Self::processed`] once their writes are
+    /// added.  A batch may end in the middle of a row, so after an
+    /// interruption the writes of the last row are repeated and must be
+    /// idempotent.
+    pub fn with_progress(
+        store: &'a Store,
+        batch_size_limit: usize,
+        progress: MigrationProgress,
+    ) -> Self {
+        Self { progress: Some(progress), ..Self::new(store, batch_size_limit) }
+    }
+
+    /// Records that all writes of the row with the given key were added.
+    pub fn processed(&mut self, key: &[u8]) {
+        if let Some(progress) = &mut self.progress {
+            progress.advance(key);
         }
     }
 
     fn commit(&mut self) -> std::io::Result<()> {
-        let store_update = self.store_update.take().unwrap();
+        let mut store_update = self.store_update.take().unwrap();
+        if let Some(progress) = &self.progress {
+            progress.save(&mut store_update)?;
+        }
         store_update.commit()?;
         self.store_update = Some(self.store.store_update());
         self.batch_size = 0;
@@ -102,7 +358,9 @@ impl<'a> BatchedStoreUpdate<'a> {
         if self.batch_size > 0 {
             self.commit()?;
         }
-
+        if let Some(progress) = &self.progress {
+            progress.finish(self.store)?;
+        }
         Ok(())
     }
 }
@@ -113,11 +371,13 @@ impl<'a> BatchedStoreUpdate<'a> {
 /// The new column removes the need for high-latency read-modify-write operations when committing
 /// new blocks.
 pub fn migrate_32_to_33(store: &Store) -> anyhow::Result<()> {
-    let mut update = BatchedStoreUpdate::new(&store, 10_000_000);
-    for row in
-        store.iter_prefix_ser::<Vec<ExecutionOutcomeWithIdAndProof>>(DBCol::_TransactionResult, &[])
-    {
-        let (_, mut outcomes) = row?;
+    let mut progress = MigrationProgress::load(store, 32)?;
+    progress.set_total(estimate_num_keys(store, DBCol::_TransactionResult));
+    let resume_from = progress.resume_from();
+    let mut update = BatchedStoreUpdate::with_progress(&store, 10_000_000, progress);
+    for row in store.iter_range(DBCol::_TransactionResult, resume_from.as_deref(), None) {
+        let (key, value) = row?;
+        let mut outcomes = Vec::<ExecutionOutcomeWithIdAndProof>::try_from_slice(&value)?;
         // It appears that it was possible that the same entry in the original column contained
         // duplicate outcomes. We remove them here to avoid panicing due to issuing a
         // self-overwriting transaction.
@@ -133,6 +393,7 @@ pub fn migrate_32_to_33(store: &Store) -> anyhow::Result<()> {
                 },
             )?;
         }
+        update.processed(&key);
     }
     update.finish()?;
     let mut delete_old_update = store.store_update();
@@ -380,3 +641,34 @@ pub fn migrate_40_to_41(store: &Store, kind: DbKind, is_node_archival: bool) ->
     update.commit()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BatchedStoreUpdate, MigrationProgress};
+    use crate::test_utils::create_test_store;
+    use crate::DBCol;
+
+    /// An interrupted migration continues after the last row of the last
+    /// batch written.
+    #[test]
+    fn test_resume_migration() {
+        let store = create_test_store();
+        let progress = MigrationProgress::load(&store, 32).unwrap();
+        assert_eq!(progress.resume_from(), None);
+        // Every write fills a batch.
+        let mut update = BatchedStoreUpdate::with_progress(&store, 1, progress);
+        for key in [b"a", b"b"] {
+            update.set_ser(DBCol::BlockMisc, key, &0u8).unwrap();
+            update.processed(key);
+        }
+        drop(update);
+
+        let progress = MigrationProgress::load(&store, 32).unwrap();
+        assert_eq!(progress.resume_from().as_deref(), Some(&b"a\0"[..]));
+        // The progress of another migration isn't picked up.
+        assert_eq!(MigrationProgress::load(&store, 33).unwrap().resume_from(), None);
+
+        BatchedStoreUpdate::with_progress(&store, 1, progress).finish().unwrap();
+        assert_eq!(MigrationProgress::load(&store, 32).unwrap().resume_from(), None);
+    }
+}
@@ -1,7 +1,13 @@
+use crate::bulk_load::BulkLoadPhase;
+use crate::db::backend::DatabaseBackend;
 use crate::db::rocksdb::snapshot::{Snapshot, SnapshotError, SnapshotRemoveError};
-use crate::db::rocksdb::RocksDB;
+use crate::db::{CompressedDB, Database, ShardedDB};
 use crate::metadata::{DbKind, DbMetadata, DbVersion, DB_VERSION};
+use crate::migrations::{
+    estimate_migration, MigrationDescription, MigrationEstimate, MigrationRecord,
+};
 use crate::{DBCol, DBTransaction, Mode, NodeStorage, Store, StoreConfig, Temperature};
+use near_primitives::shard_layout::ShardUId;
 use std::sync::Arc;
 
 #[derive(Debug, thiserror::Error)]
@@ -104,6 +110,15 @@ pub enum StoreOpenerError {
     #[error("{0}")]
     MigrationError(#[source] anyhow::Error),
 
+    /// Rolling back a migration was requested but none was interrupted.
+    #[error("No interrupted migration to roll back")]
+    NoMigrationInProgress,
+
+    /// The interrupted migration has no snapshot to roll back to, either
+    /// because `migration_snapshot` was disabled or it was deleted.
+    #[error("Migration from version {0} has no snapshot to roll back to")]
+    NoMigrationSnapshot(DbVersion),
+
     /// Checkpointing errors.
     #[error("{0}")]
     CheckpointError(#[source] anyhow::Error),
@@ -162,10 +177,10 @@ fn is_valid_kind_archive(kind: DbKind, archive: bool) -> bool {
 ///     .open();
 /// ```
 pub struct StoreOpener<'a> {
-    /// Opener for an instance of RPC or Hot RocksDB store.
+    /// Opener for an instance of RPC or Hot store.
     hot: DBOpener<'a>,
 
-    /// Opener for an instance of Cold RocksDB store if one was configured.
+    /// Opener for an instance of Cold store if one was configured.
     cold: Option<DBOpener<'a>>,
 
     /// Whether the opener should expect archival db or not.
@@ -176,7 +191,7 @@ pub struct StoreOpener<'a> {
     migrator: Option<&'a dyn StoreMigrator>,
 }
 
-/// Opener for a single RocksDB instance.
+/// Opener for a single database instance.
 struct DBOpener<'a> {
     /// Path to the database.
     ///
@@ -193,6 +208,15 @@ struct DBOpener<'a> {
     /// counted column.  It’s important that the value is correct.  RPC and
     /// Archive databases are considered hot.
     temp: Temperature,
+
+    /// Storage engine of the database, by default the one selected in the
+    /// configuration.
+    backend: &'a dyn DatabaseBackend,
+
+    /// Resolved paths to the databases of the shards kept apart from the main
+    /// database, see `shard_dbs` of [`StoreConfig`].  Always empty for the
+    /// cold database.
+    shard_dbs: Vec<(ShardUId, std::path::PathBuf)>,
 }
 
 impl<'a> StoreOpener<'a> {
@@ -221,7 +245,17 @@ impl<'a> StoreOpener<'a> {
         self
     }
 
-    /// Returns path to the underlying RocksDB database.
+    /// Opens the hot and cold databases with given storage engine instead of
+    /// the one selected by `backend` in the store configuration.
+    pub fn with_backend(mut self, backend: &'a dyn DatabaseBackend) -> Self {
+        self.hot.backend = backend;
+        if let Some(cold) = &mut self.cold {
+            cold.backend = backend;
+        }
+        self
+    }
+
+    /// Returns path to the underlying database.
     ///
     /// Does not check whether the database actually exists.
     pub fn path(&self) -> &std::path::Path {
@@ -240,7 +274,7 @@ impl<'a> StoreOpener<'a> {
         self.open_in_mode(Mode::ReadWrite)
     }
 
-    /// Opens the RocksDB database(s) for hot and cold (if configured) storages.
+    /// Opens the database(s) for hot and cold (if configured) storages.
     ///
     /// When opening in read-only mode, verifies that the database version is
     /// what the node expects and fails if it isn’t.  If database doesn’t exist,
@@ -256,6 +290,7 @@ impl<'a> StoreOpener<'a> {
             };
             tracing::info!(target: "db_opener", path=hot_path, cold_path=cold_path, "Opening NodeStorage");
         }
+        crate::io_features::configure(self.hot.config);
 
         let hot_snapshot = {
             Self::ensure_created(mode, &self.hot)?;
@@ -279,7 +314,7 @@ impl<'a> StoreOpener<'a> {
             .transpose()?
             .map(|(db, _)| db);
 
-        let storage = NodeStorage::from_rocksdb(hot_db, cold_db);
+        let storage = NodeStorage::from_databases(hot_db, cold_db);
 
         hot_snapshot.remove()?;
         cold_snapshot.remove()?;
@@ -324,6 +359,75 @@ impl<'a> StoreOpener<'a> {
         Ok((hot_snapshot, cold_snapshot))
     }
 
+    /// Estimates the migrations the databases need, without running them.
+    ///
+    /// Returns an empty list if the databases are up to date.
+    pub fn estimate_migrations(&self) -> Result<Vec<MigrationEstimate>, StoreOpenerError> {
+        let mut estimates = vec![];
+        for opener in std::iter::once(&self.hot).chain(self.cold.as_ref()) {
+            let metadata = opener.get_metadata()?.ok_or(StoreOpenerError::DbDoesNotExist)?;
+            let version = metadata.version;
+            if version == DB_VERSION {
+                continue;
+            }
+            if version > DB_VERSION {
+                return Err(StoreOpenerError::DbVersionTooNew { got: version, want: DB_VERSION });
+            }
+            let migrator = self
+                .migrator
+                .ok_or(StoreOpenerError::DbVersionMismatch { got: version, want: DB_VERSION })?;
+            if let Err(release) = migrator.check_support(version) {
+                return Err(StoreOpenerError::DbVersionTooOld {
+                    got: version,
+                    want: DB_VERSION,
+                    latest_release: release,
+                });
+            }
+            let store = Self::open_store(Mode::ReadOnly, opener, version)?;
+            for version in version..DB_VERSION {
+                let description = migrator.describe(version);
+                estimates.push(estimate_migration(&store, version, &description)?);
+            }
+        }
+        Ok(estimates)
+    }
+
+    /// Restores the databases from the snapshots taken before a migration
+    /// which was interrupted or failed.
+    ///
+    /// Returns the version of the restored hot database, or of the cold one
+    /// if only its migration was interrupted.  The databases of the shards
+    /// kept apart from the main database aren't part of the snapshot and are
+    /// left as they are.
+    pub fn rollback_migration(&self) -> Result<DbVersion, StoreOpenerError> {
+        let hot_version = Self::rollback(&self.hot)?;
+        let cold_version = self.cold.as_ref().map(Self::rollback).transpose()?.flatten();
+        hot_version.or(cold_version).ok_or(StoreOpenerError::NoMigrationInProgress)
+    }
+
+    fn rollback(opener: &DBOpener) -> Result<Option<DbVersion>, StoreOpenerError> {
+        let record = MigrationRecord::read(&Self::open_store_unsafe(Mode::ReadOnly, opener)?)?;
+        let Some(record) = record else {
+            return Ok(None);
+        };
+        let snapshot = record
+            .snapshot
+            .filter(|path| path.exists())
+            .ok_or(StoreOpenerError::NoMigrationSnapshot(record.from_version))?;
+        tracing::info!(target: "db_opener", path=%opener.path.display(), snapshot_path=%snapshot.display(),
+                       "Rolling back the database to version {}", record.from_version);
+
+        // The snapshot is inside the database directory by default, so it's
+        // moved out of the way before the directory is deleted.
+        let mut restore_path = opener.path.clone().into_os_string();
+        restore_path.push(".rollback");
+        let restore_path = std::path::PathBuf::from(restore_path);
+        std::fs::rename(&snapshot, &restore_path)?;
+        std::fs::remove_dir_all(&opener.path)?;
+        std::fs::rename(&restore_path, &opener.path)?;
+        Ok(Some(record.from_version))
+    }
+
     // Creates the DB if it doesn't exist.
     fn ensure_created(mode: Mode, opener: &DBOpener) -> Result<(), StoreOpenerError> {
         let meta = opener.get_metadata()?;
@@ -338,8 +442,7 @@ impl<'a> StoreOpener<'a> {
             None if mode.can_create() => {
                 tracing::info!(target: "db_opener", path=%opener.path.display(), "The database doesn't exist, creating it.");
 
-                let db = opener.create()?;
-                let store = Store { storage: Arc::new(db) };
+                let store = Store { storage: opener.create()? };
                 store.set_db_version(DB_VERSION)?;
                 return Ok(());
             }
@@ -430,7 +533,24 @@ impl<'a> StoreOpener<'a> {
             });
         }
 
-        let snapshot = opener.snapshot()?;
+        // A migration which was interrupted left its record behind.  It is
+        // continued with the snapshot it took before it started, the snapshot
+        // taken now would have the changes it already made.
+        let record = MigrationRecord::read(&Self::open_store(mode, opener, version)?)?;
+        let snapshot = match record {
+            Some(record) => {
+                tracing::info!(target: "db_opener", path=%opener.path.display(), from_version=record.from_version,
+                               "Continuing interrupted migration");
+                Snapshot(record.snapshot.filter(|path| path.exists()))
+            }
+            None => {
+                let snapshot = opener.snapshot()?;
+                let record =
+                    MigrationRecord { from_version: version, snapshot: snapshot.0.clone() };
+                record.write(&Self::open_store(mode, opener, version)?)?;
+                snapshot
+            }
+        };
 
         for version in version..DB_VERSION {
             tracing::info!(target: "db_opener", path=%opener.path.display(),
@@ -438,14 +558,16 @@ impl<'a> StoreOpener<'a> {
                            version, version + 1);
 
             // Note: here we open the cold store as a regular Store object
-            // backed by RocksDB. It doesn't matter today as we don't expect any
+            // backed by the raw database. It doesn't matter today as we don't expect any
             // old migrations on the cold storage. In the future however it may
             // be better to wrap it in the ColdDB object instead.
 
             let store = Self::open_store(mode, opener, version)?;
+            let _bulk_load = store.bulk_load(BulkLoadPhase::Migration);
             migrator.migrate(&store, version, kind).map_err(StoreOpenerError::MigrationError)?;
             store.set_db_version(version + 1)?;
         }
+        MigrationRecord::delete(&Self::open_store(mode, opener, DB_VERSION)?)?;
 
         if cfg!(feature = "nightly") || cfg!(feature = "nightly_protocol") {
             let version = 10000;
@@ -466,20 +588,17 @@ impl<'a> StoreOpener<'a> {
         opener: &DBOpener,
         version: DbVersion,
     ) -> Result<Store, StoreOpenerError> {
-        let (db, _) = opener.open(mode, version)?;
-        let store = Store { storage: Arc::new(db) };
-        Ok(store)
+        let (storage, _) = opener.open(mode, version)?;
+        Ok(Store { storage })
     }
 
     fn open_store_unsafe(mode: Mode, opener: &DBOpener) -> Result<Store, StoreOpenerError> {
-        let db = opener.open_unsafe(mode)?;
-        let store = Store { storage: Arc::new(db) };
-        Ok(store)
+        Ok(Store { storage: opener.open_unsafe(mode)? })
     }
 }
 
 impl<'a> DBOpener<'a> {
-    /// Constructs new opener for a single RocksDB builder.
+    /// Constructs new opener for a single database.
     ///
     /// The path to the database is resolved based on the path in config with
     /// given home_dir as base directory for resolving relative paths.
@@ -487,7 +606,18 @@ impl<'a> DBOpener<'a> {
         let path = if temp == Temperature::Hot { "data" } else { "cold-data" };
         let path = config.path.as_deref().unwrap_or_else(|| std::path::Path::new(path));
         let path = home_dir.join(path);
-        Self { path, config, temp }
+        let shard_dbs = if temp == Temperature::Hot { config.shard_dbs.as_slice() } else { &[] };
+        let shard_dbs = shard_dbs
+            .iter()
+            .map(|shard_db| {
+                let shard_path = match &shard_db.path {
+                    Some(shard_path) => home_dir.join(shard_path),
+                    None => ShardedDB::default_shard_db_path(&path, shard_db.shard_uid),
+                };
+                (shard_db.shard_uid, shard_path)
+            })
+            .collect();
+        Self { path, config, temp, backend: config.backend.get(), shard_dbs }
     }
 
     /// Returns version and kind of the database or `None` if it doesn’t exist.
@@ -499,7 +629,7 @@ impl<'a> DBOpener<'a> {
     /// introduced, the kind is returned as `None`.  Otherwise, it’s also
     /// fetched and if it’s not there error is returned.
     fn get_metadata(&self) -> std::io::Result<Option<DbMetadata>> {
-        RocksDB::get_metadata(&self.path, self.config)
+        self.backend.get_metadata(&self.path, self.config)
     }
 
     /// Opens the database in given mode checking expected version and kind.
@@ -518,9 +648,13 @@ impl<'a> DBOpener<'a> {
     /// new version.
     ///
     /// Use [`Self::create`] to create a new database.
-    fn open(&self, mode: Mode, want_version: DbVersion) -> std::io::Result<(RocksDB, DbMetadata)> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        let metadata = DbMetadata::read(&db)?;
+    fn open(
+        &self,
+        mode: Mode,
+        want_version: DbVersion,
+    ) -> std::io::Result<(Arc<dyn Database>, DbMetadata)> {
+        let db = self.open_with_shard_dbs(mode)?;
+        let metadata = DbMetadata::read(db.as_ref())?;
         if want_version != metadata.version {
             let msg = format!("unexpected DbVersion {}; expected {want_version}", metadata.version);
             Err(std::io::Error::other(msg))
@@ -533,19 +667,41 @@ impl<'a> DBOpener<'a> {
     ///
     /// This is only suitable when creating the database or setting the version
     /// and kind for the first time.
-    fn open_unsafe(&self, mode: Mode) -> std::io::Result<RocksDB> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        Ok(db)
+    fn open_unsafe(&self, mode: Mode) -> std::io::Result<Arc<dyn Database>> {
+        self.open_with_shard_dbs(mode)
     }
 
     /// Creates a new database.
-    fn create(&self) -> std::io::Result<RocksDB> {
-        RocksDB::open(&self.path, &self.config, Mode::Create, self.temp)
+    fn create(&self) -> std::io::Result<Arc<dyn Database>> {
+        self.open_with_shard_dbs(Mode::Create)
+    }
+
+    /// Opens the database together with the databases of the shards kept
+    /// apart from it, if any.  The databases of the shards have no metadata
+    /// and are created whenever missing, e.g. when a shard is added to the
+    /// configuration.  The values of the State column are compressed as set
+    /// by `value_compression`.
+    fn open_with_shard_dbs(&self, mode: Mode) -> std::io::Result<Arc<dyn Database>> {
+        let db = self.backend.open(&self.path, self.config, mode, self.temp)?;
+        if self.shard_dbs.is_empty() {
+            return Ok(CompressedDB::new(db, &self.config.value_compression, mode)?);
+        }
+        let shard_mode = match mode {
+            Mode::ReadOnly | Mode::Create | Mode::Secondary => mode,
+            Mode::ReadWrite | Mode::ReadWriteExisting => Mode::ReadWrite,
+        };
+        let mut shards = vec![];
+        for (shard_uid, path) in &self.shard_dbs {
+            tracing::info!(target: "db_opener", %shard_uid, path=%path.display(), "Opening database of shard");
+            shards.push((*shard_uid, self.backend.open(path, self.config, shard_mode, self.temp)?));
+        }
+        let db = ShardedDB::new(db, shards)?;
+        Ok(CompressedDB::new(db, &self.config.value_compression, mode)?)
     }
 
     /// Creates a new snapshot for the database.
     fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
-        Snapshot::new(&self.path, &self.config, self.temp)
+        self.backend.snapshot(&self.path, self.config, self.temp)
     }
 }
 
@@ -561,6 +717,14 @@ pub trait StoreMigrator {
     /// **Panics** if `version` ≥ [`DB_VERSION`].
     fn check_support(&self, version: DbVersion) -> Result<(), &'static str>;
 
+    /// Describes the migration from given version to the next one, for
+    /// estimating it with `neard database run-migrations --dry-run`.
+    ///
+    /// By default the migration is assumed to not read anything.
+    fn describe(&self, _version: DbVersion) -> MigrationDescription {
+        MigrationDescription::default()
+    }
+
     /// Performs database migration from given version to the next one.
     ///
     /// The function only does single migration from `version` to `version + 1`.
@@ -607,7 +771,12 @@ pub fn checkpoint_hot_storage_and_cleanup_columns(
         .map_err(StoreOpenerError::CheckpointError)?;
 
     // As only path from config is used in StoreOpener, default config with custom path will do.
+    // The databases of the shards are checkpointed next to the main one.
     let mut config = StoreConfig::default();
+    config.shard_dbs = ShardedDB::list_default_shard_dbs(&checkpoint_path)?
+        .into_iter()
+        .map(|shard_uid| crate::ShardDbConfig { shard_uid, path: None })
+        .collect();
     config.path = Some(checkpoint_path);
     let archive = hot_store.get_db_kind()? == Some(DbKind::Archive);
     let opener = StoreOpener::new(checkpoint_base_path, archive, &config, None);
@@ -641,6 +810,97 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_memory_backend() {
+        let config = StoreConfig {
+            backend: crate::db::backend::StoreBackend::Memory,
+            ..StoreConfig::test_config()
+        };
+        let home_dir = tempfile::tempdir().unwrap();
+        let opener = StoreOpener::new(home_dir.path(), false, &config, None);
+        assert!(matches!(
+            opener.open_in_mode(Mode::ReadOnly),
+            Err(StoreOpenerError::DbDoesNotExist)
+        ));
+
+        let store = opener.open_in_mode(Mode::Create).unwrap().get_hot_store();
+        assert_eq!(store.get_db_kind().unwrap(), Some(DbKind::RPC));
+        let mut store_update = store.store_update();
+        store_update.insert(DBCol::Block, vec![1], vec![42]);
+        store_update.commit().unwrap();
+        // Nothing is written to the disk.
+        assert!(!opener.path().exists());
+
+        // Opening the same path again gives the same database.
+        let store = opener.open_in_mode(Mode::ReadWriteExisting).unwrap().get_hot_store();
+        check_keys_existence(&store, &DBCol::Block, &vec![vec![1]], true);
+        assert!(matches!(
+            opener.open_in_mode(Mode::Create),
+            Err(StoreOpenerError::DbAlreadyExists)
+        ));
+    }
+
+    #[test]
+    fn test_shard_dbs() {
+        let shard_uid = ShardUId::single_shard();
+        let main_config = StoreConfig {
+            backend: crate::db::backend::StoreBackend::Memory,
+            ..StoreConfig::test_config()
+        };
+        let config = StoreConfig {
+            shard_dbs: vec![crate::ShardDbConfig { shard_uid, path: None }],
+            ..main_config.clone()
+        };
+        let home_dir = tempfile::tempdir().unwrap();
+        let opener = StoreOpener::new(home_dir.path(), false, &config, None);
+        let store = opener.open_in_mode(Mode::Create).unwrap().get_hot_store();
+        let key = [&shard_uid.to_bytes()[..], b"key"].concat();
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::FlatState, &key, b"value");
+        store_update.commit().unwrap();
+
+        // The row of the shard is only in the database of the shard.
+        let store = opener.open_in_mode(Mode::ReadWriteExisting).unwrap().get_hot_store();
+        check_keys_existence(&store, &DBCol::FlatState, &vec![key.clone()], true);
+        let main_opener = StoreOpener::new(home_dir.path(), false, &main_config, None);
+        let main_store = main_opener.open_in_mode(Mode::ReadWriteExisting).unwrap().get_hot_store();
+        check_keys_existence(&main_store, &DBCol::FlatState, &vec![key], false);
+    }
+
+    /// Rolling back an interrupted migration restores the snapshot taken
+    /// before it started.
+    #[test]
+    fn test_rollback_migration() {
+        let (_home_dir, opener) = NodeStorage::test_opener();
+        let store = opener.open().unwrap().get_hot_store();
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::BlockMisc, b"before", b"value");
+        store_update.commit().unwrap();
+        drop(store);
+
+        // A migration which wrote to the database and was interrupted.
+        let snapshot = opener.hot.snapshot().unwrap();
+        let store =
+            StoreOpener::open_store(Mode::ReadWriteExisting, &opener.hot, DB_VERSION).unwrap();
+        let record = MigrationRecord { from_version: DB_VERSION - 1, snapshot: snapshot.0.clone() };
+        record.write(&store).unwrap();
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::BlockMisc, b"after", b"value");
+        store_update.commit().unwrap();
+        drop(store);
+        drop(snapshot);
+
+        assert_eq!(opener.rollback_migration().unwrap(), DB_VERSION - 1);
+        let store = opener.open().unwrap().get_hot_store();
+        check_keys_existence(&store, &DBCol::BlockMisc, &vec![b"before".to_vec()], true);
+        check_keys_existence(&store, &DBCol::BlockMisc, &vec![b"after".to_vec()], false);
+        drop(store);
+        assert!(matches!(
+            opener.rollback_migration(),
+            Err(StoreOpenerError::NoMigrationInProgress)
+        ));
+    }
+
     #[test]
     fn test_checkpoint_hot_storage_and_cleanup_columns() {
         let (home_dir, opener) = NodeStorage::test_opener();
@@ -0,0 +1,98 @@
+//! Following the writes of another `neard` process from a RocksDB secondary
+//! instance, see [`crate::Mode::Secondary`].
+//!
+//! A secondary instance sees the database as it was when it was opened or
+//! last caught up.  Catching up replays the WAL of the primary and picks up
+//! the files it flushed and compacted since, so a read-only process, e.g. one
+//! serving RPC on the machine of a validator, follows the chain without being
+//! restarted.  The lag of the secondary instance is exported as the time since
+//! the head block it sees was produced.
+
+use crate::config::SecondaryConfig;
+use crate::db::HEAD_KEY;
+use crate::{metrics, DBCol, NodeStorage};
+use near_primitives::block::{BlockHeader, Tip};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the loop checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct SecondaryCatchUpHandle {
+    handle: JoinHandle<()>,
+    keep_running: Arc<AtomicBool>,
+}
+
+impl SecondaryCatchUpHandle {
+    /// Starts catching up the storage, opened in `Mode::Secondary`, with the
+    /// primary every `catch_up_period`.
+    pub fn start(storage: NodeStorage, config: SecondaryConfig) -> Self {
+        let keep_running = Arc::new(AtomicBool::new(true));
+        let keep_running_clone = keep_running.clone();
+        let handle = std::thread::spawn(move || {
+            while keep_running.load(Ordering::Relaxed) {
+                if let Err(err) = catch_up(&storage) {
+                    tracing::warn!(target: "store", ?err, "Failed to catch up with the primary");
+                }
+                let next_run = Instant::now() + config.catch_up_period;
+                while keep_running.load(Ordering::Relaxed) && Instant::now() < next_run {
+                    std::thread::sleep(STOP_POLL_INTERVAL.min(config.catch_up_period));
+                }
+            }
+        });
+        Self { handle, keep_running: keep_running_clone }
+    }
+
+    pub fn stop(self) {
+        self.keep_running.store(false, Ordering::Relaxed);
+        self.handle.join().expect("join should not fail here");
+    }
+}
+
+fn catch_up(storage: &NodeStorage) -> io::Result<()> {
+    {
+        let _timer = metrics::SECONDARY_CATCH_UP_TIME.start_timer();
+        storage.catch_up_with_primary()?;
+    }
+    let store = storage.get_hot_store();
+    let Some(head) = store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)? else {
+        return Ok(());
+    };
+    metrics::SECONDARY_HEAD_HEIGHT.set(head.height as i64);
+    if let Some(header) =
+        store.get_ser::<BlockHeader>(DBCol::BlockHeader, head.last_block_hash.as_ref())?
+    {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let lag = (now.as_nanos() as u64).saturating_sub(header.raw_timestamp());
+        metrics::SECONDARY_LAG_MS.set((lag / 1_000_000) as i64);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DBCol, Mode, NodeStorage, StoreConfig};
+
+    /// A secondary instance sees the writes of the primary made after it was
+    /// opened once it caught up.
+    #[test]
+    fn test_catch_up_with_primary() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let store_config = StoreConfig::test_config();
+        let opener = NodeStorage::opener(home_dir.path(), false, &store_config, None);
+        let primary = opener.open().unwrap();
+        let secondary = opener.open_in_mode(Mode::Secondary).unwrap();
+
+        let mut store_update = primary.get_hot_store().store_update();
+        store_update.set(DBCol::BlockMisc, b"key", b"value");
+        store_update.commit().unwrap();
+        assert!(secondary.get_hot_store().get(DBCol::BlockMisc, b"key").unwrap().is_none());
+
+        secondary.catch_up_with_primary().unwrap();
+        let value = secondary.get_hot_store().get(DBCol::BlockMisc, b"key").unwrap();
+        assert_eq!(value.as_deref(), Some(&b"value"[..]));
+    }
+}
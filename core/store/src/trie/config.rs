@@ -1,8 +1,13 @@
-use crate::config::{PrefetchConfig, TrieCacheConfig};
+use crate::config::{
+    FlatStorageKeyFilterConfig, PrefetchConfig, PrefetchRuleConfig, TrieCacheConfig,
+};
+use crate::memory_budget::MemoryBudget;
 use crate::StoreConfig;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::AccountId;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 use tracing::error;
 
 /// Default memory limit, if nothing else is configured.
@@ -33,11 +38,24 @@ pub struct TrieConfig {
     pub sweat_prefetch_senders: Vec<AccountId>,
     pub claim_sweat_prefetch_config: Vec<PrefetchConfig>,
     pub kaiching_prefetch_config: Vec<PrefetchConfig>,
+    /// Prefetching of contract storage keys derived from call arguments.
+    pub prefetch_rules: Vec<PrefetchRuleConfig>,
 
     /// List of shards we will load into memory.
     pub load_mem_tries_for_shards: Vec<ShardUId>,
     /// Whether mem-trie should be loaded for each tracked shard.
     pub load_mem_tries_for_tracked_shards: bool,
+    /// Directory where mem-tries are saved on shutdown and loaded from on
+    /// startup, if set.
+    pub mem_tries_persistence_dir: Option<PathBuf>,
+    /// Budget the shard caches register with, if their sizes are managed by
+    /// one rather than fixed by the cache configs.
+    pub memory_budget: Option<Arc<MemoryBudget>>,
+    /// Filters of the keys in the flat storages of the shards.
+    pub flat_storage_key_filter: FlatStorageKeyFilterConfig,
+    /// Directory of the memory mapped copies of the flat state of the
+    /// shards, if flat storage reads are served from them.
+    pub flat_state_mmap_dir: Option<PathBuf>,
 }
 
 impl TrieConfig {
@@ -47,6 +65,7 @@ impl TrieConfig {
 
         this.shard_cache_config = config.trie_cache.clone();
         this.view_shard_cache_config = config.view_trie_cache.clone();
+        this.flat_storage_key_filter = config.flat_storage_key_filter.clone();
 
         this.enable_receipt_prefetching = config.enable_receipt_prefetching;
         for account in &config.sweat_prefetch_receivers {
@@ -63,6 +82,7 @@ impl TrieConfig {
         }
         this.claim_sweat_prefetch_config.clone_from(&config.claim_sweat_prefetch_config);
         this.kaiching_prefetch_config.clone_from(&config.kaiching_prefetch_config);
+        this.prefetch_rules.clone_from(&config.prefetch_rules);
         this.load_mem_tries_for_shards.clone_from(&config.load_mem_tries_for_shards);
         this.load_mem_tries_for_tracked_shards = config.load_mem_tries_for_tracked_shards;
 
@@ -92,5 +112,6 @@ impl TrieConfig {
                 && !self.sweat_prefetch_senders.is_empty())
             || !self.claim_sweat_prefetch_config.is_empty()
             || !self.kaiching_prefetch_config.is_empty()
+            || !self.prefetch_rules.is_empty()
     }
 }
@@ -449,6 +449,20 @@ impl<'a> TrieIterator<'a> {
             TrieIterator::Memtrie(iter) => Ok(iter.seek_prefix(key)),
         }
     }
+
+    /// Position the iterator on the first element with key >= `key`.  Unlike
+    /// with [`Self::seek_prefix`], the iteration continues past the keys
+    /// starting with `key`.
+    pub fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> Result<(), StorageError> {
+        let key = NibbleSlice::new(key.as_ref());
+        match self {
+            TrieIterator::Disk(iter) => iter.seek_nibble_slice(key, false).map(drop),
+            TrieIterator::Memtrie(iter) => {
+                iter.seek_nibble_slice(key, false);
+                Ok(())
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,11 +1,13 @@
 use super::metrics::MEM_TRIE_ARENA_ACTIVE_ALLOCS_COUNT;
 use super::single_thread::STArenaMemory;
 use super::{ArenaMemory, ArenaPos, ArenaSliceMut};
+use crate::io_features::new_arena_chunk;
 use crate::trie::mem::arena::metrics::{
     MEM_TRIE_ARENA_ACTIVE_ALLOCS_BYTES, MEM_TRIE_ARENA_MEMORY_USAGE_BYTES,
 };
 use crate::trie::mem::arena::ArenaMemoryMut;
 use crate::trie::mem::flexible_data::encoding::BorshFixedSize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use near_o11y::metrics::IntGauge;
 
 /// Simple bump allocator with freelists.
@@ -75,6 +77,16 @@ pub(crate) const fn allocation_size(size_class: usize) -> usize {
 
 const NUM_ALLOCATION_CLASSES: usize = allocation_class(MAX_ALLOC_SIZE) + 1;
 
+/// The part of the allocator which is saved together with the memory of the
+/// arena, so that the arena can continue allocating after it's restored.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub(super) struct AllocatorState {
+    freelists: [ArenaPos; NUM_ALLOCATION_CLASSES],
+    next_alloc_pos: ArenaPos,
+    active_allocs_bytes: u64,
+    active_allocs_count: u64,
+}
+
 impl Allocator {
     pub fn new(name: String) -> Self {
         Self {
@@ -103,6 +115,26 @@ impl Allocator {
         allocator
     }
 
+    pub(super) fn new_from_state(name: String, state: AllocatorState) -> Self {
+        let mut allocator = Self::new_with_initial_stats(
+            name,
+            state.active_allocs_bytes as usize,
+            state.active_allocs_count as usize,
+        );
+        allocator.freelists = state.freelists;
+        allocator.next_alloc_pos = state.next_alloc_pos;
+        allocator
+    }
+
+    pub(super) fn state(&self) -> AllocatorState {
+        AllocatorState {
+            freelists: self.freelists,
+            next_alloc_pos: self.next_alloc_pos,
+            active_allocs_bytes: self.active_allocs_bytes as u64,
+            active_allocs_count: self.active_allocs_count as u64,
+        }
+    }
+
     pub fn update_memory_usage_gauge(&self, memory: &STArenaMemory) {
         self.memory_usage_gauge.set(memory.chunks.len() as i64 * CHUNK_SIZE as i64);
     }
@@ -110,7 +142,7 @@ impl Allocator {
     /// Adds a new chunk to the arena, and updates the next_alloc_pos to the beginning of
     /// the new chunk.
     fn new_chunk(&mut self, memory: &mut STArenaMemory) {
-        memory.chunks.push(vec![0; CHUNK_SIZE]);
+        memory.chunks.push(new_arena_chunk(CHUNK_SIZE));
         self.next_alloc_pos =
             ArenaPos { chunk: u32::try_from(memory.chunks.len() - 1).unwrap(), pos: 0 };
         self.update_memory_usage_gauge(memory);
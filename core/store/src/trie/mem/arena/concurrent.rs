@@ -4,6 +4,7 @@ use std::sync::Arc;
 use super::alloc::{allocation_class, allocation_size, CHUNK_SIZE};
 use super::single_thread::STArena;
 use super::{Arena, ArenaMemory, ArenaMemoryMut, ArenaMut, ArenaPos, ArenaSliceMut};
+use crate::io_features::new_arena_chunk;
 
 /// Arena that can be allocated on from multiple threads, but still allowing conversion to a
 /// single-threaded `STArena` afterwards.
@@ -100,7 +101,7 @@ impl ConcurrentArenaMemory {
             self.chunk_pos_global_to_local.push(usize::MAX);
         }
         self.chunk_pos_global_to_local[pos] = self.chunks.len();
-        self.chunks.push((pos, vec![0; CHUNK_SIZE]));
+        self.chunks.push((pos, new_arena_chunk(CHUNK_SIZE)));
     }
 
     pub fn chunk(&self, pos: usize) -> &[u8] {
@@ -1,7 +1,10 @@
 use std::convert::From;
+use std::io::{Read, Write};
 use std::sync::Arc;
 
-use super::alloc::Allocator;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::alloc::{Allocator, AllocatorState};
 use super::frozen::{FrozenArena, FrozenArenaMemory};
 use super::single_thread::{STArena, STArenaMemory};
 use super::{
@@ -125,6 +128,41 @@ impl HybridArena {
         self.memory.chunks_offset() > 0
     }
 
+    /// Writes the memory and the allocator state of the arena, so that it can
+    /// be restored with `read_from`.  Arenas with shared memory can't be
+    /// written, as the shared memory belongs to another arena.
+    pub(crate) fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        assert!(!self.has_shared_memory(), "Cannot write arena with shared memory");
+        self.allocator.state().serialize(writer)?;
+        let chunks = &self.memory.owned_memory.chunks;
+        (chunks.len() as u32).serialize(writer)?;
+        for chunk in chunks {
+            (chunk.len() as u32).serialize(writer)?;
+            writer.write_all(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Restores an arena written with `write_to`.
+    pub(crate) fn read_from(name: String, reader: &mut impl Read) -> std::io::Result<Self> {
+        let allocator =
+            Allocator::new_from_state(name, AllocatorState::deserialize_reader(reader)?);
+        let num_chunks = u32::deserialize_reader(reader)?;
+        let mut chunks = Vec::with_capacity(num_chunks as usize);
+        for _ in 0..num_chunks {
+            let len = u32::deserialize_reader(reader)?;
+            let mut chunk = crate::io_features::new_arena_chunk(len as usize);
+            reader.read_exact(&mut chunk)?;
+            chunks.push(chunk);
+        }
+        let owned_memory = STArenaMemory { chunks };
+        allocator.update_memory_usage_gauge(&owned_memory);
+        Ok(Self {
+            memory: HybridArenaMemory { owned_memory, shared_memory: Arc::new(Default::default()) },
+            allocator,
+        })
+    }
+
     /// Number of active allocations (alloc calls minus dealloc calls).
     #[cfg(test)]
     pub fn num_active_allocs(&self) -> usize {
@@ -4,10 +4,11 @@ use super::node::MemTrieNodeId;
 use crate::flat::store_helper::{
     decode_flat_state_db_key, get_all_deltas_metadata, get_delta_changes, get_flat_storage_status,
 };
-use crate::flat::{FlatStorageError, FlatStorageStatus};
+use crate::flat::{BlockInfo, FlatStorageError, FlatStorageStatus};
 use crate::trie::mem::arena::Arena;
 use crate::trie::mem::construction::TrieConstructor;
 use crate::trie::mem::parallel_loader::load_memtrie_in_parallel;
+use crate::trie::mem::persistence::load_mem_tries;
 use crate::{DBCol, NibbleSlice, Store};
 use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
@@ -16,8 +17,10 @@ use near_primitives::state::FlatStateValue;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{BlockHeight, StateRoot};
 use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Loads a trie from the FlatState column. The returned `MemTries` contains
 /// exactly one trie root.
@@ -94,7 +97,67 @@ fn load_memtrie_single_thread(
     Ok((arena, root_id))
 }
 
-fn get_state_root(
+/// Computes the state roots of the flat head and of `blocks` from the flat
+/// state and the deltas of the blocks, without checking them against the
+/// roots in `ChunkExtra`.  `blocks` must be a chain of blocks following the
+/// flat head, which must not move meanwhile.  Returns `None` if
+/// `keep_running` was reset before the computation was done.
+pub(crate) fn compute_state_roots_from_flat_state(
+    store: &Store,
+    shard_uid: ShardUId,
+    flat_head: &BlockInfo,
+    blocks: &[BlockInfo],
+    keep_running: &AtomicBool,
+) -> Result<Option<Vec<StateRoot>>, StorageError> {
+    let mut arena = STArena::new(shard_uid.to_string());
+    let mut recon = TrieConstructor::new(&mut arena);
+    for (i, item) in store
+        .iter_prefix_ser::<FlatStateValue>(DBCol::FlatState, &borsh::to_vec(&shard_uid).unwrap())
+        .enumerate()
+    {
+        if i % 10000 == 0 && !keep_running.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let (key, value) = item.map_err(|err| {
+            FlatStorageError::StorageInternalError(format!("Error iterating over FlatState: {err}"))
+        })?;
+        let (_, key) = decode_flat_state_db_key(&key).map_err(|err| {
+            FlatStorageError::StorageInternalError(format!("invalid FlatState key format: {err}"))
+        })?;
+        recon.add_leaf(NibbleSlice::new(&key), value);
+    }
+    let (mut mem_tries, mut state_root) = match recon.finalize() {
+        Some(root_id) => {
+            let state_root = root_id.as_ptr(arena.memory()).view().node_hash();
+            let mem_tries =
+                MemTries::new_from_arena_and_root(shard_uid, flat_head.height, arena, root_id);
+            (mem_tries, state_root)
+        }
+        None => (MemTries::new(shard_uid), StateRoot::default()),
+    };
+    let mut state_roots = vec![state_root];
+    for block in blocks {
+        let changes = get_delta_changes(store, shard_uid, block.hash)?.ok_or_else(|| {
+            StorageError::StorageInconsistentState(format!(
+                "No flat state delta for block {} in shard {}",
+                block.hash, shard_uid
+            ))
+        })?;
+        let mut trie_update = mem_tries.update(state_root, false)?;
+        for (key, value) in changes.0 {
+            match value {
+                Some(value) => trie_update.insert_memtrie_only(&key, value),
+                None => trie_update.delete(&key),
+            };
+        }
+        let mem_trie_changes = trie_update.to_mem_trie_changes_only();
+        state_root = mem_tries.apply_memtrie_changes(block.height, &mem_trie_changes);
+        state_roots.push(state_root);
+    }
+    Ok(Some(state_roots))
+}
+
+pub(crate) fn get_state_root(
     store: &Store,
     block_hash: CryptoHash,
     shard_uid: ShardUId,
@@ -127,6 +190,29 @@ pub fn load_trie_from_flat_state_and_delta(
     shard_uid: ShardUId,
     state_root: Option<StateRoot>,
     parallelize: bool,
+) -> Result<MemTries, StorageError> {
+    load_trie_and_delta(store, shard_uid, state_root, parallelize, None)
+}
+
+/// Same as `load_trie_from_flat_state_and_delta`, but starts from the tries
+/// saved in `persistence_dir` on shutdown if they were saved at the current
+/// flat head, see `persistence`.
+pub fn load_persisted_trie_and_delta(
+    store: &Store,
+    shard_uid: ShardUId,
+    state_root: Option<StateRoot>,
+    parallelize: bool,
+    persistence_dir: &Path,
+) -> Result<MemTries, StorageError> {
+    load_trie_and_delta(store, shard_uid, state_root, parallelize, Some(persistence_dir))
+}
+
+fn load_trie_and_delta(
+    store: &Store,
+    shard_uid: ShardUId,
+    state_root: Option<StateRoot>,
+    parallelize: bool,
+    persistence_dir: Option<&Path>,
 ) -> Result<MemTries, StorageError> {
     debug!(target: "memtrie", %shard_uid, "Loading base trie from flat state...");
     let flat_head = match get_flat_storage_status(&store, shard_uid)? {
@@ -144,10 +230,50 @@ pub fn load_trie_from_flat_state_and_delta(
         None => get_state_root(store, flat_head.hash, shard_uid)?,
     };
 
+    if let Some(dir) = persistence_dir {
+        let load_start = Instant::now();
+        match load_mem_tries(dir, shard_uid, flat_head.hash) {
+            // The root of the flat head may have been inserted at a later
+            // height, if the following chunks were missing.
+            Ok(Some(mut mem_tries))
+                if state_root == StateRoot::default()
+                    || mem_tries.get_root(&state_root).is_ok() =>
+            {
+                match apply_flat_state_deltas(store, shard_uid, &mut mem_tries) {
+                    Ok(()) => {
+                        info!(target: "memtrie", %shard_uid, "Done loading saved memtries, took {:?}", load_start.elapsed());
+                        return Ok(mem_tries);
+                    }
+                    Err(err) => {
+                        warn!(target: "memtrie", %shard_uid, ?err, "Cannot apply deltas to saved memtries, loading from flat state")
+                    }
+                }
+            }
+            Ok(Some(_)) => {
+                warn!(target: "memtrie", %shard_uid, "Saved memtries don't have the root of the flat head, loading from flat state")
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!(target: "memtrie", %shard_uid, ?err, "Cannot read saved memtries, loading from flat state")
+            }
+        }
+    }
+
     let mut mem_tries =
         load_trie_from_flat_state(&store, shard_uid, state_root, flat_head.height, parallelize)
             .unwrap();
+    apply_flat_state_deltas(store, shard_uid, &mut mem_tries)?;
+    debug!(target: "memtrie", %shard_uid, "Done loading memtries for shard");
+    Ok(mem_tries)
+}
 
+/// Applies the flat state deltas on top of the tries of the flat head.  The
+/// deltas of blocks whose roots the tries already have are skipped.
+fn apply_flat_state_deltas(
+    store: &Store,
+    shard_uid: ShardUId,
+    mem_tries: &mut MemTries,
+) -> Result<(), StorageError> {
     debug!(target: "memtrie", %shard_uid, "Loading flat state deltas...");
     // We load the deltas in order of height, so that we always have the previous state root
     // already loaded.
@@ -162,6 +288,9 @@ pub fn load_trie_from_flat_state_and_delta(
         if let Some(changes) = delta {
             let old_state_root = get_state_root(store, prev_hash, shard_uid)?;
             let new_state_root = get_state_root(store, hash, shard_uid)?;
+            if mem_tries.has_root_at_height(height, &new_state_root) {
+                continue;
+            }
 
             let mut trie_update = mem_tries.update(old_state_root, false)?;
             for (key, value) in changes.0 {
@@ -179,14 +308,12 @@ pub fn load_trie_from_flat_state_and_delta(
         }
         debug!(target: "memtrie", %shard_uid, "Applied memtrie changes for height {}", height);
     }
-
-    debug!(target: "memtrie", %shard_uid, "Done loading memtries for shard");
-    Ok(mem_tries)
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::load_trie_from_flat_state_and_delta;
+    use super::{compute_state_roots_from_flat_state, load_trie_from_flat_state_and_delta};
     use crate::flat::test_utils::MockChain;
     use crate::flat::{store_helper, BlockInfo, FlatStorageReadyStatus, FlatStorageStatus};
     use crate::test_utils::{
@@ -300,6 +427,47 @@ mod tests {
         check_maybe_parallelize(keys, true);
     }
 
+    /// The root computed from the flat state is the committed one, unless a
+    /// value of the flat state is corrupted.
+    #[test]
+    fn test_compute_state_roots_from_flat_state() {
+        let shard_tries = TestTriesBuilder::new().with_flat_storage(true).build();
+        let shard_uid = ShardUId::single_shard();
+        let changes = (0u8..100).map(|i| (vec![i, 1], Some(vec![i]))).collect::<Vec<_>>();
+        test_populate_flat_storage(
+            &shard_tries,
+            shard_uid,
+            &CryptoHash::default(),
+            &CryptoHash::default(),
+            &changes,
+        );
+        let state_root =
+            test_populate_trie(&shard_tries, &Trie::EMPTY_ROOT, shard_uid, changes.clone());
+        let flat_head = BlockInfo::genesis(CryptoHash::default(), 1);
+        let keep_running = std::sync::atomic::AtomicBool::new(true);
+        let compute = || {
+            compute_state_roots_from_flat_state(
+                &shard_tries.get_store(),
+                shard_uid,
+                &flat_head,
+                &[],
+                &keep_running,
+            )
+            .unwrap()
+        };
+        assert_eq!(compute(), Some(vec![state_root]));
+
+        let corrupted = vec![(changes[42].0.clone(), Some(vec![0]))];
+        test_populate_flat_storage(
+            &shard_tries,
+            shard_uid,
+            &CryptoHash::default(),
+            &CryptoHash::default(),
+            &corrupted,
+        );
+        assert_ne!(compute(), Some(vec![state_root]));
+    }
+
     fn nibbles(hex: &str) -> Vec<u8> {
         if hex == "_" {
             return vec![];
@@ -1,6 +1,8 @@
 use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
 use std::sync::Arc;
 
+use borsh::{BorshDeserialize, BorshSerialize};
 use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
@@ -13,7 +15,7 @@ use crate::Trie;
 
 use super::arena::hybrid::{HybridArena, HybridArenaMemory};
 use super::arena::single_thread::STArena;
-use super::arena::Arena;
+use super::arena::{Arena, ArenaPos};
 use super::flexible_data::value::ValueView;
 use super::iter::STMemTrieIterator;
 use super::lookup::memtrie_lookup;
@@ -102,6 +104,65 @@ impl MemTries {
             .set(self.roots.len() as i64);
     }
 
+    /// Writes the tries, including the roots of every height, so that they can
+    /// be restored with `read_from`.
+    pub(super) fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        let roots: Vec<(StateRoot, Vec<ArenaPos>)> = self
+            .roots
+            .iter()
+            .map(|(state_root, ids)| (*state_root, ids.iter().map(|id| id.pos).collect()))
+            .collect();
+        (roots, &self.heights).serialize(writer)?;
+        self.arena.write_to(writer)
+    }
+
+    /// Restores the tries written with `write_to`.
+    pub(super) fn read_from(shard_uid: ShardUId, reader: &mut impl Read) -> std::io::Result<Self> {
+        let (roots, heights) = <(
+            Vec<(StateRoot, Vec<ArenaPos>)>,
+            BTreeMap<BlockHeight, Vec<StateRoot>>,
+        )>::deserialize_reader(reader)?;
+        let arena = HybridArena::read_from(shard_uid.to_string(), reader)?;
+        let roots: HashMap<_, Vec<_>> = roots
+            .into_iter()
+            .map(|(state_root, ids)| {
+                (state_root, ids.into_iter().map(|pos| MemTrieNodeId { pos }).collect())
+            })
+            .collect();
+        for (state_root, ids) in &roots {
+            for id in ids {
+                if id.as_ptr(arena.memory()).view().node_hash() != *state_root {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("memtrie root {state_root} doesn't match its node"),
+                    ));
+                }
+            }
+        }
+        MEM_TRIE_NUM_ROOTS.with_label_values(&[&shard_uid.to_string()]).set(roots.len() as i64);
+        Ok(Self { arena, roots, heights, shard_uid })
+    }
+
+    /// Returns whether the tries share memory with the tries of another shard,
+    /// which happens for a while after resharding.
+    pub(crate) fn has_shared_memory(&self) -> bool {
+        self.arena.has_shared_memory()
+    }
+
+    /// Returns whether the state root was inserted at the given height.
+    pub(super) fn has_root_at_height(
+        &self,
+        block_height: BlockHeight,
+        state_root: &StateRoot,
+    ) -> bool {
+        self.heights.get(&block_height).map_or(false, |roots| roots.contains(state_root))
+    }
+
+    /// Returns whether the tries hold the given state root, at any height.
+    pub fn contains_root(&self, state_root: &StateRoot) -> bool {
+        self.roots.contains_key(state_root)
+    }
+
     /// Returns the root node corresponding to the given state root.
     pub(super) fn get_root(
         &self,
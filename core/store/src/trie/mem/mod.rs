@@ -9,6 +9,7 @@ pub mod mem_tries;
 pub mod metrics;
 pub mod node;
 mod parallel_loader;
+pub(crate) mod persistence;
 pub mod updating;
 
 /// Check this, because in the code we conveniently assume usize is 8 bytes.
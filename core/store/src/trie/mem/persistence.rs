@@ -0,0 +1,151 @@
+//! Saving in-memory tries on shutdown and restoring them on startup.
+//!
+//! Loading memtries from flat storage takes minutes for big shards, while
+//! reading them back from a file takes about as long as the disk needs to read
+//! the arena.  The file contains the whole `MemTries` of a shard, i.e. the
+//! roots of the flat head and of the blocks after it, so only the deltas
+//! written after the file was saved need to be applied.
+//!
+//! A file is only valid for the flat storage head it was saved at.  If the
+//! flat head moved since, e.g. because the node ran without persistence for a
+//! while, the file is discarded and the memtries are loaded from flat storage.
+
+use super::mem_tries::MemTries;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"MEMTRIES";
+const FORMAT_VERSION: u32 = 1;
+const BUFFER_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct Header {
+    format_version: u32,
+    shard_uid: ShardUId,
+    flat_head: CryptoHash,
+}
+
+fn file_path(dir: &Path, shard_uid: ShardUId) -> PathBuf {
+    dir.join(format!("{shard_uid}.memtries"))
+}
+
+/// Saves the memtries of the shard whose flat storage head is `flat_head`.
+/// The file is written next to its final location and moved there once
+/// complete, so that an interrupted save doesn't leave a broken file.
+pub(crate) fn save_mem_tries(
+    dir: &Path,
+    shard_uid: ShardUId,
+    flat_head: CryptoHash,
+    mem_tries: &MemTries,
+) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let path = file_path(dir, shard_uid);
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, File::create(&tmp_path)?);
+    writer.write_all(MAGIC)?;
+    Header { format_version: FORMAT_VERSION, shard_uid, flat_head }.serialize(&mut writer)?;
+    mem_tries.write_to(&mut writer)?;
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, &path)
+}
+
+/// Loads the memtries of the shard saved at `flat_head`, if there are any.
+///
+/// The file is removed in any case: the restored memtries diverge from it as
+/// soon as the next block is applied, and a stale file would only waste disk.
+pub(crate) fn load_mem_tries(
+    dir: &Path,
+    shard_uid: ShardUId,
+    flat_head: CryptoHash,
+) -> io::Result<Option<MemTries>> {
+    let path = file_path(dir, shard_uid);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let result = read_mem_tries(file, shard_uid, flat_head);
+    std::fs::remove_file(&path)?;
+    result
+}
+
+fn read_mem_tries(
+    file: File,
+    shard_uid: ShardUId,
+    flat_head: CryptoHash,
+) -> io::Result<Option<MemTries>> {
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a memtries file"));
+    }
+    let header = Header::deserialize_reader(&mut reader)?;
+    if header.format_version != FORMAT_VERSION || header.shard_uid != shard_uid {
+        tracing::info!(target: "memtrie", %shard_uid, format_version=header.format_version, "Discarding saved memtries of another shard or format");
+        return Ok(None);
+    }
+    if header.flat_head != flat_head {
+        tracing::info!(target: "memtrie", %shard_uid, saved_flat_head=%header.flat_head, %flat_head, "Discarding saved memtries, flat head has moved");
+        return Ok(None);
+    }
+    let mem_tries = MemTries::read_from(shard_uid, &mut reader)?;
+    Ok(Some(mem_tries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_mem_tries, save_mem_tries};
+    use crate::trie::mem::mem_tries::MemTries;
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::state::FlatStateValue;
+    use near_primitives::types::{BlockHeight, StateRoot};
+
+    fn insert(tries: &mut MemTries, root: StateRoot, key: &[u8], height: BlockHeight) -> StateRoot {
+        let mut update = tries.update(root, false).unwrap();
+        update.insert_memtrie_only(key, FlatStateValue::Inlined(key.to_vec()));
+        let changes = update.to_mem_trie_changes_only();
+        tries.apply_memtrie_changes(height, &changes)
+    }
+
+    #[test]
+    fn test_save_and_load_mem_tries() {
+        let dir = tempfile::tempdir().unwrap();
+        let shard_uid = ShardUId::single_shard();
+        let flat_head = hash(b"flat head");
+
+        let mut tries = MemTries::new(shard_uid);
+        let root1 = insert(&mut tries, CryptoHash::default(), b"first", 1);
+        let root2 = insert(&mut tries, root1, b"second", 2);
+        save_mem_tries(dir.path(), shard_uid, flat_head, &tries).unwrap();
+
+        let mut loaded = load_mem_tries(dir.path(), shard_uid, flat_head).unwrap().unwrap();
+        assert_eq!(loaded.num_roots(), 2);
+        assert!(loaded.has_root_at_height(1, &root1));
+        assert!(loaded.has_root_at_height(2, &root2));
+        assert_eq!(
+            loaded.lookup(&root2, b"first", None).unwrap().map(|value| value.to_flat_value()),
+            Some(FlatStateValue::Inlined(b"first".to_vec()))
+        );
+        // The restored arena keeps allocating and freeing memory.
+        let root3 = insert(&mut loaded, root2, b"third", 3);
+        assert!(loaded.has_root_at_height(3, &root3));
+        loaded.delete_until_height(4);
+        assert_eq!(loaded.num_roots(), 0);
+        assert_eq!(loaded.arena().num_active_allocs(), 0);
+
+        // Loading consumes the file.
+        assert!(load_mem_tries(dir.path(), shard_uid, flat_head).unwrap().is_none());
+
+        // A file saved at another flat head is discarded.
+        save_mem_tries(dir.path(), shard_uid, flat_head, &tries).unwrap();
+        assert!(load_mem_tries(dir.path(), shard_uid, hash(b"other")).unwrap().is_none());
+        assert!(load_mem_tries(dir.path(), shard_uid, flat_head).unwrap().is_none());
+    }
+}
@@ -15,7 +15,8 @@ pub use crate::trie::nibble_slice::NibbleSlice;
 pub use crate::trie::prefetching_trie_storage::{PrefetchApi, PrefetchError};
 pub use crate::trie::shard_tries::{KeyForStateChanges, ShardTries, WrappedTrieChanges};
 pub use crate::trie::state_snapshot::{
-    SnapshotError, StateSnapshot, StateSnapshotConfig, STATE_SNAPSHOT_COLUMNS,
+    list_state_checkpoints, open_state_checkpoint, SnapshotError, StateSnapshot,
+    StateSnapshotConfig, STATE_SNAPSHOT_COLUMNS,
 };
 pub use crate::trie::trie_storage::{TrieCache, TrieCachingStorage, TrieDBStorage, TrieStorage};
 use crate::StorageError;
@@ -49,6 +50,7 @@ pub mod iterator;
 pub mod mem;
 mod nibble_slice;
 mod prefetching_trie_storage;
+pub mod range_iterator;
 mod raw_node;
 pub mod receipts_column_helper;
 pub mod resharding_v2;
@@ -700,6 +702,20 @@ impl Trie {
         trie
     }
 
+    /// Makes a new trie over the same state whose accesses neither accumulate
+    /// a state proof nor charge gas, so that looking ahead at the state does
+    /// not change the outcome of the chunk it is done for.
+    pub fn untracked_reads(&self) -> Self {
+        let mut trie = Self::new_with_memtries(
+            self.storage.clone(),
+            self.memtries.clone(),
+            self.root,
+            self.flat_storage_chunk_view.clone(),
+        );
+        trie.charge_gas_for_trie_node_access = false;
+        trie
+    }
+
     /// Takes the recorded state proof out of the trie.
     pub fn recorded_storage(&self) -> Option<PartialStorage> {
         self.recorder.as_ref().map(|recorder| recorder.borrow_mut().recorded_storage())
@@ -722,6 +738,14 @@ impl Trie {
             .unwrap_or_default()
     }
 
+    /// Breakdown of the recorded state proof size, zero if not recording.
+    pub fn storage_proof_size(&self) -> near_witness_size::StorageProofSize {
+        self.recorder
+            .as_ref()
+            .map(|recorder| recorder.borrow().storage_proof_size())
+            .unwrap_or_default()
+    }
+
     /// Constructs a Trie from the partial storage (i.e. state proof) that
     /// was returned from recorded_storage(). If used to access the same trie
     /// nodes as when the partial storage was generated, this trie will behave
@@ -761,22 +785,23 @@ impl Trie {
         self.storage.as_caching_storage()
     }
 
-    /// Request recording of the code for the given account.
-    pub fn request_code_recording(&self, account_id: AccountId) {
+    /// Request recording of the code stored under the given key, i.e.
+    /// `TrieKey::ContractCode`, `TrieKey::NamespacedContractCode` or
+    /// `TrieKey::GlobalContractCode`.
+    pub fn request_code_recording(&self, key: TrieKey) {
         let Some(recorder) = &self.recorder else {
             return;
         };
         {
             let mut r = recorder.borrow_mut();
-            if r.codes_to_record.contains(&account_id) {
+            if r.codes_to_record.contains(&key) {
                 return;
             }
-            r.codes_to_record.insert(account_id.clone());
+            r.codes_to_record.insert(key.clone());
         }
 
         // Get code length from ValueRef to update estimated upper bound for
         // recorded state.
-        let key = TrieKey::ContractCode { account_id };
         let value_ref = self.get_optimized_ref(&key.to_vec(), KeyLookupMode::FlatStorage);
         if let Ok(Some(value_ref)) = value_ref {
             let mut r = recorder.borrow_mut();
@@ -1464,6 +1489,20 @@ impl Trie {
         Ok(bytes.to_vec())
     }
 
+    /// Returns the raw bytes of several ValueRefs, in the order of the
+    /// hashes.  Unless the trie is recording, the values are read at once,
+    /// bypassing the accounting cache, so this is meant for reads which
+    /// aren't charged, e.g. generating state parts.
+    pub fn retrieve_values(&self, hashes: &[CryptoHash]) -> Result<Vec<Arc<[u8]>>, StorageError> {
+        if self.recorder.is_some() {
+            return hashes
+                .iter()
+                .map(|hash| self.internal_retrieve_trie_node(hash, true))
+                .collect();
+        }
+        self.storage.retrieve_raw_bytes_many(hashes)
+    }
+
     /// Check if the column contains a value with the given `key`.
     ///
     /// This method is guaranteed to not inspect the value stored for this key, which would
@@ -1579,8 +1618,7 @@ impl Trie {
         } else {
             HashSet::default()
         };
-        for account_id in codes_to_record {
-            let trie_key = TrieKey::ContractCode { account_id: account_id.clone() };
+        for trie_key in codes_to_record {
             let _ = self.get(&trie_key.to_vec());
         }
 
@@ -1,4 +1,4 @@
-use crate::config::PrefetchConfig;
+use crate::config::{PrefetchConfig, PrefetchRuleConfig};
 use crate::sync_utils::Monitor;
 use crate::{
     metrics, DBCol, MissingTrieValueContext, StorageError, Store, Trie, TrieCache,
@@ -87,6 +87,7 @@ pub struct PrefetchApi {
     pub sweat_prefetch_senders: Vec<AccountId>,
     pub claim_sweat_prefetch_config: Vec<PrefetchConfig>,
     pub kaiching_prefetch_config: Vec<PrefetchConfig>,
+    pub prefetch_rules: Vec<PrefetchRuleConfig>,
 
     pub shard_uid: ShardUId,
 }
@@ -413,6 +414,7 @@ impl PrefetchApi {
         let enable_receipt_prefetching = trie_config.enable_receipt_prefetching;
         let claim_sweat_prefetch_config = trie_config.claim_sweat_prefetch_config.clone();
         let kaiching_prefetch_config = trie_config.kaiching_prefetch_config.clone();
+        let prefetch_rules = trie_config.prefetch_rules.clone();
         let this = Self {
             work_queue_tx,
             work_queue_rx,
@@ -422,6 +424,7 @@ impl PrefetchApi {
             sweat_prefetch_senders,
             claim_sweat_prefetch_config,
             kaiching_prefetch_config,
+            prefetch_rules,
             shard_uid,
             store,
             shard_cache,
@@ -0,0 +1,243 @@
+//! Iteration over a range of keys in pages, for listing big parts of the
+//! state, e.g. the storage of a contract, without loading it all at once.
+//!
+//! The position of the iteration is captured by a [`TrieRangeCursor`], so
+//! that a later request, possibly on another iterator over the same state
+//! root, can resume where the previous one stopped.
+
+use super::iterator::{TrieItem, TrieIterator};
+use crate::flat::{FlatStateIterator, FlatStorageChunkView};
+use crate::{StorageError, Trie};
+use near_primitives::state::FlatStateValue;
+use std::sync::Arc;
+
+/// Range of keys, `start` inclusive and `end` exclusive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyRange {
+    pub start: Vec<u8>,
+    /// Unbounded if not set.
+    pub end: Option<Vec<u8>>,
+}
+
+impl KeyRange {
+    /// Range of all keys starting with `prefix`.
+    pub fn prefix(prefix: &[u8]) -> Self {
+        Self { start: prefix.to_vec(), end: prefix_successor(prefix) }
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        key >= self.start.as_slice() && self.end.as_deref().map_or(true, |end| key < end)
+    }
+}
+
+/// Returns the smallest key greater than all keys starting with `prefix`.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Key from which the iteration resumes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrieRangeCursor(pub Vec<u8>);
+
+/// Returns the smallest key greater than `key`.
+fn key_successor(key: &[u8]) -> Vec<u8> {
+    let mut next = key.to_vec();
+    next.push(0);
+    next
+}
+
+enum RangeSource<'a> {
+    Trie(TrieIterator<'a>),
+    /// Flat storage only has the values at its head, so this iterates over
+    /// the state at the flat head rather than at the block of the view.
+    FlatStorage {
+        view: &'a FlatStorageChunkView,
+        trie: &'a Trie,
+        iter: FlatStateIterator<'a>,
+    },
+}
+
+pub struct TrieRangeIterator<'a> {
+    source: RangeSource<'a>,
+    range: KeyRange,
+    /// Item read ahead by [`Self::next_n`] to tell whether the range has more.
+    peeked: Option<TrieItem>,
+    /// Key from which the iteration continues, unset once the range is over.
+    next_start: Option<Vec<u8>>,
+}
+
+impl<'a> TrieRangeIterator<'a> {
+    /// Iterates over the range with a trie iterator, e.g. one from
+    /// `Trie::lock_for_iter`.
+    pub fn new(iter: TrieIterator<'a>, range: KeyRange) -> Result<Self, StorageError> {
+        let start = range.start.clone();
+        let mut this =
+            Self { source: RangeSource::Trie(iter), range, peeked: None, next_start: None };
+        this.seek(&start)?;
+        Ok(this)
+    }
+
+    /// Iterates over the range in flat storage, which is faster than the trie
+    /// unless it is in memory.  Values stored by reference are read from
+    /// `trie`.
+    pub fn new_flat(
+        view: &'a FlatStorageChunkView,
+        trie: &'a Trie,
+        range: KeyRange,
+    ) -> Result<Self, StorageError> {
+        let iter = view.iter_flat_state_entries(Some(&range.start), range.end.as_deref());
+        let source = RangeSource::FlatStorage { view, trie, iter };
+        let next_start = Some(range.start.clone());
+        Ok(Self { source, range, peeked: None, next_start })
+    }
+
+    /// Positions the iterator on the first key in the range >= `key`, e.g.
+    /// on the first key with some prefix.
+    pub fn seek(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        let key = key.max(self.range.start.as_slice()).to_vec();
+        match &mut self.source {
+            RangeSource::Trie(iter) => iter.seek(&key)?,
+            RangeSource::FlatStorage { view, iter, .. } => {
+                *iter = view.iter_flat_state_entries(Some(&key), self.range.end.as_deref());
+            }
+        }
+        self.peeked = None;
+        self.next_start = self.range.contains(&key).then_some(key);
+        Ok(())
+    }
+
+    /// Resumes the iteration from a cursor returned by [`Self::cursor`].
+    pub fn resume(&mut self, cursor: &TrieRangeCursor) -> Result<(), StorageError> {
+        self.seek(&cursor.0)
+    }
+
+    /// Returns the cursor to resume the iteration from, unless all keys of
+    /// the range were returned.
+    pub fn cursor(&self) -> Option<TrieRangeCursor> {
+        match &self.peeked {
+            Some((key, _)) => Some(TrieRangeCursor(key.clone())),
+            None => self.next_start.clone().map(TrieRangeCursor),
+        }
+    }
+
+    /// Returns up to `count` next items.  Reads one item ahead, so that
+    /// [`Self::cursor`] is unset if the range has no more items.
+    pub fn next_n(&mut self, count: usize) -> Result<Vec<TrieItem>, StorageError> {
+        let mut items = Vec::with_capacity(count.min(1024));
+        while items.len() < count {
+            match self.next() {
+                Some(item) => items.push(item?),
+                None => return Ok(items),
+            }
+        }
+        if self.peeked.is_none() {
+            self.peeked = self.read_next().transpose()?;
+        }
+        Ok(items)
+    }
+
+    /// Consumes the iterator and returns the trie nodes visited by the
+    /// underlying disk iterator, see `DiskTrieIterator::into_visited_nodes`.
+    pub fn into_visited_nodes(self) -> Vec<Arc<[u8]>> {
+        match self.source {
+            RangeSource::Trie(TrieIterator::Disk(iter)) => iter.into_visited_nodes(),
+            _ => vec![],
+        }
+    }
+
+    fn read_next(&mut self) -> Option<Result<TrieItem, StorageError>> {
+        self.next_start.as_ref()?;
+        let item = match &mut self.source {
+            RangeSource::Trie(iter) => iter.next(),
+            RangeSource::FlatStorage { trie, iter, .. } => iter.next().map(|item| {
+                let (key, value) = item?;
+                let value = match value {
+                    FlatStateValue::Inlined(value) => value,
+                    FlatStateValue::Ref(value_ref) => trie.retrieve_value(&value_ref.hash)?,
+                };
+                Ok((key, value))
+            }),
+        };
+        match item {
+            Some(Ok((key, value))) if self.range.contains(&key) => {
+                self.next_start = Some(key_successor(&key));
+                Some(Ok((key, value)))
+            }
+            Some(Err(err)) => Some(Err(err)),
+            Some(Ok(_)) | None => {
+                self.next_start = None;
+                None
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for TrieRangeIterator<'a> {
+    type Item = Result<TrieItem, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(item) => Some(Ok(item)),
+            None => self.read_next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyRange, TrieRangeIterator};
+    use crate::test_utils::{test_populate_trie, TestTriesBuilder};
+    use crate::Trie;
+    use near_primitives::shard_layout::ShardUId;
+
+    #[test]
+    fn test_prefix_range() {
+        assert_eq!(KeyRange::prefix(b"ab").end, Some(b"ac".to_vec()));
+        assert_eq!(KeyRange::prefix(&[1, 255]).end, Some(vec![2]));
+        assert_eq!(KeyRange::prefix(&[255, 255]).end, None);
+    }
+
+    #[test]
+    fn test_trie_range_iterator_pages() {
+        let tries = TestTriesBuilder::new().build();
+        let shard_uid = ShardUId::single_shard();
+        let changes: Vec<_> = [&b"a"[..], b"b0", b"b1", b"b10", b"b2", b"b3", b"c"]
+            .iter()
+            .map(|key| (key.to_vec(), Some(key.to_vec())))
+            .collect();
+        let root = test_populate_trie(&tries, &Trie::EMPTY_ROOT, shard_uid, changes);
+        let trie = tries.get_trie_for_shard(shard_uid, root);
+        let trie = trie.lock_for_iter();
+
+        let keys = |items: Vec<(Vec<u8>, Vec<u8>)>| {
+            items.into_iter().map(|(key, _)| key).collect::<Vec<_>>()
+        };
+        let mut iter =
+            TrieRangeIterator::new(trie.iter().unwrap(), KeyRange::prefix(b"b")).unwrap();
+        assert_eq!(keys(iter.next_n(2).unwrap()), vec![b"b0".to_vec(), b"b1".to_vec()]);
+        let cursor = iter.cursor().unwrap();
+        assert_eq!(cursor.0, b"b10".to_vec());
+
+        // A new iterator resumes from the cursor.
+        let mut iter =
+            TrieRangeIterator::new(trie.iter().unwrap(), KeyRange::prefix(b"b")).unwrap();
+        iter.resume(&cursor).unwrap();
+        assert_eq!(keys(iter.next_n(2).unwrap()), vec![b"b10".to_vec(), b"b2".to_vec()]);
+        // The last page is complete, so there is nothing to resume from.
+        assert_eq!(keys(iter.next_n(2).unwrap()), vec![b"b3".to_vec()]);
+        assert_eq!(iter.cursor(), None);
+
+        iter.seek(b"b2").unwrap();
+        assert_eq!(keys(iter.next_n(10).unwrap()), vec![b"b2".to_vec(), b"b3".to_vec()]);
+        // Seeking before the range starts at its beginning.
+        iter.seek(b"a").unwrap();
+        assert_eq!(keys(iter.next_n(1).unwrap()), vec![b"b0".to_vec()]);
+    }
+}
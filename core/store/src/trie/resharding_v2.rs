@@ -11,7 +11,7 @@ use near_primitives::receipt::{PromiseYieldTimeout, Receipt};
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_part::PartId;
 use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
-use near_primitives::trie_key::TrieKey;
+use near_primitives::trie_key::{col, TrieKey};
 use near_primitives::types::{StateChangeCause, StateRoot};
 use std::collections::HashMap;
 
@@ -32,6 +32,7 @@ impl ShardTries {
     /// The caller must guarantee that `state_roots` contains all shard_ids
     /// that `key_to_shard_id` that may return
     /// Ignore changes on DelayedReceipts or DelayedReceiptsIndices
+    /// Global contract codes are added to all the children shards
     /// Returns `store_update` and the new state_roots for children shards
     pub fn add_values_to_children_states(
         &self,
@@ -40,6 +41,11 @@ impl ShardTries {
         account_id_to_shard_id: &dyn Fn(&AccountId) -> ShardUId,
     ) -> Result<(StoreUpdate, HashMap<ShardUId, StateRoot>), StorageError> {
         self.add_values_to_children_states_impl(state_roots, values, &|raw_key| {
+            // Every shard stores a copy of each global contract, so it is
+            // copied to all the children.
+            if raw_key.first() == Some(&col::GLOBAL_CONTRACT_CODE) {
+                return Ok(state_roots.keys().copied().collect());
+            }
             // Here changes on DelayedReceipt, DelayedReceiptIndices, PromiseYieldTimeout, and
             // PromiseYieldIndices will be excluded. Both the delayed receipts and the yield
             // timeouts are organized in queues; they cannot be handled part by part because
@@ -49,9 +55,9 @@ impl ShardTries {
                 StorageError::StorageInconsistentState(err)
             })? {
                 let new_shard_uid = account_id_to_shard_id(&account_id);
-                Ok(Some(new_shard_uid))
+                Ok(vec![new_shard_uid])
             } else {
-                Ok(None)
+                Ok(vec![])
             }
         })
     }
@@ -60,12 +66,15 @@ impl ShardTries {
         &self,
         state_roots: &HashMap<ShardUId, StateRoot>,
         values: Vec<(Vec<u8>, Option<Vec<u8>>)>,
-        key_to_shard_id: &dyn Fn(&[u8]) -> Result<Option<ShardUId>, StorageError>,
+        key_to_shard_ids: &dyn Fn(&[u8]) -> Result<Vec<ShardUId>, StorageError>,
     ) -> Result<(StoreUpdate, HashMap<ShardUId, StateRoot>), StorageError> {
         let mut changes_by_shard: HashMap<_, Vec<_>> = HashMap::new();
         for (raw_key, value) in values.into_iter() {
-            if let Some(new_shard_uid) = key_to_shard_id(&raw_key)? {
-                changes_by_shard.entry(new_shard_uid).or_default().push((raw_key, value));
+            for new_shard_uid in key_to_shard_ids(&raw_key)? {
+                changes_by_shard
+                    .entry(new_shard_uid)
+                    .or_default()
+                    .push((raw_key.clone(), value.clone()));
             }
         }
         let mut new_state_roots = state_roots.clone();
@@ -432,10 +441,10 @@ mod tests {
 
                 let (store_update, new_state_roots) = tries
                     .add_values_to_children_states_impl(&state_roots, changes, &|raw_key| {
-                        Ok(Some(ShardUId {
+                        Ok(vec![ShardUId {
                             version: 1,
                             shard_id: (hash(raw_key).0[0] as NumShards % num_shards) as u32,
-                        }))
+                        }])
                     })
                     .unwrap();
                 store_update.commit().unwrap();
@@ -455,6 +464,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_add_global_contract_code_to_children_states() {
+        let tries = TestTriesBuilder::new().build();
+        let state_roots: HashMap<_, _> =
+            (0..2).map(|x| (ShardUId { version: 1, shard_id: x }, Trie::EMPTY_ROOT)).collect();
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        let account_key = TrieKey::ContractData { account_id, key: b"key".to_vec() }.to_vec();
+        let code_key = TrieKey::GlobalContractCode { code_hash: hash(b"code") }.to_vec();
+        let values = vec![
+            (account_key.clone(), Some(b"value".to_vec())),
+            (code_key.clone(), Some(b"code".to_vec())),
+        ];
+
+        let (store_update, state_roots) = tries
+            .add_values_to_children_states(&state_roots, values, &|_| ShardUId {
+                version: 1,
+                shard_id: 0,
+            })
+            .unwrap();
+        store_update.commit().unwrap();
+
+        let get = |shard_id, key: &[u8]| {
+            let shard_uid = ShardUId { version: 1, shard_id };
+            tries.get_trie_for_shard(shard_uid, state_roots[&shard_uid]).get(key).unwrap()
+        };
+        assert_eq!(get(0, &account_key), Some(b"value".to_vec()));
+        assert_eq!(get(1, &account_key), None);
+        assert_eq!(get(0, &code_key), Some(b"code".to_vec()));
+        assert_eq!(get(1, &code_key), Some(b"code".to_vec()));
+    }
+
     #[test]
     fn test_get_delayed_receipts() {
         let mut rng = rand::thread_rng();
@@ -4,7 +4,10 @@ use super::TrieRefcountSubtraction;
 use crate::flat::store_helper::remove_all_state_values;
 use crate::flat::{FlatStorageManager, FlatStorageStatus};
 use crate::trie::config::TrieConfig;
-use crate::trie::mem::loading::load_trie_from_flat_state_and_delta;
+use crate::trie::mem::loading::{
+    load_persisted_trie_and_delta, load_trie_from_flat_state_and_delta,
+};
+use crate::trie::mem::persistence::save_mem_tries;
 use crate::trie::prefetching_trie_storage::PrefetchingThreadsHandle;
 use crate::trie::trie_storage::{TrieCache, TrieCachingStorage};
 use crate::trie::{TrieRefcountAddition, POISONED_LOCK_ERR};
@@ -20,7 +23,7 @@ use near_primitives::types::{
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
-use tracing::info;
+use tracing::{info, warn};
 
 struct ShardTriesInner {
     store: Store,
@@ -89,6 +92,16 @@ impl ShardTries {
         TrieUpdate::new(self.get_view_trie_for_shard(shard_uid, state_root))
     }
 
+    /// Like [`Self::new_trie_update_view`], but reading from the memtries of the shard. Returns
+    /// `None` if the memtries are not loaded or no longer hold `state_root`.
+    pub fn new_trie_update_memtrie_view(
+        &self,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+    ) -> Option<TrieUpdate> {
+        self.get_memtrie_view_trie_for_shard(shard_uid, state_root).map(TrieUpdate::new)
+    }
+
     #[tracing::instrument(
         level = "trace",
         target = "store::trie::shard_tries",
@@ -111,6 +124,14 @@ impl ShardTries {
         is_view || self.get_mem_tries(shard_uid).is_none()
     }
 
+    fn get_trie_storage(&self, shard_uid: ShardUId, is_view: bool) -> Arc<dyn TrieStorage> {
+        if let Some(cache) = self.get_trie_cache_for(shard_uid, is_view) {
+            Arc::new(self.create_caching_storage(cache, shard_uid, is_view))
+        } else {
+            Arc::new(TrieDBStorage::new(self.0.store.clone(), shard_uid))
+        }
+    }
+
     fn get_trie_for_shard_internal(
         &self,
         shard_uid: ShardUId,
@@ -118,12 +139,7 @@ impl ShardTries {
         is_view: bool,
         block_hash: Option<CryptoHash>,
     ) -> Trie {
-        let storage: Arc<dyn TrieStorage> =
-            if let Some(cache) = self.get_trie_cache_for(shard_uid, is_view) {
-                Arc::new(self.create_caching_storage(cache, shard_uid, is_view))
-            } else {
-                Arc::new(TrieDBStorage::new(self.0.store.clone(), shard_uid))
-            };
+        let storage = self.get_trie_storage(shard_uid, is_view);
         let flat_storage_chunk_view = block_hash
             .and_then(|block_hash| self.0.flat_storage_manager.chunk_view(shard_uid, block_hash));
         // Do not use memtries for view queries, for two reasons: memtries do not provide historical state,
@@ -166,6 +182,27 @@ impl ShardTries {
         self.get_trie_for_shard_internal(shard_uid, state_root, true, None)
     }
 
+    /// Returns a view trie reading from the memtries of the shard, or `None` if the memtries are
+    /// not loaded or no longer hold `state_root`.
+    ///
+    /// The memtries only hold the recent roots, which is what most view queries ask for. Lookups
+    /// take the memtries read lock only for their own duration, so a block being applied is not
+    /// blocked for the whole query. The flip side is that the root may be garbage collected while
+    /// the trie is still in use; the lookups then fail with `StorageInconsistentState` and the
+    /// caller should retry on [`Self::get_view_trie_for_shard`].
+    pub fn get_memtrie_view_trie_for_shard(
+        &self,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+    ) -> Option<Trie> {
+        let memtries = self.get_mem_tries(shard_uid)?;
+        if !memtries.read().unwrap().contains_root(&state_root) {
+            return None;
+        }
+        let storage = self.get_trie_storage(shard_uid, true);
+        Some(Trie::new_with_memtries(storage, Some(memtries), state_root, None))
+    }
+
     pub fn store_update(&self) -> StoreUpdate {
         StoreUpdate::new(self.get_db().clone())
     }
@@ -354,6 +391,17 @@ impl ShardTries {
         )
     }
 
+    /// Decrements the reference counts of trie nodes, e.g. of the insertions
+    /// of trie changes reverted with [`TrieRefcountAddition::revert`].
+    pub fn subtract_refcounts(
+        &self,
+        subtractions: &[TrieRefcountSubtraction],
+        shard_uid: ShardUId,
+        store_update: &mut StoreUpdate,
+    ) {
+        self.apply_deletions_inner(subtractions, shard_uid, store_update)
+    }
+
     /// NOTE: This method does not update memtries, thus if memtries could be enabled, also call `apply_memtrie_changes`.
     /// TODO: Consider calling apply_memtrie_changes in this function or adding a new function to call both.
     pub fn apply_all(
@@ -431,12 +479,21 @@ impl ShardTries {
         parallelize: bool,
     ) -> Result<(), StorageError> {
         info!(target: "memtrie", "Loading trie to memory for shard {:?}...", shard_uid);
-        let mem_tries = load_trie_from_flat_state_and_delta(
-            &self.0.store,
-            *shard_uid,
-            state_root,
-            parallelize,
-        )?;
+        let mem_tries = match &self.0.trie_config.mem_tries_persistence_dir {
+            Some(dir) => load_persisted_trie_and_delta(
+                &self.0.store,
+                *shard_uid,
+                state_root,
+                parallelize,
+                dir,
+            )?,
+            None => load_trie_from_flat_state_and_delta(
+                &self.0.store,
+                *shard_uid,
+                state_root,
+                parallelize,
+            )?,
+        };
         self.0.mem_tries.write().unwrap().insert(*shard_uid, Arc::new(RwLock::new(mem_tries)));
         info!(target: "memtrie", "Memtrie loading complete for shard {:?}", shard_uid);
         Ok(())
@@ -486,6 +543,48 @@ impl ShardTries {
         Ok(())
     }
 
+    /// Saves the loaded in-memory tries to the persistence directory, if one
+    /// is configured, so that the next start doesn't need to load them from
+    /// flat storage.  Should be called on shutdown, after blocks are no longer
+    /// applied.  Failures are logged, as the tries can always be loaded from
+    /// flat storage instead.
+    pub fn save_mem_tries(&self) {
+        let Some(dir) = &self.0.trie_config.mem_tries_persistence_dir else {
+            return;
+        };
+        // The memtries are only a startup speedup, they can be loaded from
+        // flat storage just as well.
+        if !crate::disk_pressure::non_essential_writes_allowed() {
+            warn!(target: "memtrie", "Not saving memtries, the disk is running out of space");
+            return;
+        }
+        let mem_tries = self.0.mem_tries.read().unwrap();
+        for (shard_uid, tries) in mem_tries.iter() {
+            let flat_head = match self.0.flat_storage_manager.get_flat_storage_status(*shard_uid) {
+                FlatStorageStatus::Ready(status) => status.flat_head,
+                status => {
+                    warn!(target: "memtrie", %shard_uid, ?status, "Not saving memtries, flat storage is not ready");
+                    continue;
+                }
+            };
+            let tries = tries.read().unwrap();
+            if tries.has_shared_memory() {
+                warn!(target: "memtrie", %shard_uid, "Not saving memtries, they share memory with another shard");
+                continue;
+            }
+            info!(target: "memtrie", %shard_uid, "Saving memtries...");
+            let start = std::time::Instant::now();
+            match save_mem_tries(dir, *shard_uid, flat_head.hash, &tries) {
+                Ok(()) => {
+                    info!(target: "memtrie", %shard_uid, "Saved memtries, took {:?}", start.elapsed())
+                }
+                Err(err) => {
+                    warn!(target: "memtrie", %shard_uid, ?err, "Failed to save memtries")
+                }
+            }
+        }
+    }
+
     /// Retrieves the in-memory tries for the shard.
     pub fn get_mem_tries(&self, shard_uid: ShardUId) -> Option<Arc<RwLock<MemTries>>> {
         let guard = self.0.mem_tries.read().unwrap();
@@ -548,6 +647,10 @@ impl WrappedTrieChanges {
         &self.state_changes
     }
 
+    pub fn trie_changes(&self) -> &TrieChanges {
+        &self.trie_changes
+    }
+
     pub fn apply_mem_changes(&self) {
         self.tries.apply_memtrie_changes(&self.trie_changes, self.shard_uid, self.block_height);
     }
@@ -744,11 +847,14 @@ impl KeyForStateChanges {
 #[cfg(test)]
 mod test {
     use crate::{
-        config::TrieCacheConfig, test_utils::create_test_store,
-        trie::DEFAULT_SHARD_CACHE_TOTAL_SIZE_LIMIT, TrieConfig,
+        config::TrieCacheConfig,
+        test_utils::{create_test_store, test_populate_trie, TestTriesBuilder},
+        trie::DEFAULT_SHARD_CACHE_TOTAL_SIZE_LIMIT,
+        TrieConfig,
     };
 
     use super::*;
+    use assert_matches::assert_matches;
     use std::{assert_eq, str::FromStr};
 
     fn create_trie() -> ShardTries {
@@ -938,4 +1044,28 @@ mod test {
         let mut iter = store.iter_prefix(DBCol::State, &key_prefix);
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn test_memtrie_view_trie() {
+        let shard_uid = ShardUId::single_shard();
+        let tries =
+            TestTriesBuilder::new().with_flat_storage(true).with_in_memory_tries(true).build();
+        let key = b"alice".to_vec();
+        let value = vec![0, 1, 2, 3, 4];
+        let root = test_populate_trie(
+            &tries,
+            &Trie::EMPTY_ROOT,
+            shard_uid,
+            vec![(key.clone(), Some(value.clone()))],
+        );
+
+        let trie = tries.get_memtrie_view_trie_for_shard(shard_uid, root).unwrap();
+        assert_eq!(trie.get(&key), Ok(Some(value.clone())));
+
+        // Once the root is garbage collected from the memtries, it can only be read from disk.
+        tries.delete_memtrie_roots_up_to_height(shard_uid, 2);
+        assert_matches!(trie.get(&key), Err(StorageError::StorageInconsistentState(_)));
+        assert!(tries.get_memtrie_view_trie_for_shard(shard_uid, root).is_none());
+        assert_eq!(tries.get_view_trie_for_shard(shard_uid, root).get(&key), Ok(Some(value)));
+    }
 }
@@ -229,11 +229,12 @@ impl Trie {
         let lookup_values_timer = metrics::GET_STATE_PART_LOOKUP_REF_VALUES_ELAPSED
             .with_label_values(&[&shard_id.to_string()])
             .start_timer();
+        let hashes: Vec<CryptoHash> = value_refs.iter().map(|(_, hash)| *hash).collect();
         let looked_up_value_refs: Vec<_> = value_refs
             .iter()
-            .map(|(k, hash)| Ok((k.clone(), Some(state_trie.retrieve_value(hash)?.to_vec()))))
-            .collect::<Result<_, StorageError>>()
-            .unwrap();
+            .zip(state_trie.retrieve_values(&hashes)?)
+            .map(|((k, _), value)| (k.clone(), Some(value.to_vec())))
+            .collect();
         all_state_part_items.extend(looked_up_value_refs.iter().cloned());
         let lookup_values_duration = lookup_values_timer.stop_and_record();
 
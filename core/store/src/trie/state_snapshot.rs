@@ -1,9 +1,10 @@
 use crate::config::StateSnapshotType;
-use crate::db::STATE_SNAPSHOT_KEY;
+use crate::db::{ShardedDB, STATE_SNAPSHOT_KEY};
 use crate::flat::{FlatStorageManager, FlatStorageStatus};
 use crate::Mode;
 use crate::{checkpoint_hot_storage_and_cleanup_columns, metrics, DBCol, NodeStorage};
 use crate::{option_to_not_found, ShardTries};
+use crate::{ShardDbConfig, StoreOpenerError};
 use crate::{Store, StoreConfig};
 use near_primitives::block::Block;
 use near_primitives::errors::EpochError;
@@ -137,6 +138,62 @@ pub struct StateSnapshotConfig {
     pub home_dir: PathBuf,
     pub hot_store_path: PathBuf,
     pub state_snapshot_subdir: PathBuf,
+    /// Number of state checkpoints to keep, see
+    /// [`ShardTries::get_state_checkpoints_dir`].
+    pub num_checkpoints: usize,
+}
+
+/// Directory within the hot store path where state checkpoints are kept.
+const STATE_CHECKPOINTS_SUBDIR: &str = "state_checkpoints";
+
+/// Returns the blocks whose state checkpoints are in `checkpoints_dir`,
+/// oldest first.
+pub fn list_state_checkpoints(checkpoints_dir: &Path) -> io::Result<Vec<CryptoHash>> {
+    if !checkpoints_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut checkpoints = vec![];
+    for entry in std::fs::read_dir(checkpoints_dir)? {
+        let entry = entry?;
+        // Skips checkpoints which are still being made.
+        let Some(hash) = entry.file_name().to_str().and_then(|name| name.parse().ok()) else {
+            continue;
+        };
+        checkpoints.push((entry.metadata()?.modified()?, hash));
+    }
+    checkpoints.sort();
+    Ok(checkpoints.into_iter().map(|(_, hash)| hash).collect())
+}
+
+/// Opens the state checkpoint made at the epoch boundary after
+/// `prev_block_hash`.  The checkpoint isn't used by the node, so it can be
+/// opened while the node is running.  Opening it for writing modifies the
+/// checkpoint itself, clone it with
+/// [`checkpoint_hot_storage_and_cleanup_columns`] to get a database to
+/// change, e.g. for forking the network.
+pub fn open_state_checkpoint(
+    checkpoints_dir: &Path,
+    prev_block_hash: &CryptoHash,
+    mode: Mode,
+) -> Result<Store, StoreOpenerError> {
+    let checkpoint_dir = checkpoints_dir.join(prev_block_hash.to_string());
+    let checkpoint_path = checkpoint_dir.join("data");
+    if !checkpoint_path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no state checkpoint for {prev_block_hash} in {}", checkpoints_dir.display()),
+        )
+        .into());
+    }
+    let mut config = StoreConfig::default();
+    config.shard_dbs = ShardedDB::list_default_shard_dbs(&checkpoint_path)?
+        .into_iter()
+        .map(|shard_uid| ShardDbConfig { shard_uid, path: None })
+        .collect();
+    // The checkpoint has the kind of the hot database, which is archival on
+    // archival nodes.
+    let storage = NodeStorage::opener(&checkpoint_dir, true, &config, None).open_in_mode(mode)?;
+    Ok(storage.get_hot_store())
 }
 
 pub const STATE_SNAPSHOT_COLUMNS: &[DBCol] = &[
@@ -238,9 +295,64 @@ impl ShardTries {
 
         metrics::HAS_STATE_SNAPSHOT.set(1);
         tracing::info!(target: "state_snapshot", ?prev_block_hash, "Made a checkpoint");
+
+        // The state snapshot is needed, the state checkpoints are not.
+        if let Err(err) = self.update_state_checkpoints(&prev_block_hash) {
+            tracing::error!(target: "state_snapshot", ?err, ?prev_block_hash, "Failed to update the state checkpoints");
+        }
         Ok(Some(state_snapshot_lock.as_ref().unwrap().get_shard_uids()))
     }
 
+    /// Makes a state checkpoint at the epoch boundary after `prev_block_hash`
+    /// and deletes the oldest ones beyond `num_checkpoints`.
+    fn update_state_checkpoints(&self, prev_block_hash: &CryptoHash) -> anyhow::Result<()> {
+        let StateSnapshotConfig { home_dir, hot_store_path, num_checkpoints, .. } =
+            self.state_snapshot_config();
+        let checkpoints_dir = Self::get_state_checkpoints_dir(home_dir, hot_store_path);
+        let checkpoint_dir = checkpoints_dir.join(prev_block_hash.to_string());
+        if *num_checkpoints > 0 && !checkpoint_dir.exists() {
+            let _timer = metrics::CREATE_STATE_CHECKPOINT_ELAPSED.start_timer();
+            // Made under a temporary name so that a checkpoint interrupted
+            // by a crash isn't mistaken for a complete one.
+            let tmp_dir = checkpoints_dir.join(format!("{prev_block_hash}.tmp"));
+            if tmp_dir.exists() {
+                std::fs::remove_dir_all(&tmp_dir)?;
+            }
+            // The flat storage updates are stopped while the snapshot is
+            // made, so the flat state of the checkpoint is at the same blocks
+            // as the flat state of the snapshot before its head was moved.
+            let storage =
+                checkpoint_hot_storage_and_cleanup_columns(&self.get_store(), &tmp_dir, None)?;
+            drop(storage);
+            // Test storage is copied in memory and has no directory.
+            if tmp_dir.exists() {
+                std::fs::rename(&tmp_dir, &checkpoint_dir)?;
+                tracing::info!(target: "state_snapshot", ?prev_block_hash, ?checkpoint_dir, "Made a state checkpoint");
+            }
+        }
+
+        let checkpoints = list_state_checkpoints(&checkpoints_dir)?;
+        let num_to_delete = checkpoints.len().saturating_sub(*num_checkpoints);
+        for hash in &checkpoints[..num_to_delete] {
+            std::fs::remove_dir_all(checkpoints_dir.join(hash.to_string()))?;
+            tracing::info!(target: "state_snapshot", ?hash, "Deleted a state checkpoint");
+        }
+        metrics::STATE_CHECKPOINTS.set((checkpoints.len() - num_to_delete) as i64);
+        Ok(())
+    }
+
+    /// Directory of the state checkpoints, full copies of the hot database
+    /// made together with the state snapshots at the epoch boundaries.
+    /// Unlike a state snapshot, which only has the flat state and is
+    /// replaced at the next epoch, a checkpoint has every column, so the
+    /// chain and the trie at the boundary can be read from it, and the last
+    /// few are kept.  RocksDB checkpoints hard link the SST files, a
+    /// checkpoint takes up disk space only as the node compacts the files it
+    /// shares with the checkpoint away.
+    pub fn get_state_checkpoints_dir(home_dir: &Path, hot_store_path: &Path) -> PathBuf {
+        home_dir.join(hot_store_path).join(STATE_CHECKPOINTS_SUBDIR)
+    }
+
     /// Deletes all snapshots and unsets the STATE_SNAPSHOT_KEY.
     pub fn delete_state_snapshot(&self) {
         let _span =
@@ -3,21 +3,19 @@ use borsh::BorshDeserialize;
 use near_primitives::challenge::PartialState;
 use near_primitives::hash::CryptoHash;
 use near_primitives::trie_key::col::ALL_COLUMNS_WITH_NAMES;
-use near_primitives::types::AccountId;
+use near_primitives::trie_key::TrieKey;
+use near_witness_size::StorageProofSize;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
 /// A simple struct to capture a state proof as it's being accumulated.
 pub struct TrieRecorder {
     recorded: HashMap<CryptoHash, Arc<[u8]>>,
-    size: usize,
-    /// Counts removals performed while recording.
-    /// recorded_storage_size_upper_bound takes it into account when calculating the total size.
-    removal_counter: usize,
-    /// Counts the total size of the contract codes read while recording.
-    code_len_counter: usize,
-    /// Account IDs for which the code should be recorded.
-    pub codes_to_record: HashSet<AccountId>,
+    /// Size of the recorded nodes, and the removals and contract codes which
+    /// recorded_storage_size_upper_bound takes into account.
+    size: StorageProofSize,
+    /// Keys of the contract codes which should be recorded.
+    pub codes_to_record: HashSet<TrieKey>,
 }
 
 #[derive(Clone, Debug)]
@@ -48,9 +46,7 @@ impl TrieRecorder {
     pub fn new() -> Self {
         Self {
             recorded: HashMap::new(),
-            size: 0,
-            removal_counter: 0,
-            code_len_counter: 0,
+            size: StorageProofSize::default(),
             codes_to_record: Default::default(),
         }
     }
@@ -66,16 +62,16 @@ impl TrieRecorder {
     pub fn record(&mut self, hash: &CryptoHash, node: Arc<[u8]>) {
         let size = node.len();
         if self.recorded.insert(*hash, node).is_none() {
-            self.size += size;
+            self.size.record_node(size);
         }
     }
 
     pub fn record_removal(&mut self) {
-        self.removal_counter = self.removal_counter.saturating_add(1)
+        self.size.record_removal();
     }
 
     pub fn record_code_len(&mut self, code_len: usize) {
-        self.code_len_counter = self.code_len_counter.saturating_add(code_len)
+        self.size.record_code(code_len);
     }
 
     pub fn recorded_storage(&mut self) -> PartialStorage {
@@ -85,6 +81,10 @@ impl TrieRecorder {
     }
 
     pub fn recorded_storage_size(&self) -> usize {
+        self.size.nodes_size
+    }
+
+    pub fn storage_proof_size(&self) -> StorageProofSize {
         self.size
     }
 
@@ -93,11 +93,7 @@ impl TrieRecorder {
     /// An upper-bound estimation of the true recorded size after finalization.
     /// See https://github.com/near/nearcore/issues/10890 and https://github.com/near/nearcore/pull/11000 for details.
     pub fn recorded_storage_size_upper_bound(&self) -> usize {
-        // Charge 2000 bytes for every removal
-        let removals_size = self.removal_counter.saturating_mul(2000);
-        self.recorded_storage_size()
-            .saturating_add(removals_size)
-            .saturating_add(self.code_len_counter)
+        self.size.upper_bound()
     }
 
     /// Get statisitics about the recorded trie. Useful for observability and debugging.
@@ -114,9 +110,9 @@ impl TrieRecorder {
         }
         TrieRecorderStats {
             items_count: self.recorded.len(),
-            total_size: self.size,
-            removal_counter: self.removal_counter,
-            code_len_counter: self.code_len_counter,
+            total_size: self.size.nodes_size,
+            removal_counter: self.size.removals,
+            code_len_counter: self.size.code_size,
             trie_column_sizes,
         }
     }
@@ -1,3 +1,4 @@
+use crate::memory_budget::{BudgetedCache, CacheUsage};
 use crate::trie::config::TrieConfig;
 use crate::trie::prefetching_trie_storage::PrefetcherResult;
 use crate::trie::POISONED_LOCK_ERR;
@@ -73,6 +74,9 @@ pub struct TrieCacheInner {
     shard_id: ShardId,
     /// Whether cache is used for view calls execution.
     is_view: bool,
+    /// Lookups which did and didn't find their key, read by the memory budget.
+    hits: u64,
+    misses: u64,
     // Counters tracking operations happening inside the shard cache.
     // Stored here to avoid overhead of looking them up on hot paths.
     metrics: TrieCacheMetrics,
@@ -127,12 +131,36 @@ impl TrieCacheInner {
             total_size_limit,
             shard_id,
             is_view,
+            hits: 0,
+            misses: 0,
             metrics,
         }
     }
 
     pub(crate) fn get(&mut self, key: &CryptoHash) -> Option<Arc<[u8]>> {
-        self.cache.get(key).cloned()
+        let value = self.cache.get(key).cloned();
+        if value.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        value
+    }
+
+    /// Changes the upper bound for the total size, evicting the least
+    /// recently used values if the cache is larger.
+    pub(crate) fn set_total_size_limit(&mut self, total_size_limit: u64) {
+        let total_size_limit = total_size_limit.max(1);
+        let max_elements = total_size_limit.div_ceil(Self::PER_ENTRY_OVERHEAD);
+        let max_elements = NonZeroUsize::new(usize::try_from(max_elements).unwrap()).unwrap();
+        while self.total_size > total_size_limit || self.cache.len() > max_elements.get() {
+            match self.cache.pop_lru() {
+                Some((_, value)) => self.remove_value_of_size(value.len()),
+                None => break,
+            }
+        }
+        self.cache.resize(max_elements);
+        self.total_size_limit = total_size_limit;
     }
 
     pub(crate) fn clear(&mut self) {
@@ -244,12 +272,17 @@ impl TrieCache {
             .unwrap_or(cache_config.default_max_bytes)
             .as_u64();
         let queue_capacity = config.deletions_queue_capacity();
-        Self(Arc::new(Mutex::new(TrieCacheInner::new(
+        let cache = Self(Arc::new(Mutex::new(TrieCacheInner::new(
             queue_capacity,
             total_size_limit,
             shard_uid.shard_id(),
             is_view,
-        ))))
+        ))));
+        if let Some(memory_budget) = &config.memory_budget {
+            let inner: Arc<dyn BudgetedCache> = cache.0.clone();
+            memory_budget.register(Arc::downgrade(&inner));
+        }
+        cache
     }
 
     pub fn get(&self, key: &CryptoHash) -> Option<Arc<[u8]>> {
@@ -281,6 +314,32 @@ impl TrieCache {
     }
 }
 
+impl BudgetedCache for Mutex<TrieCacheInner> {
+    fn name(&self) -> String {
+        let guard = self.lock().expect(POISONED_LOCK_ERR);
+        format!("trie_{}{}", guard.shard_id, if guard.is_view { "_view" } else { "" })
+    }
+
+    fn usage(&self) -> CacheUsage {
+        let guard = self.lock().expect(POISONED_LOCK_ERR);
+        let entry_size = match guard.len() {
+            0 => TrieCacheInner::PER_ENTRY_OVERHEAD,
+            len => guard.total_size / len as u64,
+        };
+        CacheUsage {
+            hits: guard.hits,
+            misses: guard.misses,
+            size: guard.total_size,
+            size_limit: guard.total_size_limit,
+            entry_size,
+        }
+    }
+
+    fn set_size_limit(&self, size_limit: u64) {
+        self.lock().expect(POISONED_LOCK_ERR).set_total_size_limit(size_limit);
+    }
+}
+
 pub trait TrieStorage: Send + Sync {
     /// Get bytes of a serialized `TrieNode`.
     ///
@@ -289,6 +348,21 @@ pub trait TrieStorage: Send + Sync {
     /// [`StorageError`] if the storage fails internally or the hash is not present.
     fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError>;
 
+    /// Get bytes of several nodes or values at once, in the order of the
+    /// hashes.  Storages backed by the database read them with a single
+    /// multi-get, which overlaps the reads.
+    ///
+    /// # Errors
+    ///
+    /// [`StorageError`] if the storage fails internally or any of the hashes
+    /// is not present.
+    fn retrieve_raw_bytes_many(
+        &self,
+        hashes: &[CryptoHash],
+    ) -> Result<Vec<Arc<[u8]>>, StorageError> {
+        hashes.iter().map(|hash| self.retrieve_raw_bytes(hash)).collect()
+    }
+
     /// DEPRECATED.
     /// Returns `TrieCachingStorage` if `TrieStorage` is implemented by it.
     /// TODO (#9004) remove all remaining calls.
@@ -543,6 +617,44 @@ impl TrieStorage for TrieCachingStorage {
         Ok(val)
     }
 
+    fn retrieve_raw_bytes_many(
+        &self,
+        hashes: &[CryptoHash],
+    ) -> Result<Vec<Arc<[u8]>>, StorageError> {
+        // The prefetcher reserves the slots of the values one by one.
+        if self.prefetch_api.is_some() {
+            return hashes.iter().map(|hash| self.retrieve_raw_bytes(hash)).collect();
+        }
+        let mut values: Vec<Option<Arc<[u8]>>> = {
+            let mut guard = self.shard_cache.lock();
+            hashes.iter().map(|hash| guard.get(hash)).collect()
+        };
+        let misses: Vec<CryptoHash> = hashes
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| value.is_none())
+            .map(|(hash, _)| *hash)
+            .collect();
+        self.metrics.shard_cache_hits.inc_by((hashes.len() - misses.len()) as u64);
+        self.metrics.shard_cache_misses.inc_by(misses.len() as u64);
+        let mut read = read_nodes_from_db(&self.store, self.shard_uid, &misses)?.into_iter();
+
+        let mut guard = self.shard_cache.lock();
+        for (hash, value) in hashes.iter().zip(&mut values) {
+            if value.is_some() {
+                continue;
+            }
+            let val = read.next().expect("one value was read for each miss");
+            if val.len() < TrieConfig::max_cached_value_size() {
+                guard.put(*hash, val.clone());
+            } else {
+                self.metrics.shard_cache_too_large.inc();
+            }
+            *value = Some(val);
+        }
+        Ok(values.into_iter().map(|value| value.expect("all values are retrieved")).collect())
+    }
+
     fn as_caching_storage(&self) -> Option<&TrieCachingStorage> {
         Some(self)
     }
@@ -561,6 +673,31 @@ fn read_node_from_db(
     Ok(val.into())
 }
 
+fn read_nodes_from_db(
+    store: &Store,
+    shard_uid: ShardUId,
+    hashes: &[CryptoHash],
+) -> Result<Vec<Arc<[u8]>>, StorageError> {
+    let keys: Vec<_> = hashes
+        .iter()
+        .map(|hash| TrieCachingStorage::get_key_from_shard_uid_and_hash(shard_uid, hash))
+        .collect();
+    let keys: Vec<&[u8]> = keys.iter().map(|key| key.as_ref()).collect();
+    let values =
+        store.multi_get(DBCol::State, &keys).map_err(|_| StorageError::StorageInternalError)?;
+    hashes
+        .iter()
+        .zip(values)
+        .map(|(hash, value)| {
+            let value = value.ok_or(StorageError::MissingTrieValue(
+                MissingTrieValueContext::TrieStorage,
+                *hash,
+            ))?;
+            Ok(value.into())
+        })
+        .collect()
+}
+
 impl TrieCachingStorage {
     fn read_from_db(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
         read_node_from_db(&self.store, self.shard_uid, hash)
@@ -590,6 +727,13 @@ impl TrieStorage for TrieDBStorage {
     fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
         read_node_from_db(&self.store, self.shard_uid, hash)
     }
+
+    fn retrieve_raw_bytes_many(
+        &self,
+        hashes: &[CryptoHash],
+    ) -> Result<Vec<Arc<[u8]>>, StorageError> {
+        read_nodes_from_db(&self.store, self.shard_uid, hashes)
+    }
 }
 
 #[cfg(test)]
@@ -688,6 +832,20 @@ mod trie_cache_tests {
         assert!(cache.cache.contains(&hash(&[3])));
     }
 
+    #[test]
+    fn test_set_total_size_limit() {
+        let mut cache = TrieCacheInner::new(100, 1000, 0, false);
+        put_value(&mut cache, &[1]);
+        put_value(&mut cache, &[2]);
+        put_value(&mut cache, &[3]);
+
+        cache.set_total_size_limit(2 * (TrieCacheInner::PER_ENTRY_OVERHEAD + 1));
+        assert_eq!(cache.current_total_size(), 2 * (TrieCacheInner::PER_ENTRY_OVERHEAD + 1));
+        assert!(!cache.cache.contains(&hash(&[1])));
+        assert!(cache.cache.contains(&hash(&[2])));
+        assert!(cache.cache.contains(&hash(&[3])));
+    }
+
     #[test]
     fn test_small_memory_limit() {
         let total_size_limit = 1;
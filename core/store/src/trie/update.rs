@@ -3,6 +3,7 @@ use super::accounting_cache::TrieAccountingCacheSwitch;
 use super::{OptimizedValueRef, Trie, TrieWithReadLock};
 use crate::trie::{KeyLookupMode, TrieChanges};
 use crate::{StorageError, TrieStorage};
+use near_primitives::account::AccountNamespace;
 use near_primitives::hash::CryptoHash;
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
@@ -19,7 +20,8 @@ mod iterator;
 /// Currently, uses `TrieStorage`. Consider implementing separate logic for
 /// requesting and compiling contracts, as any contract code read and
 /// compilation is a major bottleneck during chunk execution.
-struct ContractStorage {
+#[derive(Clone)]
+pub struct ContractStorage {
     storage: Arc<dyn TrieStorage>,
 }
 
@@ -90,6 +92,12 @@ impl TrieUpdate {
         &self.trie
     }
 
+    /// Storage of the contract code as of before this update, which unlike the
+    /// update itself can be used from other threads.
+    pub fn contract_storage(&self) -> ContractStorage {
+        self.contract_storage.clone()
+    }
+
     pub fn get_ref(
         &self,
         key: &TrieKey,
@@ -143,7 +151,30 @@ impl TrieUpdate {
         account_id: AccountId,
         code_hash: CryptoHash,
     ) -> Option<near_vm_runner::ContractCode> {
-        let key = TrieKey::ContractCode { account_id }.to_vec();
+        self.get_code_by_key(TrieKey::ContractCode { account_id }, code_hash)
+    }
+
+    /// Like `get_code`, for the contract deployed to a namespace of the account.
+    pub fn get_namespaced_code(
+        &self,
+        account_id: AccountId,
+        namespace: AccountNamespace,
+        code_hash: CryptoHash,
+    ) -> Option<near_vm_runner::ContractCode> {
+        self.get_code_by_key(TrieKey::NamespacedContractCode { account_id, namespace }, code_hash)
+    }
+
+    /// Like `get_code`, for a global contract.
+    pub fn get_global_code(&self, code_hash: CryptoHash) -> Option<near_vm_runner::ContractCode> {
+        self.get_code_by_key(TrieKey::GlobalContractCode { code_hash }, code_hash)
+    }
+
+    fn get_code_by_key(
+        &self,
+        trie_key: TrieKey,
+        code_hash: CryptoHash,
+    ) -> Option<near_vm_runner::ContractCode> {
+        let key = trie_key.to_vec();
         let raw_code_update = if let Some(key_value) = self.prospective.get(&key) {
             Some(key_value.value.as_ref().map(<Vec<u8>>::clone))
         } else if let Some(changes_with_trie_key) = self.committed.get(&key) {
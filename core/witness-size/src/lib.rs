@@ -0,0 +1,91 @@
+//! Accounting of the storage proof size, i.e. of the part of the state
+//! witness made of the state touched while applying a chunk.
+//!
+//! The exact size of the proof is only known once the trie changes of the
+//! whole chunk are finalized, which is too late to stop a single receipt
+//! from blowing up the witness.  Instead the runtime keeps an upper bound of
+//! the size as the receipt executes and fails the receipt once its
+//! contribution exceeds `per_receipt_storage_proof_size_limit`.  This crate
+//! has that accounting without the rest of the runtime, so that contract
+//! developers can check the access patterns of their contracts against the
+//! limit, e.g. with the `sandbox_storage_proof_size` host function of sandbox
+//! nodes.
+
+/// Size charged for every removal from the trie.  Removing a key may merge
+/// trie nodes, which needs a sibling of the removed node in the proof.  The
+/// sibling isn't read until the trie changes are finalized, so its size isn't
+/// known while the receipt executes.
+/// See <https://github.com/near/nearcore/issues/10890> for details.
+pub const REMOVAL_SIZE_UPPER_BOUND: usize = 2000;
+
+/// Size of the storage proof recorded so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StorageProofSize {
+    /// Total size of the distinct trie nodes and values read.
+    pub nodes_size: usize,
+    /// Number of removals from the trie.
+    pub removals: usize,
+    /// Total size of the contract codes read.
+    pub code_size: usize,
+}
+
+impl StorageProofSize {
+    /// Records a trie node or value read for the first time.  Nodes already
+    /// in the proof don't add to its size.
+    pub fn record_node(&mut self, size: usize) {
+        self.nodes_size = self.nodes_size.saturating_add(size);
+    }
+
+    pub fn record_removal(&mut self) {
+        self.removals = self.removals.saturating_add(1);
+    }
+
+    pub fn record_code(&mut self, code_len: usize) {
+        self.code_size = self.code_size.saturating_add(code_len);
+    }
+
+    /// Upper bound of the size of the proof once finalized, which is what
+    /// the limits on the storage proof size are checked against.
+    pub fn upper_bound(&self) -> usize {
+        self.nodes_size
+            .saturating_add(self.removals.saturating_mul(REMOVAL_SIZE_UPPER_BOUND))
+            .saturating_add(self.code_size)
+    }
+
+    /// Returns what was recorded since `earlier`, e.g. by a single receipt.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            nodes_size: self.nodes_size.saturating_sub(earlier.nodes_size),
+            removals: self.removals.saturating_sub(earlier.removals),
+            code_size: self.code_size.saturating_sub(earlier.code_size),
+        }
+    }
+
+    /// Whether a receipt which recorded this much would fail with
+    /// `RecordedStorageExceeded` given the per receipt limit.
+    pub fn exceeds(&self, limit: usize) -> bool {
+        self.upper_bound() > limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StorageProofSize, REMOVAL_SIZE_UPPER_BOUND};
+
+    #[test]
+    fn test_storage_proof_size_upper_bound() {
+        let mut size = StorageProofSize::default();
+        size.record_node(100);
+        size.record_code(1000);
+        let before = size;
+        size.record_node(50);
+        size.record_removal();
+        assert_eq!(size.upper_bound(), 1150 + REMOVAL_SIZE_UPPER_BOUND);
+
+        let receipt = size.since(&before);
+        assert_eq!(receipt, StorageProofSize { nodes_size: 50, removals: 1, code_size: 0 });
+        assert_eq!(receipt.upper_bound(), 50 + REMOVAL_SIZE_UPPER_BOUND);
+        assert!(!receipt.exceeds(receipt.upper_bound()));
+        assert!(receipt.exceeds(receipt.upper_bound() - 1));
+    }
+}
@@ -44,7 +44,7 @@ pub fn get_runtime_and_trie_from_genesis(genesis: &Genesis) -> (Runtime, ShardTr
     let runtime = Runtime::new();
     let mut account_ids: HashSet<AccountId> = HashSet::new();
     genesis.for_each_record(|record: &StateRecord| {
-        account_ids.insert(state_record_to_account_id(record).clone());
+        account_ids.extend(state_record_to_account_id(record).cloned());
     });
     let writers = std::sync::atomic::AtomicUsize::new(0);
     let storage_usage_config = &RuntimeConfig::test().fees.storage_usage_config;
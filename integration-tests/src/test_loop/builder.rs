@@ -236,6 +236,8 @@ impl TestLoopBuilder {
                 location: external_storage_location,
                 num_concurrent_requests: 1,
                 num_concurrent_requests_during_catchup: 1,
+                trusted_dump_producers: vec![],
+                external_storage_fallback_threshold: 0,
             }),
         };
 
@@ -426,6 +428,7 @@ impl TestLoopBuilder {
             validator_signer.clone(),
             epoch_manager.clone(),
             store,
+            false,
         );
 
         let gc_actor = GCActor::new(
@@ -149,6 +149,7 @@ fn test_epoch_sync_from_genesis() {
         tier1_accounts_data: Vec::new(),
         tier1_accounts_keys: Vec::new(),
         tier1_connections: Vec::new(),
+        reachability: Default::default(),
     }));
 
     // Check that the new node will reach a high height as well.
@@ -0,0 +1,56 @@
+use near_chain_configs::Genesis;
+use near_client::test_utils::TestEnv;
+use near_o11y::testonly::init_test_logger;
+use nearcore::epoch_shard_stats::save_last_finished_epoch_shard_stats;
+use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+const EPOCH_LENGTH: u64 = 5;
+
+fn save_stats(env: &TestEnv) -> Vec<near_primitives::types::EpochShardStats> {
+    let client = &env.clients[0];
+    save_last_finished_epoch_shard_stats(
+        client.chain.chain_store(),
+        client.epoch_manager.as_ref(),
+        client.runtime_adapter.as_ref(),
+    )
+    .unwrap()
+}
+
+/// Check that the statistics are saved once per finished epoch and that the
+/// account delta is computed against the previous epoch.
+#[test]
+fn test_save_epoch_shard_stats() {
+    init_test_logger();
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = EPOCH_LENGTH;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+
+    // Nothing is saved until the first epoch is finalized.
+    env.produce_block(0, 1);
+    assert!(save_stats(&env).is_empty());
+
+    for height in 2..=EPOCH_LENGTH + 4 {
+        env.produce_block(0, height);
+    }
+    let first_stats = save_stats(&env);
+    assert_eq!(first_stats.len(), 1);
+    let stats = &first_stats[0];
+    assert!(stats.state_size > 0);
+    assert!(stats.num_accounts >= 2);
+    assert_eq!(stats.num_accounts_delta, None);
+    assert!(stats.num_chunks > 0);
+    assert_eq!(
+        env.clients[0].chain.chain_store().get_all_epoch_shard_stats(&stats.epoch_id).unwrap(),
+        first_stats
+    );
+    // The statistics of the epoch are only saved once.
+    assert!(save_stats(&env).is_empty());
+
+    for height in EPOCH_LENGTH + 5..=2 * EPOCH_LENGTH + 4 {
+        env.produce_block(0, height);
+    }
+    let second_stats = save_stats(&env);
+    assert_eq!(second_stats.len(), 1);
+    assert_eq!(second_stats[0].epoch_height, stats.epoch_height + 1);
+    assert_eq!(second_stats[0].num_accounts_delta, Some(0));
+}
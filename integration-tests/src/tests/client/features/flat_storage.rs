@@ -130,10 +130,14 @@ fn test_flat_storage_upgrade() {
                 .outcome_with_id
                 .outcome
                 .metadata;
-            if let ExecutionMetadata::V3(profile_data) = metadata {
-                profile_data.get_ext_cost(ExtCosts::touching_trie_node)
-            } else {
-                panic!("Too old version of metadata: {metadata:?}");
+            match metadata {
+                ExecutionMetadata::V3(profile_data) => {
+                    profile_data.get_ext_cost(ExtCosts::touching_trie_node)
+                }
+                ExecutionMetadata::V4(metadata) => {
+                    metadata.gas_profile.get_ext_cost(ExtCosts::touching_trie_node)
+                }
+                _ => panic!("Too old version of metadata: {metadata:?}"),
             }
         })
         .collect();
@@ -116,7 +116,7 @@ fn get_nonce(env: &mut TestEnv, signer: &InMemorySigner) -> u64 {
 }
 
 fn account_exists(env: &mut TestEnv, account_id: AccountId) -> bool {
-    let request = QueryRequest::ViewAccount { account_id };
+    let request = QueryRequest::ViewAccount { account_id, include_proof: false };
     env.query_view(request).is_ok()
 }
 
@@ -444,6 +444,37 @@ fn test_catchup_succeeds_even_if_no_new_blocks() {
     wait_for_flat_storage_creation(&mut env, START_HEIGHT + 3, shard_uid, false);
 }
 
+/// Check that flat storage can be rebuilt while the node keeps processing blocks.
+#[test]
+fn test_flat_storage_rebuild() {
+    init_test_logger();
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let store = create_test_store();
+    let shard_uid = ShardLayout::v0_single_shard().shard_uids().next().unwrap();
+    let mut env = setup_env(&genesis, store.clone());
+    for height in 1..START_HEIGHT {
+        env.produce_block(0, height);
+    }
+    assert_matches!(
+        get_flat_storage_manager(&env).get_flat_storage_status(shard_uid),
+        FlatStorageStatus::Ready(_)
+    );
+
+    env.clients[0].rebuild_flat_storage(shard_uid).unwrap();
+    assert!(get_flat_storage_manager(&env).get_flat_storage_for_shard(shard_uid).is_none());
+    assert_eq!(
+        store_helper::get_flat_storage_status(&store, shard_uid),
+        Ok(FlatStorageStatus::Empty)
+    );
+
+    // Another rebuild is rejected once creation is in progress.
+    env.produce_block(0, START_HEIGHT);
+    env.clients[0].run_flat_storage_creation_step().unwrap();
+    assert!(env.clients[0].rebuild_flat_storage(shard_uid).is_err());
+
+    wait_for_flat_storage_creation(&mut env, START_HEIGHT + 1, shard_uid, true);
+}
+
 /// Tests the flat storage iterator. Running on a chain with 3 shards, and couple blocks produced.
 #[test]
 fn test_flat_storage_iter() {
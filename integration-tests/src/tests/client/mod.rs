@@ -3,6 +3,7 @@ mod block_corruption;
 mod challenges;
 mod chunks_management;
 mod cold_storage;
+mod epoch_shard_stats;
 mod features;
 mod flat_storage;
 mod process_blocks;
@@ -979,6 +979,7 @@ fn client_sync_headers() {
                             last_block: Some(BlockInfo { height: 5, hash: hash(&[5]) }),
                             tracked_shards: vec![],
                             archival: false,
+                            rpc_url: None,
                         },
                     },
                     received_bytes_per_sec: 0,
@@ -1005,6 +1006,7 @@ fn client_sync_headers() {
                 tier1_connections: vec![],
                 tier1_accounts_keys: vec![],
                 tier1_accounts_data: vec![],
+                reachability: Default::default(),
             })
             .with_span_context(),
         );
@@ -2117,7 +2119,10 @@ fn test_data_reset_before_state_sync() {
             &head.prev_block_hash,
             &head.last_block_hash,
             head_block.header().epoch_id(),
-            &QueryRequest::ViewAccount { account_id: "test_account".parse().unwrap() },
+            &QueryRequest::ViewAccount {
+                account_id: "test_account".parse().unwrap(),
+                include_proof: false,
+            },
         )
         .unwrap();
     assert_matches!(response.kind, QueryResponseKind::ViewAccount(_));
@@ -2131,7 +2136,10 @@ fn test_data_reset_before_state_sync() {
         &head.prev_block_hash,
         &head.last_block_hash,
         head_block.header().epoch_id(),
-        &QueryRequest::ViewAccount { account_id: "test_account".parse().unwrap() },
+        &QueryRequest::ViewAccount {
+            account_id: "test_account".parse().unwrap(),
+            include_proof: false,
+        },
     );
     // TODO(#3742): ViewClient still has data in cache by current design.
     assert!(response.is_ok());
@@ -3102,7 +3110,7 @@ fn test_query_final_state() {
                     &final_head.prev_block_hash,
                     last_final_block.hash(),
                     last_final_block.header().epoch_id(),
-                    &QueryRequest::ViewAccount { account_id },
+                    &QueryRequest::ViewAccount { account_id, include_proof: false },
                 )
                 .unwrap();
             match response.kind {
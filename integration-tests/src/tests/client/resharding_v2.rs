@@ -535,7 +535,7 @@ fn check_account(env: &TestEnv, account_id: &AccountId, block: &Block) {
                 prev_hash,
                 block.hash(),
                 block.header().epoch_id(),
-                &QueryRequest::ViewAccount { account_id: account_id.clone() },
+                &QueryRequest::ViewAccount { account_id: account_id.clone(), include_proof: false },
             )
             .unwrap();
 
@@ -551,7 +551,10 @@ fn check_account(env: &TestEnv, account_id: &AccountId, block: &Block) {
                     block.header().prev_hash(),
                     block.hash(),
                     block.header().epoch_id(),
-                    &QueryRequest::ViewAccount { account_id: account_id.clone() },
+                    &QueryRequest::ViewAccount {
+                        account_id: account_id.clone(),
+                        include_proof: false,
+                    },
                 )
                 .unwrap();
         }
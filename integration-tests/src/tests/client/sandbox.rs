@@ -3,13 +3,16 @@ use near_chain_configs::Genesis;
 use near_client::test_utils::TestEnv;
 use near_client::ProcessTxResponse;
 use near_crypto::{InMemorySigner, KeyType, Signer};
-use near_primitives::account::Account;
+use near_primitives::account::{AccessKey, Account};
+use near_primitives::hash::CryptoHash;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
+use near_primitives::sandbox::SandboxStateDeletion;
 use near_primitives::state_record::StateRecord;
 use near_primitives::transaction::{
     Action, DeployContractAction, FunctionCallAction, SignedTransaction,
 };
 use near_primitives::types::{AccountId, BlockHeight, Nonce};
+use near_primitives::views::{AccessKeyListOptions, QueryRequest, QueryResponseKind};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 
 fn test_setup() -> (TestEnv, Signer) {
@@ -106,3 +109,48 @@ fn test_patch_account() {
     let test1_after = env.query_account("test1".parse().unwrap());
     assert_eq!(test1_after.amount, 10);
 }
+
+fn query_access_key_count(env: &mut TestEnv, account_id: AccountId) -> usize {
+    let request =
+        QueryRequest::ViewAccessKeyList { account_id, options: AccessKeyListOptions::default() };
+    match env.query_view(request).unwrap().kind {
+        QueryResponseKind::AccessKeyList(list) => list.keys.len(),
+        _ => panic!("Wrong return value"),
+    }
+}
+
+#[test]
+fn test_patch_state_batch() {
+    let (mut env, signer) = test_setup();
+    let test1_key =
+        InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1").public_key();
+    let state_item = env.query_state("test0".parse().unwrap()).swap_remove(0);
+    let test2: Account = env.query_account("test1".parse().unwrap()).into();
+
+    let patch = SandboxStatePatch::new(vec![
+        StateRecord::AccessKey {
+            account_id: "test2".parse().unwrap(),
+            public_key: signer.public_key(),
+            access_key: AccessKey::full_access(),
+        },
+        StateRecord::Account { account_id: "test2".parse().unwrap(), account: test2 },
+    ])
+    .with_deletions(vec![
+        SandboxStateDeletion::Data {
+            account_id: "test0".parse().unwrap(),
+            data_key: state_item.key,
+        },
+        SandboxStateDeletion::Contract { account_id: "test0".parse().unwrap() },
+        SandboxStateDeletion::AccessKey {
+            account_id: "test1".parse().unwrap(),
+            public_key: test1_key,
+        },
+    ]);
+    env.clients[0].chain.patch_state(patch);
+    do_blocks(&mut env, 9, 20);
+
+    assert!(env.query_state("test0".parse().unwrap()).is_empty());
+    assert_eq!(env.query_account("test0".parse().unwrap()).code_hash, CryptoHash::default());
+    assert_eq!(query_access_key_count(&mut env, "test1".parse().unwrap()), 0);
+    assert_eq!(query_access_key_count(&mut env, "test2".parse().unwrap()), 1);
+}
@@ -224,7 +224,10 @@ fn run_state_sync_with_dumped_parts(
                 &head.prev_block_hash,
                 &head.last_block_hash,
                 head_block.header().epoch_id(),
-                &QueryRequest::ViewAccount { account_id: "test_account".parse().unwrap() },
+                &QueryRequest::ViewAccount {
+                    account_id: "test_account".parse().unwrap(),
+                    include_proof: false,
+                },
             )
             .unwrap();
         assert_matches!(response.kind, QueryResponseKind::ViewAccount(_));
@@ -331,7 +334,10 @@ fn run_state_sync_with_dumped_parts(
             &synced_block_tip.prev_block_hash,
             &synced_block_tip.last_block_hash,
             synced_block_header.epoch_id(),
-            &QueryRequest::ViewAccount { account_id: "test_account".parse().unwrap() },
+            &QueryRequest::ViewAccount {
+                account_id: "test_account".parse().unwrap(),
+                include_proof: false,
+            },
         );
 
         if is_final_block_in_new_epoch {
@@ -14,7 +14,7 @@ use near_store::{
     config::TrieCacheConfig, test_utils::create_test_store, Mode, ShardTries, StateSnapshotConfig,
     StoreConfig, TrieConfig,
 };
-use near_store::{NodeStorage, Store};
+use near_store::{list_state_checkpoints, open_state_checkpoint, DBCol, NodeStorage, Store};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 use std::path::PathBuf;
 
@@ -30,6 +30,7 @@ impl StateSnaptshotTestEnv {
         home_dir: PathBuf,
         hot_store_path: PathBuf,
         state_snapshot_subdir: PathBuf,
+        num_checkpoints: usize,
         store: &Store,
     ) -> Self {
         let trie_cache_config = TrieCacheConfig {
@@ -49,6 +50,7 @@ impl StateSnaptshotTestEnv {
             home_dir: home_dir.clone(),
             hot_store_path: hot_store_path.clone(),
             state_snapshot_subdir: state_snapshot_subdir.clone(),
+            num_checkpoints,
         };
         let shard_tries = ShardTries::new(
             store.clone(),
@@ -66,7 +68,7 @@ fn set_up_test_env_for_state_snapshots(store: &Store) -> StateSnaptshotTestEnv {
         tempfile::Builder::new().prefix("storage").tempdir().unwrap().path().to_path_buf();
     let hot_store_path = PathBuf::from("data");
     let state_snapshot_subdir = PathBuf::from("state_snapshot");
-    StateSnaptshotTestEnv::new(home_dir, hot_store_path, state_snapshot_subdir, store)
+    StateSnaptshotTestEnv::new(home_dir, hot_store_path, state_snapshot_subdir, 0, store)
 }
 
 #[test]
@@ -257,3 +259,53 @@ fn test_make_state_snapshot() {
         )
     );
 }
+
+#[test]
+// Makes a state snapshot after processing every block, with two state
+// checkpoints kept. Checks that the latest checkpoints can be opened and have
+// the blocks processed before they were made.
+fn test_state_checkpoints() {
+    init_test_logger();
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config)
+        .clients_count(1)
+        .use_state_snapshots()
+        .real_stores()
+        .nightshade_runtimes(&genesis)
+        .build();
+
+    let store = env.clients[0].chain.chain_store().store();
+    let home_dir =
+        tempfile::Builder::new().prefix("storage").tempdir().unwrap().path().to_path_buf();
+    let test_env = StateSnaptshotTestEnv::new(
+        home_dir,
+        PathBuf::from("data"),
+        PathBuf::from("state_snapshot"),
+        2,
+        store,
+    );
+    let checkpoints_dir =
+        ShardTries::get_state_checkpoints_dir(&test_env.home_dir, &test_env.hot_store_path);
+
+    let mut block_hashes = vec![];
+    for i in 1..=3 {
+        let block = env.clients[0].produce_block(i).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+        test_env.shard_tries.delete_state_snapshot();
+        test_env
+            .shard_tries
+            .create_state_snapshot(*block.hash(), &[ShardUId::single_shard()], &block)
+            .unwrap();
+        block_hashes.push(*block.hash());
+        // Checkpoints are ordered by the time they were made.
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    assert_eq!(list_state_checkpoints(&checkpoints_dir).unwrap(), block_hashes[1..]);
+    for block_hash in &block_hashes[1..] {
+        let checkpoint =
+            open_state_checkpoint(&checkpoints_dir, block_hash, Mode::ReadOnly).unwrap();
+        assert!(checkpoint.exists(DBCol::Block, block_hash.as_ref()).unwrap());
+    }
+    assert!(open_state_checkpoint(&checkpoints_dir, &block_hashes[0], Mode::ReadOnly).is_err());
+}
@@ -494,6 +494,8 @@ fn sync_state_dump() {
                                         },
                                         num_concurrent_requests: 1,
                                         num_concurrent_requests_during_catchup: 1,
+                                        trusted_dump_producers: vec![],
+                                        external_storage_fallback_threshold: 0,
                                     });
 
                                 let nearcore::NearNode {
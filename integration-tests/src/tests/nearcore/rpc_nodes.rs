@@ -280,6 +280,7 @@ fn test_query_rpc_account_view_must_succeed() {
                 block_reference: near_primitives::types::BlockReference::Finality(Finality::Final),
                 request: near_primitives::views::QueryRequest::ViewAccount {
                     account_id: "near.0".parse().unwrap(),
+                    include_proof: false,
                 },
             })
             .await
@@ -319,7 +320,7 @@ fn test_query_rpc_account_view_account_doesnt_exist_must_return_error() {
                 .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                     block_reference: near_primitives::types::BlockReference::Finality(Finality::Final),
                     request: near_primitives::views::QueryRequest::ViewAccount {
-                        account_id: "accountdoesntexist.0".parse().unwrap(),
+                        account_id: "accountdoesntexist.0".parse().unwrap(), include_proof: false,
                     },
                 })
                 .await;
@@ -456,6 +457,7 @@ fn test_check_unknown_tx_must_return_error() {
                                     sender_account_id: transaction.transaction.signer_id().clone(),
                                 },
                                 wait_until: TxExecutionStatus::None,
+                                include_proof: false,
                             })
                             .map_err(|err| {
                                 assert_eq!(
@@ -515,6 +517,7 @@ fn test_tx_status_on_lightclient_must_return_does_not_track_shard() {
                         let request = RpcTransactionStatusRequest {
                             transaction_info: TransactionInfo::from_signed_tx(transaction),
                             wait_until: TxExecutionStatus::None,
+                            include_proof: false,
                         };
                         let _ = client
                             .tx(request)
@@ -280,6 +280,7 @@ fn test_validator_kickout() {
                                         BlockReference::latest(),
                                         QueryRequest::ViewAccount {
                                             account_id: test_nodes[i as usize].account_id.clone(),
+                                            include_proof: false,
                                         },
                                     )
                                     .with_span_context(),
@@ -306,6 +307,7 @@ fn test_validator_kickout() {
                                         BlockReference::latest(),
                                         QueryRequest::ViewAccount {
                                             account_id: test_nodes[i as usize].account_id.clone(),
+                                            include_proof: false,
                                         },
                                     )
                                     .with_span_context(),
@@ -461,6 +463,7 @@ fn test_validator_join() {
                                     BlockReference::latest(),
                                     QueryRequest::ViewAccount {
                                         account_id: test_nodes[1].account_id.clone(),
+                                        include_proof: false,
                                     },
                                 )
                                 .with_span_context(),
@@ -480,6 +483,7 @@ fn test_validator_join() {
                                     BlockReference::latest(),
                                     QueryRequest::ViewAccount {
                                         account_id: test_nodes[2].account_id.clone(),
+                                        include_proof: false,
                                     },
                                 )
                                 .with_span_context(),
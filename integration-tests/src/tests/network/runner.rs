@@ -148,6 +148,7 @@ fn setup_network_node(
         validator_signer,
         epoch_manager,
         runtime.store().clone(),
+        false,
     ));
     shards_manager_adapter.bind(shards_manager_actor.with_auto_span_context());
     let peer_manager = PeerManagerActor::spawn(
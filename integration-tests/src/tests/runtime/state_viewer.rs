@@ -1,26 +1,31 @@
-use std::{collections::HashMap, io, sync::Arc};
+use std::{collections::HashMap, io, num::NonZeroU32, sync::Arc};
 
 use borsh::BorshDeserialize;
 
 use crate::runtime_utils::{get_runtime_and_trie, get_test_trie_viewer, TEST_SHARD_UID};
+use near_crypto::{KeyType, PublicKey};
 use near_primitives::{
-    account::Account,
+    account::{AccessKey, AccessKeyPermission, Account, FunctionCallPermission},
     hash::{hash as sha256, CryptoHash},
     serialize::to_base64,
     trie_key::trie_key_parsers,
     types::{AccountId, StateRoot},
-    views::StateItem,
+    views::{AccessKeyListOptions, AccessKeyPermissionKind, StateItem, ViewStateOptions},
 };
 use near_primitives::{
+    challenge::PartialState,
     test_utils::MockEpochInfoProvider,
     trie_key::TrieKey,
     types::{EpochId, StateChangeCause},
     version::PROTOCOL_VERSION,
 };
-use near_store::{set_account, NibbleSlice, RawTrieNode, RawTrieNodeWithSize, ShardUId};
+use near_store::{
+    get_account, set_access_key, set_account, NibbleSlice, PartialStorage, RawTrieNode,
+    RawTrieNodeWithSize, ShardUId, Trie,
+};
 use node_runtime::state_viewer::errors;
 use node_runtime::state_viewer::*;
-use testlib::runtime_utils::alice_account;
+use testlib::runtime_utils::{alice_account, bob_account};
 
 struct ProofVerifier {
     nodes: HashMap<CryptoHash, RawTrieNodeWithSize>,
@@ -205,8 +210,9 @@ fn assert_view_state(
         .map(|(key, value)| StateItem { key: key.to_vec().into(), value: value.to_vec().into() })
         .collect::<Vec<_>>();
 
-    let view_state =
-        |include_proof| trie_viewer.view_state(&state_update, &alice, prefix, include_proof);
+    let view_state = |include_proof| {
+        trie_viewer.view_state(&state_update, &alice, prefix, include_proof, &Default::default())
+    };
 
     // Test without proof
     let result = view_state(false).unwrap();
@@ -355,6 +361,25 @@ fn test_view_state() {
     }
 }
 
+#[test]
+fn test_view_account_with_proof() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let trie = tries.get_view_trie_for_shard(TEST_SHARD_UID, root);
+    let trie_viewer = TrieViewer::default();
+    let (account, proof) = trie_viewer.view_account_with_proof(&trie, &alice_account()).unwrap();
+    assert_eq!(Some(account.clone()), get_account(&trie, &alice_account()).unwrap());
+
+    // The proof alone is enough to look up the account against the state root,
+    // but not to look up any other account.
+    let partial_storage = PartialStorage { nodes: PartialState::TrieValues(proof) };
+    let proof_trie = Trie::from_recorded_storage(partial_storage, root, false);
+    assert_eq!(Some(account), get_account(&proof_trie, &alice_account()).unwrap());
+    assert!(get_account(&proof_trie, &bob_account()).is_err());
+
+    let result = trie_viewer.view_account_with_proof(&trie, &"alina".parse().unwrap());
+    assert!(matches!(result, Err(errors::ViewAccountError::AccountDoesNotExist { .. })));
+}
+
 #[test]
 fn test_view_state_too_large() {
     let (_, tries, root) = get_runtime_and_trie();
@@ -365,7 +390,8 @@ fn test_view_state_too_large() {
         &Account::new(0, 0, 0, CryptoHash::default(), 50_001, PROTOCOL_VERSION),
     );
     let trie_viewer = TrieViewer::new(Some(50_000), None);
-    let result = trie_viewer.view_state(&state_update, &alice_account(), b"", false);
+    let result =
+        trie_viewer.view_state(&state_update, &alice_account(), b"", false, &Default::default());
     assert!(matches!(result, Err(errors::ViewStateError::AccountStateTooLarge { .. })));
 }
 
@@ -381,10 +407,141 @@ fn test_view_state_with_large_contract() {
     );
     state_update.set(TrieKey::ContractCode { account_id: alice_account() }, contract_code);
     let trie_viewer = TrieViewer::new(Some(50_000), None);
-    let result = trie_viewer.view_state(&state_update, &alice_account(), b"", false);
+    let result =
+        trie_viewer.view_state(&state_update, &alice_account(), b"", false, &Default::default());
     assert!(result.is_ok());
 }
 
+#[test]
+fn test_view_state_pagination() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let mut state_update = tries.new_trie_update(TEST_SHARD_UID, root);
+    for i in 0..10 {
+        let key = format!("key{i}").into_bytes();
+        state_update.set(TrieKey::ContractData { account_id: alice_account(), key }, vec![i]);
+    }
+    state_update.set(
+        TrieKey::ContractData { account_id: bob_account(), key: b"key".to_vec() },
+        b"bob".to_vec(),
+    );
+    state_update.commit(StateChangeCause::InitialState);
+    let trie_changes = state_update.finalize().unwrap().1;
+    let mut db_changes = tries.store_update();
+    let new_root = tries.apply_all(&trie_changes, TEST_SHARD_UID, &mut db_changes);
+    db_changes.commit().unwrap();
+    let state_update = tries.new_trie_update(TEST_SHARD_UID, new_root);
+
+    // A limit lifts the size limit of the state.
+    let trie_viewer = TrieViewer::new(Some(1), None);
+    let view_state = |options: ViewStateOptions| {
+        trie_viewer.view_state(&state_update, &alice_account(), b"key", false, &options)
+    };
+    assert!(matches!(
+        view_state(ViewStateOptions::default()),
+        Err(errors::ViewStateError::AccountStateTooLarge { .. })
+    ));
+
+    // Paging through the values lists every value exactly once, in order.
+    let mut values = vec![];
+    let mut cursor = None;
+    loop {
+        let page = view_state(ViewStateOptions { cursor, limit: Some(3) }).unwrap();
+        assert!(page.values.len() <= 3);
+        values.extend(page.values);
+        match page.next_cursor {
+            Some(next_cursor) => cursor = Some(next_cursor),
+            None => break,
+        }
+    }
+    let want = (0..10)
+        .map(|i| StateItem { key: format!("key{i}").into_bytes().into(), value: vec![i].into() })
+        .collect::<Vec<_>>();
+    assert_eq!(values, want);
+
+    // The page ending with the last value has no cursor.
+    let page = view_state(ViewStateOptions { cursor: None, limit: Some(10) }).unwrap();
+    assert_eq!(page.values.len(), 10);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn test_view_access_key_list_pagination() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let mut state_update = tries.new_trie_update(TEST_SHARD_UID, root);
+    let account_id: AccountId = "relayer.near".parse().unwrap();
+    for i in 0..10 {
+        let public_key = PublicKey::from_seed(KeyType::ED25519, &format!("key{i}"));
+        let access_key = match i % 3 {
+            0 => AccessKey::full_access(),
+            1 => AccessKey {
+                nonce: 0,
+                permission: AccessKeyPermission::FunctionCall(FunctionCallPermission {
+                    allowance: None,
+                    receiver_id: "app.near".to_string(),
+                    method_names: vec![],
+                }),
+            },
+            _ => AccessKey {
+                nonce: 0,
+                permission: AccessKeyPermission::FunctionCall(FunctionCallPermission {
+                    allowance: None,
+                    receiver_id: "other.near".to_string(),
+                    method_names: vec![],
+                }),
+            },
+        };
+        set_access_key(&mut state_update, account_id.clone(), public_key, &access_key);
+    }
+    let trie_viewer = TrieViewer::default();
+    let list = |options: AccessKeyListOptions| {
+        trie_viewer.view_access_key_list(&state_update, &account_id, &options).unwrap()
+    };
+
+    let all_keys = list(AccessKeyListOptions::default());
+    assert_eq!(all_keys.keys.len(), 10);
+    assert_eq!(all_keys.next_cursor, None);
+
+    // Paging through the keys lists every key exactly once, in the same order.
+    let mut paged_keys = vec![];
+    let mut cursor = None;
+    loop {
+        let page =
+            list(AccessKeyListOptions { cursor, limit: NonZeroU32::new(3), ..Default::default() });
+        assert!(page.keys.len() <= 3);
+        paged_keys.extend(page.keys);
+        match page.next_cursor {
+            Some(next_cursor) => cursor = Some(next_cursor),
+            None => break,
+        }
+    }
+    assert_eq!(paged_keys, all_keys.keys);
+
+    let full_access = list(AccessKeyListOptions {
+        permission: Some(AccessKeyPermissionKind::FullAccess),
+        ..Default::default()
+    });
+    assert_eq!(full_access.keys.len(), 4);
+    let function_call = list(AccessKeyListOptions {
+        permission: Some(AccessKeyPermissionKind::FunctionCall),
+        ..Default::default()
+    });
+    assert_eq!(function_call.keys.len(), 6);
+    let app_keys = list(AccessKeyListOptions {
+        receiver_id: Some("app.near".parse().unwrap()),
+        limit: NonZeroU32::new(2),
+        ..Default::default()
+    });
+    assert_eq!(app_keys.keys.len(), 2);
+    let last_app_keys = list(AccessKeyListOptions {
+        cursor: app_keys.next_cursor,
+        receiver_id: Some("app.near".parse().unwrap()),
+        limit: NonZeroU32::new(2),
+        ..Default::default()
+    });
+    assert_eq!(last_app_keys.keys.len(), 1);
+    assert_eq!(last_app_keys.next_cursor, None);
+}
+
 #[test]
 fn test_log_when_panic() {
     let (viewer, root) = get_test_trie_viewer();
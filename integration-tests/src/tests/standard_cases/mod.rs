@@ -29,7 +29,9 @@ use crate::user::User;
 use near_parameters::{RuntimeConfig, RuntimeConfigStore};
 use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptV0};
 use near_primitives::test_utils;
-use near_primitives::transaction::{Action, DeployContractAction, FunctionCallAction};
+use near_primitives::transaction::{
+    Action, DeployContractAction, FunctionCallAction, RefundReason,
+};
 use testlib::fees_utils::FeeHelper;
 use testlib::runtime_utils::{
     alice_account, bob_account, eve_dot_alice_account, x_dot_y_dot_alice_account,
@@ -538,6 +540,19 @@ pub fn test_refund_on_send_money_to_non_existent_account(node: impl Node) {
         )
     );
     assert_eq!(transaction_result.receipts_outcome.len(), 3);
+    let transfer_refunds = &transaction_result.receipts_outcome[0].outcome.refunds;
+    let deposit_refunds = transfer_refunds
+        .iter()
+        .filter(|refund| refund.reason == RefundReason::FailedReceiptDeposit)
+        .collect::<Vec<_>>();
+    assert_eq!(deposit_refunds.len(), 1);
+    assert_eq!(&deposit_refunds[0].receiver_id, account_id);
+    assert_eq!(deposit_refunds[0].amount, money_used);
+    assert!(transaction_result
+        .receipts_outcome
+        .iter()
+        .flat_map(|outcome| &outcome.outcome.refunds)
+        .all(|refund| refund.reason != RefundReason::DeletedAccountBalance));
     let new_root = node_user.get_state_root();
     assert_ne!(root, new_root);
     let result1 = node_user.view_account(account_id).unwrap();
@@ -68,7 +68,8 @@ impl RpcUser {
 
 impl User for RpcUser {
     fn view_account(&self, account_id: &AccountId) -> Result<AccountView, String> {
-        let query = QueryRequest::ViewAccount { account_id: account_id.clone() };
+        let query =
+            QueryRequest::ViewAccount { account_id: account_id.clone(), include_proof: false };
         match self.query(query)?.kind {
             QueryResponseKind::ViewAccount(account_view) => Ok(account_view),
             _ => Err("Invalid type of response".into()),
@@ -80,6 +81,7 @@ impl User for RpcUser {
             account_id: account_id.clone(),
             prefix: prefix.to_vec().into(),
             include_proof: false,
+            options: Default::default(),
         };
         match self.query(query)?.kind {
             QueryResponseKind::ViewState(view_state_result) => Ok(view_state_result),
@@ -88,7 +90,10 @@ impl User for RpcUser {
     }
 
     fn is_locked(&self, account_id: &AccountId) -> Result<bool, String> {
-        let query = QueryRequest::ViewAccessKeyList { account_id: account_id.clone() };
+        let query = QueryRequest::ViewAccessKeyList {
+            account_id: account_id.clone(),
+            options: Default::default(),
+        };
         match self.query(query)?.kind {
             QueryResponseKind::AccessKeyList(access_keys) => Ok(access_keys.keys.is_empty()),
             _ => Err("Invalid type of response".into()),
@@ -195,6 +200,7 @@ impl User for RpcUser {
                 sender_account_id: self.account_id.clone(),
             },
             wait_until: TxExecutionStatus::Final,
+            include_proof: false,
         };
         self.actix(move |client| client.tx(request))
             .unwrap()
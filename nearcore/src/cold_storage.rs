@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::sync::{atomic::AtomicBool, Arc};
 
 use near_chain::types::Tip;
@@ -14,6 +15,33 @@ use near_store::{
 use crate::config::SplitStorageConfig;
 use crate::{metrics, NearConfig};
 
+/// Adds the cold store at `cold_store_path` to config.json at `path`, keeping
+/// the original next to it as `config.json.split-storage.bak`.  Also enables
+/// the settings split storage relies on.  The cold store config of the file,
+/// if any, is kept.  Comments aren't preserved.
+pub fn enable_split_storage_in_config_file(
+    path: &Path,
+    cold_store_path: &Path,
+) -> anyhow::Result<()> {
+    let json_str = std::fs::read_to_string(path)?;
+    let json_str = near_config_utils::strip_comments_from_json_str(&json_str)?;
+    let mut config: serde_json::Value = serde_json::from_str(&json_str)?;
+    let serde_json::Value::Object(fields) = &mut config else {
+        anyhow::bail!("config must be a JSON object");
+    };
+    fields.entry("cold_store").or_insert_with(|| serde_json::json!({ "path": cold_store_path }));
+    fields.insert("save_trie_changes".to_string(), true.into());
+    fields
+        .entry("split_storage")
+        .or_insert_with(|| serde_json::json!({ "enable_split_storage_view_client": true }));
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".split-storage.bak");
+    std::fs::copy(path, &backup)?;
+    std::fs::write(path, serde_json::to_string_pretty(&config)?)?;
+    Ok(())
+}
+
 /// A handle that keeps the state of the cold store loop and can be used to stop it.
 pub struct ColdStoreLoopHandle {
     join_handle: std::thread::JoinHandle<()>,
@@ -0,0 +1,424 @@
+//! Database compactions run by the node itself, either scheduled in
+//! `compaction_schedule` of the store configs or requested by the operator
+//! through the `/debug/api/compaction` admin endpoint.
+//!
+//! Compactions run one at a time on a dedicated thread.  Scheduled ones are
+//! queued at the start of every maintenance window and only start while the
+//! window lasts; the requested ones wait for the next window unless they are
+//! requested to run right away.  The thread also exports the compaction debt
+//! of every column as a metric, which tells how much work RocksDB will have
+//! to catch up on.
+
+use crate::metrics;
+use crate::NearConfig;
+use chrono::{NaiveDateTime, NaiveTime, Utc};
+use near_store::config::CompactionScheduleConfig;
+use near_store::{DBCol, NodeStorage, Store, Temperature};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
+
+/// How often the job refreshes the debt metrics and checks the windows.
+const COMPACTION_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Compaction of a single column.
+#[derive(Clone, Debug)]
+struct CompactionJob {
+    temperature: Temperature,
+    col: DBCol,
+    start: Option<Vec<u8>>,
+    end: Option<Vec<u8>>,
+    /// Skips the compaction unless the debt of the column is at least this
+    /// big, in bytes.
+    min_debt: Option<u64>,
+    manual: bool,
+    /// Whether the compaction may start outside of the maintenance windows.
+    now: bool,
+}
+
+/// Parsed `compaction_schedule` of a store config.
+#[derive(Default)]
+struct Schedule {
+    windows: Vec<(NaiveTime, NaiveTime)>,
+    compactions: Vec<CompactionJob>,
+}
+
+impl Schedule {
+    fn parse(config: &CompactionScheduleConfig, temperature: Temperature) -> anyhow::Result<Self> {
+        let parse_time = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|err| anyhow::anyhow!("invalid maintenance window time {time:?}: {err}"))
+        };
+        let windows = config
+            .maintenance_windows
+            .iter()
+            .map(|window| Ok((parse_time(&window.start)?, parse_time(&window.end)?)))
+            .collect::<anyhow::Result<_>>()?;
+        let mut compactions = vec![];
+        for compaction in &config.compactions {
+            let start = parse_key(compaction.start.as_deref())?;
+            let end = parse_key(compaction.end.as_deref())?;
+            for column in &compaction.columns {
+                compactions.push(CompactionJob {
+                    temperature,
+                    col: parse_column(column)?,
+                    start: start.clone(),
+                    end: end.clone(),
+                    min_debt: compaction.min_debt.map(|debt| debt.as_u64()),
+                    manual: false,
+                    now: false,
+                });
+            }
+        }
+        Ok(Self { windows, compactions })
+    }
+
+    /// Returns the start of the maintenance window `now` falls into.
+    fn current_window_start(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.windows.iter().find_map(|&(start, end)| window_start(start, end, now))
+    }
+}
+
+/// Returns the start of the window from `start` to `end` which `now` falls
+/// into, if any.  The window ends on the next day if `end` is before `start`.
+fn window_start(start: NaiveTime, end: NaiveTime, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let time = now.time();
+    let date = now.date();
+    if start <= end {
+        (start <= time && time < end).then(|| date.and_time(start))
+    } else if start <= time {
+        Some(date.and_time(start))
+    } else if time < end {
+        Some(date.pred_opt()?.and_time(start))
+    } else {
+        None
+    }
+}
+
+fn parse_column(name: &str) -> anyhow::Result<DBCol> {
+    DBCol::iter()
+        .find(|col| <&str>::from(col) == name)
+        .ok_or_else(|| anyhow::anyhow!("unknown column {name:?}"))
+}
+
+fn parse_key(key: Option<&str>) -> anyhow::Result<Option<Vec<u8>>> {
+    key.map(|key| hex::decode(key).map_err(|err| anyhow::anyhow!("invalid key {key:?}: {err}")))
+        .transpose()
+}
+
+/// A database the compactions run on.
+struct CompactedStore {
+    temperature: Temperature,
+    store: Store,
+    schedule: Schedule,
+    /// Start of the last maintenance window the scheduled compactions were
+    /// queued in.
+    last_window_start: Mutex<Option<NaiveDateTime>>,
+}
+
+impl CompactedStore {
+    fn new(temperature: Temperature, store: Store, schedule: Schedule) -> Self {
+        Self { temperature, store, schedule, last_window_start: Mutex::new(None) }
+    }
+}
+
+#[derive(Default)]
+struct CompactionQueue {
+    queued: VecDeque<CompactionJob>,
+    running: Option<CompactionJob>,
+}
+
+/// Runs the compactions, see the module documentation.  Shared between the
+/// compaction thread and the admin endpoint.
+pub struct CompactionScheduler {
+    hot: CompactedStore,
+    cold: Option<CompactedStore>,
+    queue: Mutex<CompactionQueue>,
+}
+
+impl CompactionScheduler {
+    fn new(config: &NearConfig, storage: &NodeStorage) -> anyhow::Result<Self> {
+        let hot_schedule =
+            Schedule::parse(&config.config.store.compaction_schedule, Temperature::Hot)?;
+        let hot = CompactedStore::new(Temperature::Hot, storage.get_hot_store(), hot_schedule);
+        let cold = match storage.get_cold_store() {
+            Some(store) => {
+                let schedule = match &config.config.cold_store {
+                    Some(cold_config) => {
+                        Schedule::parse(&cold_config.compaction_schedule, Temperature::Cold)?
+                    }
+                    None => Schedule::default(),
+                };
+                Some(CompactedStore::new(Temperature::Cold, store, schedule))
+            }
+            None => None,
+        };
+        Ok(Self { hot, cold, queue: Mutex::new(CompactionQueue::default()) })
+    }
+
+    fn stores(&self) -> impl Iterator<Item = &CompactedStore> {
+        std::iter::once(&self.hot).chain(self.cold.iter())
+    }
+
+    fn store(&self, temperature: Temperature) -> Option<&CompactedStore> {
+        match temperature {
+            Temperature::Hot => Some(&self.hot),
+            Temperature::Cold => self.cold.as_ref(),
+        }
+    }
+
+    fn in_window(&self, temperature: Temperature, now: NaiveDateTime) -> bool {
+        self.store(temperature)
+            .is_some_and(|store| store.schedule.current_window_start(now).is_some())
+    }
+
+    /// Queues the scheduled compactions of the stores which entered a new
+    /// maintenance window.
+    fn queue_scheduled(&self, now: NaiveDateTime) {
+        for store in self.stores() {
+            let window_start = store.schedule.current_window_start(now);
+            let mut last_window_start = store.last_window_start.lock().unwrap();
+            if window_start.is_none() || window_start == *last_window_start {
+                continue;
+            }
+            *last_window_start = window_start;
+            self.queue(store.schedule.compactions.clone());
+        }
+    }
+
+    fn queue(&self, jobs: Vec<CompactionJob>) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.queued.extend(jobs);
+        metrics::COMPACTION_QUEUE_LENGTH.set(queue.queued.len() as i64);
+    }
+
+    /// Takes the first queued compaction which may start now and marks it as
+    /// running.
+    fn start_next(&self, now: NaiveDateTime) -> Option<CompactionJob> {
+        let mut queue = self.queue.lock().unwrap();
+        let index =
+            queue.queued.iter().position(|job| job.now || self.in_window(job.temperature, now))?;
+        let job = queue.queued.remove(index)?;
+        queue.running = Some(job.clone());
+        metrics::COMPACTION_QUEUE_LENGTH.set(queue.queued.len() as i64);
+        Some(job)
+    }
+
+    fn finish(&self) {
+        self.queue.lock().unwrap().running = None;
+    }
+
+    fn run(&self, job: &CompactionJob) -> std::io::Result<()> {
+        let Some(store) = self.store(job.temperature) else { return Ok(()) };
+        if let Some(min_debt) = job.min_debt {
+            let debt = store.store.compaction_debt(job.col)?.unwrap_or(0);
+            if debt < min_debt {
+                tracing::debug!(target: "compaction", ?job, debt, "Skipping compaction of a column without enough debt");
+                return Ok(());
+            }
+        }
+        tracing::info!(target: "compaction", ?job, "Compacting");
+        let started = Instant::now();
+        store.store.compact_range(job.col, job.start.as_deref(), job.end.as_deref())?;
+        let temperature: &str = job.temperature.into();
+        let col: &str = job.col.into();
+        let trigger = if job.manual { "manual" } else { "scheduled" };
+        metrics::COMPACTIONS.with_label_values(&[temperature, col, trigger]).inc();
+        metrics::COMPACTION_DURATION
+            .with_label_values(&[temperature, col])
+            .observe(started.elapsed().as_secs_f64());
+        tracing::info!(target: "compaction", ?job, elapsed = ?started.elapsed(), "Compacted");
+        Ok(())
+    }
+
+    /// Returns the compaction debt of every column of the databases which
+    /// compact.
+    fn debt(&self) -> Vec<(Temperature, DBCol, u64)> {
+        let mut debt = vec![];
+        for store in self.stores() {
+            for col in DBCol::iter() {
+                match store.store.compaction_debt(col) {
+                    Ok(Some(bytes)) => debt.push((store.temperature, col, bytes)),
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::debug!(target: "compaction", %col, ?err, "Failed to get compaction debt");
+                    }
+                }
+            }
+        }
+        debt
+    }
+
+    fn update_debt_metrics(&self) {
+        for (temperature, col, bytes) in self.debt() {
+            let temperature: &str = temperature.into();
+            metrics::COMPACTION_DEBT
+                .with_label_values(&[temperature, col.into()])
+                .set(bytes as i64);
+        }
+    }
+}
+
+/// A handle that can be used to stop the compaction loop.
+pub struct CompactionLoopHandle {
+    join_handle: std::thread::JoinHandle<()>,
+    keep_going: Arc<AtomicBool>,
+}
+
+impl CompactionLoopHandle {
+    /// Stops the loop.  Waits for the running compaction, if any, to finish.
+    pub fn stop(self) {
+        self.keep_going.store(false, Ordering::Relaxed);
+        if self.join_handle.join().is_err() {
+            tracing::error!(target: "compaction", "Failed to join the compaction thread");
+        }
+    }
+}
+
+pub fn spawn_compaction_loop(
+    config: &NearConfig,
+    storage: &NodeStorage,
+) -> anyhow::Result<(Arc<CompactionScheduler>, CompactionLoopHandle)> {
+    let scheduler = Arc::new(CompactionScheduler::new(config, storage)?);
+    let keep_going = Arc::new(AtomicBool::new(true));
+    let keep_going_clone = keep_going.clone();
+    let scheduler_clone = scheduler.clone();
+    let join_handle = std::thread::Builder::new()
+        .name("compaction".to_string())
+        .spawn(move || compaction_loop(&keep_going_clone, &scheduler_clone))?;
+    Ok((scheduler, CompactionLoopHandle { join_handle, keep_going }))
+}
+
+fn compaction_loop(keep_going: &AtomicBool, scheduler: &CompactionScheduler) {
+    let mut last_poll: Option<Instant> = None;
+    while keep_going.load(Ordering::Relaxed) {
+        let now = Utc::now().naive_utc();
+        scheduler.queue_scheduled(now);
+        if let Some(job) = scheduler.start_next(now) {
+            if let Err(err) = scheduler.run(&job) {
+                tracing::warn!(target: "compaction", ?job, ?err, "Compaction failed");
+            }
+            scheduler.finish();
+            continue;
+        }
+        if last_poll.map_or(true, |last| last.elapsed() >= COMPACTION_POLL_INTERVAL) {
+            last_poll = Some(Instant::now());
+            scheduler.update_debt_metrics();
+        }
+        // Sleep in short steps, so that stopping the loop and manual
+        // compactions don't have to wait for the whole poll interval.
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+#[cfg(feature = "json_rpc")]
+mod rpc {
+    use super::{parse_column, parse_key, CompactionJob, CompactionScheduler};
+    use chrono::Utc;
+    use near_jsonrpc_primitives::errors::RpcError;
+    use near_jsonrpc_primitives::types::compaction::{
+        ColumnCompactionDebtView, CompactionHandler, CompactionJobView, CompactionRequest,
+        CompactionStatusView,
+    };
+    use near_store::Temperature;
+
+    impl From<&CompactionJob> for CompactionJobView {
+        fn from(job: &CompactionJob) -> Self {
+            Self {
+                column: job.col.to_string(),
+                start: job.start.as_ref().map(hex::encode),
+                end: job.end.as_ref().map(hex::encode),
+                cold: job.temperature == Temperature::Cold,
+                manual: job.manual,
+            }
+        }
+    }
+
+    impl CompactionHandler for CompactionScheduler {
+        fn status(&self) -> Result<CompactionStatusView, RpcError> {
+            let now = Utc::now().naive_utc();
+            let (running, queued) = {
+                let queue = self.queue.lock().unwrap();
+                (
+                    queue.running.as_ref().map(Into::into),
+                    queue.queued.iter().map(Into::into).collect(),
+                )
+            };
+            let debt = self
+                .debt()
+                .into_iter()
+                .filter(|(_, _, bytes)| *bytes > 0)
+                .map(|(temperature, col, debt_bytes)| ColumnCompactionDebtView {
+                    column: col.to_string(),
+                    cold: temperature == Temperature::Cold,
+                    debt_bytes,
+                })
+                .collect();
+            Ok(CompactionStatusView {
+                in_maintenance_window: self.in_window(Temperature::Hot, now),
+                running,
+                queued,
+                debt,
+            })
+        }
+
+        fn schedule(&self, request: CompactionRequest) -> Result<CompactionStatusView, RpcError> {
+            let invalid = |err: anyhow::Error| RpcError::invalid_params(err.to_string());
+            let temperature = if request.cold { Temperature::Cold } else { Temperature::Hot };
+            if self.store(temperature).is_none() {
+                return Err(RpcError::invalid_params("The node has no cold storage".to_string()));
+            }
+            let start = parse_key(request.start.as_deref()).map_err(invalid)?;
+            let end = parse_key(request.end.as_deref()).map_err(invalid)?;
+            let jobs = request
+                .columns
+                .iter()
+                .map(|column| {
+                    Ok(CompactionJob {
+                        temperature,
+                        col: parse_column(column).map_err(invalid)?,
+                        start: start.clone(),
+                        end: end.clone(),
+                        min_debt: None,
+                        manual: true,
+                        now: request.now,
+                    })
+                })
+                .collect::<Result<Vec<_>, RpcError>>()?;
+            self.queue(jobs);
+            self.status()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::window_start;
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    fn time(hour: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, min, 0).unwrap()
+    }
+
+    fn date_time(day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap().and_time(time(hour, min))
+    }
+
+    #[test]
+    fn test_window_start() {
+        let (start, end) = (time(2, 0), time(4, 0));
+        assert_eq!(window_start(start, end, date_time(10, 1, 59)), None);
+        assert_eq!(window_start(start, end, date_time(10, 2, 0)), Some(date_time(10, 2, 0)));
+        assert_eq!(window_start(start, end, date_time(10, 3, 59)), Some(date_time(10, 2, 0)));
+        assert_eq!(window_start(start, end, date_time(10, 4, 0)), None);
+
+        // The window wraps around midnight.
+        let (start, end) = (time(23, 0), time(1, 0));
+        assert_eq!(window_start(start, end, date_time(10, 22, 0)), None);
+        assert_eq!(window_start(start, end, date_time(10, 23, 30)), Some(date_time(10, 23, 0)));
+        assert_eq!(window_start(start, end, date_time(11, 0, 30)), Some(date_time(10, 23, 0)));
+        assert_eq!(window_start(start, end, date_time(11, 1, 0)), None);
+    }
+}
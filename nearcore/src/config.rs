@@ -1,3 +1,4 @@
+use crate::config_migration::CONFIG_VERSION;
 use crate::download_file::{run_download_file, FileDownloadError};
 use crate::dyn_config::LOG_CONFIG_FILENAME;
 use anyhow::{anyhow, bail, Context};
@@ -16,17 +17,17 @@ use near_chain_configs::{
     default_orphan_state_witness_pool_size, default_produce_chunk_add_transactions_time_limit,
     default_state_sync_enabled, default_state_sync_timeout, default_sync_check_period,
     default_sync_height_threshold, default_sync_max_block_requests, default_sync_step_period,
-    default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
-    default_tx_routing_height_horizon, default_view_client_threads,
-    default_view_client_throttle_period, get_initial_supply, ChunkDistributionNetworkConfig,
-    ClientConfig, EpochSyncConfig, GCConfig, Genesis, GenesisConfig, GenesisValidationMode,
-    LogSummaryStyle, MutableConfigValue, MutableValidatorSigner, ReshardingConfig, StateSyncConfig,
-    BLOCK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_PRODUCER_KICKOUT_THRESHOLD,
-    CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD, EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD,
-    GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME, INITIAL_GAS_LIMIT, MAX_INFLATION_RATE,
-    MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE, NUM_BLOCKS_PER_YEAR,
-    NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE, PROTOCOL_UPGRADE_STAKE_THRESHOLD,
-    TRANSACTION_VALIDITY_PERIOD,
+    default_transaction_pool_receiver_shard_size_limit, default_transaction_pool_size_limit,
+    default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
+    default_view_client_threads, default_view_client_throttle_period, get_initial_supply,
+    ChunkDistributionNetworkConfig, ClientConfig, EpochSyncConfig, GCConfig, Genesis,
+    GenesisConfig, GenesisValidationMode, LogSummaryStyle, MutableConfigValue,
+    MutableValidatorSigner, ReshardingConfig, StateSyncConfig, BLOCK_PRODUCER_KICKOUT_THRESHOLD,
+    CHUNK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD,
+    EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD, GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME,
+    INITIAL_GAS_LIMIT, MAX_INFLATION_RATE, MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE,
+    NUM_BLOCKS_PER_YEAR, NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE,
+    PROTOCOL_UPGRADE_STAKE_THRESHOLD, TRANSACTION_VALIDITY_PERIOD,
 };
 use near_config_utils::{DownloadConfigType, ValidationError, ValidationErrors};
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey};
@@ -36,6 +37,7 @@ use near_jsonrpc::RpcConfig;
 use near_network::config::NetworkConfig;
 use near_network::tcp;
 use near_o11y::log_config::LogConfig;
+use near_parameters::RuntimeConfigStore;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::test_utils::create_test_signer;
@@ -49,6 +51,7 @@ use near_primitives::version::PROTOCOL_VERSION;
 #[cfg(feature = "rosetta_rpc")]
 use near_rosetta_rpc::RosettaRpcConfig;
 use near_store::config::StateSnapshotType;
+use near_store::memory_budget::{BudgetedCache, MemoryBudget};
 use near_store::{StateSnapshotConfig, Store, TrieConfig};
 use near_telemetry::TelemetryConfig;
 use near_vm_runner::{ContractRuntimeCache, FilesystemContractRuntimeCache};
@@ -212,6 +215,14 @@ impl Default for Consensus {
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 #[serde(default)]
 pub struct Config {
+    /// Version of the schema of the config, see
+    /// [`crate::config_migration`].  Configs without it are at version 0.
+    #[serde(default)]
+    pub config_version: u32,
+    /// If true, the node refuses to start with unrecognised fields in the
+    /// config instead of ignoring them with a warning.
+    #[serde(skip_serializing_if = "is_false")]
+    pub reject_unrecognised_fields: bool,
     pub genesis_file: String,
     pub genesis_records_file: Option<String>,
     pub validator_key_file: String,
@@ -283,6 +294,13 @@ pub struct Config {
     /// Setting this value too low (<1MB) on the validator might lead to production of smaller
     /// chunks and underutilizing the capacity of the network.
     pub transaction_pool_size_limit: Option<u64>,
+    /// Limit of the size of transactions targeting a single receiver shard in the per-shard
+    /// transaction pool measured in bytes. If not set, the size will be unbounded.
+    ///
+    /// Transactions targeting a congested shard stay in the pool until the congestion goes away.
+    /// This limit keeps them from taking the space of transactions targeting other shards, so it
+    /// should be lower than `transaction_pool_size_limit`.
+    pub transaction_pool_receiver_shard_size_limit: Option<u64>,
     // Configuration for resharding.
     pub resharding_config: ReshardingConfig,
     /// If the node is not a chunk producer within that many blocks, then route
@@ -317,12 +335,46 @@ pub struct Config {
     ///
     /// Each loaded contract will increase the baseline memory use of the node appreciably.
     pub max_loaded_contracts: usize,
+    /// Size limit of the compiled contracts stored on disk. The ones read least recently and
+    /// least often are removed to stay within it. `null` lets the cache grow without bounds.
+    pub compiled_contracts_disk_limit: Option<ByteSize>,
     /// Save observed instances of ChunkStateWitness to the database in DBCol::LatestChunkStateWitnesses.
     /// Saving the latest witnesses is useful for analysis and debugging.
     /// When this option is enabled, the node will save ALL witnesses it observes, even invalid ones,
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// If true, chunk producers run the checks performed by chunk validators against
+    /// every state witness they produce before distributing it. A witness which fails
+    /// the checks is not distributed, which makes producer-side bugs visible locally
+    /// instead of as missed chunks across the network.
+    pub validate_produced_state_witnesses: bool,
+    /// If true, the node saves statistics of every tracked shard at the end of each
+    /// epoch to DBCol::EpochShardStats, e.g. state size, number of accounts and gas
+    /// burnt. The statistics are never garbage collected and can be queried with the
+    /// `EXPERIMENTAL_epoch_shard_stats` RPC method.
+    pub save_epoch_shard_stats: bool,
+    /// Accounts to watch regardless of the tracked shards. As a chunk validator,
+    /// the node records their balances, transactions and incoming receipts seen
+    /// in the state witnesses it validates. The data is kept in memory and can
+    /// be queried with the `EXPERIMENTAL_watched_accounts` RPC method.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub watched_accounts: Vec<AccountId>,
+    /// Opt-in independent check of stateless validation for RPC and archival
+    /// nodes. When the node isn't a validator, it collects the state witnesses
+    /// which reach it, applies their chunks for the shards it doesn't track
+    /// and exports the outcome in the `near_shadow_chunk_application_total`
+    /// metric. Mismatches mean that a chunk producer and this node disagree
+    /// on the result of applying the chunk. Witnesses are distributed to
+    /// chunk validators only, so the node checks only the chunks whose
+    /// witnesses reach it.
+    pub shadow_chunk_application: bool,
+    /// YAML file, relative to the home directory, with runtime parameter values
+    /// replacing the built-in ones of every protocol version, in the format of
+    /// `parameters.yaml`. Lets a localnet try out proposed parameter changes.
+    /// The node refuses to start with it on mainnet and testnet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_parameter_overrides_file: Option<PathBuf>,
 }
 
 fn is_false(value: &bool) -> bool {
@@ -331,6 +383,8 @@ fn is_false(value: &bool) -> bool {
 impl Default for Config {
     fn default() -> Self {
         Config {
+            config_version: CONFIG_VERSION,
+            reject_unrecognised_fields: false,
             genesis_file: GENESIS_CONFIG_FILENAME.to_string(),
             genesis_records_file: None,
             validator_key_file: VALIDATOR_KEY_FILE.to_string(),
@@ -363,6 +417,8 @@ impl Default for Config {
             epoch_sync: default_epoch_sync(),
             state_sync_enabled: default_state_sync_enabled(),
             transaction_pool_size_limit: default_transaction_pool_size_limit(),
+            transaction_pool_receiver_shard_size_limit:
+                default_transaction_pool_receiver_shard_size_limit(),
             enable_multiline_logging: default_enable_multiline_logging(),
             resharding_config: ReshardingConfig::default(),
             tx_routing_height_horizon: default_tx_routing_height_horizon(),
@@ -372,7 +428,13 @@ impl Default for Config {
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
             max_loaded_contracts: 256,
+            compiled_contracts_disk_limit: Some(ByteSize::gib(20)),
             save_latest_witnesses: false,
+            validate_produced_state_witnesses: false,
+            save_epoch_shard_stats: false,
+            watched_accounts: vec![],
+            shadow_chunk_application: false,
+            runtime_parameter_overrides_file: None,
         }
     }
 }
@@ -462,9 +524,35 @@ impl Config {
             error_message: format!("Failed to deserialize config from {}: {:?}", path.display(), e),
         })?;
 
+        if config.config_version > CONFIG_VERSION {
+            return Err(ValidationError::ConfigFileError {
+                error_message: format!(
+                    "{} is at version {} but this binary supports versions up to {CONFIG_VERSION}",
+                    path.display(),
+                    config.config_version,
+                ),
+            });
+        }
+        if config.config_version < CONFIG_VERSION {
+            warn!(
+                target: "neard",
+                "{} is at version {} of the config schema, run `neard config migrate` to upgrade it to version {CONFIG_VERSION}",
+                path.display(),
+                config.config_version,
+            );
+        }
+
         if !unrecognised_fields.is_empty() {
             let s = if unrecognised_fields.len() > 1 { "s" } else { "" };
             let fields = unrecognised_fields.join(", ");
+            if config.reject_unrecognised_fields {
+                return Err(ValidationError::ConfigFileError {
+                    error_message: format!(
+                        "{}: encountered unrecognised field{s}: {fields}",
+                        path.display(),
+                    ),
+                });
+            }
             warn!(
                 target: "neard",
                 "{}: encountered unrecognised field{s}: {fields}",
@@ -583,6 +671,8 @@ impl NearConfig {
                 state_sync: config.state_sync.unwrap_or_default(),
                 epoch_sync: config.epoch_sync.unwrap_or_default(),
                 transaction_pool_size_limit: config.transaction_pool_size_limit,
+                transaction_pool_receiver_shard_size_limit: config
+                    .transaction_pool_receiver_shard_size_limit,
                 enable_multiline_logging: config.enable_multiline_logging.unwrap_or(true),
                 resharding_config: MutableConfigValue::new(
                     config.resharding_config,
@@ -597,6 +687,10 @@ impl NearConfig {
                 orphan_state_witness_pool_size: config.orphan_state_witness_pool_size,
                 orphan_state_witness_max_size: config.orphan_state_witness_max_size,
                 save_latest_witnesses: config.save_latest_witnesses,
+                validate_produced_state_witnesses: config.validate_produced_state_witnesses,
+                save_epoch_shard_stats: config.save_epoch_shard_stats,
+                watched_accounts: config.watched_accounts,
+                shadow_chunk_application: config.shadow_chunk_application,
             },
             network_config: NetworkConfig::new(
                 config.network,
@@ -656,6 +750,18 @@ impl NightshadeRuntime {
         store: Store,
         config: &NearConfig,
         epoch_manager: Arc<EpochManagerHandle>,
+    ) -> std::io::Result<Arc<NightshadeRuntime>> {
+        Self::from_config_with_memory_budget(home_dir, store, config, epoch_manager, None)
+    }
+
+    /// Like `from_config`, with the trie caches and the loaded contracts
+    /// cache of the runtime sized by `memory_budget`.
+    pub fn from_config_with_memory_budget(
+        home_dir: &Path,
+        store: Store,
+        config: &NearConfig,
+        epoch_manager: Arc<EpochManagerHandle>,
+        memory_budget: Option<Arc<MemoryBudget>>,
     ) -> std::io::Result<Arc<NightshadeRuntime>> {
         // TODO (#9989): directly use the new state snapshot config once the migration is done.
         let mut state_snapshot_type =
@@ -673,15 +779,46 @@ impl NightshadeRuntime {
                 .clone()
                 .unwrap_or_else(|| PathBuf::from("data")),
             state_snapshot_subdir: PathBuf::from("state_snapshot"),
+            num_checkpoints: config.config.store.state_snapshot_config.num_checkpoints,
         };
+        let mut trie_config = TrieConfig::from_store_config(&config.config.store);
+        if config.config.store.persist_mem_tries {
+            trie_config.mem_tries_persistence_dir =
+                Some(home_dir.join(&state_snapshot_config.hot_store_path).join("memtries"));
+        }
+        if config.config.store.flat_state_mmap {
+            trie_config.flat_state_mmap_dir =
+                Some(home_dir.join(&state_snapshot_config.hot_store_path).join("flat_state_mmap"));
+        }
+        trie_config.memory_budget.clone_from(&memory_budget);
         // FIXME: this (and other contract runtime resources) should probably get constructed by
         // the caller and passed into this `NightshadeRuntime::from_config` here. But that's a big
         // refactor...
-        let contract_cache = FilesystemContractRuntimeCache::with_memory_cache(
+        let contract_cache = FilesystemContractRuntimeCache::with_limits(
             home_dir,
             config.config.store.path.as_ref(),
             config.config.max_loaded_contracts,
+            config.config.compiled_contracts_disk_limit.map(|limit| limit.as_u64()),
         )?;
+        if let Some(memory_budget) = &memory_budget {
+            let memory_cache: Arc<dyn BudgetedCache> = contract_cache.shared_memory_cache();
+            memory_budget.register(Arc::downgrade(&memory_cache));
+        }
+        let runtime_config_store = match &config.config.runtime_parameter_overrides_file {
+            Some(path) => {
+                let path = home_dir.join(path);
+                let overrides = fs::read_to_string(&path).map_err(|err| {
+                    std::io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+                })?;
+                let store = RuntimeConfigStore::for_chain_id_with_overrides(
+                    &config.genesis.config.chain_id,
+                    &overrides,
+                )
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+                Some(store)
+            }
+            None => None,
+        };
         Ok(NightshadeRuntime::new(
             store,
             ContractRuntimeCache::handle(&contract_cache),
@@ -689,9 +826,9 @@ impl NightshadeRuntime {
             epoch_manager,
             config.client_config.trie_viewer_state_size_limit,
             config.client_config.max_gas_burnt_view,
-            None,
+            runtime_config_store,
             config.config.gc.gc_num_epochs_to_keep(),
-            TrieConfig::from_store_config(&config.config.store),
+            trie_config,
             state_snapshot_config,
         ))
     }
@@ -1476,12 +1613,18 @@ pub fn load_config(
     if genesis.is_none() || network_signer.is_none() {
         panic!("Genesis and network_signer should not be None by now.")
     }
-    let near_config = NearConfig::new(
+    let mut near_config = NearConfig::new(
         config,
         genesis.unwrap(),
         network_signer.unwrap().into(),
         MutableConfigValue::new(validator_signer, "validator_signer"),
     )?;
+    if let Some(path) = &mut near_config.network_config.access_list_file {
+        *path = dir.join(&path);
+    }
+    if let Some(record_traffic) = &mut near_config.network_config.record_traffic {
+        record_traffic.file = dir.join(&record_traffic.file);
+    }
     Ok(near_config)
 }
 
@@ -1673,6 +1816,10 @@ mod tests {
                     gc_fork_clean_step: 420,
                     gc_num_epochs_to_keep: 24,
                     gc_step_period: Duration::seconds(1),
+                    prune_headers: false,
+                    deep_pruning: false,
+                    clear_abandoned_forks: true,
+                    throttle: Default::default(),
                 }
             } else {
                 GCConfig {
@@ -1680,6 +1827,10 @@ mod tests {
                     gc_fork_clean_step: 100,
                     gc_num_epochs_to_keep: 5,
                     gc_step_period: Duration::seconds(1),
+                    prune_headers: false,
+                    deep_pruning: false,
+                    clear_abandoned_forks: true,
+                    throttle: Default::default(),
                 }
             };
             assert_eq!(want_gc, config.gc);
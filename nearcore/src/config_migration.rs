@@ -0,0 +1,156 @@
+//! Migrations of config.json between versions of its schema.
+//!
+//! The version of a config is stored in its `config_version` field, configs
+//! without it are at version 0.  Migrations work on the JSON of the config
+//! rather than on [`Config`], so that the fields which the operator didn't
+//! set stay unset and keep following the defaults of future releases.
+
+use crate::config::Config;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Current version of the config schema.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Migration from version `i` to `i + 1` is at index `i`.  Each migration
+/// returns the descriptions of the changes it made.
+const MIGRATIONS: [fn(&mut Map<String, Value>) -> Vec<String>; CONFIG_VERSION as usize] =
+    [migrate_to_v1];
+
+/// Outcome of migrating a config.
+#[derive(Debug)]
+pub struct ConfigMigration {
+    pub from_version: u32,
+    pub changes: Vec<String>,
+    /// Fields which the current schema doesn't know, e.g. misspelled ones.
+    /// They can't be migrated automatically.
+    pub unrecognised_fields: Vec<String>,
+}
+
+/// Returns the schema version of the config.
+pub fn config_version(config: &Value) -> anyhow::Result<u32> {
+    match config.get("config_version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow::anyhow!("invalid config_version: {version}")),
+    }
+}
+
+/// Migrates the config to the current schema version.
+pub fn migrate_config(config: &mut Value) -> anyhow::Result<ConfigMigration> {
+    let from_version = config_version(config)?;
+    anyhow::ensure!(
+        from_version <= CONFIG_VERSION,
+        "config version {from_version} is newer than {CONFIG_VERSION} supported by this binary"
+    );
+    let Value::Object(fields) = config else {
+        anyhow::bail!("config must be a JSON object");
+    };
+    let mut changes = vec![];
+    for migration in &MIGRATIONS[from_version as usize..] {
+        changes.extend(migration(fields));
+    }
+    if from_version < CONFIG_VERSION {
+        fields.insert("config_version".to_string(), CONFIG_VERSION.into());
+    }
+
+    let mut unrecognised_fields = vec![];
+    let _: Config =
+        serde_ignored::deserialize(&*config, |field| unrecognised_fields.push(field.to_string()))?;
+    Ok(ConfigMigration { from_version, changes, unrecognised_fields })
+}
+
+/// Migrates config.json at `path` in place, keeping the original next to it
+/// as `config.json.v{version}.bak`.  Comments aren't preserved.
+pub fn migrate_config_file(path: &Path, dry_run: bool) -> anyhow::Result<ConfigMigration> {
+    let json_str = std::fs::read_to_string(path)?;
+    let json_str = near_config_utils::strip_comments_from_json_str(&json_str)?;
+    let mut config: Value = serde_json::from_str(&json_str)?;
+    let migration = migrate_config(&mut config)?;
+    if !dry_run && migration.from_version < CONFIG_VERSION {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".v{}.bak", migration.from_version));
+        std::fs::copy(path, &backup)?;
+        std::fs::write(path, serde_json::to_string_pretty(&config)?)?;
+    }
+    Ok(migration)
+}
+
+/// Replaces the deprecated `state_snapshot_enabled` of the store configs
+/// with `state_snapshot_config`.
+fn migrate_to_v1(config: &mut Map<String, Value>) -> Vec<String> {
+    let mut changes = vec![];
+    for section in ["store", "cold_store"] {
+        let Some(Value::Object(store)) = config.get_mut(section) else { continue };
+        let Some(enabled) = store.remove("state_snapshot_enabled") else { continue };
+        if enabled == Value::Bool(true) {
+            set_field(
+                store,
+                &["state_snapshot_config", "state_snapshot_type"],
+                "EveryEpoch".into(),
+            );
+            changes.push(format!(
+                "{section}.state_snapshot_enabled moved to {section}.state_snapshot_config.state_snapshot_type"
+            ));
+        } else {
+            changes.push(format!("{section}.state_snapshot_enabled removed"));
+        }
+    }
+    changes
+}
+
+/// Sets the field at the given path, creating the missing sections.
+fn set_field(config: &mut Map<String, Value>, path: &[&str], value: Value) {
+    let (field, sections) = path.split_last().expect("path must not be empty");
+    let mut current = config;
+    for section in sections {
+        let entry = current.entry(*section).or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        current = entry.as_object_mut().unwrap();
+    }
+    current.insert(field.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{migrate_config, CONFIG_VERSION};
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_config() {
+        let mut config = json!({
+            "archive": true,
+            "store": {"path": "hot", "state_snapshot_enabled": true},
+            "cold_store": {"path": "cold", "state_snapshot_enabled": false},
+            "tracked_shardz": [0],
+        });
+        let migration = migrate_config(&mut config).unwrap();
+        assert_eq!(migration.from_version, 0);
+        assert_eq!(migration.changes.len(), 2);
+        assert_eq!(migration.unrecognised_fields, vec!["tracked_shardz".to_string()]);
+        assert_eq!(
+            config,
+            json!({
+                "config_version": CONFIG_VERSION,
+                "archive": true,
+                "store": {"path": "hot", "state_snapshot_config": {"state_snapshot_type": "EveryEpoch"}},
+                "cold_store": {"path": "cold"},
+                "tracked_shardz": [0],
+            })
+        );
+
+        // Migrating again is a no-op.
+        let migrated = config.clone();
+        let migration = migrate_config(&mut config).unwrap();
+        assert_eq!(migration.from_version, CONFIG_VERSION);
+        assert!(migration.changes.is_empty());
+        assert_eq!(config, migrated);
+
+        let mut config = json!({"config_version": CONFIG_VERSION + 1});
+        assert!(migrate_config(&mut config).is_err());
+    }
+}
@@ -1,7 +1,9 @@
 use near_chain_configs::{ExternalStorageLocation, SyncConfig};
 use near_config_utils::{ValidationError, ValidationErrors};
+use near_store::DBCol;
 use std::collections::HashSet;
 use std::path::Path;
+use strum::IntoEnumIterator;
 
 use crate::config::Config;
 
@@ -79,6 +81,12 @@ impl<'a> ConfigValidator<'a> {
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        let gc_throttle = &self.config.gc.throttle;
+        if gc_throttle.compaction_debt_soft_limit >= gc_throttle.compaction_debt_hard_limit {
+            let error_message = format!("gc.throttle.compaction_debt_soft_limit should be below gc.throttle.compaction_debt_hard_limit, but they are {} and {}.", gc_throttle.compaction_debt_soft_limit, gc_throttle.compaction_debt_hard_limit);
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
         if let Some(state_sync) = &self.config.state_sync {
             if let Some(dump_config) = &state_sync.dump {
                 if let Some(restart_dump_for_shards) = &dump_config.restart_dump_for_shards {
@@ -157,6 +165,67 @@ impl<'a> ConfigValidator<'a> {
             let error_message = format!("'config.tx_routing_height_horizon' can't be too high to avoid spamming the network. Keep it below 100. Got {tx_routing_height_horizon}.");
             self.validation_errors.push_config_semantics_error(error_message);
         }
+
+        if self.config.gc.deep_pruning {
+            if self.config.archive {
+                let error_message =
+                    "'config.gc.deep_pruning' can't be enabled on archival nodes.".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            // Dumps of the state of an epoch are made while the next one goes
+            // on, from the blocks deep pruning may have deleted already.
+            if self.config.state_sync.as_ref().is_some_and(|state_sync| state_sync.dump.is_some()) {
+                let error_message = "'config.gc.deep_pruning' can't be enabled together with 'config.state_sync.dump'.".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if self.config.rpc.is_some() {
+                tracing::warn!(target: "config", "'config.gc.deep_pruning' is enabled, RPC queries about the blocks, chunks and transactions of the pruned epochs will fail");
+            }
+        }
+
+        let memory_budget = &self.config.store.memory_budget;
+        if memory_budget.enabled {
+            let percent = memory_budget.max_available_memory_percent;
+            if percent == 0 || percent > 100 {
+                let error_message = format!("'config.store.memory_budget.max_available_memory_percent' needs to be between 1 and 100, got {percent}.");
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if memory_budget.min_cache_bytes > memory_budget.max_bytes {
+                let error_message = "'config.store.memory_budget.min_cache_bytes' can't be greater than 'config.store.memory_budget.max_bytes'.".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
+        if self.config.compiled_contracts_disk_limit.is_some_and(|limit| limit.as_u64() == 0) {
+            let error_message = "'config.compiled_contracts_disk_limit' needs to be positive, use null to lift the limit.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
+        for rule in &self.config.store.prefetch_rules {
+            let pointers = rule
+                .keys
+                .iter()
+                .flat_map(|pattern| pattern.list.iter().chain(std::iter::once(&pattern.value)));
+            for pointer in pointers {
+                if !pointer.is_empty() && !pointer.starts_with('/') {
+                    let error_message = format!("'config.store.prefetch_rules' of {:?} contains {pointer:?}, which isn't a JSON pointer.", rule.receiver);
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+        }
+
+        let stores = std::iter::once(("store", &self.config.store))
+            .chain(self.config.cold_store.as_ref().map(|store| ("cold_store", store)));
+        for (name, store) in stores {
+            for column in store.rocksdb_overrides.keys() {
+                if !DBCol::iter().any(|col| <&str>::from(col) == column) {
+                    let error_message = format!(
+                        "'config.{name}.rocksdb_overrides' contains unknown column {column:?}."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+        }
     }
 
     fn result_with_full_error(&self) -> Result<(), ValidationError> {
@@ -231,6 +300,27 @@ mod tests {
         validate_config(&config).unwrap();
     }
 
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.store.rocksdb_overrides' contains unknown column \\\"Sate\\\"."
+    )]
+    fn test_rocksdb_overrides_unknown_column() {
+        let mut config = Config::default();
+        config.store.rocksdb_overrides.insert("Sate".to_string(), Default::default());
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.gc.deep_pruning' can't be enabled on archival nodes."
+    )]
+    fn test_deep_pruning_archival() {
+        let mut config = Config::default();
+        config.archive = true;
+        config.gc.deep_pruning = true;
+        validate_config(&config).unwrap();
+    }
+
     #[test]
     #[should_panic(
         expected = "\\nconfig.json semantic issue: 'config.tx_routing_height_horizon' needs to be at least 2, got 1."
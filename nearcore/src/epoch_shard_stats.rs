@@ -0,0 +1,209 @@
+//! Opt-in job which saves statistics of the shards at the end of every epoch,
+//! see `EpochShardStats`. The statistics are kept forever, so that operators
+//! get longitudinal data on the growth of the shards.
+//!
+//! The job runs on its own thread and only looks at the last epoch finalized
+//! by the final head. Epochs which finished while the node was offline or
+//! syncing are skipped. Statistics are only saved for the shards which the
+//! node has the state of.
+
+use near_chain::types::RuntimeAdapter;
+use near_chain::{ChainStore, ChainStoreAccess, Error};
+use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::trie_key::col;
+use near_primitives::types::{EpochId, EpochShardStats, StateRoot};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::NearConfig;
+
+/// How often the job checks whether a new epoch was finalized.
+const EPOCH_SHARD_STATS_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A handle that can be used to stop the epoch shard stats loop.
+pub struct EpochShardStatsLoopHandle {
+    join_handle: std::thread::JoinHandle<()>,
+    keep_going: Arc<AtomicBool>,
+}
+
+impl EpochShardStatsLoopHandle {
+    pub fn stop(self) {
+        self.keep_going.store(false, Ordering::Relaxed);
+        if self.join_handle.join().is_err() {
+            tracing::error!(target: "epoch_shard_stats", "Failed to join the epoch shard stats thread");
+        }
+    }
+}
+
+pub fn spawn_epoch_shard_stats_loop(
+    config: &NearConfig,
+    chain_store: ChainStore,
+    epoch_manager: Arc<dyn EpochManagerAdapter>,
+    runtime: Arc<dyn RuntimeAdapter>,
+) -> anyhow::Result<Option<EpochShardStatsLoopHandle>> {
+    if !config.client_config.save_epoch_shard_stats {
+        return Ok(None);
+    }
+    let keep_going = Arc::new(AtomicBool::new(true));
+    let keep_going_clone = keep_going.clone();
+    tracing::info!(target: "epoch_shard_stats", "Spawning the epoch shard stats loop");
+    let join_handle =
+        std::thread::Builder::new().name("epoch_shard_stats".to_string()).spawn(move || {
+            epoch_shard_stats_loop(
+                &keep_going_clone,
+                &chain_store,
+                epoch_manager.as_ref(),
+                runtime.as_ref(),
+            )
+        })?;
+    Ok(Some(EpochShardStatsLoopHandle { join_handle, keep_going }))
+}
+
+fn epoch_shard_stats_loop(
+    keep_going: &AtomicBool,
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime: &dyn RuntimeAdapter,
+) {
+    let mut last_attempt: Option<Instant> = None;
+    while keep_going.load(Ordering::Relaxed) {
+        // Sleep in short steps, so that stopping the loop doesn't have to wait
+        // for the whole poll interval.
+        if last_attempt.is_some_and(|last| last.elapsed() < EPOCH_SHARD_STATS_POLL_INTERVAL) {
+            std::thread::sleep(Duration::from_secs(1));
+            continue;
+        }
+        last_attempt = Some(Instant::now());
+        if !near_store::disk_pressure::non_essential_writes_allowed() {
+            tracing::debug!(target: "epoch_shard_stats", "Skipping epoch shard stats due to disk pressure");
+            continue;
+        }
+        match save_last_finished_epoch_shard_stats(chain_store, epoch_manager, runtime) {
+            Ok(saved) => {
+                for stats in saved {
+                    tracing::info!(target: "epoch_shard_stats", ?stats, "Saved epoch shard stats");
+                }
+            }
+            Err(err) => {
+                tracing::warn!(target: "epoch_shard_stats", ?err, "Failed to save epoch shard stats");
+            }
+        }
+    }
+}
+
+/// Computes and saves the statistics of the shards over the last epoch
+/// finalized by the final head, unless they were saved before. Returns the
+/// newly saved statistics.
+pub fn save_last_finished_epoch_shard_stats(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime: &dyn RuntimeAdapter,
+) -> Result<Vec<EpochShardStats>, Error> {
+    let final_head = chain_store.final_head()?;
+    let epoch_first_block =
+        *epoch_manager.get_block_info(&final_head.last_block_hash)?.epoch_first_block();
+    let last_block_hash = *chain_store.get_block_header(&epoch_first_block)?.prev_hash();
+    let last_block_header = chain_store.get_block_header(&last_block_hash)?;
+    // The genesis block is not a part of any finished epoch.
+    if last_block_header.height() == chain_store.get_genesis_height() {
+        return Ok(vec![]);
+    }
+    let epoch_id = *last_block_header.epoch_id();
+
+    let mut saved = vec![];
+    for shard_uid in epoch_manager.get_shard_layout(&epoch_id)?.shard_uids() {
+        if chain_store.get_epoch_shard_stats(&epoch_id, &shard_uid)?.is_some() {
+            continue;
+        }
+        let state_root = match chain_store.get_chunk_extra(&last_block_hash, &shard_uid) {
+            Ok(chunk_extra) => *chunk_extra.state_root(),
+            // The shard wasn't tracked.
+            Err(Error::DBNotFoundErr(_)) => continue,
+            Err(err) => return Err(err),
+        };
+        let stats = compute_epoch_shard_stats(
+            chain_store,
+            epoch_manager,
+            runtime,
+            epoch_id,
+            last_block_hash,
+            shard_uid,
+            state_root,
+        )?;
+        chain_store.save_epoch_shard_stats(&stats)?;
+        saved.push(stats);
+    }
+    Ok(saved)
+}
+
+fn compute_epoch_shard_stats(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime: &dyn RuntimeAdapter,
+    epoch_id: EpochId,
+    last_block_hash: CryptoHash,
+    shard_uid: ShardUId,
+    state_root: StateRoot,
+) -> Result<EpochShardStats, Error> {
+    let shard_id = shard_uid.shard_id();
+    let trie = runtime.get_tries().get_view_trie_for_shard(shard_uid, state_root);
+    let state_size = trie.retrieve_root_node()?.memory_usage;
+    let mut iter = trie.disk_iter()?;
+    iter.seek_prefix([col::ACCOUNT])?;
+    let mut num_accounts = 0;
+    for item in iter {
+        item?;
+        num_accounts += 1;
+    }
+
+    let mut stats = EpochShardStats {
+        epoch_id,
+        epoch_height: epoch_manager.get_epoch_info(&epoch_id)?.epoch_height(),
+        shard_uid,
+        last_block_hash,
+        state_size,
+        num_accounts,
+        num_accounts_delta: None,
+        num_chunks: 0,
+        num_transactions: 0,
+        num_receipts: 0,
+        gas_burnt: 0,
+    };
+
+    // Walk the blocks of the epoch backwards, ending on the last block of the
+    // previous epoch.
+    let genesis_height = chain_store.get_genesis_height();
+    let mut header = chain_store.get_block_header(&last_block_hash)?;
+    while header.epoch_id() == &epoch_id && header.height() > genesis_height {
+        let block = chain_store.get_block(header.hash())?;
+        let chunk_header = block
+            .chunks()
+            .iter()
+            .find(|chunk| chunk.shard_id() == shard_id)
+            .cloned()
+            .ok_or(Error::InvalidShardId(shard_id))?;
+        if chunk_header.is_new_chunk(header.height()) {
+            let num_outcomes =
+                chain_store.get_outcomes_by_block_hash_and_shard_id(header.hash(), shard_id)?.len();
+            let num_transactions =
+                chain_store.get_chunk(&chunk_header.chunk_hash())?.transactions().len();
+            stats.num_chunks += 1;
+            stats.num_transactions += num_transactions as u64;
+            // Every applied transaction and receipt has an outcome. Invalid
+            // transactions don't, hence the saturation.
+            stats.num_receipts += num_outcomes.saturating_sub(num_transactions) as u64;
+            stats.gas_burnt += chain_store.get_chunk_extra(header.hash(), &shard_uid)?.gas_used();
+        }
+        header = chain_store.get_block_header(header.prev_hash())?;
+    }
+
+    if header.epoch_id() != &epoch_id {
+        let prev_stats = chain_store.get_epoch_shard_stats(header.epoch_id(), &shard_uid)?;
+        stats.num_accounts_delta =
+            prev_stats.map(|prev_stats| num_accounts as i64 - prev_stats.num_accounts as i64);
+    }
+    Ok(stats)
+}
@@ -0,0 +1,58 @@
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::types::host_call_trace::{
+    FunctionCallTraceView, HostCallTraceHandler, HostCallTraceRequest, HostCallTraceStatusView,
+    HostCallView,
+};
+use near_primitives::hash::CryptoHash;
+use node_runtime::host_call_trace::HostCallTracer;
+use std::sync::Arc;
+
+/// Serves the host function calls traced by the runtime applying the chunks.
+pub(crate) struct HostCallTraceHandlerImpl {
+    pub tracer: Arc<HostCallTracer>,
+}
+
+impl HostCallTraceHandler for HostCallTraceHandlerImpl {
+    fn status(&self) -> Result<HostCallTraceStatusView, RpcError> {
+        let mut receivers: Vec<_> = self.tracer.receivers().into_iter().collect();
+        receivers.sort();
+        Ok(HostCallTraceStatusView { receivers, num_traces: self.tracer.num_traces() })
+    }
+
+    fn set_receivers(
+        &self,
+        request: HostCallTraceRequest,
+    ) -> Result<HostCallTraceStatusView, RpcError> {
+        tracing::info!(target: "runtime", receivers = ?request.receivers, "Tracing host function calls");
+        self.tracer.set_receivers(request.receivers.into_iter().collect());
+        self.status()
+    }
+
+    fn trace(&self, receipt_id: CryptoHash) -> Result<Vec<FunctionCallTraceView>, RpcError> {
+        let Some(traces) = self.tracer.get(&receipt_id) else {
+            return Err(RpcError::invalid_params(format!("no trace of receipt {receipt_id}")));
+        };
+        Ok(traces
+            .into_iter()
+            .map(|trace| FunctionCallTraceView {
+                receiver_id: trace.receiver_id,
+                method_name: trace.method_name,
+                truncated: trace.host_calls.truncated,
+                host_calls: trace
+                    .host_calls
+                    .calls
+                    .into_iter()
+                    .map(|call| HostCallView {
+                        name: call.name.to_string(),
+                        args: call.args,
+                        burnt_gas_before: call.burnt_gas_before,
+                        burnt_gas_after: call.burnt_gas_after,
+                        used_gas_before: call.used_gas_before,
+                        used_gas_after: call.used_gas_after,
+                        failed: call.failed,
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+}
@@ -5,6 +5,8 @@ use crate::entity_debug::EntityDebugHandlerImpl;
 use crate::metrics::spawn_trie_metrics_loop;
 
 use crate::cold_storage::spawn_cold_store_loop;
+use crate::compaction::{spawn_compaction_loop, CompactionLoopHandle};
+use crate::epoch_shard_stats::{spawn_epoch_shard_stats_loop, EpochShardStatsLoopHandle};
 use crate::state_sync::StateSyncDumper;
 use actix::{Actor, Addr};
 use actix_rt::ArbiterHandle;
@@ -12,14 +14,14 @@ use anyhow::Context;
 use cold_storage::ColdStoreLoopHandle;
 use near_async::actix::AddrWithAutoSpanContextExt;
 use near_async::actix_wrapper::{spawn_actix_actor, ActixWrapper};
-use near_async::messaging::{IntoMultiSender, IntoSender, LateBoundSender};
+use near_async::messaging::{noop, IntoMultiSender, IntoSender, LateBoundSender};
 use near_async::time::{self, Clock};
 pub use near_chain::runtime::NightshadeRuntime;
 use near_chain::state_snapshot_actor::{
     get_delete_snapshot_callback, get_make_snapshot_callback, SnapshotCallbacks, StateSnapshotActor,
 };
 use near_chain::types::RuntimeAdapter;
-use near_chain::{Chain, ChainGenesis};
+use near_chain::{Chain, ChainGenesis, ChainStore};
 use near_chain_configs::ReshardingHandle;
 use near_chain_configs::SyncConfig;
 use near_chunks::shards_manager_actor::start_shards_manager;
@@ -36,11 +38,19 @@ use near_epoch_manager::EpochManagerAdapter;
 use near_network::PeerManagerActor;
 use near_primitives::block::GenesisId;
 use near_primitives::types::EpochId;
-use near_store::flat::FlatStateValuesInliningMigrationHandle;
+use near_store::db::backend::StoreBackend;
+use near_store::db::ShardedDB;
+use near_store::disk_pressure::DiskPressureMonitorHandle;
+use near_store::flat::{
+    FlatStateValuesInliningMigrationHandle, FlatStorageDeltasCompactionHandle, StateRootAuditHandle,
+};
 use near_store::genesis::initialize_sharded_genesis_state;
-use near_store::metadata::DbKind;
+use near_store::memory_budget::{MemoryBudget, MemoryBudgetHandle};
+use near_store::metadata::{DbKind, DbVersion};
 use near_store::metrics::spawn_db_metrics_loop;
-use near_store::{NodeStorage, Store, StoreOpenerError};
+use near_store::migrations::MigrationEstimate;
+use near_store::secondary::SecondaryCatchUpHandle;
+use near_store::{Mode, NodeStorage, ShardTries, Store, StoreOpenerError};
 use near_telemetry::TelemetryActor;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
@@ -48,15 +58,20 @@ use tokio::sync::broadcast;
 
 pub mod append_only_map;
 pub mod cold_storage;
+pub mod compaction;
 pub mod config;
 #[cfg(test)]
 mod config_duration_test;
+pub mod config_migration;
 mod config_validate;
 mod download_file;
 pub mod dyn_config;
 #[cfg(feature = "json_rpc")]
 mod entity_debug;
 mod entity_debug_serializer;
+pub mod epoch_shard_stats;
+#[cfg(feature = "json_rpc")]
+mod host_call_trace;
 mod metrics;
 pub mod migrations;
 pub mod state_sync;
@@ -175,6 +190,8 @@ pub fn open_storage(home_dir: &Path, near_config: &mut NearConfig) -> anyhow::Re
         Err(StoreOpenerError::MigrationError(err)) => {
             Err(err)
         },
+        // Only returned when rolling back a migration.
+        Err(StoreOpenerError::NoMigrationInProgress | StoreOpenerError::NoMigrationSnapshot(_)) => unreachable!(),
         Err(StoreOpenerError::CheckpointError(err)) => {
             Err(err)
         },
@@ -184,6 +201,38 @@ pub fn open_storage(home_dir: &Path, near_config: &mut NearConfig) -> anyhow::Re
     Ok(storage)
 }
 
+/// Estimates the migrations `open_storage` would run, without running them.
+pub fn estimate_migrations(
+    home_dir: &Path,
+    near_config: &NearConfig,
+) -> anyhow::Result<Vec<MigrationEstimate>> {
+    let migrator = migrations::Migrator::new(near_config);
+    let opener = NodeStorage::opener(
+        home_dir,
+        near_config.client_config.archive,
+        &near_config.config.store,
+        near_config.config.cold_store.as_ref(),
+    )
+    .with_migrator(&migrator);
+    opener.estimate_migrations().with_context(|| {
+        format!("unable to estimate migrations of database at {}", opener.path().display())
+    })
+}
+
+/// Restores the database from the snapshot taken before an interrupted
+/// migration and returns the version it was restored to.
+pub fn rollback_migration(home_dir: &Path, near_config: &NearConfig) -> anyhow::Result<DbVersion> {
+    let opener = NodeStorage::opener(
+        home_dir,
+        near_config.client_config.archive,
+        &near_config.config.store,
+        near_config.config.cold_store.as_ref(),
+    );
+    opener.rollback_migration().with_context(|| {
+        format!("unable to roll back migration of database at {}", opener.path().display())
+    })
+}
+
 // Safely get the split store while checking that all conditions to use it are met.
 fn get_split_store(config: &NearConfig, storage: &NodeStorage) -> anyhow::Result<Option<Store>> {
     // SplitStore should only be used on archival nodes.
@@ -219,14 +268,35 @@ pub struct NearNode {
     /// The cold_store_loop_handle will only be set if the cold store is configured.
     /// It's a handle to a background thread that copies data from the hot store to the cold store.
     pub cold_store_loop_handle: Option<ColdStoreLoopHandle>,
+    /// Only set if `save_epoch_shard_stats` is enabled. It's a handle to a
+    /// background thread that saves statistics of the shards of finished epochs.
+    pub epoch_shard_stats_loop_handle: Option<EpochShardStatsLoopHandle>,
+    /// A handle to the background thread that runs database compactions,
+    /// see `compaction_schedule` in the store config.
+    pub compaction_loop_handle: CompactionLoopHandle,
     /// Contains handles to background threads that may be dumping state to S3.
     pub state_sync_dumper: StateSyncDumper,
     /// A handle to control background flat state values inlining migration.
     /// Needed temporarily, will be removed after the migration is completed.
     pub flat_state_migration_handle: FlatStateValuesInliningMigrationHandle,
+    /// Only set if `flat_storage_deltas` of the store config limits the
+    /// deltas.  It's a handle to the background thread compacting them.
+    pub flat_storage_deltas_compaction_handle: Option<FlatStorageDeltasCompactionHandle>,
+    /// Only set if `state_root_audit` of the store config is enabled.  It's
+    /// a handle to the background thread auditing flat storage.
+    pub state_root_audit_handle: Option<StateRootAuditHandle>,
+    /// Only set if `disk_pressure` of the store config is enabled.  It's a
+    /// handle to the background thread checking the free disk space.
+    pub disk_pressure_monitor_handle: Option<DiskPressureMonitorHandle>,
+    /// Only set if `memory_budget` of the store config is enabled.  It's a
+    /// handle to the background thread resizing the caches.
+    pub memory_budget_handle: Option<MemoryBudgetHandle>,
     // A handle that allows the main process to interrupt resharding if needed.
     // This typically happens when the main process is interrupted.
     pub resharding_handle: ReshardingHandle,
+    /// Tries of the node.  Their in-memory part is saved on shutdown if
+    /// `persist_mem_tries` is set in the store config.
+    pub shard_tries: ShardTries,
 }
 
 pub fn start_with_config(home_dir: &Path, config: NearConfig) -> anyhow::Result<NearNode> {
@@ -242,6 +312,19 @@ pub fn start_with_config_and_synchronization(
     config_updater: Option<ConfigUpdater>,
 ) -> anyhow::Result<NearNode> {
     let storage = open_storage(home_dir, &mut config)?;
+    near_store::io_throttle::configure(config.config.store.io_throttle.clone());
+    let disk_pressure_monitor_handle = {
+        let shutdown_signal = shutdown_signal.clone();
+        DiskPressureMonitorHandle::start(
+            config.config.store.disk_pressure.clone(),
+            disk_pressure_monitored_databases(home_dir, &config),
+            move || {
+                if let Some(shutdown_signal) = shutdown_signal {
+                    let _ = shutdown_signal.send(());
+                }
+            },
+        )
+    };
     let db_metrics_arbiter = if config.client_config.enable_statistics_export {
         let period = config.client_config.log_summary_period;
         let db_metrics_arbiter_handle = spawn_db_metrics_loop(&storage, period)?;
@@ -271,13 +354,21 @@ pub fn start_with_config_and_synchronization(
 
     let shard_tracker =
         ShardTracker::new(TrackedConfig::from_config(&config.client_config), epoch_manager.clone());
-    let runtime = NightshadeRuntime::from_config(
+    let memory_budget = config
+        .config
+        .store
+        .memory_budget
+        .enabled
+        .then(|| Arc::new(MemoryBudget::new(config.config.store.memory_budget.clone())));
+    let runtime = NightshadeRuntime::from_config_with_memory_budget(
         home_dir,
         storage.get_hot_store(),
         &config,
         epoch_manager.clone(),
+        memory_budget.clone(),
     )
     .context("could not create the transaction runtime")?;
+    let _host_call_tracer = runtime.host_call_tracer();
 
     // Get the split store. If split store is some then create a new set of structures for
     // the view client. Otherwise just re-use the existing ones.
@@ -290,21 +381,38 @@ pub fn start_with_config_and_synchronization(
                 TrackedConfig::from_config(&config.client_config),
                 epoch_manager.clone(),
             );
-            let view_runtime = NightshadeRuntime::from_config(
+            let view_runtime = NightshadeRuntime::from_config_with_memory_budget(
                 home_dir,
                 split_store.clone(),
                 &config,
                 view_epoch_manager.clone(),
+                memory_budget.clone(),
             )
             .context("could not create the transaction runtime")?;
             (view_epoch_manager, view_shard_tracker, view_runtime)
         } else {
             (epoch_manager.clone(), shard_tracker.clone(), runtime.clone())
         };
+    let memory_budget_handle = memory_budget.map(MemoryBudgetHandle::start);
 
     let cold_store_loop_handle = spawn_cold_store_loop(&config, &storage, epoch_manager.clone())?;
+    let (_compaction_scheduler, compaction_loop_handle) = spawn_compaction_loop(&config, &storage)?;
+    let epoch_shard_stats_loop_handle = spawn_epoch_shard_stats_loop(
+        &config,
+        ChainStore::new(
+            storage.get_hot_store(),
+            config.genesis.config.genesis_height,
+            config.client_config.save_trie_changes,
+        ),
+        epoch_manager.clone(),
+        runtime.clone(),
+    )?;
 
-    let telemetry = ActixWrapper::new(TelemetryActor::new(config.telemetry_config.clone())).start();
+    let telemetry = ActixWrapper::new(
+        TelemetryActor::new(config.telemetry_config.clone())
+            .with_node_key(config.network_config.node_key.clone()),
+    )
+    .start();
     let chain_genesis = ChainGenesis::new(&config.genesis.config);
     let state_roots = near_store::get_genesis_state_roots(runtime.store())?
         .expect("genesis should be initialized.");
@@ -374,6 +482,7 @@ pub fn start_with_config_and_synchronization(
             config.validator_signer.clone(),
             epoch_manager.clone(),
             storage.get_hot_store(),
+            config.client_config.shadow_chunk_application,
         ));
 
     let (_gc_actor, gc_arbiter) = spawn_actix_actor(GCActor::new(
@@ -429,7 +538,16 @@ pub fn start_with_config_and_synchronization(
             runtime.get_flat_storage_manager(),
             config.client_config.client_background_migration_threads,
         );
+    let flat_storage_deltas_compaction_handle = FlatStorageDeltasCompactionHandle::start(
+        runtime.get_flat_storage_manager(),
+        config.config.store.flat_storage_deltas.clone(),
+    );
+    let state_root_audit_handle = StateRootAuditHandle::start(
+        runtime.get_flat_storage_manager(),
+        config.config.store.state_root_audit.clone(),
+    );
 
+    let shard_tries = runtime.get_tries();
     let mut state_sync_dumper = StateSyncDumper {
         clock: Clock::real(),
         client_config: config.client_config.clone(),
@@ -478,6 +596,8 @@ pub fn start_with_config_and_synchronization(
             #[cfg(feature = "test_features")]
             _gc_actor.with_auto_span_context().into_multi_sender(),
             Arc::new(entity_debug_handler),
+            _compaction_scheduler,
+            Arc::new(host_call_trace::HostCallTraceHandlerImpl { tracer: _host_call_tracer }),
         ));
     }
 
@@ -517,8 +637,116 @@ pub fn start_with_config_and_synchronization(
         rpc_servers,
         arbiters,
         cold_store_loop_handle,
+        epoch_shard_stats_loop_handle,
+        compaction_loop_handle,
         state_sync_dumper,
         flat_state_migration_handle,
+        flat_storage_deltas_compaction_handle,
+        state_root_audit_handle,
+        disk_pressure_monitor_handle,
+        memory_budget_handle,
         resharding_handle,
+        shard_tries,
     })
 }
+
+/// A read-only process serving RPC from the database of a node running on
+/// the same machine, see [`start_secondary_rpc`].
+pub struct SecondaryRpcNode {
+    pub view_client: Addr<ViewClientActor>,
+    pub rpc_servers: Vec<(&'static str, actix_web::dev::ServerHandle)>,
+    /// A handle to the background thread catching up with the writes of the
+    /// node owning the database.
+    pub catch_up_handle: SecondaryCatchUpHandle,
+}
+
+/// Starts serving RPC from the database of another node on the same machine,
+/// opened as a RocksDB secondary instance which follows the writes of the
+/// node.  Only the view client runs, so requests which need the client or
+/// the network, e.g. sending transactions, fail.
+pub fn start_secondary_rpc(
+    home_dir: &Path,
+    config: NearConfig,
+) -> anyhow::Result<SecondaryRpcNode> {
+    let storage = NodeStorage::opener(
+        home_dir,
+        config.client_config.archive,
+        &config.config.store,
+        config.config.cold_store.as_ref(),
+    )
+    .open_in_mode(Mode::Secondary)?;
+    let catch_up_handle =
+        SecondaryCatchUpHandle::start(storage.clone(), config.config.store.secondary.clone());
+
+    let store = get_split_store(&config, &storage)?.unwrap_or_else(|| storage.get_hot_store());
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &config.genesis.config);
+    let shard_tracker =
+        ShardTracker::new(TrackedConfig::from_config(&config.client_config), epoch_manager.clone());
+    let runtime = NightshadeRuntime::from_config(home_dir, store, &config, epoch_manager.clone())
+        .context("could not create the transaction runtime")?;
+    let view_client = ViewClientActorInner::spawn_actix_actor(
+        Clock::real(),
+        config.validator_signer.clone(),
+        ChainGenesis::new(&config.genesis.config),
+        epoch_manager.clone(),
+        shard_tracker,
+        runtime.clone(),
+        noop().into_multi_sender(),
+        config.client_config.clone(),
+        near_client::adversarial::Controls::new(config.client_config.archive),
+    );
+
+    #[allow(unused_mut)]
+    let mut rpc_servers = Vec::new();
+    #[cfg(feature = "json_rpc")]
+    if let Some(rpc_config) = config.rpc_config {
+        let entity_debug_handler = EntityDebugHandlerImpl {
+            epoch_manager,
+            runtime,
+            hot_store: storage.get_hot_store(),
+            cold_store: storage.get_cold_store(),
+        };
+        rpc_servers.extend(near_jsonrpc::start_http(
+            rpc_config,
+            config.genesis.config.clone(),
+            noop().into_multi_sender(),
+            view_client.clone().with_auto_span_context().into_multi_sender(),
+            noop().into_multi_sender(),
+            #[cfg(feature = "test_features")]
+            noop().into_multi_sender(),
+            Arc::new(entity_debug_handler),
+            // The database can only be compacted by the node owning it.
+            Arc::new(near_jsonrpc_primitives::types::compaction::DummyCompactionHandler {}),
+            Arc::new(near_jsonrpc_primitives::types::host_call_trace::DummyHostCallTraceHandler {}),
+        ));
+    }
+
+    Ok(SecondaryRpcNode { view_client, rpc_servers, catch_up_handle })
+}
+
+/// Returns the names and paths of the databases on local disks.
+fn disk_pressure_monitored_databases(
+    home_dir: &Path,
+    config: &NearConfig,
+) -> Vec<(String, PathBuf)> {
+    let mut databases = vec![];
+    let store = &config.config.store;
+    if store.backend == StoreBackend::RocksDB {
+        let path = home_dir.join(store.path.as_deref().unwrap_or(Path::new("data")));
+        for shard_db in &store.shard_dbs {
+            let shard_path = match &shard_db.path {
+                Some(shard_path) => home_dir.join(shard_path),
+                None => ShardedDB::default_shard_db_path(&path, shard_db.shard_uid),
+            };
+            databases.push((format!("hot_{}", shard_db.shard_uid), shard_path));
+        }
+        databases.push(("hot".to_string(), path));
+    }
+    if let Some(cold_store) = &config.config.cold_store {
+        if cold_store.backend == StoreBackend::RocksDB {
+            let path = cold_store.path.as_deref().unwrap_or(Path::new("cold-data"));
+            databases.push(("cold".to_string(), home_dir.join(path)));
+        }
+    }
+    databases
+}
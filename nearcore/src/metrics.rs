@@ -85,6 +85,48 @@ pub(crate) static STATE_SYNC_DUMP_EPOCH_HEIGHT: LazyLock<IntGaugeVec> = LazyLock
     .unwrap()
 });
 
+pub(crate) static STATE_SYNC_DUMP_UPLOAD_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_dump_upload_retries",
+        "Requests to external storage retried by the state dumper",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static COMPACTION_DEBT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    try_create_int_gauge_vec(
+        "near_compaction_debt_bytes",
+        "Estimated number of bytes compactions of the column still have to rewrite",
+        &["temperature", "col"],
+    )
+    .unwrap()
+});
+
+pub(crate) static COMPACTIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_compactions_total",
+        "Number of compactions run by the node, scheduled or requested by the operator",
+        &["temperature", "col", "trigger"],
+    )
+    .unwrap()
+});
+
+pub(crate) static COMPACTION_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    try_create_histogram_vec(
+        "near_compaction_duration_seconds",
+        "Time needed to compact a column",
+        &["temperature", "col"],
+        Some(exponential_buckets(1.0, 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static COMPACTION_QUEUE_LENGTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge("near_compaction_queue_length", "Number of compactions waiting to run")
+        .unwrap()
+});
+
 fn log_trie_item(key: Vec<u8>, value: Vec<u8>) {
     if !tracing::level_enabled!(tracing::Level::TRACE) {
         return;
@@ -1,7 +1,7 @@
 use near_chain::{ChainStore, ChainStoreAccess};
 use near_primitives::utils::index_to_bytes;
 use near_store::metadata::{DbKind, DbVersion, DB_VERSION};
-use near_store::migrations::BatchedStoreUpdate;
+use near_store::migrations::{BatchedStoreUpdate, MigrationDescription};
 use near_store::{DBCol, Store};
 
 /// Fix an issue with block ordinal (#5761)
@@ -69,6 +69,38 @@ impl<'a> near_store::StoreMigrator for Migrator<'a> {
         }
     }
 
+    fn describe(&self, version: DbVersion) -> MigrationDescription {
+        let (summary, reads, writes) = match version {
+            32 => (
+                "Split transaction results by block",
+                vec![DBCol::_TransactionResult],
+                vec![DBCol::TransactionResultForBlock],
+            ),
+            33 => ("Set the database kind", vec![], vec![]),
+            34 => ("Delete the peers column", vec![], vec![]),
+            35 => ("Create flat storage in the background after the start", vec![], vec![]),
+            36 => (
+                "Rewrite flat state changes in the new format",
+                vec![DBCol::FlatStateChanges],
+                vec![DBCol::FlatStateChanges],
+            ),
+            37 => (
+                "Add prev block hash to flat state delta metadata",
+                vec![DBCol::FlatStateDeltaMetadata],
+                vec![DBCol::FlatStateDeltaMetadata],
+            ),
+            38 => (
+                "Add endorsement stats to epoch summaries",
+                vec![DBCol::EpochInfo, DBCol::EpochValidatorInfo],
+                vec![DBCol::EpochInfo, DBCol::EpochValidatorInfo],
+            ),
+            39 => ("Delete the receipt to shard id column", vec![], vec![]),
+            40 => ("Delete partial chunks from the cold database", vec![], vec![]),
+            _ => ("", vec![], vec![]),
+        };
+        MigrationDescription { summary, reads, writes }
+    }
+
     fn migrate(
         &self,
         store: &Store,
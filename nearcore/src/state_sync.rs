@@ -9,7 +9,7 @@ use near_chain::types::RuntimeAdapter;
 use near_chain::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode, Error};
 use near_chain_configs::{ClientConfig, ExternalStorageLocation, MutableValidatorSigner};
 use near_client::sync::external::{
-    create_bucket_readwrite, external_storage_location, StateFileType,
+    create_bucket_readwrite, external_storage_location, StateFileType, MULTIPART_CHUNK_SIZE,
 };
 use near_client::sync::external::{
     external_storage_location_directory, get_part_id_from_filename, is_part_filename,
@@ -18,13 +18,18 @@ use near_client::sync::external::{
 use near_client::sync::state::{StateSync, STATE_DUMP_ITERATION_TIME_LIMIT_SECS};
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
-use near_primitives::hash::CryptoHash;
+use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::state_part::PartId;
-use near_primitives::state_sync::{StatePartKey, StateSyncDumpProgress};
+use near_primitives::state_sync::{
+    SignedStateDumpManifest, StateDumpManifest, StatePartKey, StatePartUpload,
+    StateSyncDumpProgress,
+};
 use near_primitives::types::{AccountId, EpochHeight, EpochId, ShardId, StateRoot};
-use near_store::DBCol;
+use near_store::io_attribution::IoSubsystem;
+use near_store::{io_throttle, DBCol};
 use rand::{thread_rng, Rng};
 use std::collections::HashSet;
+use std::future::Future;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
@@ -325,8 +330,219 @@ async fn upload_state_header(
     }
 }
 
+/// Signs and uploads the manifest of the dumped header and parts to external
+/// storage. Nodes without a validator key don't sign manifests.
+/// Returns true if the manifest is in external storage or isn't needed.
+async fn upload_state_manifest(
+    chain_id: &String,
+    epoch_id: &EpochId,
+    epoch_height: u64,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    validator: &MutableValidatorSigner,
+    chain: &Chain,
+    runtime: &dyn RuntimeAdapter,
+    external: &ExternalConnection,
+) -> bool {
+    if validator.get().is_none() {
+        return true;
+    }
+    match external
+        .is_state_sync_manifest_stored_for_epoch(shard_id, chain_id, epoch_id, epoch_height)
+        .await
+    {
+        Ok(true) => return true,
+        Ok(false) => {}
+        Err(err) => {
+            tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to determine manifest presence in external storage.");
+            return false;
+        }
+    }
+    let manifest = match get_state_dump_manifest(shard_id, sync_hash, chain, runtime) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to compute the state dump manifest.");
+            return false;
+        }
+    };
+    let Some(signer) = validator.get() else {
+        return true;
+    };
+    let manifest = SignedStateDumpManifest::new(manifest, &signer);
+    let data = borsh::to_vec(&manifest).expect("manifest serialization can't fail");
+    let file_type = StateFileType::StateManifest;
+    let location =
+        external_storage_location(chain_id, epoch_id, epoch_height, shard_id, &file_type);
+    match external.put_file(file_type, &data, shard_id, &location).await {
+        Err(err) => {
+            tracing::warn!(target: "state_sync_dump", shard_id, epoch_height, ?err, "Failed to put manifest into external storage. Will retry next iteration.");
+            false
+        }
+        Ok(_) => {
+            tracing::debug!(target: "state_sync_dump", shard_id, epoch_height, public_key = %manifest.public_key, "Manifest saved to external storage.");
+            true
+        }
+    }
+}
+
+/// Computes the hashes of the header and of all parts of the dumped state.
+/// Parts missing in the DB, e.g. dumped by another node, are obtained again.
+fn get_state_dump_manifest(
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    chain: &Chain,
+    runtime: &dyn RuntimeAdapter,
+) -> anyhow::Result<StateDumpManifest> {
+    let header = get_serialized_header(shard_id, sync_hash, chain)?;
+    let (state_root, num_parts, sync_prev_prev_hash) =
+        get_in_progress_data(shard_id, sync_hash, chain)?;
+    let part_hashes = (0..num_parts)
+        .map(|part_id| {
+            let part = get_or_obtain_state_part(
+                runtime,
+                shard_id,
+                sync_hash,
+                &sync_prev_prev_hash,
+                &state_root,
+                part_id,
+                num_parts,
+                chain,
+            )?;
+            Ok(hash(&part))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(StateDumpManifest { sync_hash, shard_id, header_hash: hash(&header), part_hashes })
+}
+
 const FAILURES_ALLOWED_PER_ITERATION: u32 = 10;
 
+/// Attempts of every request to external storage before the upload of a
+/// part is given up for the iteration.
+const UPLOAD_ATTEMPTS: u32 = 5;
+const UPLOAD_INITIAL_BACKOFF: Duration = Duration::seconds(1);
+const UPLOAD_MAX_BACKOFF: Duration = Duration::seconds(30);
+
+/// Sends the request until it succeeds, backing off exponentially between
+/// the attempts.  A failed PUT is often a throttled or dropped connection,
+/// retrying it soon is cheaper than dumping the part again.
+async fn with_retries<T, F: Future<Output = anyhow::Result<T>>>(
+    clock: &Clock,
+    shard_id: ShardId,
+    keep_running: &AtomicBool,
+    mut request: impl FnMut() -> F,
+) -> anyhow::Result<T> {
+    let mut backoff = UPLOAD_INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Ok(value) => return Ok(value),
+            Err(err)
+                if attempt < UPLOAD_ATTEMPTS
+                    && keep_running.load(std::sync::atomic::Ordering::Relaxed) =>
+            {
+                tracing::debug!(target: "state_sync_dump", shard_id, attempt, ?backoff, ?err, "Request to external storage failed, retrying");
+                metrics::STATE_SYNC_DUMP_UPLOAD_RETRIES
+                    .with_label_values(&[&shard_id.to_string()])
+                    .inc();
+                clock.sleep(backoff).await;
+                backoff = std::cmp::min(backoff * 2, UPLOAD_MAX_BACKOFF);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Uploads a state part to external storage.  Large parts are uploaded to
+/// S3 in chunks, and the upload is saved after every chunk, so that after a
+/// failure or a restart of the node the chunks already uploaded aren't
+/// uploaded again.
+async fn upload_state_part(
+    clock: &Clock,
+    shard_id: ShardId,
+    part_id: u64,
+    num_parts: u64,
+    state_part: &[u8],
+    location: &str,
+    chain: &Chain,
+    external: &ExternalConnection,
+    keep_running: &AtomicBool,
+) -> anyhow::Result<()> {
+    if !external.supports_multipart_upload() || state_part.len() <= MULTIPART_CHUNK_SIZE {
+        return with_retries(clock, shard_id, keep_running, || {
+            external.put_file(
+                StateFileType::StatePart { part_id, num_parts },
+                state_part,
+                shard_id,
+                location,
+            )
+        })
+        .await;
+    }
+
+    let chain_store = chain.chain_store();
+    let part_hash = hash(state_part);
+    let (mut upload, continued) = match chain_store.get_state_part_upload(shard_id)? {
+        Some(upload) if upload.location == location && upload.part_hash == part_hash => {
+            tracing::debug!(target: "state_sync_dump", shard_id, part_id, num_chunks_uploaded = upload.chunk_etags.len(), "Continuing the upload of a state part");
+            (upload, true)
+        }
+        saved => {
+            if let Some(stale) = saved {
+                // The chunks of an abandoned upload take space until the
+                // bucket's lifecycle rules remove them.
+                if let Err(err) =
+                    external.abort_multipart_upload(&stale.location, &stale.upload_id).await
+                {
+                    tracing::debug!(target: "state_sync_dump", shard_id, location = stale.location, ?err, "Failed to abort an unfinished upload");
+                }
+            }
+            let upload_id = with_retries(clock, shard_id, keep_running, || {
+                external.start_multipart_upload(location)
+            })
+            .await?;
+            let upload = StatePartUpload {
+                location: location.to_string(),
+                part_id,
+                upload_id,
+                part_hash,
+                chunk_etags: vec![],
+            };
+            chain_store.set_state_part_upload(shard_id, Some(&upload))?;
+            (upload, false)
+        }
+    };
+
+    let result = async {
+        let chunks = state_part.chunks(MULTIPART_CHUNK_SIZE).enumerate();
+        for (index, chunk) in chunks.skip(upload.chunk_etags.len()) {
+            let etag = with_retries(clock, shard_id, keep_running, || {
+                external.put_multipart_chunk(location, &upload.upload_id, index, chunk)
+            })
+            .await?;
+            upload.chunk_etags.push(etag);
+            chain_store.set_state_part_upload(shard_id, Some(&upload))?;
+        }
+        with_retries(clock, shard_id, keep_running, || {
+            external.complete_multipart_upload(location, &upload.upload_id, &upload.chunk_etags)
+        })
+        .await?;
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+    match &result {
+        Ok(()) => chain_store.set_state_part_upload(shard_id, None)?,
+        // An upload continued after a restart may have expired in the
+        // meantime, don't keep trying it.
+        Err(_) if continued => {
+            let _ = external.abort_multipart_upload(location, &upload.upload_id).await;
+            chain_store.set_state_part_upload(shard_id, None)?;
+        }
+        Err(_) => {}
+    }
+    result
+}
+
 async fn state_sync_dump(
     clock: Clock,
     shard_id: ShardId,
@@ -352,6 +568,11 @@ async fn state_sync_dump(
     // Note that without this check the state dumping thread is unstoppable, i.e. non-interruptable.
     while keep_running.load(std::sync::atomic::Ordering::Relaxed) {
         tracing::debug!(target: "state_sync_dump", shard_id, "Running StateSyncDump loop iteration");
+        if !near_store::disk_pressure::non_essential_writes_allowed() {
+            tracing::debug!(target: "state_sync_dump", shard_id, "Paused due to disk pressure");
+            clock.sleep(iteration_delay).await;
+            continue;
+        }
         let account_id = validator.get().map(|v| v.validator_id().clone());
         let current_state = get_current_state(
             &chain,
@@ -437,13 +658,40 @@ async fn state_sync_dump(
                                     num_parts,
                                     num_parts,
                                 );
-                                Some(StateSyncDumpProgress::AllDumped { epoch_id, epoch_height })
+                                let manifest_uploaded = upload_state_manifest(
+                                    &chain_id,
+                                    &epoch_id,
+                                    epoch_height,
+                                    shard_id,
+                                    sync_hash,
+                                    &validator,
+                                    &chain,
+                                    runtime.as_ref(),
+                                    &external,
+                                )
+                                .await;
+                                if manifest_uploaded {
+                                    Some(StateSyncDumpProgress::AllDumped {
+                                        epoch_id,
+                                        epoch_height,
+                                    })
+                                } else {
+                                    None
+                                }
                             }
                             Ok(missing_parts) => {
                                 let mut parts_to_dump = missing_parts.clone();
                                 let timer = Instant::now();
                                 let mut dumped_any_state_part = false;
                                 let mut failures_cnt = 0;
+                                // The part whose upload didn't finish before
+                                // a restart goes first.
+                                let mut unfinished_part_id = chain
+                                    .chain_store()
+                                    .get_state_part_upload(shard_id)
+                                    .ok()
+                                    .flatten()
+                                    .map(|upload| upload.part_id);
                                 // Stop if the node is stopped.
                                 // Note that without this check the state dumping thread is unstoppable, i.e. non-interruptable.
                                 while keep_running.load(std::sync::atomic::Ordering::Relaxed)
@@ -456,19 +704,34 @@ async fn state_sync_dump(
                                         .with_label_values(&[&shard_id.to_string()])
                                         .start_timer();
 
+                                    let unfinished_part =
+                                        unfinished_part_id.take().and_then(|part_id| {
+                                            let idx = parts_to_dump
+                                                .iter()
+                                                .position(|id| *id == part_id)?;
+                                            Some((part_id, idx))
+                                        });
                                     let (part_id, selected_idx) =
-                                        select_random_part_id_with_index(&parts_to_dump);
-
-                                    let state_part = obtain_and_store_state_part(
-                                        runtime.as_ref(),
-                                        shard_id,
-                                        sync_hash,
-                                        &sync_prev_prev_hash,
-                                        &state_root,
-                                        part_id,
-                                        num_parts,
-                                        &chain,
-                                    );
+                                        unfinished_part.unwrap_or_else(|| {
+                                            select_random_part_id_with_index(&parts_to_dump)
+                                        });
+
+                                    // Obtaining the part blocks the thread
+                                    // anyway, so it may as well wait for the disk.
+                                    let state_part = {
+                                        let _io_subsystem = IoSubsystem::StateDump.enter();
+                                        io_throttle::throttle(IoSubsystem::StateDump);
+                                        get_or_obtain_state_part(
+                                            runtime.as_ref(),
+                                            shard_id,
+                                            sync_hash,
+                                            &sync_prev_prev_hash,
+                                            &state_root,
+                                            part_id,
+                                            num_parts,
+                                            &chain,
+                                        )
+                                    };
                                     let state_part = match state_part {
                                         Ok(state_part) => state_part,
                                         Err(err) => {
@@ -486,9 +749,18 @@ async fn state_sync_dump(
                                         shard_id,
                                         &file_type,
                                     );
-                                    if let Err(err) = external
-                                        .put_file(file_type, &state_part, shard_id, &location)
-                                        .await
+                                    if let Err(err) = upload_state_part(
+                                        &clock,
+                                        shard_id,
+                                        part_id,
+                                        num_parts,
+                                        &state_part,
+                                        &location,
+                                        &chain,
+                                        &external,
+                                        &keep_running,
+                                    )
+                                    .await
                                     {
                                         // no need to break if there's an error, we should keep dumping other parts.
                                         // reason is we are dumping random selected parts, so it's fine if we are not able to finish all of them
@@ -508,12 +780,9 @@ async fn state_sync_dump(
                                     );
                                     dumped_any_state_part = true;
                                 }
-                                if parts_to_dump.is_empty() {
-                                    Some(StateSyncDumpProgress::AllDumped {
-                                        epoch_id,
-                                        epoch_height,
-                                    })
-                                } else if dumped_any_state_part {
+                                // Once all parts are dumped, the next iteration
+                                // uploads the manifest and marks the epoch done.
+                                if dumped_any_state_part {
                                     Some(StateSyncDumpProgress::InProgress {
                                         epoch_id,
                                         epoch_height,
@@ -607,6 +876,34 @@ fn update_dumped_size_and_cnt_metrics(
         .set(num_parts as i64);
 }
 
+/// Returns the part saved by an earlier attempt to dump it, or obtains and
+/// saves it.
+fn get_or_obtain_state_part(
+    runtime: &dyn RuntimeAdapter,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    sync_prev_prev_hash: &CryptoHash,
+    state_root: &StateRoot,
+    part_id: u64,
+    num_parts: u64,
+    chain: &Chain,
+) -> Result<Vec<u8>, Error> {
+    let key = borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id))?;
+    if let Some(state_part) = chain.chain_store().store().get(DBCol::StateParts, &key)? {
+        return Ok(state_part.to_vec());
+    }
+    obtain_and_store_state_part(
+        runtime,
+        shard_id,
+        sync_hash,
+        sync_prev_prev_hash,
+        state_root,
+        part_id,
+        num_parts,
+        chain,
+    )
+}
+
 /// Obtains and then saves the part data.
 fn obtain_and_store_state_part(
     runtime: &dyn RuntimeAdapter,
@@ -127,6 +127,9 @@ impl NeardCmd {
             NeardSubCommand::ValidateConfig(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
+            NeardSubCommand::Config(cmd) => {
+                cmd.run(&home_dir)?;
+            }
             NeardSubCommand::UndoBlock(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
@@ -239,6 +242,9 @@ pub(super) enum NeardSubCommand {
     /// validate config files including genesis.json and config.json
     ValidateConfig(ValidateConfigCommand),
 
+    /// Manage config.json
+    Config(ConfigCommand),
+
     /// reset the head of the chain locally to the prev block of current head
     UndoBlock(UndoBlockCommand),
 
@@ -423,6 +429,11 @@ pub(super) struct RunCmd {
     /// configuration will be taken.
     #[clap(long)]
     max_gas_burnt_view: Option<Gas>,
+    /// Instead of running a node, serve RPC read-only from the database of a
+    /// node running on the same machine, following its writes.  See
+    /// `store.secondary` in the config for how often the writes are picked up.
+    #[clap(long)]
+    secondary: bool,
 }
 
 impl RunCmd {
@@ -503,12 +514,17 @@ impl RunCmd {
             }
         }
 
+        if self.secondary {
+            run_secondary(home_dir, near_config, verbose_target, o11y_opts);
+            return;
+        }
+
         let (tx_crash, mut rx_crash) = broadcast::channel::<()>(16);
         let (tx_config_update, rx_config_update) =
             broadcast::channel::<Result<UpdateableConfigs, Arc<UpdateableConfigLoaderError>>>(16);
         let sys = actix::System::new();
 
-        sys.block_on(async move {
+        let shard_tries = sys.block_on(async move {
             // Initialize the subscriber that takes care of both logging and tracing.
             let _subscriber_guard = default_subscriber_with_opentelemetry(
                 make_env_filter(verbose_target).unwrap(),
@@ -529,9 +545,16 @@ impl RunCmd {
             let nearcore::NearNode {
                 rpc_servers,
                 cold_store_loop_handle,
+                epoch_shard_stats_loop_handle,
+                compaction_loop_handle,
                 mut state_sync_dumper,
                 flat_state_migration_handle,
+                flat_storage_deltas_compaction_handle,
+                state_root_audit_handle,
+                disk_pressure_monitor_handle,
+                memory_budget_handle,
                 resharding_handle,
+                shard_tries,
                 ..
             } = nearcore::start_with_config_and_synchronization(
                 home_dir,
@@ -555,9 +578,25 @@ impl RunCmd {
             if let Some(handle) = cold_store_loop_handle {
                 handle.stop()
             }
+            if let Some(handle) = epoch_shard_stats_loop_handle {
+                handle.stop()
+            }
+            compaction_loop_handle.stop();
             state_sync_dumper.stop();
             resharding_handle.stop();
             flat_state_migration_handle.stop();
+            if let Some(handle) = flat_storage_deltas_compaction_handle {
+                handle.stop()
+            }
+            if let Some(handle) = state_root_audit_handle {
+                handle.stop()
+            }
+            if let Some(handle) = disk_pressure_monitor_handle {
+                handle.stop()
+            }
+            if let Some(handle) = memory_budget_handle {
+                handle.stop()
+            }
             futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
                 server.stop(true).await;
                 debug!(target: "neard", "{} server stopped", name);
@@ -566,13 +605,61 @@ impl RunCmd {
             actix::System::current().stop();
             // Disable the subscriber to properly shutdown the tracer.
             near_o11y::reload(Some("error"), None, Some("off")).unwrap();
+            shard_tries
         });
         sys.run().unwrap();
+        // Blocks are no longer applied once the actors have stopped, so the
+        // in-memory tries can be saved consistently.
+        shard_tries.save_mem_tries();
         info!(target: "neard", "Waiting for RocksDB to gracefully shutdown");
         RocksDB::block_until_all_instances_are_dropped();
     }
 }
 
+/// Serves RPC from a RocksDB secondary instance of the database of the node
+/// in `home_dir`, see `neard run --secondary`.
+fn run_secondary(
+    home_dir: &Path,
+    near_config: nearcore::NearConfig,
+    verbose_target: Option<&str>,
+    o11y_opts: &near_o11y::Options,
+) {
+    let (_tx_crash, mut rx_crash) = broadcast::channel::<()>(16);
+    let sys = actix::System::new();
+    sys.block_on(async move {
+        let _subscriber_guard = default_subscriber_with_opentelemetry(
+            make_env_filter(verbose_target).unwrap(),
+            o11y_opts,
+            near_config.client_config.chain_id.clone(),
+            near_config.network_config.node_key.public_key().clone(),
+            None,
+        )
+        .await
+        .global();
+
+        let nearcore::SecondaryRpcNode { rpc_servers, catch_up_handle, .. } =
+            nearcore::start_secondary_rpc(home_dir, near_config).expect("start_secondary_rpc");
+
+        let sig = loop {
+            let sig = wait_for_interrupt_signal(home_dir, &mut rx_crash).await;
+            if sig != "SIGHUP" {
+                break sig;
+            }
+        };
+        warn!(target: "neard", "{}, stopping...", sig);
+        catch_up_handle.stop();
+        futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
+            server.stop(true).await;
+            debug!(target: "neard", "{} server stopped", name);
+        }))
+        .await;
+        actix::System::current().stop();
+        near_o11y::reload(Some("error"), None, Some("off")).unwrap();
+    });
+    sys.run().unwrap();
+    RocksDB::block_until_all_instances_are_dropped();
+}
+
 #[cfg(not(unix))]
 async fn wait_for_interrupt_signal(_home_dir: &Path, mut _rx_crash: &Receiver<()>) -> &str {
     // TODO(#6372): Support graceful shutdown on windows.
@@ -591,7 +678,7 @@ async fn wait_for_interrupt_signal(_home_dir: &Path, rx_crash: &mut Receiver<()>
          _ = sigint.recv()  => "SIGINT",
          _ = sigterm.recv() => "SIGTERM",
          _ = sighup.recv() => "SIGHUP",
-         _ = rx_crash.recv() => "ClientActor died",
+         _ = rx_crash.recv() => "ClientActor died or the node is halting",
     }
 }
 
@@ -788,6 +875,47 @@ impl ValidateConfigCommand {
     }
 }
 
+#[derive(clap::Parser)]
+pub(super) struct ConfigCommand {
+    #[clap(subcommand)]
+    subcmd: ConfigSubCommand,
+}
+
+#[derive(clap::Parser)]
+enum ConfigSubCommand {
+    /// Upgrade config.json to the current version of the config schema,
+    /// keeping the original in config.json.v<VERSION>.bak
+    Migrate {
+        /// Only print the changes, without modifying config.json.
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+impl ConfigCommand {
+    pub(super) fn run(&self, home_dir: &Path) -> anyhow::Result<()> {
+        match self.subcmd {
+            ConfigSubCommand::Migrate { dry_run } => {
+                let path = home_dir.join(nearcore::config::CONFIG_FILENAME);
+                let migration = nearcore::config_migration::migrate_config_file(&path, dry_run)?;
+                for change in &migration.changes {
+                    println!("{change}");
+                }
+                println!(
+                    "{} migrated from version {} to {}",
+                    path.display(),
+                    migration.from_version,
+                    nearcore::config_migration::CONFIG_VERSION
+                );
+                for field in &migration.unrecognised_fields {
+                    warn!(target: "neard", "unrecognised field {field}, it can't be migrated");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{CryptoHash, NeardCmd, NeardSubCommand, VerifyProofError, VerifyProofSubCommand};
@@ -75,6 +75,7 @@ class TestRpcFinality(unittest.TestCase):
         # Block 5 (doomslug) - has the transaction (so this is the moment when state is removed from test0)
         # Block 4 (final) - has no information about the transaction.
 
+        # 'doomslug' is an alias of 'near-final'.
         # So with optimistic finality: test0 = -10, test1 = +10
         # with doomslug (state as of block 5): test0 = -10, test1 = 0
         # with final (state as of block 4): test0 = 0, test1 = 0
@@ -83,14 +84,14 @@ class TestRpcFinality(unittest.TestCase):
             amounts = [
                 int(nodes[0].get_account(acc_id, finality)['result']['amount'])
                 - balances[acc_id]
-                for finality in ["optimistic", "near-final", "final"]
+                for finality in ["optimistic", "near-final", "doomslug", "final"]
             ]
             print(f"Account amounts: {acc_id}: {amounts}")
 
             if acc_id == 'test0':
-                self.assertEqual([-10, -10, 0], amounts)
+                self.assertEqual([-10, -10, -10, 0], amounts)
             else:
-                self.assertEqual([10, 0, 0], amounts)
+                self.assertEqual([10, 0, 0, 0], amounts)
 
 
 if __name__ == '__main__':
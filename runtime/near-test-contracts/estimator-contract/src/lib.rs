@@ -40,6 +40,8 @@ extern "C" {
     fn alt_bn128_g1_multiexp(value_len: u64, value_ptr: u64, register_id: u64);
     fn alt_bn128_g1_sum(value_len: u64, value_ptr: u64, register_id: u64);
     fn alt_bn128_pairing_check(value_len: u64, value_ptr: u64) -> u64;
+    #[cfg(feature = "nightly")]
+    fn alt_bn128_groth16_verify(value_len: u64, value_ptr: u64) -> u64;
     fn bls12381_p1_sum(value_len: u64, value_ptr: u64, register_id: u64) -> u64;
     fn bls12381_p2_sum(value_len: u64, value_ptr: u64, register_id: u64) -> u64;
     fn bls12381_g1_multiexp(value_len: u64, value_ptr: u64, register_id: u64) -> u64;
@@ -54,6 +56,8 @@ extern "C" {
     fn sha256(value_len: u64, value_ptr: u64, register_id: u64);
     fn keccak256(value_len: u64, value_ptr: u64, register_id: u64);
     fn keccak512(value_len: u64, value_ptr: u64, register_id: u64);
+    #[cfg(feature = "nightly")]
+    fn sha3_256(value_len: u64, value_ptr: u64, register_id: u64);
     fn ripemd160(value_len: u64, value_ptr: u64, register_id: u64);
     fn ecrecover(
         hash_len: u64,
@@ -463,6 +467,31 @@ pub unsafe fn keccak512_10kib_10k() {
     }
 }
 
+// Function to measure `sha3_256_base` and `sha3_256_byte`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However `sha3_256` computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute sha3_256 on 10b 10k times.
+#[cfg(feature = "nightly")]
+#[no_mangle]
+pub unsafe fn sha3_256_10b_10k() {
+    let buffer = [65u8; 10];
+    for _ in 0..10_000 {
+        sha3_256(buffer.len() as u64, buffer.as_ptr() as *const u64 as u64, 0);
+    }
+}
+// Function to measure `sha3_256_base` and `sha3_256_byte`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However `sha3_256` computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute sha3_256 on 10kib 10k times.
+#[cfg(feature = "nightly")]
+#[no_mangle]
+pub unsafe fn sha3_256_10kib_10k() {
+    let buffer = [65u8; 10240];
+    for _ in 0..10_000 {
+        sha3_256(buffer.len() as u64, buffer.as_ptr() as *const u64 as u64, 0);
+    }
+}
+
 // Function to measure `ripemd160_base` and `ripemd160_block`. Also measures `base`, `write_register_base`,
 // and `write_register_byte`. However `ripemd160` computation is more expensive than register writing
 // so we are okay overcharging it.
@@ -754,6 +783,62 @@ pub unsafe fn alt_bn128_pairing_check_10_10() {
     }
 }
 
+// Generators of G1 and G2. The verification key and the proof are made of them,
+// which is not a valid proof, but verifying it costs as much as a valid one.
+#[cfg(feature = "nightly")]
+#[rustfmt::skip]
+const ALT_BN128_G1: [u8; 64] = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+#[cfg(feature = "nightly")]
+#[rustfmt::skip]
+const ALT_BN128_G2: [u8; 128] = [237, 246, 146, 217, 92, 189, 222, 70, 221, 218, 94, 247, 212, 34, 67, 103, 121, 68, 92, 94, 102, 0, 106, 66, 118, 30, 31, 18, 239, 222, 0, 24, 194, 18, 243, 174, 183, 133, 228, 151, 18, 231, 169, 53, 51, 73, 170, 241, 37, 93, 251, 49, 183, 191, 96, 114, 58, 72, 13, 146, 147, 147, 142, 25, 170, 125, 250, 102, 1, 204, 230, 76, 123, 211, 67, 12, 105, 231, 209, 227, 143, 64, 203, 141, 128, 113, 171, 74, 235, 109, 140, 219, 165, 94, 200, 18, 91, 151, 34, 209, 220, 218, 172, 85, 243, 142, 179, 112, 51, 49, 75, 188, 149, 51, 12, 105, 173, 153, 158, 236, 117, 240, 95, 88, 208, 137, 6, 9];
+
+// Verifies a Groth16 proof with `num_inputs` public inputs 10 times.
+#[cfg(feature = "nightly")]
+unsafe fn alt_bn128_groth16_verify_10(num_inputs: usize) {
+    let mut buffer = [0u8; 768 + 96 * 10];
+    let mut len = 0;
+    let header = [
+        &ALT_BN128_G1[..],
+        &ALT_BN128_G2,
+        &ALT_BN128_G2,
+        &ALT_BN128_G2,
+        &ALT_BN128_G1,
+        &ALT_BN128_G1,
+        &ALT_BN128_G2,
+        &ALT_BN128_G1,
+    ];
+    for point in header {
+        buffer[len..len + point.len()].copy_from_slice(point);
+        len += point.len();
+    }
+    for _ in 0..num_inputs {
+        buffer[len..len + 64].copy_from_slice(&ALT_BN128_G1);
+        // A 252 bit scalar, so that the multiexp isn't cheaper than usual.
+        buffer[len + 64..len + 95].fill(0xff);
+        buffer[len + 95] = 0x0f;
+        len += 96;
+    }
+    for _ in 0..10 {
+        alt_bn128_groth16_verify(len as u64, buffer.as_ptr() as *const u64 as u64);
+    }
+}
+
+// Function to measure `alt_bn128_groth16_verify_base`. Also measures `base`, `read_memory_base`
+// and `read_memory_byte`, but the pairings are far more expensive than reading the memory.
+// Verify a proof with no public inputs 10 times.
+#[cfg(feature = "nightly")]
+#[no_mangle]
+pub unsafe fn alt_bn128_groth16_verify_0_10() {
+    alt_bn128_groth16_verify_10(0);
+}
+// Function to measure `alt_bn128_groth16_verify_element`.
+// Verify a proof with 10 public inputs 10 times.
+#[cfg(feature = "nightly")]
+#[no_mangle]
+pub unsafe fn alt_bn128_groth16_verify_10_10() {
+    alt_bn128_groth16_verify_10(10);
+}
+
 #[no_mangle]
 pub unsafe fn bls12381_p1_sum_0_100() {
     let buffer: [u8; 0] = [];
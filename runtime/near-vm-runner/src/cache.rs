@@ -13,6 +13,7 @@ use std::collections::HashMap;
 use std::fmt;
 use std::io::{Read, Write};
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, BorshSerialize, ProtocolSchema)]
@@ -200,11 +201,14 @@ impl fmt::Debug for MockContractRuntimeCache {
 /// directory will be removed when the last instance of this cache is dropped.
 ///
 /// Clones of this type share the same underlying state and information. The cache is thread safe
-/// and atomic.
+/// and atomic. A node keeps a single instance for all the shards it tracks, so that a contract
+/// deployed to several shards is compiled and stored once.
 ///
-/// This cache however does not implement any clean-up policies. While it is possible to truncate
-/// a file that has been written to the cache before (`put` an empty buffer), the file will remain
-/// in place until an operator (or somebody else) removes files at their own discretion.
+/// Created with [`Self::with_limits`], the cache keeps the files within a size limit, evicting
+/// them with a segmented LRU policy: the files read again after they were written are evicted only
+/// once the ones which weren't are gone. Files left over from the previous runs are accounted for
+/// on start, so the executables persist over restarts. Otherwise the cache grows without bounds,
+/// until an operator (or somebody else) removes files at their own discretion.
 #[derive(Clone)]
 pub struct FilesystemContractRuntimeCache {
     state: Arc<FilesystemContractRuntimeCacheState>,
@@ -212,7 +216,9 @@ pub struct FilesystemContractRuntimeCache {
 
 struct FilesystemContractRuntimeCacheState {
     dir: rustix::fd::OwnedFd,
-    any_cache: AnyCache,
+    any_cache: Arc<AnyCache>,
+    /// Sizes of the files, if the cache is bounded.
+    disk_index: Option<Mutex<DiskIndex>>,
     test_temp_dir: Option<tempfile::TempDir>,
 }
 
@@ -236,6 +242,18 @@ impl FilesystemContractRuntimeCache {
         home_dir: &std::path::Path,
         store_path: Option<&SP>,
         memory_cache_size: usize,
+    ) -> std::io::Result<Self> {
+        Self::with_limits(home_dir, store_path, memory_cache_size, None)
+    }
+
+    /// Like [`Self::with_memory_cache`], and also keeps the files within `max_disk_bytes`, if
+    /// set. The files already in the directory count towards the limit, the oldest of them are
+    /// evicted right away if there are too many.
+    pub fn with_limits<SP: AsRef<std::path::Path> + ?Sized>(
+        home_dir: &std::path::Path,
+        store_path: Option<&SP>,
+        memory_cache_size: usize,
+        max_disk_bytes: Option<u64>,
     ) -> std::io::Result<Self> {
         let store_path = store_path.map(AsRef::as_ref).unwrap_or_else(|| "data".as_ref());
         let path: std::path::PathBuf =
@@ -248,15 +266,66 @@ impl FilesystemContractRuntimeCache {
             path = %path.display(),
             message = "opened a contract executable cache directory"
         );
+        let disk_index = match max_disk_bytes {
+            None => None,
+            Some(max_bytes) => {
+                let mut index = DiskIndex::new(max_bytes);
+                for (key, bytes) in scan_cache_dir(&path)? {
+                    index.insert(key, bytes);
+                }
+                let evicted = index.evict();
+                remove_cached_files(&dir, &evicted);
+                tracing::info!(
+                    target: "vm",
+                    path = %path.display(),
+                    bytes = index.bytes(),
+                    evicted = evicted.len(),
+                    message = "loaded the index of the contract executable cache"
+                );
+                Some(Mutex::new(index))
+            }
+        };
         Ok(Self {
             state: Arc::new(FilesystemContractRuntimeCacheState {
                 dir,
-                any_cache: AnyCache::new(memory_cache_size),
+                any_cache: Arc::new(AnyCache::new(memory_cache_size)),
+                disk_index,
                 test_temp_dir: None,
             }),
         })
     }
 
+    /// Records a file of `bytes` written under `key`, removing the files evicted to make room.
+    fn record_put(&self, key: &CryptoHash, bytes: u64) {
+        let Some(index) = &self.state.disk_index else { return };
+        let evicted = {
+            let mut index = index.lock().unwrap();
+            index.insert(*key, bytes);
+            index.evict()
+        };
+        remove_cached_files(&self.state.dir, &evicted);
+    }
+
+    /// Records a read of the file under `key`, of `bytes`.
+    fn record_get(&self, key: &CryptoHash, bytes: u64) {
+        let Some(index) = &self.state.disk_index else { return };
+        let evicted = {
+            let mut index = index.lock().unwrap();
+            if !index.touch(key) {
+                // Written by some other instance of the cache.
+                index.insert(*key, bytes);
+            }
+            index.evict()
+        };
+        remove_cached_files(&self.state.dir, &evicted);
+    }
+
+    /// The in-memory cache shared by the clones of this cache, e.g. to be
+    /// resized while the node is running.
+    pub fn shared_memory_cache(&self) -> Arc<AnyCache> {
+        self.state.any_cache.clone()
+    }
+
     pub fn test() -> std::io::Result<Self> {
         let tempdir = tempfile::TempDir::new()?;
         let mut cache = Self::new(tempdir.path(), None::<&str>)?;
@@ -316,11 +385,13 @@ impl ContractRuntimeCache for FilesystemContractRuntimeCache {
             }
         }
         temp_file.write_all(&value.wasm_bytes.to_le_bytes())?;
+        let bytes = temp_file.as_file().metadata()?.len();
         let temp_filename = temp_file.into_temp_path();
         // This is atomic, so there wouldn't be instances where getters see an intermediate state.
         rustix::fs::renameat(&self.state.dir, &*temp_filename, &self.state.dir, final_filename)?;
         // Don't attempt deleting the temporary file now that it has been moved.
         std::mem::forget(temp_filename);
+        self.record_put(key, bytes);
         Ok(())
     }
 
@@ -353,6 +424,7 @@ impl ContractRuntimeCache for FilesystemContractRuntimeCache {
             // The file turns out to be empty/truncated? Treat as if there's no cached file.
             return Ok(None);
         }
+        self.record_get(key, buffer.len() as u64);
         let wasm_bytes = u64::from_le_bytes(buffer[buffer.len() - 8..].try_into().unwrap());
         let tag = buffer[buffer.len() - 9];
         buffer.truncate(buffer.len() - 9);
@@ -380,6 +452,112 @@ impl ContractRuntimeCache for FilesystemContractRuntimeCache {
     }
 }
 
+/// Share of the size limit taken at most by the files read again after they were written.
+const PROTECTED_SHARE: f64 = 0.8;
+
+/// Sizes of the files in a bounded [`FilesystemContractRuntimeCache`], in the order of eviction.
+struct DiskIndex {
+    max_bytes: u64,
+    /// Files not read since they were written, evicted first.
+    probation: lru::LruCache<CryptoHash, u64>,
+    probation_bytes: u64,
+    /// Files read at least once since they were written.
+    protected: lru::LruCache<CryptoHash, u64>,
+    protected_bytes: u64,
+}
+
+impl DiskIndex {
+    fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            probation: lru::LruCache::unbounded(),
+            probation_bytes: 0,
+            protected: lru::LruCache::unbounded(),
+            protected_bytes: 0,
+        }
+    }
+
+    fn bytes(&self) -> u64 {
+        self.probation_bytes + self.protected_bytes
+    }
+
+    fn insert(&mut self, key: CryptoHash, bytes: u64) {
+        if let Some(old) = self.protected.pop(&key) {
+            self.protected_bytes -= old;
+        }
+        if let Some(old) = self.probation.put(key, bytes) {
+            self.probation_bytes -= old;
+        }
+        self.probation_bytes += bytes;
+    }
+
+    /// Marks the file as used, returns false if it isn't known.
+    fn touch(&mut self, key: &CryptoHash) -> bool {
+        if self.protected.get(key).is_some() {
+            return true;
+        }
+        let Some(bytes) = self.probation.pop(key) else { return false };
+        self.probation_bytes -= bytes;
+        self.protected.put(*key, bytes);
+        self.protected_bytes += bytes;
+        let max_protected = (self.max_bytes as f64 * PROTECTED_SHARE) as u64;
+        while self.protected_bytes > max_protected {
+            let Some((key, bytes)) = self.protected.pop_lru() else { break };
+            self.protected_bytes -= bytes;
+            self.probation.put(key, bytes);
+            self.probation_bytes += bytes;
+        }
+        true
+    }
+
+    /// Drops the files over the size limit, returns their keys.
+    fn evict(&mut self) -> Vec<CryptoHash> {
+        let mut evicted = vec![];
+        while self.bytes() > self.max_bytes {
+            if let Some((key, bytes)) = self.probation.pop_lru() {
+                self.probation_bytes -= bytes;
+                evicted.push(key);
+            } else if let Some((key, bytes)) = self.protected.pop_lru() {
+                self.protected_bytes -= bytes;
+                evicted.push(key);
+            } else {
+                break;
+            }
+        }
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_compiled_contract_cache_disk_usage(self.bytes(), evicted.len());
+        evicted
+    }
+}
+
+/// Lists the cached files in the directory, the oldest first. Removes the temporary files left
+/// over by writes which were interrupted.
+fn scan_cache_dir(path: &std::path::Path) -> std::io::Result<Vec<(CryptoHash, u64)>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        if name.starts_with(".tmp") {
+            let _ = std::fs::remove_file(entry.path());
+            continue;
+        }
+        let Ok(key) = name.parse::<CryptoHash>() else { continue };
+        let metadata = entry.metadata()?;
+        files.push((metadata.modified()?, key, metadata.len()));
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, key, bytes)| (key, bytes)).collect())
+}
+
+fn remove_cached_files(dir: &rustix::fd::OwnedFd, keys: &[CryptoHash]) {
+    for key in keys {
+        if let Err(err) = rustix::fs::unlinkat(dir, key.to_string(), rustix::fs::AtFlags::empty()) {
+            tracing::debug!(target: "vm", %key, ?err, "failed to remove an evicted contract executable");
+        }
+    }
+}
+
 type AnyCacheValue = dyn Any + Send;
 
 /// Cache that can store instances of any type, keyed by a CryptoHash.
@@ -387,6 +565,8 @@ type AnyCacheValue = dyn Any + Send;
 /// Used primarily for storage of artifacts on a per-VM basis.
 pub struct AnyCache {
     cache: Option<Mutex<lru::LruCache<CryptoHash, Box<AnyCacheValue>>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
 impl AnyCache {
@@ -397,6 +577,32 @@ impl AnyCache {
             } else {
                 None
             },
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of lookups which found their key in the cache so far.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups which had to generate their value so far.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Maximal number of cached entries, zero if the cache is disabled.
+    pub fn capacity(&self) -> usize {
+        self.cache.as_ref().map_or(0, |cache| cache.lock().unwrap().cap().get())
+    }
+
+    /// Changes the maximal number of cached entries, evicting the least
+    /// recently used ones if there are more.  A disabled cache stays
+    /// disabled.
+    pub fn resize(&self, size: NonZeroUsize) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().resize(size);
         }
     }
 
@@ -458,10 +664,12 @@ impl AnyCache {
         {
             let mut guard = cache.lock().unwrap();
             if let Some(cached_value) = guard.get(&key) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
                 // Same here.
                 return Ok(with(&**cached_value));
             }
         }
+        self.misses.fetch_add(1, Ordering::Relaxed);
         let generated = generate()?;
         let result = with(&*generated);
         {
@@ -582,4 +790,48 @@ mod tests {
         );
         assert!(matches!(result, Err("mikan")));
     }
+
+    #[test]
+    fn disk_index_segments() {
+        let key = |i: u8| CryptoHash::hash_bytes(&[i]);
+        let mut index = DiskIndex::new(100);
+        for i in 0..4 {
+            index.insert(key(i), 25);
+        }
+        assert!(index.evict().is_empty());
+        // Read again, the first file outlives the ones written after it.
+        assert!(index.touch(&key(0)));
+        index.insert(key(4), 25);
+        assert_eq!(index.evict(), vec![key(1)]);
+        index.insert(key(5), 50);
+        assert_eq!(index.evict(), vec![key(2), key(3)]);
+        assert!(!index.touch(&key(1)));
+        assert_eq!(index.bytes(), 100);
+    }
+
+    #[test]
+    fn filesystem_cache_limit() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let value = || CompiledContractInfo {
+            wasm_bytes: 0,
+            compiled: CompiledContract::Code(vec![0; 91]),
+        };
+        let key = |i: u8| CryptoHash::hash_bytes(&[i]);
+        let cache =
+            FilesystemContractRuntimeCache::with_limits(tempdir.path(), None::<&str>, 0, Some(250))
+                .unwrap();
+        cache.put(&key(0), value()).unwrap();
+        cache.put(&key(1), value()).unwrap();
+        assert!(cache.get(&key(0)).unwrap().is_some());
+        cache.put(&key(2), value()).unwrap();
+        assert!(cache.get(&key(1)).unwrap().is_none());
+        drop(cache);
+
+        // The files stay over restarts, and count towards the limit.
+        let cache =
+            FilesystemContractRuntimeCache::with_limits(tempdir.path(), None::<&str>, 0, Some(100))
+                .unwrap();
+        let cached = (0..3).filter(|i| cache.get(&key(*i)).unwrap().is_some()).count();
+        assert_eq!(cached, 1);
+    }
 }
@@ -23,6 +23,23 @@ use opts::*;
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub(crate) struct WasmFeatures {
     sign_extension: bool,
+    simd: bool,
+}
+
+impl WasmFeatures {
+    /// Features of the contracts prepared with `config` and run by `kind`.
+    ///
+    /// SIMD is only allowed in the VMs which can compile it.  The singlepass
+    /// backends of near-vm and wasmer2 don't generate vector code, so there the
+    /// contracts using it are rejected by preparation rather than deployed and
+    /// then failing to compile on every call.
+    pub(crate) fn new(config: &crate::logic::Config, kind: near_parameters::vm::VMKind) -> Self {
+        let version = config.limit_config.contract_prepare_version;
+        let simd = config.wasm_simd
+            && version == crate::logic::ContractPrepareVersion::V2
+            && kind == near_parameters::vm::VMKind::Wasmtime;
+        WasmFeatures { simd, ..WasmFeatures::from(version) }
+    }
 }
 
 impl From<crate::logic::ContractPrepareVersion> for WasmFeatures {
@@ -32,7 +49,7 @@ impl From<crate::logic::ContractPrepareVersion> for WasmFeatures {
             crate::logic::ContractPrepareVersion::V1 => false,
             crate::logic::ContractPrepareVersion::V2 => true,
         };
-        WasmFeatures { sign_extension }
+        WasmFeatures { sign_extension, simd: false }
     }
 }
 
@@ -43,12 +60,12 @@ impl From<WasmFeatures> for finite_wasm::wasmparser::WasmFeatures {
             floats: true,
             mutable_global: true,
             sign_extension: f.sign_extension,
+            simd: f.simd,
 
             reference_types: REFERENCE_TYPES,
             // wasmer singlepass compiler requires multi_value return values to be disabled.
             multi_value: MULTI_VALUE,
             bulk_memory: BULK_MEMORY,
-            simd: SIMD,
             threads: THREADS,
             tail_call: TAIL_CALL,
             multi_memory: MULTI_MEMORY,
@@ -104,10 +121,10 @@ impl From<WasmFeatures> for near_vm_types::Features {
         Self {
             mutable_global: true,
             sign_extension: f.sign_extension,
+            simd: f.simd,
 
             threads: THREADS,
             reference_types: REFERENCE_TYPES,
-            simd: SIMD,
             bulk_memory: BULK_MEMORY,
             multi_value: MULTI_VALUE,
             tail_call: TAIL_CALL,
@@ -152,11 +169,11 @@ impl From<WasmFeatures> for wasmer_types::Features {
 
 #[cfg(feature = "wasmtime_vm")]
 impl From<WasmFeatures> for wasmtime::Config {
-    fn from(_: WasmFeatures) -> Self {
+    fn from(f: WasmFeatures) -> Self {
         let mut config = wasmtime::Config::default();
         config.wasm_threads(THREADS);
         config.wasm_reference_types(REFERENCE_TYPES);
-        config.wasm_simd(SIMD);
+        config.wasm_simd(f.simd);
         config.wasm_bulk_memory(BULK_MEMORY);
         config.wasm_multi_value(MULTI_VALUE);
         config.wasm_multi_memory(MULTI_MEMORY);
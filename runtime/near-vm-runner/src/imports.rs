@@ -121,9 +121,11 @@ imports! {
     // # Math API #
     // ############
     random_seed<[register_id: u64] -> []>,
+    #[random_beacon] random_beacon<[register_id: u64] -> []>,
     sha256<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     keccak256<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     keccak512<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
+    #[sha3_host_functions] sha3_256<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     #[ed25519_verify] ed25519_verify<[sig_len: u64,
         sig_ptr: u64,
         msg_len: u64,
@@ -142,6 +144,7 @@ imports! {
     log_utf8<[len: u64, ptr: u64] -> []>,
     log_utf16<[len: u64, ptr: u64] -> []>,
     abort<[msg_ptr: u32, filename_ptr: u32, line: u32, col: u32] -> []>,
+    #[structured_events] emit_event<[topics_len: u64, topics_ptr: u64, data_len: u64, data_ptr: u64] -> []>,
     // ################
     // # Promises API #
     // ################
@@ -274,6 +277,7 @@ imports! {
     #[alt_bn128] alt_bn128_g1_multiexp<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     #[alt_bn128] alt_bn128_g1_sum<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     #[alt_bn128] alt_bn128_pairing_check<[value_len: u64, value_ptr: u64] -> [u64]>,
+    #[groth16_verify] alt_bn128_groth16_verify<[value_len: u64, value_ptr: u64] -> [u64]>,
     // #############
     // # BLS12-381 #
     // #############
@@ -291,6 +295,7 @@ imports! {
     // #  Sandbox  #
     // #############
     ##["sandbox"] sandbox_debug_log<[len: u64, ptr: u64] -> []>,
+    ##["sandbox"] sandbox_storage_proof_size<[] -> [u64]>,
 
     // Sleep for the given number of nanoseconds. This is the ultimate
     // undercharging function as it doesn't consume much gas or computes but
@@ -314,6 +319,13 @@ pub(crate) const fn should_trace_host_function(host_function: &str) -> bool {
     }
 }
 
+/// Whether the calls of the host function are recorded when the host calls
+/// of an execution are traced. The internal and gas metering functions would
+/// only drown the calls made by the contract itself.
+pub(crate) const fn should_record_host_call(module: &str, host_function: &str) -> bool {
+    str_eq(module, "env") && should_trace_host_function(host_function)
+}
+
 /// Constant-time string equality, work-around for `"foo" == "bar"` not working
 /// in const context yet.
 const fn str_eq(s1: &str, s2: &str) -> bool {
@@ -11,6 +11,7 @@ mod instrument;
 pub mod logic;
 #[cfg(feature = "metrics")]
 mod metrics;
+mod native_contracts;
 #[cfg(all(feature = "near_vm", target_arch = "x86_64"))]
 mod near_vm_runner;
 #[cfg(feature = "prepare")]
@@ -29,7 +30,7 @@ mod wasmtime_runner;
 
 pub use crate::logic::with_ext_cost_counter;
 pub use cache::{
-    get_contract_cache_key, precompile_contract, CompiledContract, CompiledContractInfo,
+    get_contract_cache_key, precompile_contract, AnyCache, CompiledContract, CompiledContractInfo,
     ContractRuntimeCache, FilesystemContractRuntimeCache, MockContractRuntimeCache,
     NoContractRuntimeCache,
 };
@@ -92,6 +92,57 @@ pub(super) fn pairing_check(
     Ok(res)
 }
 
+/// Verification key `(alpha, beta, gamma, delta, ic_0)` followed by the proof
+/// `(a, b, c)`.
+const GROTH16_HEADER_SIZE: usize = 4 * POINT_SIZE + 4 * 2 * POINT_SIZE;
+/// Public input `input_i` with its verification key point `ic_i`, encoded the
+/// same way as a multiexp element.
+const GROTH16_ELEMENT_SIZE: usize = G1_MULTIEXP_ELEMENT_SIZE;
+
+pub(super) fn split_groth16_input(
+    data: &[u8],
+) -> Result<(&[u8; GROTH16_HEADER_SIZE], &[[u8; GROTH16_ELEMENT_SIZE]]), InvalidInput> {
+    if data.len() < GROTH16_HEADER_SIZE {
+        return Err(InvalidInput::new("groth16 input too short", data));
+    }
+    let (header, elements) = data.split_at(GROTH16_HEADER_SIZE);
+    Ok((header.try_into().unwrap(), split_elements(elements)?))
+}
+
+pub(super) fn groth16_verify(
+    header: &[u8; GROTH16_HEADER_SIZE],
+    elements: &[[u8; GROTH16_ELEMENT_SIZE]],
+) -> Result<bool, InvalidInput> {
+    let mut header = &header[..];
+    let alpha = decode_g1(read(&mut header))?;
+    let beta = decode_g2(read(&mut header))?;
+    let gamma = decode_g2(read(&mut header))?;
+    let delta = decode_g2(read(&mut header))?;
+    let ic0 = decode_g1(read(&mut header))?;
+    let a = decode_g1(read(&mut header))?;
+    let b = decode_g2(read(&mut header))?;
+    let c = decode_g1(read(&mut header))?;
+
+    let mut terms = vec![(ic0, bn::Fr::one())];
+    for chunk in elements {
+        let (ic, input) = stdx::split_array(chunk);
+        terms.push((decode_g1(ic)?, decode_fr(input)?));
+    }
+    let vk_x = bn::G1::multiexp(&terms);
+
+    // e(a, b) = e(alpha, beta) * e(vk_x, gamma) * e(c, delta)
+    let res =
+        bn::pairing_batch(&[(a, b), (-alpha, beta), (-vk_x, gamma), (-c, delta)]) == bn::Gt::one();
+
+    Ok(res)
+}
+
+fn read<'a, const N: usize>(data: &mut &'a [u8]) -> &'a [u8; N] {
+    let (head, rest) = data.split_at(N);
+    *data = rest;
+    head.try_into().unwrap()
+}
+
 fn encode_g1(val: bn::G1) -> [u8; POINT_SIZE] {
     let (x, y) = bn::AffineG1::from_jacobian(val)
         .map(|p| (p.x(), p.y()))
@@ -47,6 +47,9 @@ pub struct VMContext {
     pub prepaid_gas: Gas,
     /// Initial seed for randomness
     pub random_seed: Vec<u8>,
+    /// Randomness of the receipt, see `VMLogic::random_beacon`. `None` in view calls and for
+    /// receipts created in the block they are executed in.
+    pub random_beacon: Option<Vec<u8>>,
     /// If Some, it means that execution is made in a view mode and defines its configuration.
     /// View mode means that only read-only operations are allowed.
     /// See <https://nomicon.io/Proposals/0018-view-change-method.html> for more details.
@@ -54,6 +57,9 @@ pub struct VMContext {
     /// How many `DataReceipt`'s should receive this execution result. This should be empty if
     /// this function call is a part of a batch and it is not the last action.
     pub output_data_receivers: Vec<AccountId>,
+    /// Whether to record the host function calls made by the contract in
+    /// `VMOutcome::host_calls`.
+    pub trace_host_calls: bool,
 }
 
 impl VMContext {
@@ -276,6 +276,13 @@ pub enum HostError {
     RecordedStorageExceeded {
         limit: ByteSize,
     },
+    /// The topics of an event are not a whole number of hashes, or more than the limit.
+    InvalidEventTopics {
+        length: u64,
+        limit: u64,
+    },
+    /// The receipt was created in the block it is executed in, so it has no random beacon.
+    RandomBeaconUnavailable,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -519,6 +526,14 @@ impl std::fmt::Display for HostError {
                 "Size of the recorded trie storage proof has exceeded the allowed limit ({})",
                 limit
             ),
+            InvalidEventTopics { length, limit } => write!(
+                f,
+                "Event topics of {length} bytes are not at most {limit} hashes of 32 bytes"
+            ),
+            RandomBeaconUnavailable => write!(
+                f,
+                "random_beacon is not available to receipts created in the block they are executed in"
+            ),
         }
     }
 }
@@ -193,13 +193,6 @@ impl GasCounter {
     /// structure into consideration could be added. But since that would have
     /// to happen after loading, we cannot pre-charge it. This is the main
     /// motivation to (only) have this simple fee.
-    #[cfg(any(
-        feature = "wasmtime_vm",
-        all(
-            target_arch = "x86_64",
-            any(feature = "wasmer0_vm", feature = "wasmer2_vm", feature = "near_vm")
-        )
-    ))]
     pub(crate) fn add_contract_loading_fee(&mut self, code_len: u64) -> Result<()> {
         self.pay_per(ExtCosts::contract_loading_bytes, code_len)?;
         self.pay_base(ExtCosts::contract_loading_base)
@@ -210,13 +203,6 @@ impl GasCounter {
     /// Does VM independent checks that happen after the instantiation of
     /// VMLogic but before loading the executable. This includes pre-charging gas
     /// costs for loading the executable, which depends on the size of the WASM code.
-    #[cfg(any(
-        feature = "wasmtime_vm",
-        all(
-            target_arch = "x86_64",
-            any(feature = "wasmer0_vm", feature = "wasmer2_vm", feature = "near_vm")
-        )
-    ))]
     pub(crate) fn before_loading_executable(
         &mut self,
         config: &near_parameters::vm::Config,
@@ -3,7 +3,9 @@ use super::dependencies::{External, MemSlice, MemoryLike};
 use super::errors::{FunctionCallError, InconsistentStateError};
 use super::gas_counter::GasCounter;
 use super::recorded_storage_counter::RecordedStorageCounter;
-use super::types::{PromiseIndex, PromiseResult, ReceiptIndex, ReturnData};
+use super::types::{
+    ContractEvent, PromiseIndex, PromiseResult, ReceiptIndex, ReturnData, MAX_EVENT_TOPICS,
+};
 use super::utils::split_method_names;
 use super::ValuePtr;
 use super::{HostError, VMLogicError};
@@ -41,7 +43,9 @@ pub struct ExecutionResultState {
     gas_counter: GasCounter,
     /// Logs written by the runtime.
     logs: Vec<String>,
-    /// Tracks the total log length. The sum of length of all logs.
+    /// Events emitted with `emit_event`.
+    events: Vec<ContractEvent>,
+    /// Tracks the total log length. The sum of length of all logs and events.
     total_log_length: u64,
     /// What method returns.
     return_data: ReturnData,
@@ -50,6 +54,8 @@ pub struct ExecutionResultState {
     current_account_balance: Balance,
     /// Storage usage of the current account at the moment
     current_storage_usage: StorageUsage,
+    /// Host function calls made by the contract, if they are traced.
+    host_calls: Option<HostCallTrace>,
 }
 
 impl ExecutionResultState {
@@ -69,10 +75,12 @@ impl ExecutionResultState {
             config,
             gas_counter,
             logs: vec![],
+            events: vec![],
             total_log_length: 0,
             return_data: ReturnData::None,
             current_account_balance,
             current_storage_usage,
+            host_calls: context.trace_host_calls.then(HostCallTrace::default),
         }
     }
 
@@ -88,8 +96,11 @@ impl ExecutionResultState {
     }
 
     /// Checks that the current log number didn't reach the limit yet, so we can add a new message.
+    ///
+    /// Events count as logs.
     fn check_can_add_a_log_message(&self) -> Result<()> {
-        if self.logs.len() as u64 >= self.config.limit_config.max_number_logs {
+        let number_of_logs = self.logs.len().saturating_add(self.events.len());
+        if number_of_logs as u64 >= self.config.limit_config.max_number_logs {
             Err(HostError::NumberOfLogsExceeded { limit: self.config.limit_config.max_number_logs }
                 .into())
         } else {
@@ -98,7 +109,20 @@ impl ExecutionResultState {
     }
 
     fn checked_push_log(&mut self, message: String) -> Result<()> {
-        let len = u64::try_from(message.len()).unwrap_or(u64::MAX);
+        self.add_total_log_length(message.len())?;
+        self.logs.push(message);
+        Ok(())
+    }
+
+    fn checked_push_event(&mut self, event: ContractEvent) -> Result<()> {
+        let len = (event.topics.len() * size_of::<CryptoHash>()).saturating_add(event.data.len());
+        self.add_total_log_length(len)?;
+        self.events.push(event);
+        Ok(())
+    }
+
+    fn add_total_log_length(&mut self, len: usize) -> Result<()> {
+        let len = u64::try_from(len).unwrap_or(u64::MAX);
         let Some(total_log_length) = self.total_log_length.checked_add(len) else {
             return self.total_log_length_exceeded(len);
         };
@@ -106,7 +130,6 @@ impl ExecutionResultState {
         if self.total_log_length > self.config.limit_config.max_total_log_length {
             return self.total_log_length_exceeded(len);
         }
-        self.logs.push(message);
         Ok(())
     }
 
@@ -139,8 +162,10 @@ impl ExecutionResultState {
             used_gas,
             compute_usage,
             logs: self.logs,
+            events: self.events,
             profile,
             aborted: None,
+            host_calls: self.host_calls,
         }
     }
 }
@@ -263,6 +288,39 @@ impl<'a> VMLogic<'a> {
         }
     }
 
+    /// Makes the host function call `name` with `args`, recording it if the
+    /// host function calls of this execution are traced.
+    pub(crate) fn trace_host_call<T>(
+        &mut self,
+        name: &'static str,
+        args: &[u64],
+        call: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        if self.result_state.host_calls.is_none() {
+            return call(self);
+        }
+        let burnt_gas_before = self.result_state.gas_counter.burnt_gas();
+        let used_gas_before = self.result_state.gas_counter.used_gas();
+        let result = call(self);
+        let host_call = HostCall {
+            name,
+            args: args.to_vec(),
+            burnt_gas_before,
+            burnt_gas_after: self.result_state.gas_counter.burnt_gas(),
+            used_gas_before,
+            used_gas_after: self.result_state.gas_counter.used_gas(),
+            failed: result.is_err(),
+        };
+        if let Some(trace) = &mut self.result_state.host_calls {
+            if trace.calls.len() < MAX_TRACED_HOST_CALLS {
+                trace.calls.push(host_call);
+            } else {
+                trace.truncated = true;
+            }
+        }
+        result
+    }
+
     /// Returns reference to logs that have been created so far.
     pub fn logs(&self) -> &[String] {
         &self.result_state.logs
@@ -323,6 +381,34 @@ impl<'a> VMLogic<'a> {
         )
     }
 
+    /// Returns the content of the register `register_id` to a native contract, which has no guest
+    /// memory to copy it into.
+    ///
+    /// # Cost
+    ///
+    /// `base + read_register_base + read_register_byte * num_bytes`
+    pub fn native_read_register(&mut self, register_id: u64) -> Result<Vec<u8>> {
+        self.result_state.gas_counter.pay_base(base)?;
+        let data = self.registers.get(&mut self.result_state.gas_counter, register_id)?;
+        Ok(data.to_vec())
+    }
+
+    /// Writes `data` of a native contract into the register `register_id`, so that it can be
+    /// passed to the host functions which accept a register in place of a guest memory pointer.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes`
+    pub fn native_write_register(&mut self, register_id: u64, data: &[u8]) -> Result<()> {
+        self.result_state.gas_counter.pay_base(base)?;
+        self.registers.set(
+            &mut self.result_state.gas_counter,
+            &self.config.limit_config,
+            register_id,
+            data,
+        )
+    }
+
     /// Writes the entire content from the register `register_id` into the memory of the guest starting with `ptr`.
     ///
     /// # Arguments
@@ -987,6 +1073,48 @@ impl<'a> VMLogic<'a> {
         Ok(res as u64)
     }
 
+    /// Verifies a Groth16 proof on alt_bn128 curve, checking
+    /// e(a, b) = e(alpha, beta) * e(vk_x, gamma) * e(c, delta),
+    /// where vk_x = ic_0 + \sum_i input_i * ic_i. Returns 1 if the proof is valid
+    /// and 0 otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - verification key `(alpha:G1, beta:G2, gamma:G2, delta:G2, ic_0:G1)`,
+    ///   then proof `(a:G1, b:G2, c:G1)`, then sequence of `(ic_i:G1, input_i:Fr)`,
+    ///   one per public input. Points and scalars are encoded the same way as in
+    ///   `alt_bn128_g1_multiexp` and `alt_bn128_pairing_check`.
+    ///
+    ///   `value` is encoded a as packed, little-endian
+    ///   `(G1, G2, G2, G2, G1, G1, G2, G1, [(G1, u256)])` tuple.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit the function returns `MemoryAccessViolation`.
+    ///
+    /// If point coordinates are not on curve, point is not in the subgroup, scalar
+    /// is not in the field or data are wrong serialized, for example,
+    /// `value.len() < 768` or `(value.len() - 768)%96!=0`, the function returns
+    /// `AltBn128InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + read_memory_base + read_memory_byte * num_bytes + alt_bn128_groth16_verify_base + alt_bn128_groth16_verify_element * num_inputs`
+    pub fn alt_bn128_groth16_verify(&mut self, value_len: u64, value_ptr: u64) -> Result<u64> {
+        self.result_state.gas_counter.pay_base(alt_bn128_groth16_verify_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let (header, elements) = super::alt_bn128::split_groth16_input(&data)?;
+        self.result_state
+            .gas_counter
+            .pay_per(alt_bn128_groth16_verify_element, elements.len() as u64)?;
+
+        let res = super::alt_bn128::groth16_verify(header, elements)?;
+
+        Ok(res as u64)
+    }
+
     bls12381_impl!(
         r"Calculates the sum of signed elements on the BLS12-381 curve.
 It accepts an arbitrary number of pairs (sign_i, p_i),
@@ -1409,6 +1537,58 @@ bls12381_p2_decompress_base + bls12381_p2_decompress_element * num_elements`
         )
     }
 
+    /// Writes the 32 bytes of randomness of the current receipt into the register.
+    ///
+    /// The value is the hash of the receipt id and of the random value of the block the receipt
+    /// is executed in, which is the hash of the VRF output of the block producer over the random
+    /// value of the previous block. It is the same for all the actions of a receipt, while
+    /// `random_seed` differs per action.
+    ///
+    /// The beacon is only available to receipts created in an earlier block. Their content, and so
+    /// anything the contract fixed in them, was committed before the random value of the block
+    /// they run in could be computed by anybody but its block producer. Receipts created in the
+    /// block they run in, that is the receipts of transactions an account signs to itself, may be
+    /// included by the producers of the block knowing the value, and get `RandomBeaconUnavailable`
+    /// instead. A contract should therefore record a bet in one receipt and read the beacon in a
+    /// promise it creates, for instance a callback to itself.
+    ///
+    /// # Security
+    ///
+    /// * The block producer cannot choose the value: the VRF output is unique for its key and the
+    ///   previous random value, and anybody can check it with the proof in the block. Its only
+    ///   choice is to not produce the block at all, losing the reward of the block, so that the
+    ///   receipt runs in a later block with another value. A contract paying out more than a block
+    ///   reward on a single outcome must account for that.
+    /// * The value is public once the block is published, so it must not be used as a secret.
+    ///
+    /// # Errors
+    ///
+    /// * If the registers exceed the memory limit returns `MemoryAccessViolation`.
+    /// * If called as view function returns `ProhibitedInView`.
+    /// * If the receipt was created in the current block returns `RandomBeaconUnavailable`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes`
+    pub fn random_beacon(&mut self, register_id: u64) -> Result<()> {
+        self.result_state.gas_counter.pay_base(base)?;
+
+        if self.context.is_view() {
+            return Err(
+                HostError::ProhibitedInView { method_name: "random_beacon".to_string() }.into()
+            );
+        }
+        let Some(random_beacon) = &self.context.random_beacon else {
+            return Err(HostError::RandomBeaconUnavailable.into());
+        };
+        self.registers.set(
+            &mut self.result_state.gas_counter,
+            &self.config.limit_config,
+            register_id,
+            random_beacon.as_slice(),
+        )
+    }
+
     /// Hashes the given value using sha256 and returns it into `register_id`.
     ///
     /// # Errors
@@ -1487,6 +1667,35 @@ bls12381_p2_decompress_base + bls12381_p2_decompress_element * num_elements`
         )
     }
 
+    /// Hashes the given value using SHA3-256 and returns it into `register_id`.
+    ///
+    /// Unlike `keccak256`, this is the hash standardized as FIPS 202, which differs from the
+    /// Keccak-256 used by Ethereum in the padding.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers use more memory than
+    /// the limit with `MemoryAccessViolation`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes + sha3_256_base + sha3_256_byte * num_bytes`
+    pub fn sha3_256(&mut self, value_len: u64, value_ptr: u64, register_id: u64) -> Result<()> {
+        self.result_state.gas_counter.pay_base(sha3_256_base)?;
+        let value = get_memory_or_register!(self, value_ptr, value_len)?;
+        self.result_state.gas_counter.pay_per(sha3_256_byte, value.len() as u64)?;
+
+        use sha3::Digest;
+
+        let value_hash = sha3::Sha3_256::digest(&value);
+        self.registers.set(
+            &mut self.result_state.gas_counter,
+            &self.config.limit_config,
+            register_id,
+            value_hash.as_slice(),
+        )
+    }
+
     /// Hashes the given value using RIPEMD-160 and returns it into `register_id`.
     ///
     /// # Errors
@@ -2969,6 +3178,57 @@ bls12381_p2_decompress_base + bls12381_p2_decompress_element * num_elements`
         Err(HostError::GuestPanic { panic_msg: message }.into())
     }
 
+    /// Emits an event indexed by the topics at `topics_ptr`, with the payload at `data_ptr`.
+    ///
+    /// The topics are 32-byte hashes laid out one after the other, so `topics_len` is 32 times
+    /// their number. Unlike logs, events are not parsed by anyone but their readers: they are
+    /// recorded as they are in the execution outcome, and their topics in the bloom filter of
+    /// the chunk. Events count towards the limits of logs.
+    ///
+    /// # Errors
+    ///
+    /// * If the topics or the data extend outside the memory of the guest with
+    ///   `MemoryAccessViolation`;
+    /// * If the length of the topics is not a multiple of 32 or there are more than
+    ///   `MAX_EVENT_TOPICS` topics returns `InvalidEventTopics`.
+    /// * If number of bytes read + `total_log_length` exceeds the `max_total_log_length` returns
+    ///   `TotalLogLengthExceeded`.
+    /// * If the total number of logs and events will exceed the `max_number_logs` returns
+    ///   `NumberOfLogsExceeded`.
+    ///
+    /// # Cost
+    ///
+    /// `base + log_base + log_byte * (topics_len + data_len) + read memory cost`
+    pub fn emit_event(
+        &mut self,
+        topics_len: u64,
+        topics_ptr: u64,
+        data_len: u64,
+        data_ptr: u64,
+    ) -> Result<()> {
+        self.result_state.gas_counter.pay_base(base)?;
+        self.result_state.check_can_add_a_log_message()?;
+        let topics = get_memory_or_register!(self, topics_ptr, topics_len)?;
+        if topics.len() % size_of::<CryptoHash>() != 0
+            || topics.len() / size_of::<CryptoHash>() > MAX_EVENT_TOPICS
+        {
+            return Err(HostError::InvalidEventTopics {
+                length: topics.len() as u64,
+                limit: MAX_EVENT_TOPICS as u64,
+            }
+            .into());
+        }
+        let topics = topics
+            .chunks_exact(size_of::<CryptoHash>())
+            .map(|topic| CryptoHash::try_from(topic).expect("topics have the length of a hash"))
+            .collect::<Vec<_>>();
+        let data = get_memory_or_register!(self, data_ptr, data_len)?.into_owned();
+        self.result_state.gas_counter.pay_base(log_base)?;
+        let len = (topics.len() * size_of::<CryptoHash>()).saturating_add(data.len());
+        self.result_state.gas_counter.pay_per(log_byte, len as u64)?;
+        self.result_state.checked_push_event(ContractEvent { topics, data })
+    }
+
     // ###############
     // # Storage API #
     // ###############
@@ -3370,6 +3630,21 @@ bls12381_p2_decompress_base + bls12381_p2_decompress_element * num_elements`
         Ok(())
     }
 
+    /// Returns the size of the storage proof recorded so far by the receipt,
+    /// which is what `per_receipt_storage_proof_size_limit` is checked
+    /// against.  Lets contract developers measure how much their methods add
+    /// to the state witness before deploying them.
+    ///
+    /// # Cost
+    ///
+    /// 0
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_storage_proof_size(&mut self) -> Result<u64> {
+        self.recorded_storage_counter.observe_size(self.ext.get_recorded_storage_size())?;
+        let size = self.recorded_storage_counter.get_storage_size()?;
+        Ok(u64::try_from(size).map_err(|_| InconsistentStateError::IntegerOverflow)?)
+    }
+
     /// DEPRECATED
     /// Creates an iterator object inside the host. Returns the identifier that uniquely
     /// differentiates the given iterator from other iterators that can be simultaneously created.
@@ -3508,6 +3783,32 @@ bls12381_p2_decompress_base + bls12381_p2_decompress_element * num_elements`
     }
 }
 
+/// Maximum number of host function calls recorded in a trace.
+const MAX_TRACED_HOST_CALLS: usize = 100_000;
+
+/// A host function call made by a contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostCall {
+    pub name: &'static str,
+    /// Arguments of the call, mostly pointers to the guest memory and lengths
+    /// of the data there.
+    pub args: Vec<u64>,
+    pub burnt_gas_before: Gas,
+    pub burnt_gas_after: Gas,
+    pub used_gas_before: Gas,
+    pub used_gas_after: Gas,
+    /// Whether the call returned an error, usually aborting the execution.
+    pub failed: bool,
+}
+
+/// Host function calls made by a contract during its execution, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostCallTrace {
+    pub calls: Vec<HostCall>,
+    /// Whether the calls after the first `MAX_TRACED_HOST_CALLS` are missing.
+    pub truncated: bool,
+}
+
 #[derive(PartialEq)]
 pub struct VMOutcome {
     pub balance: Balance,
@@ -3517,9 +3818,13 @@ pub struct VMOutcome {
     pub used_gas: Gas,
     pub compute_usage: Compute,
     pub logs: Vec<String>,
+    /// Events emitted with `emit_event`.
+    pub events: Vec<ContractEvent>,
     /// Data collected from making a contract call
     pub profile: ProfileDataV3,
     pub aborted: Option<FunctionCallError>,
+    /// Host function calls made by the contract, if `VMContext::trace_host_calls` is set.
+    pub host_calls: Option<HostCallTrace>,
 }
 
 impl VMOutcome {
@@ -3550,8 +3855,10 @@ impl VMOutcome {
             used_gas: 0,
             compute_usage: 0,
             logs: Vec::new(),
+            events: Vec::new(),
             profile: ProfileDataV3::default(),
             aborted: Some(error),
+            host_calls: None,
         }
     }
 
@@ -18,7 +18,7 @@ pub use context::VMContext;
 pub use dependencies::{External, MemSlice, MemoryLike, TrieNodesCount, ValuePtr};
 pub use errors::{HostError, VMLogicError};
 pub use gas_counter::{with_ext_cost_counter, GasCounter};
-pub use logic::{ExecutionResultState, VMLogic, VMOutcome};
+pub use logic::{ExecutionResultState, HostCall, HostCallTrace, VMLogic, VMOutcome};
 pub use near_parameters::vm::{Config, ContractPrepareVersion, LimitConfig, StorageGetMode};
 pub use near_primitives_core::types::ProtocolVersion;
-pub use types::ReturnData;
+pub use types::{ContractEvent, ReturnData, MAX_EVENT_TOPICS};
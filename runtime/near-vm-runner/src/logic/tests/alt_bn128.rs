@@ -268,3 +268,78 @@ fn test_alt_bn128_pairing_check() {
     check_err(b"XXXX", "slice of size 4 cannot be precisely split into chunks of size 192");
     check_err(&le_bytes![0x0 0x0  0x0 0x0 0x0 0x0, 0x0 0x0  0x0 0x0 0x0 0x111], "invalid g2");
 }
+
+#[test]
+fn test_alt_bn128_groth16_verify() {
+    fn fr(s: &str) -> bn::Fr {
+        bn::Fr::from_str(s).unwrap()
+    }
+    fn push_u256(buf: &mut Vec<u8>, val: bn::arith::U256) {
+        let [lo, hi] = val.0;
+        buf.extend(lo.to_le_bytes());
+        buf.extend(hi.to_le_bytes());
+    }
+    fn push_g1(buf: &mut Vec<u8>, p: bn::G1) {
+        let p = bn::AffineG1::from_jacobian(p).unwrap();
+        push_u256(buf, p.x().into_u256());
+        push_u256(buf, p.y().into_u256());
+    }
+    fn push_g2(buf: &mut Vec<u8>, p: bn::G2) {
+        let p = bn::AffineG2::from_jacobian(p).unwrap();
+        for c in [p.x(), p.y()] {
+            push_u256(buf, c.real().into_u256());
+            push_u256(buf, c.imaginary().into_u256());
+        }
+    }
+    #[track_caller]
+    fn check(input: &[u8], expected: Result<u64, &str>) {
+        let mut logic_builder = VMLogicBuilder::default();
+        let mut logic = logic_builder.build();
+        let input = logic.internal_mem_write(input);
+
+        let res = logic.alt_bn128_groth16_verify(input.len, input.ptr);
+        if let Some((res, expected)) = check_result(res, expected) {
+            assert_eq!(res, expected)
+        }
+    }
+
+    // A proof for the verification key made up of known discrete logarithms,
+    // with `a` chosen so that the verification equation holds.
+    let (alpha, beta, gamma, delta) = (fr("11"), fr("12"), fr("13"), fr("14"));
+    let (ic0, ic1, ic2) = (fr("21"), fr("22"), fr("23"));
+    let (b, c) = (fr("31"), fr("32"));
+    let proof = |inputs: [bn::Fr; 2]| {
+        let vk_x = ic0 + inputs[0] * ic1 + inputs[1] * ic2;
+        let a = (alpha * beta + vk_x * gamma + c * delta) * b.inverse().unwrap();
+        let (g1, g2) = (bn::G1::one(), bn::G2::one());
+        let mut buf = Vec::new();
+        push_g1(&mut buf, g1 * alpha);
+        push_g2(&mut buf, g2 * beta);
+        push_g2(&mut buf, g2 * gamma);
+        push_g2(&mut buf, g2 * delta);
+        push_g1(&mut buf, g1 * ic0);
+        push_g1(&mut buf, g1 * a);
+        push_g2(&mut buf, g2 * b);
+        push_g1(&mut buf, g1 * c);
+        buf
+    };
+    let with_inputs = |mut buf: Vec<u8>, inputs: [bn::Fr; 2]| {
+        for (ic, input) in [(ic1, inputs[0]), (ic2, inputs[1])] {
+            push_g1(&mut buf, bn::G1::one() * ic);
+            push_u256(&mut buf, input.into_u256());
+        }
+        buf
+    };
+
+    let inputs = [fr("41"), fr("42")];
+    check(&with_inputs(proof(inputs), inputs), Ok(1));
+    // The proof doesn't hold for other public inputs.
+    check(&with_inputs(proof(inputs), [fr("41"), fr("43")]), Ok(0));
+
+    let valid = with_inputs(proof(inputs), inputs);
+    check(&valid[..767], Err("groth16 input too short"));
+    check(&valid[..valid.len() - 1], Err("cannot be precisely split into chunks of size 96"));
+    let mut invalid_fr = valid.clone();
+    invalid_fr[valid.len() - 32..].fill(0xff);
+    check(&invalid_fr, Err("invalid fr"));
+}
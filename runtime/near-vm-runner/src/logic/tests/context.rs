@@ -1,3 +1,4 @@
+use crate::logic::HostError;
 use crate::{logic::tests::vm_logic_builder::VMLogicBuilder, tests::test_vm_config};
 use near_primitives_core::config::ViewConfig;
 
@@ -62,6 +63,7 @@ decl_test_bytes!(
 decl_test_bytes!(test_signer_account_pk, signer_account_pk, ctx, ctx.signer_account_pk);
 
 decl_test_bytes!(test_random_seed, random_seed, ctx, ctx.random_seed);
+decl_test_bytes!(test_random_beacon, random_beacon, ctx, ctx.random_beacon.as_ref().unwrap());
 
 decl_test_bytes!(test_input, input, ctx, ctx.input);
 
@@ -109,3 +111,11 @@ fn test_attached_deposit_view() {
     test_view(1);
     test_view(u128::MAX);
 }
+
+#[test]
+fn test_random_beacon_unavailable() {
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.context.random_beacon = None;
+    let mut logic = logic_builder.build();
+    assert_eq!(logic.random_beacon(0), Err(HostError::RandomBeaconUnavailable.into()));
+}
@@ -1,9 +1,10 @@
 use crate::logic::tests::helpers::*;
 use crate::logic::tests::vm_logic_builder::VMLogicBuilder;
-use crate::logic::HostError;
+use crate::logic::{ContractEvent, HostError, MAX_EVENT_TOPICS};
 use crate::logic::{MemSlice, VMLogic, VMLogicError};
 use crate::map;
 use near_parameters::ExtCosts;
+use near_primitives_core::hash::{hash, CryptoHash};
 
 #[test]
 fn test_valid_utf8() {
@@ -145,6 +146,75 @@ fn test_log_number_limit() {
     assert_eq!(outcome.logs.len() as u64, max_number_logs);
 }
 
+#[test]
+fn test_emit_event() {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut logic = logic_builder.build();
+    let topics = [hash(b"Transfer"), hash(b"alice.near")];
+    let topics_bytes = topics.iter().flat_map(|topic| topic.0).collect::<Vec<_>>();
+    let topics_slice = logic.internal_mem_write(&topics_bytes);
+    let data = logic.internal_mem_write(b"{\"amount\":\"10\"}");
+    logic.emit_event(topics_slice.len, topics_slice.ptr, data.len, data.ptr).unwrap();
+
+    let outcome = logic.compute_outcome();
+    assert_eq!(
+        outcome.events,
+        vec![ContractEvent { topics: topics.to_vec(), data: b"{\"amount\":\"10\"}".to_vec() }]
+    );
+    assert!(outcome.logs.is_empty());
+    assert_costs(map! {
+        ExtCosts::base: 1,
+        ExtCosts::log_base: 1,
+        ExtCosts::log_byte: topics_slice.len + data.len,
+        ExtCosts::read_memory_base: 2,
+        ExtCosts::read_memory_byte: topics_slice.len + data.len,
+    });
+}
+
+#[test]
+fn test_emit_event_invalid_topics() {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut logic = logic_builder.build();
+    let data = logic.internal_mem_write(b"data");
+
+    let topics = logic.internal_mem_write(&[0; 33]);
+    assert_eq!(
+        logic.emit_event(topics.len, topics.ptr, data.len, data.ptr),
+        Err(HostError::InvalidEventTopics { length: 33, limit: MAX_EVENT_TOPICS as u64 }.into())
+    );
+
+    let too_many = vec![0; (MAX_EVENT_TOPICS + 1) * CryptoHash::LENGTH];
+    let topics = logic.internal_mem_write(&too_many);
+    assert_eq!(
+        logic.emit_event(topics.len, topics.ptr, data.len, data.ptr),
+        Err(HostError::InvalidEventTopics { length: topics.len, limit: MAX_EVENT_TOPICS as u64 }
+            .into())
+    );
+
+    // No topics at all is fine.
+    logic.emit_event(0, 0, data.len, data.ptr).unwrap();
+    let outcome = logic.compute_outcome();
+    assert_eq!(outcome.events, vec![ContractEvent { topics: vec![], data: b"data".to_vec() }]);
+}
+
+#[test]
+fn test_emit_event_counts_as_log() {
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.config.limit_config.max_number_logs = 2;
+    let mut logic = logic_builder.build();
+    let bytes = logic.internal_mem_write(b"blabla");
+    logic.log_utf8(bytes.len, bytes.ptr).unwrap();
+    logic.emit_event(0, 0, bytes.len, bytes.ptr).unwrap();
+    assert_eq!(
+        logic.emit_event(0, 0, bytes.len, bytes.ptr),
+        Err(HostError::NumberOfLogsExceeded { limit: 2 }.into())
+    );
+
+    let outcome = logic.compute_outcome();
+    assert_eq!(outcome.logs.len(), 1);
+    assert_eq!(outcome.events.len(), 1);
+}
+
 fn append_utf16(dst: &mut Vec<u8>, string: &str) {
     for code_unit in string.encode_utf16() {
         dst.extend_from_slice(&code_unit.to_le_bytes());
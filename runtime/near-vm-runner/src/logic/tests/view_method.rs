@@ -15,6 +15,7 @@ fn test_prohibited_view_methods() {
     test_prohibited!(signer_account_id, 0);
     test_prohibited!(signer_account_pk, 0);
     test_prohibited!(predecessor_account_id, 0);
+    test_prohibited!(random_beacon, 0);
     test_prohibited!(prepaid_gas);
     test_prohibited!(used_gas);
     test_prohibited!(promise_create, 0, 0, 0, 0, 0, 0, 0, 0);
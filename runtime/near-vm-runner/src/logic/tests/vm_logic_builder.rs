@@ -81,8 +81,10 @@ fn get_context() -> VMContext {
         attached_deposit: 10,
         prepaid_gas: 10u64.pow(14),
         random_seed: vec![0, 1, 2],
+        random_beacon: Some(vec![3, 4, 5]),
         view_config: None,
         output_data_receivers: vec![],
+        trace_host_calls: false,
     }
 }
 
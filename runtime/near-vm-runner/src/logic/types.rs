@@ -1,3 +1,4 @@
+use near_primitives_core::hash::CryptoHash;
 pub use near_primitives_core::types::*;
 
 pub type PublicKey = Vec<u8>;
@@ -28,6 +29,18 @@ impl ReturnData {
     }
 }
 
+/// The maximum number of topics of an event emitted with `emit_event`.
+pub const MAX_EVENT_TOPICS: usize = 4;
+
+/// An event emitted by a contract with `emit_event`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ContractEvent {
+    /// Hashes the event is indexed by, at most [`MAX_EVENT_TOPICS`] of them.
+    pub topics: Vec<CryptoHash>,
+    /// Payload of the event, opaque to the protocol.
+    pub data: Vec<u8>,
+}
+
 /// When there is a callback attached to one or more contract calls the execution results of these
 /// calls are available to the contract invoked through the callback.
 #[derive(Debug, PartialEq)]
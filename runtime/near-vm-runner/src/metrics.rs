@@ -1,5 +1,6 @@
 use near_o11y::metrics::{
-    try_create_histogram_vec, try_create_int_counter_vec, HistogramVec, IntCounterVec,
+    try_create_histogram_vec, try_create_int_counter, try_create_int_counter_vec,
+    try_create_int_gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge,
 };
 use std::sync::LazyLock;
 use std::{cell::RefCell, time::Duration};
@@ -41,6 +42,22 @@ static COMPILED_CONTRACT_CACHE_HITS_TOTAL: LazyLock<IntCounterVec> = LazyLock::n
     .unwrap()
 });
 
+static COMPILED_CONTRACT_CACHE_DISK_BYTES: LazyLock<IntGauge> = LazyLock::new(|| {
+    try_create_int_gauge(
+        "near_vm_compiled_contract_cache_disk_bytes",
+        "Size of the compiled contracts stored on disk, if the cache is bounded",
+    )
+    .unwrap()
+});
+
+static COMPILED_CONTRACT_CACHE_EVICTIONS_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    try_create_int_counter(
+        "near_vm_compiled_contract_cache_evictions_total",
+        "The number of compiled contracts removed from disk to keep the cache within its size limit",
+    )
+    .unwrap()
+});
+
 #[derive(Default, Copy, Clone)]
 struct Metrics {
     near_vm_compilation_time: Duration,
@@ -74,6 +91,11 @@ pub(crate) fn record_compiled_contract_cache_lookup(is_hit: bool) {
     });
 }
 
+pub(crate) fn record_compiled_contract_cache_disk_usage(bytes: u64, evicted: usize) {
+    COMPILED_CONTRACT_CACHE_DISK_BYTES.set(bytes as i64);
+    COMPILED_CONTRACT_CACHE_EVICTIONS_TOTAL.inc_by(evicted as u64);
+}
+
 pub fn reset_metrics() {
     METRICS.with_borrow_mut(|m| *m = Metrics::default());
 }
@@ -0,0 +1,125 @@
+//! Native implementations of widely used contracts.
+//!
+//! When the `native_contracts` config is enabled, a call to a contract whose code hash is in
+//! [`NATIVE_CONTRACTS`] runs the audited native implementation of that code instead of the WASM
+//! code itself. The native implementation goes through the same host functions of [`VMLogic`] as
+//! the WASM code does, so storage, receipts and logs are charged the same. What it saves is the
+//! per-byte contract loading fee and the gas of the WASM instructions.
+//!
+//! No implementation is registered yet, so no protocol version enables the config.
+
+use crate::logic::errors::{FunctionCallError, MethodResolveError, VMLogicError};
+use crate::logic::{Config, ExecutionResultState, GasCounter};
+use crate::logic::{External, MemSlice, MemoryLike, VMContext, VMLogic, VMOutcome};
+use crate::runner::VMResult;
+use near_parameters::RuntimeFeesConfig;
+use near_primitives_core::hash::CryptoHash;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+type Result<T, E = VMLogicError> = ::std::result::Result<T, E>;
+
+/// A native implementation of a contract.
+///
+/// It must behave exactly as the WASM code it stands for: export the same methods, read and
+/// write the same storage keys, return the same values and fail with the same errors. Only the
+/// gas burnt differs.
+pub(crate) trait NativeContract: Sync {
+    /// Whether the WASM code exports `method`.
+    fn has_method(&self, method: &str) -> bool;
+
+    /// Runs `method`.
+    ///
+    /// There is no guest memory, so the data is passed to and from the host functions in
+    /// registers, see [`VMLogic::native_write_register`] and [`VMLogic::native_read_register`].
+    fn call(&self, method: &str, logic: &mut VMLogic) -> Result<()>;
+}
+
+/// The native contracts, by the hash of the WASM code they implement.
+///
+/// Changing this list changes the protocol: an entry can only be added together with a protocol
+/// feature that enables it.
+static NATIVE_CONTRACTS: &[(CryptoHash, &dyn NativeContract)] = &[];
+
+/// Returns the native implementation of the code with `code_hash`, if there is one and the
+/// `native_contracts` config enables them.
+pub(crate) fn find(config: &Config, code_hash: &CryptoHash) -> Option<&'static dyn NativeContract> {
+    if !config.native_contracts {
+        return None;
+    }
+    NATIVE_CONTRACTS.iter().find(|(hash, _)| hash == code_hash).map(|(_, contract)| *contract)
+}
+
+/// Prepares a call to `method` of a native contract.
+///
+/// There is nothing to compile or load, so only the base contract loading fee is charged.
+pub(crate) fn prepare(
+    contract: &'static dyn NativeContract,
+    config: Arc<Config>,
+    mut gas_counter: GasCounter,
+    method: &str,
+) -> Box<dyn crate::PreparedContract> {
+    let loaded = gas_counter.before_loading_executable(&config, method, 0);
+    Box::new(PreparedNativeContract {
+        contract,
+        config,
+        gas_counter,
+        method: method.to_string(),
+        loaded,
+    })
+}
+
+struct PreparedNativeContract {
+    contract: &'static dyn NativeContract,
+    config: Arc<Config>,
+    gas_counter: GasCounter,
+    method: String,
+    loaded: Result<(), FunctionCallError>,
+}
+
+impl crate::PreparedContract for PreparedNativeContract {
+    fn run(
+        self: Box<Self>,
+        ext: &mut dyn External,
+        context: &VMContext,
+        fees_config: Arc<RuntimeFeesConfig>,
+    ) -> VMResult {
+        let PreparedNativeContract { contract, config, gas_counter, method, loaded } = *self;
+        let result_state = ExecutionResultState::new(context, gas_counter, config);
+        if let Err(e) = loaded {
+            return Ok(VMOutcome::abort(result_state, e));
+        }
+        if !contract.has_method(&method) {
+            let e = FunctionCallError::MethodResolveError(MethodResolveError::MethodNotFound);
+            return Ok(VMOutcome::abort_but_nop_outcome_in_old_protocol(result_state, e));
+        }
+
+        let mut memory = NoMemory;
+        let mut logic = VMLogic::new(ext, context, fees_config, result_state, &mut memory);
+        match contract.call(&method, &mut logic) {
+            Ok(()) => Ok(VMOutcome::ok(logic.result_state)),
+            Err(err) => Ok(VMOutcome::abort(logic.result_state, err.try_into()?)),
+        }
+    }
+}
+
+/// Native contracts have no guest memory, they pass data to the host functions in registers.
+struct NoMemory;
+
+impl MemoryLike for NoMemory {
+    fn fits_memory(&self, _slice: MemSlice) -> Result<(), ()> {
+        Err(())
+    }
+
+    fn view_memory(&self, _slice: MemSlice) -> Result<Cow<[u8]>, ()> {
+        Err(())
+    }
+
+    fn read_memory(&self, _offset: u64, _buffer: &mut [u8]) -> Result<(), ()> {
+        Err(())
+    }
+
+    fn write_memory(&mut self, _offset: u64, _buffer: &[u8]) -> Result<(), ()> {
+        Err(())
+    }
+}
@@ -127,8 +127,7 @@ impl NearVM {
             })
             .clone();
 
-        let features =
-            crate::features::WasmFeatures::from(config.limit_config.contract_prepare_version);
+        let features = crate::features::WasmFeatures::new(&config, VMKind::NearVm);
         Self {
             config,
             engine: Universal::new(compiler)
@@ -527,7 +526,10 @@ impl near_vm_vm::Tunables for &NearVM {
 
     /// Instrumentation configuration: gas accounting config
     fn gas_cfg(&self) -> Box<dyn finite_wasm::wasmparser::VisitOperator<Output = u64>> {
-        Box::new(GasCostCfg(u64::from(self.config.regular_op_cost)))
+        Box::new(GasCostCfg {
+            regular_op_cost: u64::from(self.config.regular_op_cost),
+            simd_op_cost: u64::from(self.config.simd_op_cost),
+        })
     }
 }
 
@@ -565,7 +567,11 @@ impl finite_wasm::max_stack::SizeConfig for MaxStackCfg {
     }
 }
 
-struct GasCostCfg(u64);
+struct GasCostCfg {
+    regular_op_cost: u64,
+    /// Cost of the operations from the SIMD proposal.
+    simd_op_cost: u64,
+}
 
 macro_rules! gas_cost {
     ($( @$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
@@ -585,8 +591,11 @@ macro_rules! gas_cost {
     (@@mvp $_op:ident $_self:ident $({ $($_arg:ident: $_argty:ty),* })? => visit_else) => {
         0
     };
+    (@@simd $_op:ident $self:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident) => {
+        $self.simd_op_cost
+    };
     (@@$_proposal:ident $_op:ident $self:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident) => {
-        $self.0
+        $self.regular_op_cost
     };
 }
 
@@ -771,7 +780,14 @@ impl<'e, 'l, 'lr> Resolver for NearVmImports<'e, 'l, 'lr> {
                             // lifetime and so it is safe to dereference the `env` pointer which is
                             // known to be derived from a valid `&'vmlogic mut VMLogic<'_>` in the
                             // first place.
-                            unsafe { (*env).$func( $( $arg_name, )* ) }
+                            let logic = unsafe { &mut *env };
+                            if $crate::imports::should_record_host_call(stringify!($mod), stringify!($name)) {
+                                logic.trace_host_call(stringify!($name), &[$( $arg_name as u64 ),*], |logic| {
+                                    logic.$func( $( $arg_name, )* )
+                                })
+                            } else {
+                                logic.$func( $( $arg_name, )* )
+                            }
                         }));
                         // We want to ensure that the only kind of error that host function calls
                         // return are VMLogicError. This is important because we later attempt to
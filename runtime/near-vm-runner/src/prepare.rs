@@ -30,7 +30,7 @@ pub fn prepare_contract(
         (kind != VMKind::NearVm) || (prepare == crate::logic::ContractPrepareVersion::V2),
         "NearVM only works with contract prepare version V2",
     );
-    let features = crate::features::WasmFeatures::from(prepare);
+    let features = crate::features::WasmFeatures::new(config, kind);
     match prepare {
         crate::logic::ContractPrepareVersion::V0 => {
             // NB: v1 here is not a bug, we are reusing the code.
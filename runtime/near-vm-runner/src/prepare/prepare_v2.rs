@@ -281,7 +281,10 @@ pub(crate) fn prepare_contract(
 
     let res = finite_wasm::Analysis::new()
         .with_stack(Box::new(SimpleMaxStackCfg))
-        .with_gas(Box::new(SimpleGasCostCfg(u64::from(config.regular_op_cost))))
+        .with_gas(Box::new(SimpleGasCostCfg {
+            regular_op_cost: u64::from(config.regular_op_cost),
+            simd_op_cost: u64::from(config.simd_op_cost),
+        }))
         .analyze(&lightly_steamed)
         .map_err(|err| {
             tracing::error!(?err, ?kind, "Analysis failed");
@@ -331,7 +334,11 @@ impl finite_wasm::max_stack::SizeConfig for SimpleMaxStackCfg {
     }
 }
 
-struct SimpleGasCostCfg(u64);
+struct SimpleGasCostCfg {
+    regular_op_cost: u64,
+    /// Cost of the operations from the SIMD proposal.
+    simd_op_cost: u64,
+}
 
 macro_rules! gas_cost {
     ($( @$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
@@ -351,8 +358,11 @@ macro_rules! gas_cost {
     (@@mvp $_op:ident $_self:ident $({ $($_arg:ident: $_argty:ty),* })? => visit_else) => {
         0
     };
+    (@@simd $_op:ident $self:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident) => {
+        $self.simd_op_cost
+    };
     (@@$_proposal:ident $_op:ident $self:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident) => {
-        $self.0
+        $self.regular_op_cost
     };
 }
 
@@ -421,4 +431,14 @@ mod test {
             }
         });
     }
+
+    #[test]
+    fn simd_ops_cost() {
+        use super::wp::VisitOperator;
+        let mut cfg = super::SimpleGasCostCfg { regular_op_cost: 3, simd_op_cost: 5 };
+        assert_eq!(cfg.visit_i32_add(), 3);
+        assert_eq!(cfg.visit_i32x4_add(), 5);
+        assert_eq!(cfg.visit_v128_any_true(), 5);
+        assert_eq!(cfg.visit_end(), 0);
+    }
 }
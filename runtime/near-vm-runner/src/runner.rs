@@ -45,6 +45,9 @@ pub fn prepare(
     gas_counter: crate::logic::GasCounter,
     method: &str,
 ) -> Box<dyn crate::PreparedContract> {
+    if let Some(native) = crate::native_contracts::find(&wasm_config, &contract.hash()) {
+        return crate::native_contracts::prepare(native, wasm_config, gas_counter, method);
+    }
     let vm_kind = wasm_config.vm_kind;
     let runtime = vm_kind
         .runtime(wasm_config)
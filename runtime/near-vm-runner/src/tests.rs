@@ -1,6 +1,7 @@
 mod cache;
 mod compile_errors;
 mod fuzzers;
+mod native_contracts;
 mod regression_tests;
 mod rs_contract;
 mod runtime_errors;
@@ -69,7 +70,9 @@ fn create_context(input: Vec<u8>) -> VMContext {
         attached_deposit: 2u128,
         prepaid_gas: 10_u64.pow(14),
         random_seed: vec![0, 1, 2],
+        random_beacon: Some(vec![3, 4, 5]),
         view_config: None,
         output_data_receivers: vec![],
+        trace_host_calls: false,
     }
 }
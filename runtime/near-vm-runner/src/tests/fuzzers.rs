@@ -56,8 +56,10 @@ pub fn create_context(input: Vec<u8>) -> VMContext {
         attached_deposit: 2u128,
         prepaid_gas: 10_u64.pow(14),
         random_seed: vec![0, 1, 2],
+        random_beacon: Some(vec![3, 4, 5]),
         view_config: None,
         output_data_receivers: vec![],
+        trace_host_calls: false,
     }
 }
 
@@ -0,0 +1,98 @@
+use crate::logic::errors::VMLogicError;
+use crate::logic::mocks::mock_external::MockedExternal;
+use crate::logic::VMLogic;
+use crate::native_contracts::{self, NativeContract};
+use crate::runner::VMKindExt;
+use crate::tests::{create_context, test_vm_config};
+use crate::ContractCode;
+use near_parameters::RuntimeFeesConfig;
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// Native counterpart of `write_key_value` and `read_value` of the rs test contract.
+struct NativeRsContract;
+
+// Registers the data is passed in and out of the host functions.
+const RESULT_REGISTER: u64 = 0;
+const KEY_REGISTER: u64 = 1;
+const VALUE_REGISTER: u64 = 2;
+
+impl NativeContract for NativeRsContract {
+    fn has_method(&self, method: &str) -> bool {
+        matches!(method, "write_key_value" | "read_value")
+    }
+
+    fn call(&self, method: &str, logic: &mut VMLogic) -> Result<(), VMLogicError> {
+        logic.input(RESULT_REGISTER)?;
+        let input = logic.native_read_register(RESULT_REGISTER)?;
+        match method {
+            "write_key_value" => {
+                let Some(key_len) = input.len().checked_sub(size_of::<u64>()) else {
+                    return logic.panic();
+                };
+                let (key, value) = input.split_at(key_len);
+                logic.native_write_register(KEY_REGISTER, key)?;
+                logic.native_write_register(VALUE_REGISTER, value)?;
+                let evicted = logic.storage_write(
+                    u64::MAX,
+                    KEY_REGISTER,
+                    u64::MAX,
+                    VALUE_REGISTER,
+                    RESULT_REGISTER,
+                )?;
+                logic.native_write_register(VALUE_REGISTER, &evicted.to_le_bytes())?;
+                logic.value_return(u64::MAX, VALUE_REGISTER)
+            }
+            "read_value" => {
+                if input.len() != size_of::<u64>() {
+                    return logic.panic();
+                }
+                logic.native_write_register(KEY_REGISTER, &input)?;
+                if logic.storage_read(u64::MAX, KEY_REGISTER, RESULT_REGISTER)? == 0 {
+                    return Ok(());
+                }
+                logic.value_return(u64::MAX, RESULT_REGISTER)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn encode(xs: &[u64]) -> Vec<u8> {
+    xs.iter().flat_map(|it| it.to_le_bytes()).collect()
+}
+
+#[test]
+fn test_native_contract_matches_wasm() {
+    let config = Arc::new(test_vm_config());
+    let fees = Arc::new(RuntimeFeesConfig::test());
+    let code = || ContractCode::new(near_test_contracts::rs_contract().to_vec(), None);
+    let mut wasm_ext = MockedExternal::with_code(code());
+    let mut native_ext = MockedExternal::with_code(code());
+
+    let calls = [
+        ("write_key_value", encode(&[10, 20])),
+        ("write_key_value", encode(&[10, 30])),
+        ("read_value", encode(&[10])),
+        ("read_value", encode(&[11])),
+        ("missing_method", vec![]),
+    ];
+    for (method, input) in calls {
+        let context = create_context(input);
+        let runtime = config.vm_kind.runtime(config.clone()).expect("runtime is not enabled");
+        let wasm = runtime
+            .prepare(&wasm_ext, None, context.make_gas_counter(&config), method)
+            .run(&mut wasm_ext, &context, Arc::clone(&fees))
+            .unwrap();
+        let gas_counter = context.make_gas_counter(&config);
+        let native =
+            native_contracts::prepare(&NativeRsContract, config.clone(), gas_counter, method)
+                .run(&mut native_ext, &context, Arc::clone(&fees))
+                .unwrap();
+
+        assert_eq!(native.return_data, wasm.return_data, "{method}");
+        assert_eq!(native.aborted, wasm.aborted, "{method}");
+        assert!(native.burnt_gas < wasm.burnt_gas, "{method}");
+    }
+    assert_eq!(native_ext.fake_trie, wasm_ext.fake_trie);
+}
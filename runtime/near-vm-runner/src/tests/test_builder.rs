@@ -26,8 +26,10 @@ pub(crate) fn test_builder() -> TestBuilder {
         attached_deposit: 2u128,
         prepaid_gas: 10_u64.pow(14),
         random_seed: vec![0, 1, 2],
+        random_beacon: Some(vec![3, 4, 5]),
         view_config: None,
         output_data_receivers: vec![],
+        trace_host_calls: false,
     };
     let mut skip = HashSet::new();
     for kind in [VMKind::Wasmer0, VMKind::Wasmer2, VMKind::NearVm, VMKind::Wasmtime] {
@@ -69,3 +69,39 @@ pub fn test_ts_contract() {
         }
     });
 }
+
+#[test]
+pub fn test_host_call_trace() {
+    let config = Arc::new(test_vm_config());
+    with_vm_variants(&config, |vm_kind: VMKind| {
+        let code = ContractCode::new(near_test_contracts::ts_contract().to_vec(), None);
+        let mut fake_external = MockedExternal::with_code(code);
+        let fees = Arc::new(RuntimeFeesConfig::test());
+        let mut run = |context: &crate::logic::VMContext| {
+            let runtime = vm_kind.runtime(config.clone()).expect("runtime has not been compiled");
+            let gas_counter = context.make_gas_counter(&config);
+            runtime
+                .prepare(&fake_external, None, gas_counter, "try_storage_write")
+                .run(&mut fake_external, context, Arc::clone(&fees))
+                .expect("execution failed")
+        };
+
+        let outcome = run(&create_context(b"foo bar".to_vec()));
+        assert_eq!(outcome.host_calls, None);
+
+        let mut context = create_context(b"foo bar".to_vec());
+        context.trace_host_calls = true;
+        let outcome = run(&context);
+        let trace = outcome.host_calls.expect("host calls are traced");
+        assert!(!trace.truncated);
+        let names: Vec<_> = trace.calls.iter().map(|call| call.name).collect();
+        assert!(names.contains(&"input"), "{names:?}");
+        assert!(!names.contains(&"finite_wasm_gas"), "{names:?}");
+        let write = trace.calls.iter().find(|call| call.name == "storage_write").unwrap();
+        assert_eq!(write.args.len(), 5);
+        assert!(write.burnt_gas_after > write.burnt_gas_before);
+        assert!(!write.failed);
+        let last = trace.calls.last().unwrap();
+        assert!(last.burnt_gas_after <= outcome.burnt_gas);
+    });
+}
@@ -3,6 +3,7 @@ use super::test_vm_config;
 use crate::prepare::prepare_contract;
 use crate::tests::with_vm_variants;
 use expect_test::expect;
+use near_parameters::vm::VMKind;
 
 static SIMD: &str = r#"
 (module
@@ -96,7 +97,6 @@ static EXPECTED_UNSUPPORTED: &[(&str, &str)] = &[
     ("bulk_memory", BULK_MEMORY),
     ("reference_types", REFERENCE_TYPES),
     ("threads", THREADS),
-    ("simd", SIMD),
 ];
 
 #[test]
@@ -112,6 +112,25 @@ fn ensure_fails_verification() {
     });
 }
 
+#[test]
+fn simd_requires_protocol_feature() {
+    let wasm = wat::parse_str(SIMD).expect("parsing test wat should succeed");
+    let mut config = test_vm_config();
+    config.wasm_simd = false;
+    with_vm_variants(&config, |kind| {
+        assert!(prepare_contract(&wasm, &config, kind).is_err());
+    });
+    config.wasm_simd = true;
+    with_vm_variants(&config, |kind| {
+        // Only wasmtime compiles the SIMD instructions so far.
+        if kind == VMKind::Wasmtime {
+            prepare_contract(&wasm, &config, kind).expect("simd contract should prepare");
+        } else {
+            assert!(prepare_contract(&wasm, &config, kind).is_err());
+        }
+    });
+}
+
 #[test]
 fn ensure_fails_execution() {
     for (_feature_name, wat) in EXPECTED_UNSUPPORTED {
@@ -241,8 +241,7 @@ impl Wasmer2VM {
         let compiler = Singlepass::new();
         // We only support universal engine at the moment.
         assert_eq!(WASMER2_CONFIG.engine, WasmerEngine::Universal);
-        let features =
-            crate::features::WasmFeatures::from(config.limit_config.contract_prepare_version);
+        let features = crate::features::WasmFeatures::new(&config, VMKind::Wasmer2);
         Self {
             config,
             engine: Universal::new(compiler).target(target).features(features.into()).engine(),
@@ -751,7 +750,14 @@ impl<'e, 'l, 'lr> Resolver for Wasmer2Imports<'e, 'l, 'lr> {
                         // lifetime and so it is safe to dereference the `env` pointer which is
                         // known to be derived from a valid `&'vmlogic mut VMLogic<'_>` in the
                         // first place.
-                        unsafe { (*env).$func( $( $arg_name, )* ) }
+                        let logic = unsafe { &mut *env };
+                        if $crate::imports::should_record_host_call(stringify!($mod), stringify!($name)) {
+                            logic.trace_host_call(stringify!($name), &[$( $arg_name as u64 ),*], |logic| {
+                                logic.$func( $( $arg_name, )* )
+                            })
+                        } else {
+                            logic.$func( $( $arg_name, )* )
+                        }
                     }));
                     // We want to ensure that the only kind of error that host function calls
                     // return are VMLogicError. This is important because we later attempt to
@@ -584,7 +584,13 @@ pub(crate) fn build_imports(
                         tracing::trace_span!(target: "vm::host_function", stringify!($name)).entered()
                     });
                     let logic: &mut VMLogic<'_> = unsafe { &mut *(ctx.data as *mut VMLogic<'_>) };
-                    logic.$func( $( $arg_name, )* )
+                    if $crate::imports::should_record_host_call(stringify!($mod), stringify!($name)) {
+                        logic.trace_host_call(stringify!($name), &[$( $arg_name as u64 ),*], |logic| {
+                            logic.$func( $( $arg_name, )* )
+                        })
+                    } else {
+                        logic.$func( $( $arg_name, )* )
+                    }
                 }
 
                 match stringify!($mod) {
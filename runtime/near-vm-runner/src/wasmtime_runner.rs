@@ -130,8 +130,7 @@ pub fn get_engine(config: &wasmtime::Config) -> Engine {
 }
 
 pub(crate) fn default_wasmtime_config(config: &Config) -> wasmtime::Config {
-    let features =
-        crate::features::WasmFeatures::from(config.limit_config.contract_prepare_version);
+    let features = crate::features::WasmFeatures::new(config, VMKind::Wasmtime);
     let mut config = wasmtime::Config::from(features);
     config.max_wasm_stack(1024 * 1024 * 1024); // wasm stack metering is implemented by instrumentation, we don't want wasmtime to trap before that
     config
@@ -476,7 +475,14 @@ fn link<'a, 'b>(
                     crate::wasmtime_runner::CALLER.with(|runner_caller| *runner_caller.borrow_mut() = std::mem::transmute(caller));
                 }
                 let logic: &mut VMLogic<'_> = unsafe { &mut *(data as *mut VMLogic<'_>) };
-                match logic.$func( $( $arg_name as $arg_type, )* ) {
+                let result = if imports::should_record_host_call(stringify!($mod), stringify!($name)) {
+                    logic.trace_host_call(stringify!($name), &[$( $arg_name as u64 ),*], |logic| {
+                        logic.$func( $( $arg_name as $arg_type, )* )
+                    })
+                } else {
+                    logic.$func( $( $arg_name as $arg_type, )* )
+                };
+                match result {
                     Ok(result) => Ok(result as ($( $returns ),* ) ),
                     Err(err) => {
                         Err(ErrorContainer(std::sync::Mutex::new(Some(err))).into())
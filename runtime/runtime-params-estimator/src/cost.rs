@@ -228,6 +228,13 @@ pub enum Cost {
     /// attempt to cause slow loads and stores. The total time spent in the
     /// runtime is divided by the number of executed instructions.
     WasmInstruction,
+    /// Estimates `wasm_config.simd_op_cost` which is charged instead of
+    /// `regular_op_cost` for every executed operation from the SIMD proposal.
+    ///
+    /// Estimation: Run a loop of vector negations and subtract the time of the
+    /// same loop without them, divided by the number of negations.  Contracts
+    /// are run in wasmtime, as the near-vm backend doesn't compile SIMD yet.
+    WasmSimdInstruction,
 
     // # Reading and writing memory
     // The hosting runtime sometimes copies data between in and out of WASM
@@ -368,7 +375,7 @@ pub enum Cost {
 
     // Cryptographic host functions:
     // The runtime provides host functions for sha256, keccak256, keccak512,
-    // ripemd160 hashes. Plus, there is a ECDSA signature verification host
+    // sha3_256, ripemd160 hashes. Plus, there is a ECDSA signature verification host
     // function.
     // All these host functions are estimated by executing a transaction with a
     // single function call in them, that just invokes the given host function.
@@ -393,6 +400,12 @@ pub enum Cost {
     /// Estimates `keccak512_byte`, the cost charged per input byte in calls to the
     /// keccak512-hash host function.
     Keccak512Byte,
+    /// Estimates `sha3_256_base`, the cost charged once per call to the
+    /// sha3_256-hash host function.
+    Sha3256Base,
+    /// Estimates `sha3_256_byte`, the cost charged per input byte in calls to the
+    /// sha3_256-hash host function.
+    Sha3256Byte,
     /// Estimates `ripemd160_base`, the cost charged once per call to the
     /// ripemd160-hash host function.
     Ripemd160Base,
@@ -605,6 +618,12 @@ pub enum Cost {
     AltBn128PairingCheckElement,
     AltBn128G1SumBase,
     AltBn128G1SumElement,
+    /// Estimates `alt_bn128_groth16_verify_base`, the cost charged once per
+    /// Groth16 proof verification, dominated by the four pairings.
+    AltBn128Groth16VerifyBase,
+    /// Estimates `alt_bn128_groth16_verify_element`, the cost charged per
+    /// public input of the verified proof.
+    AltBn128Groth16VerifyElement,
     Bls12381P1SumBase,
     Bls12381P1SumElement,
     Bls12381P2SumBase,
@@ -19,6 +19,9 @@ pub fn costs_to_runtime_config(cost_table: &CostTable) -> anyhow::Result<Runtime
     let regular_op_cost = cost_table
         .get(Cost::WasmInstruction)
         .with_context(|| format!("undefined cost: {}", Cost::WasmInstruction))?;
+    let simd_op_cost = cost_table
+        .get(Cost::WasmSimdInstruction)
+        .with_context(|| format!("undefined cost: {}", Cost::WasmSimdInstruction))?;
 
     // Take latest VM limit config, because estimation doesn't affect it.
     // Note that if you run estimation against stable version, it doesn't catch updates of nightly
@@ -33,6 +36,7 @@ pub fn costs_to_runtime_config(cost_table: &CostTable) -> anyhow::Result<Runtime
             ext_costs: ext_costs_config(cost_table)?,
             grow_mem_cost: 1,
             regular_op_cost: u32::try_from(regular_op_cost).unwrap(),
+            simd_op_cost: u32::try_from(simd_op_cost).unwrap(),
             limit_config: vm_limit_config,
             ..*latest_runtime_config.wasm_config
         }),
@@ -122,6 +126,8 @@ fn estimation(cost: ExtCosts) -> Option<Cost> {
         ExtCosts::keccak256_byte => Cost::Keccak256Byte,
         ExtCosts::keccak512_base => Cost::Keccak512Base,
         ExtCosts::keccak512_byte => Cost::Keccak512Byte,
+        ExtCosts::sha3_256_base => Cost::Sha3256Base,
+        ExtCosts::sha3_256_byte => Cost::Sha3256Byte,
         ExtCosts::ripemd160_base => Cost::Ripemd160Base,
         ExtCosts::ripemd160_block => Cost::Ripemd160Block,
         ExtCosts::ecrecover_base => Cost::EcrecoverBase,
@@ -152,6 +158,8 @@ fn estimation(cost: ExtCosts) -> Option<Cost> {
         ExtCosts::alt_bn128_g1_multiexp_element => Cost::AltBn128G1MultiexpElement,
         ExtCosts::alt_bn128_pairing_check_base => Cost::AltBn128PairingCheckBase,
         ExtCosts::alt_bn128_pairing_check_element => Cost::AltBn128PairingCheckElement,
+        ExtCosts::alt_bn128_groth16_verify_base => Cost::AltBn128Groth16VerifyBase,
+        ExtCosts::alt_bn128_groth16_verify_element => Cost::AltBn128Groth16VerifyElement,
         ExtCosts::yield_create_base => Cost::YieldCreateBase,
         ExtCosts::yield_create_byte => Cost::YieldCreateByte,
         ExtCosts::bls12381_p1_sum_base => Cost::Bls12381P1SumBase,
@@ -99,6 +99,7 @@ use estimator_params::sha256_cost;
 use gas_cost::{LeastSquaresTolerance, NonNegativeTolerance};
 use gas_metering::gas_metering_cost;
 use near_crypto::{KeyType, SecretKey};
+use near_parameters::vm::{Config as VMConfig, VMKind};
 use near_parameters::{ExtCosts, RuntimeConfigStore, RuntimeFeesConfig};
 use near_primitives::account::{AccessKey, AccessKeyPermission, FunctionCallPermission};
 use near_primitives::transaction::{
@@ -213,6 +214,7 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::ActionDelegateExec, action_costs::delegate_exec),
     (Cost::HostFunctionCall, host_function_call),
     (Cost::WasmInstruction, wasm_instruction),
+    (Cost::WasmSimdInstruction, wasm_simd_instruction),
     (Cost::DataReceiptCreationBase, data_receipt_creation_base),
     (Cost::DataReceiptCreationPerByte, data_receipt_creation_per_byte),
     (Cost::ReadMemoryBase, read_memory_base),
@@ -235,6 +237,10 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::Keccak256Byte, keccak256_byte),
     (Cost::Keccak512Base, keccak512_base),
     (Cost::Keccak512Byte, keccak512_byte),
+    #[cfg(feature = "nightly")]
+    (Cost::Sha3256Base, sha3_256_base),
+    #[cfg(feature = "nightly")]
+    (Cost::Sha3256Byte, sha3_256_byte),
     (Cost::Ripemd160Base, ripemd160_base),
     (Cost::Ripemd160Block, ripemd160_block),
     (Cost::EcrecoverBase, ecrecover_base),
@@ -246,6 +252,10 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::AltBn128G1SumElement, alt_bn128g1_sum_element),
     (Cost::AltBn128PairingCheckBase, alt_bn128_pairing_check_base),
     (Cost::AltBn128PairingCheckElement, alt_bn128_pairing_check_element),
+    #[cfg(feature = "nightly")]
+    (Cost::AltBn128Groth16VerifyBase, alt_bn128_groth16_verify_base),
+    #[cfg(feature = "nightly")]
+    (Cost::AltBn128Groth16VerifyElement, alt_bn128_groth16_verify_element),
     (Cost::StorageHasKeyBase, storage_has_key_base),
     (Cost::StorageHasKeyByte, storage_has_key_byte),
     (Cost::StorageReadBase, storage_read_base),
@@ -941,6 +951,82 @@ fn wasm_instruction(ctx: &mut EstimatorContext) -> GasCost {
     per_instruction
 }
 
+fn wasm_simd_instruction(ctx: &mut EstimatorContext) -> GasCost {
+    // The near-vm backend rejects the SIMD instructions until it can compile
+    // them, so they are measured in the VM which does.
+    let vm_kind = VMKind::Wasmtime;
+    let config_store = RuntimeConfigStore::new(None);
+    let mut config = VMConfig::clone(&config_store.get_config(PROTOCOL_VERSION).wasm_config);
+    config.vm_kind = vm_kind;
+    config.wasm_simd = true;
+    let config = Arc::new(config);
+    let fees = Arc::new(RuntimeFeesConfig::test());
+    let cache = MockContractRuntimeCache::default();
+
+    let n_iters = 10;
+    let n_loops = 10_000;
+    let ops_per_loop = 1_000;
+
+    let measure = |ops_per_loop: u64| {
+        let code = make_simd_loop_contract(n_loops, ops_per_loop);
+        let mut fake_external = MockedExternal::with_code(code);
+        let mut run = || {
+            let context = create_context(vec![]);
+            let gas_counter = context.make_gas_counter(&config);
+            let vm_result = vm_kind
+                .runtime(Arc::clone(&config))
+                .unwrap()
+                .prepare(&fake_external, Some(&cache), gas_counter, "simd")
+                .run(&mut fake_external, &context, Arc::clone(&fees))
+                .expect("fatal_error");
+            assert!(vm_result.aborted.is_none());
+        };
+        run();
+        let start = GasCost::measure(ctx.config.metric);
+        for _ in 0..n_iters {
+            run();
+        }
+        start.elapsed()
+    };
+
+    let total = measure(ops_per_loop);
+    let base = measure(0);
+    total.saturating_sub(&base, &NonNegativeTolerance::PER_MILLE)
+        / (n_iters * n_loops * ops_per_loop)
+}
+
+/// Contract whose `simd` method runs `n_loops` times a loop with
+/// `ops_per_loop` vector negations.
+fn make_simd_loop_contract(n_loops: u64, ops_per_loop: u64) -> ContractCode {
+    let ops = "i32x4.neg\n".repeat(ops_per_loop as usize);
+    let code = format!(
+        "
+        (module
+            (export \"simd\" (func 0))
+              (func (;0;)
+                (local i32 v128)
+                i32.const {n_loops}
+                local.set 0
+                block
+                  loop
+                    local.get 1
+                    {ops}
+                    local.set 1
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.tee 0
+                    i32.const 0
+                    i32.gt_s
+                    br_if 0
+                  end
+                end
+              )
+            )"
+    );
+    ContractCode::new(wat::parse_str(code).unwrap(), None)
+}
+
 fn read_memory_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost(ctx, "read_memory_10b_10k", ExtCosts::read_memory_base, 10_000)
 }
@@ -1027,6 +1113,15 @@ fn keccak512_byte(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost(ctx, "keccak512_10kib_10k", ExtCosts::keccak512_byte, 10 * 1024 * 10_000)
 }
 
+#[cfg(feature = "nightly")]
+fn sha3_256_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "sha3_256_10b_10k", ExtCosts::sha3_256_base, 10_000)
+}
+#[cfg(feature = "nightly")]
+fn sha3_256_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "sha3_256_10kib_10k", ExtCosts::sha3_256_byte, 10 * 1024 * 10_000)
+}
+
 fn ripemd160_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost(ctx, "ripemd160_10b_10k", ExtCosts::ripemd160_base, 10_000)
 }
@@ -1084,6 +1179,20 @@ fn alt_bn128_pairing_check_element(ctx: &mut EstimatorContext) -> GasCost {
     )
 }
 
+#[cfg(feature = "nightly")]
+fn alt_bn128_groth16_verify_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "alt_bn128_groth16_verify_0_10", ExtCosts::alt_bn128_groth16_verify_base, 10)
+}
+#[cfg(feature = "nightly")]
+fn alt_bn128_groth16_verify_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(
+        ctx,
+        "alt_bn128_groth16_verify_10_10",
+        ExtCosts::alt_bn128_groth16_verify_element,
+        10 * 10,
+    )
+}
+
 fn bls12381_p1_sum_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost(ctx, "bls12381_p1_sum_0_100", ExtCosts::bls12381_p1_sum_base, 100)
 }
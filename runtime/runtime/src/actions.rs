@@ -1,28 +1,36 @@
 use crate::config::{
-    safe_add_compute, safe_add_gas, total_prepaid_exec_fees, total_prepaid_gas,
-    total_prepaid_send_fees,
+    global_contract_distribution_fee, safe_add_compute, safe_add_gas, total_prepaid_exec_fees,
+    total_prepaid_gas, total_prepaid_send_fees,
 };
 use crate::ext::{ExternalError, RuntimeContractExt, RuntimeExt};
+use crate::host_call_trace::FunctionCallTrace;
 use crate::receipt_manager::ReceiptManager;
+use crate::verifier::validate_limited_full_access_actions;
 use crate::{metrics, ActionResult, ApplyState};
 use near_crypto::PublicKey;
 use near_parameters::{AccountCreationConfig, ActionCosts, RuntimeConfig, RuntimeFeesConfig};
-use near_primitives::account::{AccessKey, AccessKeyPermission, Account};
+use near_primitives::account::{AccessKey, AccessKeyPermission, Account, AccountNamespace};
+use near_primitives::action::atomic_batch::AtomicBatchAction;
 use near_primitives::action::delegate::{DelegateAction, SignedDelegateAction};
 use near_primitives::checked_feature;
 use near_primitives::config::ViewConfig;
-use near_primitives::errors::{ActionError, ActionErrorKind, InvalidAccessKeyError, RuntimeError};
+use near_primitives::errors::{
+    ActionError, ActionErrorKind, IntegerOverflowError, InvalidAccessKeyError, RuntimeError,
+};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
-    ActionReceipt, DataReceipt, Receipt, ReceiptEnum, ReceiptPriority, ReceiptV0,
+    ActionReceipt, DataReceipt, IdempotencyKeys, Receipt, ReceiptEnum, ReceiptPriority, ReceiptV0,
 };
+use near_primitives::shard_layout::ShardLayout;
 use near_primitives::transaction::{
     Action, AddKeyAction, DeleteAccountAction, DeleteKeyAction, DeployContractAction,
-    FunctionCallAction, StakeAction,
+    DeployGlobalContractAction, DeployNamespacedContractAction, FunctionCallAction,
+    IdempotencyKeyAction, RefundReason, StakeAction, UseGlobalContractAction,
 };
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{
-    AccountId, Balance, BlockHeight, EpochInfoProvider, Gas, StorageUsage, TrieCacheMode,
+    AccountId, Balance, BlockHeight, EpochInfoProvider, Gas, ShardId, StorageUsage, TrieCacheMode,
 };
 use near_primitives::utils::account_is_implicit;
 use near_primitives::version::{
@@ -30,9 +38,10 @@ use near_primitives::version::{
 };
 use near_primitives_core::account::id::AccountType;
 use near_store::{
-    enqueue_promise_yield_timeout, get_access_key, get_code, get_promise_yield_indices,
-    remove_access_key, remove_account, set_access_key, set_code, set_promise_yield_indices,
-    StorageError, TrieUpdate,
+    enqueue_promise_yield_timeout, get_access_key, get_code, get_idempotency_keys,
+    get_namespaced_code, get_promise_yield_indices, has_global_code, remove_access_key,
+    remove_account, remove_idempotency_keys, set_access_key, set_code, set_global_code,
+    set_idempotency_keys, set_namespaced_code, set_promise_yield_indices, StorageError, TrieUpdate,
 };
 use near_vm_runner::logic::errors::{
     CompilationError, FunctionCallError, InconsistentStateError, VMRunnerError,
@@ -49,6 +58,7 @@ pub(crate) fn execute_function_call(
     apply_state: &ApplyState,
     runtime_ext: &mut RuntimeExt,
     predecessor_id: &AccountId,
+    random_beacon: Option<CryptoHash>,
     action_receipt: &ActionReceipt,
     promise_results: Arc<[near_vm_runner::logic::types::PromiseResult]>,
     function_call: &FunctionCallAction,
@@ -87,10 +97,34 @@ pub(crate) fn execute_function_call(
         attached_deposit: function_call.deposit,
         prepaid_gas: function_call.gas,
         random_seed,
+        random_beacon: random_beacon.map(|beacon| beacon.as_ref().to_vec()),
         view_config,
         output_data_receivers,
+        trace_host_calls: apply_state
+            .host_call_tracer
+            .as_ref()
+            .map_or(false, |tracer| tracer.is_traced(&account_id)),
     };
+    let mut outcome = run_function_call(contract, apply_state, runtime_ext, &context, config)?;
 
+    if !context.view_config.is_some() {
+        let unused_gas = function_call.gas.saturating_sub(outcome.used_gas);
+        let distributed = runtime_ext.receipt_manager.distribute_gas(unused_gas)?;
+        outcome.used_gas = safe_add_gas(outcome.used_gas, distributed)?;
+    }
+
+    Ok(outcome)
+}
+
+/// Runs a prepared contract in `context`, translating the errors of the contract runtime.
+pub(crate) fn run_function_call(
+    contract: Box<dyn near_vm_runner::PreparedContract>,
+    apply_state: &ApplyState,
+    runtime_ext: &mut RuntimeExt,
+    context: &VMContext,
+    config: &RuntimeConfig,
+) -> Result<VMOutcome, RuntimeError> {
+    let account_id = runtime_ext.account_id().clone();
     // Enable caching chunk mode for the function call. This allows to charge for nodes touched in a chunk only once for
     // the first access time. Although nodes are accessed for other actions as well, we do it only here because we
     // charge only for trie nodes touched during function calls.
@@ -102,7 +136,7 @@ pub(crate) fn execute_function_call(
         false => None,
     };
     let mode_guard = runtime_ext.trie_update.with_trie_cache_mode(mode);
-    let result = near_vm_runner::run(contract, runtime_ext, &context, Arc::clone(&config.fees));
+    let result = near_vm_runner::run(contract, runtime_ext, context, Arc::clone(&config.fees));
     drop(mode_guard);
     near_vm_runner::report_metrics(
         &apply_state.shard_id.to_string(),
@@ -118,27 +152,27 @@ pub(crate) fn execute_function_call(
     // than leaking the exact details further up.
     // Note that this does not include errors caused by user code / input, those are
     // stored in outcome.aborted.
-    let mut outcome = match result {
+    match result {
         Err(VMRunnerError::ContractCodeNotPresent) => {
             let error = FunctionCallError::CompilationError(CompilationError::CodeDoesNotExist {
                 account_id: account_id.as_str().into(),
             });
-            return Ok(VMOutcome::nop_outcome(error));
+            Ok(VMOutcome::nop_outcome(error))
         }
         Err(VMRunnerError::ExternalError(any_err)) => {
             let err: ExternalError =
                 any_err.downcast().expect("Downcasting AnyError should not fail");
-            return Err(match err {
+            Err(match err {
                 ExternalError::StorageError(err) => err.into(),
                 ExternalError::ValidatorError(err) => RuntimeError::ValidatorError(err),
-            });
+            })
         }
         Err(VMRunnerError::InconsistentStateError(
             err @ InconsistentStateError::IntegerOverflow,
-        )) => return Err(StorageError::StorageInconsistentState(err.to_string()).into()),
+        )) => Err(StorageError::StorageInconsistentState(err.to_string()).into()),
         Err(VMRunnerError::CacheError(err)) => {
             metrics::FUNCTION_CALL_PROCESSED_CACHE_ERRORS.with_label_values(&[(&err).into()]).inc();
-            return Err(StorageError::StorageInconsistentState(err.to_string()).into());
+            Err(StorageError::StorageInconsistentState(err.to_string()).into())
         }
         Err(VMRunnerError::LoadingError(msg)) => {
             panic!("Contract runtime failed to load a contrct: {msg}")
@@ -149,16 +183,8 @@ pub(crate) fn execute_function_call(
         Err(VMRunnerError::WasmUnknownError { debug_message }) => {
             panic!("Wasmer returned unknown message: {}", debug_message)
         }
-        Ok(r) => r,
-    };
-
-    if !context.view_config.is_some() {
-        let unused_gas = function_call.gas.saturating_sub(outcome.used_gas);
-        let distributed = runtime_ext.receipt_manager.distribute_gas(unused_gas)?;
-        outcome.used_gas = safe_add_gas(outcome.used_gas, distributed)?;
+        Ok(r) => Ok(r),
     }
-
-    Ok(outcome)
 }
 
 pub(crate) fn prepare_function_call(
@@ -166,6 +192,7 @@ pub(crate) fn prepare_function_call(
     apply_state: &ApplyState,
     account: &Account,
     account_id: &AccountId,
+    namespace: Option<&AccountNamespace>,
     function_call: &FunctionCallAction,
     config: &RuntimeConfig,
     view_config: Option<ViewConfig>,
@@ -185,6 +212,7 @@ pub(crate) fn prepare_function_call(
         trie_update: state_update,
         account_id,
         account,
+        namespace,
         current_protocol_version: apply_state.current_protocol_version,
     };
     let contract = near_vm_runner::prepare(
@@ -206,10 +234,12 @@ pub(crate) fn action_function_call(
     promise_results: Arc<[near_vm_runner::logic::types::PromiseResult]>,
     result: &mut ActionResult,
     account_id: &AccountId,
+    namespace: Option<&AccountNamespace>,
     function_call: &FunctionCallAction,
     action_hash: &CryptoHash,
     config: &RuntimeConfig,
     is_last_action: bool,
+    receipt_is_local: bool,
     epoch_info_provider: &(dyn EpochInfoProvider),
     contract: Box<dyn PreparedContract>,
 ) -> Result<(), RuntimeError> {
@@ -219,7 +249,17 @@ pub(crate) fn action_function_call(
         )
         .into());
     }
-    state_update.trie.request_code_recording(account_id.clone());
+    let code_key = match namespace {
+        None if account.global_contract() => {
+            TrieKey::GlobalContractCode { code_hash: account.code_hash() }
+        }
+        None => TrieKey::ContractCode { account_id: account_id.clone() },
+        Some(namespace) => TrieKey::NamespacedContractCode {
+            account_id: account_id.clone(),
+            namespace: namespace.clone(),
+        },
+    };
+    state_update.trie.request_code_recording(code_key);
     #[cfg(feature = "test_features")]
     apply_recorded_storage_garbage(function_call, state_update);
 
@@ -229,6 +269,7 @@ pub(crate) fn action_function_call(
         &mut receipt_manager,
         account_id.clone(),
         account.clone(),
+        namespace.cloned(),
         *action_hash,
         apply_state.epoch_id,
         apply_state.prev_block_hash,
@@ -236,11 +277,17 @@ pub(crate) fn action_function_call(
         epoch_info_provider,
         apply_state.current_protocol_version,
     );
-    let outcome = execute_function_call(
+    // Local receipts may be included knowing the random value of the block, see
+    // `VMLogic::random_beacon`.
+    let random_beacon = (!receipt_is_local).then(|| {
+        near_primitives::utils::create_random_beacon(receipt.receipt_id(), &apply_state.random_seed)
+    });
+    let mut outcome = execute_function_call(
         contract,
         apply_state,
         &mut runtime_ext,
         receipt.predecessor_id(),
+        random_beacon,
         action_receipt,
         promise_results,
         function_call,
@@ -249,6 +296,19 @@ pub(crate) fn action_function_call(
         is_last_action,
         None,
     )?;
+    if let (Some(tracer), Some(host_calls)) =
+        (&apply_state.host_call_tracer, outcome.host_calls.take())
+    {
+        tracer.record(
+            *receipt.receipt_id(),
+            FunctionCallTrace {
+                action_hash: *action_hash,
+                receiver_id: account_id.clone(),
+                method_name: function_call.method_name.clone(),
+                host_calls,
+            },
+        );
+    }
 
     match &outcome.aborted {
         None => {
@@ -377,6 +437,7 @@ pub(crate) fn action_function_call(
         account.set_storage_usage(outcome.storage_usage);
         result.result = Ok(outcome.return_data);
         result.new_receipts.extend(new_receipts);
+        result.events.extend(outcome.events.into_iter().map(crate::conversions::Convert::convert));
     }
 
     Ok(())
@@ -444,15 +505,18 @@ pub(crate) fn try_refund_allowance(
 ) -> Result<(), StorageError> {
     if let Some(mut access_key) = get_access_key(state_update, account_id, public_key)? {
         let mut updated = false;
-        if let AccessKeyPermission::FunctionCall(function_call_permission) =
-            &mut access_key.permission
-        {
-            if let Some(allowance) = function_call_permission.allowance.as_mut() {
-                let new_allowance = allowance.saturating_add(deposit);
-                if new_allowance > *allowance {
-                    *allowance = new_allowance;
-                    updated = true;
-                }
+        let allowance = match &mut access_key.permission {
+            AccessKeyPermission::FunctionCall(function_call_permission) => {
+                function_call_permission.allowance.as_mut()
+            }
+            AccessKeyPermission::LimitedFullAccess(permission) => Some(&mut permission.allowance),
+            AccessKeyPermission::FullAccess => None,
+        };
+        if let Some(allowance) = allowance {
+            let new_allowance = allowance.saturating_add(deposit);
+            if new_allowance > *allowance {
+                *allowance = new_allowance;
+                updated = true;
             }
         }
         if updated {
@@ -673,6 +737,169 @@ pub(crate) fn action_deploy_contract(
     Ok(())
 }
 
+pub(crate) fn action_deploy_namespaced_contract(
+    state_update: &mut TrieUpdate,
+    account: &mut Account,
+    account_id: &AccountId,
+    deploy_contract: &DeployNamespacedContractAction,
+    apply_state: &ApplyState,
+) -> Result<(), StorageError> {
+    let _span =
+        tracing::debug_span!(target: "runtime", "action_deploy_namespaced_contract").entered();
+    let namespace = &deploy_contract.namespace;
+    let code = ContractCode::new(deploy_contract.code.clone(), None);
+    let (prev_code_length, added_usage) = match account.namespace_code_hash(namespace) {
+        Some(prev_code_hash) => {
+            let prev_code =
+                get_namespaced_code(state_update, account_id, namespace, Some(prev_code_hash))?;
+            (prev_code.map(|code| code.code().len() as u64).unwrap_or_default(), 0)
+        }
+        // A new namespace also stores its name and code hash in the account.
+        None => (0, namespace_storage_usage(namespace)),
+    };
+    account.set_storage_usage(account.storage_usage().saturating_sub(prev_code_length));
+    let added_usage = added_usage + code.code().len() as u64;
+    account.set_storage_usage(account.storage_usage().checked_add(added_usage).ok_or_else(
+        || {
+            StorageError::StorageInconsistentState(format!(
+                "Storage usage integer overflow for account {}",
+                account_id
+            ))
+        },
+    )?);
+    account.set_namespace_code_hash(namespace.clone(), *code.hash());
+    set_namespaced_code(state_update, account_id.clone(), namespace.clone(), &code);
+    precompile_contract(
+        &code,
+        Arc::clone(&apply_state.config.wasm_config),
+        apply_state.cache.as_deref(),
+    )
+    .ok();
+    Ok(())
+}
+
+/// Storage taken by a namespace in the account, besides its code.
+fn namespace_storage_usage(namespace: &AccountNamespace) -> StorageUsage {
+    (namespace.len() + std::mem::size_of::<CryptoHash>()) as StorageUsage
+}
+
+/// Amount burnt from the balance of the account deploying a global contract.
+///
+/// Every shard stores a copy of the code, but its storage is paid for only
+/// once, at the storage price of a contract deployed to an account.  The gas
+/// of the receipts distributing the code to the other shards is paid for up
+/// front, at the current gas price, as those receipts have no signer to
+/// charge.
+pub(crate) fn global_contract_deploy_cost(
+    config: &RuntimeConfig,
+    gas_price: Balance,
+    shard_layout: &ShardLayout,
+    code_len: usize,
+) -> Result<Balance, IntegerOverflowError> {
+    let storage_cost = config
+        .storage_amount_per_byte()
+        .checked_mul(code_len as Balance)
+        .ok_or(IntegerOverflowError)?;
+    let num_other_shards = shard_layout.shard_ids().count().saturating_sub(1) as Balance;
+    let distribution_cost = (global_contract_distribution_fee(config, code_len) as Balance)
+        .checked_mul(num_other_shards)
+        .and_then(|gas| gas.checked_mul(gas_price))
+        .ok_or(IntegerOverflowError)?;
+    storage_cost.checked_add(distribution_cost).ok_or(IntegerOverflowError)
+}
+
+/// Returns the receipt distributing a global contract to the first shard of
+/// `shard_layout` it was not delivered to yet, if there is one.
+///
+/// Resharding copies the global contracts of a shard to its children, so the
+/// shards only have to be tracked within one shard layout.
+pub(crate) fn next_global_contract_distribution(
+    shard_layout: &ShardLayout,
+    account_id: &AccountId,
+    code: Vec<u8>,
+    already_delivered_shards: Vec<ShardId>,
+) -> Option<Receipt> {
+    let target_shard =
+        shard_layout.shard_ids().find(|shard_id| !already_delivered_shards.contains(shard_id))?;
+    Some(Receipt::new_global_contract_distribution(
+        account_id.clone(),
+        code,
+        shard_layout.version(),
+        target_shard,
+        already_delivered_shards,
+    ))
+}
+
+pub(crate) fn action_deploy_global_contract(
+    state_update: &mut TrieUpdate,
+    apply_state: &ApplyState,
+    account: &mut Account,
+    account_id: &AccountId,
+    deploy_contract: &DeployGlobalContractAction,
+    result: &mut ActionResult,
+    epoch_info_provider: &(dyn EpochInfoProvider),
+) -> Result<(), RuntimeError> {
+    let _span = tracing::debug_span!(target: "runtime", "action_deploy_global_contract").entered();
+    let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+    let deploy_cost = global_contract_deploy_cost(
+        &apply_state.config,
+        apply_state.gas_price,
+        &shard_layout,
+        deploy_contract.code.len(),
+    )?;
+    let Some(amount) = account.amount().checked_sub(deploy_cost) else {
+        result.result = Err(ActionErrorKind::LackBalanceForState {
+            account_id: account_id.clone(),
+            amount: deploy_cost - account.amount(),
+        }
+        .into());
+        return Ok(());
+    };
+    account.set_amount(amount);
+
+    let code = ContractCode::new(deploy_contract.code.clone(), None);
+    set_global_code(state_update, &code);
+    precompile_contract(
+        &code,
+        Arc::clone(&apply_state.config.wasm_config),
+        apply_state.cache.as_deref(),
+    )
+    .ok();
+
+    result.new_receipts.extend(next_global_contract_distribution(
+        &shard_layout,
+        account_id,
+        deploy_contract.code.clone(),
+        vec![apply_state.shard_id],
+    ));
+    Ok(())
+}
+
+pub(crate) fn action_use_global_contract(
+    state_update: &mut TrieUpdate,
+    account: &mut Account,
+    account_id: &AccountId,
+    use_contract: &UseGlobalContractAction,
+    result: &mut ActionResult,
+) -> Result<(), StorageError> {
+    let _span = tracing::debug_span!(target: "runtime", "action_use_global_contract").entered();
+    let code_hash = use_contract.code_hash;
+    if !has_global_code(state_update, code_hash)? {
+        result.result = Err(ActionErrorKind::GlobalContractDoesNotExist { code_hash }.into());
+        return Ok(());
+    }
+    // The code of a global contract is not stored under the account, so the
+    // contract deployed to the account before, if any, is removed.
+    if let Some(prev_code) = get_code(state_update, account_id, Some(account.code_hash()))? {
+        state_update.remove(TrieKey::ContractCode { account_id: account_id.clone() });
+        account.set_storage_usage(
+            account.storage_usage().saturating_sub(prev_code.code().len() as u64),
+        );
+    }
+    account.set_global_contract_hash(code_hash);
+    Ok(())
+}
+
 pub(crate) fn action_delete_account(
     state_update: &mut TrieUpdate,
     account: &mut Option<Account>,
@@ -693,6 +920,12 @@ pub(crate) fn action_delete_account(
             debug_assert!(account_storage_usage > code_len);
             account_storage_usage = account_storage_usage.saturating_sub(code_len);
         }
+        // Like the main contract, the namespaced contracts are cheap to remove.
+        for (namespace, code_hash) in account.namespaces() {
+            let code = get_namespaced_code(state_update, account_id, namespace, Some(*code_hash))?;
+            let code_len = code.map(|code| code.code().len() as u64).unwrap_or_default();
+            account_storage_usage = account_storage_usage.saturating_sub(code_len);
+        }
         if account_storage_usage > Account::MAX_ACCOUNT_DELETION_STORAGE_USAGE {
             result.result = Err(ActionErrorKind::DeleteAccountWithLargeState {
                 account_id: account_id.clone(),
@@ -704,18 +937,84 @@ pub(crate) fn action_delete_account(
     // We use current amount as a pay out to beneficiary.
     let account_balance = account.as_ref().unwrap().amount();
     if account_balance > 0 {
-        result.new_receipts.push(Receipt::new_balance_refund(
-            &delete_account.beneficiary_id,
-            account_balance,
-            ReceiptPriority::NoPriority,
-        ));
+        result.push_refund(
+            Receipt::new_balance_refund(
+                &delete_account.beneficiary_id,
+                account_balance,
+                ReceiptPriority::NoPriority,
+            ),
+            RefundReason::DeletedAccountBalance,
+        );
     }
     remove_account(state_update, account_id)?;
+    if ProtocolFeature::IdempotencyKeys.enabled(current_protocol_version) {
+        remove_idempotency_keys(state_update, account_id)?;
+    }
     *actor_id = receipt.predecessor_id().clone();
     *account = None;
     Ok(())
 }
 
+/// Fails the receipt if the account executed a receipt with the same key from
+/// the same predecessor within the window, or if it can't remember more keys
+/// of the predecessor, and remembers the key otherwise.
+/// The key is forgotten along with the other changes of the receipt if a later
+/// action fails, so that the receipt can be retried.
+pub(crate) fn action_idempotency_key(
+    apply_state: &ApplyState,
+    state_update: &mut TrieUpdate,
+    account: &mut Account,
+    result: &mut ActionResult,
+    account_id: &AccountId,
+    predecessor_id: &AccountId,
+    idempotency_key: &IdempotencyKeyAction,
+) -> Result<(), StorageError> {
+    let storage_config = &apply_state.config.fees.storage_usage_config;
+    // Charged like any other record: the key, the value and the extra bytes.
+    let trie_key_len = TrieKey::IdempotencyKeys {
+        account_id: account_id.clone(),
+        predecessor_id: predecessor_id.clone(),
+    }
+    .len() as u64;
+    let record_storage_usage = |keys: &IdempotencyKeys| {
+        trie_key_len
+            + borsh::object_length(keys).unwrap() as u64
+            + storage_config.num_extra_bytes_record
+    };
+    let prev_keys = get_idempotency_keys(state_update, account_id, predecessor_id)?;
+    let prev_storage_usage = prev_keys.as_ref().map(record_storage_usage).unwrap_or_default();
+    let mut keys = prev_keys.unwrap_or_default();
+    keys.prune(apply_state.block_height);
+    let key_hash = IdempotencyKeys::key_hash(&idempotency_key.key);
+    if keys.contains(&key_hash) {
+        result.result =
+            Err(ActionErrorKind::DuplicateIdempotencyKey { account_id: account_id.clone() }.into());
+        return Ok(());
+    }
+    if keys.is_full() {
+        result.result =
+            Err(ActionErrorKind::IdempotencyKeysFull { account_id: account_id.clone() }.into());
+        return Ok(());
+    }
+    keys.insert(key_hash, apply_state.block_height);
+    set_idempotency_keys(state_update, account_id.clone(), predecessor_id.clone(), &keys);
+    // The storage stake of the keys is checked along with the rest of the
+    // state of the account once the receipt is applied.
+    account.set_storage_usage(
+        account
+            .storage_usage()
+            .saturating_sub(prev_storage_usage)
+            .checked_add(record_storage_usage(&keys))
+            .ok_or_else(|| {
+                StorageError::StorageInconsistentState(format!(
+                    "Storage usage integer overflow for account {}",
+                    account_id
+                ))
+            })?,
+    );
+    Ok(())
+}
+
 pub(crate) fn action_delete_key(
     fee_config: &RuntimeFeesConfig,
     state_update: &mut TrieUpdate,
@@ -871,6 +1170,73 @@ pub(crate) fn apply_delegate_action(
     Ok(())
 }
 
+/// Returns the receipts sent by an atomic batch on behalf of `sender_id`.
+pub(crate) fn atomic_batch_receipts(
+    sender_id: &AccountId,
+    action_receipt: &ActionReceipt,
+    atomic_batch: &AtomicBatchAction,
+) -> Vec<Receipt> {
+    atomic_batch
+        .receipts
+        .iter()
+        .map(|receipt| {
+            Receipt::V0(ReceiptV0 {
+                predecessor_id: sender_id.clone(),
+                receiver_id: receipt.receiver_id.clone(),
+                receipt_id: CryptoHash::default(),
+
+                receipt: ReceiptEnum::Action(ActionReceipt {
+                    signer_id: action_receipt.signer_id.clone(),
+                    signer_public_key: action_receipt.signer_public_key.clone(),
+                    gas_price: action_receipt.gas_price,
+                    output_data_receivers: vec![],
+                    input_data_ids: vec![],
+                    actions: receipt.get_actions(),
+                }),
+            })
+        })
+        .collect()
+}
+
+/// Sends the receipts of an atomic batch.  By the time the action is applied,
+/// the runtime has already checked that congestion control lets all of them
+/// through, see `Runtime::process_receipt`.
+pub(crate) fn apply_atomic_batch_action(
+    apply_state: &ApplyState,
+    action_receipt: &ActionReceipt,
+    sender_id: &AccountId,
+    atomic_batch: &AtomicBatchAction,
+    result: &mut ActionResult,
+) -> Result<(), RuntimeError> {
+    if sender_id != &action_receipt.signer_id {
+        result.result = Err(ActionErrorKind::AtomicBatchReceiverIsNotSigner {
+            receiver_id: sender_id.clone(),
+            signer_id: action_receipt.signer_id.clone(),
+        }
+        .into());
+        return Ok(());
+    }
+    if apply_state.block_height > atomic_batch.max_block_height {
+        result.result = Err(ActionErrorKind::AtomicBatchExpired.into());
+        return Ok(());
+    }
+
+    // As for delegate actions, the send fees of the receipts were prepaid and
+    // are burnt now, while their exec fees and gas are passed along with them.
+    let prepaid_send_fees = total_prepaid_send_fees(&apply_state.config, &action_receipt.actions)?;
+    result.gas_used = safe_add_gas(result.gas_used, prepaid_send_fees)?;
+    result.gas_burnt = safe_add_gas(result.gas_burnt, prepaid_send_fees)?;
+    // TODO(#8806): Support compute costs for actions. For now they match burnt gas.
+    result.compute_usage = safe_add_compute(result.compute_usage, prepaid_send_fees)?;
+    for new_receipt in atomic_batch_receipts(sender_id, action_receipt, atomic_batch) {
+        let required_gas = receipt_required_gas(apply_state, &new_receipt)?;
+        result.gas_used = safe_add_gas(result.gas_used, required_gas)?;
+        result.new_receipts.push(new_receipt);
+    }
+
+    Ok(())
+}
+
 /// Returns Gas amount is required to execute Receipt and all actions it contains
 fn receipt_required_gas(apply_state: &ApplyState, receipt: &Receipt) -> Result<Gas, RuntimeError> {
     Ok(match receipt.receipt() {
@@ -890,7 +1256,9 @@ fn receipt_required_gas(apply_state: &ApplyState, receipt: &Receipt) -> Result<G
 
             required_gas
         }
-        ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => 0,
+        ReceiptEnum::Data(_)
+        | ReceiptEnum::PromiseResume(_)
+        | ReceiptEnum::GlobalContractDistribution(_) => 0,
     })
 }
 
@@ -998,6 +1366,31 @@ fn validate_delegate_action_key(
             return Ok(());
         }
     };
+    // The relayer pays for the delegate action, so the allowance of a
+    // `LimitedFullAccess` key is left as is.
+    if let AccessKeyPermission::LimitedFullAccess(ref permission) = access_key.permission {
+        if let Some(expiry_height) = permission.expiry_height {
+            if apply_state.block_height > expiry_height {
+                result.result = Err(ActionErrorKind::DelegateActionAccessKeyError(
+                    InvalidAccessKeyError::AccessKeyExpired {
+                        account_id: delegate_action.sender_id.clone(),
+                        public_key: delegate_action.public_key.clone().into(),
+                        expiry_height,
+                    },
+                )
+                .into());
+                return Ok(());
+            }
+        }
+        if let Err(err) = validate_limited_full_access_actions(
+            &delegate_action.sender_id,
+            &delegate_action.receiver_id,
+            &actions,
+        ) {
+            result.result = Err(ActionErrorKind::DelegateActionAccessKeyError(err).into());
+            return Ok(());
+        }
+    }
 
     set_access_key(
         state_update,
@@ -1016,7 +1409,13 @@ pub(crate) fn check_actor_permissions(
     account_id: &AccountId,
 ) -> Result<(), ActionError> {
     match action {
-        Action::DeployContract(_) | Action::Stake(_) | Action::AddKey(_) | Action::DeleteKey(_) => {
+        Action::DeployContract(_)
+        | Action::DeployNamespacedContract(_)
+        | Action::DeployGlobalContract(_)
+        | Action::UseGlobalContract(_)
+        | Action::Stake(_)
+        | Action::AddKey(_)
+        | Action::DeleteKey(_) => {
             if actor_id != account_id {
                 return Err(ActionErrorKind::ActorNoPermission {
                     account_id: account_id.clone(),
@@ -1042,7 +1441,8 @@ pub(crate) fn check_actor_permissions(
             }
         }
         Action::CreateAccount(_) | Action::FunctionCall(_) | Action::Transfer(_) => (),
-        Action::Delegate(_) => (),
+        Action::NamespacedFunctionCall(_) => (),
+        Action::Delegate(_) | Action::IdempotencyKey(_) | Action::AtomicBatch(_) => (),
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
         Action::NonrefundableStorageTransfer(_) => (),
     };
@@ -1128,7 +1528,13 @@ pub(crate) fn check_account_existence(
         | Action::AddKey(_)
         | Action::DeleteKey(_)
         | Action::DeleteAccount(_)
-        | Action::Delegate(_) => {
+        | Action::Delegate(_)
+        | Action::IdempotencyKey(_)
+        | Action::AtomicBatch(_)
+        | Action::DeployNamespacedContract(_)
+        | Action::NamespacedFunctionCall(_)
+        | Action::DeployGlobalContract(_)
+        | Action::UseGlobalContract(_) => {
             if account.is_none() {
                 return Err(ActionErrorKind::AccountDoesNotExist {
                     account_id: account_id.clone(),
@@ -1185,12 +1591,13 @@ mod tests {
 
     use super::*;
     use crate::near_primitives::shard_layout::ShardUId;
-    use near_primitives::account::FunctionCallPermission;
+    use near_primitives::account::{FunctionCallPermission, LimitedFullAccessPermission};
     use near_primitives::action::delegate::NonDelegateAction;
     use near_primitives::congestion_info::BlockCongestionInfo;
     use near_primitives::errors::InvalidAccessKeyError;
     use near_primitives::hash::hash;
     use near_primitives::runtime::migration_data::MigrationFlags;
+    use near_primitives::test_utils::MockEpochInfoProvider;
     use near_primitives::transaction::CreateAccountAction;
     use near_primitives::trie_key::TrieKey;
     use near_primitives::types::{EpochId, StateChangeCause};
@@ -1439,6 +1846,7 @@ mod tests {
             migration_data: Arc::default(),
             migration_flags: MigrationFlags::default(),
             congestion_info: BlockCongestionInfo::default(),
+            host_call_tracer: None,
         }
     }
 
@@ -1462,6 +1870,208 @@ mod tests {
 
         tries.new_trie_update(ShardUId::single_shard(), root)
     }
+
+    #[test]
+    fn test_try_refund_allowance_limited_full_access() {
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        let public_key = PublicKey::from_seed(near_crypto::KeyType::ED25519, "alice");
+        let access_key = AccessKey {
+            nonce: 0,
+            permission: AccessKeyPermission::LimitedFullAccess(LimitedFullAccessPermission {
+                allowance: 100,
+                expiry_height: None,
+            }),
+        };
+        let mut state_update = setup_account(&account_id, &public_key, &access_key);
+
+        try_refund_allowance(&mut state_update, &account_id, &public_key, 50).unwrap();
+        let access_key = get_access_key(&state_update, &account_id, &public_key).unwrap().unwrap();
+        let AccessKeyPermission::LimitedFullAccess(permission) = access_key.permission else {
+            panic!("unexpected permission {:?}", access_key.permission);
+        };
+        assert_eq!(permission.allowance, 150);
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let tries = TestTriesBuilder::new().build();
+        let mut state_update =
+            tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+        let account_id: AccountId = "bridge.near".parse().unwrap();
+        let relayer_id: AccountId = "relayer.near".parse().unwrap();
+        let mut account = Account::new(100, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION);
+        let key = IdempotencyKeyAction { key: b"transfer-1".to_vec() };
+        let mut apply = |block_height, predecessor_id: &AccountId, account: &mut Account| {
+            let mut result = ActionResult::default();
+            action_idempotency_key(
+                &create_apply_state(block_height),
+                &mut state_update,
+                account,
+                &mut result,
+                &account_id,
+                predecessor_id,
+                &key,
+            )
+            .unwrap();
+            result.result
+        };
+
+        let storage_config = RuntimeConfig::test().fees.storage_usage_config.clone();
+        let other_id: AccountId = "other.near".parse().unwrap();
+        let record_storage_usage = |predecessor_id: &AccountId, num_keys: usize| {
+            let trie_key = TrieKey::IdempotencyKeys {
+                account_id: account_id.clone(),
+                predecessor_id: predecessor_id.clone(),
+            };
+            // A u32 length followed by a 32-byte hash and a u64 height per key.
+            let value_len = 4 + num_keys * (32 + 8);
+            (trie_key.len() + value_len) as u64 + storage_config.num_extra_bytes_record
+        };
+
+        assert!(apply(10, &relayer_id, &mut account).is_ok());
+        assert_eq!(account.storage_usage(), 100 + record_storage_usage(&relayer_id, 1));
+        // A retry within the window fails.
+        assert_eq!(
+            apply(11, &relayer_id, &mut account),
+            Err(ActionErrorKind::DuplicateIdempotencyKey { account_id: account_id.clone() }.into())
+        );
+        // The keys of each predecessor are separate.
+        assert!(apply(11, &other_id, &mut account).is_ok());
+        let storage_usage = account.storage_usage();
+        assert_eq!(
+            storage_usage,
+            100 + record_storage_usage(&relayer_id, 1) + record_storage_usage(&other_id, 1)
+        );
+        // Once the window ends the key is forgotten, the storage of the pruned
+        // keys is released.
+        let block_height = 10 + IdempotencyKeys::WINDOW;
+        assert!(apply(block_height, &relayer_id, &mut account).is_ok());
+        assert_eq!(account.storage_usage(), storage_usage);
+    }
+
+    /// Keys are never forgotten before their window ends, new keys of a
+    /// predecessor are rejected instead while the account remembers too many
+    /// of them.  Other predecessors are not affected.
+    #[test]
+    fn test_idempotency_keys_full() {
+        let tries = TestTriesBuilder::new().build();
+        let mut state_update =
+            tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+        let account_id: AccountId = "bridge.near".parse().unwrap();
+        let relayer_id: AccountId = "relayer.near".parse().unwrap();
+        let other_id: AccountId = "other.near".parse().unwrap();
+        let mut account = Account::new(100, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION);
+        let mut apply = |block_height, predecessor_id: &AccountId, i: usize| {
+            let mut result = ActionResult::default();
+            action_idempotency_key(
+                &create_apply_state(block_height),
+                &mut state_update,
+                &mut account,
+                &mut result,
+                &account_id,
+                predecessor_id,
+                &IdempotencyKeyAction { key: format!("transfer-{i}").into_bytes() },
+            )
+            .unwrap();
+            result.result
+        };
+
+        for i in 0..IdempotencyKeys::MAX_ENTRIES {
+            assert!(apply(10, &relayer_id, i).is_ok());
+        }
+        assert_eq!(
+            apply(11, &relayer_id, IdempotencyKeys::MAX_ENTRIES),
+            Err(ActionErrorKind::IdempotencyKeysFull { account_id: account_id.clone() }.into())
+        );
+        // The first key is still remembered, so its retry doesn't run twice.
+        assert_eq!(
+            apply(11, &relayer_id, 0),
+            Err(ActionErrorKind::DuplicateIdempotencyKey { account_id: account_id.clone() }.into())
+        );
+        // Another predecessor's keys are still accepted.
+        assert!(apply(11, &other_id, IdempotencyKeys::MAX_ENTRIES).is_ok());
+        assert!(apply(11, &other_id, 0).is_ok());
+        // Once the window of the keys ends, new keys are accepted again.
+        assert!(
+            apply(10 + IdempotencyKeys::WINDOW, &relayer_id, IdempotencyKeys::MAX_ENTRIES).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_global_contract() {
+        let tries = TestTriesBuilder::new().build();
+        let mut state_update =
+            tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+        let apply_state = create_apply_state(1);
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        let own_code = ContractCode::new(vec![1; 10], None);
+        set_code(&mut state_update, account_id.clone(), &own_code);
+        let amount = 10u128.pow(24);
+        let mut account = Account::new(amount, 0, 0, *own_code.hash(), 110, PROTOCOL_VERSION);
+
+        let global_code = vec![2; 20];
+        let code_hash = hash(&global_code);
+        let mut result = ActionResult::default();
+        action_deploy_global_contract(
+            &mut state_update,
+            &apply_state,
+            &mut account,
+            &account_id,
+            &DeployGlobalContractAction { code: global_code.clone() },
+            &mut result,
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+        assert!(result.result.is_ok());
+        // The storage is paid for by burning tokens, not by staking them.
+        let storage_cost = apply_state.config.storage_amount_per_byte() * 20;
+        assert_eq!(account.amount(), amount - storage_cost);
+        // With more shards, the distribution to the other shards is paid for
+        // up front as well.
+        let distribution_cost = global_contract_distribution_fee(&apply_state.config, 20)
+            as Balance
+            * apply_state.gas_price;
+        assert_eq!(
+            global_contract_deploy_cost(
+                &apply_state.config,
+                apply_state.gas_price,
+                &ShardLayout::v0(4, 0),
+                20
+            )
+            .unwrap(),
+            storage_cost + 3 * distribution_cost
+        );
+        assert_eq!(account.storage_usage(), 110);
+        assert!(has_global_code(&state_update, code_hash).unwrap());
+        // With a single shard there is nowhere to distribute the code to.
+        assert!(result.new_receipts.is_empty());
+
+        let mut use_contract = |code_hash, account: &mut Account| {
+            let mut result = ActionResult::default();
+            action_use_global_contract(
+                &mut state_update,
+                account,
+                &account_id,
+                &UseGlobalContractAction { code_hash },
+                &mut result,
+            )
+            .unwrap();
+            result.result
+        };
+        let unknown_hash = hash(b"unknown");
+        assert_eq!(
+            use_contract(unknown_hash, &mut account),
+            Err(ActionErrorKind::GlobalContractDoesNotExist { code_hash: unknown_hash }.into())
+        );
+        assert!(use_contract(code_hash, &mut account).is_ok());
+        assert!(account.global_contract());
+        assert_eq!(account.code_hash(), code_hash);
+        // The contract of the account is removed along with its storage.
+        assert_eq!(account.storage_usage(), 100);
+        assert!(get_code(&state_update, &account_id, None).unwrap().is_none());
+        assert_eq!(state_update.get_global_code(code_hash).unwrap().code(), global_code);
+    }
+
     fn non_delegate_action(action: Action) -> NonDelegateAction {
         NonDelegateAction::try_from(action)
             .expect("cannot violate type invariants, not even in test")
@@ -1,12 +1,15 @@
 use crate::near_primitives::shard_layout::ShardUId;
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
+use near_primitives::challenge::TrieValue;
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{
     AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::ViewStateResult;
+use near_primitives::views::{
+    AccessKeyList, AccessKeyListOptions, ViewStateOptions, ViewStateResult,
+};
 use near_vm_runner::ContractCode;
 
 /// Adapter for querying runtime.
@@ -18,6 +21,13 @@ pub trait ViewRuntimeAdapter {
         account_id: &AccountId,
     ) -> Result<Account, crate::state_viewer::errors::ViewAccountError>;
 
+    fn view_account_with_proof(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        account_id: &AccountId,
+    ) -> Result<(Account, Vec<TrieValue>), crate::state_viewer::errors::ViewAccountError>;
+
     fn view_contract_code(
         &self,
         shard_uid: &ShardUId,
@@ -51,12 +61,13 @@ pub trait ViewRuntimeAdapter {
         public_key: &PublicKey,
     ) -> Result<AccessKey, crate::state_viewer::errors::ViewAccessKeyError>;
 
-    fn view_access_keys(
+    fn view_access_key_list(
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
         account_id: &AccountId,
-    ) -> Result<Vec<(PublicKey, AccessKey)>, crate::state_viewer::errors::ViewAccessKeyError>;
+        options: &AccessKeyListOptions,
+    ) -> Result<AccessKeyList, crate::state_viewer::errors::ViewAccessKeyError>;
 
     fn view_state(
         &self,
@@ -65,5 +76,6 @@ pub trait ViewRuntimeAdapter {
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
+        options: &ViewStateOptions,
     ) -> Result<ViewStateResult, crate::state_viewer::errors::ViewStateError>;
 }
@@ -10,7 +10,10 @@ use num_traits::pow::Pow;
 // Just re-exporting RuntimeConfig for backwards compatibility.
 use near_parameters::{transfer_exec_fee, transfer_send_fee, ActionCosts, RuntimeConfig};
 pub use near_primitives::num_rational::Rational32;
-use near_primitives::transaction::{Action, DeployContractAction, Transaction};
+use near_primitives::transaction::{
+    Action, DeployContractAction, DeployGlobalContractAction, DeployNamespacedContractAction,
+    IdempotencyKeyAction, Transaction,
+};
 use near_primitives::types::{AccountId, Balance, Compute, Gas};
 
 /// Describes the cost of converting this transaction into a receipt.
@@ -26,7 +29,8 @@ pub struct TransactionCost {
     pub receipt_gas_price: Balance,
     /// Total costs in tokens for this transaction (including all deposits).
     pub total_cost: Balance,
-    /// The amount of tokens burnt by converting this transaction to a receipt.
+    /// The amount of tokens burnt by converting this transaction to a receipt, including the
+    /// priority fee.
     pub burnt_amount: Balance,
 }
 
@@ -132,7 +136,7 @@ pub fn total_send_fees(
                                 .fee(ActionCosts::add_function_call_key_byte)
                                 .send_fee(sender_is_receiver)
                 }
-                AccessKeyPermission::FullAccess => {
+                AccessKeyPermission::FullAccess | AccessKeyPermission::LimitedFullAccess(_) => {
                     fees.fee(ActionCosts::add_full_access_key).send_fee(sender_is_receiver)
                 }
             },
@@ -150,6 +154,46 @@ pub fn total_send_fees(
                         &delegate_action.receiver_id,
                     )?
             }
+            // The key is charged like function call arguments, the storage of
+            // its hash is paid for by the storage stake of the receiver.
+            IdempotencyKey(IdempotencyKeyAction { key }) => {
+                fees.fee(ActionCosts::function_call_byte).send_fee(sender_is_receiver)
+                    * key.len() as u64
+            }
+            // Sending the receipts of the batch is prepaid, see
+            // `total_prepaid_send_fees`, so that it can be refunded.
+            AtomicBatch(_) => {
+                fees.fee(ActionCosts::new_action_receipt).send_fee(sender_is_receiver)
+            }
+            // The namespace is charged per byte like the code or the
+            // arguments it comes with.
+            DeployNamespacedContract(action) => {
+                let DeployNamespacedContractAction { namespace, code } = action.as_ref();
+                let num_bytes = namespace.len() as u64 + code.len() as u64;
+                fees.fee(ActionCosts::deploy_contract_base).send_fee(sender_is_receiver)
+                    + fees.fee(ActionCosts::deploy_contract_byte).send_fee(sender_is_receiver)
+                        * num_bytes
+            }
+            NamespacedFunctionCall(action) => {
+                let function_call_action = &action.function_call;
+                let num_bytes = action.namespace.len() as u64
+                    + function_call_action.method_name.as_bytes().len() as u64
+                    + function_call_action.args.len() as u64;
+                fees.fee(ActionCosts::function_call_base).send_fee(sender_is_receiver)
+                    + fees.fee(ActionCosts::function_call_byte).send_fee(sender_is_receiver)
+                        * num_bytes
+            }
+            // The storage of a global contract is paid for by burning
+            // tokens, the gas fees are the ones of a regular deployment.
+            DeployGlobalContract(DeployGlobalContractAction { code }) => {
+                let num_bytes = code.len() as u64;
+                fees.fee(ActionCosts::deploy_contract_base).send_fee(sender_is_receiver)
+                    + fees.fee(ActionCosts::deploy_contract_byte).send_fee(sender_is_receiver)
+                        * num_bytes
+            }
+            UseGlobalContract(_) => {
+                fees.fee(ActionCosts::deploy_contract_base).send_fee(sender_is_receiver)
+            }
         };
         result = safe_add_gas(result, delta)?;
     }
@@ -157,10 +201,11 @@ pub fn total_send_fees(
 }
 
 /// Total sum of gas that needs to be burnt to send the inner actions of DelegateAction
+/// and the receipts of AtomicBatchAction.
 ///
-/// This is only relevant for DelegateAction, where the send fees of the inner actions
-/// need to be prepaid. All other actions burn send fees directly, so calling this function
-/// with other actions will return 0.
+/// This is only relevant for DelegateAction and AtomicBatchAction, where the send fees
+/// of the inner actions need to be prepaid. All other actions burn send fees directly,
+/// so calling this function with other actions will return 0.
 pub fn total_prepaid_send_fees(
     config: &RuntimeConfig,
     actions: &[Action],
@@ -180,6 +225,27 @@ pub fn total_prepaid_send_fees(
                     &delegate_action.receiver_id,
                 )?
             }
+            // The sender isn't known here, the receipts are charged as if
+            // they were sent to other accounts.
+            AtomicBatch(atomic_batch) => {
+                let mut batch_fees = 0;
+                for receipt in &atomic_batch.receipts {
+                    batch_fees = safe_add_gas(
+                        batch_fees,
+                        config.fees.fee(ActionCosts::new_action_receipt).send_fee(false),
+                    )?;
+                    batch_fees = safe_add_gas(
+                        batch_fees,
+                        total_send_fees(
+                            config,
+                            false,
+                            &receipt.get_actions(),
+                            &receipt.receiver_id,
+                        )?,
+                    )?;
+                }
+                batch_fees
+            }
             _ => 0,
         };
         result = safe_add_gas(result, delta)?;
@@ -234,16 +300,47 @@ pub fn exec_fee(config: &RuntimeConfig, action: &Action, receiver_id: &AccountId
                 fees.fee(ActionCosts::add_function_call_key_base).exec_fee()
                     + num_bytes * fees.fee(ActionCosts::add_function_call_key_byte).exec_fee()
             }
-            AccessKeyPermission::FullAccess => {
+            AccessKeyPermission::FullAccess | AccessKeyPermission::LimitedFullAccess(_) => {
                 fees.fee(ActionCosts::add_full_access_key).exec_fee()
             }
         },
         DeleteKey(_) => fees.fee(ActionCosts::delete_key).exec_fee(),
         DeleteAccount(_) => fees.fee(ActionCosts::delete_account).exec_fee(),
         Delegate(_) => fees.fee(ActionCosts::delegate).exec_fee(),
+        IdempotencyKey(IdempotencyKeyAction { key }) => {
+            fees.fee(ActionCosts::function_call_byte).exec_fee() * key.len() as u64
+        }
+        AtomicBatch(_) => fees.fee(ActionCosts::new_action_receipt).exec_fee(),
+        DeployNamespacedContract(action) => {
+            let DeployNamespacedContractAction { namespace, code } = action.as_ref();
+            let num_bytes = namespace.len() as u64 + code.len() as u64;
+            fees.fee(ActionCosts::deploy_contract_base).exec_fee()
+                + fees.fee(ActionCosts::deploy_contract_byte).exec_fee() * num_bytes
+        }
+        NamespacedFunctionCall(action) => {
+            let function_call_action = &action.function_call;
+            let num_bytes = action.namespace.len() as u64
+                + function_call_action.method_name.as_bytes().len() as u64
+                + function_call_action.args.len() as u64;
+            fees.fee(ActionCosts::function_call_base).exec_fee()
+                + fees.fee(ActionCosts::function_call_byte).exec_fee() * num_bytes
+        }
+        DeployGlobalContract(DeployGlobalContractAction { code }) => {
+            global_contract_distribution_fee(config, code.len())
+        }
+        UseGlobalContract(_) => fees.fee(ActionCosts::deploy_contract_base).exec_fee(),
     }
 }
 
+/// Gas burnt by storing the code of a global contract in a shard, i.e. by the
+/// `DeployGlobalContract` action in its own shard and by a
+/// `GlobalContractDistribution` receipt in each of the other shards.
+pub fn global_contract_distribution_fee(config: &RuntimeConfig, code_len: usize) -> Gas {
+    let fees = &config.fees;
+    fees.fee(ActionCosts::deploy_contract_base).exec_fee()
+        + fees.fee(ActionCosts::deploy_contract_byte).exec_fee() * code_len as u64
+}
+
 /// Returns transaction costs for a given transaction.
 pub fn tx_cost(
     config: &RuntimeConfig,
@@ -301,7 +398,10 @@ pub fn tx_cost(
         gas_remaining,
         total_prepaid_exec_fees(config, transaction.actions(), transaction.receiver_id())?,
     )?;
-    let burnt_amount = safe_gas_to_balance(gas_price, gas_burnt)?;
+    let burnt_amount = safe_add_balance(
+        safe_gas_to_balance(gas_price, gas_burnt)?,
+        transaction.priority_fee_amount(),
+    )?;
     let remaining_gas_amount = safe_gas_to_balance(receipt_gas_price, gas_remaining)?;
     let mut total_cost = safe_add_balance(burnt_amount, remaining_gas_amount)?;
     total_cost = safe_add_balance(total_cost, total_deposit(&transaction.actions())?)?;
@@ -331,6 +431,16 @@ pub fn total_prepaid_exec_fees(
                 exec_fee(config, action, &signed_delegate_action.delegate_action.receiver_id),
             )?;
             delta = safe_add_gas(delta, fees.fee(ActionCosts::new_action_receipt).exec_fee())?;
+        } else if let Action::AtomicBatch(atomic_batch) = action {
+            // The same as for Action::Delegate, for each of the receipts.
+            delta = exec_fee(config, action, receiver_id);
+            for receipt in &atomic_batch.receipts {
+                delta = safe_add_gas(
+                    delta,
+                    total_prepaid_exec_fees(config, &receipt.get_actions(), &receipt.receiver_id)?,
+                )?;
+                delta = safe_add_gas(delta, fees.fee(ActionCosts::new_action_receipt).exec_fee())?;
+            }
         } else {
             delta = exec_fee(config, action, receiver_id);
         }
@@ -349,6 +459,13 @@ pub fn total_deposit(actions: &[Action]) -> Result<Balance, IntegerOverflowError
             // refunded to Sender of DelegateAction
             let actions = signed_delegate_action.delegate_action.get_actions();
             action_balance = total_deposit(&actions)?;
+        } else if let Action::AtomicBatch(atomic_batch) = action {
+            let mut batch_balance: Balance = 0;
+            for receipt in &atomic_batch.receipts {
+                batch_balance =
+                    safe_add_balance(batch_balance, total_deposit(&receipt.get_actions())?)?;
+            }
+            action_balance = batch_balance;
         } else {
             action_balance = action.get_deposit_balance();
         }
@@ -366,6 +483,12 @@ pub fn total_prepaid_gas(actions: &[Action]) -> Result<Gas, IntegerOverflowError
         if let Action::Delegate(signed_delegate_action) = action {
             let actions = signed_delegate_action.delegate_action.get_actions();
             action_gas = total_prepaid_gas(&actions)?;
+        } else if let Action::AtomicBatch(atomic_batch) = action {
+            let mut batch_gas: Gas = 0;
+            for receipt in &atomic_batch.receipts {
+                batch_gas = safe_add_gas(batch_gas, total_prepaid_gas(&receipt.get_actions())?)?;
+            }
+            action_gas = batch_gas;
         } else {
             action_gas = action.get_prepaid_gas();
         }
@@ -1,5 +1,6 @@
 use crate::config::{
-    safe_add_gas, total_prepaid_exec_fees, total_prepaid_gas, total_prepaid_send_fees,
+    global_contract_distribution_fee, safe_add_gas, total_prepaid_exec_fees, total_prepaid_gas,
+    total_prepaid_send_fees,
 };
 use crate::ApplyState;
 use near_parameters::{ActionCosts, RuntimeConfig};
@@ -162,6 +163,23 @@ impl<'a> ReceiptSink<'a> {
             ),
         }
     }
+
+    /// Whether all the receipts would be forwarded right away rather than
+    /// buffered, given the receipts forwarded so far.  Used by atomic
+    /// batches, which send their receipts only all together.
+    pub(crate) fn can_forward_all(
+        &self,
+        receipts: &[Receipt],
+        apply_state: &ApplyState,
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Result<bool, RuntimeError> {
+        match self {
+            ReceiptSink::V1(_inner) => Ok(true),
+            ReceiptSink::V2(inner) => {
+                inner.can_forward_all(receipts, apply_state, epoch_info_provider)
+            }
+        }
+    }
 }
 
 impl ReceiptSinkV1<'_> {
@@ -232,8 +250,8 @@ impl ReceiptSinkV2<'_> {
         state_update: &mut TrieUpdate,
         epoch_info_provider: &dyn EpochInfoProvider,
     ) -> Result<(), RuntimeError> {
-        let shard = epoch_info_provider
-            .account_id_to_shard_id(receipt.receiver_id(), &apply_state.epoch_id)?;
+        let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+        let shard = receipt.receiver_shard_id(&shard_layout);
         match Self::try_forward(
             receipt,
             shard,
@@ -249,6 +267,38 @@ impl ReceiptSinkV2<'_> {
         Ok(())
     }
 
+    fn can_forward_all(
+        &self,
+        receipts: &[Receipt],
+        apply_state: &ApplyState,
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Result<bool, RuntimeError> {
+        let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+        let mut needed: HashMap<ShardId, (Gas, u64)> = HashMap::new();
+        for receipt in receipts {
+            let shard = receipt.receiver_shard_id(&shard_layout);
+            // Receipts are forwarded in order, so nothing can pass the ones
+            // already buffered for the shard.
+            if self.outgoing_buffers.buffer_len(shard).unwrap_or(0) > 0 {
+                return Ok(false);
+            }
+            let (gas, size) = needed.entry(shard).or_default();
+            *gas = safe_add_gas(*gas, receipt_congestion_gas(receipt, &apply_state.config)?)?;
+            *size = size.checked_add(receipt_size(receipt)? as u64).ok_or(IntegerOverflowError)?;
+        }
+        Ok(needed.into_iter().all(|(shard, (gas, size))| {
+            // The same defaults as in `try_forward`.
+            let (gas_limit, size_limit) = match self.outgoing_limit.get(&shard) {
+                Some(limit) => (limit.gas, limit.size),
+                None => (
+                    Gas::MAX,
+                    apply_state.config.congestion_control_config.outgoing_receipts_usual_size_limit,
+                ),
+            };
+            gas_limit > gas && size_limit > size
+        }))
+    }
+
     /// Forward a receipt if possible and return whether it was forwarded or
     /// not.
     ///
@@ -350,6 +400,11 @@ pub(crate) fn receipt_congestion_gas(
             // of it without expensive state lookups.
             Ok(0)
         }
+        ReceiptEnum::GlobalContractDistribution(distribution) => {
+            // The receipt burns the fee of storing the code in the shard,
+            // which was paid for by the action that deployed it.
+            Ok(global_contract_distribution_fee(config, distribution.code.len()))
+        }
     }
 }
 
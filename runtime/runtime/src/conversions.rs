@@ -82,6 +82,12 @@ impl Convert<near_store::trie::TrieNodesCount> for near_vm_runner::logic::TrieNo
     }
 }
 
+impl Convert<near_vm_runner::logic::ContractEvent> for near_primitives::events::ContractEvent {
+    fn convert(other: near_vm_runner::logic::ContractEvent) -> Self {
+        Self { topics: other.topics, data: other.data }
+    }
+}
+
 mod profile_data_v3 {
     use near_vm_runner::ProfileDataV3 as From;
     impl super::Convert<From> for near_primitives::profile_data_v3::ProfileDataV3 {
@@ -1,11 +1,11 @@
 use crate::conversions::Convert;
 use crate::receipt_manager::ReceiptManager;
 use near_primitives::account::id::AccountType;
-use near_primitives::account::Account;
+use near_primitives::account::{Account, AccountNamespace};
 use near_primitives::checked_feature;
 use near_primitives::errors::{EpochError, StorageError};
 use near_primitives::hash::CryptoHash;
-use near_primitives::trie_key::{trie_key_parsers, TrieKey};
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{AccountId, Balance, EpochId, EpochInfoProvider, Gas, TrieCacheMode};
 use near_primitives::utils::create_receipt_id_from_action_hash;
 use near_primitives::version::ProtocolVersion;
@@ -22,6 +22,9 @@ pub struct RuntimeExt<'a> {
     pub(crate) receipt_manager: &'a mut ReceiptManager,
     account_id: AccountId,
     account: Account,
+    /// Namespace of the called contract, which scopes its storage. `None`
+    /// for the main contract of the account.
+    namespace: Option<AccountNamespace>,
     action_hash: CryptoHash,
     data_count: u64,
     epoch_id: EpochId,
@@ -65,6 +68,7 @@ impl<'a> RuntimeExt<'a> {
         receipt_manager: &'a mut ReceiptManager,
         account_id: AccountId,
         account: Account,
+        namespace: Option<AccountNamespace>,
         action_hash: CryptoHash,
         epoch_id: EpochId,
         prev_block_hash: CryptoHash,
@@ -77,6 +81,7 @@ impl<'a> RuntimeExt<'a> {
             receipt_manager,
             account_id,
             account,
+            namespace,
             action_hash,
             data_count: 0,
             epoch_id,
@@ -98,7 +103,16 @@ impl<'a> RuntimeExt<'a> {
     }
 
     pub fn create_storage_key(&self, key: &[u8]) -> TrieKey {
-        TrieKey::ContractData { account_id: self.account_id.clone(), key: key.to_vec() }
+        match &self.namespace {
+            None => {
+                TrieKey::ContractData { account_id: self.account_id.clone(), key: key.to_vec() }
+            }
+            Some(namespace) => TrieKey::NamespacedContractData {
+                account_id: self.account_id.clone(),
+                namespace: namespace.clone(),
+                key: key.to_vec(),
+            },
+        }
     }
 
     #[inline]
@@ -159,24 +173,26 @@ impl<'a> External for RuntimeExt<'a> {
     }
 
     fn storage_remove_subtree(&mut self, prefix: &[u8]) -> ExtResult<()> {
+        // The raw keys of the contract storage start with the same bytes as
+        // the key of the empty data key, followed by the data key itself.
+        let data_key_offset = self.create_storage_key(&[]).len();
         let data_keys = self
             .trie_update
-            .iter(&trie_key_parsers::get_raw_prefix_for_contract_data(&self.account_id, prefix))
+            .iter(&self.create_storage_key(prefix).to_vec())
             .map_err(wrap_storage_error)?
             .map(|raw_key| {
-                trie_key_parsers::parse_data_key_from_contract_data_key(&raw_key?, &self.account_id)
-                    .map_err(|_e| {
-                        StorageError::StorageInconsistentState(
-                            "Can't parse data key from raw key for ContractData".to_string(),
-                        )
-                    })
-                    .map(Vec::from)
+                let raw_key = raw_key?;
+                raw_key.get(data_key_offset..).map(Vec::from).ok_or_else(|| {
+                    StorageError::StorageInconsistentState(
+                        "Can't parse data key from raw key for ContractData".to_string(),
+                    )
+                })
             })
             .collect::<Result<Vec<_>, _>>()
             .map_err(wrap_storage_error)?;
         for key in data_keys {
-            self.trie_update
-                .remove(TrieKey::ContractData { account_id: self.account_id.clone(), key });
+            let storage_key = self.create_storage_key(&key);
+            self.trie_update.remove(storage_key);
         }
         Ok(())
     }
@@ -365,12 +381,17 @@ pub(crate) struct RuntimeContractExt<'a> {
     pub(crate) trie_update: &'a TrieUpdate,
     pub(crate) account_id: &'a AccountId,
     pub(crate) account: &'a Account,
+    /// Namespace of the contract, `None` for the main contract of the account.
+    pub(crate) namespace: Option<&'a AccountNamespace>,
     pub(crate) current_protocol_version: ProtocolVersion,
 }
 
 impl<'a> Contract for RuntimeContractExt<'a> {
     fn hash(&self) -> CryptoHash {
-        self.account.code_hash()
+        match self.namespace {
+            None => self.account.code_hash(),
+            Some(namespace) => self.account.namespace_code_hash(namespace).unwrap_or_default(),
+        }
     }
 
     fn get_code(&self) -> Option<Arc<ContractCode>> {
@@ -378,7 +399,8 @@ impl<'a> Contract for RuntimeContractExt<'a> {
         let code_hash = self.hash();
         let version = self.current_protocol_version;
 
-        if checked_feature!("stable", EthImplicitAccounts, version)
+        if self.namespace.is_none()
+            && checked_feature!("stable", EthImplicitAccounts, version)
             && account_id.get_account_type() == AccountType::EthImplicitAccount
         {
             // Accounts that look like eth implicit accounts and have existed prior to the
@@ -396,6 +418,15 @@ impl<'a> Contract for RuntimeContractExt<'a> {
             false => None,
         };
         let _guard = self.trie_update.with_trie_cache_mode(mode);
-        self.trie_update.get_code(self.account_id.clone(), code_hash).map(Arc::new)
+        let code = match self.namespace {
+            None if self.account.global_contract() => self.trie_update.get_global_code(code_hash),
+            None => self.trie_update.get_code(self.account_id.clone(), code_hash),
+            Some(namespace) => self.trie_update.get_namespaced_code(
+                self.account_id.clone(),
+                namespace.clone(),
+                code_hash,
+            ),
+        };
+        code.map(Arc::new)
     }
 }
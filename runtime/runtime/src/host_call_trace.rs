@@ -0,0 +1,126 @@
+//! Traces of the host function calls made by the contracts, for the contract
+//! developers profiling the gas usage on a local node.
+//!
+//! Tracing is off until it is enabled for some receivers, e.g. through the
+//! debug RPC. Then every function call to those receivers records the host
+//! functions it calls, with their arguments and the gas burnt before and after
+//! each call. The traces of the most recent receipts are kept in memory.
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::AccountId;
+use near_vm_runner::logic::HostCallTrace;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// Number of receipts whose traces are kept.
+const MAX_TRACED_RECEIPTS: usize = 1000;
+
+/// Host function calls made by one function call action of a receipt.
+#[derive(Clone, Debug)]
+pub struct FunctionCallTrace {
+    /// Hash of the action, unique within the receipt.
+    pub action_hash: CryptoHash,
+    pub receiver_id: AccountId,
+    pub method_name: String,
+    pub host_calls: HostCallTrace,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// Receivers whose function calls are traced.
+    receivers: HashSet<AccountId>,
+    traces: lru::LruCache<CryptoHash, Vec<FunctionCallTrace>>,
+}
+
+/// Records the host function calls of the receipts sent to the chosen
+/// receivers. Shared by the runtime, which fills it, and the debug RPC.
+#[derive(Debug)]
+pub struct HostCallTracer {
+    inner: Mutex<Inner>,
+}
+
+impl Default for HostCallTracer {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                receivers: HashSet::new(),
+                traces: lru::LruCache::new(NonZeroUsize::new(MAX_TRACED_RECEIPTS).unwrap()),
+            }),
+        }
+    }
+}
+
+impl HostCallTracer {
+    /// Traces the function calls to `receivers` from now on, instead of the
+    /// ones traced before. An empty set disables tracing.
+    pub fn set_receivers(&self, receivers: HashSet<AccountId>) {
+        self.inner.lock().unwrap().receivers = receivers;
+    }
+
+    pub fn receivers(&self) -> HashSet<AccountId> {
+        self.inner.lock().unwrap().receivers.clone()
+    }
+
+    /// Number of receipts with a trace kept.
+    pub fn num_traces(&self) -> usize {
+        self.inner.lock().unwrap().traces.len()
+    }
+
+    pub fn is_traced(&self, receiver_id: &AccountId) -> bool {
+        self.inner.lock().unwrap().receivers.contains(receiver_id)
+    }
+
+    /// Records the trace of a function call action of the receipt. A receipt
+    /// may be applied more than once, e.g. when producing and then validating
+    /// the chunk, so the trace of the same action replaces the previous one.
+    pub fn record(&self, receipt_id: CryptoHash, trace: FunctionCallTrace) {
+        let mut inner = self.inner.lock().unwrap();
+        let traces = inner.traces.get_or_insert_mut(receipt_id, Vec::new);
+        match traces.iter_mut().find(|old| old.action_hash == trace.action_hash) {
+            Some(old) => *old = trace,
+            None => traces.push(trace),
+        }
+    }
+
+    /// Traces of the function call actions of the receipt, in the order they
+    /// were executed.
+    pub fn get(&self, receipt_id: &CryptoHash) -> Option<Vec<FunctionCallTrace>> {
+        self.inner.lock().unwrap().traces.get(receipt_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FunctionCallTrace, HostCallTracer};
+    use near_primitives::hash::CryptoHash;
+    use near_vm_runner::logic::HostCallTrace;
+
+    fn trace(action: u64, method_name: &str) -> FunctionCallTrace {
+        FunctionCallTrace {
+            action_hash: CryptoHash::hash_borsh(action),
+            receiver_id: "alice.near".parse().unwrap(),
+            method_name: method_name.to_string(),
+            host_calls: HostCallTrace::default(),
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let tracer = HostCallTracer::default();
+        assert!(!tracer.is_traced(&"alice.near".parse().unwrap()));
+        tracer.set_receivers(["alice.near".parse().unwrap()].into_iter().collect());
+        assert!(tracer.is_traced(&"alice.near".parse().unwrap()));
+        assert!(!tracer.is_traced(&"bob.near".parse().unwrap()));
+
+        let receipt_id = CryptoHash::hash_borsh(0u64);
+        tracer.record(receipt_id, trace(0, "first"));
+        tracer.record(receipt_id, trace(1, "second"));
+        // Applying the receipt again replaces the traces of its actions.
+        tracer.record(receipt_id, trace(0, "first"));
+        let methods: Vec<_> =
+            tracer.get(&receipt_id).unwrap().into_iter().map(|t| t.method_name).collect();
+        assert_eq!(methods, ["first", "second"]);
+        assert_eq!(tracer.num_traces(), 1);
+    }
+}
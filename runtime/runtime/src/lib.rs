@@ -1,14 +1,17 @@
 use crate::actions::*;
 use crate::balance_checker::check_balance;
 use crate::config::{
-    exec_fee, safe_add_balance, safe_add_compute, safe_add_gas, safe_gas_to_balance, total_deposit,
-    total_prepaid_exec_fees, total_prepaid_gas,
+    exec_fee, global_contract_distribution_fee, safe_add_balance, safe_add_compute, safe_add_gas,
+    safe_gas_to_balance, total_deposit, total_prepaid_exec_fees, total_prepaid_gas,
 };
 use crate::congestion_control::DelayedReceiptQueueWrapper;
+use crate::host_call_trace::HostCallTracer;
+use crate::pipelining::ContractCompilationPipeline;
 use crate::prefetch::TriePrefetcher;
 use crate::verifier::{check_storage_stake, validate_receipt, StorageStakingError};
 pub use crate::verifier::{
-    validate_transaction, verify_and_charge_transaction, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT,
+    validate_access_key_expiry, validate_transaction, verify_and_charge_transaction,
+    ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT,
 };
 use config::total_prepaid_send_fees;
 pub use congestion_control::bootstrap_congestion_info;
@@ -24,19 +27,21 @@ use near_primitives::errors::{
     ActionError, ActionErrorKind, IntegerOverflowError, InvalidTxError, RuntimeError,
     TxExecutionError,
 };
+use near_primitives::events::ContractEvent;
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
-    ActionReceipt, DataReceipt, DelayedReceiptIndices, PromiseYieldIndices, PromiseYieldTimeout,
-    Receipt, ReceiptEnum, ReceiptV0, ReceivedData,
+    ActionReceipt, DataReceipt, DelayedReceiptIndices, GlobalContractDistributionReceipt,
+    PromiseYieldIndices, PromiseYieldTimeout, Receipt, ReceiptEnum, ReceiptV0, ReceivedData,
 };
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
+use near_primitives::sandbox::SandboxStateDeletion;
 use near_primitives::state_record::StateRecord;
 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
 use near_primitives::transaction::NonrefundableStorageTransferAction;
 use near_primitives::transaction::{
-    Action, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus, LogEntry,
-    SignedTransaction, TransferAction,
+    Action, ExecutionMetadata, ExecutionMetadataV4, ExecutionOutcome, ExecutionOutcomeWithId,
+    ExecutionStatus, LogEntry, RefundReason, RefundReceipt, SignedTransaction, TransferAction,
 };
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
@@ -53,12 +58,14 @@ use near_primitives_core::apply::ApplyChunkReason;
 use near_store::trie::receipts_column_helper::DelayedReceiptQueue;
 use near_store::{
     get, get_account, get_postponed_receipt, get_promise_yield_receipt, get_received_data,
-    has_received_data, remove_account, remove_postponed_receipt, remove_promise_yield_receipt, set,
-    set_access_key, set_account, set_code, set_postponed_receipt, set_promise_yield_receipt,
-    set_received_data, PartialStorage, StorageError, Trie, TrieAccess, TrieChanges, TrieUpdate,
+    has_received_data, remove_access_key, remove_account, remove_postponed_receipt,
+    remove_promise_yield_receipt, set, set_access_key, set_account, set_code, set_global_code,
+    set_postponed_receipt, set_promise_yield_receipt, set_received_data, PartialStorage,
+    StorageError, Trie, TrieAccess, TrieChanges, TrieUpdate,
 };
 use near_vm_runner::logic::types::PromiseResult;
 use near_vm_runner::logic::ReturnData;
+use near_vm_runner::precompile_contract;
 pub use near_vm_runner::with_ext_cost_counter;
 use near_vm_runner::ContractCode;
 use near_vm_runner::ContractRuntimeCache;
@@ -75,7 +82,9 @@ pub mod config;
 mod congestion_control;
 mod conversions;
 pub mod ext;
+pub mod host_call_trace;
 mod metrics;
+mod pipelining;
 mod prefetch;
 pub mod receipt_manager;
 pub mod state_viewer;
@@ -131,6 +140,8 @@ pub struct ApplyState {
     /// the congestion info needs to be computed while applying receipts.
     /// TODO(congestion_info) - verify performance of initialization when congested
     pub congestion_info: BlockCongestionInfo,
+    /// Records the host function calls of the traced receivers, if any.
+    pub host_call_tracer: Option<Arc<HostCallTracer>>,
 }
 
 /// Contains information to update validators accounts at the first block of a new epoch.
@@ -195,12 +206,21 @@ pub struct ActionResult {
     pub compute_usage: Compute,
     pub result: Result<ReturnData, ActionError>,
     pub logs: Vec<LogEntry>,
+    pub events: Vec<ContractEvent>,
     pub new_receipts: Vec<Receipt>,
+    /// Refund receipts among `new_receipts`, by their index.
+    pub refunds: Vec<(usize, RefundReason)>,
     pub validator_proposals: Vec<ValidatorStake>,
     pub profile: Box<ProfileDataV3>,
 }
 
 impl ActionResult {
+    /// Adds a refund receipt to `new_receipts`, recording why it's issued.
+    pub fn push_refund(&mut self, receipt: Receipt, reason: RefundReason) {
+        self.refunds.push((self.new_receipts.len(), reason));
+        self.new_receipts.push(receipt);
+    }
+
     pub fn merge(&mut self, mut next_result: ActionResult) -> Result<(), RuntimeError> {
         assert!(next_result.gas_burnt_for_function_call <= next_result.gas_burnt);
         assert!(
@@ -224,11 +244,18 @@ impl ActionResult {
             *receipt_index += self.new_receipts.len() as u64;
         }
         if self.result.is_ok() {
+            // Shifting local refund receipt indices to be global receipt indices.
+            for (receipt_index, reason) in next_result.refunds {
+                self.refunds.push((self.new_receipts.len() + receipt_index, reason));
+            }
             self.new_receipts.append(&mut next_result.new_receipts);
             self.validator_proposals.append(&mut next_result.validator_proposals);
+            self.events.append(&mut next_result.events);
         } else {
             self.new_receipts.clear();
+            self.refunds.clear();
             self.validator_proposals.clear();
+            self.events.clear();
         }
         Ok(())
     }
@@ -243,7 +270,9 @@ impl Default for ActionResult {
             compute_usage: 0,
             result: Ok(ReturnData::None),
             logs: vec![],
+            events: vec![],
             new_receipts: vec![],
+            refunds: vec![],
             validator_proposals: vec![],
             profile: Default::default(),
         }
@@ -375,6 +404,7 @@ impl Runtime {
         action_hash: &CryptoHash,
         action_index: usize,
         actions: &[Action],
+        receipt_is_local: bool,
         epoch_info_provider: &(dyn EpochInfoProvider),
     ) -> Result<ActionResult, RuntimeError> {
         let _span = tracing::debug_span!(
@@ -442,6 +472,7 @@ impl Runtime {
                     apply_state,
                     account,
                     account_id,
+                    None,
                     function_call,
                     &apply_state.config,
                     None,
@@ -456,10 +487,12 @@ impl Runtime {
                     promise_results,
                     &mut result,
                     account_id,
+                    None,
                     function_call,
                     action_hash,
                     &apply_state.config,
                     is_last_action,
+                    receipt_is_local,
                     epoch_info_provider,
                     contract,
                 )?;
@@ -549,6 +582,89 @@ impl Runtime {
                     receipt.priority(),
                 )?;
             }
+            Action::IdempotencyKey(idempotency_key) => {
+                action_idempotency_key(
+                    apply_state,
+                    state_update,
+                    account.as_mut().expect(EXPECT_ACCOUNT_EXISTS),
+                    &mut result,
+                    account_id,
+                    receipt.predecessor_id(),
+                    idempotency_key,
+                )?;
+            }
+            Action::AtomicBatch(atomic_batch) => {
+                apply_atomic_batch_action(
+                    apply_state,
+                    action_receipt,
+                    account_id,
+                    atomic_batch,
+                    &mut result,
+                )?;
+            }
+            Action::DeployNamespacedContract(deploy_contract) => {
+                action_deploy_namespaced_contract(
+                    state_update,
+                    account.as_mut().expect(EXPECT_ACCOUNT_EXISTS),
+                    account_id,
+                    deploy_contract,
+                    apply_state,
+                )?;
+            }
+            Action::NamespacedFunctionCall(namespaced_call) => {
+                let account = account.as_mut().expect(EXPECT_ACCOUNT_EXISTS);
+                let namespace = Some(&namespaced_call.namespace);
+                let function_call = &namespaced_call.function_call;
+                let contract = prepare_function_call(
+                    state_update,
+                    apply_state,
+                    account,
+                    account_id,
+                    namespace,
+                    function_call,
+                    &apply_state.config,
+                    None,
+                );
+                let is_last_action = action_index + 1 == actions.len();
+                action_function_call(
+                    state_update,
+                    apply_state,
+                    account,
+                    receipt,
+                    action_receipt,
+                    promise_results,
+                    &mut result,
+                    account_id,
+                    namespace,
+                    function_call,
+                    action_hash,
+                    &apply_state.config,
+                    is_last_action,
+                    receipt_is_local,
+                    epoch_info_provider,
+                    contract,
+                )?;
+            }
+            Action::DeployGlobalContract(deploy_contract) => {
+                action_deploy_global_contract(
+                    state_update,
+                    apply_state,
+                    account.as_mut().expect(EXPECT_ACCOUNT_EXISTS),
+                    account_id,
+                    deploy_contract,
+                    &mut result,
+                    epoch_info_provider,
+                )?;
+            }
+            Action::UseGlobalContract(use_contract) => {
+                action_use_global_contract(
+                    state_update,
+                    account.as_mut().expect(EXPECT_ACCOUNT_EXISTS),
+                    account_id,
+                    use_contract,
+                    &mut result,
+                )?;
+            }
         };
         Ok(result)
     }
@@ -559,6 +675,7 @@ impl Runtime {
         state_update: &mut TrieUpdate,
         apply_state: &ApplyState,
         receipt: &Receipt,
+        receipt_is_local: bool,
         receipt_sink: &mut ReceiptSink,
         validator_proposals: &mut Vec<ValidatorStake>,
         stats: &mut ApplyStats,
@@ -614,6 +731,7 @@ impl Runtime {
         result.compute_usage = exec_fees;
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
         let mut nonrefundable_amount_burnt: Balance = 0;
+        let mut global_contract_deploy_burnt: Balance = 0;
 
         // Executing actions one by one
         for (action_index, action) in action_receipt.actions.iter().enumerate() {
@@ -636,6 +754,7 @@ impl Runtime {
                 &action_hash,
                 action_index,
                 &action_receipt.actions,
+                receipt_is_local,
                 epoch_info_provider,
             )?;
             if new_result.result.is_ok() {
@@ -663,6 +782,18 @@ impl Runtime {
             {
                 nonrefundable_amount_burnt = safe_add_balance(nonrefundable_amount_burnt, *deposit)?
             }
+            if let Action::DeployGlobalContract(deploy_contract) = action {
+                let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+                global_contract_deploy_burnt = safe_add_balance(
+                    global_contract_deploy_burnt,
+                    global_contract_deploy_cost(
+                        &apply_state.config,
+                        apply_state.gas_price,
+                        &shard_layout,
+                        deploy_contract.code.len(),
+                    )?,
+                )?;
+            }
         }
 
         // Going to check balance covers account's storage.
@@ -751,6 +882,10 @@ impl Runtime {
             stats.other_burnt_amount =
                 safe_add_balance(stats.other_burnt_amount, nonrefundable_amount_burnt)?;
         }
+        if result.result.is_ok() {
+            stats.other_burnt_amount =
+                safe_add_balance(stats.other_burnt_amount, global_contract_deploy_burnt)?;
+        }
 
         // If the receipt is a refund, then we consider it free without burnt gas.
         let gas_burnt: Gas =
@@ -830,6 +965,30 @@ impl Runtime {
             };
         }
 
+        // Recording the refunds with the ids their receipts get below.
+        let refunds = result
+            .refunds
+            .iter()
+            .map(|&(receipt_index, reason)| {
+                let refund = &result.new_receipts[receipt_index];
+                let ReceiptEnum::Action(refund_receipt) = refund.receipt() else {
+                    unreachable!("refund receipts are action receipts");
+                };
+                Ok(RefundReceipt {
+                    receipt_id: create_receipt_id_from_receipt_id(
+                        apply_state.current_protocol_version,
+                        receipt.receipt_id(),
+                        &apply_state.prev_block_hash,
+                        &apply_state.block_hash,
+                        receipt_index,
+                    ),
+                    receiver_id: refund.receiver_id().clone(),
+                    amount: total_deposit(&refund_receipt.actions)?,
+                    reason,
+                })
+            })
+            .collect::<Result<Vec<_>, RuntimeError>>()?;
+
         // Generating receipt IDs
         let receipt_ids = result
             .new_receipts
@@ -845,9 +1004,13 @@ impl Runtime {
                 );
 
                 new_receipt.set_receipt_id(receipt_id);
+                // The transaction deploying a global contract is final once the
+                // contract has been distributed to all the shards.
                 let is_action = matches!(
                     new_receipt.receipt(),
-                    ReceiptEnum::Action(_) | ReceiptEnum::PromiseYield(_)
+                    ReceiptEnum::Action(_)
+                        | ReceiptEnum::PromiseYield(_)
+                        | ReceiptEnum::GlobalContractDistribution(_)
                 );
 
                 let res = receipt_sink.forward_or_buffer_receipt(
@@ -883,6 +1046,17 @@ impl Runtime {
 
         Self::print_log(&result.logs);
 
+        let gas_profile = conversions::Convert::convert(*result.profile);
+        let metadata = if result.events.is_empty() && refunds.is_empty() {
+            ExecutionMetadata::V3(Box::new(gas_profile))
+        } else {
+            ExecutionMetadata::V4(Box::new(ExecutionMetadataV4 {
+                gas_profile,
+                events: result.events,
+                refunds,
+            }))
+        };
+
         Ok(ExecutionOutcomeWithId {
             id: *receipt.receipt_id(),
             outcome: ExecutionOutcome {
@@ -893,9 +1067,83 @@ impl Runtime {
                 compute_usage: Some(result.compute_usage),
                 tokens_burnt,
                 executor_id: account_id.clone(),
-                metadata: ExecutionMetadata::V3(Box::new(conversions::Convert::convert(
-                    *result.profile,
-                ))),
+                metadata,
+            },
+        })
+    }
+
+    /// Stores the code of a global contract in the shard and passes it on to
+    /// the next shard which doesn't have it yet.
+    ///
+    /// The gas of the receipt was paid for by the account which deployed the
+    /// contract, so it is burnt here without burning any tokens.
+    fn apply_global_contract_distribution_receipt(
+        &self,
+        state_update: &mut TrieUpdate,
+        apply_state: &ApplyState,
+        receipt: &Receipt,
+        distribution: &GlobalContractDistributionReceipt,
+        receipt_sink: &mut ReceiptSink,
+        epoch_info_provider: &(dyn EpochInfoProvider),
+    ) -> Result<ExecutionOutcomeWithId, RuntimeError> {
+        // TODO(#8806): Support compute costs for actions. For now they match burnt gas.
+        let gas_burnt =
+            global_contract_distribution_fee(&apply_state.config, distribution.code.len());
+        let code = ContractCode::new(distribution.code.clone(), None);
+        set_global_code(state_update, &code);
+        state_update
+            .commit(StateChangeCause::ReceiptProcessing { receipt_hash: receipt.get_hash() });
+        precompile_contract(
+            &code,
+            Arc::clone(&apply_state.config.wasm_config),
+            apply_state.cache.as_deref(),
+        )
+        .ok();
+
+        let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+        // A receipt sent before a resharding refers to the shards of the
+        // previous layout.  The distribution starts over in the new layout
+        // then, which at worst stores the code again in some shards.
+        let mut already_delivered_shards =
+            if distribution.shard_layout_version == shard_layout.version() {
+                distribution.already_delivered_shards.clone()
+            } else {
+                vec![]
+            };
+        already_delivered_shards.push(apply_state.shard_id);
+        let mut receipt_ids = vec![];
+        if let Some(mut next_receipt) = next_global_contract_distribution(
+            &shard_layout,
+            receipt.receiver_id(),
+            distribution.code.clone(),
+            already_delivered_shards,
+        ) {
+            let receipt_id = create_receipt_id_from_receipt_id(
+                apply_state.current_protocol_version,
+                receipt.receipt_id(),
+                &apply_state.prev_block_hash,
+                &apply_state.block_hash,
+                0,
+            );
+            next_receipt.set_receipt_id(receipt_id);
+            receipt_sink.forward_or_buffer_receipt(
+                next_receipt,
+                apply_state,
+                state_update,
+                epoch_info_provider,
+            )?;
+            receipt_ids.push(receipt_id);
+        }
+
+        Ok(ExecutionOutcomeWithId {
+            id: *receipt.receipt_id(),
+            outcome: ExecutionOutcome {
+                status: ExecutionStatus::SuccessValue(vec![]),
+                receipt_ids,
+                gas_burnt,
+                compute_usage: Some(gas_burnt),
+                executor_id: receipt.receiver_id().clone(),
+                ..Default::default()
             },
         })
     }
@@ -954,21 +1202,27 @@ impl Runtime {
         }
 
         if deposit_refund > 0 {
-            result.new_receipts.push(Receipt::new_balance_refund(
-                receipt.predecessor_id(),
-                deposit_refund,
-                receipt.priority(),
-            ));
+            result.push_refund(
+                Receipt::new_balance_refund(
+                    receipt.predecessor_id(),
+                    deposit_refund,
+                    receipt.priority(),
+                ),
+                RefundReason::FailedReceiptDeposit,
+            );
         }
         if gas_balance_refund > 0 {
             // Gas refunds refund the allowance of the access key, so if the key exists on the
             // account it will increase the allowance by the refund amount.
-            result.new_receipts.push(Receipt::new_gas_refund(
-                &action_receipt.signer_id,
-                gas_balance_refund,
-                action_receipt.signer_public_key.clone(),
-                receipt.priority(),
-            ));
+            result.push_refund(
+                Receipt::new_gas_refund(
+                    &action_receipt.signer_id,
+                    gas_balance_refund,
+                    action_receipt.signer_public_key.clone(),
+                    receipt.priority(),
+                ),
+                RefundReason::UnusedGas,
+            );
         }
         Ok(gas_deficit_amount)
     }
@@ -978,6 +1232,7 @@ impl Runtime {
         state_update: &mut TrieUpdate,
         apply_state: &ApplyState,
         receipt: &Receipt,
+        receipt_is_local: bool,
         receipt_sink: &mut ReceiptSink,
         validator_proposals: &mut Vec<ValidatorStake>,
         stats: &mut ApplyStats,
@@ -1046,6 +1301,7 @@ impl Runtime {
                                 state_update,
                                 apply_state,
                                 &ready_receipt,
+                                false,
                                 receipt_sink,
                                 validator_proposals,
                                 stats,
@@ -1100,6 +1356,7 @@ impl Runtime {
                             state_update,
                             apply_state,
                             receipt,
+                            receipt_is_local,
                             receipt_sink,
                             validator_proposals,
                             stats,
@@ -1151,6 +1408,7 @@ impl Runtime {
                             state_update,
                             apply_state,
                             &yield_receipt,
+                            false,
                             receipt_sink,
                             validator_proposals,
                             stats,
@@ -1164,6 +1422,18 @@ impl Runtime {
                     return Ok(None);
                 }
             }
+            ReceiptEnum::GlobalContractDistribution(ref distribution) => {
+                return self
+                    .apply_global_contract_distribution_receipt(
+                        state_update,
+                        apply_state,
+                        receipt,
+                        distribution,
+                        receipt_sink,
+                        epoch_info_provider,
+                    )
+                    .map(Some);
+            }
         };
         // We didn't trigger execution, so we need to commit the state.
         state_update
@@ -1487,11 +1757,40 @@ impl Runtime {
         )
     }
 
-    fn apply_state_patch(&self, state_update: &mut TrieUpdate, state_patch: SandboxStatePatch) {
+    fn apply_state_patch(
+        &self,
+        state_update: &mut TrieUpdate,
+        state_patch: SandboxStatePatch,
+    ) -> Result<(), RuntimeError> {
         if state_patch.is_empty() {
-            return;
+            return Ok(());
+        }
+        let (records, deletions) = state_patch.into_parts();
+        for deletion in deletions {
+            match deletion {
+                SandboxStateDeletion::Account { account_id } => {
+                    remove_account(state_update, &account_id)?;
+                }
+                SandboxStateDeletion::AccessKey { account_id, public_key } => {
+                    remove_access_key(state_update, account_id, public_key);
+                }
+                SandboxStateDeletion::Contract { account_id } => {
+                    if let Some(mut account) = get_account(state_update, &account_id)? {
+                        account.set_code_hash(CryptoHash::default());
+                        set_account(state_update, account_id.clone(), &account);
+                    }
+                    state_update.remove(TrieKey::ContractCode { account_id });
+                }
+                SandboxStateDeletion::Data { account_id, data_key } => {
+                    state_update.remove(TrieKey::ContractData { key: data_key.into(), account_id });
+                }
+            }
         }
-        for record in state_patch {
+        // Accounts go first so that code and keys of accounts created by the
+        // same patch land on an existing account.
+        let (accounts, records): (Vec<_>, Vec<_>) =
+            records.into_iter().partition(|record| matches!(record, StateRecord::Account { .. }));
+        for record in accounts.into_iter().chain(records) {
             match record {
                 StateRecord::Account { account_id, account } => {
                     set_account(state_update, account_id, &account);
@@ -1500,11 +1799,12 @@ impl Runtime {
                     state_update.set(TrieKey::ContractData { key: data_key.into(), account_id }, value.into());
                 }
                 StateRecord::Contract { account_id, code } => {
-                    let acc = get_account(state_update, &account_id).expect("Failed to read state").expect("Code state record should be preceded by the corresponding account record");
+                    let mut acc = get_account(state_update, &account_id)?.expect("Code state record requires the account to exist or to be patched along with it");
                     // Recompute contract code hash.
                     let code = ContractCode::new(code, None);
-                    set_code(state_update, account_id, &code);
-                    assert_eq!(*code.hash(), acc.code_hash());
+                    set_code(state_update, account_id.clone(), &code);
+                    acc.set_code_hash(*code.hash());
+                    set_account(state_update, account_id, &acc);
                 }
                 StateRecord::AccessKey { account_id, public_key, access_key } => {
                     set_access_key(state_update, account_id, public_key, &access_key);
@@ -1513,6 +1813,7 @@ impl Runtime {
             }
         }
         state_update.commit(StateChangeCause::Migration);
+        Ok(())
     }
 
     /// Processes a collection of transactions.
@@ -1556,15 +1857,66 @@ impl Runtime {
         Ok(())
     }
 
+    /// Whether the receipt holds an atomic batch whose receipts can't all be
+    /// forwarded in this chunk.  Such a receipt is put back into the delayed
+    /// receipts to be retried in the next chunk.  Batches which would fail
+    /// anyway, e.g. because they expired, are applied right away, so that
+    /// their deposits and gas are refunded.
+    ///
+    /// Each retry counts towards the compute limit of the chunk like applying
+    /// a receipt does, so waiting batches can't fill the chunk for free.
+    fn atomic_batch_must_wait(
+        &self,
+        receipt: &Receipt,
+        apply_state: &ApplyState,
+        receipt_sink: &ReceiptSink,
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Result<bool, RuntimeError> {
+        if !ProtocolFeature::AtomicBatches.enabled(apply_state.current_protocol_version) {
+            return Ok(false);
+        }
+        let ReceiptEnum::Action(action_receipt) = receipt.receipt() else {
+            return Ok(false);
+        };
+        let [Action::AtomicBatch(atomic_batch)] = action_receipt.actions.as_slice() else {
+            return Ok(false);
+        };
+        if receipt.receiver_id() != &action_receipt.signer_id
+            || apply_state.block_height > atomic_batch.max_block_height
+        {
+            return Ok(false);
+        }
+        let receipts = atomic_batch_receipts(receipt.receiver_id(), action_receipt, atomic_batch);
+        Ok(!receipt_sink.can_forward_all(&receipts, apply_state, epoch_info_provider)?)
+    }
+
     /// This function wraps [Runtime::process_receipt]. It adds a tracing span around the latter
     /// and populates various metrics.
     fn process_receipt_with_metrics<'a>(
         &self,
         receipt: &Receipt,
+        receipt_is_local: bool,
         processing_state: &mut ApplyProcessingReceiptState<'a>,
         mut receipt_sink: &mut ReceiptSink,
         mut validator_proposals: &mut Vec<ValidatorStake>,
     ) -> Result<(), RuntimeError> {
+        if self.atomic_batch_must_wait(
+            receipt,
+            processing_state.apply_state,
+            receipt_sink,
+            processing_state.epoch_info_provider,
+        )? {
+            tracing::debug!(target: "runtime", receipt_id = %receipt.receipt_id(), "Atomic batch waits for congestion to clear");
+            let retry_compute = processing_state
+                .apply_state
+                .config
+                .fees
+                .fee(ActionCosts::new_action_receipt)
+                .exec_fee();
+            processing_state.total.add(0, retry_compute)?;
+            processing_state.waiting_atomic_batches.push(receipt.clone());
+            return Ok(());
+        }
         let span = tracing::debug_span!(
             target: "runtime",
             "process_receipt",
@@ -1575,16 +1927,22 @@ impl Runtime {
             compute_usage = tracing::field::Empty,
         )
         .entered();
+        if let Some(pipeline) = &processing_state.compilation_pipeline {
+            pipeline.wait(receipt);
+        }
         let total = &mut processing_state.total;
         let state_update = &mut processing_state.state_update;
         let node_counter_before = state_update.trie().get_trie_nodes_count();
         let recorded_storage_size_before = state_update.trie().recorded_storage_size();
         let storage_proof_size_upper_bound_before =
             state_update.trie().recorded_storage_size_upper_bound();
+        #[cfg(feature = "sandbox")]
+        let storage_proof_size_before = state_update.trie().storage_proof_size();
         let result = self.process_receipt(
             state_update,
             processing_state.apply_state,
             receipt,
+            receipt_is_local,
             &mut receipt_sink,
             &mut validator_proposals,
             &mut processing_state.stats,
@@ -1619,6 +1977,23 @@ impl Runtime {
                 .with_label_values(&[shard_id_str.as_str()])
                 .observe(recorded_storage_proof_ratio);
         }
+        // Lets contract developers see what their receipts add to the state
+        // witness, compared to `per_receipt_storage_proof_size_limit`.
+        #[cfg(feature = "sandbox")]
+        {
+            let size = state_update.trie().storage_proof_size().since(&storage_proof_size_before);
+            tracing::debug!(
+                target: "sandbox",
+                receipt_id = %receipt.receipt_id(),
+                receiver = %receipt.receiver_id(),
+                nodes_size = size.nodes_size,
+                removals = size.removals,
+                code_size = size.code_size,
+                upper_bound = size.upper_bound(),
+                limit = processing_state.apply_state.config.wasm_config.limit_config.per_receipt_storage_proof_size_limit,
+                "Storage proof size of receipt",
+            );
+        }
         if let Some(outcome_with_id) = result? {
             let gas_burnt = outcome_with_id.outcome.gas_burnt;
             let compute_usage = outcome_with_id
@@ -1653,6 +2028,9 @@ impl Runtime {
             _ = prefetcher.prefetch_receipts_data(front);
             _ = prefetcher.prefetch_receipts_data(back);
         }
+        if let Some(pipeline) = &mut processing_state.compilation_pipeline {
+            pipeline.submit(&processing_state.local_receipts);
+        }
         while let Some(receipt) = processing_state.next_local_receipt() {
             if processing_state.total.compute >= compute_limit
                 || proof_size_limit.is_some_and(|limit| {
@@ -1669,6 +2047,7 @@ impl Runtime {
                 // the `verify_and_charge_transaction`.
                 self.process_receipt_with_metrics(
                     &receipt,
+                    true,
                     &mut processing_state,
                     receipt_sink,
                     validator_proposals,
@@ -1730,6 +2109,7 @@ impl Runtime {
 
             self.process_receipt_with_metrics(
                 &receipt,
+                false,
                 &mut processing_state,
                 receipt_sink,
                 validator_proposals,
@@ -1782,6 +2162,7 @@ impl Runtime {
             } else {
                 self.process_receipt_with_metrics(
                     &receipt,
+                    false,
                     &mut processing_state,
                     receipt_sink,
                     validator_proposals,
@@ -1852,6 +2233,16 @@ impl Runtime {
             proof_size_limit,
         )?;
 
+        // Atomic batches which have to wait are delayed only now, so that
+        // they aren't popped again from the delayed receipts in this chunk.
+        for receipt in std::mem::take(&mut processing_state.waiting_atomic_batches) {
+            processing_state.delayed_receipts.push(
+                &mut processing_state.state_update,
+                &receipt,
+                &processing_state.apply_state.config,
+            )?;
+        }
+
         let shard_id_str = processing_state.apply_state.shard_id.to_string();
         if processing_state.total.compute >= compute_limit {
             metrics::CHUNK_RECEIPTS_LIMITED_BY
@@ -1929,7 +2320,7 @@ impl Runtime {
         )?;
 
         state_update.commit(StateChangeCause::UpdatedDelayedReceipts);
-        self.apply_state_patch(&mut state_update, state_patch);
+        self.apply_state_patch(&mut state_update, state_patch)?;
         let chunk_recorded_size_upper_bound =
             state_update.trie.recorded_storage_size_upper_bound() as f64;
         let shard_id_str = apply_state.shard_id.to_string();
@@ -2289,10 +2680,24 @@ impl<'a> ApplyProcessingState<'a> {
         incoming_receipts: &'a [Receipt],
         delayed_receipts: DelayedReceiptQueueWrapper,
     ) -> ApplyProcessingReceiptState<'a> {
+        let mut compilation_pipeline = self.apply_state.cache.as_ref().map(|cache| {
+            ContractCompilationPipeline::new(
+                &self.state_update,
+                Arc::clone(&self.apply_state.config.wasm_config),
+                cache.handle(),
+            )
+        });
+        if let Some(pipeline) = &mut compilation_pipeline {
+            // Incoming receipts only run after the transactions and the local
+            // and delayed receipts, so their contracts have the most time to
+            // compile.
+            pipeline.submit(incoming_receipts);
+        }
         ApplyProcessingReceiptState {
             protocol_version: self.protocol_version,
             apply_state: self.apply_state,
             prefetcher: self.prefetcher,
+            compilation_pipeline,
             state_update: self.state_update,
             epoch_info_provider: self.epoch_info_provider,
             transactions: self.transactions,
@@ -2303,6 +2708,7 @@ impl<'a> ApplyProcessingState<'a> {
             local_receipts: VecDeque::new(),
             incoming_receipts,
             delayed_receipts,
+            waiting_atomic_batches: Vec::new(),
         }
     }
 }
@@ -2313,6 +2719,7 @@ struct ApplyProcessingReceiptState<'a> {
     protocol_version: ProtocolVersion,
     apply_state: &'a ApplyState,
     prefetcher: Option<TriePrefetcher>,
+    compilation_pipeline: Option<ContractCompilationPipeline>,
     state_update: TrieUpdate,
     epoch_info_provider: &'a (dyn EpochInfoProvider),
     transactions: &'a [SignedTransaction],
@@ -2323,6 +2730,9 @@ struct ApplyProcessingReceiptState<'a> {
     local_receipts: VecDeque<Receipt>,
     incoming_receipts: &'a [Receipt],
     delayed_receipts: DelayedReceiptQueueWrapper,
+    /// Receipts of atomic batches to put into the delayed receipts at the
+    /// end of the chunk, see [Runtime::atomic_batch_must_wait].
+    waiting_atomic_batches: Vec<Receipt>,
 }
 
 impl<'a> ApplyProcessingReceiptState<'a> {
@@ -2371,6 +2781,7 @@ pub mod estimator {
             state_update,
             apply_state,
             receipt,
+            false,
             &mut receipt_sink,
             validator_proposals,
             stats,
@@ -433,6 +433,15 @@ static CHUNK_RECORDED_TRIE_NODES_VALUES_SIZE: LazyLock<HistogramVec> = LazyLock:
     .unwrap()
 });
 
+pub(crate) static PIPELINED_CONTRACT_COMPILATIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    try_create_int_counter_vec(
+        "near_pipelined_contract_compilations",
+        "Contracts compiled ahead of the receipts calling them, by who compiled them first",
+        &["compiled_by"], // compiled_by is either "pipeline" or "apply_thread"
+    )
+    .unwrap()
+});
+
 pub(crate) static CHUNK_RECEIPTS_LIMITED_BY: LazyLock<IntCounterVec> = LazyLock::new(|| {
     try_create_int_counter_vec(
         "near_chunk_receipts_limited_by",
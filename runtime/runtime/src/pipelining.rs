@@ -0,0 +1,249 @@
+//! Compilation of contracts ahead of the receipts that call them.
+//!
+//! A function call to a contract that is not in the compiled contracts cache
+//! has to compile it first, which for a large contract takes much longer than
+//! running it. Right after a popular contract is deployed or the VM config
+//! changes, a chunk can have many such calls, each compiling on the apply
+//! thread in turn. The pipeline gets to see the receipts of a chunk before they
+//! are applied and compiles the contracts they call on the rayon thread pool,
+//! so that by the time a receipt runs, its contract is in the cache or at least
+//! on its way there.
+//!
+//! Compiling ahead must not change the outcome of the chunk. That holds
+//! because:
+//! * the gas charged for loading a contract does not depend on whether it was
+//!   in the cache;
+//! * the receivers' accounts are read through [`Trie::untracked_reads`], which
+//!   neither records the reads in the state witness nor charges gas for them;
+//! * a failure to read or compile a contract ahead is ignored, the apply thread
+//!   will run into it again and handle it as it always did.
+//!
+//! The pipeline only sees the state from before the chunk. Contracts deployed
+//! by the chunk itself are compiled by their deploy actions.
+//!
+//! A receipt whose contract is still being compiled waits for the compilation
+//! rather than compiling the same contract again. If the compilation has not
+//! started yet, for instance because the thread pool is busy applying other
+//! chunks, the apply thread runs it itself instead of waiting for a free
+//! thread.
+
+use crate::metrics::PIPELINED_CONTRACT_COMPILATIONS;
+use near_parameters::vm::Config;
+use near_primitives::hash::CryptoHash;
+use near_primitives::receipt::{Receipt, ReceiptEnum};
+use near_primitives::transaction::Action;
+use near_primitives::types::AccountId;
+use near_store::{get_account, ContractStorage, Trie, TrieUpdate};
+use near_vm_runner::{get_contract_cache_key, precompile_contract, ContractRuntimeCache};
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+pub(crate) struct ContractCompilationPipeline {
+    trie: Trie,
+    contract_storage: ContractStorage,
+    config: Arc<Config>,
+    cache: Box<dyn ContractRuntimeCache>,
+    /// Compilations submitted so far, by the code hash of the contract.
+    compilations: HashMap<CryptoHash, Arc<Compilation>>,
+    /// Code hashes of the contracts of the receivers seen so far.
+    receivers: HashMap<AccountId, CryptoHash>,
+}
+
+impl ContractCompilationPipeline {
+    pub(crate) fn new(
+        state_update: &TrieUpdate,
+        config: Arc<Config>,
+        cache: Box<dyn ContractRuntimeCache>,
+    ) -> Self {
+        Self {
+            trie: state_update.trie().untracked_reads(),
+            contract_storage: state_update.contract_storage(),
+            config,
+            cache,
+            compilations: HashMap::new(),
+            receivers: HashMap::new(),
+        }
+    }
+
+    /// Starts compiling the contracts `receipts` call, unless they are already
+    /// compiled or being compiled.
+    pub(crate) fn submit<'r>(&mut self, receipts: impl IntoIterator<Item = &'r Receipt>) {
+        for receipt in receipts {
+            if !calls_contract(receipt) || self.receivers.contains_key(receipt.receiver_id()) {
+                continue;
+            }
+            let code_hash = match get_account(&self.trie, receipt.receiver_id()) {
+                Ok(Some(account)) => account.code_hash(),
+                Ok(None) | Err(_) => continue,
+            };
+            self.receivers.insert(receipt.receiver_id().clone(), code_hash);
+            if code_hash == CryptoHash::default() || self.compilations.contains_key(&code_hash) {
+                continue;
+            }
+            let cache_key = get_contract_cache_key(code_hash, &self.config);
+            if self.cache.has(&cache_key).unwrap_or(false) {
+                continue;
+            }
+            let compilation = Arc::new(Compilation {
+                code_hash,
+                contract_storage: self.contract_storage.clone(),
+                config: Arc::clone(&self.config),
+                cache: self.cache.handle(),
+                state: Mutex::new(CompilationState::Queued),
+                compiled: Condvar::new(),
+            });
+            self.compilations.insert(code_hash, Arc::clone(&compilation));
+            rayon::spawn(move || compilation.run("pipeline"));
+        }
+    }
+
+    /// Blocks until the contract `receipt` calls, if it was submitted, is
+    /// compiled.
+    pub(crate) fn wait(&self, receipt: &Receipt) {
+        if !calls_contract(receipt) {
+            return;
+        }
+        let Some(code_hash) = self.receivers.get(receipt.receiver_id()) else {
+            return;
+        };
+        if let Some(compilation) = self.compilations.get(code_hash) {
+            compilation.wait();
+        }
+    }
+}
+
+/// Whether the receipt has a function call to the contract deployed on its
+/// receiver.
+fn calls_contract(receipt: &Receipt) -> bool {
+    match receipt.receipt() {
+        ReceiptEnum::Action(action_receipt) | ReceiptEnum::PromiseYield(action_receipt) => {
+            action_receipt.actions.iter().any(|action| matches!(action, Action::FunctionCall(_)))
+        }
+        ReceiptEnum::Data(_)
+        | ReceiptEnum::PromiseResume(_)
+        | ReceiptEnum::GlobalContractDistribution(_) => false,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CompilationState {
+    Queued,
+    Compiling,
+    Done,
+}
+
+struct Compilation {
+    code_hash: CryptoHash,
+    contract_storage: ContractStorage,
+    config: Arc<Config>,
+    cache: Box<dyn ContractRuntimeCache>,
+    state: Mutex<CompilationState>,
+    compiled: Condvar,
+}
+
+impl Compilation {
+    /// Compiles the contract, unless another thread has already started to.
+    fn run(&self, compiled_by: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if *state != CompilationState::Queued {
+                return;
+            }
+            *state = CompilationState::Compiling;
+        }
+        let _span = tracing::debug_span!(
+            target: "runtime",
+            "pipelined_contract_compilation",
+            code_hash = %self.code_hash,
+            compiled_by,
+        )
+        .entered();
+        if let Some(code) = self.contract_storage.get(self.code_hash) {
+            // Errors are left to the receipts calling the contract to report.
+            _ = precompile_contract(&code, Arc::clone(&self.config), Some(self.cache.as_ref()));
+            PIPELINED_CONTRACT_COMPILATIONS.with_label_values(&[compiled_by]).inc();
+        }
+        *self.state.lock().unwrap() = CompilationState::Done;
+        self.compiled.notify_all();
+    }
+
+    fn wait(&self) {
+        self.run("apply_thread");
+        let mut state = self.state.lock().unwrap();
+        while *state != CompilationState::Done {
+            state = self.compiled.wait(state).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContractCompilationPipeline;
+    use near_crypto::{KeyType, PublicKey};
+    use near_parameters::RuntimeConfig;
+    use near_primitives::hash::hash;
+    use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptV0};
+    use near_primitives::test_utils::account_new;
+    use near_primitives::transaction::{Action, FunctionCallAction};
+    use near_primitives::types::{AccountId, StateChangeCause};
+    use near_store::test_utils::TestTriesBuilder;
+    use near_store::{set_account, set_code, ShardUId, Trie, TrieUpdate};
+    use near_vm_runner::{
+        get_contract_cache_key, ContractCode, ContractRuntimeCache, FilesystemContractRuntimeCache,
+    };
+    use std::sync::Arc;
+
+    fn function_call_receipt(receiver_id: &AccountId) -> Receipt {
+        Receipt::V0(ReceiptV0 {
+            predecessor_id: receiver_id.clone(),
+            receiver_id: receiver_id.clone(),
+            receipt_id: hash(receiver_id.as_bytes()),
+            receipt: ReceiptEnum::Action(ActionReceipt {
+                signer_id: receiver_id.clone(),
+                signer_public_key: PublicKey::empty(KeyType::ED25519),
+                gas_price: 0,
+                output_data_receivers: vec![],
+                input_data_ids: vec![],
+                actions: vec![Action::FunctionCall(Box::new(FunctionCallAction {
+                    method_name: "log_something".to_string(),
+                    args: vec![],
+                    gas: 0,
+                    deposit: 0,
+                }))],
+            }),
+        })
+    }
+
+    // This test only works on platforms that support wasmer2.
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_contract_compiled_ahead() {
+        let tries = TestTriesBuilder::new().build();
+        let shard_uid = ShardUId::single_shard();
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        let code = ContractCode::new(near_test_contracts::rs_contract().to_vec(), None);
+        let mut state_update = tries.new_trie_update(shard_uid, Trie::EMPTY_ROOT);
+        set_account(&mut state_update, account_id.clone(), &account_new(0, *code.hash()));
+        set_code(&mut state_update, account_id.clone(), &code);
+        state_update.commit(StateChangeCause::InitialState);
+        let trie_changes = state_update.finalize().unwrap().1;
+        let mut store_update = tries.store_update();
+        let root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+        store_update.commit().unwrap();
+
+        let config = Arc::clone(&RuntimeConfig::test().wasm_config);
+        let cache = FilesystemContractRuntimeCache::test().unwrap();
+        let trie = tries.get_trie_for_shard(shard_uid, root).recording_reads();
+        let state_update = TrieUpdate::new(trie);
+        let mut pipeline =
+            ContractCompilationPipeline::new(&state_update, Arc::clone(&config), cache.handle());
+        let receipt = function_call_receipt(&account_id);
+        pipeline.submit([&receipt]);
+        pipeline.wait(&receipt);
+
+        let key = get_contract_cache_key(*code.hash(), &config);
+        assert!(cache.has(&key).unwrap());
+        // Reading the account ahead does not end up in the state witness.
+        assert_eq!(state_update.trie().recorded_storage_size(), 0);
+    }
+}
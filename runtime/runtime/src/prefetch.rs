@@ -49,12 +49,18 @@ use near_primitives::transaction::{Action, SignedTransaction};
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::AccountId;
 use near_primitives::types::StateRoot;
+use near_store::config::{PrefetchKeyEncoding, PrefetchKeyPattern, PrefetchRuleConfig};
 use near_store::{PrefetchApi, PrefetchError, Trie};
 use sha2::Digest;
 use std::str::FromStr;
 use tracing::{debug, warn};
 
 use crate::metrics;
+
+/// Most keys the configured prefetch rules prefetch for a single call, so
+/// that a call with huge arguments doesn't flood the prefetcher.
+const MAX_RULE_KEYS_PER_CALL: usize = 1000;
+
 /// Transaction runtime view of the prefetching subsystem.
 pub(crate) struct TriePrefetcher {
     prefetch_api: PrefetchApi,
@@ -97,7 +103,9 @@ impl TriePrefetcher {
                 ReceiptEnum::Action(action_receipt) | ReceiptEnum::PromiseYield(action_receipt) => {
                     action_receipt
                 }
-                ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => {
+                ReceiptEnum::Data(_)
+                | ReceiptEnum::PromiseResume(_)
+                | ReceiptEnum::GlobalContractDistribution(_) => {
                     continue;
                 }
             };
@@ -137,6 +145,20 @@ impl TriePrefetcher {
                             };
                             self.prefetch_trie_key(trie_key)?;
                         }
+                        Action::IdempotencyKey(_) => {
+                            let trie_key = TrieKey::IdempotencyKeys {
+                                account_id: account_id.clone(),
+                                predecessor_id: receipt.predecessor_id().clone(),
+                            };
+                            self.prefetch_trie_key(trie_key)?;
+                        }
+                        Action::NamespacedFunctionCall(namespaced_call) => {
+                            let trie_key = TrieKey::NamespacedContractCode {
+                                account_id: account_id.clone(),
+                                namespace: namespaced_call.namespace.clone(),
+                            };
+                            self.prefetch_trie_key(trie_key)?;
+                        }
                         _ => {}
                     }
                 }
@@ -183,6 +205,19 @@ impl TriePrefetcher {
                 }) {
                     self.prefetch_kaiching(account_id.clone(), &fn_call.args)?;
                 }
+
+                for rule in &self.prefetch_api.prefetch_rules {
+                    if rule.receiver == account_id.as_str()
+                        && rule.method_name == fn_call.method_name
+                        && (rule.senders.is_empty()
+                            || rule
+                                .senders
+                                .iter()
+                                .any(|sender| sender == receipt.predecessor_id().as_str()))
+                    {
+                        self.prefetch_by_rule(&account_id, rule, &fn_call.args)?;
+                    }
+                }
             }
         }
         Ok(())
@@ -380,11 +415,71 @@ impl TriePrefetcher {
         }
         Ok(())
     }
+
+    /// Prefetches the keys of a configured rule, see [`PrefetchRuleConfig`].
+    fn prefetch_by_rule(
+        &self,
+        account_id: &AccountId,
+        rule: &PrefetchRuleConfig,
+        arg: &[u8],
+    ) -> Result<(), PrefetchError> {
+        let Ok(json) = serde_json::de::from_slice::<serde_json::Value>(arg) else {
+            return Ok(());
+        };
+        for key in rule_keys(rule, &json) {
+            let trie_key = TrieKey::ContractData { account_id: account_id.clone(), key };
+            near_o11y::io_trace!(count: "prefetch");
+            self.prefetch_trie_key(trie_key)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the contract storage keys the rule derives from the arguments of
+/// a call.
+fn rule_keys(rule: &PrefetchRuleConfig, args: &serde_json::Value) -> Vec<Vec<u8>> {
+    let mut keys = vec![];
+    for pattern in &rule.keys {
+        let elements: Vec<&serde_json::Value> = match &pattern.list {
+            Some(list) => match args.pointer(list).and_then(|list| list.as_array()) {
+                Some(list) => list.iter().collect(),
+                None => continue,
+            },
+            None => vec![args],
+        };
+        for element in elements {
+            let Some(value) = element.pointer(&pattern.value).and_then(|value| value.as_str())
+            else {
+                continue;
+            };
+            if keys.len() == MAX_RULE_KEYS_PER_CALL {
+                return keys;
+            }
+            keys.push(pattern_key(pattern, value));
+        }
+    }
+    keys
+}
+
+fn pattern_key(pattern: &PrefetchKeyPattern, value: &str) -> Vec<u8> {
+    let mut key = pattern.prefix.clone();
+    match pattern.encoding {
+        PrefetchKeyEncoding::Raw => key.extend_from_slice(value.as_bytes()),
+        PrefetchKeyEncoding::Borsh => {
+            key.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            key.extend_from_slice(value.as_bytes());
+        }
+        PrefetchKeyEncoding::Sha256 => key.extend(sha2::Sha256::digest(value.as_bytes())),
+    }
+    if pattern.hash_key {
+        key = sha2::Sha256::digest(&key).to_vec();
+    }
+    key
 }
 
 #[cfg(test)]
 mod tests {
-    use super::TriePrefetcher;
+    use super::{rule_keys, TriePrefetcher};
     use near_primitives::{trie_key::TrieKey, types::AccountId};
     use near_store::test_utils::{create_test_store, test_populate_trie};
     use near_store::{ShardTries, ShardUId, StateSnapshotConfig, Trie, TrieConfig};
@@ -564,6 +659,35 @@ mod tests {
         );
     }
 
+    /// The rules can express the built-in prefetchers, e.g. the one of SWEAT.
+    #[test]
+    fn test_rule_keys() {
+        use near_store::config::{PrefetchKeyEncoding, PrefetchKeyPattern, PrefetchRuleConfig};
+        use sha2::Digest;
+
+        let rule = PrefetchRuleConfig {
+            receiver: "token.sweat".to_owned(),
+            method_name: "record_batch".to_owned(),
+            keys: vec![PrefetchKeyPattern {
+                list: Some("/steps_batch".to_owned()),
+                value: "/0".to_owned(),
+                prefix: vec![0x74, 0x00],
+                encoding: PrefetchKeyEncoding::Sha256,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let args = serde_json::json!({"steps_batch": [["alice.near", 10], ["bob.near", 20], [5]]});
+        let expected: Vec<Vec<u8>> = ["alice.near", "bob.near"]
+            .iter()
+            .map(|account| [&[0x74, 0x00][..], &sha2::Sha256::digest(account.as_bytes())].concat())
+            .collect();
+        assert_eq!(rule_keys(&rule, &args), expected);
+
+        // Arguments without the list don't match.
+        assert!(rule_keys(&rule, &serde_json::json!({"amounts": []})).is_empty());
+    }
+
     #[track_caller]
     fn accounts_to_trie_keys(input: &[&str]) -> Vec<TrieKey> {
         input
@@ -1,13 +1,13 @@
-use crate::actions::execute_function_call;
+use crate::actions::run_function_call;
 use crate::ext::RuntimeExt;
 use crate::receipt_manager::ReceiptManager;
 use crate::{prepare_function_call, ApplyState};
 use near_crypto::{KeyType, PublicKey};
-use near_parameters::RuntimeConfigStore;
+use near_parameters::{RuntimeConfig, RuntimeConfigStore};
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::borsh::BorshDeserialize;
+use near_primitives::challenge::{PartialState, TrieValue};
 use near_primitives::hash::CryptoHash;
-use near_primitives::receipt::ActionReceipt;
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
 use near_primitives::transaction::FunctionCallAction;
 use near_primitives::trie_key::trie_key_parsers;
@@ -15,10 +15,16 @@ use near_primitives::types::{
     AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, ShardId,
 };
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{StateItem, ViewStateResult};
+use near_primitives::views::{
+    AccessKeyInfoView, AccessKeyList, AccessKeyListOptions, StateItem, ViewStateOptions,
+    ViewStateResult,
+};
 use near_primitives_core::config::ViewConfig;
-use near_store::{get_access_key, get_account, get_code, TrieUpdate};
-use near_vm_runner::logic::{ProtocolVersion, ReturnData};
+use near_store::{
+    get_access_key, get_account, get_code, KeyRange, Trie, TrieIterator, TrieRangeCursor,
+    TrieRangeIterator, TrieUpdate,
+};
+use near_vm_runner::logic::{ProtocolVersion, ReturnData, VMContext};
 use near_vm_runner::{ContractCode, ContractRuntimeCache};
 use std::{str, sync::Arc, time::Instant};
 use tracing::debug;
@@ -53,22 +59,24 @@ pub struct TrieViewer {
     state_size_limit: Option<u64>,
     /// Gas limit used when handling call_function queries.
     max_gas_burnt_view: Gas,
+    /// Runtime config the call_function queries run with. Kept around because
+    /// building the config store parses every parameter file.
+    runtime_config: Arc<RuntimeConfig>,
 }
 
 impl Default for TrieViewer {
     fn default() -> Self {
-        let config_store = RuntimeConfigStore::new(None);
-        let latest_runtime_config = config_store.get_config(PROTOCOL_VERSION);
-        let max_gas_burnt = latest_runtime_config.wasm_config.limit_config.max_gas_burnt;
-        Self { state_size_limit: None, max_gas_burnt_view: max_gas_burnt }
+        Self::new(None, None)
     }
 }
 
 impl TrieViewer {
     pub fn new(state_size_limit: Option<u64>, max_gas_burnt_view: Option<Gas>) -> Self {
+        let config_store = RuntimeConfigStore::new(None);
+        let runtime_config = Arc::clone(config_store.get_config(PROTOCOL_VERSION));
         let max_gas_burnt_view =
-            max_gas_burnt_view.unwrap_or_else(|| TrieViewer::default().max_gas_burnt_view);
-        Self { state_size_limit, max_gas_burnt_view }
+            max_gas_burnt_view.unwrap_or(runtime_config.wasm_config.limit_config.max_gas_burnt);
+        Self { state_size_limit, max_gas_burnt_view, runtime_config }
     }
 
     pub fn view_account(
@@ -83,6 +91,26 @@ impl TrieViewer {
         })
     }
 
+    /// Looks up the account like [`Self::view_account`], additionally
+    /// returning the trie nodes on the path to the account record. The nodes
+    /// prove the account against the state root of `trie`, regardless of
+    /// whether the lookup itself was served from memtrie, flat storage or disk.
+    pub fn view_account_with_proof(
+        &self,
+        trie: &Trie,
+        account_id: &AccountId,
+    ) -> Result<(Account, Vec<TrieValue>), errors::ViewAccountError> {
+        let trie = trie.recording_reads();
+        let account = get_account(&trie, account_id)?.ok_or_else(|| {
+            errors::ViewAccountError::AccountDoesNotExist {
+                requested_account_id: account_id.clone(),
+            }
+        })?;
+        let PartialState::TrieValues(proof) =
+            trie.recorded_storage().expect("trie is recording reads").nodes;
+        Ok((account, proof))
+    }
+
     pub fn view_contract_code(
         &self,
         state_update: &TrieUpdate,
@@ -137,19 +165,81 @@ impl TrieViewer {
         access_keys
     }
 
+    /// Lists the access keys of the account matching `options`, in the order in which they are
+    /// stored in the trie. If the list is limited and there are more matching keys, the last
+    /// listed key is returned as the cursor of the next page.
+    pub fn view_access_key_list(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+        options: &AccessKeyListOptions,
+    ) -> Result<AccessKeyList, errors::ViewAccessKeyError> {
+        let prefix = trie_key_parsers::get_raw_prefix_for_access_keys(account_id);
+        let mut iter = TrieRangeIterator::new(
+            TrieIterator::Disk(state_update.trie().disk_iter()?),
+            KeyRange::prefix(&prefix),
+        )?;
+        // The keys are ordered by the borsh representation of the public key, so the listing
+        // continues right after the cursor without reading the keys up to it.
+        if let Some(cursor) = &options.cursor {
+            let mut key = prefix.clone();
+            key.extend(near_primitives::borsh::to_vec(cursor).unwrap());
+            // The smallest key after the cursor.
+            key.push(0);
+            iter.seek(&key)?;
+        }
+        let limit = options.limit.map_or(usize::MAX, |limit| limit.get() as usize);
+        let mut access_key_list = AccessKeyList { keys: vec![], next_cursor: None };
+        for item in iter {
+            let (key, value) = item?;
+            let public_key = &key[prefix.len()..];
+            let access_key = AccessKey::try_from_slice(&value).map_err(|_| {
+                errors::ViewAccessKeyError::InternalError {
+                    error_message: format!(
+                        "Unexpected invalid access key {:?} received from store",
+                        value
+                    ),
+                }
+            })?;
+            if !options.matches(&access_key) {
+                continue;
+            }
+            if access_key_list.keys.len() >= limit {
+                access_key_list.next_cursor =
+                    access_key_list.keys.last().map(|key| key.public_key.clone());
+                break;
+            }
+            let public_key = PublicKey::try_from_slice(public_key).map_err(|_| {
+                errors::ViewAccessKeyError::InternalError {
+                    error_message: format!(
+                        "Unexpected invalid public key {:?} received from store",
+                        public_key
+                    ),
+                }
+            })?;
+            access_key_list
+                .keys
+                .push(AccessKeyInfoView { public_key, access_key: access_key.into() });
+        }
+        Ok(access_key_list)
+    }
+
     pub fn view_state(
         &self,
         state_update: &TrieUpdate,
         account_id: &AccountId,
         prefix: &[u8],
         include_proof: bool,
+        options: &ViewStateOptions,
     ) -> Result<ViewStateResult, errors::ViewStateError> {
         match get_account(state_update, account_id)? {
             Some(account) => {
                 let code_len = get_code(state_update, account_id, Some(account.code_hash()))?
                     .map(|c| c.code().len() as u64)
                     .unwrap_or_default();
-                if let Some(limit) = self.state_size_limit {
+                // Pages are limited by the request, so the state can be of any size.
+                let state_size_limit = self.state_size_limit.filter(|_| options.limit.is_none());
+                if let Some(limit) = state_size_limit {
                     if account.storage_usage().saturating_sub(code_len) > limit {
                         return Err(errors::ViewStateError::AccountStateTooLarge {
                             requested_account_id: account_id.clone(),
@@ -169,15 +259,41 @@ impl TrieViewer {
         let acc_sep_len = query.len() - prefix.len();
         let mut iter = state_update.trie().disk_iter()?;
         iter.remember_visited_nodes(include_proof);
-        iter.seek_prefix(&query)?;
-        for item in &mut iter {
-            let (key, value) = item?;
+        // A prefix seek visits no nodes outside of the prefix, which keeps the proof minimal.
+        if options.limit.is_none() && options.cursor.is_none() {
+            iter.seek_prefix(&query)?;
+            for item in &mut iter {
+                let (key, value) = item?;
+                values.push(StateItem {
+                    key: key[acc_sep_len..].to_vec().into(),
+                    value: value.into(),
+                });
+            }
+            let proof = iter.into_visited_nodes();
+            return Ok(ViewStateResult { values, proof, next_cursor: None });
+        }
+
+        // Cursors are keys without the account part, like the keys of the values.
+        let mut iter = TrieRangeIterator::new(TrieIterator::Disk(iter), KeyRange::prefix(&query))?;
+        if let Some(cursor) = &options.cursor {
+            let mut key = query[..acc_sep_len].to_vec();
+            key.extend_from_slice(cursor.as_ref());
+            iter.resume(&TrieRangeCursor(key))?;
+        }
+        let limit = options.limit.map_or(usize::MAX, |limit| limit as usize);
+        for (key, value) in iter.next_n(limit)? {
             values.push(StateItem { key: key[acc_sep_len..].to_vec().into(), value: value.into() });
         }
+        let next_cursor = iter.cursor().map(|cursor| cursor.0[acc_sep_len..].to_vec().into());
         let proof = iter.into_visited_nodes();
-        Ok(ViewStateResult { values, proof })
+        Ok(ViewStateResult { values, proof, next_cursor })
     }
 
+    /// Runs a read-only call of `method_name` on `contract_id`.
+    ///
+    /// Unlike a function call action, the call is not part of a receipt: there is no
+    /// signer to check, no gas to distribute to promises (view calls cannot create any)
+    /// and nothing to commit, as view calls cannot write to the state either.
     pub fn call_function(
         &self,
         mut state_update: TrieUpdate,
@@ -200,8 +316,7 @@ impl TrieViewer {
         let public_key = PublicKey::empty(KeyType::ED25519);
         let empty_hash = CryptoHash::default();
         let mut receipt_manager = ReceiptManager::default();
-        let config_store = RuntimeConfigStore::new(None);
-        let config = config_store.get_config(PROTOCOL_VERSION);
+        let config = &self.runtime_config;
         let apply_state = ApplyState {
             apply_reason: None,
             block_height: view_state.block_height,
@@ -222,14 +337,7 @@ impl TrieViewer {
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
             congestion_info: Default::default(),
-        };
-        let action_receipt = ActionReceipt {
-            signer_id: originator_id.clone(),
-            signer_public_key: public_key,
-            gas_price: 0,
-            output_data_receivers: vec![],
-            input_data_ids: vec![],
-            actions: vec![],
+            host_call_tracer: None,
         };
         let function_call = FunctionCallAction {
             method_name: method_name.to_string(),
@@ -243,15 +351,42 @@ impl TrieViewer {
             &apply_state,
             &account,
             &contract_id,
+            None,
             &function_call,
             config,
             view_config.clone(),
         );
+        let context = VMContext {
+            current_account_id: contract_id.clone(),
+            signer_account_id: originator_id.clone(),
+            signer_account_pk: borsh::to_vec(&public_key).expect("Failed to serialize"),
+            predecessor_account_id: originator_id.clone(),
+            input: function_call.args,
+            promise_results: [].into(),
+            block_height: view_state.block_height,
+            block_timestamp: view_state.block_timestamp,
+            epoch_height: view_state.epoch_height,
+            account_balance: account.amount(),
+            account_locked_balance: account.locked(),
+            storage_usage: account.storage_usage(),
+            attached_deposit: 0,
+            prepaid_gas: function_call.gas,
+            random_seed: near_primitives::utils::create_random_seed(
+                view_state.current_protocol_version,
+                empty_hash,
+                root,
+            ),
+            random_beacon: None,
+            view_config,
+            output_data_receivers: vec![],
+            trace_host_calls: false,
+        };
         let mut runtime_ext = RuntimeExt::new(
             &mut state_update,
             &mut receipt_manager,
             contract_id.clone(),
             account,
+            None,
             empty_hash,
             view_state.epoch_id,
             view_state.prev_block_hash,
@@ -259,20 +394,10 @@ impl TrieViewer {
             epoch_info_provider,
             view_state.current_protocol_version,
         );
-        let outcome = execute_function_call(
-            contract,
-            &apply_state,
-            &mut runtime_ext,
-            originator_id,
-            &action_receipt,
-            [].into(),
-            &function_call,
-            &empty_hash,
-            config,
-            true,
-            view_config,
-        )
-        .map_err(|e| errors::CallFunctionError::InternalError { error_message: e.to_string() })?;
+        let outcome = run_function_call(contract, &apply_state, &mut runtime_ext, &context, config)
+            .map_err(|e| errors::CallFunctionError::InternalError {
+                error_message: e.to_string(),
+            })?;
         let elapsed = now.elapsed();
         let time_ms =
             (elapsed.as_secs() as f64 / 1_000.0) + f64::from(elapsed.subsec_nanos()) / 1_000_000.0;
@@ -1,5 +1,5 @@
 use super::{to_yocto, GAS_PRICE};
-use crate::config::safe_add_gas;
+use crate::config::{global_contract_distribution_fee, safe_add_gas};
 use crate::congestion_control::{receipt_congestion_gas, receipt_size};
 use crate::tests::{create_receipt_with_actions, MAX_ATTACHED_GAS};
 use crate::total_prepaid_exec_fees;
@@ -8,6 +8,7 @@ use assert_matches::assert_matches;
 use near_crypto::{InMemorySigner, KeyType, PublicKey, Signer};
 use near_parameters::{ActionCosts, ExtCosts, ParameterCost, RuntimeConfig};
 use near_primitives::account::AccessKey;
+use near_primitives::action::atomic_batch::{AtomicBatchAction, AtomicBatchReceipt, BatchedAction};
 use near_primitives::action::delegate::{DelegateAction, NonDelegateAction, SignedDelegateAction};
 use near_primitives::action::Action;
 use near_primitives::checked_feature;
@@ -18,11 +19,12 @@ use near_primitives::errors::{ActionErrorKind, FunctionCallError, TxExecutionErr
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum, ReceiptPriority, ReceiptV0};
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
-use near_primitives::shard_layout::ShardUId;
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::test_utils::{account_new, MockEpochInfoProvider};
 use near_primitives::transaction::{
-    AddKeyAction, DeleteKeyAction, DeployContractAction, ExecutionOutcomeWithId, ExecutionStatus,
-    FunctionCallAction, SignedTransaction, TransferAction,
+    AddKeyAction, DeleteKeyAction, DeployContractAction, DeployGlobalContractAction,
+    ExecutionOutcomeWithId, ExecutionStatus, FunctionCallAction, RefundReason, SignedTransaction,
+    TransferAction, UseGlobalContractAction,
 };
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
@@ -32,7 +34,7 @@ use near_primitives::utils::create_receipt_id_from_transaction;
 use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
 use near_store::test_utils::TestTriesBuilder;
 use near_store::trie::receipts_column_helper::ShardsOutgoingReceiptBuffer;
-use near_store::{get_account, set_access_key, set_account, ShardTries, Trie};
+use near_store::{get_account, has_global_code, set_access_key, set_account, ShardTries, Trie};
 use near_vm_runner::FilesystemContractRuntimeCache;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -102,6 +104,7 @@ fn setup_runtime_for_shard(
         migration_data: Arc::new(MigrationData::default()),
         migration_flags: MigrationFlags::default(),
         congestion_info,
+        host_call_tracer: None,
     };
 
     (runtime, tries, root, apply_state, signer, MockEpochInfoProvider::default())
@@ -754,6 +757,13 @@ fn test_apply_deficit_gas_for_function_call_covered() {
         }
         _ => unreachable!(),
     };
+    // The outcome records the refund when it's issued.
+    let refunds = result.outcomes[0].outcome.metadata.refunds();
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds[0].receipt_id, *result.outgoing_receipts[0].receipt_id());
+    assert_eq!(refunds[0].receiver_id, bob_account());
+    assert_eq!(refunds[0].amount, expected_refund);
+    assert_eq!(refunds[0].reason, RefundReason::UnusedGas);
 }
 
 #[test]
@@ -1594,3 +1604,296 @@ fn test_deploy_and_call_local_receipts() {
         ActionErrorKind::FunctionCallError(FunctionCallError::MethodResolveError(_))
     );
 }
+
+fn atomic_batch_receipt(signer: Arc<Signer>, deposit: Balance, max_block_height: u64) -> Receipt {
+    let transfer = Action::Transfer(TransferAction { deposit });
+    create_receipt_with_actions(
+        alice_account(),
+        signer,
+        vec![Action::AtomicBatch(Box::new(AtomicBatchAction {
+            max_block_height,
+            receipts: vec![AtomicBatchReceipt {
+                receiver_id: bob_account(),
+                actions: vec![BatchedAction::try_from(transfer).unwrap()],
+            }],
+        }))],
+    )
+}
+
+/// Sets up the runtime on shard 1 while shard 0, which holds all accounts,
+/// is fully congested, so that nothing can be forwarded to it.
+fn setup_congested_runtime(
+    gas_limit: Gas,
+) -> (Runtime, ShardTries, CryptoHash, ApplyState, Arc<Signer>, impl EpochInfoProvider) {
+    let local_shard_uid = ShardUId { version: 0, shard_id: 1 };
+    let (runtime, tries, root, mut apply_state, signer, epoch_info_provider) =
+        setup_runtime_for_shard(to_yocto(1_000_000), to_yocto(500_000), gas_limit, local_shard_uid);
+    apply_state.shard_id = 1;
+    let max_congestion_incoming_gas: Gas =
+        apply_state.config.congestion_control_config.max_congestion_incoming_gas;
+    let congestion_info = &mut apply_state.congestion_info.get_mut(&0).unwrap().congestion_info;
+    congestion_info.add_delayed_receipt_gas(max_congestion_incoming_gas).unwrap();
+    congestion_info.set_allowed_shard(0);
+    apply_state.congestion_info.insert(1, Default::default());
+    (runtime, tries, root, apply_state, signer, epoch_info_provider)
+}
+
+fn transfers_to_bob(apply_result: &ApplyResult) -> Vec<&Receipt> {
+    apply_result
+        .outgoing_receipts
+        .iter()
+        .filter(|receipt| receipt.receiver_id() == &bob_account())
+        .collect()
+}
+
+/// Without congestion, the receipts of an atomic batch are sent right away.
+#[test]
+fn test_atomic_batch_send() {
+    if !ProtocolFeature::AtomicBatches.enabled(PROTOCOL_VERSION) {
+        return;
+    }
+    let (runtime, tries, root, apply_state, signer, epoch_info_provider) =
+        setup_runtime(to_yocto(1_000_000), to_yocto(500_000), 10u64.pow(15));
+    let receipt = atomic_batch_receipt(signer, to_yocto(1), 10);
+
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(ShardUId::single_shard(), root),
+            &None,
+            &apply_state,
+            &[receipt.clone()],
+            &[],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+
+    let outcome = apply_result.outcomes.iter().find(|o| &o.id == receipt.receipt_id()).unwrap();
+    assert_matches!(outcome.outcome.status, ExecutionStatus::SuccessValue(_));
+    assert_eq!(transfers_to_bob(&apply_result).len(), 1);
+    assert_eq!(apply_result.delayed_receipts_count, 0);
+}
+
+/// Atomic batches wait in the delayed receipts while the receiving shard is
+/// congested.  Each retry counts towards the compute limit, so that waiting
+/// batches aren't rescanned for free in every chunk.
+#[test]
+fn test_atomic_batch_waits_for_congestion() {
+    if !ProtocolFeature::AtomicBatches.enabled(PROTOCOL_VERSION) {
+        return;
+    }
+    // A single receipt per chunk fits into the compute limit.
+    let gas_limit = 1;
+    let (runtime, tries, root, mut apply_state, signer, epoch_info_provider) =
+        setup_congested_runtime(gas_limit);
+    let local_shard_uid = ShardUId { version: 0, shard_id: 1 };
+    let receipts = vec![
+        atomic_batch_receipt(signer.clone(), to_yocto(1), 10),
+        atomic_batch_receipt(signer, to_yocto(2), 10),
+    ];
+
+    // The first batch waits and the second one is delayed by the compute
+    // used for the retry.
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(local_shard_uid, root),
+            &None,
+            &apply_state,
+            &receipts,
+            &[],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+    assert!(apply_result.outcomes.is_empty());
+    assert!(apply_result.outgoing_receipts.is_empty());
+    assert_eq!(apply_result.delayed_receipts_count, 2);
+
+    // Only one of the waiting batches is retried in the next chunk.
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(local_shard_uid, root),
+            &None,
+            &apply_state,
+            &[],
+            &[],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+    assert!(apply_result.outcomes.is_empty());
+    assert_eq!(apply_result.processed_delayed_receipts.len(), 1);
+    assert_eq!(apply_result.delayed_receipts_count, 2);
+
+    // Once the congestion clears, the batches are sent.
+    apply_state.congestion_info.insert(0, ExtendedCongestionInfo::default());
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(local_shard_uid, root),
+            &None,
+            &apply_state,
+            &[],
+            &[],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let outcome = &apply_result.outcomes[0];
+    assert_eq!(&outcome.id, receipts[0].receipt_id());
+    assert_matches!(outcome.outcome.status, ExecutionStatus::SuccessValue(_));
+    assert_eq!(transfers_to_bob(&apply_result).len(), 1);
+    assert_eq!(apply_result.delayed_receipts_count, 1);
+}
+
+/// An atomic batch which waits until its `max_block_height` fails, so that
+/// its deposits and gas are refunded.
+#[test]
+fn test_atomic_batch_expires() {
+    if !ProtocolFeature::AtomicBatches.enabled(PROTOCOL_VERSION) {
+        return;
+    }
+    let (runtime, tries, root, mut apply_state, signer, epoch_info_provider) =
+        setup_congested_runtime(10u64.pow(15));
+    let local_shard_uid = ShardUId { version: 0, shard_id: 1 };
+    let receipt = atomic_batch_receipt(signer, to_yocto(1), apply_state.block_height);
+
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(local_shard_uid, root),
+            &None,
+            &apply_state,
+            &[receipt.clone()],
+            &[],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+    assert!(apply_result.outcomes.is_empty());
+    assert_eq!(apply_result.delayed_receipts_count, 1);
+
+    apply_state.block_height += 1;
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(local_shard_uid, root),
+            &None,
+            &apply_state,
+            &[],
+            &[],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let outcome = apply_result.outcomes.iter().find(|o| &o.id == receipt.receipt_id()).unwrap();
+    let action_error = assert_matches!(
+        &outcome.outcome.status,
+        ExecutionStatus::Failure(TxExecutionError::ActionError(ae)) => ae
+    );
+    assert_eq!(action_error.kind, ActionErrorKind::AtomicBatchExpired);
+    assert!(transfers_to_bob(&apply_result).is_empty());
+    assert_eq!(apply_result.delayed_receipts_count, 0);
+}
+
+/// A global contract is deployed on the shard of its account, distributed to
+/// the other shard and then used by an account to call it.
+#[test]
+fn test_global_contract_deploy_distribute_and_call() {
+    if !ProtocolFeature::GlobalContracts.enabled(PROTOCOL_VERSION) {
+        return;
+    }
+    let (runtime, tries, root, mut apply_state, signer, _) =
+        setup_runtime(to_yocto(1_000_000), to_yocto(500_000), 10u64.pow(15));
+    let epoch_info_provider =
+        MockEpochInfoProvider { shard_layout: ShardLayout::v0(2, 0), ..Default::default() };
+    let code = near_test_contracts::rs_contract().to_vec();
+    let code_hash = hash(&code);
+
+    let deploy_tx = SignedTransaction::from_actions(
+        1,
+        alice_account(),
+        alice_account(),
+        &*signer,
+        vec![Action::DeployGlobalContract(DeployGlobalContractAction { code: code.clone() })],
+        CryptoHash::default(),
+        0,
+    );
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(ShardUId::single_shard(), root),
+            &None,
+            &apply_state,
+            &[],
+            &[deploy_tx],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+    let distributions: Vec<_> = apply_result
+        .outgoing_receipts
+        .iter()
+        .filter(|receipt| matches!(receipt.receipt(), ReceiptEnum::GlobalContractDistribution(_)))
+        .collect();
+    let distribution = assert_matches!(&distributions[..], [distribution] => distribution);
+    assert_eq!(distribution.receiver_shard_id(&epoch_info_provider.shard_layout), 1);
+
+    // The other shard stores the code too and the distribution ends there.
+    let other_shard_uid = ShardUId { version: 0, shard_id: 1 };
+    apply_state.shard_id = 1;
+    apply_state.congestion_info.insert(1, Default::default());
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(other_shard_uid, MerkleHash::default()),
+            &None,
+            &apply_state,
+            &[distribution.clone()],
+            &[],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let other_root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+    apply_state.shard_id = 0;
+    let outcome = &apply_result.outcomes[0].outcome;
+    assert_eq!(outcome.status, ExecutionStatus::SuccessValue(vec![]));
+    let distribution_fee = global_contract_distribution_fee(&apply_state.config, code.len());
+    assert_eq!(outcome.gas_burnt, distribution_fee);
+    assert_eq!(outcome.compute_usage, Some(distribution_fee));
+    assert!(apply_result.outgoing_receipts.is_empty());
+    let other_state = tries.new_trie_update(other_shard_uid, other_root);
+    assert!(has_global_code(&other_state, code_hash).unwrap());
+
+    let call_tx = SignedTransaction::from_actions(
+        2,
+        alice_account(),
+        alice_account(),
+        &*signer,
+        vec![
+            Action::UseGlobalContract(UseGlobalContractAction { code_hash }),
+            Action::FunctionCall(Box::new(FunctionCallAction {
+                method_name: "log_something".to_string(),
+                args: vec![],
+                gas: MAX_ATTACHED_GAS / 2,
+                deposit: 0,
+            })),
+        ],
+        CryptoHash::default(),
+        0,
+    );
+    let apply_result = runtime
+        .apply(
+            tries.get_trie_for_shard(ShardUId::single_shard(), root),
+            &None,
+            &apply_state,
+            &[],
+            &[call_tx],
+            &epoch_info_provider,
+            Default::default(),
+        )
+        .unwrap();
+    let outcome = &apply_result.outcomes[1].outcome;
+    assert_eq!(outcome.status, ExecutionStatus::SuccessValue(vec![]));
+    assert_eq!(&outcome.logs[..], ["hello"]);
+}
@@ -2,8 +2,10 @@ use crate::config::{total_prepaid_gas, tx_cost, TransactionCost};
 use crate::near_primitives::account::Account;
 use crate::VerificationResult;
 use near_crypto::key_conversion::is_valid_staking_key;
+use near_crypto::PublicKey;
 use near_parameters::RuntimeConfig;
-use near_primitives::account::AccessKeyPermission;
+use near_primitives::account::{AccessKey, AccessKeyPermission, AccountNamespace};
+use near_primitives::action::atomic_batch::AtomicBatchAction;
 use near_primitives::action::delegate::SignedDelegateAction;
 use near_primitives::checked_feature;
 use near_primitives::errors::{
@@ -12,7 +14,9 @@ use near_primitives::errors::{
 use near_primitives::receipt::{ActionReceipt, DataReceipt, Receipt, ReceiptEnum};
 use near_primitives::transaction::DeleteAccountAction;
 use near_primitives::transaction::{
-    Action, AddKeyAction, DeployContractAction, FunctionCallAction, SignedTransaction, StakeAction,
+    Action, AddKeyAction, DeployContractAction, DeployGlobalContractAction,
+    DeployNamespacedContractAction, FunctionCallAction, IdempotencyKeyAction,
+    NamespacedFunctionCallAction, SignedTransaction, StakeAction,
 };
 use near_primitives::types::{AccountId, Balance};
 use near_primitives::types::{BlockHeight, StorageUsage};
@@ -25,6 +29,19 @@ use near_vm_runner::logic::LimitConfig;
 
 pub const ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT: StorageUsage = 770;
 
+/// Maximum length of the key of an `IdempotencyKey` action.  Receivers only
+/// store a hash of the key, so this just keeps transactions from carrying
+/// arbitrary payloads in it.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: u64 = 64;
+
+/// Maximum number of receipts sent by an `AtomicBatch` action.
+pub const MAX_ATOMIC_BATCH_RECEIPTS: u64 = 16;
+
+/// Maximum number of blocks between the height at which an `AtomicBatch`
+/// action is submitted and its `max_block_height`.  A batch which can't be
+/// sent yet is retried in every chunk until then, so this bounds the retries.
+pub const MAX_ATOMIC_BATCH_WAIT_HEIGHT: BlockHeight = 100;
+
 /// Possible errors when checking whether an account has enough tokens for storage staking
 /// Read details of state staking
 /// <https://nomicon.io/Economics/README.html#state-stake>.
@@ -97,8 +114,9 @@ pub fn validate_transaction(
     verify_signature: bool,
     current_protocol_version: ProtocolVersion,
 ) -> Result<TransactionCost, InvalidTxError> {
-    // Don't allow V1 currently. This will be changed when the new protocol version is introduced.
-    if matches!(signed_transaction.transaction, near_primitives::transaction::Transaction::V1(_)) {
+    if matches!(signed_transaction.transaction, near_primitives::transaction::Transaction::V1(_))
+        && !ProtocolFeature::TransactionPriorityFee.enabled(current_protocol_version)
+    {
         return Err(InvalidTxError::InvalidTransactionVersion);
     }
     let transaction = &signed_transaction.transaction;
@@ -199,6 +217,15 @@ pub fn verify_and_charge_transaction(
         }
     };
 
+    if let Some(height) = block_height {
+        validate_atomic_batch_max_block_height(transaction.actions(), height)
+            .map_err(InvalidTxError::ActionsValidation)?;
+    }
+
+    if let Some(height) = block_height {
+        validate_access_key_expiry(signer_id, transaction.public_key(), &access_key, height)?;
+    }
+
     access_key.nonce = transaction.nonce();
 
     signer.set_amount(signer.amount().checked_sub(total_cost).ok_or_else(|| {
@@ -209,19 +236,24 @@ pub fn verify_and_charge_transaction(
         }
     })?);
 
-    if let AccessKeyPermission::FunctionCall(ref mut function_call_permission) =
-        access_key.permission
-    {
-        if let Some(ref mut allowance) = function_call_permission.allowance {
-            *allowance = allowance.checked_sub(total_cost).ok_or_else(|| {
-                InvalidTxError::InvalidAccessKeyError(InvalidAccessKeyError::NotEnoughAllowance {
-                    account_id: signer_id.clone(),
-                    public_key: transaction.public_key().clone().into(),
-                    allowance: *allowance,
-                    cost: total_cost,
-                })
-            })?;
+    let allowance = match access_key.permission {
+        AccessKeyPermission::FunctionCall(ref mut function_call_permission) => {
+            function_call_permission.allowance.as_mut()
+        }
+        AccessKeyPermission::LimitedFullAccess(ref mut permission) => {
+            Some(&mut permission.allowance)
         }
+        AccessKeyPermission::FullAccess => None,
+    };
+    if let Some(allowance) = allowance {
+        *allowance = allowance.checked_sub(total_cost).ok_or_else(|| {
+            InvalidTxError::InvalidAccessKeyError(InvalidAccessKeyError::NotEnoughAllowance {
+                account_id: signer_id.clone(),
+                public_key: transaction.public_key().clone().into(),
+                allowance: *allowance,
+                cost: total_cost,
+            })
+        })?;
     }
 
     match check_storage_stake(&signer, config, current_protocol_version) {
@@ -281,6 +313,14 @@ pub fn verify_and_charge_transaction(
             .into());
         }
     };
+    if let AccessKeyPermission::LimitedFullAccess(_) = access_key.permission {
+        validate_limited_full_access_actions(
+            signer_id,
+            transaction.receiver_id(),
+            transaction.actions(),
+        )
+        .map_err(InvalidTxError::InvalidAccessKeyError)?;
+    }
 
     set_access_key(state_update, signer_id.clone(), transaction.public_key().clone(), &access_key);
     set_account(state_update, signer_id.clone(), &signer);
@@ -288,6 +328,69 @@ pub fn verify_and_charge_transaction(
     Ok(VerificationResult { gas_burnt, gas_remaining, receipt_gas_price, burnt_amount })
 }
 
+/// Checks that the `LimitedFullAccess` key `public_key` of `signer_id` hasn't expired at
+/// `block_height`. Other access keys never expire.
+pub fn validate_access_key_expiry(
+    signer_id: &AccountId,
+    public_key: &PublicKey,
+    access_key: &AccessKey,
+    block_height: BlockHeight,
+) -> Result<(), InvalidTxError> {
+    if let AccessKeyPermission::LimitedFullAccess(ref permission) = access_key.permission {
+        if let Some(expiry_height) = permission.expiry_height {
+            if block_height > expiry_height {
+                return Err(InvalidTxError::InvalidAccessKeyError(
+                    InvalidAccessKeyError::AccessKeyExpired {
+                        account_id: signer_id.clone(),
+                        public_key: public_key.clone().into(),
+                        expiry_height,
+                    },
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the `actions` sent by `signer_id` to `receiver_id` with a `LimitedFullAccess` key
+/// can't spend more than its allowance, that is they don't change the keys or the code of the
+/// signer, call its contract nor delete it, directly or in an atomic batch. The contract of the
+/// signer can move its balance without charging the allowance, so it can't be called either.
+pub(crate) fn validate_limited_full_access_actions(
+    signer_id: &AccountId,
+    receiver_id: &AccountId,
+    actions: &[Action],
+) -> Result<(), InvalidAccessKeyError> {
+    for action in actions {
+        match action {
+            Action::AddKey(_)
+            | Action::DeleteKey(_)
+            | Action::DeployContract(_)
+            | Action::DeployNamespacedContract(_)
+            | Action::DeployGlobalContract(_)
+            | Action::UseGlobalContract(_)
+            | Action::DeleteAccount(_)
+            | Action::FunctionCall(_)
+            | Action::NamespacedFunctionCall(_)
+                if receiver_id == signer_id =>
+            {
+                return Err(InvalidAccessKeyError::RequiresFullAccess);
+            }
+            Action::AtomicBatch(atomic_batch) => {
+                for receipt in &atomic_batch.receipts {
+                    validate_limited_full_access_actions(
+                        signer_id,
+                        &receipt.receiver_id,
+                        &receipt.get_actions(),
+                    )?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 /// Validates a given receipt. Checks validity of the Action or Data receipt.
 pub(crate) fn validate_receipt(
     limit_config: &LimitConfig,
@@ -322,6 +425,9 @@ pub(crate) fn validate_receipt(
         ReceiptEnum::Data(data_receipt) | ReceiptEnum::PromiseResume(data_receipt) => {
             validate_data_receipt(limit_config, data_receipt)
         }
+        // The code was validated by the `DeployGlobalContract` action that
+        // started the distribution.
+        ReceiptEnum::GlobalContractDistribution(_) => Ok(()),
     }
 }
 
@@ -362,6 +468,8 @@ fn validate_data_receipt(
 /// - Checks that the total number of actions doesn't exceed the limit.
 /// - Checks that there not other action if Action::Delegate is present.
 /// - Validates each individual action.
+/// - Checks that an Action::IdempotencyKey comes first and along with function calls.
+/// - Checks that there are no other actions if Action::AtomicBatch is present.
 /// - Checks that the total prepaid gas doesn't exceed the limit.
 pub(crate) fn validate_actions(
     limit_config: &LimitConfig,
@@ -395,10 +503,27 @@ pub(crate) fn validate_actions(
                 }
                 found_delegate_action = true;
             }
+            if let Action::AtomicBatch(_) = action {
+                check_feature_enabled(ProtocolFeature::AtomicBatches, current_protocol_version)?;
+                if actions.len() > 1 {
+                    return Err(ActionsValidationError::AtomicBatchMustBeOnlyOne);
+                }
+            }
         }
         validate_action(limit_config, action, current_protocol_version)?;
     }
 
+    if actions.iter().any(|action| matches!(action, Action::IdempotencyKey(_))) {
+        if actions[1..].iter().any(|action| matches!(action, Action::IdempotencyKey(_))) {
+            return Err(ActionsValidationError::IdempotencyKeyMustBeFirst);
+        }
+        if !actions.iter().any(|action| {
+            matches!(action, Action::FunctionCall(_) | Action::NamespacedFunctionCall(_))
+        }) {
+            return Err(ActionsValidationError::IdempotencyKeyWithoutFunctionCall);
+        }
+    }
+
     let total_prepaid_gas =
         total_prepaid_gas(actions).map_err(|_| ActionsValidationError::IntegerOverflow)?;
     if total_prepaid_gas > limit_config.max_total_prepaid_gas {
@@ -427,10 +552,26 @@ pub fn validate_action(
             check_feature_enabled(ProtocolFeature::NonrefundableStorage, current_protocol_version)
         }
         Action::Stake(a) => validate_stake_action(a),
-        Action::AddKey(a) => validate_add_key_action(limit_config, a),
+        Action::AddKey(a) => validate_add_key_action(limit_config, a, current_protocol_version),
         Action::DeleteKey(_) => Ok(()),
         Action::DeleteAccount(a) => validate_delete_action(a),
         Action::Delegate(a) => validate_delegate_action(limit_config, a, current_protocol_version),
+        Action::IdempotencyKey(a) => validate_idempotency_key_action(a, current_protocol_version),
+        Action::AtomicBatch(a) => {
+            validate_atomic_batch_action(limit_config, a, current_protocol_version)
+        }
+        Action::DeployNamespacedContract(a) => {
+            validate_deploy_namespaced_contract_action(limit_config, a, current_protocol_version)
+        }
+        Action::NamespacedFunctionCall(a) => {
+            validate_namespaced_function_call_action(limit_config, a, current_protocol_version)
+        }
+        Action::DeployGlobalContract(a) => {
+            validate_deploy_global_contract_action(limit_config, a, current_protocol_version)
+        }
+        Action::UseGlobalContract(_) => {
+            check_feature_enabled(ProtocolFeature::GlobalContracts, current_protocol_version)
+        }
     }
 }
 
@@ -444,6 +585,61 @@ fn validate_delegate_action(
     Ok(())
 }
 
+/// Validates `AtomicBatchAction`. Checks the number of receipts and the actions of each of them.
+fn validate_atomic_batch_action(
+    limit_config: &LimitConfig,
+    action: &AtomicBatchAction,
+    current_protocol_version: ProtocolVersion,
+) -> Result<(), ActionsValidationError> {
+    let total_number = action.receipts.len() as u64;
+    if total_number == 0 || total_number > MAX_ATOMIC_BATCH_RECEIPTS {
+        return Err(ActionsValidationError::InvalidAtomicBatchReceiptsNumber {
+            total_number,
+            limit: MAX_ATOMIC_BATCH_RECEIPTS,
+        });
+    }
+    for receipt in &action.receipts {
+        validate_actions(limit_config, &receipt.get_actions(), current_protocol_version)?;
+    }
+    Ok(())
+}
+
+/// Checks that the `max_block_height` of an `AtomicBatch` action is at most
+/// `MAX_ATOMIC_BATCH_WAIT_HEIGHT` blocks after `block_height`.
+fn validate_atomic_batch_max_block_height(
+    actions: &[Action],
+    block_height: BlockHeight,
+) -> Result<(), ActionsValidationError> {
+    let limit = block_height.saturating_add(MAX_ATOMIC_BATCH_WAIT_HEIGHT);
+    for action in actions {
+        if let Action::AtomicBatch(atomic_batch) = action {
+            if atomic_batch.max_block_height > limit {
+                return Err(ActionsValidationError::AtomicBatchMaxBlockHeightTooLarge {
+                    max_block_height: atomic_batch.max_block_height,
+                    limit,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validates `IdempotencyKeyAction`. Checks that the key length doesn't exceed the limit.
+fn validate_idempotency_key_action(
+    action: &IdempotencyKeyAction,
+    current_protocol_version: ProtocolVersion,
+) -> Result<(), ActionsValidationError> {
+    check_feature_enabled(ProtocolFeature::IdempotencyKeys, current_protocol_version)?;
+    if action.key.len() as u64 > MAX_IDEMPOTENCY_KEY_LENGTH {
+        return Err(ActionsValidationError::IdempotencyKeyLengthExceeded {
+            length: action.key.len() as u64,
+            limit: MAX_IDEMPOTENCY_KEY_LENGTH,
+        });
+    }
+
+    Ok(())
+}
+
 /// Validates `DeployContractAction`. Checks that the given contract size doesn't exceed the limit.
 fn validate_deploy_contract_action(
     limit_config: &LimitConfig,
@@ -459,6 +655,63 @@ fn validate_deploy_contract_action(
     Ok(())
 }
 
+/// Validates `DeployNamespacedContractAction`. Checks the namespace and the contract size.
+fn validate_deploy_namespaced_contract_action(
+    limit_config: &LimitConfig,
+    action: &DeployNamespacedContractAction,
+    current_protocol_version: ProtocolVersion,
+) -> Result<(), ActionsValidationError> {
+    check_feature_enabled(ProtocolFeature::AccountNamespaces, current_protocol_version)?;
+    validate_namespace(&action.namespace)?;
+    if action.code.len() as u64 > limit_config.max_contract_size {
+        return Err(ActionsValidationError::ContractSizeExceeded {
+            size: action.code.len() as u64,
+            limit: limit_config.max_contract_size,
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates `DeployGlobalContractAction`. Checks that the given contract size doesn't exceed the
+/// limit of a contract deployed to an account.
+fn validate_deploy_global_contract_action(
+    limit_config: &LimitConfig,
+    action: &DeployGlobalContractAction,
+    current_protocol_version: ProtocolVersion,
+) -> Result<(), ActionsValidationError> {
+    check_feature_enabled(ProtocolFeature::GlobalContracts, current_protocol_version)?;
+    if action.code.len() as u64 > limit_config.max_contract_size {
+        return Err(ActionsValidationError::ContractSizeExceeded {
+            size: action.code.len() as u64,
+            limit: limit_config.max_contract_size,
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates `NamespacedFunctionCallAction`. Checks the namespace and the function call.
+fn validate_namespaced_function_call_action(
+    limit_config: &LimitConfig,
+    action: &NamespacedFunctionCallAction,
+    current_protocol_version: ProtocolVersion,
+) -> Result<(), ActionsValidationError> {
+    check_feature_enabled(ProtocolFeature::AccountNamespaces, current_protocol_version)?;
+    validate_namespace(&action.namespace)?;
+    validate_function_call_action(limit_config, &action.function_call)
+}
+
+fn validate_namespace(namespace: &AccountNamespace) -> Result<(), ActionsValidationError> {
+    if !namespace.is_valid() {
+        return Err(ActionsValidationError::InvalidNamespace {
+            namespace: namespace.as_str().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
 /// Validates `FunctionCallAction`. Checks that the method name length doesn't exceed the limit and
 /// the length of the arguments doesn't exceed the limit.
 fn validate_function_call_action(
@@ -503,7 +756,11 @@ fn validate_stake_action(action: &StakeAction) -> Result<(), ActionsValidationEr
 fn validate_add_key_action(
     limit_config: &LimitConfig,
     action: &AddKeyAction,
+    current_protocol_version: ProtocolVersion,
 ) -> Result<(), ActionsValidationError> {
+    if let AccessKeyPermission::LimitedFullAccess(_) = &action.access_key.permission {
+        check_feature_enabled(ProtocolFeature::LimitedFullAccessKeys, current_protocol_version)?;
+    }
     if let AccessKeyPermission::FunctionCall(fc) = &action.access_key.permission {
         // Check whether `receiver_id` is a valid account_id. Historically, we
         // allowed arbitrary strings there!
@@ -555,7 +812,6 @@ fn validate_delete_action(action: &DeleteAccountAction) -> Result<(), ActionsVal
     Ok(())
 }
 
-#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
 fn check_feature_enabled(
     feature: ProtocolFeature,
     current_protocol_version: ProtocolVersion,
@@ -584,13 +840,17 @@ mod tests {
     use std::sync::Arc;
 
     use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
-    use near_primitives::account::{AccessKey, FunctionCallPermission};
+    use near_primitives::account::{
+        AccessKey, FunctionCallPermission, LimitedFullAccessPermission,
+    };
+    use near_primitives::action::atomic_batch::{AtomicBatchReceipt, BatchedAction};
     use near_primitives::action::delegate::{DelegateAction, NonDelegateAction};
     use near_primitives::hash::{hash, CryptoHash};
     use near_primitives::receipt::ReceiptPriority;
     use near_primitives::test_utils::account_new;
     use near_primitives::transaction::{
         CreateAccountAction, DeleteAccountAction, DeleteKeyAction, StakeAction, TransferAction,
+        UseGlobalContractAction, PRIORITY_FEE_UNIT,
     };
     use near_primitives::types::{AccountId, Balance, MerkleHash, StateChangeCause};
     use near_primitives::version::PROTOCOL_VERSION;
@@ -1064,26 +1324,64 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_transaction_invalid_transaction_version() {
+    fn test_validate_transaction_priority_fee() {
         let config = RuntimeConfig::test();
         let (signer, mut state_update, gas_price) =
             setup_common(TESTING_INIT_BALANCE, 0, Some(AccessKey::full_access()));
-
-        assert_err_both_validations(
-            &config,
-            &mut state_update,
-            gas_price,
-            &SignedTransaction::from_actions_v1(
+        let protocol_version = ProtocolFeature::TransactionPriorityFee.protocol_version();
+        let transaction = |priority_fee| {
+            SignedTransaction::from_actions_v1(
                 1,
                 alice_account(),
                 bob_account(),
                 &*signer,
                 vec![Action::Transfer(TransferAction { deposit: 100 })],
                 CryptoHash::default(),
-                1,
+                priority_fee,
+            )
+        };
+
+        assert_eq!(
+            validate_transaction(&config, gas_price, &transaction(1), true, protocol_version - 1),
+            Err(InvalidTxError::InvalidTransactionVersion),
+        );
+        assert_eq!(
+            verify_and_charge_transaction(
+                &config,
+                &mut state_update,
+                gas_price,
+                &transaction(1),
+                true,
+                None,
+                protocol_version - 1,
             ),
-            InvalidTxError::InvalidTransactionVersion,
+            Err(InvalidTxError::InvalidTransactionVersion),
         );
+
+        // The priority fee is burnt on top of the gas.
+        let cost =
+            validate_transaction(&config, gas_price, &transaction(0), true, protocol_version)
+                .unwrap();
+        let cost_with_fee =
+            validate_transaction(&config, gas_price, &transaction(5), true, protocol_version)
+                .unwrap();
+        assert_eq!(cost_with_fee.gas_burnt, cost.gas_burnt);
+        assert_eq!(cost_with_fee.burnt_amount, cost.burnt_amount + 5 * PRIORITY_FEE_UNIT);
+        assert_eq!(cost_with_fee.total_cost, cost.total_cost + 5 * PRIORITY_FEE_UNIT);
+
+        let verification_result = verify_and_charge_transaction(
+            &config,
+            &mut state_update,
+            gas_price,
+            &transaction(5),
+            true,
+            None,
+            protocol_version,
+        )
+        .expect("valid transaction");
+        let account = get_account(&state_update, &alice_account()).unwrap().unwrap();
+        assert_eq!(account.amount(), TESTING_INIT_BALANCE - cost_with_fee.total_cost);
+        assert_eq!(verification_result.burnt_amount, cost_with_fee.burnt_amount);
     }
 
     #[test]
@@ -1173,6 +1471,142 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_transaction_limited_full_access() {
+        let config = RuntimeConfig::test();
+        let (signer, mut state_update, gas_price) = setup_common(
+            TESTING_INIT_BALANCE,
+            0,
+            Some(AccessKey {
+                nonce: 0,
+                permission: AccessKeyPermission::LimitedFullAccess(LimitedFullAccessPermission {
+                    allowance: NEAR_BASE,
+                    expiry_height: Some(100),
+                }),
+            }),
+        );
+        let mut verify = |nonce, receiver_id: AccountId, actions, block_height| {
+            verify_and_charge_transaction(
+                &config,
+                &mut state_update,
+                gas_price,
+                &SignedTransaction::from_actions(
+                    nonce,
+                    alice_account(),
+                    receiver_id,
+                    &*signer,
+                    actions,
+                    CryptoHash::default(),
+                    0,
+                ),
+                true,
+                Some(block_height),
+                PROTOCOL_VERSION,
+            )
+            .map(|_| {
+                get_access_key(&state_update, &alice_account(), &signer.public_key())
+                    .unwrap()
+                    .unwrap()
+            })
+        };
+        let transfer = |deposit| Action::Transfer(TransferAction { deposit });
+
+        let access_key = verify(1, bob_account(), vec![transfer(NEAR_BASE / 2)], 100).unwrap();
+        let AccessKeyPermission::LimitedFullAccess(permission) = access_key.permission else {
+            panic!("unexpected permission {:?}", access_key.permission);
+        };
+        assert!(permission.allowance < NEAR_BASE / 2);
+
+        assert!(matches!(
+            verify(2, bob_account(), vec![transfer(NEAR_BASE / 2)], 100),
+            Err(InvalidTxError::InvalidAccessKeyError(
+                InvalidAccessKeyError::NotEnoughAllowance { .. }
+            ))
+        ));
+        assert_eq!(
+            verify(
+                2,
+                alice_account(),
+                vec![Action::AddKey(Box::new(AddKeyAction {
+                    public_key: PublicKey::from_seed(KeyType::ED25519, "session"),
+                    access_key: AccessKey::full_access(),
+                }))],
+                100,
+            ),
+            Err(InvalidTxError::InvalidAccessKeyError(InvalidAccessKeyError::RequiresFullAccess))
+        );
+        assert_eq!(
+            verify(
+                2,
+                alice_account(),
+                vec![Action::FunctionCall(Box::new(FunctionCallAction {
+                    method_name: "transfer_out".to_string(),
+                    args: vec![],
+                    gas: 100,
+                    deposit: 0,
+                }))],
+                100,
+            ),
+            Err(InvalidTxError::InvalidAccessKeyError(InvalidAccessKeyError::RequiresFullAccess))
+        );
+        assert_eq!(
+            verify(2, bob_account(), vec![transfer(1)], 101),
+            Err(InvalidTxError::InvalidAccessKeyError(InvalidAccessKeyError::AccessKeyExpired {
+                account_id: alice_account(),
+                public_key: signer.public_key().into(),
+                expiry_height: 100,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_validate_transaction_atomic_batch_max_block_height() {
+        let config = RuntimeConfig::test();
+        let (signer, mut state_update, gas_price) =
+            setup_common(TESTING_INIT_BALANCE, 0, Some(AccessKey::full_access()));
+        let mut verify = |nonce, max_block_height| {
+            let receipt = AtomicBatchReceipt {
+                receiver_id: bob_account(),
+                actions: vec![BatchedAction::try_from(Action::Transfer(TransferAction {
+                    deposit: 1,
+                }))
+                .unwrap()],
+            };
+            verify_and_charge_transaction(
+                &config,
+                &mut state_update,
+                gas_price,
+                &SignedTransaction::from_actions(
+                    nonce,
+                    alice_account(),
+                    alice_account(),
+                    &*signer,
+                    vec![Action::AtomicBatch(Box::new(AtomicBatchAction {
+                        max_block_height,
+                        receipts: vec![receipt],
+                    }))],
+                    CryptoHash::default(),
+                    0,
+                ),
+                true,
+                Some(10),
+                ProtocolFeature::AtomicBatches.protocol_version(),
+            )
+            .map(|_| ())
+        };
+
+        assert_eq!(verify(1, 10 + MAX_ATOMIC_BATCH_WAIT_HEIGHT), Ok(()));
+        assert_eq!(
+            verify(2, 11 + MAX_ATOMIC_BATCH_WAIT_HEIGHT),
+            Err(InvalidTxError::ActionsValidation(
+                ActionsValidationError::AtomicBatchMaxBlockHeightTooLarge {
+                    max_block_height: 11 + MAX_ATOMIC_BATCH_WAIT_HEIGHT,
+                    limit: 10 + MAX_ATOMIC_BATCH_WAIT_HEIGHT,
+                }
+            ))
+        );
+    }
+
     /// Setup: account has 1B yoctoN and is 180 bytes. Storage requirement is 1M per byte.
     /// Test that such account can not send 950M yoctoN out as that will leave it under storage requirements.
     /// If zero balance account is enabled, however, the transaction should succeed
@@ -1868,6 +2302,29 @@ mod tests {
         .expect("valid action");
     }
 
+    #[test]
+    fn test_validate_action_add_key_limited_full_access() {
+        let protocol_version = ProtocolFeature::LimitedFullAccessKeys.protocol_version();
+        let action = Action::AddKey(Box::new(AddKeyAction {
+            public_key: PublicKey::empty(KeyType::ED25519),
+            access_key: AccessKey {
+                nonce: 0,
+                permission: AccessKeyPermission::LimitedFullAccess(LimitedFullAccessPermission {
+                    allowance: NEAR_BASE,
+                    expiry_height: None,
+                }),
+            },
+        }));
+        assert_eq!(validate_action(&test_limit_config(), &action, protocol_version), Ok(()));
+        assert_eq!(
+            validate_action(&test_limit_config(), &action, protocol_version - 1),
+            Err(ActionsValidationError::UnsupportedProtocolFeature {
+                protocol_feature: "LimitedFullAccessKeys".to_string(),
+                version: protocol_version,
+            }),
+        );
+    }
+
     #[test]
     fn test_validate_action_valid_delete_key() {
         validate_action(
@@ -1938,6 +2395,191 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_idempotency_key() {
+        let protocol_version = ProtocolFeature::IdempotencyKeys.protocol_version();
+        let key = |len| Action::IdempotencyKey(IdempotencyKeyAction { key: vec![1; len] });
+        let function_call = Action::FunctionCall(Box::new(FunctionCallAction {
+            method_name: "hello".to_string(),
+            args: vec![],
+            gas: 100,
+            deposit: 0,
+        }));
+        let validate = |actions: &[Action], protocol_version| {
+            validate_actions(&test_limit_config(), actions, protocol_version)
+        };
+
+        assert_eq!(validate(&[key(32), function_call.clone()], protocol_version), Ok(()));
+        assert_eq!(
+            validate(&[key(32), function_call.clone()], protocol_version - 1),
+            Err(ActionsValidationError::UnsupportedProtocolFeature {
+                protocol_feature: "IdempotencyKeys".to_string(),
+                version: protocol_version,
+            }),
+        );
+        assert_eq!(
+            validate(&[function_call.clone(), key(32)], protocol_version),
+            Err(ActionsValidationError::IdempotencyKeyMustBeFirst),
+        );
+        assert_eq!(
+            validate(&[key(32), key(32), function_call.clone()], protocol_version),
+            Err(ActionsValidationError::IdempotencyKeyMustBeFirst),
+        );
+        assert_eq!(
+            validate(&[key(32), Action::Transfer(TransferAction { deposit: 1 })], protocol_version),
+            Err(ActionsValidationError::IdempotencyKeyWithoutFunctionCall),
+        );
+        assert_eq!(
+            validate(&[key(65), function_call], protocol_version),
+            Err(ActionsValidationError::IdempotencyKeyLengthExceeded {
+                length: 65,
+                limit: MAX_IDEMPOTENCY_KEY_LENGTH,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_validate_atomic_batch() {
+        let protocol_version = ProtocolFeature::AtomicBatches.protocol_version();
+        let transfer = Action::Transfer(TransferAction { deposit: 1 });
+        let batch = |num_receipts, action: &Action| {
+            let receipt = AtomicBatchReceipt {
+                receiver_id: bob_account(),
+                actions: vec![BatchedAction::try_from(action.clone()).unwrap()],
+            };
+            Action::AtomicBatch(Box::new(AtomicBatchAction {
+                max_block_height: 10,
+                receipts: vec![receipt; num_receipts],
+            }))
+        };
+        let validate = |actions: &[Action], protocol_version| {
+            validate_actions(&test_limit_config(), actions, protocol_version)
+        };
+
+        assert_eq!(validate(&[batch(2, &transfer)], protocol_version), Ok(()));
+        assert_eq!(
+            validate(&[batch(2, &transfer)], protocol_version - 1),
+            Err(ActionsValidationError::UnsupportedProtocolFeature {
+                protocol_feature: "AtomicBatches".to_string(),
+                version: protocol_version,
+            }),
+        );
+        assert_eq!(
+            validate(&[batch(1, &transfer), transfer.clone()], protocol_version),
+            Err(ActionsValidationError::AtomicBatchMustBeOnlyOne),
+        );
+        assert_eq!(
+            validate(&[batch(0, &transfer)], protocol_version),
+            Err(ActionsValidationError::InvalidAtomicBatchReceiptsNumber {
+                total_number: 0,
+                limit: MAX_ATOMIC_BATCH_RECEIPTS,
+            }),
+        );
+        assert_eq!(
+            validate(&[batch(17, &transfer)], protocol_version),
+            Err(ActionsValidationError::InvalidAtomicBatchReceiptsNumber {
+                total_number: 17,
+                limit: MAX_ATOMIC_BATCH_RECEIPTS,
+            }),
+        );
+        // The actions of the receipts are validated too.
+        let zero_gas_call = Action::FunctionCall(Box::new(FunctionCallAction {
+            method_name: "hello".to_string(),
+            args: vec![],
+            gas: 0,
+            deposit: 0,
+        }));
+        assert_eq!(
+            validate(&[batch(1, &zero_gas_call)], protocol_version),
+            Err(ActionsValidationError::FunctionCallZeroAttachedGas),
+        );
+    }
+
+    #[test]
+    fn test_validate_account_namespaces() {
+        let protocol_version = ProtocolFeature::AccountNamespaces.protocol_version();
+        let deploy = |namespace: &str| {
+            Action::DeployNamespacedContract(Box::new(DeployNamespacedContractAction {
+                namespace: AccountNamespace::new(namespace),
+                code: vec![1; 5],
+            }))
+        };
+        let call = |namespace: &str| {
+            Action::NamespacedFunctionCall(Box::new(NamespacedFunctionCallAction {
+                namespace: AccountNamespace::new(namespace),
+                function_call: FunctionCallAction {
+                    method_name: "hello".to_string(),
+                    args: vec![],
+                    gas: 100,
+                    deposit: 0,
+                },
+            }))
+        };
+        let validate = |actions: &[Action], protocol_version| {
+            validate_actions(&test_limit_config(), actions, protocol_version)
+        };
+
+        assert_eq!(validate(&[deploy("token"), call("token")], protocol_version), Ok(()));
+        assert_eq!(
+            validate(&[deploy("token")], protocol_version - 1),
+            Err(ActionsValidationError::UnsupportedProtocolFeature {
+                protocol_feature: "AccountNamespaces".to_string(),
+                version: protocol_version,
+            }),
+        );
+        assert_eq!(
+            validate(&[call("Token")], protocol_version),
+            Err(ActionsValidationError::InvalidNamespace { namespace: "Token".to_string() }),
+        );
+        assert_eq!(
+            validate(&[deploy("")], protocol_version),
+            Err(ActionsValidationError::InvalidNamespace { namespace: String::new() }),
+        );
+        assert_eq!(
+            validate(
+                &[Action::IdempotencyKey(IdempotencyKeyAction { key: vec![1; 32] }), call("token")],
+                protocol_version,
+            ),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn test_validate_global_contracts() {
+        let protocol_version = ProtocolFeature::GlobalContracts.protocol_version();
+        let limit_config = test_limit_config();
+        let deploy = |code_len: u64| {
+            Action::DeployGlobalContract(DeployGlobalContractAction {
+                code: vec![1; code_len as usize],
+            })
+        };
+        let use_contract = Action::UseGlobalContract(Box::new(UseGlobalContractAction {
+            code_hash: hash(&[1; 5]),
+        }));
+        let validate = |actions: &[Action], protocol_version| {
+            validate_actions(&limit_config, actions, protocol_version)
+        };
+
+        assert_eq!(validate(&[deploy(5), use_contract.clone()], protocol_version), Ok(()));
+        for action in [deploy(5), use_contract] {
+            assert_eq!(
+                validate(&[action], protocol_version - 1),
+                Err(ActionsValidationError::UnsupportedProtocolFeature {
+                    protocol_feature: "GlobalContracts".to_string(),
+                    version: protocol_version,
+                }),
+            );
+        }
+        let max_contract_size = limit_config.max_contract_size;
+        assert_eq!(
+            validate(&[deploy(max_contract_size + 1)], protocol_version),
+            Err(ActionsValidationError::ContractSizeExceeded {
+                size: max_contract_size + 1,
+                limit: max_contract_size,
+            }),
+        );
+    }
+
     #[test]
     fn test_truncate_string() {
         fn check(input: &str, limit: usize, want: &str) {
@@ -78,7 +78,7 @@ impl StandaloneRuntime {
 
         let mut account_ids: HashSet<AccountId> = HashSet::new();
         genesis.for_each_record(|record: &StateRecord| {
-            account_ids.insert(state_record_to_account_id(record).clone());
+            account_ids.extend(state_record_to_account_id(record).cloned());
         });
         let writers = std::sync::atomic::AtomicUsize::new(0);
         let shard_uid = ShardUId::from_shard_id_and_layout(0, &genesis.config.shard_layout);
@@ -122,6 +122,7 @@ impl StandaloneRuntime {
             migration_data: Arc::new(MigrationData::default()),
             migration_flags: MigrationFlags::default(),
             congestion_info,
+            host_call_tracer: None,
         };
 
         Self {
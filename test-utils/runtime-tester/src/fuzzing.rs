@@ -661,6 +661,11 @@ impl Account {
         for (_, key) in &self.keys {
             match &key.access_key.permission {
                 AccessKeyPermission::FullAccess => function_call_keys.push(key.signer.clone()),
+                AccessKeyPermission::LimitedFullAccess(_) => {
+                    if self.id.as_str() != receiver_id {
+                        function_call_keys.push(key.signer.clone())
+                    }
+                }
                 AccessKeyPermission::FunctionCall(function_call_permission) => {
                     if function_call_permission.receiver_id == receiver_id {
                         function_call_keys.push(key.signer.clone())
@@ -8,14 +8,19 @@ use near_epoch_manager::{EpochManager, EpochManagerAdapter, EpochManagerHandle};
 use near_primitives::block::Tip;
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::hash::CryptoHash;
-use near_store::cold_storage::{copy_all_data_to_cold, update_cold_db, update_cold_head};
+use near_store::cold_storage::{
+    copy_all_data_to_cold, copy_all_data_to_cold_throttled, update_cold_db, update_cold_head,
+    CopyAllDataToColdStatus,
+};
 use near_store::metadata::DbKind;
 use near_store::{DBCol, NodeStorage, Store, StoreOpener};
 use near_store::{COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY, TAIL_KEY};
 use nearcore::NearConfig;
 use rand::seq::SliceRandom;
 use std::io::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use strum::IntoEnumIterator;
 
 #[derive(clap::Parser)]
@@ -57,6 +62,15 @@ enum SubCommand {
     /// Modifies cold db from config to be considered not initialised.
     /// Doesn't actually delete any data, except for HEAD and COLD_HEAD in BlockMisc.
     ResetCold(ResetColdCmd),
+    /// Converts a legacy archival node to split storage in place, with the
+    /// node stopped.  Creates the cold db, copies the cold columns to it,
+    /// verifies the copy, turns the archival db into the hot db and adds the
+    /// cold store to config.json.  Can be run again if interrupted.
+    ///
+    /// The hot db keeps the history copied to cold until garbage collection
+    /// gets through it, which takes long for big dbs.  To start with a small
+    /// hot db, use `prepare-hot` with an RPC db instead.
+    Migrate(MigrateCmd),
 }
 
 impl ColdStoreCommand {
@@ -69,6 +83,10 @@ impl ColdStoreCommand {
             if self.readwrite { near_store::Mode::ReadWrite } else { near_store::Mode::ReadOnly };
         let mut near_config = nearcore::config::load_config(&home_dir, genesis_validation)
             .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+        // The cold store may not be configured yet.
+        if let SubCommand::Migrate(cmd) = &self.subcmd {
+            return cmd.run(home_dir, near_config);
+        }
 
         let opener = self.get_opener(home_dir, &mut near_config);
 
@@ -93,6 +111,7 @@ impl ColdStoreCommand {
             SubCommand::PrepareHot(cmd) => cmd.run(&storage, &home_dir, &near_config),
             SubCommand::CheckStateRoot(cmd) => cmd.run(&storage),
             SubCommand::ResetCold(cmd) => cmd.run(&storage),
+            SubCommand::Migrate(_) => unreachable!(),
         }
     }
 
@@ -268,11 +287,10 @@ fn copy_all_blocks(storage: &NodeStorage, batch_size: usize, check: bool) {
     if check {
         for col in DBCol::iter() {
             if col.is_cold() {
-                println!(
-                    "Performed {} {:?} checks",
-                    check_iter(&storage.get_hot_store(), &storage.get_cold_store().unwrap(), col),
-                    col
-                );
+                let num_checks =
+                    check_iter(&storage.get_hot_store(), &storage.get_cold_store().unwrap(), col)
+                        .unwrap_or_else(|e| panic!("{:#}", e));
+                println!("Performed {} {:?} checks", num_checks, col);
             }
         }
     }
@@ -283,11 +301,11 @@ fn check_key(
     second_store: &near_store::Store,
     col: DBCol,
     key: &[u8],
-) {
-    let first_res = first_store.get(col, key);
-    let second_res = second_store.get(col, key);
-
-    assert_eq!(first_res.unwrap(), second_res.unwrap());
+) -> anyhow::Result<()> {
+    let first_res = first_store.get(col, key)?;
+    let second_res = second_store.get(col, key)?;
+    anyhow::ensure!(first_res == second_res, "Values of {:?} key {:?} differ", col, key);
+    Ok(())
 }
 
 /// Checks that `first_store`'s column `col` is fully included in `second_store`
@@ -297,13 +315,14 @@ fn check_iter(
     first_store: &near_store::Store,
     second_store: &near_store::Store,
     col: DBCol,
-) -> u64 {
+) -> anyhow::Result<u64> {
     let mut num_checks = 0;
-    for (key, _value) in first_store.iter(col).map(Result::unwrap) {
-        check_key(first_store, second_store, col, &key);
+    for item in first_store.iter(col) {
+        let (key, _value) = item?;
+        check_key(first_store, second_store, col, &key)?;
         num_checks += 1;
     }
-    num_checks
+    Ok(num_checks)
 }
 
 /// Calls get_ser on Store with provided temperature from provided NodeStorage.
@@ -656,6 +675,107 @@ impl CheckStateRootCmd {
     }
 }
 
+#[derive(clap::Args)]
+struct MigrateCmd {
+    /// Path of the cold db, relative to the home dir.  Only used if the cold
+    /// store isn't configured in config.json yet.
+    #[clap(long, default_value = "cold-data")]
+    cold_store_path: PathBuf,
+    /// Threshold size of the write transaction.
+    #[clap(short = 'b', long, default_value_t = 500_000_000)]
+    batch_size: usize,
+    /// Maximum number of megabytes copied per second, unlimited by default.
+    #[clap(long)]
+    max_copy_rate_mb: Option<u64>,
+    /// Don't check that the cold columns of both dbs are equal after copying.
+    #[clap(long)]
+    skip_verification: bool,
+}
+
+impl MigrateCmd {
+    pub fn run(&self, home_dir: &Path, mut near_config: NearConfig) -> anyhow::Result<()> {
+        anyhow::ensure!(near_config.config.archive, "Only archival nodes can use split storage");
+        let cold_store_configured = near_config.config.cold_store.is_some();
+        near_config.config.cold_store.get_or_insert_with(|| near_store::StoreConfig {
+            path: Some(self.cold_store_path.clone()),
+            ..near_store::StoreConfig::default()
+        });
+        let opener = NodeStorage::opener(
+            home_dir,
+            true,
+            &near_config.config.store,
+            near_config.config.cold_store.as_ref(),
+        );
+        let storage = opener.open_in_mode(near_store::Mode::ReadWrite)?;
+        let hot_store = storage.get_hot_store();
+
+        match hot_store.get_db_kind()? {
+            Some(DbKind::Archive) => {
+                self.copy_to_cold(&storage)?;
+                tracing::info!(target: "cold-store-migrate", "Changing the DbKind of the archival db to Hot");
+                hot_store.set_db_kind(DbKind::Hot)?;
+            }
+            Some(DbKind::Hot) => {
+                tracing::info!(target: "cold-store-migrate", "The db is already a hot db");
+            }
+            kind => anyhow::bail!("Unexpected hot db kind {:?}, expected Archive", kind),
+        }
+
+        if !cold_store_configured {
+            let config_path = home_dir.join(nearcore::config::CONFIG_FILENAME);
+            nearcore::cold_storage::enable_split_storage_in_config_file(
+                &config_path,
+                &self.cold_store_path,
+            )?;
+            tracing::info!(target: "cold-store-migrate", ?config_path, "Added the cold store to the config");
+        }
+        tracing::info!(target: "cold-store-migrate", "Migrated to split storage, the node can be started");
+        Ok(())
+    }
+
+    /// Copies the cold columns and sets the cold head, unless a previous run
+    /// already did.  The cold head is only set once the copy is verified.
+    fn copy_to_cold(&self, storage: &NodeStorage) -> anyhow::Result<()> {
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().context("Cold storage is not configured")?;
+        let cold_db = storage.cold_db().context("Cold storage is not configured")?;
+        if let Some(cold_head) = cold_store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)? {
+            tracing::info!(target: "cold-store-migrate", cold_head_height = cold_head.height, "The cold db is already populated");
+            return Ok(());
+        }
+
+        // The node is stopped, so the final head doesn't move during the copy.
+        let final_head = hot_store
+            .get_ser::<Tip>(DBCol::BlockMisc, FINAL_HEAD_KEY)?
+            .context("FINAL_HEAD not found in the archival db")?;
+        tracing::info!(target: "cold-store-migrate", final_head_height = final_head.height, "Copying the cold columns");
+        let keep_going = Arc::new(AtomicBool::new(true));
+        let max_bytes_per_second = self.max_copy_rate_mb.map(|rate| rate * 1_000_000);
+        match copy_all_data_to_cold_throttled(
+            cold_db.clone(),
+            &hot_store,
+            self.batch_size,
+            max_bytes_per_second,
+            &keep_going,
+        )? {
+            CopyAllDataToColdStatus::EverythingCopied => {}
+            CopyAllDataToColdStatus::Interrupted => anyhow::bail!("Copying was interrupted"),
+        }
+
+        if !self.skip_verification {
+            for col in DBCol::iter().filter(|col| col.is_cold()) {
+                tracing::info!(target: "cold-store-migrate", ?col, "Verifying column");
+                let num_checks = check_iter(&hot_store, &cold_store, col)
+                    .context("Cold db doesn't match the archival db")?;
+                tracing::info!(target: "cold-store-migrate", ?col, num_checks, "Verified column");
+            }
+        }
+
+        update_cold_head(cold_db, &hot_store, &final_head.height)?;
+        Ok(())
+    }
+}
+
 #[derive(clap::Args)]
 struct ResetColdCmd {}
 
@@ -0,0 +1,424 @@
+//! Incremental backups of the hot database.
+//!
+//! A backup starts with a RocksDB checkpoint, which is a consistent view of
+//! the database.  The node commits the data of a block together with the head
+//! pointing at it, so the checkpoint ends at a block boundary and the backup
+//! is named after the height of its head.  The files of the checkpoint are
+//! then copied to the backup storage, either a directory or a bucket of an
+//! object storage.
+//!
+//! SST and blob files are never modified once written.  They are stored once
+//! under `sst/` and shared by all the backups which include them, so only the
+//! files written since the previous backup are copied.  The other files of
+//! the checkpoint, e.g. the MANIFEST, are small and copied to
+//! `backups/<height>/files/` every time.  `backups/<height>/manifest.json`
+//! lists the files of the backup and is written last, so that a backup
+//! interrupted half way is never restored.
+
+use anyhow::Context;
+use near_chain_configs::GenesisValidationMode;
+use near_primitives::block::Tip;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::types::BlockHeight;
+use near_store::db::{open_bucket, HEAD_KEY};
+use near_store::{checkpoint_hot_storage_and_cleanup_columns, DBCol, NodeStorage, Store};
+use nearcore::{load_config, open_storage};
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Number of bytes at the end of an SST or blob file identifying it.
+const FINGERPRINT_TAIL_SIZE: u64 = 64 * 1024;
+
+/// Where the backups are stored.
+#[derive(clap::Args)]
+pub(crate) struct BackupStorageArgs {
+    /// Directory holding the backups.
+    #[arg(long, required_unless_present = "object_storage_config")]
+    directory: Option<PathBuf>,
+
+    /// JSON file with the location of the bucket holding the backups, in the
+    /// format of `object_storage` in the store config.
+    #[arg(long, conflicts_with = "directory")]
+    object_storage_config: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub(crate) struct BackupDatabaseCommand {
+    #[clap(flatten)]
+    storage: BackupStorageArgs,
+}
+
+#[derive(clap::Args)]
+pub(crate) struct RestoreDatabaseCommand {
+    #[clap(flatten)]
+    storage: BackupStorageArgs,
+
+    /// Height of the backup to restore.  By default, the latest backup is
+    /// restored.
+    #[arg(long)]
+    height: Option<BlockHeight>,
+
+    /// Print the heights of the available backups instead of restoring one.
+    #[arg(long)]
+    list: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+struct BackupManifest {
+    height: BlockHeight,
+    block_hash: CryptoHash,
+    files: Vec<BackupFile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+struct BackupFile {
+    /// Path of the file relative to the checkpoint, e.g. `data/000042.sst`.
+    path: String,
+    /// Name of the object holding the file in the backup storage.
+    object: String,
+    size: u64,
+}
+
+impl BackupDatabaseCommand {
+    pub(crate) fn run(
+        &self,
+        home: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let storage = BackupStorage::open(&self.storage)?;
+        let checkpoint_base = home.join("backup-checkpoint");
+        let head = {
+            let mut near_config = load_config(home, genesis_validation)?;
+            let node_storage = open_storage(home, &mut near_config)?;
+            create_checkpoint(&node_storage.get_hot_store(), &checkpoint_base)?
+        };
+        println!(
+            "Created a checkpoint at height {}, the node can be started again while it is copied",
+            head.height
+        );
+        let result = upload_checkpoint(&checkpoint_base, &head, &storage);
+        std::fs::remove_dir_all(&checkpoint_base)?;
+        let (manifest, copied) = result?;
+        let total: u64 = manifest.files.iter().map(|file| file.size).sum();
+        println!(
+            "Backed up the database at height {}, copied {} of {}",
+            manifest.height,
+            bytesize::ByteSize(copied),
+            bytesize::ByteSize(total)
+        );
+        Ok(())
+    }
+}
+
+impl RestoreDatabaseCommand {
+    pub(crate) fn run(
+        &self,
+        home: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let storage = BackupStorage::open(&self.storage)?;
+        if self.list {
+            for height in list_backups(&storage)? {
+                println!("{height}");
+            }
+            return Ok(());
+        }
+        let near_config = load_config(home, genesis_validation)?;
+        let opener = NodeStorage::opener(
+            home,
+            near_config.client_config.archive,
+            &near_config.config.store,
+            None,
+        );
+        let manifest = restore(&storage, self.height, opener.path())?;
+        println!("Restored the database at height {} {}", manifest.height, manifest.block_hash);
+        Ok(())
+    }
+}
+
+/// Storage of the backups.  Objects are named by paths with `/` separators.
+enum BackupStorage {
+    Directory(PathBuf),
+    Bucket { bucket: s3::Bucket, prefix: String },
+}
+
+impl BackupStorage {
+    fn open(args: &BackupStorageArgs) -> anyhow::Result<Self> {
+        if let Some(path) = &args.object_storage_config {
+            let config: near_store::ObjectStorageConfig =
+                serde_json::from_slice(&std::fs::read(path)?)
+                    .with_context(|| format!("failed to parse {}", path.display()))?;
+            return Ok(Self::Bucket { bucket: open_bucket(&config)?, prefix: config.prefix });
+        }
+        Ok(Self::Directory(args.directory.clone().unwrap()))
+    }
+
+    /// Returns the names of all the objects starting with the prefix.
+    fn list(&self, prefix: &str) -> anyhow::Result<Vec<String>> {
+        match self {
+            Self::Directory(root) => {
+                let dir = root.join(prefix);
+                if !dir.exists() {
+                    return Ok(vec![]);
+                }
+                list_files(&dir)?.into_iter().map(|path| relative_name(root, &path)).collect()
+            }
+            Self::Bucket { bucket, prefix: root } => {
+                let results = bucket.list_blocking(format!("{root}{prefix}"), None)?;
+                Ok(results
+                    .into_iter()
+                    .flat_map(|result| result.contents)
+                    .filter_map(|object| object.key.strip_prefix(root.as_str()).map(String::from))
+                    .collect())
+            }
+        }
+    }
+
+    fn put(&self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        match self {
+            Self::Directory(root) => {
+                let path = root.join(name);
+                std::fs::create_dir_all(path.parent().unwrap())?;
+                // Written under a temporary name first, so that an interrupted
+                // copy doesn't leave behind a truncated object.
+                let tmp_path = path.with_extension("tmp");
+                std::fs::write(&tmp_path, data)?;
+                std::fs::rename(&tmp_path, &path)?;
+            }
+            Self::Bucket { bucket, prefix } => {
+                bucket.put_object_blocking(format!("{prefix}{name}"), data)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn put_file(&self, name: &str, path: &Path) -> anyhow::Result<()> {
+        match self {
+            Self::Directory(root) => {
+                let target = root.join(name);
+                std::fs::create_dir_all(target.parent().unwrap())?;
+                let tmp_target = target.with_extension("tmp");
+                std::fs::copy(path, &tmp_target)?;
+                std::fs::rename(&tmp_target, &target)?;
+                Ok(())
+            }
+            Self::Bucket { .. } => self.put(name, &std::fs::read(path)?),
+        }
+    }
+
+    fn get(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Directory(root) => Ok(std::fs::read(root.join(name))?),
+            Self::Bucket { bucket, prefix } => {
+                let response = bucket.get_object_blocking(format!("{prefix}{name}"))?;
+                if response.status_code() != 200 {
+                    anyhow::bail!("failed to get {name}: status {}", response.status_code());
+                }
+                Ok(response.bytes().to_vec())
+            }
+        }
+    }
+}
+
+fn backup_dir(height: BlockHeight) -> String {
+    // Zero padded, so that the backups are listed in the order of heights.
+    format!("backups/{height:020}")
+}
+
+/// Checkpoints the hot database into `checkpoint_base` and returns the head
+/// of the checkpoint.
+fn create_checkpoint(hot_store: &Store, checkpoint_base: &Path) -> anyhow::Result<Tip> {
+    if checkpoint_base.exists() {
+        // Left behind by an interrupted backup.
+        std::fs::remove_dir_all(checkpoint_base)?;
+    }
+    let checkpoint = checkpoint_hot_storage_and_cleanup_columns(hot_store, checkpoint_base, None)?;
+    let head: Option<Tip> = checkpoint.get_hot_store().get_ser(DBCol::BlockMisc, HEAD_KEY)?;
+    head.context("the database has no head")
+}
+
+/// Copies the files of the checkpoint which aren't in the storage yet.
+/// Returns the manifest of the backup and the number of bytes copied.
+fn upload_checkpoint(
+    checkpoint_base: &Path,
+    head: &Tip,
+    storage: &BackupStorage,
+) -> anyhow::Result<(BackupManifest, u64)> {
+    let shared: HashSet<String> = storage.list("sst/")?.into_iter().collect();
+    let backup_dir = backup_dir(head.height);
+    let mut files = vec![];
+    let mut copied = 0;
+    for path in list_files(checkpoint_base)? {
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if file_name == "LOCK" || file_name.starts_with("LOG") {
+            // Left by opening the checkpoint, not needed to restore it.
+            continue;
+        }
+        let relative = relative_name(checkpoint_base, &path)?;
+        let size = std::fs::metadata(&path)?.len();
+        let object = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext @ ("sst" | "blob")) => {
+                let object = format!("sst/{}.{ext}", fingerprint(&path, size)?);
+                if !shared.contains(&object) {
+                    storage.put_file(&object, &path)?;
+                    copied += size;
+                }
+                object
+            }
+            _ => {
+                let object = format!("{backup_dir}/files/{relative}");
+                storage.put_file(&object, &path)?;
+                copied += size;
+                object
+            }
+        };
+        files.push(BackupFile { path: relative, object, size });
+    }
+    let manifest = BackupManifest { height: head.height, block_hash: head.last_block_hash, files };
+    storage.put(&format!("{backup_dir}/manifest.json"), &serde_json::to_vec_pretty(&manifest)?)?;
+    Ok((manifest, copied))
+}
+
+/// Identifies an SST or blob file by its size and its tail, which holds the
+/// index and the checksums of the file.  File numbers alone aren't enough,
+/// they start over when the database is created again.
+fn fingerprint(path: &Path, size: u64) -> anyhow::Result<CryptoHash> {
+    let mut file = std::fs::File::open(path)?;
+    let tail_size = size.min(FINGERPRINT_TAIL_SIZE);
+    file.seek(SeekFrom::Start(size - tail_size))?;
+    let mut data = size.to_le_bytes().to_vec();
+    file.take(tail_size).read_to_end(&mut data)?;
+    Ok(hash(&data))
+}
+
+/// Returns the heights of the complete backups, in increasing order.
+fn list_backups(storage: &BackupStorage) -> anyhow::Result<Vec<BlockHeight>> {
+    let mut heights: Vec<BlockHeight> = storage
+        .list("backups/")?
+        .iter()
+        .filter_map(|name| name.strip_prefix("backups/")?.strip_suffix("/manifest.json"))
+        .filter_map(|height| height.parse().ok())
+        .collect();
+    heights.sort();
+    Ok(heights)
+}
+
+/// Restores the backup at the height, or the latest one, into a new database
+/// at `db_path`.
+fn restore(
+    storage: &BackupStorage,
+    height: Option<BlockHeight>,
+    db_path: &Path,
+) -> anyhow::Result<BackupManifest> {
+    let height = match height {
+        Some(height) => height,
+        None => *list_backups(storage)?.last().context("there are no backups")?,
+    };
+    let manifest: BackupManifest =
+        serde_json::from_slice(&storage.get(&format!("{}/manifest.json", backup_dir(height)))?)
+            .with_context(|| format!("failed to read the manifest of the backup at {height}"))?;
+
+    let mut targets = vec![];
+    for file in &manifest.files {
+        // The checkpoint holds the main database in `data` and the databases
+        // of the shards next to it in `data-shards`.
+        let suffix = file.path.strip_prefix("data").context("unexpected path in backup")?;
+        let mut target = db_path.as_os_str().to_owned();
+        target.push(suffix);
+        targets.push(PathBuf::from(target));
+    }
+    for dir in targets.iter().filter_map(|target| target.parent()) {
+        if dir.exists() && std::fs::read_dir(dir)?.next().is_some() {
+            anyhow::bail!("{} isn't empty, move the database away first", dir.display());
+        }
+    }
+    for (file, target) in manifest.files.iter().zip(&targets) {
+        std::fs::create_dir_all(target.parent().unwrap())?;
+        let data = storage.get(&file.object)?;
+        if data.len() as u64 != file.size {
+            anyhow::bail!("{} has {} bytes instead of {}", file.object, data.len(), file.size);
+        }
+        std::fs::write(target, data)?;
+    }
+    Ok(manifest)
+}
+
+/// Returns the paths of the files in the directory and its subdirectories,
+/// sorted.
+fn list_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            files.extend(list_files(&entry.path())?);
+        } else {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn relative_name(root: &Path, path: &Path) -> anyhow::Result<String> {
+    let relative = path.strip_prefix(root)?;
+    let components: Option<Vec<&str>> =
+        relative.components().map(|component| component.as_os_str().to_str()).collect();
+    Ok(components.context("non UTF-8 path")?.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_checkpoint, list_backups, restore, upload_checkpoint, BackupStorage};
+    use near_primitives::block::Tip;
+    use near_primitives::hash::hash;
+    use near_store::db::HEAD_KEY;
+    use near_store::{DBCol, Mode, NodeStorage, StoreConfig};
+
+    /// Backs up the database twice and restores the first backup.  The
+    /// second backup copies only the files written in between.
+    #[test]
+    fn test_backup_and_restore() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let backup_dir = tempfile::tempdir().unwrap();
+        let storage = BackupStorage::Directory(backup_dir.path().to_path_buf());
+        let store_config = StoreConfig::test_config();
+        let opener = NodeStorage::opener(home_dir.path(), false, &store_config, None);
+        let checkpoint_base = home_dir.path().join("backup-checkpoint");
+
+        let backup = |height: u64| {
+            let node_storage = opener.open().unwrap();
+            let store = node_storage.get_hot_store();
+            let mut store_update = store.store_update();
+            store_update.insert(DBCol::Block, height.to_le_bytes().to_vec(), vec![42]);
+            let tip = Tip {
+                height,
+                last_block_hash: hash(&height.to_le_bytes()),
+                prev_block_hash: Default::default(),
+                epoch_id: Default::default(),
+                next_epoch_id: Default::default(),
+            };
+            store_update.set_ser(DBCol::BlockMisc, HEAD_KEY, &tip).unwrap();
+            store_update.commit().unwrap();
+            let head = create_checkpoint(&store, &checkpoint_base).unwrap();
+            assert_eq!(head, tip);
+            upload_checkpoint(&checkpoint_base, &head, &storage).unwrap()
+        };
+        let (first, first_copied) = backup(1);
+        let (second, second_copied) = backup(2);
+        assert_eq!(first_copied, first.files.iter().map(|file| file.size).sum::<u64>());
+        assert!(second_copied < second.files.iter().map(|file| file.size).sum::<u64>());
+        assert_eq!(list_backups(&storage).unwrap(), vec![1, 2]);
+
+        let restore_dir = tempfile::tempdir().unwrap();
+        let restore_opener = NodeStorage::opener(restore_dir.path(), false, &store_config, None);
+        let manifest = restore(&storage, Some(1), restore_opener.path()).unwrap();
+        assert_eq!(manifest, first);
+        let restored =
+            restore_opener.open_in_mode(Mode::ReadWriteExisting).unwrap().get_hot_store();
+        assert!(restored.exists(DBCol::Block, &1u64.to_le_bytes()).unwrap());
+        assert!(!restored.exists(DBCol::Block, &2u64.to_le_bytes()).unwrap());
+        // Restoring over an existing database is refused.
+        assert!(restore(&storage, None, restore_opener.path()).is_err());
+    }
+}
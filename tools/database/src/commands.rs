@@ -4,13 +4,18 @@ use crate::analyse_gas_usage::AnalyseGasUsageCommand;
 use crate::analyse_high_load::HighLoadStatsCommand;
 use crate::analyze_contract_sizes::AnalyzeContractSizesCommand;
 use crate::analyze_delayed_receipt::AnalyzeDelayedReceiptCommand;
+use crate::backup::{BackupDatabaseCommand, RestoreDatabaseCommand};
 use crate::compact::RunCompactionCommand;
 use crate::corrupt::CorruptStateSnapshotCommand;
+use crate::export_state::ExportStateCommand;
 use crate::make_snapshot::MakeSnapshotCommand;
 use crate::memtrie::LoadMemTrieCommand;
+use crate::portable_snapshot::{ExportSnapshotCommand, ImportSnapshotCommand};
 use crate::resharding_v2::ReshardingV2Command;
-use crate::run_migrations::RunMigrationsCommand;
+use crate::run_migrations::{RollbackMigrationCommand, RunMigrationsCommand};
+use crate::state_checkpoints::StateCheckpointsCommand;
 use crate::state_perf::StatePerfCommand;
+use crate::verify::VerifyDatabaseCommand;
 use crate::write_to_db::WriteCryptoHashCommand;
 use clap::Parser;
 use near_chain_configs::GenesisValidationMode;
@@ -31,6 +36,12 @@ enum SubCommand {
     /// Analyse gas usage in a chosen sequnce of blocks
     AnalyseGasUsage(AnalyseGasUsageCommand),
 
+    /// Back up the hot database incrementally into a directory or a bucket
+    Backup(BackupDatabaseCommand),
+
+    /// Restore the hot database from a backup made by `backup`
+    Restore(RestoreDatabaseCommand),
+
     /// Change DbKind of hot or cold db.
     ChangeDbKind(ChangeDbKindCommand),
 
@@ -43,9 +54,27 @@ enum SubCommand {
     /// Make snapshot of the database
     MakeSnapshot(MakeSnapshotCommand),
 
+    /// Export the state at the beginning of an epoch into a portable snapshot
+    ExportSnapshot(ExportSnapshotCommand),
+
+    /// Import a portable snapshot made by `export-snapshot`
+    ImportSnapshot(ImportSnapshotCommand),
+
+    /// Export accounts, access keys and contract data from flat storage into
+    /// Parquet or CSV files for analytics
+    Export(ExportStateCommand),
+
     /// Run migrations
     RunMigrations(RunMigrationsCommand),
 
+    /// Restore the database from the snapshot taken before an interrupted
+    /// or failed migration
+    RollbackMigration(RollbackMigrationCommand),
+
+    /// List the state checkpoints kept at the epoch boundaries, or clone one
+    /// into a new database
+    StateCheckpoints(StateCheckpointsCommand),
+
     /// Run performance test for State column reads.
     /// Uses RocksDB data specified via --home argument.
     StatePerf(StatePerfCommand),
@@ -64,6 +93,10 @@ enum SubCommand {
 
     /// Perform on demand resharding V2
     Resharding(ReshardingV2Command),
+
+    /// Check the database for corrupt or missing rows and optionally repair
+    /// them by refetching blocks from peers
+    Verify(VerifyDatabaseCommand),
 }
 
 impl DatabaseCommand {
@@ -75,6 +108,8 @@ impl DatabaseCommand {
         match &self.subcmd {
             SubCommand::AnalyseDataSizeDistribution(cmd) => cmd.run(home),
             SubCommand::AnalyseGasUsage(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::Backup(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::Restore(cmd) => cmd.run(home, genesis_validation),
             SubCommand::ChangeDbKind(cmd) => cmd.run(home, genesis_validation),
             SubCommand::CompactDatabase(cmd) => cmd.run(home),
             SubCommand::CorruptStateSnapshot(cmd) => cmd.run(home),
@@ -82,7 +117,12 @@ impl DatabaseCommand {
                 let near_config = load_config(home, genesis_validation);
                 cmd.run(home, near_config.config.archive, &near_config.config.store)
             }
+            SubCommand::ExportSnapshot(cmd) => cmd.run(home, load_config(home, genesis_validation)),
+            SubCommand::ImportSnapshot(cmd) => cmd.run(home, load_config(home, genesis_validation)),
+            SubCommand::Export(cmd) => cmd.run(home, genesis_validation),
             SubCommand::RunMigrations(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::RollbackMigration(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::StateCheckpoints(cmd) => cmd.run(home),
             SubCommand::StatePerf(cmd) => cmd.run(home),
             SubCommand::LoadMemTrie(cmd) => cmd.run(home, genesis_validation),
             SubCommand::WriteCryptoHash(cmd) => cmd.run(home, genesis_validation),
@@ -93,6 +133,7 @@ impl DatabaseCommand {
                 let near_config = load_config(home, genesis_validation);
                 cmd.run(near_config, home)
             }
+            SubCommand::Verify(cmd) => cmd.run(home, genesis_validation),
         }
     }
 }
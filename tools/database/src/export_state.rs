@@ -0,0 +1,446 @@
+//! Exports accounts, access keys and contract data from flat storage into
+//! columnar files, so that the state can be analysed without walking the
+//! trie.
+//!
+//! Every shard is exported at its flat head, into one file per table and
+//! shard.  The heads are listed in `export.json` next to the files, the
+//! shards may be a few blocks apart if the node was running.  The state of
+//! a past epoch can be exported from a state checkpoint, which doesn't need
+//! the node to be stopped.
+
+use crate::utils::state_checkpoints_dir;
+use anyhow::Context;
+use arrow_array::builder::{BinaryBuilder, StringBuilder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use borsh::BorshDeserialize;
+use clap::Parser;
+use near_chain_configs::GenesisValidationMode;
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_primitives::account::{AccessKey, Account};
+use near_primitives::block::Tip;
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::to_base64;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::state::FlatStateValue;
+use near_primitives::trie_key::col;
+use near_primitives::trie_key::trie_key_parsers::{
+    parse_account_id_from_access_key_key, parse_account_id_from_account_key,
+    parse_account_id_from_contract_data_key, parse_data_key_from_contract_data_key,
+    parse_public_key_from_access_key_key,
+};
+use near_primitives::types::{BlockHeight, ShardId};
+use near_store::db::HEAD_KEY;
+use near_store::flat::store_helper;
+use near_store::flat::FlatStorageStatus;
+use near_store::{open_state_checkpoint, DBCol, Mode, Store, TrieDBStorage, TrieStorage};
+use nearcore::{load_config, open_storage};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Number of rows written at once.  Values stored in the State column are
+/// also looked up in batches of this size.
+const BATCH_SIZE: usize = 10_000;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Parquet,
+    /// Binary keys and values are encoded with base64.
+    Csv,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Parser)]
+pub(crate) struct ExportStateCommand {
+    /// Format of the exported files.
+    #[arg(long, value_enum, default_value_t = ExportFormat::Parquet)]
+    format: ExportFormat,
+
+    /// Directory to write the files into.
+    #[arg(long)]
+    output_dir: PathBuf,
+
+    /// Shards to export, all of the current shard layout by default.
+    #[arg(long)]
+    shard_id: Vec<ShardId>,
+
+    /// Export from the state checkpoint made at the end of the epoch whose
+    /// last block is this, see `neard database state-checkpoints list`.
+    #[arg(long)]
+    checkpoint: Option<CryptoHash>,
+}
+
+#[derive(Clone, Copy)]
+enum CellType {
+    String,
+    U64,
+    Bytes,
+}
+
+enum Cell {
+    String(String),
+    U64(u64),
+    Bytes(Vec<u8>),
+}
+
+struct Table {
+    name: &'static str,
+    /// Trie column the rows come from.
+    col: u8,
+    columns: &'static [(&'static str, CellType)],
+}
+
+// Balances don't fit into 64 bits, they are exported as decimal strings.
+const TABLES: [Table; 3] = [
+    Table {
+        name: "accounts",
+        col: col::ACCOUNT,
+        columns: &[
+            ("account_id", CellType::String),
+            ("amount", CellType::String),
+            ("locked", CellType::String),
+            ("code_hash", CellType::String),
+            ("storage_usage", CellType::U64),
+        ],
+    },
+    Table {
+        name: "access_keys",
+        col: col::ACCESS_KEY,
+        columns: &[
+            ("account_id", CellType::String),
+            ("public_key", CellType::String),
+            ("nonce", CellType::U64),
+            ("permission", CellType::String),
+        ],
+    },
+    Table {
+        name: "contract_data",
+        col: col::CONTRACT_DATA,
+        columns: &[
+            ("account_id", CellType::String),
+            ("key", CellType::Bytes),
+            ("value", CellType::Bytes),
+        ],
+    },
+];
+
+impl Table {
+    fn to_row(&self, key: &[u8], value: &[u8]) -> anyhow::Result<Vec<Cell>> {
+        Ok(match self.col {
+            col::ACCOUNT => {
+                let account_id = parse_account_id_from_account_key(key)?;
+                let account = Account::try_from_slice(value)?;
+                vec![
+                    Cell::String(account_id.to_string()),
+                    Cell::String(account.amount().to_string()),
+                    Cell::String(account.locked().to_string()),
+                    Cell::String(account.code_hash().to_string()),
+                    Cell::U64(account.storage_usage()),
+                ]
+            }
+            col::ACCESS_KEY => {
+                let account_id = parse_account_id_from_access_key_key(key)?;
+                let public_key = parse_public_key_from_access_key_key(key, &account_id)?;
+                let access_key = AccessKey::try_from_slice(value)?;
+                vec![
+                    Cell::String(account_id.to_string()),
+                    Cell::String(public_key.to_string()),
+                    Cell::U64(access_key.nonce),
+                    Cell::String(serde_json::to_string(&access_key.permission)?),
+                ]
+            }
+            col::CONTRACT_DATA => {
+                let account_id = parse_account_id_from_contract_data_key(key)?;
+                let data_key = parse_data_key_from_contract_data_key(key, &account_id)?;
+                vec![
+                    Cell::String(account_id.to_string()),
+                    Cell::Bytes(data_key.to_vec()),
+                    Cell::Bytes(value.to_vec()),
+                ]
+            }
+            column => unreachable!("no table for trie column {column}"),
+        })
+    }
+}
+
+trait TableWriter {
+    fn write_rows(&mut self, rows: Vec<Vec<Cell>>) -> anyhow::Result<()>;
+    fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+struct ParquetTableWriter {
+    schema: Arc<Schema>,
+    writer: ArrowWriter<File>,
+}
+
+impl ParquetTableWriter {
+    fn new(table: &Table, file: File) -> anyhow::Result<Self> {
+        let fields: Vec<Field> = table
+            .columns
+            .iter()
+            .map(|(name, cell_type)| {
+                let data_type = match cell_type {
+                    CellType::String => DataType::Utf8,
+                    CellType::U64 => DataType::UInt64,
+                    CellType::Bytes => DataType::Binary,
+                };
+                Field::new(*name, data_type, false)
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+        Ok(Self { schema, writer })
+    }
+}
+
+impl TableWriter for ParquetTableWriter {
+    fn write_rows(&mut self, rows: Vec<Vec<Cell>>) -> anyhow::Result<()> {
+        let mut columns: Vec<Vec<Cell>> = self.schema.fields().iter().map(|_| vec![]).collect();
+        for row in rows {
+            for (column, cell) in columns.iter_mut().zip(row) {
+                column.push(cell);
+            }
+        }
+        let arrays: Vec<ArrayRef> = columns
+            .into_iter()
+            .zip(self.schema.fields())
+            .map(|(column, field)| -> ArrayRef {
+                match field.data_type() {
+                    DataType::UInt64 => {
+                        let mut builder = UInt64Builder::new();
+                        for cell in column {
+                            let Cell::U64(value) = cell else { unreachable!() };
+                            builder.append_value(value);
+                        }
+                        Arc::new(builder.finish())
+                    }
+                    DataType::Binary => {
+                        let mut builder = BinaryBuilder::new();
+                        for cell in column {
+                            let Cell::Bytes(value) = cell else { unreachable!() };
+                            builder.append_value(value);
+                        }
+                        Arc::new(builder.finish())
+                    }
+                    _ => {
+                        let mut builder = StringBuilder::new();
+                        for cell in column {
+                            let Cell::String(value) = cell else { unreachable!() };
+                            builder.append_value(value);
+                        }
+                        Arc::new(builder.finish())
+                    }
+                }
+            })
+            .collect();
+        self.writer.write(&RecordBatch::try_new(self.schema.clone(), arrays)?)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> anyhow::Result<()> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+struct CsvTableWriter {
+    writer: csv::Writer<File>,
+}
+
+impl CsvTableWriter {
+    fn new(table: &Table, file: File) -> anyhow::Result<Self> {
+        let mut writer = csv::Writer::from_writer(file);
+        writer.write_record(table.columns.iter().map(|(name, _)| name))?;
+        Ok(Self { writer })
+    }
+}
+
+impl TableWriter for CsvTableWriter {
+    fn write_rows(&mut self, rows: Vec<Vec<Cell>>) -> anyhow::Result<()> {
+        for row in rows {
+            self.writer.write_record(row.into_iter().map(|cell| match cell {
+                Cell::String(value) => value,
+                Cell::U64(value) => value.to_string(),
+                Cell::Bytes(value) => to_base64(&value),
+            }))?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Entry of `export.json`.
+#[derive(serde::Serialize)]
+struct ExportedShard {
+    shard_uid: String,
+    flat_head_hash: CryptoHash,
+    flat_head_height: BlockHeight,
+    /// Number of rows of every table.
+    rows: Vec<(&'static str, u64)>,
+}
+
+impl ExportStateCommand {
+    pub(crate) fn run(
+        &self,
+        home: &PathBuf,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let mut near_config = load_config(home, genesis_validation)?;
+        let store = match &self.checkpoint {
+            Some(prev_block_hash) => open_state_checkpoint(
+                &state_checkpoints_dir(home)?,
+                prev_block_hash,
+                Mode::ReadOnly,
+            )?,
+            None => open_storage(home, &mut near_config)?.get_hot_store(),
+        };
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let head: Tip =
+            store.get_ser(DBCol::BlockMisc, HEAD_KEY)?.context("the database has no head")?;
+        let shard_layout = epoch_manager.get_shard_layout(&head.epoch_id)?;
+        let shard_uids: Vec<ShardUId> = if self.shard_id.is_empty() {
+            shard_layout.shard_uids().collect()
+        } else {
+            self.shard_id
+                .iter()
+                .map(|shard_id| ShardUId::from_shard_id_and_layout(*shard_id, &shard_layout))
+                .collect()
+        };
+
+        std::fs::create_dir_all(&self.output_dir)?;
+        let mut exported = vec![];
+        for shard_uid in shard_uids {
+            exported.push(self.export_shard(&store, shard_uid)?);
+        }
+        let manifest = File::create(self.output_dir.join("export.json"))?;
+        serde_json::to_writer_pretty(manifest, &exported)?;
+        Ok(())
+    }
+
+    fn export_shard(&self, store: &Store, shard_uid: ShardUId) -> anyhow::Result<ExportedShard> {
+        let FlatStorageStatus::Ready(status) =
+            store_helper::get_flat_storage_status(store, shard_uid)?
+        else {
+            anyhow::bail!("flat storage of shard {shard_uid} isn't ready");
+        };
+        let flat_head = status.flat_head;
+        tracing::info!(target: "export-state", %shard_uid, height = flat_head.height, "Exporting shard");
+
+        let trie_storage = TrieDBStorage::new(store.clone(), shard_uid);
+        let mut rows = vec![];
+        for table in &TABLES {
+            let path = self.table_path(table, shard_uid);
+            let file = File::create(&path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            let mut writer: Box<dyn TableWriter> = match self.format {
+                ExportFormat::Parquet => Box::new(ParquetTableWriter::new(table, file)?),
+                ExportFormat::Csv => Box::new(CsvTableWriter::new(table, file)?),
+            };
+            let count = export_table(store, &trie_storage, shard_uid, table, writer.as_mut())?;
+            writer.finish()?;
+            tracing::info!(target: "export-state", %shard_uid, table = table.name, count, "Exported table");
+            rows.push((table.name, count));
+        }
+        Ok(ExportedShard {
+            shard_uid: shard_uid.to_string(),
+            flat_head_hash: flat_head.hash,
+            flat_head_height: flat_head.height,
+            rows,
+        })
+    }
+
+    fn table_path(&self, table: &Table, shard_uid: ShardUId) -> PathBuf {
+        let file_name =
+            format!("{}.shard{}.{}", table.name, shard_uid.shard_id, self.format.extension());
+        self.output_dir.join(file_name)
+    }
+}
+
+/// Writes the rows of a table from the flat state of the shard.  Returns the
+/// number of rows.
+fn export_table(
+    store: &Store,
+    trie_storage: &TrieDBStorage,
+    shard_uid: ShardUId,
+    table: &Table,
+    writer: &mut dyn TableWriter,
+) -> anyhow::Result<u64> {
+    let mut count = 0;
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let from = [table.col];
+    let to = [table.col + 1];
+    let mut entries =
+        store_helper::iter_flat_state_entries(shard_uid, store, Some(&from), Some(&to));
+    loop {
+        batch.clear();
+        for entry in entries.by_ref().take(BATCH_SIZE) {
+            batch.push(entry?);
+        }
+        if batch.is_empty() {
+            return Ok(count);
+        }
+
+        // Values which aren't inlined are stored in the State column.
+        let hashes: Vec<CryptoHash> = batch
+            .iter()
+            .filter_map(|(_, value)| match value {
+                FlatStateValue::Ref(value_ref) => Some(value_ref.hash),
+                FlatStateValue::Inlined(_) => None,
+            })
+            .collect();
+        let mut values = trie_storage.retrieve_raw_bytes_many(&hashes)?.into_iter();
+        let rows = batch
+            .iter()
+            .map(|(key, value)| match value {
+                FlatStateValue::Ref(_) => {
+                    table.to_row(key, &values.next().expect("a value for each ref"))
+                }
+                FlatStateValue::Inlined(value) => table.to_row(key, value),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        count += rows.len() as u64;
+        writer.write_rows(rows)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cell, CsvTableWriter, TableWriter, TABLES};
+
+    #[test]
+    fn test_csv_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("contract_data.csv");
+        let contract_data = &TABLES[2];
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = Box::new(CsvTableWriter::new(contract_data, file).unwrap());
+        writer
+            .write_rows(vec![vec![
+                Cell::String("alice.near".to_string()),
+                Cell::Bytes(b"key".to_vec()),
+                Cell::Bytes(vec![0, 1, 2]),
+            ]])
+            .unwrap();
+        writer.finish().unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(csv, "account_id,key,value\nalice.near,a2V5,AAEC\n");
+    }
+}
@@ -4,14 +4,19 @@ mod analyse_gas_usage;
 mod analyse_high_load;
 mod analyze_contract_sizes;
 mod analyze_delayed_receipt;
+mod backup;
 mod block_iterators;
 pub mod commands;
 mod compact;
 mod corrupt;
+mod export_state;
 mod make_snapshot;
 mod memtrie;
+mod portable_snapshot;
 mod resharding_v2;
 mod run_migrations;
+mod state_checkpoints;
 mod state_perf;
 mod utils;
+mod verify;
 mod write_to_db;
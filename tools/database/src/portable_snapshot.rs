@@ -0,0 +1,426 @@
+//! Export and import of the state at an epoch boundary in a format which
+//! doesn't depend on the layout of the database.
+//!
+//! A snapshot is a stream starting with [`SNAPSHOT_MAGIC`] and the format
+//! version, followed by frames.  Every frame is the little endian length of
+//! a Borsh encoded [`SnapshotFrame`], the frame itself and its hash, so that a
+//! corrupted snapshot is detected as soon as it is read.  The frames are, in
+//! order:
+//! * the [`SnapshotHeader`],
+//! * the blocks needed to finalize the state of the shards,
+//! * for every shard, its state sync header followed by its state parts,
+//! * the end frame with the number of the preceding frames, so that a
+//!   truncated snapshot is detected too.
+//!
+//! The state parts are the same as the ones of state sync, so importing a
+//! snapshot restores both the trie and the flat storage of every shard.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_async::time::Clock;
+use near_chain::rayon_spawner::RayonAsyncComputationSpawner;
+use near_chain::types::ChainConfig;
+use near_chain::{BlockProcessingArtifact, Chain, ChainGenesis, DoomslugThresholdMode};
+use near_chain_configs::MutableConfigValue;
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::EpochManager;
+use near_primitives::block::Block;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::state_part::PartId;
+use near_primitives::state_sync::ShardStateSyncResponseHeader;
+use near_primitives::types::{BlockHeight, EpochId, ShardId};
+use near_primitives::utils::MaybeValidated;
+use nearcore::{open_storage, NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"NEARSNAP";
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+struct SnapshotHeader {
+    chain_id: String,
+    epoch_id: EpochId,
+    /// First block of the epoch.  The state is the one before applying its
+    /// chunks, i.e. at the end of the previous epoch.
+    sync_hash: CryptoHash,
+    sync_height: BlockHeight,
+    shard_ids: Vec<ShardId>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+enum SnapshotFrame {
+    Header(SnapshotHeader),
+    Block(Box<Block>),
+    ShardHeader { shard_id: ShardId, header: ShardStateSyncResponseHeader },
+    StatePart { shard_id: ShardId, part_id: u64, num_parts: u64, data: Vec<u8> },
+    End { num_frames: u64 },
+}
+
+struct SnapshotWriter<W: Write> {
+    writer: W,
+    num_frames: u64,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    fn new(mut writer: W) -> anyhow::Result<Self> {
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&SNAPSHOT_FORMAT_VERSION.to_le_bytes())?;
+        Ok(Self { writer, num_frames: 0 })
+    }
+
+    fn write(&mut self, frame: &SnapshotFrame) -> anyhow::Result<()> {
+        let bytes = borsh::to_vec(frame)?;
+        self.writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        self.writer.write_all(&bytes)?;
+        self.writer.write_all(hash(&bytes).as_bytes())?;
+        self.num_frames += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> anyhow::Result<W> {
+        self.write(&SnapshotFrame::End { num_frames: self.num_frames })?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+struct SnapshotReader<R: Read> {
+    reader: R,
+    num_frames: u64,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        anyhow::ensure!(&magic == SNAPSHOT_MAGIC, "not a state snapshot");
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        anyhow::ensure!(
+            version == SNAPSHOT_FORMAT_VERSION,
+            "unsupported snapshot format version {version}, expected {SNAPSHOT_FORMAT_VERSION}"
+        );
+        Ok(Self { reader, num_frames: 0 })
+    }
+
+    fn read(&mut self) -> anyhow::Result<SnapshotFrame> {
+        let mut len = [0; 8];
+        self.reader.read_exact(&mut len)?;
+        let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut bytes)?;
+        let mut checksum = [0; 32];
+        self.reader.read_exact(&mut checksum)?;
+        anyhow::ensure!(
+            hash(&bytes).as_bytes() == &checksum,
+            "checksum mismatch in frame {}",
+            self.num_frames
+        );
+        let frame = SnapshotFrame::try_from_slice(&bytes)?;
+        if let SnapshotFrame::End { num_frames } = frame {
+            anyhow::ensure!(
+                num_frames == self.num_frames,
+                "snapshot has {} frames but its end frame expects {num_frames}",
+                self.num_frames
+            );
+        }
+        self.num_frames += 1;
+        Ok(frame)
+    }
+}
+
+#[derive(clap::Args)]
+pub(crate) struct ExportSnapshotCommand {
+    /// File to write the snapshot to, `-` for the standard output.
+    #[clap(long)]
+    output: PathBuf,
+    /// Any block of the exported epoch.  Defaults to the head, i.e. the
+    /// state at the beginning of the current epoch is exported.
+    #[clap(long)]
+    block_hash: Option<String>,
+}
+
+impl ExportSnapshotCommand {
+    pub(crate) fn run(&self, home_dir: &Path, near_config: NearConfig) -> anyhow::Result<()> {
+        let chain_id = near_config.genesis.config.chain_id.clone();
+        let chain = create_chain(home_dir, near_config, true)?;
+        let block_hash = match &self.block_hash {
+            Some(block_hash) => CryptoHash::from_str(block_hash)
+                .map_err(|err| anyhow::anyhow!("invalid block hash: {err}"))?,
+            None => chain.head()?.last_block_hash,
+        };
+        let sync_hash = *chain.epoch_manager.get_block_info(&block_hash)?.epoch_first_block();
+        let sync_header = chain.get_block_header(&sync_hash)?;
+        let epoch_id = *sync_header.epoch_id();
+        let shard_ids = chain.epoch_manager.shard_ids(&epoch_id)?;
+        let shard_headers = shard_ids
+            .iter()
+            .map(|&shard_id| chain.compute_state_response_header(shard_id, sync_hash))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let output: Box<dyn Write> = if self.output.as_os_str() == "-" {
+            Box::new(std::io::stdout().lock())
+        } else {
+            Box::new(std::fs::File::create(&self.output)?)
+        };
+        let mut writer = SnapshotWriter::new(BufWriter::new(output))?;
+        writer.write(&SnapshotFrame::Header(SnapshotHeader {
+            chain_id,
+            epoch_id,
+            sync_hash,
+            sync_height: sync_header.height(),
+            shard_ids: shard_ids.clone(),
+        }))?;
+        tracing::info!(target: "portable-snapshot", ?epoch_id, ?sync_hash, ?shard_ids, "Exporting state snapshot");
+
+        // Finalizing the state of a shard applies the blocks since the last
+        // chunk of the shard was included.
+        let min_height_included = shard_headers
+            .iter()
+            .map(ShardStateSyncResponseHeader::chunk_height_included)
+            .min()
+            .unwrap_or(sync_header.height());
+        let mut blocks = vec![];
+        let mut next_hash = sync_hash;
+        loop {
+            let block = chain.get_block(&next_hash)?;
+            if block.header().height() < min_height_included {
+                break;
+            }
+            next_hash = *block.header().prev_hash();
+            blocks.push(block);
+        }
+        for block in blocks.into_iter().rev() {
+            writer.write(&SnapshotFrame::Block(Box::new(block)))?;
+        }
+
+        let sync_prev_header = chain.get_previous_header(&sync_header)?;
+        let sync_prev_prev_hash = sync_prev_header.prev_hash();
+        for (shard_id, header) in shard_ids.into_iter().zip(shard_headers) {
+            let state_root = header.chunk_prev_state_root();
+            let num_parts = header.num_state_parts();
+            writer.write(&SnapshotFrame::ShardHeader { shard_id, header })?;
+            for part_id in 0..num_parts {
+                let data = chain.runtime_adapter.obtain_state_part(
+                    shard_id,
+                    sync_prev_prev_hash,
+                    &state_root,
+                    PartId::new(part_id, num_parts),
+                )?;
+                writer.write(&SnapshotFrame::StatePart { shard_id, part_id, num_parts, data })?;
+            }
+            tracing::info!(target: "portable-snapshot", shard_id, num_parts, "Exported state of shard");
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[derive(clap::Args)]
+pub(crate) struct ImportSnapshotCommand {
+    /// File to read the snapshot from, `-` for the standard input.
+    #[clap(long)]
+    input: PathBuf,
+}
+
+impl ImportSnapshotCommand {
+    /// Imports the snapshot into the database of a stopped node.  The node
+    /// needs the block headers up to the snapshot epoch, so it has to be
+    /// started and left to sync the headers first.
+    pub(crate) fn run(&self, home_dir: &Path, near_config: NearConfig) -> anyhow::Result<()> {
+        let chain_id = near_config.genesis.config.chain_id.clone();
+        let archive = near_config.client_config.archive;
+        let mut chain = create_chain(home_dir, near_config, false)?;
+
+        let input: Box<dyn Read> = if self.input.as_os_str() == "-" {
+            Box::new(std::io::stdin().lock())
+        } else {
+            Box::new(std::fs::File::open(&self.input)?)
+        };
+        let mut reader = SnapshotReader::new(BufReader::new(input))?;
+        let SnapshotFrame::Header(header) = reader.read()? else {
+            anyhow::bail!("state snapshot doesn't start with a header");
+        };
+        anyhow::ensure!(
+            header.chain_id == chain_id,
+            "state snapshot of chain {} can't be imported into chain {chain_id}",
+            header.chain_id
+        );
+        let sync_hash = header.sync_hash;
+        chain.get_block_header(&sync_hash).map_err(|err| {
+            anyhow::anyhow!(
+                "block header {sync_hash} at height {} is missing, let the node sync the headers first: {err}",
+                header.sync_height
+            )
+        })?;
+        if !archive {
+            let runtime_adapter = chain.runtime_adapter.clone();
+            let epoch_manager = chain.epoch_manager.clone();
+            chain.mut_chain_store().reset_data_pre_state_sync(
+                sync_hash,
+                runtime_adapter,
+                epoch_manager,
+            )?;
+        }
+        tracing::info!(target: "portable-snapshot", epoch_id = ?header.epoch_id, ?sync_hash, shard_ids = ?header.shard_ids, "Importing state snapshot");
+
+        let mut current_shard = None;
+        loop {
+            match reader.read()? {
+                SnapshotFrame::Header(_) => anyhow::bail!("unexpected header in state snapshot"),
+                SnapshotFrame::Block(block) => {
+                    // The header is known from the header sync, the rest of
+                    // the block is checked against it.
+                    chain.get_block_header(block.hash())?;
+                    let block = MaybeValidated::from(*block);
+                    chain.validate_block(&block)?;
+                    chain.save_block(block)?;
+                }
+                SnapshotFrame::ShardHeader { shard_id, header: shard_header } => {
+                    if let Some(shard_id) = current_shard.take() {
+                        finalize_shard(&mut chain, shard_id, sync_hash)?;
+                    }
+                    chain.set_state_header(shard_id, sync_hash, shard_header)?;
+                    current_shard = Some(shard_id);
+                }
+                SnapshotFrame::StatePart { shard_id, part_id, num_parts, data } => {
+                    anyhow::ensure!(
+                        current_shard == Some(shard_id),
+                        "state part of shard {shard_id} without its header"
+                    );
+                    let part_id = PartId::new(part_id, num_parts);
+                    chain.set_state_part(shard_id, sync_hash, part_id, &data)?;
+                    let state_root =
+                        chain.get_state_header(shard_id, sync_hash)?.chunk_prev_state_root();
+                    chain.runtime_adapter.apply_state_part(
+                        shard_id,
+                        &state_root,
+                        part_id,
+                        &data,
+                        &header.epoch_id,
+                    )?;
+                }
+                SnapshotFrame::End { .. } => break,
+            }
+        }
+        if let Some(shard_id) = current_shard {
+            finalize_shard(&mut chain, shard_id, sync_hash)?;
+        }
+        chain.reset_heads_post_state_sync(
+            &None,
+            sync_hash,
+            &mut BlockProcessingArtifact::default(),
+            None,
+        )?;
+        tracing::info!(target: "portable-snapshot", ?sync_hash, "Imported state snapshot");
+        Ok(())
+    }
+}
+
+/// Creates the flat storage of the shard once all its parts are applied and
+/// finalizes its state the same way state sync does.
+fn finalize_shard(
+    chain: &mut Chain,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+) -> anyhow::Result<()> {
+    let shard_state_header = chain.get_state_header(shard_id, sync_hash)?;
+    let chunk = shard_state_header.cloned_chunk();
+    if *chunk.prev_block() != CryptoHash::default() {
+        let epoch_id = *chain.get_block_header(&sync_hash)?.epoch_id();
+        let shard_uid = chain.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
+        chain.create_flat_storage_for_shard(shard_uid, &chunk)?;
+    }
+    chain.set_state_finalize(shard_id, sync_hash)?;
+    chain.clear_downloaded_parts(shard_id, sync_hash, shard_state_header.num_state_parts())?;
+    tracing::info!(target: "portable-snapshot", shard_id, "Imported state of shard");
+    Ok(())
+}
+
+fn create_chain(
+    home_dir: &Path,
+    mut near_config: NearConfig,
+    view_client: bool,
+) -> anyhow::Result<Chain> {
+    let store = open_storage(home_dir, &mut near_config)?.get_hot_store();
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+    let shard_tracker = ShardTracker::new(
+        TrackedConfig::from_config(&near_config.client_config),
+        epoch_manager.clone(),
+    );
+    let runtime =
+        NightshadeRuntime::from_config(home_dir, store, &near_config, epoch_manager.clone())?;
+    let chain_genesis = ChainGenesis::new(&near_config.genesis.config);
+    let client_config = near_config.client_config;
+    let chain = if view_client {
+        Chain::new_for_view_client(
+            Clock::real(),
+            epoch_manager,
+            shard_tracker,
+            runtime,
+            &chain_genesis,
+            DoomslugThresholdMode::TwoThirds,
+            false,
+        )?
+    } else {
+        let chain_config = ChainConfig {
+            save_trie_changes: client_config.save_trie_changes,
+            background_migration_threads: client_config.client_background_migration_threads,
+            resharding_config: client_config.resharding_config,
+        };
+        Chain::new(
+            Clock::real(),
+            epoch_manager,
+            shard_tracker,
+            runtime,
+            &chain_genesis,
+            DoomslugThresholdMode::TwoThirds,
+            chain_config,
+            None,
+            Arc::new(RayonAsyncComputationSpawner),
+            MutableConfigValue::new(None, "validator_signer"),
+        )?
+    };
+    Ok(chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SnapshotFrame, SnapshotReader, SnapshotWriter};
+
+    fn state_part(part_id: u64) -> SnapshotFrame {
+        SnapshotFrame::StatePart {
+            shard_id: 0,
+            part_id,
+            num_parts: 2,
+            data: vec![part_id as u8; 10],
+        }
+    }
+
+    #[test]
+    fn test_snapshot_frames() {
+        let mut writer = SnapshotWriter::new(vec![]).unwrap();
+        writer.write(&state_part(0)).unwrap();
+        writer.write(&state_part(1)).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = SnapshotReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.read().unwrap(), state_part(0));
+        assert_eq!(reader.read().unwrap(), state_part(1));
+        assert_eq!(reader.read().unwrap(), SnapshotFrame::End { num_frames: 2 });
+
+        // A corrupted frame is rejected.
+        let mut corrupted = bytes.clone();
+        corrupted[30] ^= 1;
+        let mut reader = SnapshotReader::new(corrupted.as_slice()).unwrap();
+        assert!(reader.read().is_err());
+
+        // So is a truncated snapshot.
+        let mut reader = SnapshotReader::new(&bytes[..bytes.len() - 1]).unwrap();
+        reader.read().unwrap();
+        reader.read().unwrap();
+        assert!(reader.read().is_err());
+    }
+}
@@ -2,7 +2,12 @@ use near_chain_configs::GenesisValidationMode;
 use std::path::Path;
 
 #[derive(clap::Args)]
-pub(crate) struct RunMigrationsCommand {}
+pub(crate) struct RunMigrationsCommand {
+    /// Estimate how long the migrations would take and how much they would
+    /// read and write, without running them.
+    #[clap(long)]
+    dry_run: bool,
+}
 
 impl RunMigrationsCommand {
     pub(crate) fn run(
@@ -12,7 +17,46 @@ impl RunMigrationsCommand {
     ) -> anyhow::Result<()> {
         let mut near_config = nearcore::config::load_config(&home_dir, genesis_validation)
             .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+        if self.dry_run {
+            let estimates = nearcore::estimate_migrations(home_dir, &near_config)?;
+            if estimates.is_empty() {
+                println!("The database is up to date");
+            }
+            for estimate in estimates {
+                let duration = match estimate.duration {
+                    Some(duration) => format!("{:?}", duration),
+                    None => "unknown".to_string(),
+                };
+                println!(
+                    "{} -> {}: {}\n  read: {}, write: {}, time: {}",
+                    estimate.version,
+                    estimate.version + 1,
+                    estimate.summary,
+                    bytesize::ByteSize(estimate.read_bytes),
+                    bytesize::ByteSize(estimate.write_bytes),
+                    duration,
+                );
+            }
+            return Ok(());
+        }
         nearcore::open_storage(home_dir, &mut near_config)?;
         Ok(())
     }
 }
+
+#[derive(clap::Args)]
+pub(crate) struct RollbackMigrationCommand {}
+
+impl RollbackMigrationCommand {
+    pub(crate) fn run(
+        &self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(&home_dir, genesis_validation)
+            .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+        let version = nearcore::rollback_migration(home_dir, &near_config)?;
+        println!("Rolled back the database to version {version}");
+        Ok(())
+    }
+}
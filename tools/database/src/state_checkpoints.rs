@@ -0,0 +1,72 @@
+use crate::utils::state_checkpoints_dir;
+use near_primitives::block::Tip;
+use near_primitives::hash::CryptoHash;
+use near_store::db::{FINAL_HEAD_KEY, HEAD_KEY};
+use near_store::{
+    checkpoint_hot_storage_and_cleanup_columns, list_state_checkpoints, open_state_checkpoint,
+    DBCol, Mode,
+};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args)]
+pub(crate) struct StateCheckpointsCommand {
+    #[clap(subcommand)]
+    subcmd: StateCheckpointsSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum StateCheckpointsSubCommand {
+    /// List the state checkpoints, oldest first
+    List,
+    /// Copy a state checkpoint into a new database, e.g. to run
+    /// `neard fork-network` on it without stopping the node
+    Clone(CloneCheckpointCmd),
+}
+
+#[derive(clap::Args)]
+struct CloneCheckpointCmd {
+    /// Hash of the last block of the epoch at whose end the checkpoint was
+    /// made, as listed by `list`.
+    #[clap(long)]
+    prev_block_hash: CryptoHash,
+    /// Destination directory, the database is created in its `data`
+    /// subdirectory.
+    #[clap(long)]
+    destination: PathBuf,
+}
+
+impl StateCheckpointsCommand {
+    pub(crate) fn run(&self, home: &Path) -> anyhow::Result<()> {
+        let checkpoints_dir = state_checkpoints_dir(home)?;
+        match &self.subcmd {
+            StateCheckpointsSubCommand::List => {
+                for prev_block_hash in list_state_checkpoints(&checkpoints_dir)? {
+                    let store =
+                        open_state_checkpoint(&checkpoints_dir, &prev_block_hash, Mode::ReadOnly)?;
+                    let head = store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?;
+                    let final_head = store.get_ser::<Tip>(DBCol::BlockMisc, FINAL_HEAD_KEY)?;
+                    println!(
+                        "{prev_block_hash} head: {:?} final head: {:?}",
+                        head.map(|tip| tip.height),
+                        final_head.map(|tip| tip.height),
+                    );
+                }
+            }
+            StateCheckpointsSubCommand::Clone(cmd) => {
+                // RocksDB can't make a checkpoint of a database opened read
+                // only.  Nothing is written to the checkpoint.
+                let store = open_state_checkpoint(
+                    &checkpoints_dir,
+                    &cmd.prev_block_hash,
+                    Mode::ReadWriteExisting,
+                )?;
+                checkpoint_hot_storage_and_cleanup_columns(&store, &cmd.destination, None)?;
+                println!(
+                    "Cloned the state checkpoint into {}",
+                    cmd.destination.join("data").display()
+                );
+            }
+        }
+        Ok(())
+    }
+}
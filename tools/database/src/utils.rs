@@ -1,8 +1,8 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::anyhow;
-use near_store::{DBCol, NodeStorage, Store};
+use near_store::{DBCol, NodeStorage, ShardTries, Store};
 use strum::IntoEnumIterator;
 
 pub(crate) fn open_rocksdb(
@@ -43,6 +43,15 @@ pub(crate) fn open_state_snapshot(home: &Path, mode: near_store::Mode) -> anyhow
     Ok(store)
 }
 
+/// Returns the directory of the state checkpoints of the node.
+pub(crate) fn state_checkpoints_dir(home: &Path) -> anyhow::Result<PathBuf> {
+    let config = nearcore::config::Config::from_file_skip_validation(
+        &home.join(nearcore::config::CONFIG_FILENAME),
+    )?;
+    let hot_store_path = config.store.path.unwrap_or_else(|| PathBuf::from("data"));
+    Ok(ShardTries::get_state_checkpoints_dir(home, &hot_store_path))
+}
+
 pub(crate) fn resolve_column(col_name: &str) -> anyhow::Result<DBCol> {
     DBCol::iter()
         .filter(|db_col| <&str>::from(db_col) == col_name)
@@ -0,0 +1,277 @@
+use borsh::BorshDeserialize;
+use clap::Parser;
+use near_chain::block_refetch::add_blocks_to_refetch;
+use near_chain_configs::GenesisValidationMode;
+use near_primitives::block::{Block, BlockHeader};
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::sharding::{PartialEncodedChunk, ShardChunk};
+use near_primitives::types::BlockHeight;
+use near_store::db::{CHUNK_TAIL_KEY, TAIL_KEY};
+use near_store::{DBCol, Store};
+use nearcore::{load_config, open_storage};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+pub(crate) struct VerifyDatabaseCommand {
+    /// Check the rows of the State column.  This reads the whole state, so
+    /// it takes much longer than the other checks.
+    #[arg(long)]
+    check_state: bool,
+
+    /// Delete the missing or corrupt blocks and have the node request them
+    /// from its peers once it is started again.  Headers which are corrupt
+    /// are restored from their blocks where those are intact.
+    #[arg(long)]
+    repair: bool,
+}
+
+/// A row which failed a check.
+struct Corruption {
+    col: DBCol,
+    reason: String,
+}
+
+#[derive(Default)]
+struct Findings {
+    corruptions: Vec<Corruption>,
+    /// Blocks which are corrupt or missing and can be requested from peers.
+    blocks_to_refetch: HashSet<CryptoHash>,
+    /// Corrupt headers whose blocks are intact.
+    headers_to_restore: Vec<BlockHeader>,
+}
+
+impl Findings {
+    fn report(&mut self, col: DBCol, key: &[u8], reason: impl Into<String>) {
+        let corruption = Corruption { col, reason: reason.into() };
+        let key_str = match CryptoHash::try_from(key) {
+            Ok(hash) => hash.to_string(),
+            Err(_) => format!("{key:?}"),
+        };
+        eprintln!("{}: {}: {}", <&str>::from(col), key_str, corruption.reason);
+        self.corruptions.push(corruption);
+    }
+}
+
+impl VerifyDatabaseCommand {
+    pub(crate) fn run(
+        &self,
+        home: &PathBuf,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let mut near_config = load_config(home, genesis_validation)?;
+        let node_storage = open_storage(home, &mut near_config)?;
+        let store = node_storage.get_hot_store();
+
+        let mut findings = Findings::default();
+        let headers = verify_headers(&store, &mut findings)?;
+        verify_blocks(&store, &headers, &mut findings)?;
+        verify_chunks(&store, &mut findings)?;
+        if self.check_state {
+            verify_state(&store, &mut findings)?;
+        }
+
+        println!("Found {} corrupt or missing rows", findings.corruptions.len());
+        for col in [
+            DBCol::BlockHeader,
+            DBCol::BlockHeight,
+            DBCol::Block,
+            DBCol::Chunks,
+            DBCol::PartialChunks,
+            DBCol::State,
+        ] {
+            let count = findings.corruptions.iter().filter(|c| c.col == col).count();
+            if count > 0 {
+                println!("  {}: {}", <&str>::from(col), count);
+            }
+        }
+        if !self.repair {
+            if !findings.corruptions.is_empty() {
+                println!(
+                    "Run with --repair to refetch {} blocks",
+                    findings.blocks_to_refetch.len()
+                );
+            }
+            return Ok(());
+        }
+        repair(&store, &findings)
+    }
+}
+
+/// Checks that the headers decode and are stored under their hashes.
+/// Returns the hashes of the intact headers.
+fn verify_headers(store: &Store, findings: &mut Findings) -> anyhow::Result<HashSet<CryptoHash>> {
+    let mut headers = HashSet::new();
+    for item in store.iter(DBCol::BlockHeader) {
+        let (key, value) = item?;
+        match BlockHeader::try_from_slice(&value) {
+            Ok(header) if header.hash().as_ref() == &key[..] => {
+                headers.insert(*header.hash());
+            }
+            Ok(header) => findings.report(
+                DBCol::BlockHeader,
+                &key,
+                format!("stored under the wrong hash, its hash is {}", header.hash()),
+            ),
+            Err(err) => findings.report(DBCol::BlockHeader, &key, format!("can't decode: {err}")),
+        }
+    }
+
+    for item in store.iter(DBCol::BlockHeight) {
+        let (key, value) = item?;
+        match CryptoHash::try_from_slice(&value) {
+            Ok(hash) if headers.contains(&hash) => {}
+            Ok(hash) => {
+                findings.report(DBCol::BlockHeight, &key, format!("header {hash} is missing"));
+            }
+            Err(err) => findings.report(DBCol::BlockHeight, &key, format!("can't decode: {err}")),
+        }
+    }
+    Ok(headers)
+}
+
+/// Checks that the blocks decode, are stored under their hashes and match
+/// their headers, that the blocks of the canonical chain above the tail are
+/// present, and that the chunks they include are present.
+fn verify_blocks(
+    store: &Store,
+    headers: &HashSet<CryptoHash>,
+    findings: &mut Findings,
+) -> anyhow::Result<()> {
+    let chunk_tail: BlockHeight = store.get_ser(DBCol::BlockMisc, CHUNK_TAIL_KEY)?.unwrap_or(0);
+    let mut blocks = HashSet::new();
+    for item in store.iter(DBCol::Block) {
+        let (key, value) = item?;
+        let Ok(hash) = CryptoHash::try_from(&key[..]) else {
+            findings.report(DBCol::Block, &key, "key isn't a hash");
+            continue;
+        };
+        let block = match Block::try_from_slice(&value) {
+            Ok(block) => block,
+            Err(err) => {
+                findings.report(DBCol::Block, &key, format!("can't decode: {err}"));
+                findings.blocks_to_refetch.insert(hash);
+                continue;
+            }
+        };
+        if block.hash() != &hash {
+            findings.report(DBCol::Block, &key, "stored under the wrong hash");
+            findings.blocks_to_refetch.insert(hash);
+            continue;
+        }
+        if let Err(err) = block.check_validity() {
+            findings.report(DBCol::Block, &key, format!("doesn't match its header: {err:?}"));
+            findings.blocks_to_refetch.insert(hash);
+            continue;
+        }
+        if !headers.contains(&hash) {
+            findings.headers_to_restore.push(block.header().clone());
+        }
+        blocks.insert(hash);
+
+        if block.header().height() < chunk_tail {
+            continue;
+        }
+        for chunk in block.chunks().iter() {
+            if chunk.height_included() != block.header().height() {
+                continue;
+            }
+            let chunk_hash = chunk.chunk_hash();
+            if !store.exists(DBCol::PartialChunks, chunk_hash.as_ref())? {
+                findings.report(
+                    DBCol::Block,
+                    &key,
+                    format!("partial chunk {} is missing", chunk_hash.0),
+                );
+            }
+        }
+    }
+
+    let tail: BlockHeight = store.get_ser(DBCol::BlockMisc, TAIL_KEY)?.unwrap_or(0);
+    for item in store.iter(DBCol::BlockHeight) {
+        let (key, value) = item?;
+        let (Ok(height), Ok(hash)) =
+            (BlockHeight::try_from_slice(&key), CryptoHash::try_from_slice(&value))
+        else {
+            continue;
+        };
+        if height >= tail && !blocks.contains(&hash) && !findings.blocks_to_refetch.contains(&hash)
+        {
+            findings.report(
+                DBCol::Block,
+                hash.as_ref(),
+                format!("block at height {height} is missing"),
+            );
+            findings.blocks_to_refetch.insert(hash);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the chunks and the partial chunks decode and are stored under
+/// their hashes.
+fn verify_chunks(store: &Store, findings: &mut Findings) -> anyhow::Result<()> {
+    for item in store.iter(DBCol::Chunks) {
+        let (key, value) = item?;
+        match ShardChunk::try_from_slice(&value) {
+            Ok(chunk) if chunk.cloned_header().compute_hash().0.as_ref() == &key[..] => {}
+            Ok(_) => findings.report(DBCol::Chunks, &key, "stored under the wrong hash"),
+            Err(err) => findings.report(DBCol::Chunks, &key, format!("can't decode: {err}")),
+        }
+    }
+    for item in store.iter(DBCol::PartialChunks) {
+        let (key, value) = item?;
+        match PartialEncodedChunk::try_from_slice(&value) {
+            Ok(chunk) if chunk.cloned_header().compute_hash().0.as_ref() == &key[..] => {}
+            Ok(_) => findings.report(DBCol::PartialChunks, &key, "stored under the wrong hash"),
+            Err(err) => findings.report(DBCol::PartialChunks, &key, format!("can't decode: {err}")),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the trie nodes and values are stored under their hashes.
+fn verify_state(store: &Store, findings: &mut Findings) -> anyhow::Result<()> {
+    for item in store.iter(DBCol::State) {
+        let (key, value) = item?;
+        if key.len() != 40 || hash(&value).as_ref() != &key[8..] {
+            findings.report(DBCol::State, &key, "value doesn't match its hash");
+        }
+    }
+    Ok(())
+}
+
+fn repair(store: &Store, findings: &Findings) -> anyhow::Result<()> {
+    // Rows of insert-only columns must be deleted before they are written
+    // again with different values.
+    let mut store_update = store.store_update();
+    for header in &findings.headers_to_restore {
+        store_update.delete(DBCol::BlockHeader, header.hash().as_ref());
+    }
+    for hash in &findings.blocks_to_refetch {
+        store_update.delete(DBCol::Block, hash.as_ref());
+    }
+    store_update.commit()?;
+    let mut store_update = store.store_update();
+    for header in &findings.headers_to_restore {
+        store_update.insert_ser(DBCol::BlockHeader, header.hash().as_ref(), header)?;
+    }
+    store_update.commit()?;
+    let blocks: Vec<CryptoHash> = findings.blocks_to_refetch.iter().copied().collect();
+    add_blocks_to_refetch(store, &blocks)?;
+
+    println!(
+        "Restored {} headers, {} blocks will be requested from peers once the node is started",
+        findings.headers_to_restore.len(),
+        blocks.len()
+    );
+    let unrepaired = findings
+        .corruptions
+        .iter()
+        .filter(|c| matches!(c.col, DBCol::Chunks | DBCol::PartialChunks | DBCol::State))
+        .count();
+    if unrepaired > 0 {
+        println!("{unrepaired} corrupt chunks or state rows can't be repaired, sync the node from scratch");
+    }
+    Ok(())
+}
@@ -28,6 +28,7 @@ export const PeerStorageView = ({ addr }: PeerStorageViewProps) => {
                 <th>Last seen</th>
                 <th>Last connection attempt</th>
                 <th>Status</th>
+                <th>Score</th>
             </thead>
             <tbody>
                 {peerStore!.status_response.PeerStore.peer_states.map((peer) => {
@@ -56,6 +57,7 @@ export const PeerStorageView = ({ addr }: PeerStorageViewProps) => {
                                     <td>{peer.status}</td>
                                 </>
                             )}
+                            <td>{peer.score}</td>
                         </tr>
                     );
                 })}
@@ -1,6 +1,6 @@
 import { MouseEvent, useCallback, useState } from 'react';
 import { useQuery } from '@tanstack/react-query';
-import { PeerAddr, fetchFullStatus } from './api';
+import { PeerAddr, Tier1ConnectionView, fetchFullStatus, fetchTier1Connections } from './api';
 import { addDebugPortLink, formatDurationInMillis, formatTraffic } from './utils';
 import './Tier1View.scss';
 
@@ -14,6 +14,9 @@ export const Tier1View = ({ addr }: Tier1ViewProps) => {
         error: fullStatusError,
         isLoading: fullStatusLoading,
     } = useQuery(['fullStatus', addr], () => fetchFullStatus(addr));
+    const { data: tier1Connections } = useQuery(['tier1Connections', addr], () =>
+        fetchTier1Connections(addr)
+    );
 
     if (fullStatusLoading) {
         return <div>Loading...</div>;
@@ -34,6 +37,13 @@ export const Tier1View = ({ addr }: Tier1ViewProps) => {
     }
 
     const networkInfo = fullStatus.detailed_debug_status!.network_info;
+    const connectionsByPeer = new Map<string, Tier1ConnectionView>();
+    for (const connection of tier1Connections?.status_response.Tier1Connections.connections ??
+        []) {
+        connectionsByPeer.set(connection.peer_id, connection);
+    }
+    const recentFailures =
+        tier1Connections?.status_response.Tier1Connections.recent_failures ?? [];
 
     const rendered = new Set();
     const rows = [];
@@ -67,6 +77,9 @@ export const Tier1View = ({ addr }: Tier1ViewProps) => {
             lastPingClass = 'peer_far_behind';
         }
 
+        const connection = connectionsByPeer.get(peer.peer_id);
+        const rtt = connection?.rtt_millis;
+
         rows.push(
             <tr key={accountKey}>
                 <td>{addDebugPortLink(peer.addr)}</td>
@@ -82,6 +95,8 @@ export const Tier1View = ({ addr }: Tier1ViewProps) => {
                 <td>{peer.is_outbound_peer ? 'OUT' : 'IN'}</td>
                 <td>{formatDurationInMillis(peer.connection_established_time_millis)}</td>
                 <td>{formatTraffic(peer.received_bytes_per_sec, peer.sent_bytes_per_sec)}</td>
+                <td>{rtt !== undefined && rtt !== null ? `${rtt} ms` : ''}</td>
+                <td>{connection?.pinned ? 'yes' : ''}</td>
             </tr>
         );
     }
@@ -117,6 +132,8 @@ export const Tier1View = ({ addr }: Tier1ViewProps) => {
                 <td></td>
                 <td></td>
                 <td></td>
+                <td></td>
+                <td></td>
             </tr>
         );
     }
@@ -141,6 +158,8 @@ export const Tier1View = ({ addr }: Tier1ViewProps) => {
                 <td></td>
                 <td></td>
                 <td></td>
+                <td></td>
+                <td></td>
             </tr>
         );
     }
@@ -161,10 +180,38 @@ export const Tier1View = ({ addr }: Tier1ViewProps) => {
                         <th>Connection type</th>
                         <th>First connection</th>
                         <th>Traffic (last minute)</th>
+                        <th>RTT</th>
+                        <th>Pinned</th>
                     </tr>
                 </thead>
                 <tbody>{rows}</tbody>
             </table>
+            <h3>Recent connection failures</h3>
+            <table className="peers">
+                <thead>
+                    <tr>
+                        <th>Time</th>
+                        <th>AccountKey</th>
+                        <th>PeerId</th>
+                        <th>Address</th>
+                        <th>Error</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {recentFailures
+                        .slice()
+                        .reverse()
+                        .map((failure, i) => (
+                            <tr key={i}>
+                                <td>{new Date(failure.time * 1000).toISOString()}</td>
+                                <td>{failure.account_key.substring(8, 14)}...</td>
+                                <td>{failure.peer_id.substring(8, 14)}...</td>
+                                <td>{failure.addr}</td>
+                                <td>{failure.error}</td>
+                            </tr>
+                        ))}
+                </tbody>
+            </table>
         </div>
     );
 };
@@ -246,6 +246,7 @@ export interface KnownPeerStateView {
     first_seen: number;
     last_seen: number;
     last_attempt: [number, string] | null;
+    score: number;
 }
 
 export interface SyncStatusResponse {
@@ -350,6 +351,34 @@ export interface SnapshotHostsResponse {
     };
 }
 
+export interface Tier1ConnectionView {
+    peer_id: string;
+    addr: string;
+    is_outbound_peer: boolean;
+    peer_account_key: string | null;
+    account_keys: string[];
+    pinned: boolean;
+    connection_established_time_millis: number;
+    rtt_millis: number | null;
+}
+
+export interface Tier1ConnectionFailureView {
+    peer_id: string;
+    addr: string;
+    account_key: string;
+    time: number;
+    error: string;
+}
+
+export interface Tier1ConnectionsResponse {
+    status_response: {
+        Tier1Connections: {
+            connections: Tier1ConnectionView[];
+            recent_failures: Tier1ConnectionFailureView[];
+        };
+    };
+}
+
 export type DroppedReason = 'HeightProcessed' | 'TooManyProcessingBlocks';
 
 export type BlockProcessingStatus =
@@ -464,6 +493,11 @@ export async function fetchSnapshotHosts(addr: string): Promise<SnapshotHostsRes
     return await response.json();
 }
 
+export async function fetchTier1Connections(addr: string): Promise<Tier1ConnectionsResponse> {
+    const response = await fetch(`http://${addr}/debug/api/tier1`);
+    return await response.json();
+}
+
 export async function fetchChainProcessingStatus(
     addr: string
 ): Promise<ChainProcessingStatusResponse> {
@@ -596,6 +596,36 @@ impl ForkNetworkCommand {
                         storage_mutator.set_delayed_receipt(index_delayed_receipt, &receipt)?;
                         index_delayed_receipt += 1;
                     }
+                    StateRecord::NamespacedData { account_id, namespace, data_key, value } => {
+                        // TODO(eth-implicit) Change back to is_implicit() when ETH-implicit accounts are supported.
+                        if account_id.get_account_type() == AccountType::NearImplicitAccount {
+                            let new_account_id = map_account(&account_id, None);
+                            storage_mutator.delete_namespaced_data(
+                                account_id,
+                                namespace.clone(),
+                                &data_key,
+                            )?;
+                            storage_mutator.set_namespaced_data(
+                                new_account_id,
+                                namespace,
+                                &data_key,
+                                value,
+                            )?;
+                            contract_data_updated += 1;
+                        }
+                    }
+                    StateRecord::NamespacedContract { account_id, namespace, code } => {
+                        // TODO(eth-implicit) Change back to is_implicit() when ETH-implicit accounts are supported.
+                        if account_id.get_account_type() == AccountType::NearImplicitAccount {
+                            let new_account_id = map_account(&account_id, None);
+                            storage_mutator
+                                .delete_namespaced_code(account_id, namespace.clone())?;
+                            storage_mutator.set_namespaced_code(new_account_id, namespace, code)?;
+                            contract_code_updated += 1;
+                        }
+                    }
+                    // Global contracts aren't stored under an account.
+                    StateRecord::GlobalContract { .. } => {}
                 }
                 records_parsed += 1;
             } else {
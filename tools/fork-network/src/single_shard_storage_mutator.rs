@@ -1,6 +1,6 @@
 use near_chain::types::RuntimeAdapter;
 use near_crypto::PublicKey;
-use near_primitives::account::{AccessKey, Account};
+use near_primitives::account::{AccessKey, Account, AccountNamespace};
 use near_primitives::borsh;
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::Receipt;
@@ -91,6 +91,49 @@ impl SingleShardStorageMutator {
         self.remove(TrieKey::ContractCode { account_id })
     }
 
+    pub(crate) fn set_namespaced_data(
+        &mut self,
+        account_id: AccountId,
+        namespace: AccountNamespace,
+        data_key: &StoreKey,
+        value: StoreValue,
+    ) -> anyhow::Result<()> {
+        self.set(
+            TrieKey::NamespacedContractData { account_id, namespace, key: data_key.to_vec() },
+            borsh::to_vec(&value)?,
+        )
+    }
+
+    pub(crate) fn delete_namespaced_data(
+        &mut self,
+        account_id: AccountId,
+        namespace: AccountNamespace,
+        data_key: &StoreKey,
+    ) -> anyhow::Result<()> {
+        self.remove(TrieKey::NamespacedContractData {
+            account_id,
+            namespace,
+            key: data_key.to_vec(),
+        })
+    }
+
+    pub(crate) fn set_namespaced_code(
+        &mut self,
+        account_id: AccountId,
+        namespace: AccountNamespace,
+        value: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        self.set(TrieKey::NamespacedContractCode { account_id, namespace }, value)
+    }
+
+    pub(crate) fn delete_namespaced_code(
+        &mut self,
+        account_id: AccountId,
+        namespace: AccountNamespace,
+    ) -> anyhow::Result<()> {
+        self.remove(TrieKey::NamespacedContractCode { account_id, namespace })
+    }
+
     pub(crate) fn set_postponed_receipt(&mut self, receipt: &Receipt) -> anyhow::Result<()> {
         self.set(
             TrieKey::PostponedReceipt {
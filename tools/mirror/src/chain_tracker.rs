@@ -809,7 +809,7 @@ impl TxTracker {
                     }
                 }
             }
-            ReceiptEnumView::Data { .. } => {}
+            ReceiptEnumView::Data { .. } | ReceiptEnumView::GlobalContractDistribution { .. } => {}
         };
         Ok(())
     }
@@ -224,7 +224,9 @@ pub(crate) fn map_records<P: AsRef<Path>>(
                 }
                 records_seq.serialize_element(&r).unwrap();
             }
-            StateRecord::Contract { account_id, .. } => {
+            StateRecord::Contract { account_id, .. }
+            | StateRecord::NamespacedContract { account_id, .. }
+            | StateRecord::NamespacedData { account_id, .. } => {
                 // TODO(eth-implicit) Change back to is_implicit() when ETH-implicit accounts are supported.
                 if account_id.get_account_type() == AccountType::NearImplicitAccount {
                     *account_id = crate::key_mapping::map_account(&account_id, secret.as_ref());
@@ -246,6 +248,9 @@ pub(crate) fn map_records<P: AsRef<Path>>(
                 map_receipt(receipt, secret.as_ref(), &default_key);
                 records_seq.serialize_element(&r).unwrap();
             }
+            StateRecord::GlobalContract { .. } => {
+                records_seq.serialize_element(&r).unwrap();
+            }
         };
     })?;
 
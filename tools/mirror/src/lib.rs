@@ -775,7 +775,7 @@ async fn account_exists(
         .send(
             Query::new(
                 BlockReference::Finality(Finality::None),
-                QueryRequest::ViewAccount { account_id: account_id.clone() },
+                QueryRequest::ViewAccount { account_id: account_id.clone(), include_proof: false },
             )
             .with_span_context(),
         )
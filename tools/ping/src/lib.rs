@@ -417,6 +417,10 @@ async fn ping_via_node(
                     "Received Handshake Failure: {:?}. Is the public key given with --peer correct?",
                     reason,
                 ),
+                HandshakeFailureReason::AdmissionChallenge(_) => anyhow::bail!(
+                    "Received Handshake Failure: {:?}. The peer is flooded with handshakes, try again later.",
+                    reason,
+                ),
             }
         }
         Err(e) => {
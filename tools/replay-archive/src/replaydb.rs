@@ -111,6 +111,21 @@ impl Database for ReplayDB {
         self.write_db.write(batch)
     }
 
+    fn write_checked(
+        &self,
+        batch: DBTransaction,
+        check: &dyn Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        let columns = batch.columns();
+        assert!(
+            columns.is_disjoint(&self.archival_columns),
+            "Attempted to write archival columns: {:?}",
+            columns.intersection(&self.archival_columns).collect_vec()
+        );
+        self.columns_written.lock().unwrap().extend(columns);
+        self.write_db.write_checked(batch, check)
+    }
+
     fn flush(&self) -> io::Result<()> {
         unreachable!()
     }
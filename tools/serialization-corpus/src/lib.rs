@@ -0,0 +1,304 @@
+//! Golden serialization corpus of consensus types.
+//!
+//! For every protocol version returned by [`corpus_protocol_versions`] the
+//! corpus holds the Borsh encoding and the JSON view of deterministic sample
+//! values of the covered types. The committed corpus is compared against the
+//! output of [`generate`], so that an accidental change of the wire format is
+//! caught before it reaches the network.
+
+use anyhow::Context;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::Signer;
+use near_primitives::action::{Action, TransferAction};
+use near_primitives::block_header::BlockHeader;
+use near_primitives::congestion_info::CongestionInfo;
+use near_primitives::hash::hash;
+use near_primitives::receipt::{Receipt, ReceiptPriority};
+use near_primitives::sharding::{ShardChunkHeader, ShardChunkHeaderV3};
+use near_primitives::test_utils::{create_test_signer, create_user_test_signer};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::AccountId;
+use near_primitives::version::{ProtocolFeature, ProtocolVersion, PROTOCOL_VERSION};
+use near_primitives::views::{
+    BlockHeaderView, ChunkHeaderView, ReceiptView, SignedTransactionView,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Directory of the committed corpus.
+pub const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/res");
+
+const BLOCK_HEADER: &str = "block_header";
+const CHUNK_HEADER: &str = "chunk_header";
+const TRANSACTION_V0: &str = "transaction_v0";
+const TRANSACTION_V1: &str = "transaction_v1";
+const RECEIPT_V0: &str = "receipt_v0";
+const RECEIPT_V1: &str = "receipt_v1";
+
+/// Encodings of a single sample value.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CorpusEntry {
+    pub name: &'static str,
+    pub borsh: Vec<u8>,
+    pub json: serde_json::Value,
+}
+
+/// Protocol versions at which the corpus is generated. These are the versions
+/// at which the format of one of the covered types changed, plus the latest
+/// supported version.
+pub fn corpus_protocol_versions() -> Vec<ProtocolVersion> {
+    let mut versions = vec![
+        // Last version producing BlockHeaderV1 genesis headers.
+        29,
+        // Last version producing BlockHeaderV2.
+        ProtocolFeature::BlockHeaderV3.protocol_version() - 1,
+        ProtocolFeature::BlockHeaderV3.protocol_version(),
+        ProtocolFeature::BlockHeaderV4.protocol_version(),
+        // Chunk headers carry congestion info.
+        ProtocolFeature::CongestionControl.protocol_version(),
+        // Introduces BlockHeaderV5.
+        ProtocolFeature::ChunkEndorsementsInBlockHeader.protocol_version(),
+        PROTOCOL_VERSION,
+    ];
+    versions.retain(|version| *version <= PROTOCOL_VERSION);
+    versions.sort();
+    versions.dedup();
+    versions
+}
+
+/// Generates the sample values for the given protocol version. The output
+/// only depends on the protocol version.
+pub fn generate(protocol_version: ProtocolVersion) -> Vec<CorpusEntry> {
+    let block_header = sample_block_header(protocol_version);
+    let chunk_header = sample_chunk_header(protocol_version);
+    let alice: AccountId = "alice.near".parse().unwrap();
+    let signer: Signer = create_user_test_signer(&alice).into();
+    let actions = vec![Action::Transfer(TransferAction { deposit: 1_000 })];
+    let block_hash = hash(b"corpus block");
+    let transaction_v0 = SignedTransaction::from_actions(
+        1,
+        alice.clone(),
+        "bob.near".parse().unwrap(),
+        &signer,
+        actions.clone(),
+        block_hash,
+        0,
+    );
+    let transaction_v1 = SignedTransaction::from_actions_v1(
+        2,
+        alice,
+        "bob.near".parse().unwrap(),
+        &signer,
+        actions,
+        block_hash,
+        7,
+    );
+    let receiver_id = "bob.near".parse().unwrap();
+    let receipt_v0 = Receipt::new_balance_refund(&receiver_id, 1_000, ReceiptPriority::NoPriority);
+    let receipt_v1 = Receipt::new_gas_refund(
+        &receiver_id,
+        1_000,
+        signer.public_key(),
+        ReceiptPriority::Priority(7),
+    );
+
+    vec![
+        entry(BLOCK_HEADER, &block_header, BlockHeaderView::from(block_header.clone())),
+        entry(CHUNK_HEADER, &chunk_header, ChunkHeaderView::from(chunk_header.clone())),
+        entry(TRANSACTION_V0, &transaction_v0, SignedTransactionView::from(transaction_v0.clone())),
+        entry(TRANSACTION_V1, &transaction_v1, SignedTransactionView::from(transaction_v1.clone())),
+        entry(RECEIPT_V0, &receipt_v0, ReceiptView::from(receipt_v0.clone())),
+        entry(RECEIPT_V1, &receipt_v1, ReceiptView::from(receipt_v1.clone())),
+    ]
+}
+
+fn sample_block_header(protocol_version: ProtocolVersion) -> BlockHeader {
+    BlockHeader::genesis(
+        protocol_version,
+        0,
+        hash(b"state root"),
+        hash(b"block body"),
+        hash(b"outgoing receipts"),
+        hash(b"chunk headers"),
+        hash(b"chunk transactions"),
+        4,
+        hash(b"challenges"),
+        near_time::Utc::from_unix_timestamp(1_600_000_000).unwrap(),
+        100_000_000,
+        1_000_000_000_000_000_000_000_000_000_000,
+        hash(b"next block producers"),
+    )
+}
+
+fn sample_chunk_header(protocol_version: ProtocolVersion) -> ShardChunkHeader {
+    let congestion_info =
+        ProtocolFeature::CongestionControl.enabled(protocol_version).then(CongestionInfo::default);
+    ShardChunkHeader::V3(ShardChunkHeaderV3::new(
+        protocol_version,
+        hash(b"prev block"),
+        hash(b"prev state root"),
+        hash(b"prev outcome root"),
+        hash(b"encoded merkle root"),
+        1_024,
+        1,
+        0,
+        1_000_000,
+        1_000_000_000_000_000,
+        0,
+        hash(b"prev outgoing receipts"),
+        hash(b"transactions"),
+        vec![],
+        congestion_info,
+        &create_test_signer("test.near"),
+    ))
+}
+
+fn entry<T: BorshSerialize, V: Serialize>(name: &'static str, value: &T, view: V) -> CorpusEntry {
+    CorpusEntry {
+        name,
+        borsh: borsh::to_vec(value).expect("borsh serialization can't fail"),
+        json: serde_json::to_value(view).expect("JSON serialization can't fail"),
+    }
+}
+
+/// Checks that decoding the entry and encoding it again yields the same bytes
+/// for Borsh and the same value for JSON.
+pub fn check_round_trip(entry: &CorpusEntry) -> anyhow::Result<()> {
+    match entry.name {
+        BLOCK_HEADER => round_trip::<BlockHeader, BlockHeaderView>(entry),
+        CHUNK_HEADER => round_trip::<ShardChunkHeader, ChunkHeaderView>(entry),
+        TRANSACTION_V0 | TRANSACTION_V1 => {
+            round_trip::<SignedTransaction, SignedTransactionView>(entry)
+        }
+        RECEIPT_V0 | RECEIPT_V1 => round_trip::<Receipt, ReceiptView>(entry),
+        name => anyhow::bail!("unknown corpus entry {name}"),
+    }
+}
+
+fn round_trip<T, V>(entry: &CorpusEntry) -> anyhow::Result<()>
+where
+    T: BorshSerialize + BorshDeserialize,
+    V: Serialize + DeserializeOwned,
+{
+    let value = T::try_from_slice(&entry.borsh)
+        .with_context(|| format!("failed to decode borsh of {}", entry.name))?;
+    anyhow::ensure!(
+        borsh::to_vec(&value)? == entry.borsh,
+        "borsh encoding of {} doesn't round-trip",
+        entry.name
+    );
+    let view: V = serde_json::from_value(entry.json.clone())
+        .with_context(|| format!("failed to decode JSON of {}", entry.name))?;
+    anyhow::ensure!(
+        serde_json::to_value(&view)? == entry.json,
+        "JSON encoding of {} doesn't round-trip",
+        entry.name
+    );
+    Ok(())
+}
+
+fn version_dir(corpus_dir: &Path, protocol_version: ProtocolVersion) -> PathBuf {
+    corpus_dir.join(format!("protocol_version_{protocol_version}"))
+}
+
+/// Writes the given entries as the corpus of the protocol version. The Borsh
+/// encoding is stored hex encoded so that the corpus diffs well.
+pub fn write_corpus(
+    corpus_dir: &Path,
+    protocol_version: ProtocolVersion,
+    entries: &[CorpusEntry],
+) -> anyhow::Result<()> {
+    let dir = version_dir(corpus_dir, protocol_version);
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    for entry in entries {
+        let borsh_path = dir.join(format!("{}.borsh.hex", entry.name));
+        std::fs::write(&borsh_path, format!("{}\n", hex::encode(&entry.borsh)))
+            .with_context(|| format!("failed to write {}", borsh_path.display()))?;
+        let json_path = dir.join(format!("{}.json", entry.name));
+        std::fs::write(&json_path, format!("{}\n", serde_json::to_string_pretty(&entry.json)?))
+            .with_context(|| format!("failed to write {}", json_path.display()))?;
+    }
+    Ok(())
+}
+
+/// Reads the corpus entry with the given name, or returns `None` if there is
+/// no corpus for the protocol version.
+pub fn read_corpus_entry(
+    corpus_dir: &Path,
+    protocol_version: ProtocolVersion,
+    name: &'static str,
+) -> anyhow::Result<Option<CorpusEntry>> {
+    let dir = version_dir(corpus_dir, protocol_version);
+    if !dir.exists() {
+        return Ok(None);
+    }
+    let borsh_path = dir.join(format!("{name}.borsh.hex"));
+    let borsh = std::fs::read_to_string(&borsh_path)
+        .with_context(|| format!("failed to read {}", borsh_path.display()))?;
+    let json_path = dir.join(format!("{name}.json"));
+    let json = std::fs::read_to_string(&json_path)
+        .with_context(|| format!("failed to read {}", json_path.display()))?;
+    Ok(Some(CorpusEntry {
+        name,
+        borsh: hex::decode(borsh.trim())?,
+        json: serde_json::from_str(&json)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_entries_round_trip() {
+        for protocol_version in corpus_protocol_versions() {
+            for entry in generate(protocol_version) {
+                check_round_trip(&entry).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_generation_is_deterministic() {
+        for protocol_version in corpus_protocol_versions() {
+            assert_eq!(generate(protocol_version), generate(protocol_version));
+        }
+    }
+
+    /// Every generated entry must be committed; run the binary of this crate to
+    /// generate the corpus of a new protocol version.
+    #[test]
+    fn test_matches_committed_corpus() {
+        let corpus_dir = Path::new(CORPUS_DIR);
+        for protocol_version in corpus_protocol_versions() {
+            for entry in generate(protocol_version) {
+                let committed = read_corpus_entry(corpus_dir, protocol_version, entry.name)
+                    .unwrap()
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "{} is missing from the corpus of protocol version {protocol_version}",
+                            entry.name
+                        )
+                    });
+                check_round_trip(&committed).unwrap();
+                assert_eq!(
+                    committed, entry,
+                    "encoding of {} changed at protocol version {protocol_version}",
+                    entry.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_and_read_corpus() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = generate(PROTOCOL_VERSION);
+        write_corpus(dir.path(), PROTOCOL_VERSION, &entries).unwrap();
+        for entry in entries {
+            let read = read_corpus_entry(dir.path(), PROTOCOL_VERSION, entry.name).unwrap();
+            assert_eq!(read, Some(entry));
+        }
+    }
+}
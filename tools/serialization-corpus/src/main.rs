@@ -0,0 +1,20 @@
+use clap::Parser;
+use near_serialization_corpus::{corpus_protocol_versions, generate, write_corpus, CORPUS_DIR};
+use std::path::PathBuf;
+
+/// Generates the golden serialization corpus of consensus types.
+#[derive(Parser)]
+struct Cli {
+    /// Directory to write the corpus to.
+    #[clap(long, default_value = CORPUS_DIR)]
+    out_dir: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    for protocol_version in corpus_protocol_versions() {
+        write_corpus(&cli.out_dir, protocol_version, &generate(protocol_version))?;
+        println!("Wrote corpus for protocol version {protocol_version}");
+    }
+    Ok(())
+}
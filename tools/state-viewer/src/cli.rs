@@ -10,6 +10,7 @@ use near_chain::types::RuntimeStorageConfig;
 use near_chain_configs::{GenesisChangeConfig, GenesisValidationMode};
 use near_primitives::account::id::AccountId;
 use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::from_base64;
 use near_primitives::sharding::ChunkHash;
 use near_primitives::trie_key::col;
 use near_primitives::types::{BlockHeight, ShardId, StateRoot};
@@ -54,6 +55,9 @@ pub enum StateViewerSubCommand {
     /// Dump contract data in storage of given account to binary file.
     #[clap(alias = "dump_account_storage")]
     DumpAccountStorage(DumpAccountStorageCmd),
+    /// Dump contract data of given account in pages, as JSON lines of base64 keys and values.
+    #[clap(alias = "dump_contract_state")]
+    DumpContractState(DumpContractStateCmd),
     /// Dump deployed contract code of given account to wasm file.
     #[clap(alias = "dump_code")]
     DumpCode(DumpCodeCmd),
@@ -163,6 +167,7 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::ClearCache => clear_cache(store),
             StateViewerSubCommand::ContractAccounts(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpAccountStorage(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::DumpContractState(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpCode(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpState(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpStateRedis(cmd) => cmd.run(home_dir, near_config, store),
@@ -432,6 +437,42 @@ impl DumpAccountStorageCmd {
     }
 }
 
+#[derive(clap::Parser)]
+pub struct DumpContractStateCmd {
+    #[clap(long)]
+    account_id: AccountId,
+    /// Only keys starting with this prefix are dumped.
+    #[clap(long, default_value = "")]
+    prefix: String,
+    /// Maximum number of values to dump.
+    #[clap(long, default_value = "10000")]
+    page_size: usize,
+    /// Base64 key to continue from, as printed by the previous dump.
+    #[clap(long)]
+    cursor: Option<String>,
+    #[clap(long, value_parser)]
+    output: PathBuf,
+    #[clap(long, default_value = "latest")]
+    block_height: String,
+}
+
+impl DumpContractStateCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        let cursor = self.cursor.map(|cursor| from_base64(&cursor).expect("invalid base64 cursor"));
+        dump_contract_state(
+            &self.account_id,
+            self.prefix.as_bytes(),
+            self.page_size,
+            cursor,
+            &self.output,
+            self.block_height,
+            home_dir,
+            near_config,
+            store,
+        );
+    }
+}
+
 #[derive(clap::Parser)]
 pub struct DumpCodeCmd {
     #[clap(long)]
@@ -29,6 +29,7 @@ use near_primitives::apply::ApplyChunkReason;
 use near_primitives::block::Block;
 use near_primitives::epoch_info::EpochInfo;
 use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::to_base64;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::sharding::{ChunkHash, ShardChunk};
@@ -36,14 +37,19 @@ use near_primitives::state::FlatStateValue;
 use near_primitives::state_record::state_record_to_account_id;
 use near_primitives::state_record::StateRecord;
 use near_primitives::trie_key::col::COLUMNS_WITH_ACCOUNT_ID_IN_KEY;
+use near_primitives::trie_key::trie_key_parsers;
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{BlockHeight, EpochId, ShardId};
 use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::views::StateItem;
 use near_primitives_core::types::{Balance, EpochHeight};
 use near_store::flat::FlatStorageChunkView;
 use near_store::flat::FlatStorageManager;
 use near_store::TrieStorage;
-use near_store::{DBCol, Store, Trie, TrieCache, TrieCachingStorage, TrieConfig, TrieDBStorage};
+use near_store::{
+    DBCol, KeyRange, Store, Trie, TrieCache, TrieCachingStorage, TrieConfig, TrieDBStorage,
+    TrieRangeCursor, TrieRangeIterator,
+};
 use nearcore::NightshadeRuntimeExt;
 use nearcore::{NearConfig, NightshadeRuntime};
 use node_runtime::adapter::ViewRuntimeAdapter;
@@ -330,6 +336,16 @@ pub(crate) fn apply_tx(
     .map(|_| ())
 }
 
+fn parse_load_trie_mode(block_height: &str) -> LoadTrieMode {
+    if block_height == "latest" {
+        LoadTrieMode::Latest
+    } else if let Ok(height) = block_height.parse::<u64>() {
+        LoadTrieMode::Height(height)
+    } else {
+        panic!("block_height should be either number or \"latest\"")
+    }
+}
+
 pub(crate) fn dump_account_storage(
     account_id: String,
     storage_key: String,
@@ -339,13 +355,7 @@ pub(crate) fn dump_account_storage(
     near_config: NearConfig,
     store: Store,
 ) {
-    let block_height = if block_height == "latest" {
-        LoadTrieMode::Latest
-    } else if let Ok(height) = block_height.parse::<u64>() {
-        LoadTrieMode::Height(height)
-    } else {
-        panic!("block_height should be either number or \"latest\"")
-    };
+    let block_height = parse_load_trie_mode(&block_height);
     let (_, runtime, state_roots, header) =
         load_trie_stop_at_height(store, home_dir, &near_config, block_height);
     for (shard_id, state_root) in state_roots.iter().enumerate() {
@@ -379,6 +389,54 @@ pub(crate) fn dump_account_storage(
     std::process::exit(1);
 }
 
+/// Writes up to `page_size` values of the contract data of the account, starting at `cursor`, and
+/// prints the cursor of the next page.  The data is iterated without loading all of it, so
+/// accounts with large state can be dumped page by page.
+pub(crate) fn dump_contract_state(
+    account_id: &AccountId,
+    prefix: &[u8],
+    page_size: usize,
+    cursor: Option<Vec<u8>>,
+    output: &Path,
+    block_height: String,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+) {
+    let block_height = parse_load_trie_mode(&block_height);
+    let (epoch_manager, runtime, state_roots, header) =
+        load_trie_stop_at_height(store, home_dir, &near_config, block_height);
+    let shard_id = epoch_manager.account_id_to_shard_id(account_id, header.epoch_id()).unwrap();
+    let trie = runtime
+        .get_trie_for_shard(shard_id, header.prev_hash(), state_roots[shard_id as usize], false)
+        .unwrap();
+    let trie = trie.lock_for_iter();
+
+    let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
+    let acc_sep_len = query.len() - prefix.len();
+    let mut iter = TrieRangeIterator::new(trie.iter().unwrap(), KeyRange::prefix(&query)).unwrap();
+    if let Some(cursor) = cursor {
+        iter.resume(&TrieRangeCursor([&query[..acc_sep_len], &cursor].concat())).unwrap();
+    }
+    let items = iter.next_n(page_size).unwrap();
+
+    let mut file = std::io::BufWriter::new(File::create(output).unwrap());
+    for (key, value) in &items {
+        let item =
+            StateItem { key: key[acc_sep_len..].to_vec().into(), value: value.clone().into() };
+        serde_json::to_writer(&mut file, &item).unwrap();
+        writeln!(file).unwrap();
+    }
+    file.flush().unwrap();
+    println!("Dumped {} values of account {} into {}", items.len(), account_id, output.display());
+    match iter.cursor() {
+        Some(cursor) => {
+            println!("To dump the next page, pass --cursor {}", to_base64(&cursor.0[acc_sep_len..]))
+        }
+        None => println!("This is the last page"),
+    }
+}
+
 pub(crate) fn dump_code(
     account_id: String,
     output: &Path,
@@ -1327,9 +1385,9 @@ fn get_state_stats_group_by<'a>(
                 let key_size = key.len() as u64;
                 let value_size = value.len() as u64;
                 let size = ByteSize::b(key_size + value_size);
-                let state_record = StateRecord::from_raw_key_value(key, value);
-                state_record.map(|state_record| StateStatsStateRecord {
-                    account_id: state_record_to_account_id(&state_record).clone(),
+                let state_record = StateRecord::from_raw_key_value(key, value)?;
+                Some(StateStatsStateRecord {
+                    account_id: state_record_to_account_id(&state_record)?.clone(),
                     state_record,
                     size,
                 })
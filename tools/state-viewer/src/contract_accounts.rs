@@ -136,6 +136,13 @@ pub(crate) enum ActionType {
     DeleteAccount,
     DataReceipt,
     Delegate,
+    IdempotencyKey,
+    AtomicBatch,
+    DeployNamespacedContract,
+    NamespacedFunctionCall,
+    DeployGlobalContract,
+    UseGlobalContract,
+    GlobalContractDistribution,
 }
 
 impl ContractAccount {
@@ -348,6 +355,18 @@ fn try_find_actions_spawned_by_receipt(
                                     Action::DeleteKey(_) => ActionType::DeleteKey,
                                     Action::DeleteAccount(_) => ActionType::DeleteAccount,
                                     Action::Delegate(_) => ActionType::Delegate,
+                                    Action::IdempotencyKey(_) => ActionType::IdempotencyKey,
+                                    Action::AtomicBatch(_) => ActionType::AtomicBatch,
+                                    Action::DeployNamespacedContract(_) => {
+                                        ActionType::DeployNamespacedContract
+                                    }
+                                    Action::NamespacedFunctionCall(_) => {
+                                        ActionType::NamespacedFunctionCall
+                                    }
+                                    Action::DeployGlobalContract(_) => {
+                                        ActionType::DeployGlobalContract
+                                    }
+                                    Action::UseGlobalContract(_) => ActionType::UseGlobalContract,
                                 };
                                 entry
                                     .actions
@@ -361,6 +380,12 @@ fn try_find_actions_spawned_by_receipt(
                                 .get_or_insert_with(Default::default)
                                 .insert(ActionType::DataReceipt);
                         }
+                        ReceiptEnum::GlobalContractDistribution(_) => {
+                            entry
+                                .actions
+                                .get_or_insert_with(Default::default)
+                                .insert(ActionType::GlobalContractDistribution);
+                        }
                     }
                 }
             }
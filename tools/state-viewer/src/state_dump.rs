@@ -6,6 +6,7 @@ use near_epoch_manager::EpochManagerAdapter;
 use near_epoch_manager::EpochManagerHandle;
 use near_primitives::account::id::AccountId;
 use near_primitives::block::BlockHeader;
+use near_primitives::hash::hash;
 use near_primitives::state_record::state_record_to_account_id;
 use near_primitives::state_record::StateRecord;
 use near_primitives::types::{AccountInfo, Balance, StateRoot};
@@ -209,10 +210,9 @@ fn should_include_record(
 ) -> bool {
     match account_allowlist {
         None => true,
-        Some(allowlist) => {
-            let current_account_id = state_record_to_account_id(record);
-            allowlist.contains(current_account_id)
-        }
+        // The records without an account, like global contracts, are kept.
+        Some(allowlist) => state_record_to_account_id(record)
+            .map_or(true, |account_id| allowlist.contains(account_id)),
     }
 }
 
@@ -236,6 +236,8 @@ fn iterate_over_records(
         }
     };
     let mut total_supply = 0;
+    // Every shard stores all the global contracts, they are dumped once.
+    let mut global_contracts = HashSet::new();
     for (shard_id, state_root) in state_roots.iter().enumerate() {
         let trie = runtime
             .get_trie_for_shard(shard_id as u64, last_block_header.prev_hash(), *state_root, false)
@@ -246,6 +248,11 @@ fn iterate_over_records(
                 if !should_include_record(&sr, &account_allowlist) {
                     continue;
                 }
+                if let StateRecord::GlobalContract { code } = &sr {
+                    if !global_contracts.insert(hash(code)) {
+                        continue;
+                    }
+                }
                 if let StateRecord::Account { account_id, account } = &mut sr {
                     if account.locked() > 0 {
                         let stake = *validators.get(account_id).map(|(_, s)| s).unwrap_or(&0);